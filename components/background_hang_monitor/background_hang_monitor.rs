@@ -11,10 +11,12 @@ use msg::constellation_msg::{
     BackgroundHangMonitor, BackgroundHangMonitorClone, BackgroundHangMonitorRegister,
 };
 use msg::constellation_msg::{HangAlert, HangAnnotation, HangMonitorAlert, SamplerControlMsg};
+use msg::constellation_msg::{HangProfile, HangProfileSymbol, HangTaskAnnotation};
+use profile_traits::gecko::GeckoProfile;
 use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Clone)]
 pub struct HangMonitorRegister {
@@ -90,8 +92,14 @@ pub enum MonitoredComponentMsg {
     Unregister,
     /// Notify start of new activity for a given component,
     NotifyActivity(HangAnnotation),
+    /// Notify the task source and the name of the task of the current activity.
+    NotifyTask(&'static str, &'static str),
     /// Notify start of waiting for a new task to come-in for processing.
     NotifyWait,
+    /// Provide a way to ask the component for the stack of its script.
+    SetJsStackRequester(Box<dyn Fn() + Send>),
+    /// The stack of the script of the component, as requested.
+    NotifyJsStack(Vec<HangProfileSymbol>),
 }
 
 /// A wrapper around a sender to the monitor,
@@ -131,10 +139,22 @@ impl BackgroundHangMonitor for BackgroundHangMonitorChan {
         let msg = MonitoredComponentMsg::NotifyActivity(annotation);
         self.send(msg);
     }
+    fn notify_task(&self, task_source: &'static str, task_name: &'static str) {
+        let msg = MonitoredComponentMsg::NotifyTask(task_source, task_name);
+        self.send(msg);
+    }
     fn notify_wait(&self) {
         let msg = MonitoredComponentMsg::NotifyWait;
         self.send(msg);
     }
+    fn set_js_stack_requester(&self, requester: Box<dyn Fn() + Send>) {
+        let msg = MonitoredComponentMsg::SetJsStackRequester(requester);
+        self.send(msg);
+    }
+    fn notify_js_stack(&self, stack: Vec<HangProfileSymbol>) {
+        let msg = MonitoredComponentMsg::NotifyJsStack(stack);
+        self.send(msg);
+    }
     fn unregister(&self) {
         let msg = MonitoredComponentMsg::Unregister;
         self.send(msg);
@@ -145,6 +165,11 @@ struct MonitoredComponent {
     sampler: Box<dyn Sampler>,
     last_activity: Instant,
     last_annotation: Option<HangAnnotation>,
+    last_task: Option<(&'static str, &'static str)>,
+    js_stack_requester: Option<Box<dyn Fn() + Send>>,
+    /// Whether the stack of the script was requested for the current activity.
+    js_stack_requested: bool,
+    js_stack: Option<Vec<HangProfileSymbol>>,
     transient_hang_timeout: Duration,
     permanent_hang_timeout: Duration,
     sent_transient_alert: bool,
@@ -152,6 +177,26 @@ struct MonitoredComponent {
     is_waiting: bool,
 }
 
+impl MonitoredComponent {
+    /// Forget the state of the previous activity.
+    fn reset_activity(&mut self) {
+        self.last_activity = Instant::now();
+        self.last_task = None;
+        self.js_stack_requested = false;
+        self.js_stack = None;
+        self.sent_transient_alert = false;
+        self.sent_permanent_alert = false;
+    }
+
+    fn task_annotation(&self) -> Option<HangTaskAnnotation> {
+        self.last_task
+            .map(|(task_source, task_name)| HangTaskAnnotation {
+                task_source: task_source.to_owned(),
+                task_name: task_name.to_owned(),
+            })
+    }
+}
+
 struct Sample(MonitoredComponentId, Instant, NativeStack);
 
 pub struct BackgroundHangMonitorWorker {
@@ -163,7 +208,6 @@ pub struct BackgroundHangMonitorWorker {
     sampling_duration: Option<Duration>,
     sampling_max_duration: Option<Duration>,
     last_sample: Instant,
-    creation: Instant,
     sampling_baseline: Instant,
     samples: VecDeque<Sample>,
 }
@@ -185,47 +229,40 @@ impl BackgroundHangMonitorWorker {
             sampling_max_duration: None,
             last_sample: Instant::now(),
             sampling_baseline: Instant::now(),
-            creation: Instant::now(),
             samples: Default::default(),
         }
     }
 
+    /// Send the samples taken since sampling was enabled, as a profile in the format
+    /// of the Gecko profiler with a thread for each monitored component.
     fn finish_sampled_profile(&mut self) {
-        let mut bytes = vec![];
-        bytes.extend(
-            format!(
-                "{{ \"rate\": {}, \"start\": {}, \"data\": [\n",
-                self.sampling_duration.unwrap().as_millis(),
-                (self.sampling_baseline - self.creation).as_millis(),
-            )
-            .as_bytes(),
-        );
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let start_time =
+            now.as_secs_f64() * 1000. - self.sampling_baseline.elapsed().as_secs_f64() * 1000.;
+        let interval = self.sampling_duration.unwrap().as_secs_f64() * 1000.;
+        let mut profile = GeckoProfile::new(start_time, interval);
 
-        let mut first = true;
         let to_resolve = self.samples.len();
         for (i, Sample(id, instant, stack)) in self.samples.drain(..).enumerate() {
             println!("Resolving {}/{}", i + 1, to_resolve);
-            let profile = stack.to_hangprofile();
+            let hang_profile = stack.to_hangprofile();
             let name = match self.component_names.get(&id) {
-                Some(ref s) => format!("\"{}\"", s),
-                None => format!("null"),
+                Some(name) => name.clone(),
+                None => format!("{:?} {} {}", id.1, id.0.namespace_id.0, id.0.index.0.get()),
             };
-            let json = format!(
-                "{}{{ \"name\": {}, \"namespace\": {}, \"index\": {}, \"type\": \"{:?}\", \
-                 \"time\": {}, \"frames\": {} }}",
-                if !first { ",\n" } else { "" },
-                name,
-                id.0.namespace_id.0,
-                id.0.index.0.get(),
-                id.1,
-                (instant - self.sampling_baseline).as_millis(),
-                serde_json::to_string(&profile.backtrace).unwrap(),
-            );
-            bytes.extend(json.as_bytes());
-            first = false;
+            // The backtraces start from the youngest frame.
+            let frames = hang_profile
+                .backtrace
+                .iter()
+                .rev()
+                .filter_map(|symbol| symbol.name.as_ref().map(|name| &**name));
+            let time = (instant - self.sampling_baseline).as_secs_f64() * 1000.;
+            profile.thread(&name).add_sample(time, frames);
         }
 
-        bytes.extend(b"\n] }");
+        let bytes = serde_json::to_vec(&profile).unwrap();
         let _ = self
             .constellation_chan
             .send(HangMonitorAlert::Profile(bytes));
@@ -257,9 +294,12 @@ impl BackgroundHangMonitorWorker {
                         None
                     }
                     Ok(SamplerControlMsg::Disable) => {
-                        println!("Disabling profiler.");
-                        self.finish_sampled_profile();
-                        self.sampling_duration = None;
+                        // Disabling the profiler when it isn't sampling is a no-op.
+                        if self.sampling_duration.is_some() {
+                            println!("Disabling profiler.");
+                            self.finish_sampled_profile();
+                            self.sampling_duration = None;
+                        }
                         None
                     }
                     Err(_) => return false,
@@ -303,6 +343,10 @@ impl BackgroundHangMonitorWorker {
                     sampler,
                     last_activity: Instant::now(),
                     last_annotation: None,
+                    last_task: None,
+                    js_stack_requester: None,
+                    js_stack_requested: false,
+                    js_stack: None,
                     transient_hang_timeout,
                     permanent_hang_timeout,
                     sent_transient_alert: false,
@@ -330,22 +374,42 @@ impl BackgroundHangMonitorWorker {
                     .monitored_components
                     .get_mut(&component_id)
                     .expect("Received NotifyActivity for an unknown component");
-                component.last_activity = Instant::now();
+                component.reset_activity();
                 component.last_annotation = Some(annotation);
-                component.sent_transient_alert = false;
-                component.sent_permanent_alert = false;
                 component.is_waiting = false;
             },
+            (component_id, MonitoredComponentMsg::NotifyTask(task_source, task_name)) => {
+                let component = self
+                    .monitored_components
+                    .get_mut(&component_id)
+                    .expect("Received NotifyTask for an unknown component");
+                component.last_task = Some((task_source, task_name));
+            },
             (component_id, MonitoredComponentMsg::NotifyWait) => {
                 let component = self
                     .monitored_components
                     .get_mut(&component_id)
                     .expect("Received NotifyWait for an unknown component");
-                component.last_activity = Instant::now();
-                component.sent_transient_alert = false;
-                component.sent_permanent_alert = false;
+                component.reset_activity();
                 component.is_waiting = true;
             },
+            (component_id, MonitoredComponentMsg::SetJsStackRequester(requester)) => {
+                let component = self
+                    .monitored_components
+                    .get_mut(&component_id)
+                    .expect("Received SetJsStackRequester for an unknown component");
+                component.js_stack_requester = Some(requester);
+            },
+            (component_id, MonitoredComponentMsg::NotifyJsStack(stack)) => {
+                let component = self
+                    .monitored_components
+                    .get_mut(&component_id)
+                    .expect("Received NotifyJsStack for an unknown component");
+                // The stacks requested for previous activities are ignored.
+                if component.js_stack_requested {
+                    component.js_stack = Some(stack);
+                }
+            },
         }
     }
 
@@ -359,15 +423,24 @@ impl BackgroundHangMonitorWorker {
                 if monitored.sent_permanent_alert {
                     continue;
                 }
-                let profile = match monitored.sampler.suspend_and_sample_thread() {
+                let mut profile = match monitored.sampler.suspend_and_sample_thread() {
                     Ok(native_stack) => Some(native_stack.to_hangprofile()),
                     Err(()) => None,
                 };
+                if let Some(js_stack) = monitored.js_stack.take() {
+                    profile
+                        .get_or_insert_with(|| HangProfile {
+                            backtrace: vec![],
+                            js_backtrace: vec![],
+                        })
+                        .js_backtrace = js_stack;
+                }
                 let _ = self
                     .constellation_chan
                     .send(HangMonitorAlert::Hang(HangAlert::Permanent(
                         component_id.clone(),
                         last_annotation,
+                        monitored.task_annotation(),
                         profile,
                     )));
                 monitored.sent_permanent_alert = true;
@@ -382,8 +455,16 @@ impl BackgroundHangMonitorWorker {
                     .send(HangMonitorAlert::Hang(HangAlert::Transient(
                         component_id.clone(),
                         last_annotation,
+                        monitored.task_annotation(),
                     )));
                 monitored.sent_transient_alert = true;
+
+                // The stack of the script is captured by the component itself, when it's
+                // interrupted, so it's requested early to be ready if the hang persists.
+                if let Some(ref requester) = monitored.js_stack_requester {
+                    monitored.js_stack_requested = true;
+                    requester();
+                }
             }
         }
     }
@@ -75,6 +75,7 @@ impl NativeStack {
     pub fn to_hangprofile(&self) -> HangProfile {
         let mut profile = HangProfile {
             backtrace: Vec::new(),
+            js_backtrace: Vec::new(),
         };
         for ip in self.instruction_ptrs.iter().rev() {
             if ip.is_null() {
@@ -9,8 +9,9 @@ use background_hang_monitor::HangMonitorRegister;
 use ipc_channel::ipc;
 use msg::constellation_msg::ScriptHangAnnotation;
 use msg::constellation_msg::TEST_PIPELINE_ID;
-use msg::constellation_msg::{HangAlert, HangAnnotation, HangMonitorAlert};
+use msg::constellation_msg::{HangAlert, HangAnnotation, HangMonitorAlert, HangProfileSymbol};
 use msg::constellation_msg::{MonitoredComponentId, MonitoredComponentType};
+use std::sync::mpsc;
 use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
@@ -44,7 +45,7 @@ fn test_hang_monitoring() {
 
     // Check for a transient hang alert.
     match background_hang_monitor_receiver.recv().unwrap() {
-        HangMonitorAlert::Hang(HangAlert::Transient(component_id, _annotation)) => {
+        HangMonitorAlert::Hang(HangAlert::Transient(component_id, _annotation, _task)) => {
             let expected = MonitoredComponentId(TEST_PIPELINE_ID, MonitoredComponentType::Script);
             assert_eq!(expected, component_id);
         },
@@ -56,7 +57,12 @@ fn test_hang_monitoring() {
 
     // Check for a permanent hang alert.
     match background_hang_monitor_receiver.recv().unwrap() {
-        HangMonitorAlert::Hang(HangAlert::Permanent(component_id, _annotation, _profile)) => {
+        HangMonitorAlert::Hang(HangAlert::Permanent(
+            component_id,
+            _annotation,
+            _task,
+            _profile,
+        )) => {
             let expected = MonitoredComponentId(TEST_PIPELINE_ID, MonitoredComponentType::Script);
             assert_eq!(expected, component_id);
         },
@@ -72,7 +78,7 @@ fn test_hang_monitoring() {
 
     // Check for a transient hang alert.
     match background_hang_monitor_receiver.recv().unwrap() {
-        HangMonitorAlert::Hang(HangAlert::Transient(component_id, _annotation)) => {
+        HangMonitorAlert::Hang(HangAlert::Transient(component_id, _annotation, _task)) => {
             let expected = MonitoredComponentId(TEST_PIPELINE_ID, MonitoredComponentType::Script);
             assert_eq!(expected, component_id);
         },
@@ -96,7 +102,7 @@ fn test_hang_monitoring() {
 
     // We're getting new hang alerts for the latest task.
     match background_hang_monitor_receiver.recv().unwrap() {
-        HangMonitorAlert::Hang(HangAlert::Transient(component_id, _annotation)) => {
+        HangMonitorAlert::Hang(HangAlert::Transient(component_id, _annotation, _task)) => {
             let expected = MonitoredComponentId(TEST_PIPELINE_ID, MonitoredComponentType::Script);
             assert_eq!(expected, component_id);
         },
@@ -146,3 +152,63 @@ fn test_hang_monitoring_unregister() {
     // No new alert yet
     assert!(background_hang_monitor_receiver.try_recv().is_err());
 }
+
+#[test]
+fn test_hang_monitoring_task_and_js_stack() {
+    let _lock = SERIAL.lock().unwrap();
+
+    let (background_hang_monitor_ipc_sender, background_hang_monitor_receiver) =
+        ipc::channel().expect("ipc channel failure");
+    let (_sampler_sender, sampler_receiver) = ipc::channel().expect("ipc channel failure");
+
+    let background_hang_monitor_register =
+        HangMonitorRegister::init(background_hang_monitor_ipc_sender.clone(), sampler_receiver);
+    let background_hang_monitor = background_hang_monitor_register.register_component(
+        MonitoredComponentId(TEST_PIPELINE_ID, MonitoredComponentType::Script),
+        Duration::from_millis(10),
+        Duration::from_millis(200),
+    );
+    let (js_stack_request_sender, js_stack_request_receiver) = mpsc::channel();
+    background_hang_monitor.set_js_stack_requester(Box::new(move || {
+        let _ = js_stack_request_sender.send(());
+    }));
+
+    // Start running a task.
+    let hang_annotation = HangAnnotation::Script(ScriptHangAnnotation::TimerEvent);
+    background_hang_monitor.notify_activity(hang_annotation);
+    background_hang_monitor.notify_task("Timer", "TimerTask");
+
+    // The transient hang alert is annotated with the task.
+    match background_hang_monitor_receiver.recv().unwrap() {
+        HangMonitorAlert::Hang(HangAlert::Transient(_component_id, _annotation, task)) => {
+            let task = task.expect("The task should be annotated");
+            assert_eq!(task.task_source, "Timer");
+            assert_eq!(task.task_name, "TimerTask");
+        },
+        _ => unreachable!(),
+    }
+
+    // The stack of the script is requested, and sent back by the component.
+    js_stack_request_receiver
+        .recv_timeout(Duration::from_millis(1000))
+        .expect("The stack of the script should be requested");
+    background_hang_monitor.notify_js_stack(vec![HangProfileSymbol {
+        name: Some("spin".to_owned()),
+        filename: Some("http://example.com/script.js".to_owned()),
+        lineno: Some(3),
+    }]);
+
+    // The permanent hang alert includes the stack of the script.
+    match background_hang_monitor_receiver.recv().unwrap() {
+        HangMonitorAlert::Hang(HangAlert::Permanent(_component_id, _annotation, task, profile)) => {
+            assert_eq!(task.unwrap().task_name, "TimerTask");
+            let js_backtrace = profile.expect("There should be a profile").js_backtrace;
+            assert_eq!(js_backtrace.len(), 1);
+            assert_eq!(js_backtrace[0].name.as_ref().unwrap(), "spin");
+            assert_eq!(js_backtrace[0].lineno, Some(3));
+        },
+        _ => unreachable!(),
+    }
+
+    background_hang_monitor.unregister();
+}
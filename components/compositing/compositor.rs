@@ -8,28 +8,33 @@ use crate::compositor_thread::{InitialCompositorState, Msg};
 use crate::gl;
 use crate::touch::{TouchAction, TouchHandler};
 use crate::windowing::{
-    self, EmbedderCoordinates, MouseWindowEvent, WebRenderDebugOption, WindowMethods,
+    self, EmbedderCoordinates, EventLoopObserver, FrameReport, MouseWindowEvent, OffscreenTexture,
+    WebRenderDebugOption, WindowMethods,
 };
 use crate::{CompositionPipeline, ConstellationMsg, SendableFrameTree};
 use crossbeam_channel::Sender;
 use embedder_traits::Cursor;
-use euclid::{Point2D, Rect, Scale, Vector2D};
+use euclid::{Point2D, Rect, Scale, Size2D, Vector2D};
 use gfx_traits::Epoch;
 #[cfg(feature = "gl")]
 use image::{DynamicImage, ImageFormat};
-use ipc_channel::ipc;
+use ipc_channel::ipc::{self, IpcSender};
 use libc::c_void;
-use msg::constellation_msg::{PipelineId, PipelineIndex, PipelineNamespaceId};
-use net_traits::image::base::Image;
+use msg::constellation_msg::{
+    PipelineId, PipelineIndex, PipelineNamespaceId, TopLevelBrowsingContextId,
+};
+use net_traits::image::base::{Image, ImageOrientation};
 use net_traits::image_cache::CorsStatus;
 use num_traits::FromPrimitive;
 #[cfg(feature = "gl")]
 use pixels::PixelFormat;
 use profile_traits::time::{self as profile_time, profile, ProfilerCategory};
-use script_traits::CompositorEvent::{MouseButtonEvent, MouseMoveEvent, TouchEvent, WheelEvent};
-use script_traits::{AnimationState, AnimationTickType, LayoutControlMsg};
+use script_traits::CompositorEvent::{
+    DoubleTapEvent, MouseButtonEvent, MouseMoveEvent, TouchEvent, WheelEvent,
+};
+use script_traits::{AnimationState, AnimationTickType, BoxModelHighlight, LayoutControlMsg};
 use script_traits::{
-    MouseButton, MouseEventType, ScrollState, TouchEventType, TouchId, WheelDelta,
+    MouseButton, MouseEventType, ScrollState, TouchEventType, TouchId, WheelDelta, WheelMode,
 };
 use script_traits::{UntrustedNodeAddress, WindowSizeData, WindowSizeType};
 use servo_geometry::{DeviceIndependentPixel, FramebufferUintLength};
@@ -41,11 +46,14 @@ use std::num::NonZeroU32;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use style_traits::viewport::ViewportConstraints;
+use std::time::Duration;
+use style_traits::viewport::{UserZoom, ViewportConstraints};
 use style_traits::{CSSPixel, DevicePixel, PinchZoomFactor};
 use time::{now, precise_time_ns, precise_time_s};
-use webrender_api::units::{DeviceIntPoint, DeviceIntSize, DevicePoint, LayoutVector2D};
-use webrender_api::{self, HitTestFlags, HitTestResult, ScrollLocation};
+use webrender_api::units::{
+    DeviceIntPoint, DeviceIntRect, DeviceIntSize, DevicePoint, LayoutPoint, LayoutVector2D,
+};
+use webrender_api::{self, HitTestFlags, HitTestResult, ScrollClamping, ScrollLocation};
 use webvr_traits::WebVRMainThreadHeartbeat;
 
 #[derive(Debug, PartialEq)]
@@ -64,6 +72,13 @@ enum NotReadyToPaint {
 const MAX_ZOOM: f32 = 8.0;
 const MIN_ZOOM: f32 = 0.1;
 
+// The tallest viewport used for full page screenshots, which fits in the
+// renderbuffers of most GL implementations.
+const MAX_FULL_PAGE_HEIGHT: i32 = 16384;
+
+// The space left on each side of a block zoomed on with a double tap, in CSS pixels.
+const DOUBLE_TAP_ZOOM_MARGIN: f32 = 8.0;
+
 trait ConvertPipelineIdFromWebRender {
     fn from_webrender(&self) -> PipelineId;
 }
@@ -126,9 +141,30 @@ pub struct IOCompositor<Window: WindowMethods + ?Sized> {
     min_viewport_zoom: Option<PinchZoomFactor>,
     max_viewport_zoom: Option<PinchZoomFactor>,
 
+    /// The viewport zoom a double tap returns to, when the page is zoomed in.
+    initial_viewport_zoom: PinchZoomFactor,
+
+    /// Whether the user may change the viewport zoom, as set by `user-scalable`.
+    user_zoom: UserZoom,
+
+    /// The origin of the layout viewport of the root pipeline, which contains the visual
+    /// viewport and is only moved when the visual viewport is scrolled past its edges.
+    layout_viewport_origin: LayoutPoint,
+
+    /// The scale and the offset from the layout viewport of the visual viewport, as last
+    /// sent to the root pipeline.
+    visual_viewport: (f32, LayoutVector2D),
+
+    /// Device pixel ratios set by the embedder, replacing the one of the window.
+    device_pixel_ratio_overrides:
+        HashMap<TopLevelBrowsingContextId, Scale<f32, DeviceIndependentPixel, DevicePixel>>,
+
     /// "Desktop-style" zoom that resizes the viewport to fit the window.
     page_zoom: Scale<f32, CSSPixel, DeviceIndependentPixel>,
 
+    /// The box model of the node highlighted by the devtools inspector, drawn on top of the page.
+    devtools_highlight: Option<BoxModelHighlight>,
+
     /// The type of composition to perform
     composite_target: CompositeTarget,
 
@@ -157,6 +193,9 @@ pub struct IOCompositor<Window: WindowMethods + ?Sized> {
     /// The channel on which messages can be sent to the time profiler.
     time_profiler_chan: profile_time::ProfilerChan,
 
+    /// The observer of the painted frames, if the embedder instruments them.
+    event_loop_observer: Option<Arc<dyn EventLoopObserver>>,
+
     /// Touch input state machine
     touch_handler: TouchHandler,
 
@@ -170,6 +209,10 @@ pub struct IOCompositor<Window: WindowMethods + ?Sized> {
     /// image for the reftest framework.
     ready_to_save_state: ReadyState,
 
+    /// A full page screenshot waiting for the page to be laid out at its full size,
+    /// along with the coordinates to restore once it has been taken.
+    pending_full_page_png: Option<(EmbedderCoordinates, IpcSender<Option<Image>>)>,
+
     /// The webrender renderer.
     webrender: webrender::Renderer,
 
@@ -195,6 +238,10 @@ pub struct IOCompositor<Window: WindowMethods + ?Sized> {
     /// The coordinates of the native window, its view and the screen.
     embedder_coordinates: EmbedderCoordinates,
 
+    /// The texture frames are rendered into, when the embedder asked to render offscreen.
+    #[cfg(feature = "gl")]
+    offscreen_target: Option<gl::OffscreenRenderTarget>,
+
     /// Current mouse cursor.
     cursor: Cursor,
 
@@ -254,6 +301,9 @@ struct PipelineDetails {
 
     /// Whether this pipeline is visible
     visible: bool,
+
+    /// The viewport constraints most recently computed by layout, if any.
+    viewport_constraints: Option<ViewportConstraints>,
 }
 
 impl PipelineDetails {
@@ -263,6 +313,7 @@ impl PipelineDetails {
             animations_running: false,
             animation_callbacks_running: false,
             visible: true,
+            viewport_constraints: None,
         }
     }
 }
@@ -307,22 +358,32 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             composite_target,
             shutdown_state: ShutdownState::NotShuttingDown,
             page_zoom: Scale::new(1.0),
+            devtools_highlight: None,
             viewport_zoom: PinchZoomFactor::new(1.0),
             min_viewport_zoom: None,
             max_viewport_zoom: None,
+            initial_viewport_zoom: PinchZoomFactor::new(1.0),
+            user_zoom: UserZoom::Zoom,
+            layout_viewport_origin: LayoutPoint::zero(),
+            visual_viewport: (1.0, LayoutVector2D::zero()),
+            device_pixel_ratio_overrides: HashMap::new(),
             zoom_action: false,
             zoom_time: 0f64,
             frame_tree_id: FrameTreeId(0),
             constellation_chan: state.constellation_chan,
             time_profiler_chan: state.time_profiler_chan,
+            event_loop_observer: state.event_loop_observer,
             last_composite_time: 0,
             ready_to_save_state: ReadyState::Unknown,
+            pending_full_page_png: None,
             webrender: state.webrender,
             webrender_document: state.webrender_document,
             webrender_api: state.webrender_api,
             webvr_heartbeats: state.webvr_heartbeats,
             webxr_main_thread: state.webxr_main_thread,
             pending_paint_metrics: HashMap::new(),
+            #[cfg(feature = "gl")]
+            offscreen_target: None,
             cursor: Cursor::None,
             cursor_pos: DevicePoint::new(0.0, 0.0),
             output_file,
@@ -358,6 +419,12 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
 
     pub fn deinit(self) {
         self.window.make_gl_context_current();
+        #[cfg(feature = "gl")]
+        {
+            if let Some(target) = self.offscreen_target {
+                target.delete(&*self.window.gl());
+            }
+        }
         self.webrender.deinit();
     }
 
@@ -441,6 +508,14 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
                 self.touch_handler.on_event_processed(result);
             },
 
+            (Msg::ZoomToRect(pipeline_id, rect, point), ShutdownState::NotShuttingDown) => {
+                self.zoom_to_rect(pipeline_id, rect, point);
+            },
+
+            (Msg::HighlightBoxModel(pipeline_id, highlight), ShutdownState::NotShuttingDown) => {
+                self.highlight_box_model(pipeline_id, highlight);
+            },
+
             (Msg::CreatePng(rect, reply), ShutdownState::NotShuttingDown) => {
                 let res = self.composite_specific_target(CompositeTarget::WindowAndPng, rect);
                 if let Err(ref e) = res {
@@ -452,6 +527,10 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
                 }
             },
 
+            (Msg::CreateFullPagePng(page_size, reply), ShutdownState::NotShuttingDown) => {
+                self.start_full_page_png(page_size, reply);
+            },
+
             (
                 Msg::ViewportConstrained(pipeline_id, constraints),
                 ShutdownState::NotShuttingDown,
@@ -534,6 +613,30 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
                 self.on_mouse_window_move_event_class(DevicePoint::new(point.x, point.y));
             },
 
+            (
+                Msg::WebDriverWheelScrollEvent(x, y, delta_x, delta_y),
+                ShutdownState::NotShuttingDown,
+            ) => {
+                let dppx = self.device_pixels_per_page_px();
+                let point = dppx.transform_point(Point2D::new(x, y));
+                let point = DevicePoint::new(point.x, point.y);
+                let delta = WheelDelta {
+                    x: delta_x,
+                    y: delta_y,
+                    z: 0.,
+                    mode: WheelMode::DeltaPixel,
+                };
+                self.on_wheel_event(delta, point);
+                // WebRender scrolls down with negative deltas, in device pixels.
+                let scroll_delta =
+                    LayoutVector2D::new(-delta_x as f32, -delta_y as f32) * dppx.get();
+                self.on_scroll_event(
+                    ScrollLocation::Delta(scroll_delta),
+                    point.to_i32(),
+                    TouchEventType::Move,
+                );
+            },
+
             (Msg::PendingPaintMetric(pipeline_id, epoch), _) => {
                 self.pending_paint_metrics.insert(pipeline_id, epoch);
             },
@@ -630,6 +733,7 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             frame_tree.pipeline.id
         );
 
+        let old_hidpi_factor = self.window_hidpi_factor();
         self.root_pipeline = Some(frame_tree.pipeline.clone());
 
         let pipeline_id = frame_tree.pipeline.id.to_webrender();
@@ -641,6 +745,20 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
 
         self.create_pipeline_details_for_frame_tree(&frame_tree);
 
+        // Layout only reports viewport constraints when a page has them, so a new
+        // root pipeline starts out from the defaults.
+        let constraints = self
+            .pipeline_details
+            .get(&frame_tree.pipeline.id)
+            .and_then(|details| details.viewport_constraints.clone());
+        self.apply_viewport_constraints(constraints);
+
+        // The new browser might have a different device pixel ratio override.
+        if self.window_hidpi_factor() != old_hidpi_factor {
+            self.update_zoom_transform();
+            self.send_window_size(WindowSizeType::Resize);
+        }
+
         self.frame_tree_id.next();
     }
 
@@ -657,12 +775,13 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
     }
 
     fn send_window_size(&self, size_type: WindowSizeType) {
-        let dppx = self.page_zoom * self.embedder_coordinates.hidpi_factor;
+        let hidpi_factor = self.window_hidpi_factor();
+        let dppx = self.page_zoom * hidpi_factor;
 
         self.webrender_api.set_document_view(
             self.webrender_document,
             self.embedder_coordinates.get_flipped_viewport(),
-            self.embedder_coordinates.hidpi_factor.get(),
+            hidpi_factor.get(),
         );
 
         let initial_viewport = self.embedder_coordinates.viewport.size.to_f32() / dppx;
@@ -687,15 +806,23 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
     pub fn on_resize_window_event(&mut self) {
         debug!("compositor resize requested");
 
+        // Resize the viewport once the full page screenshot has been taken.
+        if let Some((ref mut coords, _)) = self.pending_full_page_png {
+            *coords = self.window.get_coordinates();
+            return;
+        }
+
         let old_coords = self.embedder_coordinates;
         self.embedder_coordinates = self.window.get_coordinates();
 
-        // A size change could also mean a resolution change.
-        if self.embedder_coordinates.hidpi_factor != old_coords.hidpi_factor {
+        // A size change could also mean a resolution change, for instance when
+        // the window moves to a monitor with a different scale factor.
+        let hidpi_changed = self.embedder_coordinates.hidpi_factor != old_coords.hidpi_factor;
+        if hidpi_changed {
             self.update_zoom_transform();
         }
 
-        if self.embedder_coordinates.viewport == old_coords.viewport {
+        if self.embedder_coordinates.viewport == old_coords.viewport && !hidpi_changed {
             return;
         }
 
@@ -703,6 +830,86 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
         self.composite_if_necessary(CompositingReason::Resize);
     }
 
+    pub fn on_device_pixel_ratio_override(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        ratio: Option<f32>,
+    ) {
+        let old_hidpi_factor = self.window_hidpi_factor();
+        match ratio {
+            Some(ratio) if ratio > 0.0 => {
+                self.device_pixel_ratio_overrides
+                    .insert(top_level_browsing_context_id, Scale::new(ratio));
+            },
+            Some(ratio) => return warn!("Ignoring invalid device pixel ratio {}.", ratio),
+            None => {
+                self.device_pixel_ratio_overrides
+                    .remove(&top_level_browsing_context_id);
+            },
+        }
+
+        if self.window_hidpi_factor() == old_hidpi_factor {
+            return;
+        }
+
+        self.update_zoom_transform();
+        self.send_window_size(WindowSizeType::Resize);
+        self.composite_if_necessary(CompositingReason::Resize);
+    }
+
+    /// Resize the viewport to the height of the page. The screenshot is taken by
+    /// `finish_full_page_png` once the page has been laid out at that size.
+    fn start_full_page_png(
+        &mut self,
+        page_size: Size2D<f32, CSSPixel>,
+        reply: IpcSender<Option<Image>>,
+    ) {
+        if self.pending_full_page_png.is_some() {
+            warn!("Ignoring full page screenshot request while another one is pending.");
+            if let Err(e) = reply.send(None) {
+                warn!("Sending reply to create png failed ({:?}).", e);
+            }
+            return;
+        }
+
+        let original_coords = self.embedder_coordinates;
+        let page_height = (page_size.height * self.device_pixels_per_page_px().get()).ceil() as i32;
+        let size = DeviceIntSize::new(
+            original_coords.viewport.size.width,
+            page_height
+                .max(original_coords.viewport.size.height)
+                .min(MAX_FULL_PAGE_HEIGHT),
+        );
+        self.embedder_coordinates.viewport = DeviceIntRect::new(DeviceIntPoint::zero(), size);
+        self.embedder_coordinates.framebuffer = size;
+        self.pending_full_page_png = Some((original_coords, reply));
+
+        // Don't reuse a stable state computed for the previous size.
+        if self.ready_to_save_state == ReadyState::ReadyToSaveImage {
+            self.ready_to_save_state = ReadyState::Unknown;
+        }
+        self.send_window_size(WindowSizeType::Resize);
+    }
+
+    /// Take the pending full page screenshot if the page is ready, and restore the
+    /// size of the viewport.
+    fn finish_full_page_png(&mut self) {
+        let img = match self.composite_specific_target(CompositeTarget::WindowAndPng, None) {
+            Ok(img) => img,
+            Err(UnableToComposite::NotReadyToPaintImage(_)) => return,
+        };
+        let (original_coords, reply) = match self.pending_full_page_png.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        self.embedder_coordinates = original_coords;
+        self.send_window_size(WindowSizeType::Resize);
+        self.composite_if_necessary(CompositingReason::Resize);
+        if let Err(e) = reply.send(img) {
+            warn!("Sending reply to create png failed ({:?}).", e);
+        }
+    }
+
     pub fn on_mouse_window_event_class(&mut self, mouse_window_event: MouseWindowEvent) {
         if self.convert_mouse_to_touch {
             match mouse_window_event {
@@ -879,8 +1086,162 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
     fn on_touch_up(&mut self, identifier: TouchId, point: DevicePoint) {
         self.send_touch_event(TouchEventType::Up, identifier, point);
 
-        if let TouchAction::Click = self.touch_handler.on_touch_up(identifier, point) {
-            self.simulate_mouse_click(point);
+        match self.touch_handler.on_touch_up(identifier, point) {
+            TouchAction::Click => self.simulate_mouse_click(point),
+            TouchAction::DoubleTap => self.on_double_tap(point),
+            _ => {},
+        }
+    }
+
+    /// Zoom out if the page is zoomed in. Otherwise, ask script for the block that
+    /// was tapped, which is zoomed on by `zoom_to_rect`.
+    fn on_double_tap(&mut self, point: DevicePoint) {
+        if self.user_zoom == UserZoom::Fixed {
+            return;
+        }
+
+        if self.pinch_zoom_level() > self.initial_viewport_zoom.get() {
+            let mut txn = webrender_api::Transaction::new();
+            self.set_pinch_zoom_level(self.initial_viewport_zoom.get());
+            txn.set_pinch_zoom(webrender_api::ZoomFactor::new(self.pinch_zoom_level()));
+            txn.generate_frame();
+            self.webrender_api
+                .send_transaction(self.webrender_document, txn);
+            self.send_viewport_rects();
+            self.send_visual_viewport();
+            return;
+        }
+
+        let results = self.hit_test_at_point(point);
+        if let Some(item) = results.items.first() {
+            let event = DoubleTapEvent(
+                item.point_in_viewport.to_untyped(),
+                Some(UntrustedNodeAddress(item.tag.0 as *const c_void)),
+            );
+            let pipeline_id = PipelineId::from_webrender(item.pipeline);
+            let msg = ConstellationMsg::ForwardEvent(pipeline_id, event);
+            if let Err(e) = self.constellation_chan.send(msg) {
+                warn!("Sending event to constellation failed ({:?}).", e);
+            }
+        }
+    }
+
+    /// Draw the box model of the node inspected in the devtools over the page, or stop
+    /// drawing it if `highlight` is `None`.
+    fn highlight_box_model(
+        &mut self,
+        pipeline_id: PipelineId,
+        highlight: Option<BoxModelHighlight>,
+    ) {
+        // TODO: Offset the boxes of nodes in iframes by the position of their frame.
+        if self.get_root_pipeline_id() != Some(pipeline_id) {
+            return;
+        }
+        self.devtools_highlight = highlight;
+        self.composite_if_necessary(CompositingReason::DevtoolsHighlight);
+    }
+
+    /// Zoom so that a rect of the page of the root pipeline fills the width of the
+    /// viewport, and scroll to keep the given point of the page vertically centered.
+    fn zoom_to_rect(
+        &mut self,
+        pipeline_id: PipelineId,
+        rect: Rect<f32, CSSPixel>,
+        point: Point2D<f32, CSSPixel>,
+    ) {
+        // Zooming on the content of iframes isn't supported.
+        if self.root_pipeline.as_ref().map(|pipeline| pipeline.id) != Some(pipeline_id) {
+            return;
+        }
+        let rect = rect.inflate(DOUBLE_TAP_ZOOM_MARGIN, 0.0);
+        if rect.size.width <= 0.0 {
+            return;
+        }
+
+        let viewport = self
+            .embedder_coordinates
+            .viewport
+            .size
+            .to_f32()
+            .to_untyped() /
+            self.device_pixels_per_page_px().get();
+        self.set_pinch_zoom_level(viewport.width / rect.size.width);
+        let zoom = self.pinch_zoom_level();
+
+        let visible_height = viewport.height / zoom;
+        let origin = LayoutPoint::new(
+            rect.origin.x.max(0.0),
+            (point.y - visible_height / 2.0).max(0.0),
+        );
+        let mut txn = webrender_api::Transaction::new();
+        txn.set_pinch_zoom(webrender_api::ZoomFactor::new(zoom));
+        txn.scroll_node_with_id(
+            origin,
+            pipeline_id.root_scroll_id(),
+            ScrollClamping::ToContentBounds,
+        );
+        txn.generate_frame();
+        self.webrender_api
+            .send_transaction(self.webrender_document, txn);
+        self.waiting_for_results_of_scroll = true;
+        self.send_viewport_rects();
+        self.send_visual_viewport();
+    }
+
+    /// Let the document of the root pipeline know the scale of its visual viewport, and
+    /// its offset from the layout viewport.
+    ///
+    /// The visual viewport is what the root scroll node shows once pinch zoomed. The
+    /// layout viewport keeps the size of the unzoomed viewport and follows the visual
+    /// viewport only when it would otherwise leave it.
+    fn send_visual_viewport(&mut self) {
+        let pipeline_id = match self.root_pipeline {
+            Some(ref pipeline) => pipeline.id,
+            None => return,
+        };
+        let root_scroll_id = pipeline_id.root_scroll_id();
+        let visual_origin = self
+            .webrender_api
+            .get_scroll_node_state(self.webrender_document)
+            .into_iter()
+            .find(|state| state.id == root_scroll_id)
+            .map_or(LayoutPoint::zero(), |state| {
+                // WebRender scroll offsets are negative.
+                LayoutPoint::zero() - state.scroll_offset
+            });
+
+        let zoom = self.pinch_zoom_level();
+        let layout_size = self
+            .embedder_coordinates
+            .viewport
+            .size
+            .to_f32()
+            .to_untyped() /
+            self.device_pixels_per_page_px().get();
+        let visual_size = layout_size / zoom;
+        let mut layout_origin = self.layout_viewport_origin;
+        layout_origin.x = layout_origin
+            .x
+            .min(visual_origin.x)
+            .max(visual_origin.x + visual_size.width - layout_size.width);
+        layout_origin.y = layout_origin
+            .y
+            .min(visual_origin.y)
+            .max(visual_origin.y + visual_size.height - layout_size.height);
+        self.layout_viewport_origin = layout_origin;
+
+        let visual_viewport = (zoom, visual_origin - layout_origin);
+        if self.visual_viewport == visual_viewport {
+            return;
+        }
+        self.visual_viewport = visual_viewport;
+        let msg = ConstellationMsg::SetVisualViewport(
+            pipeline_id,
+            zoom,
+            Vector2D::from_untyped(visual_viewport.1.to_untyped()),
+        );
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Sending visual viewport to constellation failed ({:?}).", e);
         }
     }
 
@@ -999,11 +1360,12 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             txn.generate_frame();
             self.webrender_api
                 .send_transaction(self.webrender_document, txn);
-            self.waiting_for_results_of_scroll = true
+            self.waiting_for_results_of_scroll = true;
         }
 
         if had_events {
             self.send_viewport_rects();
+            self.send_visual_viewport();
         }
     }
 
@@ -1062,19 +1424,59 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             .as_ref()
             .map_or(false, |root_pipeline| root_pipeline.id == pipeline_id);
 
+        self.pipeline_details(pipeline_id).viewport_constraints = Some(constraints.clone());
+
         if is_root {
-            self.viewport_zoom = constraints.initial_zoom;
-            self.min_viewport_zoom = constraints.min_zoom;
-            self.max_viewport_zoom = constraints.max_zoom;
-            self.update_zoom_transform();
+            self.apply_viewport_constraints(Some(constraints));
         }
     }
 
+    fn apply_viewport_constraints(&mut self, constraints: Option<ViewportConstraints>) {
+        match constraints {
+            Some(constraints) => {
+                self.viewport_zoom = constraints.initial_zoom;
+                self.initial_viewport_zoom = constraints.initial_zoom;
+                self.min_viewport_zoom = constraints.min_zoom;
+                self.max_viewport_zoom = constraints.max_zoom;
+                self.user_zoom = constraints.user_zoom;
+            },
+            None => {
+                self.viewport_zoom = PinchZoomFactor::new(1.0);
+                self.initial_viewport_zoom = PinchZoomFactor::new(1.0);
+                self.min_viewport_zoom = None;
+                self.max_viewport_zoom = None;
+                self.user_zoom = UserZoom::Zoom;
+            },
+        }
+
+        let mut txn = webrender_api::Transaction::new();
+        txn.set_pinch_zoom(webrender_api::ZoomFactor::new(self.pinch_zoom_level()));
+        txn.generate_frame();
+        self.webrender_api
+            .send_transaction(self.webrender_document, txn);
+
+        self.update_zoom_transform();
+        self.send_visual_viewport();
+    }
+
     fn hidpi_factor(&self) -> Scale<f32, DeviceIndependentPixel, DevicePixel> {
         if self.output_file.is_some() {
             return Scale::new(1.0);
         }
-        self.embedder_coordinates.hidpi_factor
+        self.window_hidpi_factor()
+    }
+
+    /// The scale factor of the window, unless the embedder has overridden it
+    /// for the current browser.
+    fn window_hidpi_factor(&self) -> Scale<f32, DeviceIndependentPixel, DevicePixel> {
+        self.root_pipeline
+            .as_ref()
+            .and_then(|pipeline| {
+                self.device_pixel_ratio_overrides
+                    .get(&pipeline.top_level_browsing_context_id)
+            })
+            .cloned()
+            .unwrap_or(self.embedder_coordinates.hidpi_factor)
     }
 
     fn device_pixels_per_page_px(&self) -> Scale<f32, CSSPixel, DevicePixel> {
@@ -1248,6 +1650,7 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
         target: CompositeTarget,
         rect: Option<Rect<f32, CSSPixel>>,
     ) -> Result<Option<Image>, UnableToComposite> {
+        let start = precise_time_ns();
         let size = self.embedder_coordinates.framebuffer.to_u32();
 
         self.window.make_gl_context_current();
@@ -1275,7 +1678,15 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
 
         let rt_info = match target {
             #[cfg(feature = "gl")]
-            CompositeTarget::Window => gl::RenderTargetInfo::default(),
+            CompositeTarget::Window => {
+                if self.window.render_to_texture() {
+                    let size = DeviceIntSize::from_untyped(
+                        self.embedder_coordinates.framebuffer.to_untyped(),
+                    );
+                    self.bind_offscreen_target(size);
+                }
+                gl::RenderTargetInfo::default()
+            },
             #[cfg(feature = "gl")]
             CompositeTarget::WindowAndPng | CompositeTarget::PngFile => gl::initialize_png(
                 &*self.window.gl(),
@@ -1303,6 +1714,10 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             },
         );
 
+        if target == CompositeTarget::Window {
+            self.draw_devtools_highlight();
+        }
+
         // If there are pending paint metrics, we check if any of the painted epochs is
         // one of the ones that the paint metrics recorder is expecting . In that case,
         // we get the current time, inform the layout thread about it and remove the
@@ -1373,6 +1788,8 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
                     bytes: ipc::IpcSharedMemory::from_bytes(&*img),
                     id: None,
                     cors_status: CorsStatus::Safe,
+                    animation: None,
+                    orientation: ImageOrientation::Normal,
                 })
             },
             #[cfg(feature = "gl")]
@@ -1410,10 +1827,28 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             _ => None,
         };
 
-        // Perform the page flip. This will likely block for a while.
-        self.window.present();
+        if !self.present_offscreen_target(target) {
+            // Perform the page flip. This will likely block for a while.
+            self.window.present();
+        }
 
-        self.last_composite_time = precise_time_ns();
+        let end = precise_time_ns();
+        if let Some(ref observer) = self.event_loop_observer {
+            let reason = match self.composition_request {
+                CompositionRequest::NoCompositingNecessary => None,
+                CompositionRequest::CompositeNow(reason) => Some(reason),
+            };
+            let interval = match self.last_composite_time {
+                0 => 0,
+                last_composite_time => end - last_composite_time,
+            };
+            observer.frame(FrameReport {
+                reason,
+                duration: Duration::from_nanos(end - start),
+                interval: Duration::from_nanos(interval),
+            });
+        }
+        self.last_composite_time = end;
 
         self.composition_request = CompositionRequest::NoCompositingNecessary;
 
@@ -1423,6 +1858,50 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
         Ok(rv)
     }
 
+    /// Bind the offscreen render target, creating it or resizing it if needed.
+    #[cfg(feature = "gl")]
+    fn bind_offscreen_target(&mut self, size: DeviceIntSize) {
+        let gl = self.window.gl();
+        if let Some(target) = self.offscreen_target.take() {
+            if target.size() == size {
+                self.offscreen_target = Some(target);
+            } else {
+                target.delete(&*gl);
+            }
+        }
+        self.offscreen_target
+            .get_or_insert_with(|| gl::OffscreenRenderTarget::new(&*gl, size))
+            .bind(&*gl);
+    }
+
+    /// Hand the offscreen render target to the embedder, if the last frame was
+    /// rendered into it. Returns whether it was.
+    #[cfg(feature = "gl")]
+    fn present_offscreen_target(&mut self, target: CompositeTarget) -> bool {
+        if target != CompositeTarget::Window || !self.window.render_to_texture() {
+            return false;
+        }
+        let target = match self.offscreen_target {
+            Some(ref target) => target,
+            None => return false,
+        };
+        target.unbind(&*self.window.gl());
+        if let Some(ref pipeline) = self.root_pipeline {
+            let texture = OffscreenTexture {
+                texture_id: target.texture_id(),
+                size: target.size(),
+            };
+            self.window
+                .present_texture(pipeline.top_level_browsing_context_id, texture);
+        }
+        true
+    }
+
+    #[cfg(not(feature = "gl"))]
+    fn present_offscreen_target(&mut self, _target: CompositeTarget) -> bool {
+        false
+    }
+
     fn composite_if_necessary(&mut self, reason: CompositingReason) {
         if self.composition_request == CompositionRequest::NoCompositingNecessary {
             if self.is_running_problem_test {
@@ -1458,6 +1937,51 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
         gl.disable(gleam::gl::SCISSOR_TEST);
     }
 
+    /// Outline the boxes highlighted by the devtools inspector on top of the rendered page.
+    fn draw_devtools_highlight(&self) {
+        let highlight = match self.devtools_highlight {
+            Some(ref highlight) => highlight,
+            None => return,
+        };
+        let gl = self.window.gl();
+        let scale = self.device_pixels_per_page_px();
+        let viewport = self.embedder_coordinates.viewport;
+        let framebuffer_height = self.embedder_coordinates.framebuffer.height;
+        let thickness = self.hidpi_factor().get().round().max(1.0) as i32;
+
+        // Fill the given rect of the viewport, in device pixels from its top left corner.
+        let fill = |x: i32, y: i32, width: i32, height: i32| {
+            // OpenGL uses a bottom-left origin.
+            gl.scissor(
+                viewport.origin.x + x,
+                framebuffer_height - viewport.origin.y - y - height,
+                width.max(0),
+                height.max(0),
+            );
+            gl.clear(gleam::gl::COLOR_BUFFER_BIT);
+        };
+
+        // The same colors as the box model highlighter of Firefox.
+        let boxes = [
+            (highlight.margin, (0.93, 0.78, 0.52)),
+            (highlight.border, (1.0, 0.91, 0.57)),
+            (highlight.padding, (0.73, 0.55, 0.87)),
+            (highlight.content, (0.53, 0.72, 0.95)),
+        ];
+        gl.enable(gleam::gl::SCISSOR_TEST);
+        for &(rect, (r, g, b)) in boxes.iter() {
+            let rect = scale.transform_rect(&rect).round_out().to_i32();
+            let (x, y) = (rect.origin.x, rect.origin.y);
+            let (width, height) = (rect.size.width, rect.size.height);
+            gl.clear_color(r, g, b, 1.0);
+            fill(x, y, width, thickness);
+            fill(x, y + height - thickness, width, thickness);
+            fill(x, y, thickness, height);
+            fill(x + width - thickness, y, thickness, height);
+        }
+        gl.disable(gleam::gl::SCISSOR_TEST);
+    }
+
     fn get_root_pipeline_id(&self) -> Option<PipelineId> {
         self.root_pipeline.as_ref().map(|pipeline| pipeline.id)
     }
@@ -1499,6 +2023,10 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
             CompositionRequest::CompositeNow(_) => self.composite(),
         }
 
+        if self.pending_full_page_png.is_some() {
+            self.finish_full_page_png();
+        }
+
         // Send every VR display that wants one a main-thread heartbeat
         for webvr_heartbeat in &mut self.webvr_heartbeats {
             webvr_heartbeat.heartbeat();
@@ -1540,6 +2068,9 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
     }
 
     fn set_pinch_zoom_level(&mut self, mut zoom: f32) {
+        if self.user_zoom == UserZoom::Fixed {
+            return;
+        }
         if let Some(min) = self.min_viewport_zoom {
             zoom = f32::max(min.get(), zoom);
         }
@@ -1640,4 +2171,6 @@ pub enum CompositingReason {
     NewWebRenderScrollFrame,
     /// The window has been resized and will need to be synchronously repainted.
     Resize,
+    /// The devtools inspector has highlighted a different node.
+    DevtoolsHighlight,
 }
@@ -5,17 +5,18 @@
 //! Communication with the compositor thread.
 
 use crate::compositor::CompositingReason;
+use crate::windowing::EventLoopObserver;
 use crate::{ConstellationMsg, SendableFrameTree};
 use crossbeam_channel::{Receiver, Sender};
 use embedder_traits::EventLoopWaker;
-use euclid::Rect;
+use euclid::{Point2D, Rect, Size2D};
 use gfx_traits::Epoch;
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::{PipelineId, TopLevelBrowsingContextId};
 use net_traits::image::base::Image;
 use profile_traits::mem;
 use profile_traits::time;
-use script_traits::{AnimationState, EventResult, MouseButton, MouseEventType};
+use script_traits::{AnimationState, BoxModelHighlight, EventResult, MouseButton, MouseEventType};
 use std::fmt::{Debug, Error, Formatter};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
@@ -83,8 +84,17 @@ pub enum Msg {
     Recomposite(CompositingReason),
     /// Script has handled a touch event, and either prevented or allowed default actions.
     TouchEventProcessed(EventResult),
+    /// Script has found the block that was double tapped in a pipeline. Zoom so that the
+    /// rect fills the width of the viewport, keeping the point in view.
+    ZoomToRect(PipelineId, Rect<f32, CSSPixel>, Point2D<f32, CSSPixel>),
+    /// Draw the box model highlight of the devtools inspector over the given pipeline,
+    /// or remove it.
+    HighlightBoxModel(PipelineId, Option<BoxModelHighlight>),
     /// Composite to a PNG file and return the Image over a passed channel.
     CreatePng(Option<Rect<f32, CSSPixel>>, IpcSender<Option<Image>>),
+    /// Resize the viewport to the given size of the page, composite it to a PNG file,
+    /// restore the size of the viewport and return the Image over a passed channel.
+    CreateFullPagePng(Size2D<f32, CSSPixel>, IpcSender<Option<Image>>),
     /// Alerts the compositor that the viewport has been constrained in some manner
     ViewportConstrained(PipelineId, ViewportConstraints),
     /// A reply to the compositor asking if the output image is stable.
@@ -114,6 +124,8 @@ pub enum Msg {
     WebDriverMouseButtonEvent(MouseEventType, MouseButton, f32, f32),
     /// WebDriver mouse move event
     WebDriverMouseMoveEvent(f32, f32),
+    /// WebDriver mouse wheel event, with its delta
+    WebDriverWheelScrollEvent(f32, f32, f64, f64),
 
     /// Get Window Informations size and position.
     GetClientWindow(IpcSender<(DeviceIntSize, DeviceIntPoint)>),
@@ -133,7 +145,10 @@ impl Debug for Msg {
             Msg::SetFrameTree(..) => write!(f, "SetFrameTree"),
             Msg::Recomposite(..) => write!(f, "Recomposite"),
             Msg::TouchEventProcessed(..) => write!(f, "TouchEventProcessed"),
+            Msg::ZoomToRect(..) => write!(f, "ZoomToRect"),
+            Msg::HighlightBoxModel(..) => write!(f, "HighlightBoxModel"),
             Msg::CreatePng(..) => write!(f, "CreatePng"),
+            Msg::CreateFullPagePng(..) => write!(f, "CreateFullPagePng"),
             Msg::ViewportConstrained(..) => write!(f, "ViewportConstrained"),
             Msg::IsReadyToSaveImageReply(..) => write!(f, "IsReadyToSaveImageReply"),
             Msg::PipelineVisibilityChanged(..) => write!(f, "PipelineVisibilityChanged"),
@@ -144,6 +159,7 @@ impl Debug for Msg {
             Msg::LoadComplete(..) => write!(f, "LoadComplete"),
             Msg::WebDriverMouseButtonEvent(..) => write!(f, "WebDriverMouseButtonEvent"),
             Msg::WebDriverMouseMoveEvent(..) => write!(f, "WebDriverMouseMoveEvent"),
+            Msg::WebDriverWheelScrollEvent(..) => write!(f, "WebDriverWheelScrollEvent"),
             Msg::GetClientWindow(..) => write!(f, "GetClientWindow"),
             Msg::GetScreenSize(..) => write!(f, "GetScreenSize"),
             Msg::GetScreenAvailSize(..) => write!(f, "GetScreenAvailSize"),
@@ -170,4 +186,6 @@ pub struct InitialCompositorState {
     pub webvr_heartbeats: Vec<Box<dyn WebVRMainThreadHeartbeat>>,
     pub webxr_main_thread: webxr::MainThreadRegistry,
     pub pending_wr_frame: Arc<AtomicBool>,
+    /// The observer of the frames, if the embedder instruments them.
+    pub event_loop_observer: Option<Arc<dyn EventLoopObserver>>,
 }
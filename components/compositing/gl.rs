@@ -5,6 +5,7 @@
 use gleam::gl;
 use image::RgbImage;
 use servo_geometry::FramebufferUintLength;
+use webrender_api::units::DeviceIntSize;
 
 #[derive(Default)]
 pub struct RenderTargetInfo {
@@ -124,3 +125,98 @@ pub fn draw_img(
 
     RgbImage::from_raw(width as u32, height as u32, pixels).expect("Flipping image failed!")
 }
+
+/// A framebuffer backed by a texture, which is kept alive across frames so that
+/// the embedder can use the texture of the last rendered frame.
+pub struct OffscreenRenderTarget {
+    framebuffer_id: gl::GLuint,
+    texture_id: gl::GLuint,
+    depth_renderbuffer_id: gl::GLuint,
+    size: DeviceIntSize,
+}
+
+impl OffscreenRenderTarget {
+    pub fn new(gl: &dyn gl::Gl, size: DeviceIntSize) -> OffscreenRenderTarget {
+        let framebuffer_id = gl.gen_framebuffers(1)[0];
+        gl.bind_framebuffer(gl::FRAMEBUFFER, framebuffer_id);
+
+        let texture_id = gl.gen_textures(1)[0];
+        gl.bind_texture(gl::TEXTURE_2D, texture_id);
+        gl.tex_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as gl::GLint,
+            size.width as gl::GLsizei,
+            size.height as gl::GLsizei,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            None,
+        );
+        gl.tex_parameter_i(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            gl::LINEAR as gl::GLint,
+        );
+        gl.tex_parameter_i(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MIN_FILTER,
+            gl::LINEAR as gl::GLint,
+        );
+        gl.framebuffer_texture_2d(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            texture_id,
+            0,
+        );
+        gl.bind_texture(gl::TEXTURE_2D, 0);
+
+        let depth_renderbuffer_id = gl.gen_renderbuffers(1)[0];
+        gl.bind_renderbuffer(gl::RENDERBUFFER, depth_renderbuffer_id);
+        gl.renderbuffer_storage(
+            gl::RENDERBUFFER,
+            gl::DEPTH_COMPONENT24,
+            size.width as gl::GLsizei,
+            size.height as gl::GLsizei,
+        );
+        gl.framebuffer_renderbuffer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::RENDERBUFFER,
+            depth_renderbuffer_id,
+        );
+        gl.bind_renderbuffer(gl::RENDERBUFFER, 0);
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+
+        OffscreenRenderTarget {
+            framebuffer_id,
+            texture_id,
+            depth_renderbuffer_id,
+            size,
+        }
+    }
+
+    pub fn size(&self) -> DeviceIntSize {
+        self.size
+    }
+
+    pub fn texture_id(&self) -> gl::GLuint {
+        self.texture_id
+    }
+
+    /// Make the following draw calls render into this target.
+    pub fn bind(&self, gl: &dyn gl::Gl) {
+        gl.bind_framebuffer(gl::FRAMEBUFFER, self.framebuffer_id);
+    }
+
+    pub fn unbind(&self, gl: &dyn gl::Gl) {
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    pub fn delete(self, gl: &dyn gl::Gl) {
+        gl.delete_textures(&[self.texture_id]);
+        gl.delete_renderbuffers(&[self.depth_renderbuffer_id]);
+        gl.delete_framebuffers(&[self.framebuffer_id]);
+    }
+}
@@ -11,7 +11,10 @@ pub use crate::compositor::CompositingReason;
 pub use crate::compositor::IOCompositor;
 pub use crate::compositor::ShutdownState;
 pub use crate::compositor_thread::CompositorProxy;
-use embedder_traits::Cursor;
+use crossbeam_channel::Sender;
+use devtools_traits::DevtoolsControlMsg;
+use embedder_traits::{Cursor, PromptId, PromptResponse, ScreenshotId, SessionState};
+use euclid::Vector2D;
 use gfx_traits::Epoch;
 use ipc_channel::ipc::IpcSender;
 use keyboard_types::KeyboardEvent;
@@ -22,12 +25,15 @@ use script_traits::{
     AnimationTickType, LogEntry, WebDriverCommandMsg, WindowSizeData, WindowSizeType,
 };
 use script_traits::{
-    CompositorEvent, ConstellationControlMsg, LayoutControlMsg, MediaSessionActionType,
+    CompositorEvent, ConstellationControlMsg, EmbedderStylesheets, LayoutControlMsg,
+    MediaSessionActionType, ScreenshotArea,
 };
+use servo_config::prefs::PrefValue;
 use servo_url::ServoUrl;
 use std::collections::HashMap;
 use std::fmt;
 use std::time::Duration;
+use style_traits::CSSPixel;
 use webvr_traits::WebVREvent;
 
 mod compositor;
@@ -92,6 +98,8 @@ pub enum ConstellationMsg {
     WebVREvents(Vec<PipelineId>, Vec<WebVREvent>),
     /// Create a new top level browsing context.
     NewBrowser(ServoUrl, TopLevelBrowsingContextId),
+    /// Create a new top level browsing context in private browsing mode.
+    NewPrivateBrowser(ServoUrl, TopLevelBrowsingContextId),
     /// Close a top level browsing context.
     CloseBrowser(TopLevelBrowsingContextId),
     /// Panic a top level browsing context.
@@ -108,10 +116,45 @@ pub enum ConstellationMsg {
     DisableProfiler,
     /// Request to exit from fullscreen mode
     ExitFullScreen(TopLevelBrowsingContextId),
+    /// Request to exit from picture-in-picture
+    ExitPictureInPicture(TopLevelBrowsingContextId),
+    /// The set of media input or output devices changed.
+    MediaDevicesChanged,
     /// Media session action.
     MediaSessionAction(MediaSessionActionType),
     /// Toggle browser visibility.
     ChangeBrowserVisibility(TopLevelBrowsingContextId, bool),
+    /// Collect the state of all browsers, and send it to the embedder.
+    SaveSession,
+    /// Create new browsers from a previously saved session.
+    RestoreSession(SessionState),
+    /// Take a screenshot of a browser, and send it to the embedder with the given id.
+    CaptureScreenshot(TopLevelBrowsingContextId, ScreenshotArea, ScreenshotId),
+    /// The scale of the visual viewport of a top-level pipeline, or its offset from the
+    /// layout viewport, changed.
+    SetVisualViewport(PipelineId, f32, Vector2D<f32, CSSPixel>),
+    /// Free as much memory as possible, and report how much was recovered to the embedder.
+    MemoryPressure,
+    /// Collect the reports of the memory profiler, and send them to the embedder.
+    CollectMemoryReports,
+    /// Override preferences for the documents of a browser.
+    SetPreferenceOverrides(TopLevelBrowsingContextId, HashMap<String, PrefValue>),
+    /// Replace the stylesheets supplied by the embedder for the documents of a browser.
+    SetStylesheets(TopLevelBrowsingContextId, EmbedderStylesheets),
+    /// Report the number of subresources blocked by the content blocker in a browser.
+    ReportBlockedContent(TopLevelBrowsingContextId),
+    /// Answer a prompt shown by the embedder.
+    RespondToPrompt(PromptId, PromptResponse),
+    /// Report the size of the content of a browser to the embedder whenever it changes.
+    SetAutoResize(TopLevelBrowsingContextId, bool),
+    /// Freeze or resume the documents of a browser.
+    FreezeBrowser(TopLevelBrowsingContextId, bool),
+    /// Discard the documents of a hidden browser, until it is shown again.
+    DiscardBrowser(TopLevelBrowsingContextId),
+    /// Connect through a proxy, except to some hosts, or directly.
+    SetProxy(Option<String>, Vec<String>),
+    /// The devtools server was started after the constellation.
+    SetDevtoolsChan(Sender<DevtoolsControlMsg>),
 }
 
 impl fmt::Debug for ConstellationMsg {
@@ -134,6 +177,7 @@ impl fmt::Debug for ConstellationMsg {
             LogEntry(..) => "LogEntry",
             WebVREvents(..) => "WebVREvents",
             NewBrowser(..) => "NewBrowser",
+            NewPrivateBrowser(..) => "NewPrivateBrowser",
             CloseBrowser(..) => "CloseBrowser",
             SendError(..) => "SendError",
             SelectBrowser(..) => "SelectBrowser",
@@ -142,8 +186,25 @@ impl fmt::Debug for ConstellationMsg {
             EnableProfiler(..) => "EnableProfiler",
             DisableProfiler => "DisableProfiler",
             ExitFullScreen(..) => "ExitFullScreen",
+            ExitPictureInPicture(..) => "ExitPictureInPicture",
+            MediaDevicesChanged => "MediaDevicesChanged",
             MediaSessionAction(..) => "MediaSessionAction",
             ChangeBrowserVisibility(..) => "ChangeBrowserVisibility",
+            SaveSession => "SaveSession",
+            RestoreSession(..) => "RestoreSession",
+            CaptureScreenshot(..) => "CaptureScreenshot",
+            SetVisualViewport(..) => "SetVisualViewport",
+            MemoryPressure => "MemoryPressure",
+            CollectMemoryReports => "CollectMemoryReports",
+            SetPreferenceOverrides(..) => "SetPreferenceOverrides",
+            SetStylesheets(..) => "SetStylesheets",
+            ReportBlockedContent(..) => "ReportBlockedContent",
+            RespondToPrompt(..) => "RespondToPrompt",
+            SetAutoResize(..) => "SetAutoResize",
+            FreezeBrowser(..) => "FreezeBrowser",
+            DiscardBrowser(..) => "DiscardBrowser",
+            SetProxy(..) => "SetProxy",
+            SetDevtoolsChan(..) => "SetDevtoolsChan",
         };
         write!(formatter, "ConstellationMsg::{}", variant)
     }
@@ -6,13 +6,19 @@ use self::TouchState::*;
 use euclid::{Point2D, Scale, Vector2D};
 use script_traits::{EventResult, TouchId};
 use style_traits::DevicePixel;
+use time::precise_time_s;
 
 /// Minimum number of `DeviceIndependentPixel` to begin touch scrolling.
 const TOUCH_PAN_MIN_SCREEN_PX: f32 = 20.0;
 
+/// Maximum number of seconds between two taps of a double tap.
+const DOUBLE_TAP_MAX_INTERVAL: f64 = 0.3;
+
 pub struct TouchHandler {
     pub state: TouchState,
     pub active_touch_points: Vec<TouchPoint>,
+    /// The location and time of the last tap, to detect double taps.
+    last_tap: Option<(Point2D<f32, DevicePixel>, f64)>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -58,6 +64,8 @@ pub enum TouchState {
 pub enum TouchAction {
     /// Simulate a mouse click.
     Click,
+    /// Zoom on the tapped content, or zoom out if already zoomed in.
+    DoubleTap,
     /// Scroll by the provided offset.
     Scroll(Vector2D<f32, DevicePixel>),
     /// Zoom by a magnification factor and scroll by the provided offset.
@@ -73,6 +81,7 @@ impl TouchHandler {
         TouchHandler {
             state: Nothing,
             active_touch_points: Vec::new(),
+            last_tap: None,
         }
     }
 
@@ -140,7 +149,7 @@ impl TouchHandler {
         action
     }
 
-    pub fn on_touch_up(&mut self, id: TouchId, _point: Point2D<f32, DevicePixel>) -> TouchAction {
+    pub fn on_touch_up(&mut self, id: TouchId, point: Point2D<f32, DevicePixel>) -> TouchAction {
         match self.active_touch_points.iter().position(|t| t.id == id) {
             Some(i) => {
                 self.active_touch_points.swap_remove(i);
@@ -154,7 +163,19 @@ impl TouchHandler {
                 // FIXME: If the duration exceeds some threshold, send a contextmenu event instead.
                 // FIXME: Don't send a click if preventDefault is called on the touchend event.
                 self.state = Nothing;
-                TouchAction::Click
+                let now = precise_time_s();
+                match self.last_tap.take() {
+                    Some((last_point, last_time))
+                        if now - last_time < DOUBLE_TAP_MAX_INTERVAL &&
+                            (point - last_point).length() < TOUCH_PAN_MIN_SCREEN_PX =>
+                    {
+                        TouchAction::DoubleTap
+                    },
+                    _ => {
+                        self.last_tap = Some((point, now));
+                        TouchAction::Click
+                    },
+                }
             },
             Nothing | Panning => {
                 self.state = Nothing;
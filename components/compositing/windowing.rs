@@ -4,20 +4,27 @@
 
 //! Abstract windowing methods. The concrete implementations of these can be found in `platform/`.
 
+use crate::compositor::CompositingReason;
 use canvas::{SurfaceProviders, WebGlExecutor};
-use embedder_traits::EventLoopWaker;
+use embedder_traits::TaskReport;
+use embedder_traits::{EventLoopWaker, PromptId, PromptResponse, ScreenshotId, SessionState};
 use euclid::Scale;
 #[cfg(feature = "gl")]
 use gleam::gl;
 use keyboard_types::KeyboardEvent;
 use msg::constellation_msg::{PipelineId, TopLevelBrowsingContextId, TraversalDirection};
+use net_traits::protocol_handler::ProtocolRegistry;
+use script_traits::{EmbedderStylesheets, ScreenshotArea};
 use script_traits::{MediaSessionActionType, MouseButton, TouchEventType, TouchId, WheelDelta};
+use servo_config::prefs::PrefValue;
 use servo_geometry::DeviceIndependentPixel;
 use servo_media::player::context::{GlApi, GlContext, NativeDisplay};
 use servo_url::ServoUrl;
+use std::collections::HashMap;
 use std::fmt::{Debug, Error, Formatter};
 #[cfg(feature = "gl")]
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 use style_traits::DevicePixel;
 
@@ -84,12 +91,19 @@ pub enum WindowEvent {
     Quit,
     /// Sent when the user exits from fullscreen mode
     ExitFullScreen(TopLevelBrowsingContextId),
+    /// Sent when the user closes the picture-in-picture surface
+    ExitPictureInPicture(TopLevelBrowsingContextId),
+    /// Sent when the set of media input or output devices changed
+    MediaDevicesChanged,
     /// Sent when a key input state changes
     Keyboard(KeyboardEvent),
     /// Sent when Ctr+R/Apple+R is called to reload the current page.
     Reload(TopLevelBrowsingContextId),
     /// Create a new top level browsing context
     NewBrowser(ServoUrl, TopLevelBrowsingContextId),
+    /// Create a new top level browsing context in private browsing mode. Its cookies, cache
+    /// and storage are only kept in memory, and are discarded when it is closed.
+    NewPrivateBrowser(ServoUrl, TopLevelBrowsingContextId),
     /// Close a top level browsing context
     CloseBrowser(TopLevelBrowsingContextId),
     /// Panic a top level browsing context.
@@ -108,6 +122,54 @@ pub enum WindowEvent {
     MediaSessionAction(MediaSessionActionType),
     /// Set browser visibility. A hidden browser will not tick the animations.
     ChangeBrowserVisibility(TopLevelBrowsingContextId, bool),
+    /// Request the state of the current session. It will be sent back to the embedder
+    /// with `EmbedderMsg::SessionSaved`.
+    SaveSession,
+    /// Restore a session previously saved with `SaveSession`. A new browser is created
+    /// for each saved browser.
+    RestoreSession(SessionState),
+    /// Take a screenshot of a browser. It will be sent back to the embedder with
+    /// `EmbedderMsg::ScreenshotCaptured`, with the given id.
+    CaptureScreenshot(TopLevelBrowsingContextId, ScreenshotArea, ScreenshotId),
+    /// Override the device pixel ratio of a browser, or go back to the one of
+    /// the window when `None`.
+    OverrideDevicePixelRatio(TopLevelBrowsingContextId, Option<f32>),
+    /// Sent when the system is low on memory. The caches are evicted, the documents
+    /// of the session histories discarded and the JS heaps garbage collected, and the
+    /// memory recovered is reported with `EmbedderMsg::MemoryPressureHandled`.
+    MemoryPressure,
+    /// Measure the memory used by the engine. The reports of the memory profiler, with
+    /// the DOM of each document and the leaked windows, are sent back to the embedder
+    /// with `EmbedderMsg::MemoryReports`.
+    CollectMemoryReports,
+    /// Override preferences for the documents of a browser, such as `dom.scripting.enabled`,
+    /// replacing the previous overrides.
+    SetPreferenceOverrides(TopLevelBrowsingContextId, HashMap<String, PrefValue>),
+    /// Replace the user agent and user stylesheets of the documents of a browser, which
+    /// are restyled at once.
+    SetStylesheets(TopLevelBrowsingContextId, EmbedderStylesheets),
+    /// Request the number of subresources of the documents of a browser which were blocked
+    /// or rewritten by the content blocker, which is reported with
+    /// `EmbedderMsg::BlockedContentReport`.
+    ReportBlockedContent(TopLevelBrowsingContextId),
+    /// Answer a prompt shown with `EmbedderMsg::ShowPrompt`.
+    RespondToPrompt(PromptId, PromptResponse),
+    /// Ask a browser to report the size of its content with `EmbedderMsg::ContentSizeChanged`
+    /// whenever it changes, so that the embedder can size the browser to fit its content.
+    SetAutoResize(TopLevelBrowsingContextId, bool),
+    /// Freeze the documents of a browser, such as one which stays in the background,
+    /// or resume them. Their timers and tasks wait until they are resumed, and they are
+    /// notified with the `freeze` and `resume` events.
+    FreezeBrowser(TopLevelBrowsingContextId, bool),
+    /// Discard the documents of a hidden browser to free their memory. The current one
+    /// keeps its entry in the session history, and is loaded again with
+    /// `document.wasDiscarded` set once the browser is selected, shown, reloaded or
+    /// traversed, without any unload event or embedder message in between.
+    DiscardBrowser(TopLevelBrowsingContextId),
+    /// Connect through the proxy of the given URL, such as `http://proxy:3128` or
+    /// `socks5://proxy:1080`, except to the given hosts, or directly when `None`. It
+    /// applies to the connections which the network stack opens from now on.
+    SetProxy(Option<String>, Vec<String>),
 }
 
 impl Debug for WindowEvent {
@@ -131,6 +193,7 @@ impl Debug for WindowEvent {
             WindowEvent::Quit => write!(f, "Quit"),
             WindowEvent::Reload(..) => write!(f, "Reload"),
             WindowEvent::NewBrowser(..) => write!(f, "NewBrowser"),
+            WindowEvent::NewPrivateBrowser(..) => write!(f, "NewPrivateBrowser"),
             WindowEvent::SendError(..) => write!(f, "SendError"),
             WindowEvent::CloseBrowser(..) => write!(f, "CloseBrowser"),
             WindowEvent::SelectBrowser(..) => write!(f, "SelectBrowser"),
@@ -138,8 +201,24 @@ impl Debug for WindowEvent {
             WindowEvent::CaptureWebRender => write!(f, "CaptureWebRender"),
             WindowEvent::ToggleSamplingProfiler(..) => write!(f, "ToggleSamplingProfiler"),
             WindowEvent::ExitFullScreen(..) => write!(f, "ExitFullScreen"),
+            WindowEvent::ExitPictureInPicture(..) => write!(f, "ExitPictureInPicture"),
+            WindowEvent::MediaDevicesChanged => write!(f, "MediaDevicesChanged"),
             WindowEvent::MediaSessionAction(..) => write!(f, "MediaSessionAction"),
             WindowEvent::ChangeBrowserVisibility(..) => write!(f, "ChangeBrowserVisibility"),
+            WindowEvent::SaveSession => write!(f, "SaveSession"),
+            WindowEvent::RestoreSession(..) => write!(f, "RestoreSession"),
+            WindowEvent::CaptureScreenshot(..) => write!(f, "CaptureScreenshot"),
+            WindowEvent::OverrideDevicePixelRatio(..) => write!(f, "OverrideDevicePixelRatio"),
+            WindowEvent::MemoryPressure => write!(f, "MemoryPressure"),
+            WindowEvent::CollectMemoryReports => write!(f, "CollectMemoryReports"),
+            WindowEvent::SetPreferenceOverrides(..) => write!(f, "SetPreferenceOverrides"),
+            WindowEvent::SetStylesheets(..) => write!(f, "SetStylesheets"),
+            WindowEvent::ReportBlockedContent(..) => write!(f, "ReportBlockedContent"),
+            WindowEvent::RespondToPrompt(..) => write!(f, "RespondToPrompt"),
+            WindowEvent::SetAutoResize(..) => write!(f, "SetAutoResize"),
+            WindowEvent::FreezeBrowser(..) => write!(f, "FreezeBrowser"),
+            WindowEvent::DiscardBrowser(..) => write!(f, "DiscardBrowser"),
+            WindowEvent::SetProxy(..) => write!(f, "SetProxy"),
         }
     }
 }
@@ -171,6 +250,25 @@ pub trait WindowMethods {
     fn get_native_display(&self) -> NativeDisplay;
     /// Get the GL api
     fn get_gl_api(&self) -> GlApi;
+    /// Whether the compositor should render into an offscreen texture instead of
+    /// the framebuffer of the window. When it does, `present_texture` is called
+    /// instead of `present` once a frame has been rendered.
+    fn render_to_texture(&self) -> bool {
+        false
+    }
+    /// Hands the texture containing the last frame rendered for a browser to the embedder.
+    /// The texture belongs to the compositor, and is only valid until the next frame.
+    fn present_texture(&self, _browser_id: TopLevelBrowsingContextId, _texture: OffscreenTexture) {}
+}
+
+/// A texture in the GL context of the compositor, containing a rendered frame.
+#[derive(Clone, Copy, Debug)]
+pub struct OffscreenTexture {
+    /// The GL name of the texture.
+    pub texture_id: u32,
+    /// The size of the texture. The frame is upside down, as the origin of
+    /// GL textures is their bottom-left corner.
+    pub size: DeviceIntSize,
 }
 
 pub trait EmbedderMethods {
@@ -192,6 +290,42 @@ pub trait EmbedderMethods {
         _: SurfaceProviders,
     ) {
     }
+
+    /// The handlers of the custom schemes which the embedder serves, such as the
+    /// bundled assets of an application.
+    fn get_protocol_handlers(&self) -> ProtocolRegistry {
+        ProtocolRegistry::default()
+    }
+
+    /// The observer of the event loops, if the embedder instruments them.
+    fn create_event_loop_observer(&mut self) -> Option<Arc<dyn EventLoopObserver>> {
+        None
+    }
+}
+
+/// A frame which the compositor painted.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameReport {
+    /// Why the frame was painted, unless it was painted on demand, such as for a
+    /// screenshot.
+    pub reason: Option<CompositingReason>,
+    /// How long the compositor took to paint the frame, including the page flip.
+    pub duration: Duration,
+    /// The time since the previous frame, which exceeds the refresh interval of the
+    /// display for janky frames. It is zero for the first frame.
+    pub interval: Duration,
+}
+
+/// Receives the instrumentation of the script threads and the compositor, so that
+/// embedders can build performance dashboards. The long tasks are reported from the
+/// constellation thread, and the frames from the thread of the compositor.
+pub trait EventLoopObserver: Send + Sync {
+    /// A task of the given browser ran for longer than the
+    /// `dom.instrumentation.long_task_threshold` pref.
+    fn long_task(&self, _browser_id: TopLevelBrowsingContextId, _report: TaskReport) {}
+
+    /// The compositor painted a frame.
+    fn frame(&self, _report: FrameReport) {}
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -16,8 +16,11 @@ pub mod pref_util;
 pub mod prefs;
 
 pub mod basedir;
+pub mod locale;
 #[allow(unsafe_code)]
 pub mod opts;
+pub mod proxy;
+pub mod user_agent;
 
 pub fn servo_version() -> String {
     let cargo_version = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,109 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The languages of the user, in order of preference.
+//!
+//! They are the language tags of the `intl.accept_languages` preference, separated by
+//! commas, such as `fr-CH,fr,en`. They are exposed to documents by `navigator.language`
+//! and `navigator.languages`, sent to sites in the `Accept-Language` header, and the first
+//! one is the default locale of the `Intl` objects and of the form controls. The changes
+//! made through `Preferences::set`, as `set_languages` does, reach the documents, which
+//! are then sent a `languagechange` event.
+
+use crate::prefs;
+
+/// The language of the user when the preference has no valid language tag.
+const DEFAULT_LANGUAGE: &str = "en-US";
+
+/// The languages of the user, in order of preference, of which there is at least one.
+pub fn languages() -> Vec<String> {
+    let mut languages: Vec<String> = vec![];
+    for language in pref!(intl.accept_languages)
+        .split(',')
+        .filter_map(normalize_language)
+    {
+        if !languages
+            .iter()
+            .any(|other| other.eq_ignore_ascii_case(&language))
+        {
+            languages.push(language);
+        }
+    }
+    if languages.is_empty() {
+        languages.push(DEFAULT_LANGUAGE.to_owned());
+    }
+    languages
+}
+
+/// The preferred language of the user.
+pub fn language() -> String {
+    languages().remove(0)
+}
+
+/// Set the languages of the user, in order of preference, from language tags or POSIX
+/// locales such as `pt_BR.UTF-8`.
+pub fn set_languages(languages: &[&str]) -> Result<(), String> {
+    if languages.is_empty() {
+        return Err("there is no language".to_owned());
+    }
+    let languages = languages
+        .iter()
+        .map(|language| {
+            normalize_language(language)
+                .ok_or_else(|| format!("invalid language tag {:?}", language))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    prefs::pref_map()
+        .set("intl.accept_languages", languages.join(","))
+        .map_err(|e| e.to_string())
+}
+
+/// The value of the `Accept-Language` header, in which the languages after the first one
+/// are given decreasing weights, such as `fr-CH, fr; q=0.7, en; q=0.3`.
+pub fn accept_language() -> String {
+    let languages = languages();
+    let count = languages.len();
+    languages
+        .into_iter()
+        .enumerate()
+        .map(|(index, language)| {
+            if index == 0 {
+                return language;
+            }
+            // The weights are rounded to tenths, but never to 0, which would mean that
+            // the language is not accepted.
+            let weight = (((count - index) * 10 + count / 2) / count).max(1);
+            format!("{}; q=0.{}", language, weight)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A language tag, with the subtags of a POSIX locale separated by hyphens and without
+/// its encoding and modifier, or `None` if it isn't well-formed.
+fn normalize_language(language: &str) -> Option<String> {
+    let language = language
+        .trim()
+        .split(|c| c == '.' || c == '@')
+        .next()
+        .unwrap_or("")
+        .replace('_', "-");
+    let mut subtags = language.split('-');
+    let primary = subtags.next().unwrap_or("");
+    if primary.len() < 2 ||
+        primary.len() > 8 ||
+        !primary.bytes().all(|byte| byte.is_ascii_alphabetic())
+    {
+        return None;
+    }
+    let valid_subtags = subtags.all(|subtag| {
+        !subtag.is_empty() &&
+            subtag.len() <= 8 &&
+            subtag.bytes().all(|byte| byte.is_ascii_alphanumeric())
+    });
+    if !valid_subtags {
+        return None;
+    }
+    Some(language)
+}
@@ -509,6 +509,17 @@ fn default_user_agent_string(agent: UserAgent) -> &'static str {
     }
 }
 
+/// The user agent string which a value of the `--user-agent` option stands for, which is
+/// either one of the `ios`, `android` and `desktop` keywords or the user agent string itself.
+pub(crate) fn parse_user_agent(user_agent: String) -> Cow<'static, str> {
+    match &*user_agent {
+        "ios" => default_user_agent_string(UserAgent::iOS).into(),
+        "android" => default_user_agent_string(UserAgent::Android).into(),
+        "desktop" => default_user_agent_string(UserAgent::Desktop).into(),
+        _ => user_agent.into(),
+    }
+}
+
 #[cfg(target_os = "android")]
 const DEFAULT_USER_AGENT: UserAgent = UserAgent::Android;
 
@@ -913,10 +924,7 @@ pub fn from_cmdline_args(mut opts: Options, args: &[String]) -> ArgumentParsingR
     }
 
     let user_agent = match opt_match.opt_str("u") {
-        Some(ref ua) if ua == "ios" => default_user_agent_string(UserAgent::iOS).into(),
-        Some(ref ua) if ua == "android" => default_user_agent_string(UserAgent::Android).into(),
-        Some(ref ua) if ua == "desktop" => default_user_agent_string(UserAgent::Desktop).into(),
-        Some(ua) => ua.into(),
+        Some(ua) => parse_user_agent(ua),
         None => default_user_agent_string(DEFAULT_USER_AGENT).into(),
     };
 
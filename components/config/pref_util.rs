@@ -6,7 +6,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum PrefValue {
@@ -188,10 +188,15 @@ impl<P, V> Accessor<P, V> {
     }
 }
 
+/// A callback which is notified of each preference changed through `Preferences`, with
+/// its key and new value, on the thread which changed it.
+pub type PrefObserver = Box<dyn Fn(&str, &PrefValue) + Send + Sync>;
+
 pub struct Preferences<'m, P> {
     user_prefs: Arc<RwLock<P>>,
     default_prefs: P,
     accessors: &'m HashMap<String, Accessor<P, PrefValue>>,
+    observers: RwLock<Vec<PrefObserver>>,
 }
 
 impl<'m, P: Clone> Preferences<'m, P> {
@@ -202,6 +207,53 @@ impl<'m, P: Clone> Preferences<'m, P> {
             user_prefs: Arc::new(RwLock::new(default_prefs.clone())),
             default_prefs,
             accessors,
+            observers: RwLock::new(vec![]),
+        }
+    }
+
+    /// Subscribe to the changes of the preferences made by `set`, `set_all`, `reset` and
+    /// `reset_all`, but not the ones made directly to the values, as `set_pref!` does.
+    /// The observers must not subscribe other observers.
+    pub fn add_observer<F>(&self, observer: F)
+    where
+        F: Fn(&str, &PrefValue) + Send + Sync + 'static,
+    {
+        self.observers.write().unwrap().push(Box::new(observer));
+    }
+
+    /// Subscribe to the changes of the typed value which `getter` reads from the values,
+    /// such as the value of one preference, as `observe_pref!` does. The observer is
+    /// called with the new value each time it differs from the last one.
+    pub fn add_typed_observer<T, G, F>(&self, getter: G, observer: F)
+    where
+        P: Send + Sync + 'static,
+        T: Clone + PartialEq + Send + 'static,
+        G: Fn(&P) -> T + Send + Sync + 'static,
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        let values = self.values();
+        let last_value = Mutex::new(getter(&values.read().unwrap()));
+        self.add_observer(move |_, _| {
+            let value = getter(&values.read().unwrap());
+            {
+                let mut last_value = last_value.lock().unwrap();
+                if *last_value == value {
+                    return;
+                }
+                *last_value = value.clone();
+            }
+            observer(value);
+        });
+    }
+
+    /// Notify the observers of the changed preferences, once the values are unlocked so
+    /// that they can read them.
+    fn notify(&self, changes: Vec<(String, PrefValue)>) {
+        let observers = self.observers.read().unwrap();
+        for (key, value) in &changes {
+            for observer in observers.iter() {
+                observer(key, value);
+            }
         }
     }
 
@@ -220,6 +272,15 @@ impl<'m, P: Clone> Preferences<'m, P> {
         }
     }
 
+    /// Retrieve the default value of a preference using its key
+    pub fn get_default(&self, key: &str) -> PrefValue {
+        if let Some(accessor) = self.accessors.get(key) {
+            (accessor.getter)(&self.default_prefs)
+        } else {
+            PrefValue::Missing
+        }
+    }
+
     /// Creates an iterator over all keys and values
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = (String, PrefValue)> + 'a {
         let prefs = self.user_prefs.read().unwrap();
@@ -233,12 +294,25 @@ impl<'m, P: Clone> Preferences<'m, P> {
         self.accessors.keys().map(String::as_str)
     }
 
-    fn set_inner<V>(&self, key: &str, mut prefs: &mut P, val: V) -> Result<(), PrefError>
+    /// Set a preference, returning its new value if it changed.
+    fn set_inner<V>(
+        &self,
+        key: &str,
+        mut prefs: &mut P,
+        val: V,
+    ) -> Result<Option<(String, PrefValue)>, PrefError>
     where
         V: Into<PrefValue>,
     {
         if let Some(accessor) = self.accessors.get(key) {
-            Ok((accessor.setter)(&mut prefs, val.into()))
+            let old_pref = (accessor.getter)(&prefs);
+            (accessor.setter)(&mut prefs, val.into());
+            let new_pref = (accessor.getter)(&prefs);
+            if new_pref == old_pref {
+                Ok(None)
+            } else {
+                Ok(Some((String::from(key), new_pref)))
+            }
         } else {
             Err(PrefError::NoSuchPref(String::from(key)))
         }
@@ -249,27 +323,42 @@ impl<'m, P: Clone> Preferences<'m, P> {
     where
         V: Into<PrefValue>,
     {
-        let mut prefs = self.user_prefs.write().unwrap();
-        self.set_inner(key, &mut prefs, val)
+        let change = {
+            let mut prefs = self.user_prefs.write().unwrap();
+            self.set_inner(key, &mut prefs, val)?
+        };
+        self.notify(change.into_iter().collect());
+        Ok(())
     }
 
     pub fn set_all<M>(&self, values: M) -> Result<(), PrefError>
     where
         M: IntoIterator<Item = (String, PrefValue)>,
     {
-        let mut prefs = self.user_prefs.write().unwrap();
-        for (k, v) in values.into_iter() {
-            self.set_inner(&k, &mut prefs, v)?;
-        }
-        Ok(())
+        let mut changes = vec![];
+        let result = {
+            let mut prefs = self.user_prefs.write().unwrap();
+            values.into_iter().try_for_each(|(k, v)| {
+                changes.extend(self.set_inner(&k, &mut prefs, v)?);
+                Ok(())
+            })
+        };
+        self.notify(changes);
+        result
     }
 
     pub fn reset(&self, key: &str) -> Result<PrefValue, PrefError> {
         if let Some(accessor) = self.accessors.get(key) {
-            let mut prefs = self.user_prefs.write().unwrap();
-            let old_pref = (accessor.getter)(&prefs);
-            let default_pref = (accessor.getter)(&self.default_prefs);
-            (accessor.setter)(&mut prefs, default_pref);
+            let (old_pref, default_pref) = {
+                let mut prefs = self.user_prefs.write().unwrap();
+                let old_pref = (accessor.getter)(&prefs);
+                let default_pref = (accessor.getter)(&self.default_prefs);
+                (accessor.setter)(&mut prefs, default_pref.clone());
+                (old_pref, default_pref)
+            };
+            if default_pref != old_pref {
+                self.notify(vec![(String::from(key), default_pref)]);
+            }
             Ok(old_pref)
         } else {
             Err(PrefError::NoSuchPref(String::from(key)))
@@ -277,6 +366,23 @@ impl<'m, P: Clone> Preferences<'m, P> {
     }
 
     pub fn reset_all(&self) {
-        *self.user_prefs.write().unwrap() = self.default_prefs.clone();
+        let changes = {
+            let mut prefs = self.user_prefs.write().unwrap();
+            let changes = self
+                .accessors
+                .iter()
+                .filter_map(|(key, accessor)| {
+                    let default_pref = (accessor.getter)(&self.default_prefs);
+                    if (accessor.getter)(&prefs) == default_pref {
+                        None
+                    } else {
+                        Some((key.clone(), default_pref))
+                    }
+                })
+                .collect();
+            *prefs = self.default_prefs.clone();
+            changes
+        };
+        self.notify(changes);
     }
 }
@@ -47,6 +47,18 @@ macro_rules! set_pref {
     }};
 }
 
+/// A convenience macro for subscribing to the changes of a preference using its static
+/// path, with its typed value. Passing an invalid path is a compile-time error.
+#[macro_export]
+macro_rules! observe_pref {
+    ($($segment: ident).+, $observer: expr) => {{
+        $crate::prefs::pref_map().add_typed_observer(
+            |prefs| prefs $(.$segment)+.clone(),
+            $observer,
+        )
+    }};
+}
+
 /// Access preferences using their `String` keys. Note that the key may be different from the
 /// static path because legacy keys contain hyphens, or because a preference name has been renamed.
 ///
@@ -158,10 +170,19 @@ mod gen {
                     },
                 },
             },
+            devtools: {
+                server: {
+                    enabled: bool,
+                    port: i64,
+                },
+            },
             dom: {
                 webgpu: {
                     enabled: bool,
                 },
+                audio_output: {
+                    enabled: bool,
+                },
                 bluetooth: {
                     enabled: bool,
                     testing: {
@@ -176,10 +197,16 @@ mod gen {
                     #[serde(rename = "dom.compositionevent.enabled")]
                     enabled: bool,
                 },
+                cookiestore: {
+                    enabled: bool,
+                },
                 custom_elements: {
                     #[serde(rename = "dom.customelements.enabled")]
                     enabled: bool,
                 },
+                dialogs: {
+                    max_without_activation: i64,
+                },
                 document: {
                     dblclick_timeout: i64,
                     dblclick_dist: i64,
@@ -193,6 +220,10 @@ mod gen {
                 gamepad: {
                     enabled: bool,
                 },
+                instrumentation: {
+                    enabled: bool,
+                    long_task_threshold: i64,
+                },
                 microdata: {
                     testing: {
                         enabled: bool,
@@ -207,6 +238,15 @@ mod gen {
                 mutation_observer: {
                     enabled: bool,
                 },
+                navigator: {
+                    device_memory: {
+                        max: f64,
+                        min: f64,
+                    },
+                    hardware_concurrency: {
+                        max: i64,
+                    },
+                },
                 offscreen_canvas: {
                     enabled: bool,
                 },
@@ -216,6 +256,15 @@ mod gen {
                         allowed_in_nonsecure_contexts: bool,
                     }
                 },
+                picture_in_picture: {
+                    enabled: bool,
+                },
+                resize_observer: {
+                    enabled: bool,
+                },
+                scripting: {
+                    enabled: bool,
+                },
                 serviceworker: {
                     enabled: bool,
                     timeout_seconds: i64,
@@ -277,6 +326,10 @@ mod gen {
                     #[serde(default)]
                     enabled: bool,
                 },
+                user_activation: {
+                    required: bool,
+                    transient_timeout_ms: i64,
+                },
                 webgl: {
                     dom_to_texture: {
                         enabled: bool,
@@ -325,6 +378,9 @@ mod gen {
                     enabled: bool,
                 }
             },
+            intl: {
+                accept_languages: String,
+            },
             js: {
                 asmjs: {
                     enabled: bool,
@@ -375,6 +431,11 @@ mod gen {
                         high_frequency_high_limit_mb: i64,
                         high_frequency_low_limit_mb: i64,
                         high_frequency_time_limit_ms: i64,
+                        idle: {
+                            enabled: bool,
+                            slice_ms: i64,
+                            threshold_mb: i64,
+                        },
                         incremental: {
                             enabled: bool,
                             slice_ms: i64,
@@ -415,6 +476,12 @@ mod gen {
                     enabled: bool,
                 },
                 timers: {
+                    alignment: i64,
+                    budget: {
+                        delay: i64,
+                        maximum: i64,
+                        regeneration_rate: i64,
+                    },
                     minimum_duration: i64,
                 },
                 wasm: {
@@ -426,6 +493,9 @@ mod gen {
                         enabled: bool,
                     }
                 },
+                weakrefs: {
+                    enabled: bool,
+                },
                 werror: {
                     enabled: bool,
                 },
@@ -439,6 +509,9 @@ mod gen {
                 columns: {
                     enabled: bool,
                 },
+                display_color_gamut: String,
+                prefers_color_scheme: String,
+                prefers_reduced_motion: bool,
                 #[serde(default = "default_layout_threads")]
                 threads: i64,
                 viewport: {
@@ -450,6 +523,14 @@ mod gen {
                 }
             },
             media: {
+                autoplay: {
+                    muted: {
+                        enabled: bool,
+                    },
+                    unmuted: {
+                        enabled: bool,
+                    },
+                },
                 glvideo: {
                     enabled: bool,
                 },
@@ -458,6 +539,20 @@ mod gen {
                 }
             },
             network: {
+                client_hints: {
+                    architecture: String,
+                    brand: String,
+                    enabled: bool,
+                    full_version: String,
+                    mobile: bool,
+                    model: String,
+                    platform: String,
+                    platform_version: String,
+                },
+                content_blocker: {
+                    enabled: bool,
+                    filter_lists: String,
+                },
                 enforce_tls: {
                     enabled: bool,
                     localhost: bool,
@@ -466,9 +561,25 @@ mod gen {
                 http_cache: {
                     #[serde(rename = "network.http-cache.disabled")]
                     disabled: bool,
+                    #[serde(rename = "network.http-cache.disk-capacity")]
+                    disk_capacity: i64,
                 },
                 mime: {
                     sniff: bool,
+                },
+                proxy: {
+                    bypass: String,
+                    url: String,
+                },
+                user_agent: {
+                    default: String,
+                    overrides: String,
+                },
+            },
+            profiler: {
+                trace: {
+                    enabled: bool,
+                    path: String,
                 }
             },
             session_history: {
@@ -0,0 +1,43 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate servo_config;
+
+use servo_config::locale::{accept_language, language, languages, set_languages};
+
+#[test]
+fn test_languages() {
+    assert_eq!(languages(), vec!["en-US", "en"]);
+    assert_eq!(accept_language(), "en-US, en; q=0.5");
+
+    set_languages(&["fr-CH", "fr", "en"]).unwrap();
+    assert_eq!(pref!(intl.accept_languages), "fr-CH,fr,en");
+    assert_eq!(language(), "fr-CH");
+    assert_eq!(accept_language(), "fr-CH, fr; q=0.7, en; q=0.3");
+
+    set_languages(&["pt_BR.UTF-8", " de "]).unwrap();
+    assert_eq!(languages(), vec!["pt-BR", "de"]);
+
+    assert!(set_languages(&[]).is_err());
+    assert!(set_languages(&["en-US", "not a language"]).is_err());
+    assert!(set_languages(&["e"]).is_err());
+    assert!(set_languages(&["en--US"]).is_err());
+    assert_eq!(languages(), vec!["pt-BR", "de"]);
+
+    // The invalid and repeated language tags of the preference are skipped.
+    set_pref!(intl.accept_languages, "ja, x, JA, ,zh-Hant-TW".to_owned());
+    assert_eq!(languages(), vec!["ja", "zh-Hant-TW"]);
+
+    set_pref!(intl.accept_languages, "?".to_owned());
+    assert_eq!(languages(), vec!["en-US"]);
+    assert_eq!(accept_language(), "en-US");
+
+    let many: Vec<String> = (b'a'..=b'l').map(|c| format!("l{}", c as char)).collect();
+    let many: Vec<&str> = many.iter().map(String::as_str).collect();
+    set_languages(&many).unwrap();
+    assert!(accept_language().ends_with("ll; q=0.1"));
+
+    set_pref!(intl.accept_languages, "en-US,en".to_owned());
+}
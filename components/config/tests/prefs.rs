@@ -12,6 +12,7 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fs::{self, File};
 use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn test_create_prefs_map() {
@@ -244,6 +245,103 @@ fn test_set_all_error_on_unknown_field() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn observe(prefs: &Preferences<gen::TestPrefs>) -> Arc<Mutex<Vec<(String, PrefValue)>>> {
+    let changes = Arc::new(Mutex::new(vec![]));
+    let observed_changes = changes.clone();
+    prefs.add_observer(move |key, value| {
+        observed_changes
+            .lock()
+            .unwrap()
+            .push((key.to_owned(), value.clone()))
+    });
+    changes
+}
+
+#[test]
+fn test_observers_notified_of_changes() -> Result<(), Box<dyn Error>> {
+    let def_prefs: gen::TestPrefs = serde_json::from_str(DEF_JSON_STR)?;
+    let prefs = Preferences::new(def_prefs, &gen::TEST_PREF_ACCESSORS);
+    let changes = observe(&prefs);
+
+    prefs.set("pref_i64", 24)?;
+    prefs.set("pref_bool", true)?;
+    prefs.reset("pref_i64")?;
+    assert_eq!(
+        *changes.lock().unwrap(),
+        vec![
+            (String::from("pref_i64"), PrefValue::from(24)),
+            (String::from("pref_i64"), PrefValue::from(23)),
+        ],
+        "The preferences which keep their value aren't notified"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_observers_notified_of_set_all_and_reset_all() -> Result<(), Box<dyn Error>> {
+    let def_prefs: gen::TestPrefs = serde_json::from_str(DEF_JSON_STR)?;
+    let prefs = Preferences::new(def_prefs, &gen::TEST_PREF_ACCESSORS);
+    let changes = observe(&prefs);
+
+    let mut overrides = HashMap::new();
+    overrides.insert(String::from("pref_string"), PrefValue::from("new value"));
+    overrides.insert(String::from("a.renamed.pref"), PrefValue::from(42));
+    prefs.set_all(overrides.into_iter())?;
+    assert_eq!(
+        *changes.lock().unwrap(),
+        vec![(String::from("pref_string"), PrefValue::from("new value"))]
+    );
+
+    changes.lock().unwrap().clear();
+    prefs.reset_all();
+    assert_eq!(
+        *changes.lock().unwrap(),
+        vec![(String::from("pref_string"), PrefValue::from("hello"))]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_observers_can_read_prefs() -> Result<(), Box<dyn Error>> {
+    let prefs = Preferences::new(gen::TestPrefs::default(), &gen::TEST_PREF_ACCESSORS);
+    let values = prefs.values();
+    let observed_values = Arc::new(Mutex::new(vec![]));
+    let observed = observed_values.clone();
+    prefs.add_observer(move |_, _| {
+        observed
+            .lock()
+            .unwrap()
+            .push(values.read().unwrap().pref_i64)
+    });
+
+    prefs.set("pref_i64", 5)?;
+    assert_eq!(*observed_values.lock().unwrap(), vec![5]);
+    Ok(())
+}
+
+#[test]
+fn test_typed_observers_notified_of_their_value() -> Result<(), Box<dyn Error>> {
+    let def_prefs: gen::TestPrefs = serde_json::from_str(DEF_JSON_STR)?;
+    let prefs = Preferences::new(def_prefs, &gen::TEST_PREF_ACCESSORS);
+    let values = Arc::new(Mutex::new(vec![]));
+    let observed_values = values.clone();
+    prefs.add_typed_observer(
+        |prefs| prefs.pref_i64,
+        move |value: i64| observed_values.lock().unwrap().push(value),
+    );
+
+    prefs.set("pref_bool", true)?;
+    prefs.set("pref_i64", 24)?;
+    prefs.set("pref_i64", 24)?;
+    prefs.reset_all();
+    assert_eq!(
+        *values.lock().unwrap(),
+        vec![24, 23],
+        "The observer is only notified when its value changes"
+    );
+    Ok(())
+}
+
 #[cfg(not(any(target_os = "android", feature = "uwp")))]
 #[test]
 fn test_default_config_dir_create_read_write() {
@@ -0,0 +1,69 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate servo_config;
+
+use servo_config::proxy::{proxy_for_host, set_proxy, Proxy, ProxyKind};
+
+#[test]
+fn test_proxy_parse() {
+    assert_eq!(
+        Proxy::parse("http://proxy.example:3128"),
+        Ok(Proxy {
+            kind: ProxyKind::Http,
+            host: "proxy.example".to_owned(),
+            port: 3128,
+            credentials: None,
+        })
+    );
+    assert_eq!(
+        Proxy::parse("socks5://user:p%40ss@[::2]"),
+        Ok(Proxy {
+            kind: ProxyKind::Socks5,
+            host: "::2".to_owned(),
+            port: 1080,
+            credentials: Some(("user".to_owned(), "p@ss".to_owned())),
+        })
+    );
+    assert!(Proxy::parse("ftp://proxy.example").is_err());
+    assert!(Proxy::parse("not a url").is_err());
+}
+
+#[test]
+fn test_proxy_for_host() {
+    assert!(set_proxy(Some("gopher://proxy.example"), &[]).is_err());
+    assert_eq!(pref!(network.proxy.url), "");
+    assert_eq!(proxy_for_host("example.com"), None);
+
+    set_proxy(
+        Some("http://proxy.example:3128"),
+        &["intranet.example", ".corp.example", "<local>", "192.0.2.1"],
+    )
+    .unwrap();
+    assert_eq!(
+        pref!(network.proxy.bypass),
+        "intranet.example,.corp.example,<local>,192.0.2.1"
+    );
+
+    let proxied = |host| proxy_for_host(host).is_some();
+    assert!(proxied("example.com"));
+    assert!(proxied("notintranet.example"));
+    assert!(proxied("192.0.2.2"));
+    assert!(!proxied("intranet.example"));
+    assert!(!proxied("www.Intranet.example"));
+    assert!(!proxied("corp.example"));
+    assert!(!proxied("a.b.corp.example"));
+    assert!(!proxied("printer"));
+    assert!(!proxied("192.0.2.1"));
+    assert!(!proxied("localhost"));
+    assert!(!proxied("127.0.0.1"));
+    assert!(!proxied("[::1]"));
+
+    set_proxy(Some("http://proxy.example:3128"), &["*"]).unwrap();
+    assert!(!proxied("example.com"));
+
+    set_proxy(None, &[]).unwrap();
+    assert!(!proxied("example.com"));
+}
@@ -0,0 +1,73 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate servo_config;
+
+use servo_config::user_agent::{set_user_agent_override, user_agent_for_host};
+use std::sync::mpsc::channel;
+use std::sync::Mutex;
+
+const USER_AGENT: &'static str = "Servo Test";
+
+#[test]
+fn test_user_agent_overrides() {
+    let (sender, receiver) = channel();
+    let sender = Mutex::new(sender);
+    observe_pref!(network.user_agent.overrides, move |overrides: String| {
+        let _ = sender.lock().unwrap().send(overrides);
+    });
+
+    set_user_agent_override("example.com", Some("Override"));
+    set_user_agent_override("www.example.com", Some("More Specific"));
+    set_user_agent_override("example.org", Some("Removed"));
+    set_user_agent_override("example.org", None);
+    assert_eq!(
+        pref!(network.user_agent.overrides),
+        "example.com=Override|www.example.com=More Specific"
+    );
+    assert_eq!(
+        receiver.try_iter().last().unwrap(),
+        "example.com=Override|www.example.com=More Specific",
+        "The observers are notified of the overrides"
+    );
+
+    assert_eq!(user_agent_for_host(None, USER_AGENT), USER_AGENT);
+    assert_eq!(
+        user_agent_for_host(Some("example.org"), USER_AGENT),
+        USER_AGENT
+    );
+    assert_eq!(
+        user_agent_for_host(Some("notexample.com"), USER_AGENT),
+        USER_AGENT
+    );
+    assert_eq!(
+        user_agent_for_host(Some("example.com"), USER_AGENT),
+        "Override"
+    );
+    assert_eq!(
+        user_agent_for_host(Some("A.Example.com"), USER_AGENT),
+        "Override"
+    );
+    assert_eq!(
+        user_agent_for_host(Some("a.www.example.com"), USER_AGENT),
+        "More Specific"
+    );
+
+    set_pref!(network.user_agent.default, "Default".to_owned());
+    assert_eq!(
+        user_agent_for_host(Some("example.org"), USER_AGENT),
+        "Default"
+    );
+    assert_eq!(
+        user_agent_for_host(Some("example.com"), USER_AGENT),
+        "Override"
+    );
+
+    set_user_agent_override("example.com", Some("android"));
+    assert!(user_agent_for_host(Some("example.com"), USER_AGENT).contains("Android"));
+
+    set_pref!(network.user_agent.default, String::new());
+    set_pref!(network.user_agent.overrides, String::new());
+}
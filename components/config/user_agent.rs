@@ -0,0 +1,78 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The user agent string which is sent to sites and exposed to their documents.
+//!
+//! It is the one of the `--user-agent` option, unless embedders replace it with the
+//! `network.user_agent.default` preference, or with the `network.user_agent.overrides`
+//! preference for the sites which only work with the user agent string of another browser.
+//! The overrides are entries separated by `|`, each of which is a domain and a user agent
+//! string separated by `=`, such as `example.com=Mozilla/5.0 (Android; Mobile) ...`, and
+//! which apply to the subdomains of the domain too. Both preferences also take the `ios`,
+//! `android` and `desktop` keywords, like the option does.
+//!
+//! The user agent client hints are described by the `network.client_hints` preferences.
+
+use crate::opts::parse_user_agent;
+use crate::prefs::pref_map;
+use std::borrow::Cow;
+
+/// The user agent string for a host, given the one of the `--user-agent` option.
+pub fn user_agent_for_host<'a>(host: Option<&str>, user_agent: &'a str) -> Cow<'a, str> {
+    let overrides = pref!(network.user_agent.overrides);
+    if let Some(user_agent) = host.and_then(|host| find_override(&overrides, host)) {
+        return parse_user_agent(user_agent.to_owned());
+    }
+    match pref!(network.user_agent.default) {
+        ref default if default.is_empty() => Cow::Borrowed(user_agent),
+        default => parse_user_agent(default),
+    }
+}
+
+/// Set the user agent string for a domain and its subdomains, or remove the one which was
+/// set for it. The observers of the preferences are notified, so that it reaches the
+/// content processes.
+pub fn set_user_agent_override(domain: &str, user_agent: Option<&str>) {
+    let domain = domain.trim().to_ascii_lowercase();
+    let overrides = pref!(network.user_agent.overrides);
+    let mut entries: Vec<String> = overrides
+        .split('|')
+        .filter(|entry| match parse_entry(entry) {
+            Some((entry_domain, _)) => entry_domain != domain,
+            None => false,
+        })
+        .map(str::to_owned)
+        .collect();
+    if let Some(user_agent) = user_agent {
+        entries.push(format!("{}={}", domain, user_agent.trim()));
+    }
+    if let Err(e) = pref_map().set("network.user_agent.overrides", entries.join("|")) {
+        warn!("Failed to set the user agent overrides ({:?}).", e);
+    }
+}
+
+fn parse_entry(entry: &str) -> Option<(String, &str)> {
+    let mut parts = entry.splitn(2, '=');
+    let domain = parts.next()?.trim().trim_start_matches('.');
+    let user_agent = parts.next()?.trim();
+    if domain.is_empty() || user_agent.is_empty() {
+        return None;
+    }
+    Some((domain.to_ascii_lowercase(), user_agent))
+}
+
+/// The user agent string of the most specific entry whose domain is the host or one of its
+/// parent domains.
+fn find_override<'a>(overrides: &'a str, host: &str) -> Option<&'a str> {
+    let host = host.to_ascii_lowercase();
+    overrides
+        .split('|')
+        .filter_map(parse_entry)
+        .filter(|(domain, _)| {
+            host == *domain ||
+                (host.ends_with(&**domain) && host[..host.len() - domain.len()].ends_with('.'))
+        })
+        .max_by_key(|(domain, _)| domain.len())
+        .map(|(_, user_agent)| user_agent)
+}
@@ -93,9 +93,13 @@ use crate::browsingcontext::NewBrowsingContextInfo;
 use crate::browsingcontext::{
     AllBrowsingContextsIterator, BrowsingContext, FullyActiveBrowsingContextsIterator,
 };
+use crate::discarded::DiscardedDocument;
 use crate::event_loop::EventLoop;
 use crate::network_listener::NetworkListener;
 use crate::pipeline::{InitialPipelineState, Pipeline};
+use crate::prompts::Prompts;
+use crate::protocol_handlers::ProtocolHandlers;
+use crate::session::{save_session, PendingBrowserSession, PendingSessionHistoryEntry};
 use crate::session_history::{
     JointSessionHistory, NeedsToReload, SessionHistoryChange, SessionHistoryDiff,
 };
@@ -108,14 +112,20 @@ use canvas_traits::webgl::WebGLThreads;
 use canvas_traits::ConstellationCanvasMsg;
 use compositing::compositor_thread::CompositorProxy;
 use compositing::compositor_thread::Msg as ToCompositorMsg;
+use compositing::windowing::EventLoopObserver;
 use compositing::{ConstellationMsg as FromCompositorMsg, SendableFrameTree};
 use crossbeam_channel::{after, never, unbounded, Receiver, Sender};
 use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg};
+use devtools_traits::{ConsoleMessage, LogLevel, ScriptToDevtoolsControlMsg, StackFrame};
+use embedder_traits::MemoryReport;
+use embedder_traits::SessionState;
 use embedder_traits::{Cursor, EmbedderMsg, EmbedderProxy, EventLoopWaker};
-use embedder_traits::{MediaSessionEvent, MediaSessionPlaybackState};
-use euclid::{default::Size2D as UntypedSize2D, Size2D};
+use embedder_traits::{MediaSessionEvent, MediaSessionPlaybackState, MemoryPressureReport};
+use embedder_traits::{PromptId, PromptMsg, PromptRequest, PromptResponse, ScreenshotId};
+use euclid::{default::Size2D as UntypedSize2D, Size2D, Vector2D};
 use gfx::font_cache_thread::FontCacheThread;
 use gfx_traits::Epoch;
+use image::{DynamicImage, ImageFormat, RgbImage};
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use ipc_channel::Error as IpcError;
@@ -133,10 +143,14 @@ use msg::constellation_msg::{
     BrowsingContextGroupId, BrowsingContextId, HistoryStateId, PipelineId,
     TopLevelBrowsingContextId,
 };
+use msg::constellation_msg::{HangAlert, MonitoredComponentType};
+use net::storage_thread::StorageThreadFactory;
+use net_traits::image::base::Image;
 use net_traits::pub_domains::reg_host;
 use net_traits::request::RequestBuilder;
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
-use net_traits::{self, FetchResponseMsg, IpcSend, ResourceThreads};
+use net_traits::{self, CrashReport, FetchResponseMsg, IpcSend, ResourceThreads};
+use profile_traits::gecko::GeckoProfile;
 use profile_traits::mem;
 use profile_traits::time;
 use script_traits::CompositorEvent::{MouseButtonEvent, MouseMoveEvent};
@@ -145,7 +159,8 @@ use script_traits::{
     AnimationState, AnimationTickType, AuxiliaryBrowsingContextLoadInfo, BroadcastMsg,
     CompositorEvent,
 };
-use script_traits::{ConstellationControlMsg, DiscardBrowsingContext};
+use script_traits::{ClientFrameType, ClientInfo, DOMMessage};
+use script_traits::{ConstellationControlMsg, DiscardBrowsingContext, EmbedderStylesheets};
 use script_traits::{DocumentActivity, DocumentState, LayoutControlMsg, LoadData, LoadOrigin};
 use script_traits::{HistoryEntryReplacement, IFrameSizeMsg, WindowSizeData, WindowSizeType};
 use script_traits::{
@@ -153,9 +168,10 @@ use script_traits::{
 };
 use script_traits::{LayoutMsg as FromLayoutMsg, ScriptMsg as FromScriptMsg, ScriptThreadFactory};
 use script_traits::{MediaSessionActionType, MouseEventType};
-use script_traits::{MessagePortMsg, PortMessageTask, StructuredSerializedData};
+use script_traits::{MessagePortMsg, PortMessageTask, ScreenshotArea, StructuredSerializedData};
 use script_traits::{SWManagerMsg, ScopeThings, UpdatePipelineIdReason, WebDriverCommandMsg};
 use serde::{Deserialize, Serialize};
+use servo_config::prefs::{self, PrefValue};
 use servo_config::{opts, pref};
 use servo_rand::{random, Rng, ServoRng, SliceRandom};
 use servo_remutex::ReentrantMutex;
@@ -170,6 +186,7 @@ use std::rc::{Rc, Weak};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use style_traits::viewport::ViewportConstraints;
 use style_traits::CSSPixel;
 use webgpu::{WebGPU, WebGPURequest};
@@ -177,6 +194,21 @@ use webvr_traits::{WebVREvent, WebVRMsg};
 
 type PendingApprovalNavigations = HashMap<PipelineId, (LoadData, HistoryEntryReplacement)>;
 
+/// A profile of the sampling profiler, gathered from the background hang monitors of
+/// every process.
+struct SampledProfile {
+    /// The profiles received so far, with the markers of the time profiler.
+    profile: GeckoProfile,
+    /// The number of background hang monitors whose profile is still expected.
+    pending: usize,
+    /// Where to send the profile once gathered, instead of the embedder.
+    sender: Option<IpcSender<Vec<u8>>>,
+    /// The sampling rate and maximum duration, which the background hang monitors of
+    /// the processes started while profiling are enabled with.
+    rate: Duration,
+    max_duration: Duration,
+}
+
 #[derive(Debug)]
 /// The state used by MessagePortInfo to represent the various states the port can be in.
 enum TransferState {
@@ -223,6 +255,18 @@ struct Browser {
 
     /// The joint session history for this browser.
     session_history: JointSessionHistory,
+
+    /// The load data of the document which crashed, which is loaded again when
+    /// the about:failure page replacing it is reloaded.
+    crashed_load_data: Option<LoadData>,
+
+    /// The document which the embedder discarded, which is loaded again when the
+    /// browser is shown or reloaded.
+    discarded: Option<DiscardedDocument>,
+
+    /// The browser whose private browsing session this browser uses, if it is private.
+    /// The auxiliary browsers share the session of their opener.
+    private_session: Option<TopLevelBrowsingContextId>,
 }
 
 /// A browsing context group.
@@ -281,6 +325,10 @@ pub struct Constellation<Message, LTF, STF> {
     /// Channels to control all sampling profilers.
     sampling_profiler_control: Vec<IpcSender<SamplerControlMsg>>,
 
+    /// The profile of the sampling profiler, while it is enabled or until it is
+    /// gathered.
+    sampled_profile: Option<SampledProfile>,
+
     /// A channel for the background hang monitor to send messages
     /// to the constellation.
     background_hang_monitor_sender: Option<IpcSender<HangMonitorAlert>>,
@@ -303,6 +351,45 @@ pub struct Constellation<Message, LTF, STF> {
     /// A channel for the constellation to receive messages from network listener.
     network_listener_receiver: Receiver<(PipelineId, FetchResponseMsg)>,
 
+    /// A channel for the threads watching the content processes to report their exit,
+    /// with the reason of their crash if they didn't exit successfully.
+    content_process_sender: Sender<(PipelineId, Option<String>)>,
+
+    /// A channel for the constellation to receive the exits of the content processes.
+    content_process_receiver: Receiver<(PipelineId, Option<String>)>,
+
+    /// A channel for the constellation to receive the changes of the preferences, which
+    /// are propagated to the content processes.
+    pref_change_receiver: Receiver<(String, PrefValue)>,
+
+    /// A channel for the constellation to receive the prompts of the network and
+    /// devtools threads.
+    prompt_receiver: Receiver<PromptMsg>,
+
+    /// The prompts of the browsers, which are shown by the embedder one at a time.
+    prompts: Prompts,
+
+    /// The preferences overridden for a top-level browsing context, which are sent to
+    /// every event loop, including those created later.
+    pref_overrides: HashMap<TopLevelBrowsingContextId, HashMap<String, PrefValue>>,
+
+    /// The stylesheets supplied by the embedder for a top-level browsing context, which
+    /// are sent to every event loop, including those created later.
+    embedder_stylesheets: HashMap<TopLevelBrowsingContextId, EmbedderStylesheets>,
+
+    /// The top-level browsing contexts which report the size of their content to the
+    /// embedder, which are sent to every event loop, including those created later.
+    auto_resize_browsers: HashSet<TopLevelBrowsingContextId>,
+
+    /// The resource threads of the private browsing sessions, by the browser which
+    /// started them. The state of a session is discarded by the net and storage threads
+    /// once all of its channels are dropped.
+    private_sessions: HashMap<TopLevelBrowsingContextId, ResourceThreads>,
+
+    /// The event loops running in a content process, by the pipeline which started
+    /// the process.
+    content_processes: HashMap<PipelineId, Weak<EventLoop>>,
+
     /// A channel for the constellation to receive messages from the compositor thread.
     compositor_receiver: Receiver<FromCompositorMsg>,
 
@@ -498,6 +585,12 @@ pub struct Constellation<Message, LTF, STF> {
 
     /// Pipeline ID of the active media session.
     active_media_session: Option<PipelineId>,
+
+    /// The handlers registered with `navigator.registerProtocolHandler()`.
+    protocol_handlers: ProtocolHandlers,
+
+    /// The observer of the long tasks, if the embedder instruments the event loops.
+    event_loop_observer: Option<Arc<dyn EventLoopObserver>>,
 }
 
 /// State needed to construct a constellation.
@@ -508,6 +601,10 @@ pub struct InitialConstellationState {
     /// A channel through which messages can be sent to the compositor.
     pub compositor_proxy: CompositorProxy,
 
+    /// A channel for the constellation to receive the prompts of the network and
+    /// devtools threads.
+    pub prompt_receiver: Receiver<PromptMsg>,
+
     /// A channel to the debugger, if applicable.
     pub debugger_chan: Option<debugger::Sender>,
 
@@ -557,6 +654,9 @@ pub struct InitialConstellationState {
 
     /// A flag share with the compositor to indicate that a WR frame is in progress.
     pub pending_wr_frame: Arc<AtomicBool>,
+
+    /// The observer of the long tasks, if the embedder instruments the event loops.
+    pub event_loop_observer: Option<Arc<dyn EventLoopObserver>>,
 }
 
 /// Data needed for webdriver
@@ -900,6 +1000,13 @@ where
 
                 let (network_listener_sender, network_listener_receiver) = unbounded();
 
+                let (content_process_sender, content_process_receiver) = unbounded();
+
+                let (pref_change_sender, pref_change_receiver) = unbounded();
+                prefs::pref_map().add_observer(move |key, value| {
+                    let _ = pref_change_sender.send((key.to_owned(), value.clone()));
+                });
+
                 let swmanager_receiver =
                     route_ipc_receiver_to_new_mpsc_receiver_preserving_errors(swmanager_receiver);
 
@@ -945,12 +1052,23 @@ where
                     background_hang_monitor_receiver,
                     background_monitor_register,
                     sampling_profiler_control: sampler_chan,
+                    sampled_profile: None,
                     layout_sender: ipc_layout_sender,
                     script_receiver: script_receiver,
                     compositor_receiver: compositor_receiver,
                     layout_receiver: layout_receiver,
                     network_listener_sender: network_listener_sender,
                     network_listener_receiver: network_listener_receiver,
+                    content_process_sender,
+                    content_process_receiver,
+                    pref_change_receiver,
+                    prompt_receiver: state.prompt_receiver,
+                    prompts: Prompts::new(),
+                    pref_overrides: HashMap::new(),
+                    embedder_stylesheets: HashMap::new(),
+                    auto_resize_browsers: HashSet::new(),
+                    private_sessions: HashMap::new(),
+                    content_processes: HashMap::new(),
                     embedder_proxy: state.embedder_proxy,
                     compositor_proxy: state.compositor_proxy,
                     active_browser_id: None,
@@ -1016,6 +1134,8 @@ where
                     player_context: state.player_context,
                     event_loop_waker: state.event_loop_waker,
                     active_media_session: None,
+                    protocol_handlers: ProtocolHandlers::new(opts::get().config_dir.clone()),
+                    event_loop_observer: state.event_loop_observer,
                 };
 
                 constellation.run();
@@ -1214,11 +1334,18 @@ where
         };
 
         let resource_threads = if is_private {
-            self.private_resource_threads.clone()
+            self.browsers
+                .get(&top_level_browsing_context_id)
+                .and_then(|browser| browser.private_session)
+                .and_then(|session| self.private_sessions.get(&session))
+                .unwrap_or(&self.private_resource_threads)
+                .clone()
         } else {
             self.public_resource_threads.clone()
         };
 
+        let is_new_event_loop = event_loop.is_none();
+
         let result = Pipeline::spawn::<Message, LTF, STF>(InitialPipelineState {
             id: pipeline_id,
             browsing_context_id,
@@ -1243,6 +1370,7 @@ where
             swmanager_thread: self.swmanager_sender.clone(),
             font_cache_thread: self.font_cache_thread.clone(),
             resource_threads,
+            is_private,
             time_profiler_chan: self.time_profiler_chan.clone(),
             mem_profiler_chan: self.mem_profiler_chan.clone(),
             window_size: WindowSizeData {
@@ -1271,9 +1399,66 @@ where
         };
 
         if let Some(sampler_chan) = pipeline.sampler_control_chan {
+            match self.sampled_profile {
+                Some(ref sampled_profile) if sampled_profile.pending == 0 => {
+                    let msg = SamplerControlMsg::Enable(
+                        sampled_profile.rate,
+                        sampled_profile.max_duration,
+                    );
+                    if let Err(e) = sampler_chan.send(msg) {
+                        warn!("error communicating with sampling profiler: {}", e);
+                    }
+                },
+                _ => {},
+            }
             self.sampling_profiler_control.push(sampler_chan);
         }
 
+        // A new event loop doesn't know the overrides of the preferences and the
+        // stylesheets of the embedder yet.
+        if is_new_event_loop {
+            for (top_level_browsing_context_id, overrides) in &self.pref_overrides {
+                let msg = ConstellationControlMsg::SetPreferenceOverrides(
+                    *top_level_browsing_context_id,
+                    overrides.clone(),
+                );
+                if let Err(e) = pipeline.pipeline.event_loop.send(msg) {
+                    warn!("Failed to send preference overrides to script ({:?}).", e);
+                }
+            }
+            for (top_level_browsing_context_id, stylesheets) in &self.embedder_stylesheets {
+                let msg = ConstellationControlMsg::SetStylesheets(
+                    *top_level_browsing_context_id,
+                    stylesheets.clone(),
+                );
+                if let Err(e) = pipeline.pipeline.event_loop.send(msg) {
+                    warn!("Failed to send stylesheets to script ({:?}).", e);
+                }
+            }
+            for top_level_browsing_context_id in &self.auto_resize_browsers {
+                let msg =
+                    ConstellationControlMsg::SetAutoResize(*top_level_browsing_context_id, true);
+                if let Err(e) = pipeline.pipeline.event_loop.send(msg) {
+                    warn!("Failed to send auto resize to script ({:?}).", e);
+                }
+            }
+        }
+
+        if let Some(content_process) = pipeline.content_process {
+            self.content_processes
+                .insert(pipeline_id, Rc::downgrade(&pipeline.pipeline.event_loop));
+            let content_process_sender = self.content_process_sender.clone();
+            let result = thread::Builder::new()
+                .name("ContentProcessWatcher".to_owned())
+                .spawn(move || {
+                    let crash_reason = content_process.wait();
+                    let _ = content_process_sender.send((pipeline_id, crash_reason));
+                });
+            if let Err(e) = result {
+                warn!("Failed to spawn content process watching thread ({:?}).", e);
+            }
+        }
+
         if let Some(host) = host {
             debug!(
                 "Adding new host entry {} for top-level browsing context {}.",
@@ -1390,10 +1575,15 @@ where
                 "no replacement"
             },
         );
-        self.handle_load_start_msg(
-            change.top_level_browsing_context_id,
-            change.browsing_context_id,
-        );
+        if self
+            .discarded_document(change.top_level_browsing_context_id, change.new_pipeline_id)
+            .is_none()
+        {
+            self.handle_load_start_msg(
+                change.top_level_browsing_context_id,
+                change.browsing_context_id,
+            );
+        }
         self.pending_changes.push(change);
     }
 
@@ -1407,6 +1597,9 @@ where
             Compositor(FromCompositorMsg),
             Layout(FromLayoutMsg),
             NetworkListener((PipelineId, FetchResponseMsg)),
+            ContentProcess((PipelineId, Option<String>)),
+            Preference((String, PrefValue)),
+            Prompt(PromptMsg),
             FromSWManager(SWManagerMsg),
             Timer(TimerSchedulerMsg),
         }
@@ -1450,6 +1643,19 @@ where
                     msg.expect("Unexpected network listener channel panic in constellation")
                 ))
             }
+            recv(self.content_process_receiver) -> msg => {
+                Ok(Request::ContentProcess(
+                    msg.expect("Unexpected content process channel panic in constellation")
+                ))
+            }
+            recv(self.pref_change_receiver) -> msg => {
+                Ok(Request::Preference(
+                    msg.expect("Unexpected preference channel panic in constellation")
+                ))
+            }
+            recv(self.prompt_receiver) -> msg => {
+                Ok(Request::Prompt(msg.expect("Unexpected prompt channel panic in constellation")))
+            }
             recv(self.swmanager_receiver) -> msg => {
                 msg.expect("Unexpected panic channel panic in constellation").map(Request::FromSWManager)
             }
@@ -1485,6 +1691,15 @@ where
             Request::NetworkListener(message) => {
                 self.handle_request_from_network_listener(message);
             },
+            Request::ContentProcess((pipeline_id, crash_reason)) => {
+                self.handle_content_process_exit(pipeline_id, crash_reason);
+            },
+            Request::Preference((key, value)) => {
+                self.handle_preference_change(key, value);
+            },
+            Request::Prompt(message) => {
+                self.handle_prompt_msg(message);
+            },
             Request::FromSWManager(message) => {
                 self.handle_request_from_swmanager(message);
             },
@@ -1499,16 +1714,160 @@ where
         let _ = sender.send(self.next_pipeline_namespace_id());
     }
 
-    fn handle_request_from_background_hang_monitor(&self, message: HangMonitorAlert) {
+    fn handle_request_from_background_hang_monitor(&mut self, message: HangMonitorAlert) {
         match message {
-            HangMonitorAlert::Profile(bytes) => self
-                .embedder_proxy
-                .send((None, EmbedderMsg::ReportProfile(bytes))),
+            HangMonitorAlert::Profile(bytes) => {
+                let sampled_profile = match self.sampled_profile {
+                    Some(ref mut sampled_profile) if sampled_profile.pending > 0 => sampled_profile,
+                    _ => {
+                        return self
+                            .embedder_proxy
+                            .send((None, EmbedderMsg::ReportProfile(bytes)));
+                    },
+                };
+                match serde_json::from_slice(&bytes) {
+                    Ok(profile) => sampled_profile.profile.add_threads(profile),
+                    Err(e) => warn!("Failed to parse a sampled profile: {}", e),
+                }
+                sampled_profile.pending -= 1;
+                self.maybe_report_sampled_profile();
+            },
             HangMonitorAlert::Hang(hang) => {
                 // TODO: In case of a permanent hang being reported, add a "kill script" workflow,
                 // via the embedder?
                 warn!("Component hang alert: {:?}", hang);
+                let pipeline_id = hang.component_id().0;
+                self.report_hang_to_devtools(&hang);
+                let top_level_browsing_context_id = self
+                    .pipelines
+                    .get(&pipeline_id)
+                    .map(|pipeline| pipeline.top_level_browsing_context_id);
+                self.embedder_proxy
+                    .send((top_level_browsing_context_id, EmbedderMsg::HangReport(hang)));
+            },
+        }
+    }
+
+    /// Show the hangs of the script threads as warnings in the console of the devtools,
+    /// with the stack of the script for the permanent ones.
+    fn report_hang_to_devtools(&self, hang: &HangAlert) {
+        let chan = match self.devtools_chan {
+            Some(ref chan) => chan,
+            None => return,
+        };
+        let (component_id, task, js_backtrace, kind) = match hang {
+            HangAlert::Transient(component_id, _, task) => (component_id, task, None, "busy"),
+            HangAlert::Permanent(component_id, _, task, profile) => (
+                component_id,
+                task,
+                profile.as_ref().map(|profile| &profile.js_backtrace),
+                "unresponsive",
+            ),
+        };
+        if component_id.1 != MonitoredComponentType::Script {
+            return;
+        }
+        let message = match task {
+            Some(task) => format!(
+                "The page is {}, running the task {} of the {} task source.",
+                kind, task.task_name, task.task_source
+            ),
+            None => format!("The page is {}.", kind),
+        };
+        let stacktrace = js_backtrace.map(|backtrace| {
+            backtrace
+                .iter()
+                .map(|symbol| StackFrame {
+                    filename: symbol.filename.clone().unwrap_or_default(),
+                    functionName: symbol.name.clone().unwrap_or_default(),
+                    lineNumber: symbol.lineno.unwrap_or(0),
+                    columnNumber: 0,
+                })
+                .collect()
+        });
+        let console_message = ConsoleMessage {
+            message,
+            logLevel: LogLevel::Warn,
+            filename: String::new(),
+            lineNumber: 0,
+            columnNumber: 0,
+            arguments: vec![],
+            stacktrace,
+        };
+        let msg = ScriptToDevtoolsControlMsg::ConsoleAPI(component_id.0, console_message, None);
+        if let Err(e) = chan.send(DevtoolsControlMsg::FromScript(msg)) {
+            warn!("Sending hang report to devtools failed ({:?}).", e);
+        }
+    }
+
+    fn handle_enable_profiler(&mut self, rate: Duration, max_duration: Duration) {
+        if self.sampled_profile.is_some() {
+            return warn!("Sampling profiler enabled while already profiling.");
+        }
+        for chan in &self.sampling_profiler_control {
+            if let Err(e) = chan.send(SamplerControlMsg::Enable(rate, max_duration)) {
+                warn!("error communicating with sampling profiler: {}", e);
+            }
+        }
+        self.time_profiler_chan
+            .send(time::ProfilerMsg::StartRecordingMarkers);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let profile = GeckoProfile::new(now.as_secs_f64() * 1000., rate.as_secs_f64() * 1000.);
+        self.sampled_profile = Some(SampledProfile {
+            profile: profile,
+            pending: 0,
+            sender: None,
+            rate,
+            max_duration,
+        });
+    }
+
+    /// Stop the sampling profilers, and add the markers of the time profiler to the
+    /// profile, which is reported once every background hang monitor sent theirs.
+    fn handle_disable_profiler(&mut self, sender: Option<IpcSender<Vec<u8>>>) {
+        // Dropping the sender lets the requester know that there is no profile.
+        let sampled_profile = match self.sampled_profile {
+            Some(ref mut sampled_profile) if sampled_profile.pending == 0 => sampled_profile,
+            _ => return warn!("Sampling profiler disabled without being enabled."),
+        };
+
+        let mut pending = 0;
+        for chan in &self.sampling_profiler_control {
+            match chan.send(SamplerControlMsg::Disable) {
+                Ok(()) => pending += 1,
+                Err(e) => warn!("error communicating with sampling profiler: {}", e),
+            }
+        }
+        sampled_profile.pending = pending;
+        sampled_profile.sender = sender;
+
+        let (markers_sender, markers_receiver) = ipc::channel().expect("ipc channel failure");
+        self.time_profiler_chan
+            .send(time::ProfilerMsg::StopRecordingMarkers(markers_sender));
+        match markers_receiver.recv() {
+            Ok(markers) => sampled_profile.profile.add_markers(markers),
+            Err(e) => warn!("Failed to get the markers of the time profiler: {:?}", e),
+        }
+        self.maybe_report_sampled_profile();
+    }
+
+    fn maybe_report_sampled_profile(&mut self) {
+        match self.sampled_profile {
+            Some(ref sampled_profile) if sampled_profile.pending == 0 => {},
+            _ => return,
+        }
+        let sampled_profile = self.sampled_profile.take().unwrap();
+        let bytes = serde_json::to_vec(&sampled_profile.profile).unwrap_or_default();
+        match sampled_profile.sender {
+            Some(sender) => {
+                let _ = sender.send(bytes);
             },
+            None => self
+                .embedder_proxy
+                .send((None, EmbedderMsg::ReportProfile(bytes))),
         }
     }
 
@@ -1534,6 +1893,87 @@ where
                 // store service worker manager for communicating with it.
                 self.swmanager_chan = Some(sw_sender);
             },
+            SWManagerMsg::MatchClients(origin, sender) => {
+                let _ = sender.send(self.service_worker_clients(&origin));
+            },
+            SWManagerMsg::ClaimClients(scope_url, sender) => {
+                self.handle_claim_clients(scope_url);
+                let _ = sender.send(());
+            },
+            SWManagerMsg::PostMessageToClient(pipeline_id, scope_url, msg) => {
+                self.handle_post_message_to_client(pipeline_id, scope_url, msg);
+            },
+        }
+    }
+
+    /// The documents of an origin which can be clients of its service workers, which are
+    /// the active documents out of private sessions, where service workers aren't available.
+    /// <https://w3c.github.io/ServiceWorker/#clients-matchall>
+    fn service_worker_clients(&self, origin: &ImmutableOrigin) -> Vec<ClientInfo> {
+        self.browsing_contexts
+            .values()
+            .filter(|browsing_context| !browsing_context.is_private)
+            .filter_map(|browsing_context| {
+                let pipeline = self.pipelines.get(&browsing_context.pipeline_id)?;
+                if pipeline.url.origin() != *origin {
+                    return None;
+                }
+                let frame_type = if browsing_context.parent_pipeline_id.is_some() {
+                    ClientFrameType::Nested
+                } else if pipeline.opener.is_some() {
+                    ClientFrameType::Auxiliary
+                } else {
+                    ClientFrameType::TopLevel
+                };
+                Some(ClientInfo {
+                    pipeline_id: pipeline.id,
+                    id: pipeline.client_id,
+                    url: pipeline.url.clone(),
+                    frame_type,
+                    controller: pipeline.service_worker_controller.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Let the clients of the origin of a scope be claimed by its service worker, which
+    /// each of their script threads does if the url of the document is in the scope.
+    /// <https://w3c.github.io/ServiceWorker/#clients-claim>
+    fn handle_claim_clients(&self, scope_url: ServoUrl) {
+        for client in self.service_worker_clients(&scope_url.origin()) {
+            let pipeline = match self.pipelines.get(&client.pipeline_id) {
+                Some(pipeline) => pipeline,
+                None => continue,
+            };
+            let msg = ConstellationControlMsg::ClaimByServiceWorker(
+                client.pipeline_id,
+                scope_url.clone(),
+            );
+            if let Err(err) = pipeline.event_loop.send(msg) {
+                warn!(
+                    "Failed to claim pipeline {} for a service worker ({:?}).",
+                    client.pipeline_id, err
+                );
+            }
+        }
+    }
+
+    /// Forward a message from the service worker of a scope to the document of a client.
+    /// <https://w3c.github.io/ServiceWorker/#client-postmessage>
+    fn handle_post_message_to_client(
+        &mut self,
+        pipeline_id: PipelineId,
+        scope_url: ServoUrl,
+        msg: DOMMessage,
+    ) {
+        let msg =
+            ConstellationControlMsg::PostMessageFromServiceWorker(pipeline_id, scope_url, msg);
+        let result = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => pipeline.event_loop.send(msg),
+            None => return warn!("Service worker message to closed pipeline {}.", pipeline_id),
+        };
+        if let Err(e) = result {
+            self.handle_send_error(pipeline_id, e);
         }
     }
 
@@ -1653,7 +2093,18 @@ where
             // Create a new top level browsing context. Will use response_chan to return
             // the browsing context id.
             FromCompositorMsg::NewBrowser(url, top_level_browsing_context_id) => {
-                self.handle_new_top_level_browsing_context(url, top_level_browsing_context_id);
+                self.handle_new_top_level_browsing_context(
+                    url,
+                    top_level_browsing_context_id,
+                    false,
+                );
+            },
+            FromCompositorMsg::NewPrivateBrowser(url, top_level_browsing_context_id) => {
+                self.handle_new_top_level_browsing_context(
+                    url,
+                    top_level_browsing_context_id,
+                    true,
+                );
             },
             // Close a top level browsing context.
             FromCompositorMsg::CloseBrowser(top_level_browsing_context_id) => {
@@ -1670,7 +2121,7 @@ where
             },
             // Send frame tree to WebRender. Make it visible.
             FromCompositorMsg::SelectBrowser(top_level_browsing_context_id) => {
-                self.send_frame_tree(top_level_browsing_context_id);
+                self.handle_select_browser(top_level_browsing_context_id);
             },
             // Handle a forward or back request
             FromCompositorMsg::TraverseHistory(top_level_browsing_context_id, direction) => {
@@ -1699,28 +2150,76 @@ where
             },
             FromCompositorMsg::SetCursor(cursor) => self.handle_set_cursor_msg(cursor),
             FromCompositorMsg::EnableProfiler(rate, max_duration) => {
-                for chan in &self.sampling_profiler_control {
-                    if let Err(e) = chan.send(SamplerControlMsg::Enable(rate, max_duration)) {
-                        warn!("error communicating with sampling profiler: {}", e);
-                    }
-                }
-            },
-            FromCompositorMsg::DisableProfiler => {
-                for chan in &self.sampling_profiler_control {
-                    if let Err(e) = chan.send(SamplerControlMsg::Disable) {
-                        warn!("error communicating with sampling profiler: {}", e);
-                    }
-                }
+                self.handle_enable_profiler(rate, max_duration);
             },
+            FromCompositorMsg::DisableProfiler => self.handle_disable_profiler(None),
             FromCompositorMsg::ExitFullScreen(top_level_browsing_context_id) => {
                 self.handle_exit_fullscreen_msg(top_level_browsing_context_id);
             },
+            FromCompositorMsg::ExitPictureInPicture(top_level_browsing_context_id) => {
+                self.handle_exit_picture_in_picture_msg(top_level_browsing_context_id);
+            },
+            FromCompositorMsg::MediaDevicesChanged => {
+                self.handle_media_devices_changed_msg();
+            },
             FromCompositorMsg::MediaSessionAction(action) => {
                 self.handle_media_session_action_msg(action);
             },
             FromCompositorMsg::ChangeBrowserVisibility(top_level_browsing_context_id, visible) => {
                 self.handle_change_browser_visibility(top_level_browsing_context_id, visible);
             },
+            FromCompositorMsg::SaveSession => {
+                self.handle_save_session();
+            },
+            FromCompositorMsg::MemoryPressure => {
+                self.handle_memory_pressure();
+            },
+            FromCompositorMsg::CollectMemoryReports => {
+                self.handle_collect_memory_reports();
+            },
+            FromCompositorMsg::SetPreferenceOverrides(top_level_browsing_context_id, overrides) => {
+                self.handle_set_preference_overrides(top_level_browsing_context_id, overrides);
+            },
+            FromCompositorMsg::SetStylesheets(top_level_browsing_context_id, stylesheets) => {
+                self.handle_set_stylesheets(top_level_browsing_context_id, stylesheets);
+            },
+            FromCompositorMsg::SetAutoResize(top_level_browsing_context_id, enabled) => {
+                self.handle_set_auto_resize(top_level_browsing_context_id, enabled);
+            },
+            FromCompositorMsg::FreezeBrowser(top_level_browsing_context_id, frozen) => {
+                self.handle_freeze_browser(top_level_browsing_context_id, frozen);
+            },
+            FromCompositorMsg::DiscardBrowser(top_level_browsing_context_id) => {
+                self.handle_discard_browser(top_level_browsing_context_id);
+            },
+            // Only the documents of the event loops started from now on are inspectable, since
+            // the event loops and the resource threads are given their devtools channel when
+            // they start.
+            FromCompositorMsg::SetDevtoolsChan(devtools_chan) => {
+                self.devtools_chan = Some(devtools_chan);
+            },
+            FromCompositorMsg::ReportBlockedContent(top_level_browsing_context_id) => {
+                self.handle_report_blocked_content(top_level_browsing_context_id);
+            },
+            // The proxy is shared by the public and private resource threads.
+            FromCompositorMsg::SetProxy(url, bypass) => {
+                let msg = net_traits::CoreResourceMsg::SetProxy(url, bypass);
+                if let Err(e) = self.public_resource_threads.send(msg) {
+                    warn!("Failed to send the proxy to net ({:?}).", e);
+                }
+            },
+            FromCompositorMsg::RespondToPrompt(prompt_id, response) => {
+                self.handle_respond_to_prompt(prompt_id, response);
+            },
+            FromCompositorMsg::RestoreSession(session) => {
+                self.handle_restore_session(session);
+            },
+            FromCompositorMsg::CaptureScreenshot(top_level_browsing_context_id, area, id) => {
+                self.handle_capture_screenshot(top_level_browsing_context_id, area, id);
+            },
+            FromCompositorMsg::SetVisualViewport(pipeline_id, scale, offset) => {
+                self.handle_set_visual_viewport(pipeline_id, scale, offset);
+            },
         }
     }
 
@@ -1800,8 +2299,14 @@ where
                 self.handle_schedule_broadcast(source_pipeline_id, router_id, message);
             },
             FromScriptMsg::ForwardToEmbedder(embedder_msg) => {
-                self.embedder_proxy
-                    .send((Some(source_top_ctx_id), embedder_msg));
+                // The document standing for a discarded one goes unnoticed.
+                if self
+                    .discarded_document(source_top_ctx_id, source_pipeline_id)
+                    .is_none()
+                {
+                    self.embedder_proxy
+                        .send((Some(source_top_ctx_id), embedder_msg));
+                }
             },
             FromScriptMsg::PipelineExited => {
                 self.handle_pipeline_exited(source_pipeline_id);
@@ -1835,6 +2340,20 @@ where
             FromScriptMsg::AbortLoadUrl => {
                 self.handle_abort_load_url_msg(source_pipeline_id);
             },
+            FromScriptMsg::NavigateBrowsingContext(browsing_context_id, load_data, replace) => {
+                self.handle_navigate_browsing_context_msg(
+                    source_pipeline_id,
+                    browsing_context_id,
+                    load_data,
+                    replace,
+                );
+            },
+            FromScriptMsg::CloseAuxiliaryBrowsingContext(browsing_context_id) => {
+                self.handle_close_auxiliary_browsing_context_msg(browsing_context_id);
+            },
+            FromScriptMsg::FocusBrowsingContext(browsing_context_id) => {
+                self.handle_focus_browsing_context_msg(browsing_context_id);
+            },
             // A page loaded has completed all parsing, script, and reflow messages have been sent.
             FromScriptMsg::LoadComplete => {
                 self.handle_load_complete_msg(source_top_ctx_id, source_pipeline_id)
@@ -1919,9 +2438,34 @@ where
             FromScriptMsg::LogEntry(thread_name, entry) => {
                 self.handle_log_entry(Some(source_top_ctx_id), thread_name, entry);
             },
+            FromScriptMsg::ReportLongTask(report) => {
+                if let Some(ref observer) = self.event_loop_observer {
+                    observer.long_task(source_top_ctx_id, report);
+                }
+            },
             FromScriptMsg::TouchEventProcessed(result) => self
                 .compositor_proxy
                 .send(ToCompositorMsg::TouchEventProcessed(result)),
+            FromScriptMsg::ZoomToRect(rect, point) => self
+                .compositor_proxy
+                .send(ToCompositorMsg::ZoomToRect(source_pipeline_id, rect, point)),
+            FromScriptMsg::HighlightBoxModel(highlight) => {
+                self.compositor_proxy
+                    .send(ToCompositorMsg::HighlightBoxModel(
+                        source_pipeline_id,
+                        highlight,
+                    ))
+            },
+            FromScriptMsg::EnableProfiler(rate, max_duration) => {
+                self.handle_enable_profiler(rate, max_duration);
+            },
+            FromScriptMsg::DisableProfiler(sender) => self.handle_disable_profiler(sender),
+            FromScriptMsg::NotifyUserActivation => {
+                self.handle_notify_user_activation_msg(source_pipeline_id);
+            },
+            FromScriptMsg::ConsumeUserActivation => {
+                self.handle_consume_user_activation_msg(source_pipeline_id);
+            },
             FromScriptMsg::GetBrowsingContextInfo(pipeline_id, sender) => {
                 let result = self
                     .pipelines
@@ -1961,9 +2505,23 @@ where
                     );
                 }
             },
+            FromScriptMsg::Prompt(request, sender) => {
+                self.queue_prompt(Some(source_top_ctx_id), request, sender);
+            },
+            FromScriptMsg::RegisterProtocolHandler(scheme, url) => {
+                self.protocol_handlers.register(scheme, url);
+            },
+            FromScriptMsg::UnregisterProtocolHandler(scheme, url) => {
+                self.protocol_handlers.unregister(&scheme, &url);
+            },
             FromScriptMsg::RegisterServiceWorker(scope_things, scope) => {
                 self.handle_register_serviceworker(scope_things, scope);
             },
+            FromScriptMsg::SetServiceWorkerController(scope) => {
+                if let Some(pipeline) = self.pipelines.get_mut(&source_pipeline_id) {
+                    pipeline.service_worker_controller = Some(scope);
+                }
+            },
             FromScriptMsg::ForwardDOMMessage(msg_vec, scope_url) => {
                 if let Some(ref mgr) = self.swmanager_chan {
                     let _ = mgr.send(ServiceWorkerMsg::ForwardDOMMessage(msg_vec, scope_url));
@@ -2639,8 +3197,21 @@ where
         new_value: Option<String>,
     ) {
         let origin = url.origin();
+        let source_browser = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => pipeline.top_level_browsing_context_id,
+            None => return warn!("Storage event from closed pipeline {}.", pipeline_id),
+        };
+        // The storage of a private session is only shared with its own documents, and the
+        // session storage with the documents of the same browser.
+        let private_session = self.private_session(source_browser);
         for pipeline in self.pipelines.values() {
-            if (pipeline.id != pipeline_id) && (pipeline.url.origin() == origin) {
+            let same_storage = match storage {
+                StorageType::Local => {
+                    self.private_session(pipeline.top_level_browsing_context_id) == private_session
+                },
+                StorageType::Session => pipeline.top_level_browsing_context_id == source_browser,
+            };
+            if (pipeline.id != pipeline_id) && (pipeline.url.origin() == origin) && same_storage {
                 let msg = ConstellationControlMsg::DispatchStorageEvent(
                     pipeline.id,
                     storage,
@@ -2838,6 +3409,10 @@ where
     fn handle_pipeline_exited(&mut self, pipeline_id: PipelineId) {
         debug!("Pipeline {:?} exited.", pipeline_id);
         self.pipelines.remove(&pipeline_id);
+        let msg = net_traits::CoreResourceMsg::PipelineExited(pipeline_id);
+        if let Err(e) = self.public_resource_threads.send(msg) {
+            warn!("Failed to send pipeline exit to net ({:?}).", e);
+        }
     }
 
     fn handle_send_error(&mut self, pipeline_id: PipelineId, err: IpcError) {
@@ -2853,6 +3428,48 @@ where
         }
     }
 
+    /// Recover the browsers which had documents in a content process which crashed,
+    /// like when one of their threads panics.
+    fn handle_content_process_exit(
+        &mut self,
+        pipeline_id: PipelineId,
+        crash_reason: Option<String>,
+    ) {
+        let event_loop = match self.content_processes.remove(&pipeline_id) {
+            Some(event_loop) => event_loop,
+            None => {
+                return warn!(
+                    "Unknown content process of pipeline {} exited.",
+                    pipeline_id
+                )
+            },
+        };
+        let (crash_reason, crashed_event_loop) = match (crash_reason, event_loop.upgrade()) {
+            (Some(crash_reason), Some(crashed_event_loop)) => (crash_reason, crashed_event_loop),
+            // The process exited normally, or after all its documents were closed.
+            _ => return,
+        };
+        error!("{} for pipeline {}.", crash_reason, pipeline_id);
+
+        // The next documents of the sites of the crashed event loop start a new one.
+        for bc_group in self.browsing_context_group_set.values_mut() {
+            bc_group
+                .event_loops
+                .retain(|_, other_event_loop| !other_event_loop.ptr_eq(&event_loop));
+        }
+        let mut top_level_browsing_context_ids = vec![];
+        for pipeline in self.pipelines.values() {
+            if Rc::ptr_eq(&pipeline.event_loop, &crashed_event_loop) &&
+                !top_level_browsing_context_ids.contains(&pipeline.top_level_browsing_context_id)
+            {
+                top_level_browsing_context_ids.push(pipeline.top_level_browsing_context_id);
+            }
+        }
+        for top_level_browsing_context_id in top_level_browsing_context_ids {
+            self.handle_panic(top_level_browsing_context_id, crash_reason.clone(), None);
+        }
+    }
+
     fn handle_panic(
         &mut self,
         top_level_browsing_context_id: TopLevelBrowsingContextId,
@@ -2875,7 +3492,7 @@ where
 
         self.embedder_proxy.send((
             Some(top_level_browsing_context_id),
-            EmbedderMsg::Panic(reason, backtrace),
+            EmbedderMsg::Panic(reason.clone(), backtrace.clone()),
         ));
 
         let browsing_context = match self.browsing_contexts.get(&browsing_context_id) {
@@ -2884,6 +3501,7 @@ where
         };
         let window_size = browsing_context.size;
         let pipeline_id = browsing_context.pipeline_id;
+        let is_private = browsing_context.is_private;
         let is_visible = browsing_context.is_visible;
 
         let pipeline = match self.pipelines.get(&pipeline_id) {
@@ -2892,6 +3510,23 @@ where
         };
         let pipeline_url = pipeline.url.clone();
         let opener = pipeline.opener;
+        let mut crashed_load_data = pipeline.load_data.clone();
+        crashed_load_data.url = pipeline_url.clone();
+
+        let crash_report = CrashReport {
+            url: pipeline_url.clone(),
+            reason: reason,
+            backtrace: backtrace,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+        };
+        if let Err(e) = self
+            .public_resource_threads
+            .send(net_traits::CoreResourceMsg::AddCrashReport(crash_report))
+        {
+            warn!("Sending crash report to resource thread failed ({})", e);
+        }
 
         self.close_browsing_context_children(
             browsing_context_id,
@@ -2907,10 +3542,19 @@ where
 
         warn!("creating replacement pipeline for about:failure");
 
+        // The about:failure page only replaces the entry of the document which crashed,
+        // its other entries reload it.
+        self.get_joint_session_history(top_level_browsing_context_id)
+            .discard_same_document_entries(pipeline_id, crashed_load_data.clone());
+
+        // Reloading the about:failure page reloads the document which crashed.
+        if let Some(browser) = self.browsers.get_mut(&top_level_browsing_context_id) {
+            browser.crashed_load_data = Some(crashed_load_data);
+        }
+
         let new_pipeline_id = PipelineId::new();
         let load_data = LoadData::new(LoadOrigin::Constellation, failure_url, None, None, None);
         let sandbox = IFrameSandboxState::IFrameSandboxed;
-        let is_private = false;
         self.new_pipeline(
             new_pipeline_id,
             browsing_context_id,
@@ -2923,11 +3567,13 @@ where
             is_private,
             is_visible,
         );
+        // The session history entry of the document which crashed is replaced, so
+        // that its pipeline is closed.
         self.add_pending_change(SessionHistoryChange {
             top_level_browsing_context_id: top_level_browsing_context_id,
             browsing_context_id: browsing_context_id,
             new_pipeline_id: new_pipeline_id,
-            replace: None,
+            replace: Some(NeedsToReload::No(pipeline_id)),
             new_browsing_context_info: None,
             window_size,
         });
@@ -3023,7 +3669,19 @@ where
         &mut self,
         url: ServoUrl,
         top_level_browsing_context_id: TopLevelBrowsingContextId,
+        is_private: bool,
     ) {
+        let load_data = LoadData::new(LoadOrigin::Constellation, url, None, None, None);
+        self.new_top_level_browsing_context(load_data, top_level_browsing_context_id, is_private);
+    }
+
+    /// Create a new top-level browsing context, and return the id of its initial pipeline.
+    fn new_top_level_browsing_context(
+        &mut self,
+        load_data: LoadData,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        is_private: bool,
+    ) -> PipelineId {
         let window_size = self.window_size.initial_viewport;
         let pipeline_id = PipelineId::new();
         let msg = (
@@ -3032,11 +3690,16 @@ where
         );
         self.embedder_proxy.send(msg);
         let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
-        let load_data = LoadData::new(LoadOrigin::Constellation, url, None, None, None);
         let sandbox = IFrameSandboxState::IFrameUnsandboxed;
-        let is_private = false;
         let is_visible = true;
 
+        let private_session = if is_private {
+            self.new_private_session(top_level_browsing_context_id);
+            Some(top_level_browsing_context_id)
+        } else {
+            None
+        };
+
         // Register this new top-level browsing context id as a browser and set
         // its focused browsing context to be itself.
         self.browsers.insert(
@@ -3044,6 +3707,9 @@ where
             Browser {
                 focused_browsing_context_id: browsing_context_id,
                 session_history: JointSessionHistory::new(),
+                crashed_load_data: None,
+                discarded: None,
+                private_session,
             },
         );
 
@@ -3080,20 +3746,102 @@ where
             }),
             window_size,
         });
+        pipeline_id
     }
 
-    fn handle_close_top_level_browsing_context(
-        &mut self,
-        top_level_browsing_context_id: TopLevelBrowsingContextId,
-    ) {
-        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
-        self.close_browsing_context(browsing_context_id, ExitPipelineMode::Normal);
-        self.browsers.remove(&top_level_browsing_context_id);
-        if self.active_browser_id == Some(top_level_browsing_context_id) {
-            self.active_browser_id = None;
+    /// Start a private browsing session for the given browser, with its own cookies,
+    /// cache and storage, which are only kept in memory.
+    fn new_private_session(&mut self, top_level_browsing_context_id: TopLevelBrowsingContextId) {
+        let (core_sender, core_receiver) = ipc::channel().expect("Failed to create IPC channel!");
+        let msg = net_traits::CoreResourceMsg::NewPrivateSession(core_receiver);
+        if let Err(e) = self.public_resource_threads.send(msg) {
+            warn!("Failed to start private session in net ({:?}).", e);
         }
-        let browsing_context = match self.browsing_contexts.get(&browsing_context_id) {
-            Some(bc) => bc,
+        let storage_sender: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(None);
+        self.private_sessions.insert(
+            top_level_browsing_context_id,
+            ResourceThreads::new(core_sender, storage_sender),
+        );
+    }
+
+    /// The resource threads of a browser, which are those of its private session if it is
+    /// private.
+    fn browser_resource_threads(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> &ResourceThreads {
+        self.private_session(top_level_browsing_context_id)
+            .and_then(|session| self.private_sessions.get(&session))
+            .unwrap_or(&self.public_resource_threads)
+    }
+
+    /// The browser whose private session the given browser uses, if it is private.
+    fn private_session(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> Option<TopLevelBrowsingContextId> {
+        self.browsers
+            .get(&top_level_browsing_context_id)
+            .and_then(|browser| browser.private_session)
+    }
+
+    fn handle_close_top_level_browsing_context(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) {
+        // Unblock the documents waiting for an answer before closing them.
+        if let Some(prompt_id) = self.prompts.close(top_level_browsing_context_id) {
+            self.embedder_proxy.send((
+                Some(top_level_browsing_context_id),
+                EmbedderMsg::HidePrompt(prompt_id),
+            ));
+        }
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        self.close_browsing_context(browsing_context_id, ExitPipelineMode::Normal);
+        let msg = StorageThreadMsg::DiscardSessionStorage(top_level_browsing_context_id);
+        if let Err(e) = self
+            .browser_resource_threads(top_level_browsing_context_id)
+            .send(msg)
+        {
+            warn!("Failed to discard session storage ({:?}).", e);
+        }
+        let private_session = self
+            .browsers
+            .remove(&top_level_browsing_context_id)
+            .and_then(|browser| browser.private_session);
+        if let Some(session) = private_session {
+            // The session is discarded with the last of the browsers using it.
+            if !self
+                .browsers
+                .values()
+                .any(|browser| browser.private_session == Some(session))
+            {
+                self.private_sessions.remove(&session);
+            }
+        }
+        if self
+            .pref_overrides
+            .contains_key(&top_level_browsing_context_id)
+        {
+            self.handle_set_preference_overrides(top_level_browsing_context_id, HashMap::new());
+        }
+        if self
+            .embedder_stylesheets
+            .contains_key(&top_level_browsing_context_id)
+        {
+            self.handle_set_stylesheets(top_level_browsing_context_id, Default::default());
+        }
+        if self
+            .auto_resize_browsers
+            .contains(&top_level_browsing_context_id)
+        {
+            self.handle_set_auto_resize(top_level_browsing_context_id, false);
+        }
+        if self.active_browser_id == Some(top_level_browsing_context_id) {
+            self.active_browser_id = None;
+        }
+        let browsing_context = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(bc) => bc,
             None => {
                 warn!("BC has closed before it has started");
                 return;
@@ -3351,9 +4099,13 @@ where
             new_pipeline_id,
         } = load_info;
 
-        let (script_sender, opener_browsing_context_id) =
+        let (script_sender, opener_browsing_context_id, opener_top_level_browsing_context_id) =
             match self.pipelines.get(&opener_pipeline_id) {
-                Some(pipeline) => (pipeline.event_loop.clone(), pipeline.browsing_context_id),
+                Some(pipeline) => (
+                    pipeline.event_loop.clone(),
+                    pipeline.browsing_context_id,
+                    pipeline.top_level_browsing_context_id,
+                ),
                 None => {
                     return warn!(
                         "Auxiliary loaded url in closed iframe {}.",
@@ -3385,11 +4137,15 @@ where
 
         assert!(!self.pipelines.contains_key(&new_pipeline_id));
         self.pipelines.insert(new_pipeline_id, pipeline);
+        let private_session = self.private_session(opener_top_level_browsing_context_id);
         self.browsers.insert(
             new_top_level_browsing_context_id,
             Browser {
                 focused_browsing_context_id: new_browsing_context_id,
                 session_history: JointSessionHistory::new(),
+                crashed_load_data: None,
+                discarded: None,
+                private_session,
             },
         );
 
@@ -3509,13 +4265,158 @@ where
         self.embedder_proxy.send(msg);
     }
 
+    /// Navigate a browsing context from a `Location` object of another origin, whose
+    /// script thread doesn't know the pipeline of the browsing context. The navigation
+    /// is started by the script thread of the browsing context, which scrolls to the
+    /// fragment of the url instead if only the fragment changes.
+    fn handle_navigate_browsing_context_msg(
+        &mut self,
+        source_pipeline_id: PipelineId,
+        browsing_context_id: BrowsingContextId,
+        load_data: LoadData,
+        replace: HistoryEntryReplacement,
+    ) {
+        let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(ctx) => ctx.pipeline_id,
+            None => {
+                return warn!(
+                    "Browsing context {} navigated after closure.",
+                    browsing_context_id
+                );
+            },
+        };
+        if !self.is_allowed_to_navigate(source_pipeline_id, browsing_context_id) {
+            return warn!(
+                "Pipeline {} isn't allowed to navigate browsing context {}.",
+                source_pipeline_id, browsing_context_id
+            );
+        }
+        // The `javascript:` urls of other origins don't run in the document.
+        if load_data.url.scheme() == "javascript" {
+            return warn!(
+                "Pipeline {} navigated browsing context {} of another origin to a javascript url.",
+                source_pipeline_id, browsing_context_id
+            );
+        }
+        let msg = ConstellationControlMsg::Navigate(pipeline_id, load_data, replace);
+        let result = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => pipeline.event_loop.send(msg),
+            None => return warn!("Pipeline {} navigated after closure.", pipeline_id),
+        };
+        if let Err(e) = result {
+            self.handle_send_error(pipeline_id, e);
+        }
+    }
+
+    /// Whether the document of a pipeline is allowed to navigate a browsing context,
+    /// according to the sandboxing flags of the document, which are those of the
+    /// iframes it is nested in.
+    /// <https://html.spec.whatwg.org/multipage/#allowed-to-navigate>
+    fn is_allowed_to_navigate(
+        &self,
+        source_pipeline_id: PipelineId,
+        browsing_context_id: BrowsingContextId,
+    ) -> bool {
+        let (source_browsing_context_id, mut current_pipeline_id) =
+            match self.pipelines.get(&source_pipeline_id) {
+                Some(pipeline) => (pipeline.browsing_context_id, Some(source_pipeline_id)),
+                None => return false,
+            };
+        let mut sandboxed_navigation = false;
+        let mut sandboxed_top_level_navigation = false;
+        let mut is_ancestor = false;
+        while let Some(pipeline) = current_pipeline_id.and_then(|id| self.pipelines.get(&id)) {
+            sandboxed_navigation |= pipeline.load_data.sandboxed_navigation;
+            sandboxed_top_level_navigation |= pipeline.load_data.sandboxed_top_level_navigation;
+            if pipeline.browsing_context_id == browsing_context_id &&
+                pipeline.browsing_context_id != source_browsing_context_id
+            {
+                is_ancestor = true;
+            }
+            current_pipeline_id = self
+                .browsing_contexts
+                .get(&pipeline.browsing_context_id)
+                .and_then(|ctx| ctx.parent_pipeline_id);
+        }
+        if browsing_context_id == source_browsing_context_id || !sandboxed_navigation {
+            return true;
+        }
+        let (is_top_level, opener) = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(ctx) => (
+                ctx.parent_pipeline_id.is_none(),
+                self.pipelines
+                    .get(&ctx.pipeline_id)
+                    .and_then(|pipeline| pipeline.opener),
+            ),
+            None => return false,
+        };
+        match (is_top_level, is_ancestor) {
+            // Step 1, the descendants of the sandboxed document can be navigated.
+            (false, _) => self
+                .fully_active_descendant_browsing_contexts_iter(source_browsing_context_id)
+                .any(|ctx| ctx.id == browsing_context_id),
+            // Step 2.
+            (true, true) => !sandboxed_top_level_navigation,
+            // Step 3, the popups opened by the sandboxed document can be navigated.
+            (true, false) => opener == Some(source_browsing_context_id),
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-window-close>
+    fn handle_close_auxiliary_browsing_context_msg(
+        &mut self,
+        browsing_context_id: BrowsingContextId,
+    ) {
+        let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(ctx) if ctx.parent_pipeline_id.is_none() => ctx.pipeline_id,
+            Some(_) => return warn!("Closing nested browsing context {}.", browsing_context_id),
+            None => return warn!("Browsing context {} closed twice.", browsing_context_id),
+        };
+        // Only the browsing contexts opened by script are script-closable,
+        // when they are of another origin.
+        let (is_auxiliary, top_level_browsing_context_id) = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => (
+                pipeline.opener.is_some(),
+                pipeline.top_level_browsing_context_id,
+            ),
+            None => return warn!("Pipeline {} closed after closure.", pipeline_id),
+        };
+        if !is_auxiliary {
+            return warn!(
+                "Browsing context {} isn't script-closable.",
+                browsing_context_id
+            );
+        }
+        self.handle_close_top_level_browsing_context(top_level_browsing_context_id);
+    }
+
+    /// Focus a browsing context from a `Window` object of another origin, which focuses
+    /// the iframes it is nested in.
+    /// <https://html.spec.whatwg.org/multipage/#dom-window-focus>
+    fn handle_focus_browsing_context_msg(&mut self, browsing_context_id: BrowsingContextId) {
+        let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(ctx) => ctx.pipeline_id,
+            None => {
+                return warn!(
+                    "Browsing context {} focused after closure.",
+                    browsing_context_id
+                );
+            },
+        };
+        self.handle_focus_msg(pipeline_id);
+    }
+
     fn load_url(
         &mut self,
         top_level_browsing_context_id: TopLevelBrowsingContextId,
         source_id: PipelineId,
-        load_data: LoadData,
+        mut load_data: LoadData,
         replace: HistoryEntryReplacement,
     ) -> Option<PipelineId> {
+        // https://html.spec.whatwg.org/multipage/#custom-handlers
+        if let Some(url) = self.protocol_handlers.resolve(&load_data.url) {
+            load_data.url = url;
+        }
         let replace_debug = match replace {
             HistoryEntryReplacement::Enabled => "",
             HistoryEntryReplacement::Disabled => "not",
@@ -3699,6 +4600,12 @@ where
             .get(&BrowsingContextId::from(top_level_browsing_context_id))
             .map(|ctx| ctx.pipeline_id == pipeline_id)
             .unwrap_or(false);
+        let is_discarded_placeholder = self
+            .discarded_document(top_level_browsing_context_id, pipeline_id)
+            .is_some();
+        if pipeline_is_top_level_pipeline && is_discarded_placeholder {
+            return;
+        }
         if pipeline_is_top_level_pipeline {
             // Is there any pending pipeline that will replace the current top level pipeline
             let current_top_level_pipeline_will_be_replaced = self
@@ -3759,6 +4666,14 @@ where
         top_level_browsing_context_id: TopLevelBrowsingContextId,
         direction: TraversalDirection,
     ) {
+        // A discarded browser is loaded again instead, as its session history only
+        // becomes meaningful again then.
+        if self.restore_discarded_browser(top_level_browsing_context_id) {
+            return warn!(
+                "Not traversing the history of the discarded browser {}.",
+                top_level_browsing_context_id
+            );
+        }
         let mut browsing_context_changes = HashMap::<BrowsingContextId, NeedsToReload>::new();
         let mut pipeline_changes = HashMap::<PipelineId, (Option<HistoryStateId>, ServoUrl)>::new();
         let mut url_to_load = HashMap::<PipelineId, ServoUrl>::new();
@@ -4163,6 +5078,28 @@ where
                 );
             },
         };
+        if self.restore_discarded_browser(top_level_browsing_context_id) {
+            return;
+        }
+        let is_failure = self
+            .pipelines
+            .get(&pipeline_id)
+            .map_or(false, |pipeline| pipeline.url.as_str() == "about:failure");
+        if is_failure {
+            let crashed_load_data = self
+                .browsers
+                .get_mut(&top_level_browsing_context_id)
+                .and_then(|browser| browser.crashed_load_data.take());
+            if let Some(load_data) = crashed_load_data {
+                self.load_url(
+                    top_level_browsing_context_id,
+                    pipeline_id,
+                    load_data,
+                    HistoryEntryReplacement::Enabled,
+                );
+                return;
+            }
+        }
         let msg = ConstellationControlMsg::Reload(pipeline_id);
         let result = match self.pipelines.get(&pipeline_id) {
             None => return warn!("Pipeline {} got reload event after closure.", pipeline_id),
@@ -4507,9 +5444,32 @@ where
                 self.compositor_proxy
                     .send(ToCompositorMsg::WebDriverMouseMoveEvent(x, y));
             },
-            WebDriverCommandMsg::TakeScreenshot(_, rect, reply) => {
+            WebDriverCommandMsg::WheelScrollAction(x, y, delta_x, delta_y) => {
                 self.compositor_proxy
-                    .send(ToCompositorMsg::CreatePng(rect, reply));
+                    .send(ToCompositorMsg::WebDriverWheelScrollEvent(
+                        x, y, delta_x, delta_y,
+                    ));
+            },
+            WebDriverCommandMsg::TakeScreenshot(top_level_browsing_context_id, area, reply) => {
+                self.take_screenshot(top_level_browsing_context_id, area, reply);
+            },
+            WebDriverCommandMsg::GetPrompt(top_level_browsing_context_id, reply) => {
+                let prompt = self
+                    .prompts
+                    .current(Some(top_level_browsing_context_id))
+                    .map(|(_, request)| request.clone());
+                let _ = reply.send(prompt);
+            },
+            WebDriverCommandMsg::RespondToPrompt(top_level_browsing_context_id, response) => {
+                let prompt_id = match self.prompts.current(Some(top_level_browsing_context_id)) {
+                    Some((prompt_id, _)) => prompt_id,
+                    None => return warn!("WebDriver responded to a prompt which is not shown."),
+                };
+                self.embedder_proxy.send((
+                    Some(top_level_browsing_context_id),
+                    EmbedderMsg::HidePrompt(prompt_id),
+                ));
+                self.handle_respond_to_prompt(prompt_id, response);
             },
         }
     }
@@ -4519,6 +5479,9 @@ where
         top_level_browsing_context_id: TopLevelBrowsingContextId,
         visible: bool,
     ) {
+        if visible {
+            self.restore_discarded_browser(top_level_browsing_context_id);
+        }
         let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
         let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
             Some(browsing_context) => browsing_context.pipeline_id,
@@ -4540,20 +5503,55 @@ where
         };
     }
 
+    /// Make the given browser the one displayed by the compositor. The previously
+    /// displayed browser is moved offscreen, and stops ticking its animations.
+    fn handle_select_browser(&mut self, top_level_browsing_context_id: TopLevelBrowsingContextId) {
+        if let Some(previous_browser_id) = self.active_browser_id {
+            if previous_browser_id != top_level_browsing_context_id {
+                self.handle_change_browser_visibility(previous_browser_id, false);
+            }
+        }
+        self.handle_change_browser_visibility(top_level_browsing_context_id, true);
+        self.send_frame_tree(top_level_browsing_context_id);
+    }
+
     fn notify_history_changed(&self, top_level_browsing_context_id: TopLevelBrowsingContextId) {
         // Send a flat projection of the history to embedder.
-        // The final vector is a concatenation of the LoadData of the past
-        // entries, the current entry and the future entries.
-        // LoadData of inner frames are ignored and replaced with the LoadData
-        // of the parent.
+        let (entries, current_index) =
+            match self.session_history_entries(top_level_browsing_context_id) {
+                Some(entries) => entries,
+                None => return,
+            };
+        let urls = entries
+            .into_iter()
+            .map(|(_, load_data)| load_data.url)
+            .collect();
+        let msg = (
+            Some(top_level_browsing_context_id),
+            EmbedderMsg::HistoryChanged(urls, current_index),
+        );
+        self.embedder_proxy.send(msg);
+    }
 
+    /// Returns a flat projection of the joint session history of a top-level browsing context,
+    /// and the index of the current entry in it.
+    /// The final vector is a concatenation of the LoadData of the past
+    /// entries, the current entry and the future entries, along with the id of the
+    /// pipeline of each entry if that pipeline is still alive.
+    /// LoadData of inner frames are ignored and replaced with the LoadData
+    /// of the parent.
+    fn session_history_entries(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> Option<(Vec<(Option<PipelineId>, LoadData)>, usize)> {
         let session_history = match self.browsers.get(&top_level_browsing_context_id) {
             Some(browser) => &browser.session_history,
             None => {
-                return warn!(
+                warn!(
                     "Session history does not exist for {}",
                     top_level_browsing_context_id
                 );
+                return None;
             },
         };
 
@@ -4561,144 +5559,846 @@ where
         let browsing_context = match self.browsing_contexts.get(&browsing_context_id) {
             Some(browsing_context) => browsing_context,
             None => {
-                return warn!(
-                    "notify_history_changed error after top-level browsing context closed."
-                );
+                warn!("Session history requested after top-level browsing context closed.");
+                return None;
             },
         };
 
-        let current_load_data = match self.pipelines.get(&browsing_context.pipeline_id) {
-            Some(pipeline) => pipeline.load_data.clone(),
+        let current_entry = match self.pipelines.get(&browsing_context.pipeline_id) {
+            // A discarded document stays in the session history.
+            Some(pipeline) => {
+                match self.discarded_document(top_level_browsing_context_id, pipeline.id) {
+                    Some(discarded) => (None, discarded.load_data().clone()),
+                    None => (Some(pipeline.id), pipeline.load_data.clone()),
+                }
+            },
             None => {
-                return warn!(
+                warn!(
                     "Pipeline {} refresh after closure.",
                     browsing_context.pipeline_id
                 );
+                return None;
             },
         };
 
+        let resolve_entry = |previous_entry: &mut (Option<PipelineId>, LoadData),
+                             reloader: &NeedsToReload| {
+            let entry = match *reloader {
+                NeedsToReload::No(pipeline_id) => match self.pipelines.get(&pipeline_id) {
+                    Some(pipeline) => (Some(pipeline_id), pipeline.load_data.clone()),
+                    None => previous_entry.clone(),
+                },
+                NeedsToReload::Yes(_, ref load_data) => (None, load_data.clone()),
+            };
+            *previous_entry = entry.clone();
+            Some(entry)
+        };
+
         // If LoadData was ignored, use the LoadData of the previous SessionHistoryEntry, which
         // is the LoadData of the parent browsing context.
-        let resolve_load_data_future =
-            |previous_load_data: &mut LoadData, diff: &SessionHistoryDiff| match *diff {
+        let resolve_entry_future = |previous_entry: &mut (Option<PipelineId>, LoadData),
+                                    diff: &SessionHistoryDiff| {
+            match *diff {
                 SessionHistoryDiff::BrowsingContextDiff {
                     browsing_context_id,
                     ref new_reloader,
                     ..
-                } => {
-                    if browsing_context_id == top_level_browsing_context_id {
-                        let load_data = match *new_reloader {
-                            NeedsToReload::No(pipeline_id) => {
-                                match self.pipelines.get(&pipeline_id) {
-                                    Some(pipeline) => pipeline.load_data.clone(),
-                                    None => previous_load_data.clone(),
-                                }
-                            },
-                            NeedsToReload::Yes(_, ref load_data) => load_data.clone(),
-                        };
-                        *previous_load_data = load_data.clone();
-                        Some(load_data)
-                    } else {
-                        Some(previous_load_data.clone())
-                    }
+                } if browsing_context_id == top_level_browsing_context_id => {
+                    resolve_entry(previous_entry, new_reloader)
                 },
-                _ => Some(previous_load_data.clone()),
-            };
+                _ => Some(previous_entry.clone()),
+            }
+        };
 
-        let resolve_load_data_past =
-            |previous_load_data: &mut LoadData, diff: &SessionHistoryDiff| match *diff {
+        let resolve_entry_past = |previous_entry: &mut (Option<PipelineId>, LoadData),
+                                  diff: &SessionHistoryDiff| {
+            match *diff {
                 SessionHistoryDiff::BrowsingContextDiff {
                     browsing_context_id,
                     ref old_reloader,
                     ..
-                } => {
-                    if browsing_context_id == top_level_browsing_context_id {
-                        let load_data = match *old_reloader {
-                            NeedsToReload::No(pipeline_id) => {
-                                match self.pipelines.get(&pipeline_id) {
-                                    Some(pipeline) => pipeline.load_data.clone(),
-                                    None => previous_load_data.clone(),
-                                }
-                            },
-                            NeedsToReload::Yes(_, ref load_data) => load_data.clone(),
-                        };
-                        *previous_load_data = load_data.clone();
-                        Some(load_data)
-                    } else {
-                        Some(previous_load_data.clone())
-                    }
+                } if browsing_context_id == top_level_browsing_context_id => {
+                    resolve_entry(previous_entry, old_reloader)
                 },
-                _ => Some(previous_load_data.clone()),
-            };
+                _ => Some(previous_entry.clone()),
+            }
+        };
 
-        let mut entries: Vec<LoadData> = session_history
+        let mut entries: Vec<(Option<PipelineId>, LoadData)> = session_history
             .past
             .iter()
             .rev()
-            .scan(current_load_data.clone(), &resolve_load_data_past)
+            .scan(current_entry.clone(), &resolve_entry_past)
             .collect();
 
         entries.reverse();
 
         let current_index = entries.len();
 
-        entries.push(current_load_data.clone());
+        entries.push(current_entry.clone());
 
         entries.extend(
             session_history
                 .future
                 .iter()
                 .rev()
-                .scan(current_load_data, &resolve_load_data_future),
-        );
-        let urls = entries.iter().map(|entry| entry.url.clone()).collect();
-        let msg = (
-            Some(top_level_browsing_context_id),
-            EmbedderMsg::HistoryChanged(urls, current_index),
+                .scan(current_entry, &resolve_entry_future),
         );
-        self.embedder_proxy.send(msg);
+        Some((entries, current_index))
     }
 
-    fn load_url_for_webdriver(
+    /// Collect the session history of every browser, along with the state of their
+    /// documents, and send it to the embedder once every document has replied.
+    fn handle_save_session(&self) {
+        let mut browsers = vec![];
+        for (top_level_browsing_context_id, browser) in &self.browsers {
+            // Nothing is kept of the private browsers once they are closed.
+            if browser.private_session.is_some() {
+                continue;
+            }
+            let (entries, current_index) =
+                match self.session_history_entries(*top_level_browsing_context_id) {
+                    Some(entries) => entries,
+                    None => continue,
+                };
+            let entries: Vec<_> = entries
+                .into_iter()
+                .map(|(pipeline_id, load_data)| {
+                    let pipeline = pipeline_id.and_then(|id| self.pipelines.get(&id));
+                    let document_state_receiver = match pipeline {
+                        Some(pipeline) => {
+                            let (sender, receiver) =
+                                ipc::channel().expect("Failed to create IPC channel!");
+                            let msg =
+                                ConstellationControlMsg::GetSavedDocumentState(pipeline.id, sender);
+                            match pipeline.event_loop.send(msg) {
+                                Ok(()) => Some(receiver),
+                                Err(e) => {
+                                    warn!("Failed to request document state ({:?}).", e);
+                                    None
+                                },
+                            }
+                        },
+                        None => None,
+                    };
+                    PendingSessionHistoryEntry {
+                        url: load_data.url,
+                        document_state: load_data.document_state,
+                        document_state_receiver,
+                    }
+                })
+                .collect();
+            let (storage_sender, storage_receiver) =
+                ipc::channel().expect("Failed to create IPC channel!");
+            let msg =
+                StorageThreadMsg::GetSessionStorage(storage_sender, *top_level_browsing_context_id);
+            let session_storage_receiver = match self.public_resource_threads.send(msg) {
+                Ok(()) => Some(storage_receiver),
+                Err(e) => {
+                    warn!("Failed to request session storage ({:?}).", e);
+                    None
+                },
+            };
+            browsers.push(PendingBrowserSession {
+                entries,
+                current_index,
+                session_storage_receiver,
+            });
+        }
+
+        // The replies are received on the router thread, so that a busy or hung script
+        // thread doesn't block the constellation.
+        let embedder_proxy = self.embedder_proxy.clone();
+        save_session(browsers, move |session| {
+            embedder_proxy.send((None, EmbedderMsg::SessionSaved(session)));
+        });
+    }
+
+    /// The event loops of the pipelines, without duplicates.
+    fn event_loops(&self) -> Vec<Rc<EventLoop>> {
+        let mut event_loops: Vec<Rc<EventLoop>> = vec![];
+        for pipeline in self.pipelines.values() {
+            if !event_loops
+                .iter()
+                .any(|event_loop| Rc::ptr_eq(event_loop, &pipeline.event_loop))
+            {
+                event_loops.push(pipeline.event_loop.clone());
+            }
+        }
+        event_loops
+    }
+
+    /// Propagate a change of a preference in the main process to the event loops, which
+    /// may run in content processes with their own preferences.
+    fn handle_preference_change(&mut self, key: String, value: PrefValue) {
+        for event_loop in self.event_loops() {
+            let msg = ConstellationControlMsg::SetPreference(key.clone(), value.clone());
+            if let Err(e) = event_loop.send(msg) {
+                warn!("Failed to send preference change to script ({:?}).", e);
+            }
+        }
+    }
+
+    fn handle_set_preference_overrides(
         &mut self,
         top_level_browsing_context_id: TopLevelBrowsingContextId,
-        load_data: LoadData,
-        reply: IpcSender<webdriver_msg::LoadStatus>,
-        replace: HistoryEntryReplacement,
+        overrides: HashMap<String, PrefValue>,
     ) {
-        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
-        let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
-            Some(browsing_context) => browsing_context.pipeline_id,
-            None => {
-                return warn!(
-                    "Webdriver load for closed browsing context {}.",
-                    browsing_context_id
-                );
-            },
-        };
-        if let Some(new_pipeline_id) = self.load_url(
-            top_level_browsing_context_id,
-            pipeline_id,
-            load_data,
-            replace,
-        ) {
-            self.webdriver.load_channel = Some((new_pipeline_id, reply));
+        if overrides.is_empty() {
+            self.pref_overrides.remove(&top_level_browsing_context_id);
+        } else {
+            self.pref_overrides
+                .insert(top_level_browsing_context_id, overrides.clone());
+        }
+        for event_loop in self.event_loops() {
+            let msg = ConstellationControlMsg::SetPreferenceOverrides(
+                top_level_browsing_context_id,
+                overrides.clone(),
+            );
+            if let Err(e) = event_loop.send(msg) {
+                warn!("Failed to send preference overrides to script ({:?}).", e);
+            }
         }
     }
 
-    fn change_session_history(&mut self, change: SessionHistoryChange) {
-        debug!(
-            "Setting browsing context {} to be pipeline {}.",
-            change.browsing_context_id, change.new_pipeline_id
-        );
-
-        // If the currently focused browsing context is a child of the browsing
-        // context in which the page is being loaded, then update the focused
-        // browsing context to be the one where the page is being loaded.
-        if self.focused_browsing_context_is_descendant_of(change.browsing_context_id) {
-            self.browsers
-                .entry(change.top_level_browsing_context_id)
-                .and_modify(|browser| {
+    fn handle_set_stylesheets(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        stylesheets: EmbedderStylesheets,
+    ) {
+        if stylesheets.is_empty() {
+            self.embedder_stylesheets
+                .remove(&top_level_browsing_context_id);
+        } else {
+            self.embedder_stylesheets
+                .insert(top_level_browsing_context_id, stylesheets.clone());
+        }
+        for event_loop in self.event_loops() {
+            let msg = ConstellationControlMsg::SetStylesheets(
+                top_level_browsing_context_id,
+                stylesheets.clone(),
+            );
+            if let Err(e) = event_loop.send(msg) {
+                warn!("Failed to send stylesheets to script ({:?}).", e);
+            }
+        }
+    }
+
+    /// Freeze or resume the fully active documents of a browser.
+    fn handle_freeze_browser(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        frozen: bool,
+    ) {
+        let pipeline_ids: Vec<PipelineId> = self
+            .fully_active_browsing_contexts_iter(top_level_browsing_context_id)
+            .map(|browsing_context| browsing_context.pipeline_id)
+            .collect();
+
+        for id in pipeline_ids {
+            let msg = ConstellationControlMsg::SetDocumentFrozen(id, frozen);
+            let result = match self.pipelines.get(&id) {
+                Some(pipeline) => pipeline.event_loop.send(msg),
+                None => continue,
+            };
+            if let Err(e) = result {
+                self.handle_send_error(id, e);
+            }
+        }
+    }
+
+    /// Discard the documents of a browser in the background, to free the memory of their
+    /// script and layout threads. The current one is replaced with about:blank, which
+    /// stands for it in the session history until the browser is shown again.
+    /// <https://wicg.github.io/page-lifecycle/#discarding>
+    fn handle_discard_browser(&mut self, top_level_browsing_context_id: TopLevelBrowsingContextId) {
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        let browsing_context = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(browsing_context) => browsing_context,
+            None => {
+                return warn!(
+                    "Browsing context {} got discard event after closure.",
+                    browsing_context_id
+                );
+            },
+        };
+        if browsing_context.is_visible {
+            return warn!(
+                "Not discarding the visible browser {}.",
+                top_level_browsing_context_id
+            );
+        }
+        let window_size = browsing_context.size;
+        let pipeline_id = browsing_context.pipeline_id;
+        let is_private = browsing_context.is_private;
+
+        let pipeline = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => pipeline,
+            None => return warn!("Pipeline {} got discard event after closure.", pipeline_id),
+        };
+        let opener = pipeline.opener;
+        let new_pipeline_id = PipelineId::new();
+        let discarded = DiscardedDocument::new(
+            new_pipeline_id,
+            pipeline.load_data.clone(),
+            pipeline.url.clone(),
+        );
+
+        match self.browsers.get_mut(&top_level_browsing_context_id) {
+            // A browser is discarded once, until it is loaded again.
+            Some(browser) if browser.discarded.is_some() => return,
+            Some(browser) => browser.discarded = Some(discarded),
+            None => return warn!("Discarding a browser after its closure."),
+        }
+
+        // The documents kept alive for history traversals are reloaded when traversed to.
+        self.evict_history(top_level_browsing_context_id, 0);
+        self.close_browsing_context_children(
+            browsing_context_id,
+            DiscardBrowsingContext::No,
+            ExitPipelineMode::Force,
+        );
+
+        self.new_pipeline(
+            new_pipeline_id,
+            browsing_context_id,
+            top_level_browsing_context_id,
+            None,
+            opener,
+            window_size,
+            DiscardedDocument::placeholder_load_data(),
+            IFrameSandboxState::IFrameUnsandboxed,
+            is_private,
+            false,
+        );
+        self.add_pending_change(SessionHistoryChange {
+            top_level_browsing_context_id: top_level_browsing_context_id,
+            browsing_context_id: browsing_context_id,
+            new_pipeline_id: new_pipeline_id,
+            replace: Some(NeedsToReload::No(pipeline_id)),
+            new_browsing_context_info: None,
+            window_size,
+        });
+    }
+
+    /// The discarded document of a browser, if the about:blank pipeline which stands for
+    /// it is the given one.
+    fn discarded_document(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        pipeline_id: PipelineId,
+    ) -> Option<&DiscardedDocument> {
+        self.browsers
+            .get(&top_level_browsing_context_id)
+            .and_then(|browser| browser.discarded.as_ref())
+            .filter(|discarded| discarded.is_placeholder(pipeline_id))
+    }
+
+    /// Load again the document of a browser which was discarded, if any, replacing the
+    /// about:blank document which stands for it.
+    fn restore_discarded_browser(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> bool {
+        let discarded = match self.browsers.get_mut(&top_level_browsing_context_id) {
+            Some(browser) => browser.discarded.take(),
+            None => None,
+        };
+        let discarded = match discarded {
+            Some(discarded) => discarded,
+            None => return false,
+        };
+        // The about:blank document may not have replaced the discarded one yet.
+        self.handle_abort_load_url_msg(discarded.placeholder_pipeline_id());
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(browsing_context) => browsing_context.pipeline_id,
+            None => return false,
+        };
+        debug!(
+            "Reloading the discarded document {} of browser {}.",
+            discarded.load_data().url,
+            top_level_browsing_context_id
+        );
+        self.load_url(
+            top_level_browsing_context_id,
+            pipeline_id,
+            discarded.into_load_data(),
+            HistoryEntryReplacement::Enabled,
+        );
+        true
+    }
+
+    fn handle_set_auto_resize(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        enabled: bool,
+    ) {
+        if enabled {
+            self.auto_resize_browsers
+                .insert(top_level_browsing_context_id);
+        } else {
+            self.auto_resize_browsers
+                .remove(&top_level_browsing_context_id);
+        }
+        for event_loop in self.event_loops() {
+            let msg =
+                ConstellationControlMsg::SetAutoResize(top_level_browsing_context_id, enabled);
+            if let Err(e) = event_loop.send(msg) {
+                warn!("Failed to send auto resize to script ({:?}).", e);
+            }
+        }
+    }
+
+    fn handle_memory_pressure(&mut self) {
+        // Drop the documents kept alive for history traversals, they are reloaded
+        // when traversed to.
+        let top_level_browsing_context_ids: Vec<_> = self.browsers.keys().cloned().collect();
+        let mut evicted = 0;
+        for top_level_browsing_context_id in top_level_browsing_context_ids {
+            evicted += self.evict_history(top_level_browsing_context_id, 0);
+        }
+
+        let mut event_loops: Vec<Rc<EventLoop>> = vec![];
+        for pipeline in self.pipelines.values() {
+            if let Err(e) = pipeline.layout_chan.send(LayoutControlMsg::MemoryPressure) {
+                warn!("Failed to send memory pressure to layout ({:?}).", e);
+            }
+            if !event_loops
+                .iter()
+                .any(|event_loop| Rc::ptr_eq(event_loop, &pipeline.event_loop))
+            {
+                event_loops.push(pipeline.event_loop.clone());
+            }
+        }
+
+        let receivers: Vec<_> = event_loops
+            .iter()
+            .filter_map(|event_loop| {
+                let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
+                match event_loop.send(ConstellationControlMsg::MemoryPressure(sender)) {
+                    Ok(()) => Some(receiver),
+                    Err(e) => {
+                        warn!("Failed to send memory pressure to script ({:?}).", e);
+                        None
+                    },
+                }
+            })
+            .collect();
+
+        // Wait for the script threads on a separate thread, so that a busy script thread
+        // doesn't block the constellation.
+        let embedder_proxy = self.embedder_proxy.clone();
+        let result = thread::Builder::new()
+            .name("MemoryPressureReporter".to_owned())
+            .spawn(move || {
+                let mut report = MemoryPressureReport {
+                    documents: evicted,
+                    ..Default::default()
+                };
+                for receiver in receivers {
+                    if let Ok(script_report) = receiver.recv() {
+                        report.add(script_report);
+                    }
+                }
+                embedder_proxy.send((None, EmbedderMsg::MemoryPressureHandled(report)));
+            });
+        if let Err(e) = result {
+            warn!(
+                "Failed to spawn memory pressure reporting thread ({:?}).",
+                e
+            );
+        }
+    }
+
+    fn handle_collect_memory_reports(&mut self) {
+        let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
+        self.mem_profiler_chan
+            .send(mem::ProfilerMsg::Report(sender));
+
+        // The memory profiler waits for the reporters of every thread, so the reports are
+        // forwarded from a separate thread to avoid blocking the constellation.
+        let embedder_proxy = self.embedder_proxy.clone();
+        let result = thread::Builder::new()
+            .name("MemoryReportsForwarder".to_owned())
+            .spawn(move || {
+                let reports = receiver
+                    .recv()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|report| MemoryReport {
+                        path: report.path,
+                        size: report.size,
+                    })
+                    .collect();
+                embedder_proxy.send((None, EmbedderMsg::MemoryReports(reports)));
+            });
+        if let Err(e) = result {
+            warn!("Failed to spawn memory reports thread ({:?}).", e);
+        }
+    }
+
+    fn handle_report_blocked_content(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) {
+        let pipeline_ids: Vec<PipelineId> = self
+            .fully_active_browsing_contexts_iter(top_level_browsing_context_id)
+            .map(|browsing_context| browsing_context.pipeline_id)
+            .collect();
+        let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
+        let msg = net_traits::CoreResourceMsg::GetBlockedCount(pipeline_ids, sender);
+        if let Err(e) = self.public_resource_threads.send(msg) {
+            return warn!("Failed to send blocked count request to net ({:?}).", e);
+        }
+        let embedder_proxy = self.embedder_proxy.clone();
+        let result = thread::Builder::new()
+            .name("BlockedContentReporter".to_owned())
+            .spawn(move || {
+                if let Ok(count) = receiver.recv() {
+                    embedder_proxy.send((
+                        Some(top_level_browsing_context_id),
+                        EmbedderMsg::BlockedContentReport(count),
+                    ));
+                }
+            });
+        if let Err(e) = result {
+            warn!(
+                "Failed to spawn blocked content reporting thread ({:?}).",
+                e
+            );
+        }
+    }
+
+    /// Queue a prompt for a browser, and ask the embedder to show it if no other prompt
+    /// of the browser is shown.
+    fn queue_prompt(
+        &mut self,
+        top_level_browsing_context_id: Option<TopLevelBrowsingContextId>,
+        request: PromptRequest,
+        sender: IpcSender<PromptResponse>,
+    ) {
+        let shown = self
+            .prompts
+            .push(top_level_browsing_context_id, request, sender);
+        if let Some((prompt_id, request)) = shown {
+            self.embedder_proxy.send((
+                top_level_browsing_context_id,
+                EmbedderMsg::ShowPrompt(prompt_id, request),
+            ));
+        }
+    }
+
+    fn handle_prompt_msg(&mut self, msg: PromptMsg) {
+        let top_level_browsing_context_id = match msg.pipeline_id {
+            Some(pipeline_id) => match self.pipelines.get(&pipeline_id) {
+                Some(pipeline) => Some(pipeline.top_level_browsing_context_id),
+                None => {
+                    warn!("Prompt for closed pipeline {}.", pipeline_id);
+                    let _ = msg.sender.send(PromptResponse::Dismiss);
+                    return;
+                },
+            },
+            None => None,
+        };
+        self.queue_prompt(top_level_browsing_context_id, msg.request, msg.sender);
+    }
+
+    /// Answer a prompt, and show the next prompt of its browser, if any.
+    fn handle_respond_to_prompt(&mut self, prompt_id: PromptId, response: PromptResponse) {
+        let result = self.prompts.respond(prompt_id, response);
+        let (top_level_browsing_context_id, next) = match result {
+            Some(result) => result,
+            None => return warn!("Response to prompt {:?} which is not shown.", prompt_id),
+        };
+        if let Some((prompt_id, request)) = next {
+            self.embedder_proxy.send((
+                top_level_browsing_context_id,
+                EmbedderMsg::ShowPrompt(prompt_id, request),
+            ));
+        }
+    }
+
+    fn handle_set_visual_viewport(
+        &mut self,
+        pipeline_id: PipelineId,
+        scale: f32,
+        offset: Vector2D<f32, CSSPixel>,
+    ) {
+        let msg = ConstellationControlMsg::SetVisualViewport(pipeline_id, scale, offset);
+        let result = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => pipeline.event_loop.send(msg),
+            None => {
+                return warn!(
+                    "Pipeline {} got visual viewport after closure.",
+                    pipeline_id
+                )
+            },
+        };
+        if let Err(e) = result {
+            self.handle_send_error(pipeline_id, e);
+        }
+    }
+
+    /// Propagate a user activation to the ancestors of a pipeline, which might be
+    /// in other script threads.
+    /// <https://html.spec.whatwg.org/multipage/#activation-notification>
+    fn handle_notify_user_activation_msg(&mut self, pipeline_id: PipelineId) {
+        let mut ancestor_pipeline_ids = vec![];
+        let mut current_pipeline_id = pipeline_id;
+        while let Some(parent_pipeline_id) = self
+            .pipelines
+            .get(&current_pipeline_id)
+            .and_then(|pipeline| self.browsing_contexts.get(&pipeline.browsing_context_id))
+            .and_then(|browsing_context| browsing_context.parent_pipeline_id)
+        {
+            ancestor_pipeline_ids.push(parent_pipeline_id);
+            current_pipeline_id = parent_pipeline_id;
+        }
+
+        for ancestor_pipeline_id in ancestor_pipeline_ids {
+            let msg = ConstellationControlMsg::NotifyUserActivation(ancestor_pipeline_id);
+            let result = match self.pipelines.get(&ancestor_pipeline_id) {
+                Some(pipeline) => pipeline.event_loop.send(msg),
+                None => continue,
+            };
+            if let Err(e) = result {
+                self.handle_send_error(ancestor_pipeline_id, e);
+            }
+        }
+    }
+
+    /// Consume the user activation of all the documents of the top-level browsing
+    /// context of a pipeline.
+    /// <https://html.spec.whatwg.org/multipage/#consume-user-activation>
+    fn handle_consume_user_activation_msg(&mut self, pipeline_id: PipelineId) {
+        let top_level_browsing_context_id = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => pipeline.top_level_browsing_context_id,
+            None => {
+                return warn!(
+                    "Pipeline {} consumed user activation after closure.",
+                    pipeline_id
+                )
+            },
+        };
+
+        let pipeline_ids: Vec<PipelineId> = self
+            .fully_active_browsing_contexts_iter(top_level_browsing_context_id)
+            .map(|browsing_context| browsing_context.pipeline_id)
+            .filter(|id| *id != pipeline_id)
+            .collect();
+
+        for id in pipeline_ids {
+            let msg = ConstellationControlMsg::ConsumeUserActivation(id);
+            let result = match self.pipelines.get(&id) {
+                Some(pipeline) => pipeline.event_loop.send(msg),
+                None => continue,
+            };
+            if let Err(e) = result {
+                self.handle_send_error(id, e);
+            }
+        }
+    }
+
+    /// Ask the compositor for a screenshot of a browser. Full page screenshots need the
+    /// size of the document first, which is waited for on a separate thread.
+    fn take_screenshot(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        area: ScreenshotArea,
+        reply: IpcSender<Option<Image>>,
+    ) {
+        let rect = match area {
+            ScreenshotArea::Viewport => None,
+            ScreenshotArea::Rect(rect) => Some(rect),
+            ScreenshotArea::FullPage => {
+                return self.take_full_page_screenshot(top_level_browsing_context_id, reply);
+            },
+        };
+        self.compositor_proxy
+            .send(ToCompositorMsg::CreatePng(rect, reply));
+    }
+
+    fn take_full_page_screenshot(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        reply: IpcSender<Option<Image>>,
+    ) {
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        let pipeline = self
+            .browsing_contexts
+            .get(&browsing_context_id)
+            .and_then(|browsing_context| self.pipelines.get(&browsing_context.pipeline_id));
+        let pipeline = match pipeline {
+            Some(pipeline) => pipeline,
+            None => {
+                warn!(
+                    "{}: Screenshot of unknown browser",
+                    top_level_browsing_context_id
+                );
+                let _ = reply.send(None);
+                return;
+            },
+        };
+        let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
+        let msg = ConstellationControlMsg::GetDocumentScrollSize(pipeline.id, sender);
+        if let Err(e) = pipeline.event_loop.send(msg) {
+            warn!("Failed to request document size ({:?}).", e);
+            let _ = reply.send(None);
+            return;
+        }
+
+        let compositor_proxy = self.compositor_proxy.clone();
+        let result = thread::Builder::new()
+            .name("FullPageScreenshot".to_owned())
+            .spawn(move || match receiver.recv() {
+                Ok(Some(page_size)) => {
+                    compositor_proxy.send(ToCompositorMsg::CreateFullPagePng(page_size, reply));
+                },
+                _ => {
+                    let _ = reply.send(None);
+                },
+            });
+        if let Err(e) = result {
+            warn!("Failed to spawn full page screenshot thread ({:?}).", e);
+        }
+    }
+
+    /// Take a screenshot of a browser, and send it to the embedder as a PNG, with the id
+    /// of the request so that the embedder can tell the screenshots apart.
+    fn handle_capture_screenshot(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        area: ScreenshotArea,
+        id: ScreenshotId,
+    ) {
+        let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
+        self.take_screenshot(top_level_browsing_context_id, area, sender);
+
+        // Encoding the image is slow, keep it off the constellation thread.
+        let embedder_proxy = self.embedder_proxy.clone();
+        let result = thread::Builder::new()
+            .name("ScreenshotEncoder".to_owned())
+            .spawn(move || {
+                let png = receiver
+                    .recv()
+                    .ok()
+                    .and_then(|image| image)
+                    .and_then(|image| {
+                        let rgb =
+                            RgbImage::from_raw(image.width, image.height, image.bytes.to_vec())?;
+                        let mut png = Vec::new();
+                        DynamicImage::ImageRgb8(rgb)
+                            .write_to(&mut png, ImageFormat::Png)
+                            .ok()?;
+                        Some(png)
+                    });
+                embedder_proxy.send((
+                    Some(top_level_browsing_context_id),
+                    EmbedderMsg::ScreenshotCaptured(id, png),
+                ));
+            });
+        if let Err(e) = result {
+            warn!("Failed to spawn screenshot encoding thread ({:?}).", e);
+        }
+    }
+
+    /// Create a new browser for each browser of a saved session. Only the current
+    /// entry of each browser is loaded, the other entries are added to the session history
+    /// as discarded entries which will be reloaded when traversed to.
+    fn handle_restore_session(&mut self, session: SessionState) {
+        for browser in session.browsers {
+            if browser.current_index >= browser.entries.len() {
+                warn!("Ignoring saved browser with an invalid current entry.");
+                continue;
+            }
+            let top_level_browsing_context_id = TopLevelBrowsingContextId::new();
+            let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+            let msg = StorageThreadMsg::SetSessionStorage(
+                top_level_browsing_context_id,
+                browser.session_storage,
+            );
+            if let Err(e) = self.public_resource_threads.send(msg) {
+                warn!("Failed to restore session storage ({:?}).", e);
+            }
+            let mut reloaders: Vec<NeedsToReload> = browser
+                .entries
+                .into_iter()
+                .map(|entry| {
+                    let mut load_data =
+                        LoadData::new(LoadOrigin::Constellation, entry.url, None, None, None);
+                    load_data.document_state = entry.document_state;
+                    NeedsToReload::Yes(PipelineId::new(), load_data)
+                })
+                .collect();
+
+            let current_load_data = match reloaders[browser.current_index] {
+                NeedsToReload::Yes(_, ref load_data) => load_data.clone(),
+                NeedsToReload::No(..) => unreachable!(),
+            };
+            let pipeline_id = self.new_top_level_browsing_context(
+                current_load_data,
+                top_level_browsing_context_id,
+                false,
+            );
+            reloaders[browser.current_index] = NeedsToReload::No(pipeline_id);
+
+            let diffs: Vec<SessionHistoryDiff> = reloaders
+                .windows(2)
+                .map(|reloaders| SessionHistoryDiff::BrowsingContextDiff {
+                    browsing_context_id,
+                    old_reloader: reloaders[0].clone(),
+                    new_reloader: reloaders[1].clone(),
+                })
+                .collect();
+            let mut diffs = diffs.into_iter();
+            let session_history = self.get_joint_session_history(top_level_browsing_context_id);
+            session_history.past = diffs.by_ref().take(browser.current_index).collect();
+            // Future diffs are stored with the closest entry at the end.
+            session_history.future = diffs.rev().collect();
+        }
+    }
+
+    fn load_url_for_webdriver(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        load_data: LoadData,
+        reply: IpcSender<webdriver_msg::LoadStatus>,
+        replace: HistoryEntryReplacement,
+    ) {
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
+            Some(browsing_context) => browsing_context.pipeline_id,
+            None => {
+                return warn!(
+                    "Webdriver load for closed browsing context {}.",
+                    browsing_context_id
+                );
+            },
+        };
+        if let Some(new_pipeline_id) = self.load_url(
+            top_level_browsing_context_id,
+            pipeline_id,
+            load_data,
+            replace,
+        ) {
+            self.webdriver.load_channel = Some((new_pipeline_id, reply));
+        }
+    }
+
+    fn change_session_history(&mut self, change: SessionHistoryChange) {
+        debug!(
+            "Setting browsing context {} to be pipeline {}.",
+            change.browsing_context_id, change.new_pipeline_id
+        );
+
+        // If the currently focused browsing context is a child of the browsing
+        // context in which the page is being loaded, then update the focused
+        // browsing context to be the one where the page is being loaded.
+        if self.focused_browsing_context_is_descendant_of(change.browsing_context_id) {
+            self.browsers
+                .entry(change.top_level_browsing_context_id)
+                .and_modify(|browser| {
                     browser.focused_browsing_context_id = change.browsing_context_id
                 });
         }
@@ -4745,8 +6445,27 @@ where
                     pipeline.notify_visibility(false);
                 }
 
+                // A discarded document isn't unloaded, it is gone at once.
                 // https://html.spec.whatwg.org/multipage/#unload-a-document
-                self.unload_document(old_pipeline_id);
+                let is_discarded = self
+                    .discarded_document(
+                        change.top_level_browsing_context_id,
+                        change.new_pipeline_id,
+                    )
+                    .is_some();
+                if !is_discarded {
+                    self.unload_document(old_pipeline_id);
+                }
+                // Navigating away from the document standing for a discarded one drops it.
+                if change.browsing_context_id == change.top_level_browsing_context_id &&
+                    !is_discarded
+                {
+                    if let Some(browser) =
+                        self.browsers.get_mut(&change.top_level_browsing_context_id)
+                    {
+                        browser.discarded = None;
+                    }
+                }
                 // Deactivate the old pipeline, and activate the new one.
                 let (pipelines_to_close, states_to_close) = if let Some(replace_reloader) =
                     change.replace
@@ -4857,11 +6576,21 @@ where
     }
 
     fn trim_history(&mut self, top_level_browsing_context_id: TopLevelBrowsingContextId) {
+        let history_length = pref!(session_history.max_length) as usize;
+        self.evict_history(top_level_browsing_context_id, history_length);
+    }
+
+    /// Close the pipelines of the session history which are more than `history_length`
+    /// entries away from the current one, so that they are reloaded when traversed to.
+    /// Returns the number of closed pipelines.
+    fn evict_history(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        history_length: usize,
+    ) -> usize {
         let pipelines_to_evict = {
             let session_history = self.get_joint_session_history(top_level_browsing_context_id);
 
-            let history_length = pref!(session_history.max_length) as usize;
-
             // The past is stored with older entries at the front.
             // We reverse the iter so that newer entries are at the front and then
             // skip _n_ entries and evict the remaining entries.
@@ -4910,9 +6639,11 @@ where
 
         let session_history = self.get_joint_session_history(top_level_browsing_context_id);
 
+        let evicted = dead_pipelines.len();
         for (alive_id, dead) in dead_pipelines {
             session_history.replace_reloader(NeedsToReload::No(alive_id), dead);
         }
+        evicted
     }
 
     fn handle_activate_document_msg(&mut self, pipeline_id: PipelineId) {
@@ -4974,6 +6705,24 @@ where
         if let Some(top_level_browsing_context_id) = top_level_browsing_context_id {
             let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
             self.resize_browsing_context(new_size, size_type, browsing_context_id);
+
+            // Nested browsing contexts are only resized when the layout of their parent
+            // gives their iframe a new size, so a new device pixel ratio is sent to them
+            // here, so that they can evaluate their media queries and `srcset` again.
+            if new_size.device_pixel_ratio != self.window_size.device_pixel_ratio {
+                let nested_browsing_contexts: Vec<_> = self
+                    .all_descendant_browsing_contexts_iter(browsing_context_id)
+                    .filter(|browsing_context| browsing_context.id != browsing_context_id)
+                    .map(|browsing_context| (browsing_context.id, browsing_context.size))
+                    .collect();
+                for (id, size) in nested_browsing_contexts {
+                    let window_size = WindowSizeData {
+                        initial_viewport: size,
+                        device_pixel_ratio: new_size.device_pixel_ratio,
+                    };
+                    self.resize_browsing_context(window_size, WindowSizeType::Resize, id);
+                }
+            }
         }
 
         if let Some(resize_channel) = self.webdriver.resize_channel.take() {
@@ -4992,6 +6741,43 @@ where
         self.switch_fullscreen_mode(browsing_context_id);
     }
 
+    fn handle_exit_picture_in_picture_msg(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) {
+        // The picture-in-picture element may be in any document of the browser.
+        let pipeline_ids: Vec<PipelineId> = self
+            .fully_active_browsing_contexts_iter(top_level_browsing_context_id)
+            .map(|browsing_context| browsing_context.pipeline_id)
+            .collect();
+
+        for id in pipeline_ids {
+            let msg = ConstellationControlMsg::ExitPictureInPicture(id);
+            let result = match self.pipelines.get(&id) {
+                Some(pipeline) => pipeline.event_loop.send(msg),
+                None => continue,
+            };
+            if let Err(e) = result {
+                self.handle_send_error(id, e);
+            }
+        }
+    }
+
+    /// Let every document know that the media devices available to content changed.
+    fn handle_media_devices_changed_msg(&mut self) {
+        let pipeline_ids: Vec<PipelineId> = self.pipelines.keys().cloned().collect();
+        for id in pipeline_ids {
+            let msg = ConstellationControlMsg::MediaDevicesChanged(id);
+            let result = match self.pipelines.get(&id) {
+                Some(pipeline) => pipeline.event_loop.send(msg),
+                None => continue,
+            };
+            if let Err(e) = result {
+                self.handle_send_error(id, e);
+            }
+        }
+    }
+
     /// Handle updating actual viewport / zoom due to @viewport rules
     fn handle_viewport_constrained_msg(
         &mut self,
@@ -5468,6 +7254,9 @@ where
             .or_insert_with(|| Browser {
                 focused_browsing_context_id: BrowsingContextId::from(top_level_id),
                 session_history: JointSessionHistory::new(),
+                crashed_load_data: None,
+                discarded: None,
+                private_session: None,
             })
             .session_history
     }
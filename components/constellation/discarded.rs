@@ -0,0 +1,62 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The documents which the embedder discards to free the memory of hidden browsers. An
+//! about:blank document stands for each of them in the session history, until it is
+//! loaded again.
+//!
+//! <https://wicg.github.io/page-lifecycle/#discarding>
+
+use msg::constellation_msg::PipelineId;
+use script_traits::{LoadData, LoadOrigin};
+use servo_url::ServoUrl;
+
+/// A document which the embedder discarded to save memory.
+pub struct DiscardedDocument {
+    /// The about:blank pipeline which stands for the discarded document.
+    placeholder_pipeline_id: PipelineId,
+    /// The load data with which the discarded document is loaded again.
+    load_data: LoadData,
+}
+
+impl DiscardedDocument {
+    /// Discard the document loaded with the given load data, whose URL may have changed
+    /// since, in favour of the given pipeline.
+    pub fn new(
+        placeholder_pipeline_id: PipelineId,
+        mut load_data: LoadData,
+        url: ServoUrl,
+    ) -> Self {
+        load_data.url = url;
+        load_data.was_discarded = true;
+        DiscardedDocument {
+            placeholder_pipeline_id,
+            load_data,
+        }
+    }
+
+    /// The load data of the about:blank document which stands for a discarded one.
+    pub fn placeholder_load_data() -> LoadData {
+        let blank_url = ServoUrl::parse("about:blank").expect("infallible");
+        LoadData::new(LoadOrigin::Constellation, blank_url, None, None, None)
+    }
+
+    pub fn placeholder_pipeline_id(&self) -> PipelineId {
+        self.placeholder_pipeline_id
+    }
+
+    /// Whether the given pipeline is the one which stands for the discarded document.
+    pub fn is_placeholder(&self, pipeline_id: PipelineId) -> bool {
+        self.placeholder_pipeline_id == pipeline_id
+    }
+
+    /// The load data with which the discarded document is loaded again.
+    pub fn load_data(&self) -> &LoadData {
+        &self.load_data
+    }
+
+    pub fn into_load_data(self) -> LoadData {
+        self.load_data
+    }
+}
@@ -13,9 +13,12 @@ extern crate serde;
 
 mod browsingcontext;
 mod constellation;
+mod discarded;
 mod event_loop;
 mod network_listener;
 mod pipeline;
+mod prompts;
+mod protocol_handlers;
 #[cfg(all(
     not(target_os = "windows"),
     not(target_os = "ios"),
@@ -24,13 +27,18 @@ mod pipeline;
     not(target_arch = "aarch64")
 ))]
 mod sandboxing;
+mod session;
 mod session_history;
 mod timer_scheduler;
 
 pub use crate::constellation::{
     Constellation, FromCompositorLogger, FromScriptLogger, InitialConstellationState,
 };
+pub use crate::discarded::DiscardedDocument;
 pub use crate::pipeline::UnprivilegedPipelineContent;
+pub use crate::prompts::Prompts;
+pub use crate::session::{save_session, PendingBrowserSession, PendingSessionHistoryEntry};
+pub use crate::session_history::{JointSessionHistory, NeedsToReload, SessionHistoryDiff};
 #[cfg(all(
     not(target_os = "windows"),
     not(target_os = "ios"),
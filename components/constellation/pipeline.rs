@@ -48,6 +48,7 @@ use std::process;
 use std::rc::Rc;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use uuid::Uuid;
 use webvr_traits::WebVRMsg;
 
 /// A `Pipeline` is the constellation's view of a `Document`. Each pipeline has an
@@ -98,6 +99,13 @@ pub struct Pipeline {
 
     /// Has this pipeline received a notification that it is completely loaded?
     pub completely_loaded: bool,
+
+    /// The id of the document of this pipeline as a client of service workers.
+    /// <https://w3c.github.io/ServiceWorker/#dom-client-id>
+    pub client_id: Uuid,
+
+    /// The scope of the service worker controlling the document of this pipeline, if any.
+    pub service_worker_controller: Option<ServoUrl>,
 }
 
 /// Initial setup data needed to construct a pipeline.
@@ -157,6 +165,9 @@ pub struct InitialPipelineState {
     /// Channels to the resource-related threads.
     pub resource_threads: ResourceThreads,
 
+    /// Whether the resource threads are those of a private browsing session.
+    pub is_private: bool,
+
     /// A channel to the time profiler thread.
     pub time_profiler_chan: time::ProfilerChan,
 
@@ -209,6 +220,47 @@ pub struct InitialPipelineState {
 pub struct NewPipeline {
     pub pipeline: Pipeline,
     pub sampler_control_chan: Option<IpcSender<SamplerControlMsg>>,
+    /// The content process started for the event loop of the pipeline, if any.
+    pub content_process: Option<ContentProcess>,
+}
+
+/// A content process, started with or without a sandbox, which the constellation
+/// watches to recover from its crashes.
+pub enum ContentProcess {
+    Unsandboxed(process::Child),
+    #[cfg(all(
+        not(target_os = "windows"),
+        not(target_os = "ios"),
+        not(target_os = "android"),
+        not(target_arch = "arm"),
+        not(target_arch = "aarch64")
+    ))]
+    Sandboxed(gaol::sandbox::Process),
+}
+
+impl ContentProcess {
+    /// Block until the process exits, and return the reason it crashed, if it did.
+    pub fn wait(self) -> Option<String> {
+        match self {
+            ContentProcess::Unsandboxed(mut child) => match child.wait() {
+                Ok(status) if status.success() => None,
+                Ok(status) => Some(format!("Content process crashed ({})", status)),
+                Err(e) => Some(format!("Content process was lost ({})", e)),
+            },
+            #[cfg(all(
+                not(target_os = "windows"),
+                not(target_os = "ios"),
+                not(target_os = "android"),
+                not(target_arch = "arm"),
+                not(target_arch = "aarch64")
+            ))]
+            ContentProcess::Sandboxed(process) => match process.wait() {
+                Ok(status) if status.success() => None,
+                Ok(status) => Some(format!("Sandboxed content process crashed ({:?})", status)),
+                Err(e) => Some(format!("Sandboxed content process was lost ({})", e)),
+            },
+        }
+    }
 }
 
 impl Pipeline {
@@ -223,7 +275,7 @@ impl Pipeline {
         // probably requires a general low-memory strategy.
         let (pipeline_chan, pipeline_port) = ipc::channel().expect("Pipeline main chan");
 
-        let (script_chan, sampler_chan) = match state.event_loop {
+        let (script_chan, sampler_chan, content_process) = match state.event_loop {
             Some(script_chan) => {
                 let new_layout_info = NewLayoutInfo {
                     parent_info: state.parent_pipeline_id,
@@ -241,7 +293,7 @@ impl Pipeline {
                 {
                     warn!("Sending to script during pipeline creation failed ({})", e);
                 }
-                (script_chan, None)
+                (script_chan, None, None)
             },
             None => {
                 let (script_chan, script_port) = ipc::channel().expect("Pipeline script chan");
@@ -289,6 +341,7 @@ impl Pipeline {
                     swmanager_thread: state.swmanager_thread,
                     font_cache_thread: state.font_cache_thread,
                     resource_threads: state.resource_threads,
+                    is_private: state.is_private,
                     time_profiler_chan: state.time_profiler_chan,
                     mem_profiler_chan: state.mem_profiler_chan,
                     window_size: state.window_size,
@@ -312,11 +365,11 @@ impl Pipeline {
                 // Spawn the child process.
                 //
                 // Yes, that's all there is to it!
-                let sampler_chan = if opts::multiprocess() {
+                let (sampler_chan, content_process) = if opts::multiprocess() {
                     let (sampler_chan, sampler_port) = ipc::channel().expect("Sampler chan");
                     unprivileged_pipeline_content.sampling_profiler_port = Some(sampler_port);
-                    let _ = unprivileged_pipeline_content.spawn_multiprocess()?;
-                    Some(sampler_chan)
+                    let content_process = unprivileged_pipeline_content.spawn_multiprocess()?;
+                    (Some(sampler_chan), Some(content_process))
                 } else {
                     // Should not be None in single-process mode.
                     if opts::get().background_hang_monitor {
@@ -328,18 +381,18 @@ impl Pipeline {
                             Some(register),
                             state.event_loop_waker,
                         );
-                        None
+                        (None, None)
                     } else {
                         unprivileged_pipeline_content.start_all::<Message, LTF, STF>(
                             false,
                             None,
                             state.event_loop_waker,
                         );
-                        None
+                        (None, None)
                     }
                 };
 
-                (EventLoop::new(script_chan), sampler_chan)
+                (EventLoop::new(script_chan), sampler_chan, content_process)
             },
         };
 
@@ -357,6 +410,7 @@ impl Pipeline {
         Ok(NewPipeline {
             pipeline,
             sampler_control_chan: sampler_chan,
+            content_process,
         })
     }
 
@@ -388,6 +442,8 @@ impl Pipeline {
             history_state_id: None,
             history_states: HashSet::new(),
             completely_loaded: false,
+            client_id: Uuid::new_v4(),
+            service_worker_controller: None,
         };
 
         pipeline.notify_visibility(is_visible);
@@ -506,6 +562,7 @@ pub struct UnprivilegedPipelineContent {
     swmanager_thread: IpcSender<SWManagerMsg>,
     font_cache_thread: FontCacheThread,
     resource_threads: ResourceThreads,
+    is_private: bool,
     time_profiler_chan: time::ProfilerChan,
     mem_profiler_chan: profile_mem::ProfilerChan,
     window_size: WindowSizeData,
@@ -564,6 +621,7 @@ impl UnprivilegedPipelineContent {
                 scheduler_chan: self.scheduler_chan,
                 bluetooth_thread: self.bluetooth_thread,
                 resource_threads: self.resource_threads,
+                is_private: self.is_private,
                 image_cache: image_cache.clone(),
                 time_profiler_chan: self.time_profiler_chan.clone(),
                 mem_profiler_chan: self.mem_profiler_chan.clone(),
@@ -637,7 +695,7 @@ impl UnprivilegedPipelineContent {
         target_arch = "arm",
         all(target_arch = "aarch64", not(target_os = "windows"))
     ))]
-    pub fn spawn_multiprocess(self) -> Result<(), Error> {
+    pub fn spawn_multiprocess(self) -> Result<ContentProcess, Error> {
         use ipc_channel::ipc::IpcOneShotServer;
         // Note that this function can panic, due to process creation,
         // avoiding this panic would require a mechanism for dealing
@@ -648,14 +706,14 @@ impl UnprivilegedPipelineContent {
         let path_to_self = env::current_exe().expect("Failed to get current executor.");
         let mut child_process = process::Command::new(path_to_self);
         self.setup_common(&mut child_process, token);
-        let _ = child_process
+        let child = child_process
             .spawn()
             .expect("Failed to start unsandboxed child process!");
 
         let (_receiver, sender) = server.accept().expect("Server failed to accept.");
         sender.send(self)?;
 
-        Ok(())
+        Ok(ContentProcess::Unsandboxed(child))
     }
 
     #[cfg(all(
@@ -665,7 +723,7 @@ impl UnprivilegedPipelineContent {
         not(target_arch = "arm"),
         not(target_arch = "aarch64")
     ))]
-    pub fn spawn_multiprocess(self) -> Result<(), Error> {
+    pub fn spawn_multiprocess(self) -> Result<ContentProcess, Error> {
         use crate::sandboxing::content_process_sandbox_profile;
         use gaol::sandbox::{self, Sandbox, SandboxMethods};
         use ipc_channel::ipc::IpcOneShotServer;
@@ -694,31 +752,33 @@ impl UnprivilegedPipelineContent {
             .expect("Failed to create IPC one-shot server.");
 
         // If there is a sandbox, use the `gaol` API to create the child process.
-        if self.opts.sandbox {
+        let child = if self.opts.sandbox {
             let mut command = sandbox::Command::me().expect("Failed to get current sandbox.");
             self.setup_common(&mut command, token);
 
             let profile = content_process_sandbox_profile();
-            let _ = Sandbox::new(profile)
+            let process = Sandbox::new(profile)
                 .start(&mut command)
                 .expect("Failed to start sandboxed child process!");
+            ContentProcess::Sandboxed(process)
         } else {
             let path_to_self = env::current_exe().expect("Failed to get current executor.");
             let mut child_process = process::Command::new(path_to_self);
             self.setup_common(&mut child_process, token);
-            let _ = child_process
+            let child = child_process
                 .spawn()
                 .expect("Failed to start unsandboxed child process!");
-        }
+            ContentProcess::Unsandboxed(child)
+        };
 
         let (_receiver, sender) = server.accept().expect("Server failed to accept.");
         sender.send(self)?;
 
-        Ok(())
+        Ok(child)
     }
 
     #[cfg(any(target_os = "windows", target_os = "ios"))]
-    pub fn spawn_multiprocess(self) -> Result<(), Error> {
+    pub fn spawn_multiprocess(self) -> Result<ContentProcess, Error> {
         error!("Multiprocess is not supported on Windows or iOS.");
         process::exit(1);
     }
@@ -0,0 +1,123 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The prompts shown by the embedder on behalf of the browsers. The prompts of each
+//! browser are queued, and only the first one of a queue is shown: the next one is
+//! shown once it has been answered, by the user or by WebDriver. Pending permission
+//! requests which are identical are coalesced into a single prompt.
+
+use embedder_traits::{PromptId, PromptRequest, PromptResponse};
+use ipc_channel::ipc::IpcSender;
+use msg::constellation_msg::TopLevelBrowsingContextId;
+use std::collections::{HashMap, VecDeque};
+
+struct Prompt {
+    id: PromptId,
+    request: PromptRequest,
+    /// The senders of the requests which were coalesced into the prompt.
+    responders: Vec<IpcSender<PromptResponse>>,
+}
+
+impl Prompt {
+    fn respond(self, response: PromptResponse) {
+        for responder in self.responders {
+            if let Err(e) = responder.send(response.clone()) {
+                warn!("Failed to send response to prompt {:?} ({}).", self.id, e);
+            }
+        }
+    }
+}
+
+/// The queues of prompts, by browser. Prompts which are not requested for a browser,
+/// such as those of devtools, are queued for `None`.
+pub struct Prompts {
+    next_id: u64,
+    queues: HashMap<Option<TopLevelBrowsingContextId>, VecDeque<Prompt>>,
+}
+
+impl Prompts {
+    pub fn new() -> Prompts {
+        Prompts {
+            next_id: 0,
+            queues: HashMap::new(),
+        }
+    }
+
+    /// Queue a prompt for the given browser. Returns the prompt to show if the queue
+    /// was empty.
+    pub fn push(
+        &mut self,
+        browser_id: Option<TopLevelBrowsingContextId>,
+        request: PromptRequest,
+        sender: IpcSender<PromptResponse>,
+    ) -> Option<(PromptId, PromptRequest)> {
+        let queue = self.queues.entry(browser_id).or_insert_with(VecDeque::new);
+        if let PromptRequest::Permission(..) = request {
+            if let Some(prompt) = queue.iter_mut().find(|prompt| prompt.request == request) {
+                prompt.responders.push(sender);
+                return None;
+            }
+        }
+        let id = PromptId(self.next_id);
+        self.next_id += 1;
+        queue.push_back(Prompt {
+            id: id,
+            request: request.clone(),
+            responders: vec![sender],
+        });
+        if queue.len() == 1 {
+            Some((id, request))
+        } else {
+            None
+        }
+    }
+
+    /// Answer the prompt with the given id, if it is shown. Returns its browser, and
+    /// the next prompt of the browser to show, if any.
+    pub fn respond(
+        &mut self,
+        id: PromptId,
+        response: PromptResponse,
+    ) -> Option<(
+        Option<TopLevelBrowsingContextId>,
+        Option<(PromptId, PromptRequest)>,
+    )> {
+        let browser_id = *self
+            .queues
+            .iter()
+            .find(|(_, queue)| queue.front().map(|prompt| prompt.id) == Some(id))?
+            .0;
+        let queue = self.queues.get_mut(&browser_id)?;
+        queue.pop_front()?.respond(response);
+        let next = queue
+            .front()
+            .map(|prompt| (prompt.id, prompt.request.clone()));
+        if next.is_none() {
+            self.queues.remove(&browser_id);
+        }
+        Some((browser_id, next))
+    }
+
+    /// The prompt shown for the given browser, if any.
+    pub fn current(
+        &self,
+        browser_id: Option<TopLevelBrowsingContextId>,
+    ) -> Option<(PromptId, &PromptRequest)> {
+        self.queues
+            .get(&browser_id)
+            .and_then(|queue| queue.front())
+            .map(|prompt| (prompt.id, &prompt.request))
+    }
+
+    /// Dismiss the prompts of a browser which is closed. Returns the id of the prompt
+    /// which was shown, if any.
+    pub fn close(&mut self, browser_id: TopLevelBrowsingContextId) -> Option<PromptId> {
+        let queue = self.queues.remove(&Some(browser_id))?;
+        let shown = queue.front().map(|prompt| prompt.id);
+        for prompt in queue {
+            prompt.respond(PromptResponse::Dismiss);
+        }
+        shown
+    }
+}
@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The protocol handlers registered by pages with `navigator.registerProtocolHandler()`.
+//! The navigations to a URL of a registered scheme load the URL of its handler instead.
+//!
+//! <https://html.spec.whatwg.org/multipage/#custom-handlers>
+
+use net::resource_thread::{read_json_from_file, write_json_to_file};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// <https://url.spec.whatwg.org/#component-percent-encode-set>
+const COMPONENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'$')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b',')
+    .add(b'/')
+    .add(b':')
+    .add(b';')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'@')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+const FILENAME: &'static str = "protocol_handlers.json";
+
+pub struct ProtocolHandlers {
+    /// The URL templates of the handlers, which contain `%s`, by scheme.
+    handlers: HashMap<String, String>,
+    /// The directory where the handlers are kept between sessions.
+    config_dir: Option<PathBuf>,
+}
+
+impl ProtocolHandlers {
+    pub fn new(config_dir: Option<PathBuf>) -> ProtocolHandlers {
+        let mut handlers = HashMap::new();
+        if let Some(ref config_dir) = config_dir {
+            read_json_from_file(&mut handlers, config_dir, FILENAME);
+        }
+        ProtocolHandlers {
+            handlers: handlers,
+            config_dir: config_dir,
+        }
+    }
+
+    /// Register the handler of the given scheme, replacing the previous one.
+    pub fn register(&mut self, scheme: String, url: ServoUrl) {
+        self.handlers.insert(scheme, url.into_string());
+        self.save();
+    }
+
+    /// Unregister the handler of the given scheme, if it has the given URL.
+    pub fn unregister(&mut self, scheme: &str, url: &ServoUrl) {
+        if self.handlers.get(scheme).map(String::as_str) == Some(url.as_str()) {
+            self.handlers.remove(scheme);
+            self.save();
+        }
+    }
+
+    /// The URL which a navigation to the given URL loads instead, if its scheme has a
+    /// handler.
+    pub fn resolve(&self, url: &ServoUrl) -> Option<ServoUrl> {
+        let template = self.handlers.get(url.scheme())?;
+        let escaped_url = utf8_percent_encode(url.as_str(), COMPONENT).to_string();
+        ServoUrl::parse(&template.replacen("%s", &escaped_url, 1)).ok()
+    }
+
+    fn save(&self) {
+        if let Some(ref config_dir) = self.config_dir {
+            write_json_to_file(&self.handlers, config_dir, FILENAME);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Saving the session of the browsers, whose document states and session storage
+//! are sent back asynchronously by the event loops and the storage thread.
+
+use embedder_traits::{BrowserSessionState, SavedDocumentState};
+use embedder_traits::{SessionHistoryEntryState, SessionState};
+use ipc_channel::ipc::IpcReceiver;
+use ipc_channel::router::ROUTER;
+use serde::{Deserialize, Serialize};
+use servo_url::ServoUrl;
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+/// The session storage of a browser, by origin.
+pub type SessionStorage = BTreeMap<String, BTreeMap<String, String>>;
+
+/// An entry of the session history of a browser, whose document may still have to
+/// send its state.
+pub struct PendingSessionHistoryEntry {
+    pub url: ServoUrl,
+    /// The state kept in the session history, used when the document doesn't reply.
+    pub document_state: Option<SavedDocumentState>,
+    /// Where the event loop of the document sends its state, if it was asked for it.
+    pub document_state_receiver: Option<IpcReceiver<Option<SavedDocumentState>>>,
+}
+
+/// A browser whose session is being saved.
+pub struct PendingBrowserSession {
+    pub entries: Vec<PendingSessionHistoryEntry>,
+    pub current_index: usize,
+    /// Where the storage thread sends the session storage of the browser, if it was
+    /// asked for it.
+    pub session_storage_receiver: Option<IpcReceiver<SessionStorage>>,
+}
+
+/// The session being gathered, which is passed to the callback once every reply came.
+struct SessionSaver {
+    session: SessionState,
+    /// The number of replies which are still expected.
+    pending: usize,
+    callback: Option<Box<dyn FnOnce(SessionState) + Send>>,
+}
+
+impl SessionSaver {
+    fn reply_received(&mut self) {
+        self.pending -= 1;
+        self.maybe_finish();
+    }
+
+    fn maybe_finish(&mut self) {
+        if self.pending > 0 {
+            return;
+        }
+        if let Some(callback) = self.callback.take() {
+            callback(mem::replace(
+                &mut self.session,
+                SessionState { browsers: vec![] },
+            ));
+        }
+    }
+}
+
+/// A reply expected by a `SessionSaver`, which is owned by the route of its receiver.
+/// The route is dropped when the sender is, so a reply which never comes, such as that
+/// of an event loop which crashed, is counted as received with no value.
+struct PendingReply {
+    saver: Arc<Mutex<SessionSaver>>,
+    received: bool,
+}
+
+impl PendingReply {
+    fn receive(&mut self, store: impl FnOnce(&mut SessionState)) {
+        if self.received {
+            return;
+        }
+        self.received = true;
+        let mut saver = self.saver.lock().unwrap();
+        store(&mut saver.session);
+        saver.reply_received();
+    }
+}
+
+impl Drop for PendingReply {
+    fn drop(&mut self) {
+        if !self.received {
+            self.received = true;
+            if let Ok(mut saver) = self.saver.lock() {
+                saver.reply_received();
+            }
+        }
+    }
+}
+
+/// A receiver of the state of a browser, with where to store it in the session.
+enum Reply {
+    DocumentState(usize, usize, IpcReceiver<Option<SavedDocumentState>>),
+    SessionStorage(usize, IpcReceiver<SessionStorage>),
+}
+
+/// Route a receiver to the router thread, which stores its reply in the session
+/// with `store`.
+fn route_reply<T, F>(receiver: IpcReceiver<T>, saver: &Arc<Mutex<SessionSaver>>, store: F)
+where
+    T: for<'de> Deserialize<'de> + Serialize + Send + 'static,
+    F: Fn(&mut SessionState, T) + Send + 'static,
+{
+    let mut reply = PendingReply {
+        saver: saver.clone(),
+        received: false,
+    };
+    ROUTER.add_route(
+        receiver.to_opaque(),
+        Box::new(move |message| match message.to::<T>() {
+            Ok(value) => reply.receive(|session| store(session, value)),
+            Err(e) => warn!("Failed to receive the state of a saved session ({:?}).", e),
+        }),
+    );
+}
+
+/// Gather the session of the given browsers without waiting for the replies of their
+/// event loops, which are received on the router thread. `callback` is called with
+/// the session once every reply came, or once their senders were dropped.
+pub fn save_session<F>(browsers: Vec<PendingBrowserSession>, callback: F)
+where
+    F: FnOnce(SessionState) + Send + 'static,
+{
+    let mut replies = vec![];
+    let mut session = SessionState { browsers: vec![] };
+    for (browser_index, browser) in browsers.into_iter().enumerate() {
+        let mut entries = vec![];
+        for (entry_index, entry) in browser.entries.into_iter().enumerate() {
+            if let Some(receiver) = entry.document_state_receiver {
+                replies.push(Reply::DocumentState(browser_index, entry_index, receiver));
+            }
+            entries.push(SessionHistoryEntryState {
+                url: entry.url,
+                document_state: entry.document_state,
+            });
+        }
+        if let Some(receiver) = browser.session_storage_receiver {
+            replies.push(Reply::SessionStorage(browser_index, receiver));
+        }
+        session.browsers.push(BrowserSessionState {
+            entries,
+            current_index: browser.current_index,
+            session_storage: Default::default(),
+        });
+    }
+
+    let saver = Arc::new(Mutex::new(SessionSaver {
+        session,
+        pending: replies.len(),
+        callback: Some(Box::new(callback)),
+    }));
+    for reply in replies {
+        match reply {
+            Reply::DocumentState(browser_index, entry_index, receiver) => {
+                route_reply(receiver, &saver, move |session, state| {
+                    // A document which was unloaded in the meantime keeps the state
+                    // saved in the session history.
+                    if let Some(state) = state {
+                        let entry = &mut session.browsers[browser_index].entries[entry_index];
+                        entry.document_state = Some(state);
+                    }
+                })
+            },
+            Reply::SessionStorage(browser_index, receiver) => {
+                route_reply(receiver, &saver, move |session, storage| {
+                    session.browsers[browser_index].session_storage = storage;
+                })
+            },
+        }
+    }
+    saver.lock().unwrap().maybe_finish();
+}
@@ -87,6 +87,22 @@ impl JointSessionHistory {
         }
     }
 
+    /// Marks the same-document entries of a pipeline which crashed as discarded, so that
+    /// they reload its document with the given `LoadData` if ever traversed to, instead of
+    /// going to the pipeline which replaces it in its entry.
+    pub fn discard_same_document_entries(&mut self, pipeline_id: PipelineId, load_data: LoadData) {
+        let alive = NeedsToReload::No(pipeline_id);
+        let dead = NeedsToReload::Yes(pipeline_id, load_data);
+        for diff in self.past.iter_mut().chain(self.future.iter_mut()) {
+            match *diff {
+                SessionHistoryDiff::BrowsingContextDiff { .. } => {},
+                SessionHistoryDiff::PipelineDiff { .. } | SessionHistoryDiff::HashDiff { .. } => {
+                    diff.replace_reloader(&alive, &dead)
+                },
+            }
+        }
+    }
+
     pub fn remove_entries_for_browsing_context(&mut self, context_id: BrowsingContextId) {
         debug!("removing entries for context {}", context_id);
         self.past.retain(|diff| match diff {
@@ -0,0 +1,64 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use constellation::DiscardedDocument;
+use http::Method;
+use msg::constellation_msg::{PipelineId, PipelineNamespace, PipelineNamespaceId};
+use script_traits::{LoadData, LoadOrigin};
+use servo_url::ServoUrl;
+
+fn url(url: &str) -> ServoUrl {
+    ServoUrl::parse(url).unwrap()
+}
+
+#[test]
+fn test_discarded_document_is_loaded_again_at_its_current_url() {
+    PipelineNamespace::install(PipelineNamespaceId(1));
+    let placeholder = PipelineId::new();
+    let mut load_data = LoadData::new(
+        LoadOrigin::Constellation,
+        url("http://a.example/form"),
+        None,
+        None,
+        None,
+    );
+    load_data.method = Method::POST;
+
+    // The document moved to another URL with the History API since it was loaded.
+    let discarded = DiscardedDocument::new(placeholder, load_data, url("http://a.example/state"));
+
+    let load_data = discarded.load_data();
+    assert_eq!(load_data.url, url("http://a.example/state"));
+    assert_eq!(load_data.method, Method::POST);
+    assert!(load_data.was_discarded);
+}
+
+#[test]
+fn test_only_the_placeholder_stands_for_the_discarded_document() {
+    PipelineNamespace::install(PipelineNamespaceId(1));
+    let placeholder = PipelineId::new();
+    let other = PipelineId::new();
+    let load_data = LoadData::new(
+        LoadOrigin::Constellation,
+        url("http://a.example/"),
+        None,
+        None,
+        None,
+    );
+
+    let discarded = DiscardedDocument::new(placeholder, load_data, url("http://a.example/"));
+
+    assert_eq!(discarded.placeholder_pipeline_id(), placeholder);
+    assert!(discarded.is_placeholder(placeholder));
+    assert!(!discarded.is_placeholder(other));
+    assert!(discarded.into_load_data().was_discarded);
+}
+
+#[test]
+fn test_placeholder_is_a_blank_document_which_was_not_discarded() {
+    let load_data = DiscardedDocument::placeholder_load_data();
+
+    assert_eq!(load_data.url, url("about:blank"));
+    assert!(!load_data.was_discarded);
+}
@@ -0,0 +1,131 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use constellation::Prompts;
+use embedder_traits::{PermissionName, PermissionPrompt, PromptRequest, PromptResponse};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use msg::constellation_msg::{PipelineNamespace, PipelineNamespaceId, TopLevelBrowsingContextId};
+
+fn channel() -> (IpcSender<PromptResponse>, IpcReceiver<PromptResponse>) {
+    ipc::channel().unwrap()
+}
+
+#[test]
+fn test_prompts_of_a_browser_are_shown_one_at_a_time() {
+    PipelineNamespace::install(PipelineNamespaceId(1));
+    let browser = Some(TopLevelBrowsingContextId::new());
+    let other_browser = Some(TopLevelBrowsingContextId::new());
+    let mut prompts = Prompts::new();
+
+    let (first_sender, first_receiver) = channel();
+    let (second_sender, second_receiver) = channel();
+    let (other_sender, _other_receiver) = channel();
+    let (first_id, _) = prompts
+        .push(
+            browser,
+            PromptRequest::Alert("first".to_owned()),
+            first_sender,
+        )
+        .expect("The first prompt of a browser isn't shown");
+    assert!(prompts
+        .push(
+            browser,
+            PromptRequest::Alert("second".to_owned()),
+            second_sender
+        )
+        .is_none());
+    // The prompts of another browser have their own queue.
+    assert!(prompts
+        .push(other_browser, PromptRequest::BeforeUnload, other_sender)
+        .is_some());
+
+    let (responded_browser, next) = prompts.respond(first_id, PromptResponse::Accept).unwrap();
+    assert_eq!(responded_browser, browser);
+    assert_eq!(first_receiver.recv().unwrap(), PromptResponse::Accept);
+    let (second_id, second_request) = next.expect("The next prompt isn't shown");
+    assert_eq!(second_request, PromptRequest::Alert("second".to_owned()));
+    assert_eq!(prompts.current(browser).map(|(id, _)| id), Some(second_id));
+
+    // Only the prompt which is shown can be answered.
+    assert!(prompts.respond(first_id, PromptResponse::Dismiss).is_none());
+
+    let (_, next) = prompts.respond(second_id, PromptResponse::Dismiss).unwrap();
+    assert!(next.is_none());
+    assert_eq!(second_receiver.recv().unwrap(), PromptResponse::Dismiss);
+    assert!(prompts.current(browser).is_none());
+    assert!(prompts.current(other_browser).is_some());
+}
+
+#[test]
+fn test_identical_permission_prompts_are_coalesced() {
+    PipelineNamespace::install(PipelineNamespaceId(2));
+    let browser = Some(TopLevelBrowsingContextId::new());
+    let mut prompts = Prompts::new();
+    let geolocation =
+        PromptRequest::Permission(PermissionPrompt::Request(PermissionName::Geolocation));
+
+    let (alert_sender, _alert_receiver) = channel();
+    let (first_sender, first_receiver) = channel();
+    let (second_sender, second_receiver) = channel();
+    let (camera_sender, _camera_receiver) = channel();
+    let (alert_id, _) = prompts
+        .push(
+            browser,
+            PromptRequest::Alert("alert".to_owned()),
+            alert_sender,
+        )
+        .unwrap();
+    prompts.push(browser, geolocation.clone(), first_sender);
+    prompts.push(browser, geolocation.clone(), second_sender);
+    prompts.push(
+        browser,
+        PromptRequest::Permission(PermissionPrompt::Request(PermissionName::Camera)),
+        camera_sender,
+    );
+
+    let (permission_id, request) = prompts
+        .respond(alert_id, PromptResponse::Accept)
+        .unwrap()
+        .1
+        .unwrap();
+    assert_eq!(request, geolocation);
+    let (_, next) = prompts
+        .respond(permission_id, PromptResponse::Accept)
+        .unwrap();
+    // Both requests are answered by the same prompt.
+    assert_eq!(first_receiver.recv().unwrap(), PromptResponse::Accept);
+    assert_eq!(second_receiver.recv().unwrap(), PromptResponse::Accept);
+    let (_, request) = next.expect("The camera permission isn't asked for");
+    assert_eq!(
+        request,
+        PromptRequest::Permission(PermissionPrompt::Request(PermissionName::Camera))
+    );
+}
+
+#[test]
+fn test_prompts_of_a_closed_browser_are_dismissed() {
+    PipelineNamespace::install(PipelineNamespaceId(3));
+    let browser_id = TopLevelBrowsingContextId::new();
+    let mut prompts = Prompts::new();
+
+    let (shown_sender, shown_receiver) = channel();
+    let (queued_sender, queued_receiver) = channel();
+    let (shown_id, _) = prompts
+        .push(
+            Some(browser_id),
+            PromptRequest::Confirm("shown".to_owned()),
+            shown_sender,
+        )
+        .unwrap();
+    prompts.push(
+        Some(browser_id),
+        PromptRequest::Confirm("queued".to_owned()),
+        queued_sender,
+    );
+
+    assert_eq!(prompts.close(browser_id), Some(shown_id));
+    assert_eq!(shown_receiver.recv().unwrap(), PromptResponse::Dismiss);
+    assert_eq!(queued_receiver.recv().unwrap(), PromptResponse::Dismiss);
+    assert!(prompts.current(Some(browser_id)).is_none());
+}
@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use constellation::{save_session, PendingBrowserSession, PendingSessionHistoryEntry};
+use crossbeam_channel::{unbounded, Receiver};
+use embedder_traits::{SavedDocumentState, SessionState};
+use ipc_channel::ipc;
+use servo_url::ServoUrl;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+fn saved_state(scroll_y: f64) -> Option<SavedDocumentState> {
+    Some(SavedDocumentState {
+        scroll_x: 0.,
+        scroll_y,
+        form_controls: vec![],
+    })
+}
+
+fn entry(url: &str, document_state: Option<SavedDocumentState>) -> PendingSessionHistoryEntry {
+    PendingSessionHistoryEntry {
+        url: ServoUrl::parse(url).unwrap(),
+        document_state,
+        document_state_receiver: None,
+    }
+}
+
+fn save(browsers: Vec<PendingBrowserSession>) -> Receiver<SessionState> {
+    let (sender, receiver) = unbounded();
+    save_session(browsers, move |session| sender.send(session).unwrap());
+    receiver
+}
+
+#[test]
+fn test_save_session_without_replies() {
+    let session = save(vec![PendingBrowserSession {
+        entries: vec![entry("http://a.example/", saved_state(10.))],
+        current_index: 0,
+        session_storage_receiver: None,
+    }])
+    .try_recv()
+    .unwrap();
+
+    assert_eq!(session.browsers.len(), 1);
+    let state = session.browsers[0].entries[0].document_state.as_ref();
+    assert_eq!(state.unwrap().scroll_y, 10.);
+}
+
+#[test]
+fn test_save_session_waits_for_replies() {
+    let (document_sender, document_receiver) = ipc::channel().unwrap();
+    let (storage_sender, storage_receiver) = ipc::channel().unwrap();
+    let mut current = entry("http://b.example/", None);
+    current.document_state_receiver = Some(document_receiver);
+    let sessions = save(vec![PendingBrowserSession {
+        entries: vec![entry("http://a.example/", saved_state(10.)), current],
+        current_index: 1,
+        session_storage_receiver: Some(storage_receiver),
+    }]);
+
+    document_sender.send(saved_state(20.)).unwrap();
+    assert!(sessions.recv_timeout(Duration::from_millis(100)).is_err());
+
+    let mut storage = BTreeMap::new();
+    storage.insert("http://b.example".to_owned(), BTreeMap::new());
+    storage_sender.send(storage.clone()).unwrap();
+    let session = sessions.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    let browser = &session.browsers[0];
+    assert_eq!(browser.current_index, 1);
+    assert_eq!(browser.session_storage, storage);
+    let states: Vec<_> = browser
+        .entries
+        .iter()
+        .map(|entry| entry.document_state.as_ref().unwrap().scroll_y)
+        .collect();
+    assert_eq!(states, vec![10., 20.]);
+    assert!(sessions.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
+#[test]
+fn test_save_session_with_dropped_sender() {
+    let (document_sender, document_receiver) =
+        ipc::channel::<Option<SavedDocumentState>>().unwrap();
+    let mut current = entry("http://a.example/", saved_state(10.));
+    current.document_state_receiver = Some(document_receiver);
+    let sessions = save(vec![PendingBrowserSession {
+        entries: vec![current],
+        current_index: 0,
+        session_storage_receiver: None,
+    }]);
+
+    // The event loop of the document crashed, so the state of the session history is kept.
+    drop(document_sender);
+    let session = sessions.recv_timeout(Duration::from_secs(5)).unwrap();
+    let state = session.browsers[0].entries[0].document_state.as_ref();
+    assert_eq!(state.unwrap().scroll_y, 10.);
+}
@@ -0,0 +1,83 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use constellation::{JointSessionHistory, NeedsToReload, SessionHistoryDiff};
+use msg::constellation_msg::{
+    HistoryStateId, PipelineId, PipelineNamespace, PipelineNamespaceId, TEST_BROWSING_CONTEXT_ID,
+};
+use script_traits::{LoadData, LoadOrigin};
+use servo_url::ServoUrl;
+
+fn url(url: &str) -> ServoUrl {
+    ServoUrl::parse(url).unwrap()
+}
+
+fn pipeline_reloader(diff: &SessionHistoryDiff) -> &NeedsToReload {
+    match *diff {
+        SessionHistoryDiff::PipelineDiff {
+            ref pipeline_reloader,
+            ..
+        } |
+        SessionHistoryDiff::HashDiff {
+            ref pipeline_reloader,
+            ..
+        } => pipeline_reloader,
+        SessionHistoryDiff::BrowsingContextDiff { .. } => panic!("not a same-document diff"),
+    }
+}
+
+#[test]
+fn test_crash_only_replaces_the_entry_of_the_crashed_document() {
+    PipelineNamespace::install(PipelineNamespaceId(1));
+    let previous = PipelineId::new();
+    let crashed = PipelineId::new();
+    let failure = PipelineId::new();
+
+    let mut session_history = JointSessionHistory::new();
+    session_history.past = vec![
+        SessionHistoryDiff::BrowsingContextDiff {
+            browsing_context_id: TEST_BROWSING_CONTEXT_ID,
+            old_reloader: NeedsToReload::No(previous),
+            new_reloader: NeedsToReload::No(crashed),
+        },
+        SessionHistoryDiff::PipelineDiff {
+            pipeline_reloader: NeedsToReload::No(crashed),
+            old_history_state_id: None,
+            old_url: url("http://a.example/"),
+            new_history_state_id: HistoryStateId::new(),
+            new_url: url("http://a.example/state"),
+        },
+    ];
+    session_history.future = vec![SessionHistoryDiff::HashDiff {
+        pipeline_reloader: NeedsToReload::No(crashed),
+        old_url: url("http://a.example/state"),
+        new_url: url("http://a.example/state#hash"),
+    }];
+
+    let load_data = LoadData::new(
+        LoadOrigin::Constellation,
+        url("http://a.example/state"),
+        None,
+        None,
+        None,
+    );
+    session_history.discard_same_document_entries(crashed, load_data.clone());
+    // What the session history change of about:failure does.
+    session_history.replace_reloader(NeedsToReload::No(crashed), NeedsToReload::No(failure));
+
+    match session_history.past[0] {
+        SessionHistoryDiff::BrowsingContextDiff {
+            ref old_reloader,
+            ref new_reloader,
+            ..
+        } => {
+            assert_eq!(*old_reloader, NeedsToReload::No(previous));
+            assert_eq!(*new_reloader, NeedsToReload::No(failure));
+        },
+        _ => panic!("the browsing context diff was removed"),
+    }
+    let dead = NeedsToReload::Yes(crashed, load_data);
+    assert_eq!(*pipeline_reloader(&session_history.past[1]), dead);
+    assert_eq!(*pipeline_reloader(&session_history.future[0]), dead);
+}
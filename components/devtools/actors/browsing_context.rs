@@ -81,6 +81,7 @@ pub struct BrowsingContextActorMsg {
     actor: String,
     title: String,
     url: String,
+    isPrivate: bool,
     outerWindowID: u32,
     browsingContextId: u32,
     consoleActor: String,
@@ -110,6 +111,7 @@ pub struct BrowsingContextActor {
     pub name: String,
     pub title: String,
     pub url: String,
+    pub is_private: bool,
     pub console: String,
     pub emulation: String,
     pub inspector: String,
@@ -232,6 +234,7 @@ impl BrowsingContextActor {
             },
             title: self.title.clone(),
             url: self.url.clone(),
+            isPrivate: self.is_private,
             browsingContextId: 0, //FIXME should come from constellation
             outerWindowID: 0,     //FIXME: this should probably be the pipeline id
             consoleActor: self.console.clone(),
@@ -8,6 +8,7 @@
 //! inspection, JS evaluation, autocompletion) in Servo.
 
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::frame::encode_value;
 use crate::actors::object::ObjectActor;
 use crate::protocol::JsonPacketStream;
 use crate::{ConsoleAPICall, ConsoleMessage, ConsoleMsg, PageErrorMsg};
@@ -17,6 +18,7 @@ use devtools_traits::EvaluateJSReply::{NullValue, NumberValue, VoidValue};
 use devtools_traits::{
     CachedConsoleMessageTypes, ConsoleAPI, DevtoolScriptControlMsg, LogLevel, PageError,
 };
+use devtools_traits::{ConsoleArgument, DebuggerValue, ObjectPreview};
 use ipc_channel::ipc::{self, IpcSender};
 use msg::constellation_msg::PipelineId;
 use serde_json::{self, Map, Number, Value};
@@ -25,6 +27,88 @@ use std::net::TcpStream;
 use time::precise_time_ns;
 use uuid::Uuid;
 
+/// Convert an argument of a console API call to its protocol representation.
+fn encode_argument(registry: &ActorRegistry, argument: ConsoleArgument) -> Value {
+    let value = match argument {
+        ConsoleArgument::Undefined => DebuggerValue::Undefined,
+        ConsoleArgument::Null => DebuggerValue::Null,
+        ConsoleArgument::Boolean(val) => DebuggerValue::Boolean(val),
+        ConsoleArgument::Number(val) => DebuggerValue::Number(val),
+        ConsoleArgument::String(s) => DebuggerValue::String(s),
+        ConsoleArgument::Object(preview) => return encode_object(registry, preview),
+    };
+    encode_value(registry, value)
+}
+
+fn encode_object(registry: &ActorRegistry, preview: ObjectPreview) -> Value {
+    let ObjectPreview {
+        class,
+        name,
+        length,
+        items,
+        ownProperties,
+        ownPropertiesLength,
+    } = preview;
+
+    // The objects can't be inspected further than their preview yet.
+    let actor = registry.new_name("object");
+    registry.register_later(Box::new(ObjectActor {
+        name: actor.clone(),
+        uuid: String::new(),
+    }));
+
+    let mut m = Map::new();
+    m.insert("type".to_owned(), Value::String("object".to_owned()));
+    m.insert("class".to_owned(), Value::String(class));
+    m.insert("actor".to_owned(), Value::String(actor));
+    m.insert("extensible".to_owned(), Value::Bool(true));
+    m.insert("frozen".to_owned(), Value::Bool(false));
+    m.insert("sealed".to_owned(), Value::Bool(false));
+    m.insert(
+        "ownPropertyLength".to_owned(),
+        Value::from(ownPropertiesLength),
+    );
+    if let Some(name) = name {
+        m.insert("name".to_owned(), Value::String(name.clone()));
+        m.insert("displayName".to_owned(), Value::String(name));
+        return Value::Object(m);
+    }
+
+    let mut preview = Map::new();
+    if let Some(length) = length {
+        preview.insert("kind".to_owned(), Value::String("ArrayLike".to_owned()));
+        preview.insert("length".to_owned(), Value::from(length));
+        if let Some(items) = items {
+            let items = items
+                .into_iter()
+                .map(|item| encode_argument(registry, item))
+                .collect();
+            preview.insert("items".to_owned(), Value::Array(items));
+        }
+    } else {
+        let properties = ownProperties
+            .into_iter()
+            .map(|(name, value)| {
+                let mut property = Map::new();
+                property.insert("configurable".to_owned(), Value::Bool(true));
+                property.insert("enumerable".to_owned(), Value::Bool(true));
+                property.insert("writable".to_owned(), Value::Bool(true));
+                property.insert("value".to_owned(), encode_argument(registry, value));
+                (name, Value::Object(property))
+            })
+            .collect();
+        preview.insert("kind".to_owned(), Value::String("Object".to_owned()));
+        preview.insert("ownProperties".to_owned(), Value::Object(properties));
+        preview.insert(
+            "ownPropertiesLength".to_owned(),
+            Value::from(ownPropertiesLength),
+        );
+        preview.insert("safeGetterValues".to_owned(), Value::Object(Map::new()));
+    }
+    m.insert("preview".to_owned(), Value::Object(preview));
+    Value::Object(m)
+}
+
 trait EncodableConsoleMessage {
     fn encode(&self) -> serde_json::Result<String>;
 }
@@ -205,15 +289,40 @@ impl ConsoleActor {
         }
     }
 
-    pub(crate) fn handle_console_api(&self, console_message: ConsoleMessage) {
+    pub(crate) fn handle_console_api(
+        &self,
+        registry: &ActorRegistry,
+        console_message: ConsoleMessage,
+    ) {
         let level = match console_message.logLevel {
             LogLevel::Debug => "debug",
             LogLevel::Info => "info",
             LogLevel::Warn => "warn",
             LogLevel::Error => "error",
-            _ => "log",
+            LogLevel::Clear => "clear",
+            LogLevel::Trace => "trace",
+            LogLevel::Table => "table",
+            LogLevel::Dir => "dir",
+            LogLevel::Group => "group",
+            LogLevel::GroupCollapsed => "groupCollapsed",
+            LogLevel::GroupEnd => "groupEnd",
+            LogLevel::Log => "log",
         }
         .to_owned();
+        let arguments: Vec<Value> = if console_message.arguments.is_empty() {
+            vec![Value::String(console_message.message)]
+        } else {
+            console_message
+                .arguments
+                .into_iter()
+                .map(|argument| encode_argument(registry, argument))
+                .collect()
+        };
+        let function_name = console_message
+            .stacktrace
+            .as_ref()
+            .and_then(|stack| stack.first())
+            .map_or(String::new(), |frame| frame.functionName.clone());
         self.cached_events
             .borrow_mut()
             .push(CachedConsoleMessage::ConsoleAPI(ConsoleAPI {
@@ -221,10 +330,12 @@ impl ConsoleActor {
                 level: level.clone(),
                 filename: console_message.filename.clone(),
                 lineNumber: console_message.lineNumber as u32,
-                functionName: "".to_string(), //TODO
+                functionName: function_name,
                 timeStamp: precise_time_ns(),
                 private: false,
-                arguments: vec![console_message.message.clone()],
+                arguments: arguments.clone(),
+                columnNumber: console_message.columnNumber as u32,
+                stacktrace: console_message.stacktrace.clone(),
             }));
         let msg = ConsoleAPICall {
             from: self.name(),
@@ -232,10 +343,11 @@ impl ConsoleActor {
             message: ConsoleMsg {
                 level: level,
                 timeStamp: precise_time_ns(),
-                arguments: vec![console_message.message],
+                arguments: arguments,
                 filename: console_message.filename,
                 lineNumber: console_message.lineNumber,
                 columnNumber: console_message.columnNumber,
+                stacktrace: console_message.stacktrace,
             },
         };
         for stream in &mut *self.streams.borrow_mut() {
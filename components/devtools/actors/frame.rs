@@ -0,0 +1,244 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/frame.js).
+
+use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::object::ObjectActor;
+use crate::protocol::JsonPacketStream;
+use devtools_traits::DevtoolScriptControlMsg::GetEnvironment;
+use devtools_traits::{DebuggerValue, DevtoolScriptControlMsg, EnvironmentInfo, FrameInfo};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::net::TcpStream;
+
+/// Convert a value inspected by the debugger to its protocol representation.
+pub fn encode_value(registry: &ActorRegistry, value: DebuggerValue) -> Value {
+    let typed = |type_: &str| {
+        let mut m = Map::new();
+        m.insert("type".to_owned(), Value::String(type_.to_owned()));
+        Value::Object(m)
+    };
+    match value {
+        DebuggerValue::Undefined => typed("undefined"),
+        DebuggerValue::Null => typed("null"),
+        DebuggerValue::Boolean(val) => Value::Bool(val),
+        DebuggerValue::Number(val) => {
+            if val.is_nan() {
+                typed("NaN")
+            } else if val.is_infinite() {
+                typed(if val < 0. { "-Infinity" } else { "Infinity" })
+            } else if val == 0. && val.is_sign_negative() {
+                typed("-0")
+            } else {
+                Value::Number(Number::from_f64(val).unwrap())
+            }
+        },
+        DebuggerValue::String(s) => Value::String(s),
+        DebuggerValue::Object { class, name } => {
+            // The properties of the objects of a paused script can't be inspected yet,
+            // so every object gets a new actor.
+            let actor = registry.new_name("object");
+            registry.register_later(Box::new(ObjectActor {
+                name: actor.clone(),
+                uuid: String::new(),
+            }));
+
+            let mut m = Map::new();
+            m.insert("type".to_owned(), Value::String("object".to_owned()));
+            m.insert("class".to_owned(), Value::String(class));
+            m.insert("actor".to_owned(), Value::String(actor));
+            m.insert("extensible".to_owned(), Value::Bool(true));
+            m.insert("frozen".to_owned(), Value::Bool(false));
+            m.insert("sealed".to_owned(), Value::Bool(false));
+            if let Some(name) = name {
+                m.insert("name".to_owned(), Value::String(name));
+            }
+            Value::Object(m)
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct WhereMsg {
+    actor: String,
+    line: u32,
+    column: u32,
+}
+
+#[derive(Serialize)]
+pub struct FrameForm {
+    actor: String,
+    depth: u32,
+    #[serde(rename = "type")]
+    type_: String,
+    displayName: Option<String>,
+    this: Value,
+    arguments: Vec<Value>,
+    #[serde(rename = "where")]
+    where_: WhereMsg,
+    state: String,
+}
+
+#[derive(Serialize)]
+struct VariableMsg {
+    value: Value,
+}
+
+#[derive(Serialize)]
+struct BindingsMsg {
+    arguments: Vec<Value>,
+    variables: HashMap<String, VariableMsg>,
+}
+
+#[derive(Serialize)]
+struct FunctionMsg {
+    displayName: String,
+}
+
+#[derive(Serialize)]
+struct EnvironmentForm {
+    actor: String,
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function: Option<FunctionMsg>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bindings: Option<BindingsMsg>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    object: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<Box<EnvironmentForm>>,
+}
+
+#[derive(Serialize)]
+struct GetEnvironmentReply {
+    from: String,
+    #[serde(flatten)]
+    environment: EnvironmentForm,
+}
+
+/// A frame of the call stack of a paused script.
+pub struct FrameActor {
+    name: String,
+    frame: FrameInfo,
+    /// The actor of the source of the frame.
+    source: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+}
+
+impl FrameActor {
+    pub fn new(
+        name: String,
+        frame: FrameInfo,
+        source: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        pipeline: PipelineId,
+    ) -> FrameActor {
+        FrameActor {
+            name: name,
+            frame: frame,
+            source: source,
+            script_chan: script_chan,
+            pipeline: pipeline,
+        }
+    }
+
+    pub fn encodable(&self, registry: &ActorRegistry) -> FrameForm {
+        let frame = self.frame.clone();
+        FrameForm {
+            actor: self.name(),
+            depth: frame.id,
+            type_: frame.type_,
+            displayName: frame.displayName,
+            this: encode_value(registry, frame.this),
+            arguments: frame
+                .arguments
+                .into_iter()
+                .map(|argument| encode_value(registry, argument))
+                .collect(),
+            where_: WhereMsg {
+                actor: self.source.clone(),
+                line: frame.location.line,
+                column: frame.location.column,
+            },
+            state: "on-stack".to_owned(),
+        }
+    }
+}
+
+/// Nest the given scopes of a frame, from the innermost one.
+fn encode_environment(
+    registry: &ActorRegistry,
+    mut scopes: Vec<EnvironmentInfo>,
+) -> Option<EnvironmentForm> {
+    let mut parent = None;
+    while let Some(scope) = scopes.pop() {
+        let EnvironmentInfo {
+            type_,
+            functionName,
+            variables,
+            object,
+        } = scope;
+        let bindings = if object.is_none() {
+            Some(BindingsMsg {
+                arguments: vec![],
+                variables: variables
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = encode_value(registry, value);
+                        (name, VariableMsg { value: value })
+                    })
+                    .collect(),
+            })
+        } else {
+            None
+        };
+        parent = Some(EnvironmentForm {
+            actor: registry.new_name("environment"),
+            type_: type_,
+            function: functionName.map(|name| FunctionMsg { displayName: name }),
+            bindings: bindings,
+            object: object.map(|object| encode_value(registry, object)),
+            parent: parent.map(Box::new),
+        });
+    }
+    parent
+}
+
+impl Actor for FrameActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        registry: &ActorRegistry,
+        msg_type: &str,
+        _msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "getEnvironment" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(GetEnvironment(self.pipeline, self.frame.id, tx))
+                    .unwrap();
+                let scopes = rx.recv().map_err(|_| ())?;
+                let msg = GetEnvironmentReply {
+                    from: self.name(),
+                    environment: encode_environment(registry, scopes).ok_or(())?,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
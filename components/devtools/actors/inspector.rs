@@ -7,13 +7,18 @@
 
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
 use crate::protocol::JsonPacketStream;
+use devtools_traits::DevtoolScriptControlMsg::{GetAppliedStyles, GetComputedStyle};
 use devtools_traits::DevtoolScriptControlMsg::{GetChildren, GetDocumentElement, GetRootNode};
-use devtools_traits::DevtoolScriptControlMsg::{GetLayout, ModifyAttribute};
-use devtools_traits::{ComputedNodeLayout, DevtoolScriptControlMsg, NodeInfo};
+use devtools_traits::DevtoolScriptControlMsg::{GetLayout, ModifyAttribute, ModifyInlineStyle};
+use devtools_traits::DevtoolScriptControlMsg::{HighlightNode, SetNodePicking, WantsDomMutations};
+use devtools_traits::{AppliedStyles, ComputedNodeLayout, DevtoolScriptControlMsg, DomMutation};
+use devtools_traits::{NodeInfo, StyleModification};
 use ipc_channel::ipc::{self, IpcSender};
 use msg::constellation_msg::PipelineId;
 use serde_json::{self, Map, Value};
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
 use std::net::TcpStream;
 
 pub struct InspectorActor {
@@ -38,6 +43,8 @@ struct HighlighterMsg {
 
 struct HighlighterActor {
     name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
 }
 
 pub struct NodeActor {
@@ -56,6 +63,12 @@ struct HideBoxModelReply {
     from: String,
 }
 
+#[derive(Serialize)]
+struct ShowReply {
+    from: String,
+    value: bool,
+}
+
 impl Actor for HighlighterActor {
     fn name(&self) -> String {
         self.name.clone()
@@ -63,19 +76,41 @@ impl Actor for HighlighterActor {
 
     fn handle_message(
         &self,
-        _registry: &ActorRegistry,
+        registry: &ActorRegistry,
         msg_type: &str,
-        _msg: &Map<String, Value>,
+        msg: &Map<String, Value>,
         stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "showBoxModel" => {
+                let target = msg.get("node").unwrap().as_str().unwrap();
+                self.highlight(Some(registry.actor_to_script(target.to_owned())));
                 let msg = ShowBoxModelReply { from: self.name() };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             },
 
             "hideBoxModel" => {
+                self.highlight(None);
+                let msg = HideBoxModelReply { from: self.name() };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            // The messages of the highlighters returned by getHighlighterByType.
+            "show" => {
+                let target = msg.get("node").unwrap().as_str().unwrap();
+                self.highlight(Some(registry.actor_to_script(target.to_owned())));
+                let msg = ShowReply {
+                    from: self.name(),
+                    value: true,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "hide" => {
+                self.highlight(None);
                 let msg = HideBoxModelReply { from: self.name() };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
@@ -86,6 +121,14 @@ impl Actor for HighlighterActor {
     }
 }
 
+impl HighlighterActor {
+    fn highlight(&self, node: Option<String>) {
+        self.script_chan
+            .send(HighlightNode(self.pipeline, node))
+            .unwrap();
+    }
+}
+
 #[derive(Serialize)]
 struct ModifyAttributeReply {
     from: String,
@@ -247,10 +290,66 @@ impl NodeInfoToProtocol for NodeInfo {
     }
 }
 
-struct WalkerActor {
+pub struct WalkerActor {
     name: String,
     script_chan: IpcSender<DevtoolScriptControlMsg>,
     pipeline: PipelineId,
+    streams: RefCell<Vec<TcpStream>>,
+    /// The DOM mutations that haven't been requested by the client yet.
+    pending_mutations: RefCell<Vec<DomMutation>>,
+}
+
+#[derive(Serialize)]
+struct NewMutationsMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum MutationMsg {
+    Attributes {
+        #[serde(rename = "type")]
+        type_: String,
+        target: String,
+        attributeName: String,
+        attributeNamespace: String,
+        newValue: Option<String>,
+    },
+    CharacterData {
+        #[serde(rename = "type")]
+        type_: String,
+        target: String,
+        newValue: String,
+    },
+    ChildList {
+        #[serde(rename = "type")]
+        type_: String,
+        target: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+        numChildren: usize,
+    },
+}
+
+#[derive(Serialize)]
+struct GetMutationsReply {
+    from: String,
+    mutations: Vec<MutationMsg>,
+}
+
+#[derive(Serialize)]
+struct PickReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct PickerEventMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    node: NodeActorMsg,
 }
 
 #[derive(Serialize)]
@@ -313,6 +412,37 @@ impl Actor for WalkerActor {
                 ActorMessageStatus::Processed
             },
 
+            "getMutations" => {
+                let mutations = mem::replace(&mut *self.pending_mutations.borrow_mut(), vec![]);
+                let msg = GetMutationsReply {
+                    from: self.name(),
+                    mutations: mutations
+                        .into_iter()
+                        .filter_map(|mutation| encode_mutation(registry, mutation))
+                        .collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "pick" => {
+                self.script_chan
+                    .send(SetNodePicking(self.pipeline, true))
+                    .unwrap();
+                let msg = PickReply { from: self.name() };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "cancelPick" => {
+                self.script_chan
+                    .send(SetNodePicking(self.pipeline, false))
+                    .unwrap();
+                let msg = PickReply { from: self.name() };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
             "clearPseudoClassLocks" => {
                 let msg = ClearPseudoclassesReply { from: self.name() };
                 stream.write_json_packet(&msg);
@@ -351,6 +481,95 @@ impl Actor for WalkerActor {
     }
 }
 
+/// Translate the node ids of a mutation to actor names, dropping the nodes that the
+/// client doesn't know about.
+fn encode_mutation(registry: &ActorRegistry, mutation: DomMutation) -> Option<MutationMsg> {
+    let known_actors = |ids: Vec<String>| -> Vec<String> {
+        ids.into_iter()
+            .filter(|id| registry.script_actor_registered(id.clone()))
+            .map(|id| registry.script_to_actor(id))
+            .collect()
+    };
+    let target_actor = |target: String| -> Option<String> {
+        if registry.script_actor_registered(target.clone()) {
+            Some(registry.script_to_actor(target))
+        } else {
+            None
+        }
+    };
+    Some(match mutation {
+        DomMutation::Attributes {
+            target,
+            attributeName,
+            attributeNamespace,
+            newValue,
+        } => MutationMsg::Attributes {
+            type_: "attributes".to_owned(),
+            target: target_actor(target)?,
+            attributeName: attributeName,
+            attributeNamespace: attributeNamespace,
+            newValue: newValue,
+        },
+        DomMutation::CharacterData { target, newValue } => MutationMsg::CharacterData {
+            type_: "characterData".to_owned(),
+            target: target_actor(target)?,
+            newValue: newValue,
+        },
+        DomMutation::ChildList {
+            target,
+            added,
+            removed,
+            numChildren,
+        } => MutationMsg::ChildList {
+            type_: "childList".to_owned(),
+            target: target_actor(target)?,
+            added: known_actors(added),
+            removed: known_actors(removed),
+            numChildren: numChildren,
+        },
+    })
+}
+
+impl WalkerActor {
+    /// Queue a mutation of the DOM for the client, telling it if it has no
+    /// mutations to request yet.
+    pub(crate) fn handle_dom_mutation(&self, mutation: DomMutation) {
+        let mut pending_mutations = self.pending_mutations.borrow_mut();
+        if pending_mutations.is_empty() {
+            let msg = NewMutationsMsg {
+                from: self.name(),
+                type_: "newMutations".to_owned(),
+            };
+            for stream in &mut *self.streams.borrow_mut() {
+                stream.write_json_packet(&msg);
+            }
+        }
+        pending_mutations.push(mutation);
+    }
+
+    /// Tell the client that the node picker hovered or picked the given node.
+    pub(crate) fn handle_picker_event(
+        &self,
+        registry: &ActorRegistry,
+        picked: bool,
+        node: NodeInfo,
+    ) {
+        let type_ = if picked {
+            "picker-node-picked"
+        } else {
+            "picker-node-hovered"
+        };
+        let msg = PickerEventMsg {
+            from: self.name(),
+            type_: type_.to_owned(),
+            node: node.encode(registry, true, self.script_chan.clone(), self.pipeline),
+        };
+        for stream in &mut *self.streams.borrow_mut() {
+            stream.write_json_packet(&msg);
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct GetPageStyleReply {
     from: String,
@@ -378,10 +597,17 @@ struct GetAppliedReply {
 
 #[derive(Serialize)]
 struct GetComputedReply {
-    computed: Vec<u32>, //XXX all css props
+    computed: HashMap<String, ComputedProperty>,
     from: String,
 }
 
+#[derive(Serialize)]
+struct ComputedProperty {
+    value: String,
+    priority: String,
+    matched: bool,
+}
+
 #[derive(Serialize)]
 struct AppliedEntry {
     rule: String,
@@ -394,12 +620,117 @@ struct AppliedEntry {
 struct AppliedRule {
     actor: String,
     #[serde(rename = "type")]
-    type_: String,
-    href: String,
+    type_: u16,
+    href: Option<String>,
     cssText: String,
+    authoredText: String,
+    selectors: Vec<String>,
     line: u32,
     column: u32,
-    parentStyleSheet: String,
+    parentStyleSheet: Option<String>,
+}
+
+/// The `type` of rules of style sheets, as in `CSSRule.STYLE_RULE`.
+const STYLE_RULE: u16 = 1;
+/// The `type` of the rules of inline styles, as in `CSSRule.ELEMENT_STYLE`.
+const ELEMENT_STYLE: u16 = 100;
+
+/// A rule applying to a node, that can edit the inline style of the node.
+struct StyleRuleActor {
+    name: String,
+    /// The node whose inline style this rule is, if it is one.
+    node: Option<String>,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+}
+
+#[derive(Serialize)]
+struct ModifyPropertiesReply {
+    from: String,
+    rule: AppliedRule,
+}
+
+impl Actor for StyleRuleActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        _registry: &ActorRegistry,
+        msg_type: &str,
+        msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "modifyProperties" => {
+                // Only inline styles can be edited.
+                let node = match self.node {
+                    Some(ref node) => node.clone(),
+                    None => return Ok(ActorMessageStatus::Ignored),
+                };
+                let mods = msg.get("modifications").unwrap().as_array().unwrap();
+                let modifications = mods
+                    .iter()
+                    .filter_map(|json_mod| {
+                        let name = json_mod.get("name")?.as_str()?.to_owned();
+                        let value = match json_mod.get("type")?.as_str()? {
+                            "set" => Some(json_mod.get("value")?.as_str()?.to_owned()),
+                            "remove" => None,
+                            _ => return None,
+                        };
+                        let priority = json_mod
+                            .get("priority")
+                            .and_then(Value::as_str)
+                            .unwrap_or("")
+                            .to_owned();
+                        Some(StyleModification {
+                            name: name,
+                            value: value,
+                            priority: priority,
+                        })
+                    })
+                    .collect();
+                self.script_chan
+                    .send(ModifyInlineStyle(
+                        self.pipeline,
+                        node.clone(),
+                        modifications,
+                    ))
+                    .unwrap();
+
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(GetAppliedStyles(self.pipeline, node, tx))
+                    .unwrap();
+                let applied = rx.recv().unwrap().ok_or(())?;
+                let msg = ModifyPropertiesReply {
+                    from: self.name(),
+                    rule: self.element_style_rule(applied.inlineStyle),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+impl StyleRuleActor {
+    fn element_style_rule(&self, css_text: String) -> AppliedRule {
+        AppliedRule {
+            actor: self.name(),
+            type_: ELEMENT_STYLE,
+            href: None,
+            authoredText: css_text.clone(),
+            cssText: css_text,
+            selectors: vec![],
+            line: 0,
+            column: 0,
+            parentStyleSheet: None,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -473,10 +804,63 @@ impl Actor for PageStyleActor {
     ) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "getApplied" => {
-                //TODO: query script for relevant applied styles to node (msg.node)
+                let target = msg.get("node").unwrap().as_str().unwrap();
+                let node = registry.actor_to_script(target.to_owned());
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(GetAppliedStyles(self.pipeline, node.clone(), tx))
+                    .unwrap();
+                let AppliedStyles {
+                    inlineStyle,
+                    matchedRules,
+                } = rx.recv().unwrap().ok_or(())?;
+
+                let inline_rule = StyleRuleActor {
+                    name: registry.new_name("domstylerule"),
+                    node: Some(node),
+                    script_chan: self.script_chan.clone(),
+                    pipeline: self.pipeline,
+                };
+                let mut entries = vec![AppliedEntry {
+                    rule: inline_rule.name(),
+                    pseudoElement: Value::Null,
+                    isSystem: false,
+                    matchedSelectors: vec![],
+                }];
+                let mut rules = vec![inline_rule.element_style_rule(inlineStyle)];
+                registry.register_later(Box::new(inline_rule));
+
+                // The rules are listed from the most to the least specific.
+                for matched_rule in matchedRules.into_iter().rev() {
+                    let rule = StyleRuleActor {
+                        name: registry.new_name("domstylerule"),
+                        node: None,
+                        script_chan: self.script_chan.clone(),
+                        pipeline: self.pipeline,
+                    };
+                    entries.push(AppliedEntry {
+                        rule: rule.name(),
+                        pseudoElement: Value::Null,
+                        isSystem: false,
+                        matchedSelectors: vec![matched_rule.selectorText.clone()],
+                    });
+                    rules.push(AppliedRule {
+                        actor: rule.name(),
+                        type_: STYLE_RULE,
+                        href: matched_rule.href,
+                        authoredText: matched_rule.cssText.clone(),
+                        cssText: matched_rule.cssText,
+                        selectors: vec![matched_rule.selectorText],
+                        line: 0,
+                        column: 0,
+                        parentStyleSheet: None,
+                    });
+                    registry.register_later(Box::new(rule));
+                }
+
                 let msg = GetAppliedReply {
-                    entries: vec![],
-                    rules: vec![],
+                    entries: entries,
+                    rules: rules,
                     sheets: vec![],
                     from: self.name(),
                 };
@@ -485,9 +869,28 @@ impl Actor for PageStyleActor {
             },
 
             "getComputed" => {
-                //TODO: query script for relevant computed styles on node (msg.node)
+                let target = msg.get("node").unwrap().as_str().unwrap();
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(GetComputedStyle(
+                        self.pipeline,
+                        registry.actor_to_script(target.to_owned()),
+                        tx,
+                    ))
+                    .unwrap();
+                let properties = rx.recv().unwrap().ok_or(())?;
                 let msg = GetComputedReply {
-                    computed: vec![],
+                    computed: properties
+                        .into_iter()
+                        .map(|(name, value)| {
+                            let property = ComputedProperty {
+                                value: value,
+                                priority: String::new(),
+                                matched: false,
+                            };
+                            (name, property)
+                        })
+                        .collect(),
                     from: self.name(),
                 };
                 stream.write_json_packet(&msg);
@@ -603,10 +1006,15 @@ impl Actor for InspectorActor {
                         name: registry.new_name("walker"),
                         script_chan: self.script_chan.clone(),
                         pipeline: self.pipeline,
+                        streams: RefCell::new(vec![stream.try_clone().unwrap()]),
+                        pending_mutations: RefCell::new(vec![]),
                     };
                     let mut walker_name = self.walker.borrow_mut();
                     *walker_name = Some(walker.name());
                     registry.register_later(Box::new(walker));
+                    self.script_chan
+                        .send(WantsDomMutations(self.pipeline, true))
+                        .unwrap();
                 }
 
                 let (tx, rx) = ipc::channel().unwrap();
@@ -654,10 +1062,12 @@ impl Actor for InspectorActor {
             //TODO: this is an old message; try adding highlightable to the root traits instead
             //      and support getHighlighter instead
             //"highlight" => {}
-            "getHighlighter" => {
+            "getHighlighter" | "getHighlighterByType" => {
                 if self.highlighter.borrow().is_none() {
                     let highlighter_actor = HighlighterActor {
                         name: registry.new_name("highlighter"),
+                        script_chan: self.script_chan.clone(),
+                        pipeline: self.pipeline,
                     };
                     let mut highlighter = self.highlighter.borrow_mut();
                     *highlighter = Some(highlighter_actor.name());
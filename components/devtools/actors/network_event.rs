@@ -32,6 +32,9 @@ struct HttpResponse {
     headers: Option<HeaderMap>,
     status: Option<(StatusCode, String)>,
     body: Option<Vec<u8>>,
+    body_size: usize,
+    receive_time: u64,
+    from_cache: bool,
 }
 
 pub struct NetworkEventActor {
@@ -66,13 +69,14 @@ pub struct ResponseStartMsg {
     pub statusText: String,
     pub headersSize: usize,
     pub discardResponseBody: bool,
+    pub fromCache: bool,
 }
 
 #[derive(Serialize)]
 pub struct ResponseContentMsg {
     pub mimeType: String,
-    pub contentSize: u32,
-    pub transferredSize: u32,
+    pub contentSize: usize,
+    pub transferredSize: usize,
     pub discardResponseBody: bool,
 }
 
@@ -115,10 +119,18 @@ struct GetResponseHeadersReply {
     rawHeaders: String,
 }
 
+#[derive(Serialize)]
+struct ResponseContent {
+    mimeType: String,
+    text: String,
+    size: usize,
+    encoding: Option<String>,
+}
+
 #[derive(Serialize)]
 struct GetResponseContentReply {
     from: String,
-    content: Option<Vec<u8>>,
+    content: Option<ResponseContent>,
     contentDiscarded: bool,
 }
 
@@ -148,7 +160,7 @@ struct Timings {
     connect: u64,
     send: u64,
     wait: u32,
-    receive: u32,
+    receive: u64,
 }
 
 #[derive(Serialize)]
@@ -272,30 +284,41 @@ impl Actor for NetworkEventActor {
                 ActorMessageStatus::Processed
             },
             "getResponseContent" => {
+                let content = self.response.body.as_ref().map(|body| {
+                    // Bodies that aren't valid UTF-8 are sent base64 encoded.
+                    let (text, encoding) = match String::from_utf8(body.clone()) {
+                        Ok(text) => (text, None),
+                        Err(_) => (base64::encode(body), Some("base64".to_owned())),
+                    };
+                    ResponseContent {
+                        mimeType: self.mime_type(),
+                        text: text,
+                        size: self.response.body_size,
+                        encoding: encoding,
+                    }
+                });
                 let msg = GetResponseContentReply {
                     from: self.name(),
-                    content: self.response.body.clone(),
+                    content: content,
                     contentDiscarded: self.response.body.is_none(),
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             },
             "getEventTimings" => {
-                // TODO: This is a fake timings msg
+                // TODO: Send the correct values for blocked, dns and wait.
                 let timingsObj = Timings {
                     blocked: 0,
                     dns: 0,
                     connect: self.request.connect_time,
                     send: self.request.send_time,
                     wait: 0,
-                    receive: 0,
+                    receive: self.response.receive_time,
                 };
-                let total = timingsObj.connect + timingsObj.send;
-                // TODO: Send the correct values for all these fields.
                 let msg = GetEventTimingsReply {
                     from: self.name(),
                     timings: timingsObj,
-                    totalTime: total,
+                    totalTime: self.total_time(),
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
@@ -334,6 +357,9 @@ impl NetworkEventActor {
                 headers: None,
                 status: None,
                 body: None,
+                body_size: 0,
+                receive_time: 0,
+                from_cache: false,
             },
             is_xhr: false,
         }
@@ -358,6 +384,9 @@ impl NetworkEventActor {
             (StatusCode::from_u16(s).unwrap(), status_text)
         });
         self.response.body = response.body;
+        self.response.body_size = response.body_size;
+        self.response.receive_time = response.receive_time;
+        self.response.from_cache = response.from_cache;
     }
 
     pub fn event_actor(&self) -> EventActor {
@@ -392,24 +421,31 @@ impl NetworkEventActor {
             status: status_code.to_string(),
             statusText: status_message,
             headersSize: hSize,
-            discardResponseBody: false,
+            discardResponseBody: self.response.body.is_none(),
+            fromCache: self.response.from_cache,
         }
     }
 
+    fn mime_type(&self) -> String {
+        self.response
+            .headers
+            .as_ref()
+            .and_then(|headers| headers.typed_get::<ContentType>())
+            .map_or("".to_owned(), |ct| ct.to_string())
+    }
+
     pub fn response_content(&self) -> ResponseContentMsg {
-        let mut mString = "".to_owned();
-        if let Some(ref headers) = self.response.headers {
-            mString = match headers.typed_get::<ContentType>() {
-                Some(ct) => ct.to_string(),
-                _ => "".to_owned(),
-            };
-        }
-        // TODO: Set correct values when response's body is sent to the devtools in http_loader.
+        // Nothing goes over the network for responses served from the cache.
+        let transferred_size = if self.response.from_cache {
+            0
+        } else {
+            self.response_headers().headersSize + self.response.body_size
+        };
         ResponseContentMsg {
-            mimeType: mString,
-            contentSize: 0,
-            transferredSize: 0,
-            discardResponseBody: true,
+            mimeType: self.mime_type(),
+            contentSize: self.response.body_size,
+            transferredSize: transferred_size,
+            discardResponseBody: self.response.body.is_none(),
         }
     }
 
@@ -462,6 +498,6 @@ impl NetworkEventActor {
     }
 
     pub fn total_time(&self) -> u64 {
-        self.request.connect_time + self.request.send_time
+        self.request.connect_time + self.request.send_time + self.response.receive_time
     }
 }
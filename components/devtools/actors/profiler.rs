@@ -2,12 +2,140 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/perf.js).
+//!
+//! The profiles are those of the sampling profiler, in the format of the Gecko profiler.
+
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::protocol::JsonPacketStream;
+use devtools_traits::DevtoolScriptControlMsg;
+use devtools_traits::DevtoolScriptControlMsg::{StartProfiler, StopProfiler};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
 use serde_json::{Map, Value};
+use std::cell::Cell;
+use std::mem;
 use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// The sampling interval, in milliseconds, when the client doesn't give one.
+const DEFAULT_INTERVAL: u64 = 10;
+
+/// How long the samples are kept, in milliseconds, after which the oldest ones are
+/// discarded.
+const MAX_DURATION: u64 = 60_000;
+
+#[derive(Serialize)]
+struct StartedReply {
+    from: String,
+    started: bool,
+}
+
+#[derive(Serialize)]
+struct StoppedReply {
+    from: String,
+    stopped: bool,
+}
+
+#[derive(Serialize)]
+struct IsActiveReply {
+    from: String,
+    isActive: bool,
+    currentTime: f64,
+}
+
+#[derive(Serialize)]
+struct GetProfileReply {
+    from: String,
+    profile: Value,
+    currentTime: f64,
+}
+
+/// The profile of the last run of the profiler.
+enum Profile {
+    None,
+    /// The profiler was stopped, and the profile is still being gathered. The clients
+    /// which asked for it, with the time at which they did, are replied to once it is.
+    Pending(Vec<(TcpStream, f64)>),
+    Received(Value),
+}
 
 pub struct ProfilerActor {
     name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    /// When the profiler was started, if it is running.
+    started: Cell<Option<Instant>>,
+    profile: Arc<Mutex<Profile>>,
+}
+
+impl ProfilerActor {
+    pub fn new(
+        name: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        pipeline: PipelineId,
+    ) -> ProfilerActor {
+        ProfilerActor {
+            name: name,
+            script_chan: script_chan,
+            pipeline: pipeline,
+            started: Cell::new(None),
+            profile: Arc::new(Mutex::new(Profile::None)),
+        }
+    }
+
+    /// The time since the profiler was started, in milliseconds.
+    fn current_time(&self) -> f64 {
+        self.started.get().map_or(0., |started| {
+            let elapsed = started.elapsed();
+            elapsed.as_secs() as f64 * 1000. + elapsed.subsec_nanos() as f64 / 1000000.
+        })
+    }
+
+    /// Stop the profiler if it is running. Gathering the profile can take a while, so
+    /// it is received on another thread, which replies to the clients waiting for it.
+    fn stop(&self) {
+        if self.started.take().is_none() {
+            return;
+        }
+        let (tx, rx) = ipc::channel().unwrap();
+        self.script_chan
+            .send(StopProfiler(self.pipeline, tx))
+            .unwrap();
+        *self.profile.lock().unwrap() = Profile::Pending(vec![]);
+
+        let name = self.name();
+        let profile = self.profile.clone();
+        thread::Builder::new()
+            .name("DevtoolsProfile".to_owned())
+            .spawn(move || {
+                // The profile is null if the profiler couldn't send one.
+                let value = rx
+                    .recv()
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                    .unwrap_or(Value::Null);
+                let waiting = match mem::replace(
+                    &mut *profile.lock().unwrap(),
+                    Profile::Received(value.clone()),
+                ) {
+                    Profile::Pending(waiting) => waiting,
+                    _ => vec![],
+                };
+                for (mut stream, current_time) in waiting {
+                    let msg = GetProfileReply {
+                        from: name.clone(),
+                        profile: value.clone(),
+                        currentTime: current_time,
+                    };
+                    stream.write_json_packet(&msg);
+                }
+            })
+            .expect("Thread spawning failed");
+    }
 }
 
 impl Actor for ProfilerActor {
@@ -18,16 +146,74 @@ impl Actor for ProfilerActor {
     fn handle_message(
         &self,
         _registry: &ActorRegistry,
-        _msg_type: &str,
-        _msg: &Map<String, Value>,
-        _stream: &mut TcpStream,
+        msg_type: &str,
+        msg: &Map<String, Value>,
+        stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
-        Ok(ActorMessageStatus::Ignored)
-    }
-}
+        Ok(match msg_type {
+            "startProfiler" => {
+                if self.started.get().is_none() {
+                    let interval = msg
+                        .get("options")
+                        .and_then(|options| options.get("interval"))
+                        .or(msg.get("interval"))
+                        .and_then(|interval| interval.as_f64())
+                        .map_or(DEFAULT_INTERVAL, |interval| interval.max(1.) as u64);
+                    self.script_chan
+                        .send(StartProfiler(self.pipeline, interval, MAX_DURATION))
+                        .unwrap();
+                    self.started.set(Some(Instant::now()));
+                }
+                let msg = StartedReply {
+                    from: self.name(),
+                    started: true,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
 
-impl ProfilerActor {
-    pub fn new(name: String) -> ProfilerActor {
-        ProfilerActor { name: name }
+            "stopProfiler" => {
+                self.stop();
+                let msg = StoppedReply {
+                    from: self.name(),
+                    stopped: true,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "isActive" => {
+                let msg = IsActiveReply {
+                    from: self.name(),
+                    isActive: self.started.get().is_some(),
+                    currentTime: self.current_time(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            // The samples can't be read while the profiler runs, so it is stopped first.
+            "getProfile" => {
+                let current_time = self.current_time();
+                self.stop();
+                let profile = match *self.profile.lock().unwrap() {
+                    Profile::Pending(ref mut waiting) => {
+                        waiting.push((stream.try_clone().unwrap(), current_time));
+                        return Ok(ActorMessageStatus::Processed);
+                    },
+                    Profile::Received(ref profile) => profile.clone(),
+                    Profile::None => Value::Null,
+                };
+                let msg = GetProfileReply {
+                    from: self.name(),
+                    profile: profile,
+                    currentTime: current_time,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
     }
 }
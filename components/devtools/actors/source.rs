@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/source.js).
+
+use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::protocol::JsonPacketStream;
+use devtools_traits::DevtoolScriptControlMsg::{GetBreakpointPositions, GetSourceText};
+use devtools_traits::{DevtoolScriptControlMsg, SourceInfo};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::net::TcpStream;
+
+#[derive(Clone, Serialize)]
+pub struct SourceForm {
+    pub actor: String,
+    pub url: String,
+    isBlackBoxed: bool,
+    /// The client resolves the source map, relatively to the URL of the source.
+    sourceMapURL: Option<String>,
+    sourceMapBaseURL: String,
+    introductionType: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SourceReply {
+    from: String,
+    source: String,
+    contentType: String,
+}
+
+#[derive(Serialize)]
+struct BreakableLinesReply {
+    from: String,
+    lines: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct BreakpointPositionsReply {
+    from: String,
+    positions: BTreeMap<u32, Vec<u32>>,
+}
+
+pub struct SourceActor {
+    name: String,
+    /// The id of the source in the debugger of the script thread.
+    pub source_id: u32,
+    url: String,
+    source_map_url: Option<String>,
+    introduction_type: Option<String>,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+}
+
+impl SourceActor {
+    pub fn new(
+        name: String,
+        source: SourceInfo,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        pipeline: PipelineId,
+    ) -> SourceActor {
+        SourceActor {
+            name: name,
+            source_id: source.id,
+            url: source.url,
+            source_map_url: source.sourceMapURL,
+            introduction_type: source.introductionType,
+            script_chan: script_chan,
+            pipeline: pipeline,
+        }
+    }
+
+    pub fn encodable(&self) -> SourceForm {
+        SourceForm {
+            actor: self.name(),
+            url: self.url.clone(),
+            isBlackBoxed: false,
+            sourceMapURL: self.source_map_url.clone(),
+            sourceMapBaseURL: self.url.clone(),
+            introductionType: self.introduction_type.clone(),
+        }
+    }
+
+    fn breakpoint_positions(&self) -> Result<Vec<(u32, u32)>, ()> {
+        let (tx, rx) = ipc::channel().unwrap();
+        self.script_chan
+            .send(GetBreakpointPositions(self.pipeline, self.source_id, tx))
+            .unwrap();
+        rx.recv().map_err(|_| ())
+    }
+}
+
+impl Actor for SourceActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        _registry: &ActorRegistry,
+        msg_type: &str,
+        _msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "source" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(GetSourceText(self.pipeline, self.source_id, tx))
+                    .unwrap();
+                let msg = SourceReply {
+                    from: self.name(),
+                    source: rx.recv().map_err(|_| ())?.unwrap_or_default(),
+                    contentType: "text/javascript".to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "getBreakableLines" => {
+                let mut lines: Vec<u32> = self
+                    .breakpoint_positions()?
+                    .into_iter()
+                    .map(|(line, _)| line)
+                    .collect();
+                lines.sort();
+                lines.dedup();
+                let msg = BreakableLinesReply {
+                    from: self.name(),
+                    lines: lines,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "getBreakpointPositionsCompressed" => {
+                let mut positions = BTreeMap::new();
+                for (line, column) in self.breakpoint_positions()? {
+                    positions.entry(line).or_insert(vec![]).push(column);
+                }
+                let msg = BreakpointPositionsReply {
+                    from: self.name(),
+                    positions: positions,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
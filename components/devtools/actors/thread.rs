@@ -3,8 +3,18 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::frame::{FrameActor, FrameForm};
+use crate::actors::source::{SourceActor, SourceForm};
 use crate::protocol::JsonPacketStream;
+use devtools_traits::DevtoolScriptControlMsg::{self, AttachDebugger, DetachDebugger};
+use devtools_traits::DevtoolScriptControlMsg::{GetFrames, Interrupt, Resume};
+use devtools_traits::DevtoolScriptControlMsg::{RemoveBreakpoint, SetBreakpoint};
+use devtools_traits::{FrameInfo, PauseInfo, ResumeLimit, SourceInfo, SourceLocation};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
 use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::net::TcpStream;
 
 #[derive(Serialize)]
@@ -30,6 +40,30 @@ struct WhyMsg {
     type_: String,
 }
 
+#[derive(Serialize)]
+struct ThreadPausedMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    actor: String,
+    frame: FrameForm,
+    why: WhyMsg,
+}
+
+#[derive(Serialize)]
+struct NewSourceMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    source: SourceForm,
+}
+
+#[derive(Serialize)]
+struct FramesReply {
+    from: String,
+    frames: Vec<FrameForm>,
+}
+
 #[derive(Serialize)]
 struct ThreadResumedReply {
     from: String,
@@ -52,12 +86,9 @@ struct ReconfigureReply {
 #[derive(Serialize)]
 struct SourcesReply {
     from: String,
-    sources: Vec<Source>,
+    sources: Vec<SourceForm>,
 }
 
-#[derive(Serialize)]
-enum Source {}
-
 #[derive(Serialize)]
 struct VoidAttachedReply {
     from: String,
@@ -65,11 +96,115 @@ struct VoidAttachedReply {
 
 pub struct ThreadActor {
     name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    streams: RefCell<Vec<TcpStream>>,
+    /// The sources of the debugger, by their id in the script thread.
+    sources: RefCell<HashMap<u32, SourceForm>>,
+    /// The actors of the frames of the current pause.
+    frames: RefCell<Vec<String>>,
 }
 
 impl ThreadActor {
-    pub fn new(name: String) -> ThreadActor {
-        ThreadActor { name: name }
+    pub fn new(
+        name: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        pipeline: PipelineId,
+    ) -> ThreadActor {
+        ThreadActor {
+            name: name,
+            script_chan: script_chan,
+            pipeline: pipeline,
+            streams: RefCell::new(vec![]),
+            sources: RefCell::new(HashMap::new()),
+            frames: RefCell::new(vec![]),
+        }
+    }
+
+    fn add_source(&self, registry: &ActorRegistry, source: SourceInfo) -> Option<SourceForm> {
+        if self.sources.borrow().contains_key(&source.id) {
+            return None;
+        }
+        let source_id = source.id;
+        let actor = SourceActor::new(
+            registry.new_name("source"),
+            source,
+            self.script_chan.clone(),
+            self.pipeline,
+        );
+        let form = actor.encodable();
+        self.sources.borrow_mut().insert(source_id, form.clone());
+        registry.register_later(Box::new(actor));
+        Some(form)
+    }
+
+    /// Tell the client about a source that was loaded after it attached.
+    pub(crate) fn handle_new_source(&self, registry: &ActorRegistry, source: SourceInfo) {
+        if let Some(form) = self.add_source(registry, source) {
+            let msg = NewSourceMsg {
+                from: self.name(),
+                type_: "newSource".to_owned(),
+                source: form,
+            };
+            for stream in &mut *self.streams.borrow_mut() {
+                stream.write_json_packet(&msg);
+            }
+        }
+    }
+
+    /// Tell the client that the debugger paused.
+    pub(crate) fn handle_paused(&self, registry: &ActorRegistry, pause: PauseInfo) {
+        self.clear_frames(registry);
+        let frame = self.frame_actor(registry, pause.frame);
+        let msg = ThreadPausedMsg {
+            from: self.name(),
+            type_: "paused".to_owned(),
+            actor: registry.new_name("pause"),
+            frame: frame.encodable(registry),
+            why: WhyMsg { type_: pause.why },
+        };
+        registry.register_later(Box::new(frame));
+        for stream in &mut *self.streams.borrow_mut() {
+            stream.write_json_packet(&msg);
+        }
+    }
+
+    fn frame_actor(&self, registry: &ActorRegistry, frame: FrameInfo) -> FrameActor {
+        let source = self
+            .sources
+            .borrow()
+            .get(&frame.location.sourceId)
+            .map_or(String::new(), |source| source.actor.clone());
+        let name = registry.new_name("frame");
+        self.frames.borrow_mut().push(name.clone());
+        FrameActor::new(name, frame, source, self.script_chan.clone(), self.pipeline)
+    }
+
+    /// Drop the actors of the frames of the previous pause.
+    fn clear_frames(&self, registry: &ActorRegistry) {
+        for name in self.frames.borrow_mut().drain(..) {
+            registry.drop_actor_later(name);
+        }
+    }
+
+    /// Find the location of a breakpoint, whose source is given either by its actor
+    /// or by its URL.
+    fn breakpoint_location(&self, msg: &Map<String, Value>) -> Option<SourceLocation> {
+        let location = msg.get("location")?.as_object()?;
+        let sources = self.sources.borrow();
+        let source_id = match location.get("sourceId").and_then(Value::as_str) {
+            Some(actor) => sources.iter().find(|(_, form)| form.actor == actor),
+            None => {
+                let url = location.get("sourceUrl")?.as_str()?;
+                sources.iter().find(|(_, form)| form.url == url)
+            },
+        }?
+        .0;
+        Some(SourceLocation {
+            sourceId: *source_id,
+            line: location.get("line")?.as_u64()? as u32,
+            column: location.get("column").and_then(Value::as_u64).unwrap_or(0) as u32,
+        })
     }
 }
 
@@ -82,11 +217,21 @@ impl Actor for ThreadActor {
         &self,
         registry: &ActorRegistry,
         msg_type: &str,
-        _msg: &Map<String, Value>,
+        msg: &Map<String, Value>,
         stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "attach" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(AttachDebugger(self.pipeline, tx))
+                    .unwrap();
+                // Workers don't have a debugger, and drop the sender.
+                for source in rx.recv().unwrap_or(vec![]) {
+                    self.add_source(registry, source);
+                }
+                self.streams.borrow_mut().push(stream.try_clone().unwrap());
+
                 let msg = ThreadAttached {
                     from: self.name(),
                     type_: "paused".to_owned(),
@@ -105,7 +250,29 @@ impl Actor for ThreadActor {
                 ActorMessageStatus::Processed
             },
 
+            "detach" => {
+                self.script_chan
+                    .send(DetachDebugger(self.pipeline))
+                    .unwrap();
+                self.clear_frames(registry);
+                self.streams.borrow_mut().clear();
+                stream.write_json_packet(&VoidAttachedReply { from: self.name() });
+                ActorMessageStatus::Processed
+            },
+
             "resume" => {
+                let limit = msg
+                    .get("resumeLimit")
+                    .and_then(|limit| limit.get("type"))
+                    .and_then(Value::as_str)
+                    .and_then(|type_| match type_ {
+                        "next" => Some(ResumeLimit::Next),
+                        "step" => Some(ResumeLimit::Step),
+                        "finish" => Some(ResumeLimit::Finish),
+                        _ => None,
+                    });
+                self.clear_frames(registry);
+                self.script_chan.send(Resume(self.pipeline, limit)).unwrap();
                 let msg = ThreadResumedReply {
                     from: self.name(),
                     type_: "resumed".to_owned(),
@@ -116,6 +283,7 @@ impl Actor for ThreadActor {
             },
 
             "interrupt" => {
+                self.script_chan.send(Interrupt(self.pipeline)).unwrap();
                 let msg = ThreadInterruptedReply {
                     from: self.name(),
                     type_: "interrupted".to_owned(),
@@ -132,7 +300,57 @@ impl Actor for ThreadActor {
             "sources" => {
                 let msg = SourcesReply {
                     from: self.name(),
-                    sources: vec![],
+                    sources: self.sources.borrow().values().cloned().collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "setBreakpoint" => {
+                if let Some(location) = self.breakpoint_location(msg) {
+                    self.script_chan
+                        .send(SetBreakpoint(self.pipeline, location))
+                        .unwrap();
+                }
+                stream.write_json_packet(&VoidAttachedReply { from: self.name() });
+                ActorMessageStatus::Processed
+            },
+
+            "removeBreakpoint" => {
+                if let Some(location) = self.breakpoint_location(msg) {
+                    self.script_chan
+                        .send(RemoveBreakpoint(self.pipeline, location))
+                        .unwrap();
+                }
+                stream.write_json_packet(&VoidAttachedReply { from: self.name() });
+                ActorMessageStatus::Processed
+            },
+
+            "frames" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(GetFrames(self.pipeline, tx)).unwrap();
+                let start = msg.get("start").and_then(Value::as_u64).unwrap_or(0) as usize;
+                let count = msg
+                    .get("count")
+                    .and_then(Value::as_u64)
+                    .map_or(usize::max_value(), |count| count as usize);
+
+                let frames = rx
+                    .recv()
+                    .unwrap_or(vec![])
+                    .into_iter()
+                    .skip(start)
+                    .take(count)
+                    .map(|frame| {
+                        let actor = self.frame_actor(registry, frame);
+                        let form = actor.encodable(registry);
+                        registry.register_later(Box::new(actor));
+                        form
+                    })
+                    .collect();
+                let msg = FramesReply {
+                    from: self.name(),
+                    frames: frames,
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
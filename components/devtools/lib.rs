@@ -23,7 +23,7 @@ use crate::actors::console::ConsoleActor;
 use crate::actors::device::DeviceActor;
 use crate::actors::emulation::EmulationActor;
 use crate::actors::framerate::FramerateActor;
-use crate::actors::inspector::InspectorActor;
+use crate::actors::inspector::{InspectorActor, WalkerActor};
 use crate::actors::network_event::{EventActor, NetworkEventActor, ResponseStartMsg};
 use crate::actors::performance::PerformanceActor;
 use crate::actors::preference::PreferenceActor;
@@ -37,16 +37,19 @@ use crate::actors::worker::WorkerActor;
 use crate::protocol::JsonPacketStream;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use devtools_traits::{ChromeToDevtoolsControlMsg, ConsoleMessage, DevtoolsControlMsg};
-use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo, LogLevel, NetworkEvent};
-use devtools_traits::{PageError, ScriptToDevtoolsControlMsg, WorkerId};
-use embedder_traits::{EmbedderMsg, EmbedderProxy, PromptDefinition, PromptOrigin, PromptResult};
+use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo, DomMutation, LogLevel};
+use devtools_traits::{NetworkEvent, NodeInfo, PageError, PauseInfo, ScriptToDevtoolsControlMsg};
+use devtools_traits::{SourceInfo, StackFrame, WorkerId};
+use embedder_traits::{EmbedderMsg, EmbedderProxy, PromptMsg, PromptRequest, PromptResponse};
 use ipc_channel::ipc::{self, IpcSender};
 use msg::constellation_msg::PipelineId;
+use serde_json::Value;
 use std::borrow::ToOwned;
 use std::cell::RefCell;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::HashMap;
 use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
@@ -57,6 +60,7 @@ mod actors {
     pub mod console;
     pub mod device;
     pub mod emulation;
+    pub mod frame;
     pub mod framerate;
     pub mod inspector;
     pub mod memory;
@@ -67,6 +71,7 @@ mod actors {
     pub mod process;
     pub mod profiler;
     pub mod root;
+    pub mod source;
     pub mod stylesheets;
     pub mod thread;
     pub mod timeline;
@@ -86,10 +91,11 @@ struct ConsoleAPICall {
 struct ConsoleMsg {
     level: String,
     timeStamp: u64,
-    arguments: Vec<String>,
+    arguments: Vec<Value>,
     filename: String,
     lineNumber: usize,
     columnNumber: usize,
+    stacktrace: Option<Vec<StackFrame>>,
 }
 
 #[derive(Serialize)]
@@ -136,38 +142,107 @@ struct ResponseStartUpdateMsg {
 }
 
 /// Spin up a devtools server that listens for connections on the specified port.
-pub fn start_server(port: u16, embedder: EmbedderProxy) -> Sender<DevtoolsControlMsg> {
+/// The user is asked to accept the connections with prompts sent to `prompt_sender`.
+/// The server keeps track of the pages while it doesn't listen, so that they can still be
+/// inspected once it listens again.
+pub fn start_server(
+    port: u16,
+    embedder: EmbedderProxy,
+    prompt_sender: Sender<PromptMsg>,
+) -> Sender<DevtoolsControlMsg> {
     let (sender, receiver) = unbounded();
     {
         let sender = sender.clone();
         thread::Builder::new()
             .name("Devtools".to_owned())
-            .spawn(move || run_server(sender, receiver, port, embedder))
+            .spawn(move || run_server(sender, receiver, port, embedder, prompt_sender))
             .expect("Thread spawning failed");
     }
     sender
 }
 
+/// The listener of a devtools server, whose connections are accepted on their own thread.
+struct Listener {
+    port: u16,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Listener {
+    /// Listen for connections on `port`, and tell the embedder on which port we listen.
+    fn start(
+        port: u16,
+        sender: Sender<DevtoolsControlMsg>,
+        embedder: &EmbedderProxy,
+        prompt_sender: Sender<PromptMsg>,
+    ) -> Option<Listener> {
+        let bound = TcpListener::bind(&("0.0.0.0", port)).ok().and_then(|l| {
+            l.local_addr()
+                .map(|addr| addr.port())
+                .ok()
+                .map(|port| (l, port))
+        });
+
+        let port = bound.as_ref().map(|(_, port)| *port).ok_or(());
+        embedder.send((None, EmbedderMsg::OnDevtoolsStarted(port)));
+
+        let (listener, port) = bound?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let acceptor_stopped = stopped.clone();
+        thread::Builder::new()
+            .name("DevtoolsClientAcceptor".to_owned())
+            .spawn(move || {
+                // accept connections and process them, spawning a new thread for each one
+                for stream in listener.incoming() {
+                    if acceptor_stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // Prompt user for permission
+                    let (prompt_response_sender, receiver) =
+                        ipc::channel().expect("Failed to create IPC channel!");
+                    let message = "Accept incoming devtools connection?".to_owned();
+                    let msg = PromptMsg {
+                        pipeline_id: None,
+                        request: PromptRequest::YesNo(message),
+                        sender: prompt_response_sender,
+                    };
+                    if prompt_sender.send(msg).is_err() {
+                        break;
+                    }
+                    if receiver.recv().ok() != Some(PromptResponse::Accept) {
+                        continue;
+                    }
+                    if acceptor_stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // connection succeeded and accepted
+                    sender
+                        .send(DevtoolsControlMsg::FromChrome(
+                            ChromeToDevtoolsControlMsg::AddClient(stream.unwrap()),
+                        ))
+                        .unwrap();
+                }
+            })
+            .expect("Thread spawning failed");
+
+        Some(Listener { port, stopped })
+    }
+
+    /// Stop accepting connections.
+    fn stop(self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // The acceptor only sees that it was stopped once it accepts a connection.
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+    }
+}
+
 fn run_server(
     sender: Sender<DevtoolsControlMsg>,
     receiver: Receiver<DevtoolsControlMsg>,
     port: u16,
     embedder: EmbedderProxy,
+    prompt_sender: Sender<PromptMsg>,
 ) {
-    let bound = TcpListener::bind(&("0.0.0.0", port)).ok().and_then(|l| {
-        l.local_addr()
-            .map(|addr| addr.port())
-            .ok()
-            .map(|port| (l, port))
-    });
-
-    let port = bound.as_ref().map(|(_, port)| *port).ok_or(());
-    embedder.send((None, EmbedderMsg::OnDevtoolsStarted(port)));
-
-    let listener = match bound {
-        Some((l, _)) => l,
-        None => return,
-    };
+    let mut listener = Listener::start(port, sender.clone(), &embedder, prompt_sender.clone());
 
     let mut registry = ActorRegistry::new();
 
@@ -289,21 +364,28 @@ fn run_server(
                 pipeline: pipeline,
             };
 
-            let timeline = TimelineActor::new(actors.new_name("timeline"), pipeline, script_sender);
+            let timeline =
+                TimelineActor::new(actors.new_name("timeline"), pipeline, script_sender.clone());
 
-            let profiler = ProfilerActor::new(actors.new_name("profiler"));
+            let profiler =
+                ProfilerActor::new(actors.new_name("profiler"), script_sender.clone(), pipeline);
             let performance = PerformanceActor::new(actors.new_name("performance"));
 
             // the strange switch between styleSheets and stylesheets is due
             // to an inconsistency in devtools. See Bug #1498893 in bugzilla
             let styleSheets = StyleSheetsActor::new(actors.new_name("stylesheets"));
-            let thread = ThreadActor::new(actors.new_name("context"));
+            let thread = ThreadActor::new(actors.new_name("context"), script_sender, pipeline);
 
-            let DevtoolsPageInfo { title, url } = page_info;
+            let DevtoolsPageInfo {
+                title,
+                url,
+                is_private,
+            } = page_info;
             let target = BrowsingContextActor {
                 name: actors.new_name("target"),
                 title: String::from(title),
                 url: url.into_string(),
+                is_private: is_private,
                 console: console.name(),
                 emulation: emulation.name(),
                 inspector: inspector.name(),
@@ -386,9 +468,10 @@ fn run_server(
             Some(name) => name,
             None => return,
         };
-        let actors = actors.lock().unwrap();
+        let mut actors = actors.lock().unwrap();
         let console_actor = actors.find::<ConsoleActor>(&console_actor_name);
-        console_actor.handle_console_api(console_message);
+        console_actor.handle_console_api(&actors, console_message);
+        actors.process_pending_actors();
     }
 
     fn find_console_actor(
@@ -413,6 +496,94 @@ fn run_server(
         }
     }
 
+    /// Run the given closure on the walker of the inspector of the given
+    /// pipeline, if the client has asked for one.
+    fn with_walker<F: FnOnce(&ActorRegistry, &WalkerActor)>(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        actor_pipelines: &HashMap<PipelineId, String>,
+        f: F,
+    ) {
+        let mut actors = actors.lock().unwrap();
+        let actor_name = match (*actor_pipelines).get(&id) {
+            Some(name) => name,
+            None => return,
+        };
+        let inspector_name = &actors.find::<BrowsingContextActor>(actor_name).inspector;
+        let walker_name = match *actors
+            .find::<InspectorActor>(inspector_name)
+            .walker
+            .borrow()
+        {
+            Some(ref name) => name.clone(),
+            None => return,
+        };
+        f(&actors, actors.find::<WalkerActor>(&walker_name));
+        actors.process_pending_actors();
+    }
+
+    fn handle_dom_mutation(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        mutation: DomMutation,
+        actor_pipelines: &HashMap<PipelineId, String>,
+    ) {
+        with_walker(actors, id, actor_pipelines, |_, walker| {
+            walker.handle_dom_mutation(mutation)
+        });
+    }
+
+    fn handle_picker_event(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        picked: bool,
+        node: NodeInfo,
+        actor_pipelines: &HashMap<PipelineId, String>,
+    ) {
+        with_walker(actors, id, actor_pipelines, |registry, walker| {
+            walker.handle_picker_event(registry, picked, node)
+        });
+    }
+
+    /// Run the given closure on the thread actor of the given pipeline.
+    fn with_thread<F: FnOnce(&ActorRegistry, &ThreadActor)>(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        actor_pipelines: &HashMap<PipelineId, String>,
+        f: F,
+    ) {
+        let mut actors = actors.lock().unwrap();
+        let actor_name = match (*actor_pipelines).get(&id) {
+            Some(name) => name,
+            None => return,
+        };
+        let thread_name = &actors.find::<BrowsingContextActor>(actor_name).thread;
+        f(&actors, actors.find::<ThreadActor>(thread_name));
+        actors.process_pending_actors();
+    }
+
+    fn handle_new_source(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        source: SourceInfo,
+        actor_pipelines: &HashMap<PipelineId, String>,
+    ) {
+        with_thread(actors, id, actor_pipelines, |registry, thread| {
+            thread.handle_new_source(registry, source)
+        });
+    }
+
+    fn handle_debugger_paused(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        pause: PauseInfo,
+        actor_pipelines: &HashMap<PipelineId, String>,
+    ) {
+        with_thread(actors, id, actor_pipelines, |registry, thread| {
+            thread.handle_paused(registry, pause)
+        });
+    }
+
     fn handle_network_event(
         actors: Arc<Mutex<ActorRegistry>>,
         mut connections: Vec<TcpStream>,
@@ -563,31 +734,6 @@ fn run_server(
         }
     }
 
-    thread::Builder::new()
-        .name("DevtoolsClientAcceptor".to_owned())
-        .spawn(move || {
-            // accept connections and process them, spawning a new thread for each one
-            for stream in listener.incoming() {
-                // Prompt user for permission
-                let (embedder_sender, receiver) =
-                    ipc::channel().expect("Failed to create IPC channel!");
-                let message = "Accept incoming devtools connection?".to_owned();
-                let prompt = PromptDefinition::YesNo(message, embedder_sender);
-                let msg = EmbedderMsg::Prompt(prompt, PromptOrigin::Trusted);
-                embedder.send((None, msg));
-                if receiver.recv().unwrap() != PromptResult::Primary {
-                    continue;
-                }
-                // connection succeeded and accepted
-                sender
-                    .send(DevtoolsControlMsg::FromChrome(
-                        ChromeToDevtoolsControlMsg::AddClient(stream.unwrap()),
-                    ))
-                    .unwrap();
-            }
-        })
-        .expect("Thread spawning failed");
-
     while let Ok(msg) = receiver.recv() {
         match msg {
             DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::AddClient(stream)) => {
@@ -630,6 +776,25 @@ fn run_server(
                 id,
                 page_error,
             )) => handle_page_error(actors.clone(), id, page_error, &actor_pipelines),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::DomMutation(
+                id,
+                mutation,
+            )) => handle_dom_mutation(actors.clone(), id, mutation, &actor_pipelines),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::PickerNodeHovered(
+                id,
+                node,
+            )) => handle_picker_event(actors.clone(), id, false, node, &actor_pipelines),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::PickerNodePicked(
+                id,
+                node,
+            )) => handle_picker_event(actors.clone(), id, true, node, &actor_pipelines),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::NewSource(id, source)) => {
+                handle_new_source(actors.clone(), id, source, &actor_pipelines)
+            },
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::DebuggerPaused(
+                id,
+                pause,
+            )) => handle_debugger_paused(actors.clone(), id, pause, &actor_pipelines),
             DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::ReportCSSError(
                 id,
                 css_error,
@@ -640,6 +805,8 @@ fn run_server(
                     filename: css_error.filename,
                     lineNumber: css_error.line as usize,
                     columnNumber: css_error.column as usize,
+                    arguments: vec![],
+                    stacktrace: None,
                 };
                 handle_console_message(
                     actors.clone(),
@@ -675,9 +842,26 @@ fn run_server(
                     network_event,
                 );
             },
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::StartListening(port)) => {
+                if listener.is_none() {
+                    listener =
+                        Listener::start(port, sender.clone(), &embedder, prompt_sender.clone());
+                }
+            },
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::StopListening) => {
+                if let Some(listener) = listener.take() {
+                    listener.stop();
+                }
+                for connection in accepted_connections.drain(..) {
+                    let _ = connection.shutdown(Shutdown::Both);
+                }
+            },
             DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::ServerExitMsg) => break,
         }
     }
+    if let Some(listener) = listener {
+        listener.stop();
+    }
     for connection in &mut accepted_connections {
         let _ = connection.shutdown(Shutdown::Both);
     }
@@ -0,0 +1,325 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::Server;
+use devtools_traits::DevtoolScriptControlMsg;
+use devtools_traits::{DebuggerValue, EnvironmentInfo, FrameInfo, PauseInfo, ResumeLimit};
+use devtools_traits::{ScriptToDevtoolsControlMsg, SourceInfo, SourceLocation};
+use msg::constellation_msg::TEST_PIPELINE_ID;
+use serde_json::{json, Value};
+
+const SCRIPT_URL: &'static str = "http://example.com/script.js";
+
+fn source(id: u32, url: &str) -> SourceInfo {
+    SourceInfo {
+        id: id,
+        url: url.to_owned(),
+        sourceMapURL: Some("script.js.map".to_owned()),
+        introductionType: Some("scriptElement".to_owned()),
+    }
+}
+
+fn frame(id: u32, name: &str, line: u32) -> FrameInfo {
+    FrameInfo {
+        id: id,
+        type_: "call".to_owned(),
+        displayName: Some(name.to_owned()),
+        location: SourceLocation {
+            sourceId: 1,
+            line: line,
+            column: 4,
+        },
+        this: DebuggerValue::Object {
+            class: "Window".to_owned(),
+            name: None,
+        },
+        arguments: vec![
+            DebuggerValue::Number(1.),
+            DebuggerValue::Number(-0.),
+            DebuggerValue::Number(std::f64::NEG_INFINITY),
+            DebuggerValue::Undefined,
+        ],
+    }
+}
+
+/// Attach to the thread actor of the page, whose script thread has loaded a script, and
+/// return the thread actor.
+fn attach(server: &mut Server) -> String {
+    let tab = server.tab();
+    let tab_attached = server.request(tab["actor"].as_str().unwrap(), "attach", json!({}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::WantsLiveNotifications(_, true) => {},
+        _ => panic!("The console didn't ask for live notifications"),
+    }
+    let thread = tab_attached["threadActor"].as_str().unwrap().to_owned();
+
+    server.send(json!({"to": thread, "type": "attach"}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::AttachDebugger(pipeline, sender) => {
+            assert_eq!(pipeline, TEST_PIPELINE_ID);
+            sender.send(vec![source(1, SCRIPT_URL)]).unwrap();
+        },
+        _ => panic!("The debugger wasn't attached"),
+    }
+    let paused = server.recv();
+    assert_eq!(paused["type"], "paused");
+    assert_eq!(paused["why"]["type"], "attached");
+    assert_eq!(server.recv()["from"], json!(thread));
+    thread
+}
+
+fn source_actor(server: &mut Server, thread: &str, url: &str) -> String {
+    let sources = server.request(thread, "sources", json!({}));
+    sources["sources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|source| source["url"] == url)
+        .map(|source| source["actor"].as_str().unwrap().to_owned())
+        .unwrap()
+}
+
+#[test]
+fn test_sources_are_listed_with_their_source_maps() {
+    let mut server = Server::start();
+    let thread = attach(&mut server);
+
+    let sources = server.request(&thread, "sources", json!({}));
+    let sources = sources["sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0]["url"], SCRIPT_URL);
+    // The client resolves the source map relatively to the source.
+    assert_eq!(sources[0]["sourceMapURL"], "script.js.map");
+    assert_eq!(sources[0]["sourceMapBaseURL"], SCRIPT_URL);
+    assert_eq!(sources[0]["introductionType"], "scriptElement");
+
+    // The sources loaded after attaching are announced once.
+    let other_url = "http://example.com/other.js";
+    server.send_from_script(ScriptToDevtoolsControlMsg::NewSource(
+        TEST_PIPELINE_ID,
+        source(2, other_url),
+    ));
+    server.send_from_script(ScriptToDevtoolsControlMsg::NewSource(
+        TEST_PIPELINE_ID,
+        source(2, other_url),
+    ));
+    server.send_from_script(ScriptToDevtoolsControlMsg::NewSource(
+        TEST_PIPELINE_ID,
+        source(3, "http://example.com/last.js"),
+    ));
+    let new_source = server.recv();
+    assert_eq!(new_source["type"], "newSource");
+    assert_eq!(new_source["source"]["url"], other_url);
+    let new_source = server.recv();
+    assert_eq!(new_source["source"]["url"], "http://example.com/last.js");
+    let sources = server.request(&thread, "sources", json!({}));
+    assert_eq!(sources["sources"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn test_sources_have_their_text_and_breakpoint_positions() {
+    let mut server = Server::start();
+    let thread = attach(&mut server);
+    let source = source_actor(&mut server, &thread, SCRIPT_URL);
+
+    server.send(json!({"to": source, "type": "source"}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetSourceText(_, 1, sender) => {
+            sender.send(Some("f(1);".to_owned())).unwrap()
+        },
+        _ => panic!("The text of the source wasn't requested"),
+    }
+    let reply = server.recv();
+    assert_eq!(reply["source"], "f(1);");
+    assert_eq!(reply["contentType"], "text/javascript");
+
+    let positions = vec![(3, 4), (1, 0), (3, 0)];
+    server.send(json!({"to": source, "type": "getBreakableLines"}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetBreakpointPositions(_, 1, sender) => {
+            sender.send(positions.clone()).unwrap()
+        },
+        _ => panic!("The breakpoint positions weren't requested"),
+    }
+    assert_eq!(server.recv()["lines"], json!([1, 3]));
+
+    server.send(json!({"to": source, "type": "getBreakpointPositionsCompressed"}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetBreakpointPositions(_, 1, sender) => {
+            sender.send(positions).unwrap()
+        },
+        _ => panic!("The breakpoint positions weren't requested"),
+    }
+    assert_eq!(server.recv()["positions"], json!({"1": [0], "3": [4, 0]}));
+}
+
+#[test]
+fn test_breakpoints_are_set_by_source_actor_or_url() {
+    let mut server = Server::start();
+    let thread = attach(&mut server);
+    let source = source_actor(&mut server, &thread, SCRIPT_URL);
+
+    let reply = server.request(
+        &thread,
+        "setBreakpoint",
+        json!({"location": {"sourceId": source, "line": 3, "column": 4}}),
+    );
+    assert_eq!(reply["from"], json!(thread));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::SetBreakpoint(_, location) => {
+            assert_eq!(location.sourceId, 1);
+            assert_eq!(location.line, 3);
+            assert_eq!(location.column, 4);
+        },
+        _ => panic!("The breakpoint wasn't set"),
+    }
+
+    server.request(
+        &thread,
+        "removeBreakpoint",
+        json!({"location": {"sourceUrl": SCRIPT_URL, "line": 5}}),
+    );
+    match server.recv_script() {
+        DevtoolScriptControlMsg::RemoveBreakpoint(_, location) => {
+            assert_eq!(location.sourceId, 1);
+            assert_eq!(location.line, 5);
+            assert_eq!(location.column, 0);
+        },
+        _ => panic!("The breakpoint wasn't removed"),
+    }
+
+    // The breakpoints of unknown sources are ignored.
+    server.request(
+        &thread,
+        "setBreakpoint",
+        json!({"location": {"sourceUrl": "http://example.com/unknown.js", "line": 1}}),
+    );
+    server.request(&thread, "interrupt", json!({}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::Interrupt(_) => {},
+        _ => panic!("A breakpoint was set in an unknown source"),
+    }
+}
+
+#[test]
+fn test_pauses_report_their_frames_and_scopes() {
+    let mut server = Server::start();
+    let thread = attach(&mut server);
+    let source = source_actor(&mut server, &thread, SCRIPT_URL);
+
+    server.send_from_script(ScriptToDevtoolsControlMsg::DebuggerPaused(
+        TEST_PIPELINE_ID,
+        PauseInfo {
+            why: "breakpoint".to_owned(),
+            frame: frame(0, "f", 3),
+        },
+    ));
+    let paused = server.recv();
+    assert_eq!(paused["type"], "paused");
+    assert_eq!(paused["why"]["type"], "breakpoint");
+    let frame_form = &paused["frame"];
+    assert_eq!(frame_form["depth"], 0);
+    assert_eq!(frame_form["displayName"], "f");
+    assert_eq!(
+        frame_form["where"],
+        json!({"actor": source, "line": 3, "column": 4})
+    );
+    assert_eq!(frame_form["this"]["type"], "object");
+    assert_eq!(frame_form["this"]["class"], "Window");
+    assert_eq!(
+        frame_form["arguments"],
+        json!([1.0, {"type": "-0"}, {"type": "-Infinity"}, {"type": "undefined"}])
+    );
+    let frame_actor = frame_form["actor"].as_str().unwrap().to_owned();
+
+    server.send(json!({"to": thread, "type": "frames", "start": 1, "count": 1}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetFrames(_, sender) => sender
+            .send(vec![frame(0, "f", 3), frame(1, "g", 7), frame(2, "h", 9)])
+            .unwrap(),
+        _ => panic!("The frames weren't requested"),
+    }
+    let frames = server.recv();
+    let frames = frames["frames"].as_array().unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0]["depth"], 1);
+    assert_eq!(frames[0]["displayName"], "g");
+
+    server.send(json!({"to": frame_actor, "type": "getEnvironment"}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetEnvironment(_, 0, sender) => sender
+            .send(vec![
+                EnvironmentInfo {
+                    type_: "function".to_owned(),
+                    functionName: Some("f".to_owned()),
+                    variables: vec![
+                        ("x".to_owned(), DebuggerValue::Number(1.)),
+                        ("y".to_owned(), DebuggerValue::Number(std::f64::NAN)),
+                    ],
+                    object: None,
+                },
+                EnvironmentInfo {
+                    type_: "object".to_owned(),
+                    functionName: None,
+                    variables: vec![],
+                    object: Some(DebuggerValue::Object {
+                        class: "Window".to_owned(),
+                        name: None,
+                    }),
+                },
+            ])
+            .unwrap(),
+        _ => panic!("The environment wasn't requested"),
+    }
+    // The scopes are nested from the innermost one.
+    let environment = server.recv();
+    assert_eq!(environment["type"], "function");
+    assert_eq!(environment["function"]["displayName"], "f");
+    let variables = &environment["bindings"]["variables"];
+    assert_eq!(variables["x"]["value"], 1.0);
+    assert_eq!(variables["y"]["value"], json!({"type": "NaN"}));
+    let parent = &environment["parent"];
+    assert_eq!(parent["type"], "object");
+    assert_eq!(parent["object"]["class"], "Window");
+    assert_eq!(parent["bindings"], Value::Null);
+    assert_eq!(parent["parent"], Value::Null);
+}
+
+#[test]
+fn test_the_debugger_resumes_steps_and_detaches() {
+    let mut server = Server::start();
+    let thread = attach(&mut server);
+
+    for (limit, expected) in &[
+        (json!({"type": "next"}), Some(ResumeLimit::Next)),
+        (json!({"type": "step"}), Some(ResumeLimit::Step)),
+        (json!({"type": "finish"}), Some(ResumeLimit::Finish)),
+        (Value::Null, None),
+    ] {
+        let resumed = server.request(&thread, "resume", json!({ "resumeLimit": limit }));
+        assert_eq!(resumed["type"], "resumed");
+        assert_eq!(server.recv()["from"], json!(thread));
+        match server.recv_script() {
+            DevtoolScriptControlMsg::Resume(_, limit) => {
+                assert_eq!(format!("{:?}", limit), format!("{:?}", expected))
+            },
+            _ => panic!("The debugger wasn't resumed"),
+        }
+    }
+
+    let interrupted = server.request(&thread, "interrupt", json!({}));
+    assert_eq!(interrupted["type"], "interrupted");
+    match server.recv_script() {
+        DevtoolScriptControlMsg::Interrupt(pipeline) => assert_eq!(pipeline, TEST_PIPELINE_ID),
+        _ => panic!("The debugger wasn't interrupted"),
+    }
+
+    server.request(&thread, "detach", json!({}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::DetachDebugger(pipeline) => {
+            assert_eq!(pipeline, TEST_PIPELINE_ID)
+        },
+        _ => panic!("The debugger wasn't detached"),
+    }
+}
@@ -0,0 +1,323 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::Server;
+use devtools_traits::{AppliedStyles, DevtoolScriptControlMsg, DomMutation, MatchedRule};
+use devtools_traits::{NodeInfo, ScriptToDevtoolsControlMsg};
+use msg::constellation_msg::TEST_PIPELINE_ID;
+use serde_json::{json, Value};
+
+fn node(id: &str, parent: &str, name: &str, num_children: usize) -> NodeInfo {
+    NodeInfo {
+        uniqueId: id.to_owned(),
+        baseURI: "http://example.com/".to_owned(),
+        parent: parent.to_owned(),
+        nodeType: if parent.is_empty() { 9 } else { 1 },
+        namespaceURI: String::new(),
+        nodeName: name.to_owned(),
+        numChildren: num_children,
+        name: String::new(),
+        publicId: String::new(),
+        systemId: String::new(),
+        attrs: vec![],
+        isDocumentElement: name == "HTML",
+        shortValue: String::new(),
+        incompleteValue: false,
+    }
+}
+
+/// The inspector of the page, its walker, and the actor of the document element, whose
+/// only child is the body.
+struct Inspector {
+    inspector: String,
+    walker: String,
+    html: String,
+    body: String,
+}
+
+fn get_walker(server: &mut Server) -> Inspector {
+    let tab = server.tab();
+    let inspector = tab["inspectorActor"].as_str().unwrap().to_owned();
+
+    server.send(json!({"to": inspector, "type": "getWalker"}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::WantsDomMutations(pipeline, true) => {
+            assert_eq!(pipeline, TEST_PIPELINE_ID)
+        },
+        _ => panic!("The walker didn't ask for the mutations"),
+    }
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetRootNode(_, sender) => sender
+            .send(Some(node("document", "", "#document", 1)))
+            .unwrap(),
+        _ => panic!("The root node wasn't requested"),
+    }
+    let reply = server.recv();
+    let walker = reply["walker"]["actor"].as_str().unwrap().to_owned();
+    let root = reply["walker"]["root"]["actor"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let html = children(
+        server,
+        &walker,
+        &root,
+        "document",
+        vec![node("html", "document", "HTML", 1)],
+    );
+    let body = children(
+        server,
+        &walker,
+        &html,
+        "html",
+        vec![node("body", "html", "BODY", 0)],
+    );
+    Inspector {
+        inspector: inspector,
+        walker: walker,
+        html: html,
+        body: body,
+    }
+}
+
+/// Request the children of a node, returning the actor of the first one.
+fn children(
+    server: &mut Server,
+    walker: &str,
+    parent: &str,
+    parent_id: &str,
+    children: Vec<NodeInfo>,
+) -> String {
+    server.send(json!({"to": walker, "type": "children", "node": parent}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetChildren(_, id, sender) => {
+            assert_eq!(id, parent_id);
+            sender.send(Some(children)).unwrap();
+        },
+        _ => panic!("The children weren't requested"),
+    }
+    let reply = server.recv();
+    let child = &reply["nodes"][0];
+    assert_eq!(child["parent"], json!(parent));
+    child["actor"].as_str().unwrap().to_owned()
+}
+
+#[test]
+fn test_dom_mutations_are_queued_for_the_client() {
+    let mut server = Server::start();
+    let inspector = get_walker(&mut server);
+
+    server.send_from_script(ScriptToDevtoolsControlMsg::DomMutation(
+        TEST_PIPELINE_ID,
+        DomMutation::ChildList {
+            target: "html".to_owned(),
+            added: vec!["unknown".to_owned()],
+            removed: vec!["body".to_owned()],
+            numChildren: 1,
+        },
+    ));
+    server.send_from_script(ScriptToDevtoolsControlMsg::DomMutation(
+        TEST_PIPELINE_ID,
+        DomMutation::Attributes {
+            target: "unknown".to_owned(),
+            attributeName: "id".to_owned(),
+            attributeNamespace: String::new(),
+            newValue: Some("unknown".to_owned()),
+        },
+    ));
+    server.send_from_script(ScriptToDevtoolsControlMsg::DomMutation(
+        TEST_PIPELINE_ID,
+        DomMutation::Attributes {
+            target: "html".to_owned(),
+            attributeName: "lang".to_owned(),
+            attributeNamespace: String::new(),
+            newValue: None,
+        },
+    ));
+    // The client is only told about the first of the mutations, and the event of the
+    // picker is only sent once the server handled all of them.
+    server.send_from_script(ScriptToDevtoolsControlMsg::PickerNodeHovered(
+        TEST_PIPELINE_ID,
+        node("html", "document", "HTML", 1),
+    ));
+    let new_mutations = server.recv();
+    assert_eq!(new_mutations["type"], "newMutations");
+    assert_eq!(new_mutations["from"], json!(inspector.walker));
+    assert_eq!(server.recv()["type"], "picker-node-hovered");
+
+    // The nodes which the client doesn't know about are left out.
+    let reply = server.request(&inspector.walker, "getMutations", json!({}));
+    assert_eq!(
+        reply["mutations"],
+        json!([
+            {
+                "type": "childList",
+                "target": inspector.html,
+                "added": [],
+                "removed": [inspector.body],
+                "numChildren": 1,
+            },
+            {
+                "type": "attributes",
+                "target": inspector.html,
+                "attributeName": "lang",
+                "attributeNamespace": "",
+                "newValue": null,
+            },
+        ])
+    );
+    let reply = server.request(&inspector.walker, "getMutations", json!({}));
+    assert_eq!(reply["mutations"], json!([]));
+
+    server.send_from_script(ScriptToDevtoolsControlMsg::DomMutation(
+        TEST_PIPELINE_ID,
+        DomMutation::CharacterData {
+            target: "body".to_owned(),
+            newValue: "text".to_owned(),
+        },
+    ));
+    assert_eq!(server.recv()["type"], "newMutations");
+}
+
+#[test]
+fn test_the_picker_reports_nodes_and_the_highlighter_shows_them() {
+    let mut server = Server::start();
+    let inspector = get_walker(&mut server);
+
+    server.request(&inspector.walker, "pick", json!({}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::SetNodePicking(_, true) => {},
+        _ => panic!("The picker wasn't started"),
+    }
+    server.send_from_script(ScriptToDevtoolsControlMsg::PickerNodeHovered(
+        TEST_PIPELINE_ID,
+        node("body", "html", "BODY", 0),
+    ));
+    let hovered = server.recv();
+    assert_eq!(hovered["type"], "picker-node-hovered");
+    assert_eq!(hovered["node"]["actor"], json!(inspector.body));
+    // The picked nodes which the client doesn't know about yet get an actor.
+    server.send_from_script(ScriptToDevtoolsControlMsg::PickerNodePicked(
+        TEST_PIPELINE_ID,
+        node("div", "body", "DIV", 0),
+    ));
+    let picked = server.recv();
+    assert_eq!(picked["type"], "picker-node-picked");
+    assert_eq!(picked["node"]["nodeName"], "DIV");
+    assert_eq!(picked["node"]["parent"], json!(inspector.body));
+    assert!(picked["node"]["actor"]
+        .as_str()
+        .unwrap()
+        .starts_with("node"));
+    server.request(&inspector.walker, "cancelPick", json!({}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::SetNodePicking(_, false) => {},
+        _ => panic!("The picker wasn't stopped"),
+    }
+
+    let reply = server.request(&inspector.inspector, "getHighlighterByType", json!({}));
+    let highlighter = reply["highligter"]["actor"].as_str().unwrap().to_owned();
+    let reply = server.request(&highlighter, "show", json!({ "node": inspector.body }));
+    assert_eq!(reply["value"], true);
+    match server.recv_script() {
+        DevtoolScriptControlMsg::HighlightNode(_, highlighted) => {
+            assert_eq!(highlighted, Some("body".to_owned()))
+        },
+        _ => panic!("The node wasn't highlighted"),
+    }
+    server.request(&highlighter, "hideBoxModel", json!({}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::HighlightNode(_, highlighted) => assert_eq!(highlighted, None),
+        _ => panic!("The highlight wasn't cleared"),
+    }
+}
+
+#[test]
+fn test_applied_styles_are_listed_and_inline_styles_edited() {
+    let mut server = Server::start();
+    let inspector = get_walker(&mut server);
+    let reply = server.request(&inspector.inspector, "getPageStyle", json!({}));
+    let page_style = reply["pageStyle"]["actor"].as_str().unwrap().to_owned();
+
+    server.send(json!({"to": page_style, "type": "getApplied", "node": inspector.body}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetAppliedStyles(_, id, sender) => {
+            assert_eq!(id, "body");
+            let rule = |selector: &str, css_text: &str, index| MatchedRule {
+                selectorText: selector.to_owned(),
+                cssText: css_text.to_owned(),
+                href: Some("http://example.com/style.css".to_owned()),
+                styleSheetIndex: index,
+            };
+            let applied = AppliedStyles {
+                inlineStyle: "color: red;".to_owned(),
+                matchedRules: vec![rule("body", "margin: 0;", 0), rule(".x", "color: blue;", 1)],
+            };
+            sender.send(Some(applied)).unwrap();
+        },
+        _ => panic!("The applied styles weren't requested"),
+    }
+    // The inline style comes first, then the rules from the most specific one.
+    let reply = server.recv();
+    let rules = reply["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0]["type"], 100);
+    assert_eq!(rules[0]["cssText"], "color: red;");
+    assert_eq!(rules[1]["type"], 1);
+    assert_eq!(rules[1]["selectors"], json!([".x"]));
+    assert_eq!(rules[2]["selectors"], json!(["body"]));
+    assert_eq!(rules[2]["href"], "http://example.com/style.css");
+    let entries = reply["entries"].as_array().unwrap();
+    let entry_rules: Vec<&Value> = entries.iter().map(|entry| &entry["rule"]).collect();
+    let rule_actors: Vec<&Value> = rules.iter().map(|rule| &rule["actor"]).collect();
+    assert_eq!(entry_rules, rule_actors);
+    let inline_rule = rules[0]["actor"].as_str().unwrap().to_owned();
+
+    server.send(json!({
+        "to": inline_rule,
+        "type": "modifyProperties",
+        "modifications": [
+            {"type": "set", "name": "color", "value": "blue", "priority": "important"},
+            {"type": "remove", "name": "margin"},
+        ],
+    }));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::ModifyInlineStyle(_, id, modifications) => {
+            assert_eq!(id, "body");
+            assert_eq!(modifications.len(), 2);
+            assert_eq!(modifications[0].name, "color");
+            assert_eq!(modifications[0].value, Some("blue".to_owned()));
+            assert_eq!(modifications[0].priority, "important");
+            assert_eq!(modifications[1].name, "margin");
+            assert_eq!(modifications[1].value, None);
+        },
+        _ => panic!("The inline style wasn't modified"),
+    }
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetAppliedStyles(_, _, sender) => {
+            let applied = AppliedStyles {
+                inlineStyle: "color: blue !important;".to_owned(),
+                matchedRules: vec![],
+            };
+            sender.send(Some(applied)).unwrap();
+        },
+        _ => panic!("The applied styles weren't requested again"),
+    }
+    let reply = server.recv();
+    assert_eq!(reply["rule"]["actor"], json!(inline_rule));
+    assert_eq!(reply["rule"]["cssText"], "color: blue !important;");
+
+    server.send(json!({"to": page_style, "type": "getComputed", "node": inspector.body}));
+    match server.recv_script() {
+        DevtoolScriptControlMsg::GetComputedStyle(_, id, sender) => {
+            assert_eq!(id, "body");
+            let properties = vec![("color".to_owned(), "rgb(0, 0, 255)".to_owned())];
+            sender.send(Some(properties)).unwrap();
+        },
+        _ => panic!("The computed style wasn't requested"),
+    }
+    let reply = server.recv();
+    assert_eq!(reply["computed"]["color"]["value"], "rgb(0, 0, 255)");
+}
@@ -0,0 +1,19 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::Server;
+use devtools_traits::ChromeToDevtoolsControlMsg;
+
+#[test]
+fn test_stop_and_resume_listening() {
+    let mut server = Server::start();
+
+    server.send_from_chrome(ChromeToDevtoolsControlMsg::StopListening);
+    assert!(server.is_disconnected());
+
+    server.send_from_chrome(ChromeToDevtoolsControlMsg::StartListening(0));
+    server.reconnect();
+    // The pages which were known before the server stopped listening are still inspectable.
+    assert_eq!(server.tab()["url"], "http://example.com/");
+}
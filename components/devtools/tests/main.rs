@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#![cfg(test)]
+
+mod debugger;
+mod inspector;
+mod listening;
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolScriptControlMsg};
+use devtools_traits::{DevtoolsControlMsg, DevtoolsPageInfo, ScriptToDevtoolsControlMsg};
+use embedder_traits::{EmbedderMsg, EmbedderProxy, EventLoopWaker, PromptMsg, PromptResponse};
+use ipc_channel::ipc::{self, IpcReceiver};
+use msg::constellation_msg::{TopLevelBrowsingContextId, TEST_PIPELINE_ID};
+use serde_json::{json, Value};
+use servo_url::ServoUrl;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+fn create_embedder_proxy() -> (
+    EmbedderProxy,
+    Receiver<(Option<TopLevelBrowsingContextId>, EmbedderMsg)>,
+) {
+    struct DummyEventLoopWaker {}
+    impl EventLoopWaker for DummyEventLoopWaker {
+        fn wake(&self) {}
+        fn clone_box(&self) -> Box<dyn EventLoopWaker> {
+            Box::new(DummyEventLoopWaker {})
+        }
+    }
+
+    let (sender, receiver) = unbounded();
+    let proxy = EmbedderProxy {
+        sender: sender,
+        event_loop_waker: Box::new(DummyEventLoopWaker {}),
+    };
+    (proxy, receiver)
+}
+
+/// A devtools server with a connected client and a page, whose script thread is played
+/// by the test.
+pub struct Server {
+    devtools: Sender<DevtoolsControlMsg>,
+    embedder: Receiver<(Option<TopLevelBrowsingContextId>, EmbedderMsg)>,
+    prompts: Receiver<PromptMsg>,
+    script: IpcReceiver<DevtoolScriptControlMsg>,
+    client: TcpStream,
+}
+
+impl Server {
+    pub fn start() -> Server {
+        let (embedder, embedder_receiver) = create_embedder_proxy();
+        let (prompt_sender, prompt_receiver) = unbounded();
+        let devtools = devtools::start_server(0, embedder, prompt_sender);
+
+        let (script_sender, script) = ipc::channel().unwrap();
+        let page_info = DevtoolsPageInfo {
+            title: "Test".to_owned(),
+            url: ServoUrl::parse("http://example.com/").unwrap(),
+            is_private: false,
+        };
+        devtools
+            .send(DevtoolsControlMsg::FromScript(
+                ScriptToDevtoolsControlMsg::NewGlobal(
+                    (TEST_PIPELINE_ID, None),
+                    script_sender,
+                    page_info,
+                ),
+            ))
+            .unwrap();
+
+        let port = Server::listening_port(&embedder_receiver);
+        let mut server = Server {
+            devtools: devtools,
+            embedder: embedder_receiver,
+            prompts: prompt_receiver,
+            script: script,
+            client: TcpStream::connect(("127.0.0.1", port)).unwrap(),
+        };
+        server.accept();
+        server
+    }
+
+    fn listening_port(
+        embedder: &Receiver<(Option<TopLevelBrowsingContextId>, EmbedderMsg)>,
+    ) -> u16 {
+        match embedder.recv().unwrap() {
+            (_, EmbedderMsg::OnDevtoolsStarted(Ok(port))) => port,
+            _ => panic!("The devtools server didn't start"),
+        }
+    }
+
+    /// Accept the connection of the client, which receives the root actor.
+    fn accept(&mut self) {
+        let prompt = self.prompts.recv().unwrap();
+        prompt.sender.send(PromptResponse::Accept).unwrap();
+        assert_eq!(self.recv()["from"], "root");
+    }
+
+    /// Connect the client again, once the server listens on a new port.
+    pub fn reconnect(&mut self) {
+        let port = Server::listening_port(&self.embedder);
+        self.client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        self.accept();
+    }
+
+    /// Send a message to the server as the embedder.
+    pub fn send_from_chrome(&self, msg: ChromeToDevtoolsControlMsg) {
+        self.devtools
+            .send(DevtoolsControlMsg::FromChrome(msg))
+            .unwrap();
+    }
+
+    /// Whether the server closed the connection of the client.
+    pub fn is_disconnected(&mut self) -> bool {
+        let mut byte = [0];
+        match self.client.read(&mut byte) {
+            Ok(read) => read == 0,
+            Err(_) => true,
+        }
+    }
+
+    /// Send a message to the server as the script thread of the page.
+    pub fn send_from_script(&self, msg: ScriptToDevtoolsControlMsg) {
+        self.devtools
+            .send(DevtoolsControlMsg::FromScript(msg))
+            .unwrap();
+    }
+
+    /// Receive the next message which the server sent to the script thread of the page.
+    pub fn recv_script(&self) -> DevtoolScriptControlMsg {
+        self.script.recv().unwrap()
+    }
+
+    pub fn send(&mut self, packet: Value) {
+        let packet = packet.to_string();
+        write!(self.client, "{}:{}", packet.len(), packet).unwrap();
+    }
+
+    /// Receive the next packet which the server sent to the client.
+    pub fn recv(&mut self) -> Value {
+        let mut length = vec![];
+        loop {
+            let mut byte = [0];
+            self.client.read_exact(&mut byte).unwrap();
+            if byte[0] == b':' {
+                break;
+            }
+            length.push(byte[0]);
+        }
+        let length = String::from_utf8(length).unwrap().parse().unwrap();
+        let mut packet = vec![0; length];
+        self.client.read_exact(&mut packet).unwrap();
+        serde_json::from_slice(&packet).unwrap()
+    }
+
+    /// Send a packet to an actor which replies without asking the script thread.
+    pub fn request(&mut self, to: &str, type_: &str, mut packet: Value) -> Value {
+        packet["to"] = json!(to);
+        packet["type"] = json!(type_);
+        self.send(packet);
+        self.recv()
+    }
+
+    /// The form of the browsing context of the page.
+    pub fn tab(&mut self) -> Value {
+        self.request("root", "listTabs", json!({}))["tabs"][0].clone()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.devtools.send(DevtoolsControlMsg::FromChrome(
+            ChromeToDevtoolsControlMsg::ServerExitMsg,
+        ));
+    }
+}
@@ -33,6 +33,8 @@ use uuid::Uuid;
 pub struct DevtoolsPageInfo {
     pub title: String,
     pub url: ServoUrl,
+    /// Whether the page belongs to a private browsing session.
+    pub is_private: bool,
 }
 
 #[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
@@ -58,6 +60,10 @@ pub enum DevtoolsControlMsg {
 pub enum ChromeToDevtoolsControlMsg {
     /// A new client has connected to the server.
     AddClient(TcpStream),
+    /// Listen for connections on the given port, if the server isn't already listening.
+    StartListening(u16),
+    /// Stop listening for connections, and close the connections of the clients.
+    StopListening,
     /// The browser is shutting down.
     ServerExitMsg,
     /// A network event occurred (request, reply, etc.). The actor with the
@@ -86,6 +92,18 @@ pub enum ScriptToDevtoolsControlMsg {
 
     /// Report a page error for the given pipeline
     ReportPageError(PipelineId, PageError),
+
+    /// A node of the document of the given pipeline was mutated.
+    DomMutation(PipelineId, DomMutation),
+    /// The node picker is hovering the given node of the given pipeline.
+    PickerNodeHovered(PipelineId, NodeInfo),
+    /// The node picker picked the given node of the given pipeline.
+    PickerNodePicked(PipelineId, NodeInfo),
+
+    /// The debugger of the script thread of the given pipeline found a new source.
+    NewSource(PipelineId, SourceInfo),
+    /// The given pipeline was paused by its debugger.
+    DebuggerPaused(PipelineId, PauseInfo),
 }
 
 /// Serialized JS return values
@@ -178,6 +196,45 @@ pub struct ComputedNodeLayout {
     pub height: f32,
 }
 
+/// A mutation of a DOM node, identified by unique id.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum DomMutation {
+    Attributes {
+        target: String,
+        attributeName: String,
+        attributeNamespace: String,
+        newValue: Option<String>,
+    },
+    CharacterData {
+        target: String,
+        newValue: String,
+    },
+    ChildList {
+        target: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+        numChildren: usize,
+    },
+}
+
+/// A CSS style rule that matches a given element.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MatchedRule {
+    pub selectorText: String,
+    pub cssText: String,
+    /// The URL of the style sheet the rule belongs to, if it isn't inline.
+    pub href: Option<String>,
+    pub styleSheetIndex: usize,
+}
+
+/// The styles applied to an element, from its inline style and from the style sheets of
+/// its document, ordered from the least to the most specific.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AppliedStyles {
+    pub inlineStyle: String,
+    pub matchedRules: Vec<MatchedRule>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AutoMargins {
     pub top: bool,
@@ -202,6 +259,20 @@ pub enum DevtoolScriptControlMsg {
     GetLayout(PipelineId, String, IpcSender<Option<ComputedNodeLayout>>),
     /// Update a given node's attributes with a list of modifications.
     ModifyAttribute(PipelineId, String, Vec<Modification>),
+    /// Retrieve the computed value of every CSS property of the given node in the given pipeline.
+    GetComputedStyle(PipelineId, String, IpcSender<Option<Vec<(String, String)>>>),
+    /// Retrieve the style rules applying to the given node in the given pipeline.
+    GetAppliedStyles(PipelineId, String, IpcSender<Option<AppliedStyles>>),
+    /// Update a given node's inline style with a list of modifications.
+    ModifyInlineStyle(PipelineId, String, Vec<StyleModification>),
+    /// Request notifications of the mutations of the DOM of a given pipeline
+    /// (true if desired, false otherwise).
+    WantsDomMutations(PipelineId, bool),
+    /// Highlight the box model of the given node in the given pipeline, or clear the
+    /// highlight if there is no node.
+    HighlightNode(PipelineId, Option<String>),
+    /// Start or stop picking nodes with the mouse in the given pipeline.
+    SetNodePicking(PipelineId, bool),
     /// Request live console messages for a given pipeline (true if desired, false otherwise).
     WantsLiveNotifications(PipelineId, bool),
     /// Request live notifications for a given set of timeline events for a given pipeline.
@@ -217,6 +288,105 @@ pub enum DevtoolScriptControlMsg {
     RequestAnimationFrame(PipelineId, String),
     /// Direct the given pipeline to reload the current page.
     Reload(PipelineId),
+    /// Start debugging the scripts of the given pipeline, retrieving the sources that
+    /// were already loaded.
+    AttachDebugger(PipelineId, IpcSender<Vec<SourceInfo>>),
+    /// Stop debugging the scripts of the given pipeline, removing its breakpoints.
+    DetachDebugger(PipelineId),
+    /// Retrieve the text of the source with the given id.
+    GetSourceText(PipelineId, u32, IpcSender<Option<String>>),
+    /// Retrieve the lines and columns where a breakpoint can be set in the source with
+    /// the given id.
+    GetBreakpointPositions(PipelineId, u32, IpcSender<Vec<(u32, u32)>>),
+    /// Set a breakpoint at the given location.
+    SetBreakpoint(PipelineId, SourceLocation),
+    /// Remove the breakpoint at the given location.
+    RemoveBreakpoint(PipelineId, SourceLocation),
+    /// Pause the given pipeline as soon as it runs a script.
+    Interrupt(PipelineId),
+    /// Resume the paused pipeline, stepping until the given limit if there is one.
+    Resume(PipelineId, Option<ResumeLimit>),
+    /// Retrieve the call stack of the paused pipeline, from the youngest frame.
+    GetFrames(PipelineId, IpcSender<Vec<FrameInfo>>),
+    /// Retrieve the chain of scopes of the given frame of the paused pipeline, from the
+    /// innermost scope.
+    GetEnvironment(PipelineId, u32, IpcSender<Vec<EnvironmentInfo>>),
+    /// Start the sampling profiler, with the given sampling interval and maximum
+    /// duration in milliseconds.
+    StartProfiler(PipelineId, u64, u64),
+    /// Stop the sampling profiler, and send the profile once it has been gathered.
+    StopProfiler(PipelineId, IpcSender<Vec<u8>>),
+}
+
+/// A script source, as seen by a debugger.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SourceInfo {
+    pub id: u32,
+    pub url: String,
+    /// The URL of the source map of the source, if it has one.
+    pub sourceMapURL: Option<String>,
+    pub introductionType: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SourceLocation {
+    pub sourceId: u32,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// How far a paused debugger steps when it is resumed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum ResumeLimit {
+    /// Pause at the next line of the current frame, or in the caller.
+    Next,
+    /// Pause at the next line, entering the functions that are called.
+    Step,
+    /// Pause once the current frame returned.
+    Finish,
+}
+
+/// A value inspected by a debugger.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum DebuggerValue {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Object { class: String, name: Option<String> },
+}
+
+/// A frame of the call stack of a paused script.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FrameInfo {
+    /// The depth of the frame, which is 0 for the youngest frame.
+    pub id: u32,
+    pub type_: String,
+    pub displayName: Option<String>,
+    pub location: SourceLocation,
+    pub this: DebuggerValue,
+    pub arguments: Vec<DebuggerValue>,
+}
+
+/// A scope of a frame of a paused script.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnvironmentInfo {
+    /// One of "declarative", "function", "object" or "with".
+    pub type_: String,
+    pub functionName: Option<String>,
+    /// The bindings of the scope, if it is not backed by an object.
+    pub variables: Vec<(String, DebuggerValue)>,
+    /// The object backing the scope, if there is one.
+    pub object: Option<DebuggerValue>,
+}
+
+/// Why and where a script was paused.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PauseInfo {
+    /// One of "breakpoint", "debuggerStatement", "interrupted" or "resumeLimit".
+    pub why: String,
+    pub frame: FrameInfo,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -225,6 +395,14 @@ pub struct Modification {
     pub newValue: Option<String>,
 }
 
+/// A change to a CSS property of an inline style; a missing value removes the property.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StyleModification {
+    pub name: String,
+    pub value: Option<String>,
+    pub priority: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum LogLevel {
     Log,
@@ -232,15 +410,64 @@ pub enum LogLevel {
     Info,
     Warn,
     Error,
+    Clear,
+    Trace,
+    Table,
+    Dir,
+    Group,
+    GroupCollapsed,
+    GroupEnd,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConsoleMessage {
+    /// The message as printed to the standard output.
     pub message: String,
     pub logLevel: LogLevel,
     pub filename: String,
     pub lineNumber: usize,
     pub columnNumber: usize,
+    /// The arguments of the call, once the format specifiers of the first one have been
+    /// substituted. The message is used when there are none.
+    pub arguments: Vec<ConsoleArgument>,
+    /// The stack of the call, from the innermost frame.
+    pub stacktrace: Option<Vec<StackFrame>>,
+}
+
+/// An argument of a console API call.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ConsoleArgument {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Object(ObjectPreview),
+}
+
+/// The preview of an object passed to the console API, which is shown without
+/// inspecting the object.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ObjectPreview {
+    pub class: String,
+    /// The name of functions.
+    pub name: Option<String>,
+    /// The length of arrays.
+    pub length: Option<u32>,
+    /// The first elements of arrays, unless the object is nested too deep.
+    pub items: Option<Vec<ConsoleArgument>>,
+    /// The first own enumerable properties of other objects, unless the object is
+    /// nested too deep.
+    pub ownProperties: Vec<(String, ConsoleArgument)>,
+    pub ownPropertiesLength: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StackFrame {
+    pub filename: String,
+    pub functionName: String,
+    pub lineNumber: u32,
+    pub columnNumber: u32,
 }
 
 bitflags! {
@@ -279,7 +506,9 @@ pub struct ConsoleAPI {
     pub functionName: String,
     pub timeStamp: u64,
     pub private: bool,
-    pub arguments: Vec<String>,
+    pub arguments: Vec<serde_json::Value>,
+    pub columnNumber: u32,
+    pub stacktrace: Option<Vec<StackFrame>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -306,10 +535,19 @@ pub struct HttpRequest {
 pub struct HttpResponse {
     pub headers: Option<HeaderMap>,
     pub status: Option<(u16, Vec<u8>)>,
+    /// A preview of the response body, truncated to at most `RESPONSE_BODY_PREVIEW_LIMIT` bytes.
     pub body: Option<Vec<u8>>,
+    /// The size of the whole response body, in bytes.
+    pub body_size: usize,
+    /// The time spent receiving the response body, in milliseconds.
+    pub receive_time: u64,
+    pub from_cache: bool,
     pub pipeline_id: PipelineId,
 }
 
+/// The largest response body preview that is sent to the devtools.
+pub const RESPONSE_BODY_PREVIEW_LIMIT: usize = 1024 * 1024;
+
 #[derive(Debug)]
 pub enum NetworkEvent {
     HttpRequest(HttpRequest),
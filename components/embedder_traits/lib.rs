@@ -16,10 +16,14 @@ pub mod resources;
 use crossbeam_channel::{Receiver, Sender};
 use ipc_channel::ipc::IpcSender;
 use keyboard_types::KeyboardEvent;
+use msg::constellation_msg::HangAlert;
 use msg::constellation_msg::{InputMethodType, PipelineId, TopLevelBrowsingContextId};
 use servo_url::ServoUrl;
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Error, Formatter};
+use std::time::Duration;
 use webrender_api::units::{DeviceIntPoint, DeviceIntSize};
+use webrender_api::ImageKey;
 
 pub use webxr_api::MainThreadWaker as EventLoopWaker;
 
@@ -106,16 +110,69 @@ impl EmbedderReceiver {
     }
 }
 
-#[derive(Deserialize, Serialize)]
-pub enum PromptDefinition {
-    /// Show a message.
-    Alert(String, IpcSender<()>),
-    /// Ask a Ok/Cancel question.
-    OkCancel(String, IpcSender<PromptResult>),
+/// The identifier of a prompt shown by the embedder, unique for the session.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct PromptId(pub u64);
+
+/// The identifier of a screenshot requested by the embedder, which is sent back with it.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ScreenshotId(pub u64);
+
+/// A question asked to the user on behalf of a browser. Prompts are queued per
+/// browser by the constellation, and only the first prompt of each queue is shown.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum PromptRequest {
+    /// Show a message (`window.alert`).
+    Alert(String),
+    /// Ask an Ok/Cancel question (`window.confirm`).
+    Confirm(String),
+    /// Ask the user to enter text, with a default value (`window.prompt`).
+    Input(String, String),
+    /// Ask whether the document may be unloaded, after its `beforeunload` handlers
+    /// requested a confirmation.
+    BeforeUnload,
+    /// Ask for the credentials to access the given URL, within the given realm.
+    Authentication(ServoUrl, Option<String>),
+    /// Ask for a permission.
+    Permission(PermissionPrompt),
     /// Ask a Yes/No question.
-    YesNo(String, IpcSender<PromptResult>),
-    /// Ask the user to enter text.
-    Input(String, String, IpcSender<Option<String>>),
+    YesNo(String),
+}
+
+impl PromptRequest {
+    /// Whether the message of the prompt comes from content.
+    pub fn origin(&self) -> PromptOrigin {
+        match *self {
+            PromptRequest::Alert(..) | PromptRequest::Confirm(..) | PromptRequest::Input(..) => {
+                PromptOrigin::Untrusted
+            },
+            _ => PromptOrigin::Trusted,
+        }
+    }
+}
+
+/// The answer to a prompt.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum PromptResponse {
+    /// The prompt was closed by clicking on the primary button (ok/yes).
+    Accept,
+    /// The prompt was closed by clicking on the secondary button (cancel/no),
+    /// or dismissed.
+    Dismiss,
+    /// The text entered in an `Input` prompt.
+    Text(String),
+    /// The user name and password entered in an `Authentication` prompt.
+    Credentials(String, String),
+}
+
+/// A prompt requested by a thread outside of the content processes, such as
+/// the network or devtools threads.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PromptMsg {
+    /// The pipeline the prompt is shown for, if any.
+    pub pipeline_id: Option<PipelineId>,
+    pub request: PromptRequest,
+    pub sender: IpcSender<PromptResponse>,
 }
 
 #[derive(Deserialize, PartialEq, Serialize)]
@@ -139,24 +196,24 @@ pub enum PromptResult {
 
 #[derive(Deserialize, Serialize)]
 pub enum EmbedderMsg {
-    /// A status message to be displayed by the browser chrome.
-    Status(Option<String>),
     /// Alerts the embedder that the current page has changed its title.
     ChangePageTitle(Option<String>),
     /// Move the window to a point
     MoveTo(DeviceIntPoint),
     /// Resize the window to size
     ResizeTo(DeviceIntSize),
-    /// Show dialog to user
-    Prompt(PromptDefinition, PromptOrigin),
+    /// Show a prompt to the user. The answer is sent back with
+    /// `WindowEvent::RespondToPrompt`.
+    ShowPrompt(PromptId, PromptRequest),
+    /// A prompt was answered by other means (e.g. WebDriver) or its browser was
+    /// closed, and should be hidden.
+    HidePrompt(PromptId),
     /// Whether or not to allow a pipeline to load a url.
     AllowNavigationRequest(PipelineId, ServoUrl),
     /// Whether or not to allow script to open a new tab/browser
     AllowOpeningBrowser(IpcSender<bool>),
     /// A new browser was created by script
     BrowserCreated(TopLevelBrowsingContextId),
-    /// Wether or not to unload a document
-    AllowUnload(IpcSender<bool>),
     /// Sends an unconsumed key event back to the embedder.
     Keyboard(KeyboardEvent),
     /// Gets system clipboard contents
@@ -173,6 +230,13 @@ pub enum EmbedderMsg {
     HistoryChanged(Vec<ServoUrl>, usize),
     /// Enter or exit fullscreen
     SetFullscreenState(bool),
+    /// A video entered picture-in-picture. The embedder should present the given
+    /// image in a picture-in-picture surface. Sent again whenever the image changes.
+    EnterPictureInPicture(ImageKey, DeviceIntSize),
+    /// The picture-in-picture surface is no longer needed.
+    LeavePictureInPicture,
+    /// Get the media input and output devices that can be exposed to content.
+    GetMediaDevices(IpcSender<Vec<MediaDeviceInfo>>),
     /// The load of a page has begun
     LoadStart,
     /// The load of a page has completed
@@ -185,32 +249,51 @@ pub enum EmbedderMsg {
     GetSelectedBluetoothDevice(Vec<String>, IpcSender<Option<String>>),
     /// Open file dialog to select files. Set boolean flag to true allows to select multiple files.
     SelectFiles(Vec<FilterPattern>, bool, IpcSender<Option<Vec<String>>>),
-    /// Open interface to request permission specified by prompt.
-    PromptPermission(PermissionPrompt, IpcSender<PermissionRequest>),
     /// Request to present an IME to the user when an editable element is focused.
     ShowIME(InputMethodType),
     /// Request to hide the IME when the editable element is blurred.
     HideIME,
     /// Servo has shut down
     Shutdown,
-    /// Report a complete sampled profile
+    /// Report a complete sampled profile, in the format of the Gecko profiler
     ReportProfile(Vec<u8>),
     /// Notifies the embedder about media session events
     /// (i.e. when there is metadata for the active media session, playback state changes...).
     MediaSessionEvent(MediaSessionEvent),
     /// Report the status of Devtools Server
     OnDevtoolsStarted(Result<u16, ()>),
+    /// The user requested a context menu that was not prevented by content.
+    ShowContextMenu(ContextMenuInfo),
+    /// The link under the mouse cursor has changed. `None` means that no link is hovered.
+    HoveredLinkChanged(Option<ServoUrl>),
+    /// The state of the session, as requested by the embedder, has been collected.
+    SessionSaved(SessionState),
+    /// A screenshot requested by the embedder has been taken. It is encoded as PNG,
+    /// or None if the screenshot couldn't be taken.
+    ScreenshotCaptured(ScreenshotId, Option<Vec<u8>>),
+    /// The memory pressure signaled by the embedder has been handled.
+    MemoryPressureHandled(MemoryPressureReport),
+    /// The memory reports requested with `WindowEvent::CollectMemoryReports`.
+    MemoryReports(Vec<MemoryReport>),
+    /// A script or layout thread of the browser is hanging, with the task it runs and,
+    /// for a permanent hang, its native and JS stacks.
+    HangReport(HangAlert),
+    /// The number of subresources of the documents of the browser which were blocked or
+    /// rewritten by the content blocker.
+    BlockedContentReport(usize),
+    /// The size of the content of a browser which was asked to auto-resize with
+    /// `WindowEvent::SetAutoResize` changed.
+    ContentSizeChanged(DeviceIntSize),
 }
 
 impl Debug for EmbedderMsg {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         match *self {
-            EmbedderMsg::Status(..) => write!(f, "Status"),
             EmbedderMsg::ChangePageTitle(..) => write!(f, "ChangePageTitle"),
             EmbedderMsg::MoveTo(..) => write!(f, "MoveTo"),
             EmbedderMsg::ResizeTo(..) => write!(f, "ResizeTo"),
-            EmbedderMsg::Prompt(..) => write!(f, "Prompt"),
-            EmbedderMsg::AllowUnload(..) => write!(f, "AllowUnload"),
+            EmbedderMsg::ShowPrompt(..) => write!(f, "ShowPrompt"),
+            EmbedderMsg::HidePrompt(..) => write!(f, "HidePrompt"),
             EmbedderMsg::AllowNavigationRequest(..) => write!(f, "AllowNavigationRequest"),
             EmbedderMsg::Keyboard(..) => write!(f, "Keyboard"),
             EmbedderMsg::GetClipboardContents(..) => write!(f, "GetClipboardContents"),
@@ -221,12 +304,14 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::CloseBrowser => write!(f, "CloseBrowser"),
             EmbedderMsg::HistoryChanged(..) => write!(f, "HistoryChanged"),
             EmbedderMsg::SetFullscreenState(..) => write!(f, "SetFullscreenState"),
+            EmbedderMsg::EnterPictureInPicture(..) => write!(f, "EnterPictureInPicture"),
+            EmbedderMsg::LeavePictureInPicture => write!(f, "LeavePictureInPicture"),
+            EmbedderMsg::GetMediaDevices(..) => write!(f, "GetMediaDevices"),
             EmbedderMsg::LoadStart => write!(f, "LoadStart"),
             EmbedderMsg::LoadComplete => write!(f, "LoadComplete"),
             EmbedderMsg::Panic(..) => write!(f, "Panic"),
             EmbedderMsg::GetSelectedBluetoothDevice(..) => write!(f, "GetSelectedBluetoothDevice"),
             EmbedderMsg::SelectFiles(..) => write!(f, "SelectFiles"),
-            EmbedderMsg::PromptPermission(..) => write!(f, "PromptPermission"),
             EmbedderMsg::ShowIME(..) => write!(f, "ShowIME"),
             EmbedderMsg::HideIME => write!(f, "HideIME"),
             EmbedderMsg::Shutdown => write!(f, "Shutdown"),
@@ -235,10 +320,143 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::ReportProfile(..) => write!(f, "ReportProfile"),
             EmbedderMsg::MediaSessionEvent(..) => write!(f, "MediaSessionEvent"),
             EmbedderMsg::OnDevtoolsStarted(..) => write!(f, "OnDevtoolsStarted"),
+            EmbedderMsg::ShowContextMenu(..) => write!(f, "ShowContextMenu"),
+            EmbedderMsg::HoveredLinkChanged(..) => write!(f, "HoveredLinkChanged"),
+            EmbedderMsg::SessionSaved(..) => write!(f, "SessionSaved"),
+            EmbedderMsg::ScreenshotCaptured(..) => write!(f, "ScreenshotCaptured"),
+            EmbedderMsg::MemoryPressureHandled(..) => write!(f, "MemoryPressureHandled"),
+            EmbedderMsg::MemoryReports(..) => write!(f, "MemoryReports"),
+            EmbedderMsg::HangReport(..) => write!(f, "HangReport"),
+            EmbedderMsg::BlockedContentReport(..) => write!(f, "BlockedContentReport"),
+            EmbedderMsg::ContentSizeChanged(..) => write!(f, "ContentSizeChanged"),
         }
     }
 }
 
+/// The result of hit testing the point where a context menu was requested,
+/// used by the embedder to decide which entries to show.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ContextMenuInfo {
+    /// The position of the request, relative to the top-left of the browser.
+    pub point: DeviceIntPoint,
+    /// The URL of the document the context menu was requested in.
+    pub page_url: ServoUrl,
+    /// The resolved URL of the closest ancestor link, if any.
+    pub link_url: Option<ServoUrl>,
+    /// The resolved URL of the image under the cursor, if any.
+    pub image_url: Option<ServoUrl>,
+    /// The currently selected text in the document, if not empty.
+    pub selected_text: Option<String>,
+    /// Whether the target is an editable element (text input, textarea…).
+    pub is_editable: bool,
+}
+
+/// A measurement of the memory profiler, as shown in about:memory.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MemoryReport {
+    /// The path of the measurement, such as `["explicit", "url(...)", "dom", "HTMLDivElement"]`,
+    /// or `["ghost-windows", "window(...)", ...]` for the windows which leaked after their
+    /// document was discarded.
+    pub path: Vec<String>,
+    /// The measured size, in bytes.
+    pub size: usize,
+}
+
+/// The memory recovered after a memory pressure signal.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct MemoryPressureReport {
+    /// The bytes freed by garbage collecting the JS heaps of the script threads.
+    pub js_heap: usize,
+    /// The bytes of the decoded images evicted from the image caches.
+    pub images: usize,
+    /// The number of documents evicted from the session histories, which are
+    /// reloaded when the user navigates back to them.
+    pub documents: usize,
+}
+
+impl MemoryPressureReport {
+    /// Add the memory recovered by another part of the engine.
+    pub fn add(&mut self, other: MemoryPressureReport) {
+        self.js_heap += other.js_heap;
+        self.images += other.images;
+        self.documents += other.documents;
+    }
+}
+
+/// A task which ran on the event loop of a script thread for longer than the
+/// `dom.instrumentation.long_task_threshold` pref.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TaskReport {
+    /// The pipeline which the task ran for.
+    pub pipeline_id: PipelineId,
+    /// The kind of the task, such as `TimerEvent` or `NetworkEvent`.
+    pub category: String,
+    /// How long the task ran.
+    pub duration: Duration,
+    /// The number of tasks which were waiting in the task queue of the event loop
+    /// when it completed.
+    pub queue_length: usize,
+}
+
+/// A snapshot of all the browsers of a Servo instance, which can be used
+/// to restore them after a restart.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SessionState {
+    pub browsers: Vec<BrowserSessionState>,
+}
+
+impl SessionState {
+    /// Serialize the session to a compact binary representation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("Failed to serialize session state")
+    }
+
+    /// Deserialize a session previously serialized with `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SessionState, bincode::Error> {
+        bincode::deserialize(bytes)
+    }
+}
+
+/// The session history of a single top-level browsing context.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BrowserSessionState {
+    /// The session history entries, oldest first.
+    pub entries: Vec<SessionHistoryEntryState>,
+    /// The index of the current entry in `entries`.
+    pub current_index: usize,
+    /// The session storage of the browser, by origin.
+    pub session_storage: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// A single entry of the session history of a top-level browsing context.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SessionHistoryEntryState {
+    pub url: ServoUrl,
+    /// The state of the document, if it could be retrieved.
+    pub document_state: Option<SavedDocumentState>,
+}
+
+/// The parts of a document that the user may have changed and that are
+/// restored when the document is loaded again from a saved session.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SavedDocumentState {
+    /// The horizontal scroll offset of the viewport, in CSS pixels.
+    pub scroll_x: f64,
+    /// The vertical scroll offset of the viewport, in CSS pixels.
+    pub scroll_y: f64,
+    /// The state of the form controls of the document, in tree order.
+    pub form_controls: Vec<FormControlState>,
+}
+
+/// The state of a single form control.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum FormControlState {
+    /// The checkedness of a checkbox or a radio button.
+    Checked(bool),
+    /// The value of a text control.
+    Value(String),
+}
+
 /// Filter for file selection;
 /// the `String` content is expected to be extension (e.g, "doc", without the prefixing ".")
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -306,8 +524,28 @@ pub enum MediaSessionEvent {
     SetPositionState(MediaPositionState),
 }
 
-/// Enum with variants that match the DOM PermissionName enum
+/// The kind of a media input or output device.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum MediaDeviceKind {
+    AudioInput,
+    AudioOutput,
+    VideoInput,
+}
+
+/// A media input or output device known to the embedder.
 #[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MediaDeviceInfo {
+    /// An identifier of the device that persists across sessions.
+    pub device_id: String,
+    pub kind: MediaDeviceKind,
+    /// A human readable description of the device, e.g. "External USB Webcam".
+    pub label: String,
+    /// Devices that belong to the same physical device share a group identifier.
+    pub group_id: String,
+}
+
+/// Enum with variants that match the DOM PermissionName enum
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum PermissionName {
     Geolocation,
     Notifications,
@@ -323,15 +561,8 @@ pub enum PermissionName {
 }
 
 /// Information required to display a permission prompt
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum PermissionPrompt {
     Insecure(PermissionName),
     Request(PermissionName),
 }
-
-/// Status for prompting user for permission.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub enum PermissionRequest {
-    Granted,
-    Denied,
-}
@@ -187,6 +187,8 @@ bitflags! {
         const RTL_FLAG = 0x08;
         #[doc = "Set if word-break is set to keep-all."]
         const KEEP_ALL_FLAG = 0x10;
+        #[doc = "Set if white-space is set to break-spaces."]
+        const BREAK_SPACES_FLAG = 0x20;
     }
 }
 
@@ -198,6 +200,8 @@ pub struct ShapingOptions {
     pub letter_spacing: Option<Au>,
     /// Spacing to add between each word. Corresponds to the CSS 2.1 `word-spacing` property.
     pub word_spacing: Au,
+    /// The advance of a tab character. Corresponds to the CSS `tab-size` property.
+    pub tab_size: Au,
     /// The Unicode script property of the characters in this run.
     pub script: Script,
     /// Various flags.
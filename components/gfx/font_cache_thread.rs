@@ -12,11 +12,12 @@ use crate::platform::font_list::system_default_family;
 use crate::platform::font_list::SANS_SERIF_FONT_FAMILY;
 use crate::platform::font_template::FontTemplateData;
 use app_units::Au;
+use content_security_policy::CspList;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use net_traits::request::{Destination, RequestBuilder};
 use net_traits::{fetch_async, CoreResourceThread, FetchResponseMsg};
 use servo_atoms::Atom;
-use servo_url::ServoUrl;
+use servo_url::{ImmutableOrigin, ServoUrl};
 use std::borrow::ToOwned;
 use std::collections::HashMap;
 use std::ops::Deref;
@@ -113,12 +114,25 @@ pub enum Command {
         Au,
         IpcSender<webrender_api::FontInstanceKey>,
     ),
-    AddWebFont(LowercaseString, EffectiveSources, IpcSender<()>),
+    AddWebFont(
+        LowercaseString,
+        EffectiveSources,
+        WebFontRequester,
+        IpcSender<()>,
+    ),
     AddDownloadedWebFont(LowercaseString, ServoUrl, Vec<u8>, IpcSender<()>),
     Exit(IpcSender<()>),
     Ping,
 }
 
+/// The document which loads a web font, whose origin and Content Security Policies
+/// apply to the fetch of the font.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebFontRequester {
+    pub origin: ImmutableOrigin,
+    pub csp_list: Option<CspList>,
+}
+
 /// Reply messages sent from the font cache thread to the FontContext caller.
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Reply {
@@ -195,8 +209,8 @@ impl FontCache {
 
                     let _ = result.send(instance_key);
                 },
-                Command::AddWebFont(family_name, sources, result) => {
-                    self.handle_add_web_font(family_name, sources, result);
+                Command::AddWebFont(family_name, sources, requester, result) => {
+                    self.handle_add_web_font(family_name, sources, requester, result);
                 },
                 Command::AddDownloadedWebFont(family_name, url, bytes, result) => {
                     let templates = &mut self.web_families.get_mut(&family_name).unwrap();
@@ -216,6 +230,7 @@ impl FontCache {
         &mut self,
         family_name: LowercaseString,
         mut sources: EffectiveSources,
+        requester: WebFontRequester,
         sender: IpcSender<()>,
     ) {
         let src = if let Some(src) = sources.next() {
@@ -238,7 +253,10 @@ impl FontCache {
                     None => return,
                 };
 
-                let request = RequestBuilder::new(url.clone()).destination(Destination::Font);
+                let request = RequestBuilder::new(url.clone())
+                    .destination(Destination::Font)
+                    .origin(requester.origin.clone())
+                    .csp_list(requester.csp_list.clone());
 
                 let channel_to_self = self.channel_to_self.clone();
                 let bytes = Mutex::new(Vec::new());
@@ -247,7 +265,8 @@ impl FontCache {
                 fetch_async(request, &self.core_resource_thread, move |response| {
                     match response {
                         FetchResponseMsg::ProcessRequestBody |
-                        FetchResponseMsg::ProcessRequestEOF => (),
+                        FetchResponseMsg::ProcessRequestEOF |
+                        FetchResponseMsg::ProcessCspViolations(_) => (),
                         FetchResponseMsg::ProcessResponse(meta_result) => {
                             trace!(
                                 "@font-face {} metadata ok={:?}",
@@ -268,6 +287,7 @@ impl FontCache {
                                 let msg = Command::AddWebFont(
                                     family_name.clone(),
                                     sources.clone(),
+                                    requester.clone(),
                                     sender.clone(),
                                 );
                                 channel_to_self.send(msg).unwrap();
@@ -287,6 +307,7 @@ impl FontCache {
                                     let msg = Command::AddWebFont(
                                         family_name.clone(),
                                         sources.clone(),
+                                        requester.clone(),
                                         sender.clone(),
                                     );
                                     channel_to_self.send(msg).unwrap();
@@ -315,7 +336,7 @@ impl FontCache {
                 if found {
                     sender.send(()).unwrap();
                 } else {
-                    let msg = Command::AddWebFont(family_name, sources, sender);
+                    let msg = Command::AddWebFont(family_name, sources, requester, sender);
                     self.channel_to_self.send(msg).unwrap();
                 }
             },
@@ -477,12 +498,14 @@ impl FontCacheThread {
         &self,
         family: FamilyName,
         sources: EffectiveSources,
+        requester: WebFontRequester,
         sender: IpcSender<()>,
     ) {
         self.chan
             .send(Command::AddWebFont(
                 LowercaseString::new(&family.name),
                 sources,
+                requester,
                 sender,
             ))
             .unwrap();
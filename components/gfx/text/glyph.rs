@@ -577,14 +577,15 @@ impl<'a> GlyphStore {
         range: &Range<ByteIndex>,
         advance: Au,
         extra_word_spacing: Au,
+        extra_letter_spacing: Au,
     ) -> (usize, Au) {
         let mut index = 0;
         let mut current_advance = Au(0);
         for glyph in self.iter_glyphs_for_byte_range(range) {
             if glyph.char_is_space() {
-                current_advance += glyph.advance() + extra_word_spacing
+                current_advance += glyph.advance() + extra_word_spacing + extra_letter_spacing
             } else {
-                current_advance += glyph.advance()
+                current_advance += glyph.advance() + extra_letter_spacing
             }
             if current_advance > advance {
                 break;
@@ -595,13 +596,29 @@ impl<'a> GlyphStore {
     }
 
     #[inline]
-    pub fn advance_for_byte_range(&self, range: &Range<ByteIndex>, extra_word_spacing: Au) -> Au {
-        if range.begin() == ByteIndex(0) && range.end() == self.len() {
+    pub fn advance_for_byte_range(
+        &self,
+        range: &Range<ByteIndex>,
+        extra_word_spacing: Au,
+        extra_letter_spacing: Au,
+    ) -> Au {
+        if range.begin() == ByteIndex(0) &&
+            range.end() == self.len() &&
+            extra_letter_spacing == Au(0)
+        {
             self.total_advance + extra_word_spacing * self.total_spaces
         } else if !self.has_detailed_glyphs {
-            self.advance_for_byte_range_simple_glyphs(range, extra_word_spacing)
+            self.advance_for_byte_range_simple_glyphs(
+                range,
+                extra_word_spacing,
+                extra_letter_spacing,
+            )
         } else {
-            self.advance_for_byte_range_simple_glyphs(range, extra_word_spacing)
+            self.advance_for_byte_range_simple_glyphs(
+                range,
+                extra_word_spacing,
+                extra_letter_spacing,
+            )
         }
     }
 
@@ -610,13 +627,14 @@ impl<'a> GlyphStore {
         &self,
         range: &Range<ByteIndex>,
         extra_word_spacing: Au,
+        extra_letter_spacing: Au,
     ) -> Au {
         self.iter_glyphs_for_byte_range(range)
             .fold(Au(0), |advance, glyph| {
                 if glyph.char_is_space() {
-                    advance + glyph.advance() + extra_word_spacing
+                    advance + glyph.advance() + extra_word_spacing + extra_letter_spacing
                 } else {
-                    advance + glyph.advance()
+                    advance + glyph.advance() + extra_letter_spacing
                 }
             })
     }
@@ -375,12 +375,11 @@ impl Shaper {
                 if is_bidi_control(character) {
                     // Don't add any glyphs for bidi control chars
                 } else if character == '\t' {
-                    // Treat tabs in pre-formatted text as a fixed number of spaces.
+                    // Treat tabs in pre-formatted text as a fixed advance, given by `tab-size`.
                     //
                     // TODO: Proper tab stops.
-                    const TAB_COLS: i32 = 8;
-                    let (space_glyph_id, space_advance) = glyph_space_advance(self.font);
-                    let advance = Au::from_f64_px(space_advance) * TAB_COLS;
+                    let (space_glyph_id, _) = glyph_space_advance(self.font);
+                    let advance = options.tab_size;
                     let data =
                         GlyphData::new(space_glyph_id, advance, Default::default(), true, true);
                     glyphs.add_glyph_for_byte_index(byte_idx, character, &data);
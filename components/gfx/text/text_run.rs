@@ -34,6 +34,8 @@ pub struct TextRun {
     pub glyphs: Arc<Vec<GlyphRun>>,
     pub bidi_level: bidi::Level,
     pub extra_word_spacing: Au,
+    /// Spacing added after every glyph, to justify the text with `text-justify: inter-character`.
+    pub extra_letter_spacing: Au,
 }
 
 impl Drop for TextRun {
@@ -195,6 +197,7 @@ impl<'a> TextRun {
                 glyphs: Arc::new(glyphs),
                 bidi_level: bidi_level,
                 extra_word_spacing: Au(0),
+                extra_letter_spacing: Au(0),
             },
             break_at_zero,
         )
@@ -263,13 +266,26 @@ impl<'a> TextRun {
                 options
                     .flags
                     .insert(ShapingFlags::IS_WHITESPACE_SHAPING_FLAG);
-                glyphs.push(GlyphRun {
-                    glyph_store: font.shape_text(&text[whitespace.clone()], &options),
-                    range: Range::new(
-                        ByteIndex(whitespace.start as isize),
-                        ByteIndex(whitespace.len() as isize),
-                    ),
-                });
+
+                // With `white-space: break-spaces` there is a soft wrap opportunity after every
+                // space, so give each of them a glyph run of its own.
+                let break_spaces = options.flags.contains(ShapingFlags::BREAK_SPACES_FLAG);
+                let mut start = whitespace.start;
+                while start < whitespace.end {
+                    let end = if break_spaces {
+                        start + text[start..].chars().next().unwrap().len_utf8()
+                    } else {
+                        whitespace.end
+                    };
+                    glyphs.push(GlyphRun {
+                        glyph_store: font.shape_text(&text[start..end], &options),
+                        range: Range::new(
+                            ByteIndex(start as isize),
+                            ByteIndex((end - start) as isize),
+                        ),
+                    });
+                    start = end;
+                }
             }
             slice.start = whitespace.end;
         }
@@ -290,9 +306,11 @@ impl<'a> TextRun {
         self.natural_word_slices_in_range(range)
             .fold(Au(0), |advance, slice| {
                 advance +
-                    slice
-                        .glyphs
-                        .advance_for_byte_range(&slice.range, self.extra_word_spacing)
+                    slice.glyphs.advance_for_byte_range(
+                        &slice.range,
+                        self.extra_word_spacing,
+                        self.extra_letter_spacing,
+                    )
             })
     }
 
@@ -310,7 +328,11 @@ impl<'a> TextRun {
         slice_range: &Range<ByteIndex>,
     ) -> RunMetrics {
         RunMetrics::new(
-            glyphs.advance_for_byte_range(slice_range, self.extra_word_spacing),
+            glyphs.advance_for_byte_range(
+                slice_range,
+                self.extra_word_spacing,
+                self.extra_letter_spacing,
+            ),
             self.font_metrics.ascent,
             self.font_metrics.descent,
         )
@@ -373,6 +395,7 @@ impl<'a> TextRun {
                     &slice.range,
                     remaining,
                     self.extra_word_spacing,
+                    self.extra_letter_spacing,
                 );
                 remaining -= slice_advance;
                 slice_index
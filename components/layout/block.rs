@@ -1675,6 +1675,8 @@ impl BlockFlow {
                     .get_inherited_text()
                     .text_indent
                     .to_used_value(containing_block_size);
+                kid.as_mut_inline().text_align_last =
+                    self.fragment.style().get_inherited_text().text_align_last;
             }
         }
     }
@@ -2535,12 +2537,15 @@ impl Flow for BlockFlow {
     fn contains_roots_of_absolute_flow_tree(&self) -> bool {
         self.contains_relatively_positioned_fragments() ||
             self.is_root() ||
-            self.fragment.has_filter_transform_or_perspective()
+            self.fragment.has_filter_transform_or_perspective() ||
+            self.fragment.will_change_creates_containing_block()
     }
 
     /// Returns true if this is an absolute containing block.
     fn is_absolute_containing_block(&self) -> bool {
-        self.contains_positioned_fragments() || self.fragment.has_filter_transform_or_perspective()
+        self.contains_positioned_fragments() ||
+            self.fragment.has_filter_transform_or_perspective() ||
+            self.fragment.will_change_creates_containing_block()
     }
 
     fn update_late_computed_inline_position_if_necessary(&mut self, inline_position: Au) {
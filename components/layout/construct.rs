@@ -32,7 +32,8 @@ use crate::fragment::{
 use crate::fragment::{
     TableColumnFragmentInfo, UnscannedTextFragmentInfo, WhitespaceStrippingResult,
 };
-use crate::inline::{InlineFlow, InlineFragmentNodeFlags, InlineFragmentNodeInfo};
+use crate::inline::{FirstLineStyles, InlineFlow, InlineFragmentContext};
+use crate::inline::{InlineFragmentNodeFlags, InlineFragmentNodeInfo};
 use crate::linked_list::prepend_from;
 use crate::list_item::{ListItemFlow, ListStyleTypeContent};
 use crate::multicol::{MulticolColumnFlow, MulticolFlow};
@@ -44,10 +45,11 @@ use crate::table_colgroup::TableColGroupFlow;
 use crate::table_row::TableRowFlow;
 use crate::table_rowgroup::TableRowGroupFlow;
 use crate::table_wrapper::TableWrapperFlow;
-use crate::text::TextRunScanner;
+use crate::text::{self, TextRunScanner};
 use crate::traversal::PostorderNodeMutTraversal;
 use crate::wrapper::{LayoutNodeLayoutData, TextContent, ThreadSafeLayoutNodeHelpers};
 use crate::ServoArc;
+use app_units::Au;
 use script_layout_interface::wrapper_traits::{
     PseudoElementType, ThreadSafeLayoutElement, ThreadSafeLayoutNode,
 };
@@ -65,14 +67,20 @@ use style::computed_values::empty_cells::T as EmptyCells;
 use style::computed_values::float::T as Float;
 use style::computed_values::list_style_position::T as ListStylePosition;
 use style::computed_values::position::T as Position;
-use style::context::SharedStyleContext;
+use style::context::{CascadeInputs, SharedStyleContext};
 use style::dom::TElement;
+use style::font_metrics::ServoMetricsProvider;
 use style::logical_geometry::Direction;
-use style::properties::ComputedValues;
+use style::properties::{self, ComputedValues};
+use style::rule_cache::RuleCacheConditions;
 use style::selector_parser::{PseudoElement, RestyleDamage};
 use style::servo::restyle_damage::ServoRestyleDamage;
+use style::values::computed::font::FontSize;
+use style::values::computed::NonNegativeLength;
 use style::values::generics::counters::ContentItem;
+use style::values::generics::text::InitialLetter;
 use style::values::generics::url::UrlOrNone as ImageUrlOrNone;
+use ucd::{Codepoint, UnicodeCategory};
 
 /// The results of flow construction for a DOM node.
 #[derive(Clone)]
@@ -493,6 +501,37 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
             return;
         }
 
+        // These fragments hold the first formatted line of the node unless an in-flow block came
+        // before them, so `::first-letter` and `::first-line` apply to them.
+        let is_first_formatted_line = flow.base().children.iter().all(|kid| {
+            kid.base().flags.is_float() ||
+                kid.base()
+                    .flags
+                    .contains(FlowFlags::IS_ABSOLUTELY_POSITIONED)
+        });
+        let (first_letter_style, first_line_style) = match node.as_element() {
+            Some(element) if is_first_formatted_line => (
+                element.part_pseudo_style(&PseudoElement::FirstLetter),
+                element.part_pseudo_style(&PseudoElement::FirstLine),
+            ),
+            _ => (None, None),
+        };
+        if let Some(first_letter_style) = first_letter_style {
+            if let Some(float_flow) =
+                self.split_first_letter(&mut fragments.fragments, node, first_letter_style)
+            {
+                legalizer.add_child::<ConcreteThreadSafeLayoutNode::ConcreteElement>(
+                    self.style_context(),
+                    flow,
+                    float_flow,
+                );
+            }
+            if fragments.fragments.is_empty() {
+                absolute_descendants.push_descendants(fragments.absolute_descendants);
+                return;
+            }
+        }
+
         // Build a list of all the inline-block fragments before fragments is moved.
         let mut inline_block_flows = vec![];
         for fragment in &fragments.fragments {
@@ -520,10 +559,18 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
                     mem::replace(&mut fragments.fragments, LinkedList::new()),
                 )
             });
-        let mut inline_flow_ref = FlowRef::new(Arc::new(InlineFlow::from_fragments(
+        let mut inline_flow = InlineFlow::from_fragments(
             scanned_fragments,
             node.style(self.style_context()).writing_mode,
-        )));
+        );
+        inline_flow.first_line = first_line_style.map(|first_line_style| {
+            Box::new(self.first_line_styles(
+                &inline_flow.fragments.fragments,
+                node,
+                first_line_style,
+            ))
+        });
+        let mut inline_flow_ref = FlowRef::new(Arc::new(inline_flow));
 
         // Add all the inline-block fragments as children of the inline flow.
         for inline_block_flow in &inline_block_flows {
@@ -563,6 +610,261 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
         )
     }
 
+    /// Cascades the rules that matched `style` again with other parents, since the parts of an
+    /// element inside `::first-letter` or `::first-line` inherit from those pseudo-elements.
+    fn cascade_with_parent(
+        &self,
+        style: &ServoArc<ComputedValues>,
+        parent: &ComputedValues,
+        parent_ignoring_first_line: &ComputedValues,
+    ) -> ServoArc<ComputedValues> {
+        let inputs = CascadeInputs::new_from_style(style);
+        let rules = match inputs.rules {
+            Some(ref rules) => rules,
+            None => return style.clone(),
+        };
+        let context = self.style_context();
+        properties::cascade::<ConcreteThreadSafeLayoutNode::ConcreteElement>(
+            context.stylist.device(),
+            style.pseudo(),
+            rules,
+            &context.guards,
+            Some(parent),
+            Some(parent_ignoring_first_line),
+            Some(parent),
+            inputs.visited_rules.as_ref(),
+            &ServoMetricsProvider,
+            context.quirks_mode(),
+            /* rule_cache = */ None,
+            &mut RuleCacheConditions::default(),
+            /* element = */ None,
+        )
+    }
+
+    /// Splits the first letter of the node out of the first text fragment that has one, styled
+    /// with `::first-letter`. If the letter floats, it is returned as a float flow to be placed
+    /// before the remaining fragments.
+    ///
+    /// https://drafts.csswg.org/css-pseudo-4/#first-letter-pseudo
+    fn split_first_letter(
+        &mut self,
+        fragments: &mut LinkedList<Fragment>,
+        node: &ConcreteThreadSafeLayoutNode,
+        first_letter_style: ServoArc<ComputedValues>,
+    ) -> Option<FlowRef> {
+        let mut found = None;
+        for (index, fragment) in fragments.iter().enumerate() {
+            match fragment.specific {
+                SpecificFragmentInfo::UnscannedText(ref info) => {
+                    if let Some(range) = first_letter_range(&info.text) {
+                        found = Some((index, range));
+                        break;
+                    }
+                },
+                SpecificFragmentInfo::InlineAbsolute(_) |
+                SpecificFragmentInfo::InlineAbsoluteHypothetical(_) => {},
+                _ => return None,
+            }
+        }
+        let (index, (start, end)) = found?;
+
+        let mut rest = fragments.split_off(index);
+        let fragment = rest.pop_front().unwrap();
+        let text = match fragment.specific {
+            SpecificFragmentInfo::UnscannedText(ref info) => info.text.clone(),
+            _ => unreachable!(),
+        };
+        let piece =
+            |text: &str, style: &ServoArc<ComputedValues>, flags: InlineFragmentNodeFlags| {
+                let mut piece = fragment.clone();
+                piece.style = style.clone();
+                piece.specific = SpecificFragmentInfo::UnscannedText(Box::new(
+                    UnscannedTextFragmentInfo::new(text.into(), None),
+                ));
+                if let Some(ref mut inline_context) = piece.inline_context {
+                    for node in &mut inline_context.nodes {
+                        node.flags.remove(flags);
+                    }
+                }
+                piece
+            };
+
+        if start > 0 {
+            fragments.push_back(piece(
+                &text[..start],
+                &fragment.style,
+                InlineFragmentNodeFlags::LAST_FRAGMENT_OF_ELEMENT,
+            ));
+        }
+
+        let mut letter_style =
+            self.cascade_with_parent(&first_letter_style, &fragment.style, &fragment.style);
+        let float_kind = match letter_style.get_text().initial_letter {
+            InitialLetter::Specified(size, _) => {
+                let float = if letter_style.writing_mode.is_bidi_ltr() {
+                    Float::Left
+                } else {
+                    Float::Right
+                };
+                ServoArc::make_mut(&mut letter_style)
+                    .mutate_box()
+                    .set_float(float);
+                self.size_initial_letter(
+                    &mut letter_style,
+                    &node.style(self.style_context()),
+                    size,
+                );
+                FloatKind::from_property(float)
+            },
+            InitialLetter::Normal => FloatKind::from_property(letter_style.get_box().float),
+        };
+        let all_flags = InlineFragmentNodeFlags::FIRST_FRAGMENT_OF_ELEMENT |
+            InlineFragmentNodeFlags::LAST_FRAGMENT_OF_ELEMENT;
+        let mut letter = piece(&text[start..end], &letter_style, all_flags);
+
+        let float_flow = if float_kind.is_some() {
+            // A floated letter lays out in a block of its own.
+            letter.inline_context = None;
+            let mut unscanned = LinkedList::new();
+            unscanned.push_back(letter);
+            let scanned = with_thread_local_font_context(self.layout_context, |font_context| {
+                TextRunScanner::new().scan_for_runs(font_context, unscanned)
+            });
+            let mut inline_flow = InlineFlow::from_fragments(scanned, letter_style.writing_mode);
+            inline_flow.minimum_line_metrics =
+                with_thread_local_font_context(self.layout_context, |font_context| {
+                    inline_flow.minimum_line_metrics(font_context, &letter_style)
+                });
+            let mut inline_flow_ref = FlowRef::new(Arc::new(inline_flow));
+            inline_flow_ref.finish();
+
+            let block_fragment = Fragment::from_opaque_node_and_style(
+                fragment.node,
+                fragment.pseudo,
+                letter_style.clone(),
+                fragment.selected_style.clone(),
+                fragment.restyle_damage,
+                SpecificFragmentInfo::Generic,
+            );
+            let mut float_flow = FlowRef::new(Arc::new(BlockFlow::from_fragment_and_float_kind(
+                block_fragment,
+                float_kind,
+            )));
+            float_flow.add_new_child(inline_flow_ref);
+            float_flow.finish();
+            Some(float_flow)
+        } else {
+            // An inline letter is an inline box around the text it contains.
+            letter
+                .inline_context
+                .get_or_insert_with(InlineFragmentContext::new)
+                .nodes
+                .insert(
+                    0,
+                    InlineFragmentNodeInfo {
+                        address: fragment.node,
+                        style: letter_style.clone(),
+                        selected_style: fragment.selected_style.clone(),
+                        pseudo: fragment.pseudo,
+                        flags: all_flags,
+                    },
+                );
+            fragments.push_back(letter);
+            None
+        };
+
+        if end < text.len() {
+            fragments.push_back(piece(
+                &text[end..],
+                &fragment.style,
+                InlineFragmentNodeFlags::FIRST_FRAGMENT_OF_ELEMENT,
+            ));
+        }
+        fragments.append(&mut rest);
+        float_flow
+    }
+
+    /// Scales the font of an initial letter so that it spans the given number of lines of its
+    /// block, from the cap height of the first line to the baseline of the last.
+    ///
+    /// https://drafts.csswg.org/css-inline/#sizing-initial-letters
+    fn size_initial_letter(
+        &self,
+        letter_style: &mut ServoArc<ComputedValues>,
+        block_style: &ComputedValues,
+        size: f32,
+    ) {
+        let metrics = with_thread_local_font_context(self.layout_context, |font_context| {
+            text::font_metrics_for_style(font_context, block_style.clone_font())
+        });
+        if metrics.ascent == Au(0) {
+            return;
+        }
+        // We don't have the cap height of fonts, so approximate it with the ascent.
+        let line_height = text::line_height_from_style(block_style, &metrics).to_f32_px();
+        let ascent = metrics.ascent.to_f32_px();
+        let scale = ((size - 1.).max(0.) * line_height + ascent) / ascent;
+        let font_size = block_style.get_font().font_size.size().px() * scale;
+
+        let font = ServoArc::make_mut(letter_style).mutate_font();
+        font.set_font_size(FontSize {
+            size: NonNegativeLength::new(font_size),
+            keyword_info: None,
+        });
+        font.compute_font_hash();
+    }
+
+    /// Computes the styles that the given fragments and their inline ancestors take on the first
+    /// line of the node, by cascading them again with the `::first-line` style as their parent.
+    fn first_line_styles(
+        &self,
+        fragments: &[Fragment],
+        node: &ConcreteThreadSafeLayoutNode,
+        first_line_style: ServoArc<ComputedValues>,
+    ) -> FirstLineStyles {
+        let block_style = node.style(self.style_context());
+        let mut styles: Vec<(ServoArc<ComputedValues>, ServoArc<ComputedValues>)> = vec![];
+        for fragment in fragments {
+            let mut parent = block_style.clone();
+            let mut first_line_parent = first_line_style.clone();
+            let ancestors = fragment
+                .inline_context
+                .iter()
+                .flat_map(|inline_context| inline_context.nodes.iter().rev())
+                .map(|node| &node.style);
+            let own_style = match fragment.specific {
+                SpecificFragmentInfo::ScannedText(_) => Some(&fragment.style),
+                _ => None,
+            };
+            for style in ancestors.chain(own_style) {
+                let first_line_variant = match styles
+                    .iter()
+                    .find(|&&(ref original, _)| ServoArc::ptr_eq(original, style))
+                {
+                    Some(&(_, ref first_line_variant)) => first_line_variant.clone(),
+                    None => {
+                        let first_line_variant = if ServoArc::ptr_eq(style, &parent) {
+                            first_line_parent.clone()
+                        } else {
+                            self.cascade_with_parent(style, &first_line_parent, &parent)
+                        };
+                        styles.push((style.clone(), first_line_variant.clone()));
+                        first_line_variant
+                    },
+                };
+                parent = style.clone();
+                first_line_parent = first_line_variant;
+            }
+        }
+
+        FirstLineStyles {
+            address: node.opaque(),
+            style: first_line_style,
+            styles: styles,
+            originals: None,
+        }
+    }
+
     fn build_block_flow_using_construction_result_of_child(
         &mut self,
         flow: &mut FlowRef,
@@ -1697,6 +1999,20 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
                 return false;
             }
 
+            // The styles of `::first-letter` and `::first-line` are baked into the fragments
+            // they apply to.
+            if let Some(element) = node.as_element() {
+                if element
+                    .part_pseudo_style(&PseudoElement::FirstLetter)
+                    .is_some() ||
+                    element
+                        .part_pseudo_style(&PseudoElement::FirstLine)
+                        .is_some()
+                {
+                    return false;
+                }
+            }
+
             let damage = node.restyle_damage();
             let mut data = node.mutate_layout_data().unwrap();
 
@@ -2018,6 +2334,7 @@ where
 
     fn construction_result_mut(self, data: &mut LayoutData) -> &mut ConstructionResult {
         match self.get_pseudo_element_type() {
+            PseudoElementType::Backdrop => &mut data.backdrop_flow_construction_result,
             PseudoElementType::Before => &mut data.before_flow_construction_result,
             PseudoElementType::After => &mut data.after_flow_construction_result,
             PseudoElementType::DetailsSummary => &mut data.details_summary_flow_construction_result,
@@ -2135,6 +2452,53 @@ pub fn strip_ignorable_whitespace_from_end(this: &mut LinkedList<Fragment>) {
 
 /// If the 'unicode-bidi' property has a value other than 'normal', return the bidi control codes
 /// to inject before and after the text content of the element.
+/// Returns the byte range of the first typographic letter unit of the given text, along with the
+/// punctuation around it, or `None` if the text has none.
+///
+/// https://drafts.csswg.org/css-pseudo-4/#first-letter-pattern
+fn first_letter_range(text: &str) -> Option<(usize, usize)> {
+    fn is_punctuation(character: char) -> bool {
+        match character.category() {
+            UnicodeCategory::OpenPunctuation |
+            UnicodeCategory::ClosePunctuation |
+            UnicodeCategory::InitialPunctuation |
+            UnicodeCategory::FinalPunctuation |
+            UnicodeCategory::OtherPunctuation => true,
+            _ => false,
+        }
+    }
+
+    fn is_mark(character: char) -> bool {
+        match character.category() {
+            UnicodeCategory::NonspacingMark |
+            UnicodeCategory::SpacingMark |
+            UnicodeCategory::EnclosingMark => true,
+            _ => false,
+        }
+    }
+
+    let mut characters = text.char_indices().peekable();
+    while characters.peek().map_or(false, |&(_, c)| c.is_whitespace()) {
+        characters.next();
+    }
+    let start = characters.peek()?.0;
+    while characters.peek().map_or(false, |&(_, c)| is_punctuation(c)) {
+        characters.next();
+    }
+    match characters.next() {
+        Some((_, letter)) if !letter.is_whitespace() => {},
+        _ => return None,
+    }
+    while characters
+        .peek()
+        .map_or(false, |&(_, c)| is_mark(c) || is_punctuation(c))
+    {
+        characters.next();
+    }
+    let end = characters.peek().map_or(text.len(), |&(index, _)| index);
+    Some((start, end))
+}
+
 fn bidi_control_chars(style: &ServoArc<ComputedValues>) -> Option<(&'static str, &'static str)> {
     use style::computed_values::direction::T::*;
     use style::computed_values::unicode_bidi::T::*;
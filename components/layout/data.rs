@@ -32,6 +32,8 @@ pub struct LayoutData {
     /// details.
     pub flow_construction_result: ConstructionResult,
 
+    pub backdrop_flow_construction_result: ConstructionResult,
+
     pub before_flow_construction_result: ConstructionResult,
 
     pub after_flow_construction_result: ConstructionResult,
@@ -49,6 +51,7 @@ impl LayoutData {
     pub fn new() -> LayoutData {
         Self {
             flow_construction_result: ConstructionResult::None,
+            backdrop_flow_construction_result: ConstructionResult::None,
             before_flow_construction_result: ConstructionResult::None,
             after_flow_construction_result: ConstructionResult::None,
             details_summary_flow_construction_result: ConstructionResult::None,
@@ -19,6 +19,7 @@ use crate::display_list::items::{ClippingRegion, DisplayItem, DisplayItemMetadat
 use crate::display_list::items::{CommonDisplayItem, DisplayListSection};
 use crate::display_list::items::{IframeDisplayItem, OpaqueNode};
 use crate::display_list::items::{PopAllTextShadowsDisplayItem, PushTextShadowDisplayItem};
+use crate::display_list::items::ScrollOffsetMap;
 use crate::display_list::items::{StackingContext, StackingContextType, StickyFrameData};
 use crate::display_list::items::{TextOrientation, WebRenderImageInfo};
 use crate::display_list::ToLayout;
@@ -47,6 +48,7 @@ use range::Range;
 use script_traits::IFrameSize;
 use servo_config::opts;
 use servo_geometry::{self, MaxRect};
+use std::cmp::max;
 use std::default::Default;
 use std::f32;
 use std::mem;
@@ -129,6 +131,16 @@ static THREAD_TINT_COLORS: [ColorF; 8] = [
 // See: https://github.com/servo/servo/issues/17230#issuecomment-564307277
 const MAX_GLYPHS_PER_TEXT_RUN: usize = 2000;
 
+// The area of the elements given their WebRender nodes ahead of the changes named by their
+// `will-change`, as a number of viewports. Past it, `will-change` only establishes stacking
+// contexts and containing blocks, so that a page which sets it on every element doesn't
+// make WebRender build a node for each of them.
+const WILL_CHANGE_BUDGET_VIEWPORTS: f32 = 3.0;
+
+// The number of elements given their WebRender nodes ahead of the changes named by their
+// `will-change`, which keeps many small elements from making as many nodes.
+const WILL_CHANGE_BUDGET_ELEMENTS: usize = 64;
+
 pub struct InlineNodeBorderInfo {
     is_first_fragment_of_element: bool,
     is_last_fragment_of_element: bool,
@@ -167,6 +179,9 @@ pub struct StackingContextCollectionState {
 
     pub clip_scroll_nodes: Vec<ClipScrollNode>,
 
+    /// The scroll origins of the scroll frames whose content overflows to their start.
+    pub scroll_origins: ScrollOffsetMap,
+
     /// The current stacking context id, used to keep track of state when building.
     /// recursively building and processing the display list.
     pub current_stacking_context_id: StackingContextId,
@@ -200,10 +215,21 @@ pub struct StackingContextCollectionState {
 
     /// The flow parent's content box, used to calculate sticky constraints.
     parent_stacking_relative_content_box: Rect<Au>,
+
+    /// The area, in square pixels, which the elements given WebRender nodes because of
+    /// `will-change` can still cover.
+    will_change_area_budget: f32,
+
+    /// The number of elements which can still be given WebRender nodes because of
+    /// `will-change`.
+    will_change_element_budget: usize,
 }
 
 impl StackingContextCollectionState {
-    pub fn new(pipeline_id: PipelineId) -> StackingContextCollectionState {
+    pub fn new(
+        pipeline_id: PipelineId,
+        viewport_size: Size2D<Au>,
+    ) -> StackingContextCollectionState {
         let root_clip_indices =
             ClippingAndScrolling::simple(ClipScrollNodeIndex::root_scroll_node());
 
@@ -225,6 +251,7 @@ impl StackingContextCollectionState {
             root_stacking_context: StackingContext::root(),
             stacking_context_info,
             clip_scroll_nodes,
+            scroll_origins: ScrollOffsetMap::default(),
             current_stacking_context_id: StackingContextId::root(),
             current_real_stacking_context_id: StackingContextId::root(),
             next_stacking_context_id: StackingContextId::root().next(),
@@ -234,7 +261,25 @@ impl StackingContextCollectionState {
             clip_stack: Vec::new(),
             containing_block_clip_stack: Vec::new(),
             parent_stacking_relative_content_box: Rect::zero(),
+            will_change_area_budget: viewport_size.width.to_f32_px() *
+                viewport_size.height.to_f32_px() *
+                WILL_CHANGE_BUDGET_VIEWPORTS,
+            will_change_element_budget: WILL_CHANGE_BUDGET_ELEMENTS,
+        }
+    }
+
+    /// Whether a fragment can be given the WebRender node which a change named by its
+    /// `will-change` would need, ahead of the change. This takes the area of the fragment
+    /// from the budget of the display list if it can.
+    fn promote_for_will_change(&mut self, fragment: &Fragment) -> bool {
+        let size = fragment.border_box.size;
+        let area = size.inline.to_f32_px() * size.block.to_f32_px();
+        if self.will_change_element_budget == 0 || area > self.will_change_area_budget {
+            return false;
         }
+        self.will_change_element_budget -= 1;
+        self.will_change_area_budget -= area;
+        true
     }
 
     fn allocate_stacking_context_info(
@@ -309,6 +354,9 @@ pub struct DisplayListBuildState<'a> {
     /// A vector of ClipScrollNodes which will be given ids during WebRender DL conversion.
     pub clip_scroll_nodes: Vec<ClipScrollNode>,
 
+    /// The scroll origins of the scroll frames whose content overflows to their start.
+    pub scroll_origins: ScrollOffsetMap,
+
     /// The items in this display list.
     pub items: FnvHashMap<StackingContextId, Vec<DisplayItem>>,
 
@@ -343,6 +391,7 @@ impl<'a> DisplayListBuildState<'a> {
             items: FnvHashMap::default(),
             stacking_context_info: state.stacking_context_info,
             clip_scroll_nodes: state.clip_scroll_nodes,
+            scroll_origins: state.scroll_origins,
             processing_scrolling_overflow_element: false,
             current_stacking_context_id: StackingContextId::root(),
             current_clipping_and_scrolling: ClippingAndScrolling::simple(
@@ -460,6 +509,7 @@ impl<'a> DisplayListBuildState<'a> {
         DisplayList {
             list: list,
             clip_scroll_nodes: self.clip_scroll_nodes,
+            scroll_origins: self.scroll_origins,
         }
     }
 
@@ -631,7 +681,9 @@ impl Fragment {
     ) -> bool {
         self.stacking_context_id = state.allocate_stacking_context_info(StackingContextType::Real);
 
-        let established_reference_frame = if self.can_establish_reference_frame() {
+        let establishes_reference_frame = self.can_establish_reference_frame() ||
+            (self.will_change_transform() && state.promote_for_will_change(self));
+        let established_reference_frame = if establishes_reference_frame {
             // WebRender currently creates reference frames automatically, so just add
             // a placeholder node to allocate a ClipScrollNodeIndex for this reference frame.
             self.established_reference_frame =
@@ -1963,6 +2015,7 @@ impl Fragment {
             self.transform_matrix(&border_box),
             self.style().get_used_transform_style().to_layout(),
             self.perspective_matrix(&border_box),
+            self.style().get_box().backface_visibility.to_layout(),
             parent_clipping_and_scrolling,
             established_reference_frame,
         )
@@ -2340,10 +2393,19 @@ impl BlockFlow {
 
     /// Returns true if this fragment may establish a reference frame and this block
     /// creates a stacking context. Both are necessary in order to establish a reference
-    /// frame.
-    fn is_reference_frame(&self, context_type: Option<StackingContextType>) -> bool {
+    /// frame. A fragment whose `will-change` names `transform` gets its reference frame
+    /// before its transform changes, within the budget of the display list.
+    fn is_reference_frame(
+        &self,
+        state: &mut StackingContextCollectionState,
+        context_type: Option<StackingContextType>,
+    ) -> bool {
         match context_type {
-            Some(StackingContextType::Real) => self.fragment.can_establish_reference_frame(),
+            Some(StackingContextType::Real) => {
+                self.fragment.can_establish_reference_frame() ||
+                    (self.fragment.will_change_transform() &&
+                        state.promote_for_will_change(&self.fragment))
+            },
             _ => false,
         }
     }
@@ -2366,7 +2428,7 @@ impl BlockFlow {
             state.current_real_stacking_context_id = self.base.stacking_context_id;
         }
 
-        let established_reference_frame = if self.is_reference_frame(stacking_context_type) {
+        let established_reference_frame = if self.is_reference_frame(state, stacking_context_type) {
             // WebRender currently creates reference frames automatically, so just add
             // a placeholder node to allocate a ClipScrollNodeIndex for this reference frame.
             Some(state.add_clip_scroll_node(ClipScrollNode::placeholder()))
@@ -2389,7 +2451,9 @@ impl BlockFlow {
         let creates_containing_block = !flags
             .contains(StackingContextCollectionFlags::POSITION_NEVER_CREATES_CONTAINING_BLOCK);
         let abspos_containing_block = established_reference_frame.is_some() ||
-            (creates_containing_block && self.positioning() != StylePosition::Static);
+            (creates_containing_block &&
+                (self.positioning() != StylePosition::Static ||
+                    self.fragment.will_change_creates_containing_block()));
         if abspos_containing_block {
             state.containing_block_clipping_and_scrolling = state.current_clipping_and_scrolling;
         }
@@ -2593,7 +2657,9 @@ impl BlockFlow {
             self.base.overflow.scroll.size.width > content_box.size.width ||
             self.base.overflow.scroll.size.height > content_box.size.height ||
             StyleOverflow::Hidden == self.fragment.style.get_box().overflow_x ||
-            StyleOverflow::Hidden == self.fragment.style.get_box().overflow_y;
+            StyleOverflow::Hidden == self.fragment.style.get_box().overflow_y ||
+            (self.fragment.will_change_scroll_position() &&
+                state.promote_for_will_change(&self.fragment));
 
         self.mark_scrolling_overflow(has_scrolling_overflow);
         if !has_scrolling_overflow {
@@ -2626,10 +2692,26 @@ impl BlockFlow {
 
         let external_id =
             ExternalScrollId(self.fragment.unique_id(), state.pipeline_id.to_webrender());
+
+        // The content of a right-to-left scroll frame overflows to its left, and the frame is
+        // initially scrolled to the right of its content box.
+        let mut content_rect = Rect::new(content_box.origin, content_size);
+        let overflow_left = border_box.origin.x + self.base.overflow.scroll.origin.x;
+        if !self.fragment.style.writing_mode.is_bidi_ltr() && overflow_left < content_box.origin.x
+        {
+            let scroll_origin = content_box.origin.x - overflow_left;
+            content_rect.origin.x = overflow_left;
+            content_rect.size.width += scroll_origin;
+            state.scroll_origins.insert(
+                external_id,
+                LayoutVector2D::new(scroll_origin.to_f32_px(), 0.0),
+            );
+        }
+
         let new_clip_scroll_index = state.add_clip_scroll_node(ClipScrollNode {
             parent_index: self.clipping_and_scrolling().scrolling,
             clip: clip,
-            content_rect: Rect::new(content_box.origin, content_size).to_layout(),
+            content_rect: content_rect.to_layout(),
             node_type: ClipScrollNodeType::ScrollFrame(sensitivity, external_id),
         });
 
@@ -2972,9 +3054,9 @@ fn convert_text_run_to_glyphs(
     for slice in text_run.natural_word_slices_in_visual_order(&range) {
         for glyph in slice.glyphs.iter_glyphs_for_byte_range(&slice.range) {
             let glyph_advance = if glyph.char_is_space() {
-                glyph.advance() + text_run.extra_word_spacing
+                glyph.advance() + text_run.extra_word_spacing + text_run.extra_letter_spacing
             } else {
-                glyph.advance()
+                glyph.advance() + text_run.extra_letter_spacing
             };
             if !slice.glyphs.is_whitespace() {
                 let glyph_offset = glyph.offset().unwrap_or(Point2D::zero());
@@ -3029,6 +3111,35 @@ impl IndexableText {
                 .range_index_of_advance(&item[0].range, offset.x),
         )
     }
+
+    /// Returns the index of the character of the text of a node that is nearest to a
+    /// point relative to the stacking context of the text.
+    pub fn text_index_at_point(&self, node: OpaqueNode, point: Point2D<Au>) -> Option<usize> {
+        let items = self.inner.get(&node)?;
+        // The text of a node is contiguous in its text run.
+        let text_start = items.iter().map(|item| item.range.begin()).min()?;
+
+        // The point is on the line of the last item starting above it, or on the first
+        // line if there is no such item.
+        let line_top = items
+            .iter()
+            .map(|item| item.origin.y)
+            .filter(|&top| top <= point.y)
+            .max()
+            .or_else(|| items.iter().map(|item| item.origin.y).min())?;
+        let mut line_items = items.iter().filter(|item| item.origin.y == line_top);
+        let first_item = line_items.next()?;
+        let item = line_items
+            .filter(|item| item.origin.x <= point.x)
+            .last()
+            .unwrap_or(first_item);
+
+        let advance = max(point.x - item.baseline_origin.x, Au(0));
+        let index_in_item = item.text_run.range_index_of_advance(&item.range, advance);
+        let text_before_item =
+            &item.text_run.text[text_start.to_usize()..item.range.begin().to_usize()];
+        Some(text_before_item.chars().count() + index_in_item)
+    }
 }
 
 trait ToF32Px {
@@ -4,6 +4,7 @@
 
 use app_units::Au;
 use euclid::default::{Point2D, Rect, SideOffsets2D, Size2D, Vector2D};
+use style::computed_values::backface_visibility::T as BackfaceVisibility;
 use style::computed_values::image_rendering::T as ImageRendering;
 use style::computed_values::mix_blend_mode::T as MixBlendMode;
 use style::computed_values::transform_style::T as TransformStyle;
@@ -91,6 +92,16 @@ impl ToLayout for MixBlendMode {
     }
 }
 
+impl ToLayout for BackfaceVisibility {
+    type Type = wr::PrimitiveFlags;
+    fn to_layout(&self) -> Self::Type {
+        match *self {
+            BackfaceVisibility::Visible => wr::PrimitiveFlags::IS_BACKFACE_VISIBLE,
+            BackfaceVisibility::Hidden => wr::PrimitiveFlags::empty(),
+        }
+    }
+}
+
 impl ToLayout for TransformStyle {
     type Type = wr::TransformStyle;
     fn to_layout(&self) -> Self::Type {
@@ -100,6 +100,9 @@ impl ClippingAndScrolling {
 pub struct DisplayList {
     pub list: Vec<DisplayItem>,
     pub clip_scroll_nodes: Vec<ClipScrollNode>,
+    /// The offsets of the scroll frames whose content overflows to their start, such as the
+    /// right-to-left ones, at which they are scrolled to the start of their content box.
+    pub scroll_origins: ScrollOffsetMap,
 }
 
 impl DisplayList {
@@ -192,6 +195,10 @@ pub struct StackingContext {
     /// The perspective matrix to be applied to children.
     pub perspective: Option<LayoutTransform>,
 
+    /// The flags of the primitives in this stacking context, which say whether their back faces
+    /// are visible.
+    pub prim_flags: PrimitiveFlags,
+
     /// The clip and scroll info for this StackingContext.
     pub parent_clipping_and_scrolling: ClippingAndScrolling,
 
@@ -214,6 +221,7 @@ impl StackingContext {
         transform: Option<LayoutTransform>,
         transform_style: TransformStyle,
         perspective: Option<LayoutTransform>,
+        prim_flags: PrimitiveFlags,
         parent_clipping_and_scrolling: ClippingAndScrolling,
         established_reference_frame: Option<ClipScrollNodeIndex>,
     ) -> StackingContext {
@@ -229,6 +237,7 @@ impl StackingContext {
             transform,
             transform_style,
             perspective,
+            prim_flags,
             parent_clipping_and_scrolling,
             established_reference_frame,
         }
@@ -248,6 +257,7 @@ impl StackingContext {
             None,
             TransformStyle::Flat,
             None,
+            PrimitiveFlags::default(),
             ClippingAndScrolling::simple(ClipScrollNodeIndex::root_scroll_node()),
             None,
         )
@@ -22,6 +22,8 @@ struct ClipScrollState {
     spatial_ids: Vec<Option<SpatialId>>,
     active_clip_id: ClipId,
     active_spatial_id: SpatialId,
+    /// The primitive flags of the stacking contexts that are being built, innermost last.
+    prim_flags: Vec<PrimitiveFlags>,
 }
 
 /// Contentful paint, for the purpose of
@@ -55,6 +57,7 @@ impl DisplayList {
             spatial_ids,
             active_clip_id: ClipId::root(webrender_pipeline),
             active_spatial_id: SpatialId::root_scroll_node(webrender_pipeline),
+            prim_flags: vec![PrimitiveFlags::default()],
         };
 
         let mut builder = DisplayListBuilder::with_capacity(
@@ -194,32 +197,40 @@ impl DisplayItem {
                 let mut bounds = stacking_context.bounds;
                 let spatial_id =
                     if let Some(frame_index) = stacking_context.established_reference_frame {
-                        let (transform, ref_frame) =
-                            match (stacking_context.transform, stacking_context.perspective) {
-                                (None, Some(p)) => (
-                                    p,
-                                    ReferenceFrameKind::Perspective {
-                                        scrolling_relative_to: None,
-                                    },
-                                ),
-                                (Some(t), None) => (t, ReferenceFrameKind::Transform),
-                                (Some(t), Some(p)) => (
-                                    t.pre_transform(&p),
-                                    ReferenceFrameKind::Perspective {
-                                        scrolling_relative_to: None,
-                                    },
-                                ),
-                                (None, None) => unreachable!(),
-                            };
-
-                        let spatial_id = builder.push_reference_frame(
-                            stacking_context.bounds.origin,
-                            state.active_spatial_id,
-                            stacking_context.transform_style,
-                            PropertyBinding::Value(transform),
-                            ref_frame,
+                        debug_assert!(
+                            stacking_context.transform.is_some() ||
+                                stacking_context.perspective.is_some()
                         );
 
+                        // The transform places the element in the 3D rendering context of its
+                        // parent, while the perspective only applies to its children, so they
+                        // get reference frames of their own. This keeps WebRender from
+                        // flattening the transformed children of an element with perspective
+                        // before projecting them.
+                        let mut spatial_id = state.active_spatial_id;
+                        let mut origin = stacking_context.bounds.origin;
+                        if let Some(transform) = stacking_context.transform {
+                            spatial_id = builder.push_reference_frame(
+                                origin,
+                                spatial_id,
+                                stacking_context.transform_style,
+                                PropertyBinding::Value(transform),
+                                ReferenceFrameKind::Transform,
+                            );
+                            origin = LayoutPoint::zero();
+                        }
+                        if let Some(perspective) = stacking_context.perspective {
+                            spatial_id = builder.push_reference_frame(
+                                origin,
+                                spatial_id,
+                                stacking_context.transform_style,
+                                PropertyBinding::Value(perspective),
+                                ReferenceFrameKind::Perspective {
+                                    scrolling_relative_to: None,
+                                },
+                            );
+                        }
+
                         state.spatial_ids[frame_index.to_index()] = Some(spatial_id);
                         state.clip_ids[frame_index.to_index()] = Some(cur_clip_id);
 
@@ -234,10 +245,15 @@ impl DisplayItem {
                     builder.push_iter(&stacking_context.filters);
                 }
 
+                // Primitives inside the stacking context are in the plane of its element, so
+                // they share its backface visibility. This is also what hides them from hit
+                // testing when they face away.
+                state.prim_flags.push(stacking_context.prim_flags);
+
                 let wr_item = PushStackingContextDisplayItem {
                     origin: bounds.origin,
                     spatial_id,
-                    prim_flags: PrimitiveFlags::default(),
+                    prim_flags: stacking_context.prim_flags,
                     stacking_context: StackingContext {
                         transform_style: stacking_context.transform_style,
                         mix_blend_mode: stacking_context.mix_blend_mode,
@@ -253,6 +269,7 @@ impl DisplayItem {
                 IsContentful(false)
             },
             DisplayItem::PopStackingContext(_) => {
+                state.prim_flags.pop();
                 builder.pop_stacking_context();
                 IsContentful(false)
             },
@@ -335,8 +352,10 @@ fn build_common_item_properties(
         clip_rect: base.clip_rect,
         spatial_id: state.active_spatial_id,
         clip_id: state.active_clip_id,
-        // TODO(gw): Make use of the WR backface visibility functionality.
-        flags: PrimitiveFlags::default(),
+        flags: *state
+            .prim_flags
+            .last()
+            .expect("Tried to build an item outside of the root stacking context."),
         hit_info: tag,
         item_key: None,
     }
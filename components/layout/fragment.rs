@@ -47,6 +47,7 @@ use style::computed_values::box_sizing::T as BoxSizing;
 use style::computed_values::clear::T as Clear;
 use style::computed_values::color::T as Color;
 use style::computed_values::display::T as Display;
+use style::computed_values::image_orientation::T as ImageOrientation;
 use style::computed_values::mix_blend_mode::T as MixBlendMode;
 use style::computed_values::overflow_wrap::T as OverflowWrap;
 use style::computed_values::overflow_x::T as StyleOverflow;
@@ -64,6 +65,7 @@ use style::values::computed::counters::ContentItem;
 use style::values::computed::{Length, Size, VerticalAlign};
 use style::values::generics::box_::{Perspective, VerticalAlignKeyword};
 use style::values::generics::transform;
+use style::values::specified::box_::WillChangeBits;
 use webrender_api;
 use webrender_api::units::LayoutTransform;
 
@@ -443,6 +445,24 @@ impl ImageFragmentInfo {
 
         let current_pixel_density = density.unwrap_or(1f64);
 
+        // Decoded images are oriented as their EXIF metadata say, unless the element's
+        // `image-orientation` is `none`.
+        // https://drafts.csswg.org/css-images/#the-image-orientation
+        let image_orientation = node
+            .style(layout_context.shared_context())
+            .get_inherited_box()
+            .image_orientation;
+        let image_or_metadata = match image_or_metadata {
+            Some(ImageOrMetadata::Image(ref image))
+                if image_orientation == ImageOrientation::None =>
+            {
+                Some(ImageOrMetadata::Image(
+                    layout_context.image_cache.find_unoriented_image(image),
+                ))
+            },
+            image_or_metadata => image_or_metadata,
+        };
+
         let (image, metadata) = match image_or_metadata {
             Some(ImageOrMetadata::Image(i)) => {
                 let height = (i.height as f64 / current_pixel_density) as u32;
@@ -584,7 +604,7 @@ pub struct SplitInfo {
 }
 
 impl SplitInfo {
-    fn new(range: Range<ByteIndex>, info: &ScannedTextFragmentInfo) -> SplitInfo {
+    pub fn new(range: Range<ByteIndex>, info: &ScannedTextFragmentInfo) -> SplitInfo {
         let inline_size = info.run.advance_for_range(&range);
         SplitInfo {
             range: range,
@@ -1928,6 +1948,11 @@ impl Fragment {
             max_inline_size
         );
 
+        // With `white-space: break-spaces`, preserved spaces don't hang at the end of the line,
+        // but there is no soft wrap opportunity before the first space following a word.
+        let breaks_spaces = self.white_space().breaks_spaces();
+        let mut follows_word = false;
+
         for slice in slice_iterator {
             debug!(
                 "calculate_split_position_using_breaking_strategy: considering slice \
@@ -1942,8 +1967,12 @@ impl Fragment {
                 .metrics_for_slice(slice.glyphs, &slice.range);
             let advance = metrics.advance_width;
 
+            let is_whitespace = slice.glyphs.is_whitespace();
+            let stays_on_line = is_whitespace && (!breaks_spaces || follows_word);
+            follows_word = !is_whitespace;
+
             // Have we found the split point?
-            if advance <= remaining_inline_size || slice.glyphs.is_whitespace() {
+            if advance <= remaining_inline_size || stays_on_line {
                 // Keep going; we haven't found the split point yet.
                 debug!("calculate_split_position_using_breaking_strategy: enlarging span");
                 remaining_inline_size = remaining_inline_size - advance;
@@ -2056,8 +2085,10 @@ impl Fragment {
     /// if necessary.
     pub fn reset_text_range_and_inline_size(&mut self) {
         if let SpecificFragmentInfo::ScannedText(ref mut info) = self.specific {
-            if info.run.extra_word_spacing != Au(0) {
-                Arc::make_mut(&mut info.run).extra_word_spacing = Au(0);
+            if info.run.extra_word_spacing != Au(0) || info.run.extra_letter_spacing != Au(0) {
+                let run = Arc::make_mut(&mut info.run);
+                run.extra_word_spacing = Au(0);
+                run.extra_letter_spacing = Au(0);
             }
 
             // FIXME (mbrubeck): Do we need to restore leading too?
@@ -2730,6 +2761,36 @@ impl Fragment {
             self.style().get_box().perspective != Perspective::None
     }
 
+    /// Returns true if `will-change` names a property whose change would make this fragment
+    /// the containing block of its absolutely or fixed positioned descendants.
+    pub fn will_change_creates_containing_block(&self) -> bool {
+        self.style()
+            .get_box()
+            .will_change
+            .bits()
+            .intersects(WillChangeBits::ABSPOS_CB | WillChangeBits::FIXPOS_CB)
+    }
+
+    /// Returns true if `will-change` names `transform`, so that this fragment can get its
+    /// reference frame before its transform changes.
+    pub fn will_change_transform(&self) -> bool {
+        self.style()
+            .get_box()
+            .will_change
+            .bits()
+            .contains(WillChangeBits::TRANSFORM)
+    }
+
+    /// Returns true if `will-change` names `scroll-position`, so that this fragment can get
+    /// its scroll frame before its content overflows.
+    pub fn will_change_scroll_position(&self) -> bool {
+        self.style()
+            .get_box()
+            .will_change
+            .bits()
+            .contains(WillChangeBits::SCROLL)
+    }
+
     /// Returns true if this fragment establishes a new stacking context and false otherwise.
     pub fn establishes_stacking_context(&self) -> bool {
         // Text fragments shouldn't create stacking contexts.
@@ -2748,6 +2809,19 @@ impl Fragment {
             return true;
         }
 
+        // The element gets the stacking context which the change of a property named by
+        // `will-change` would create, so that the change doesn't restructure the stacking
+        // context tree.
+        if self
+            .style()
+            .get_box()
+            .will_change
+            .bits()
+            .contains(WillChangeBits::STACKING_CONTEXT)
+        {
+            return true;
+        }
+
         if self.has_filter_transform_or_perspective() {
             return true;
         }
@@ -3432,6 +3506,9 @@ bitflags! {
         const IS_BLOCK_FLEX_ITEM = 0b0000_0010;
         /// Whether this fragment represents the generated text from a text-overflow clip.
         const IS_ELLIPSIS = 0b0000_0100;
+        /// Whether this fragment is the last one on the first line of its block, which was
+        /// found with the `::first-line` styles of the fragments on it.
+        const ENDS_FIRST_LINE = 0b0000_1000;
     }
 }
 
@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::block::AbsoluteAssignBSizesTraversal;
-use crate::context::{LayoutContext, LayoutFontContext};
+use crate::context::{with_thread_local_font_context, LayoutContext, LayoutFontContext};
 use crate::display_list::items::{DisplayListSection, OpaqueNode};
 use crate::display_list::{
     BorderPaintingMode, DisplayListBuildState, StackingContextCollectionState,
@@ -15,26 +15,30 @@ use crate::flow_ref::FlowRef;
 use crate::fragment::FragmentFlags;
 use crate::fragment::SpecificFragmentInfo;
 use crate::fragment::{CoordinateSystem, Fragment, FragmentBorderBoxIterator, Overflow};
+use crate::fragment::{ScannedTextFlags, SplitInfo, UnscannedTextFragmentInfo};
 use crate::layout_debug;
 use crate::model::IntrinsicISizesContribution;
 use crate::text;
+use crate::text::TextRunScanner;
 use crate::traversal::PreorderFlowTraversal;
 use crate::ServoArc;
 use app_units::{Au, MIN_AU};
 use euclid::default::{Point2D, Rect, Size2D};
 use gfx::font::FontMetrics;
+use gfx::text::glyph::ByteIndex;
 use gfx_traits::print_tree::PrintTree;
 use range::{Range, RangeIndex};
 use script_layout_interface::wrapper_traits::PseudoElementType;
 use servo_geometry::MaxRect;
 use std::cmp::max;
-use std::collections::VecDeque;
+use std::collections::{LinkedList, VecDeque};
 use std::sync::Arc;
 use std::{fmt, i32, isize, mem};
 use style::computed_values::display::T as Display;
 use style::computed_values::overflow_x::T as StyleOverflow;
 use style::computed_values::position::T as Position;
 use style::computed_values::text_align::T as TextAlign;
+use style::computed_values::text_align_last::T as TextAlignLast;
 use style::computed_values::text_justify::T as TextJustify;
 use style::computed_values::white_space::T as WhiteSpace;
 use style::logical_geometry::{LogicalRect, LogicalSize, WritingMode};
@@ -351,6 +355,38 @@ impl LineBreaker {
         flow.lines = lines;
     }
 
+    /// Reflows fragments until the first line has been committed, and returns those which were
+    /// pulled from the iterator but did not end up on it.
+    fn scan_for_first_line<I>(
+        &mut self,
+        mut fragment_iter: I,
+        flow: &InlineFlow,
+        layout_context: &LayoutContext,
+    ) -> Vec<Fragment>
+    where
+        I: Iterator<Item = Fragment>,
+    {
+        self.reset_scanner();
+
+        while self.lines.is_empty() {
+            let fragment = match self.next_unbroken_fragment(&mut fragment_iter) {
+                None => break,
+                Some(fragment) => fragment,
+            };
+            self.reflow_fragment(fragment, flow, layout_context);
+        }
+        if self.lines.is_empty() && !self.pending_line_is_empty() {
+            self.flush_current_line()
+        }
+
+        let mut leftover = match self.lines.first() {
+            Some(line) => self.new_fragments.split_off(line.range.end().to_usize()),
+            None => vec![],
+        };
+        leftover.extend(self.work_list.drain(..));
+        leftover
+    }
+
     /// Reflows the given fragments, which have been plucked out of the inline flow.
     fn reflow_fragments<'a, I>(
         &mut self,
@@ -657,7 +693,9 @@ impl LineBreaker {
 
         // If we must flush the line after finishing this fragment due to `white-space: pre`,
         // detect that.
-        let line_flush_mode = if fragment.white_space().preserve_newlines() {
+        let line_flush_mode = if fragment.flags.contains(FragmentFlags::ENDS_FIRST_LINE) {
+            LineFlushMode::Flush
+        } else if fragment.white_space().preserve_newlines() {
             if fragment.requires_line_break_afterward_if_wrapping_on_newlines() {
                 LineFlushMode::Flush
             } else {
@@ -971,6 +1009,14 @@ pub struct InlineFlow {
     /// (because percentages are relative to the containing block, and we aren't in a position to
     /// compute things relative to our parent's containing block).
     pub first_line_indentation: Au,
+
+    /// The alignment of the last line, and of any line ending in a forced line break. Like
+    /// `first_line_indentation`, this is determined by our block parent.
+    pub text_align_last: TextAlignLast,
+
+    /// The `::first-line` styles of our block parent, if it has any and this is its first line.
+    #[serde(skip_serializing)]
+    pub first_line: Option<Box<FirstLineStyles>>,
 }
 
 impl InlineFlow {
@@ -981,6 +1027,8 @@ impl InlineFlow {
             lines: Vec::new(),
             minimum_line_metrics: LineMetrics::new(Au(0), Au(0)),
             first_line_indentation: Au(0),
+            text_align_last: TextAlignLast::Auto,
+            first_line: None,
         };
 
         if flow
@@ -1004,7 +1052,6 @@ impl InlineFlow {
         line: &Line,
         line_align: TextAlign,
         indentation: Au,
-        is_last_line: bool,
     ) {
         // Figure out how much inline-size we have.
         let slack_inline_size = max(Au(0), line.green_zone.inline - line.bounds.size.inline);
@@ -1036,8 +1083,13 @@ impl InlineFlow {
         // necessary.
         let mut inline_start_position_for_fragment = line.bounds.start.i + indentation;
         match line_align {
-            TextAlign::Justify if !is_last_line && text_justify != TextJustify::None => {
-                InlineFlow::justify_inline_fragments(fragments, line, slack_inline_size)
+            TextAlign::Justify if text_justify != TextJustify::None => {
+                InlineFlow::justify_inline_fragments(
+                    fragments,
+                    line,
+                    slack_inline_size,
+                    text_justify,
+                )
             },
             TextAlign::Justify | TextAlign::Start => {},
             TextAlign::Center | TextAlign::ServoCenter => {
@@ -1130,13 +1182,15 @@ impl InlineFlow {
         fragments: &mut InlineFragments,
         line: &Line,
         slack_inline_size: Au,
+        text_justify: TextJustify,
     ) {
         // Fast path.
         if slack_inline_size == Au(0) {
             return;
         }
 
-        // First, calculate the number of expansion opportunities (spaces, normally).
+        // First, calculate the number of expansion opportunities: spaces for inter-word
+        // justification, and the gaps between glyphs for inter-character justification.
         let mut expansion_opportunities = 0;
         for fragment_index in line.range.each_index() {
             let fragment = fragments.get(fragment_index.to_usize());
@@ -1150,16 +1204,28 @@ impl InlineFlow {
                 .run
                 .character_slices_in_range(&fragment_range)
             {
-                expansion_opportunities += slice.glyphs.space_count_in_range(&slice.range)
+                expansion_opportunities += match text_justify {
+                    TextJustify::InterCharacter => slice
+                        .glyphs
+                        .iter_glyphs_for_byte_range(&slice.range)
+                        .count() as u32,
+                    _ => slice.glyphs.space_count_in_range(&slice.range),
+                }
             }
         }
 
+        // There is no gap after the last glyph on the line.
+        if text_justify == TextJustify::InterCharacter {
+            expansion_opportunities = expansion_opportunities.saturating_sub(1);
+        }
+
         if expansion_opportunities == 0 {
             return;
         }
 
         // Then distribute all the space across the expansion opportunities.
         let space_per_expansion_opportunity = slack_inline_size / expansion_opportunities as i32;
+        let mut last_text_fragment_index = None;
         for fragment_index in line.range.each_index() {
             let fragment = fragments.get_mut(fragment_index.to_usize());
             let scanned_text_fragment_info = match fragment.specific {
@@ -1168,7 +1234,13 @@ impl InlineFlow {
             };
             let fragment_range = scanned_text_fragment_info.range;
             let run = Arc::make_mut(&mut scanned_text_fragment_info.run);
-            run.extra_word_spacing = space_per_expansion_opportunity;
+            match text_justify {
+                TextJustify::InterCharacter => {
+                    run.extra_letter_spacing = space_per_expansion_opportunity
+                },
+                _ => run.extra_word_spacing = space_per_expansion_opportunity,
+            }
+            last_text_fragment_index = Some(fragment_index);
 
             // Recompute the fragment's border box size.
             let new_inline_size = run.advance_for_range(&fragment_range);
@@ -1183,6 +1255,125 @@ impl InlineFlow {
                 new_size,
             );
         }
+
+        // The spacing after the last glyph on the line doesn't take up any room.
+        if text_justify == TextJustify::InterCharacter {
+            if let Some(fragment_index) = last_text_fragment_index {
+                let fragment = fragments.get_mut(fragment_index.to_usize());
+                fragment.border_box.size.inline =
+                    fragment.border_box.size.inline - space_per_expansion_opportunity;
+            }
+        }
+    }
+
+    /// Returns the alignment of a line that `text-align-last` applies to.
+    ///
+    /// https://drafts.csswg.org/css-text-3/#text-align-last-property
+    fn text_align_for_last_line(
+        text_align: TextAlign,
+        text_align_last: TextAlignLast,
+    ) -> TextAlign {
+        match text_align_last {
+            TextAlignLast::Auto if text_align == TextAlign::Justify => TextAlign::Start,
+            TextAlignLast::Auto => text_align,
+            TextAlignLast::Start => TextAlign::Start,
+            TextAlignLast::End => TextAlign::End,
+            TextAlignLast::Left => TextAlign::Left,
+            TextAlignLast::Right => TextAlign::Right,
+            TextAlignLast::Center => TextAlign::Center,
+            TextAlignLast::Justify => TextAlign::Justify,
+        }
+    }
+
+    /// Puts back the fragments that `restyle_first_line` replaced during the previous reflow.
+    fn restore_fragments_without_first_line_styles(&mut self) {
+        if let Some(originals) = self
+            .first_line
+            .as_ref()
+            .and_then(|first_line| first_line.originals.as_ref())
+        {
+            self.fragments.fragments = originals.clone();
+        }
+    }
+
+    /// Replaces the fragments that fit on the first line with copies in the `::first-line`
+    /// styles, splitting the fragment that straddles the end of the line.
+    ///
+    /// https://drafts.csswg.org/css-pseudo-4/#first-line-styling
+    fn restyle_first_line(&mut self, layout_context: &LayoutContext, indentation: Au) {
+        let (mut first_line_fragments, lengths, leftover_length) = {
+            let first_line = match self.first_line {
+                Some(ref first_line) if !self.fragments.is_empty() => first_line,
+                _ => return,
+            };
+            let mut restyled =
+                FirstLineFragments::new(first_line, &self.fragments.fragments, layout_context);
+            let mut scanner = LineBreaker::new(
+                self.base.floats.clone(),
+                indentation,
+                &self.minimum_line_metrics,
+            );
+            let mut leftover = scanner.scan_for_first_line(&mut restyled, self, layout_context);
+            leftover.extend(restyled.pending.drain(..));
+            let leftover_length: usize = leftover.iter().map(content_length).sum();
+            (scanner.new_fragments, restyled.lengths, leftover_length)
+        };
+
+        // Undo any ellipsis that the first line scan may have produced; the line will be broken
+        // again below.
+        first_line_fragments = first_line_fragments
+            .into_iter()
+            .filter(|fragment| !fragment.flags.contains(FragmentFlags::IS_ELLIPSIS))
+            .map(|fragment| match fragment.specific {
+                SpecificFragmentInfo::TruncatedFragment(info) => info.full,
+                _ => fragment,
+            })
+            .collect();
+        let last_index = match first_line_fragments.len() {
+            0 => return,
+            length => length - 1,
+        };
+        for (index, fragment) in first_line_fragments.iter_mut().enumerate() {
+            let node = fragment
+                .inline_context
+                .as_mut()
+                .and_then(|inline_context| inline_context.nodes.last_mut())
+                .expect("restyled fragments are always inside the first line box");
+            if index == 0 {
+                node.flags
+                    .insert(InlineFragmentNodeFlags::FIRST_FRAGMENT_OF_ELEMENT);
+            }
+            if index == last_index {
+                node.flags
+                    .insert(InlineFragmentNodeFlags::LAST_FRAGMENT_OF_ELEMENT);
+                fragment.flags.insert(FragmentFlags::ENDS_FIRST_LINE);
+            }
+        }
+
+        // Find the original fragment which the first line ends in, and how many of its
+        // characters made it onto the line. The restyled text may have a different length if
+        // `text-transform` differs, so scale it back to the original.
+        let restyled_length: usize = lengths.iter().map(|&(_, length)| length).sum();
+        let mut remaining = restyled_length.saturating_sub(leftover_length);
+        let mut end = (lengths.len(), 0);
+        for (index, &(original_length, length)) in lengths.iter().enumerate() {
+            if remaining < length {
+                end = (index, remaining * original_length / length);
+                break;
+            }
+            remaining -= length;
+        }
+
+        let originals = mem::replace(&mut self.fragments.fragments, vec![]);
+        let mut fragments = first_line_fragments;
+        for (index, fragment) in originals.into_iter().enumerate().skip(end.0) {
+            if index == end.0 && end.1 > 0 {
+                fragments.extend(fragment_after_char_offset(&fragment, end.1));
+            } else {
+                fragments.push(fragment);
+            }
+        }
+        self.fragments.fragments = fragments;
     }
 
     /// Sets final fragment positions in the block direction for one line.
@@ -1509,7 +1700,7 @@ impl Flow for InlineFlow {
                         intrinsic_sizes_for_inline_run = IntrinsicISizesContribution::new();
                     }
                 },
-                WhiteSpace::PreWrap | WhiteSpace::PreLine => {
+                WhiteSpace::PreWrap | WhiteSpace::PreLine | WhiteSpace::BreakSpaces => {
                     // Flush the intrinsic sizes we were gathering up for the nonbroken run, if
                     // necessary.
                     intrinsic_sizes_for_inline_run
@@ -1579,6 +1770,9 @@ impl Flow for InlineFlow {
         let container_block_size = self.base.block_container_explicit_block_size;
         self.base.position.size.inline = inline_size;
 
+        // Start over from the fragments as they were before the first line was restyled.
+        self.restore_fragments_without_first_line_styles();
+
         {
             let this = &mut *self;
             for fragment in this.fragments.fragments.iter_mut() {
@@ -1590,6 +1784,10 @@ impl Flow for InlineFlow {
             }
         }
 
+        if let Some(ref mut first_line) = self.first_line {
+            first_line.originals = Some(self.fragments.fragments.clone());
+        }
+
         // If there are any inline-block kids, propagate explicit block and inline
         // sizes down to them.
         let block_container_explicit_block_size = self.base.block_container_explicit_block_size;
@@ -1624,6 +1822,8 @@ impl Flow for InlineFlow {
             self.base.floats
         );
 
+        self.restore_fragments_without_first_line_styles();
+
         // Assign the block-size and late-computed inline-sizes for the inline fragments.
         for fragment in &mut self.fragments.fragments {
             fragment.update_late_computed_replaced_inline_size_if_necessary();
@@ -1642,6 +1842,9 @@ impl Flow for InlineFlow {
             self.first_line_indentation
         };
 
+        // Lay out the first line in the `::first-line` styles, if there are any.
+        self.restyle_first_line(layout_context, indentation);
+
         // Perform line breaking.
         let mut scanner = LineBreaker::new(
             self.base.floats.clone(),
@@ -1652,15 +1855,28 @@ impl Flow for InlineFlow {
 
         // Now, go through each line and lay out the fragments inside.
         let line_count = self.lines.len();
+        let text_align = self.base.flags.text_align();
         for (line_index, line) in self.lines.iter_mut().enumerate() {
+            // `text-align-last` applies to the last line, and to any line that ends in a forced
+            // line break.
+            let is_last_line = line_index + 1 == line_count ||
+                (!line.range.is_empty() &&
+                    self.fragments
+                        .get(line.range.end().to_usize() - 1)
+                        .requires_line_break_afterward_if_wrapping_on_newlines());
+            let line_align = if is_last_line {
+                InlineFlow::text_align_for_last_line(text_align, self.text_align_last)
+            } else {
+                text_align
+            };
+
             // Lay out fragments in the inline direction, and justify them if
             // necessary.
             InlineFlow::set_inline_fragment_positions(
                 &mut self.fragments,
                 line,
-                self.base.flags.text_align(),
+                line_align,
                 indentation,
-                line_index + 1 == line_count,
             );
 
             // Compute the final positions in the block direction of each fragment.
@@ -1988,6 +2204,15 @@ impl Flow for InlineFlow {
         for fragment in &mut self.fragments.fragments {
             (*mutator)(fragment)
         }
+        if let Some(originals) = self
+            .first_line
+            .as_mut()
+            .and_then(|first_line| first_line.originals.as_mut())
+        {
+            for fragment in originals {
+                (*mutator)(fragment)
+            }
+        }
     }
 
     fn contains_positioned_fragments(&self) -> bool {
@@ -2106,6 +2331,168 @@ fn inline_contexts_are_equal(
     }
 }
 
+/// The `::first-line` styles that apply to the fragments of an inline flow.
+pub struct FirstLineStyles {
+    /// The block that the first line belongs to.
+    pub address: OpaqueNode,
+    /// The style of the `::first-line` pseudo-element itself.
+    pub style: ServoArc<ComputedValues>,
+    /// The styles of the fragments and their inline ancestors, paired with the styles they take on
+    /// when they are on the first line.
+    pub styles: Vec<(ServoArc<ComputedValues>, ServoArc<ComputedValues>)>,
+    /// The fragments of the flow as they were before the last reflow restyled the first line.
+    pub originals: Option<Vec<Fragment>>,
+}
+
+impl FirstLineStyles {
+    /// Returns the first line variant of the given style.
+    fn style_for(&self, style: &ServoArc<ComputedValues>) -> ServoArc<ComputedValues> {
+        match self
+            .styles
+            .iter()
+            .find(|&&(ref original, _)| ServoArc::ptr_eq(original, style))
+        {
+            Some(&(_, ref first_line_style)) => first_line_style.clone(),
+            None => style.clone(),
+        }
+    }
+
+    /// Returns copies of the given fragment in the first line styles, wrapped in the first line
+    /// box. Text is shaped again, since the font may have changed.
+    fn restyle(&self, fragment: &Fragment, layout_context: &LayoutContext) -> Vec<Fragment> {
+        let mut restyled = fragment.clone();
+        if let Some(ref mut inline_context) = restyled.inline_context {
+            for node in &mut inline_context.nodes {
+                node.style = self.style_for(&node.style);
+                node.selected_style = self.style_for(&node.selected_style);
+            }
+        }
+        restyled
+            .inline_context
+            .get_or_insert_with(InlineFragmentContext::new)
+            .nodes
+            .push(InlineFragmentNodeInfo {
+                address: self.address,
+                style: self.style.clone(),
+                selected_style: self.style.clone(),
+                pseudo: PseudoElementType::Normal,
+                flags: InlineFragmentNodeFlags::empty(),
+            });
+
+        let info = match fragment.specific {
+            SpecificFragmentInfo::ScannedText(ref info) => info,
+            _ => return vec![restyled],
+        };
+        restyled.style = self.style_for(&fragment.style);
+        restyled.selected_style = self.style_for(&fragment.selected_style);
+        restyled.specific = SpecificFragmentInfo::UnscannedText(Box::new(
+            UnscannedTextFragmentInfo::new(info.text().into(), None),
+        ));
+        let mut unscanned = LinkedList::new();
+        unscanned.push_back(restyled);
+        let mut fragments = with_thread_local_font_context(layout_context, |font_context| {
+            TextRunScanner::new().scan_for_runs(font_context, unscanned)
+        })
+        .fragments;
+
+        // Carry over the line breaking constraints that came from the original's neighbours.
+        let suppress = info
+            .flags
+            .contains(ScannedTextFlags::SUPPRESS_LINE_BREAK_BEFORE);
+        let requires = info.requires_line_break_afterward_if_wrapping_on_newlines();
+        if let Some(&mut SpecificFragmentInfo::ScannedText(ref mut info)) =
+            fragments.first_mut().map(|fragment| &mut fragment.specific)
+        {
+            info.flags
+                .set(ScannedTextFlags::SUPPRESS_LINE_BREAK_BEFORE, suppress);
+        }
+        if let Some(&mut SpecificFragmentInfo::ScannedText(ref mut info)) =
+            fragments.last_mut().map(|fragment| &mut fragment.specific)
+        {
+            info.flags.set(
+                ScannedTextFlags::REQUIRES_LINE_BREAK_AFTERWARD_IF_WRAPPING_ON_NEWLINES,
+                requires,
+            );
+        }
+        fragments
+    }
+}
+
+/// Restyles the fragments of an inline flow for the first line as the line breaker pulls them,
+/// so that only the fragments that can end up on the first line are shaped again.
+struct FirstLineFragments<'a, 'b> {
+    first_line: &'a FirstLineStyles,
+    originals: &'a [Fragment],
+    layout_context: &'a LayoutContext<'b>,
+    /// The restyled fragments of the last original which have not been pulled yet.
+    pending: VecDeque<Fragment>,
+    /// The content length of each original that was restyled, and of its restyled fragments.
+    lengths: Vec<(usize, usize)>,
+}
+
+impl<'a, 'b> FirstLineFragments<'a, 'b> {
+    fn new(
+        first_line: &'a FirstLineStyles,
+        originals: &'a [Fragment],
+        layout_context: &'a LayoutContext<'b>,
+    ) -> FirstLineFragments<'a, 'b> {
+        FirstLineFragments {
+            first_line: first_line,
+            originals: originals,
+            layout_context: layout_context,
+            pending: VecDeque::new(),
+            lengths: vec![],
+        }
+    }
+}
+
+impl<'a, 'b> Iterator for FirstLineFragments<'a, 'b> {
+    type Item = Fragment;
+
+    fn next(&mut self) -> Option<Fragment> {
+        while self.pending.is_empty() {
+            let original = self.originals.get(self.lengths.len())?;
+            let restyled = self.first_line.restyle(original, self.layout_context);
+            self.lengths.push((
+                content_length(original),
+                restyled.iter().map(content_length).sum(),
+            ));
+            self.pending.extend(restyled);
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// The number of characters in a text fragment. Any other fragment counts as one.
+fn content_length(fragment: &Fragment) -> usize {
+    match fragment.specific {
+        SpecificFragmentInfo::ScannedText(ref info) => info.text().chars().count(),
+        _ => 1,
+    }
+}
+
+/// Returns the part of a text fragment after the given number of characters, if there is any,
+/// as the continuation of the fragment onto the next line.
+fn fragment_after_char_offset(fragment: &Fragment, offset: usize) -> Option<Fragment> {
+    let info = match fragment.specific {
+        SpecificFragmentInfo::ScannedText(ref info) => info,
+        _ => return None,
+    };
+    let (byte_offset, _) = info.text().char_indices().nth(offset)?;
+    let begin = info.range.begin() + ByteIndex(byte_offset as isize);
+    let split = SplitInfo::new(Range::new(begin, info.range.end() - begin), info);
+    let mut fragment = fragment.transform_with_split_info(&split, info.run.clone(), false);
+    fragment.border_padding.inline_start = Au(0);
+    if let Some(ref mut inline_context) = fragment.inline_context {
+        for node in &mut inline_context.nodes {
+            node.flags
+                .remove(InlineFragmentNodeFlags::FIRST_FRAGMENT_OF_ELEMENT);
+        }
+    }
+    fragment.border_box.size.inline += fragment.border_padding.inline_end;
+    Some(fragment)
+}
+
 /// Ascent and space needed above and below the baseline for a fragment. See CSS 2.1 § 10.8.1.
 ///
 /// Descent is not included in this structure because it can be computed from the fragment's
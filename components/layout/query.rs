@@ -4,7 +4,6 @@
 
 //! Utilities for querying the layout, as needed by the layout thread.
 
-use crate::construct::ConstructionResult;
 use crate::context::LayoutContext;
 use crate::display_list::items::{DisplayList, OpaqueNode, ScrollOffsetMap};
 use crate::display_list::IndexableText;
@@ -13,34 +12,39 @@ use crate::fragment::{Fragment, FragmentBorderBoxIterator, SpecificFragmentInfo}
 use crate::inline::InlineFragmentNodeFlags;
 use crate::opaque_node::OpaqueNodeMethods;
 use crate::sequential;
-use crate::wrapper::LayoutNodeLayoutData;
+use crate::ServoArc;
 use app_units::Au;
-use euclid::default::{Point2D, Rect, Size2D, Vector2D};
+use euclid::default::{Point2D, Rect, SideOffsets2D, Size2D, Vector2D};
 use euclid::Size2D as TypedSize2D;
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::PipelineId;
 use script_layout_interface::rpc::TextIndexResponse;
+use script_layout_interface::rpc::{BoxSizes, BoxSizesResponse};
 use script_layout_interface::rpc::{ContentBoxResponse, ContentBoxesResponse, LayoutRPC};
 use script_layout_interface::rpc::{NodeGeometryResponse, NodeScrollIdResponse};
 use script_layout_interface::rpc::{OffsetParentResponse, ResolvedStyleResponse, StyleResponse};
 use script_layout_interface::wrapper_traits::{
-    LayoutNode, ThreadSafeLayoutElement, ThreadSafeLayoutNode,
+    LayoutNode, PseudoElementType, ThreadSafeLayoutElement, ThreadSafeLayoutNode,
 };
 use script_layout_interface::StyleData;
 use script_layout_interface::{LayoutElementType, LayoutNodeType};
 use script_traits::LayoutMsg as ConstellationMsg;
 use script_traits::UntrustedNodeAddress;
 use std::cmp::{max, min};
-use std::ops::Deref;
 use std::sync::{Arc, Mutex};
+use style::computed_values::box_sizing::T as BoxSizing;
 use style::computed_values::display::T as Display;
 use style::computed_values::position::T as Position;
 use style::computed_values::visibility::T as Visibility;
 use style::context::{StyleContext, ThreadLocalStyleContext};
 use style::dom::TElement;
+use style::font_metrics::ServoMetricsProvider;
 use style::logical_geometry::{BlockFlowDirection, InlineBaseDirection, WritingMode};
+use style::properties::ComputedValues;
 use style::properties::{style_structs, LonghandId, PropertyDeclarationId, PropertyId};
 use style::selector_parser::PseudoElement;
+use style::stylist::RuleInclusion;
+use style::values::specified::box_::DisplayOutside;
 use style_traits::{CSSPixel, ToCss};
 use webrender_api::ExternalScrollId;
 
@@ -62,6 +66,9 @@ pub struct LayoutThreadData {
     /// A queued response for the content boxes of a node.
     pub content_boxes_response: Vec<Rect<Au>>,
 
+    /// A queued response for the sizes of the boxes of a node.
+    pub box_sizes_response: Option<BoxSizes>,
+
     /// A queued response for the client {top, left, width, height} of a node in pixels.
     pub client_rect_response: Rect<i32>,
 
@@ -137,6 +144,12 @@ impl LayoutRPC for LayoutRPCImpl {
         ContentBoxesResponse(rw_data.content_boxes_response.clone())
     }
 
+    fn box_sizes(&self) -> BoxSizesResponse {
+        let &LayoutRPCImpl(ref rw_data) = self;
+        let rw_data = rw_data.lock().unwrap();
+        BoxSizesResponse(rw_data.box_sizes_response)
+    }
+
     fn nodes_from_point_response(&self) -> Vec<UntrustedNodeAddress> {
         let &LayoutRPCImpl(ref rw_data) = self;
         let rw_data = rw_data.lock().unwrap();
@@ -268,15 +281,6 @@ enum MarginPadding {
     Padding,
 }
 
-enum PositionProperty {
-    Left,
-    Right,
-    Top,
-    Bottom,
-    Width,
-    Height,
-}
-
 #[derive(Debug)]
 enum OverflowDirection {
     RightAndDown,
@@ -285,48 +289,51 @@ enum OverflowDirection {
     RightAndUp,
 }
 
-struct PositionRetrievingFragmentBorderBoxIterator {
+/// The boxes of a fragment, with the border box relative to the document.
+struct UsedBox {
+    border_box: Rect<Au>,
+    margin: SideOffsets2D<Au>,
+    border: SideOffsets2D<Au>,
+    border_padding: SideOffsets2D<Au>,
+    is_replaced: bool,
+}
+
+struct UsedBoxRetrievingFragmentBorderBoxIterator {
     node_address: OpaqueNode,
-    result: Option<Au>,
-    position: Point2D<Au>,
-    property: PositionProperty,
+    pseudo: PseudoElementType,
+    result: Option<UsedBox>,
 }
 
-impl PositionRetrievingFragmentBorderBoxIterator {
+impl UsedBoxRetrievingFragmentBorderBoxIterator {
     fn new(
         node_address: OpaqueNode,
-        property: PositionProperty,
-        position: Point2D<Au>,
-    ) -> PositionRetrievingFragmentBorderBoxIterator {
-        PositionRetrievingFragmentBorderBoxIterator {
+        pseudo: PseudoElementType,
+    ) -> UsedBoxRetrievingFragmentBorderBoxIterator {
+        UsedBoxRetrievingFragmentBorderBoxIterator {
             node_address: node_address,
-            position: position,
-            property: property,
+            pseudo: pseudo,
             result: None,
         }
     }
 }
 
-impl FragmentBorderBoxIterator for PositionRetrievingFragmentBorderBoxIterator {
+impl FragmentBorderBoxIterator for UsedBoxRetrievingFragmentBorderBoxIterator {
     fn process(&mut self, fragment: &Fragment, _: i32, border_box: &Rect<Au>) {
-        let border_padding = fragment
-            .border_padding
-            .to_physical(fragment.style.writing_mode);
-        self.result = Some(match self.property {
-            PositionProperty::Left => self.position.x,
-            PositionProperty::Top => self.position.y,
-            PositionProperty::Width => border_box.size.width - border_padding.horizontal(),
-            PositionProperty::Height => border_box.size.height - border_padding.vertical(),
-            // TODO: the following 2 calculations are completely wrong.
-            // They should return the difference between the parent's and this
-            // fragment's border boxes.
-            PositionProperty::Right => border_box.max_x() + self.position.x,
-            PositionProperty::Bottom => border_box.max_y() + self.position.y,
+        let writing_mode = fragment.style.writing_mode;
+        self.result = Some(UsedBox {
+            border_box: *border_box,
+            margin: fragment.margin.to_physical(writing_mode),
+            border: fragment.border_width().to_physical(writing_mode),
+            border_padding: fragment.border_padding.to_physical(writing_mode),
+            is_replaced: fragment.is_replaced(),
         });
     }
 
     fn should_process(&mut self, fragment: &Fragment) -> bool {
-        fragment.contains_node(self.node_address)
+        // Only the first fragment of a node is used.
+        self.result.is_none() &&
+            fragment.pseudo == self.pseudo &&
+            fragment.contains_node(self.node_address)
     }
 }
 
@@ -385,6 +392,29 @@ pub fn process_content_box_request(
     iterator.rect
 }
 
+pub fn process_box_sizes_request(
+    requested_node: OpaqueNode,
+    layout_root: &mut dyn Flow,
+) -> Option<BoxSizes> {
+    let mut iterator = BoxSizesIterator {
+        node_address: requested_node,
+        box_sizes: None,
+    };
+    sequential::iterate_through_flow_tree_fragment_border_boxes(layout_root, &mut iterator);
+    iterator.box_sizes
+}
+
+/// The intrinsic size of the content of a document: the max-content width of its root
+/// element, and the height of its content when it is laid out at the width of the
+/// viewport. Unlike its scrolling area, it may be smaller than the viewport.
+pub fn process_content_size_request(layout_root: &mut dyn Flow) -> Size2D<Au> {
+    // FIXME: This assumes that the writing mode of the root element is horizontal.
+    let base = layout_root.base();
+    let height =
+        base.position.start.b + max(base.position.size.block, base.overflow.scroll.max_y());
+    Size2D::new(base.intrinsic_inline_sizes.preferred_inline_size, height)
+}
+
 pub fn process_content_boxes_request(
     requested_node: OpaqueNode,
     layout_root: &mut dyn Flow,
@@ -396,6 +426,38 @@ pub fn process_content_boxes_request(
     iterator.rects
 }
 
+/// The sizes of the boxes of the first fragment of a node.
+struct BoxSizesIterator {
+    node_address: OpaqueNode,
+    box_sizes: Option<BoxSizes>,
+}
+
+impl FragmentBorderBoxIterator for BoxSizesIterator {
+    fn process(&mut self, fragment: &Fragment, _: i32, border_box: &Rect<Au>) {
+        let writing_mode = fragment.style.writing_mode;
+        let border_padding = fragment.border_padding.to_physical(writing_mode);
+        let border = fragment.border_width().to_physical(writing_mode);
+        let content_box = Rect::new(
+            Point2D::new(
+                border_padding.left - border.left,
+                border_padding.top - border.top,
+            ),
+            Size2D::new(
+                max(border_box.size.width - border_padding.horizontal(), Au(0)),
+                max(border_box.size.height - border_padding.vertical(), Au(0)),
+            ),
+        );
+        self.box_sizes = Some(BoxSizes {
+            content_box,
+            border_box: border_box.size,
+        });
+    }
+
+    fn should_process(&mut self, fragment: &Fragment) -> bool {
+        self.box_sizes.is_none() && fragment.node == self.node_address
+    }
+}
+
 struct FragmentClientRectQueryIterator {
     node_address: OpaqueNode,
     client_rect: Rect<i32>,
@@ -723,13 +785,19 @@ pub fn process_node_scroll_area_request(
             let left = min(iterator.union_rect.origin.x, iterator.origin_rect.origin.x);
             Rect::new(
                 Point2D::new(left, iterator.origin_rect.origin.y),
-                Size2D::new(iterator.origin_rect.size.width, bottom),
+                Size2D::new(iterator.origin_rect.max_x() - left, bottom),
             )
         },
         OverflowDirection::LeftAndUp => {
             let top = min(iterator.union_rect.origin.y, iterator.origin_rect.origin.y);
             let left = min(iterator.union_rect.origin.x, iterator.origin_rect.origin.x);
-            Rect::new(Point2D::new(left, top), iterator.origin_rect.size)
+            Rect::new(
+                Point2D::new(left, top),
+                Size2D::new(
+                    iterator.origin_rect.max_x() - left,
+                    iterator.origin_rect.max_y() - top,
+                ),
+            )
         },
         OverflowDirection::RightAndUp => {
             let top = min(iterator.union_rect.origin.y, iterator.origin_rect.origin.y);
@@ -739,7 +807,7 @@ pub fn process_node_scroll_area_request(
             );
             Rect::new(
                 Point2D::new(iterator.origin_rect.origin.x, top),
-                Size2D::new(right, iterator.origin_rect.size.height),
+                Size2D::new(right, iterator.origin_rect.max_y() - top),
             )
         },
     }
@@ -757,7 +825,6 @@ pub fn process_resolved_style_request<'a, N>(
 where
     N: LayoutNode,
 {
-    use style::stylist::RuleInclusion;
     use style::traversal::resolve_style;
 
     let element = node.as_element().unwrap();
@@ -765,7 +832,13 @@ where
     // We call process_resolved_style_request after performing a whole-document
     // traversal, so in the common case, the element is styled.
     if element.get_data().is_some() {
-        return process_resolved_style_request_internal(node, pseudo, property, layout_root);
+        return process_resolved_style_request_internal(
+            context,
+            node,
+            pseudo,
+            property,
+            layout_root,
+        );
     }
 
     // In a display: none subtree. No pseudo-element exists.
@@ -798,6 +871,7 @@ where
 
 /// The primary resolution logic, which assumes that the element is styled.
 fn process_resolved_style_request_internal<'a, N>(
+    context: &LayoutContext,
     requested_node: N,
     pseudo: &Option<PseudoElement>,
     property: &PropertyId,
@@ -806,10 +880,32 @@ fn process_resolved_style_request_internal<'a, N>(
 where
     N: LayoutNode,
 {
+    let element = requested_node.as_element().unwrap();
     let layout_el = requested_node.to_threadsafe().as_element().unwrap();
     let layout_el = match *pseudo {
+        Some(PseudoElement::Backdrop) => layout_el.get_backdrop_pseudo(),
         Some(PseudoElement::Before) => layout_el.get_before_pseudo(),
         Some(PseudoElement::After) => layout_el.get_after_pseudo(),
+        Some(ref pseudo @ PseudoElement::Marker) |
+        Some(ref pseudo @ PseudoElement::Placeholder) => {
+            // These pseudo-elements are never constructed by layout, so their style is
+            // cascaded here, and they have no used values.
+            return lazy_pseudo_element_style(context, element, pseudo)
+                .map_or(String::new(), |style| {
+                    resolved_value_to_string(&style, property, |_| None)
+                });
+        },
+        Some(ref pseudo @ PseudoElement::FirstLetter) |
+        Some(ref pseudo @ PseudoElement::FirstLine) => {
+            // These pseudo-elements are cascaded eagerly, but they are parts of the boxes
+            // of their element rather than nodes of layout.
+            return element
+                .borrow_data()
+                .and_then(|data| data.styles.pseudos.get(pseudo).cloned())
+                .map_or(String::new(), |style| {
+                    resolved_value_to_string(&style, property, |_| None)
+                });
+        },
         Some(PseudoElement::DetailsSummary) |
         Some(PseudoElement::DetailsContent) |
         Some(PseudoElement::Selection) => None,
@@ -828,7 +924,24 @@ where
         Some(layout_el) => layout_el,
     };
 
-    let style = &*layout_el.resolved_style();
+    let style = layout_el.resolved_style();
+    let pseudo = layout_el.get_pseudo_element_type();
+    resolved_value_to_string(&style, property, |longhand_id| {
+        used_value_for_property(context, element, pseudo, &style, longhand_id, layout_root)
+    })
+}
+
+/// Serialize the resolved value of a property, which is its used value if there is
+/// one, and its computed value otherwise.
+/// <https://drafts.csswg.org/cssom/#resolved-values>
+fn resolved_value_to_string<F>(
+    style: &ComputedValues,
+    property: &PropertyId,
+    used_value: F,
+) -> String
+where
+    F: FnOnce(LonghandId) -> Option<Au>,
+{
     let longhand_id = match *property {
         PropertyId::LonghandAlias(id, _) | PropertyId::Longhand(id) => id,
         // Firefox returns blank strings for the computed value of shorthands,
@@ -838,61 +951,66 @@ where
             return style.computed_value_to_string(PropertyDeclarationId::Custom(name));
         },
     };
+    match used_value(longhand_id) {
+        Some(value) => value.to_css_string(),
+        // FIXME: implement used value computation for line-height
+        None => style.computed_value_to_string(PropertyDeclarationId::Longhand(longhand_id)),
+    }
+}
 
-    let positioned = match style.get_box().position {
-        Position::Relative | Position::Sticky | Position::Fixed | Position::Absolute => true,
+/// The style of a lazily cascaded pseudo-element of an element, if it exists.
+fn lazy_pseudo_element_style<E: TElement>(
+    context: &LayoutContext,
+    element: E,
+    pseudo: &PseudoElement,
+) -> Option<ServoArc<ComputedValues>> {
+    let data = element.borrow_data()?;
+    let primary_style = data.styles.primary();
+    let exists = match *pseudo {
+        PseudoElement::Marker => primary_style.get_box().clone_display().is_list_item(),
+        PseudoElement::Placeholder => {
+            *element.namespace() == ns!(html) &&
+                (*element.local_name() == local_name!("input") ||
+                    *element.local_name() == local_name!("textarea"))
+        },
         _ => false,
     };
+    if !exists {
+        return None;
+    }
+    let shared = &context.style_context;
+    shared.stylist.lazily_compute_pseudo_element_style(
+        &shared.guards,
+        element,
+        pseudo,
+        RuleInclusion::All,
+        primary_style,
+        /* is_probe = */ false,
+        &ServoMetricsProvider,
+        /* matching_func = */ None,
+    )
+}
 
-    //TODO: determine whether requested property applies to the element.
-    //      eg. width does not apply to non-replaced inline elements.
-    // Existing browsers disagree about when left/top/right/bottom apply
-    // (Chrome seems to think they never apply and always returns resolved values).
-    // There are probably other quirks.
-    let applies = true;
-
-    fn used_value_for_position_property<N: LayoutNode>(
-        layout_el: <N::ConcreteThreadSafeLayoutNode as ThreadSafeLayoutNode>::ConcreteThreadSafeLayoutElement,
-        layout_root: &mut dyn Flow,
-        requested_node: N,
-        longhand_id: LonghandId,
-    ) -> String {
-        let maybe_data = layout_el.borrow_layout_data();
-        let position = maybe_data.map_or(Point2D::zero(), |data| {
-            match (*data).flow_construction_result {
-                ConstructionResult::Flow(ref flow_ref, _) => flow_ref
-                    .deref()
-                    .base()
-                    .stacking_relative_position
-                    .to_point(),
-                // TODO(dzbarsky) search parents until we find node with a flow ref.
-                // https://github.com/servo/servo/issues/8307
-                _ => Point2D::zero(),
-            }
-        });
-        let property = match longhand_id {
-            LonghandId::Bottom => PositionProperty::Bottom,
-            LonghandId::Top => PositionProperty::Top,
-            LonghandId::Left => PositionProperty::Left,
-            LonghandId::Right => PositionProperty::Right,
-            LonghandId::Width => PositionProperty::Width,
-            LonghandId::Height => PositionProperty::Height,
-            _ => unreachable!(),
-        };
-        let mut iterator = PositionRetrievingFragmentBorderBoxIterator::new(
-            requested_node.opaque(),
-            property,
-            position,
-        );
-        sequential::iterate_through_flow_tree_fragment_border_boxes(layout_root, &mut iterator);
-        iterator
-            .result
-            .map(|r| r.to_css_string())
-            .unwrap_or(String::new())
+/// The used value of a property of an element or of one of its pseudo-elements, for
+/// the properties whose resolved value is their used value.
+/// <https://drafts.csswg.org/cssom/#resolved-values>
+fn used_value_for_property<E: TElement>(
+    context: &LayoutContext,
+    element: E,
+    pseudo: PseudoElementType,
+    style: &ComputedValues,
+    longhand_id: LonghandId,
+    layout_root: &mut dyn Flow,
+) -> Option<Au> {
+    // Elements without a box only have computed values.
+    let display = style.get_box().display;
+    if display == Display::None || display == Display::Contents {
+        return None;
     }
 
-    // TODO: we will return neither the computed nor used value for margin and padding.
+    let node = element.as_node().opaque();
     match longhand_id {
+        // TODO: we will return neither the computed nor used value for margin and padding.
         LonghandId::MarginBottom |
         LonghandId::MarginTop |
         LonghandId::MarginLeft |
@@ -901,7 +1019,7 @@ where
         LonghandId::PaddingTop |
         LonghandId::PaddingLeft |
         LonghandId::PaddingRight
-            if applies && style.get_box().display != Display::None =>
+            if pseudo == PseudoElementType::Normal =>
         {
             let (margin_padding, side) = match longhand_id {
                 LonghandId::MarginBottom => (MarginPadding::Margin, Side::Bottom),
@@ -915,30 +1033,148 @@ where
                 _ => unreachable!(),
             };
             let mut iterator = MarginRetrievingFragmentBorderBoxIterator::new(
-                requested_node.opaque(),
+                node,
                 side,
                 margin_padding,
                 style.writing_mode,
             );
             sequential::iterate_through_flow_tree_fragment_border_boxes(layout_root, &mut iterator);
-            iterator
-                .result
-                .map(|r| r.to_css_string())
-                .unwrap_or(String::new())
-        }
+            iterator.result
+        },
+        LonghandId::Width | LonghandId::Height => {
+            let used_box = used_box_of(node, pseudo, layout_root)?;
+            // The sizes do not apply to non-replaced inline elements.
+            if display.is_inline_flow() && !used_box.is_replaced {
+                return None;
+            }
+            let mut size = used_box.border_box.size;
+            if style.get_position().box_sizing == BoxSizing::ContentBox {
+                size.width -= used_box.border_padding.horizontal();
+                size.height -= used_box.border_padding.vertical();
+            }
+            Some(match longhand_id {
+                LonghandId::Width => size.width,
+                _ => size.height,
+            })
+        },
+        LonghandId::Bottom | LonghandId::Top | LonghandId::Right | LonghandId::Left => {
+            used_value_for_inset(context, element, pseudo, style, longhand_id, layout_root)
+        },
+        _ => None,
+    }
+}
 
-        LonghandId::Bottom | LonghandId::Top | LonghandId::Right | LonghandId::Left
-            if applies && positioned && style.get_box().display != Display::None =>
-        {
-            used_value_for_position_property(layout_el, layout_root, requested_node, longhand_id)
+/// The used value of an inset property of a positioned element.
+/// <https://drafts.csswg.org/cssom/#resolved-value-special-case-property-like-top>
+fn used_value_for_inset<E: TElement>(
+    context: &LayoutContext,
+    element: E,
+    pseudo: PseudoElementType,
+    style: &ComputedValues,
+    longhand_id: LonghandId,
+    layout_root: &mut dyn Flow,
+) -> Option<Au> {
+    let position_scheme = style.get_box().position;
+    let containing_block = match position_scheme {
+        Position::Static => return None,
+        _ => containing_block_rect(context, element, pseudo, position_scheme, layout_root)?,
+    };
+
+    match position_scheme {
+        Position::Absolute | Position::Fixed => {
+            let used_box = used_box_of(element.as_node().opaque(), pseudo, layout_root)?;
+            let margin_box = used_box.border_box.outer_rect(used_box.margin);
+            Some(match longhand_id {
+                LonghandId::Left => margin_box.min_x() - containing_block.min_x(),
+                LonghandId::Top => margin_box.min_y() - containing_block.min_y(),
+                LonghandId::Right => containing_block.max_x() - margin_box.max_x(),
+                _ => containing_block.max_y() - margin_box.max_y(),
+            })
         },
-        LonghandId::Width | LonghandId::Height
-            if applies && style.get_box().display != Display::None =>
-        {
-            used_value_for_position_property(layout_el, layout_root, requested_node, longhand_id)
+        _ => {
+            // Relatively positioned boxes are offset by their insets, and an auto
+            // inset is the opposite of the inset of the other side.
+            let position = style.get_position();
+            let width = containing_block.size.width;
+            let height = containing_block.size.height;
+            let (start, end, basis) = match longhand_id {
+                LonghandId::Left => (&position.left, &position.right, width),
+                LonghandId::Right => (&position.right, &position.left, width),
+                LonghandId::Top => (&position.top, &position.bottom, height),
+                _ => (&position.bottom, &position.top, height),
+            };
+            Some(
+                match (start.to_used_value(basis), end.to_used_value(basis)) {
+                    (Some(start), _) => start,
+                    (None, Some(end)) => -end,
+                    (None, None) => Au(0),
+                },
+            )
+        },
+    }
+}
+
+/// The boxes of the first fragment of a node or of one of its pseudo-elements, if it
+/// has any.
+fn used_box_of(
+    node: OpaqueNode,
+    pseudo: PseudoElementType,
+    layout_root: &mut dyn Flow,
+) -> Option<UsedBox> {
+    let mut iterator = UsedBoxRetrievingFragmentBorderBoxIterator::new(node, pseudo);
+    sequential::iterate_through_flow_tree_fragment_border_boxes(layout_root, &mut iterator);
+    iterator.result
+}
+
+/// The parent of an element in the flat tree, or the element itself for its
+/// pseudo-elements.
+fn parent_element<E: TElement>(element: E, pseudo: PseudoElementType) -> Option<E> {
+    match pseudo {
+        PseudoElementType::Normal => element.traversal_parent(),
+        _ => Some(element),
+    }
+}
+
+/// The rectangle of the containing block of an element or of one of its
+/// pseudo-elements with the given positioning scheme, relative to the document.
+/// <https://drafts.csswg.org/css2/visudet.html#containing-block-details>
+fn containing_block_rect<E: TElement>(
+    context: &LayoutContext,
+    element: E,
+    pseudo: PseudoElementType,
+    position: Position,
+    layout_root: &mut dyn Flow,
+) -> Option<Rect<Au>> {
+    let initial_containing_block =
+        Rect::new(Point2D::zero(), context.style_context.viewport_size());
+    match position {
+        Position::Fixed => Some(initial_containing_block),
+        Position::Absolute => {
+            // The padding box of the nearest positioned ancestor.
+            let mut ancestor = parent_element(element, pseudo);
+            while let Some(element) = ancestor {
+                let is_positioned = element.borrow_data().map_or(false, |data| {
+                    data.styles.primary().get_box().position != Position::Static
+                });
+                if is_positioned {
+                    let node = element.as_node().opaque();
+                    let used_box = used_box_of(node, PseudoElementType::Normal, layout_root)?;
+                    return Some(used_box.border_box.inner_rect(used_box.border));
+                }
+                ancestor = element.traversal_parent();
+            }
+            Some(initial_containing_block)
+        },
+        _ => {
+            // The content box of the parent.
+            let parent = match parent_element(element, pseudo) {
+                Some(parent) => parent,
+                None => return Some(initial_containing_block),
+            };
+            let node = parent.as_node().opaque();
+            let used_box = used_box_of(node, PseudoElementType::Normal, layout_root)?;
+            Some(used_box.border_box.inner_rect(used_box.border_padding))
         },
-        // FIXME: implement used value computation for line-height
-        _ => style.computed_value_to_string(PropertyDeclarationId::Longhand(longhand_id)),
     }
 }
 
@@ -986,30 +1222,32 @@ pub fn process_element_inner_text_query<N: LayoutNode>(
     node: N,
     indexable_text: &IndexableText,
 ) -> String {
-    // Step 1.
-    let mut results = Vec::new();
     // Step 2.
-    inner_text_collection_steps(node, indexable_text, &mut results);
+    let mut results = Vec::new();
+    let mut child = node.first_child();
+    while let Some(node) = child {
+        rendered_text_collection_steps(node, indexable_text, &mut results);
+        child = node.next_sibling();
+    }
     let mut max_req_line_break_count = 0;
-    let mut inner_text = Vec::new();
+    let mut inner_text = String::new();
     for item in results {
         match item {
             InnerTextItem::Text(s) => {
-                if max_req_line_break_count > 0 {
-                    // Step 5.
-                    for _ in 0..max_req_line_break_count {
-                        inner_text.push("\u{000A}".to_owned());
-                    }
-                    max_req_line_break_count = 0;
-                }
                 // Step 3.
-                if !s.is_empty() {
-                    inner_text.push(s.to_owned());
+                if s.is_empty() {
+                    continue;
                 }
+                // Step 5.
+                for _ in 0..max_req_line_break_count {
+                    inner_text.push('\u{000A}');
+                }
+                max_req_line_break_count = 0;
+                inner_text.push_str(&s);
             },
             InnerTextItem::RequiredLineBreakCount(count) => {
                 // Step 4.
-                if inner_text.len() == 0 {
+                if inner_text.is_empty() {
                     // Remove required line break count at the start.
                     continue;
                 }
@@ -1022,100 +1260,135 @@ pub fn process_element_inner_text_query<N: LayoutNode>(
             },
         }
     }
-    inner_text.into_iter().collect()
+    // Step 6.
+    inner_text
 }
 
-// https://html.spec.whatwg.org/multipage/#inner-text-collection-steps
-#[allow(unsafe_code)]
-fn inner_text_collection_steps<N: LayoutNode>(
+// https://html.spec.whatwg.org/multipage/#rendered-text-collection-steps
+fn rendered_text_collection_steps<N: LayoutNode>(
     node: N,
     indexable_text: &IndexableText,
     results: &mut Vec<InnerTextItem>,
 ) {
+    let style = match node.type_id() {
+        LayoutNodeType::Text => node.parent_node().and_then(style_of),
+        _ => style_of(node),
+    };
+    // Step 3. Nodes without a style are not being rendered, and neither are their
+    // descendants.
+    let style = match style {
+        Some(ref style) if style.get_box().display != Display::None => style,
+        _ => return,
+    };
+    if let LayoutNodeType::Text = node.type_id() {
+        // Step 2.
+        if style.get_inherited_box().visibility != Visibility::Visible {
+            return;
+        }
+        // Step 4. Text runs have their white space processed and their text
+        // transformed already.
+        if let Some(text_content) = indexable_text.get(node.opaque()) {
+            for content in text_content {
+                let range = content.range.begin().to_usize()..content.range.end().to_usize();
+                let text = content.text_run.text[range].replace('\u{00AD}', "");
+                results.push(InnerTextItem::Text(text));
+            }
+        }
+        return;
+    }
+
+    // Step 1.
     let mut items = Vec::new();
-    for child in node.traverse_preorder() {
-        let node = match child.type_id() {
-            LayoutNodeType::Text => child.parent_node().unwrap(),
-            _ => child,
-        };
+    let mut child = node.first_child();
+    while let Some(node) = child {
+        rendered_text_collection_steps(node, indexable_text, &mut items);
+        child = node.next_sibling();
+    }
 
-        let element_data = unsafe {
-            node.get_style_and_layout_data()
-                .map(|d| &(*(d.ptr.as_ptr() as *mut StyleData)).element_data)
-        };
+    // Step 2.
+    if style.get_inherited_box().visibility != Visibility::Visible {
+        results.append(&mut items);
+        return;
+    }
 
-        if element_data.is_none() {
-            continue;
-        }
+    if let LayoutNodeType::Element(LayoutElementType::HTMLBRElement) = node.type_id() {
+        // Step 5.
+        items.push(InnerTextItem::Text(String::from(
+            "\u{000A}", /* line feed */
+        )));
+    }
 
-        let style = match element_data.unwrap().borrow().styles.get_primary() {
-            None => continue,
-            Some(style) => style.clone(),
-        };
+    let display = style.get_box().display;
+    if display == Display::TableCell && !is_last_table_cell(node) {
+        // Step 6.
+        items.push(InnerTextItem::Text(String::from("\u{0009}" /* tab */)));
+    }
+    if display == Display::TableRow && !is_last_table_row(node) {
+        // Step 7.
+        items.push(InnerTextItem::Text(String::from(
+            "\u{000A}", /* line feed */
+        )));
+    }
 
-        // Step 2.
-        if style.get_inherited_box().visibility != Visibility::Visible {
-            continue;
-        }
+    if let LayoutNodeType::Element(LayoutElementType::HTMLParagraphElement) = node.type_id() {
+        // Step 8.
+        results.push(InnerTextItem::RequiredLineBreakCount(2));
+        results.append(&mut items);
+        results.push(InnerTextItem::RequiredLineBreakCount(2));
+    } else if display.outside() == DisplayOutside::Block || display == Display::TableCaption {
+        // Step 9.
+        results.push(InnerTextItem::RequiredLineBreakCount(1));
+        results.append(&mut items);
+        results.push(InnerTextItem::RequiredLineBreakCount(1));
+    } else {
+        // Step 10.
+        results.append(&mut items);
+    }
+}
 
-        // Step 3.
-        let display = style.get_box().display;
-        if !child.is_connected() || display == Display::None {
-            continue;
-        }
+#[allow(unsafe_code)]
+fn style_of<N: LayoutNode>(node: N) -> Option<ServoArc<ComputedValues>> {
+    let element_data = unsafe {
+        node.get_style_and_layout_data()
+            .map(|d| &(*(d.ptr.as_ptr() as *mut StyleData)).element_data)
+    }?;
+    let data = element_data.borrow();
+    data.styles.get_primary().cloned()
+}
 
-        match child.type_id() {
-            LayoutNodeType::Text => {
-                // Step 4.
-                if let Some(text_content) = indexable_text.get(child.opaque()) {
-                    for content in text_content {
-                        items.push(InnerTextItem::Text(content.text_run.text.to_string()));
-                    }
-                }
-            },
-            LayoutNodeType::Element(LayoutElementType::HTMLBRElement) => {
-                // Step 5.
-                items.push(InnerTextItem::Text(String::from(
-                    "\u{000A}", /* line feed */
-                )));
-            },
-            LayoutNodeType::Element(LayoutElementType::HTMLParagraphElement) => {
-                // Step 8.
-                items.insert(0, InnerTextItem::RequiredLineBreakCount(2));
-                items.push(InnerTextItem::RequiredLineBreakCount(2));
-            },
-            _ => {},
-        }
+fn has_display<N: LayoutNode>(node: N, displays: &[Display]) -> bool {
+    style_of(node).map_or(false, |style| displays.contains(&style.get_box().display))
+}
 
-        match display {
-            Display::TableCell if !is_last_table_cell() => {
-                // Step 6.
-                items.push(InnerTextItem::Text(String::from("\u{0009}" /* tab */)));
-            },
-            Display::TableRow if !is_last_table_row() => {
-                // Step 7.
-                items.push(InnerTextItem::Text(String::from(
-                    "\u{000A}", /* line feed */
-                )));
-            },
-            Display::Block | Display::Flex | Display::TableCaption | Display::Table => {
-                // Step 9.
-                items.insert(0, InnerTextItem::RequiredLineBreakCount(1));
-                items.push(InnerTextItem::RequiredLineBreakCount(1));
-            },
-            _ => {},
+fn has_next_sibling_with_display<N: LayoutNode>(node: N, displays: &[Display]) -> bool {
+    let mut sibling = node.next_sibling();
+    while let Some(node) = sibling {
+        if has_display(node, displays) {
+            return true;
         }
+        sibling = node.next_sibling();
     }
-
-    results.append(&mut items);
+    false
 }
 
-fn is_last_table_cell() -> bool {
-    // FIXME(ferjm) Implement this.
-    false
+fn is_last_table_cell<N: LayoutNode>(node: N) -> bool {
+    !has_next_sibling_with_display(node, &[Display::TableCell])
 }
 
-fn is_last_table_row() -> bool {
-    // FIXME(ferjm) Implement this.
-    false
+fn is_last_table_row<N: LayoutNode>(node: N) -> bool {
+    let row_groups = [
+        Display::TableRowGroup,
+        Display::TableHeaderGroup,
+        Display::TableFooterGroup,
+    ];
+    if has_next_sibling_with_display(node, &[Display::TableRow]) {
+        return false;
+    }
+    // Rows of the following row groups of the table come after this one.
+    match node.parent_node() {
+        Some(parent) if has_display(parent, &row_groups) => {
+            !has_next_sibling_with_display(parent, &row_groups)
+        },
+        _ => true,
+    }
 }
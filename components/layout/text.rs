@@ -21,13 +21,15 @@ use std::collections::LinkedList;
 use std::mem;
 use std::sync::Arc;
 use style::computed_values::text_rendering::T as TextRendering;
+use style::computed_values::unicode_bidi::T as UnicodeBidi;
 use style::computed_values::white_space::T as WhiteSpace;
 use style::computed_values::word_break::T as WordBreak;
 use style::logical_geometry::{LogicalSize, WritingMode};
 use style::properties::style_structs::Font as FontStyleStruct;
 use style::properties::ComputedValues;
+use style::values::generics::length::LengthOrNumber;
 use style::values::generics::text::LineHeight;
-use style::values::specified::text::{TextTransform, TextTransformCase};
+use style::values::specified::text::{TextTransform, TextTransformCase, TextTransformOther};
 use unicode_bidi as bidi;
 use unicode_script::{get_script, Script};
 use xi_unicode::LineBreakLeafIter;
@@ -52,6 +54,105 @@ fn text(fragments: &LinkedList<Fragment>) -> String {
     text
 }
 
+/// The directional isolate initiators and terminator, with which the `unicode-bidi` values
+/// `isolate`, `isolate-override` and `plaintext` are implemented, but which `unicode_bidi`
+/// resolves as if they were embeddings.
+const LEFT_TO_RIGHT_ISOLATE: char = '\u{2066}';
+const RIGHT_TO_LEFT_ISOLATE: char = '\u{2067}';
+const FIRST_STRONG_ISOLATE: char = '\u{2068}';
+const POP_DIRECTIONAL_ISOLATE: char = '\u{2069}';
+
+/// Returns the bidi embedding levels of the bytes of `text`, whose paragraphs have the level
+/// `para_level`, or the level of their first strong character if it is `None`.
+///
+/// The content of the directional isolates is resolved apart from the text around it, to
+/// which each isolate is a single neutral character, as in rule X5a of UAX #9.
+fn bidi_levels(text: &str, para_level: Option<bidi::Level>) -> Vec<bidi::Level> {
+    // The top-level isolates, as their initiator, the range of their content and the end of
+    // their terminator. An isolate without a terminator ends with its paragraph.
+    let mut isolates = vec![];
+    let mut open = None;
+    let mut depth = 0;
+    for (index, character) in text.char_indices() {
+        match character {
+            LEFT_TO_RIGHT_ISOLATE | RIGHT_TO_LEFT_ISOLATE | FIRST_STRONG_ISOLATE => {
+                if depth == 0 {
+                    open = Some((character, index + character.len_utf8()));
+                }
+                depth += 1;
+            },
+            POP_DIRECTIONAL_ISOLATE if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let (initiator, start) = open.take().unwrap();
+                    let end = index + character.len_utf8();
+                    isolates.push((initiator, start..index, end));
+                }
+            },
+            _ if depth > 0 && bidi::bidi_class(character) == bidi::BidiClass::B => {
+                depth = 0;
+                let (initiator, start) = open.take().unwrap();
+                isolates.push((initiator, start..index, index));
+            },
+            _ => {},
+        }
+    }
+    if let Some((initiator, start)) = open {
+        isolates.push((initiator, start..text.len(), text.len()));
+    }
+
+    if isolates.is_empty() {
+        return bidi::BidiInfo::new(text, para_level).levels;
+    }
+
+    // Resolve the text around the isolates, whose bytes are replaced by other neutrals.
+    let mut masked = text.as_bytes().to_vec();
+    for &(initiator, ref content, end) in &isolates {
+        let start = content.start - initiator.len_utf8();
+        for byte in &mut masked[start..end] {
+            *byte = b'!';
+        }
+    }
+    let masked = String::from_utf8(masked).unwrap();
+    let mut levels = bidi::BidiInfo::new(&masked, para_level).levels;
+
+    // Then resolve the content of each isolate, at the next level above the level of its
+    // initiator that has the direction of the isolate.
+    for (initiator, content, _) in isolates {
+        let level = levels[content.start - initiator.len_utf8()];
+        let content_text = &text[content.clone()];
+        let rtl = match initiator {
+            LEFT_TO_RIGHT_ISOLATE => false,
+            RIGHT_TO_LEFT_ISOLATE => true,
+            _ => first_strong_is_rtl(content_text),
+        };
+        let number = level.number() + if level.is_rtl() == rtl { 2 } else { 1 };
+        let content_level = bidi::Level::new(number).unwrap_or(level);
+        let content_levels = bidi_levels(content_text, Some(content_level));
+        levels[content].copy_from_slice(&content_levels);
+    }
+    levels
+}
+
+/// Returns whether the first strong character of `text` outside of its directional isolates
+/// is right-to-left, as in rule P2 of UAX #9.
+fn first_strong_is_rtl(text: &str) -> bool {
+    let mut depth = 0;
+    for character in text.chars() {
+        match character {
+            LEFT_TO_RIGHT_ISOLATE | RIGHT_TO_LEFT_ISOLATE | FIRST_STRONG_ISOLATE => depth += 1,
+            POP_DIRECTIONAL_ISOLATE if depth > 0 => depth -= 1,
+            _ if depth == 0 => match bidi::bidi_class(character) {
+                bidi::BidiClass::L => return false,
+                bidi::BidiClass::R | bidi::BidiClass::AL => return true,
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+    false
+}
+
 /// A stack-allocated object for scanning an inline flow into `TextRun`-containing `TextFragment`s.
 pub struct TextRunScanner {
     pub clump: LinkedList<Fragment>,
@@ -76,18 +177,19 @@ impl TextRunScanner {
         debug_assert!(!fragments.is_empty());
 
         // Calculate bidi embedding levels, so we can split bidirectional fragments for reordering.
+        // The paragraphs of a `unicode-bidi: plaintext` element get the direction of their
+        // first strong character rather than that of the element.
         let text = text(&fragments);
-        let para_level = fragments
-            .front()
-            .unwrap()
-            .style
-            .writing_mode
-            .to_bidi_level();
-        let bidi_info = bidi::BidiInfo::new(&text, Some(para_level));
+        let front_style = &fragments.front().unwrap().style;
+        let para_level = match front_style.get_text().unicode_bidi {
+            UnicodeBidi::Plaintext => None,
+            _ => Some(front_style.writing_mode.to_bidi_level()),
+        };
+        let levels = bidi_levels(&text, para_level);
 
         // Optimization: If all the text is LTR, don't bother splitting on bidi levels.
-        let bidi_levels = if bidi_info.has_rtl() {
-            Some(&bidi_info.levels[..])
+        let bidi_levels = if bidi::level::has_rtl(&levels) {
+            Some(&levels[..])
         } else {
             None
         };
@@ -181,6 +283,8 @@ impl TextRunScanner {
             let word_spacing;
             let text_rendering;
             let word_break;
+            let white_space;
+            let tab_size;
             {
                 let in_fragment = self.clump.front().unwrap();
                 let font_style = in_fragment.style().clone_font();
@@ -190,31 +294,51 @@ impl TextRunScanner {
                     WhiteSpace::Normal | WhiteSpace::Nowrap => {
                         CompressionMode::CompressWhitespaceNewline
                     },
-                    WhiteSpace::Pre | WhiteSpace::PreWrap => CompressionMode::CompressNone,
+                    WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::BreakSpaces => {
+                        CompressionMode::CompressNone
+                    },
                     WhiteSpace::PreLine => CompressionMode::CompressWhitespace,
                 };
                 text_transform = inherited_text_style.text_transform;
-                letter_spacing = inherited_text_style.letter_spacing;
+                // Percentages are relative to the font size.
+                // https://drafts.csswg.org/css-text-3/#letter-spacing-property
+                letter_spacing = inherited_text_style
+                    .letter_spacing
+                    .0
+                    .to_used_value(Au::from(in_fragment.style().get_font().font_size.size()));
+                let space_width = font_group
+                    .borrow_mut()
+                    .find_by_codepoint(&mut font_context, ' ')
+                    .and_then(|font| {
+                        let font = font.borrow();
+                        font.glyph_index(' ')
+                            .map(|glyph_id| font.glyph_h_advance(glyph_id))
+                    })
+                    .unwrap_or(font::LAST_RESORT_GLYPH_ADVANCE);
                 word_spacing = inherited_text_style
                     .word_spacing
                     .to_length()
                     .map(|l| l.into())
                     .unwrap_or_else(|| {
-                        let space_width = font_group
-                            .borrow_mut()
-                            .find_by_codepoint(&mut font_context, ' ')
-                            .and_then(|font| {
-                                let font = font.borrow();
-                                font.glyph_index(' ')
-                                    .map(|glyph_id| font.glyph_h_advance(glyph_id))
-                            })
-                            .unwrap_or(font::LAST_RESORT_GLYPH_ADVANCE);
                         inherited_text_style
                             .word_spacing
                             .to_used_value(Au::from_f64_px(space_width))
                     });
                 text_rendering = inherited_text_style.text_rendering;
                 word_break = inherited_text_style.word_break;
+                white_space = in_fragment.white_space();
+
+                // A number is a multiple of the advance of a space, including its
+                // `letter-spacing` and `word-spacing`.
+                // https://drafts.csswg.org/css-text-3/#tab-size-property
+                tab_size = match inherited_text_style.tab_size {
+                    LengthOrNumber::Length(length) => Au::from(length.0),
+                    LengthOrNumber::Number(number) => {
+                        let space_advance =
+                            Au::from_f64_px(space_width) + letter_spacing + word_spacing;
+                        space_advance.scale_by(number.0)
+                    },
+                };
             }
 
             // First, transform/compress text of all the nodes.
@@ -333,7 +457,7 @@ impl TextRunScanner {
             // example, `finally` with a wide `letter-spacing` renders as `f i n a l l y` and not
             // `ﬁ n a l l y`.
             let mut flags = ShapingFlags::empty();
-            if letter_spacing.0.px() != 0. {
+            if letter_spacing != Au(0) {
                 flags.insert(ShapingFlags::IGNORE_LIGATURES_SHAPING_FLAG);
             }
             if text_rendering == TextRendering::Optimizespeed {
@@ -343,13 +467,17 @@ impl TextRunScanner {
             if word_break == WordBreak::KeepAll {
                 flags.insert(ShapingFlags::KEEP_ALL_FLAG);
             }
+            if white_space.breaks_spaces() {
+                flags.insert(ShapingFlags::BREAK_SPACES_FLAG);
+            }
             let options = ShapingOptions {
-                letter_spacing: if letter_spacing.0.px() == 0. {
+                letter_spacing: if letter_spacing == Au(0) {
                     None
                 } else {
-                    Some(Au::from(letter_spacing.0))
+                    Some(letter_spacing)
                 },
                 word_spacing,
+                tab_size,
                 script: Script::Common,
                 flags: flags,
             };
@@ -746,8 +874,7 @@ impl RunMapping {
 /// Accounts for `text-transform`.
 ///
 /// FIXME(#4311, pcwalton): Title-case mapping can change length of the string;
-/// case mapping should be language-specific; `full-width`;
-/// use graphemes instead of characters.
+/// case mapping should be language-specific; use graphemes instead of characters.
 fn apply_style_transform_if_necessary(
     string: &mut String,
     first_character_position: usize,
@@ -796,6 +923,157 @@ fn apply_style_transform_if_necessary(
             }
         },
     }
+
+    if text_transform
+        .other_
+        .contains(TextTransformOther::FULL_WIDTH)
+    {
+        let original = string[first_character_position..].to_owned();
+        string.truncate(first_character_position);
+
+        let mut characters = original.chars().peekable();
+        while let Some(character) = characters.next() {
+            let character = full_width(character);
+
+            // A half-width voiced sound mark combines with the kana before it, if there is a
+            // precomposed form of them.
+            let voiced = characters
+                .peek()
+                .and_then(|&mark| compose_voiced_kana(character, mark));
+            match voiced {
+                Some(voiced) => {
+                    characters.next();
+                    string.push(voiced);
+                },
+                None => string.push(character),
+            }
+        }
+    }
+
+    if text_transform
+        .other_
+        .contains(TextTransformOther::FULL_SIZE_KANA)
+    {
+        let original = string[first_character_position..].to_owned();
+        string.truncate(first_character_position);
+        string.extend(original.chars().map(full_size_kana));
+    }
+}
+
+/// The half-width katakana and punctuation from U+FF61 to U+FF9F, in their full-width forms.
+const FULL_WIDTH_KATAKANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソ\
+                                   タチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+/// The full-width form of a character, for `text-transform: full-width`.
+/// <https://drafts.csswg.org/css-text-3/#valdef-text-transform-full-width>
+///
+/// FIXME: Half-width Hangul is left as is.
+fn full_width(character: char) -> char {
+    match character {
+        ' ' => '\u{3000}',
+        '!'..='~' => char::from_u32(character as u32 + 0xFEE0).unwrap(),
+        '\u{a2}' => '\u{ffe0}',
+        '\u{a3}' => '\u{ffe1}',
+        '\u{ac}' => '\u{ffe2}',
+        '\u{af}' => '\u{ffe3}',
+        '\u{a6}' => '\u{ffe4}',
+        '\u{a5}' => '\u{ffe5}',
+        '\u{20a9}' => '\u{ffe6}',
+        '\u{ff61}'..='\u{ff9f}' => FULL_WIDTH_KATAKANA
+            .chars()
+            .nth(character as usize - 0xff61)
+            .unwrap(),
+        '\u{ffe8}' => '\u{2502}',
+        '\u{ffe9}' => '\u{2190}',
+        '\u{ffea}' => '\u{2191}',
+        '\u{ffeb}' => '\u{2192}',
+        '\u{ffec}' => '\u{2193}',
+        '\u{ffed}' => '\u{25a0}',
+        '\u{ffee}' => '\u{25cb}',
+        _ => character,
+    }
+}
+
+/// The precomposed form of a full-width katakana followed by a half-width voiced (U+FF9E) or
+/// semi-voiced (U+FF9F) sound mark, if there is one.
+fn compose_voiced_kana(kana: char, mark: char) -> Option<char> {
+    let offset = match (kana, mark) {
+        ('ウ', '\u{ff9e}') => return Some('ヴ'),
+        ('ワ', '\u{ff9e}') => return Some('ヷ'),
+        ('ヲ', '\u{ff9e}') => return Some('ヺ'),
+        (_, '\u{ff9e}') if "カキクケコサシスセソタチツテトハヒフヘホ".contains(kana) => {
+            1
+        },
+        (_, '\u{ff9f}') if "ハヒフヘホ".contains(kana) => 2,
+        _ => return None,
+    };
+    char::from_u32(kana as u32 + offset)
+}
+
+/// The full-size form of a small kana, for `text-transform: full-size-kana`.
+/// <https://drafts.csswg.org/css-text-3/#small-kana>
+fn full_size_kana(character: char) -> char {
+    match character {
+        'ぁ' => 'あ',
+        'ぃ' => 'い',
+        'ぅ' => 'う',
+        'ぇ' => 'え',
+        'ぉ' => 'お',
+        'ゕ' => 'か',
+        'ゖ' => 'け',
+        'っ' => 'つ',
+        'ゃ' => 'や',
+        'ゅ' => 'ゆ',
+        'ょ' => 'よ',
+        'ゎ' => 'わ',
+        'ァ' => 'ア',
+        'ィ' => 'イ',
+        'ゥ' => 'ウ',
+        'ェ' => 'エ',
+        'ォ' => 'オ',
+        'ヵ' => 'カ',
+        'ㇰ' => 'ク',
+        'ヶ' => 'ケ',
+        'ㇱ' => 'シ',
+        'ㇲ' => 'ス',
+        'ッ' => 'ツ',
+        'ㇳ' => 'ト',
+        'ㇴ' => 'ヌ',
+        'ㇵ' => 'ハ',
+        'ㇶ' => 'ヒ',
+        'ㇷ' => 'フ',
+        'ㇸ' => 'ヘ',
+        'ㇹ' => 'ホ',
+        'ㇺ' => 'ム',
+        'ャ' => 'ヤ',
+        'ュ' => 'ユ',
+        'ョ' => 'ヨ',
+        'ㇻ' => 'ラ',
+        'ㇼ' => 'リ',
+        'ㇽ' => 'ル',
+        'ㇾ' => 'レ',
+        'ㇿ' => 'ロ',
+        'ヮ' => 'ワ',
+        '\u{1b132}' => 'こ',
+        '\u{1b150}' => 'ゐ',
+        '\u{1b151}' => 'ゑ',
+        '\u{1b152}' => 'を',
+        '\u{1b155}' => 'コ',
+        '\u{1b164}' => 'ヰ',
+        '\u{1b165}' => 'ヱ',
+        '\u{1b166}' => 'ヲ',
+        '\u{1b167}' => 'ン',
+        'ｧ' => 'ｱ',
+        'ｨ' => 'ｲ',
+        'ｩ' => 'ｳ',
+        'ｪ' => 'ｴ',
+        'ｫ' => 'ｵ',
+        'ｬ' => 'ﾔ',
+        'ｭ' => 'ﾕ',
+        'ｮ' => 'ﾖ',
+        'ｯ' => 'ﾂ',
+        _ => character,
+    }
 }
 
 #[derive(Clone)]
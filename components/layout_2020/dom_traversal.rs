@@ -27,12 +27,13 @@ use style::values::generics::counters::ContentItem;
 
 #[derive(Clone, Copy, Debug)]
 pub enum WhichPseudoElement {
+    Backdrop,
     Before,
     After,
 }
 
 pub(super) enum Contents {
-    /// Refers to a DOM subtree, plus `::before` and `::after` pseudo-elements.
+    /// Refers to a DOM subtree, plus `::backdrop`, `::before` and `::after` pseudo-elements.
     OfElement,
 
     /// Example: an `<img src=…>` element.
@@ -79,6 +80,12 @@ fn traverse_children_of<'dom, Node>(
 ) where
     Node: NodeExt<'dom>,
 {
+    traverse_pseudo_element(
+        WhichPseudoElement::Backdrop,
+        parent_element,
+        context,
+        handler,
+    );
     traverse_pseudo_element(WhichPseudoElement::Before, parent_element, context, handler);
 
     let mut next = parent_element.first_child();
@@ -136,15 +143,22 @@ fn traverse_pseudo_element<'dom, Node>(
     Node: NodeExt<'dom>,
 {
     if let Some(style) = pseudo_element_style(which, element, context) {
+        let items = || match which {
+            // `::backdrop` does not generate any content.
+            WhichPseudoElement::Backdrop => vec![],
+            WhichPseudoElement::Before | WhichPseudoElement::After => {
+                generate_pseudo_element_content(&style, element, context)
+            },
+        };
         match Display::from(style.get_box().display) {
             Display::None => element.unset_pseudo_element_box(which),
             Display::Contents => {
                 element.unset_pseudo_element_box(which);
-                let items = generate_pseudo_element_content(&style, element, context);
+                let items = items();
                 traverse_pseudo_element_contents(element, &style, context, handler, items);
             },
             Display::GeneratingBox(display) => {
-                let items = generate_pseudo_element_content(&style, element, context);
+                let items = items();
                 let contents = Contents::OfPseudoElement(items);
                 let box_slot = element.pseudo_element_box_slot(which);
                 handler.handle_element(element, &style, display, contents, box_slot);
@@ -260,12 +274,20 @@ where
     Node: NodeExt<'dom>,
 {
     match which {
+        WhichPseudoElement::Backdrop => element.to_threadsafe().get_backdrop_pseudo(),
         WhichPseudoElement::Before => element.to_threadsafe().get_before_pseudo(),
         WhichPseudoElement::After => element.to_threadsafe().get_after_pseudo(),
     }
     .and_then(|pseudo_element| {
         let style = pseudo_element.style(context.shared_context());
-        if style.ineffective_content_property() {
+        // The backdrop of an element in the top layer always generates a box,
+        // whatever its `content` property.
+        // <https://fullscreen.spec.whatwg.org/#::backdrop-pseudo-element>
+        let is_backdrop = match which {
+            WhichPseudoElement::Backdrop => true,
+            WhichPseudoElement::Before | WhichPseudoElement::After => false,
+        };
+        if !is_backdrop && style.ineffective_content_property() {
             None
         } else {
             Some(style)
@@ -454,6 +476,7 @@ where
         let mut data = self.layout_data_mut();
         let pseudos = data.pseudo_elements.get_or_insert_with(Default::default);
         let cell = match which {
+            WhichPseudoElement::Backdrop => &mut pseudos.backdrop,
             WhichPseudoElement::Before => &mut pseudos.before,
             WhichPseudoElement::After => &mut pseudos.after,
         };
@@ -463,6 +486,7 @@ where
     fn unset_pseudo_element_box(self, which: WhichPseudoElement) {
         if let Some(pseudos) = &mut self.layout_data_mut().pseudo_elements {
             match which {
+                WhichPseudoElement::Backdrop => *pseudos.backdrop.borrow_mut() = None,
                 WhichPseudoElement::Before => *pseudos.before.borrow_mut() = None,
                 WhichPseudoElement::After => *pseudos.after.borrow_mut() = None,
             }
@@ -14,6 +14,7 @@ pub struct LayoutDataForElement {
 
 #[derive(Default)]
 pub(super) struct PseudoElementBoxes {
+    pub backdrop: ArcRefCell<Option<LayoutBox>>,
     pub before: ArcRefCell<Option<LayoutBox>>,
     pub after: ArcRefCell<Option<LayoutBox>>,
 }
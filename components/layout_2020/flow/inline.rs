@@ -646,8 +646,12 @@ impl TextRun {
 
         let font_style = self.parent_style.clone_font();
         let inherited_text_style = self.parent_style.get_inherited_text();
-        let letter_spacing = if inherited_text_style.letter_spacing.0.px() != 0. {
-            Some(app_units::Au::from(inherited_text_style.letter_spacing.0))
+        let letter_spacing = inherited_text_style
+            .letter_spacing
+            .0
+            .to_used_value(font_style.font_size.size().into());
+        let letter_spacing = if letter_spacing != Au(0) {
+            Some(letter_spacing)
         } else {
             None
         };
@@ -672,21 +676,24 @@ impl TextRun {
                 .expect("could not find font");
             let mut font = font.borrow_mut();
 
+            let space_width = font
+                .glyph_index(' ')
+                .map(|glyph_id| font.glyph_h_advance(glyph_id))
+                .unwrap_or(gfx::font::LAST_RESORT_GLYPH_ADVANCE);
             let word_spacing = &inherited_text_style.word_spacing;
             let word_spacing = word_spacing
                 .to_length()
                 .map(|l| l.into())
-                .unwrap_or_else(|| {
-                    let space_width = font
-                        .glyph_index(' ')
-                        .map(|glyph_id| font.glyph_h_advance(glyph_id))
-                        .unwrap_or(gfx::font::LAST_RESORT_GLYPH_ADVANCE);
-                    word_spacing.to_used_value(Au::from_f64_px(space_width))
-                });
+                .unwrap_or_else(|| word_spacing.to_used_value(Au::from_f64_px(space_width)));
+
+            // `tab-size` isn't supported yet, so use its initial value of 8 spaces.
+            let tab_size =
+                (Au::from_f64_px(space_width) + letter_spacing.unwrap_or(Au(0)) + word_spacing) * 8;
 
             let shaping_options = gfx::font::ShapingOptions {
                 letter_spacing,
                 word_spacing,
+                tab_size,
                 script: unicode_script::Script::Common,
                 flags,
             };
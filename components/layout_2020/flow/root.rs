@@ -25,6 +25,7 @@ use crate::DefiniteContainingBlock;
 use app_units::Au;
 use euclid::default::{Point2D, Rect, Size2D};
 use gfx_traits::print_tree::PrintTree;
+use script_layout_interface::rpc::BoxSizes;
 use script_layout_interface::wrapper_traits::LayoutNode;
 use servo_arc::Arc;
 use style::dom::OpaqueNode;
@@ -356,4 +357,39 @@ impl FragmentTreeRoot {
         })
         .unwrap_or_else(Rect::zero)
     }
+
+    pub fn get_box_sizes_for_node(&self, requested_node: OpaqueNode) -> Option<BoxSizes> {
+        self.find(|fragment, containing_block| match fragment {
+            Fragment::Box(fragment) if fragment.tag == requested_node => {
+                let writing_mode = fragment.style.writing_mode;
+                let to_au = |length: Length| Au::from_f32_px(length.px());
+                let content_rect = fragment
+                    .content_rect
+                    .to_physical(writing_mode, &containing_block);
+                let padding_rect = fragment
+                    .padding_rect()
+                    .to_physical(writing_mode, &containing_block);
+                let border_rect = fragment
+                    .border_rect()
+                    .to_physical(writing_mode, &containing_block);
+                Some(BoxSizes {
+                    content_box: Rect::new(
+                        Point2D::new(
+                            to_au(content_rect.origin.x - padding_rect.origin.x),
+                            to_au(content_rect.origin.y - padding_rect.origin.y),
+                        ),
+                        Size2D::new(
+                            to_au(content_rect.size.width),
+                            to_au(content_rect.size.height),
+                        ),
+                    ),
+                    border_box: Size2D::new(
+                        to_au(border_rect.size.width),
+                        to_au(border_rect.size.height),
+                    ),
+                })
+            },
+            _ => None,
+        })
+    }
 }
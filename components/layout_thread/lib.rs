@@ -26,13 +26,14 @@ mod dom_wrapper;
 use crate::dom_wrapper::drop_style_and_layout_data;
 use crate::dom_wrapper::{ServoLayoutDocument, ServoLayoutElement, ServoLayoutNode};
 use app_units::Au;
+use content_security_policy::CspList;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use embedder_traits::resources::{self, Resource};
 use euclid::{default::Size2D as UntypedSize2D, Point2D, Rect, Scale, Size2D};
 use fnv::FnvHashMap;
 use fxhash::{FxHashMap, FxHashSet};
 use gfx::font;
-use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_cache_thread::{FontCacheThread, WebFontRequester};
 use gfx::font_context;
 use gfx_traits::{node_id_from_scroll_id, Epoch};
 use histogram::Histogram;
@@ -51,6 +52,7 @@ use layout::flow_ref::FlowRef;
 use layout::incremental::{RelayoutMode, SpecialRestyleDamage};
 use layout::layout_debug;
 use layout::parallel;
+use layout::query::{process_box_sizes_request, process_content_size_request};
 use layout::query::{process_client_rect_query, process_element_inner_text_query};
 use layout::query::{
     process_content_box_request, process_content_boxes_request, LayoutRPCImpl, LayoutThreadData,
@@ -118,7 +120,7 @@ use style::selector_parser::SnapshotMap;
 use style::servo::restyle_damage::ServoRestyleDamage;
 use style::shared_lock::{SharedRwLock, SharedRwLockReadGuard, StylesheetGuards};
 use style::stylesheets::{
-    DocumentStyleSheet, Origin, Stylesheet, StylesheetInDocument, UserAgentStylesheets,
+    DocumentStyleSheet, Origin, OriginSet, Stylesheet, StylesheetInDocument, UserAgentStylesheets,
 };
 use style::stylist::Stylist;
 use style::thread_state::{self, ThreadState};
@@ -140,6 +142,9 @@ pub struct LayoutThread {
     /// The URL of the pipeline that we belong to.
     url: ServoUrl,
 
+    /// The Content Security Policies of the document, which apply to its web fonts.
+    csp_list: Option<CspList>,
+
     /// Performs CSS selector matching and style resolution.
     stylist: Stylist,
 
@@ -182,6 +187,12 @@ pub struct LayoutThread {
     /// Is this the first reflow in this LayoutThread?
     first_reflow: Cell<bool>,
 
+    /// The user agent and user stylesheets supplied by the embedder.
+    embedder_stylesheets: Vec<DocumentStyleSheet>,
+
+    /// The stylesheet which hides the elements matching the rules of the content blocker.
+    element_hiding_stylesheet: Option<DocumentStyleSheet>,
+
     /// Flag to indicate whether to use parallel operations
     parallel_flag: bool,
 
@@ -471,6 +482,7 @@ fn add_font_face_rules(
     device: &Device,
     font_cache_thread: &FontCacheThread,
     font_cache_sender: &IpcSender<()>,
+    requester: &WebFontRequester,
     outstanding_web_fonts_counter: &Arc<AtomicUsize>,
     load_webfonts_synchronously: bool,
 ) {
@@ -482,6 +494,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    requester.clone(),
                     sender.clone(),
                 );
                 receiver.recv().unwrap();
@@ -495,6 +508,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    requester.clone(),
                     (*font_cache_sender).clone(),
                 );
             }
@@ -557,6 +571,7 @@ impl LayoutThread {
             id: id,
             top_level_browsing_context_id: top_level_browsing_context_id,
             url: url,
+            csp_list: None,
             is_iframe: is_iframe,
             port: port,
             pipeline_port: pipeline_receiver,
@@ -569,6 +584,8 @@ impl LayoutThread {
             image_cache: image_cache,
             font_cache_thread: font_cache_thread,
             first_reflow: Cell::new(true),
+            embedder_stylesheets: vec![],
+            element_hiding_stylesheet: None,
             font_cache_receiver: font_cache_receiver,
             font_cache_sender: ipc_font_cache_sender,
             parallel_flag: true,
@@ -592,6 +609,7 @@ impl LayoutThread {
                 indexable_text: IndexableText::default(),
                 content_box_response: None,
                 content_boxes_response: Vec::new(),
+                box_sizes_response: None,
                 client_rect_response: Rect::zero(),
                 scroll_id_response: None,
                 scroll_area_response: Rect::zero(),
@@ -688,6 +706,9 @@ impl LayoutThread {
             Msg::AddStylesheet(..) => LayoutHangAnnotation::AddStylesheet,
             Msg::RemoveStylesheet(..) => LayoutHangAnnotation::RemoveStylesheet,
             Msg::SetQuirksMode(..) => LayoutHangAnnotation::SetQuirksMode,
+            Msg::SetEmbedderStylesheets(..) => LayoutHangAnnotation::SetEmbedderStylesheets,
+            Msg::SetElementHidingStylesheet(..) => LayoutHangAnnotation::SetElementHidingStylesheet,
+            Msg::PreferencesChanged => LayoutHangAnnotation::PreferencesChanged,
             Msg::Reflow(..) => LayoutHangAnnotation::Reflow,
             Msg::GetRPC(..) => LayoutHangAnnotation::GetRPC,
             Msg::TickAnimations(..) => LayoutHangAnnotation::TickAnimations,
@@ -700,6 +721,7 @@ impl LayoutThread {
             Msg::GetWebFontLoadState(..) => LayoutHangAnnotation::GetWebFontLoadState,
             Msg::CreateLayoutThread(..) => LayoutHangAnnotation::CreateLayoutThread,
             Msg::SetFinalUrl(..) => LayoutHangAnnotation::SetFinalUrl,
+            Msg::SetCspList(..) => LayoutHangAnnotation::SetCspList,
             Msg::SetScrollStates(..) => LayoutHangAnnotation::SetScrollStates,
             Msg::UpdateScrollStateFromScript(..) => {
                 LayoutHangAnnotation::UpdateScrollStateFromScript
@@ -754,6 +776,10 @@ impl LayoutThread {
                 self.paint_time_metrics.maybe_set_metric(epoch, paint_time);
                 true
             },
+            Request::FromPipeline(LayoutControlMsg::MemoryPressure) => {
+                font_context::invalidate_font_caches();
+                true
+            },
             Request::FromScript(msg) => self.handle_request_helper(msg, possibly_locked_rw_data),
             Request::FromFontCache => {
                 let _rw_data = possibly_locked_rw_data.lock();
@@ -799,6 +825,13 @@ impl LayoutThread {
                     .remove_stylesheet(DocumentStyleSheet(stylesheet.clone()), &guard);
             },
             Msg::SetQuirksMode(mode) => self.handle_set_quirks_mode(mode),
+            Msg::SetEmbedderStylesheets(stylesheets) => {
+                self.handle_set_embedder_stylesheets(stylesheets)
+            },
+            Msg::SetElementHidingStylesheet(stylesheet) => {
+                self.handle_set_element_hiding_stylesheet(stylesheet)
+            },
+            Msg::PreferencesChanged => self.handle_preferences_changed(),
             Msg::GetRPC(response_chan) => {
                 response_chan
                     .send(Box::new(LayoutRPCImpl(self.rw_data.clone())) as Box<dyn LayoutRPC + Send>)
@@ -821,11 +854,15 @@ impl LayoutThread {
             },
             Msg::UpdateScrollStateFromScript(state) => {
                 let mut rw_data = possibly_locked_rw_data.lock();
+                let origin = scroll_origin(&rw_data, state.scroll_id);
                 rw_data
                     .scroll_offsets
-                    .insert(state.scroll_id, state.scroll_offset);
+                    .insert(state.scroll_id, state.scroll_offset - origin);
 
-                let point = Point2D::new(-state.scroll_offset.x, -state.scroll_offset.y);
+                let point = Point2D::new(
+                    origin.x - state.scroll_offset.x,
+                    origin.y - state.scroll_offset.y,
+                );
                 self.webrender_api.send_scroll_node(
                     self.webrender_document,
                     webrender_api::units::LayoutPoint::from_untyped(point),
@@ -855,6 +892,9 @@ impl LayoutThread {
             Msg::SetFinalUrl(final_url) => {
                 self.url = final_url;
             },
+            Msg::SetCspList(csp_list) => {
+                self.csp_list = csp_list;
+            },
             Msg::RegisterPaint(name, mut properties, painter) => {
                 debug!("Registering the painter");
                 let properties = properties
@@ -1014,12 +1054,68 @@ impl LayoutThread {
                 self.stylist.device(),
                 &self.font_cache_thread,
                 &self.font_cache_sender,
+                &WebFontRequester {
+                    origin: self.url.origin(),
+                    csp_list: self.csp_list.clone(),
+                },
                 &self.outstanding_web_fonts,
                 self.load_webfonts_synchronously,
             );
         }
     }
 
+    /// Replaces the stylesheets supplied by the embedder. Its user agent stylesheets take
+    /// the place of user-agent.css in the cascade, and its user stylesheets follow those
+    /// of the command line. Before the first reflow, they are kept to be added with the
+    /// stylesheets of the user agent.
+    fn handle_set_embedder_stylesheets(&mut self, stylesheets: Vec<DocumentStyleSheet>) {
+        let guard = UA_STYLESHEETS.shared_lock.read();
+        if !self.first_reflow.get() {
+            let old_stylesheets = ua_stylesheet_replacements(&self.embedder_stylesheets)
+                .into_iter()
+                .chain(user_stylesheets(&self.embedder_stylesheets).cloned());
+            for stylesheet in old_stylesheets {
+                self.stylist.remove_stylesheet(stylesheet, &guard);
+            }
+            // The stylesheets of the user agent which follow user-agent.css.
+            let next_stylesheet = &UA_STYLESHEETS.user_or_user_agent_stylesheets[1];
+            for stylesheet in ua_stylesheet_replacements(&stylesheets) {
+                self.stylist.insert_stylesheet_before(
+                    stylesheet.clone(),
+                    next_stylesheet.clone(),
+                    &guard,
+                );
+                self.handle_add_stylesheet(&stylesheet.0, &guard);
+            }
+            for stylesheet in user_stylesheets(&stylesheets) {
+                self.stylist.append_stylesheet(stylesheet.clone(), &guard);
+                self.handle_add_stylesheet(&stylesheet.0, &guard);
+            }
+        }
+        self.embedder_stylesheets = stylesheets;
+    }
+
+    /// Replaces the stylesheet of the content blocker, which comes after the stylesheets of
+    /// the embedder.
+    fn handle_set_element_hiding_stylesheet(&mut self, stylesheet: DocumentStyleSheet) {
+        let guard = UA_STYLESHEETS.shared_lock.read();
+        if !self.first_reflow.get() {
+            if let Some(old_stylesheet) = self.element_hiding_stylesheet.take() {
+                self.stylist.remove_stylesheet(old_stylesheet, &guard);
+            }
+            self.stylist.append_stylesheet(stylesheet.clone(), &guard);
+            self.handle_add_stylesheet(&stylesheet.0, &guard);
+        }
+        self.element_hiding_stylesheet = Some(stylesheet);
+    }
+
+    /// Restyles the whole document on the next reflow, since the rules enabled by the
+    /// preferences may have changed.
+    fn handle_preferences_changed(&mut self) {
+        self.stylist
+            .force_stylesheet_origins_dirty(OriginSet::all());
+    }
+
     /// Advances the animation clock of the document.
     fn handle_advance_clock_ms<'a, 'b>(
         &mut self,
@@ -1257,6 +1353,21 @@ impl LayoutThread {
                     viewport_size,
                     builder.finalize(),
                 );
+
+                // The new scroll frames whose content overflows to their start are scrolled
+                // to their origin, which is the zero scroll position of script.
+                for (scroll_id, origin) in &display_list.scroll_origins {
+                    if rw_data.scroll_offsets.contains_key(scroll_id) {
+                        continue;
+                    }
+                    rw_data.scroll_offsets.insert(*scroll_id, -*origin);
+                    self.webrender_api.send_scroll_node(
+                        self.webrender_document,
+                        origin.to_point(),
+                        *scroll_id,
+                        webrender_api::ScrollClamping::ToContentBounds,
+                    );
+                }
             },
         );
     }
@@ -1320,7 +1431,7 @@ impl LayoutThread {
                         &QueryMsg::StyleQuery(_) => {
                             rw_data.style_response = StyleResponse(None);
                         },
-                        &QueryMsg::TextIndexQuery(..) => {
+                        &QueryMsg::TextIndexQuery(..) | &QueryMsg::CaretPositionQuery(..) => {
                             rw_data.text_index_response = TextIndexResponse(None);
                         },
                         &QueryMsg::ElementInnerTextQuery(_) => {
@@ -1407,7 +1518,20 @@ impl LayoutThread {
         {
             if self.first_reflow.get() {
                 debug!("First reflow, rebuilding user and UA rules");
-                for stylesheet in &ua_stylesheets.user_or_user_agent_stylesheets {
+                // The stylesheets of the user agent which follow user-agent.css, and those of the
+                // command line.
+                let built_in_stylesheets = &ua_stylesheets.user_or_user_agent_stylesheets[1..];
+                let stylesheets = ua_stylesheet_replacements(&self.embedder_stylesheets)
+                    .into_iter()
+                    .chain(built_in_stylesheets.iter().cloned())
+                    .chain(user_stylesheets(&self.embedder_stylesheets).cloned())
+                    .collect::<Vec<_>>();
+                for stylesheet in &stylesheets {
+                    self.stylist
+                        .append_stylesheet(stylesheet.clone(), &ua_or_user_guard);
+                    self.handle_add_stylesheet(&stylesheet.0, &ua_or_user_guard);
+                }
+                if let Some(ref stylesheet) = self.element_hiding_stylesheet {
                     self.stylist
                         .append_stylesheet(stylesheet.clone(), &ua_or_user_guard);
                     self.handle_add_stylesheet(&stylesheet.0, &ua_or_user_guard);
@@ -1622,6 +1746,9 @@ impl LayoutThread {
                 &QueryMsg::ContentBoxesQuery(node) => {
                     rw_data.content_boxes_response = process_content_boxes_request(node, root_flow);
                 },
+                &QueryMsg::BoxSizesQuery(node) => {
+                    rw_data.box_sizes_response = process_box_sizes_request(node, root_flow);
+                },
                 &QueryMsg::TextIndexQuery(node, point_in_node) => {
                     let point_in_node = Point2D::new(
                         Au::from_f32_px(point_in_node.x),
@@ -1630,6 +1757,11 @@ impl LayoutThread {
                     rw_data.text_index_response =
                         TextIndexResponse(rw_data.indexable_text.text_index(node, point_in_node));
                 },
+                &QueryMsg::CaretPositionQuery(node, point) => {
+                    let point = Point2D::new(Au::from_f32_px(point.x), Au::from_f32_px(point.y));
+                    rw_data.text_index_response =
+                        TextIndexResponse(rw_data.indexable_text.text_index_at_point(node, point));
+                },
                 &QueryMsg::ClientRectQuery(node) => {
                     rw_data.client_rect_response = process_client_rect_query(node, root_flow);
                 },
@@ -1691,7 +1823,10 @@ impl LayoutThread {
                         .cloned();
                 },
             },
-            ReflowGoal::Full | ReflowGoal::TickAnimations => {},
+            ReflowGoal::Full => {
+                reflow_result.content_size = Some(process_content_size_request(root_flow));
+            },
+            ReflowGoal::TickAnimations => {},
         }
     }
 
@@ -1707,6 +1842,9 @@ impl LayoutThread {
             let offset = new_state.scroll_offset;
             layout_scroll_states.insert(new_state.scroll_id, offset);
 
+            // Script scroll positions are relative to the origin of the scroll frame.
+            let offset = offset + scroll_origin(&rw_data, new_state.scroll_id);
+
             if new_state.scroll_id.is_root() {
                 script_scroll_states.push((UntrustedNodeAddress::from_id(0), offset))
             } else if let Some(node_id) = node_id_from_scroll_id(new_state.scroll_id.0 as usize) {
@@ -1980,6 +2118,19 @@ impl ProfilerMetadataFactory for LayoutThread {
     }
 }
 
+/// The offset of the given scroll frame at which its script scroll position is zero.
+fn scroll_origin(
+    rw_data: &LayoutThreadData,
+    scroll_id: webrender_api::ExternalScrollId,
+) -> webrender_api::units::LayoutVector2D {
+    rw_data
+        .display_list
+        .as_ref()
+        .and_then(|display_list| display_list.scroll_origins.get(&scroll_id))
+        .cloned()
+        .unwrap_or_else(webrender_api::units::LayoutVector2D::zero)
+}
+
 // The default computed value for background-color is transparent (see
 // http://dev.w3.org/csswg/css-backgrounds/#background-color). However, we
 // need to propagate the background color from the root HTML/Body
@@ -2097,6 +2248,31 @@ fn get_ua_stylesheets() -> Result<UserAgentStylesheets, &'static str> {
     })
 }
 
+/// The stylesheets which take the place of user-agent.css in the cascade, which are the
+/// user agent stylesheets supplied by the embedder, if it supplied any.
+fn ua_stylesheet_replacements(
+    embedder_stylesheets: &[DocumentStyleSheet],
+) -> Vec<DocumentStyleSheet> {
+    let replacements: Vec<_> = embedder_stylesheets
+        .iter()
+        .filter(|stylesheet| stylesheet.0.contents.origin == Origin::UserAgent)
+        .cloned()
+        .collect();
+    if replacements.is_empty() {
+        vec![UA_STYLESHEETS.user_or_user_agent_stylesheets[0].clone()]
+    } else {
+        replacements
+    }
+}
+
+fn user_stylesheets(
+    embedder_stylesheets: &[DocumentStyleSheet],
+) -> impl Iterator<Item = &DocumentStyleSheet> {
+    embedder_stylesheets
+        .iter()
+        .filter(|stylesheet| stylesheet.0.contents.origin == Origin::User)
+}
+
 lazy_static! {
     static ref UA_STYLESHEETS: UserAgentStylesheets = {
         match get_ua_stylesheets() {
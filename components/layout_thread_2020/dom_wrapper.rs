@@ -957,6 +957,10 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
                 .element
                 .get_state_for_layout()
                 .contains(pseudo_class.state_flag()),
+            NonTSPseudoClass::Dir(_) => self
+                .element
+                .get_state_for_layout()
+                .intersects(pseudo_class.state_flag()),
 
             NonTSPseudoClass::Active |
             NonTSPseudoClass::Focus |
@@ -1274,7 +1278,8 @@ where
     pub fn new(parent: ConcreteNode) -> Self {
         let first_child: Option<ConcreteNode> = match parent.get_pseudo_element_type() {
             PseudoElementType::Normal => parent
-                .get_before_pseudo()
+                .get_backdrop_pseudo()
+                .or_else(|| parent.get_before_pseudo())
                 .or_else(|| parent.get_details_summary_pseudo())
                 .or_else(|| unsafe { parent.dangerous_first_child() }),
             PseudoElementType::DetailsContent | PseudoElementType::DetailsSummary => unsafe {
@@ -1297,7 +1302,9 @@ where
     fn next(&mut self) -> Option<ConcreteNode> {
         use selectors::Element;
         match self.parent_node.get_pseudo_element_type() {
-            PseudoElementType::Before | PseudoElementType::After => None,
+            PseudoElementType::Backdrop | PseudoElementType::Before | PseudoElementType::After => {
+                None
+            },
 
             PseudoElementType::DetailsSummary => {
                 let mut current_node = self.current_node.clone();
@@ -1342,6 +1349,12 @@ where
                 let node = self.current_node.clone();
                 if let Some(ref node) = node {
                     self.current_node = match node.get_pseudo_element_type() {
+                        PseudoElementType::Backdrop => self
+                            .parent_node
+                            .get_before_pseudo()
+                            .or_else(|| self.parent_node.get_details_summary_pseudo())
+                            .or_else(|| unsafe { self.parent_node.dangerous_first_child() })
+                            .or_else(|| self.parent_node.get_after_pseudo()),
                         PseudoElementType::Before => self
                             .parent_node
                             .get_details_summary_pseudo()
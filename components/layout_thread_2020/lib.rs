@@ -24,12 +24,13 @@ mod dom_wrapper;
 use crate::dom_wrapper::drop_style_and_layout_data;
 use crate::dom_wrapper::{ServoLayoutDocument, ServoLayoutElement, ServoLayoutNode};
 use app_units::Au;
+use content_security_policy::CspList;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use embedder_traits::resources::{self, Resource};
 use euclid::{default::Size2D as UntypedSize2D, Point2D, Rect, Scale, Size2D};
 use fnv::FnvHashMap;
 use fxhash::FxHashMap;
-use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_cache_thread::{FontCacheThread, WebFontRequester};
 use gfx::font_context;
 use gfx_traits::{node_id_from_scroll_id, Epoch};
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
@@ -37,6 +38,7 @@ use ipc_channel::router::ROUTER;
 use layout::context::LayoutContext;
 use layout::display_list::{DisplayListBuilder, WebRenderImageInfo};
 use layout::layout_debug;
+use layout::query::process_box_sizes_request;
 use layout::query::{
     process_content_box_request, process_content_boxes_request, LayoutRPCImpl, LayoutThreadData,
 };
@@ -97,7 +99,7 @@ use style::properties::PropertyId;
 use style::selector_parser::SnapshotMap;
 use style::shared_lock::{SharedRwLock, SharedRwLockReadGuard, StylesheetGuards};
 use style::stylesheets::{
-    DocumentStyleSheet, Origin, Stylesheet, StylesheetInDocument, UserAgentStylesheets,
+    DocumentStyleSheet, Origin, OriginSet, Stylesheet, StylesheetInDocument, UserAgentStylesheets,
 };
 use style::stylist::Stylist;
 use style::thread_state::{self, ThreadState};
@@ -119,6 +121,9 @@ pub struct LayoutThread {
     /// The URL of the pipeline that we belong to.
     url: ServoUrl,
 
+    /// The Content Security Policies of the document, which apply to its web fonts.
+    csp_list: Option<CspList>,
+
     /// Performs CSS selector matching and style resolution.
     stylist: Stylist,
 
@@ -158,6 +163,12 @@ pub struct LayoutThread {
     /// Is this the first reflow in this LayoutThread?
     first_reflow: Cell<bool>,
 
+    /// The user agent and user stylesheets supplied by the embedder.
+    embedder_stylesheets: Vec<DocumentStyleSheet>,
+
+    /// The stylesheet which hides the elements matching the rules of the content blocker.
+    element_hiding_stylesheet: Option<DocumentStyleSheet>,
+
     /// Starts at zero, and increased by one every time a layout completes.
     /// This can be used to easily check for invalid stale data.
     generation: Cell<u32>,
@@ -430,6 +441,7 @@ fn add_font_face_rules(
     device: &Device,
     font_cache_thread: &FontCacheThread,
     font_cache_sender: &IpcSender<()>,
+    requester: &WebFontRequester,
     outstanding_web_fonts_counter: &Arc<AtomicUsize>,
     load_webfonts_synchronously: bool,
 ) {
@@ -441,6 +453,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    requester.clone(),
                     sender.clone(),
                 );
                 receiver.recv().unwrap();
@@ -454,6 +467,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    requester.clone(),
                     (*font_cache_sender).clone(),
                 );
             }
@@ -517,6 +531,7 @@ impl LayoutThread {
             id: id,
             top_level_browsing_context_id: top_level_browsing_context_id,
             url: url,
+            csp_list: None,
             is_iframe: is_iframe,
             port: port,
             pipeline_port: pipeline_receiver,
@@ -528,6 +543,8 @@ impl LayoutThread {
             image_cache,
             font_cache_thread: font_cache_thread,
             first_reflow: Cell::new(true),
+            embedder_stylesheets: vec![],
+            element_hiding_stylesheet: None,
             font_cache_receiver: font_cache_receiver,
             font_cache_sender: ipc_font_cache_sender,
             generation: Cell::new(0),
@@ -548,6 +565,7 @@ impl LayoutThread {
                 display_list: None,
                 content_box_response: None,
                 content_boxes_response: Vec::new(),
+                box_sizes_response: None,
                 client_rect_response: Rect::zero(),
                 scroll_id_response: None,
                 scroll_area_response: Rect::zero(),
@@ -636,6 +654,9 @@ impl LayoutThread {
             Msg::AddStylesheet(..) => LayoutHangAnnotation::AddStylesheet,
             Msg::RemoveStylesheet(..) => LayoutHangAnnotation::RemoveStylesheet,
             Msg::SetQuirksMode(..) => LayoutHangAnnotation::SetQuirksMode,
+            Msg::SetEmbedderStylesheets(..) => LayoutHangAnnotation::SetEmbedderStylesheets,
+            Msg::SetElementHidingStylesheet(..) => LayoutHangAnnotation::SetElementHidingStylesheet,
+            Msg::PreferencesChanged => LayoutHangAnnotation::PreferencesChanged,
             Msg::Reflow(..) => LayoutHangAnnotation::Reflow,
             Msg::GetRPC(..) => LayoutHangAnnotation::GetRPC,
             Msg::TickAnimations(..) => LayoutHangAnnotation::TickAnimations,
@@ -648,6 +669,7 @@ impl LayoutThread {
             Msg::GetWebFontLoadState(..) => LayoutHangAnnotation::GetWebFontLoadState,
             Msg::CreateLayoutThread(..) => LayoutHangAnnotation::CreateLayoutThread,
             Msg::SetFinalUrl(..) => LayoutHangAnnotation::SetFinalUrl,
+            Msg::SetCspList(..) => LayoutHangAnnotation::SetCspList,
             Msg::SetScrollStates(..) => LayoutHangAnnotation::SetScrollStates,
             Msg::UpdateScrollStateFromScript(..) => {
                 LayoutHangAnnotation::UpdateScrollStateFromScript
@@ -702,6 +724,10 @@ impl LayoutThread {
                 self.paint_time_metrics.maybe_set_metric(epoch, paint_time);
                 true
             },
+            Request::FromPipeline(LayoutControlMsg::MemoryPressure) => {
+                font_context::invalidate_font_caches();
+                true
+            },
             Request::FromScript(msg) => self.handle_request_helper(msg, possibly_locked_rw_data),
             Request::FromFontCache => {
                 let _rw_data = possibly_locked_rw_data.lock();
@@ -747,6 +773,13 @@ impl LayoutThread {
                     .remove_stylesheet(DocumentStyleSheet(stylesheet.clone()), &guard);
             },
             Msg::SetQuirksMode(mode) => self.handle_set_quirks_mode(mode),
+            Msg::SetEmbedderStylesheets(stylesheets) => {
+                self.handle_set_embedder_stylesheets(stylesheets)
+            },
+            Msg::SetElementHidingStylesheet(stylesheet) => {
+                self.handle_set_element_hiding_stylesheet(stylesheet)
+            },
+            Msg::PreferencesChanged => self.handle_preferences_changed(),
             Msg::GetRPC(response_chan) => {
                 response_chan
                     .send(Box::new(LayoutRPCImpl(self.rw_data.clone())) as Box<dyn LayoutRPC + Send>)
@@ -801,6 +834,9 @@ impl LayoutThread {
             Msg::SetFinalUrl(final_url) => {
                 self.url = final_url;
             },
+            Msg::SetCspList(csp_list) => {
+                self.csp_list = csp_list;
+            },
             Msg::RegisterPaint(_name, _properties, _painter) => {},
             Msg::PrepareToExit(response_chan) => {
                 self.prepare_to_exit(response_chan);
@@ -922,12 +958,68 @@ impl LayoutThread {
                 self.stylist.device(),
                 &self.font_cache_thread,
                 &self.font_cache_sender,
+                &WebFontRequester {
+                    origin: self.url.origin(),
+                    csp_list: self.csp_list.clone(),
+                },
                 &self.outstanding_web_fonts,
                 self.load_webfonts_synchronously,
             );
         }
     }
 
+    /// Replaces the stylesheets supplied by the embedder. Its user agent stylesheets take
+    /// the place of user-agent.css in the cascade, and its user stylesheets follow those
+    /// of the command line. Before the first reflow, they are kept to be added with the
+    /// stylesheets of the user agent.
+    fn handle_set_embedder_stylesheets(&mut self, stylesheets: Vec<DocumentStyleSheet>) {
+        let guard = UA_STYLESHEETS.shared_lock.read();
+        if !self.first_reflow.get() {
+            let old_stylesheets = ua_stylesheet_replacements(&self.embedder_stylesheets)
+                .into_iter()
+                .chain(user_stylesheets(&self.embedder_stylesheets).cloned());
+            for stylesheet in old_stylesheets {
+                self.stylist.remove_stylesheet(stylesheet, &guard);
+            }
+            // The stylesheets of the user agent which follow user-agent.css.
+            let next_stylesheet = &UA_STYLESHEETS.user_or_user_agent_stylesheets[1];
+            for stylesheet in ua_stylesheet_replacements(&stylesheets) {
+                self.stylist.insert_stylesheet_before(
+                    stylesheet.clone(),
+                    next_stylesheet.clone(),
+                    &guard,
+                );
+                self.handle_add_stylesheet(&stylesheet.0, &guard);
+            }
+            for stylesheet in user_stylesheets(&stylesheets) {
+                self.stylist.append_stylesheet(stylesheet.clone(), &guard);
+                self.handle_add_stylesheet(&stylesheet.0, &guard);
+            }
+        }
+        self.embedder_stylesheets = stylesheets;
+    }
+
+    /// Replaces the stylesheet of the content blocker, which comes after the stylesheets of
+    /// the embedder.
+    fn handle_set_element_hiding_stylesheet(&mut self, stylesheet: DocumentStyleSheet) {
+        let guard = UA_STYLESHEETS.shared_lock.read();
+        if !self.first_reflow.get() {
+            if let Some(old_stylesheet) = self.element_hiding_stylesheet.take() {
+                self.stylist.remove_stylesheet(old_stylesheet, &guard);
+            }
+            self.stylist.append_stylesheet(stylesheet.clone(), &guard);
+            self.handle_add_stylesheet(&stylesheet.0, &guard);
+        }
+        self.element_hiding_stylesheet = Some(stylesheet);
+    }
+
+    /// Restyles the whole document on the next reflow, since the rules enabled by the
+    /// preferences may have changed.
+    fn handle_preferences_changed(&mut self) {
+        self.stylist
+            .force_stylesheet_origins_dirty(OriginSet::all());
+    }
+
     /// Advances the animation clock of the document.
     fn handle_advance_clock_ms<'a, 'b>(
         &mut self,
@@ -990,7 +1082,7 @@ impl LayoutThread {
                         &QueryMsg::StyleQuery(_) => {
                             rw_data.style_response = StyleResponse(None);
                         },
-                        &QueryMsg::TextIndexQuery(..) => {
+                        &QueryMsg::TextIndexQuery(..) | &QueryMsg::CaretPositionQuery(..) => {
                             rw_data.text_index_response = TextIndexResponse(None);
                         },
                         &QueryMsg::ElementInnerTextQuery(_) => {
@@ -1066,7 +1158,20 @@ impl LayoutThread {
         }
 
         if self.first_reflow.get() {
-            for stylesheet in &ua_stylesheets.user_or_user_agent_stylesheets {
+            // The stylesheets of the user agent which follow user-agent.css, and those of the
+            // command line.
+            let built_in_stylesheets = &ua_stylesheets.user_or_user_agent_stylesheets[1..];
+            let stylesheets = ua_stylesheet_replacements(&self.embedder_stylesheets)
+                .into_iter()
+                .chain(built_in_stylesheets.iter().cloned())
+                .chain(user_stylesheets(&self.embedder_stylesheets).cloned())
+                .collect::<Vec<_>>();
+            for stylesheet in &stylesheets {
+                self.stylist
+                    .append_stylesheet(stylesheet.clone(), &ua_or_user_guard);
+                self.handle_add_stylesheet(&stylesheet.0, &ua_or_user_guard);
+            }
+            if let Some(ref stylesheet) = self.element_hiding_stylesheet {
                 self.stylist
                     .append_stylesheet(stylesheet.clone(), &ua_or_user_guard);
                 self.handle_add_stylesheet(&stylesheet.0, &ua_or_user_guard);
@@ -1247,6 +1352,10 @@ impl LayoutThread {
                 &QueryMsg::ContentBoxesQuery(node) => {
                     rw_data.content_boxes_response = process_content_boxes_request(node);
                 },
+                &QueryMsg::BoxSizesQuery(node) => {
+                    rw_data.box_sizes_response =
+                        process_box_sizes_request(node, self.fragment_tree_root.borrow().clone());
+                },
                 &QueryMsg::TextIndexQuery(node, point_in_node) => {
                     let point_in_node = Point2D::new(
                         Au::from_f32_px(point_in_node.x),
@@ -1254,6 +1363,10 @@ impl LayoutThread {
                     );
                     rw_data.text_index_response = process_text_index_request(node, point_in_node);
                 },
+                &QueryMsg::CaretPositionQuery(node, point) => {
+                    let point = Point2D::new(Au::from_f32_px(point.x), Au::from_f32_px(point.y));
+                    rw_data.text_index_response = process_text_index_request(node, point);
+                },
                 &QueryMsg::ClientRectQuery(node) => {
                     rw_data.client_rect_response = process_node_geometry_request(
                         node,
@@ -1314,6 +1427,8 @@ impl LayoutThread {
                     rw_data.inner_window_dimensions_response = None;
                 },
             },
+            // TODO: report the content size of the document, once the intrinsic sizes of
+            //       the root element are computed.
             ReflowGoal::Full | ReflowGoal::TickAnimations => {},
         }
     }
@@ -1549,6 +1664,31 @@ fn get_ua_stylesheets() -> Result<UserAgentStylesheets, &'static str> {
     })
 }
 
+/// The stylesheets which take the place of user-agent.css in the cascade, which are the
+/// user agent stylesheets supplied by the embedder, if it supplied any.
+fn ua_stylesheet_replacements(
+    embedder_stylesheets: &[DocumentStyleSheet],
+) -> Vec<DocumentStyleSheet> {
+    let replacements: Vec<_> = embedder_stylesheets
+        .iter()
+        .filter(|stylesheet| stylesheet.0.contents.origin == Origin::UserAgent)
+        .cloned()
+        .collect();
+    if replacements.is_empty() {
+        vec![UA_STYLESHEETS.user_or_user_agent_stylesheets[0].clone()]
+    } else {
+        replacements
+    }
+}
+
+fn user_stylesheets(
+    embedder_stylesheets: &[DocumentStyleSheet],
+) -> impl Iterator<Item = &DocumentStyleSheet> {
+    embedder_stylesheets
+        .iter()
+        .filter(|stylesheet| stylesheet.0.contents.origin == Origin::User)
+}
+
 lazy_static! {
     static ref UA_STYLESHEETS: UserAgentStylesheets = {
         match get_ua_stylesheets() {
@@ -528,6 +528,9 @@ pub enum LayoutHangAnnotation {
     AddStylesheet,
     RemoveStylesheet,
     SetQuirksMode,
+    SetEmbedderStylesheets,
+    SetElementHidingStylesheet,
+    PreferencesChanged,
     Reflow,
     GetRPC,
     TickAnimations,
@@ -540,6 +543,7 @@ pub enum LayoutHangAnnotation {
     GetWebFontLoadState,
     CreateLayoutThread,
     SetFinalUrl,
+    SetCspList,
     SetScrollStates,
     UpdateScrollStateFromScript,
     RegisterPaint,
@@ -603,37 +607,73 @@ impl fmt::Debug for HangMonitorAlert {
     }
 }
 
+/// The task a component was running when it hung.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HangTaskAnnotation {
+    /// The name of the task source of the task.
+    pub task_source: String,
+    /// The name of the task itself.
+    pub task_name: String,
+}
+
 /// Hang-alerts are sent by the monitor to the constellation.
 #[derive(Deserialize, Serialize)]
 pub enum HangAlert {
     /// Report a transient hang.
-    Transient(MonitoredComponentId, HangAnnotation),
+    Transient(
+        MonitoredComponentId,
+        HangAnnotation,
+        Option<HangTaskAnnotation>,
+    ),
     /// Report a permanent hang.
-    Permanent(MonitoredComponentId, HangAnnotation, Option<HangProfile>),
+    Permanent(
+        MonitoredComponentId,
+        HangAnnotation,
+        Option<HangTaskAnnotation>,
+        Option<HangProfile>,
+    ),
+}
+
+impl HangAlert {
+    /// The hanging component.
+    pub fn component_id(&self) -> &MonitoredComponentId {
+        match self {
+            HangAlert::Transient(component_id, ..) | HangAlert::Permanent(component_id, ..) => {
+                component_id
+            },
+        }
+    }
 }
 
 impl fmt::Debug for HangAlert {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let (annotation, profile) = match self {
-            HangAlert::Transient(component_id, annotation) => {
+        let (annotation, task, profile) = match self {
+            HangAlert::Transient(component_id, annotation, task) => {
                 write!(
                     fmt,
                     "\n The following component is experiencing a transient hang: \n {:?}",
                     component_id
                 )?;
-                (annotation.clone(), None)
+                (annotation.clone(), task, None)
             },
-            HangAlert::Permanent(component_id, annotation, profile) => {
+            HangAlert::Permanent(component_id, annotation, task, profile) => {
                 write!(
                     fmt,
                     "\n The following component is experiencing a permanent hang: \n {:?}",
                     component_id
                 )?;
-                (annotation.clone(), profile.clone())
+                (annotation.clone(), task, profile.clone())
             },
         };
 
         write!(fmt, "\n Annotation for the hang:\n{:?}", annotation)?;
+        if let Some(task) = task {
+            write!(
+                fmt,
+                "\n Running task {} from the {} task source",
+                task.task_name, task.task_source
+            )?;
+        }
         if let Some(profile) = profile {
             write!(fmt, "\n {:?}", profile)?;
         }
@@ -653,12 +693,29 @@ pub struct HangProfileSymbol {
 /// Info related to the activity of an hanging component.
 pub struct HangProfile {
     pub backtrace: Vec<HangProfileSymbol>,
+    /// The stack of the script the component was running, from the innermost frame,
+    /// if it was running one and could be interrupted to capture it.
+    pub js_backtrace: Vec<HangProfileSymbol>,
 }
 
 impl fmt::Debug for HangProfile {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         let hex_width = mem::size_of::<usize>() * 2 + 2;
 
+        if !self.js_backtrace.is_empty() {
+            write!(fmt, "HangProfile JS backtrace:")?;
+            for symbol in self.js_backtrace.iter() {
+                write!(
+                    fmt,
+                    "\n      {}@{}:{}",
+                    symbol.name.as_ref().map_or("", |name| &**name),
+                    symbol.filename.as_ref().map_or("", |filename| &**filename),
+                    symbol.lineno.unwrap_or(0)
+                )?;
+            }
+            write!(fmt, "\n ")?;
+        }
+
         write!(fmt, "HangProfile backtrace:")?;
 
         if self.backtrace.len() == 0 {
@@ -720,8 +777,16 @@ pub trait BackgroundHangMonitorClone {
 pub trait BackgroundHangMonitor {
     /// Notify the start of handling an event.
     fn notify_activity(&self, annotation: HangAnnotation);
+    /// Notify the task being run for the current event, by the name of its task
+    /// source and its own.
+    fn notify_task(&self, task_source: &'static str, task_name: &'static str);
     /// Notify the start of waiting for a new event to come in.
     fn notify_wait(&self);
+    /// Give the monitor a way to ask the component for the stack of the script it
+    /// runs when it hangs, which the component answers with `notify_js_stack`.
+    fn set_js_stack_requester(&self, requester: Box<dyn Fn() + Send>);
+    /// Send the stack of the script being run, from the innermost frame.
+    fn notify_js_stack(&self, stack: Vec<HangProfileSymbol>);
     /// Unregister the component from monitor.
     fn unregister(&self);
 }
@@ -0,0 +1,295 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The internal pages of the `about:` scheme, which show the state of the browser:
+//! about:memory for the reports of the memory profiler, about:config to edit the
+//! preferences and about:crashes for the pages that crashed.
+
+use crate::resource_thread::{read_json_from_file, write_json_to_file};
+use ipc_channel::ipc;
+use net_traits::CrashReport;
+use profile_traits::mem::{ProfilerChan as MemProfilerChan, ProfilerMsg, Report};
+use servo_config::pref_util::PrefValue;
+use servo_config::prefs::pref_map;
+use servo_url::ServoUrl;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// The number of crashes kept for about:crashes.
+const MAX_CRASH_REPORTS: usize = 50;
+
+const STYLE: &'static str = "<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+td, th { border-bottom: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+ul { list-style: none; }
+pre { background: #eee; padding: 0.5em; white-space: pre-wrap; }
+.size { display: inline-block; font-family: monospace; text-align: right; width: 10em; }
+.modified { font-weight: bold; }
+</style>";
+
+pub struct AboutPages {
+    mem_profiler_chan: MemProfilerChan,
+    config_dir: Option<PathBuf>,
+    crash_reports: Mutex<Vec<CrashReport>>,
+    /// A secret included in the forms of about:config, so that other pages can't change
+    /// the preferences by loading its URL.
+    token: String,
+}
+
+impl AboutPages {
+    pub fn new(mem_profiler_chan: MemProfilerChan, config_dir: Option<PathBuf>) -> AboutPages {
+        let mut crash_reports = vec![];
+        if let Some(ref config_dir) = config_dir {
+            read_json_from_file(&mut crash_reports, config_dir, "crash_reports.json");
+        }
+        AboutPages {
+            mem_profiler_chan: mem_profiler_chan,
+            config_dir: config_dir,
+            crash_reports: Mutex::new(crash_reports),
+            token: Uuid::new_v4().to_string(),
+        }
+    }
+
+    pub fn add_crash_report(&self, crash_report: CrashReport) {
+        let mut crash_reports = self.crash_reports.lock().unwrap();
+        crash_reports.push(crash_report);
+        if crash_reports.len() > MAX_CRASH_REPORTS {
+            crash_reports.remove(0);
+        }
+        if let Some(ref config_dir) = self.config_dir {
+            write_json_to_file(&*crash_reports, config_dir, "crash_reports.json");
+        }
+    }
+
+    /// The HTML of the page with the given URL, if it is one of the internal pages.
+    pub fn load(&self, url: &ServoUrl) -> Option<String> {
+        let (title, body) = match url.path() {
+            "memory" => ("Memory", self.memory()),
+            "config" => ("Preferences", self.config(url)),
+            "crashes" => ("Crashes", self.crashes()),
+            _ => return None,
+        };
+        Some(format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>{}</head>\
+             <body><h1>{}</h1>\n{}</body></html>",
+            title, STYLE, title, body
+        ))
+    }
+
+    fn memory(&self) -> String {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.mem_profiler_chan.send(ProfilerMsg::Report(sender));
+        let reports = match receiver.recv() {
+            Ok(ref reports) if !reports.is_empty() => reports_tree(reports),
+            _ => return "<p>The memory profiler didn't send any report.</p>".to_owned(),
+        };
+        let mut html = String::from("<p><a href=\"about:memory\">Measure again</a></p>\n");
+        for (name, tree) in &reports.children {
+            let _ = write!(html, "<h2>{}</h2>\n<ul>", escape(name));
+            tree.write_html(name, &mut html);
+            html.push_str("</ul>\n");
+        }
+        html
+    }
+
+    fn config(&self, url: &ServoUrl) -> String {
+        let mut html = String::new();
+
+        // The changes are submitted as the query of the URL.
+        let query: BTreeMap<String, String> = url.as_url().query_pairs().into_owned().collect();
+        if let Some(name) = query.get("pref") {
+            if query.get("token") != Some(&self.token) {
+                html.push_str("<p>The preference wasn't changed: the page has expired.</p>\n");
+            } else if let Err(error) = set_pref(name, query.get("value")) {
+                let _ = write!(
+                    html,
+                    "<p>The preference {} wasn't changed: {}</p>\n",
+                    escape(name),
+                    escape(&error)
+                );
+            }
+        }
+
+        let prefs: BTreeMap<String, PrefValue> = pref_map().iter().collect();
+
+        html.push_str("<table><tr><th>Name</th><th>Type</th><th>Value</th><th></th></tr>\n");
+        for (name, value) in &prefs {
+            let (type_, value_str) = match *value {
+                PrefValue::Bool(value) => ("Boolean", value.to_string()),
+                PrefValue::Int(value) => ("Integer", value.to_string()),
+                PrefValue::Float(value) => ("Float", value.to_string()),
+                PrefValue::Str(ref value) => ("String", value.clone()),
+                PrefValue::Missing => continue,
+            };
+            let modified = pref_map().get_default(name) != *value;
+            let edit = match *value {
+                PrefValue::Bool(value) => format!(
+                    "<a href=\"{}\">Toggle</a>",
+                    escape(&self.config_url(name, Some(&(!value).to_string())))
+                ),
+                _ => format!(
+                    "<input value=\"{}\" data-pref=\"{}\"><button>Set</button>",
+                    escape(&value_str),
+                    escape(name)
+                ),
+            };
+            let reset = if modified {
+                format!(
+                    " <a href=\"{}\">Reset</a>",
+                    escape(&self.config_url(name, None))
+                )
+            } else {
+                String::new()
+            };
+            let _ = write!(
+                html,
+                "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}{}</td></tr>\n",
+                if modified { " class=\"modified\"" } else { "" },
+                escape(name),
+                type_,
+                escape(&value_str),
+                edit,
+                reset
+            );
+        }
+        html.push_str("</table>\n");
+
+        // Forms with a GET method don't submit their data to about: URLs, so the
+        // buttons navigate to the URL of the change instead.
+        let _ = write!(
+            html,
+            "<script>
+document.addEventListener('click', function(event) {{
+  if (event.target.localName != 'button') return;
+  var input = event.target.previousElementSibling;
+  location.href = 'about:config?token={}&pref=' + encodeURIComponent(input.dataset.pref) +
+    '&value=' + encodeURIComponent(input.value);
+}});
+</script>\n",
+            self.token
+        );
+        html
+    }
+
+    /// The URL that sets the given preference, or resets it when there is no value.
+    fn config_url(&self, name: &str, value: Option<&str>) -> String {
+        let mut url = ServoUrl::parse("about:config").unwrap().into_url();
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("token", &self.token);
+            query.append_pair("pref", name);
+            if let Some(value) = value {
+                query.append_pair("value", value);
+            }
+        }
+        url.as_str().to_owned()
+    }
+
+    fn crashes(&self) -> String {
+        let crash_reports = self.crash_reports.lock().unwrap();
+        if crash_reports.is_empty() {
+            return "<p>No page crashed.</p>".to_owned();
+        }
+        let mut html = String::new();
+        for crash_report in crash_reports.iter().rev() {
+            let _ = write!(
+                html,
+                "<h2><a href=\"{}\">{}</a></h2>\n<p>At {}, {}</p>\n",
+                escape(crash_report.url.as_str()),
+                escape(crash_report.url.as_str()),
+                format_time(crash_report.time),
+                escape(&crash_report.reason)
+            );
+            if let Some(ref backtrace) = crash_report.backtrace {
+                let _ = write!(html, "<pre>{}</pre>\n", escape(backtrace));
+            }
+        }
+        html
+    }
+}
+
+/// Set the given preference from the text of its new value, or reset it.
+fn set_pref(name: &str, value: Option<&String>) -> Result<(), String> {
+    let value = match value {
+        Some(value) => value,
+        None => {
+            return pref_map()
+                .reset(name)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        },
+    };
+    let value = match pref_map().get(name) {
+        PrefValue::Bool(_) => PrefValue::Bool(value.parse().map_err(|_| "not a boolean")?),
+        PrefValue::Int(_) => PrefValue::Int(value.parse().map_err(|_| "not an integer")?),
+        PrefValue::Float(_) => PrefValue::Float(value.parse().map_err(|_| "not a number")?),
+        PrefValue::Str(_) => PrefValue::Str(value.clone()),
+        PrefValue::Missing => return Err("unknown preference".to_owned()),
+    };
+    pref_map().set(name, value).map_err(|e| e.to_string())
+}
+
+/// The reports of the memory profiler, nested by their path.
+#[derive(Default)]
+struct ReportsTree {
+    size: usize,
+    children: BTreeMap<String, ReportsTree>,
+}
+
+fn reports_tree(reports: &[Report]) -> ReportsTree {
+    let mut root = ReportsTree::default();
+    for report in reports {
+        let mut tree = &mut root;
+        for segment in &report.path {
+            tree.size += report.size;
+            tree = tree.children.entry(segment.clone()).or_default();
+        }
+        tree.size += report.size;
+    }
+    root
+}
+
+impl ReportsTree {
+    fn write_html(&self, name: &str, html: &mut String) {
+        let _ = write!(
+            html,
+            "<li><span class=\"size\">{:.2} MiB</span> {}",
+            self.size as f64 / (1024. * 1024.),
+            escape(name)
+        );
+        if !self.children.is_empty() {
+            html.push_str("<ul>");
+            let mut children: Vec<_> = self.children.iter().collect();
+            children.sort_by(|a, b| b.1.size.cmp(&a.1.size));
+            for (name, child) in children {
+                child.write_html(name, html);
+            }
+            html.push_str("</ul>");
+        }
+        html.push_str("</li>\n");
+    }
+}
+
+fn format_time(seconds: u64) -> String {
+    let time = time::at_utc(time::Timespec::new(seconds as i64, 0));
+    time.rfc822().to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,484 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The caches of the Cache API, whose items are written to a directory of their origin
+//! whenever they change, and read back the first time the origin uses its caches.
+//! <https://w3c.github.io/ServiceWorker/#cache-objects>
+
+use crate::resource_thread::{read_json_from_file, write_json_to_file};
+use headers::{HeaderMapExt, Vary};
+use net_traits::storage_thread::{CacheId, CacheOperation, CacheOperationError};
+use net_traits::storage_thread::{CacheQueryOptions, CacheQueryTarget};
+use net_traits::storage_thread::{CacheRequest, CacheResponse, CACHE_QUOTA_SIZE_LIMIT};
+use servo_url::ImmutableOrigin;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use url::form_urlencoded;
+
+/// The file listing the caches of an origin, in its directory.
+const INDEX: &str = "index.json";
+
+/// A request and response stored in a cache, whose body is also written to a file of its own.
+struct CacheItem {
+    request: CacheRequest,
+    response: CacheResponse,
+    body_file: String,
+}
+
+impl CacheItem {
+    fn size(&self) -> usize {
+        item_size(&self.request, &self.response)
+    }
+}
+
+/// The caches of an origin.
+#[derive(Default)]
+struct OriginCaches {
+    /// The name of each cache, in the order of their creation.
+    names: Vec<(String, CacheId)>,
+    /// The items of each cache.
+    items: HashMap<CacheId, Vec<CacheItem>>,
+    /// The number of body files which were created, which names the next one.
+    body_files: u64,
+}
+
+/// The caches of an origin as they are written to its index, without the bodies.
+#[derive(Default, Deserialize, Serialize)]
+struct StoredOrigin {
+    caches: Vec<(String, Vec<StoredItem>)>,
+    body_files: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredItem {
+    request: CacheRequest,
+    response: CacheResponse,
+    body_file: String,
+}
+
+pub struct CacheStorage {
+    /// The directory containing a directory for the caches of each origin, which is none
+    /// if they are only kept in memory.
+    directory: Option<PathBuf>,
+    origins: HashMap<ImmutableOrigin, OriginCaches>,
+    next_cache_id: u64,
+}
+
+impl CacheStorage {
+    pub fn new(config_dir: Option<PathBuf>) -> CacheStorage {
+        CacheStorage {
+            directory: config_dir.map(|config_dir| config_dir.join("cache_storage")),
+            origins: HashMap::new(),
+            next_cache_id: 0,
+        }
+    }
+
+    /// The directory of the caches of an origin, whose name is its percent-encoded
+    /// serialization.
+    fn origin_directory(&self, origin: &ImmutableOrigin) -> Option<PathBuf> {
+        if !origin.is_tuple() {
+            return None;
+        }
+        let name: String =
+            form_urlencoded::byte_serialize(origin.ascii_serialization().as_bytes()).collect();
+        self.directory
+            .as_ref()
+            .map(|directory| directory.join(name))
+    }
+
+    fn new_cache_id(&mut self) -> CacheId {
+        self.next_cache_id += 1;
+        CacheId(self.next_cache_id)
+    }
+
+    /// The caches of an origin, which are read from its directory if this is the first time
+    /// they are used, skipping the items whose body cannot be read.
+    fn origin_caches(&mut self, origin: &ImmutableOrigin) -> &mut OriginCaches {
+        if !self.origins.contains_key(origin) {
+            let mut caches = OriginCaches::default();
+            if let Some(directory) = self.origin_directory(origin) {
+                let mut stored = StoredOrigin::default();
+                if directory.join(INDEX).exists() {
+                    read_json_from_file(&mut stored, &directory, INDEX);
+                }
+                caches.body_files = stored.body_files;
+                for (name, stored_items) in stored.caches {
+                    let id = self.new_cache_id();
+                    let mut items = vec![];
+                    for mut stored_item in stored_items {
+                        match fs::read(directory.join(&stored_item.body_file)) {
+                            Ok(body) => stored_item.response.body = body,
+                            Err(why) => {
+                                warn!(
+                                    "couldn't read the cached body of {}: {}",
+                                    stored_item.request.url, why
+                                );
+                                continue;
+                            },
+                        }
+                        items.push(CacheItem {
+                            request: stored_item.request,
+                            response: stored_item.response,
+                            body_file: stored_item.body_file,
+                        });
+                    }
+                    caches.names.push((name, id));
+                    caches.items.insert(id, items);
+                }
+            }
+            self.origins.insert(origin.clone(), caches);
+        }
+        self.origins.get_mut(origin).unwrap()
+    }
+
+    /// Write the index of the caches of an origin to its directory, after the bodies of
+    /// their new items.
+    fn save_index(&self, origin: &ImmutableOrigin) {
+        let (directory, caches) = match (self.origin_directory(origin), self.origins.get(origin)) {
+            (Some(directory), Some(caches)) => (directory, caches),
+            _ => return,
+        };
+        let stored = StoredOrigin {
+            caches: caches
+                .names
+                .iter()
+                .map(|&(ref name, id)| {
+                    let items = caches.items[&id]
+                        .iter()
+                        .map(|item| StoredItem {
+                            request: item.request.clone(),
+                            response: CacheResponse {
+                                response_type: item.response.response_type.clone(),
+                                url_list: item.response.url_list.clone(),
+                                status: item.response.status.clone(),
+                                headers: item.response.headers.clone(),
+                                body: vec![],
+                            },
+                            body_file: item.body_file.clone(),
+                        })
+                        .collect();
+                    (name.clone(), items)
+                })
+                .collect(),
+            body_files: caches.body_files,
+        };
+        if let Err(why) = fs::create_dir_all(&directory) {
+            warn!("couldn't create {}: {}", directory.display(), why);
+            return;
+        }
+        write_json_to_file(&stored, &directory, INDEX);
+    }
+
+    fn write_body(&self, origin: &ImmutableOrigin, item: &CacheItem) {
+        let directory = match self.origin_directory(origin) {
+            Some(directory) => directory,
+            None => return,
+        };
+        let result = fs::create_dir_all(&directory)
+            .and_then(|()| fs::write(directory.join(&item.body_file), &item.response.body));
+        if let Err(why) = result {
+            warn!(
+                "couldn't write the cached body of {}: {}",
+                item.request.url, why
+            );
+        }
+    }
+
+    fn remove_bodies(&self, origin: &ImmutableOrigin, items: &[CacheItem]) {
+        let directory = match self.origin_directory(origin) {
+            Some(directory) => directory,
+            None => return,
+        };
+        for item in items {
+            let _ = fs::remove_file(directory.join(&item.body_file));
+        }
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-has>
+    pub fn has(&mut self, origin: &ImmutableOrigin, name: &str) -> bool {
+        self.origin_caches(origin)
+            .names
+            .iter()
+            .any(|&(ref cache_name, _)| cache_name == name)
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-open>
+    pub fn open(&mut self, origin: &ImmutableOrigin, name: String) -> CacheId {
+        let existing = self
+            .origin_caches(origin)
+            .names
+            .iter()
+            .find(|&&(ref cache_name, _)| *cache_name == name)
+            .map(|&(_, id)| id);
+        if let Some(id) = existing {
+            return id;
+        }
+        let id = self.new_cache_id();
+        let caches = self.origin_caches(origin);
+        caches.names.push((name, id));
+        caches.items.insert(id, vec![]);
+        self.save_index(origin);
+        id
+    }
+
+    /// Delete the cache of an origin with a name and its items. The documents which opened
+    /// it find it empty.
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-delete>
+    pub fn delete(&mut self, origin: &ImmutableOrigin, name: &str) -> bool {
+        let caches = self.origin_caches(origin);
+        let index = match caches
+            .names
+            .iter()
+            .position(|&(ref cache_name, _)| cache_name == name)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let (_, id) = caches.names.remove(index);
+        let items = caches.items.remove(&id).unwrap_or_default();
+        self.save_index(origin);
+        self.remove_bodies(origin, &items);
+        true
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-storage-keys>
+    pub fn names(&mut self, origin: &ImmutableOrigin) -> Vec<String> {
+        self.origin_caches(origin)
+            .names
+            .iter()
+            .map(|&(ref name, _)| name.clone())
+            .collect()
+    }
+
+    /// Get the items of the caches of an origin which match a request, or all of their
+    /// items if there is none.
+    pub fn query(
+        &mut self,
+        origin: &ImmutableOrigin,
+        target: CacheQueryTarget,
+        request: Option<&CacheRequest>,
+        options: CacheQueryOptions,
+    ) -> Vec<(CacheRequest, CacheResponse)> {
+        let caches = self.origin_caches(origin);
+        let ids: Vec<CacheId> = match target {
+            CacheQueryTarget::Cache(id) => vec![id],
+            CacheQueryTarget::Named(name) => caches
+                .names
+                .iter()
+                .filter(|&&(ref cache_name, _)| *cache_name == name)
+                .map(|&(_, id)| id)
+                .collect(),
+            CacheQueryTarget::All => caches.names.iter().map(|&(_, id)| id).collect(),
+        };
+        for id in ids {
+            let items = match caches.items.get(&id) {
+                Some(items) => items,
+                None => continue,
+            };
+            let matches: Vec<_> = items
+                .iter()
+                .filter(|item| {
+                    request.map_or(true, |request| {
+                        request_matches_cached_item(
+                            request,
+                            &item.request,
+                            Some(&item.response),
+                            options,
+                        )
+                    })
+                })
+                .map(|item| (item.request.clone(), item.response.clone()))
+                .collect();
+            if !matches.is_empty() {
+                return matches;
+            }
+        }
+        vec![]
+    }
+
+    /// Apply a batch of operations to a cache, unless one of them cannot be applied or the
+    /// caches of the origin would exceed its quota, and return whether any item was removed.
+    /// <https://w3c.github.io/ServiceWorker/#batch-cache-operations-algorithm>
+    pub fn batch(
+        &mut self,
+        origin: &ImmutableOrigin,
+        id: CacheId,
+        operations: Vec<CacheOperation>,
+    ) -> Result<bool, CacheOperationError> {
+        // Step 3.1.4, before any operation is applied: an operation cannot put or delete
+        // the request which an earlier one put.
+        for (index, operation) in operations.iter().enumerate() {
+            let (request, options) = match *operation {
+                CacheOperation::Put(ref request, _) => (request, CacheQueryOptions::default()),
+                CacheOperation::Delete(ref request, options) => (request, options),
+            };
+            let matches_added_item = operations[..index].iter().any(|added| match *added {
+                CacheOperation::Put(ref added_request, ref added_response) => {
+                    request_matches_cached_item(
+                        request,
+                        added_request,
+                        Some(added_response),
+                        options,
+                    )
+                },
+                CacheOperation::Delete(..) => false,
+            });
+            if matches_added_item {
+                return Err(CacheOperationError::InvalidState);
+            }
+        }
+
+        let caches = self.origin_caches(origin);
+        let is_stored = caches.names.iter().any(|&(_, cache_id)| cache_id == id);
+        let mut body_files = caches.body_files;
+        let other_caches_size: usize = caches
+            .items
+            .iter()
+            .filter(|&(cache_id, _)| *cache_id != id)
+            .flat_map(|(_, items)| items.iter().map(CacheItem::size))
+            .sum();
+        let items = match caches.items.get_mut(&id) {
+            Some(items) => items,
+            None => return Ok(false),
+        };
+
+        // The batch is rejected before any operation is applied if the caches would exceed
+        // the quota, unless it shrinks them.
+        let size: usize = items.iter().map(CacheItem::size).sum();
+        let new_size = cache_size_after(items, &operations);
+        if other_caches_size + new_size > CACHE_QUOTA_SIZE_LIMIT && new_size > size {
+            warn!(
+                "Rejecting cache operations beyond the quota of {:?}.",
+                origin
+            );
+            return Err(CacheOperationError::QuotaExceeded);
+        }
+
+        let mut removed = vec![];
+        let mut added = vec![];
+        for operation in operations {
+            let (request, options) = match operation {
+                CacheOperation::Put(ref request, _) => {
+                    (request.clone(), CacheQueryOptions::default())
+                },
+                CacheOperation::Delete(ref request, options) => (request.clone(), options),
+            };
+            let mut index = 0;
+            while index < items.len() {
+                if request_matches_cached_item(
+                    &request,
+                    &items[index].request,
+                    Some(&items[index].response),
+                    options,
+                ) {
+                    removed.push(items.remove(index));
+                } else {
+                    index += 1;
+                }
+            }
+            if let CacheOperation::Put(request, response) = operation {
+                body_files += 1;
+                let body_file = format!("{}.body", body_files);
+                added.push(body_file.clone());
+                items.push(CacheItem {
+                    request,
+                    response,
+                    body_file,
+                });
+            }
+        }
+        caches.body_files = body_files;
+
+        let removed_any = !removed.is_empty();
+        if is_stored {
+            let items = &self.origins[origin].items[&id];
+            for item in items.iter().filter(|item| added.contains(&item.body_file)) {
+                self.write_body(origin, item);
+            }
+            self.save_index(origin);
+            self.remove_bodies(origin, &removed);
+        }
+        Ok(removed_any)
+    }
+}
+
+/// The number of bytes which an item counts for in the quota of its origin.
+fn item_size(request: &CacheRequest, response: &CacheResponse) -> usize {
+    let headers: usize = request
+        .headers
+        .iter()
+        .chain(response.headers.iter())
+        .map(|(name, value)| name.as_str().len() + value.len())
+        .sum();
+    request.url.as_str().len() + headers + response.body.len()
+}
+
+/// The number of bytes which the items of a cache add up to once a batch of operations is
+/// applied to it.
+fn cache_size_after(items: &[CacheItem], operations: &[CacheOperation]) -> usize {
+    let mut remaining: Vec<(&CacheRequest, &CacheResponse)> = items
+        .iter()
+        .map(|item| (&item.request, &item.response))
+        .collect();
+    for operation in operations {
+        let (request, options) = match *operation {
+            CacheOperation::Put(ref request, _) => (request, CacheQueryOptions::default()),
+            CacheOperation::Delete(ref request, options) => (request, options),
+        };
+        remaining.retain(|&(cached_request, cached_response)| {
+            !request_matches_cached_item(request, cached_request, Some(cached_response), options)
+        });
+        if let CacheOperation::Put(ref request, ref response) = *operation {
+            remaining.push((request, response));
+        }
+    }
+    remaining
+        .into_iter()
+        .map(|(request, response)| item_size(request, response))
+        .sum()
+}
+
+/// <https://w3c.github.io/ServiceWorker/#request-matches-cached-item-algorithm>
+fn request_matches_cached_item(
+    query: &CacheRequest,
+    request: &CacheRequest,
+    response: Option<&CacheResponse>,
+    options: CacheQueryOptions,
+) -> bool {
+    // Step 1.
+    if !options.ignore_method && query.method != "GET" {
+        return false;
+    }
+
+    // Steps 2-5.
+    let mut query_url = query.url.clone();
+    let mut cached_url = request.url.clone();
+    if options.ignore_search {
+        query_url.as_mut_url().set_query(None);
+        cached_url.as_mut_url().set_query(None);
+    }
+    query_url.set_fragment(None);
+    cached_url.set_fragment(None);
+    if query_url != cached_url {
+        return false;
+    }
+
+    // Step 6.
+    let response = match response {
+        Some(response) if !options.ignore_vary => response,
+        _ => return true,
+    };
+    let vary = match response.headers.typed_get::<Vary>() {
+        Some(vary) => vary,
+        None => return true,
+    };
+
+    // Steps 7-8.
+    if vary.is_any() {
+        return false;
+    }
+    vary.iter_strs()
+        .all(|name| query.headers.get(name) == request.headers.get(name))
+}
@@ -0,0 +1,260 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Client hints, the request headers which tell an origin about the user agent and about
+//! the viewport of its documents.
+//!
+//! The brand, the platform and whether the user agent is mobile are sent to every
+//! potentially trustworthy origin, while the other hints are only sent to the origins
+//! which opted into them with the `Accept-CH` header of their documents, for the rest of
+//! the session. Hints are never sent to the other origins which the documents fetch from.
+//!
+//! The hints about the user agent are read from the `network.client_hints` preferences,
+//! which embedders can set to describe themselves.
+//!
+//! <https://wicg.github.io/client-hints-infrastructure/>
+//! <https://wicg.github.io/ua-client-hints/>
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use net_traits::request::{ClientViewport, Destination, Origin, Request};
+use servo_config::pref;
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::collections::{HashMap, HashSet};
+use url::Host;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ClientHint {
+    Ua,
+    UaArch,
+    UaFullVersion,
+    UaMobile,
+    UaModel,
+    UaPlatform,
+    UaPlatformVersion,
+    Dpr,
+    ViewportWidth,
+}
+
+/// The hints in the order in which their headers are appended to requests.
+const CLIENT_HINTS: [ClientHint; 9] = [
+    ClientHint::Ua,
+    ClientHint::UaArch,
+    ClientHint::UaFullVersion,
+    ClientHint::UaMobile,
+    ClientHint::UaModel,
+    ClientHint::UaPlatform,
+    ClientHint::UaPlatformVersion,
+    ClientHint::Dpr,
+    ClientHint::ViewportWidth,
+];
+
+impl ClientHint {
+    /// The hint which a token of an `Accept-CH` header opts into.
+    fn from_token(token: &str) -> Option<ClientHint> {
+        Some(match &*token.to_ascii_lowercase() {
+            "sec-ch-ua" => ClientHint::Ua,
+            "sec-ch-ua-arch" => ClientHint::UaArch,
+            "sec-ch-ua-full-version" => ClientHint::UaFullVersion,
+            "sec-ch-ua-mobile" => ClientHint::UaMobile,
+            "sec-ch-ua-model" => ClientHint::UaModel,
+            "sec-ch-ua-platform" => ClientHint::UaPlatform,
+            "sec-ch-ua-platform-version" => ClientHint::UaPlatformVersion,
+            "dpr" | "sec-ch-dpr" => ClientHint::Dpr,
+            "viewport-width" | "sec-ch-viewport-width" => ClientHint::ViewportWidth,
+            _ => return None,
+        })
+    }
+
+    fn header_name(self) -> HeaderName {
+        HeaderName::from_static(match self {
+            ClientHint::Ua => "sec-ch-ua",
+            ClientHint::UaArch => "sec-ch-ua-arch",
+            ClientHint::UaFullVersion => "sec-ch-ua-full-version",
+            ClientHint::UaMobile => "sec-ch-ua-mobile",
+            ClientHint::UaModel => "sec-ch-ua-model",
+            ClientHint::UaPlatform => "sec-ch-ua-platform",
+            ClientHint::UaPlatformVersion => "sec-ch-ua-platform-version",
+            ClientHint::Dpr => "dpr",
+            ClientHint::ViewportWidth => "viewport-width",
+        })
+    }
+
+    /// Whether the hint is sent without the origin opting into it.
+    /// <https://wicg.github.io/client-hints-infrastructure/#low-entropy-hint-table>
+    fn is_low_entropy(self) -> bool {
+        match self {
+            ClientHint::Ua | ClientHint::UaMobile | ClientHint::UaPlatform => true,
+            _ => false,
+        }
+    }
+
+    /// The value of the header of the hint, in the syntax of structured headers, if the
+    /// request can tell it.
+    fn value(self, viewport: Option<ClientViewport>) -> Option<String> {
+        Some(match self {
+            ClientHint::Ua => {
+                let version = pref!(network.client_hints.full_version);
+                let major_version = version.split('.').next().unwrap_or_default();
+                format!(
+                    "{};v={}",
+                    sf_string(&pref!(network.client_hints.brand)),
+                    sf_string(major_version),
+                )
+            },
+            ClientHint::UaArch => sf_string(&match pref!(network.client_hints.architecture) {
+                ref architecture if architecture.is_empty() => default_architecture().to_owned(),
+                architecture => architecture,
+            }),
+            ClientHint::UaFullVersion => sf_string(&pref!(network.client_hints.full_version)),
+            ClientHint::UaMobile => match pref!(network.client_hints.mobile) {
+                true => "?1".to_owned(),
+                false => "?0".to_owned(),
+            },
+            ClientHint::UaModel => sf_string(&pref!(network.client_hints.model)),
+            ClientHint::UaPlatform => sf_string(&match pref!(network.client_hints.platform) {
+                ref platform if platform.is_empty() => default_platform().to_owned(),
+                platform => platform,
+            }),
+            ClientHint::UaPlatformVersion => {
+                sf_string(&pref!(network.client_hints.platform_version))
+            },
+            ClientHint::Dpr => viewport?.device_pixel_ratio.to_string(),
+            ClientHint::ViewportWidth => (viewport?.width.round() as u32).to_string(),
+        })
+    }
+}
+
+/// Serialize a string as a structured header string.
+fn sf_string(value: &str) -> String {
+    let mut serialized = String::with_capacity(value.len() + 2);
+    serialized.push('"');
+    for c in value
+        .chars()
+        .filter(|c| c.is_ascii() && !c.is_ascii_control())
+    {
+        if c == '"' || c == '\\' {
+            serialized.push('\\');
+        }
+        serialized.push(c);
+    }
+    serialized.push('"');
+    serialized
+}
+
+fn default_platform() -> &'static str {
+    if cfg!(target_os = "android") {
+        "Android"
+    } else if cfg!(target_os = "ios") {
+        "iOS"
+    } else if cfg!(target_os = "linux") {
+        "Linux"
+    } else if cfg!(target_os = "macos") {
+        "macOS"
+    } else if cfg!(target_os = "windows") {
+        "Windows"
+    } else {
+        "Unknown"
+    }
+}
+
+fn default_architecture() -> &'static str {
+    if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+        "x86"
+    } else if cfg!(any(target_arch = "arm", target_arch = "aarch64")) {
+        "arm"
+    } else {
+        ""
+    }
+}
+
+/// Whether client hints may be sent to a URL, which needs a secure transport.
+/// <https://w3c.github.io/webappsec-secure-contexts/#is-origin-trustworthy>
+fn is_potentially_trustworthy(url: &ServoUrl) -> bool {
+    if url.is_secure_scheme() {
+        return true;
+    }
+    match url.host() {
+        Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        Some(Host::Ipv4(address)) => address.is_loopback(),
+        Some(Host::Ipv6(address)) => address.is_loopback(),
+        None => false,
+    }
+}
+
+/// The client hints which origins opted into.
+/// <https://wicg.github.io/client-hints-infrastructure/#accept-ch-cache-definition>
+#[derive(Default)]
+pub struct AcceptChCache {
+    hints: HashMap<ImmutableOrigin, HashSet<ClientHint>>,
+}
+
+impl AcceptChCache {
+    pub fn new() -> AcceptChCache {
+        AcceptChCache::default()
+    }
+
+    /// The hints which an origin opted into.
+    pub fn hints(&self, origin: &ImmutableOrigin) -> Option<&HashSet<ClientHint>> {
+        self.hints.get(origin)
+    }
+
+    /// Store the hints which the `Accept-CH` header of a document opts into, in place of
+    /// those which its origin opted into before.
+    /// <https://wicg.github.io/client-hints-infrastructure/#abstract-opdef-update-the-client-hints-set>
+    pub fn update_from_response(&mut self, request: &Request, url: &ServoUrl, headers: &HeaderMap) {
+        if request.destination != Destination::Document || !is_potentially_trustworthy(url) {
+            return;
+        }
+        if !headers.contains_key("accept-ch") {
+            return;
+        }
+        let hints = headers
+            .get_all("accept-ch")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|token| ClientHint::from_token(token.trim()))
+            .collect();
+        self.hints.insert(url.origin(), hints);
+    }
+
+    /// Append the headers of the hints which the origin of a request opted into, and
+    /// those of the low entropy hints.
+    /// <https://wicg.github.io/client-hints-infrastructure/#abstract-opdef-append-client-hints-to-request>
+    pub fn append_client_hints(&self, request: &mut Request) {
+        if !pref!(network.client_hints.enabled) {
+            return;
+        }
+        let url = request.current_url();
+        if !is_potentially_trustworthy(&url) {
+            return;
+        }
+        // Navigations ask for the hints of the origin which they navigate to, and other
+        // requests for those of the origin of their document, if they fetch from it.
+        let origin = match request.origin {
+            _ if request.destination == Destination::Document => url.origin(),
+            Origin::Origin(ref origin) => origin.clone(),
+            Origin::Client => return,
+        };
+        if origin != url.origin() {
+            return;
+        }
+        let accepted = self.hints(&origin);
+        for &hint in CLIENT_HINTS.iter() {
+            if !hint.is_low_entropy() && !accepted.map_or(false, |hints| hints.contains(&hint)) {
+                continue;
+            }
+            let name = hint.header_name();
+            if request.headers.contains_key(&name) {
+                continue;
+            }
+            let value = hint
+                .value(request.viewport)
+                .and_then(|value| HeaderValue::from_str(&value).ok());
+            if let Some(value) = value {
+                request.headers.insert(name, value);
+            }
+        }
+    }
+}
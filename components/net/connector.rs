@@ -3,19 +3,48 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::hosts::replace_host;
-use hyper::client::connect::{Connect, Destination};
+use futures::future::{self, loop_fn, Either, Loop};
+use futures::{Async, Poll};
+use hyper::client::connect::{Connect, Connected, Destination};
 use hyper::client::HttpConnector as HyperHttpConnector;
 use hyper::rt::Future;
-use hyper::{Body, Client};
-use hyper_openssl::HttpsConnector;
+use hyper::{Body, Client, Uri};
+use hyper_openssl::{HttpsConnector, MaybeHttpsStream};
 use openssl::ssl::{SslConnector, SslConnectorBuilder, SslMethod, SslOptions};
 use openssl::x509;
+use servo_config::proxy::{proxy_for_host, Proxy, ProxyKind};
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::TcpStream as StdTcpStream;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::io::{read_exact, write_all, AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
 use tokio::prelude::future::Executor;
+use tokio::timer::Delay;
 
 pub const BUF_SIZE: usize = 32768;
 pub const ALPN_H2_H1: &'static [u8] = b"\x02h2\x08http/1.1";
 pub const ALPN_H1: &'static [u8] = b"\x08http/1.1";
 
+// The flow control windows of HTTP/2, larger than the 64KB of the protocol so that the
+// multiplexed responses don't wait for window updates on every round trip.
+const HTTP2_STREAM_WINDOW_SIZE: u32 = 2 * 1024 * 1024;
+const HTTP2_CONNECTION_WINDOW_SIZE: u32 = 16 * 1024 * 1024;
+
+// The largest head of a response to a CONNECT request which is read from an HTTP proxy.
+const MAX_PROXY_RESPONSE_HEAD_SIZE: usize = 8192;
+
+// How long the address of a host which was resolved by a dns-prefetch hint is used for.
+const PREFETCHED_ADDRESS_LIFETIME: Duration = Duration::from_secs(60);
+
+// How long a connection which was opened by a preconnect hint waits for the request it is
+// opened for, before it is closed.
+const PRECONNECTED_STREAM_LIFETIME: Duration = Duration::from_secs(10);
+
 // See https://wiki.mozilla.org/Security/Server_Side_TLS for orientation.
 const TLS1_2_CIPHERSUITES: &'static str = concat!(
     "ECDHE-ECDSA-CHACHA20-POLY1305:ECDHE-RSA-CHACHA20-POLY1305:",
@@ -30,34 +59,522 @@ const SIGNATURE_ALGORITHMS: &'static str = concat!(
     "RSA+SHA512:RSA+SHA384:RSA+SHA256"
 );
 
+/// The addresses of the hosts which were resolved ahead of the connections to them, by
+/// <https://w3c.github.io/resource-hints/#dns-prefetch>.
+#[derive(Clone, Default)]
+struct PrefetchedAddresses(Arc<Mutex<HashMap<String, (Instant, IpAddr)>>>);
+
+impl PrefetchedAddresses {
+    fn get(&self, host: &str) -> Option<IpAddr> {
+        let mut addresses = self.0.lock().unwrap();
+        match addresses.get(host) {
+            Some(&(resolved, address)) if resolved.elapsed() < PREFETCHED_ADDRESS_LIFETIME => {
+                Some(address)
+            },
+            Some(_) => {
+                addresses.remove(host);
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// Resolve the host on a thread of its own, since the resolver of the system blocks.
+    fn prefetch(&self, host: String) {
+        if self.get(&host).is_some() {
+            return;
+        }
+        let addresses = self.0.clone();
+        let _ = thread::Builder::new()
+            .name(format!("DnsPrefetch:{}", host))
+            .spawn(move || {
+                let address = match (&*host, 0).to_socket_addrs() {
+                    Ok(mut resolved) => resolved.next(),
+                    Err(e) => {
+                        debug!("Could not prefetch the address of {}: {}", host, e);
+                        None
+                    },
+                };
+                if let Some(address) = address {
+                    let mut addresses = addresses.lock().unwrap();
+                    addresses.insert(host, (Instant::now(), address.ip()));
+                }
+            });
+    }
+}
+
+#[derive(Clone)]
 pub struct HttpConnector {
     inner: HyperHttpConnector,
+    prefetched_addresses: PrefetchedAddresses,
 }
 
 impl HttpConnector {
-    fn new() -> HttpConnector {
+    fn new(prefetched_addresses: PrefetchedAddresses) -> HttpConnector {
         let mut inner = HyperHttpConnector::new(4);
         inner.enforce_http(false);
         inner.set_happy_eyeballs_timeout(None);
-        HttpConnector { inner }
+        HttpConnector {
+            inner,
+            prefetched_addresses,
+        }
     }
 }
 
 impl Connect for HttpConnector {
-    type Transport = <HyperHttpConnector as Connect>::Transport;
-    type Error = <HyperHttpConnector as Connect>::Error;
-    type Future = <HyperHttpConnector as Connect>::Future;
+    type Transport = TcpStream;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (TcpStream, Connected), Error = io::Error> + Send>;
 
     fn connect(&self, dest: Destination) -> Self::Future {
-        // Perform host replacement when making the actual TCP connection.
-        let mut new_dest = dest.clone();
-        let addr = replace_host(dest.host());
-        new_dest.set_host(&*addr).unwrap();
-        self.inner.connect(new_dest)
+        let proxy = match proxy_for_host(dest.host()) {
+            Some(proxy) => proxy,
+            None => {
+                // Perform host replacement when making the actual TCP connection.
+                let mut new_dest = dest.clone();
+                let addr = replace_host(dest.host());
+                let addr = match self.prefetched_addresses.get(&addr) {
+                    Some(address) => address.to_string().into(),
+                    None => addr,
+                };
+                new_dest.set_host(&*addr).unwrap();
+                return Box::new(self.inner.connect(new_dest));
+            },
+        };
+
+        let mut proxy_dest = dest.clone();
+        let proxy_host = replace_host(&proxy.host);
+        let proxy_host = if proxy_host.contains(':') {
+            format!("[{}]", proxy_host)
+        } else {
+            proxy_host.into_owned()
+        };
+        if let Err(e) = proxy_dest.set_host(&proxy_host) {
+            return Box::new(future::err(io::Error::new(io::ErrorKind::InvalidInput, e)));
+        }
+        proxy_dest.set_port(proxy.port);
+
+        let host = replace_host(dest.host().trim_matches(|c| c == '[' || c == ']')).into_owned();
+        let port = dest
+            .port()
+            .unwrap_or(if dest.scheme() == "https" { 443 } else { 80 });
+        let credentials = proxy.credentials;
+        let connecting = self.inner.connect(proxy_dest);
+        match proxy.kind {
+            // The requests to an HTTP proxy are in absolute-form, unless they are tunneled.
+            ProxyKind::Http if dest.scheme() != "https" => {
+                Box::new(connecting.map(|(stream, connected)| (stream, connected.proxy(true))))
+            },
+            ProxyKind::Http => Box::new(connecting.and_then(move |(stream, connected)| {
+                tunnel_through_http_proxy(stream, host, port, credentials)
+                    .map(|stream| (stream, connected))
+            })),
+            ProxyKind::Socks5 => Box::new(connecting.and_then(move |(stream, connected)| {
+                connect_through_socks5_proxy(stream, host, port, credentials)
+                    .map(|stream| (stream, connected))
+            })),
+        }
+    }
+}
+
+/// A stream to a proxy, over which its handshake is made.
+trait ProxyStream: AsyncRead + AsyncWrite + Send + 'static {}
+
+impl<S: AsyncRead + AsyncWrite + Send + 'static> ProxyStream for S {}
+
+/// A blocking TCP stream, whose reads and writes are always ready, so that the futures of
+/// the handshakes with proxies can be waited for on the current thread.
+struct BlockingTcpStream(StdTcpStream);
+
+impl Read for BlockingTcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for BlockingTcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl AsyncRead for BlockingTcpStream {}
+
+impl AsyncWrite for BlockingTcpStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+/// Open a connection to a host through a proxy, tunneled with `CONNECT` for an HTTP proxy,
+/// blocking until the proxy is connected to the host. It is for the connections which
+/// aren't made by the client, such as those of WebSockets.
+pub fn connect_through_proxy(proxy: Proxy, host: &str, port: u16) -> io::Result<StdTcpStream> {
+    let stream = StdTcpStream::connect((&*replace_host(&proxy.host), proxy.port))?;
+    let stream = BlockingTcpStream(stream);
+    let host = replace_host(host.trim_matches(|c| c == '[' || c == ']')).into_owned();
+    let connecting = match proxy.kind {
+        ProxyKind::Http => Either::A(tunnel_through_http_proxy(
+            stream,
+            host,
+            port,
+            proxy.credentials,
+        )),
+        ProxyKind::Socks5 => Either::B(connect_through_socks5_proxy(
+            stream,
+            host,
+            port,
+            proxy.credentials,
+        )),
+    };
+    connecting.wait().map(|stream| stream.0)
+}
+
+fn proxy_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+/// The value of the `Proxy-Authorization` header for the `Basic` authentication scheme.
+fn basic_proxy_authorization(user_name: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        base64::encode(format!("{}:{}", user_name, password).as_bytes())
+    )
+}
+
+/// <https://tools.ietf.org/html/rfc7231#section-4.3.6>
+fn tunnel_through_http_proxy<S: ProxyStream>(
+    stream: S,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+) -> impl Future<Item = S, Error = io::Error> {
+    let authority = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some((user_name, password)) = credentials {
+        request.push_str(&format!(
+            "Proxy-Authorization: {}\r\n",
+            basic_proxy_authorization(&user_name, &password)
+        ));
+    }
+    request.push_str("\r\n");
+
+    write_all(stream, request.into_bytes())
+        .and_then(|(stream, _)| {
+            // The head is read byte by byte, so that none of the tunneled bytes which
+            // follow it are consumed.
+            loop_fn((stream, Vec::new()), |(stream, mut head)| {
+                read_exact(stream, [0u8; 1]).and_then(move |(stream, byte)| {
+                    head.push(byte[0]);
+                    if head.ends_with(b"\r\n\r\n") {
+                        Ok(Loop::Break((stream, head)))
+                    } else if head.len() > MAX_PROXY_RESPONSE_HEAD_SIZE {
+                        Err(proxy_error(
+                            "The response of the proxy is too large.".to_owned(),
+                        ))
+                    } else {
+                        Ok(Loop::Continue((stream, head)))
+                    }
+                })
+            })
+        })
+        .and_then(move |(stream, head)| {
+            let status_line = String::from_utf8_lossy(&head);
+            let status_line = status_line.lines().next().unwrap_or("");
+            match status_line.split(' ').nth(1) {
+                Some(status) if status.len() == 3 && status.starts_with('2') => Ok(stream),
+                _ => Err(proxy_error(format!(
+                    "The proxy refused to connect to {}: {}",
+                    authority, status_line
+                ))),
+            }
+        })
+}
+
+/// <https://tools.ietf.org/html/rfc1928>
+fn connect_through_socks5_proxy<S: ProxyStream>(
+    stream: S,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+) -> Box<dyn Future<Item = S, Error = io::Error> + Send> {
+    let mut request = vec![5, 1, 0];
+    if let Ok(address) = host.parse::<Ipv4Addr>() {
+        request.push(1);
+        request.extend_from_slice(&address.octets());
+    } else if let Ok(address) = host.parse::<Ipv6Addr>() {
+        request.push(4);
+        request.extend_from_slice(&address.octets());
+    } else if host.len() <= 255 {
+        request.push(3);
+        request.push(host.len() as u8);
+        request.extend_from_slice(host.as_bytes());
+    } else {
+        return Box::new(future::err(proxy_error(format!(
+            "The host name {} is too long for the proxy.",
+            host
+        ))));
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+
+    // Offer the user name and password authentication if there are credentials, and no
+    // authentication in any case.
+    let methods = if credentials.is_some() {
+        vec![5, 2, 0, 2]
+    } else {
+        vec![5, 1, 0]
+    };
+    Box::new(
+        write_all(stream, methods)
+            .and_then(|(stream, _)| read_exact(stream, [0u8; 2]))
+            .and_then(move |(stream, reply)| match (reply, credentials) {
+                ([5, 0], _) => Either::A(future::ok(stream)),
+                ([5, 2], Some((user_name, password))) => {
+                    Either::B(authenticate_to_socks5_proxy(stream, user_name, password))
+                },
+                _ => Either::A(future::err(proxy_error(
+                    "The proxy accepts none of the authentication methods.".to_owned(),
+                ))),
+            })
+            .and_then(move |stream| write_all(stream, request))
+            .and_then(|(stream, _)| read_exact(stream, [0u8; 4]))
+            .and_then(|(stream, reply)| {
+                if reply[0] != 5 || reply[1] != 0 {
+                    return Either::A(future::err(proxy_error(format!(
+                        "The proxy failed to connect: {}",
+                        socks5_reply_message(reply[1])
+                    ))));
+                }
+                // Skip the bound address and port which end the reply.
+                let remaining = match reply[3] {
+                    1 => Either::A(future::ok((stream, 4 + 2))),
+                    4 => Either::A(future::ok((stream, 16 + 2))),
+                    3 => Either::B(
+                        read_exact(stream, [0u8; 1])
+                            .map(|(stream, length)| (stream, length[0] as usize + 2)),
+                    ),
+                    _ => {
+                        return Either::A(future::err(proxy_error(
+                            "The proxy replied with an unknown address type.".to_owned(),
+                        )));
+                    },
+                };
+                Either::B(
+                    remaining
+                        .and_then(|(stream, length)| read_exact(stream, vec![0u8; length]))
+                        .map(|(stream, _)| stream),
+                )
+            }),
+    )
+}
+
+/// <https://tools.ietf.org/html/rfc1929>
+fn authenticate_to_socks5_proxy<S: ProxyStream>(
+    stream: S,
+    user_name: String,
+    password: String,
+) -> Box<dyn Future<Item = S, Error = io::Error> + Send> {
+    if user_name.len() > 255 || password.len() > 255 {
+        return Box::new(future::err(proxy_error(
+            "The credentials are too long for the proxy.".to_owned(),
+        )));
+    }
+    let mut request = vec![1, user_name.len() as u8];
+    request.extend_from_slice(user_name.as_bytes());
+    request.push(password.len() as u8);
+    request.extend_from_slice(password.as_bytes());
+    Box::new(
+        write_all(stream, request)
+            .and_then(|(stream, _)| read_exact(stream, [0u8; 2]))
+            .and_then(|(stream, reply)| {
+                if reply[1] == 0 {
+                    Ok(stream)
+                } else {
+                    Err(proxy_error(
+                        "The proxy rejected the credentials.".to_owned(),
+                    ))
+                }
+            }),
+    )
+}
+
+fn socks5_reply_message(reply: u8) -> &'static str {
+    match reply {
+        1 => "general failure",
+        2 => "connection not allowed by the ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// The `Proxy-Authorization` header of the requests to a host which are sent to an HTTP
+/// proxy in absolute-form, if the proxy has credentials.
+pub fn proxy_authorization_for_host(host: &str) -> Option<String> {
+    match proxy_for_host(host) {
+        Some(proxy) if proxy.kind == ProxyKind::Http => proxy
+            .credentials
+            .map(|(user_name, password)| basic_proxy_authorization(&user_name, &password)),
+        _ => None,
+    }
+}
+
+type Connection = (
+    <HttpsConnector<HttpConnector> as Connect>::Transport,
+    Connected,
+);
+
+/// The key of the connections which can be used for the requests to a destination.
+fn connection_key(dest: &Destination) -> String {
+    let port = dest
+        .port()
+        .unwrap_or(if dest.scheme() == "https" { 443 } else { 80 });
+    format!("{}://{}:{}", dest.scheme(), dest.host(), port)
+}
+
+/// The connections which were opened ahead of the requests to their origin, by
+/// <https://w3c.github.io/resource-hints/#preconnect>.
+#[derive(Clone, Default)]
+struct PreconnectedStreams(Arc<Mutex<HashMap<String, (Instant, Connection)>>>);
+
+impl PreconnectedStreams {
+    fn contains(&self, key: &str) -> bool {
+        self.0.lock().unwrap().contains_key(key)
+    }
+
+    fn insert(&self, key: String, connection: Connection) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), connection));
+    }
+
+    fn take(&self, key: &str) -> Option<Connection> {
+        self.remove_expired();
+        self.0
+            .lock()
+            .unwrap()
+            .remove(key)
+            .map(|(_, connection)| connection)
+    }
+
+    /// Close the connections which no request used in time.
+    fn remove_expired(&self) {
+        self.0
+            .lock()
+            .unwrap()
+            .retain(|_, &mut (opened, _)| opened.elapsed() < PRECONNECTED_STREAM_LIFETIME);
+    }
+}
+
+/// The connector of the HTTP client, which tells hyper to speak HTTP/2 over the TLS
+/// connections whose server selected it with ALPN. Hyper then multiplexes the requests to
+/// an origin over a single such connection instead of opening one per request.
+#[derive(Clone)]
+pub struct Connector {
+    inner: HttpsConnector<HttpConnector>,
+    prefetched_addresses: PrefetchedAddresses,
+    preconnected_streams: PreconnectedStreams,
+}
+
+impl Connector {
+    pub fn new(tls_config: TlsConfig) -> Connector {
+        let prefetched_addresses = PrefetchedAddresses::default();
+        let http = HttpConnector::new(prefetched_addresses.clone());
+        Connector {
+            inner: HttpsConnector::with_connector(http, tls_config).unwrap(),
+            prefetched_addresses,
+            preconnected_streams: PreconnectedStreams::default(),
+        }
+    }
+
+    /// Resolve the address of a host before a connection to it is needed.
+    /// <https://w3c.github.io/resource-hints/#dns-prefetch>
+    pub fn prefetch_dns(&self, host: &str) {
+        // The address of a host which is reached through a proxy is resolved by the proxy.
+        if proxy_for_host(host).is_some() || replace_host(host) != host {
+            return;
+        }
+        self.prefetched_addresses.prefetch(host.to_owned());
+    }
+
+    /// Open a connection to the origin of a URL before a request to it is made. The
+    /// returned future keeps it for the next request which needs a new connection to
+    /// that origin, and closes it if no request used it in time.
+    /// <https://w3c.github.io/resource-hints/#preconnect>
+    pub fn preconnect(
+        &self,
+        url: &ServoUrl,
+    ) -> Option<Box<dyn Future<Item = (), Error = ()> + Send>> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        let origin = format!(
+            "{}://{}:{}/",
+            url.scheme(),
+            url.host_str()?,
+            url.port_or_known_default()?
+        );
+        let dest = Destination::try_from_uri(origin.parse::<Uri>().ok()?).ok()?;
+        let key = connection_key(&dest);
+        if self.preconnected_streams.contains(&key) {
+            return None;
+        }
+        let preconnected_streams = self.preconnected_streams.clone();
+        Some(Box::new(self.open_connection(dest).then(move |result| {
+            match result {
+                Ok(connection) => preconnected_streams.insert(key, connection),
+                Err(e) => debug!("Could not preconnect to {}: {}", key, e),
+            }
+            Delay::new(Instant::now() + PRECONNECTED_STREAM_LIFETIME).then(move |_| {
+                preconnected_streams.remove_expired();
+                Ok(())
+            })
+        })))
+    }
+
+    fn open_connection(&self, dest: Destination) -> <Self as Connect>::Future {
+        Box::new(self.inner.connect(dest).map(|(stream, connected)| {
+            let negotiated_h2 = match stream {
+                MaybeHttpsStream::Https(ref stream) => {
+                    stream.get_ref().ssl().selected_alpn_protocol() == Some(&b"h2"[..])
+                },
+                MaybeHttpsStream::Http(_) => false,
+            };
+            if negotiated_h2 {
+                (stream, connected.negotiated_h2())
+            } else {
+                (stream, connected)
+            }
+        }))
+    }
+}
+
+impl Connect for Connector {
+    type Transport = <HttpsConnector<HttpConnector> as Connect>::Transport;
+    type Error = <HttpsConnector<HttpConnector> as Connect>::Error;
+    type Future = Box<dyn Future<Item = (Self::Transport, Connected), Error = Self::Error> + Send>;
+
+    fn connect(&self, dest: Destination) -> Self::Future {
+        match self.preconnected_streams.take(&connection_key(&dest)) {
+            Some(connection) => Box::new(future::ok(connection)),
+            None => self.open_connection(dest),
+        }
     }
 }
 
-pub type Connector = HttpsConnector<HttpConnector>;
 pub type TlsConfig = SslConnectorBuilder;
 
 pub fn create_tls_config(certs: &str, alpn: &[u8]) -> TlsConfig {
@@ -107,14 +624,14 @@ pub fn create_tls_config(certs: &str, alpn: &[u8]) -> TlsConfig {
     cfg
 }
 
-pub fn create_http_client<E>(tls_config: TlsConfig, executor: E) -> Client<Connector, Body>
+pub fn create_http_client<E>(connector: Connector, executor: E) -> Client<Connector, Body>
 where
     E: Executor<Box<dyn Future<Error = (), Item = ()> + Send + 'static>> + Sync + Send + 'static,
 {
-    let connector = HttpsConnector::with_connector(HttpConnector::new(), tls_config).unwrap();
-
     Client::builder()
         .http1_title_case_headers(true)
+        .http2_initial_stream_window_size(HTTP2_STREAM_WINDOW_SIZE)
+        .http2_initial_connection_window_size(HTTP2_CONNECTION_WINDOW_SIZE)
         .executor(executor)
         .build(connector)
 }
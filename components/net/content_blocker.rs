@@ -0,0 +1,652 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A content blocker which classifies the subresource requests with the filter lists of
+//! Adblock Plus and uBlock Origin, to block them or to rewrite them to a neutral resource,
+//! and which hides the elements matching the element hiding rules of the lists.
+//!
+//! The lists are read from the files of the `network.content_blocker.filter_lists`
+//! preference, separated by commas.
+//!
+//! <https://help.eyeo.com/adblockplus/how-to-write-filters>
+
+use msg::constellation_msg::PipelineId;
+use net_traits::pub_domains::reg_suffix;
+use net_traits::request::{Destination, Origin, Request};
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use url::Position;
+
+/// The resources which the requests matching a `$redirect` rule are rewritten to.
+const REDIRECT_RESOURCES: &[(&str, &str)] = &[
+    (
+        "1x1.gif",
+        "data:image/gif;base64,R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7",
+    ),
+    (
+        "1x1-transparent.gif",
+        "data:image/gif;base64,R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7",
+    ),
+    ("empty", "data:text/plain,"),
+    ("noop.css", "data:text/css,"),
+    ("noop.html", "data:text/html,"),
+    ("noop.js", "data:application/javascript,"),
+    ("noop.txt", "data:text/plain,"),
+    ("noopcss", "data:text/css,"),
+    ("noopframe", "data:text/html,"),
+    ("noopjs", "data:application/javascript,"),
+    ("nooptext", "data:text/plain,"),
+];
+
+// The types of the requests, which the `$script`, `$image`... options restrict the rules to.
+const SCRIPT: u16 = 1 << 0;
+const IMAGE: u16 = 1 << 1;
+const STYLESHEET: u16 = 1 << 2;
+const FONT: u16 = 1 << 3;
+const MEDIA: u16 = 1 << 4;
+const OBJECT: u16 = 1 << 5;
+const SUBDOCUMENT: u16 = 1 << 6;
+const XMLHTTPREQUEST: u16 = 1 << 7;
+const WEBSOCKET: u16 = 1 << 8;
+const PING: u16 = 1 << 9;
+const OTHER: u16 = 1 << 10;
+const ALL_TYPES: u16 = (1 << 11) - 1;
+
+fn resource_type(name: &str) -> Option<u16> {
+    Some(match name {
+        "script" => SCRIPT,
+        "image" => IMAGE,
+        "stylesheet" | "css" => STYLESHEET,
+        "font" => FONT,
+        "media" => MEDIA,
+        "object" => OBJECT,
+        "subdocument" | "frame" => SUBDOCUMENT,
+        "xmlhttprequest" | "xhr" => XMLHTTPREQUEST,
+        "websocket" => WEBSOCKET,
+        "ping" => PING,
+        "other" => OTHER,
+        _ => return None,
+    })
+}
+
+fn destination_type(destination: Destination) -> u16 {
+    match destination {
+        Destination::Script |
+        Destination::Worker |
+        Destination::SharedWorker |
+        Destination::ServiceWorker |
+        Destination::AudioWorklet |
+        Destination::PaintWorklet => SCRIPT,
+        Destination::Image => IMAGE,
+        Destination::Style => STYLESHEET,
+        Destination::Font => FONT,
+        Destination::Audio | Destination::Video | Destination::Track => MEDIA,
+        Destination::Object | Destination::Embed => OBJECT,
+        Destination::Document => SUBDOCUMENT,
+        Destination::None => XMLHTTPREQUEST,
+        _ => OTHER,
+    }
+}
+
+/// What to do with a request.
+#[derive(Debug, PartialEq)]
+pub enum Classification {
+    Allow,
+    Block,
+    /// Load the given neutral resource instead, for the pages which break when the
+    /// resource fails to load.
+    Redirect(ServoUrl),
+}
+
+/// Where the pattern of a rule must match the URL.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Anchor {
+    /// Anywhere.
+    None,
+    /// At the start of the URL, with `|`.
+    Start,
+    /// At the start of the host or of one of its labels, with `||`.
+    Host,
+}
+
+#[derive(Debug)]
+struct NetworkRule {
+    anchor: Anchor,
+    /// The pattern, where `*` matches any characters and `^` a separator.
+    pattern: String,
+    /// Whether the pattern must match the end of the URL, with a trailing `|`.
+    end_anchor: bool,
+    /// The longest part of the pattern without wildcards, which the URL must contain.
+    literal: String,
+    match_case: bool,
+    types: u16,
+    /// Whether the rule only applies to the third-party requests, or only to the
+    /// first-party ones.
+    third_party: Option<bool>,
+    domains: Domains,
+    /// Whether the rule overrides the exceptions.
+    important: bool,
+    /// Whether a matching request is blocked, which isn't the case of the rules which only
+    /// choose the resource of the requests blocked by other rules.
+    blocks: bool,
+    redirect: Option<&'static str>,
+}
+
+/// The domains of the pages which a rule applies to, or doesn't apply to with `~`.
+#[derive(Debug, Default)]
+struct Domains {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Domains {
+    fn parse(domains: &str, separator: char) -> Domains {
+        let mut result = Domains::default();
+        for domain in domains.split(separator).map(str::trim) {
+            if domain.starts_with('~') {
+                result.exclude.push(domain[1..].to_ascii_lowercase());
+            } else if !domain.is_empty() {
+                result.include.push(domain.to_ascii_lowercase());
+            }
+        }
+        result
+    }
+
+    fn is_generic(&self) -> bool {
+        self.include.is_empty()
+    }
+
+    fn applies_to(&self, host: Option<&str>) -> bool {
+        let host = match host {
+            Some(host) => host,
+            None => return self.include.is_empty(),
+        };
+        if self.exclude.iter().any(|domain| is_subdomain(host, domain)) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|domain| is_subdomain(host, domain))
+    }
+}
+
+fn is_subdomain(host: &str, domain: &str) -> bool {
+    host == domain || (host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.'))
+}
+
+#[derive(Debug)]
+struct ElementHidingRule {
+    domains: Domains,
+    selector: String,
+    exception: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ContentBlocker {
+    /// The rules anchored to a whole host, by host.
+    host_rules: HashMap<String, Vec<NetworkRule>>,
+    rules: Vec<NetworkRule>,
+    exceptions: Vec<NetworkRule>,
+    element_hiding_rules: Vec<ElementHidingRule>,
+    /// The number of requests blocked or rewritten, by the pipeline which made them.
+    blocked: Mutex<HashMap<PipelineId, usize>>,
+}
+
+impl ContentBlocker {
+    /// A content blocker with the lists of the `network.content_blocker.filter_lists`
+    /// preference.
+    pub fn from_prefs() -> ContentBlocker {
+        let mut content_blocker = ContentBlocker::default();
+        let paths = pref!(network.content_blocker.filter_lists);
+        for path in paths
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+        {
+            match fs::read_to_string(path) {
+                Ok(list) => content_blocker.add_list(&list),
+                Err(e) => warn!("Failed to read the filter list {} ({}).", path, e),
+            }
+        }
+        content_blocker
+    }
+
+    /// Add the rules of a filter list. The rules which aren't supported are ignored.
+    pub fn add_list(&mut self, list: &str) {
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('!') || line.starts_with('[') {
+                continue;
+            }
+            if let Some(rule) = parse_element_hiding_rule(line) {
+                self.element_hiding_rules.extend(rule);
+                continue;
+            }
+            let (rule, exception) = match parse_network_rule(line) {
+                Some(rule) => rule,
+                None => continue,
+            };
+            if exception {
+                self.exceptions.push(rule);
+            } else if let Some(host) = rule.host() {
+                self.host_rules
+                    .entry(host.to_owned())
+                    .or_insert_with(Vec::new)
+                    .push(rule);
+            } else {
+                self.rules.push(rule);
+            }
+        }
+    }
+
+    /// Classify a request of the given destination, made by a page of the given host.
+    pub fn classify(
+        &self,
+        url: &ServoUrl,
+        source_host: Option<&str>,
+        destination: Destination,
+    ) -> Classification {
+        let request = RequestInfo::new(url, source_host, destination);
+
+        let matching = self
+            .matching_host_rules(&request)
+            .chain(self.rules.iter().filter(|rule| rule.matches(&request)));
+        let mut blocked = false;
+        let mut important = false;
+        let mut redirect = None;
+        for rule in matching {
+            blocked |= rule.blocks;
+            important |= rule.important && rule.blocks;
+            redirect = redirect.or(rule.redirect);
+        }
+        if !blocked {
+            return Classification::Allow;
+        }
+        if !important && self.exceptions.iter().any(|rule| rule.matches(&request)) {
+            return Classification::Allow;
+        }
+        match redirect.and_then(|resource| ServoUrl::parse(resource).ok()) {
+            Some(url) => Classification::Redirect(url),
+            None => Classification::Block,
+        }
+    }
+
+    /// Classify a subresource request, or the navigation of a frame, and count it if it is
+    /// blocked or rewritten.
+    pub fn classify_request(&self, request: &Request) -> Classification {
+        if !pref!(network.content_blocker.enabled) || request.top_level_navigation {
+            return Classification::Allow;
+        }
+        // The origin of a frame is the one of the document it navigates to, so the page
+        // which made the request is known from the site for cookies.
+        let source_origin = if request.destination == Destination::Document {
+            request.site_for_cookies.as_ref()
+        } else {
+            match request.origin {
+                Origin::Origin(ref origin) => Some(origin),
+                Origin::Client => None,
+            }
+        };
+        let source_host = source_origin
+            .and_then(|origin| origin.host())
+            .map(|host| host.to_string());
+        let classification = self.classify(
+            &request.current_url(),
+            source_host.as_ref().map(String::as_str),
+            request.destination,
+        );
+        if classification != Classification::Allow {
+            if let Some(pipeline_id) = request.pipeline_id {
+                *self.blocked.lock().unwrap().entry(pipeline_id).or_insert(0) += 1;
+            }
+        }
+        classification
+    }
+
+    /// The number of requests of the given pipelines which were blocked or rewritten.
+    pub fn blocked_count(&self, pipeline_ids: &[PipelineId]) -> usize {
+        let blocked = self.blocked.lock().unwrap();
+        pipeline_ids
+            .iter()
+            .filter_map(|pipeline_id| blocked.get(pipeline_id))
+            .sum()
+    }
+
+    /// Forget the requests of a pipeline which exited.
+    pub fn remove_pipeline(&self, pipeline_id: PipelineId) {
+        self.blocked.lock().unwrap().remove(&pipeline_id);
+    }
+
+    /// A stylesheet which hides the elements matching the element hiding rules of the
+    /// page at the given URL.
+    pub fn element_hiding_stylesheet(&self, url: &ServoUrl) -> Option<String> {
+        let host = url.host_str().map(str::to_ascii_lowercase);
+        let host = host.as_ref().map(String::as_str);
+        let applies = |rule: &&ElementHidingRule| rule.domains.applies_to(host);
+        let exceptions: Vec<&str> = self
+            .element_hiding_rules
+            .iter()
+            .filter(|rule| rule.exception)
+            .filter(applies)
+            .map(|rule| &*rule.selector)
+            .collect();
+        // Each selector has its own style rule, so that an invalid selector doesn't
+        // invalidate the others.
+        let stylesheet: String = self
+            .element_hiding_rules
+            .iter()
+            .filter(|rule| !rule.exception)
+            .filter(applies)
+            .filter(|rule| !exceptions.contains(&&*rule.selector))
+            .map(|rule| format!("{} {{ display: none !important; }}\n", rule.selector))
+            .collect();
+        if stylesheet.is_empty() {
+            None
+        } else {
+            Some(stylesheet)
+        }
+    }
+
+    fn matching_host_rules<'a>(
+        &'a self,
+        request: &'a RequestInfo,
+    ) -> impl Iterator<Item = &'a NetworkRule> + 'a {
+        request
+            .host_suffixes()
+            .filter_map(move |host| self.host_rules.get(host))
+            .flat_map(|rules| rules.iter())
+            .filter(move |rule| rule.matches(request))
+    }
+}
+
+/// The parts of a request which the rules match.
+struct RequestInfo<'a> {
+    url: String,
+    /// The URL in lowercase, for the rules which ignore the case.
+    lowercase_url: String,
+    /// The position of the host in the URL.
+    host_start: usize,
+    host_end: usize,
+    source_host: Option<&'a str>,
+    third_party: Option<bool>,
+    types: u16,
+}
+
+impl<'a> RequestInfo<'a> {
+    fn new(url: &ServoUrl, source_host: Option<&'a str>, destination: Destination) -> Self {
+        let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+        let third_party = source_host
+            .map(|source_host| reg_suffix(&host) != reg_suffix(&source_host.to_ascii_lowercase()));
+        let url_str = url.as_str();
+        RequestInfo {
+            url: url_str.to_owned(),
+            lowercase_url: url_str.to_ascii_lowercase(),
+            host_start: url.as_url()[..Position::BeforeHost].len(),
+            host_end: url.as_url()[..Position::AfterHost].len(),
+            source_host: source_host,
+            third_party: third_party,
+            types: destination_type(destination),
+        }
+    }
+
+    /// The host, and the suffixes of the host which start after one of its dots.
+    fn host_suffixes<'b>(&'b self) -> impl Iterator<Item = &'b str> + 'b {
+        let host = &self.lowercase_url[self.host_start..self.host_end];
+        self.label_starts()
+            .map(move |start| &host[start - self.host_start..])
+    }
+
+    /// The positions in the URL where the labels of the host start.
+    fn label_starts<'b>(&'b self) -> impl Iterator<Item = usize> + 'b {
+        let bytes = self.url.as_bytes();
+        (self.host_start..self.host_end)
+            .filter(move |&i| i == self.host_start || bytes[i - 1] == b'.')
+    }
+}
+
+impl NetworkRule {
+    /// The host which the rule is anchored to, if the rule matches the whole host.
+    fn host(&self) -> Option<&str> {
+        if self.anchor != Anchor::Host {
+            return None;
+        }
+        let end = self
+            .pattern
+            .find(|c| matches!(c, '^' | '/' | ':' | '*'))
+            .unwrap_or(self.pattern.len());
+        let host = &self.pattern[..end];
+        let complete =
+            end == self.pattern.len() || matches!(self.pattern.as_bytes()[end], b'^' | b'/' | b':');
+        if complete && !host.is_empty() && !host.ends_with('.') {
+            Some(host)
+        } else {
+            None
+        }
+    }
+
+    fn matches(&self, request: &RequestInfo) -> bool {
+        if self.types & request.types == 0 {
+            return false;
+        }
+        if self.third_party.is_some() && self.third_party != request.third_party {
+            return false;
+        }
+        if !self.domains.applies_to(request.source_host) {
+            return false;
+        }
+        let url = if self.match_case {
+            &request.url
+        } else {
+            &request.lowercase_url
+        };
+        if !url.contains(&self.literal) {
+            return false;
+        }
+        let pattern = self.pattern.as_bytes();
+        let url = url.as_bytes();
+        match self.anchor {
+            Anchor::Start => matches_at(pattern, url, self.end_anchor, false),
+            Anchor::Host => request
+                .label_starts()
+                .any(|start| matches_at(pattern, &url[start..], self.end_anchor, false)),
+            Anchor::None => matches_at(pattern, url, self.end_anchor, true),
+        }
+    }
+}
+
+/// Whether the pattern matches the start of the text, where `*` matches any characters
+/// and `^` a separator or the end of the text. With `to_end`, the pattern must match the
+/// whole text, and with `anywhere`, it may start anywhere in the text.
+///
+/// Only the last `*` is backtracked to, which takes a time proportional to the product of
+/// the lengths of the pattern and of the text, whatever the number of wildcards.
+fn matches_at(pattern: &[u8], text: &[u8], to_end: bool, anywhere: bool) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // The position in the pattern after the last `*`, and the position in the text which
+    // the rest of the pattern is matched from.
+    let mut backtrack = if anywhere { Some((0, 0)) } else { None };
+    loop {
+        if p == pattern.len() && (!to_end || t == text.len()) {
+            return true;
+        }
+        if t == text.len() {
+            // Matching more characters with a wildcard would leave fewer for the rest of
+            // the pattern, which only matches the end of the text with separators.
+            return pattern[p..].iter().all(|&c| c == b'*' || c == b'^');
+        }
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, t));
+                continue;
+            },
+            Some(&c) if c == text[t] || (c == b'^' && is_separator(text[t])) => {
+                p += 1;
+                t += 1;
+                continue;
+            },
+            _ => {},
+        }
+        match backtrack {
+            Some((star_p, star_t)) => {
+                backtrack = Some((star_p, star_t + 1));
+                p = star_p;
+                t = star_t + 1;
+            },
+            None => return false,
+        }
+    }
+}
+
+fn is_separator(c: u8) -> bool {
+    !(c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.' | b'%'))
+}
+
+/// Parse a network rule, and whether it is an exception. The rules with a regular
+/// expression or with an unknown option are ignored.
+fn parse_network_rule(line: &str) -> Option<(NetworkRule, bool)> {
+    let (line, exception) = if line.starts_with("@@") {
+        (&line[2..], true)
+    } else {
+        (line, false)
+    };
+    if line.starts_with('/') && line.ends_with('/') && line.len() > 1 {
+        return None;
+    }
+    let (pattern, options) = match line.rfind('$') {
+        Some(index) => (&line[..index], Some(&line[index + 1..])),
+        None => (line, None),
+    };
+
+    let mut rule = NetworkRule {
+        anchor: Anchor::None,
+        pattern: String::new(),
+        end_anchor: false,
+        literal: String::new(),
+        match_case: false,
+        types: ALL_TYPES,
+        third_party: None,
+        domains: Domains::default(),
+        important: false,
+        blocks: true,
+        redirect: None,
+    };
+
+    let mut included_types = 0;
+    let mut excluded_types = 0;
+    for option in options.into_iter().flat_map(|options| options.split(',')) {
+        let option = option.trim();
+        let (negated, name) = if option.starts_with('~') {
+            (true, &option[1..])
+        } else {
+            (false, option)
+        };
+        let (name, value) = match name.find('=') {
+            Some(index) => (&name[..index], Some(&name[index + 1..])),
+            None => (name, None),
+        };
+        match (name, value) {
+            ("third-party", None) | ("3p", None) => rule.third_party = Some(!negated),
+            ("first-party", None) | ("1p", None) => rule.third_party = Some(negated),
+            ("match-case", None) => rule.match_case = true,
+            ("important", None) => rule.important = true,
+            ("domain", Some(domains)) => rule.domains = Domains::parse(domains, '|'),
+            ("redirect", Some(resource)) | ("redirect-rule", Some(resource)) => {
+                // The priority of the redirection, after a colon, is ignored.
+                let resource = resource.split(':').next().unwrap_or("");
+                let redirect = REDIRECT_RESOURCES
+                    .iter()
+                    .find(|&&(resource_name, _)| resource_name == resource)?;
+                rule.redirect = Some(redirect.1);
+                rule.blocks = name == "redirect";
+            },
+            (name, None) => {
+                let types = resource_type(name)?;
+                if negated {
+                    excluded_types |= types;
+                } else {
+                    included_types |= types;
+                }
+            },
+            _ => return None,
+        }
+    }
+    if included_types != 0 {
+        rule.types = included_types;
+    }
+    rule.types &= !excluded_types;
+
+    let mut pattern = pattern;
+    if pattern.starts_with("||") {
+        rule.anchor = Anchor::Host;
+        pattern = &pattern[2..];
+    } else if pattern.starts_with('|') {
+        rule.anchor = Anchor::Start;
+        pattern = &pattern[1..];
+    }
+    if pattern.ends_with('|') {
+        rule.end_anchor = true;
+        pattern = &pattern[..pattern.len() - 1];
+    }
+    // A rule which matches every URL is almost always an error in the list.
+    if pattern.trim_matches('*').is_empty() && rule.domains.is_generic() {
+        return None;
+    }
+    rule.pattern = if rule.match_case {
+        pattern.to_owned()
+    } else {
+        pattern.to_ascii_lowercase()
+    };
+    rule.literal = rule
+        .pattern
+        .split(|c| c == '*' || c == '^')
+        .max_by_key(|part| part.len())
+        .unwrap_or("")
+        .to_owned();
+    Some((rule, exception))
+}
+
+/// Parse an element hiding rule, `domains##selector` or the exception
+/// `domains#@#selector`. The procedural and scriptlet rules are ignored.
+fn parse_element_hiding_rule(line: &str) -> Option<Option<ElementHidingRule>> {
+    let (index, separator, exception) = match (line.find("#@#"), line.find("##")) {
+        (Some(index), _) => (index, "#@#", true),
+        (None, Some(index)) => (index, "##", false),
+        (None, None) => {
+            // The other cosmetic rules, `#?#`, `#$#`...
+            return if line.contains("#?#") || line.contains("#$#") || line.contains("#%#") {
+                Some(None)
+            } else {
+                None
+            };
+        },
+    };
+    let selector = line[index + separator.len()..].trim();
+    if selector.is_empty() ||
+        selector.starts_with('+') ||
+        selector.starts_with('^') ||
+        selector.contains('{') ||
+        selector.contains('}') ||
+        has_unclosed_comment(selector)
+    {
+        return Some(None);
+    }
+    Some(Some(ElementHidingRule {
+        domains: Domains::parse(&line[..index], ','),
+        selector: selector.to_owned(),
+        exception: exception,
+    }))
+}
+
+/// Whether a selector opens a comment which it doesn't close, which would comment out the
+/// rules of the stylesheet which follow its own.
+fn has_unclosed_comment(selector: &str) -> bool {
+    let mut rest = selector;
+    while let Some(start) = rest.find("/*") {
+        match rest[start + 2..].find("*/") {
+            Some(end) => rest = &rest[start + 2 + end + 2..],
+            None => return true,
+        }
+    }
+    false
+}
@@ -5,6 +5,7 @@
 //! Implementation of cookie creation and matching as specified by
 //! http://tools.ietf.org/html/rfc6265
 
+use cookie_rs::SameSite;
 use hyper_serde::{self, Serde};
 use net_traits::pub_domains::is_pub_domain;
 use net_traits::CookieSource;
@@ -13,6 +14,19 @@ use std::borrow::ToOwned;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use time::{at, now, Duration, Tm};
 
+/// How a request relates to its site for cookies, which decides whether the cookies with a
+/// `SameSite` attribute are sent with it and set by its response.
+/// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-05#section-5.2>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SameSiteContext {
+    /// A same-site request, or a request from an API other than HTTP.
+    SameSite,
+    /// A cross-site request which navigates a top-level browsing context.
+    TopLevelNavigation { safe_method: bool },
+    /// Any other cross-site request.
+    CrossSite,
+}
+
 /// A stored cookie that wraps the definition in cookie-rs. This is used to implement
 /// various behaviours defined in the spec that rely on an associated request URL,
 /// which cookie-rs and hyper's header parsing do not support.
@@ -114,6 +128,11 @@ impl Cookie {
             return None;
         }
 
+        // https://tools.ietf.org/html/draft-west-cookie-incrementalism-01#section-3.2
+        if cookie.same_site() == Some(SameSite::None) && !cookie.secure().unwrap_or(false) {
+            return None;
+        }
+
         // https://tools.ietf.org/html/draft-west-cookie-prefixes-04#section-4
         // Step 1 of cookie prefixes
         if (cookie.name().starts_with("__Secure-") || cookie.name().starts_with("__Host-")) &&
@@ -139,6 +158,28 @@ impl Cookie {
         })
     }
 
+    /// The `SameSite` attribute of the cookie, which is `Lax` when it is missing.
+    /// <https://tools.ietf.org/html/draft-west-cookie-incrementalism-01#section-3.1>
+    pub fn same_site(&self) -> SameSite {
+        self.cookie.same_site().unwrap_or(SameSite::Lax)
+    }
+
+    /// Whether the cookie is sent with a request in a context.
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-05#section-5.4>
+    pub fn appropriate_for_context(&self, context: SameSiteContext) -> bool {
+        match (self.same_site(), context) {
+            (SameSite::None, _) | (_, SameSiteContext::SameSite) => true,
+            (SameSite::Lax, SameSiteContext::TopLevelNavigation { safe_method }) => safe_method,
+            _ => false,
+        }
+    }
+
+    /// Whether the cookie can be set by the response to a request in a context.
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-05#section-5.3> step 13
+    pub fn can_be_set_in_context(&self, context: SameSiteContext) -> bool {
+        self.same_site() == SameSite::None || context != SameSiteContext::CrossSite
+    }
+
     pub fn touch(&mut self) {
         self.last_access = now();
     }
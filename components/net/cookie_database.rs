@@ -0,0 +1,263 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A SQLite database in the profile directory which keeps the persistent cookies of the
+//! cookie storage across restarts.
+
+use crate::cookie::Cookie;
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use hyper_serde::Serde;
+use rusqlite::{params, Connection, NO_PARAMS};
+use std::collections::HashMap;
+use std::mem;
+use std::path::Path;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use time::{self, Timespec, Tm};
+
+/// The version of the schema of the database, which is stored as its `user_version`.
+const SCHEMA_VERSION: i32 = 1;
+
+/// How long the writes of the cookies are delayed so that the changes which follow them
+/// are written in the same transaction.
+const WRITE_DELAY: Duration = Duration::from_millis(500);
+
+enum DatabaseMsg {
+    /// Replace the stored cookies of a site.
+    Store(String, Vec<Cookie>),
+    /// Write the pending changes, then reply.
+    Flush(Sender<()>),
+    /// Write the pending changes, and stop the writing thread.
+    Exit,
+}
+
+/// The cookies which are persisted in a SQLite database. The database is written on a
+/// thread of its own, which batches the changes made in a short time, so that the cookie
+/// storage never waits on the disk.
+#[derive(Debug)]
+pub struct CookieDatabase {
+    sender: Sender<DatabaseMsg>,
+    writer: Option<JoinHandle<()>>,
+    /// The cookies which were stored when the database was opened.
+    stored_cookies: Vec<(String, Cookie)>,
+}
+
+impl CookieDatabase {
+    /// Open the database at a path, creating it if needed.
+    pub fn open(path: &Path) -> rusqlite::Result<CookieDatabase> {
+        let connection = Connection::open(path)?;
+        let version: i32 =
+            connection.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            // The cookies of an unknown schema can't be read, so they are dropped.
+            connection.execute_batch(&format!(
+                "DROP TABLE IF EXISTS cookies;
+                 CREATE TABLE cookies (
+                     host_key TEXT NOT NULL,
+                     name TEXT NOT NULL,
+                     domain TEXT NOT NULL,
+                     path TEXT NOT NULL,
+                     cookie TEXT NOT NULL,
+                     host_only INTEGER NOT NULL,
+                     creation_time INTEGER NOT NULL,
+                     last_access INTEGER NOT NULL,
+                     expiry_time INTEGER NOT NULL,
+                     PRIMARY KEY (host_key, name, domain, path)
+                 );
+                 PRAGMA user_version = {};",
+                SCHEMA_VERSION
+            ))?;
+        }
+        let stored_cookies = load(&connection)?;
+        let (sender, receiver) = unbounded();
+        let writer = thread::Builder::new()
+            .name("CookieDatabase".to_owned())
+            .spawn(move || write_changes(connection, receiver))
+            .expect("Thread spawning failed");
+        Ok(CookieDatabase {
+            sender,
+            writer: Some(writer),
+            stored_cookies,
+        })
+    }
+
+    /// The cookies which were stored when the database was opened, with the key of their
+    /// site in the cookie storage.
+    pub fn take_stored_cookies(&mut self) -> Vec<(String, Cookie)> {
+        mem::replace(&mut self.stored_cookies, vec![])
+    }
+
+    /// Replace the stored cookies of a site by its current persistent cookies. The
+    /// cookies are written later, along with the other changes made meanwhile.
+    pub fn store(&self, host_key: &str, cookies: &[Cookie]) {
+        // Session cookies are never written to the disk.
+        let cookies = cookies
+            .iter()
+            .filter(|cookie| cookie.persistent && cookie.expiry_time.is_some())
+            .cloned()
+            .collect();
+        let _ = self
+            .sender
+            .send(DatabaseMsg::Store(host_key.to_owned(), cookies));
+    }
+
+    /// Wait until the changes to the cookies so far are written.
+    pub fn flush(&self) {
+        let (sender, receiver) = unbounded();
+        if self.sender.send(DatabaseMsg::Flush(sender)).is_ok() {
+            let _ = receiver.recv();
+        }
+    }
+}
+
+impl Drop for CookieDatabase {
+    fn drop(&mut self) {
+        let _ = self.sender.send(DatabaseMsg::Exit);
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Write the changes to the cookies until the database is closed, batching those which
+/// are received within `WRITE_DELAY` of the first pending one.
+fn write_changes(mut connection: Connection, receiver: Receiver<DatabaseMsg>) {
+    let mut pending: HashMap<String, Vec<Cookie>> = HashMap::new();
+    let mut deadline = None;
+    loop {
+        let msg = match deadline {
+            Some(deadline) => {
+                match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(msg) => Some(msg),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => Some(DatabaseMsg::Exit),
+                }
+            },
+            None => Some(receiver.recv().unwrap_or(DatabaseMsg::Exit)),
+        };
+        match msg {
+            Some(DatabaseMsg::Store(host_key, cookies)) => {
+                pending.insert(host_key, cookies);
+                if deadline.is_none() {
+                    deadline = Some(Instant::now() + WRITE_DELAY);
+                }
+                continue;
+            },
+            Some(DatabaseMsg::Flush(sender)) => {
+                write_pending(&mut connection, &mut pending);
+                let _ = sender.send(());
+            },
+            Some(DatabaseMsg::Exit) => {
+                write_pending(&mut connection, &mut pending);
+                return;
+            },
+            None => write_pending(&mut connection, &mut pending),
+        }
+        deadline = None;
+    }
+}
+
+fn write_pending(connection: &mut Connection, pending: &mut HashMap<String, Vec<Cookie>>) {
+    if pending.is_empty() {
+        return;
+    }
+    if let Err(error) = store(connection, pending.drain()) {
+        warn!("Could not write cookies to the database: {}", error);
+    }
+}
+
+/// Remove the expired cookies from the database, and return the remaining ones with the
+/// key of their site in the cookie storage.
+fn load(connection: &Connection) -> rusqlite::Result<Vec<(String, Cookie)>> {
+    connection.execute(
+        "DELETE FROM cookies WHERE expiry_time <= ?",
+        params![to_micros(&time::now())],
+    )?;
+    let mut statement = connection.prepare(
+        "SELECT host_key, cookie, host_only, creation_time, last_access, expiry_time
+         FROM cookies ORDER BY creation_time",
+    )?;
+    let rows = statement.query_map(NO_PARAMS, |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, bool>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+
+    let mut cookies = vec![];
+    for row in rows {
+        let (host_key, cookie, host_only, creation_time, last_access, expiry_time) = row?;
+        let cookie = match cookie_rs::Cookie::parse(cookie) {
+            Ok(cookie) => cookie,
+            Err(error) => {
+                warn!("Could not parse a stored cookie: {}", error);
+                continue;
+            },
+        };
+        cookies.push((
+            host_key,
+            Cookie {
+                cookie,
+                host_only,
+                persistent: true,
+                creation_time: from_micros(creation_time),
+                last_access: from_micros(last_access),
+                expiry_time: Some(Serde(from_micros(expiry_time))),
+            },
+        ));
+    }
+    Ok(cookies)
+}
+
+/// Replace the stored cookies of some sites by their current persistent cookies, in a
+/// single transaction.
+fn store<I>(connection: &mut Connection, sites: I) -> rusqlite::Result<()>
+where
+    I: Iterator<Item = (String, Vec<Cookie>)>,
+{
+    let now = time::now().to_timespec();
+    let transaction = connection.transaction()?;
+    for (host_key, cookies) in sites {
+        transaction.execute("DELETE FROM cookies WHERE host_key = ?", params![host_key])?;
+        for cookie in cookies {
+            let expiry_time = match cookie.expiry_time {
+                Some(expiry_time) if expiry_time.to_timespec() > now => expiry_time.0,
+                _ => continue,
+            };
+            transaction.execute(
+                "INSERT OR REPLACE INTO cookies
+                 (host_key, name, domain, path, cookie, host_only, creation_time, last_access,
+                  expiry_time)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    host_key,
+                    cookie.cookie.name(),
+                    cookie.cookie.domain().unwrap_or(""),
+                    cookie.cookie.path().unwrap_or(""),
+                    cookie.cookie.to_string(),
+                    cookie.host_only,
+                    to_micros(&cookie.creation_time),
+                    to_micros(&cookie.last_access),
+                    to_micros(&expiry_time),
+                ],
+            )?;
+        }
+    }
+    transaction.commit()
+}
+
+fn to_micros(time: &Tm) -> i64 {
+    let time = time.to_timespec();
+    time.sec * 1_000_000 + i64::from(time.nsec / 1_000)
+}
+
+fn from_micros(micros: i64) -> Tm {
+    let sec = micros.div_euclid(1_000_000);
+    let nsec = micros.rem_euclid(1_000_000) as i32 * 1_000;
+    time::at(Timespec::new(sec, nsec))
+}
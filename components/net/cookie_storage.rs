@@ -5,13 +5,16 @@
 //! Implementation of cookie storage as specified in
 //! http://tools.ietf.org/html/rfc6265
 
-use crate::cookie::Cookie;
+use crate::cookie::{Cookie, SameSiteContext};
+use crate::cookie_database::CookieDatabase;
+use ipc_channel::ipc::IpcSender;
 use net_traits::pub_domains::reg_suffix;
 use net_traits::CookieSource;
 use servo_url::ServoUrl;
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::sync::Arc;
 use time::{self, Tm};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -19,6 +22,13 @@ pub struct CookieStorage {
     version: u32,
     cookies_map: HashMap<String, Vec<Cookie>>,
     max_per_host: usize,
+    /// The senders to notify of the next change of the cookies of a site, by the key of the
+    /// cookies of the site in `cookies_map`.
+    #[serde(skip)]
+    observers: HashMap<String, Vec<IpcSender<()>>>,
+    /// The database to which the persistent cookies are written as they change.
+    #[serde(skip)]
+    database: Option<Arc<CookieDatabase>>,
 }
 
 impl CookieStorage {
@@ -27,6 +37,67 @@ impl CookieStorage {
             version: 1,
             cookies_map: HashMap::new(),
             max_per_host: max_cookies,
+            observers: HashMap::new(),
+            database: None,
+        }
+    }
+
+    /// Add the persistent cookies of a database to the storage, and write the cookies of
+    /// the storage to the database from now on.
+    pub fn set_database(&mut self, mut database: CookieDatabase) {
+        for (domain, cookie) in database.take_stored_cookies() {
+            let cookies = self.cookies_map.entry(domain).or_insert(vec![]);
+            // The cookies which are already in the storage are newer.
+            let exists = cookies.iter().any(|c| {
+                c.cookie.domain() == cookie.cookie.domain() &&
+                    c.cookie.path() == cookie.cookie.path() &&
+                    c.cookie.name() == cookie.cookie.name()
+            });
+            if !exists && cookies.len() < self.max_per_host {
+                cookies.push(cookie);
+            }
+        }
+        self.database = Some(Arc::new(database));
+        for domain in self.cookies_map.keys() {
+            self.persist(domain);
+        }
+    }
+
+    /// Write the persistent cookies of every site to the database, which also records the
+    /// last time that they were accessed, and wait until they are written.
+    pub fn save_to_database(&self) {
+        for domain in self.cookies_map.keys() {
+            self.persist(domain);
+        }
+        if let Some(ref database) = self.database {
+            database.flush();
+        }
+    }
+
+    /// Queue the write of the persistent cookies of a site to the database.
+    fn persist(&self, domain: &str) {
+        if let Some(ref database) = self.database {
+            let cookies = self.cookies_map.get(domain).map_or(&[][..], |c| &c[..]);
+            database.store(domain, cookies);
+        }
+    }
+
+    /// Notify a sender of the next change of the cookies of the site of a URL, which lets
+    /// scripts cache the cookies until then.
+    pub fn observe(&mut self, url: &ServoUrl, observer: IpcSender<()>) {
+        let domain = reg_host(url.host_str().unwrap_or(""));
+        self.observers
+            .entry(domain)
+            .or_insert(vec![])
+            .push(observer);
+    }
+
+    fn notify_observers(&mut self, domain: &str) {
+        if let Some(observers) = self.observers.remove(domain) {
+            for observer in observers {
+                // The script which observes the cookies may be gone.
+                let _ = observer.send(());
+            }
         }
     }
 
@@ -86,10 +157,12 @@ impl CookieStorage {
     }
     pub fn clear_storage(&mut self, url: &ServoUrl) {
         let domain = reg_host(url.host_str().unwrap_or(""));
-        let cookies = self.cookies_map.entry(domain).or_insert(vec![]);
+        let cookies = self.cookies_map.entry(domain.clone()).or_insert(vec![]);
         for cookie in cookies.iter_mut() {
             cookie.set_expiry_time_negative();
         }
+        self.persist(&domain);
+        self.notify_observers(&domain);
     }
 
     // http://tools.ietf.org/html/rfc6265#section-5.3
@@ -105,6 +178,10 @@ impl CookieStorage {
             return;
         }
 
+        // The observers can only read the cookies again once the new one is stored.
+        let domain = reg_host(&cookie.cookie.domain().as_ref().unwrap_or(&""));
+        self.notify_observers(&domain);
+
         // Step 11
         if let Some(old_cookie) = old_cookie.unwrap() {
             // Step 11.3
@@ -112,8 +189,7 @@ impl CookieStorage {
         }
 
         // Step 12
-        let domain = reg_host(&cookie.cookie.domain().as_ref().unwrap_or(&""));
-        let cookies = self.cookies_map.entry(domain).or_insert(vec![]);
+        let cookies = self.cookies_map.entry(domain.clone()).or_insert(vec![]);
 
         if cookies.len() == self.max_per_host {
             let old_len = cookies.len();
@@ -124,10 +200,12 @@ impl CookieStorage {
             if new_len == old_len &&
                 !evict_one_cookie(cookie.cookie.secure().unwrap_or(false), cookies)
             {
+                self.persist(&domain);
                 return;
             }
         }
         cookies.push(cookie);
+        self.persist(&domain);
     }
 
     pub fn cookie_comparator(a: &Cookie, b: &Cookie) -> Ordering {
@@ -147,17 +225,33 @@ impl CookieStorage {
 
     pub fn remove_expired_cookies_for_url(&mut self, url: &ServoUrl) {
         let domain = reg_host(url.host_str().unwrap_or(""));
-        if let Entry::Occupied(mut entry) = self.cookies_map.entry(domain) {
+        if let Entry::Occupied(mut entry) = self.cookies_map.entry(domain.clone()) {
             let cookies = entry.get_mut();
+            let old_len = cookies.len();
             cookies.retain(|c| !is_cookie_expired(&c));
-            if cookies.len() == 0 {
+            let new_len = cookies.len();
+            if new_len == 0 {
                 entry.remove_entry();
             }
+            if new_len != old_len {
+                self.persist(&domain);
+            }
         }
     }
 
     // http://tools.ietf.org/html/rfc6265#section-5.4
     pub fn cookies_for_url(&mut self, url: &ServoUrl, source: CookieSource) -> Option<String> {
+        self.cookies_for_url_in_context(url, source, SameSiteContext::SameSite)
+    }
+
+    /// The cookies which are sent with a request to a URL, leaving out those which its
+    /// context doesn't allow because of their `SameSite` attribute.
+    pub fn cookies_for_url_in_context(
+        &mut self,
+        url: &ServoUrl,
+        source: CookieSource,
+        context: SameSiteContext,
+    ) -> Option<String> {
         let filterer = |c: &&mut Cookie| -> bool {
             info!(
                 " === SENT COOKIE : {} {} {:?} {:?}",
@@ -171,7 +265,7 @@ impl CookieStorage {
                 c.appropriate_for_url(url, source)
             );
             // Step 1
-            c.appropriate_for_url(url, source)
+            c.appropriate_for_url(url, source) && c.appropriate_for_context(context)
         };
         // Step 2
         let domain = reg_host(url.host_str().unwrap_or(""));
@@ -201,6 +295,17 @@ impl CookieStorage {
         }
     }
 
+    /// The time at which the first of the cookies of a URL expires.
+    pub fn first_expiry_time_for_url(&self, url: &ServoUrl, source: CookieSource) -> Option<Tm> {
+        let domain = reg_host(url.host_str().unwrap_or(""));
+        self.cookies_map
+            .get(&domain)?
+            .iter()
+            .filter(|c| c.appropriate_for_url(url, source))
+            .filter_map(|c| c.expiry_time.as_ref().map(|time| time.0))
+            .min_by_key(|time| time.to_timespec())
+    }
+
     pub fn cookies_data_for_url<'a>(
         &'a mut self,
         url: &'a ServoUrl,
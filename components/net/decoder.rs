@@ -32,7 +32,7 @@ use brotli::Decompressor;
 use bytes::{Buf, BufMut, BytesMut};
 use flate2::read::DeflateDecoder;
 use futures::{Async, Future, Poll, Stream};
-use hyper::header::{HeaderValue, CONTENT_ENCODING, TRANSFER_ENCODING};
+use hyper::header::{CONTENT_ENCODING, TRANSFER_ENCODING};
 use hyper::{self, Body, Chunk, Response};
 use libflate::non_blocking::gzip;
 use std::cmp;
@@ -73,6 +73,21 @@ enum DecoderType {
     Deflate,
 }
 
+impl DecoderType {
+    /// The decoder of a content coding, whose name is case-insensitive.
+    fn from_coding(coding: &str) -> Option<DecoderType> {
+        if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip") {
+            Some(DecoderType::Gzip)
+        } else if coding.eq_ignore_ascii_case("br") {
+            Some(DecoderType::Brotli)
+        } else if coding.eq_ignore_ascii_case("deflate") {
+            Some(DecoderType::Deflate)
+        } else {
+            None
+        }
+    }
+}
+
 enum Inner {
     /// A `PlainText` decoder just returns the response content as is.
     PlainText(Body),
@@ -134,26 +149,20 @@ impl Decoder {
     /// A decoder is just a wrapper around the hyper request that knows
     /// how to decode the content body of the request.
     ///
-    /// Uses the correct variant by inspecting the Content-Encoding header.
+    /// Uses the correct variant by inspecting the Content-Encoding header,
+    /// whose values are comma-separated lists of codings.
     pub fn detect(response: Response<Body>) -> Response<Decoder> {
         let values = response
             .headers()
             .get_all(CONTENT_ENCODING)
             .iter()
             .chain(response.headers().get_all(TRANSFER_ENCODING).iter());
-        let decoder = values.fold(None, |acc, enc| {
-            acc.or_else(|| {
-                if enc == HeaderValue::from_static("gzip") {
-                    Some(DecoderType::Gzip)
-                } else if enc == HeaderValue::from_static("br") {
-                    Some(DecoderType::Brotli)
-                } else if enc == HeaderValue::from_static("deflate") {
-                    Some(DecoderType::Deflate)
-                } else {
-                    None
-                }
-            })
-        });
+        let decoder = values
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .fold(None, |acc, coding| {
+                acc.or_else(|| DecoderType::from_coding(coding.trim()))
+            });
         match decoder {
             Some(type_) => response.map(|r| Decoder::pending(r, type_)),
             None => response.map(Decoder::plain_text),
@@ -2,15 +2,21 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::about_loader::AboutPages;
+use crate::content_blocker::{Classification, ContentBlocker};
 use crate::data_loader::decode;
 use crate::fetch::cors_cache::CorsCache;
+use crate::fetch::orb::{block_response, orb_decision, sniffs_as_protected_type};
+use crate::fetch::orb::{OrbDecision, ORB_SNIFF_LENGTH};
 use crate::filemanager_thread::{FileManager, FILE_CHUNK_SIZE};
-use crate::http_loader::{determine_request_referrer, http_fetch, HttpState};
+use crate::http_loader::{cors_check, determine_request_referrer, http_fetch, HttpState};
 use crate::http_loader::{set_default_accept, set_default_accept_language};
+use crate::preload_cache::PreloadKey;
 use crate::subresource_integrity::is_response_integrity_valid;
 use content_security_policy as csp;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use devtools_traits::DevtoolsControlMsg;
+use embedder_traits::PromptMsg;
 use headers::{AccessControlExposeHeaders, ContentType, HeaderMapExt, Range};
 use http::header::{self, HeaderMap, HeaderName};
 use hyper::Method;
@@ -19,11 +25,14 @@ use ipc_channel::ipc::IpcReceiver;
 use mime::{self, Mime};
 use net_traits::blob_url_store::{parse_blob_url, BlobURLStoreError};
 use net_traits::filemanager_thread::{FileTokenCheck, RelativePos};
+use net_traits::protocol_handler::{ProtocolBody, ProtocolHandler, ProtocolRegistry};
 use net_traits::request::{
     is_cors_safelisted_method, is_cors_safelisted_request_header, Origin, ResponseTainting, Window,
 };
-use net_traits::request::{CredentialsMode, Destination, Referrer, Request, RequestMode};
-use net_traits::response::{Response, ResponseBody, ResponseType};
+use net_traits::request::{
+    CredentialsMode, Destination, Initiator, Referrer, Request, RequestMode,
+};
+use net_traits::response::{HttpsState, Response, ResponseBody, ResponseType};
 use net_traits::{FetchTaskTarget, NetworkError, ReferrerPolicy, ResourceFetchTiming};
 use net_traits::{ResourceAttribute, ResourceTimeValue};
 use servo_arc::Arc as ServoArc;
@@ -53,8 +62,13 @@ pub enum Data {
 
 pub struct FetchContext {
     pub state: Arc<HttpState>,
+    pub about_pages: Arc<AboutPages>,
+    pub protocols: Arc<ProtocolRegistry>,
+    pub content_blocker: Arc<ContentBlocker>,
     pub user_agent: Cow<'static, str>,
     pub devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+    /// A channel to the constellation, which queues the prompts for credentials.
+    pub prompt_sender: Sender<PromptMsg>,
     pub filemanager: FileManager,
     pub file_token: FileTokenCheck,
     pub cancellation_listener: Arc<Mutex<CancellationListener>>,
@@ -92,7 +106,7 @@ impl CancellationListener {
 pub type DoneChannel = Option<(Sender<Data>, Receiver<Data>)>;
 
 /// [Fetch](https://fetch.spec.whatwg.org#concept-fetch)
-pub fn fetch(request: &mut Request, target: Target, context: &FetchContext) {
+pub fn fetch(request: &mut Request, target: Target, context: &FetchContext) -> Response {
     // Steps 7,4 of https://w3c.github.io/resource-timing/#processing-model
     // rev order okay since spec says they're equal - https://w3c.github.io/resource-timing/#dfn-starttime
     context
@@ -106,7 +120,7 @@ pub fn fetch(request: &mut Request, target: Target, context: &FetchContext) {
         .unwrap()
         .set_attribute(ResourceAttribute::StartTime(ResourceTimeValue::FetchStart));
 
-    fetch_with_cors_cache(request, &mut CorsCache::new(), target, context);
+    fetch_with_cors_cache(request, &mut CorsCache::new(), target, context)
 }
 
 pub fn fetch_with_cors_cache(
@@ -114,7 +128,7 @@ pub fn fetch_with_cors_cache(
     cache: &mut CorsCache,
     target: Target,
     context: &FetchContext,
-) {
+) -> Response {
     // Step 1.
     if request.window == Window::Client {
         // TODO: Set window to request's client object if client is a Window object
@@ -146,13 +160,15 @@ pub fn fetch_with_cors_cache(
     }
 
     // Step 8.
-    main_fetch(request, cache, false, false, target, &mut None, &context);
+    main_fetch(request, cache, false, false, target, &mut None, &context)
 }
 
 /// https://www.w3.org/TR/CSP/#should-block-request
-pub fn should_request_be_blocked_by_csp(request: &Request) -> csp::CheckResult {
+pub fn should_request_be_blocked_by_csp(
+    request: &Request,
+) -> (csp::CheckResult, Vec<csp::Violation>) {
     let origin = match &request.origin {
-        Origin::Client => return csp::CheckResult::Allowed,
+        Origin::Client => return (csp::CheckResult::Allowed, Vec::new()),
         Origin::Origin(origin) => origin,
     };
     let csp_request = csp::Request {
@@ -160,17 +176,19 @@ pub fn should_request_be_blocked_by_csp(request: &Request) -> csp::CheckResult {
         origin: origin.clone().into_url_origin(),
         redirect_count: request.redirect_count,
         destination: request.destination,
-        initiator: csp::Initiator::None,
+        initiator: match request.initiator {
+            Initiator::Prefetch => csp::Initiator::Prefetch,
+            _ => csp::Initiator::None,
+        },
         nonce: String::new(),
         integrity_metadata: request.integrity_metadata.clone(),
         parser_metadata: csp::ParserMetadata::None,
     };
-    // TODO: Instead of ignoring violations, report them.
     request
         .csp_list
         .as_ref()
-        .map(|c| c.should_request_be_blocked(&csp_request).0)
-        .unwrap_or(csp::CheckResult::Allowed)
+        .map(|c| c.should_request_be_blocked(&csp_request))
+        .unwrap_or((csp::CheckResult::Allowed, Vec::new()))
 }
 
 /// [Main fetch](https://fetch.spec.whatwg.org/#concept-main-fetch)
@@ -186,6 +204,23 @@ pub fn main_fetch(
     // Step 1.
     let mut response = None;
 
+    // The response of a preload is kept for the request it is preloaded for, which waits
+    // for it if it is still being fetched. The preloads of a resource which is already
+    // being preloaded share its response.
+    let preload_key = if recursive_flag {
+        None
+    } else {
+        PreloadKey::new(request)
+    };
+    let preloading = matches!(
+        request.initiator,
+        Initiator::Preload | Initiator::SpeculativeParse
+    );
+    let pending_preload = match preload_key {
+        Some(ref key) if preloading => context.state.preload_cache.start(key.clone()),
+        _ => None,
+    };
+
     // Step 2.
     if request.local_urls_only {
         if !matches!(
@@ -199,10 +234,13 @@ pub fn main_fetch(
     }
 
     // Step 2.2.
-    // TODO: Report violations.
+    let (check_result, violations) = should_request_be_blocked_by_csp(request);
+    if !violations.is_empty() {
+        target.process_csp_violations(request, violations);
+    }
 
     // Step 2.4.
-    if should_request_be_blocked_by_csp(request) == csp::CheckResult::Blocked {
+    if check_result == csp::CheckResult::Blocked {
         response = Some(Response::network_error(NetworkError::Internal(
             "Blocked by Content-Security-Policy".into(),
         )))
@@ -223,6 +261,19 @@ pub fn main_fetch(
     // TODO: handle blocking as mixed content.
     // TODO: handle blocking by content security policy.
 
+    // Block or rewrite the subresource requests which match the filter lists.
+    if response.is_none() {
+        match context.content_blocker.classify_request(request) {
+            Classification::Allow => {},
+            Classification::Block => {
+                response = Some(Response::network_error(NetworkError::Internal(
+                    "Blocked by the content blocker".into(),
+                )));
+            },
+            Classification::Redirect(url) => *request.current_url_mut() = url,
+        }
+    }
+
     // Step 6
     // TODO: handle request's client's referrer policy.
 
@@ -271,6 +322,17 @@ pub fn main_fetch(
     // Step 11.
     // Not applicable: see fetch_async.
 
+    // https://html.spec.whatwg.org/multipage/#consume-a-preloaded-resource
+    if response.is_none() && pending_preload.is_none() && request.initiator != Initiator::Prefetch {
+        if let Some(ref key) = preload_key {
+            response = if preloading {
+                context.state.preload_cache.get(key)
+            } else {
+                context.state.preload_cache.consume(key)
+            };
+        }
+    }
+
     // Step 12.
     let mut response = response.unwrap_or_else(|| {
         let current_url = request.current_url();
@@ -294,6 +356,17 @@ pub fn main_fetch(
 
             // Substep 2.
             scheme_fetch(request, cache, target, done_chan, context)
+        } else if context.protocols.is_fetchable(current_url.scheme()) {
+            // Substep 1.
+            request.response_tainting = ResponseTainting::CorsTainting;
+
+            // Substep 2.
+            let response = scheme_fetch(request, cache, target, done_chan, context);
+            if response.is_network_error() || cors_check(request, &response).is_ok() {
+                response
+            } else {
+                Response::network_error(NetworkError::Internal("CORS check failed".into()))
+            }
         } else if !matches!(current_url.scheme(), "http" | "https") {
             Response::network_error(NetworkError::Internal("Non-http scheme".into()))
         } else if request.use_cors_preflight ||
@@ -440,11 +513,39 @@ pub fn main_fetch(
         response
     };
 
+    // The cross-origin resources which can't be used without CORS are blocked before
+    // anything reaches the target. Only the start of their bodies is waited for, and the
+    // payloads received meanwhile are handed over before the rest of the body.
+    let mut received = vec![];
+    if response.response_type == ResponseType::Opaque {
+        let block = match orb_decision(response.actual_response()) {
+            OrbDecision::Allow => false,
+            OrbDecision::Block => true,
+            OrbDecision::Sniff(protected_type) => {
+                received = wait_for_response_start(&mut response, done_chan, ORB_SNIFF_LENGTH);
+                match *response.actual_response().body.lock().unwrap() {
+                    ResponseBody::Receiving(ref body) | ResponseBody::Done(ref body) => {
+                        let start = &body[..body.len().min(ORB_SNIFF_LENGTH)];
+                        sniffs_as_protected_type(protected_type, start)
+                    },
+                    ResponseBody::Empty => false,
+                }
+            },
+        };
+        if block {
+            // The rest of the body is left to the fetch worker, which may share it with
+            // the HTTP cache.
+            *done_chan = None;
+            received.clear();
+            block_response(response.actual_response_mut());
+        }
+    }
+
     // Step 19.
     let mut response_loaded = false;
     let mut response = if !response.is_network_error() && !request.integrity_metadata.is_empty() {
         // Step 19.1.
-        wait_for_response(&mut response, target, done_chan);
+        wait_for_response(&mut response, target, done_chan, mem::take(&mut received));
         response_loaded = true;
 
         // Step 19.2.
@@ -468,7 +569,7 @@ pub fn main_fetch(
         // by sync fetch, but we overload it here for simplicity
         target.process_response(&mut response);
         if !response_loaded {
-            wait_for_response(&mut response, target, done_chan);
+            wait_for_response(&mut response, target, done_chan, received);
         }
         // overloaded similarly to process_response
         target.process_response_eof(&response);
@@ -476,7 +577,9 @@ pub fn main_fetch(
     }
 
     // Step 21.
-    if request.body.is_some() && matches!(request.current_url().scheme(), "http" | "https") {
+    if (request.body.is_some() || request.body_stream.is_some()) &&
+        matches!(request.current_url().scheme(), "http" | "https")
+    {
         // XXXManishearth: We actually should be calling process_request
         // in http_network_fetch. However, we can't yet follow the request
         // upload progress, so I'm keeping it here for now and pretending
@@ -490,14 +593,19 @@ pub fn main_fetch(
 
     // Step 23.
     if !response_loaded {
-        wait_for_response(&mut response, target, done_chan);
+        wait_for_response(&mut response, target, done_chan, received);
     }
 
     // Step 24.
     target.process_response_eof(&response);
 
-    if let Ok(http_cache) = context.state.http_cache.write() {
+    if let Some(preload) = pending_preload {
+        preload.finish(&response);
+    }
+
+    if let Ok(mut http_cache) = context.state.http_cache.write() {
         http_cache.update_awaiting_consumers(&request, &response);
+        http_cache.write_complete_resources(&request);
     }
 
     // Steps 25-27.
@@ -505,8 +613,16 @@ pub fn main_fetch(
     response
 }
 
-fn wait_for_response(response: &mut Response, target: Target, done_chan: &mut DoneChannel) {
+fn wait_for_response(
+    response: &mut Response,
+    target: Target,
+    done_chan: &mut DoneChannel,
+    received: Vec<u8>,
+) {
     if let Some(ref ch) = *done_chan {
+        if !received.is_empty() {
+            target.process_response_chunk(received);
+        }
         loop {
             match ch
                 .1
@@ -536,6 +652,40 @@ fn wait_for_response(response: &mut Response, target: Target, done_chan: &mut Do
     }
 }
 
+/// Wait for the first `length` bytes of the body of a response without handing them to
+/// the target, so that they can be inspected first, and return the payloads received
+/// meanwhile. The bodies which are received entirely are handed over at once afterwards,
+/// like the ones which are obtained synchronously.
+fn wait_for_response_start(
+    response: &mut Response,
+    done_chan: &mut DoneChannel,
+    length: usize,
+) -> Vec<u8> {
+    let mut received = vec![];
+    let mut finished = done_chan.is_none();
+    if let Some(ref ch) = *done_chan {
+        while !finished && received.len() < length {
+            match ch
+                .1
+                .recv()
+                .expect("fetch worker should always send Done before terminating")
+            {
+                Data::Payload(vec) => received.extend_from_slice(&vec),
+                Data::Done => finished = true,
+                Data::Cancelled => {
+                    response.aborted.store(true, Ordering::Release);
+                    finished = true;
+                },
+            }
+        }
+    }
+    if finished {
+        *done_chan = None;
+        received.clear();
+    }
+    received
+}
+
 /// Range header start and end values.
 pub enum RangeRequestBounds {
     /// The range bounds are known and set to final values.
@@ -626,6 +776,23 @@ fn scheme_fetch(
             response
         },
 
+        "about" if request.destination == Destination::Document => {
+            match context.about_pages.load(&url) {
+                Some(page) => {
+                    let mut response =
+                        Response::new(url, ResourceFetchTiming::new(request.timing_type()));
+                    response
+                        .headers
+                        .typed_insert(ContentType::from(mime::TEXT_HTML_UTF_8));
+                    *response.body.lock().unwrap() = ResponseBody::Done(page.into_bytes());
+                    response.status = Some((StatusCode::OK, "OK".to_string()));
+                    response.raw_status = Some((StatusCode::OK.as_u16(), b"OK".to_vec()));
+                    response
+                },
+                None => Response::network_error(NetworkError::Internal("Unexpected scheme".into())),
+            }
+        },
+
         "http" | "https" => http_fetch(
             request, cache, false, false, false, target, done_chan, context,
         ),
@@ -786,10 +953,58 @@ fn scheme_fetch(
             Response::network_error(NetworkError::Internal("Unexpected scheme".into()))
         },
 
-        _ => Response::network_error(NetworkError::Internal("Unexpected scheme".into())),
+        scheme => match context.protocols.get(scheme) {
+            Some(handler) => protocol_fetch(handler, request, done_chan, context),
+            None => Response::network_error(NetworkError::Internal("Unexpected scheme".into())),
+        },
     }
 }
 
+/// Fetch a resource of a custom scheme from the handler which the embedder registered.
+fn protocol_fetch(
+    handler: &dyn ProtocolHandler,
+    request: &Request,
+    done_chan: &mut DoneChannel,
+    context: &FetchContext,
+) -> Response {
+    let protocol_response = match handler.load(request) {
+        Ok(protocol_response) => protocol_response,
+        Err(error) => return Response::network_error(NetworkError::Internal(error)),
+    };
+
+    let url = request.current_url();
+    let mut response = Response::new(url, ResourceFetchTiming::new(request.timing_type()));
+    let status = protocol_response.status;
+    let reason = status.canonical_reason().unwrap_or("");
+    response.status = Some((status, reason.to_string()));
+    response.raw_status = Some((status.as_u16(), reason.as_bytes().to_vec()));
+    response.headers = protocol_response.headers;
+    if handler.is_secure() {
+        response.https_state = HttpsState::Modern;
+    }
+
+    match protocol_response.body {
+        ProtocolBody::Bytes(bytes) => {
+            *response.body.lock().unwrap() = ResponseBody::Done(bytes);
+        },
+        ProtocolBody::Stream(reader) => {
+            let (done_sender, done_receiver) = unbounded();
+            *done_chan = Some((done_sender.clone(), done_receiver));
+            *response.body.lock().unwrap() = ResponseBody::Receiving(vec![]);
+
+            context.filemanager.fetch_file_in_chunks(
+                done_sender,
+                BufReader::with_capacity(FILE_CHUNK_SIZE, reader),
+                response.body.clone(),
+                context.cancellation_listener.clone(),
+                RelativePos::full_range(),
+            );
+        },
+    }
+
+    response
+}
+
 fn is_null_body_status(status: &Option<(StatusCode, String)>) -> bool {
     match *status {
         Some((status, _)) => match status {
@@ -0,0 +1,194 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Opaque Response Blocking, which keeps the cross-origin HTML, XML and JSON resources
+//! fetched without CORS out of the content processes, since they can't be used as the
+//! images, scripts, stylesheets or media which documents fetch that way. The decisions
+//! follow the ones of Cross-Origin Read Blocking: a response labelled with such a MIME
+//! type is blocked if it is `nosniff`, a partial response, or if its body sniffs as
+//! that type, which spares the scripts and images served with a wrong MIME type.
+//!
+//! <https://github.com/annevk/orb>
+//! <https://chromium.googlesource.com/chromium/src/+/master/services/network/cross_origin_read_blocking_explainer.md>
+
+use headers::{ContentType, HeaderMapExt};
+use hyper::StatusCode;
+use mime::{self, Mime};
+use net_traits::response::{Response, ResponseBody};
+use std::sync::{Arc, Mutex};
+
+/// The number of bytes at the start of a body which are sniffed.
+pub const ORB_SNIFF_LENGTH: usize = 1024;
+
+/// The kinds of resources which are protected from cross-origin no-cors requests.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProtectedType {
+    Html,
+    Xml,
+    Json,
+    /// `text/plain`, which may be any of the others.
+    Plain,
+}
+
+/// What to do with an opaque response.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrbDecision {
+    Allow,
+    Block,
+    /// Block it if its body sniffs as the given type.
+    Sniff(ProtectedType),
+}
+
+fn protected_type(mime: &Mime) -> Option<ProtectedType> {
+    let suffix = mime.suffix().map(|name| name.as_str());
+    match (mime.type_(), mime.subtype().as_str(), suffix) {
+        (mime::TEXT, "html", _) => Some(ProtectedType::Html),
+        (mime::IMAGE, "svg", Some("xml")) => None,
+        (mime::TEXT, "xml", _) | (mime::APPLICATION, "xml", _) | (_, _, Some("xml")) => {
+            Some(ProtectedType::Xml)
+        },
+        (mime::TEXT, "json", _) | (mime::APPLICATION, "json", _) | (_, _, Some("json")) => {
+            Some(ProtectedType::Json)
+        },
+        (mime::TEXT, "plain", _) => Some(ProtectedType::Plain),
+        _ => None,
+    }
+}
+
+/// Decide whether the internal response of an opaque filtered response is blocked.
+pub fn orb_decision(response: &Response) -> OrbDecision {
+    let mime: Mime = match response.headers.typed_get::<ContentType>() {
+        Some(content_type) => content_type.into(),
+        None => return OrbDecision::Allow,
+    };
+    let protected_type = match protected_type(&mime) {
+        Some(protected_type) => protected_type,
+        None => return OrbDecision::Allow,
+    };
+    let nosniff = response
+        .headers
+        .get("x-content-type-options")
+        .map_or(false, |value| {
+            value
+                .to_str()
+                .unwrap_or("")
+                .trim()
+                .eq_ignore_ascii_case("nosniff")
+        });
+    let partial = response
+        .status
+        .as_ref()
+        .map_or(false, |(status, _)| *status == StatusCode::PARTIAL_CONTENT);
+    if partial && protected_type != ProtectedType::Plain {
+        return OrbDecision::Block;
+    }
+    if nosniff {
+        return OrbDecision::Block;
+    }
+    OrbDecision::Sniff(protected_type)
+}
+
+/// Whether the start of a body confirms the type it is labelled with.
+pub fn sniffs_as_protected_type(protected_type: ProtectedType, body: &[u8]) -> bool {
+    match protected_type {
+        ProtectedType::Html => sniffs_as_html(body),
+        ProtectedType::Xml => sniffs_as_xml(body),
+        ProtectedType::Json => sniffs_as_json(body) || has_json_security_prefix(body),
+        ProtectedType::Plain => {
+            sniffs_as_html(body) ||
+                sniffs_as_xml(body) ||
+                sniffs_as_json(body) ||
+                has_json_security_prefix(body)
+        },
+    }
+}
+
+/// Replace the body and headers of a response with empty ones. The body is replaced
+/// rather than emptied, since it may be shared with the HTTP cache.
+pub fn block_response(response: &mut Response) {
+    debug!("Blocking the opaque response from {:?}", response.url());
+    response.headers.clear();
+    response.body = Arc::new(Mutex::new(ResponseBody::Empty));
+}
+
+fn skip_whitespace(body: &[u8]) -> &[u8] {
+    let start = body
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(body.len());
+    &body[start..]
+}
+
+fn starts_with_ignore_case(body: &[u8], prefix: &[u8]) -> bool {
+    body.len() >= prefix.len() && body[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn sniffs_as_html(body: &[u8]) -> bool {
+    const TAGS: &[&[u8]] = &[
+        b"<!doctype html",
+        b"<html",
+        b"<head",
+        b"<body",
+        b"<script",
+        b"<iframe",
+        b"<h1",
+        b"<div",
+        b"<font",
+        b"<table",
+        b"<a",
+        b"<style",
+        b"<title",
+        b"<b",
+        b"<br",
+        b"<p",
+    ];
+    let mut body = skip_whitespace(body);
+    // The comments are skipped, since any resource may start with them.
+    while body.starts_with(b"<!--") {
+        match body.windows(3).position(|window| window == b"-->") {
+            Some(end) => body = skip_whitespace(&body[end + 3..]),
+            None => return false,
+        }
+    }
+    TAGS.iter().any(|tag| {
+        starts_with_ignore_case(body, tag) &&
+            body.get(tag.len())
+                .map_or(false, |byte| *byte == b' ' || *byte == b'>')
+    })
+}
+
+fn sniffs_as_xml(body: &[u8]) -> bool {
+    starts_with_ignore_case(skip_whitespace(body), b"<?xml")
+}
+
+/// Whether a body starts like a JSON object, which is a syntax error as a script.
+fn sniffs_as_json(body: &[u8]) -> bool {
+    let body = skip_whitespace(body);
+    if !body.starts_with(b"{") {
+        return false;
+    }
+    let body = skip_whitespace(&body[1..]);
+    if !body.starts_with(b"\"") {
+        return false;
+    }
+    // The key is followed by a colon.
+    let mut escaped = false;
+    for (index, byte) in body.iter().enumerate().skip(1) {
+        match *byte {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return skip_whitespace(&body[index + 1..]).starts_with(b":"),
+            b'\n' | b'\r' => return false,
+            _ => {},
+        }
+    }
+    false
+}
+
+/// The prefixes with which JSON responses prevent their execution as scripts.
+fn has_json_security_prefix(body: &[u8]) -> bool {
+    const PREFIXES: &[&[u8]] = &[b")]}'", b"{}&&", b"{} &&", b"for(;;);", b"while(1);"];
+    let body = skip_whitespace(body);
+    PREFIXES.iter().any(|prefix| body.starts_with(prefix))
+}
@@ -96,6 +96,7 @@ impl FileManager {
         &self,
         sender: IpcSender<FileManagerResult<ReadFileProgress>>,
         id: Uuid,
+        rel_pos: RelativePos,
         origin: FileOrigin,
     ) {
         let store = self.store.clone();
@@ -103,7 +104,7 @@ impl FileManager {
             .upgrade()
             .and_then(|pool| {
                 pool.spawn(move || {
-                    if let Err(e) = store.try_read_file(&sender, id, origin) {
+                    if let Err(e) = store.try_read_file(&sender, id, rel_pos, origin) {
                         let _ = sender.send(Err(FileManagerThreadError::BlobURLStoreError(e)));
                     }
                 });
@@ -187,8 +188,8 @@ impl FileManager {
                         );
                     });
             },
-            FileManagerThreadMsg::ReadFile(sender, id, origin) => {
-                self.read_file(sender, id, origin);
+            FileManagerThreadMsg::ReadFile(sender, id, rel_pos, origin) => {
+                self.read_file(sender, id, rel_pos, origin);
             },
             FileManagerThreadMsg::PromoteMemory(id, blob_buf, set_valid, origin) => {
                 self.promote_memory(id, blob_buf, set_valid, origin);
@@ -208,10 +209,10 @@ impl FileManager {
         }
     }
 
-    pub fn fetch_file_in_chunks(
+    pub fn fetch_file_in_chunks<R: BufRead + Send + 'static>(
         &self,
         done_sender: Sender<Data>,
-        mut reader: BufReader<File>,
+        mut reader: R,
         res_body: ServoArc<Mutex<ResponseBody>>,
         cancellation_listener: Arc<Mutex<CancellationListener>>,
         range: RelativePos,
@@ -769,6 +770,7 @@ impl FileManagerStore {
         &self,
         sender: &IpcSender<FileManagerResult<ReadFileProgress>>,
         id: Uuid,
+        rel_pos: RelativePos,
         origin_in: FileOrigin,
     ) -> Result<(), BlobURLStoreError> {
         self.get_blob_buf(
@@ -776,7 +778,7 @@ impl FileManagerStore {
             &id,
             &FileTokenCheck::NotRequired,
             &origin_in,
-            RelativePos::full_range(),
+            rel_pos,
         )
     }
 
@@ -903,6 +905,9 @@ fn read_file_in_chunks(
     opt_filename: Option<String>,
     type_string: String,
 ) {
+    // Only read the `size` bytes of the range being read.
+    let mut file = file.take(size as u64);
+
     // First chunk
     let mut buf = vec![0; FILE_CHUNK_SIZE];
     match file.read(&mut buf) {
@@ -914,7 +919,10 @@ fn read_file_in_chunks(
                 size: size as u64,
                 bytes: buf,
             };
-            let _ = sender.send(Ok(ReadFileProgress::Meta(blob_buf)));
+            // Stop reading if the reader went away.
+            if sender.send(Ok(ReadFileProgress::Meta(blob_buf))).is_err() {
+                return;
+            }
         },
         Err(e) => {
             let _ = sender.send(Err(FileManagerThreadError::FileSystemError(e.to_string())));
@@ -932,7 +940,9 @@ fn read_file_in_chunks(
             },
             Ok(n) => {
                 buf.truncate(n);
-                let _ = sender.send(Ok(ReadFileProgress::Partial(buf)));
+                if sender.send(Ok(ReadFileProgress::Partial(buf))).is_err() {
+                    return;
+                }
             },
             Err(e) => {
                 let _ = sender.send(Err(FileManagerThreadError::FileSystemError(e.to_string())));
@@ -5,14 +5,16 @@
 #![deny(missing_docs)]
 
 //! A memory cache implementing the logic specified in <http://tools.ietf.org/html/rfc7234>
-//! and <http://tools.ietf.org/html/rfc7232>.
+//! and <http://tools.ietf.org/html/rfc7232>, whose complete resources can be written to disk
+//! as soon as they are stored, and whose bodies are read back from disk when they are used.
 
 use crate::fetch::methods::{Data, DoneChannel};
+use crate::resource_thread::{read_json_from_file, write_json_to_file};
 use crossbeam_channel::{unbounded, Sender};
 use headers::{
     CacheControl, ContentRange, Expires, HeaderMapExt, LastModified, Pragma, Range, Vary,
 };
-use http::header::HeaderValue;
+use http::header::{HeaderName, HeaderValue};
 use http::{header, HeaderMap};
 use hyper::{Method, StatusCode};
 use malloc_size_of::Measurable;
@@ -25,7 +27,9 @@ use net_traits::{FetchMetadata, Metadata, ResourceFetchTiming};
 use servo_arc::Arc;
 use servo_url::ServoUrl;
 use std::collections::HashMap;
+use std::fs;
 use std::ops::Bound;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 use std::time::SystemTime;
@@ -60,6 +64,15 @@ struct CachedResource {
     aborted: Arc<AtomicBool>,
     awaiting_body: Arc<Mutex<Vec<Sender<Data>>>>,
     data: Measurable<MeasurableCachedResource>,
+    /// The file of the disk cache the body is written to, if any.
+    body_file: Option<BodyFile>,
+}
+
+/// The file of the disk cache a body is written to.
+#[derive(Clone)]
+struct BodyFile {
+    name: String,
+    size: usize,
 }
 
 #[derive(Clone, MallocSizeOf)]
@@ -113,6 +126,77 @@ impl MallocSizeOf for CachedMetadata {
     }
 }
 
+/// A complete cached resource as it is written to disk, whose body is kept in a file of
+/// its own next to the index of the resources.
+#[derive(Deserialize, Serialize)]
+struct StoredResource {
+    url: ServoUrl,
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    request_headers: HeaderMap,
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    headers: HeaderMap,
+    final_url: ServoUrl,
+    content_type: Option<String>,
+    charset: Option<String>,
+    metadata_status: Option<(u16, Vec<u8>)>,
+    location_url: Option<Result<ServoUrl, String>>,
+    https_state: HttpsState,
+    status: Option<(u16, String)>,
+    raw_status: Option<(u16, Vec<u8>)>,
+    url_list: Vec<ServoUrl>,
+    /// The freshness lifetime, in seconds.
+    expires: i64,
+    /// The time of the last validation, in seconds since the epoch.
+    last_validated: i64,
+    body_file: String,
+    /// The size of the body, in bytes.
+    #[serde(default)]
+    body_size: usize,
+}
+
+/// The file listing the resources written to the disk cache directory.
+const DISK_CACHE_INDEX: &str = "index.json";
+
+/// The request headers which carry credentials, which are never written to disk. The
+/// resources whose responses vary on them are not written to disk either.
+const CREDENTIAL_HEADERS: [HeaderName; 3] = [
+    header::AUTHORIZATION,
+    header::COOKIE,
+    header::PROXY_AUTHORIZATION,
+];
+
+/// The headers of the original request of a cached resource that are written to disk,
+/// which are only those its Vary header nominates, since they are only used to match
+/// later requests. There are none if the resource must not be written to disk.
+fn stored_request_headers(resource: &CachedResource) -> Option<HeaderMap> {
+    let mut stored_headers = HeaderMap::new();
+    let response_headers = resource.data.metadata.headers.lock().unwrap();
+    let vary_value = match response_headers.typed_get::<Vary>() {
+        Some(vary_value) => vary_value,
+        None => return Some(stored_headers),
+    };
+    if vary_value.is_any() {
+        return None;
+    }
+    let request_headers = resource.request_headers.lock().unwrap();
+    for name in vary_value.iter_strs() {
+        let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+        if CREDENTIAL_HEADERS.contains(&name) {
+            return None;
+        }
+        for value in request_headers.get_all(&name) {
+            stored_headers.append(name.clone(), value.clone());
+        }
+    }
+    Some(stored_headers)
+}
+
 /// Wrapper around a cached response, including information on re-validation needs
 pub struct CachedResponse {
     /// The response constructed from the cached resource
@@ -126,6 +210,34 @@ pub struct CachedResponse {
 pub struct HttpCache {
     /// cached responses.
     entries: HashMap<CacheKey, Vec<CachedResource>>,
+    /// The directory the complete resources are written to, if any.
+    #[ignore_malloc_size_of = "Defined in std"]
+    disk_cache: Option<PathBuf>,
+}
+
+/// Read the body of a resource from the disk cache the first time it is used, after
+/// it was read back from disk without its body. A body which can't be read aborts the
+/// resource, which isn't used anymore.
+fn load_body(disk_cache: Option<&PathBuf>, resource: &CachedResource) -> bool {
+    let (directory, body_file) = match (disk_cache, resource.body_file.as_ref()) {
+        (Some(directory), Some(body_file)) => (directory, body_file),
+        _ => return true,
+    };
+    let mut body = resource.body.lock().unwrap();
+    if *body != ResponseBody::Empty {
+        return true;
+    }
+    match fs::read(directory.join(&body_file.name)) {
+        Ok(bytes) => {
+            *body = ResponseBody::Done(bytes);
+            true
+        },
+        Err(why) => {
+            warn!("couldn't read the cached body {}: {}", body_file.name, why);
+            resource.aborted.store(true, Ordering::Release);
+            false
+        },
+    }
 }
 
 /// Determine if a response is cacheable by default <https://tools.ietf.org/html/rfc7231#section-6.1>
@@ -267,16 +379,44 @@ fn get_response_expiry(response: &Response) -> Duration {
     Duration::seconds(0i64)
 }
 
+/// Whether the headers of a request match those of the original request of a cached
+/// resource, for every header field which the Vary header of its response nominates.
+/// <https://tools.ietf.org/html/rfc7234#section-4.1>
+fn vary_headers_match(request_headers: &HeaderMap, cached_resource: &CachedResource) -> bool {
+    let cached_headers = cached_resource.data.metadata.headers.lock().unwrap();
+    let vary_value = match cached_headers.typed_get::<Vary>() {
+        Some(vary_value) => vary_value,
+        None => return true,
+    };
+    if vary_value.is_any() {
+        return false;
+    }
+    // A header field absent from a request only matches if it was also absent from the
+    // original request.
+    let original_request_headers = cached_resource.request_headers.lock().unwrap();
+    vary_value
+        .iter_strs()
+        .all(|name| request_headers.get(name) == original_request_headers.get(name))
+}
+
 /// Request Cache-Control Directives
 /// <https://tools.ietf.org/html/rfc7234#section-5.2.1>
-fn get_expiry_adjustment_from_request_headers(request: &Request, expires: Duration) -> Duration {
+fn get_expiry_adjustment_from_request_headers(
+    request: &Request,
+    expires: Duration,
+    must_revalidate: bool,
+) -> Duration {
     let directive = match request.headers.typed_get::<CacheControl>() {
         Some(data) => data,
         None => return expires,
     };
 
     if let Some(max_age) = directive.max_stale() {
-        return expires + Duration::from_std(max_age).unwrap();
+        // A stale response which must be revalidated is never used,
+        // <https://tools.ietf.org/html/rfc7234#section-5.2.2.1>
+        if !must_revalidate {
+            return expires + Duration::from_std(max_age).unwrap();
+        }
     }
     if let Some(max_age) = directive.max_age() {
         let max_age = Duration::from_std(max_age).unwrap();
@@ -336,11 +476,14 @@ fn create_cached_response(
     response.referrer_policy = request.referrer_policy.clone();
     response.aborted = cached_resource.aborted.clone();
     let expires = cached_resource.data.expires;
-    let adjusted_expires = get_expiry_adjustment_from_request_headers(request, expires);
+    let must_revalidate = cached_headers
+        .typed_get::<CacheControl>()
+        .map_or(false, |directives| directives.must_revalidate());
+    let adjusted_expires =
+        get_expiry_adjustment_from_request_headers(request, expires, must_revalidate);
     let now = Duration::seconds(time::now().to_timespec().sec);
     let last_validated = Duration::seconds(cached_resource.data.last_validated.to_timespec().sec);
     let time_since_validated = now - last_validated;
-    // TODO: take must-revalidate into account <https://tools.ietf.org/html/rfc7234#section-5.2.2.1>
     // TODO: if this cache is to be considered shared, take proxy-revalidate into account
     // <https://tools.ietf.org/html/rfc7234#section-5.2.2.7>
     let has_expired =
@@ -373,6 +516,7 @@ fn create_resource_with_bytes_from_resource(
             expires: resource.data.expires.clone(),
             last_validated: resource.data.last_validated.clone(),
         }),
+        body_file: None,
     }
 }
 
@@ -582,12 +726,208 @@ fn handle_range_request(
     None
 }
 
+/// Remove the body of a resource from the disk cache.
+fn remove_body_file(directory: &Path, resource: &mut CachedResource) {
+    if let Some(body_file) = resource.body_file.take() {
+        if let Err(why) = fs::remove_file(directory.join(&body_file.name)) {
+            warn!("couldn't remove the cached body {}: {}", body_file.name, why);
+        }
+    }
+}
+
 impl HttpCache {
     /// Create a new memory cache instance.
     pub fn new() -> HttpCache {
         HttpCache {
             entries: HashMap::new(),
+            disk_cache: None,
+        }
+    }
+
+    /// Create a memory cache which writes its complete resources to a directory, with
+    /// the resources written there by a previous cache, whose bodies are only read when
+    /// they are used.
+    pub fn with_disk_cache(directory: &Path) -> HttpCache {
+        let mut cache = HttpCache::new();
+        if let Err(why) = fs::create_dir_all(directory) {
+            warn!("couldn't create {}: {}", directory.display(), why);
+            return cache;
+        }
+        let mut stored_resources: Vec<StoredResource> = vec![];
+        read_json_from_file(&mut stored_resources, directory, DISK_CACHE_INDEX);
+        for stored in stored_resources {
+            let status = match stored.status {
+                Some((code, reason)) => match StatusCode::from_u16(code) {
+                    Ok(code) => Some((code, reason)),
+                    Err(_) => continue,
+                },
+                None => None,
+            };
+            let resource = CachedResource {
+                request_headers: Arc::new(Mutex::new(stored.request_headers)),
+                body: Arc::new(Mutex::new(ResponseBody::Empty)),
+                aborted: Arc::new(AtomicBool::new(false)),
+                awaiting_body: Arc::new(Mutex::new(vec![])),
+                data: Measurable(MeasurableCachedResource {
+                    metadata: CachedMetadata {
+                        headers: Arc::new(Mutex::new(stored.headers)),
+                        data: Measurable(MeasurableCachedMetadata {
+                            final_url: stored.final_url,
+                            content_type: stored.content_type,
+                            charset: stored.charset,
+                            status: stored.metadata_status,
+                        }),
+                    },
+                    location_url: stored.location_url,
+                    https_state: stored.https_state,
+                    status,
+                    raw_status: stored.raw_status,
+                    url_list: stored.url_list,
+                    expires: Duration::seconds(stored.expires),
+                    last_validated: time::at(Timespec::new(stored.last_validated, 0)),
+                }),
+                body_file: Some(BodyFile {
+                    name: stored.body_file,
+                    size: stored.body_size,
+                }),
+            };
+            cache
+                .entries
+                .entry(CacheKey::from_servo_url(&stored.url))
+                .or_insert_with(|| vec![])
+                .push(resource);
+        }
+        cache.disk_cache = Some(directory.to_owned());
+        cache
+    }
+
+    /// Write the resources of an entry whose body is complete to the disk cache, then
+    /// remove the least recently validated resources from the disk cache until the size
+    /// of their bodies fits in its capacity.
+    fn write_to_disk(&mut self, key: &CacheKey) {
+        let directory = match self.disk_cache {
+            Some(ref directory) => directory,
+            None => return,
+        };
+        let mut written = false;
+        for resource in self.entries.get_mut(key).into_iter().flatten() {
+            if resource.body_file.is_some() ||
+                resource.aborted.load(Ordering::Acquire) ||
+                stored_request_headers(resource).is_none()
+            {
+                continue;
+            }
+            let name = (0..)
+                .map(|index| format!("{}.body", index))
+                .find(|name| !directory.join(name).exists())
+                .unwrap();
+            let written = match *resource.body.lock().unwrap() {
+                ResponseBody::Done(ref body) => {
+                    fs::write(directory.join(&name), body).map(|()| body.len())
+                },
+                ResponseBody::Empty | ResponseBody::Receiving(_) => continue,
+            };
+            match written {
+                Ok(size) => {
+                    resource.body_file = Some(BodyFile { name, size });
+                    written = true;
+                },
+                Err(why) => warn!("couldn't write the cached body of {}: {}", key.url, why),
+            }
+        }
+        if !written {
+            return;
+        }
+
+        let mut stored = vec![];
+        for (key, resources) in &self.entries {
+            for (index, resource) in resources.iter().enumerate() {
+                if let Some(ref body_file) = resource.body_file {
+                    let last_validated = resource.data.last_validated.to_timespec();
+                    stored.push((last_validated, body_file.size, key.clone(), index));
+                }
+            }
+        }
+        stored.sort_by_key(|(last_validated, ..)| std::cmp::Reverse(*last_validated));
+        let mut remaining_capacity = pref!(network.http_cache.disk_capacity).max(0) as usize;
+        let mut evicted = vec![];
+        for (_, size, key, index) in stored {
+            if size <= remaining_capacity {
+                remaining_capacity -= size;
+                continue;
+            }
+            let resources = match self.entries.get_mut(&key) {
+                Some(resources) => resources,
+                None => continue,
+            };
+            remove_body_file(directory, &mut resources[index]);
+            // The resources whose body was only on disk are gone.
+            if *resources[index].body.lock().unwrap() == ResponseBody::Empty {
+                evicted.push((key, index));
+            }
         }
+        evicted.sort_by_key(|(_, index)| std::cmp::Reverse(*index));
+        for (key, index) in evicted {
+            if let Some(resources) = self.entries.get_mut(&key) {
+                resources.remove(index);
+            }
+        }
+        self.entries.retain(|_, resources| !resources.is_empty());
+        self.write_disk_cache_index();
+    }
+
+    /// Write the index of the resources of the disk cache.
+    fn write_disk_cache_index(&self) {
+        let directory = match self.disk_cache {
+            Some(ref directory) => directory,
+            None => return,
+        };
+        let mut stored_resources = vec![];
+        for (key, resources) in &self.entries {
+            for resource in resources {
+                let body_file = match resource.body_file {
+                    Some(ref body_file) => body_file,
+                    None => continue,
+                };
+                let request_headers = match stored_request_headers(resource) {
+                    Some(request_headers) => request_headers,
+                    None => continue,
+                };
+                // The cookies which a response sets are not replayed from the disk cache.
+                let mut headers = resource.data.metadata.headers.lock().unwrap().clone();
+                headers.remove(header::SET_COOKIE);
+                let metadata = &resource.data.metadata;
+                stored_resources.push(StoredResource {
+                    url: key.url.clone(),
+                    request_headers,
+                    headers,
+                    final_url: metadata.data.final_url.clone(),
+                    content_type: metadata.data.content_type.clone(),
+                    charset: metadata.data.charset.clone(),
+                    metadata_status: metadata.data.status.clone(),
+                    location_url: resource.data.location_url.clone(),
+                    https_state: resource.data.https_state,
+                    status: resource
+                        .data
+                        .status
+                        .as_ref()
+                        .map(|(code, reason)| (code.as_u16(), reason.clone())),
+                    raw_status: resource.data.raw_status.clone(),
+                    url_list: resource.data.url_list.clone(),
+                    expires: resource.data.expires.num_seconds(),
+                    last_validated: resource.data.last_validated.to_timespec().sec,
+                    body_file: body_file.name.clone(),
+                    body_size: body_file.size,
+                });
+            }
+        }
+        write_json_to_file(&stored_resources, directory, DISK_CACHE_INDEX);
+    }
+
+    /// Write the resources stored for a request to the disk cache, once their body is
+    /// complete.
+    pub fn write_complete_resources(&mut self, request: &Request) {
+        self.write_to_disk(&CacheKey::new(request));
     }
 
     /// Constructing Responses from Caches.
@@ -605,6 +945,7 @@ impl HttpCache {
             return None;
         }
         let entry_key = CacheKey::new(&request);
+        let disk_cache = self.disk_cache.as_ref();
         let resources = self
             .entries
             .get(&entry_key)?
@@ -612,51 +953,14 @@ impl HttpCache {
             .filter(|r| !r.aborted.load(Ordering::Relaxed));
         let mut candidates = vec![];
         for cached_resource in resources {
-            let mut can_be_constructed = true;
-            let cached_headers = cached_resource.data.metadata.headers.lock().unwrap();
-            let original_request_headers = cached_resource.request_headers.lock().unwrap();
-            if let Some(vary_value) = cached_headers.typed_get::<Vary>() {
-                if vary_value.is_any() {
-                    debug!("vary value is any, not caching");
-                    can_be_constructed = false
-                } else {
-                    // For every header name found in the Vary header of the stored response.
-                    // Calculating Secondary Keys with Vary <https://tools.ietf.org/html/rfc7234#section-4.1>
-                    for vary_val in vary_value.iter_strs() {
-                        match request.headers.get(vary_val) {
-                            Some(header_data) => {
-                                // If the header is present in the request.
-                                if let Some(original_header_data) =
-                                    original_request_headers.get(vary_val)
-                                {
-                                    // Check that the value of the nominated header field,
-                                    // in the original request, matches the value in the current request.
-                                    if original_header_data != header_data {
-                                        debug!("headers don't match, not caching");
-                                        can_be_constructed = false;
-                                        break;
-                                    }
-                                }
-                            },
-                            None => {
-                                // If a header field is absent from a request,
-                                // it can only match a stored response if those headers,
-                                // were also absent in the original request.
-                                can_be_constructed =
-                                    original_request_headers.get(vary_val).is_none();
-                                if !can_be_constructed {
-                                    debug!("vary header present, not caching");
-                                }
-                            },
-                        }
-                        if !can_be_constructed {
-                            break;
-                        }
-                    }
+            // Calculating Secondary Keys with Vary <https://tools.ietf.org/html/rfc7234#section-4.1>
+            if vary_headers_match(&request.headers, cached_resource) {
+                if !load_body(disk_cache, cached_resource) {
+                    continue;
                 }
-            }
-            if can_be_constructed {
                 candidates.push(cached_resource);
+            } else {
+                debug!("vary headers don't match, not caching");
             }
         }
         // Support for range requests
@@ -759,8 +1063,12 @@ impl HttpCache {
     ) -> Option<Response> {
         assert_eq!(response.status.map(|s| s.0), Some(StatusCode::NOT_MODIFIED));
         let entry_key = CacheKey::new(&request);
+        let disk_cache = self.disk_cache.as_ref();
         if let Some(cached_resources) = self.entries.get_mut(&entry_key) {
             for cached_resource in cached_resources.iter_mut() {
+                if !load_body(disk_cache, cached_resource) {
+                    continue;
+                }
                 // done_chan will have been set to Some(..) by http_network_fetch.
                 // If the body is not receiving data, set the done_chan back to None.
                 // Otherwise, create a new dedicated channel to update the consumer.
@@ -795,10 +1103,17 @@ impl HttpCache {
                 constructed_response.referrer_policy = request.referrer_policy.clone();
                 constructed_response.raw_status = cached_resource.data.raw_status.clone();
                 constructed_response.url_list = cached_resource.data.url_list.clone();
+                {
+                    let mut stored_headers = cached_resource.data.metadata.headers.lock().unwrap();
+                    stored_headers.extend(response.headers);
+                    constructed_response.headers = stored_headers.clone();
+                }
+                // The freshness lifetime is that of the updated headers, from now on.
                 cached_resource.data.expires = get_response_expiry(&constructed_response);
-                let mut stored_headers = cached_resource.data.metadata.headers.lock().unwrap();
-                stored_headers.extend(response.headers);
-                constructed_response.headers = stored_headers.clone();
+                cached_resource.data.last_validated = time::now();
+                if cached_resource.body_file.is_some() {
+                    self.write_disk_cache_index();
+                }
                 return Some(constructed_response);
             }
         }
@@ -811,6 +1126,7 @@ impl HttpCache {
             for cached_resource in cached_resources.iter_mut() {
                 cached_resource.data.expires = Duration::seconds(0i64);
             }
+            self.write_disk_cache_index();
         }
     }
 
@@ -870,6 +1186,14 @@ impl HttpCache {
         if !response_is_cacheable(&metadata) {
             return;
         }
+        if response
+            .headers
+            .typed_get::<Vary>()
+            .map_or(false, |vary_value| vary_value.is_any())
+        {
+            // A response varying on everything never matches a request.
+            return;
+        }
         let expiry = get_response_expiry(&response);
         let cacheable_metadata = CachedMetadata {
             headers: Arc::new(Mutex::new(response.headers.clone())),
@@ -895,9 +1219,35 @@ impl HttpCache {
                 expires: expiry,
                 last_validated: time::now(),
             }),
+            body_file: None,
         };
-        let entry = self.entries.entry(entry_key).or_insert_with(|| vec![]);
-        entry.push(entry_resource);
+        let entry = self
+            .entries
+            .entry(entry_key.clone())
+            .or_insert_with(|| vec![]);
+        // The new response replaces the complete ones selected by the same request.
+        let (replaced, mut kept): (Vec<_>, Vec<_>) = entry.drain(..).partition(|resource| {
+            resource.data.raw_status == response.raw_status &&
+                response
+                    .raw_status
+                    .as_ref()
+                    .map_or(false, |(code, _)| *code != 206) &&
+                (resource.body_file.is_some() ||
+                    matches!(*resource.body.lock().unwrap(), ResponseBody::Done(_))) &&
+                vary_headers_match(&request.headers, resource)
+        });
+        kept.push(entry_resource);
+        *entry = kept;
+        if let Some(ref directory) = self.disk_cache {
+            let replaced_on_disk = replaced.iter().any(|resource| resource.body_file.is_some());
+            for mut resource in replaced {
+                remove_body_file(directory, &mut resource);
+            }
+            if replaced_on_disk {
+                self.write_disk_cache_index();
+            }
+        }
+        self.write_to_disk(&entry_key);
         // TODO: Complete incomplete responses, including 206 response, when stored here.
         // See A cache MAY complete a stored incomplete response by making a subsequent range request
         // https://tools.ietf.org/html/rfc7234#section-3.1
@@ -2,20 +2,27 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::connector::{create_http_client, Connector, TlsConfig};
-use crate::cookie;
+use crate::client_hints::AcceptChCache;
+use crate::connector::{create_http_client, proxy_authorization_for_host, Connector, TlsConfig};
+use crate::cookie::{self, SameSiteContext};
 use crate::cookie_storage::CookieStorage;
 use crate::decoder::Decoder;
 use crate::fetch::cors_cache::CorsCache;
 use crate::fetch::methods::{main_fetch, Data, DoneChannel, FetchContext, Target};
 use crate::hsts::HstsList;
 use crate::http_cache::{CacheKey, HttpCache};
-use crate::resource_thread::AuthCache;
+use crate::preload_cache::PreloadCache;
+use crate::resource_thread::{AuthCache, AuthCacheEntry};
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::{
     ChromeToDevtoolsControlMsg, DevtoolsControlMsg, HttpRequest as DevtoolsHttpRequest,
 };
-use devtools_traits::{HttpResponse as DevtoolsHttpResponse, NetworkEvent};
+use devtools_traits::{
+    HttpResponse as DevtoolsHttpResponse, NetworkEvent, RESPONSE_BODY_PREVIEW_LIMIT,
+};
+use embedder_traits::{PromptMsg, PromptRequest, PromptResponse};
+use futures::sync::mpsc::UnboundedReceiver;
+use futures::{Async, Poll};
 use headers::authorization::Basic;
 use headers::{AccessControlAllowCredentials, AccessControlAllowHeaders, HeaderMapExt};
 use headers::{
@@ -29,11 +36,15 @@ use http::header::{self, HeaderName, HeaderValue};
 use http::{HeaderMap, Request as HyperRequest};
 use hyper::{Body, Client, Method, Response as HyperResponse, StatusCode};
 use hyper_serde::Serde;
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
 use msg::constellation_msg::{HistoryStateId, PipelineId};
+use net_traits::pub_domains::is_same_site;
 use net_traits::quality::{quality_to_value, Quality, QualityItem};
 use net_traits::request::Origin::Origin as SpecificOrigin;
 use net_traits::request::{is_cors_safelisted_method, is_cors_safelisted_request_header};
-use net_traits::request::{CacheMode, CredentialsMode, Destination, Origin};
+use net_traits::request::{BodyChunkRequest, BodyChunkResponse};
+use net_traits::request::{CacheMode, CredentialsMode, Destination, Initiator, Origin};
 use net_traits::request::{RedirectMode, Referrer, Request, RequestBuilder, RequestMode};
 use net_traits::request::{ResponseTainting, ServiceWorkersMode};
 use net_traits::response::{HttpsState, Response, ResponseBody, ResponseType};
@@ -42,8 +53,12 @@ use net_traits::{
     RedirectEndValue, RedirectStartValue, ResourceAttribute, ResourceFetchTiming, ResourceTimeValue,
 };
 use servo_arc::Arc;
+use servo_config::locale;
+use servo_config::user_agent::user_agent_for_host;
 use servo_url::{ImmutableOrigin, ServoUrl};
+use std::cmp;
 use std::collections::{HashMap, HashSet};
+use std::io;
 use std::iter::FromIterator;
 use std::mem;
 use std::ops::Deref;
@@ -79,11 +94,16 @@ pub struct HttpState {
     pub http_cache_state: Mutex<HashMap<CacheKey, Arc<(Mutex<HttpCacheEntryState>, Condvar)>>>,
     pub auth_cache: RwLock<AuthCache>,
     pub history_states: RwLock<HashMap<HistoryStateId, Vec<u8>>>,
+    pub accept_ch_cache: RwLock<AcceptChCache>,
+    pub preload_cache: PreloadCache,
+    /// The connector of the client, which opens the connections of the resource hints.
+    pub connector: Connector,
     pub client: Client<Connector, Body>,
 }
 
 impl HttpState {
     pub fn new(tls_config: TlsConfig) -> HttpState {
+        let connector = Connector::new(tls_config);
         HttpState {
             hsts_list: RwLock::new(HstsList::new()),
             cookie_jar: RwLock::new(CookieStorage::new(150)),
@@ -91,7 +111,10 @@ impl HttpState {
             history_states: RwLock::new(HashMap::new()),
             http_cache: RwLock::new(HttpCache::new()),
             http_cache_state: Mutex::new(HashMap::new()),
-            client: create_http_client(tls_config, HANDLE.lock().unwrap().executor()),
+            accept_ch_cache: RwLock::new(AcceptChCache::new()),
+            preload_cache: PreloadCache::default(),
+            connector: connector.clone(),
+            client: create_http_client(connector, HANDLE.lock().unwrap().executor()),
         }
     }
 }
@@ -155,10 +178,9 @@ pub fn set_default_accept_language(headers: &mut HeaderMap) {
     }
 
     // TODO(eijebong): Change this once typed headers are done
-    headers.insert(
-        header::ACCEPT_LANGUAGE,
-        HeaderValue::from_static("en-US, en; q=0.5"),
-    );
+    if let Ok(value) = HeaderValue::from_str(&locale::accept_language()) {
+        headers.insert(header::ACCEPT_LANGUAGE, value);
+    }
 }
 
 /// <https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-state-no-referrer-when-downgrade>
@@ -242,14 +264,44 @@ pub fn determine_request_referrer(
     }
 }
 
+/// How a request relates to its site for cookies.
+/// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-05#section-5.2>
+fn same_site_context(request: &Request) -> SameSiteContext {
+    let site_for_cookies = match (&request.site_for_cookies, &request.origin) {
+        (Some(site_for_cookies), _) => site_for_cookies,
+        (None, Origin::Origin(origin)) => origin,
+        // A request without a client is same-site.
+        (None, Origin::Client) => return SameSiteContext::SameSite,
+    };
+    let current_origin = request.current_url().origin();
+    // A request which went through a cross-site redirect is cross-site.
+    let same_site = request
+        .url_list
+        .iter()
+        .all(|url| is_same_site(&url.origin(), &current_origin)) &&
+        is_same_site(&current_origin, site_for_cookies);
+    if same_site {
+        SameSiteContext::SameSite
+    } else if request.top_level_navigation {
+        SameSiteContext::TopLevelNavigation {
+            safe_method: request.method.is_safe(),
+        }
+    } else {
+        SameSiteContext::CrossSite
+    }
+}
+
 pub fn set_request_cookies(
     url: &ServoUrl,
+    context: SameSiteContext,
     headers: &mut HeaderMap,
     cookie_jar: &RwLock<CookieStorage>,
 ) {
     let mut cookie_jar = cookie_jar.write().unwrap();
     cookie_jar.remove_expired_cookies_for_url(url);
-    if let Some(cookie_list) = cookie_jar.cookies_for_url(url, CookieSource::HTTP) {
+    if let Some(cookie_list) =
+        cookie_jar.cookies_for_url_in_context(url, CookieSource::HTTP, context)
+    {
         headers.insert(
             header::COOKIE,
             HeaderValue::from_bytes(cookie_list.as_bytes()).unwrap(),
@@ -257,23 +309,31 @@ pub fn set_request_cookies(
     }
 }
 
-fn set_cookie_for_url(cookie_jar: &RwLock<CookieStorage>, request: &ServoUrl, cookie_val: &str) {
+fn set_cookie_for_url(
+    cookie_jar: &RwLock<CookieStorage>,
+    request: &ServoUrl,
+    context: SameSiteContext,
+    cookie_val: &str,
+) {
     let mut cookie_jar = cookie_jar.write().unwrap();
     let source = CookieSource::HTTP;
 
     if let Some(cookie) = cookie::Cookie::from_cookie_string(cookie_val.into(), request, source) {
-        cookie_jar.push(cookie, request, source);
+        if cookie.can_be_set_in_context(context) {
+            cookie_jar.push(cookie, request, source);
+        }
     }
 }
 
 fn set_cookies_from_headers(
     url: &ServoUrl,
+    context: SameSiteContext,
     headers: &HeaderMap,
     cookie_jar: &RwLock<CookieStorage>,
 ) {
     for cookie in headers.get_all(header::SET_COOKIE) {
         if let Ok(cookie_str) = std::str::from_utf8(cookie.as_bytes()) {
-            set_cookie_for_url(&cookie_jar, &url, &cookie_str);
+            set_cookie_for_url(&cookie_jar, &url, context, &cookie_str);
         }
     }
 }
@@ -321,12 +381,19 @@ fn send_response_to_devtools(
     request_id: String,
     headers: Option<HeaderMap>,
     status: Option<(u16, Vec<u8>)>,
+    body: &[u8],
+    receive_time: u64,
+    from_cache: bool,
     pipeline_id: PipelineId,
 ) {
+    let preview_len = cmp::min(body.len(), RESPONSE_BODY_PREVIEW_LIMIT);
     let response = DevtoolsHttpResponse {
         headers: headers,
         status: status,
-        body: None,
+        body: Some(body[..preview_len].to_vec()),
+        body_size: body.len(),
+        receive_time: receive_time,
+        from_cache: from_cache,
         pipeline_id: pipeline_id,
     };
     let net_event_response = NetworkEvent::HttpResponse(response);
@@ -335,6 +402,48 @@ fn send_response_to_devtools(
     let _ = devtools_chan.send(DevtoolsControlMsg::FromChrome(msg));
 }
 
+/// Report a response that was served from the http cache, which never reaches
+/// `http_network_fetch`, as a request/response pair to the devtools.
+fn send_cached_response_to_devtools(
+    request: &Request,
+    response: &Response,
+    context: &FetchContext,
+) {
+    let (devtools_chan, pipeline_id) = match (context.devtools_chan.as_ref(), request.pipeline_id) {
+        (Some(devtools_chan), Some(pipeline_id)) => (devtools_chan, pipeline_id),
+        _ => return,
+    };
+    let request_id = uuid::Uuid::new_v4().to_simple().to_string();
+    let msg = prepare_devtools_request(
+        request_id.clone(),
+        request.current_url(),
+        request.method.clone(),
+        request.headers.clone(),
+        request.body.clone(),
+        pipeline_id,
+        time::now(),
+        0,
+        0,
+        request.destination == Destination::None,
+    );
+    send_request_to_devtools(msg, devtools_chan);
+
+    let body = match *response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => body.clone(),
+        _ => vec![],
+    };
+    send_response_to_devtools(
+        devtools_chan,
+        request_id,
+        Some(response.headers.clone()),
+        response.raw_status.clone(),
+        &body,
+        0,
+        true,
+        pipeline_id,
+    );
+}
+
 fn auth_from_cache(
     auth_cache: &RwLock<AuthCache>,
     origin: &ImmutableOrigin,
@@ -353,12 +462,129 @@ fn auth_from_cache(
     }
 }
 
+/// The realm of the `Basic` challenge of a `WWW-Authenticate` header, if any.
+fn authentication_realm(headers: &HeaderMap) -> Option<String> {
+    let challenge = headers.get(header::WWW_AUTHENTICATE)?.to_str().ok()?;
+    let params = challenge.trim_start().splitn(2, ' ').collect::<Vec<_>>();
+    if params.len() != 2 || !params[0].eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    params[1].split(',').find_map(|param| {
+        let mut param = param.splitn(2, '=');
+        match (param.next(), param.next()) {
+            (Some(name), Some(value)) if name.trim().eq_ignore_ascii_case("realm") => {
+                Some(value.trim().trim_matches('"').to_owned())
+            },
+            _ => None,
+        }
+    })
+}
+
+/// Prompt the user for the credentials to access the given URL, in the browser of the
+/// given pipeline. Returns `None` if the user declined to enter them.
+fn prompt_for_credentials(
+    context: &FetchContext,
+    pipeline_id: PipelineId,
+    url: &ServoUrl,
+    realm: Option<String>,
+) -> Option<(String, String)> {
+    let (sender, receiver) = ipc::channel().ok()?;
+    let msg = PromptMsg {
+        pipeline_id: Some(pipeline_id),
+        request: PromptRequest::Authentication(url.clone(), realm),
+        sender: sender,
+    };
+    if let Err(e) = context.prompt_sender.send(msg) {
+        warn!("Failed to prompt for credentials ({}).", e);
+        return None;
+    }
+    match receiver.recv() {
+        Ok(PromptResponse::Credentials(user_name, password)) => Some((user_name, password)),
+        _ => None,
+    }
+}
+
+/// The chunks of a request body which a script streams, each of which is only requested
+/// once hyper polls for it, that is once the socket took the previous one, so that the
+/// stream is read no faster than the server receives it.
+struct BodyChunkStream {
+    sender: IpcSender<BodyChunkRequest>,
+    receiver: UnboundedReceiver<BodyChunkResponse>,
+    chunk_requested: bool,
+    done: bool,
+}
+
+impl BodyChunkStream {
+    fn new(sender: IpcSender<BodyChunkRequest>) -> BodyChunkStream {
+        let (chunk_sender, chunk_receiver) = ipc::channel().unwrap();
+        let (body_sender, body_receiver) = futures::sync::mpsc::unbounded();
+        ROUTER.add_route(
+            chunk_receiver.to_opaque(),
+            Box::new(move |message| {
+                let _ = body_sender.unbounded_send(message.to().unwrap());
+            }),
+        );
+        let _ = sender.send(BodyChunkRequest::Connect(chunk_sender));
+        BodyChunkStream {
+            sender,
+            receiver: body_receiver,
+            chunk_requested: false,
+            done: false,
+        }
+    }
+}
+
+impl Stream for BodyChunkStream {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Vec<u8>>, io::Error> {
+        loop {
+            if self.done {
+                return Ok(Async::Ready(None));
+            }
+            if !self.chunk_requested {
+                self.sender
+                    .send(BodyChunkRequest::Chunk)
+                    .map_err(|_| io::Error::new(io::ErrorKind::Other, "The stream is gone"))?;
+                self.chunk_requested = true;
+            }
+            let response = match self.receiver.poll() {
+                Ok(Async::Ready(response)) => response,
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(()) => None,
+            };
+            self.chunk_requested = false;
+            match response {
+                // An empty chunk would end a chunked body, so the next one is requested.
+                Some(BodyChunkResponse::Chunk(ref bytes)) if bytes.is_empty() => continue,
+                Some(BodyChunkResponse::Chunk(bytes)) => return Ok(Async::Ready(Some(bytes))),
+                Some(BodyChunkResponse::Done) => self.done = true,
+                Some(BodyChunkResponse::Error) | None => {
+                    self.done = true;
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "The request body stream errored",
+                    ));
+                },
+            }
+        }
+    }
+}
+
+impl Drop for BodyChunkStream {
+    fn drop(&mut self) {
+        let _ = self.sender.send(BodyChunkRequest::Done);
+    }
+}
+
 fn obtain_response(
     client: &Client<Connector, Body>,
     url: &ServoUrl,
     method: &Method,
     request_headers: &HeaderMap,
     data: &Option<Vec<u8>>,
+    body_stream: &Option<IpcSender<BodyChunkRequest>>,
     load_data_method: &Method,
     pipeline_id: &Option<PipelineId>,
     iters: u32,
@@ -381,8 +607,14 @@ fn obtain_response(
     // https://tools.ietf.org/html/rfc7231#section-6.4
     let is_redirected_request = iters != 1;
     let request_body;
-    match data {
-        &Some(ref d) if !is_redirected_request => {
+    let mut streamed_body = None;
+    match (data, body_stream) {
+        (_, &Some(ref body_stream)) if !is_redirected_request => {
+            // Without a Content-Length, hyper sends the body with the chunked coding.
+            streamed_body = Some(Body::wrap_stream(BodyChunkStream::new(body_stream.clone())));
+            request_body = vec![];
+        },
+        (&Some(ref d), _) if !is_redirected_request => {
             headers.typed_insert(ContentLength(d.len() as u64));
             request_body = d.clone();
         },
@@ -420,7 +652,7 @@ fn obtain_response(
                 .replace("{", "%7B")
                 .replace("}", "%7D"),
         )
-        .body(request_body.clone().into());
+        .body(streamed_body.unwrap_or_else(|| request_body.clone().into()));
 
     // TODO: We currently don't know when the handhhake before the connection is done
     // so our best bet would be to set `secure_connection_start` here when we are currently
@@ -439,6 +671,16 @@ fn obtain_response(
     };
     *request.headers_mut() = headers.clone();
 
+    // The credentials of a proxy are only sent to it, so they aren't exposed to devtools.
+    if url.scheme() == "http" {
+        let authorization = url.host_str().and_then(proxy_authorization_for_host);
+        if let Some(authorization) = authorization.and_then(|a| HeaderValue::from_str(&a).ok()) {
+            request
+                .headers_mut()
+                .insert(header::PROXY_AUTHORIZATION, authorization);
+        }
+    }
+
     let connect_end = precise_time_ms();
     context
         .timing
@@ -783,7 +1025,7 @@ pub fn http_redirect_fetch(
         .status
         .as_ref()
         .map_or(true, |s| s.0 != StatusCode::SEE_OTHER) &&
-        request.body.as_ref().map_or(false, |b| b.is_empty())
+        (request.body.as_ref().map_or(false, |b| b.is_empty()) || request.body_stream.is_some())
     {
         return Response::network_error(NetworkError::Internal("Request body is not done".into()));
     }
@@ -806,6 +1048,7 @@ pub fn http_redirect_fetch(
     {
         request.method = Method::GET;
         request.body = None;
+        request.body_stream = None;
     }
 
     // Step 12
@@ -906,6 +1149,8 @@ fn http_network_or_cache_fetch(
         // Step 5.6
         Some(ref http_request_body) => Some(http_request_body.len() as u64),
     };
+    // The length of a streamed body is unknown.
+    let content_length_value = content_length_value.filter(|_| http_request.body_stream.is_none());
 
     // Step 5.7
     if let Some(content_length_value) = content_length_value {
@@ -950,10 +1195,27 @@ fn http_network_or_cache_fetch(
 
     // Step 5.11
     if !http_request.headers.contains_key(header::USER_AGENT) {
-        let user_agent = context.user_agent.clone().into_owned();
-        http_request
-            .headers
-            .typed_insert::<UserAgent>(user_agent.parse().unwrap());
+        let url = http_request.current_url();
+        let user_agent = user_agent_for_host(url.host_str(), &context.user_agent);
+        if let Ok(user_agent) = user_agent.parse::<UserAgent>() {
+            http_request.headers.typed_insert(user_agent);
+        }
+    }
+
+    // https://wicg.github.io/client-hints-infrastructure/#fetch
+    context
+        .state
+        .accept_ch_cache
+        .read()
+        .unwrap()
+        .append_client_hints(http_request);
+
+    // https://wicg.github.io/nav-speculation/prefetch.html#sec-purpose-header
+    if http_request.initiator == Initiator::Prefetch {
+        http_request.headers.insert(
+            HeaderName::from_static("sec-purpose"),
+            HeaderValue::from_static("prefetch"),
+        );
     }
 
     match http_request.cache_mode {
@@ -1006,6 +1268,7 @@ fn http_network_or_cache_fetch(
         // XXXManishearth http_loader has block_cookies: support content blocking here too
         set_request_cookies(
             &current_url,
+            same_site_context(&http_request),
             &mut http_request.headers,
             &context.state.cookie_jar,
         );
@@ -1092,6 +1355,9 @@ fn http_network_or_cache_fetch(
                         (CacheMode::OnlyIfCached, _) |
                         (CacheMode::NoStore, _) |
                         (CacheMode::Reload, _) => (None, false),
+                        // A stored response is always validated in the no-cache mode,
+                        // even if the request has Cache-Control directives of its own.
+                        (CacheMode::NoCache, _) => (Some(response_from_cache.response), true),
                         (_, _) => (
                             Some(response_from_cache.response),
                             response_from_cache.needs_validation,
@@ -1192,6 +1458,10 @@ fn http_network_or_cache_fetch(
 
     wait_for_cached_response(done_chan, &mut response);
 
+    if let Some(ref response) = response {
+        send_cached_response_to_devtools(http_request, response, context);
+    }
+
     // Step 6
     // TODO: https://infra.spec.whatwg.org/#if-aborted
 
@@ -1210,8 +1480,13 @@ fn http_network_or_cache_fetch(
     // More Step 7
     if response.is_none() {
         // Substep 2
-        let forward_response =
-            http_network_fetch(http_request, credentials_flag, done_chan, context);
+        let forward_response = http_network_fetch(
+            http_request,
+            credentials_flag,
+            revalidating_flag,
+            done_chan,
+            context,
+        );
         // Substep 3
         if let Some((200..=399, _)) = forward_response.raw_status {
             if !http_request.method.is_safe() {
@@ -1269,18 +1544,32 @@ fn http_network_or_cache_fetch(
         // TODO: Spec says requires testing on multiple WWW-Authenticate headers
 
         // Substep 2
-        if http_request.body.is_some() {
-            // TODO Implement body source
+        if http_request.body_stream.is_some() {
+            return Response::network_error(NetworkError::Internal(
+                "A streamed request body cannot be sent again".into(),
+            ));
         }
 
         // Substep 3
         if !http_request.use_url_credentials || authentication_fetch_flag {
-            // FIXME: Prompt the user for username and password from the window
-
-            // Wrong, but will have to do until we are able to prompt the user
-            // otherwise this creates an infinite loop
-            // We basically pretend that the user declined to enter credentials
-            return response;
+            let pipeline_id = match http_request.pipeline_id {
+                Some(pipeline_id) => pipeline_id,
+                None => return response,
+            };
+            let realm = authentication_realm(&response.headers);
+            let url = http_request.current_url();
+            let (user_name, password) =
+                match prompt_for_credentials(context, pipeline_id, &url, realm) {
+                    Some(credentials) => credentials,
+                    // The user declined to enter credentials.
+                    None => return response,
+                };
+            let current_url = http_request.current_url_mut();
+            if current_url.set_username(&user_name).is_err() ||
+                current_url.set_password(Some(&password)).is_err()
+            {
+                return response;
+            }
         }
 
         // Make sure this is set to None,
@@ -1324,7 +1613,24 @@ fn http_network_or_cache_fetch(
 
     // Step 12
     if authentication_fetch_flag {
-        // TODO Create the authentication entry for request and the given realm
+        let current_url = http_request.current_url();
+        let authenticated = match response.status {
+            Some((StatusCode::UNAUTHORIZED, _)) => false,
+            _ => true,
+        };
+        if authenticated && has_credentials(&current_url) {
+            let entry = AuthCacheEntry {
+                user_name: current_url.username().to_owned(),
+                password: current_url.password().unwrap_or("").to_owned(),
+            };
+            context
+                .state
+                .auth_cache
+                .write()
+                .unwrap()
+                .entries
+                .insert(current_url.origin().ascii_serialization(), entry);
+        }
     }
 
     // Step 13
@@ -1353,9 +1659,13 @@ impl Drop for ResponseEndTimer {
 }
 
 /// [HTTP network fetch](https://fetch.spec.whatwg.org/#http-network-fetch)
+///
+/// `revalidating` is whether the request revalidates a stored response, which is then
+/// reported to the devtools as served from the cache if the server didn't modify it.
 fn http_network_fetch(
     request: &Request,
     credentials_flag: bool,
+    revalidating: bool,
     done_chan: &mut DoneChannel,
     context: &FetchContext,
 ) -> Response {
@@ -1398,6 +1708,7 @@ fn http_network_fetch(
         &request.method,
         &request.headers,
         &request.body,
+        &request.body_stream,
         &request.method,
         &request.pipeline_id,
         request.redirect_count + 1,
@@ -1488,23 +1799,31 @@ fn http_network_fetch(
     *res_body.lock().unwrap() = ResponseBody::Receiving(vec![]);
     let res_body2 = res_body.clone();
 
-    if let Some(ref sender) = devtools_sender {
+    // The devtools are told about the response once its body has been received,
+    // so that they can show a preview of it.
+    let mut devtools_response = None;
+    if let Some(sender) = devtools_sender {
         if let Some(m) = msg {
             send_request_to_devtools(m, &sender);
         }
 
-        // --- Tell devtools that we got a response
-        // Send an HttpResponse message to devtools with the corresponding request_id
         if let Some(pipeline_id) = pipeline_id {
-            send_response_to_devtools(
-                &sender,
+            let from_cache = revalidating &&
+                meta_status
+                    .as_ref()
+                    .map_or(false, |s| s.0 == StatusCode::NOT_MODIFIED.as_u16());
+            devtools_response = Some((
+                sender,
                 request_id.unwrap(),
                 meta_headers.map(Serde::into_inner),
                 meta_status,
+                from_cache,
                 pipeline_id,
-            );
+            ));
         }
     }
+    let devtools_response2 = devtools_response.clone();
+    let receive_start = precise_time_ms();
 
     let done_sender2 = done_sender.clone();
     let done_sender3 = done_sender.clone();
@@ -1535,6 +1854,22 @@ fn http_network_fetch(
                     ResponseBody::Receiving(ref mut body) => mem::replace(body, vec![]),
                     _ => vec![],
                 };
+                // --- Tell devtools that we got a response
+                // Send an HttpResponse message to devtools with the corresponding request_id
+                if let Some((sender, request_id, headers, status, from_cache, pipeline_id)) =
+                    devtools_response
+                {
+                    send_response_to_devtools(
+                        &sender,
+                        request_id,
+                        headers,
+                        status,
+                        &completed_body,
+                        precise_time_ms() - receive_start,
+                        from_cache,
+                        pipeline_id,
+                    );
+                }
                 *body = ResponseBody::Done(completed_body);
                 timing_ptr2
                     .lock()
@@ -1550,6 +1885,20 @@ fn http_network_fetch(
                     ResponseBody::Receiving(ref mut body) => mem::replace(body, vec![]),
                     _ => vec![],
                 };
+                if let Some((sender, request_id, headers, status, from_cache, pipeline_id)) =
+                    devtools_response2
+                {
+                    send_response_to_devtools(
+                        &sender,
+                        request_id,
+                        headers,
+                        status,
+                        &completed_body,
+                        precise_time_ms() - receive_start,
+                        from_cache,
+                        pipeline_id,
+                    );
+                }
                 *body = ResponseBody::Done(completed_body);
                 timing_ptr3
                     .lock()
@@ -1592,7 +1941,12 @@ fn http_network_fetch(
     // TODO this step isn't possible yet
     // Step 15
     if credentials_flag {
-        set_cookies_from_headers(&url, &response.headers, &context.state.cookie_jar);
+        set_cookies_from_headers(
+            &url,
+            same_site_context(request),
+            &response.headers,
+            &context.state.cookie_jar,
+        );
     }
     context
         .state
@@ -1600,6 +1954,12 @@ fn http_network_fetch(
         .write()
         .unwrap()
         .update_hsts_list_from_response(&url, &response.headers);
+    context
+        .state
+        .accept_ch_cache
+        .write()
+        .unwrap()
+        .update_from_response(request, &url, &response.headers);
 
     // TODO these steps
     // Step 16
@@ -1797,7 +2157,7 @@ fn cors_preflight_fetch(
 }
 
 /// [CORS check](https://fetch.spec.whatwg.org#concept-cors-check)
-fn cors_check(request: &Request, response: &Response) -> Result<(), ()> {
+pub fn cors_check(request: &Request, response: &Response) -> Result<(), ()> {
     // Step 1
     let origin = response.headers.typed_get::<AccessControlAllowOrigin>();
 
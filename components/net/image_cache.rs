@@ -4,7 +4,9 @@
 
 use embedder_traits::resources::{self, Resource};
 use immeta::load_from_buf;
-use net_traits::image::base::{load_from_memory, Image, ImageMetadata};
+use net_traits::image::base::{
+    exif_orientation, load_from_memory, Image, ImageMetadata, ImageOrientation, PartialImageDecoder,
+};
 use net_traits::image_cache::{CanRequestImages, CorsStatus, ImageCache, ImageResponder};
 use net_traits::image_cache::{ImageOrMetadataAvailable, ImageResponse, ImageState};
 use net_traits::image_cache::{PendingImageId, UsePlaceholder};
@@ -18,10 +20,11 @@ use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::HashMap;
 use std::io;
 use std::mem;
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use webrender_api::units::DeviceIntSize;
-use webrender_api::ImageDescriptorFlags;
+use webrender_api::units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize};
+use webrender_api::{DirtyRect, ImageDescriptorFlags, ResourceUpdate};
 
 ///
 /// TODO(gw): Remaining work on image cache:
@@ -54,10 +57,25 @@ fn get_placeholder_image(
     Ok(Arc::new(image))
 }
 
+/// The number of bytes of an image to receive before decoding the rows of the image
+/// received so far again.
+const PARTIAL_DECODING_INTERVAL: usize = 32 * 1024;
+
 fn set_webrender_image_key(webrender_api: &WebrenderIpcSender, image: &mut Image) {
     if image.id.is_some() {
         return;
     }
+    let (descriptor, data) = get_webrender_image_data(image);
+    let image_key = webrender_api.generate_image_key();
+    let mut txn = webrender_api::Transaction::new();
+    txn.add_image(image_key, descriptor, data, None);
+    webrender_api.update_resources(txn.resource_updates);
+    image.id = Some(image_key);
+}
+
+fn get_webrender_image_data(
+    image: &Image,
+) -> (webrender_api::ImageDescriptor, webrender_api::ImageData) {
     let mut bytes = Vec::new();
     let is_opaque = match image.format {
         PixelFormat::BGRA8 => {
@@ -85,12 +103,48 @@ fn set_webrender_image_key(webrender_api: &WebrenderIpcSender, image: &mut Image
         offset: 0,
         flags,
     };
-    let data = webrender_api::ImageData::new(bytes);
-    let image_key = webrender_api.generate_image_key();
+    (descriptor, webrender_api::ImageData::new(bytes))
+}
+
+/// Show the parts of an image that were decoded while it loads, and tell the listeners of
+/// its load about them. They are shown by a WebRender image that is only updated where
+/// rows were decoded since it was last updated, and which is reused for the image once it
+/// is loaded.
+fn show_partial_image(
+    webrender_api: &WebrenderIpcSender,
+    pending_load: &mut PendingLoad,
+    mut image: Image,
+    rows: Range<u32>,
+) {
+    let (descriptor, data) = get_webrender_image_data(&image);
     let mut txn = webrender_api::Transaction::new();
-    txn.add_image(image_key, descriptor, data, None);
+    let image_key = match pending_load.partial_image_key {
+        Some(image_key) => {
+            let dirty_rect = DeviceIntRect::new(
+                DeviceIntPoint::new(0, rows.start as i32),
+                DeviceIntSize::new(image.width as i32, (rows.end - rows.start) as i32),
+            );
+            txn.update_image(image_key, descriptor, data, &DirtyRect::Partial(dirty_rect));
+            image_key
+        },
+        None => {
+            let image_key = webrender_api.generate_image_key();
+            txn.add_image(image_key, descriptor, data, None);
+            image_key
+        },
+    };
     webrender_api.update_resources(txn.resource_updates);
     image.id = Some(image_key);
+    pending_load.partial_image_key = Some(image_key);
+
+    let image = Arc::new(image);
+    let url = pending_load
+        .final_url
+        .clone()
+        .unwrap_or_else(|| pending_load.url.clone());
+    for listener in &pending_load.listeners {
+        listener.respond(ImageResponse::PartiallyLoaded(image.clone(), url.clone()));
+    }
 }
 
 // ======================================================================
@@ -298,6 +352,16 @@ struct PendingLoad {
 
     /// The URL of the final response that contains a body.
     final_url: Option<ServoUrl>,
+
+    /// The decoder of the parts of the image which were received, which is only locked
+    /// by the thread receiving the bytes of the image, while the store is not locked.
+    partial_decoder: Arc<Mutex<PartialImageDecoder>>,
+
+    /// The WebRender image showing the parts of the image which were decoded before all
+    /// bytes were received, and the number of bytes that were received when they were
+    /// last decoded.
+    partial_image_key: Option<webrender_api::ImageKey>,
+    partially_decoded_bytes: usize,
 }
 
 impl PendingLoad {
@@ -316,6 +380,9 @@ impl PendingLoad {
             final_url: None,
             cors_setting,
             cors_status: CorsStatus::Unsafe,
+            partial_decoder: Arc::new(Mutex::new(PartialImageDecoder::default())),
+            partial_image_key: None,
+            partially_decoded_bytes: 0,
         }
     }
 
@@ -340,6 +407,9 @@ struct ImageCacheStore {
     // The URL used for the placeholder image
     placeholder_url: ServoUrl,
 
+    // The images without their EXIF orientation, by the key of the oriented images
+    unoriented_images: HashMap<webrender_api::ImageKey, Arc<Image>>,
+
     // Webrender API instance.
     webrender_api: WebrenderIpcSender,
 }
@@ -352,11 +422,23 @@ impl ImageCacheStore {
             None => return,
         };
 
-        match load_result {
-            LoadResult::Loaded(ref mut image) => {
+        match (&mut load_result, pending_load.partial_image_key) {
+            // The image replaces the rows which were shown while it was loading.
+            (&mut LoadResult::Loaded(ref mut image), Some(image_key)) => {
+                let (descriptor, data) = get_webrender_image_data(image);
+                let mut txn = webrender_api::Transaction::new();
+                txn.update_image(image_key, descriptor, data, &DirtyRect::All);
+                self.webrender_api.update_resources(txn.resource_updates);
+                image.id = Some(image_key);
+            },
+            (&mut LoadResult::Loaded(ref mut image), None) => {
                 set_webrender_image_key(&self.webrender_api, image)
             },
-            LoadResult::PlaceholderLoaded(..) | LoadResult::None => {},
+            (_, Some(image_key)) => {
+                self.webrender_api
+                    .update_resources(vec![ResourceUpdate::DeleteImage(image_key)]);
+            },
+            (_, None) => {},
         }
 
         let url = pending_load.final_url.clone();
@@ -406,7 +488,8 @@ impl ImageCacheStore {
                     )),
                     (&ImageResponse::PlaceholderLoaded(_, _), UsePlaceholder::No) |
                     (&ImageResponse::None, _) |
-                    (&ImageResponse::MetadataLoaded(_), _) => Err(ImageState::LoadError),
+                    (&ImageResponse::MetadataLoaded(_), _) |
+                    (&ImageResponse::PartiallyLoaded(_, _), _) => Err(ImageState::LoadError),
                 },
             )
     }
@@ -438,6 +521,7 @@ impl ImageCache for ImageCacheImpl {
                 completed_loads: HashMap::new(),
                 placeholder_image: get_placeholder_image(&webrender_api, &rippy_data).ok(),
                 placeholder_url: ServoUrl::parse("chrome://resources/rippy.png").unwrap(),
+                unoriented_images: HashMap::new(),
                 webrender_api: webrender_api,
             })),
         }
@@ -528,11 +612,74 @@ impl ImageCache for ImageCacheImpl {
         warn!("Couldn't find cached entry for listener {:?}", id);
     }
 
+    fn find_unoriented_image(&self, image: &Arc<Image>) -> Arc<Image> {
+        let image_key = match image.id {
+            Some(image_key) if image.orientation != ImageOrientation::Normal => image_key,
+            _ => return image.clone(),
+        };
+        let mut store = self.store.lock().unwrap();
+        let store = &mut *store;
+        let webrender_api = &store.webrender_api;
+        store
+            .unoriented_images
+            .entry(image_key)
+            .or_insert_with(|| {
+                let mut unoriented = image.unoriented();
+                set_webrender_image_key(webrender_api, &mut unoriented);
+                Arc::new(unoriented)
+            })
+            .clone()
+    }
+
+    fn evict_unused_images(&self) -> usize {
+        let mut store = self.store.lock().unwrap();
+        let store = &mut *store;
+        let mut size = 0;
+        let mut image_keys = vec![];
+        let unoriented_images = &mut store.unoriented_images;
+        let is_unused = |image: &Arc<Image>, unoriented_images: &HashMap<_, Arc<Image>>| {
+            Arc::strong_count(image) == 1 &&
+                image
+                    .id
+                    .and_then(|image_key| unoriented_images.get(&image_key))
+                    .map_or(true, |unoriented| Arc::strong_count(unoriented) == 1)
+        };
+        store
+            .completed_loads
+            .retain(|_, completed_load| match completed_load.image_response {
+                ImageResponse::Loaded(ref image, _) if is_unused(image, unoriented_images) => {
+                    size += image.bytes.len();
+                    size += image
+                        .animation
+                        .as_ref()
+                        .map_or(0, |animation| animation.bytes.len());
+                    if let Some(image_key) = image.id {
+                        image_keys.push(image_key);
+                        if let Some(unoriented) = unoriented_images.remove(&image_key) {
+                            size += unoriented.bytes.len();
+                            image_keys.extend(unoriented.id);
+                        }
+                    }
+                    false
+                },
+                _ => true,
+            });
+        if !image_keys.is_empty() {
+            let updates = image_keys
+                .into_iter()
+                .map(ResourceUpdate::DeleteImage)
+                .collect();
+            store.webrender_api.update_resources(updates);
+        }
+        size
+    }
+
     /// Inform the image cache about a response for a pending request.
     fn notify_pending_response(&self, id: PendingImageId, action: FetchResponseMsg) {
         match (action, id) {
             (FetchResponseMsg::ProcessRequestBody, _) |
-            (FetchResponseMsg::ProcessRequestEOF, _) => return,
+            (FetchResponseMsg::ProcessRequestEOF, _) |
+            (FetchResponseMsg::ProcessCspViolations(_), _) => return,
             (FetchResponseMsg::ProcessResponse(response), _) => {
                 debug!("Received {:?} for {:?}", response.as_ref().map(|_| ()), id);
                 let mut store = self.store.lock().unwrap();
@@ -560,22 +707,65 @@ impl ImageCache for ImageCacheImpl {
             },
             (FetchResponseMsg::ProcessResponseChunk(data), _) => {
                 debug!("Got some data for {:?}", id);
-                let mut store = self.store.lock().unwrap();
-                let pending_load = store.pending_loads.get_by_key_mut(&id).unwrap();
-                pending_load.bytes.extend_from_slice(&data);
-                //jmr0 TODO: possibly move to another task?
-                if let None = pending_load.metadata {
-                    if let Ok(metadata) = load_from_buf(&pending_load.bytes.as_slice()) {
-                        let dimensions = metadata.dimensions();
-                        let img_metadata = ImageMetadata {
-                            width: dimensions.width,
-                            height: dimensions.height,
-                        };
-                        for listener in &pending_load.listeners {
-                            listener.respond(ImageResponse::MetadataLoaded(img_metadata.clone()));
+                let (decoding_due, cors_status, partial_decoder) = {
+                    let mut store = self.store.lock().unwrap();
+                    let store = &mut *store;
+                    let pending_load = store.pending_loads.get_by_key_mut(&id).unwrap();
+                    pending_load.bytes.extend_from_slice(&data);
+                    //jmr0 TODO: possibly move to another task?
+                    if let None = pending_load.metadata {
+                        if let Ok(metadata) = load_from_buf(&pending_load.bytes.as_slice()) {
+                            let dimensions = metadata.dimensions();
+                            // The EXIF metadata precede the dimensions of the image, which
+                            // are those of the decoded image once it is oriented.
+                            let orientation = exif_orientation(pending_load.bytes.as_slice());
+                            let img_metadata = if orientation.swaps_dimensions() {
+                                ImageMetadata {
+                                    width: dimensions.height,
+                                    height: dimensions.width,
+                                }
+                            } else {
+                                ImageMetadata {
+                                    width: dimensions.width,
+                                    height: dimensions.height,
+                                }
+                            };
+                            for listener in &pending_load.listeners {
+                                listener
+                                    .respond(ImageResponse::MetadataLoaded(img_metadata.clone()));
+                            }
+                            pending_load.metadata = Some(img_metadata);
                         }
-                        pending_load.metadata = Some(img_metadata);
                     }
+                    let received_bytes = pending_load.bytes.as_slice().len();
+                    let decoding_due = pending_load.metadata.is_some() &&
+                        received_bytes >=
+                            pending_load.partially_decoded_bytes + PARTIAL_DECODING_INTERVAL;
+                    if decoding_due {
+                        pending_load.partially_decoded_bytes = received_bytes;
+                    }
+                    (
+                        decoding_due,
+                        pending_load.cors_status,
+                        pending_load.partial_decoder.clone(),
+                    )
+                };
+
+                // The image is decoded without locking the store, which the other loads
+                // and the layout threads need.
+                let mut partial_decoder = partial_decoder.lock().unwrap();
+                partial_decoder.push(&data);
+                if !decoding_due {
+                    return;
+                }
+                let (image, rows) = match partial_decoder.decode(cors_status) {
+                    Some(result) => result,
+                    None => return,
+                };
+                let mut store = self.store.lock().unwrap();
+                let store = &mut *store;
+                if let Some(pending_load) = store.pending_loads.get_by_key_mut(&id) {
+                    show_partial_image(&store.webrender_api, pending_load, image, rows);
                 }
             },
             (FetchResponseMsg::ProcessResponseEOF(result), key) => {
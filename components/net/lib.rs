@@ -17,8 +17,13 @@ extern crate serde;
 #[macro_use]
 extern crate servo_config;
 
+pub mod about_loader;
+mod cache_storage;
+pub mod client_hints;
 pub mod connector;
+pub mod content_blocker;
 pub mod cookie;
+pub mod cookie_database;
 pub mod cookie_storage;
 mod data_loader;
 mod decoder;
@@ -29,14 +34,16 @@ pub mod http_cache;
 pub mod http_loader;
 pub mod image_cache;
 pub mod mime_classifier;
+pub mod preload_cache;
 pub mod resource_thread;
-mod storage_thread;
+pub mod storage_thread;
 pub mod subresource_integrity;
 mod websocket_loader;
 /// An implementation of the [Fetch specification](https://fetch.spec.whatwg.org/)
 pub mod fetch {
     pub mod cors_cache;
     pub mod methods;
+    pub mod orb;
 }
 
 /// A module for re-exports of items used in unit tests.
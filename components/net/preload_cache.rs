@@ -0,0 +1,167 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The responses of the requests of `<link rel=preload>`, which are handed to the first
+//! matching request of the same origin instead of fetching the resource again, even if
+//! the HTTP cache wouldn't store them.
+//! <https://html.spec.whatwg.org/multipage/#map-of-preloaded-resources>
+
+use http::Method;
+use net_traits::request::{CredentialsMode, Destination, Origin, Request, RequestMode};
+use net_traits::response::Response;
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::sync::atomic::Ordering;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a preloaded response waits for the request it was preloaded for.
+const PRELOADED_RESPONSE_LIFETIME: Duration = Duration::from_secs(60);
+
+/// <https://html.spec.whatwg.org/multipage/#preload-key>
+#[derive(Clone, PartialEq)]
+pub struct PreloadKey {
+    origin: ImmutableOrigin,
+    url: ServoUrl,
+    destination: Destination,
+    mode: RequestMode,
+    credentials_mode: CredentialsMode,
+}
+
+impl PreloadKey {
+    /// The key of a request, if it can use a preloaded response.
+    pub fn new(request: &Request) -> Option<PreloadKey> {
+        let origin = match request.origin {
+            Origin::Origin(ref origin) => origin.clone(),
+            Origin::Client => return None,
+        };
+        if request.method != Method::GET {
+            return None;
+        }
+        Some(PreloadKey {
+            origin,
+            url: request.current_url(),
+            destination: request.destination,
+            mode: request.mode.clone(),
+            credentials_mode: request.credentials_mode,
+        })
+    }
+}
+
+enum PreloadedResponse {
+    /// The preload is still being fetched.
+    Pending,
+    /// The response of the preload, with its whole body.
+    Ready(Instant, Response),
+}
+
+#[derive(Default)]
+pub struct PreloadCache {
+    entries: Mutex<Vec<(PreloadKey, PreloadedResponse)>>,
+    /// Notified whenever a pending preload is done.
+    done: Condvar,
+}
+
+impl PreloadCache {
+    /// Note that a preload is being fetched, so that the matching requests wait for it
+    /// rather than fetching the resource again. Returns None if the resource is already
+    /// preloaded, in which case the preload should share the response of the other one.
+    pub fn start(&self, key: PreloadKey) -> Option<PendingPreload> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|&(_, ref preloaded)| match *preloaded {
+            PreloadedResponse::Pending => true,
+            PreloadedResponse::Ready(stored, _) => stored.elapsed() < PRELOADED_RESPONSE_LIFETIME,
+        });
+        if entries.iter().any(|&(ref other, _)| *other == key) {
+            return None;
+        }
+        entries.push((key.clone(), PreloadedResponse::Pending));
+        Some(PendingPreload {
+            cache: self,
+            key,
+            settled: false,
+        })
+    }
+
+    /// Store the response of a preload, or forget it if it failed or has none.
+    fn settle(&self, key: &PreloadKey, response: Option<&Response>) {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|&(ref other, ref preloaded)| {
+            other == key && matches!(*preloaded, PreloadedResponse::Pending)
+        });
+        if let Some(index) = index {
+            match response {
+                Some(response)
+                    if !response.is_network_error() &&
+                        response.termination_reason.is_none() &&
+                        !response.aborted.load(Ordering::Acquire) =>
+                {
+                    entries[index].1 = PreloadedResponse::Ready(Instant::now(), response.clone());
+                },
+                _ => {
+                    entries.remove(index);
+                },
+            }
+        }
+        self.done.notify_all();
+    }
+
+    /// Take the preloaded response of a request, waiting for it if it is still being
+    /// fetched. <https://html.spec.whatwg.org/multipage/#consume-a-preloaded-resource>
+    pub fn consume(&self, key: &PreloadKey) -> Option<Response> {
+        self.wait_for_response(key, true)
+    }
+
+    /// Get the response of a resource which is already preloaded, without consuming it.
+    pub fn get(&self, key: &PreloadKey) -> Option<Response> {
+        self.wait_for_response(key, false)
+    }
+
+    fn wait_for_response(&self, key: &PreloadKey, consume: bool) -> Option<Response> {
+        let mut entries = self.entries.lock().unwrap();
+        loop {
+            let index = entries.iter().position(|&(ref other, _)| other == key)?;
+            let response = match entries[index].1 {
+                PreloadedResponse::Pending => {
+                    entries = self.done.wait(entries).unwrap();
+                    continue;
+                },
+                PreloadedResponse::Ready(stored, _)
+                    if stored.elapsed() >= PRELOADED_RESPONSE_LIFETIME =>
+                {
+                    None
+                },
+                PreloadedResponse::Ready(_, ref response) => Some(response.clone()),
+            };
+            if consume || response.is_none() {
+                entries.remove(index);
+            }
+            return response;
+        }
+    }
+}
+
+/// A preload which is being fetched. It is forgotten if it is dropped before it is finished,
+/// e.g. because its fetch returned early, so that the requests waiting for it fetch the
+/// resource themselves rather than waiting forever.
+pub struct PendingPreload<'a> {
+    cache: &'a PreloadCache,
+    key: PreloadKey,
+    settled: bool,
+}
+
+impl<'a> PendingPreload<'a> {
+    /// Store the response of the preload, or forget it if it failed.
+    pub fn finish(mut self, response: &Response) {
+        self.cache.settle(&self.key, Some(response));
+        self.settled = true;
+    }
+}
+
+impl<'a> Drop for PendingPreload<'a> {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.cache.settle(&self.key, None);
+        }
+    }
+}
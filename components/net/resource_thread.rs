@@ -4,8 +4,12 @@
 
 //! A thread that takes a URL and streams back the binary data.
 
-use crate::connector::{create_http_client, create_tls_config, ALPN_H2_H1};
+use crate::about_loader::AboutPages;
+use crate::client_hints::AcceptChCache;
+use crate::connector::{create_http_client, create_tls_config, Connector, ALPN_H2_H1};
+use crate::content_blocker::ContentBlocker;
 use crate::cookie;
+use crate::cookie_database::CookieDatabase;
 use crate::cookie_storage::CookieStorage;
 use crate::fetch::cors_cache::CorsCache;
 use crate::fetch::methods::{fetch, CancellationListener, FetchContext};
@@ -13,42 +17,47 @@ use crate::filemanager_thread::FileManager;
 use crate::hsts::HstsList;
 use crate::http_cache::HttpCache;
 use crate::http_loader::{http_redirect_fetch, HttpState, HANDLE};
+use crate::preload_cache::PreloadCache;
 use crate::storage_thread::StorageThreadFactory;
 use crate::websocket_loader;
 use crossbeam_channel::Sender;
 use devtools_traits::DevtoolsControlMsg;
 use embedder_traits::resources::{self, Resource};
-use embedder_traits::EmbedderProxy;
+use embedder_traits::{EmbedderProxy, PromptMsg};
 use hyper_serde::Serde;
 use ipc_channel::ipc::{self, IpcReceiver, IpcReceiverSet, IpcSender};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use net_traits::blob_url_store::parse_blob_url;
 use net_traits::filemanager_thread::FileTokenCheck;
-use net_traits::request::{Destination, RequestBuilder};
+use net_traits::protocol_handler::ProtocolRegistry;
+use net_traits::request::{Destination, Initiator, Request, RequestBuilder};
 use net_traits::response::{Response, ResponseInit};
 use net_traits::storage_thread::StorageThreadMsg;
 use net_traits::DiscardFetch;
 use net_traits::FetchTaskTarget;
 use net_traits::WebSocketNetworkEvent;
 use net_traits::{CookieSource, CoreResourceMsg, CoreResourceThread};
-use net_traits::{CustomResponseMediator, FetchChannels};
+use net_traits::{CustomResponseMediator, FetchChannels, ObservedCookies};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
 use net_traits::{ResourceThreads, WebSocketDomAction};
 use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use profile_traits::mem::{Report, ReportKind, ReportsChan};
-use profile_traits::time::ProfilerChan;
+use profile_traits::time::{send_profile_data, ProfilerCategory, ProfilerChan, TimerMetadata};
+use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
 use serde::{Deserialize, Serialize};
 use servo_arc::Arc as ServoArc;
+use servo_config::proxy::set_proxy;
 use servo_url::ServoUrl;
 use std::borrow::{Cow, ToOwned};
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
 use std::fs::{self, File};
 use std::io::prelude::*;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, UNIX_EPOCH};
 
 /// Returns a tuple of (public, private) senders to the new threads.
 pub fn new_resource_threads(
@@ -57,8 +66,10 @@ pub fn new_resource_threads(
     time_profiler_chan: ProfilerChan,
     mem_profiler_chan: MemProfilerChan,
     embedder_proxy: EmbedderProxy,
+    prompt_sender: Sender<PromptMsg>,
     config_dir: Option<PathBuf>,
     certificate_path: Option<String>,
+    protocols: Arc<ProtocolRegistry>,
 ) -> (ResourceThreads, ResourceThreads) {
     let (public_core, private_core) = new_core_resource_thread(
         user_agent,
@@ -66,8 +77,10 @@ pub fn new_resource_threads(
         time_profiler_chan,
         mem_profiler_chan,
         embedder_proxy,
+        prompt_sender,
         config_dir.clone(),
         certificate_path,
+        protocols,
     );
     let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir);
     (
@@ -83,8 +96,10 @@ pub fn new_core_resource_thread(
     time_profiler_chan: ProfilerChan,
     mem_profiler_chan: MemProfilerChan,
     embedder_proxy: EmbedderProxy,
+    prompt_sender: Sender<PromptMsg>,
     config_dir: Option<PathBuf>,
     certificate_path: Option<String>,
+    protocols: Arc<ProtocolRegistry>,
 ) -> (CoreResourceThread, CoreResourceThread) {
     let (public_setup_chan, public_setup_port) = ipc::channel().unwrap();
     let (private_setup_chan, private_setup_port) = ipc::channel().unwrap();
@@ -97,8 +112,12 @@ pub fn new_core_resource_thread(
                 user_agent,
                 devtools_chan,
                 time_profiler_chan,
+                mem_profiler_chan.clone(),
                 embedder_proxy,
+                prompt_sender,
+                config_dir.clone(),
                 certificate_path.clone(),
+                protocols,
             );
 
             let mut channel_manager = ResourceChannelManager {
@@ -124,25 +143,37 @@ struct ResourceChannelManager {
     certificate_path: Option<String>,
 }
 
-fn create_http_states(
-    config_dir: Option<&Path>,
-    certificate_path: Option<String>,
-) -> (Arc<HttpState>, Arc<HttpState>) {
+fn read_certificates(certificate_path: Option<String>) -> String {
+    match certificate_path {
+        Some(ref path) => fs::read_to_string(path).expect("Couldn't not find certificate file"),
+        None => resources::read_string(Resource::SSLCertificates),
+    }
+}
+
+fn create_http_states(config_dir: Option<&Path>, certs: &str) -> (Arc<HttpState>, Arc<HttpState>) {
     let mut hsts_list = HstsList::from_servo_preload();
     let mut auth_cache = AuthCache::new();
-    let http_cache = HttpCache::new();
+    let mut http_cache = HttpCache::new();
     let mut cookie_jar = CookieStorage::new(150);
     if let Some(config_dir) = config_dir {
         read_json_from_file(&mut auth_cache, config_dir, "auth_cache.json");
         read_json_from_file(&mut hsts_list, config_dir, "hsts_list.json");
-        read_json_from_file(&mut cookie_jar, config_dir, "cookie_jar.json");
+        // The cookies of a profile which predates the cookie database are moved into it.
+        let legacy_cookie_jar = config_dir.join("cookie_jar.json");
+        if legacy_cookie_jar.exists() {
+            read_json_from_file(&mut cookie_jar, config_dir, "cookie_jar.json");
+        }
+        match CookieDatabase::open(&config_dir.join("cookies.sqlite")) {
+            Ok(database) => {
+                cookie_jar.set_database(database);
+                let _ = fs::remove_file(&legacy_cookie_jar);
+            },
+            Err(error) => warn!("Could not open the cookie database: {}", error),
+        }
+        http_cache = HttpCache::with_disk_cache(&config_dir.join("http_cache"));
     }
 
-    let certs = match certificate_path {
-        Some(ref path) => fs::read_to_string(path).expect("Couldn't not find certificate file"),
-        None => resources::read_string(Resource::SSLCertificates),
-    };
-
+    let connector = Connector::new(create_tls_config(certs, ALPN_H2_H1));
     let http_state = HttpState {
         hsts_list: RwLock::new(hsts_list),
         cookie_jar: RwLock::new(cookie_jar),
@@ -150,26 +181,33 @@ fn create_http_states(
         history_states: RwLock::new(HashMap::new()),
         http_cache: RwLock::new(http_cache),
         http_cache_state: Mutex::new(HashMap::new()),
-        client: create_http_client(
-            create_tls_config(&certs, ALPN_H2_H1),
-            HANDLE.lock().unwrap().executor(),
-        ),
+        accept_ch_cache: RwLock::new(AcceptChCache::new()),
+        preload_cache: PreloadCache::default(),
+        connector: connector.clone(),
+        client: create_http_client(connector, HANDLE.lock().unwrap().executor()),
     };
 
-    let private_http_state = HttpState {
+    (
+        Arc::new(http_state),
+        Arc::new(create_private_http_state(certs)),
+    )
+}
+
+/// The state of a private browsing session, which is never read from or written to the disk.
+fn create_private_http_state(certs: &str) -> HttpState {
+    let connector = Connector::new(create_tls_config(certs, ALPN_H2_H1));
+    HttpState {
         hsts_list: RwLock::new(HstsList::from_servo_preload()),
         cookie_jar: RwLock::new(CookieStorage::new(150)),
         auth_cache: RwLock::new(AuthCache::new()),
         history_states: RwLock::new(HashMap::new()),
         http_cache: RwLock::new(HttpCache::new()),
         http_cache_state: Mutex::new(HashMap::new()),
-        client: create_http_client(
-            create_tls_config(&certs, ALPN_H2_H1),
-            HANDLE.lock().unwrap().executor(),
-        ),
-    };
-
-    (Arc::new(http_state), Arc::new(private_http_state))
+        accept_ch_cache: RwLock::new(AcceptChCache::new()),
+        preload_cache: PreloadCache::default(),
+        connector: connector.clone(),
+        client: create_http_client(connector, HANDLE.lock().unwrap().executor()),
+    }
 }
 
 impl ResourceChannelManager {
@@ -180,21 +218,27 @@ impl ResourceChannelManager {
         private_receiver: IpcReceiver<CoreResourceMsg>,
         memory_reporter: IpcReceiver<ReportsChan>,
     ) {
-        let (public_http_state, private_http_state) = create_http_states(
-            self.config_dir.as_ref().map(Deref::deref),
-            self.certificate_path.clone(),
-        );
+        let certs = read_certificates(self.certificate_path.clone());
+        let (public_http_state, private_http_state) =
+            create_http_states(self.config_dir.as_ref().map(Deref::deref), &certs);
 
         let mut rx_set = IpcReceiverSet::new().unwrap();
         let private_id = rx_set.add(private_receiver).unwrap();
         let public_id = rx_set.add(public_receiver).unwrap();
         let reporter_id = rx_set.add(memory_reporter).unwrap();
 
+        // The states of the private browsing sessions, by the id of their receiver.
+        let mut private_sessions: HashMap<u64, Arc<HttpState>> = HashMap::new();
+
         loop {
             for receiver in rx_set.select().unwrap().into_iter() {
                 // Handles case where profiler thread shuts down before resource thread.
                 match receiver {
-                    ipc::IpcSelectionResult::ChannelClosed(..) => continue,
+                    ipc::IpcSelectionResult::ChannelClosed(id) => {
+                        // The state of a private session is dropped with its last sender.
+                        private_sessions.remove(&id);
+                        continue;
+                    },
                     _ => {},
                 }
                 let (id, data) = receiver.unwrap();
@@ -204,17 +248,23 @@ impl ResourceChannelManager {
                         self.process_report(msg, &private_http_state, &public_http_state);
                         continue;
                     }
-                } else {
-                    let group = if id == private_id {
-                        &private_http_state
-                    } else {
-                        assert_eq!(id, public_id);
-                        &public_http_state
+                } else if let Ok(msg) = data.to() {
+                    if let CoreResourceMsg::NewPrivateSession(receiver) = msg {
+                        let session_id = rx_set.add(receiver).unwrap();
+                        let http_state = Arc::new(create_private_http_state(&certs));
+                        private_sessions.insert(session_id, http_state);
+                        continue;
+                    }
+                    let group = match private_sessions.get(&id) {
+                        Some(http_state) => http_state,
+                        None if id == private_id => &private_http_state,
+                        None => {
+                            assert_eq!(id, public_id);
+                            &public_http_state
+                        },
                     };
-                    if let Ok(msg) = data.to() {
-                        if !self.process_msg(msg, group) {
-                            return;
-                        }
+                    if !self.process_msg(msg, group) {
+                        return;
                     }
                 }
             }
@@ -268,6 +318,12 @@ impl ResourceChannelManager {
                         .fetch(req_init, None, DiscardFetch, http_state, None)
                 },
             },
+            CoreResourceMsg::PrefetchDns(host) => http_state.connector.prefetch_dns(&host),
+            CoreResourceMsg::Preconnect(url) => {
+                if let Some(preconnect) = http_state.connector.preconnect(&url) {
+                    HANDLE.lock().unwrap().spawn(preconnect);
+                }
+            },
             CoreResourceMsg::DeleteCookies(request) => {
                 http_state
                     .cookie_jar
@@ -299,6 +355,22 @@ impl ResourceChannelManager {
                     .send(cookie_jar.cookies_for_url(&url, source))
                     .unwrap();
             },
+            CoreResourceMsg::ObserveCookiesForUrl(url, consumer, observer) => {
+                let mut cookie_jar = http_state.cookie_jar.write().unwrap();
+                cookie_jar.remove_expired_cookies_for_url(&url);
+                let cookies = cookie_jar.cookies_for_url(&url, CookieSource::NonHTTP);
+                let expiry_time = cookie_jar
+                    .first_expiry_time_for_url(&url, CookieSource::NonHTTP)
+                    .map(|time| {
+                        let time = time.to_timespec();
+                        UNIX_EPOCH + Duration::new(time.sec.max(0) as u64, time.nsec as u32)
+                    });
+                cookie_jar.observe(&url, observer);
+                let _ = consumer.send(ObservedCookies {
+                    cookies,
+                    expiry_time,
+                });
+            },
             CoreResourceMsg::NetworkMediator(mediator_chan) => {
                 self.resource_manager.swmanager_chan = Some(mediator_chan)
             },
@@ -331,6 +403,37 @@ impl ResourceChannelManager {
                 let _ = sender.send(());
             },
             CoreResourceMsg::ToFileManager(msg) => self.resource_manager.filemanager.handle(msg),
+            CoreResourceMsg::AddCrashReport(crash_report) => self
+                .resource_manager
+                .about_pages
+                .add_crash_report(crash_report),
+            CoreResourceMsg::GetElementHidingStylesheet(url, sender) => {
+                let stylesheet = if pref!(network.content_blocker.enabled) {
+                    self.resource_manager
+                        .content_blocker
+                        .element_hiding_stylesheet(&url)
+                } else {
+                    None
+                };
+                let _ = sender.send(stylesheet);
+            },
+            CoreResourceMsg::GetBlockedCount(pipeline_ids, sender) => {
+                let content_blocker = &self.resource_manager.content_blocker;
+                let _ = sender.send(content_blocker.blocked_count(&pipeline_ids));
+            },
+            CoreResourceMsg::PipelineExited(pipeline_id) => self
+                .resource_manager
+                .content_blocker
+                .remove_pipeline(pipeline_id),
+            CoreResourceMsg::SetProxy(url, bypass) => {
+                let bypass: Vec<&str> = bypass.iter().map(|host| &**host).collect();
+                if let Err(e) = set_proxy(url.as_ref().map(|url| &**url), &bypass) {
+                    warn!("Ignoring the invalid proxy ({}).", e);
+                }
+            },
+            CoreResourceMsg::NewPrivateSession(_) => {
+                unreachable!("Private sessions are started by the channel manager")
+            },
             CoreResourceMsg::Exit(sender) => {
                 if let Some(ref config_dir) = self.config_dir {
                     match http_state.auth_cache.read() {
@@ -340,7 +443,7 @@ impl ResourceChannelManager {
                         Err(_) => warn!("Error writing auth cache to disk"),
                     }
                     match http_state.cookie_jar.read() {
-                        Ok(jar) => write_json_to_file(&*jar, config_dir, "cookie_jar.json"),
+                        Ok(jar) => jar.save_to_database(),
                         Err(_) => warn!("Error writing cookie jar to disk"),
                     }
                     match http_state.hsts_list.read() {
@@ -396,13 +499,23 @@ where
     let path = config_dir.join(filename);
     let display = path.display();
 
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
+    // The data is written to a temporary file which then replaces the previous one, so that
+    // the previous data is kept if writing is interrupted.
+    let temp_path = config_dir.join(format!("{}.tmp", filename));
+    let mut file = match File::create(&temp_path) {
+        Err(why) => panic!("couldn't create {}: {}", temp_path.display(), why),
         Ok(file) => file,
     };
 
-    match file.write_all(json_encoded.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
+    if let Err(why) = file
+        .write_all(json_encoded.as_bytes())
+        .and_then(|()| file.sync_all())
+    {
+        panic!("couldn't write to {}: {}", temp_path.display(), why);
+    }
+
+    match fs::rename(&temp_path, &path) {
+        Err(why) => panic!("couldn't replace {}: {}", display, why),
         Ok(_) => println!("successfully wrote to {}", display),
     }
 }
@@ -431,10 +544,79 @@ pub struct AuthCache {
 pub struct CoreResourceManager {
     user_agent: Cow<'static, str>,
     devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+    prompt_sender: Sender<PromptMsg>,
     swmanager_chan: Option<IpcSender<CustomResponseMediator>>,
     filemanager: FileManager,
+    about_pages: Arc<AboutPages>,
+    protocols: Arc<ProtocolRegistry>,
+    content_blocker: Arc<ContentBlocker>,
     thread_pool: Arc<CoreResourceThreadPool>,
     certificate_path: Option<String>,
+    time_profiler_chan: ProfilerChan,
+}
+
+/// The priority of the work of the thread-pool, whose pending work of the highest priority
+/// is run first once every thread is busy.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum WorkPriority {
+    Lowest,
+    Low,
+    Normal,
+    High,
+    Highest,
+}
+
+impl WorkPriority {
+    /// The priority of a fetch, so that documents and the resources which block their
+    /// rendering are requested before images and media, which also decides the order of
+    /// the requests multiplexed over an HTTP/2 connection.
+    pub fn of_fetch(destination: Destination) -> WorkPriority {
+        match destination {
+            Destination::Document => WorkPriority::Highest,
+            Destination::Style |
+            Destination::Script |
+            Destination::Font |
+            Destination::Worker |
+            Destination::SharedWorker |
+            Destination::ServiceWorker => WorkPriority::High,
+            Destination::Image |
+            Destination::Audio |
+            Destination::Video |
+            Destination::Track |
+            Destination::Report => WorkPriority::Low,
+            _ => WorkPriority::Normal,
+        }
+    }
+}
+
+/// Work waiting for a thread of the pool, which is ordered by priority, and then by the
+/// order in which it was spawned.
+struct PendingWork {
+    priority: WorkPriority,
+    sequence_number: u64,
+    work: Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for PendingWork {
+    fn eq(&self, other: &PendingWork) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PendingWork {}
+
+impl PartialOrd for PendingWork {
+    fn partial_cmp(&self, other: &PendingWork) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PendingWork {
+    fn cmp(&self, other: &PendingWork) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence_number.cmp(&self.sequence_number))
+    }
 }
 
 /// The state of the thread-pool used by CoreResource.
@@ -443,6 +625,10 @@ struct ThreadPoolState {
     active_workers: u32,
     /// Whether the pool can spawn additional work.
     active: bool,
+    /// The work which was spawned, but not yet picked by a thread.
+    pending_work: BinaryHeap<PendingWork>,
+    /// The number of pieces of work spawned so far.
+    spawned_work: u64,
 }
 
 impl ThreadPoolState {
@@ -450,9 +636,26 @@ impl ThreadPoolState {
         ThreadPoolState {
             active_workers: 0,
             active: true,
+            pending_work: BinaryHeap::new(),
+            spawned_work: 0,
         }
     }
 
+    /// Add work to run once a thread picks it.
+    fn push_work(&mut self, priority: WorkPriority, work: Box<dyn FnOnce() + Send>) {
+        self.pending_work.push(PendingWork {
+            priority,
+            sequence_number: self.spawned_work,
+            work,
+        });
+        self.spawned_work += 1;
+    }
+
+    /// Pick the pending work of the highest priority.
+    fn pop_work(&mut self) -> Option<Box<dyn FnOnce() + Send>> {
+        self.pending_work.pop().map(|pending| pending.work)
+    }
+
     /// Is the pool still able to spawn new work?
     pub fn is_active(&self) -> bool {
         self.active
@@ -501,6 +704,15 @@ impl CoreResourceThreadPool {
     /// because if we do not perform work,
     /// it is because the system as a whole is exiting.
     pub fn spawn<OP>(&self, work: OP)
+    where
+        OP: FnOnce() + Send + 'static,
+    {
+        self.spawn_with_priority(WorkPriority::Normal, work)
+    }
+
+    /// Spawn work on the thread-pool, if still active, which runs before the pending work
+    /// of a lower priority.
+    pub fn spawn_with_priority<OP>(&self, priority: WorkPriority, work: OP)
     where
         OP: FnOnce() + Send + 'static,
     {
@@ -508,6 +720,7 @@ impl CoreResourceThreadPool {
             let mut state = self.state.lock().unwrap();
             if state.is_active() {
                 state.increment_active();
+                state.push_work(priority, Box::new(work));
             } else {
                 // Don't spawn any work.
                 return;
@@ -516,17 +729,22 @@ impl CoreResourceThreadPool {
 
         let state = self.state.clone();
 
+        // Each job of the pool runs one piece of the pending work, which is not necessarily
+        // the one it was spawned for.
         self.pool.spawn(move || {
-            {
+            let work = {
                 let mut state = state.lock().unwrap();
                 if !state.is_active() {
                     // Decrement number of active workers and return,
                     // without doing any work.
                     return state.decrement_active();
                 }
-            }
+                state.pop_work()
+            };
             // Perform work.
-            work();
+            if let Some(work) = work {
+                work();
+            }
             {
                 // Decrement number of active workers.
                 let mut state = state.lock().unwrap();
@@ -562,23 +780,84 @@ impl CoreResourceThreadPool {
     }
 }
 
+/// Report the phases of a fetch which got a response to the time profiler, which traces
+/// them, with the URL of the response after the redirects.
+fn report_fetch_phases(
+    time_profiler_chan: &ProfilerChan,
+    request: &Request,
+    response: &Response,
+    timing: &ResourceFetchTiming,
+) {
+    if response.is_network_error() {
+        return;
+    }
+    let url = response
+        .actual_response()
+        .url()
+        .cloned()
+        .unwrap_or_else(|| request.current_url());
+    let iframe = match request.destination {
+        Destination::Document if !request.top_level_navigation => TimerMetadataFrameType::IFrame,
+        _ => TimerMetadataFrameType::RootWindow,
+    };
+    let phases = [
+        (
+            ProfilerCategory::NetFetchRedirect,
+            timing.redirect_start,
+            timing.redirect_end,
+        ),
+        (
+            ProfilerCategory::NetFetchRequest,
+            timing.request_start,
+            timing.response_start,
+        ),
+        (
+            ProfilerCategory::NetFetchResponse,
+            timing.response_start,
+            timing.response_end,
+        ),
+    ];
+    for &(category, start, end) in &phases {
+        // The phases which didn't happen, such as the redirects or the request of a
+        // cached response, are zero.
+        if start == 0 || end < start {
+            continue;
+        }
+        let metadata = TimerMetadata {
+            url: url.as_str().into(),
+            iframe: iframe.clone(),
+            incremental: TimerMetadataReflowType::FirstReflow,
+        };
+        send_profile_data(category, Some(metadata), time_profiler_chan, start, end, 0, 0);
+    }
+}
+
 impl CoreResourceManager {
     pub fn new(
         user_agent: Cow<'static, str>,
         devtools_channel: Option<Sender<DevtoolsControlMsg>>,
-        _profiler_chan: ProfilerChan,
+        time_profiler_chan: ProfilerChan,
+        mem_profiler_chan: MemProfilerChan,
         embedder_proxy: EmbedderProxy,
+        prompt_sender: Sender<PromptMsg>,
+        config_dir: Option<PathBuf>,
         certificate_path: Option<String>,
+        protocols: Arc<ProtocolRegistry>,
     ) -> CoreResourceManager {
         let pool = CoreResourceThreadPool::new(16);
         let pool_handle = Arc::new(pool);
         CoreResourceManager {
             user_agent: user_agent,
             devtools_chan: devtools_channel,
+            prompt_sender: prompt_sender,
             swmanager_chan: None,
             filemanager: FileManager::new(embedder_proxy, Arc::downgrade(&pool_handle)),
+            about_pages: Arc::new(AboutPages::new(mem_profiler_chan, config_dir)),
+            protocols: protocols,
+            content_blocker: Arc::new(ContentBlocker::from_prefs()),
             thread_pool: pool_handle,
             certificate_path,
+            time_profiler_chan,
         }
     }
 
@@ -616,7 +895,12 @@ impl CoreResourceManager {
         let http_state = http_state.clone();
         let ua = self.user_agent.clone();
         let dc = self.devtools_chan.clone();
+        let prompt_sender = self.prompt_sender.clone();
         let filemanager = self.filemanager.clone();
+        let about_pages = self.about_pages.clone();
+        let protocols = self.protocols.clone();
+        let content_blocker = self.content_blocker.clone();
+        let time_profiler_chan = self.time_profiler_chan.clone();
 
         let timing_type = match request_builder.destination {
             Destination::Document => ResourceTimingType::Navigation,
@@ -647,22 +931,32 @@ impl CoreResourceManager {
             _ => (FileTokenCheck::NotRequired, None),
         };
 
-        self.thread_pool.spawn(move || {
+        // Speculative fetches only use the threads which no other fetch is waiting for.
+        let priority = if request.initiator == Initiator::Prefetch {
+            WorkPriority::Lowest
+        } else {
+            WorkPriority::of_fetch(request.destination)
+        };
+        self.thread_pool.spawn_with_priority(priority, move || {
             // XXXManishearth: Check origin against pipeline id (also ensure that the mode is allowed)
             // todo load context / mimesniff in fetch
             // todo referrer policy?
             // todo service worker stuff
             let context = FetchContext {
                 state: http_state,
+                about_pages: about_pages,
+                protocols: protocols,
+                content_blocker: content_blocker,
                 user_agent: ua,
                 devtools_chan: dc,
+                prompt_sender: prompt_sender,
                 filemanager: filemanager,
                 file_token,
                 cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(cancel_chan))),
                 timing: ServoArc::new(Mutex::new(ResourceFetchTiming::new(request.timing_type()))),
             };
 
-            match res_init_ {
+            let response = match res_init_ {
                 Some(res_init) => {
                     let response = Response::from_init(res_init, timing_type);
                     http_redirect_fetch(
@@ -677,6 +971,12 @@ impl CoreResourceManager {
                 },
                 None => fetch(&mut request, &mut sender, &context),
             };
+            report_fetch_phases(
+                &time_profiler_chan,
+                &request,
+                &response,
+                &context.timing.lock().unwrap(),
+            );
 
             // Remove token after fetch.
             if let Some(id) = blob_url_file_id.as_ref() {
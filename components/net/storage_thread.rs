@@ -2,9 +2,12 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::cache_storage::CacheStorage;
 use crate::resource_thread;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
-use net_traits::storage_thread::{StorageThreadMsg, StorageType};
+use msg::constellation_msg::TopLevelBrowsingContextId;
+use net_traits::storage_thread::QUOTA_SIZE_LIMIT;
+use net_traits::storage_thread::{StorageMutation, StorageThreadMsg, StorageType};
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
 use std::collections::BTreeMap;
@@ -12,8 +15,6 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::thread;
 
-const QUOTA_SIZE_LIMIT: usize = 5 * 1024 * 1024;
-
 pub trait StorageThreadFactory {
     fn new(config_dir: Option<PathBuf>) -> Self;
 }
@@ -32,10 +33,17 @@ impl StorageThreadFactory for IpcSender<StorageThreadMsg> {
     }
 }
 
+/// The storage data of each origin, with the number of bytes of its keys and values.
+type OriginData = HashMap<String, (usize, BTreeMap<String, String>)>;
+
 struct StorageManager {
     port: IpcReceiver<StorageThreadMsg>,
-    session_data: HashMap<String, (usize, BTreeMap<String, String>)>,
-    local_data: HashMap<String, (usize, BTreeMap<String, String>)>,
+    /// The session storage data of each top-level browsing context, which is kept across
+    /// its navigations.
+    session_data: HashMap<TopLevelBrowsingContextId, OriginData>,
+    local_data: OriginData,
+    /// The caches of the Cache API of each origin.
+    cache_storage: CacheStorage,
     config_dir: Option<PathBuf>,
 }
 
@@ -49,6 +57,7 @@ impl StorageManager {
             port: port,
             session_data: HashMap::new(),
             local_data: local_data,
+            cache_storage: CacheStorage::new(config_dir.clone()),
             config_dir: config_dir,
         }
     }
@@ -57,30 +66,64 @@ impl StorageManager {
 impl StorageManager {
     fn start(&mut self) {
         loop {
-            match self.port.recv().unwrap() {
-                StorageThreadMsg::Length(sender, url, storage_type) => {
-                    self.length(sender, url, storage_type)
+            let msg = match self.port.recv() {
+                Ok(msg) => msg,
+                // The storage of a private session is discarded with its last sender.
+                Err(_) => break,
+            };
+            match msg {
+                StorageThreadMsg::Snapshot(sender, browsing_context, url, storage_type) => {
+                    self.snapshot(sender, browsing_context, url, storage_type)
+                },
+                StorageThreadMsg::Mutate(
+                    sender,
+                    browsing_context,
+                    url,
+                    storage_type,
+                    mutations,
+                ) => {
+                    let origin = self.origin_as_string(url.clone());
+                    let rejected = self.mutate(browsing_context, url, storage_type, mutations);
+                    if let StorageType::Local = storage_type {
+                        self.save_state()
+                    }
+                    let other_size = self.other_size(browsing_context, &origin, storage_type);
+                    let _ = sender.send((rejected, other_size));
+                },
+                StorageThreadMsg::CloneSessionStorage(source, target, url) => {
+                    self.clone_session_storage(source, target, url)
+                },
+                StorageThreadMsg::GetSessionStorage(sender, browsing_context) => {
+                    self.get_session_storage(sender, browsing_context)
+                },
+                StorageThreadMsg::SetSessionStorage(browsing_context, data) => {
+                    self.set_session_storage(browsing_context, data)
                 },
-                StorageThreadMsg::Key(sender, url, storage_type, index) => {
-                    self.key(sender, url, storage_type, index)
+                StorageThreadMsg::DiscardSessionStorage(browsing_context) => {
+                    self.session_data.remove(&browsing_context);
                 },
-                StorageThreadMsg::Keys(sender, url, storage_type) => {
-                    self.keys(sender, url, storage_type)
+                StorageThreadMsg::HasCache(sender, origin, name) => {
+                    let _ = sender.send(self.cache_storage.has(&origin, &name));
                 },
-                StorageThreadMsg::SetItem(sender, url, storage_type, name, value) => {
-                    self.set_item(sender, url, storage_type, name, value);
-                    self.save_state()
+                StorageThreadMsg::OpenCache(sender, origin, name) => {
+                    let _ = sender.send(self.cache_storage.open(&origin, name));
                 },
-                StorageThreadMsg::GetItem(sender, url, storage_type, name) => {
-                    self.request_item(sender, url, storage_type, name)
+                StorageThreadMsg::DeleteCache(sender, origin, name) => {
+                    let _ = sender.send(self.cache_storage.delete(&origin, &name));
                 },
-                StorageThreadMsg::RemoveItem(sender, url, storage_type, name) => {
-                    self.remove_item(sender, url, storage_type, name);
-                    self.save_state()
+                StorageThreadMsg::CacheNames(sender, origin) => {
+                    let _ = sender.send(self.cache_storage.names(&origin));
                 },
-                StorageThreadMsg::Clear(sender, url, storage_type) => {
-                    self.clear(sender, url, storage_type);
-                    self.save_state()
+                StorageThreadMsg::QueryCache(sender, origin, target, request, options) => {
+                    let _ = sender.send(self.cache_storage.query(
+                        &origin,
+                        target,
+                        request.as_ref(),
+                        options,
+                    ));
+                },
+                StorageThreadMsg::BatchCacheOperations(sender, origin, cache, operations) => {
+                    let _ = sender.send(self.cache_storage.batch(&origin, cache, operations));
                 },
                 StorageThreadMsg::Exit(sender) => {
                     // Nothing to do since we save localstorage set eagerly.
@@ -99,175 +142,183 @@ impl StorageManager {
 
     fn select_data(
         &self,
+        browsing_context: TopLevelBrowsingContextId,
         storage_type: StorageType,
-    ) -> &HashMap<String, (usize, BTreeMap<String, String>)> {
+    ) -> Option<&OriginData> {
         match storage_type {
-            StorageType::Session => &self.session_data,
-            StorageType::Local => &self.local_data,
+            StorageType::Session => self.session_data.get(&browsing_context),
+            StorageType::Local => Some(&self.local_data),
         }
     }
 
     fn select_data_mut(
         &mut self,
+        browsing_context: TopLevelBrowsingContextId,
         storage_type: StorageType,
-    ) -> &mut HashMap<String, (usize, BTreeMap<String, String>)> {
+    ) -> &mut OriginData {
         match storage_type {
-            StorageType::Session => &mut self.session_data,
+            StorageType::Session => self
+                .session_data
+                .entry(browsing_context)
+                .or_insert_with(HashMap::new),
             StorageType::Local => &mut self.local_data,
         }
     }
 
-    fn length(&self, sender: IpcSender<usize>, url: ServoUrl, storage_type: StorageType) {
-        let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
-        sender
-            .send(data.get(&origin).map_or(0, |&(_, ref entry)| entry.len()))
-            .unwrap();
+    /// The number of bytes which the storage area of an origin holds.
+    fn size(
+        &self,
+        browsing_context: TopLevelBrowsingContextId,
+        origin: &str,
+        storage_type: StorageType,
+    ) -> usize {
+        self.select_data(browsing_context, storage_type)
+            .and_then(|data| data.get(origin))
+            .map_or(0, |&(total, _)| total)
     }
 
-    fn key(
+    /// The number of bytes which the other storage area of an origin holds, since the local
+    /// and session storage areas of an origin share the quota.
+    fn other_size(
         &self,
-        sender: IpcSender<Option<String>>,
-        url: ServoUrl,
+        browsing_context: TopLevelBrowsingContextId,
+        origin: &str,
         storage_type: StorageType,
-        index: u32,
-    ) {
-        let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
-        let key = data
-            .get(&origin)
-            .and_then(|&(_, ref entry)| entry.keys().nth(index as usize))
-            .cloned();
-        sender.send(key).unwrap();
+    ) -> usize {
+        let other_type = match storage_type {
+            StorageType::Local => StorageType::Session,
+            StorageType::Session => StorageType::Local,
+        };
+        self.size(browsing_context, origin, other_type)
     }
 
-    fn keys(&self, sender: IpcSender<Vec<String>>, url: ServoUrl, storage_type: StorageType) {
+    fn snapshot(
+        &self,
+        sender: IpcSender<(BTreeMap<String, String>, usize)>,
+        browsing_context: TopLevelBrowsingContextId,
+        url: ServoUrl,
+        storage_type: StorageType,
+    ) {
         let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
-        let keys = data
-            .get(&origin)
-            .map_or(vec![], |&(_, ref entry)| entry.keys().cloned().collect());
-
-        sender.send(keys).unwrap();
+        let items = self
+            .select_data(browsing_context, storage_type)
+            .and_then(|data| data.get(&origin))
+            .map_or(BTreeMap::new(), |&(_, ref entry)| entry.clone());
+        let other_size = self.other_size(browsing_context, &origin, storage_type);
+        let _ = sender.send((items, other_size));
     }
 
-    /// Sends Ok(changed, Some(old_value)) in case there was a previous
-    /// value with the same key name but with different value name
-    /// otherwise sends Err(()) to indicate that the operation would result in
-    /// exceeding the quota limit
-    fn set_item(
+    /// Copy the session storage data of the origin of a document to the top-level browsing
+    /// context which it opened.
+    /// <https://html.spec.whatwg.org/multipage/#copy-the-session-storage-bottle>
+    fn clone_session_storage(
         &mut self,
-        sender: IpcSender<Result<(bool, Option<String>), ()>>,
+        source: TopLevelBrowsingContextId,
+        target: TopLevelBrowsingContextId,
         url: ServoUrl,
-        storage_type: StorageType,
-        name: String,
-        value: String,
     ) {
         let origin = self.origin_as_string(url);
-
-        let (this_storage_size, other_storage_size) = {
-            let local_data = self.select_data(StorageType::Local);
-            let session_data = self.select_data(StorageType::Session);
-            let local_data_size = local_data.get(&origin).map_or(0, |&(total, _)| total);
-            let session_data_size = session_data.get(&origin).map_or(0, |&(total, _)| total);
-            match storage_type {
-                StorageType::Local => (local_data_size, session_data_size),
-                StorageType::Session => (session_data_size, local_data_size),
-            }
+        let entry = match self
+            .session_data
+            .get(&source)
+            .and_then(|data| data.get(&origin))
+        {
+            Some(entry) => entry.clone(),
+            None => return,
         };
-
-        let data = self.select_data_mut(storage_type);
-        if !data.contains_key(&origin) {
-            data.insert(origin.clone(), (0, BTreeMap::new()));
-        }
-
-        let message = data
-            .get_mut(&origin)
-            .map(|&mut (ref mut total, ref mut entry)| {
-                let mut new_total_size = this_storage_size + value.as_bytes().len();
-                if let Some(old_value) = entry.get(&name) {
-                    new_total_size -= old_value.as_bytes().len();
-                } else {
-                    new_total_size += name.as_bytes().len();
-                }
-
-                if (new_total_size + other_storage_size) > QUOTA_SIZE_LIMIT {
-                    return Err(());
-                }
-
-                let message =
-                    entry
-                        .insert(name.clone(), value.clone())
-                        .map_or(Ok((true, None)), |old| {
-                            if old == value {
-                                Ok((false, None))
-                            } else {
-                                Ok((true, Some(old)))
-                            }
-                        });
-                *total = new_total_size;
-                message
-            })
-            .unwrap();
-        sender.send(message).unwrap();
+        self.select_data_mut(target, StorageType::Session)
+            .insert(origin, entry);
     }
 
-    fn request_item(
+    fn get_session_storage(
         &self,
-        sender: IpcSender<Option<String>>,
-        url: ServoUrl,
-        storage_type: StorageType,
-        name: String,
+        sender: IpcSender<BTreeMap<String, BTreeMap<String, String>>>,
+        browsing_context: TopLevelBrowsingContextId,
     ) {
-        let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
-        sender
-            .send(
-                data.get(&origin)
-                    .and_then(|&(_, ref entry)| entry.get(&name))
-                    .map(String::clone),
-            )
-            .unwrap();
+        let data = self
+            .session_data
+            .get(&browsing_context)
+            .map_or(BTreeMap::new(), |data| {
+                data.iter()
+                    .map(|(origin, &(_, ref entry))| (origin.clone(), entry.clone()))
+                    .collect()
+            });
+        let _ = sender.send(data);
     }
 
-    /// Sends Some(old_value) in case there was a previous value with the key name, otherwise sends None
-    fn remove_item(
+    fn set_session_storage(
         &mut self,
-        sender: IpcSender<Option<String>>,
-        url: ServoUrl,
-        storage_type: StorageType,
-        name: String,
+        browsing_context: TopLevelBrowsingContextId,
+        data: BTreeMap<String, BTreeMap<String, String>>,
     ) {
-        let origin = self.origin_as_string(url);
-        let data = self.select_data_mut(storage_type);
-        let old_value = data
-            .get_mut(&origin)
-            .and_then(|&mut (ref mut total, ref mut entry)| {
-                entry.remove(&name).and_then(|old| {
-                    *total -= name.as_bytes().len() + old.as_bytes().len();
-                    Some(old)
-                })
-            });
-        sender.send(old_value).unwrap();
+        let data = data
+            .into_iter()
+            .map(|(origin, entry)| {
+                let total = entry
+                    .iter()
+                    .map(|(name, value)| name.as_bytes().len() + value.as_bytes().len())
+                    .sum();
+                (origin, (total, entry))
+            })
+            .collect();
+        self.session_data.insert(browsing_context, data);
     }
 
-    fn clear(&mut self, sender: IpcSender<bool>, url: ServoUrl, storage_type: StorageType) {
+    /// Apply the mutations which a document made in a task, skipping the items which would
+    /// exceed the quota. The document already checked them against its snapshot, but the
+    /// other storage area of the origin may have grown since.
+    ///
+    /// Returns the values kept for the keys of the skipped items, which the document rolls
+    /// its snapshot back to.
+    fn mutate(
+        &mut self,
+        browsing_context: TopLevelBrowsingContextId,
+        url: ServoUrl,
+        storage_type: StorageType,
+        mutations: Vec<StorageMutation>,
+    ) -> Vec<(String, Option<String>)> {
         let origin = self.origin_as_string(url);
-        let data = self.select_data_mut(storage_type);
-        sender
-            .send(
-                data.get_mut(&origin)
-                    .map_or(false, |&mut (ref mut total, ref mut entry)| {
-                        if !entry.is_empty() {
-                            entry.clear();
-                            *total = 0;
-                            true
-                        } else {
-                            false
+        let other_size = self.other_size(browsing_context, &origin, storage_type);
+        let mut rejected = vec![];
+        let data = self.select_data_mut(browsing_context, storage_type);
+        let &mut (ref mut total, ref mut entry) =
+            data.entry(origin).or_insert_with(|| (0, BTreeMap::new()));
+        for mutation in mutations {
+            match mutation {
+                StorageMutation::SetItem(name, value) => {
+                    let old_size = entry
+                        .get(&name)
+                        .map_or(0, |old| name.as_bytes().len() + old.as_bytes().len());
+                    let new_total_size =
+                        *total - old_size + name.as_bytes().len() + value.as_bytes().len();
+                    if new_total_size + other_size > QUOTA_SIZE_LIMIT {
+                        warn!("Skipping a storage item which exceeds the quota.");
+                        if !rejected.contains(&name) {
+                            rejected.push(name);
                         }
-                    }),
-            )
-            .unwrap();
+                        continue;
+                    }
+                    entry.insert(name, value);
+                    *total = new_total_size;
+                },
+                StorageMutation::RemoveItem(name) => {
+                    if let Some(old) = entry.remove(&name) {
+                        *total -= name.as_bytes().len() + old.as_bytes().len();
+                    }
+                },
+                StorageMutation::Clear => {
+                    entry.clear();
+                    *total = 0;
+                },
+            }
+        }
+        rejected
+            .into_iter()
+            .map(|name| {
+                let value = entry.get(&name).cloned();
+                (name, value)
+            })
+            .collect()
     }
 
     fn origin_as_string(&self, url: ServoUrl) -> String {
@@ -0,0 +1,328 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use msg::constellation_msg::TEST_PIPELINE_ID;
+use net::content_blocker::{Classification, ContentBlocker};
+use net_traits::request::{Destination, Origin, Request};
+use servo_config::set_pref;
+use servo_url::ServoUrl;
+use std::time::{Duration, Instant};
+
+fn content_blocker(list: &str) -> ContentBlocker {
+    let mut content_blocker = ContentBlocker::default();
+    content_blocker.add_list(list);
+    content_blocker
+}
+
+fn classify(
+    content_blocker: &ContentBlocker,
+    url: &str,
+    source_host: &str,
+    destination: Destination,
+) -> Classification {
+    let url = ServoUrl::parse(url).unwrap();
+    content_blocker.classify(&url, Some(source_host), destination)
+}
+
+#[test]
+fn test_content_blocker_blocks_host_rules() {
+    let content_blocker = content_blocker("[Adblock Plus 2.0]\n! Title: test\n||ads.example^\n");
+
+    let blocked = [
+        "https://ads.example/banner.js",
+        "https://cdn.ads.example/banner.js",
+        "http://ads.example:8080/",
+    ];
+    for url in &blocked {
+        assert_eq!(
+            classify(&content_blocker, url, "news.test", Destination::Script),
+            Classification::Block,
+            "{} isn't blocked",
+            url
+        );
+    }
+
+    let allowed = [
+        "https://ads.example.org/banner.js",
+        "https://badads.example/banner.js",
+        "https://news.test/?ref=ads.example",
+    ];
+    for url in &allowed {
+        assert_eq!(
+            classify(&content_blocker, url, "news.test", Destination::Script),
+            Classification::Allow,
+            "{} is blocked",
+            url
+        );
+    }
+}
+
+#[test]
+fn test_content_blocker_matches_wildcards_and_separators() {
+    let content_blocker = content_blocker("/banner/*/img^\n|https://tracker.\nswf|\n");
+
+    let classification = |url| classify(&content_blocker, url, "news.test", Destination::Image);
+    assert_eq!(
+        classification("https://news.test/banner/foo/img?size=1"),
+        Classification::Block
+    );
+    assert_eq!(
+        classification("https://news.test/banner/foo/img"),
+        Classification::Block
+    );
+    assert_eq!(
+        classification("https://news.test/banner/foo/imgs"),
+        Classification::Allow
+    );
+    assert_eq!(
+        classification("https://tracker.test/pixel.gif"),
+        Classification::Block
+    );
+    assert_eq!(
+        classification("http://tracker.test/pixel.gif"),
+        Classification::Allow
+    );
+    assert_eq!(
+        classification("https://news.test/movie.swf"),
+        Classification::Block
+    );
+    assert_eq!(
+        classification("https://news.test/movie.swf?autoplay"),
+        Classification::Allow
+    );
+}
+
+#[test]
+fn test_content_blocker_matches_many_wildcards_quickly() {
+    let content_blocker = content_blocker("a*a*a*a*a*a*a*a*a*a*b\n");
+
+    let url = format!("https://news.test/{}", "a".repeat(4096));
+    let start = Instant::now();
+    assert_eq!(
+        classify(&content_blocker, &url, "news.test", Destination::Image),
+        Classification::Allow
+    );
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(
+        classify(
+            &content_blocker,
+            &(url + "b"),
+            "news.test",
+            Destination::Image
+        ),
+        Classification::Block
+    );
+}
+
+#[test]
+fn test_content_blocker_options() {
+    let content_blocker = content_blocker(
+        "||widgets.test^$script,third-party\n\
+         ||fonts.test^$~font\n\
+         ||cdn.test/ads/$domain=news.test|~sport.news.test\n\
+         ||unknown.test^$some-unknown-option\n",
+    );
+
+    // The type and party of the request.
+    let url = "https://widgets.test/share.js";
+    assert_eq!(
+        classify(&content_blocker, url, "news.test", Destination::Script),
+        Classification::Block
+    );
+    assert_eq!(
+        classify(&content_blocker, url, "news.test", Destination::Image),
+        Classification::Allow
+    );
+    assert_eq!(
+        classify(
+            &content_blocker,
+            url,
+            "www.widgets.test",
+            Destination::Script
+        ),
+        Classification::Allow
+    );
+
+    // The negated types.
+    let url = "https://fonts.test/font.woff";
+    assert_eq!(
+        classify(&content_blocker, url, "news.test", Destination::Font),
+        Classification::Allow
+    );
+    assert_eq!(
+        classify(&content_blocker, url, "news.test", Destination::Style),
+        Classification::Block
+    );
+
+    // The domains of the page.
+    let url = "https://cdn.test/ads/1.png";
+    assert_eq!(
+        classify(&content_blocker, url, "www.news.test", Destination::Image),
+        Classification::Block
+    );
+    assert_eq!(
+        classify(&content_blocker, url, "sport.news.test", Destination::Image),
+        Classification::Allow
+    );
+    assert_eq!(
+        classify(&content_blocker, url, "blog.test", Destination::Image),
+        Classification::Allow
+    );
+
+    // The rules with unknown options are ignored.
+    let url = "https://unknown.test/";
+    assert_eq!(
+        classify(&content_blocker, url, "news.test", Destination::Script),
+        Classification::Allow
+    );
+}
+
+#[test]
+fn test_content_blocker_exceptions() {
+    let content_blocker = content_blocker(
+        "||ads.test^\n\
+         @@||ads.test/allowed/\n\
+         ||tracker.test^$important\n\
+         @@||tracker.test^\n",
+    );
+
+    assert_eq!(
+        classify(
+            &content_blocker,
+            "https://ads.test/banner.js",
+            "news.test",
+            Destination::Script
+        ),
+        Classification::Block
+    );
+    assert_eq!(
+        classify(
+            &content_blocker,
+            "https://ads.test/allowed/banner.js",
+            "news.test",
+            Destination::Script
+        ),
+        Classification::Allow
+    );
+    assert_eq!(
+        classify(
+            &content_blocker,
+            "https://tracker.test/pixel.gif",
+            "news.test",
+            Destination::Image
+        ),
+        Classification::Block
+    );
+}
+
+#[test]
+fn test_content_blocker_redirects() {
+    let content_blocker = content_blocker(
+        "||analytics.test/ga.js$script,redirect=noopjs\n\
+         ||pixels.test^$image,redirect-rule=1x1.gif\n\
+         ||pixels.test/track/\n",
+    );
+
+    assert_eq!(
+        classify(
+            &content_blocker,
+            "https://analytics.test/ga.js",
+            "news.test",
+            Destination::Script
+        ),
+        Classification::Redirect(ServoUrl::parse("data:application/javascript,").unwrap())
+    );
+
+    // A `redirect-rule` only rewrites the requests blocked by another rule.
+    assert_eq!(
+        classify(
+            &content_blocker,
+            "https://pixels.test/pixel.gif",
+            "news.test",
+            Destination::Image
+        ),
+        Classification::Allow
+    );
+    match classify(
+        &content_blocker,
+        "https://pixels.test/track/pixel.gif",
+        "news.test",
+        Destination::Image,
+    ) {
+        Classification::Redirect(url) => assert!(url.as_str().starts_with("data:image/gif")),
+        classification => panic!("Unexpected classification {:?}", classification),
+    }
+}
+
+#[test]
+fn test_content_blocker_element_hiding_stylesheet() {
+    let content_blocker = content_blocker(
+        "##.ad-banner\n\
+         news.test,~sport.news.test##.sponsored\n\
+         blog.test#@#.ad-banner\n\
+         news.test#?#.story:has-text(Sponsored)\n\
+         news.test##+js(nobab)\n\
+         ##div { color: red }\n",
+    );
+
+    let stylesheet =
+        |url| content_blocker.element_hiding_stylesheet(&ServoUrl::parse(url).unwrap());
+    assert_eq!(
+        stylesheet("https://www.news.test/").unwrap(),
+        ".ad-banner { display: none !important; }\n\
+         .sponsored { display: none !important; }\n"
+    );
+    assert_eq!(
+        stylesheet("https://sport.news.test/").unwrap(),
+        ".ad-banner { display: none !important; }\n"
+    );
+    assert_eq!(stylesheet("https://blog.test/"), None);
+}
+
+#[test]
+fn test_content_blocker_ignores_selectors_with_unclosed_comments() {
+    let content_blocker = content_blocker(
+        "##.ad /* banner\n\
+         ##.sponsored /* story */\n\
+         ##.ad-banner\n",
+    );
+
+    assert_eq!(
+        content_blocker
+            .element_hiding_stylesheet(&ServoUrl::parse("https://news.test/").unwrap())
+            .unwrap(),
+        ".sponsored /* story */ { display: none !important; }\n\
+         .ad-banner { display: none !important; }\n"
+    );
+}
+
+#[test]
+fn test_content_blocker_classifies_frames() {
+    set_pref!(network.content_blocker.enabled, true);
+    let content_blocker = content_blocker("||ads.test^$subdocument\n");
+
+    let url = ServoUrl::parse("https://ads.test/frame.html").unwrap();
+    let mut request = Request::new(
+        url.clone(),
+        Some(Origin::Origin(url.origin())),
+        Some(TEST_PIPELINE_ID),
+    );
+    request.destination = Destination::Document;
+    request.site_for_cookies = Some(ServoUrl::parse("https://news.test/").unwrap().origin());
+    assert_eq!(
+        content_blocker.classify_request(&request),
+        Classification::Block
+    );
+    assert_eq!(content_blocker.blocked_count(&[TEST_PIPELINE_ID]), 1);
+
+    // The pages themselves are never blocked.
+    request.top_level_navigation = true;
+    assert_eq!(
+        content_blocker.classify_request(&request),
+        Classification::Allow
+    );
+
+    content_blocker.remove_pipeline(TEST_PIPELINE_ID);
+    assert_eq!(content_blocker.blocked_count(&[TEST_PIPELINE_ID]), 0);
+}
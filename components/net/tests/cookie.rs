@@ -2,7 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use net::cookie::Cookie;
+use cookie_rs::SameSite;
+use ipc_channel::ipc;
+use net::cookie::{Cookie, SameSiteContext};
+use net::cookie_database::CookieDatabase;
 use net::cookie_storage::CookieStorage;
 use net_traits::CookieSource;
 use servo_url::ServoUrl;
@@ -475,3 +478,203 @@ fn test_cookie_eviction_all_nonsecure_new_nonsecure() {
         "extra2=bar; extra3=bar; extra4=bar; extra5=bar; foo=bar"
     );
 }
+
+#[test]
+fn test_cookie_observers_are_notified_of_the_changes_of_their_site() {
+    let mut storage = CookieStorage::new(150);
+    let url = ServoUrl::parse("http://www.example.com/").unwrap();
+    let other_url = ServoUrl::parse("http://example.org/").unwrap();
+    let (observer, invalidation) = ipc::channel().unwrap();
+    let (other_observer, other_invalidation) = ipc::channel().unwrap();
+    storage.observe(&url, observer);
+    storage.observe(&other_url, other_observer);
+
+    let cookie_url = ServoUrl::parse("http://example.com/").unwrap();
+    add_cookie_to_storage(&mut storage, &cookie_url, "foo=bar; Domain=example.com");
+    assert!(invalidation.try_recv().is_ok());
+    assert!(other_invalidation.try_recv().is_err());
+
+    // The observers are only notified of the next change.
+    add_cookie_to_storage(&mut storage, &cookie_url, "foo=baz; Domain=example.com");
+    assert!(invalidation.try_recv().is_err());
+
+    let (observer, invalidation) = ipc::channel().unwrap();
+    storage.observe(&url, observer);
+    storage.clear_storage(&url);
+    assert!(invalidation.try_recv().is_ok());
+}
+
+#[test]
+fn test_first_expiry_time_for_url() {
+    let mut storage = CookieStorage::new(150);
+    let url = ServoUrl::parse("http://example.com/").unwrap();
+    add_cookie_to_storage(&mut storage, &url, "session=1");
+    assert!(storage
+        .first_expiry_time_for_url(&url, CookieSource::NonHTTP)
+        .is_none());
+
+    add_cookie_to_storage(
+        &mut storage,
+        &url,
+        "late=1; expires=Sun, 18-Apr-2077 21:06:29 GMT",
+    );
+    add_cookie_to_storage(
+        &mut storage,
+        &url,
+        "early=1; expires=Sun, 18-Apr-2066 21:06:29 GMT",
+    );
+    add_cookie_to_storage(
+        &mut storage,
+        &url,
+        "other=1; Path=/other; expires=Sun, 18-Apr-2055 21:06:29 GMT",
+    );
+    let expiry_time = storage
+        .first_expiry_time_for_url(&url, CookieSource::NonHTTP)
+        .unwrap();
+    assert_eq!(expiry_time.tm_year + 1900, 2066);
+}
+
+#[test]
+fn test_persistent_cookies_are_kept_in_the_database() {
+    let path =
+        std::env::temp_dir().join(format!("servo-cookies-test-{}.sqlite", std::process::id()));
+    let url = ServoUrl::parse("http://example.com/").unwrap();
+    let other_url = ServoUrl::parse("http://example.org/").unwrap();
+
+    let mut storage = CookieStorage::new(150);
+    storage.set_database(CookieDatabase::open(&path).unwrap());
+    add_cookie_to_storage(&mut storage, &url, "session=1");
+    add_cookie_to_storage(
+        &mut storage,
+        &url,
+        "persistent=1; expires=Sun, 18-Apr-2077 21:06:29 GMT",
+    );
+    add_cookie_to_storage(&mut storage, &url, "max_age=1; Max-Age=3600");
+    add_cookie_to_storage(
+        &mut storage,
+        &url,
+        "expired=1; expires=Sun, 18-Apr-2077 21:06:29 GMT",
+    );
+    add_cookie_to_storage(
+        &mut storage,
+        &url,
+        "expired=1; expires=Sat, 18-Apr-2015 21:06:29 GMT",
+    );
+    add_cookie_to_storage(
+        &mut storage,
+        &other_url,
+        "cleared=1; expires=Sun, 18-Apr-2077 21:06:29 GMT",
+    );
+    storage.clear_storage(&other_url);
+    // The pending changes are written when the database is closed.
+    drop(storage);
+
+    let mut storage = CookieStorage::new(150);
+    storage.set_database(CookieDatabase::open(&path).unwrap());
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(
+        storage.cookies_for_url(&url, CookieSource::HTTP).unwrap(),
+        "persistent=1; max_age=1"
+    );
+    assert!(storage
+        .cookies_for_url(&other_url, CookieSource::HTTP)
+        .is_none());
+}
+
+fn same_site_cookie(name: &str, same_site: Option<SameSite>, url: &ServoUrl) -> Option<Cookie> {
+    let mut cookie = cookie_rs::Cookie::build(name.to_owned(), "1")
+        .secure(url.is_secure_scheme())
+        .finish();
+    if let Some(same_site) = same_site {
+        cookie.set_same_site(same_site);
+    }
+    Cookie::new_wrapped(cookie, url, CookieSource::HTTP)
+}
+
+#[test]
+fn test_same_site_none_requires_secure() {
+    let url = ServoUrl::parse("https://example.com/").unwrap();
+    assert!(same_site_cookie("secure", Some(SameSite::None), &url).is_some());
+    let url = ServoUrl::parse("http://example.com/").unwrap();
+    assert!(same_site_cookie("insecure", Some(SameSite::None), &url).is_none());
+    assert!(same_site_cookie("insecure", Some(SameSite::Lax), &url).is_some());
+}
+
+#[test]
+fn test_cookies_for_url_in_same_site_context() {
+    let url = ServoUrl::parse("https://example.com/").unwrap();
+    let mut storage = CookieStorage::new(150);
+    for &(name, same_site) in &[
+        ("default", None),
+        ("lax", Some(SameSite::Lax)),
+        ("strict", Some(SameSite::Strict)),
+        ("none", Some(SameSite::None)),
+    ] {
+        let cookie = same_site_cookie(name, same_site, &url).unwrap();
+        storage.push(cookie, &url, CookieSource::HTTP);
+    }
+
+    let mut cookies_in_context = |context| {
+        storage
+            .cookies_for_url_in_context(&url, CookieSource::HTTP, context)
+            .unwrap()
+    };
+    assert_eq!(
+        cookies_in_context(SameSiteContext::SameSite),
+        "default=1; lax=1; strict=1; none=1"
+    );
+    assert_eq!(
+        cookies_in_context(SameSiteContext::TopLevelNavigation { safe_method: true }),
+        "default=1; lax=1; none=1"
+    );
+    assert_eq!(
+        cookies_in_context(SameSiteContext::TopLevelNavigation { safe_method: false }),
+        "none=1"
+    );
+    assert_eq!(cookies_in_context(SameSiteContext::CrossSite), "none=1");
+}
+
+#[test]
+fn test_cookies_set_in_cross_site_context() {
+    let url = ServoUrl::parse("https://example.com/").unwrap();
+    let navigation = SameSiteContext::TopLevelNavigation { safe_method: false };
+    let default = same_site_cookie("default", None, &url).unwrap();
+    assert!(default.can_be_set_in_context(navigation));
+    assert!(!default.can_be_set_in_context(SameSiteContext::CrossSite));
+    let strict = same_site_cookie("strict", Some(SameSite::Strict), &url).unwrap();
+    assert!(strict.can_be_set_in_context(SameSiteContext::SameSite));
+    assert!(!strict.can_be_set_in_context(SameSiteContext::CrossSite));
+    let none = same_site_cookie("none", Some(SameSite::None), &url).unwrap();
+    assert!(none.can_be_set_in_context(SameSiteContext::CrossSite));
+}
+
+#[test]
+fn test_saved_cookies_are_written_to_the_database() {
+    let path = std::env::temp_dir().join(format!(
+        "servo-cookies-save-test-{}.sqlite",
+        std::process::id()
+    ));
+    let url = ServoUrl::parse("http://example.com/").unwrap();
+
+    let mut storage = CookieStorage::new(150);
+    storage.set_database(CookieDatabase::open(&path).unwrap());
+    add_cookie_to_storage(
+        &mut storage,
+        &url,
+        "persistent=1; expires=Sun, 18-Apr-2077 21:06:29 GMT",
+    );
+    storage.save_to_database();
+
+    // The database is read while the first one is still open.
+    let mut other_storage = CookieStorage::new(150);
+    other_storage.set_database(CookieDatabase::open(&path).unwrap());
+    assert_eq!(
+        other_storage
+            .cookies_for_url(&url, CookieSource::HTTP)
+            .unwrap(),
+        "persistent=1"
+    );
+    drop(storage);
+    drop(other_storage);
+    let _ = std::fs::remove_file(&path);
+}
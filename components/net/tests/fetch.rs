@@ -6,34 +6,42 @@ use crate::fetch_with_context;
 use crate::fetch_with_cors_cache;
 use crate::http_loader::{expect_devtools_http_request, expect_devtools_http_response};
 use crate::{
-    create_embedder_proxy, fetch, make_server, make_ssl_server, new_fetch_context,
-    DEFAULT_USER_AGENT,
+    create_about_pages, create_embedder_proxy, fetch, make_server, make_ssl_server,
+    new_fetch_context, DEFAULT_USER_AGENT,
 };
+use content_security_policy as csp;
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::HttpRequest as DevtoolsHttpRequest;
 use devtools_traits::HttpResponse as DevtoolsHttpResponse;
+use futures::Stream;
 use headers::StrictTransportSecurity;
 use headers::{AccessControlAllowCredentials, AccessControlAllowHeaders, AccessControlAllowOrigin};
 use headers::{AccessControlAllowMethods, AccessControlMaxAge, HeaderMapExt};
 use headers::{CacheControl, ContentLength, ContentType, Expires, LastModified, Pragma, UserAgent};
 use http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use http::{Method, StatusCode};
+use http::{Method, StatusCode, Version};
 use hyper::body::Body;
 use hyper::{Request as HyperRequest, Response as HyperResponse};
 use mime::{self, Mime};
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::connector::{create_tls_config, ALPN_H2_H1};
+use net::content_blocker::ContentBlocker;
 use net::fetch::cors_cache::CorsCache;
 use net::fetch::methods::{self, CancellationListener, FetchContext};
+use net::fetch::orb::ORB_SNIFF_LENGTH;
 use net::filemanager_thread::FileManager;
 use net::hsts::HstsEntry;
 use net::resource_thread::CoreResourceThreadPool;
 use net::test::HttpState;
 use net_traits::filemanager_thread::FileTokenCheck;
+use net_traits::protocol_handler::{
+    ProtocolBody, ProtocolHandler, ProtocolRegistry, ProtocolResponse,
+};
 use net_traits::request::{
-    Destination, Origin, RedirectMode, Referrer, Request, RequestBuilder, RequestMode,
+    CacheMode, Destination, Initiator, Origin, RedirectMode, Referrer, Request, RequestBuilder,
+    RequestMode,
 };
-use net_traits::response::{CacheState, Response, ResponseBody, ResponseType};
+use net_traits::response::{CacheState, HttpsState, Response, ResponseBody, ResponseType};
 use net_traits::{
     FetchTaskTarget, IncludeSubdomains, NetworkError, ReferrerPolicy, ResourceFetchTiming,
     ResourceTimingType,
@@ -41,10 +49,12 @@ use net_traits::{
 use servo_arc::Arc as ServoArc;
 use servo_url::{ImmutableOrigin, ServoUrl};
 use std::fs;
+use std::io;
 use std::iter::FromIterator;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, Weak};
+use std::thread;
 use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
@@ -132,6 +142,136 @@ fn test_fetch_aboutblank() {
     assert_eq!(*resp_body, ResponseBody::Done(vec![]));
 }
 
+#[test]
+fn test_fetch_about_config() {
+    let url = ServoUrl::parse("about:config").unwrap();
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Document;
+
+    let fetch_response = fetch(&mut request, None);
+    let actual_response = fetch_response.actual_response();
+    assert!(!actual_response.is_network_error());
+    assert_eq!(
+        actual_response.headers.typed_get::<ContentType>(),
+        Some(ContentType::from(mime::TEXT_HTML_UTF_8))
+    );
+    match *actual_response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => {
+            let body = String::from_utf8(body.clone()).unwrap();
+            assert!(body.contains("<title>Preferences</title>"));
+            assert!(body.contains("dom.webgl2.enabled"));
+        },
+        _ => panic!("expected a complete body"),
+    }
+}
+
+#[test]
+fn test_fetch_about_page_as_subresource() {
+    let url = ServoUrl::parse("about:memory").unwrap();
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+
+    let fetch_response = fetch(&mut request, None);
+    assert!(fetch_response.is_network_error());
+}
+
+struct AssetsHandler {
+    fetchable: bool,
+}
+
+impl ProtocolHandler for AssetsHandler {
+    fn load(&self, request: &Request) -> Result<ProtocolResponse, String> {
+        if request.current_url().path() != "/index.html" {
+            return Err("Asset not found".to_owned());
+        }
+        let mut response =
+            ProtocolResponse::new(mime::TEXT_HTML_UTF_8, ProtocolBody::Bytes(b"Yay!".to_vec()));
+        response.headers.typed_insert(AccessControlAllowOrigin::ANY);
+        Ok(response)
+    }
+
+    fn is_secure(&self) -> bool {
+        true
+    }
+
+    fn is_fetchable(&self) -> bool {
+        self.fetchable
+    }
+}
+
+fn new_protocol_fetch_context(fetchable: bool) -> FetchContext {
+    let mut protocols = ProtocolRegistry::default();
+    protocols
+        .register(
+            "app",
+            AssetsHandler {
+                fetchable: fetchable,
+            },
+        )
+        .unwrap();
+    let mut context = new_fetch_context(None, None, None);
+    context.protocols = Arc::new(protocols);
+    context
+}
+
+#[test]
+fn test_fetch_custom_protocol() {
+    let url = ServoUrl::parse("app://assets/index.html").unwrap();
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+
+    let mut context = new_protocol_fetch_context(false);
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    let actual_response = fetch_response.actual_response();
+    assert!(!actual_response.is_network_error());
+    assert!(matches!(actual_response.https_state, HttpsState::Modern));
+    assert_eq!(
+        actual_response.headers.typed_get::<ContentType>(),
+        Some(ContentType::from(mime::TEXT_HTML_UTF_8))
+    );
+    assert_eq!(
+        *actual_response.body.lock().unwrap(),
+        ResponseBody::Done(b"Yay!".to_vec())
+    );
+
+    let url = ServoUrl::parse("app://assets/missing.html").unwrap();
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    assert!(fetch_response.is_network_error());
+}
+
+#[test]
+fn test_cors_fetch_custom_protocol() {
+    let url = ServoUrl::parse("app://assets/index.html").unwrap();
+    let origin = Origin::Origin(ServoUrl::parse("http://example.com").unwrap().origin());
+
+    let mut request = Request::new(url.clone(), Some(origin.clone()), None);
+    request.referrer = Referrer::NoReferrer;
+    request.mode = RequestMode::CorsMode;
+    let mut context = new_protocol_fetch_context(false);
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    assert!(fetch_response.is_network_error());
+
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.mode = RequestMode::CorsMode;
+    let mut context = new_protocol_fetch_context(true);
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    assert!(!fetch_response.is_network_error());
+    assert_eq!(fetch_response.response_type, ResponseType::Cors);
+    assert_eq!(
+        *fetch_response.body.lock().unwrap(),
+        ResponseBody::Done(b"Yay!".to_vec())
+    );
+}
+
 #[test]
 fn test_fetch_blob() {
     use net_traits::blob_url_store::BlobBuf;
@@ -154,6 +294,7 @@ fn test_fetch_blob() {
             assert_eq!(self.buffer, self.expected);
             let _ = self.sender.send(response.clone());
         }
+        fn process_csp_violations(&mut self, _: &Request, _: Vec<csp::Violation>) {}
     }
 
     let context = new_fetch_context(None, None, None);
@@ -681,8 +822,12 @@ fn test_fetch_with_hsts() {
 
     let mut context = FetchContext {
         state: Arc::new(HttpState::new(tls_config)),
+        about_pages: create_about_pages(),
+        protocols: Arc::new(ProtocolRegistry::default()),
+        content_blocker: Arc::new(ContentBlocker::default()),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: None,
+        prompt_sender: unbounded().0,
         filemanager: FileManager::new(create_embedder_proxy(), Weak::new()),
         file_token: FileTokenCheck::NotRequired,
         cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
@@ -710,6 +855,62 @@ fn test_fetch_with_hsts() {
     );
 }
 
+#[test]
+fn test_fetch_uses_http2_when_negotiated_with_alpn() {
+    static MESSAGE: &'static [u8] = b"Yay!";
+    let versions = Arc::new(Mutex::new(vec![]));
+    let handler_versions = versions.clone();
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        handler_versions.lock().unwrap().push(request.version());
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let cert_path = Path::new("../../resources/self_signed_certificate_for_testing.crt")
+        .canonicalize()
+        .unwrap();
+    let key_path = Path::new("../../resources/privatekey_for_testing.key")
+        .canonicalize()
+        .unwrap();
+    let (server, mut url) = make_ssl_server(handler, cert_path.clone(), key_path.clone());
+    url.as_mut_url().set_scheme("https").unwrap();
+
+    let certs = fs::read_to_string(cert_path).expect("Couldn't find certificate file");
+    let tls_config = create_tls_config(&certs, ALPN_H2_H1);
+
+    let mut context = FetchContext {
+        state: Arc::new(HttpState::new(tls_config)),
+        about_pages: create_about_pages(),
+        protocols: Arc::new(ProtocolRegistry::default()),
+        content_blocker: Arc::new(ContentBlocker::default()),
+        user_agent: DEFAULT_USER_AGENT.into(),
+        devtools_chan: None,
+        prompt_sender: unbounded().0,
+        filemanager: FileManager::new(create_embedder_proxy(), Weak::new()),
+        file_token: FileTokenCheck::NotRequired,
+        cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
+        timing: ServoArc::new(Mutex::new(ResourceFetchTiming::new(
+            ResourceTimingType::Navigation,
+        ))),
+    };
+
+    // Both requests are sent over the HTTP/2 connection.
+    for _ in 0..2 {
+        let mut request = RequestBuilder::new(url.clone())
+            .method(Method::GET)
+            .destination(Destination::Script)
+            .origin(url.clone().origin())
+            .pipeline_id(Some(TEST_PIPELINE_ID))
+            .build();
+        let response = fetch_with_context(&mut request, &mut context);
+        match *response.internal_response.unwrap().body.lock().unwrap() {
+            ResponseBody::Done(ref body) => assert_eq!(&**body, MESSAGE),
+            _ => panic!("the response has a complete body"),
+        };
+    }
+    let _ = server.close();
+
+    assert_eq!(*versions.lock().unwrap(), vec![Version::HTTP_2; 2]);
+}
+
 #[test]
 fn test_load_adds_host_to_hsts_list_when_url_is_https() {
     let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
@@ -734,8 +935,12 @@ fn test_load_adds_host_to_hsts_list_when_url_is_https() {
 
     let mut context = FetchContext {
         state: Arc::new(HttpState::new(tls_config)),
+        about_pages: create_about_pages(),
+        protocols: Arc::new(ProtocolRegistry::default()),
+        content_blocker: Arc::new(ContentBlocker::default()),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: None,
+        prompt_sender: unbounded().0,
         filemanager: FileManager::new(create_embedder_proxy(), Weak::new()),
         file_token: FileTokenCheck::NotRequired,
         cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
@@ -771,6 +976,65 @@ fn test_load_adds_host_to_hsts_list_when_url_is_https() {
         .is_host_secure(url.host_str().unwrap()));
 }
 
+#[test]
+fn test_fetch_blocked_by_csp_reports_violations() {
+    struct FetchResponseCollector {
+        sender: Sender<(Response, Vec<csp::Violation>)>,
+        violations: Vec<csp::Violation>,
+    }
+
+    impl FetchTaskTarget for FetchResponseCollector {
+        fn process_request_body(&mut self, _: &Request) {}
+        fn process_request_eof(&mut self, _: &Request) {}
+        fn process_response(&mut self, _: &Response) {}
+        fn process_response_chunk(&mut self, _: Vec<u8>) {}
+        fn process_response_eof(&mut self, response: &Response) {
+            let _ = self
+                .sender
+                .send((response.clone(), self.violations.drain(..).collect()));
+        }
+        fn process_csp_violations(&mut self, _: &Request, violations: Vec<csp::Violation>) {
+            self.violations.extend(violations);
+        }
+    }
+
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        *response.body_mut() = b"Yay!".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Image;
+    request.csp_list = Some(csp::CspList::parse(
+        "img-src 'none'",
+        csp::PolicySource::Header,
+        csp::PolicyDisposition::Enforce,
+    ));
+
+    let (sender, receiver) = unbounded();
+    let mut target = FetchResponseCollector {
+        sender,
+        violations: vec![],
+    };
+    methods::fetch(
+        &mut request,
+        &mut target,
+        &mut new_fetch_context(None, None, None),
+    );
+    let (response, violations) = receiver.recv().unwrap();
+
+    let _ = server.close();
+    assert!(response.is_network_error());
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].directive.name, "img-src");
+    assert_eq!(
+        violations[0].resource,
+        csp::ViolationResource::Url(url.into_url())
+    );
+}
+
 #[test]
 fn test_fetch_with_sri_network_error() {
     static MESSAGE: &'static [u8] = b"alert('Hello, Network Error');";
@@ -864,6 +1128,146 @@ fn test_fetch_blocked_nosniff() {
     }
 }
 
+/// The cross-origin HTML, XML and JSON resources fetched without CORS reach the target
+/// with an empty body, unless their body shows they are mislabelled.
+#[test]
+fn test_fetch_opaque_response_blocking() {
+    fn fetch_opaque(mime: &'static str, nosniff: bool, body: &'static [u8]) -> Response {
+        let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static(mime));
+            if nosniff {
+                response.headers_mut().insert(
+                    HeaderName::from_static("x-content-type-options"),
+                    HeaderValue::from_static("nosniff"),
+                );
+            }
+            *response.body_mut() = body.to_vec().into();
+        };
+        let (server, url) = make_server(handler);
+
+        let origin = Origin::Origin(ImmutableOrigin::new_opaque());
+        let mut request = Request::new(url, Some(origin), None);
+        request.referrer = Referrer::NoReferrer;
+        request.destination = Destination::Script;
+        let response = fetch(&mut request, None);
+        let _ = server.close();
+
+        assert_eq!(response.response_type, ResponseType::Opaque);
+        response
+    }
+
+    fn is_blocked(response: &Response) -> bool {
+        let internal_response = response.actual_response();
+        let body = internal_response.body.lock().unwrap();
+        match *body {
+            ResponseBody::Empty => {
+                assert!(internal_response.headers.is_empty());
+                true
+            },
+            ResponseBody::Done(ref body) => body.is_empty(),
+            ResponseBody::Receiving(..) => panic!("the response body is incomplete"),
+        }
+    }
+
+    let tests: &[(&'static str, bool, &'static [u8], bool)] = &[
+        ("application/json", false, b"{\"secret\": 42}", true),
+        ("application/json", false, b")]}'\n[42]", true),
+        ("application/ld+json", false, b" { \"a\" : 1}", true),
+        ("application/json", false, b"var secret = 42;", false),
+        ("text/html", false, b"<!-- hi -->\n<DIV class=secret>", true),
+        ("text/html", false, b"document.write('<div>');", false),
+        ("text/html", true, b"document.write('<div>');", true),
+        ("text/xml", false, b"<?xml version=\"1.0\"?><secret/>", true),
+        (
+            "image/svg+xml",
+            false,
+            b"<?xml version=\"1.0\"?><svg/>",
+            false,
+        ),
+        ("text/plain", false, b"<html><body>secret", true),
+        ("text/plain", false, b"alert(1)", false),
+        ("text/javascript", true, b"{\"a\": 1}", false),
+    ];
+
+    for &(mime, nosniff, body, blocked) in tests {
+        let response = fetch_opaque(mime, nosniff, body);
+        assert_eq!(is_blocked(&response), blocked, "{} {:?}", mime, body);
+    }
+}
+
+/// Only the start of the body of an opaque response is sniffed before the response reaches
+/// the target, which then receives the rest of the body as it comes.
+#[test]
+fn test_fetch_opaque_response_blocking_streams_the_rest_of_the_body() {
+    struct FetchResponseCollector {
+        chunks: Sender<Vec<u8>>,
+        sender: Sender<Response>,
+    }
+    impl FetchTaskTarget for FetchResponseCollector {
+        fn process_request_body(&mut self, _: &Request) {}
+        fn process_request_eof(&mut self, _: &Request) {}
+        fn process_response(&mut self, _: &Response) {}
+        fn process_response_chunk(&mut self, chunk: Vec<u8>) {
+            let _ = self.chunks.send(chunk);
+        }
+        fn process_response_eof(&mut self, response: &Response) {
+            let _ = self.sender.send(response.clone());
+        }
+        fn process_csp_violations(&mut self, _: &Request, _: Vec<csp::Violation>) {}
+    }
+
+    let (body_sender, body_receiver) = futures::sync::mpsc::unbounded::<Vec<u8>>();
+    let body_receiver = Mutex::new(Some(body_receiver));
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .typed_insert(ContentType::from(mime::TEXT_PLAIN));
+        let body = body_receiver.lock().unwrap().take().unwrap();
+        *response.body_mut() = Body::wrap_stream(
+            body.map_err(|()| io::Error::new(io::ErrorKind::Other, "The body is gone")),
+        );
+    };
+    let (server, url) = make_server(handler);
+
+    let start = format!("alert(1);{}", " ".repeat(ORB_SNIFF_LENGTH)).into_bytes();
+    body_sender.unbounded_send(start.clone()).unwrap();
+
+    let (chunks, chunk_receiver) = unbounded();
+    let (sender, receiver) = unbounded();
+    thread::spawn(move || {
+        let origin = Origin::Origin(ImmutableOrigin::new_opaque());
+        let mut request = Request::new(url, Some(origin), None);
+        request.referrer = Referrer::NoReferrer;
+        request.destination = Destination::Script;
+        let mut target = FetchResponseCollector { chunks, sender };
+        methods::fetch(
+            &mut request,
+            &mut target,
+            &mut new_fetch_context(None, None, None),
+        );
+    });
+
+    // The start of the body reaches the target before the rest of it is sent.
+    let received = chunk_receiver.recv_timeout(Duration::from_secs(5));
+    assert_eq!(received, Ok(start.clone()));
+
+    body_sender.unbounded_send(b"alert(2);".to_vec()).unwrap();
+    drop(body_sender);
+    let response = receiver.recv().unwrap();
+    let _ = server.close();
+
+    let mut body = start;
+    body.extend_from_slice(b"alert(2);");
+    let rest: Vec<u8> = chunk_receiver.try_iter().flatten().collect();
+    assert_eq!(rest, b"alert(2);");
+    match *response.actual_response().body.lock().unwrap() {
+        ResponseBody::Done(ref done) => assert_eq!(*done, body),
+        _ => panic!("the response body is incomplete"),
+    }
+}
+
 fn setup_server_and_fetch(message: &'static [u8], redirect_cap: u32) -> Response {
     let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
         let redirects = request
@@ -1190,10 +1594,210 @@ fn test_fetch_with_devtools() {
     let httpresponse = DevtoolsHttpResponse {
         headers: Some(response_headers),
         status: Some((200, b"OK".to_vec())),
-        body: None,
+        body: Some(content.as_bytes().to_vec()),
+        body_size: content.len(),
+        receive_time: devhttpresponse.receive_time,
+        from_cache: false,
         pipeline_id: TEST_PIPELINE_ID,
     };
 
     assert_eq!(devhttprequest, httprequest);
     assert_eq!(devhttpresponse, httpresponse);
 }
+
+#[test]
+fn test_fetch_from_cache_with_devtools() {
+    static MESSAGE: &'static [u8] = b"Yay!";
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_max_age(Duration::from_secs(3600)));
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let (devtools_chan, devtools_port) = unbounded();
+    let mut context = new_fetch_context(Some(devtools_chan), None, None);
+
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    let _ = fetch_with_context(&mut request, &mut context);
+
+    let mut request = Request::new(url, Some(origin), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    let _ = fetch_with_context(&mut request, &mut context);
+    let _ = server.close();
+
+    let _ = expect_devtools_http_request(&devtools_port);
+    let network_response = expect_devtools_http_response(&devtools_port);
+    assert!(!network_response.from_cache);
+    assert_eq!(network_response.body, Some(MESSAGE.to_vec()));
+
+    let _ = expect_devtools_http_request(&devtools_port);
+    let cached_response = expect_devtools_http_response(&devtools_port);
+    assert!(cached_response.from_cache);
+    assert_eq!(cached_response.body, Some(MESSAGE.to_vec()));
+    assert_eq!(cached_response.body_size, MESSAGE.len());
+}
+
+#[test]
+fn test_fetch_revalidated_from_cache_with_devtools() {
+    static MESSAGE: &'static [u8] = b"Yay!";
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        if request.headers().contains_key(header::IF_NONE_MATCH) {
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            return;
+        }
+        response
+            .headers_mut()
+            .insert(header::ETAG, HeaderValue::from_static("\"abc\""));
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_no_cache());
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let (devtools_chan, devtools_port) = unbounded();
+    let mut context = new_fetch_context(Some(devtools_chan), None, None);
+
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    let _ = fetch_with_context(&mut request, &mut context);
+
+    let mut request = Request::new(url, Some(origin), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    let _ = fetch_with_context(&mut request, &mut context);
+    let _ = server.close();
+
+    let _ = expect_devtools_http_request(&devtools_port);
+    let network_response = expect_devtools_http_response(&devtools_port);
+    assert!(!network_response.from_cache);
+
+    let _ = expect_devtools_http_request(&devtools_port);
+    let revalidated_response = expect_devtools_http_response(&devtools_port);
+    assert_eq!(revalidated_response.status.map(|s| s.0), Some(304));
+    assert!(revalidated_response.from_cache);
+}
+
+#[test]
+fn test_fetch_with_no_cache_mode_validates_fresh_response() {
+    static MESSAGE: &'static [u8] = b"Yay!";
+    let validations = Arc::new(AtomicUsize::new(0));
+    let handler_validations = validations.clone();
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_max_age(Duration::from_secs(3600)));
+        if request.headers().contains_key(header::IF_NONE_MATCH) {
+            handler_validations.fetch_add(1, Ordering::SeqCst);
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            return;
+        }
+        response
+            .headers_mut()
+            .insert(header::ETAG, HeaderValue::from_static("\"servo\""));
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut context = new_fetch_context(None, None, None);
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    let _ = fetch_with_context(&mut request, &mut context);
+
+    // The stored response is fresh, but validated anyway in the no-cache mode.
+    let mut request = Request::new(url, Some(origin), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    request.cache_mode = CacheMode::NoCache;
+    request
+        .headers
+        .typed_insert(CacheControl::new().with_max_age(Duration::from_secs(3600)));
+    let response = fetch_with_context(&mut request, &mut context);
+    let _ = server.close();
+
+    assert_eq!(validations.load(Ordering::SeqCst), 1);
+    assert_eq!(response.status.map(|s| s.0), Some(StatusCode::OK));
+    match *response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => assert_eq!(&**body, MESSAGE),
+        _ => panic!("the validated response has a complete body"),
+    };
+}
+
+#[test]
+fn test_fetch_consumes_preloaded_response() {
+    static MESSAGE: &'static [u8] = b"Preloaded";
+    let requests = Arc::new(AtomicUsize::new(0));
+    let handler_requests = requests.clone();
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        handler_requests.fetch_add(1, Ordering::SeqCst);
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_no_store());
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut context = new_fetch_context(None, None, None);
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Script;
+    request.initiator = Initiator::Preload;
+    let _ = fetch_with_context(&mut request, &mut context);
+
+    // The response can't be stored in the HTTP cache, but the preloaded one is used once.
+    let fetch_script = |context: &mut FetchContext| {
+        let mut request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+        request.referrer = Referrer::NoReferrer;
+        request.destination = Destination::Script;
+        fetch_with_context(&mut request, context)
+    };
+    let response = fetch_script(&mut context);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    match *response.actual_response().body.lock().unwrap() {
+        ResponseBody::Done(ref body) => assert_eq!(&**body, MESSAGE),
+        _ => panic!("the preloaded response has a complete body"),
+    };
+
+    let _ = fetch_script(&mut context);
+    let _ = server.close();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_fetch_does_not_wait_for_unfinished_preload() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let handler_requests = requests.clone();
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        handler_requests.fetch_add(1, Ordering::SeqCst);
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_no_store());
+        *response.body_mut() = b"Yay!".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    // A synchronous fetch returns before the preload is finished.
+    let mut context = new_fetch_context(None, None, None);
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Script;
+    request.initiator = Initiator::Preload;
+    request.synchronous = true;
+    let _ = fetch_with_context(&mut request, &mut context);
+
+    // The request for the resource fetches it rather than waiting for the preload.
+    let mut request = Request::new(url.clone(), Some(origin), Some(TEST_PIPELINE_ID));
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Script;
+    let response = fetch_with_context(&mut request, &mut context);
+    let _ = server.close();
+
+    assert!(!response.is_network_error());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
@@ -9,7 +9,7 @@ use net::filemanager_thread::FileManager;
 use net::resource_thread::CoreResourceThreadPool;
 use net_traits::blob_url_store::BlobURLStoreError;
 use net_traits::filemanager_thread::{
-    FileManagerThreadError, FileManagerThreadMsg, ReadFileProgress,
+    FileManagerThreadError, FileManagerThreadMsg, ReadFileProgress, RelativePos,
 };
 use servo_config::set_pref;
 use std::fs::File;
@@ -59,6 +59,7 @@ fn test_filemanager() {
             filemanager.handle(FileManagerThreadMsg::ReadFile(
                 tx2,
                 selected.id.clone(),
+                RelativePos::full_range(),
                 origin.clone(),
             ));
 
@@ -93,6 +94,43 @@ fn test_filemanager() {
             }
         }
 
+        // Test by reading a range, expecting the content of that range only
+        {
+            let (tx2, rx2) = ipc::channel().unwrap();
+            filemanager.handle(FileManagerThreadMsg::ReadFile(
+                tx2,
+                selected.id.clone(),
+                RelativePos::from_opts(Some(10), Some(-10)),
+                origin.clone(),
+            ));
+
+            let mut bytes = vec![];
+            loop {
+                match rx2
+                    .recv()
+                    .expect("Broken channel")
+                    .expect("File manager reading failure is unexpected")
+                {
+                    ReadFileProgress::Meta(mut blob_buf) => {
+                        assert_eq!(blob_buf.size, test_file_content.len() as u64 - 20);
+                        bytes.append(&mut blob_buf.bytes);
+                    },
+                    ReadFileProgress::Partial(mut bytes_in) => {
+                        bytes.append(&mut bytes_in);
+                    },
+                    ReadFileProgress::EOF => {
+                        break;
+                    },
+                }
+            }
+
+            assert_eq!(
+                &test_file_content[10..test_file_content.len() - 10],
+                &*bytes,
+                "Read range differs"
+            );
+        }
+
         // Delete the id
         {
             let (tx2, rx2) = ipc::channel().unwrap();
@@ -112,6 +150,7 @@ fn test_filemanager() {
             filemanager.handle(FileManagerThreadMsg::ReadFile(
                 tx2,
                 selected.id.clone(),
+                RelativePos::full_range(),
                 origin.clone(),
             ));
 
@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crossbeam_channel::unbounded;
-use http::header::{HeaderValue, EXPIRES};
+use http::header::{HeaderValue, ACCEPT_LANGUAGE, AUTHORIZATION, COOKIE, EXPIRES, VARY};
 use http::StatusCode;
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::http_cache::HttpCache;
@@ -11,6 +11,18 @@ use net_traits::request::{Origin, Request};
 use net_traits::response::{Response, ResponseBody};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
 use servo_url::ServoUrl;
+use std::fs;
+
+fn cacheable_response(url: &ServoUrl, body: &[u8]) -> Response {
+    let timing = ResourceFetchTiming::new(ResourceTimingType::Navigation);
+    let mut response = Response::new(url.clone(), timing);
+    // Expires header makes the response cacheable.
+    response
+        .headers
+        .insert(EXPIRES, HeaderValue::from_str("-10").unwrap());
+    *response.body.lock().unwrap() = ResponseBody::Done(body.to_vec());
+    response
+}
 
 #[test]
 fn test_refreshing_resource_sets_done_chan_the_appropriate_value() {
@@ -48,3 +60,150 @@ fn test_refreshing_resource_sets_done_chan_the_appropriate_value() {
         }
     })
 }
+
+#[test]
+fn test_vary_header_absent_from_original_request_does_not_match() {
+    let url = ServoUrl::parse("https://servo.org").unwrap();
+    let origin = Origin::Origin(url.clone().origin());
+    let request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    let mut response = cacheable_response(&url, b"Servo");
+    response
+        .headers
+        .insert(VARY, HeaderValue::from_static("accept-language"));
+    let mut cache = HttpCache::new();
+    cache.store(&request, &response);
+
+    let mut done_chan = None;
+    assert!(cache.construct_response(&request, &mut done_chan).is_some());
+    let mut varying_request = Request::new(url.clone(), Some(origin), Some(TEST_PIPELINE_ID));
+    varying_request
+        .headers
+        .insert(ACCEPT_LANGUAGE, HeaderValue::from_static("fr"));
+    assert!(cache
+        .construct_response(&varying_request, &mut done_chan)
+        .is_none());
+}
+
+#[test]
+fn test_complete_resources_are_written_to_disk() {
+    let directory =
+        std::env::temp_dir().join(format!("servo-http-cache-test-{}", std::process::id()));
+    let url = ServoUrl::parse("https://servo.org").unwrap();
+    let receiving_url = ServoUrl::parse("https://servo.org/receiving").unwrap();
+    let origin = Origin::Origin(url.clone().origin());
+    let request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    let receiving_request =
+        Request::new(receiving_url.clone(), Some(origin), Some(TEST_PIPELINE_ID));
+    let response = cacheable_response(&url, b"Servo");
+    let receiving_response = cacheable_response(&receiving_url, b"");
+    *receiving_response.body.lock().unwrap() = ResponseBody::Receiving(vec![]);
+    let mut cache = HttpCache::with_disk_cache(&directory);
+    cache.store(&request, &response);
+    cache.store(&receiving_request, &receiving_response);
+
+    let disk_cache = HttpCache::with_disk_cache(&directory);
+    let mut done_chan = None;
+    let cached_response = disk_cache
+        .construct_response(&request, &mut done_chan)
+        .expect("The complete resource was read from disk");
+    assert_eq!(cached_response.response.url_list, response.url_list);
+    assert_eq!(
+        *cached_response.response.body.lock().unwrap(),
+        ResponseBody::Done(b"Servo".to_vec())
+    );
+    assert!(disk_cache
+        .construct_response(&receiving_request, &mut done_chan)
+        .is_none());
+
+    // The resource is written to disk once its body is complete.
+    *receiving_response.body.lock().unwrap() = ResponseBody::Done(b"Received".to_vec());
+    cache.write_complete_resources(&receiving_request);
+    let disk_cache = HttpCache::with_disk_cache(&directory);
+    let _ = fs::remove_dir_all(&directory);
+    let cached_response = disk_cache
+        .construct_response(&receiving_request, &mut done_chan)
+        .expect("The completed resource was read from disk");
+    assert_eq!(
+        *cached_response.response.body.lock().unwrap(),
+        ResponseBody::Done(b"Received".to_vec())
+    );
+}
+
+#[test]
+fn test_bodies_are_read_from_disk_when_they_are_used() {
+    let directory =
+        std::env::temp_dir().join(format!("servo-http-cache-lazy-test-{}", std::process::id()));
+    let url = ServoUrl::parse("https://servo.org").unwrap();
+    let origin = Origin::Origin(url.clone().origin());
+    let request = Request::new(url.clone(), Some(origin), Some(TEST_PIPELINE_ID));
+    let mut cache = HttpCache::with_disk_cache(&directory);
+    cache.store(&request, &cacheable_response(&url, b"Servo"));
+
+    // The bodies are still on disk when the cache is created, so a body removed since
+    // then can't be used.
+    let disk_cache = HttpCache::with_disk_cache(&directory);
+    for entry in fs::read_dir(&directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "body")
+        {
+            fs::remove_file(path).unwrap();
+        }
+    }
+    let _ = fs::remove_dir_all(&directory);
+    let mut done_chan = None;
+    assert!(disk_cache
+        .construct_response(&request, &mut done_chan)
+        .is_none());
+}
+
+#[test]
+fn test_only_the_request_headers_named_by_vary_are_written_to_disk() {
+    let directory =
+        std::env::temp_dir().join(format!("servo-http-cache-vary-test-{}", std::process::id()));
+    let url = ServoUrl::parse("https://servo.org").unwrap();
+    let cookie_url = ServoUrl::parse("https://servo.org/cookie").unwrap();
+    let origin = Origin::Origin(url.clone().origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), Some(TEST_PIPELINE_ID));
+    request
+        .headers
+        .insert(ACCEPT_LANGUAGE, HeaderValue::from_static("fr"));
+    request
+        .headers
+        .insert(COOKIE, HeaderValue::from_static("session=secret"));
+    request.headers.insert(
+        AUTHORIZATION,
+        HeaderValue::from_static("Basic c2Vydm86c2Vydm8="),
+    );
+    let mut cookie_request = request.clone();
+    cookie_request.url_list = vec![cookie_url.clone()];
+    let mut response = cacheable_response(&url, b"Servo");
+    response
+        .headers
+        .insert(VARY, HeaderValue::from_static("accept-language"));
+    let mut cookie_response = cacheable_response(&cookie_url, b"Cookie");
+    cookie_response
+        .headers
+        .insert(VARY, HeaderValue::from_static("cookie"));
+    let mut cache = HttpCache::with_disk_cache(&directory);
+    cache.store(&request, &response);
+    cache.store(&cookie_request, &cookie_response);
+
+    let index = fs::read_to_string(directory.join("index.json")).unwrap();
+    let cache = HttpCache::with_disk_cache(&directory);
+    let _ = fs::remove_dir_all(&directory);
+    assert!(!index.contains("secret"));
+    assert!(!index.contains("c2Vydm86c2Vydm8="));
+    let mut done_chan = None;
+    let mut matching_request = Request::new(url.clone(), Some(origin), Some(TEST_PIPELINE_ID));
+    matching_request
+        .headers
+        .insert(ACCEPT_LANGUAGE, HeaderValue::from_static("fr"));
+    assert!(cache
+        .construct_response(&matching_request, &mut done_chan)
+        .is_some());
+    assert!(cache
+        .construct_response(&cookie_request, &mut done_chan)
+        .is_none());
+}
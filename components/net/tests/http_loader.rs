@@ -11,6 +11,7 @@ use crossbeam_channel::{unbounded, Receiver};
 use devtools_traits::HttpRequest as DevtoolsHttpRequest;
 use devtools_traits::HttpResponse as DevtoolsHttpResponse;
 use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg, NetworkEvent};
+use embedder_traits::{PromptMsg, PromptRequest, PromptResponse};
 use flate2::write::{DeflateEncoder, GzEncoder};
 use flate2::Compression;
 use futures::{self, Future, Stream};
@@ -19,32 +20,56 @@ use headers::{
     AccessControlAllowOrigin, Authorization, ContentLength, Date, HeaderMapExt, Host, Origin,
     StrictTransportSecurity, UserAgent,
 };
-use http::header::{self, HeaderMap, HeaderValue};
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use http::uri::Authority;
 use http::{Method, StatusCode};
 use hyper::body::Body;
 use hyper::{Request as HyperRequest, Response as HyperResponse};
+use ipc_channel::ipc;
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::cookie::Cookie;
 use net::cookie_storage::CookieStorage;
 use net::http_loader::determine_request_referrer;
 use net::resource_thread::AuthCacheEntry;
 use net::test::replace_host_table;
-use net_traits::request::{CredentialsMode, Destination, RequestBuilder, RequestMode};
+use net_traits::request::{BodyChunkRequest, BodyChunkResponse};
+use net_traits::request::{
+    ClientViewport, CredentialsMode, Destination, RequestBuilder, RequestMode,
+};
 use net_traits::response::ResponseBody;
 use net_traits::{CookieSource, NetworkError, ReferrerPolicy};
+use servo_config::set_pref;
 use servo_url::{ImmutableOrigin, ServoUrl};
 use std::collections::HashMap;
 use std::io::Write;
 use std::str;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
 use std::time::Duration;
 
 fn mock_origin() -> ImmutableOrigin {
     ServoUrl::parse("http://servo.org").unwrap().origin()
 }
 
+/// Append the client hints which are sent to every potentially trustworthy origin, such as
+/// the local servers of the tests.
+fn append_low_entropy_client_hints(headers: &mut HeaderMap) {
+    set_pref!(network.client_hints.platform, "Servo Test".to_owned());
+    headers.insert(
+        HeaderName::from_static("sec-ch-ua"),
+        HeaderValue::from_static("\"Servo\";v=\"0\""),
+    );
+    headers.insert(
+        HeaderName::from_static("sec-ch-ua-mobile"),
+        HeaderValue::from_static("?0"),
+    );
+    headers.insert(
+        HeaderName::from_static("sec-ch-ua-platform"),
+        HeaderValue::from_static("\"Servo Test\""),
+    );
+}
+
 fn read_response(req: HyperRequest<Body>) -> impl Future<Item = String, Error = ()> {
     req.into_body()
         .concat2()
@@ -133,6 +158,8 @@ fn test_check_default_headers_loaded_in_every_request() {
 
     headers.typed_insert::<UserAgent>(crate::DEFAULT_USER_AGENT.parse().unwrap());
 
+    append_low_entropy_client_hints(&mut headers);
+
     *expected_headers.lock().unwrap() = Some(headers.clone());
 
     // Testing for method.GET
@@ -302,7 +329,10 @@ fn test_request_and_response_data_with_network_messages() {
     let httpresponse = DevtoolsHttpResponse {
         headers: Some(response_headers),
         status: Some((200, b"OK".to_vec())),
-        body: None,
+        body: Some(content.as_bytes().to_vec()),
+        body_size: content.len(),
+        receive_time: devhttpresponse.receive_time,
+        from_cache: false,
         pipeline_id: TEST_PIPELINE_ID,
     };
 
@@ -494,6 +524,72 @@ fn test_load_should_decode_the_response_as_gzip_when_response_headers_have_conte
     );
 }
 
+fn brotli_encoded(content: &[u8]) -> Vec<u8> {
+    let mut e = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+    e.write_all(content).unwrap();
+    e.into_inner()
+}
+
+#[test]
+fn test_load_should_decode_the_response_as_brotli_when_response_headers_have_content_encoding_br() {
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("br"));
+        *response.body_mut() = brotli_encoded(b"Yay!").into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::GET)
+        .body(None)
+        .destination(Destination::Document)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .build();
+
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    let internal_response = response.internal_response.unwrap();
+    assert!(internal_response.status.clone().unwrap().0.is_success());
+    assert_eq!(
+        *internal_response.body.lock().unwrap(),
+        ResponseBody::Done(b"Yay!".to_vec())
+    );
+}
+
+#[test]
+fn test_load_should_decode_content_codings_case_insensitively() {
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static("identity, BR"),
+        );
+        *response.body_mut() = brotli_encoded(b"Yay!").into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::GET)
+        .body(None)
+        .destination(Destination::Document)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .build();
+
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    let internal_response = response.internal_response.unwrap();
+    assert_eq!(
+        *internal_response.body.lock().unwrap(),
+        ResponseBody::Done(b"Yay!".to_vec())
+    );
+}
+
 #[test]
 fn test_load_doesnt_send_request_body_on_any_redirect() {
     let post_handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
@@ -604,6 +700,7 @@ fn test_load_sets_cookies_in_the_resource_manager_when_it_get_set_cookie_header_
         .method(Method::GET)
         .body(None)
         .destination(Destination::Document)
+        .top_level_navigation(true)
         .origin(mock_origin())
         .pipeline_id(Some(TEST_PIPELINE_ID))
         .credentials_mode(CredentialsMode::Include)
@@ -656,6 +753,7 @@ fn test_load_sets_requests_cookies_header_for_url_by_getting_cookies_from_the_re
         .method(Method::GET)
         .body(None)
         .destination(Destination::Document)
+        .top_level_navigation(true)
         .origin(mock_origin())
         .pipeline_id(Some(TEST_PIPELINE_ID))
         .credentials_mode(CredentialsMode::Include)
@@ -702,6 +800,7 @@ fn test_load_sends_cookie_if_nonhttp() {
         .method(Method::GET)
         .body(None)
         .destination(Destination::Document)
+        .top_level_navigation(true)
         .origin(mock_origin())
         .pipeline_id(Some(TEST_PIPELINE_ID))
         .credentials_mode(CredentialsMode::Include)
@@ -720,6 +819,50 @@ fn test_load_sends_cookie_if_nonhttp() {
         .is_success());
 }
 
+#[test]
+fn test_load_does_not_send_lax_cookies_with_cross_site_subresource_requests() {
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        assert!(request.headers().get(header::COOKIE).is_none());
+        *response.body_mut() = b"Yay!".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut context = new_fetch_context(None, None, None);
+
+    {
+        let mut cookie_jar = context.state.cookie_jar.write().unwrap();
+        let cookie = Cookie::new_wrapped(
+            CookiePair::new("mozillaIs".to_owned(), "theBest".to_owned()),
+            &url,
+            CookieSource::NonHTTP,
+        )
+        .unwrap();
+        cookie_jar.push(cookie, &url, CookieSource::HTTP);
+    }
+
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::GET)
+        .body(None)
+        .destination(Destination::Image)
+        .origin(url.origin())
+        .site_for_cookies(Some(mock_origin()))
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .credentials_mode(CredentialsMode::Include)
+        .build();
+
+    let response = fetch_with_context(&mut request, &mut context);
+
+    let _ = server.close();
+
+    assert!(response
+        .internal_response
+        .unwrap()
+        .status
+        .unwrap()
+        .0
+        .is_success());
+}
+
 #[test]
 fn test_cookie_set_with_httponly_should_not_be_available_using_getcookiesforurl() {
     let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
@@ -739,6 +882,7 @@ fn test_cookie_set_with_httponly_should_not_be_available_using_getcookiesforurl(
         .method(Method::GET)
         .body(None)
         .destination(Destination::Document)
+        .top_level_navigation(true)
         .origin(mock_origin())
         .pipeline_id(Some(TEST_PIPELINE_ID))
         .credentials_mode(CredentialsMode::Include)
@@ -786,6 +930,7 @@ fn test_when_cookie_received_marked_secure_is_ignored_for_http() {
         .method(Method::GET)
         .body(None)
         .destination(Destination::Document)
+        .top_level_navigation(true)
         .origin(mock_origin())
         .pipeline_id(Some(TEST_PIPELINE_ID))
         .credentials_mode(CredentialsMode::Include)
@@ -840,6 +985,61 @@ fn test_load_sets_content_length_to_length_of_request_body() {
         .is_success());
 }
 
+#[test]
+fn test_load_streams_request_body_with_chunked_transfer_coding() {
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        assert_eq!(request.headers().typed_get::<ContentLength>(), None);
+        assert_eq!(
+            request.headers().get(header::TRANSFER_ENCODING).unwrap(),
+            "chunked"
+        );
+        *response.body_mut() = b"Yay!".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let (body_stream, body_requests) = ipc::channel().unwrap();
+    let body_source = thread::spawn(move || {
+        let mut chunks = vec![b"Streamed ".to_vec(), b"body".to_vec()].into_iter();
+        let mut chunk_sender = None;
+        let mut chunks_requested = 0;
+        while let Ok(message) = body_requests.recv() {
+            match message {
+                BodyChunkRequest::Connect(sender) => chunk_sender = Some(sender),
+                BodyChunkRequest::Chunk => {
+                    chunks_requested += 1;
+                    let chunk = chunks
+                        .next()
+                        .map_or(BodyChunkResponse::Done, BodyChunkResponse::Chunk);
+                    chunk_sender.as_ref().unwrap().send(chunk).unwrap();
+                },
+                BodyChunkRequest::Done => break,
+            }
+        }
+        chunks_requested
+    });
+
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::POST)
+        .body_stream(body_stream)
+        .destination(Destination::Document)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .build();
+
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    assert!(response
+        .internal_response
+        .unwrap()
+        .status
+        .unwrap()
+        .0
+        .is_success());
+    assert_eq!(body_source.join().unwrap(), 3);
+}
+
 #[test]
 fn test_load_uses_explicit_accept_from_headers_in_load_data() {
     let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
@@ -1204,6 +1404,7 @@ fn test_redirect_from_x_to_y_provides_y_cookies_from_y() {
     let mut request = RequestBuilder::new(url_x.clone())
         .method(Method::GET)
         .destination(Destination::Document)
+        .top_level_navigation(true)
         .origin(mock_origin())
         .pipeline_id(Some(TEST_PIPELINE_ID))
         .credentials_mode(CredentialsMode::Include)
@@ -1253,6 +1454,7 @@ fn test_redirect_from_x_to_x_provides_x_with_cookie_from_first_response() {
     let mut request = RequestBuilder::new(url.clone())
         .method(Method::GET)
         .destination(Destination::Document)
+        .top_level_navigation(true)
         .origin(mock_origin())
         .pipeline_id(Some(TEST_PIPELINE_ID))
         .credentials_mode(CredentialsMode::Include)
@@ -1344,6 +1546,63 @@ fn test_auth_ui_needs_www_auth() {
     );
 }
 
+#[test]
+fn test_auth_prompt_credentials_are_sent_and_cached() {
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        let expected = Authorization::basic("username", "test");
+        if request.headers().typed_get::<Authorization<Basic>>() != Some(expected) {
+            *response.status_mut() = StatusCode::UNAUTHORIZED;
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"servo\""),
+            );
+        }
+    };
+    let (server, url) = make_server(handler);
+
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::GET)
+        .body(None)
+        .destination(Destination::Document)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .credentials_mode(CredentialsMode::Include)
+        .build();
+
+    let (prompt_sender, prompt_receiver) = unbounded();
+    let mut context = new_fetch_context(None, None, None);
+    context.prompt_sender = prompt_sender;
+
+    let prompt_url = url.clone();
+    let prompter = thread::spawn(move || {
+        let prompt: PromptMsg = prompt_receiver.recv().unwrap();
+        assert_eq!(prompt.pipeline_id, Some(TEST_PIPELINE_ID));
+        assert_eq!(
+            prompt.request,
+            PromptRequest::Authentication(prompt_url, Some("servo".to_owned()))
+        );
+        let credentials = PromptResponse::Credentials("username".to_owned(), "test".to_owned());
+        prompt.sender.send(credentials).unwrap();
+    });
+
+    let response = fetch_with_context(&mut request, &mut context);
+
+    prompter.join().unwrap();
+    let _ = server.close();
+
+    assert!(response
+        .internal_response
+        .unwrap()
+        .status
+        .unwrap()
+        .0
+        .is_success());
+    let auth_cache = context.state.auth_cache.read().unwrap();
+    let auth_entry = &auth_cache.entries[&url.origin().ascii_serialization()];
+    assert_eq!(auth_entry.user_name, "username");
+    assert_eq!(auth_entry.password, "test");
+}
+
 #[test]
 fn test_origin_set() {
     let origin_header = Arc::new(Mutex::new(None));
@@ -1461,3 +1720,87 @@ fn test_determine_request_referrer_longer_than_4k() {
 
     assert_eq!(referer.unwrap().as_str(), "http://example.com/");
 }
+
+#[test]
+fn test_client_hints_are_sent_once_the_origin_accepts_them() {
+    let request_headers = Arc::new(Mutex::new(vec![]));
+    let request_headers_clone = request_headers.clone();
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        request_headers_clone
+            .lock()
+            .unwrap()
+            .push(request.headers().clone());
+        response.headers_mut().insert(
+            HeaderName::from_static("accept-ch"),
+            HeaderValue::from_static("DPR, Viewport-Width, Sec-CH-UA-Full-Version"),
+        );
+    };
+    let (server, url) = make_server(handler);
+    set_pref!(network.client_hints.full_version, "0.0.1".to_owned());
+    let viewport = Some(ClientViewport {
+        device_pixel_ratio: 2.,
+        width: 800.,
+    });
+
+    let mut context = new_fetch_context(None, None, None);
+    let mut request = RequestBuilder::new(url.clone())
+        .destination(Destination::Document)
+        .origin(url.origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .viewport(viewport)
+        .build();
+    let _ = fetch_with_context(&mut request, &mut context);
+
+    let mut request = RequestBuilder::new(url.join("image.png").unwrap())
+        .destination(Destination::Image)
+        .origin(url.origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .viewport(viewport)
+        .build();
+    let _ = fetch_with_context(&mut request, &mut context);
+
+    let _ = server.close();
+
+    let request_headers = request_headers.lock().unwrap();
+    let hint = |index: usize, name: &str| {
+        request_headers[index]
+            .get(name)
+            .map(|value| value.to_str().unwrap().to_owned())
+    };
+    assert_eq!(hint(0, "sec-ch-ua"), Some("\"Servo\";v=\"0\"".to_owned()));
+    assert_eq!(hint(0, "sec-ch-ua-mobile"), Some("?0".to_owned()));
+    assert_eq!(hint(0, "dpr"), None);
+    assert_eq!(hint(0, "viewport-width"), None);
+    assert_eq!(hint(0, "sec-ch-ua-full-version"), None);
+    assert_eq!(hint(1, "dpr"), Some("2".to_owned()));
+    assert_eq!(hint(1, "viewport-width"), Some("800".to_owned()));
+    assert_eq!(
+        hint(1, "sec-ch-ua-full-version"),
+        Some("\"0.0.1\"".to_owned())
+    );
+    assert_eq!(hint(1, "sec-ch-ua-model"), None);
+}
+
+#[test]
+fn test_client_hints_are_not_sent_to_other_origins() {
+    let request_headers = Arc::new(Mutex::new(None));
+    let request_headers_clone = request_headers.clone();
+    let handler = move |request: HyperRequest<Body>, _: &mut HyperResponse<Body>| {
+        *request_headers_clone.lock().unwrap() = Some(request.headers().clone());
+    };
+    let (server, url) = make_server(handler);
+
+    let mut request = RequestBuilder::new(url.clone())
+        .destination(Destination::Image)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .build();
+    let _ = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    let request_headers = request_headers.lock().unwrap().take().unwrap();
+    assert!(!request_headers.contains_key("sec-ch-ua"));
+    assert!(!request_headers.contains_key("sec-ch-ua-mobile"));
+    assert!(!request_headers.contains_key("sec-ch-ua-platform"));
+}
@@ -7,6 +7,7 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod content_blocker;
 mod cookie;
 mod cookie_http_state;
 mod data_loader;
@@ -18,8 +19,10 @@ mod http_cache;
 mod http_loader;
 mod mime_classifier;
 mod resource_thread;
+mod storage_thread;
 mod subresource_integrity;
 
+use content_security_policy as csp;
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::DevtoolsControlMsg;
 use embedder_traits::resources::{self, Resource};
@@ -29,17 +32,22 @@ use hyper::server::conn::Http;
 use hyper::server::Server as HyperServer;
 use hyper::service::service_fn_ok;
 use hyper::{Body, Request as HyperRequest, Response as HyperResponse};
+use ipc_channel::ipc;
+use net::about_loader::AboutPages;
 use net::connector::{create_tls_config, ALPN_H2_H1};
+use net::content_blocker::ContentBlocker;
 use net::fetch::cors_cache::CorsCache;
 use net::fetch::methods::{self, CancellationListener, FetchContext};
 use net::filemanager_thread::FileManager;
 use net::resource_thread::CoreResourceThreadPool;
 use net::test::HttpState;
 use net_traits::filemanager_thread::FileTokenCheck;
+use net_traits::protocol_handler::ProtocolRegistry;
 use net_traits::request::Request;
 use net_traits::response::Response;
 use net_traits::{FetchTaskTarget, ResourceFetchTiming, ResourceTimingType};
-use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use openssl::ssl::{self, AlpnError, SslAcceptor, SslFiletype, SslMethod};
+use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use servo_arc::Arc as ServoArc;
 use servo_url::ServoUrl;
 use std::net::TcpListener as StdTcpListener;
@@ -85,6 +93,11 @@ fn create_embedder_proxy() -> EmbedderProxy {
     }
 }
 
+fn create_about_pages() -> Arc<AboutPages> {
+    let (mem_profiler_chan, _) = ipc::channel().unwrap();
+    Arc::new(AboutPages::new(MemProfilerChan(mem_profiler_chan), None))
+}
+
 fn new_fetch_context(
     dc: Option<Sender<DevtoolsControlMsg>>,
     fc: Option<EmbedderProxy>,
@@ -96,8 +109,12 @@ fn new_fetch_context(
 
     FetchContext {
         state: Arc::new(HttpState::new(tls_config)),
+        about_pages: create_about_pages(),
+        protocols: Arc::new(ProtocolRegistry::default()),
+        content_blocker: Arc::new(ContentBlocker::default()),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: dc,
+        prompt_sender: unbounded().0,
         filemanager: FileManager::new(sender, pool_handle.unwrap_or_else(|| Weak::new())),
         file_token: FileTokenCheck::NotRequired,
         cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
@@ -115,6 +132,7 @@ impl FetchTaskTarget for FetchResponseCollector {
     fn process_response_eof(&mut self, response: &Response) {
         let _ = self.sender.send(response.clone());
     }
+    fn process_csp_violations(&mut self, _: &Request, _: Vec<csp::Violation>) {}
 }
 
 fn fetch(request: &mut Request, dc: Option<Sender<DevtoolsControlMsg>>) -> Response {
@@ -199,6 +217,10 @@ where
         tls_server_config
             .set_private_key_file(&key_path, SslFiletype::PEM)
             .unwrap();
+        // Prefer HTTP/2 like most servers, which hyper switches to on its preface.
+        tls_server_config.set_alpn_select_callback(|_, client_protocols| {
+            ssl::select_next_proto(ALPN_H2_H1, client_protocols).ok_or(AlpnError::NOACK)
+        });
 
         let handler = handler.clone();
         tls_server_config
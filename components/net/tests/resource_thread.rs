@@ -3,13 +3,18 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::create_embedder_proxy;
+use crossbeam_channel::unbounded;
+use hyper_serde::Serde;
 use ipc_channel::ipc;
-use net::resource_thread::new_core_resource_thread;
+use net::resource_thread::{new_core_resource_thread, CoreResourceThreadPool, WorkPriority};
 use net::test::parse_hostsfile;
-use net_traits::CoreResourceMsg;
+use net_traits::protocol_handler::ProtocolRegistry;
+use net_traits::{CookieSource, CoreResourceMsg};
 use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use profile_traits::time::ProfilerChan;
+use servo_url::ServoUrl;
 use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
 
 fn ip(s: &str) -> IpAddr {
     s.parse().unwrap()
@@ -26,13 +31,68 @@ fn test_exit() {
         ProfilerChan(tx),
         MemProfilerChan(mtx),
         create_embedder_proxy(),
+        unbounded().0,
         None,
         None,
+        Arc::new(ProtocolRegistry::default()),
     );
     resource_thread.send(CoreResourceMsg::Exit(sender)).unwrap();
     receiver.recv().unwrap();
 }
 
+#[test]
+fn test_private_session_cookies_are_isolated() {
+    let (tx, _rx) = ipc::channel().unwrap();
+    let (mtx, _mrx) = ipc::channel().unwrap();
+    let (resource_thread, _private_resource_thread) = new_core_resource_thread(
+        "".into(),
+        None,
+        ProfilerChan(tx),
+        MemProfilerChan(mtx),
+        create_embedder_proxy(),
+        unbounded().0,
+        None,
+        None,
+        Arc::new(ProtocolRegistry::default()),
+    );
+    let (session, session_receiver) = ipc::channel().unwrap();
+    resource_thread
+        .send(CoreResourceMsg::NewPrivateSession(session_receiver))
+        .unwrap();
+
+    let url = ServoUrl::parse("https://example.test/").unwrap();
+    let cookie = cookie_rs::Cookie::parse("private=1").unwrap().into_owned();
+    session
+        .send(CoreResourceMsg::SetCookieForUrl(
+            url.clone(),
+            Serde(cookie),
+            CookieSource::HTTP,
+        ))
+        .unwrap();
+
+    let cookies = |thread: &ipc::IpcSender<CoreResourceMsg>| {
+        let (sender, receiver) = ipc::channel().unwrap();
+        thread
+            .send(CoreResourceMsg::GetCookiesForUrl(
+                url.clone(),
+                sender,
+                CookieSource::HTTP,
+            ))
+            .unwrap();
+        receiver.recv().unwrap()
+    };
+    assert_eq!(cookies(&session), Some("private=1".to_owned()));
+    assert_eq!(cookies(&resource_thread), None);
+
+    // A new session starts without the cookies of the previous one.
+    drop(session);
+    let (session, session_receiver) = ipc::channel().unwrap();
+    resource_thread
+        .send(CoreResourceMsg::NewPrivateSession(session_receiver))
+        .unwrap();
+    assert_eq!(cookies(&session), None);
+}
+
 #[test]
 fn test_parse_hostsfile() {
     let mock_hosts_file_content = "127.0.0.1 foo.bar.com\n127.0.0.2 servo.test.server";
@@ -163,3 +223,33 @@ fn test_parse_hostsfile_with_end_of_line_whitespace() {
         *hosts_table.get("servo.test.server").unwrap()
     );
 }
+
+#[test]
+fn test_pending_work_of_higher_priority_runs_first() {
+    let pool = CoreResourceThreadPool::new(1);
+    let (unblock_sender, unblock_receiver) = unbounded::<()>();
+    let (done_sender, done_receiver) = unbounded();
+    // Keep the only thread busy until every other piece of work is pending.
+    pool.spawn(move || unblock_receiver.recv().unwrap());
+    let order = Arc::new(Mutex::new(vec![]));
+    let priorities = vec![
+        WorkPriority::Low,
+        WorkPriority::Normal,
+        WorkPriority::Highest,
+        WorkPriority::Normal,
+        WorkPriority::High,
+    ];
+    for (index, priority) in priorities.into_iter().enumerate() {
+        let order = order.clone();
+        let done_sender = done_sender.clone();
+        pool.spawn_with_priority(priority, move || {
+            order.lock().unwrap().push(index);
+            done_sender.send(()).unwrap();
+        });
+    }
+    unblock_sender.send(()).unwrap();
+    for _ in 0..5 {
+        done_receiver.recv().unwrap();
+    }
+    assert_eq!(*order.lock().unwrap(), vec![2, 4, 1, 3, 0]);
+}
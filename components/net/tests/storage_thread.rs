@@ -0,0 +1,663 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use http::header::{HeaderMap, HeaderValue, ACCEPT, VARY};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::{BrowsingContextId, BrowsingContextIndex};
+use msg::constellation_msg::{TopLevelBrowsingContextId, TEST_NAMESPACE};
+use net::storage_thread::StorageThreadFactory;
+use net_traits::response::ResponseType;
+use net_traits::storage_thread::{CacheId, CacheOperation, CacheOperationError};
+use net_traits::storage_thread::{CacheQueryOptions, CacheQueryTarget};
+use net_traits::storage_thread::{CacheRequest, CacheResponse};
+use net_traits::storage_thread::{StorageMutation, StorageThreadMsg, StorageType};
+use net_traits::storage_thread::{CACHE_QUOTA_SIZE_LIMIT, QUOTA_SIZE_LIMIT};
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::collections::BTreeMap;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+fn browser(index: u32) -> TopLevelBrowsingContextId {
+    TopLevelBrowsingContextId(BrowsingContextId {
+        namespace_id: TEST_NAMESPACE,
+        index: BrowsingContextIndex(NonZeroU32::new(index).unwrap()),
+    })
+}
+
+fn snapshot(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    browsing_context: TopLevelBrowsingContextId,
+    url: &ServoUrl,
+    storage_type: StorageType,
+) -> BTreeMap<String, String> {
+    snapshot_with_other_size(storage_thread, browsing_context, url, storage_type).0
+}
+
+fn snapshot_with_other_size(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    browsing_context: TopLevelBrowsingContextId,
+    url: &ServoUrl,
+    storage_type: StorageType,
+) -> (BTreeMap<String, String>, usize) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::Snapshot(
+            sender,
+            browsing_context,
+            url.clone(),
+            storage_type,
+        ))
+        .unwrap();
+    receiver.recv().unwrap()
+}
+
+/// The values kept for the keys whose mutations were skipped.
+fn mutate(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    browsing_context: TopLevelBrowsingContextId,
+    url: &ServoUrl,
+    storage_type: StorageType,
+    mutations: Vec<StorageMutation>,
+) -> Vec<(String, Option<String>)> {
+    mutate_with_other_size(
+        storage_thread,
+        browsing_context,
+        url,
+        storage_type,
+        mutations,
+    )
+    .0
+}
+
+fn mutate_with_other_size(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    browsing_context: TopLevelBrowsingContextId,
+    url: &ServoUrl,
+    storage_type: StorageType,
+    mutations: Vec<StorageMutation>,
+) -> (Vec<(String, Option<String>)>, usize) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::Mutate(
+            sender,
+            browsing_context,
+            url.clone(),
+            storage_type,
+            mutations,
+        ))
+        .unwrap();
+    receiver.recv().unwrap()
+}
+
+fn set_session_item(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    browsing_context: TopLevelBrowsingContextId,
+    url: &ServoUrl,
+    name: &str,
+) {
+    let mutations = vec![StorageMutation::SetItem(
+        name.to_owned(),
+        "value".to_owned(),
+    )];
+    let rejected = mutate(
+        storage_thread,
+        browsing_context,
+        url,
+        StorageType::Session,
+        mutations,
+    );
+    assert!(rejected.is_empty());
+}
+
+fn cache_request(url: &str, accept: &str) -> CacheRequest {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, HeaderValue::from_str(accept).unwrap());
+    CacheRequest {
+        url: ServoUrl::parse(url).unwrap(),
+        method: "GET".to_owned(),
+        headers,
+    }
+}
+
+fn cache_response(body: &str, vary: Option<&str>) -> CacheResponse {
+    let mut headers = HeaderMap::new();
+    if let Some(vary) = vary {
+        headers.insert(VARY, HeaderValue::from_str(vary).unwrap());
+    }
+    CacheResponse {
+        response_type: ResponseType::Basic,
+        url_list: vec![],
+        status: Some((200, b"OK".to_vec())),
+        headers,
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+fn open_cache(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    origin: &ImmutableOrigin,
+    name: &str,
+) -> CacheId {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::OpenCache(
+            sender,
+            origin.clone(),
+            name.to_owned(),
+        ))
+        .unwrap();
+    receiver.recv().unwrap()
+}
+
+fn batch_cache_operations(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    origin: &ImmutableOrigin,
+    cache: CacheId,
+    operations: Vec<CacheOperation>,
+) -> Result<bool, CacheOperationError> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::BatchCacheOperations(
+            sender,
+            origin.clone(),
+            cache,
+            operations,
+        ))
+        .unwrap();
+    receiver.recv().unwrap()
+}
+
+/// The bodies of the responses of the items which match a query.
+fn query_cache(
+    storage_thread: &IpcSender<StorageThreadMsg>,
+    origin: &ImmutableOrigin,
+    target: CacheQueryTarget,
+    request: Option<CacheRequest>,
+    options: CacheQueryOptions,
+) -> Vec<String> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::QueryCache(
+            sender,
+            origin.clone(),
+            target,
+            request,
+            options,
+        ))
+        .unwrap();
+    receiver
+        .recv()
+        .unwrap()
+        .into_iter()
+        .map(|(_, response)| String::from_utf8(response.body).unwrap())
+        .collect()
+}
+
+fn exit(storage_thread: &IpcSender<StorageThreadMsg>) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread.send(StorageThreadMsg::Exit(sender)).unwrap();
+    receiver.recv().unwrap();
+}
+
+#[test]
+fn test_local_storage_mutations_are_persisted() {
+    let config_dir: PathBuf =
+        std::env::temp_dir().join(format!("servo-storage-test-{}", std::process::id()));
+    fs::create_dir_all(&config_dir).unwrap();
+    let url = ServoUrl::parse("http://example.com/page").unwrap();
+    let same_origin_url = ServoUrl::parse("http://example.com/other").unwrap();
+    let browser = browser(1);
+
+    let storage_thread: IpcSender<StorageThreadMsg> =
+        StorageThreadFactory::new(Some(config_dir.clone()));
+    let mutations = vec![
+        StorageMutation::SetItem("removed".to_owned(), "value".to_owned()),
+        StorageMutation::Clear,
+        StorageMutation::SetItem("key".to_owned(), "value".to_owned()),
+        StorageMutation::SetItem("large".to_owned(), "x".repeat(QUOTA_SIZE_LIMIT)),
+        StorageMutation::SetItem("other".to_owned(), "value".to_owned()),
+        StorageMutation::RemoveItem("other".to_owned()),
+    ];
+    assert_eq!(
+        mutate(
+            &storage_thread,
+            browser,
+            &url,
+            StorageType::Local,
+            mutations
+        ),
+        vec![("large".to_owned(), None)]
+    );
+    set_session_item(&storage_thread, browser, &url, "session");
+
+    let mut expected = BTreeMap::new();
+    expected.insert("key".to_owned(), "value".to_owned());
+    assert_eq!(
+        snapshot(
+            &storage_thread,
+            browser,
+            &same_origin_url,
+            StorageType::Local
+        ),
+        expected
+    );
+    assert_eq!(
+        snapshot(&storage_thread, browser, &url, StorageType::Session).len(),
+        1
+    );
+    exit(&storage_thread);
+
+    // Only the local storage outlives the storage thread.
+    let storage_thread: IpcSender<StorageThreadMsg> =
+        StorageThreadFactory::new(Some(config_dir.clone()));
+    assert_eq!(
+        snapshot(&storage_thread, browser, &url, StorageType::Local),
+        expected
+    );
+    assert!(snapshot(&storage_thread, browser, &url, StorageType::Session).is_empty());
+    exit(&storage_thread);
+
+    let _ = fs::remove_dir_all(&config_dir);
+}
+
+#[test]
+fn test_session_storage_is_kept_per_top_level_browsing_context() {
+    let url = ServoUrl::parse("http://example.com/page").unwrap();
+    let other_url = ServoUrl::parse("http://example.org/page").unwrap();
+    let opener = browser(1);
+    let auxiliary = browser(2);
+    let restored = browser(3);
+
+    let storage_thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(None);
+    set_session_item(&storage_thread, opener, &url, "opener");
+    set_session_item(&storage_thread, opener, &other_url, "other");
+    assert!(snapshot(&storage_thread, auxiliary, &url, StorageType::Session).is_empty());
+
+    // Only the storage area of the origin of the opener is copied, after which the
+    // browsing contexts each change their own copy.
+    storage_thread
+        .send(StorageThreadMsg::CloneSessionStorage(
+            opener,
+            auxiliary,
+            url.clone(),
+        ))
+        .unwrap();
+    set_session_item(&storage_thread, auxiliary, &url, "auxiliary");
+    let opener_items = snapshot(&storage_thread, opener, &url, StorageType::Session);
+    assert_eq!(opener_items.keys().collect::<Vec<_>>(), vec!["opener"]);
+    let auxiliary_items = snapshot(&storage_thread, auxiliary, &url, StorageType::Session);
+    assert_eq!(
+        auxiliary_items.keys().collect::<Vec<_>>(),
+        vec!["auxiliary", "opener"]
+    );
+    assert!(snapshot(&storage_thread, auxiliary, &other_url, StorageType::Session).is_empty());
+
+    // The session storage of a browser is saved and restored with it.
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::GetSessionStorage(sender, opener))
+        .unwrap();
+    let saved = receiver.recv().unwrap();
+    assert_eq!(saved.len(), 2);
+    storage_thread
+        .send(StorageThreadMsg::SetSessionStorage(restored, saved))
+        .unwrap();
+    assert_eq!(
+        snapshot(&storage_thread, restored, &other_url, StorageType::Session),
+        snapshot(&storage_thread, opener, &other_url, StorageType::Session)
+    );
+
+    storage_thread
+        .send(StorageThreadMsg::DiscardSessionStorage(opener))
+        .unwrap();
+    assert!(snapshot(&storage_thread, opener, &url, StorageType::Session).is_empty());
+    exit(&storage_thread);
+}
+
+#[test]
+fn test_local_and_session_storage_share_the_quota_of_an_origin() {
+    let url = ServoUrl::parse("http://example.com/page").unwrap();
+    let other_url = ServoUrl::parse("http://example.org/page").unwrap();
+    let browser = browser(1);
+    let half = "x".repeat(QUOTA_SIZE_LIMIT / 2);
+
+    let storage_thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(None);
+    let mutations = vec![StorageMutation::SetItem("key".to_owned(), half.clone())];
+    assert!(mutate(
+        &storage_thread,
+        browser,
+        &url,
+        StorageType::Local,
+        mutations
+    )
+    .is_empty());
+    let (_, other_size) =
+        snapshot_with_other_size(&storage_thread, browser, &url, StorageType::Session);
+    assert_eq!(other_size, "key".len() + half.len());
+
+    // The session storage item no longer fits once the local storage one is counted, and the
+    // session storage keeps the value it had, while another origin has its own quota.
+    let mutations = vec![
+        StorageMutation::SetItem("key".to_owned(), "value".to_owned()),
+        StorageMutation::SetItem("key".to_owned(), half.clone()),
+    ];
+    assert_eq!(
+        mutate_with_other_size(
+            &storage_thread,
+            browser,
+            &url,
+            StorageType::Session,
+            mutations
+        ),
+        (
+            vec![("key".to_owned(), Some("value".to_owned()))],
+            "key".len() + half.len()
+        )
+    );
+    let mutations = vec![StorageMutation::SetItem("key".to_owned(), half.clone())];
+    assert!(mutate(
+        &storage_thread,
+        browser,
+        &other_url,
+        StorageType::Session,
+        mutations
+    )
+    .is_empty());
+
+    // The quota is freed up again once the local storage item is removed.
+    let mutations = vec![StorageMutation::RemoveItem("key".to_owned())];
+    assert!(mutate(
+        &storage_thread,
+        browser,
+        &url,
+        StorageType::Local,
+        mutations
+    )
+    .is_empty());
+    let mutations = vec![StorageMutation::SetItem("key".to_owned(), half.clone())];
+    assert!(mutate(
+        &storage_thread,
+        browser,
+        &url,
+        StorageType::Session,
+        mutations
+    )
+    .is_empty());
+    assert_eq!(
+        snapshot(&storage_thread, browser, &url, StorageType::Session)["key"],
+        half
+    );
+    exit(&storage_thread);
+}
+
+#[test]
+fn test_cache_items_are_matched_and_persisted() {
+    let config_dir: PathBuf =
+        std::env::temp_dir().join(format!("servo-cache-storage-test-{}", std::process::id()));
+    fs::create_dir_all(&config_dir).unwrap();
+    let origin = ServoUrl::parse("https://example.com/").unwrap().origin();
+    let html = "https://example.com/page.html";
+    let script = "https://example.com/script.js?v=1";
+
+    let storage_thread: IpcSender<StorageThreadMsg> =
+        StorageThreadFactory::new(Some(config_dir.clone()));
+    let cache = open_cache(&storage_thread, &origin, "v1");
+    assert_eq!(open_cache(&storage_thread, &origin, "v1"), cache);
+    let puts = vec![
+        CacheOperation::Put(
+            cache_request(html, "text/html"),
+            cache_response("html", Some("Accept")),
+        ),
+        CacheOperation::Put(cache_request(script, "*/*"), cache_response("script", None)),
+    ];
+    assert_eq!(
+        batch_cache_operations(&storage_thread, &origin, cache, puts),
+        Ok(false)
+    );
+
+    // A batch which puts the same request twice is not applied at all.
+    let duplicates = vec![
+        CacheOperation::Put(cache_request(html, "*/*"), cache_response("other", None)),
+        CacheOperation::Put(cache_request(html, "*/*"), cache_response("other", None)),
+    ];
+    assert_eq!(
+        batch_cache_operations(&storage_thread, &origin, cache, duplicates),
+        Err(CacheOperationError::InvalidState)
+    );
+
+    let query = |request, options| {
+        query_cache(
+            &storage_thread,
+            &origin,
+            CacheQueryTarget::Cache(cache),
+            Some(request),
+            options,
+        )
+    };
+    let ignore_vary = CacheQueryOptions {
+        ignore_vary: true,
+        ..CacheQueryOptions::default()
+    };
+    let ignore_search = CacheQueryOptions {
+        ignore_search: true,
+        ..CacheQueryOptions::default()
+    };
+    assert_eq!(
+        query(
+            cache_request(html, "text/html"),
+            CacheQueryOptions::default()
+        ),
+        vec!["html"]
+    );
+    assert!(query(
+        cache_request(html, "text/plain"),
+        CacheQueryOptions::default()
+    )
+    .is_empty());
+    assert_eq!(
+        query(cache_request(html, "text/plain"), ignore_vary),
+        vec!["html"]
+    );
+    let other_search = "https://example.com/script.js?v=2";
+    assert!(query(
+        cache_request(other_search, "*/*"),
+        CacheQueryOptions::default()
+    )
+    .is_empty());
+    assert_eq!(
+        query(cache_request(other_search, "*/*"), ignore_search),
+        vec!["script"]
+    );
+    exit(&storage_thread);
+
+    // The caches outlive the storage thread.
+    let storage_thread: IpcSender<StorageThreadMsg> =
+        StorageThreadFactory::new(Some(config_dir.clone()));
+    let cache = open_cache(&storage_thread, &origin, "v1");
+    assert_eq!(
+        query_cache(
+            &storage_thread,
+            &origin,
+            CacheQueryTarget::All,
+            Some(cache_request(script, "*/*")),
+            CacheQueryOptions::default(),
+        ),
+        vec!["script"]
+    );
+    let delete = vec![CacheOperation::Delete(
+        cache_request(script, "*/*"),
+        CacheQueryOptions::default(),
+    )];
+    assert_eq!(
+        batch_cache_operations(&storage_thread, &origin, cache, delete),
+        Ok(true)
+    );
+    assert_eq!(
+        query_cache(
+            &storage_thread,
+            &origin,
+            CacheQueryTarget::Named("v1".to_owned()),
+            None,
+            CacheQueryOptions::default(),
+        ),
+        vec!["html"]
+    );
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::DeleteCache(
+            sender,
+            origin.clone(),
+            "v1".to_owned(),
+        ))
+        .unwrap();
+    assert!(receiver.recv().unwrap());
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::CacheNames(sender, origin.clone()))
+        .unwrap();
+    assert!(receiver.recv().unwrap().is_empty());
+    exit(&storage_thread);
+
+    let _ = fs::remove_dir_all(&config_dir);
+}
+
+#[test]
+fn test_cache_queries_only_match_get_requests_unless_ignoring_the_method() {
+    let origin = ServoUrl::parse("https://example.com/").unwrap().origin();
+    let url = "https://example.com/data.json";
+    let storage_thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(None);
+    let cache = open_cache(&storage_thread, &origin, "v1");
+    let put = vec![CacheOperation::Put(
+        cache_request(url, "*/*"),
+        cache_response("data", None),
+    )];
+    assert_eq!(
+        batch_cache_operations(&storage_thread, &origin, cache, put),
+        Ok(false)
+    );
+
+    let mut post = cache_request(url, "*/*");
+    post.method = "POST".to_owned();
+    let query = |request: &CacheRequest, options| {
+        query_cache(
+            &storage_thread,
+            &origin,
+            CacheQueryTarget::Cache(cache),
+            Some(request.clone()),
+            options,
+        )
+    };
+    assert!(query(&post, CacheQueryOptions::default()).is_empty());
+    let ignore_method = CacheQueryOptions {
+        ignore_method: true,
+        ..CacheQueryOptions::default()
+    };
+    assert_eq!(query(&post, ignore_method), vec!["data"]);
+    exit(&storage_thread);
+}
+
+#[test]
+fn test_deleted_caches_lose_their_items() {
+    let origin = ServoUrl::parse("https://example.com/").unwrap().origin();
+    let url = "https://example.com/page.html";
+    let storage_thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(None);
+    let cache = open_cache(&storage_thread, &origin, "v1");
+    let put = vec![CacheOperation::Put(
+        cache_request(url, "*/*"),
+        cache_response("page", None),
+    )];
+    assert_eq!(
+        batch_cache_operations(&storage_thread, &origin, cache, put),
+        Ok(false)
+    );
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::DeleteCache(
+            sender,
+            origin.clone(),
+            "v1".to_owned(),
+        ))
+        .unwrap();
+    assert!(receiver.recv().unwrap());
+
+    // The documents which opened the cache find it empty, and a new cache with its name
+    // doesn't get its items back.
+    assert!(query_cache(
+        &storage_thread,
+        &origin,
+        CacheQueryTarget::Cache(cache),
+        None,
+        CacheQueryOptions::default(),
+    )
+    .is_empty());
+    let new_cache = open_cache(&storage_thread, &origin, "v1");
+    assert_ne!(new_cache, cache);
+    assert!(query_cache(
+        &storage_thread,
+        &origin,
+        CacheQueryTarget::Named("v1".to_owned()),
+        None,
+        CacheQueryOptions::default(),
+    )
+    .is_empty());
+    exit(&storage_thread);
+}
+
+#[test]
+fn test_caches_of_an_origin_share_its_quota() {
+    let origin = ServoUrl::parse("https://example.com/").unwrap().origin();
+    let other_origin = ServoUrl::parse("https://example.org/").unwrap().origin();
+    let storage_thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(None);
+    let half = "x".repeat(CACHE_QUOTA_SIZE_LIMIT / 2);
+    let put = |origin: &ImmutableOrigin, cache, url: &str| {
+        let operations = vec![CacheOperation::Put(
+            cache_request(url, "*/*"),
+            cache_response(&half, None),
+        )];
+        batch_cache_operations(&storage_thread, origin, cache, operations)
+    };
+
+    let v1 = open_cache(&storage_thread, &origin, "v1");
+    let v2 = open_cache(&storage_thread, &origin, "v2");
+    assert_eq!(put(&origin, v1, "https://example.com/a"), Ok(false));
+
+    // The item doesn't fit in another cache of the origin once the URLs and headers are
+    // counted, and the batch is not applied, while another origin has its own quota.
+    assert_eq!(
+        put(&origin, v2, "https://example.com/b"),
+        Err(CacheOperationError::QuotaExceeded)
+    );
+    assert!(query_cache(
+        &storage_thread,
+        &origin,
+        CacheQueryTarget::Cache(v2),
+        None,
+        CacheQueryOptions::default(),
+    )
+    .is_empty());
+    let other_cache = open_cache(&storage_thread, &other_origin, "v1");
+    assert_eq!(
+        put(&other_origin, other_cache, "https://example.org/b"),
+        Ok(false)
+    );
+
+    // Replacing an item only counts the new one.
+    assert_eq!(put(&origin, v1, "https://example.com/a"), Ok(true));
+
+    // The quota is freed up again once the first cache is deleted.
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage_thread
+        .send(StorageThreadMsg::DeleteCache(
+            sender,
+            origin.clone(),
+            "v1".to_owned(),
+        ))
+        .unwrap();
+    assert!(receiver.recv().unwrap());
+    assert_eq!(put(&origin, v2, "https://example.com/b"), Ok(false));
+    exit(&storage_thread);
+}
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::connector::{create_tls_config, ALPN_H1};
+use crate::connector::{connect_through_proxy, create_tls_config, ALPN_H1};
 use crate::cookie::Cookie;
 use crate::fetch::methods::should_be_blocked_due_to_bad_port;
 use crate::hosts::replace_host;
@@ -17,8 +17,11 @@ use net_traits::request::{RequestBuilder, RequestMode};
 use net_traits::{CookieSource, MessageData};
 use net_traits::{WebSocketDomAction, WebSocketNetworkEvent};
 use openssl::ssl::SslStream;
+use servo_config::proxy::proxy_for_host;
 use servo_url::ServoUrl;
 use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream as StdTcpStream};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -58,6 +61,9 @@ impl<'a> Factory for Client<'a> {
 impl<'a> Handler for Client<'a> {
     fn build_request(&mut self, url: &Url) -> WebSocketResult<Request> {
         let mut req = Request::from_url(url)?;
+        // The host of the URL may have been replaced by the address to connect to.
+        req.headers_mut()
+            .retain(|&(ref name, _)| !name.eq_ignore_ascii_case("Host"));
         req.headers_mut()
             .push(("Origin".to_string(), self.origin.as_bytes().to_owned()));
         req.headers_mut().push((
@@ -182,6 +188,40 @@ impl<'a> Handler for Client<'a> {
     }
 }
 
+/// Relay the connection which the WebSocket library opens to a loopback address to a
+/// connection through a proxy, and return that address, since the library can only connect
+/// to an address by itself. The TLS connections stay end-to-end, through the relay.
+fn relay_to_proxy_connection(proxied: StdTcpStream) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let address = listener.local_addr()?;
+    thread::Builder::new()
+        .name("WebSocket proxy relay".to_owned())
+        .spawn(move || {
+            let connection = match listener.accept() {
+                Ok((connection, _)) => connection,
+                Err(e) => return warn!("Failed to relay a WebSocket to its proxy: {}", e),
+            };
+            // Only the connection of the WebSocket is relayed.
+            drop(listener);
+            let (mut connection_reader, mut proxied_writer) =
+                match (connection.try_clone(), proxied.try_clone()) {
+                    (Ok(connection_reader), Ok(proxied_writer)) => {
+                        (connection_reader, proxied_writer)
+                    },
+                    _ => return warn!("Failed to relay a WebSocket to its proxy."),
+                };
+            let (mut proxied_reader, mut connection_writer) = (proxied, connection);
+            let upstream = thread::spawn(move || {
+                let _ = io::copy(&mut connection_reader, &mut proxied_writer);
+                let _ = proxied_writer.shutdown(Shutdown::Write);
+            });
+            let _ = io::copy(&mut proxied_reader, &mut connection_writer);
+            let _ = connection_writer.shutdown(Shutdown::Write);
+            let _ = upstream.join();
+        })?;
+    Ok(address)
+}
+
 pub fn init(
     req_builder: RequestBuilder,
     resource_event_sender: IpcSender<WebSocketNetworkEvent>,
@@ -228,6 +268,24 @@ pub fn init(
             let mut net_url = req_builder.url.clone().into_url();
             net_url.set_host(Some(&host)).unwrap();
 
+            // The connections to the hosts which are proxied are tunneled through the proxy,
+            // whatever the scheme, as WebSockets can't be sent to a proxy in absolute-form.
+            if let Some(proxy) = proxy_for_host(req_builder.url.host_str().unwrap()) {
+                let port = req_url.port_or_known_default().unwrap();
+                let relayed = connect_through_proxy(proxy, req_url.host_str().unwrap(), port)
+                    .and_then(relay_to_proxy_connection);
+                let address = match relayed {
+                    Ok(address) => address,
+                    Err(e) => {
+                        debug!("Failed to connect a WebSocket through its proxy: {}", e);
+                        let _ = resource_event_sender.send(WebSocketNetworkEvent::Fail);
+                        return;
+                    },
+                };
+                net_url.set_ip_host(address.ip()).unwrap();
+                net_url.set_port(Some(address.port())).unwrap();
+            }
+
             let host = Host::from(
                 format!(
                     "{}{}",
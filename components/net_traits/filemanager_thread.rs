@@ -135,10 +135,11 @@ pub enum FileManagerThreadMsg {
         Option<Vec<String>>,
     ),
 
-    /// Read FileID-indexed file in chunks, optionally check URL validity based on boolean flag
+    /// Read the given range of a FileID-indexed file in chunks
     ReadFile(
         IpcSender<FileManagerResult<ReadFileProgress>>,
         Uuid,
+        RelativePos,
         FileOrigin,
     ),
 
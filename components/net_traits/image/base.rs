@@ -2,11 +2,18 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::image::icc::ColorProfile;
 use crate::image_cache::CorsStatus;
+use flate2::read::ZlibDecoder;
+use flate2::{Crc, Decompress, FlushDecompress, Status};
 use ipc_channel::ipc::IpcSharedMemory;
-use piston_image::{DynamicImage, ImageFormat};
+use piston_image::gif::GifDecoder;
+use piston_image::{AnimationDecoder, DynamicImage, ImageFormat};
 use pixels::PixelFormat;
 use std::fmt;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::time::Duration;
 
 #[derive(Clone, Deserialize, MallocSizeOf, Serialize)]
 pub struct Image {
@@ -18,14 +25,174 @@ pub struct Image {
     #[ignore_malloc_size_of = "Defined in webrender_api"]
     pub id: Option<webrender_api::ImageKey>,
     pub cors_status: CorsStatus,
+    /// The frames of the image if it is animated, in which case `bytes` are the pixels
+    /// of its first frame.
+    pub animation: Option<ImageAnimation>,
+    /// The orientation given by the EXIF metadata of the image, which was applied to
+    /// its pixels when it was decoded.
+    pub orientation: ImageOrientation,
+}
+
+/// The orientation of an image, as given by the Orientation tag of its EXIF metadata,
+/// which is the transformation that displays the image the right way up.
+/// <https://www.cipa.jp/std/documents/e/DC-008-2012_E.pdf>, section 4.6.4 A.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
+pub enum ImageOrientation {
+    /// The image is displayed as it is stored (1).
+    Normal,
+    /// The image is mirrored horizontally (2).
+    FlipHorizontal,
+    /// The image is rotated by 180 degrees (3).
+    Rotate180,
+    /// The image is mirrored vertically (4).
+    FlipVertical,
+    /// The image is mirrored along its top-left to bottom-right diagonal (5).
+    Transpose,
+    /// The image is rotated by 90 degrees clockwise (6).
+    Rotate90,
+    /// The image is mirrored along its top-right to bottom-left diagonal (7).
+    Transverse,
+    /// The image is rotated by 90 degrees counter-clockwise (8).
+    Rotate270,
+}
+
+impl ImageOrientation {
+    fn from_exif(value: u16) -> ImageOrientation {
+        match value {
+            2 => ImageOrientation::FlipHorizontal,
+            3 => ImageOrientation::Rotate180,
+            4 => ImageOrientation::FlipVertical,
+            5 => ImageOrientation::Transpose,
+            6 => ImageOrientation::Rotate90,
+            7 => ImageOrientation::Transverse,
+            8 => ImageOrientation::Rotate270,
+            _ => ImageOrientation::Normal,
+        }
+    }
+
+    /// The orientation which undoes this one.
+    pub fn inverse(self) -> ImageOrientation {
+        match self {
+            ImageOrientation::Rotate90 => ImageOrientation::Rotate270,
+            ImageOrientation::Rotate270 => ImageOrientation::Rotate90,
+            orientation => orientation,
+        }
+    }
+
+    /// Whether the width and the height of an image are swapped by this orientation.
+    pub fn swaps_dimensions(self) -> bool {
+        match self {
+            ImageOrientation::Transpose |
+            ImageOrientation::Rotate90 |
+            ImageOrientation::Transverse |
+            ImageOrientation::Rotate270 => true,
+            _ => false,
+        }
+    }
+
+    /// Apply this orientation to the pixels of an image of the given size, whose pixels
+    /// all have the given number of bytes. Returns the pixels and the size of the
+    /// oriented image.
+    pub fn apply(
+        self,
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        pixel_size: usize,
+    ) -> (Vec<u8>, u32, u32) {
+        if self == ImageOrientation::Normal {
+            return (bytes.to_vec(), width, height);
+        }
+        let (oriented_width, oriented_height) = if self.swaps_dimensions() {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let (w, h) = (width as usize, height as usize);
+        let mut oriented = vec![0; bytes.len()];
+        for (index, pixel) in bytes.chunks(pixel_size).enumerate() {
+            let (x, y) = (index % w, index / w);
+            let (oriented_x, oriented_y) = match self {
+                ImageOrientation::Normal => (x, y),
+                ImageOrientation::FlipHorizontal => (w - 1 - x, y),
+                ImageOrientation::Rotate180 => (w - 1 - x, h - 1 - y),
+                ImageOrientation::FlipVertical => (x, h - 1 - y),
+                ImageOrientation::Transpose => (y, x),
+                ImageOrientation::Rotate90 => (h - 1 - y, x),
+                ImageOrientation::Transverse => (h - 1 - y, w - 1 - x),
+                ImageOrientation::Rotate270 => (y, w - 1 - x),
+            };
+            let start = (oriented_y * oriented_width as usize + oriented_x) * pixel_size;
+            oriented[start..start + pixel_size].copy_from_slice(pixel);
+        }
+        (oriented, oriented_width, oriented_height)
+    }
+}
+
+impl Image {
+    /// The image as it is stored, without the orientation given by its EXIF metadata,
+    /// for elements whose `image-orientation` is `none`.
+    pub fn unoriented(&self) -> Image {
+        let pixel_size = match self.format {
+            PixelFormat::K8 => 1,
+            PixelFormat::KA8 => 2,
+            PixelFormat::RGB8 => 3,
+            PixelFormat::RGBA8 | PixelFormat::BGRA8 => 4,
+        };
+        let (bytes, width, height) =
+            self.orientation
+                .inverse()
+                .apply(&self.bytes, self.width, self.height, pixel_size);
+        Image {
+            width,
+            height,
+            format: self.format,
+            bytes: IpcSharedMemory::from_bytes(&bytes),
+            id: None,
+            cors_status: self.cors_status,
+            animation: None,
+            orientation: ImageOrientation::Normal,
+        }
+    }
+}
+
+/// The decoded frames of an animated image, which all have the size and the pixel
+/// format of the image.
+#[derive(Clone, Deserialize, MallocSizeOf, Serialize)]
+pub struct ImageAnimation {
+    pub frames: Vec<ImageFrame>,
+    /// The pixels of all frames.
+    #[ignore_malloc_size_of = "Defined in ipc-channel"]
+    pub bytes: IpcSharedMemory,
+}
+
+impl ImageAnimation {
+    /// The pixels of the frame at the given index.
+    pub fn frame_bytes(&self, index: usize) -> &[u8] {
+        &self.bytes[self.frames[index].byte_range.clone()]
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct ImageFrame {
+    /// How long the frame is displayed before the next one.
+    pub delay: Duration,
+    /// Where the pixels of the frame are in the bytes of the animation.
+    pub byte_range: Range<usize>,
 }
 
 impl fmt::Debug for Image {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Image {{ width: {}, height: {}, format: {:?}, ..., id: {:?} }}",
-            self.width, self.height, self.format, self.id
+            "Image {{ width: {}, height: {}, format: {:?}, ..., id: {:?}, frames: {} }}",
+            self.width,
+            self.height,
+            self.format,
+            self.id,
+            self.animation
+                .as_ref()
+                .map_or(1, |animation| animation.frames.len())
         )
     }
 }
@@ -50,31 +217,803 @@ pub fn load_from_memory(buffer: &[u8], cors_status: CorsStatus) -> Option<Image>
             debug!("{}", msg);
             None
         },
-        Ok(_) => match piston_image::load_from_memory(buffer) {
-            Ok(image) => {
-                let mut rgba = match image {
-                    DynamicImage::ImageRgba8(rgba) => rgba,
-                    image => image.to_rgba(),
-                };
-                pixels::rgba8_byte_swap_colors_inplace(&mut *rgba);
-                Some(Image {
-                    width: rgba.width(),
-                    height: rgba.height(),
-                    format: PixelFormat::BGRA8,
-                    bytes: IpcSharedMemory::from_bytes(&*rgba),
-                    id: None,
-                    cors_status,
-                })
+        Ok(ImageFormat::Gif) => load_gif_from_memory(buffer, cors_status),
+        Ok(ImageFormat::Png) if png_chunk(buffer, b"acTL").is_some() => {
+            load_apng_from_memory(buffer, cors_status)
+                .or_else(|| load_still_image_from_memory(buffer, cors_status))
+        },
+        Ok(_) => load_still_image_from_memory(buffer, cors_status),
+    }
+}
+
+/// Decode the only frame of an image, or the first frame of an animated image whose
+/// animation is not decoded.
+fn load_still_image_from_memory(buffer: &[u8], cors_status: CorsStatus) -> Option<Image> {
+    match piston_image::load_from_memory(buffer) {
+        Ok(image) => {
+            let mut rgba = match image {
+                DynamicImage::ImageRgba8(rgba) => rgba,
+                image => image.to_rgba(),
+            };
+            if let Some(profile) = color_profile(buffer) {
+                profile.convert_to_srgb(&mut *rgba);
+            }
+            pixels::rgba8_byte_swap_colors_inplace(&mut *rgba);
+            let orientation = exif_orientation(buffer);
+            let (bytes, width, height) = match orientation {
+                ImageOrientation::Normal => (
+                    IpcSharedMemory::from_bytes(&*rgba),
+                    rgba.width(),
+                    rgba.height(),
+                ),
+                _ => {
+                    let (bytes, width, height) =
+                        orientation.apply(&*rgba, rgba.width(), rgba.height(), 4);
+                    (IpcSharedMemory::from_bytes(&bytes), width, height)
+                },
+            };
+            Some(Image {
+                width,
+                height,
+                format: PixelFormat::BGRA8,
+                bytes,
+                id: None,
+                cors_status,
+                animation: None,
+                orientation,
+            })
+        },
+        Err(e) => {
+            debug!("Image decoding error: {:?}", e);
+            None
+        },
+    }
+}
+
+/// The maximum size of the decoded frames of an animated image. The images whose frames
+/// would need more memory are only decoded up to their first frame, and not animated.
+pub const MAX_ANIMATION_BYTES: usize = 128 * 1024 * 1024;
+
+/// Decode all frames of a GIF image, which is only animated if it has more than one.
+fn load_gif_from_memory(buffer: &[u8], cors_status: CorsStatus) -> Option<Image> {
+    let decoder = match GifDecoder::new(Cursor::new(buffer)) {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            debug!("Image decoding error: {:?}", e);
+            return None;
+        },
+    };
+    let mut frames = decoder.into_frames();
+    let mut dimensions = None;
+    let mut bytes = vec![];
+    let mut image_frames = vec![];
+    loop {
+        // All frames have the size of the first one, so the frames are only decoded
+        // while they fit.
+        if let Some((width, height)) = dimensions {
+            let frame_size = width as usize * height as usize * 4;
+            if bytes.len() + frame_size > MAX_ANIMATION_BYTES {
+                debug!("Animated image too large, only its first frame is decoded");
+                image_frames.truncate(1);
+                bytes.truncate(image_frames[0].byte_range.end);
+                break;
+            }
+        }
+        let frame = match frames.next() {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => {
+                debug!("Image decoding error: {:?}", e);
+                return None;
+            },
+            None => break,
+        };
+        dimensions.get_or_insert(frame.buffer().dimensions());
+        let (numerator, denominator) = frame.delay().numer_denom_ms();
+        let start = bytes.len();
+        bytes.extend_from_slice(&*frame.into_buffer());
+        image_frames.push(ImageFrame {
+            delay: Duration::from_micros(numerator as u64 * 1000 / denominator.max(1) as u64),
+            byte_range: start..bytes.len(),
+        });
+    }
+    let (width, height) = dimensions?;
+    pixels::rgba8_byte_swap_colors_inplace(&mut bytes);
+
+    let first_frame = image_frames[0].byte_range.clone();
+    let animation = if image_frames.len() > 1 {
+        Some(ImageAnimation {
+            frames: image_frames,
+            bytes: IpcSharedMemory::from_bytes(&bytes),
+        })
+    } else {
+        None
+    };
+    Some(Image {
+        width,
+        height,
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&bytes[first_frame]),
+        id: None,
+        cors_status,
+        animation,
+        orientation: ImageOrientation::Normal,
+    })
+}
+
+/// Decode all frames of an APNG image. Each frame is a PNG image made of the chunks of
+/// the image with the size and the data of the frame, which is composited over the
+/// previous frames as its fcTL chunk says.
+/// https://wiki.mozilla.org/APNG_Specification
+fn load_apng_from_memory(buffer: &[u8], cors_status: CorsStatus) -> Option<Image> {
+    struct ApngFrame<'a> {
+        control: &'a [u8],
+        data: Vec<&'a [u8]>,
+    }
+
+    let mut header = None;
+    let mut shared_chunks = vec![];
+    let mut frames: Vec<ApngFrame> = vec![];
+    let mut image_data_found = false;
+    for (chunk_type, data) in png_chunks(buffer) {
+        match chunk_type {
+            b"IHDR" => header = Some(data),
+            b"acTL" | b"IEND" => {},
+            b"fcTL" => frames.push(ApngFrame {
+                control: data,
+                data: vec![],
+            }),
+            // The default image is only the first frame if an fcTL chunk precedes it.
+            b"IDAT" => {
+                image_data_found = true;
+                if let Some(frame) = frames.last_mut() {
+                    frame.data.push(data);
+                }
+            },
+            // The frame data start with a sequence number.
+            b"fdAT" => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.data.push(data.get(4..)?);
+                }
             },
+            // The frames share the palette, the transparency and the other chunks
+            // which precede the image data.
+            _ if !image_data_found => shared_chunks.push((chunk_type, data)),
+            _ => {},
+        }
+    }
+    let header = header.filter(|header| header.len() == 13)?;
+    if frames.len() < 2 {
+        return None;
+    }
+
+    let read_u32 = |bytes: &[u8], offset: usize| {
+        u32::from_be_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ])
+    };
+    let (width, height) = (read_u32(header, 0), read_u32(header, 4));
+    let canvas_size = width as usize * height as usize * 4;
+    let mut canvas = vec![0; canvas_size];
+    let mut bytes = vec![];
+    let mut image_frames = vec![];
+    for frame in frames {
+        if bytes.len() + canvas_size > MAX_ANIMATION_BYTES {
+            if image_frames.is_empty() {
+                return None;
+            }
+            debug!("Animated image too large, only its first frame is decoded");
+            image_frames.truncate(1);
+            bytes.truncate(canvas_size);
+            break;
+        }
+        let control = frame.control;
+        if control.len() != 26 {
+            return None;
+        }
+        let (frame_width, frame_height) = (read_u32(control, 4), read_u32(control, 8));
+        let (x, y) = (read_u32(control, 12), read_u32(control, 16));
+        if frame_width == 0 ||
+            frame_height == 0 ||
+            x.checked_add(frame_width)? > width ||
+            y.checked_add(frame_height)? > height
+        {
+            return None;
+        }
+        let delay_numerator = u16::from_be_bytes([control[20], control[21]]) as u64;
+        let delay_denominator = match u16::from_be_bytes([control[22], control[23]]) {
+            0 => 100,
+            denominator => denominator as u64,
+        };
+        let (dispose_op, blend_op) = (control[24], control[25]);
+
+        let mut frame_header = header.to_vec();
+        frame_header[..4].copy_from_slice(&frame_width.to_be_bytes());
+        frame_header[4..8].copy_from_slice(&frame_height.to_be_bytes());
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_png_chunk(&mut png, b"IHDR", &frame_header);
+        for &(chunk_type, data) in &shared_chunks {
+            write_png_chunk(&mut png, chunk_type, data);
+        }
+        write_png_chunk(&mut png, b"IDAT", &frame.data.concat());
+        write_png_chunk(&mut png, b"IEND", &[]);
+        let pixels = match piston_image::load_from_memory_with_format(&png, ImageFormat::Png) {
+            Ok(image) => image.to_rgba(),
             Err(e) => {
                 debug!("Image decoding error: {:?}", e);
+                return None;
+            },
+        };
+
+        let region_rows = (y..y + frame_height).map(|row| {
+            let start = (row as usize * width as usize + x as usize) * 4;
+            start..start + frame_width as usize * 4
+        });
+        // APNG_DISPOSE_OP_PREVIOUS restores the canvas as it was before the frame.
+        let previous = if dispose_op == 2 {
+            Some(canvas.clone())
+        } else {
+            None
+        };
+        for (source_row, region_row) in pixels
+            .chunks(frame_width as usize * 4)
+            .zip(region_rows.clone())
+        {
+            let destination_row = &mut canvas[region_row];
+            // APNG_BLEND_OP_SOURCE replaces the pixels, and APNG_BLEND_OP_OVER
+            // composites the frame over them.
+            if blend_op == 0 {
+                destination_row.copy_from_slice(source_row);
+                continue;
+            }
+            for (source, destination) in source_row.chunks(4).zip(destination_row.chunks_mut(4)) {
+                blend_over(source, destination);
+            }
+        }
+
+        let start = bytes.len();
+        bytes.extend_from_slice(&canvas);
+        image_frames.push(ImageFrame {
+            delay: Duration::from_micros(delay_numerator * 1_000_000 / delay_denominator),
+            byte_range: start..bytes.len(),
+        });
+
+        match (dispose_op, previous) {
+            // APNG_DISPOSE_OP_BACKGROUND clears the region of the frame.
+            (1, _) => {
+                for region_row in region_rows {
+                    canvas[region_row].iter_mut().for_each(|byte| *byte = 0);
+                }
+            },
+            (_, Some(previous)) => canvas = previous,
+            _ => {},
+        }
+    }
+    if let Some(profile) = color_profile(buffer) {
+        profile.convert_to_srgb(&mut bytes);
+    }
+    pixels::rgba8_byte_swap_colors_inplace(&mut bytes);
+
+    let first_frame = image_frames[0].byte_range.clone();
+    let animation = if image_frames.len() > 1 {
+        Some(ImageAnimation {
+            frames: image_frames,
+            bytes: IpcSharedMemory::from_bytes(&bytes),
+        })
+    } else {
+        None
+    };
+    Some(Image {
+        width,
+        height,
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&bytes[first_frame]),
+        id: None,
+        cors_status,
+        animation,
+        orientation: ImageOrientation::Normal,
+    })
+}
+
+/// Composite a non-premultiplied RGBA pixel over another.
+fn blend_over(source: &[u8], destination: &mut [u8]) {
+    let source_alpha = source[3] as u32;
+    if source_alpha == 255 {
+        destination.copy_from_slice(source);
+        return;
+    }
+    if source_alpha == 0 {
+        return;
+    }
+    let destination_alpha = destination[3] as u32 * (255 - source_alpha) / 255;
+    let alpha = source_alpha + destination_alpha;
+    for channel in 0..3 {
+        destination[channel] = ((source[channel] as u32 * source_alpha +
+            destination[channel] as u32 * destination_alpha) /
+            alpha) as u8;
+    }
+    destination[3] = alpha as u8;
+}
+
+/// A decoder of an image whose bytes are still being received, which shows the parts of
+/// the image that the bytes received so far describe: the rows of PNG images, or the
+/// passes of interlaced ones, and the scans of progressive JPEG images.
+// FIXME: Baseline JPEG images are only decoded once all their bytes are received.
+pub struct PartialImageDecoder {
+    /// The bytes of the image received so far.
+    buffer: Vec<u8>,
+    state: PartialDecoderState,
+}
+
+enum PartialDecoderState {
+    /// Not enough bytes were received to know whether the image can be partially decoded.
+    Pending,
+    Png(PartialPng),
+    Jpeg(PartialJpeg),
+    Unsupported,
+}
+
+/// The state of the decoding of the rows of a PNG image, whose image data are inflated
+/// and unfiltered as they are received.
+struct PartialPng {
+    width: u32,
+    height: u32,
+    /// The number of bytes of a pixel.
+    channels: usize,
+    /// The position in the buffer of the bytes to decode next.
+    position: usize,
+    /// The number of bytes of the current IDAT chunk which were not decoded yet.
+    remaining_chunk_data: usize,
+    inflater: Decompress,
+    /// The passes of the image, which are the seven Adam7 passes if it is interlaced.
+    passes: &'static [PngPass],
+    /// The index of the pass whose rows are decoded next.
+    pass: usize,
+    /// The number of rows of the current pass which were decoded.
+    pass_rows: u32,
+    /// The inflated bytes which do not make a whole row yet, starting with its filter type.
+    filtered_row: Vec<u8>,
+    /// The unfiltered bytes of the last decoded row of the current pass, which the next
+    /// row is filtered against.
+    previous_row: Vec<u8>,
+    /// The BGRA pixels of the image, which are transparent where rows are missing.
+    pixels: Vec<u8>,
+    /// The rows of the image which changed since it was last decoded.
+    changed_rows: Option<Range<u32>>,
+}
+
+/// The pixels of a PNG image which a pass describes, starting from a column and a row
+/// with the given spacing. Until the next passes are decoded, each pixel fills a block
+/// of the given size, so that the image gets sharper with each pass.
+/// <https://www.w3.org/TR/PNG/#8Interlace>
+struct PngPass {
+    x: u32,
+    y: u32,
+    column_spacing: u32,
+    row_spacing: u32,
+    block_width: u32,
+    block_height: u32,
+}
+
+const fn png_pass(x: u32, y: u32, column_spacing: u32, row_spacing: u32) -> PngPass {
+    PngPass {
+        x,
+        y,
+        column_spacing,
+        row_spacing,
+        block_width: column_spacing - x,
+        block_height: row_spacing - y,
+    }
+}
+
+const NON_INTERLACED_PNG_PASSES: [PngPass; 1] = [png_pass(0, 0, 1, 1)];
+
+const ADAM7_PASSES: [PngPass; 7] = [
+    png_pass(0, 0, 8, 8),
+    png_pass(4, 0, 8, 8),
+    png_pass(0, 4, 4, 8),
+    png_pass(2, 0, 4, 4),
+    png_pass(0, 2, 2, 4),
+    png_pass(1, 0, 2, 2),
+    png_pass(0, 1, 1, 2),
+];
+
+impl PngPass {
+    /// The number of columns and rows of the pass in an image of the given size.
+    fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        let count = |size: u32, start: u32, spacing: u32| {
+            size.saturating_sub(start).saturating_add(spacing - 1) / spacing
+        };
+        (
+            count(width, self.x, self.column_spacing),
+            count(height, self.y, self.row_spacing),
+        )
+    }
+}
+
+/// The state of the decoding of the scans of a progressive JPEG image.
+struct PartialJpeg {
+    /// The position in the buffer of the first scan.
+    first_scan: usize,
+    /// The end of the scans which were decoded.
+    decoded_scans_end: usize,
+}
+
+impl Default for PartialImageDecoder {
+    fn default() -> PartialImageDecoder {
+        PartialImageDecoder {
+            buffer: vec![],
+            state: PartialDecoderState::Pending,
+        }
+    }
+}
+
+impl PartialImageDecoder {
+    /// Add bytes of the image which were received.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if let PartialDecoderState::Unsupported = self.state {
+            return;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Decode the bytes received since the image was last decoded. Returns the image with
+    /// the parts decoded so far, and the range of its rows which changed, if any did.
+    pub fn decode(&mut self, cors_status: CorsStatus) -> Option<(Image, Range<u32>)> {
+        if let PartialDecoderState::Pending = self.state {
+            self.state = match start_partial_decoding(&self.buffer) {
+                Some(state) => state,
+                None => return None,
+            };
+        }
+        let result = match self.state {
+            PartialDecoderState::Png(ref mut png) => png.decode(&self.buffer, cors_status),
+            PartialDecoderState::Jpeg(ref mut jpeg) => jpeg.decode(&self.buffer, cors_status),
+            PartialDecoderState::Pending | PartialDecoderState::Unsupported => return None,
+        };
+        match result {
+            Ok(result) => result,
+            Err(()) => {
+                self.state = PartialDecoderState::Unsupported;
+                self.buffer = vec![];
                 None
             },
-        },
+        }
+    }
+}
+
+/// The state in which the partial decoding of an image starts, once the metadata that
+/// precede the pixels of the image were received.
+fn start_partial_decoding(buffer: &[u8]) -> Option<PartialDecoderState> {
+    if is_png(buffer) {
+        start_partial_png_decoding(buffer)
+    } else if is_jpeg(buffer) {
+        start_partial_jpeg_decoding(buffer)
+    } else if buffer.len() < 8 {
+        None
+    } else {
+        Some(PartialDecoderState::Unsupported)
+    }
+}
+
+fn start_partial_png_decoding(buffer: &[u8]) -> Option<PartialDecoderState> {
+    // The metadata chunks precede the first IDAT chunk.
+    let mut position = 8;
+    let first_chunk_data_length = loop {
+        let header = buffer.get(position..position + 8)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if &header[4..] == b"IDAT" {
+            break length;
+        }
+        position += 12 + length;
+    };
+
+    // The header chunk follows the signature. The rows of oriented images are not the
+    // rows that are displayed, and the colors of the images with an ICC profile are only
+    // converted once decoded.
+    let header = match buffer.get(16..29) {
+        Some(header) if &buffer[12..16] == b"IHDR" => header,
+        _ => return Some(PartialDecoderState::Unsupported),
+    };
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let (bit_depth, color_type, interlace_method) = (header[8], header[9], header[12]);
+    let channels = match color_type {
+        0 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Some(PartialDecoderState::Unsupported),
+    };
+    let passes: &'static [PngPass] = match interlace_method {
+        0 => &NON_INTERLACED_PNG_PASSES,
+        1 => &ADAM7_PASSES,
+        _ => return Some(PartialDecoderState::Unsupported),
+    };
+    if bit_depth != 8 ||
+        exif_orientation(buffer) != ImageOrientation::Normal ||
+        png_chunk(buffer, b"iCCP").is_some()
+    {
+        return Some(PartialDecoderState::Unsupported);
+    }
+    let mut png = PartialPng {
+        width,
+        height,
+        channels,
+        position: position + 8,
+        remaining_chunk_data: first_chunk_data_length,
+        inflater: Decompress::new(true),
+        passes,
+        pass: 0,
+        pass_rows: 0,
+        filtered_row: vec![],
+        previous_row: vec![0; passes[0].size(width, height).0 as usize * channels],
+        pixels: vec![0; width as usize * height as usize * 4],
+        changed_rows: None,
+    };
+    png.skip_finished_passes();
+    Some(PartialDecoderState::Png(png))
+}
+
+fn start_partial_jpeg_decoding(buffer: &[u8]) -> Option<PartialDecoderState> {
+    // Progressive images are those whose frame starts with an SOF2 marker.
+    let first_scan = jpeg_first_scan(buffer)?;
+    if jpeg_segments(buffer, 0xc2).is_empty() {
+        return Some(PartialDecoderState::Unsupported);
+    }
+    Some(PartialDecoderState::Jpeg(PartialJpeg {
+        first_scan,
+        decoded_scans_end: first_scan,
+    }))
+}
+
+impl PartialPng {
+    fn decode(
+        &mut self,
+        buffer: &[u8],
+        cors_status: CorsStatus,
+    ) -> Result<Option<(Image, Range<u32>)>, ()> {
+        let mut inflated = [0; 16 * 1024];
+        while self.pass < self.passes.len() {
+            if self.remaining_chunk_data == 0 {
+                // The checksum of a chunk follows its data, and the image data can be
+                // split in several consecutive IDAT chunks.
+                let header = match buffer.get(self.position + 4..self.position + 12) {
+                    Some(header) => header,
+                    None => break,
+                };
+                if &header[4..] != b"IDAT" {
+                    return Err(());
+                }
+                self.remaining_chunk_data =
+                    u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+                self.position += 12;
+                continue;
+            }
+
+            let data_end = buffer.len().min(self.position + self.remaining_chunk_data);
+            let data = &buffer[self.position..data_end];
+            let (total_in, total_out) = (self.inflater.total_in(), self.inflater.total_out());
+            let status = self
+                .inflater
+                .decompress(data, &mut inflated, FlushDecompress::None)
+                .map_err(|_| ())?;
+            let read = (self.inflater.total_in() - total_in) as usize;
+            let written = (self.inflater.total_out() - total_out) as usize;
+            self.position += read;
+            self.remaining_chunk_data -= read;
+            self.add_inflated_bytes(&inflated[..written])?;
+            if status == Status::StreamEnd || (read == 0 && written == 0) {
+                break;
+            }
+        }
+        let rows = match self.changed_rows.take() {
+            Some(rows) => rows,
+            None => return Ok(None),
+        };
+        let image = Image {
+            width: self.width,
+            height: self.height,
+            format: PixelFormat::BGRA8,
+            bytes: IpcSharedMemory::from_bytes(&self.pixels),
+            id: None,
+            cors_status,
+            animation: None,
+            orientation: ImageOrientation::Normal,
+        };
+        Ok(Some((image, rows)))
+    }
+
+    /// Move on to the next pass which has pixels, once all rows of the current one were
+    /// decoded. The pixels of a pass are not filtered against those of the previous one.
+    fn skip_finished_passes(&mut self) {
+        let passes = self.passes;
+        while let Some(pass) = passes.get(self.pass) {
+            let (columns, rows) = pass.size(self.width, self.height);
+            if columns > 0 && self.pass_rows < rows {
+                return;
+            }
+            self.pass += 1;
+            self.pass_rows = 0;
+            let columns = passes
+                .get(self.pass)
+                .map_or(0, |pass| pass.size(self.width, self.height).0);
+            self.previous_row = vec![0; columns as usize * self.channels];
+        }
+    }
+
+    /// Fill the blocks of the pixels of an unfiltered row of the current pass.
+    fn draw_row(&mut self, row: &[u8]) {
+        let pass = &self.passes[self.pass];
+        let y = pass.y + self.pass_rows * pass.row_spacing;
+        let rows = y..(y + pass.block_height).min(self.height);
+        for (column, source) in row.chunks(self.channels).enumerate() {
+            let (r, g, b, a) = match *source {
+                [l] => (l, l, l, 0xff),
+                [l, a] => (l, l, l, a),
+                [r, g, b] => (r, g, b, 0xff),
+                [r, g, b, a] => (r, g, b, a),
+                _ => unreachable!(),
+            };
+            let x = pass.x + column as u32 * pass.column_spacing;
+            let block_width = pass.block_width.min(self.width - x) as usize;
+            for block_row in rows.clone() {
+                let start = (block_row as usize * self.width as usize + x as usize) * 4;
+                for pixel in self.pixels[start..start + block_width * 4].chunks_mut(4) {
+                    pixel.copy_from_slice(&[b, g, r, a]);
+                }
+            }
+        }
+        self.changed_rows = Some(match self.changed_rows.take() {
+            Some(changed_rows) => {
+                changed_rows.start.min(rows.start)..changed_rows.end.max(rows.end)
+            },
+            None => rows,
+        });
+    }
+
+    /// Unfilter the rows which the inflated bytes complete.
+    /// <https://www.w3.org/TR/PNG/#9Filters>
+    fn add_inflated_bytes(&mut self, mut inflated: &[u8]) -> Result<(), ()> {
+        while !inflated.is_empty() && self.pass < self.passes.len() {
+            let row_size = self.previous_row.len();
+            let missing = row_size + 1 - self.filtered_row.len();
+            let (bytes, rest) = inflated.split_at(missing.min(inflated.len()));
+            self.filtered_row.extend_from_slice(bytes);
+            inflated = rest;
+            if self.filtered_row.len() < row_size + 1 {
+                break;
+            }
+
+            let filter_type = self.filtered_row[0];
+            let mut row = vec![0; row_size];
+            for x in 0..row_size {
+                let filtered = self.filtered_row[x + 1];
+                let left = if x >= self.channels {
+                    row[x - self.channels]
+                } else {
+                    0
+                };
+                let up = self.previous_row[x];
+                let up_left = if x >= self.channels {
+                    self.previous_row[x - self.channels]
+                } else {
+                    0
+                };
+                let predictor = match filter_type {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    4 => paeth_predictor(left, up, up_left),
+                    _ => return Err(()),
+                };
+                row[x] = filtered.wrapping_add(predictor);
+            }
+
+            self.draw_row(&row);
+            self.previous_row = row;
+            self.filtered_row.clear();
+            self.pass_rows += 1;
+            self.skip_finished_passes();
+        }
+        Ok(())
+    }
+}
+
+fn paeth_predictor(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_up = (estimate - up as i16).abs();
+    let distance_up_left = (estimate - up_left as i16).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+impl PartialJpeg {
+    /// Decode the scans which were received, once a scan was completed since they were
+    /// last decoded. The coefficients of the scans which are missing are zero, so the
+    /// image gets sharper with each scan.
+    fn decode(
+        &mut self,
+        buffer: &[u8],
+        cors_status: CorsStatus,
+    ) -> Result<Option<(Image, Range<u32>)>, ()> {
+        let scans_end = jpeg_complete_scans_end(buffer, self.first_scan);
+        if scans_end <= self.decoded_scans_end {
+            return Ok(None);
+        }
+        self.decoded_scans_end = scans_end;
+        let mut scans = buffer[..scans_end].to_vec();
+        scans.extend_from_slice(&[0xff, 0xd9]);
+        let image = load_from_memory(&scans, cors_status).ok_or(())?;
+        let height = image.height;
+        Ok(Some((image, 0..height)))
+    }
+}
+
+/// The position of the SOS marker of the first scan of a JPEG image.
+fn jpeg_first_scan(buffer: &[u8]) -> Option<usize> {
+    let mut position = 2;
+    loop {
+        let header = buffer.get(position..position + 4)?;
+        if header[0] != 0xff {
+            return None;
+        }
+        if header[1] == 0xda {
+            return Some(position);
+        }
+        position += 2 + u16::from_be_bytes([header[2], header[3]]) as usize;
+    }
+}
+
+/// The end of the scans of a JPEG image which were completely received, which is the
+/// position of the marker that follows the last of them.
+fn jpeg_complete_scans_end(buffer: &[u8], first_scan: usize) -> usize {
+    let mut scans_end = first_scan;
+    let mut position = first_scan;
+    while let Some(header) = buffer.get(position..position + 4) {
+        // The end of the image has no length.
+        if header[1] == 0xd9 {
+            return position;
+        }
+        let segment_end = position + 2 + u16::from_be_bytes([header[2], header[3]]) as usize;
+        if header[1] != 0xda {
+            position = segment_end;
+            continue;
+        }
+        // The entropy-coded data of a scan end at the next marker, other than the
+        // stuffed 0xff bytes, the fill bytes and the restart markers.
+        let next_marker = buffer.get(segment_end..).and_then(|data| {
+            data.windows(2).position(|bytes| {
+                bytes[0] == 0xff &&
+                    bytes[1] != 0 &&
+                    bytes[1] != 0xff &&
+                    !(0xd0..=0xd7).contains(&bytes[1])
+            })
+        });
+        match next_marker {
+            Some(offset) => {
+                position = segment_end + offset;
+                scans_end = position;
+            },
+            None => break,
+        }
     }
+    scans_end
 }
 
 // https://developer.mozilla.org/en-US/docs/Web/HTML/Element/img
+// FIXME: AVIF images and animated WebP images are not decoded, since the version of the
+// image crate in use has no AVIF decoder and only decodes simple lossy WebP images, so
+// only GIF and APNG images are animated.
 pub fn detect_image_format(buffer: &[u8]) -> Result<ImageFormat, &str> {
     if is_gif(buffer) {
         Ok(ImageFormat::Gif)
@@ -86,11 +1025,209 @@ pub fn detect_image_format(buffer: &[u8]) -> Result<ImageFormat, &str> {
         Ok(ImageFormat::Bmp)
     } else if is_ico(buffer) {
         Ok(ImageFormat::Ico)
+    } else if is_webp(buffer) {
+        Ok(ImageFormat::WebP)
     } else {
         Err("Image Format Not Supported")
     }
 }
 
+/// The orientation given by the EXIF metadata of a JPEG or PNG image, which is normal
+/// if the image has none.
+pub fn exif_orientation(buffer: &[u8]) -> ImageOrientation {
+    let exif = if is_jpeg(buffer) {
+        jpeg_exif(buffer)
+    } else if is_png(buffer) {
+        png_exif(buffer)
+    } else {
+        None
+    };
+    exif.and_then(tiff_orientation)
+        .map_or(ImageOrientation::Normal, ImageOrientation::from_exif)
+}
+
+/// The EXIF metadata of a JPEG image, which is stored in an APP1 segment.
+fn jpeg_exif(buffer: &[u8]) -> Option<&[u8]> {
+    jpeg_segments(buffer, 0xe1)
+        .into_iter()
+        .find(|segment| segment.starts_with(b"Exif\0\0"))
+        .map(|segment| &segment[6..])
+}
+
+/// The EXIF metadata of a PNG image, which is stored in an eXIf chunk.
+fn png_exif(buffer: &[u8]) -> Option<&[u8]> {
+    png_chunk(buffer, b"eXIf")
+}
+
+/// The ICC profile of a JPEG or PNG image, which converts its colors to sRGB.
+fn color_profile(buffer: &[u8]) -> Option<ColorProfile> {
+    let profile = if is_jpeg(buffer) {
+        jpeg_icc_profile(buffer)?
+    } else if is_png(buffer) {
+        png_icc_profile(buffer)?
+    } else {
+        return None;
+    };
+    ColorProfile::parse(&profile)
+}
+
+/// The ICC profile of a JPEG image, which is split in APP2 segments that start with
+/// their sequence number.
+fn jpeg_icc_profile(buffer: &[u8]) -> Option<Vec<u8>> {
+    let mut chunks: Vec<_> = jpeg_segments(buffer, 0xe2)
+        .into_iter()
+        .filter(|segment| segment.starts_with(b"ICC_PROFILE\0") && segment.len() >= 14)
+        .map(|segment| (segment[12], &segment[14..]))
+        .collect();
+    if chunks.is_empty() {
+        return None;
+    }
+    chunks.sort_by_key(|&(sequence_number, _)| sequence_number);
+    Some(
+        chunks
+            .into_iter()
+            .flat_map(|(_, chunk)| chunk)
+            .cloned()
+            .collect(),
+    )
+}
+
+/// The ICC profile of a PNG image, which is compressed in an iCCP chunk after its name.
+fn png_icc_profile(buffer: &[u8]) -> Option<Vec<u8>> {
+    let chunk = png_chunk(buffer, b"iCCP")?;
+    let name_end = chunk.iter().position(|&byte| byte == 0)?;
+    let compressed = chunk.get(name_end + 2..)?;
+    let mut profile = vec![];
+    ZlibDecoder::new(compressed)
+        .read_to_end(&mut profile)
+        .ok()?;
+    Some(profile)
+}
+
+/// The contents of the segments of a JPEG image with the given marker which precede
+/// its scans, where the metadata are.
+fn jpeg_segments(buffer: &[u8], marker: u8) -> Vec<&[u8]> {
+    let mut segments = vec![];
+    let mut position = 2;
+    while let Some(header) = buffer.get(position..position + 4) {
+        // The scans start with an SOS marker.
+        if header[0] != 0xff || header[1] == 0xda {
+            break;
+        }
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let segment = match buffer.get(position + 4..position + 2 + length) {
+            Some(segment) => segment,
+            None => break,
+        };
+        if header[1] == marker {
+            segments.push(segment);
+        }
+        position += 2 + length;
+    }
+    segments
+}
+
+/// The contents of the chunk of a PNG image with the given type, if it precedes the image
+/// data like the metadata chunks.
+fn png_chunk<'a>(buffer: &'a [u8], chunk_type: &[u8]) -> Option<&'a [u8]> {
+    let mut position = 8;
+    loop {
+        let header = buffer.get(position..position + 8)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        match &header[4..] {
+            b"IDAT" => return None,
+            found_type if found_type == chunk_type => {
+                return buffer.get(position + 8..position + 8 + length);
+            },
+            _ => position += 12 + length,
+        }
+    }
+}
+
+/// The types and the contents of all chunks of a PNG image, up to the first truncated one.
+fn png_chunks(buffer: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = vec![];
+    let mut position = 8;
+    while let Some(header) = buffer.get(position..position + 8) {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let data = match buffer.get(position + 8..position + 8 + length) {
+            Some(data) => data,
+            None => break,
+        };
+        chunks.push((&header[4..], data));
+        position += 12 + length;
+    }
+    chunks
+}
+
+/// Append a PNG chunk of the given type to an image, with its length and its checksum.
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(chunk_type);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+/// The value of the Orientation tag in the 0th IFD of EXIF metadata, which have the
+/// structure of a TIFF file.
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes = tiff.get(offset..offset + 2)?;
+        let bytes = [bytes[0], bytes[1]];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = tiff.get(offset..offset + 4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    // The entries of an IFD, which are 12 bytes long, follow their count. The value of
+    // an entry of the SHORT type is at the start of its last 4 bytes.
+    const ORIENTATION_TAG: u16 = 0x0112;
+    const SHORT_TYPE: u16 = 3;
+    let ifd = read_u32(4)? as usize;
+    let entry_count = read_u16(ifd)? as usize;
+    (0..entry_count)
+        .map(|index| ifd + 2 + index * 12)
+        .find(|&entry| read_u16(entry) == Some(ORIENTATION_TAG))
+        .filter(|&entry| read_u16(entry + 2) == Some(SHORT_TYPE))
+        .and_then(|entry| read_u16(entry + 8))
+}
+
+/// Whether images of the given MIME type, without parameters, can be decoded.
+pub fn is_supported_image_mime_type(essence: &str) -> bool {
+    [
+        "image/bmp",
+        "image/gif",
+        "image/jpeg",
+        "image/jpg",
+        "image/pjpeg",
+        "image/png",
+        "image/vnd.microsoft.icon",
+        "image/webp",
+        "image/x-icon",
+    ]
+    .iter()
+    .any(|supported| supported.eq_ignore_ascii_case(essence))
+}
+
 fn is_gif(buffer: &[u8]) -> bool {
     buffer.starts_with(b"GIF87a") || buffer.starts_with(b"GIF89a")
 }
@@ -99,8 +1236,10 @@ fn is_jpeg(buffer: &[u8]) -> bool {
     buffer.starts_with(&[0xff, 0xd8, 0xff])
 }
 
+const PNG_SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
 fn is_png(buffer: &[u8]) -> bool {
-    buffer.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A])
+    buffer.starts_with(&PNG_SIGNATURE)
 }
 
 fn is_bmp(buffer: &[u8]) -> bool {
@@ -110,3 +1249,7 @@ fn is_bmp(buffer: &[u8]) -> bool {
 fn is_ico(buffer: &[u8]) -> bool {
     buffer.starts_with(&[0x00, 0x00, 0x01, 0x00])
 }
+
+fn is_webp(buffer: &[u8]) -> bool {
+    buffer.starts_with(b"RIFF") && buffer.len() >= 12 && &buffer[8..12] == b"WEBP"
+}
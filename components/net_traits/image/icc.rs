@@ -0,0 +1,301 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The conversion of the colors of images tagged with an ICC profile to sRGB, which is
+//! the color space that pages are composited in.
+//! <http://www.color.org/specification/ICC1v43_2010-12.pdf>
+//!
+//! The RGB profiles described by a matrix and tone curves, and the gray profiles
+//! described by a tone curve, are supported. The matrix and tone curves are used even if
+//! the profile also has lookup tables for its rendering intents.
+//!
+//! FIXME: The profiles which are only described by lookup tables, like CMYK and Lab
+//! profiles, are ignored, so the colors of their images are displayed as if they were
+//! sRGB. Wide-gamut colors are clipped to sRGB even on displays whose gamut is wider,
+//! since WebRender only renders to sRGB surfaces.
+
+/// The matrix which converts the XYZ values of the D50-based profile connection space to
+/// linear sRGB values, adapted to D50 with the Bradford transform.
+const XYZ_D50_TO_LINEAR_SRGB: [[f32; 3]; 3] = [
+    [3.1338561, -1.6168667, -0.4906146],
+    [-0.9787684, 1.9161415, 0.0334540],
+    [0.0719453, -0.2289914, 1.4052427],
+];
+
+/// The number of entries of the table which encodes linear values to sRGB.
+const SRGB_ENCODING_TABLE_SIZE: usize = 4096;
+
+/// An RGB profile described by the XYZ values of its primaries and by the tone curves
+/// of its channels, or a gray profile described by the tone curve of its only channel.
+pub struct ColorProfile {
+    /// The matrix which converts the linear values of the channels to linear sRGB values.
+    matrix: [[f32; 3]; 3],
+    /// The tone curves which convert the red, green and blue values to linear ones.
+    curves: [ToneCurve; 3],
+}
+
+/// A tone curve of an ICC profile.
+enum ToneCurve {
+    /// The values of the curve at evenly spaced inputs, between 0 and 65535.
+    Table(Vec<u16>),
+    /// The parametric curve `(a * x + b) ^ g + e` for inputs from `d` on, and
+    /// `c * x + f` for smaller inputs, which all parametric curves are special cases of.
+    Parametric {
+        g: f32,
+        a: f32,
+        b: f32,
+        c: f32,
+        d: f32,
+        e: f32,
+        f: f32,
+    },
+}
+
+impl ToneCurve {
+    fn gamma(g: f32) -> ToneCurve {
+        ToneCurve::Parametric {
+            g,
+            a: 1.,
+            b: 0.,
+            c: 0.,
+            d: 0.,
+            e: 0.,
+            f: 0.,
+        }
+    }
+
+    /// Parse a curveType or a parametricCurveType tag.
+    fn parse(tag: &[u8]) -> Option<ToneCurve> {
+        match tag.get(..4)? {
+            b"curv" => {
+                let count = read_u32(tag, 8)? as usize;
+                match count {
+                    0 => Some(ToneCurve::gamma(1.)),
+                    1 => Some(ToneCurve::gamma(read_u16(tag, 12)? as f32 / 256.)),
+                    _ => (0..count)
+                        .map(|index| read_u16(tag, 12 + index * 2))
+                        .collect::<Option<_>>()
+                        .map(ToneCurve::Table),
+                }
+            },
+            b"para" => {
+                let function_type = read_u16(tag, 8)?;
+                let parameter_count = match function_type {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return None,
+                };
+                let parameters = (0..parameter_count)
+                    .map(|index| read_s15_fixed16(tag, 12 + index * 4))
+                    .collect::<Option<Vec<_>>>()?;
+                let (g, a, b) = match function_type {
+                    0 => (parameters[0], 1., 0.),
+                    _ => (parameters[0], parameters[1], parameters[2]),
+                };
+                let start = if a != 0. { -b / a } else { 0. };
+                let (c, d, e, f) = match function_type {
+                    0 => (0., 0., 0., 0.),
+                    1 => (0., start, 0., 0.),
+                    2 => (0., start, parameters[3], parameters[3]),
+                    3 => (parameters[3], parameters[4], 0., 0.),
+                    _ => (parameters[3], parameters[4], parameters[5], parameters[6]),
+                };
+                Some(ToneCurve::Parametric {
+                    g,
+                    a,
+                    b,
+                    c,
+                    d,
+                    e,
+                    f,
+                })
+            },
+            _ => None,
+        }
+    }
+
+    /// The linear value of an input between 0 and 1.
+    fn evaluate(&self, x: f32) -> f32 {
+        match *self {
+            ToneCurve::Table(ref table) => {
+                let position = x * (table.len() - 1) as f32;
+                let index = (position as usize).min(table.len() - 2);
+                let weight = position - index as f32;
+                let value = table[index] as f32 * (1. - weight) + table[index + 1] as f32 * weight;
+                value / 65535.
+            },
+            ToneCurve::Parametric {
+                g,
+                a,
+                b,
+                c,
+                d,
+                e,
+                f,
+            } => {
+                if x >= d {
+                    (a * x + b).max(0.).powf(g) + e
+                } else {
+                    c * x + f
+                }
+            },
+        }
+    }
+}
+
+impl ColorProfile {
+    /// Parse an ICC profile, which is only supported if it is an RGB profile described
+    /// by a matrix and tone curves, or a gray profile described by a tone curve.
+    pub fn parse(profile: &[u8]) -> Option<ColorProfile> {
+        // The data color space is in the header, which is followed by the tag table.
+        let tag_count = read_u32(profile, 128)? as usize;
+        let tag = |signature: &[u8]| {
+            (0..tag_count)
+                .map(|index| 132 + index * 12)
+                .find(|&entry| profile.get(entry..entry + 4) == Some(signature))
+                .and_then(|entry| -> Option<&[u8]> {
+                    let offset = read_u32(profile, entry + 4)? as usize;
+                    let size = read_u32(profile, entry + 8)? as usize;
+                    profile.get(offset..offset.checked_add(size)?)
+                })
+        };
+        let primary = |signature: &[u8]| -> Option<[f32; 3]> {
+            let tag = tag(signature)?;
+            if tag.get(..4)? != b"XYZ " {
+                return None;
+            }
+            Some([
+                read_s15_fixed16(tag, 8)?,
+                read_s15_fixed16(tag, 12)?,
+                read_s15_fixed16(tag, 16)?,
+            ])
+        };
+
+        match profile.get(16..20)? {
+            b"RGB " => {
+                let (red, green, blue) = (primary(b"rXYZ")?, primary(b"gXYZ")?, primary(b"bXYZ")?);
+                let mut primaries = [[0.; 3]; 3];
+                for row in 0..3 {
+                    primaries[row] = [red[row], green[row], blue[row]];
+                }
+                let curves = [
+                    ToneCurve::parse(tag(b"rTRC")?)?,
+                    ToneCurve::parse(tag(b"gTRC")?)?,
+                    ToneCurve::parse(tag(b"bTRC")?)?,
+                ];
+                Some(ColorProfile {
+                    matrix: multiply(&XYZ_D50_TO_LINEAR_SRGB, &primaries),
+                    curves,
+                })
+            },
+            // The gray images are decoded to pixels whose channels are equal, and the
+            // tone curve gives the luminance relative to the white point, which is the
+            // same in linear sRGB.
+            b"GRAY" => {
+                let curve = || ToneCurve::parse(tag(b"kTRC")?);
+                Some(ColorProfile {
+                    matrix: [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]],
+                    curves: [curve()?, curve()?, curve()?],
+                })
+            },
+            _ => None,
+        }
+    }
+
+    /// Convert the colors of RGBA pixels from this profile to sRGB.
+    pub fn convert_to_srgb(&self, rgba: &mut [u8]) {
+        let matrix = &self.matrix;
+        let linear_tables: Vec<Vec<f32>> = self
+            .curves
+            .iter()
+            .map(|curve| {
+                (0..256)
+                    .map(|value| curve.evaluate(value as f32 / 255.))
+                    .collect()
+            })
+            .collect();
+        if is_srgb(matrix, &linear_tables) {
+            return;
+        }
+
+        let last_entry = (SRGB_ENCODING_TABLE_SIZE - 1) as f32;
+        let encoding_table: Vec<u8> = (0..SRGB_ENCODING_TABLE_SIZE)
+            .map(|index| (srgb_encode(index as f32 / last_entry) * 255.).round() as u8)
+            .collect();
+        for pixel in rgba.chunks_mut(4) {
+            let linear = [
+                linear_tables[0][pixel[0] as usize],
+                linear_tables[1][pixel[1] as usize],
+                linear_tables[2][pixel[2] as usize],
+            ];
+            for (channel, row) in pixel.iter_mut().zip(matrix.iter()) {
+                let value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+                let index = (value.max(0.).min(1.) * last_entry).round() as usize;
+                *channel = encoding_table[index];
+            }
+        }
+    }
+}
+
+/// Whether converting colors with the given matrix and linearization tables does not
+/// noticeably change them, which is the case for sRGB profiles.
+fn is_srgb(matrix: &[[f32; 3]; 3], linear_tables: &[Vec<f32>]) -> bool {
+    let matrix_is_identity = matrix.iter().enumerate().all(|(row, values)| {
+        values.iter().enumerate().all(|(column, value)| {
+            let identity = if row == column { 1. } else { 0. };
+            (value - identity).abs() < 0.01
+        })
+    });
+    matrix_is_identity &&
+        linear_tables.iter().all(|table| {
+            table
+                .iter()
+                .enumerate()
+                .all(|(value, linear)| (linear - srgb_decode(value as f32 / 255.)).abs() < 0.005)
+        })
+}
+
+fn multiply(left: &[[f32; 3]; 3], right: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut product = [[0.; 3]; 3];
+    for row in 0..3 {
+        for column in 0..3 {
+            product[row][column] = (0..3).map(|k| left[row][k] * right[k][column]).sum();
+        }
+    }
+    product
+}
+
+/// <https://www.w3.org/Graphics/Color/srgb>
+fn srgb_decode(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn srgb_encode(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_s15_fixed16(bytes: &[u8], offset: usize) -> Option<f32> {
+    read_u32(bytes, offset).map(|value| value as i32 as f32 / 65536.)
+}
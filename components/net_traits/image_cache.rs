@@ -67,6 +67,9 @@ pub enum ImageResponse {
     Loaded(#[ignore_malloc_size_of = "Arc"] Arc<Image>, ServoUrl),
     /// The request image metadata was loaded.
     MetadataLoaded(ImageMetadata),
+    /// The rows of the requested image that were received so far were decoded, and the
+    /// image will be loaded once its other rows are received.
+    PartiallyLoaded(#[ignore_malloc_size_of = "Arc"] Arc<Image>, ServoUrl),
     /// The requested image failed to load, so a placeholder was loaded instead.
     PlaceholderLoaded(#[ignore_malloc_size_of = "Arc"] Arc<Image>, ServoUrl),
     /// Neither the requested image nor the placeholder could be loaded.
@@ -124,6 +127,13 @@ pub trait ImageCache: Sync + Send {
 
     /// Inform the image cache about a response for a pending request.
     fn notify_pending_response(&self, id: PendingImageId, action: FetchResponseMsg);
+
+    /// Return a decoded image as it is stored, without the orientation given by its EXIF
+    /// metadata, for elements whose `image-orientation` is `none`.
+    fn find_unoriented_image(&self, image: &Arc<Image>) -> Arc<Image>;
+
+    /// Evict the decoded images which nothing else uses, and return their size in bytes.
+    fn evict_unused_images(&self) -> usize;
 }
 
 /// Whether this response passed any CORS checks, and is thus safe to read from
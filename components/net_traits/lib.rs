@@ -19,6 +19,7 @@ use crate::filemanager_thread::FileManagerThreadMsg;
 use crate::request::{Request, RequestBuilder};
 use crate::response::{HttpsState, Response, ResponseInit};
 use crate::storage_thread::StorageThreadMsg;
+use content_security_policy::Violation;
 use cookie::Cookie;
 use headers::{ContentType, HeaderMapExt, ReferrerPolicy as ReferrerPolicyHeader};
 use http::{Error as HttpError, HeaderMap};
@@ -29,14 +30,16 @@ use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use ipc_channel::Error as IpcError;
 use mime::Mime;
-use msg::constellation_msg::HistoryStateId;
+use msg::constellation_msg::{HistoryStateId, PipelineId};
 use servo_url::ServoUrl;
+use std::time::SystemTime;
 use time::precise_time_ns;
 use webrender_api::ImageKey;
 
 pub mod blob_url_store;
 pub mod filemanager_thread;
 pub mod image_cache;
+pub mod protocol_handler;
 pub mod pub_domains;
 pub mod quality;
 pub mod request;
@@ -50,6 +53,7 @@ pub mod storage_thread;
 /// caching is involved) and as a result it must live in here.
 pub mod image {
     pub mod base;
+    pub mod icc;
 }
 
 /// A loading context, for context-specific sniffing, as defined in
@@ -154,6 +158,7 @@ pub enum FetchResponseMsg {
     ProcessResponse(Result<FetchMetadata, NetworkError>),
     ProcessResponseChunk(Vec<u8>),
     ProcessResponseEOF(Result<ResourceFetchTiming, NetworkError>),
+    ProcessCspViolations(Vec<Violation>),
 }
 
 pub trait FetchTaskTarget {
@@ -179,6 +184,11 @@ pub trait FetchTaskTarget {
     ///
     /// Fired when the response is fully fetched
     fn process_response_eof(&mut self, response: &Response);
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    ///
+    /// Fired when the request violates the Content Security Policies of its client
+    fn process_csp_violations(&mut self, request: &Request, violations: Vec<Violation>);
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -204,6 +214,7 @@ pub trait FetchResponseListener {
     fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>);
     fn process_response_chunk(&mut self, chunk: Vec<u8>);
     fn process_response_eof(&mut self, response: Result<ResourceFetchTiming, NetworkError>);
+    fn process_csp_violations(&mut self, violations: Vec<Violation>);
     fn resource_timing(&self) -> &ResourceFetchTiming;
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming;
     fn submit_resource_timing(&mut self);
@@ -237,6 +248,10 @@ impl FetchTaskTarget for IpcSender<FetchResponseMsg> {
                 .clone())));
         }
     }
+
+    fn process_csp_violations(&mut self, _: &Request, violations: Vec<Violation>) {
+        let _ = self.send(FetchResponseMsg::ProcessCspViolations(violations));
+    }
 }
 
 /// A fetch task that discards all data it's sent,
@@ -254,6 +269,8 @@ impl FetchTaskTarget for DiscardFetch {
     fn process_response_chunk(&mut self, _: Vec<u8>) {}
 
     fn process_response_eof(&mut self, _: &Response) {}
+
+    fn process_csp_violations(&mut self, _: &Request, _: Vec<Violation>) {}
 }
 
 pub trait Action<Listener> {
@@ -285,6 +302,9 @@ impl<T: FetchResponseListener> Action<T> for FetchResponseMsg {
                     Err(e) => listener.process_response_eof(Err(e)),
                 }
             },
+            FetchResponseMsg::ProcessCspViolations(violations) => {
+                listener.process_csp_violations(violations)
+            },
         }
     }
 }
@@ -402,12 +422,21 @@ pub enum CoreResourceMsg {
         IpcSender<FetchResponseMsg>,
         /* cancel_chan */ Option<IpcReceiver<()>>,
     ),
+    /// Resolve the address of a host, for the connections to it which may follow.
+    /// <https://w3c.github.io/resource-hints/#dns-prefetch>
+    PrefetchDns(String),
+    /// Open a connection to the origin of a URL, for the request to it which may follow.
+    /// <https://w3c.github.io/resource-hints/#preconnect>
+    Preconnect(ServoUrl),
     /// Store a cookie for a given originating URL
     SetCookieForUrl(ServoUrl, Serde<Cookie<'static>>, CookieSource),
     /// Store a set of cookies for a given originating URL
     SetCookiesForUrl(ServoUrl, Vec<Serde<Cookie<'static>>>, CookieSource),
     /// Retrieve the stored cookies for a given URL
     GetCookiesForUrl(ServoUrl, IpcSender<Option<String>>, CookieSource),
+    /// Retrieve the cookies which scripts can read for a given URL, and notify the second
+    /// sender of the next change of the cookies of its site
+    ObserveCookiesForUrl(ServoUrl, IpcSender<ObservedCookies>, IpcSender<()>),
     /// Get a cookie by name for a given originating URL
     GetCookiesDataForUrl(
         ServoUrl,
@@ -427,11 +456,39 @@ pub enum CoreResourceMsg {
     NetworkMediator(IpcSender<CustomResponseMediator>),
     /// Message forwarded to file manager's handler
     ToFileManager(FileManagerThreadMsg),
+    /// Record a crash, to be listed on about:crashes
+    AddCrashReport(CrashReport),
+    /// Get the stylesheet which hides the elements matching the element hiding rules of
+    /// the content blocker, for the page at the given URL.
+    GetElementHidingStylesheet(ServoUrl, IpcSender<Option<String>>),
+    /// Get the number of requests of the given pipelines which the content blocker
+    /// blocked or rewrote.
+    GetBlockedCount(Vec<PipelineId>, IpcSender<usize>),
+    /// A pipeline exited, so the number of its blocked requests can be forgotten.
+    PipelineExited(PipelineId),
+    /// Connect through the proxy of the given URL, except to the given hosts, or directly
+    /// when there is none.
+    SetProxy(Option<String>, Vec<String>),
+    /// Start a private browsing session whose messages are received on the given channel.
+    /// Its cookies, cache and other state are only kept in memory, and are discarded
+    /// once all the senders of the channel are dropped.
+    NewPrivateSession(IpcReceiver<CoreResourceMsg>),
     /// Break the load handler loop, send a reply when done cleaning up local resources
     /// and exit
     Exit(IpcSender<()>),
 }
 
+/// A crash of the pipeline of a top-level browsing context.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CrashReport {
+    /// The URL of the page that crashed.
+    pub url: ServoUrl,
+    pub reason: String,
+    pub backtrace: Option<String>,
+    /// When the crash happened, in seconds since the epoch.
+    pub time: u64,
+}
+
 /// Instruct the resource thread to make a new request.
 pub fn fetch_async<F>(request: RequestBuilder, core_resource_thread: &CoreResourceThread, f: F)
 where
@@ -686,6 +743,14 @@ pub enum CookieSource {
     NonHTTP,
 }
 
+/// The cookies which scripts can read for a URL, which stay the same until the resource
+/// thread notifies that they changed, or until the first of them expires.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ObservedCookies {
+    pub cookies: Option<String>,
+    pub expiry_time: Option<SystemTime>,
+}
+
 /// Network errors that have to be exported out of the loaders
 #[derive(Clone, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
 pub enum NetworkError {
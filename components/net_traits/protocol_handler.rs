@@ -0,0 +1,121 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Custom URL schemes registered by the embedder, such as `app:` for the bundled assets
+//! of a hybrid application, and the handlers that load their resources.
+
+use crate::request::Request;
+use headers::{ContentType, HeaderMapExt};
+use http::HeaderMap;
+use hyper::StatusCode;
+use mime::Mime;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// The schemes which are implemented by the engine, and can't be registered.
+const RESERVED_SCHEMES: &[&str] = &[
+    "about",
+    "blob",
+    "chrome",
+    "data",
+    "file",
+    "ftp",
+    "http",
+    "https",
+    "javascript",
+    "ws",
+    "wss",
+];
+
+/// The body of the response of a protocol handler.
+pub enum ProtocolBody {
+    /// The whole body.
+    Bytes(Vec<u8>),
+    /// A body which is read in chunks while the resource loads, for large assets.
+    Stream(Box<dyn Read + Send>),
+}
+
+/// The response of a protocol handler.
+pub struct ProtocolResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: ProtocolBody,
+}
+
+impl ProtocolResponse {
+    /// A successful response with the given content type.
+    pub fn new(content_type: Mime, body: ProtocolBody) -> ProtocolResponse {
+        let mut headers = HeaderMap::new();
+        headers.typed_insert(ContentType::from(content_type));
+        ProtocolResponse {
+            status: StatusCode::OK,
+            headers: headers,
+            body: body,
+        }
+    }
+}
+
+/// The handler of a custom scheme. It is called from the threads of the resource
+/// thread pool, so it may be called for several requests at once.
+pub trait ProtocolHandler: Send + Sync {
+    /// Load the resource of the given request, or return the reason of the network
+    /// error which the fetch fails with.
+    fn load(&self, request: &Request) -> Result<ProtocolResponse, String>;
+
+    /// Whether the resources of the scheme are delivered securely, like those of https,
+    /// so that their documents are secure contexts.
+    fn is_secure(&self) -> bool {
+        false
+    }
+
+    /// Whether the resources of the scheme can be fetched by pages of other origins in
+    /// the cors mode, which is otherwise only allowed for http(s). Their responses must
+    /// still pass the CORS check, so the handler controls access with the
+    /// `Access-Control-Allow-Origin` header.
+    fn is_fetchable(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProtocolRegisterError {
+    /// The scheme is implemented by the engine.
+    ReservedScheme,
+    /// The scheme already has a handler.
+    DuplicateScheme,
+}
+
+/// The handlers of the custom schemes, by scheme.
+#[derive(Default)]
+pub struct ProtocolRegistry {
+    handlers: HashMap<String, Box<dyn ProtocolHandler>>,
+}
+
+impl ProtocolRegistry {
+    /// Register the handler of the given scheme, which is case-insensitive.
+    pub fn register<H: ProtocolHandler + 'static>(
+        &mut self,
+        scheme: &str,
+        handler: H,
+    ) -> Result<(), ProtocolRegisterError> {
+        let scheme = scheme.to_ascii_lowercase();
+        if RESERVED_SCHEMES.contains(&&*scheme) {
+            return Err(ProtocolRegisterError::ReservedScheme);
+        }
+        if self.handlers.contains_key(&scheme) {
+            return Err(ProtocolRegisterError::DuplicateScheme);
+        }
+        self.handlers.insert(scheme, Box::new(handler));
+        Ok(())
+    }
+
+    pub fn get(&self, scheme: &str) -> Option<&dyn ProtocolHandler> {
+        self.handlers.get(scheme).map(|handler| &**handler)
+    }
+
+    pub fn is_fetchable(&self, scheme: &str) -> bool {
+        self.get(scheme)
+            .map_or(false, |handler| handler.is_fetchable())
+    }
+}
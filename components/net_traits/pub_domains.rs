@@ -155,3 +155,24 @@ pub fn reg_host(url: &ServoUrl) -> Option<Host> {
         ImmutableOrigin::Opaque(_) => None,
     }
 }
+
+/// Whether two origins are schemefully same site, that is whether they have the same scheme
+/// and the same registered domain name.
+/// <https://html.spec.whatwg.org/multipage/#same-site>
+pub fn is_same_site(a: &ImmutableOrigin, b: &ImmutableOrigin) -> bool {
+    match (a, b) {
+        (
+            ImmutableOrigin::Tuple(scheme_a, host_a, _),
+            ImmutableOrigin::Tuple(scheme_b, host_b, _),
+        ) => {
+            scheme_a == scheme_b &&
+                match (host_a, host_b) {
+                    (Host::Domain(domain_a), Host::Domain(domain_b)) => {
+                        reg_suffix(domain_a) == reg_suffix(domain_b)
+                    },
+                    _ => host_a == host_b,
+                }
+        },
+        _ => a == b,
+    }
+}
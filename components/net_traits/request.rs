@@ -7,6 +7,7 @@ use crate::ResourceTimingType;
 use content_security_policy::{self as csp, CspList};
 use http::HeaderMap;
 use hyper::Method;
+use ipc_channel::ipc::IpcSender;
 use mime::Mime;
 use msg::constellation_msg::PipelineId;
 use servo_url::{ImmutableOrigin, ServoUrl};
@@ -18,6 +19,14 @@ pub enum Initiator {
     Download,
     ImageSet,
     Manifest,
+    Prefetch,
+    /// Not an initiator of the Fetch spec: the request of `<link rel=preload>`, whose
+    /// response is kept for the request it is preloaded for.
+    Preload,
+    /// Not an initiator of the Fetch spec: a request which the HTML parser makes ahead of
+    /// the element which needs its resource, whose response is kept like the one of a
+    /// preload. <https://html.spec.whatwg.org/multipage/#speculative-html-parsing>
+    SpeculativeParse,
     XSLT,
 }
 
@@ -114,6 +123,36 @@ pub enum ParserMetadata {
     NotParserInserted,
 }
 
+/// The viewport of the client of a request, which the `DPR` and `Viewport-Width` client
+/// hints describe.
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct ClientViewport {
+    /// The number of device pixels per CSS pixel.
+    pub device_pixel_ratio: f32,
+    /// The width of the layout viewport, in CSS pixels.
+    pub width: f32,
+}
+
+/// A message from the network to the script which streams the body of a request.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum BodyChunkRequest {
+    /// Start sending the chunks of the body to the given channel.
+    Connect(IpcSender<BodyChunkResponse>),
+    /// Send the next chunk, once the previous one was sent to the server.
+    Chunk,
+    /// Stop reading the body, which the network does not need anymore.
+    Done,
+}
+
+/// A chunk of the body of a request which a script streams, or the end of the body.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum BodyChunkResponse {
+    Chunk(Vec<u8>),
+    Done,
+    /// The stream errored, or a chunk is not a Uint8Array, which aborts the request.
+    Error,
+}
+
 #[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
 pub struct RequestBuilder {
     #[serde(
@@ -131,6 +170,8 @@ pub struct RequestBuilder {
     pub headers: HeaderMap,
     pub unsafe_request: bool,
     pub body: Option<Vec<u8>>,
+    #[ignore_malloc_size_of = "Channels are hard"]
+    pub body_stream: Option<IpcSender<BodyChunkRequest>>,
     pub service_workers_mode: ServiceWorkersMode,
     // TODO: client object
     pub destination: Destination,
@@ -156,6 +197,10 @@ pub struct RequestBuilder {
     pub url_list: Vec<ServoUrl>,
     pub parser_metadata: ParserMetadata,
     pub initiator: Initiator,
+    pub viewport: Option<ClientViewport>,
+    pub site_for_cookies: Option<ImmutableOrigin>,
+    pub top_level_navigation: bool,
+    pub keep_alive: bool,
 }
 
 impl RequestBuilder {
@@ -166,6 +211,7 @@ impl RequestBuilder {
             headers: HeaderMap::new(),
             unsafe_request: false,
             body: None,
+            body_stream: None,
             service_workers_mode: ServiceWorkersMode::All,
             destination: Destination::None,
             synchronous: false,
@@ -184,6 +230,10 @@ impl RequestBuilder {
             parser_metadata: ParserMetadata::Default,
             initiator: Initiator::None,
             csp_list: None,
+            viewport: None,
+            site_for_cookies: None,
+            top_level_navigation: false,
+            keep_alive: false,
         }
     }
 
@@ -212,6 +262,11 @@ impl RequestBuilder {
         self
     }
 
+    pub fn body_stream(mut self, body_stream: IpcSender<BodyChunkRequest>) -> RequestBuilder {
+        self.body_stream = Some(body_stream);
+        self
+    }
+
     pub fn destination(mut self, destination: Destination) -> RequestBuilder {
         self.destination = destination;
         self
@@ -277,6 +332,31 @@ impl RequestBuilder {
         self
     }
 
+    pub fn viewport(mut self, viewport: Option<ClientViewport>) -> RequestBuilder {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn csp_list(mut self, csp_list: Option<CspList>) -> RequestBuilder {
+        self.csp_list = csp_list;
+        self
+    }
+
+    pub fn site_for_cookies(mut self, site_for_cookies: Option<ImmutableOrigin>) -> RequestBuilder {
+        self.site_for_cookies = site_for_cookies;
+        self
+    }
+
+    pub fn top_level_navigation(mut self, top_level_navigation: bool) -> RequestBuilder {
+        self.top_level_navigation = top_level_navigation;
+        self
+    }
+
+    pub fn keep_alive(mut self, keep_alive: bool) -> RequestBuilder {
+        self.keep_alive = keep_alive;
+        self
+    }
+
     pub fn build(self) -> Request {
         let mut request = Request::new(
             self.url.clone(),
@@ -288,6 +368,7 @@ impl RequestBuilder {
         request.headers = self.headers;
         request.unsafe_request = self.unsafe_request;
         request.body = self.body;
+        request.body_stream = self.body_stream;
         request.service_workers_mode = self.service_workers_mode;
         request.destination = self.destination;
         request.synchronous = self.synchronous;
@@ -308,6 +389,10 @@ impl RequestBuilder {
         request.integrity_metadata = self.integrity_metadata;
         request.parser_metadata = self.parser_metadata;
         request.csp_list = self.csp_list;
+        request.viewport = self.viewport;
+        request.site_for_cookies = self.site_for_cookies;
+        request.top_level_navigation = self.top_level_navigation;
+        request.keep_alive = self.keep_alive;
         request
     }
 }
@@ -330,6 +415,10 @@ pub struct Request {
     pub unsafe_request: bool,
     /// <https://fetch.spec.whatwg.org/#concept-request-body>
     pub body: Option<Vec<u8>>,
+    /// The stream which the body is read from in place of `body`, which has no source to
+    /// send it again from, and whose length is unknown.
+    #[ignore_malloc_size_of = "Channels are hard"]
+    pub body_stream: Option<IpcSender<BodyChunkRequest>>,
     // TODO: client object
     pub window: Window,
     // TODO: target browsing context
@@ -380,6 +469,15 @@ pub struct Request {
     // boundary every time a redirect occurs.
     #[ignore_malloc_size_of = "Defined in rust-content-security-policy"]
     pub csp_list: Option<CspList>,
+    /// The viewport of the client, which client hints are sent about.
+    pub viewport: Option<ClientViewport>,
+    /// The site for cookies of the client, which the `SameSite` attribute of cookies is
+    /// enforced against, or `None` if it is the origin of the request.
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-05#section-5.2.1>
+    pub site_for_cookies: Option<ImmutableOrigin>,
+    /// Whether the request navigates a top-level browsing context, in which case
+    /// `site_for_cookies` is the origin of the document which initiated it.
+    pub top_level_navigation: bool,
 }
 
 impl Request {
@@ -391,6 +489,7 @@ impl Request {
             headers: HeaderMap::new(),
             unsafe_request: false,
             body: None,
+            body_stream: None,
             window: Window::Client,
             keep_alive: false,
             service_workers_mode: ServiceWorkersMode::All,
@@ -413,6 +512,9 @@ impl Request {
             redirect_count: 0,
             response_tainting: ResponseTainting::Basic,
             csp_list: None,
+            viewport: None,
+            site_for_cookies: None,
+            top_level_navigation: false,
         }
     }
 
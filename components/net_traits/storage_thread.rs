@@ -2,8 +2,20 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::response::ResponseType;
+use http::HeaderMap;
 use ipc_channel::ipc::IpcSender;
-use servo_url::ServoUrl;
+use msg::constellation_msg::TopLevelBrowsingContextId;
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::collections::BTreeMap;
+
+/// The number of bytes of the keys and values which the local and session storage areas of
+/// an origin may hold together.
+pub const QUOTA_SIZE_LIMIT: usize = 5 * 1024 * 1024;
+
+/// The number of bytes of the requests and responses which the caches of the Cache API of an
+/// origin may hold together.
+pub const CACHE_QUOTA_SIZE_LIMIT: usize = 50 * 1024 * 1024;
 
 #[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, Serialize)]
 pub enum StorageType {
@@ -11,35 +23,177 @@ pub enum StorageType {
     Local,
 }
 
-/// Request operations on the storage data associated with a particular url
-#[derive(Debug, Deserialize, Serialize)]
-pub enum StorageThreadMsg {
-    /// gets the number of key/value pairs present in the associated storage data
-    Length(IpcSender<usize>, ServoUrl, StorageType),
+/// A change to the storage data of an origin, which documents make to their snapshot of it
+/// and send to the storage thread together with the other changes of the same task.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum StorageMutation {
+    SetItem(String, String),
+    RemoveItem(String),
+    Clear,
+}
 
-    /// gets the name of the key at the specified index in the associated storage data
-    Key(IpcSender<Option<String>>, ServoUrl, StorageType, u32),
+impl StorageMutation {
+    /// The key which the mutation changes the value of, which is none for the mutations
+    /// which change every value.
+    pub fn key(&self) -> Option<&str> {
+        match *self {
+            StorageMutation::SetItem(ref key, _) | StorageMutation::RemoveItem(ref key) => {
+                Some(key)
+            },
+            StorageMutation::Clear => None,
+        }
+    }
+}
 
-    /// Gets the available keys in the associated storage data
-    Keys(IpcSender<Vec<String>>, ServoUrl, StorageType),
+/// The identifier of a cache of the Cache API, which keeps referring to the same cache after
+/// it is deleted from the caches of its origin.
+/// <https://w3c.github.io/ServiceWorker/#cache-objects>
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize)]
+pub struct CacheId(pub u64);
 
-    /// gets the value associated with the given key in the associated storage data
-    GetItem(IpcSender<Option<String>>, ServoUrl, StorageType, String),
+/// The request of a cached item, with the fields which the queries of a cache compare.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct CacheRequest {
+    pub url: ServoUrl,
+    pub method: String,
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    pub headers: HeaderMap,
+}
 
-    /// sets the value of the given key in the associated storage data
-    SetItem(
-        IpcSender<Result<(bool, Option<String>), ()>>,
+/// The response of a cached item, whose body is read in full before it is stored.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct CacheResponse {
+    pub response_type: ResponseType,
+    pub url_list: Vec<ServoUrl>,
+    pub status: Option<(u16, Vec<u8>)>,
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// <https://w3c.github.io/ServiceWorker/#dictdef-cachequeryoptions>
+#[derive(Clone, Copy, Debug, Default, Deserialize, MallocSizeOf, Serialize)]
+pub struct CacheQueryOptions {
+    pub ignore_search: bool,
+    pub ignore_method: bool,
+    pub ignore_vary: bool,
+}
+
+/// The caches of an origin which a query looks in.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum CacheQueryTarget {
+    /// A cache of the origin, even if it is no longer named.
+    Cache(CacheId),
+    /// The cache with a name, if the origin has one.
+    Named(String),
+    /// Each cache of the origin in the order of their creation, until one of them has a
+    /// matching item.
+    All,
+}
+
+/// <https://w3c.github.io/ServiceWorker/#dfn-cache-batch-operation>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum CacheOperation {
+    Put(CacheRequest, CacheResponse),
+    Delete(CacheRequest, CacheQueryOptions),
+}
+
+/// The reason why a batch of cache operations is not applied.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum CacheOperationError {
+    /// An operation would put or delete the request of an item which an earlier operation
+    /// of the batch puts.
+    InvalidState,
+    /// The caches of the origin would hold more than its quota.
+    QuotaExceeded,
+}
+
+/// Request operations on the storage data associated with a particular url, whose session
+/// storage data is that of a top-level browsing context
+#[derive(Debug, Deserialize, Serialize)]
+pub enum StorageThreadMsg {
+    /// gets the key/value pairs present in the associated storage data, and the number of
+    /// bytes which the other storage area of the origin holds, which counts in its quota
+    Snapshot(
+        IpcSender<(BTreeMap<String, String>, usize)>,
+        TopLevelBrowsingContextId,
         ServoUrl,
         StorageType,
-        String,
-        String,
     ),
 
-    /// removes the key/value pair for the given key in the associated storage data
-    RemoveItem(IpcSender<Option<String>>, ServoUrl, StorageType, String),
+    /// applies the mutations in order to the associated storage data, skipping those which
+    /// would exceed the quota, and sends the values it keeps for the keys they set, and the
+    /// number of bytes which the other storage area of the origin holds
+    Mutate(
+        IpcSender<(Vec<(String, Option<String>)>, usize)>,
+        TopLevelBrowsingContextId,
+        ServoUrl,
+        StorageType,
+        Vec<StorageMutation>,
+    ),
+
+    /// copies the session storage data of the origin of the url from a top-level browsing
+    /// context to the one it opened
+    CloneSessionStorage(
+        TopLevelBrowsingContextId,
+        TopLevelBrowsingContextId,
+        ServoUrl,
+    ),
+
+    /// gets the session storage data of a top-level browsing context, by origin
+    GetSessionStorage(
+        IpcSender<BTreeMap<String, BTreeMap<String, String>>>,
+        TopLevelBrowsingContextId,
+    ),
+
+    /// replaces the session storage data of a top-level browsing context, which is restored
+    /// from a saved session
+    SetSessionStorage(
+        TopLevelBrowsingContextId,
+        BTreeMap<String, BTreeMap<String, String>>,
+    ),
+
+    /// discards the session storage data of a closed top-level browsing context
+    DiscardSessionStorage(TopLevelBrowsingContextId),
+
+    /// gets whether an origin has a cache with a name
+    HasCache(IpcSender<bool>, ImmutableOrigin, String),
+
+    /// gets the cache of an origin with a name, which is created if there is none
+    OpenCache(IpcSender<CacheId>, ImmutableOrigin, String),
+
+    /// deletes the cache of an origin with a name, and gets whether there was one
+    DeleteCache(IpcSender<bool>, ImmutableOrigin, String),
 
-    /// clears the associated storage data by removing all the key/value pairs
-    Clear(IpcSender<bool>, ServoUrl, StorageType),
+    /// gets the names of the caches of an origin, in the order of their creation
+    CacheNames(IpcSender<Vec<String>>, ImmutableOrigin),
+
+    /// gets the items of caches of an origin whose request matches a query, or all of their
+    /// items if there is no query
+    QueryCache(
+        IpcSender<Vec<(CacheRequest, CacheResponse)>>,
+        ImmutableOrigin,
+        CacheQueryTarget,
+        Option<CacheRequest>,
+        CacheQueryOptions,
+    ),
+
+    /// applies a batch of operations to a cache of an origin if all of them can be applied,
+    /// and gets whether any item was deleted
+    BatchCacheOperations(
+        IpcSender<Result<bool, CacheOperationError>>,
+        ImmutableOrigin,
+        CacheId,
+        Vec<CacheOperation>,
+    ),
 
     /// send a reply when done cleaning up thread resources and then shut it down
     Exit(IpcSender<()>),
@@ -2,7 +2,14 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use net_traits::image::base::detect_image_format;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use net_traits::image::base::{detect_image_format, exif_orientation, load_from_memory};
+use net_traits::image::base::{ImageOrientation, PartialImageDecoder, MAX_ANIMATION_BYTES};
+use net_traits::image::icc::ColorProfile;
+use net_traits::image_cache::CorsStatus;
+use std::io::Write;
+use std::time::Duration;
 
 #[test]
 fn test_supported_images() {
@@ -22,3 +29,482 @@ fn test_supported_images() {
     assert!(detect_image_format(&ico).is_ok());
     assert!(detect_image_format(&junk_format).is_err());
 }
+
+/// EXIF metadata with the given Orientation tag, in big-endian or little-endian TIFF.
+fn exif(orientation: u8, big_endian: bool) -> Vec<u8> {
+    if big_endian {
+        vec![
+            b'M',
+            b'M',
+            0,
+            42,
+            0,
+            0,
+            0,
+            8,
+            0,
+            1,
+            0x01,
+            0x12,
+            0,
+            3,
+            0,
+            0,
+            0,
+            1,
+            0,
+            orientation,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]
+    } else {
+        vec![
+            b'I',
+            b'I',
+            42,
+            0,
+            8,
+            0,
+            0,
+            0,
+            1,
+            0,
+            0x12,
+            0x01,
+            3,
+            0,
+            1,
+            0,
+            0,
+            0,
+            orientation,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]
+    }
+}
+
+#[test]
+fn test_exif_orientation() {
+    let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0, 0xff, 0xe1, 0, 34];
+    jpeg.extend_from_slice(b"Exif\0\0");
+    jpeg.extend(exif(6, true));
+    jpeg.extend_from_slice(&[0xff, 0xda]);
+    assert_eq!(exif_orientation(&jpeg), ImageOrientation::Rotate90);
+
+    let mut png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 26];
+    png.extend_from_slice(b"eXIf");
+    png.extend(exif(3, false));
+    assert_eq!(exif_orientation(&png), ImageOrientation::Rotate180);
+
+    let mut png_without_exif = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 26];
+    png_without_exif.extend_from_slice(b"IDAT");
+    png_without_exif.extend(exif(3, false));
+    assert_eq!(
+        exif_orientation(&png_without_exif),
+        ImageOrientation::Normal
+    );
+
+    let truncated_jpeg = [0xff, 0xd8, 0xff, 0xe1, 0, 34, b'E', b'x'];
+    assert_eq!(exif_orientation(&truncated_jpeg), ImageOrientation::Normal);
+}
+
+#[test]
+fn test_image_orientation() {
+    // A 3x2 image whose pixels are one byte long.
+    let pixels = [0, 1, 2, 3, 4, 5];
+    let (rotated, width, height) = ImageOrientation::Rotate90.apply(&pixels, 3, 2, 1);
+    assert_eq!((width, height), (2, 3));
+    assert_eq!(rotated, [3, 0, 4, 1, 5, 2]);
+    let (flipped, width, height) = ImageOrientation::FlipVertical.apply(&pixels, 3, 2, 1);
+    assert_eq!((width, height), (3, 2));
+    assert_eq!(flipped, [3, 4, 5, 0, 1, 2]);
+    let (unrotated, width, height) = ImageOrientation::Rotate90
+        .inverse()
+        .apply(&rotated, 2, 3, 1);
+    assert_eq!((width, height), (3, 2));
+    assert_eq!(unrotated, pixels);
+}
+
+/// An ICC profile of the given data color space with the given tags.
+fn icc_profile(color_space: &[u8], tags: &[(&[u8], &[u8])]) -> Vec<u8> {
+    let mut profile = vec![0; 128];
+    profile[16..20].copy_from_slice(color_space);
+    profile.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+    let mut offset = 132 + tags.len() * 12;
+    for &(signature, data) in tags {
+        profile.extend_from_slice(signature);
+        profile.extend_from_slice(&(offset as u32).to_be_bytes());
+        profile.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len();
+    }
+    for &(_, data) in tags {
+        profile.extend_from_slice(data);
+    }
+    profile
+}
+
+#[test]
+fn test_color_profiles() {
+    // A gray profile whose values are linear.
+    let linear_curve = [b'c', b'u', b'r', b'v', 0, 0, 0, 0, 0, 0, 0, 0];
+    let profile = icc_profile(b"GRAY", &[(b"kTRC", &linear_curve)]);
+    let mut pixels = [0, 0, 0, 255, 128, 128, 128, 128, 255, 255, 255, 0];
+    ColorProfile::parse(&profile)
+        .unwrap()
+        .convert_to_srgb(&mut pixels);
+    assert_eq!(pixels, [0, 0, 0, 255, 188, 188, 188, 128, 255, 255, 255, 0]);
+
+    // The profiles which are only described by lookup tables are not supported.
+    let profile = icc_profile(b"CMYK", &[(b"A2B0", b"mft2")]);
+    assert!(ColorProfile::parse(&profile).is_none());
+    let profile = icc_profile(b"RGB ", &[(b"A2B0", b"mAB ")]);
+    assert!(ColorProfile::parse(&profile).is_none());
+}
+
+/// A GIF image of the given size, whose frames each draw one pixel and last 100ms.
+fn gif(width: u16, height: u16, frames: usize) -> Vec<u8> {
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend_from_slice(&width.to_le_bytes());
+    gif.extend_from_slice(&height.to_le_bytes());
+    // A global color table of two colors, black and white.
+    gif.extend_from_slice(&[0x80, 0, 0, 0, 0, 0, 255, 255, 255]);
+    for _ in 0..frames {
+        // The graphic control extension, with a delay of 10 hundredths of a second.
+        gif.extend_from_slice(&[0x21, 0xf9, 4, 0, 10, 0, 0, 0]);
+        // The image descriptor of a 1x1 frame, and its LZW-encoded pixel.
+        gif.extend_from_slice(&[0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0]);
+        gif.extend_from_slice(&[2, 2, 0x44, 1, 0]);
+    }
+    gif.push(0x3b);
+    gif
+}
+
+#[test]
+fn test_animated_gif() {
+    let image = load_from_memory(&gif(2, 3, 3), CorsStatus::Safe).unwrap();
+    assert_eq!((image.width, image.height), (2, 3));
+    assert_eq!(image.bytes.len(), 2 * 3 * 4);
+    let animation = image.animation.unwrap();
+    assert_eq!(animation.frames.len(), 3);
+    for (index, frame) in animation.frames.iter().enumerate() {
+        assert_eq!(frame.delay, Duration::from_millis(100));
+        assert_eq!(animation.frame_bytes(index).len(), 2 * 3 * 4);
+    }
+
+    let image = load_from_memory(&gif(2, 3, 1), CorsStatus::Safe).unwrap();
+    assert!(image.animation.is_none());
+}
+
+#[test]
+fn test_animated_gif_too_large() {
+    // Only two of the frames fit within the limit, so the image is not animated.
+    let frame_size = 4096 * 4096 * 4;
+    assert!(frame_size * 3 > MAX_ANIMATION_BYTES);
+    let image = load_from_memory(&gif(4096, 4096, 3), CorsStatus::Safe).unwrap();
+    assert_eq!(image.bytes.len(), frame_size);
+    assert!(image.animation.is_none());
+}
+
+/// A PNG chunk of the given type, with its length and its checksum.
+fn png_chunk(chunk_type: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(chunk_type);
+    chunk.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(&chunk[4..]);
+    chunk.extend_from_slice(&crc.sum().to_be_bytes());
+    chunk
+}
+
+/// A non-interlaced RGB PNG image whose rows are filtered with each of the filter types
+/// in turn, and whose image data are split in IDAT chunks of the given size.
+fn png(width: u32, height: u32, idat_size: usize) -> (Vec<u8>, Vec<u8>) {
+    let pixels: Vec<u8> = (0..width * height * 3)
+        .map(|i| (i * 7 % 251) as u8)
+        .collect();
+    let row_size = width as usize * 3;
+    let mut filtered = vec![];
+    for (y, row) in pixels.chunks(row_size).enumerate() {
+        let filter_type = (y % 5) as u8;
+        filtered.push(filter_type);
+        for x in 0..row_size {
+            let left = if x >= 3 { row[x - 3] } else { 0 };
+            let up = if y > 0 {
+                pixels[(y - 1) * row_size + x]
+            } else {
+                0
+            };
+            let up_left = if x >= 3 && y > 0 {
+                pixels[(y - 1) * row_size + x - 3]
+            } else {
+                0
+            };
+            let predictor = match filter_type {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                _ => {
+                    let estimate = left as i16 + up as i16 - up_left as i16;
+                    let (a, b, c) = (
+                        (estimate - left as i16).abs(),
+                        (estimate - up as i16).abs(),
+                        (estimate - up_left as i16).abs(),
+                    );
+                    if a <= b && a <= c {
+                        left
+                    } else if b <= c {
+                        up
+                    } else {
+                        up_left
+                    }
+                },
+            };
+            filtered.push(row[x].wrapping_sub(predictor));
+        }
+    }
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    encoder.write_all(&filtered).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let mut header = width.to_be_bytes().to_vec();
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    png.extend(png_chunk(b"IHDR", &header));
+    for data in compressed.chunks(idat_size) {
+        png.extend(png_chunk(b"IDAT", data));
+    }
+    png.extend(png_chunk(b"IEND", &[]));
+    (png, pixels)
+}
+
+#[test]
+fn test_partial_png_decoding() {
+    let (width, height) = (64, 64);
+    let (png, pixels) = png(width, height, 1000);
+    let mut decoder = PartialImageDecoder::default();
+    let mut decoded_rows = 0;
+    for bytes in png.chunks(500) {
+        decoder.push(bytes);
+        let (image, rows) = match decoder.decode(CorsStatus::Safe) {
+            Some(result) => result,
+            None => continue,
+        };
+        // Only the rows decoded since the last decoding are reported.
+        assert_eq!(rows.start, decoded_rows);
+        assert!(rows.end > rows.start);
+        decoded_rows = rows.end;
+
+        assert_eq!((image.width, image.height), (width, height));
+        let row_size = width as usize * 4;
+        for (y, row) in image.bytes.chunks(row_size).enumerate() {
+            for (x, pixel) in row.chunks(4).enumerate() {
+                if y < decoded_rows as usize {
+                    let rgb = &pixels[(y * width as usize + x) * 3..][..3];
+                    assert_eq!(pixel, &[rgb[2], rgb[1], rgb[0], 0xff][..]);
+                } else {
+                    assert_eq!(pixel, &[0, 0, 0, 0][..]);
+                }
+            }
+        }
+    }
+    assert_eq!(decoded_rows, height);
+
+    let image = load_from_memory(&png, CorsStatus::Safe).unwrap();
+    assert_eq!((image.width, image.height), (width, height));
+}
+
+/// The zlib-compressed data of an RGBA image with unfiltered rows of the given pixel.
+fn png_rgba_data(width: usize, height: usize, pixel: [u8; 4]) -> Vec<u8> {
+    let mut filtered = vec![];
+    for _ in 0..height {
+        filtered.push(0);
+        for _ in 0..width {
+            filtered.extend_from_slice(&pixel);
+        }
+    }
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    encoder.write_all(&filtered).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// An fcTL chunk with the given size and offset, disposal and blending, which lasts 50ms.
+fn apng_frame_control(
+    sequence_number: u32,
+    region: [u32; 4],
+    dispose_op: u8,
+    blend_op: u8,
+) -> Vec<u8> {
+    let mut control = sequence_number.to_be_bytes().to_vec();
+    for value in &region {
+        control.extend_from_slice(&value.to_be_bytes());
+    }
+    control.extend_from_slice(&[0, 1, 0, 20, dispose_op, blend_op]);
+    png_chunk(b"fcTL", &control)
+}
+
+#[test]
+fn test_animated_png() {
+    let red = [255, 0, 0, 255];
+    let translucent_blue = [0, 0, 255, 0x80];
+    let mut png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let mut header = 2u32.to_be_bytes().to_vec();
+    header.extend_from_slice(&2u32.to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    png.extend(png_chunk(b"IHDR", &header));
+    png.extend(png_chunk(b"acTL", &[0, 0, 0, 3, 0, 0, 0, 0]));
+    // The default image is the first frame, which is red, and whose region is cleared
+    // once it has been shown.
+    png.extend(apng_frame_control(0, [2, 2, 0, 0], 1, 0));
+    png.extend(png_chunk(b"IDAT", &png_rgba_data(2, 2, red)));
+    // The second frame draws the bottom right pixel over the previous frame.
+    png.extend(apng_frame_control(1, [1, 1, 1, 1], 0, 1));
+    let mut frame_data = 2u32.to_be_bytes().to_vec();
+    frame_data.extend(png_rgba_data(1, 1, translucent_blue));
+    png.extend(png_chunk(b"fdAT", &frame_data));
+    // The third frame draws the top left pixel over the second frame.
+    png.extend(apng_frame_control(3, [1, 1, 0, 0], 0, 1));
+    let mut frame_data = 4u32.to_be_bytes().to_vec();
+    frame_data.extend(png_rgba_data(1, 1, red));
+    png.extend(png_chunk(b"fdAT", &frame_data));
+    png.extend(png_chunk(b"IEND", &[]));
+
+    let image = load_from_memory(&png, CorsStatus::Safe).unwrap();
+    assert_eq!((image.width, image.height), (2, 2));
+    let red_bgra = [0, 0, 255, 255];
+    assert_eq!(&*image.bytes, &red_bgra.repeat(4)[..]);
+    let animation = image.animation.unwrap();
+    assert_eq!(animation.frames.len(), 3);
+    for frame in &animation.frames {
+        assert_eq!(frame.delay, Duration::from_millis(50));
+    }
+    let blue_bgra = [255, 0, 0, 0x80];
+    let transparent = [0, 0, 0, 0];
+    assert_eq!(
+        animation.frame_bytes(1),
+        &[transparent, transparent, transparent, blue_bgra].concat()[..]
+    );
+    assert_eq!(
+        animation.frame_bytes(2),
+        &[red_bgra, transparent, transparent, blue_bgra].concat()[..]
+    );
+}
+
+/// An Adam7-interlaced RGB PNG image with unfiltered rows and hardly compressible pixels.
+fn interlaced_png(width: u32, height: u32) -> (Vec<u8>, Vec<u8>) {
+    let pixels: Vec<u8> = (0..width * height * 3)
+        .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+        .collect();
+    let passes = [
+        (0, 0, 8, 8),
+        (4, 0, 8, 8),
+        (0, 4, 4, 8),
+        (2, 0, 4, 4),
+        (0, 2, 2, 4),
+        (1, 0, 2, 2),
+        (0, 1, 1, 2),
+    ];
+    let mut filtered = vec![];
+    for &(x, y, column_spacing, row_spacing) in &passes {
+        for row in (y..height).step_by(row_spacing) {
+            let columns: Vec<u32> = (x..width).step_by(column_spacing).collect();
+            if columns.is_empty() {
+                continue;
+            }
+            filtered.push(0);
+            for column in columns {
+                let start = (row * width + column) as usize * 3;
+                filtered.extend_from_slice(&pixels[start..start + 3]);
+            }
+        }
+    }
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    encoder.write_all(&filtered).unwrap();
+
+    let mut png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let mut header = width.to_be_bytes().to_vec();
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 1]);
+    png.extend(png_chunk(b"IHDR", &header));
+    png.extend(png_chunk(b"IDAT", &encoder.finish().unwrap()));
+    png.extend(png_chunk(b"IEND", &[]));
+    (png, pixels)
+}
+
+#[test]
+fn test_partial_interlaced_png_decoding() {
+    let (width, height) = (64, 64);
+    let (png, pixels) = interlaced_png(width, height);
+    let mut decoder = PartialImageDecoder::default();
+    let mut images = vec![];
+    for bytes in png.chunks(100) {
+        decoder.push(bytes);
+        if let Some((image, rows)) = decoder.decode(CorsStatus::Safe) {
+            assert!(rows.end > rows.start);
+            images.push(image.bytes);
+        }
+    }
+    let expected: Vec<u8> = pixels
+        .chunks(3)
+        .flat_map(|rgb| vec![rgb[2], rgb[1], rgb[0], 0xff])
+        .collect();
+    let pixel = |bytes: &[u8], x: usize, y: usize| {
+        let start = (y * width as usize + x) * 4;
+        bytes[start..start + 4].to_vec()
+    };
+
+    // The pixels of the first passes fill the blocks of the pixels of the next passes.
+    assert_eq!(pixel(&*images[0], 0, 0), &expected[..4]);
+    assert_eq!(pixel(&*images[0], 3, 3), &expected[..4]);
+
+    // The whole image is shown before the last passes are decoded.
+    let first_opaque_image = images
+        .iter()
+        .position(|bytes| bytes.chunks(4).all(|pixel| pixel[3] == 0xff))
+        .unwrap();
+    assert!(first_opaque_image < images.len() - 1);
+    assert_ne!(&*images[first_opaque_image], &expected[..]);
+    assert_eq!(&*images[images.len() - 1], &expected[..]);
+
+    let image = load_from_memory(&png, CorsStatus::Safe).unwrap();
+    assert_eq!(&*image.bytes, &expected[..]);
+}
+
+#[test]
+fn test_partial_progressive_jpeg_decoding() {
+    let jpeg = include_bytes!("../../../tests/wpt/mozilla/tests/css/jpeg_progressive.jpg");
+    let complete = load_from_memory(jpeg, CorsStatus::Safe).unwrap();
+
+    // The first scans describe the whole image, with fewer details.
+    let mut decoder = PartialImageDecoder::default();
+    decoder.push(&jpeg[..300]);
+    let (image, rows) = decoder.decode(CorsStatus::Safe).unwrap();
+    assert_eq!(
+        (image.width, image.height),
+        (complete.width, complete.height)
+    );
+    assert_eq!(rows, 0..complete.height);
+    assert!(decoder.decode(CorsStatus::Safe).is_none());
+
+    decoder.push(&jpeg[300..]);
+    let (image, _) = decoder.decode(CorsStatus::Safe).unwrap();
+    assert_eq!(&*image.bytes, &*complete.bytes);
+}
+
+#[test]
+fn test_partial_decoding_of_unsupported_images() {
+    let mut decoder = PartialImageDecoder::default();
+    decoder.push(&[0x42, 0x4D, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert!(decoder.decode(CorsStatus::Safe).is_none());
+}
@@ -2,7 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use net_traits::pub_domains::{is_pub_domain, is_reg_domain, pub_suffix, reg_suffix};
+use net_traits::pub_domains::{is_pub_domain, is_reg_domain, is_same_site, pub_suffix, reg_suffix};
+use servo_url::{ImmutableOrigin, ServoUrl};
 
 // These tests may need to be updated if the PSL changes.
 
@@ -120,3 +121,31 @@ fn test_weirdness() {
     );
     assert!(!is_pub_domain(pub_suffix("city.yokohama.jp")));
 }
+
+#[test]
+fn test_is_same_site() {
+    let origin = |url: &str| ServoUrl::parse(url).unwrap().origin();
+    assert!(is_same_site(
+        &origin("https://www.example.com/"),
+        &origin("https://images.example.com:8443/")
+    ));
+    assert!(is_same_site(
+        &origin("http://127.0.0.1/"),
+        &origin("http://127.0.0.1:8000/")
+    ));
+    assert!(!is_same_site(
+        &origin("http://example.com/"),
+        &origin("https://example.com/")
+    ));
+    assert!(!is_same_site(
+        &origin("https://foo.yokohama.jp/"),
+        &origin("https://bar.yokohama.jp/")
+    ));
+    assert!(!is_same_site(
+        &origin("https://example.com/"),
+        &origin("https://example.org/")
+    ));
+    let opaque = ImmutableOrigin::new_opaque();
+    assert!(is_same_site(&opaque, &opaque));
+    assert!(!is_same_site(&opaque, &ImmutableOrigin::new_opaque()));
+}
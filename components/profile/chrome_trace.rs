@@ -0,0 +1,135 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A module for writing the times of the time profiler out as a trace in the
+//! [Trace Event Format] of Chrome, which Perfetto and `chrome://tracing` can load.
+//!
+//! The content processes report their times to the time profiler of the main process,
+//! with the same monotonic clock, so the trace has a track for each thread of each
+//! process, on a common timeline.
+//!
+//! [Trace Event Format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+
+use profile_traits::time::{ProfilerCategory, TimerMetadata, TraceThread};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process;
+
+/// An RAII class for writing the trace, which is closed on destruction.
+pub struct ChromeTrace {
+    file: BufWriter<File>,
+    /// Whether an event was written, after which the events are separated by commas.
+    has_events: bool,
+    /// The processes and threads which were named in the trace.
+    named_processes: HashSet<u32>,
+    named_threads: HashSet<(u32, u64)>,
+}
+
+impl ChromeTrace {
+    pub fn create(path: String) -> Option<ChromeTrace> {
+        let mut file = match File::create(&path) {
+            Ok(file) => BufWriter::new(file),
+            Err(e) => {
+                warn!("Failed to create the trace {}: {}", path, e);
+                return None;
+            },
+        };
+        if let Err(e) = writeln!(file, "[") {
+            warn!("Failed to write the trace {}: {}", path, e);
+            return None;
+        }
+        Some(ChromeTrace {
+            file: file,
+            has_events: false,
+            named_processes: HashSet::new(),
+            named_threads: HashSet::new(),
+        })
+    }
+
+    /// Write the time of one category as a complete event on the track of its thread.
+    pub fn write_one(
+        &mut self,
+        category: &(ProfilerCategory, Option<TimerMetadata>),
+        time: (u64, u64),
+        thread: &TraceThread,
+    ) {
+        self.name_thread(thread);
+        let mut event = json!({
+            "name": format!("{:?}", category.0),
+            "cat": category_group(category.0),
+            "ph": "X",
+            "ts": time.0 as f64 / 1000.,
+            "dur": time.1.saturating_sub(time.0) as f64 / 1000.,
+            "pid": thread.process_id,
+            "tid": thread.thread_id,
+        });
+        if let Some(ref metadata) = category.1 {
+            event["args"] = json!({ "url": metadata.url });
+        }
+        self.write_event(event);
+    }
+
+    /// Write the metadata events which name the process and the thread, the first time
+    /// that they appear in the trace.
+    fn name_thread(&mut self, thread: &TraceThread) {
+        if self.named_processes.insert(thread.process_id) {
+            let name = if thread.process_id == process::id() {
+                "Servo"
+            } else {
+                "Content process"
+            };
+            self.write_event(json!({
+                "name": "process_name",
+                "ph": "M",
+                "pid": thread.process_id,
+                "args": { "name": name },
+            }));
+        }
+        if self
+            .named_threads
+            .insert((thread.process_id, thread.thread_id))
+        {
+            if let Some(ref name) = thread.thread_name {
+                self.write_event(json!({
+                    "name": "thread_name",
+                    "ph": "M",
+                    "pid": thread.process_id,
+                    "tid": thread.thread_id,
+                    "args": { "name": name },
+                }));
+            }
+        }
+    }
+
+    fn write_event(&mut self, event: Value) {
+        let separator = if self.has_events { "," } else { "" };
+        self.has_events = true;
+        if let Err(e) = writeln!(self.file, "{}{}", separator, event) {
+            warn!("Failed to write the trace: {}", e);
+        }
+    }
+}
+
+impl Drop for ChromeTrace {
+    fn drop(&mut self) {
+        let _ = writeln!(self.file, "]");
+    }
+}
+
+/// The category of the trace events of a profiler category, which the trace viewers
+/// can filter by.
+fn category_group(category: ProfilerCategory) -> &'static str {
+    match category as u32 >> 4 {
+        0x0 => "compositing",
+        0x1 => "layout",
+        0x3 => "net",
+        0x4 => "painting",
+        0x5 => "image",
+        0x6 | 0x7 => "script",
+        0x8 => "metrics",
+        _ => "other",
+    }
+}
@@ -11,6 +11,7 @@ extern crate profile_traits;
 #[macro_use]
 extern crate serde;
 
+pub mod chrome_trace;
 #[allow(unsafe_code)]
 mod heartbeats;
 #[allow(unsafe_code)]
@@ -4,6 +4,7 @@
 
 //! Timing functions.
 
+use crate::chrome_trace::ChromeTrace;
 use crate::heartbeats;
 use crate::trace_dump::TraceDump;
 use influent::client::{Client, Credentials};
@@ -12,17 +13,19 @@ use influent::measurement::{Measurement, Value};
 use ipc_channel::ipc::{self, IpcReceiver};
 use profile_traits::energy::{energy_interval_ms, read_energy_uj};
 use profile_traits::time::{
-    ProfilerCategory, ProfilerChan, ProfilerData, ProfilerMsg, TimerMetadata,
+    ProfilerCategory, ProfilerChan, ProfilerData, ProfilerMarker, ProfilerMsg, TimerMetadata,
 };
-use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
+use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType, TRACE_ENV_VAR};
 use servo_config::opts::OutputOptions;
+use servo_config::pref;
 use std::borrow::ToOwned;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::env;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{f64, thread, u32, u64};
 use time_crate::precise_time_ns;
 use tokio::prelude::Future;
@@ -116,6 +119,9 @@ impl Formattable for ProfilerCategory {
             ProfilerCategory::LayoutParallelWarmup => "Parallel Warmup",
             ProfilerCategory::LayoutDispListBuild => "Display List Construction",
             ProfilerCategory::NetHTTPRequestResponse => "Network HTTP Request/Response",
+            ProfilerCategory::NetFetchRedirect => "Fetch Redirect",
+            ProfilerCategory::NetFetchRequest => "Fetch Request",
+            ProfilerCategory::NetFetchResponse => "Fetch Response",
             ProfilerCategory::PaintingPerTile => "Painting Per Tile",
             ProfilerCategory::PaintingPrepBuff => "Buffer Prep",
             ProfilerCategory::Painting => "Painting",
@@ -162,8 +168,68 @@ impl Formattable for ProfilerCategory {
     }
 }
 
+/// The path of the trace of the `SERVO_TRACE` environment variable, or of the
+/// `profiler.trace.path` pref if it is empty or the `profiler.trace.enabled` pref is set.
+fn chrome_trace_path() -> Option<String> {
+    match env::var(TRACE_ENV_VAR) {
+        Ok(ref path) if !path.is_empty() => Some(path.clone()),
+        Ok(_) => Some(pref!(profiler.trace.path)),
+        Err(_) if pref!(profiler.trace.enabled) => Some(pref!(profiler.trace.path)),
+        Err(_) => None,
+    }
+}
+
 type ProfilerBuckets = BTreeMap<(ProfilerCategory, Option<TimerMetadata>), Vec<f64>>;
 
+/// The maximum number of markers kept while recording, after which the oldest ones
+/// are discarded.
+const MAX_MARKERS: usize = 100_000;
+
+/// Records the reported times as markers for the sampling profiler, whether or not
+/// the time profiler is printing.
+#[derive(Default)]
+pub struct MarkerRecorder {
+    /// The start of the recording, as reported by `precise_time_ns` and in
+    /// milliseconds since the epoch.
+    origin: Option<(u64, f64)>,
+    markers: VecDeque<ProfilerMarker>,
+}
+
+impl MarkerRecorder {
+    pub fn handle_msg(&mut self, msg: &ProfilerMsg) {
+        match *msg {
+            ProfilerMsg::Time((category, ref meta), (start, end), _, _) => {
+                let (origin_ns, origin_ms) = match self.origin {
+                    Some(origin) => origin,
+                    None => return,
+                };
+                let to_ms = |ns: u64| origin_ms + (ns as f64 - origin_ns as f64) / 1000000f64;
+                if self.markers.len() == MAX_MARKERS {
+                    self.markers.pop_front();
+                }
+                self.markers.push_back(ProfilerMarker {
+                    category: category,
+                    url: meta.as_ref().map(|meta| meta.url.clone()),
+                    start: to_ms(start),
+                    end: to_ms(end),
+                });
+            },
+            ProfilerMsg::StartRecordingMarkers => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                self.origin = Some((precise_time_ns(), now.as_secs_f64() * 1000f64));
+                self.markers.clear();
+            },
+            ProfilerMsg::StopRecordingMarkers(ref sender) => {
+                self.origin = None;
+                let _ = sender.send(self.markers.drain(..).collect());
+            },
+            _ => {},
+        }
+    }
+}
+
 // back end of the profiler that handles data aggregation and performance metrics
 pub struct Profiler {
     pub port: IpcReceiver<ProfilerMsg>,
@@ -171,8 +237,10 @@ pub struct Profiler {
     output: Option<OutputOptions>,
     pub last_msg: Option<ProfilerMsg>,
     trace: Option<TraceDump>,
+    chrome_trace: Option<ChromeTrace>,
     blocked_layout_queries: HashMap<String, u32>,
     profile_heartbeats: bool,
+    markers: MarkerRecorder,
 }
 
 impl Profiler {
@@ -182,6 +250,7 @@ impl Profiler {
         profile_heartbeats: bool,
     ) -> ProfilerChan {
         let (chan, port) = ipc::channel().unwrap();
+        let chrome_trace_path = chrome_trace_path();
         match *output {
             Some(ref option) => {
                 // Spawn the time profiler thread
@@ -190,8 +259,14 @@ impl Profiler {
                     .name("Time profiler".to_owned())
                     .spawn(move || {
                         let trace = file_path.as_ref().and_then(|p| TraceDump::new(p).ok());
-                        let mut profiler =
-                            Profiler::new(port, trace, Some(outputoption), profile_heartbeats);
+                        let chrome_trace = chrome_trace_path.and_then(ChromeTrace::create);
+                        let mut profiler = Profiler::new(
+                            port,
+                            trace,
+                            chrome_trace,
+                            Some(outputoption),
+                            profile_heartbeats,
+                        );
                         profiler.start();
                     })
                     .expect("Thread spawning failed");
@@ -217,28 +292,34 @@ impl Profiler {
             },
             None => {
                 // this is when the -p option hasn't been specified
-                if file_path.is_some() {
+                if file_path.is_some() || chrome_trace_path.is_some() {
                     // Spawn the time profiler
                     thread::Builder::new()
                         .name("Time profiler".to_owned())
                         .spawn(move || {
                             let trace = file_path.as_ref().and_then(|p| TraceDump::new(p).ok());
-                            let mut profiler = Profiler::new(port, trace, None, profile_heartbeats);
+                            let chrome_trace = chrome_trace_path.and_then(ChromeTrace::create);
+                            let mut profiler =
+                                Profiler::new(port, trace, chrome_trace, None, profile_heartbeats);
                             profiler.start();
                         })
                         .expect("Thread spawning failed");
                 } else {
-                    // No-op to handle messages when the time profiler is not printing:
+                    // No-op to handle messages when the time profiler is not printing,
+                    // besides recording markers:
                     thread::Builder::new()
                         .name("Time profiler".to_owned())
-                        .spawn(move || loop {
-                            match port.recv() {
-                                Err(_) => break,
-                                Ok(ProfilerMsg::Exit(chan)) => {
-                                    let _ = chan.send(());
-                                    break;
-                                },
-                                _ => {},
+                        .spawn(move || {
+                            let mut markers = MarkerRecorder::default();
+                            loop {
+                                match port.recv() {
+                                    Err(_) => break,
+                                    Ok(ProfilerMsg::Exit(chan)) => {
+                                        let _ = chan.send(());
+                                        break;
+                                    },
+                                    Ok(msg) => markers.handle_msg(&msg),
+                                }
                             }
                         })
                         .expect("Thread spawning failed");
@@ -292,6 +373,7 @@ impl Profiler {
                             (ProfilerCategory::ApplicationHeartbeat, None),
                             (start_time, end_time),
                             (start_energy, end_energy),
+                            None,
                         )) {
                             return;
                         }
@@ -308,6 +390,7 @@ impl Profiler {
     pub fn new(
         port: IpcReceiver<ProfilerMsg>,
         trace: Option<TraceDump>,
+        chrome_trace: Option<ChromeTrace>,
         output: Option<OutputOptions>,
         profile_heartbeats: bool,
     ) -> Profiler {
@@ -317,8 +400,10 @@ impl Profiler {
             output: output,
             last_msg: None,
             trace: trace,
+            chrome_trace: chrome_trace,
             blocked_layout_queries: HashMap::new(),
             profile_heartbeats,
+            markers: MarkerRecorder::default(),
         }
     }
 
@@ -335,12 +420,16 @@ impl Profiler {
     }
 
     fn handle_msg(&mut self, msg: ProfilerMsg) -> bool {
+        self.markers.handle_msg(&msg);
         match msg.clone() {
-            ProfilerMsg::Time(k, t, e) => {
+            ProfilerMsg::Time(k, t, e, thread) => {
                 heartbeats::maybe_heartbeat(&k.0, t.0, t.1, e.0, e.1, self.profile_heartbeats);
                 if let Some(ref mut trace) = self.trace {
                     trace.write_one(&k, t, e);
                 }
+                if let (Some(chrome_trace), Some(thread)) = (self.chrome_trace.as_mut(), thread) {
+                    chrome_trace.write_one(&k, t, &thread);
+                }
                 let ms = (t.1 - t.0) as f64 / 1000000f64;
                 self.find_or_insert(k, ms);
             },
@@ -362,9 +451,12 @@ impl Profiler {
             ProfilerMsg::BlockedLayoutQuery(url) => {
                 *self.blocked_layout_queries.entry(url).or_insert(0) += 1;
             },
+            ProfilerMsg::StartRecordingMarkers | ProfilerMsg::StopRecordingMarkers(_) => {},
             ProfilerMsg::Exit(chan) => {
                 heartbeats::cleanup();
                 self.print_buckets();
+                // Close the trace before the main process exits.
+                self.chrome_trace.take();
                 let _ = chan.send(());
                 return false;
             },
@@ -0,0 +1,261 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The profiles of the sampling profiler, in the [format] of the Gecko profiler, which
+//! the [Firefox Profiler] and the performance panel of the devtools can load.
+//!
+//! [format]: https://github.com/firefox-devtools/profiler/blob/master/docs-developer/gecko-profile-format.md
+//! [Firefox Profiler]: https://profiler.firefox.com
+
+use crate::time::{ProfilerCategory, ProfilerMarker};
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GeckoProfile {
+    pub meta: GeckoMeta,
+    pub libs: Vec<()>,
+    pub threads: Vec<GeckoThread>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeckoMeta {
+    /// The sampling interval, in milliseconds.
+    pub interval: f64,
+    pub process_type: u32,
+    pub product: String,
+    pub stackwalk: u32,
+    /// The start of the profile, in milliseconds since the epoch. The times of the
+    /// samples and markers are relative to it.
+    pub start_time: f64,
+    pub version: u32,
+    pub presymbolicated: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GeckoTable<S, T> {
+    pub schema: S,
+    pub data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MarkerSchema {
+    pub name: usize,
+    pub time: usize,
+    pub data: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SampleSchema {
+    pub stack: usize,
+    pub time: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrameSchema {
+    pub location: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StackSchema {
+    pub frame: usize,
+    pub prefix: usize,
+}
+
+/// The payload of an interval marker.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerData {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub name: String,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeckoThread {
+    pub name: String,
+    pub tid: u32,
+    /// The markers, as the index of their name in the string table, their time and
+    /// their payload.
+    pub markers: GeckoTable<MarkerSchema, (usize, f64, MarkerData)>,
+    /// The samples, as the index of their stack and their time.
+    pub samples: GeckoTable<SampleSchema, (Option<usize>, f64)>,
+    /// The frames, as the index of their function name in the string table.
+    pub frame_table: GeckoTable<FrameSchema, (usize,)>,
+    /// The stacks, as the index of their youngest frame and of the stack of its caller.
+    pub stack_table: GeckoTable<StackSchema, (usize, Option<usize>)>,
+    pub string_table: Vec<String>,
+    /// The indices of the strings, frames and stacks, when building the thread.
+    #[serde(skip)]
+    strings: HashMap<String, usize>,
+    #[serde(skip)]
+    frames: HashMap<usize, usize>,
+    #[serde(skip)]
+    stacks: HashMap<(usize, Option<usize>), usize>,
+}
+
+impl GeckoProfile {
+    /// Create an empty profile starting at the given time, in milliseconds since the
+    /// epoch, and sampled at the given interval in milliseconds.
+    pub fn new(start_time: f64, interval: f64) -> GeckoProfile {
+        GeckoProfile {
+            meta: GeckoMeta {
+                interval: interval,
+                process_type: 0,
+                product: "Servo".to_owned(),
+                stackwalk: 1,
+                start_time: start_time,
+                version: 4,
+                presymbolicated: true,
+            },
+            libs: vec![],
+            threads: vec![],
+        }
+    }
+
+    /// Get the thread with the given name, creating it if needed.
+    pub fn thread(&mut self, name: &str) -> &mut GeckoThread {
+        match self.threads.iter().position(|thread| thread.name == name) {
+            Some(index) => &mut self.threads[index],
+            None => {
+                let tid = self.threads.len() as u32 + 1;
+                self.threads.push(GeckoThread::new(name.to_owned(), tid));
+                self.threads.last_mut().unwrap()
+            },
+        }
+    }
+
+    /// Add the threads of another profile, moving their samples and markers to be
+    /// relative to the start of this one.
+    pub fn add_threads(&mut self, other: GeckoProfile) {
+        let offset = other.meta.start_time - self.meta.start_time;
+        for mut thread in other.threads {
+            thread.tid = self.threads.len() as u32 + 1;
+            for sample in &mut thread.samples.data {
+                sample.1 += offset;
+            }
+            for marker in &mut thread.markers.data {
+                marker.1 += offset;
+                marker.2.start_time += offset;
+                marker.2.end_time += offset;
+            }
+            self.threads.push(thread);
+        }
+    }
+
+    /// Add the times reported to the time profiler as markers, in a thread for each
+    /// subsystem of their category.
+    pub fn add_markers(&mut self, markers: Vec<ProfilerMarker>) {
+        let start_time = self.meta.start_time;
+        for marker in markers {
+            let name = match marker.url {
+                Some(ref url) => format!("{:?} {}", marker.category, url),
+                None => format!("{:?}", marker.category),
+            };
+            let data = MarkerData {
+                type_: "Text".to_owned(),
+                name: name.clone(),
+                start_time: marker.start - start_time,
+                end_time: marker.end - start_time,
+            };
+            self.thread(subsystem_name(marker.category))
+                .add_marker(&name, data);
+        }
+    }
+}
+
+/// The name of the marker thread of the given category.
+fn subsystem_name(category: ProfilerCategory) -> &'static str {
+    match (category as u32) >> 4 {
+        0x0 => "Compositor markers",
+        0x1 => "Layout markers",
+        0x3 => "Network markers",
+        0x4 => "Painting markers",
+        0x5 => "Image markers",
+        0x6 | 0x7 => "Script markers",
+        _ => "Other markers",
+    }
+}
+
+impl GeckoThread {
+    pub fn new(name: String, tid: u32) -> GeckoThread {
+        GeckoThread {
+            name: name,
+            tid: tid,
+            markers: GeckoTable {
+                schema: MarkerSchema {
+                    name: 0,
+                    time: 1,
+                    data: 2,
+                },
+                data: vec![],
+            },
+            samples: GeckoTable {
+                schema: SampleSchema { stack: 0, time: 1 },
+                data: vec![],
+            },
+            frame_table: GeckoTable {
+                schema: FrameSchema { location: 0 },
+                data: vec![],
+            },
+            stack_table: GeckoTable {
+                schema: StackSchema {
+                    frame: 0,
+                    prefix: 1,
+                },
+                data: vec![],
+            },
+            string_table: vec![],
+            strings: HashMap::new(),
+            frames: HashMap::new(),
+            stacks: HashMap::new(),
+        }
+    }
+
+    fn string(&mut self, string: &str) -> usize {
+        if let Some(&index) = self.strings.get(string) {
+            return index;
+        }
+        let index = self.string_table.len();
+        self.string_table.push(string.to_owned());
+        self.strings.insert(string.to_owned(), index);
+        index
+    }
+
+    fn frame(&mut self, name: &str) -> usize {
+        let location = self.string(name);
+        let frames = &mut self.frame_table.data;
+        *self.frames.entry(location).or_insert_with(|| {
+            frames.push((location,));
+            frames.len() - 1
+        })
+    }
+
+    /// Add a sample taken at the given time, relative to the start of the profile,
+    /// with the given function names from the outermost frame.
+    pub fn add_sample<'a, I>(&mut self, time: f64, frames: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut prefix = None;
+        for name in frames {
+            let frame = self.frame(name);
+            let stacks = &mut self.stack_table.data;
+            prefix = Some(*self.stacks.entry((frame, prefix)).or_insert_with(|| {
+                stacks.push((frame, prefix));
+                stacks.len() - 1
+            }));
+        }
+        self.samples.data.push((prefix, time));
+    }
+
+    pub fn add_marker(&mut self, name: &str, data: MarkerData) {
+        let name = self.string(name);
+        let time = data.start_time;
+        self.markers.data.push((name, time, data));
+    }
+}
@@ -15,6 +15,7 @@ extern crate serde;
 
 #[allow(unsafe_code)]
 pub mod energy;
+pub mod gecko;
 pub mod ipc;
 pub mod mem;
 pub mod time;
@@ -203,6 +203,10 @@ pub enum ProfilerMsg {
     /// Triggers printing of the memory profiling metrics.
     Print,
 
+    /// Collects the reports of all the reporters, with the "explicit" ones nested in an
+    /// "explicit" path, and sends them back.
+    Report(IpcSender<Vec<Report>>),
+
     /// Tells the memory profiler to shut down.
     Exit,
 }
@@ -4,9 +4,19 @@
 
 use crate::energy::read_energy_uj;
 use ipc_channel::ipc::IpcSender;
-use servo_config::opts;
+use servo_config::{opts, pref};
+use std::cell::Cell;
+use std::env;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
 use time::precise_time_ns;
 
+/// The environment variable which enables the trace of the time profiler, like the
+/// `profiler.trace.enabled` pref. Its value is the path of the trace, or empty for
+/// the `profiler.trace.path` pref.
+pub const TRACE_ENV_VAR: &'static str = "SERVO_TRACE";
+
 #[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct TimerMetadata {
     pub url: String,
@@ -31,13 +41,51 @@ pub enum ProfilerData {
     Record(Vec<f64>),
 }
 
+/// The thread which a time was measured on, which is reported while tracing so that
+/// the trace has a track for each thread of each process.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TraceThread {
+    pub process_id: u32,
+    /// An id which is unique among the threads of the process.
+    pub thread_id: u64,
+    pub thread_name: Option<String>,
+}
+
+impl TraceThread {
+    pub fn current() -> TraceThread {
+        static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+        thread_local!(static THREAD_ID: Cell<u64> = Cell::new(0));
+
+        let thread_id = THREAD_ID.with(|thread_id| {
+            if thread_id.get() == 0 {
+                thread_id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+            }
+            thread_id.get()
+        });
+        TraceThread {
+            process_id: process::id(),
+            thread_id: thread_id,
+            thread_name: thread::current().name().map(str::to_owned),
+        }
+    }
+}
+
+/// Whether the time profiler writes a trace, in which case the times are reported
+/// with the thread which they were measured on.
+pub fn is_tracing() -> bool {
+    thread_local!(static TRACE_ENV_VAR_IS_SET: bool = env::var_os(TRACE_ENV_VAR).is_some());
+    TRACE_ENV_VAR_IS_SET.with(|is_set| *is_set) || pref!(profiler.trace.enabled)
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum ProfilerMsg {
-    /// Normal message used for reporting time
+    /// Normal message used for reporting time, with the thread it was measured on
+    /// while tracing.
     Time(
         (ProfilerCategory, Option<TimerMetadata>),
         (u64, u64),
         (u64, u64),
+        Option<TraceThread>,
     ),
     /// Message used to get time spend entries for a particular ProfilerBuckets (in nanoseconds)
     Get(
@@ -50,10 +98,27 @@ pub enum ProfilerMsg {
     /// Report a layout query that could not be processed immediately for a particular URL.
     BlockedLayoutQuery(String),
 
+    /// Start recording the reported times as markers for the sampling profiler.
+    StartRecordingMarkers,
+
+    /// Stop recording markers, and send the ones that were recorded.
+    StopRecordingMarkers(IpcSender<Vec<ProfilerMarker>>),
+
     /// Tells the profiler to shut down.
     Exit(IpcSender<()>),
 }
 
+/// A time reported to the profiler while recording markers.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProfilerMarker {
+    pub category: ProfilerCategory,
+    pub url: Option<String>,
+    /// The start of the reported time, in milliseconds since the epoch.
+    pub start: f64,
+    /// The end of the reported time, in milliseconds since the epoch.
+    pub end: f64,
+}
+
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum ProfilerCategory {
@@ -74,6 +139,9 @@ pub enum ProfilerCategory {
     LayoutParallelWarmup = 0x1d,
     LayoutDispListBuild = 0x1e,
     NetHTTPRequestResponse = 0x30,
+    NetFetchRedirect = 0x31,
+    NetFetchRequest = 0x32,
+    NetFetchResponse = 0x33,
     PaintingPerTile = 0x41,
     PaintingPrepBuff = 0x42,
     Painting = 0x43,
@@ -173,9 +241,15 @@ pub fn send_profile_data(
     start_energy: u64,
     end_energy: u64,
 ) {
+    let thread = if is_tracing() {
+        Some(TraceThread::current())
+    } else {
+        None
+    };
     profiler_chan.send(ProfilerMsg::Time(
         (category, meta),
         (start_time, end_time),
         (start_energy, end_energy),
+        thread,
     ));
 }
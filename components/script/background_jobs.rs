@@ -0,0 +1,50 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The threads shared by the event loops for the work that would block them, like the
+//! reads of file readers and the transforms of compression streams.
+
+use crossbeam_channel::{unbounded, Sender};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+/// The number of threads performing the background jobs of all event loops.
+const BACKGROUND_THREADS: usize = 4;
+
+/// Work performed by the background threads, and what to do if it panics.
+pub struct BackgroundJob {
+    pub run: Box<dyn FnOnce() + Send>,
+    pub failed: Box<dyn FnOnce() + Send>,
+}
+
+lazy_static! {
+    /// The queue of the background jobs, whose threads are started when the first job
+    /// is queued.
+    static ref BACKGROUND_JOB_QUEUE: Sender<BackgroundJob> = {
+        let (sender, receiver) = unbounded::<BackgroundJob>();
+        for index in 0..BACKGROUND_THREADS {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("BackgroundJob#{}", index))
+                .spawn(move || {
+                    while let Ok(job) = receiver.recv() {
+                        // A job which panics fails, without stopping the thread that the
+                        // other jobs need.
+                        if panic::catch_unwind(AssertUnwindSafe(job.run)).is_err() {
+                            warn!("A background job panicked");
+                            (job.failed)();
+                        }
+                    }
+                })
+                .expect("Thread spawning failed");
+        }
+        sender
+    };
+}
+
+pub fn queue_background_job(job: BackgroundJob) {
+    BACKGROUND_JOB_QUEUE
+        .send(job)
+        .expect("Background job threads exited");
+}
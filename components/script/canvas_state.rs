@@ -251,7 +251,8 @@ impl CanvasState {
             ImageResponse::Loaded(img, _) => img,
             ImageResponse::PlaceholderLoaded(_, _) |
             ImageResponse::None |
-            ImageResponse::MetadataLoaded(_) => {
+            ImageResponse::MetadataLoaded(_) |
+            ImageResponse::PartiallyLoaded(_, _) => {
                 return None;
             },
         };
@@ -0,0 +1,258 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The debugger of a script thread.
+//!
+//! This is a SpiderMonkey `Debugger` created by resources/debugger.js in a global of its
+//! own, which is invisible to the scripts of the pages. The devtools messages are
+//! forwarded to the functions of that script as JSON, and the script blocks the
+//! execution of the debuggees when it pauses by calling back into the script thread.
+
+use crate::dom::bindings::conversions::jsstring_to_str;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::globalscope::GlobalScope;
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::script_thread::ScriptThread;
+use devtools_traits::{EnvironmentInfo, FrameInfo, PauseInfo, ResumeLimit};
+use devtools_traits::{SourceInfo, SourceLocation};
+use embedder_traits::resources::{self, Resource as EmbedderResource};
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{CallArgs, Heap, JSAutoRealm, JSContext, JSObject, JS_ClearPendingException};
+use js::jsapi::{HandleValueArray, OnNewGlobalHookOption, RealmOptions, SourceText};
+use js::jsapi::{JS_DefineDebuggerObject, JS_IsExceptionPending, JS_NewGlobalObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::wrappers::{Evaluate2, JS_CallFunctionName, JS_DefineFunction, JS_WrapObject};
+use js::rust::{CompileOptionsWrapper, MutableHandleValue, SIMPLE_GLOBAL_CLASS};
+use msg::constellation_msg::PipelineId;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::ptr;
+use std::rc::Rc;
+
+#[derive(JSTraceable)]
+pub struct ScriptDebugger {
+    /// The global in which resources/debugger.js runs.
+    global: Box<Heap<*mut JSObject>>,
+}
+
+/// A source reported by resources/debugger.js.
+#[derive(Deserialize)]
+struct NewSource {
+    pipeline: Option<PipelineId>,
+    source: SourceInfo,
+}
+
+/// A pause reported by resources/debugger.js.
+#[derive(Deserialize)]
+struct Pause {
+    pipeline: Option<PipelineId>,
+    pause: PauseInfo,
+}
+
+impl ScriptDebugger {
+    #[allow(unsafe_code)]
+    pub fn new(cx: SafeJSContext) -> Rc<ScriptDebugger> {
+        let debugger = Rc::new(ScriptDebugger {
+            global: Box::new(Heap::default()),
+        });
+        unsafe {
+            let mut options = RealmOptions::default();
+            options.creationOptions_.invisibleToDebugger_ = true;
+            rooted!(in(*cx) let global = JS_NewGlobalObject(
+                *cx,
+                &SIMPLE_GLOBAL_CLASS,
+                ptr::null_mut(),
+                OnNewGlobalHookOption::FireOnNewGlobalHook,
+                &options,
+            ));
+            assert!(!global.is_null());
+            debugger.global.set(global.get());
+
+            let _ac = JSAutoRealm::new(*cx, global.get());
+            assert!(JS_DefineDebuggerObject(*cx, global.handle().into()));
+            for &(name, native) in &[
+                (b"reportSource\0", report_source as NativeFn),
+                (b"pauseDebuggee\0", pause_debuggee as NativeFn),
+            ] {
+                assert!(!JS_DefineFunction(
+                    *cx,
+                    global.handle(),
+                    name.as_ptr() as *const _,
+                    Some(native),
+                    1,
+                    0,
+                )
+                .is_null());
+            }
+
+            let source = resources::read_string(EmbedderResource::DebuggerJS);
+            let filename = CString::new("debugger.js").unwrap();
+            let options = CompileOptionsWrapper::new(*cx, filename.as_ptr(), 1);
+            rooted!(in(*cx) let mut rval = UndefinedValue());
+            if !Evaluate2(
+                *cx,
+                options.ptr,
+                &mut SourceText {
+                    units_: source.as_ptr() as *const _,
+                    length_: source.len() as u32,
+                    ownsUnits_: false,
+                    _phantom_0: PhantomData,
+                },
+                rval.handle_mut(),
+            ) {
+                warn!("Failed to evaluate the debugger script.");
+                JS_ClearPendingException(*cx);
+            }
+        }
+        debugger
+    }
+
+    /// Start debugging the given global, returning the sources it already loaded.
+    #[allow(unsafe_code)]
+    pub fn add_debuggee(&self, global: &GlobalScope, pipeline_id: PipelineId) -> Vec<SourceInfo> {
+        let cx = global.get_cx();
+        let _ac = JSAutoRealm::new(*cx, self.global.get());
+        rooted!(in(*cx) let mut debuggee = global.reflector().get_jsobject().get());
+        unsafe {
+            if !JS_WrapObject(*cx, debuggee.handle_mut()) {
+                JS_ClearPendingException(*cx);
+                return vec![];
+            }
+        }
+        self.call(cx, "addDebuggee", Some(debuggee.get()), &pipeline_id)
+            .unwrap_or(vec![])
+    }
+
+    /// Stop debugging the given global.
+    #[allow(unsafe_code)]
+    pub fn remove_debuggee(&self, global: &GlobalScope) {
+        let cx = global.get_cx();
+        let _ac = JSAutoRealm::new(*cx, self.global.get());
+        rooted!(in(*cx) let mut debuggee = global.reflector().get_jsobject().get());
+        unsafe {
+            if !JS_WrapObject(*cx, debuggee.handle_mut()) {
+                JS_ClearPendingException(*cx);
+                return;
+            }
+        }
+        let _: Option<()> = self.call(cx, "removeDebuggee", Some(debuggee.get()), &());
+    }
+
+    pub fn source_text(&self, cx: SafeJSContext, source_id: u32) -> Option<String> {
+        self.call(cx, "getSourceText", None, &source_id)
+            .unwrap_or(None)
+    }
+
+    pub fn breakpoint_positions(&self, cx: SafeJSContext, source_id: u32) -> Vec<(u32, u32)> {
+        self.call(cx, "getBreakpointPositions", None, &source_id)
+            .unwrap_or(vec![])
+    }
+
+    pub fn set_breakpoint(&self, cx: SafeJSContext, location: SourceLocation) {
+        let _: Option<()> = self.call(cx, "setBreakpoint", None, &location);
+    }
+
+    pub fn remove_breakpoint(&self, cx: SafeJSContext, location: SourceLocation) {
+        let _: Option<()> = self.call(cx, "removeBreakpoint", None, &location);
+    }
+
+    pub fn interrupt(&self, cx: SafeJSContext) {
+        let _: Option<()> = self.call(cx, "interrupt", None, &());
+    }
+
+    pub fn frames(&self, cx: SafeJSContext) -> Vec<FrameInfo> {
+        self.call(cx, "getFrames", None, &()).unwrap_or(vec![])
+    }
+
+    pub fn environment(&self, cx: SafeJSContext, frame_id: u32) -> Vec<EnvironmentInfo> {
+        self.call(cx, "getEnvironment", None, &frame_id)
+            .unwrap_or(vec![])
+    }
+
+    /// Call the given function of resources/debugger.js, passing it an optional
+    /// object and the given argument as JSON, and deserializing its JSON result.
+    #[allow(unsafe_code)]
+    fn call<A: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        cx: SafeJSContext,
+        name: &str,
+        object: Option<*mut JSObject>,
+        argument: &A,
+    ) -> Option<R> {
+        let _ac = JSAutoRealm::new(*cx, self.global.get());
+        rooted!(in(*cx) let global = self.global.get());
+        rooted!(in(*cx) let mut json = UndefinedValue());
+        rooted!(in(*cx) let mut rval = UndefinedValue());
+        let name = CString::new(name).unwrap();
+        unsafe {
+            serde_json::to_string(argument)
+                .unwrap()
+                .to_jsval(*cx, json.handle_mut());
+            let args = match object {
+                Some(object) => vec![ObjectValue(object), json.get()],
+                None => vec![json.get()],
+            };
+            let ok = JS_CallFunctionName(
+                *cx,
+                global.handle(),
+                name.as_ptr(),
+                &HandleValueArray::from_rooted_slice(&args),
+                rval.handle_mut(),
+            );
+            if !ok || !rval.is_string() {
+                warn!("Call to the debugger failed: {:?}.", name);
+                if JS_IsExceptionPending(*cx) {
+                    JS_ClearPendingException(*cx);
+                }
+                return None;
+            }
+            let result = jsstring_to_str(*cx, rval.to_string());
+            serde_json::from_str(&result).ok()
+        }
+    }
+}
+
+type NativeFn = unsafe extern "C" fn(*mut JSContext, u32, *mut JSVal) -> bool;
+
+/// Read the first argument of a native function as a JSON string.
+#[allow(unsafe_code)]
+unsafe fn json_argument<T: DeserializeOwned>(cx: *mut JSContext, args: &CallArgs) -> Option<T> {
+    let json = args.get(0);
+    if !json.is_string() {
+        return None;
+    }
+    serde_json::from_str(&jsstring_to_str(cx, json.to_string())).ok()
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn report_source(cx: *mut JSContext, argc: u32, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if let Some(NewSource {
+        pipeline: Some(pipeline_id),
+        source,
+    }) = json_argument(cx, &args)
+    {
+        ScriptThread::debugger_new_source(pipeline_id, source);
+    }
+    *args.rval() = UndefinedValue();
+    true
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn pause_debuggee(cx: *mut JSContext, argc: u32, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let limit: Option<ResumeLimit> = match json_argument(cx, &args) {
+        Some(Pause {
+            pipeline: Some(pipeline_id),
+            pause,
+        }) => ScriptThread::debugger_paused(pipeline_id, pause),
+        _ => None,
+    };
+    serde_json::to_string(&limit)
+        .unwrap()
+        .to_jsval(cx, MutableHandleValue::from_raw(args.rval()));
+    true
+}
@@ -2,30 +2,51 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::codegen::Bindings::CSSRuleListBinding::CSSRuleListMethods;
 use crate::dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyleDeclarationMethods;
+use crate::dom::bindings::codegen::Bindings::CSSStyleRuleBinding::CSSStyleRuleMethods;
+use crate::dom::bindings::codegen::Bindings::CSSStyleSheetBinding::CSSStyleSheetMethods;
+use crate::dom::bindings::codegen::Bindings::CharacterDataBinding::CharacterDataMethods;
 use crate::dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
+use crate::dom::bindings::codegen::Bindings::StyleSheetBinding::StyleSheetMethods;
+use crate::dom::bindings::codegen::Bindings::StyleSheetListBinding::StyleSheetListMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::conversions::{jsstring_to_str, ConversionResult, FromJSValConvertible};
 use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
+use crate::dom::characterdata::CharacterData;
+use crate::dom::cssstylerule::CSSStyleRule;
+use crate::dom::cssstylesheet::CSSStyleSheet;
 use crate::dom::document::AnimationFrameCallback;
 use crate::dom::element::Element;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::htmlelement::HTMLElement;
+use crate::dom::mutationobserver::Mutation;
 use crate::dom::node::{window_from_node, Node, ShadowIncluding};
 use crate::dom::window::Window;
 use crate::realms::enter_realm;
 use crate::script_thread::Documents;
+use crate::task_source::TaskSource;
+use devtools_traits::{AppliedStyles, MatchedRule, ScriptToDevtoolsControlMsg};
 use devtools_traits::{AutoMargins, ComputedNodeLayout, TimelineMarkerType};
+use devtools_traits::{DomMutation, StyleModification};
 use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, TimelineMarker};
+use euclid::{Point2D, Rect, SideOffsets2D, Size2D};
+use html5ever::{LocalName, Namespace};
 use ipc_channel::ipc::IpcSender;
 use js::jsval::UndefinedValue;
 use js::rust::wrappers::ObjectClassName;
 use msg::constellation_msg::PipelineId;
+use script_traits::{BoxModelHighlight, ScriptMsg};
 use std::ffi::CStr;
 use std::str;
+use style::properties::{PropertyDeclarationId, ShorthandId};
+use style_traits::CSSPixel;
 use uuid::Uuid;
 
 #[allow(unsafe_code)]
@@ -213,6 +234,301 @@ pub fn handle_modify_attribute(
     }
 }
 
+pub fn handle_get_computed_style(
+    documents: &Documents,
+    pipeline: PipelineId,
+    node_id: String,
+    reply: IpcSender<Option<Vec<(String, String)>>>,
+) {
+    let node = match find_node_by_unique_id(documents, pipeline, &*node_id) {
+        None => return reply.send(None).unwrap(),
+        Some(found_node) => found_node,
+    };
+    if !node.is::<Element>() || !node.is_connected() {
+        return reply.send(None).unwrap();
+    }
+
+    let window = window_from_node(&*node);
+    let style = match window.style_query(node.to_trusted_node_address()) {
+        None => return reply.send(None).unwrap(),
+        Some(style) => style,
+    };
+    let properties = ShorthandId::All
+        .longhands()
+        .map(|longhand| {
+            let value = style.computed_value_to_string(PropertyDeclarationId::Longhand(longhand));
+            (longhand.name().to_owned(), value)
+        })
+        .collect();
+    reply.send(Some(properties)).unwrap();
+}
+
+pub fn handle_get_applied_styles(
+    documents: &Documents,
+    pipeline: PipelineId,
+    node_id: String,
+    reply: IpcSender<Option<AppliedStyles>>,
+) {
+    let node = match find_node_by_unique_id(documents, pipeline, &*node_id) {
+        None => return reply.send(None).unwrap(),
+        Some(found_node) => found_node,
+    };
+    let elem = match node.downcast::<Element>() {
+        None => return reply.send(None).unwrap(),
+        Some(elem) => elem,
+    };
+
+    let inline_style = node
+        .downcast::<HTMLElement>()
+        .map_or(String::new(), |elem| String::from(elem.Style().CssText()));
+
+    // TODO: Look into the rules nested in grouping rules such as @media.
+    let mut matched_rules = vec![];
+    let style_sheets = node.owner_doc().StyleSheets();
+    for index in 0..style_sheets.Length() {
+        let sheet = match style_sheets.Item(index) {
+            Some(sheet) => sheet,
+            None => continue,
+        };
+        let rules = match sheet
+            .downcast::<CSSStyleSheet>()
+            .map(|sheet| sheet.GetCssRules())
+        {
+            Some(Ok(rules)) => rules,
+            _ => continue,
+        };
+        for rule in (0..rules.Length()).filter_map(|index| rules.Item(index)) {
+            let rule = match rule.downcast::<CSSStyleRule>() {
+                Some(rule) => rule,
+                None => continue,
+            };
+            let selector_text = rule.SelectorText();
+            if !elem.Matches(selector_text.clone()).unwrap_or(false) {
+                continue;
+            }
+            matched_rules.push(MatchedRule {
+                selectorText: String::from(selector_text),
+                cssText: String::from(rule.Style().CssText()),
+                href: sheet.GetHref().map(String::from),
+                styleSheetIndex: index as usize,
+            });
+        }
+    }
+
+    reply
+        .send(Some(AppliedStyles {
+            inlineStyle: inline_style,
+            matchedRules: matched_rules,
+        }))
+        .unwrap();
+}
+
+pub fn handle_modify_inline_style(
+    documents: &Documents,
+    pipeline: PipelineId,
+    node_id: String,
+    modifications: Vec<StyleModification>,
+) {
+    let node = match find_node_by_unique_id(documents, pipeline, &*node_id) {
+        None => {
+            return warn!(
+                "node id {} for pipeline id {} is not found",
+                &node_id, &pipeline
+            );
+        },
+        Some(found_node) => found_node,
+    };
+
+    let style = match node.downcast::<HTMLElement>() {
+        None => return warn!("node id {} has no inline style", &node_id),
+        Some(elem) => elem.Style(),
+    };
+
+    for modification in modifications {
+        let name = DOMString::from(modification.name);
+        match modification.value {
+            Some(value) => {
+                let priority = DOMString::from(modification.priority);
+                let _ = style.SetProperty(name, DOMString::from(value), priority);
+            },
+            None => {
+                let _ = style.RemoveProperty(name);
+            },
+        }
+    }
+}
+
+pub fn handle_wants_dom_mutations(
+    documents: &Documents,
+    pipeline: PipelineId,
+    send_notifications: bool,
+) {
+    if let Some(window) = documents.find_window(pipeline) {
+        window.set_devtools_wants_dom_mutations(send_notifications);
+    }
+}
+
+/// A DOM mutation whose new values can only be read once it has happened.
+enum PendingDomMutation {
+    Attributes(LocalName, Namespace),
+    CharacterData,
+    ChildList(Vec<String>, Vec<String>),
+}
+
+fn unique_ids(nodes: Option<&[&Node]>) -> Vec<String> {
+    nodes
+        .unwrap_or(&[])
+        .iter()
+        .map(|node| node.unique_id())
+        .collect()
+}
+
+/// Notify the devtools of a mutation of the given node, if they asked for it.
+pub fn notify_dom_mutation(target: &Node, mutation: &Mutation) {
+    let window = window_from_node(target);
+    if !window.devtools_wants_dom_mutations() {
+        return;
+    }
+
+    let pending = match *mutation {
+        Mutation::Attribute {
+            ref name,
+            ref namespace,
+            ..
+        } => PendingDomMutation::Attributes(name.clone(), namespace.clone()),
+        Mutation::CharacterData { .. } => PendingDomMutation::CharacterData,
+        Mutation::ChildList { added, removed, .. } => {
+            PendingDomMutation::ChildList(unique_ids(added), unique_ids(removed))
+        },
+    };
+
+    // Mutation records are queued before the node changes, so its new state
+    // is only reported from a task.
+    let target = Trusted::new(target);
+    let _ = window.task_manager().dom_manipulation_task_source().queue(
+        task!(devtools_dom_mutation: move || {
+            let target = target.root();
+            let target_id = target.unique_id();
+            let mutation = match pending {
+                PendingDomMutation::Attributes(name, namespace) => DomMutation::Attributes {
+                    target: target_id,
+                    attributeName: String::from(&*name),
+                    attributeNamespace: String::from(&*namespace),
+                    newValue: target
+                        .downcast::<Element>()
+                        .and_then(|elem| elem.get_attribute(&namespace, &name))
+                        .map(|attr| String::from(&**attr.value())),
+                },
+                PendingDomMutation::CharacterData => DomMutation::CharacterData {
+                    target: target_id,
+                    newValue: target
+                        .downcast::<CharacterData>()
+                        .map_or(String::new(), |data| String::from(data.Data())),
+                },
+                PendingDomMutation::ChildList(added, removed) => DomMutation::ChildList {
+                    target: target_id,
+                    added: added,
+                    removed: removed,
+                    numChildren: target.children_count() as usize,
+                },
+            };
+            let window = window_from_node(&*target);
+            if let Some(chan) = window.upcast::<GlobalScope>().devtools_chan() {
+                let msg = ScriptToDevtoolsControlMsg::DomMutation(window.pipeline_id(), mutation);
+                let _ = chan.send(msg);
+            }
+        }),
+        window.upcast(),
+    );
+}
+
+/// Compute the margin, border, padding and content boxes of the given element, relative
+/// to the viewport.
+fn box_model_highlight(window: &Window, elem: &Element) -> BoxModelHighlight {
+    let rect = elem.GetBoundingClientRect();
+    let border = Rect::new(
+        Point2D::new(rect.X() as f32, rect.Y() as f32),
+        Size2D::new(rect.Width() as f32, rect.Height() as f32),
+    );
+
+    let style = window.GetComputedStyle(elem, None);
+    let px = |value: DOMString| -> f32 { value.trim_end_matches("px").parse().unwrap_or(0.) };
+    let margin = SideOffsets2D::new(
+        px(style.MarginTop()),
+        px(style.MarginRight()),
+        px(style.MarginBottom()),
+        px(style.MarginLeft()),
+    );
+    let border_widths = SideOffsets2D::new(
+        px(style.BorderTopWidth()),
+        px(style.BorderRightWidth()),
+        px(style.BorderBottomWidth()),
+        px(style.BorderLeftWidth()),
+    );
+    let padding = SideOffsets2D::new(
+        px(style.PaddingTop()),
+        px(style.PaddingRight()),
+        px(style.PaddingBottom()),
+        px(style.PaddingLeft()),
+    );
+
+    let padding_box: Rect<f32, CSSPixel> = border.inner_rect(border_widths);
+    BoxModelHighlight {
+        margin: border.outer_rect(margin),
+        border: border,
+        padding: padding_box,
+        content: padding_box.inner_rect(padding),
+    }
+}
+
+pub fn handle_highlight_node(documents: &Documents, pipeline: PipelineId, node_id: Option<String>) {
+    let window = match documents.find_window(pipeline) {
+        None => return,
+        Some(window) => window,
+    };
+    let node = node_id.and_then(|node_id| find_node_by_unique_id(documents, pipeline, &*node_id));
+    let highlight = node.as_ref().and_then(|node| {
+        let elem = node.downcast::<Element>()?;
+        Some(box_model_highlight(&window, elem))
+    });
+    window.send_to_constellation(ScriptMsg::HighlightBoxModel(highlight));
+}
+
+pub fn handle_set_node_picking(documents: &Documents, pipeline: PipelineId, picking: bool) {
+    if let Some(window) = documents.find_window(pipeline) {
+        window.set_devtools_node_picking(picking);
+        if !picking {
+            window.send_to_constellation(ScriptMsg::HighlightBoxModel(None));
+        }
+    }
+}
+
+/// Highlight the element hovered by the devtools node picker.
+pub fn handle_picker_hover(window: &Window, elem: &Element) {
+    let highlight = box_model_highlight(window, elem);
+    window.send_to_constellation(ScriptMsg::HighlightBoxModel(Some(highlight)));
+    if let Some(chan) = window.upcast::<GlobalScope>().devtools_chan() {
+        let info = elem.upcast::<Node>().summarize();
+        let _ = chan.send(ScriptToDevtoolsControlMsg::PickerNodeHovered(
+            window.pipeline_id(),
+            info,
+        ));
+    }
+}
+
+/// Hand the element clicked with the devtools node picker to the inspector, and stop picking.
+pub fn handle_picker_click(window: &Window, elem: &Element) {
+    window.set_devtools_node_picking(false);
+    window.send_to_constellation(ScriptMsg::HighlightBoxModel(None));
+    if let Some(chan) = window.upcast::<GlobalScope>().devtools_chan() {
+        let info = elem.upcast::<Node>().summarize();
+        let _ = chan.send(ScriptToDevtoolsControlMsg::PickerNodePicked(
+            window.pipeline_id(),
+            info,
+        ));
+    }
+}
+
 pub fn handle_wants_live_notifications(global: &GlobalScope, send_notifications: bool) {
     global.set_devtools_wants_updates(send_notifications);
 }
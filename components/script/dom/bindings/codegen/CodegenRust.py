@@ -2010,7 +2010,7 @@ class CGImports(CGWrapper):
             if d.interface.isIteratorInterface():
                 types += [d.interface.iterableInterface]
 
-            members = d.interface.members + d.interface.namedConstructors
+            members = d.interface.members + d.interface.legacyFactoryFunctions
             constructor = d.interface.ctor()
             if constructor:
                 members += [constructor]
@@ -2966,23 +2966,27 @@ class CGCreateInterfaceObjectsMethod(CGAbstractMethod):
                 proto = "GetRealmObjectPrototype(*cx)"
             else:
                 proto = "JS_NewPlainObject(*cx)"
-            if self.properties.static_methods.length():
-                methods = self.properties.static_methods.variableName()
-            else:
-                methods = "&[]"
+            properties = {"id": MakeNativeName(name), "name": str_to_const_array(name), "proto": proto}
+            for arrayName in ["static_methods", "static_attrs", "consts"]:
+                array = getattr(self.properties, arrayName)
+                if array.length():
+                    properties[arrayName] = array.variableName()
+                else:
+                    properties[arrayName] = "&[]"
             return CGGeneric("""\
 rooted!(in(*cx) let proto = %(proto)s);
 assert!(!proto.is_null());
 rooted!(in(*cx) let mut namespace = ptr::null_mut::<JSObject>());
 create_namespace_object(cx, global, proto.handle(), &NAMESPACE_OBJECT_CLASS,
-                        %(methods)s, %(name)s, namespace.handle_mut());
+                        %(static_methods)s, %(static_attrs)s, %(consts)s,
+                        %(name)s, namespace.handle_mut());
 assert!(!namespace.is_null());
 assert!((*cache)[PrototypeList::Constructor::%(id)s as usize].is_null());
 (*cache)[PrototypeList::Constructor::%(id)s as usize] = namespace.get();
 <*mut JSObject>::post_barrier((*cache).as_mut_ptr().offset(PrototypeList::Constructor::%(id)s as isize),
                               ptr::null_mut(),
                               namespace.get());
-""" % {"id": MakeNativeName(name), "methods": methods, "name": str_to_const_array(name), "proto": proto})
+""" % properties)
         if self.descriptor.interface.isCallback():
             assert not self.descriptor.interface.ctor() and self.descriptor.interface.hasConstants()
             return CGGeneric("""\
@@ -3001,7 +3005,12 @@ assert!((*cache)[PrototypeList::Constructor::%(id)s as usize].is_null());
             if self.descriptor.interface.getExtendedAttribute("ExceptionClass"):
                 protoGetter = "GetRealmErrorPrototype"
             elif self.descriptor.interface.isIteratorInterface():
-                protoGetter = "GetRealmIteratorPrototype"
+                # SpiderMonkey does not expose %AsyncIteratorPrototype%, so
+                # async iterators inherit from %ObjectPrototype% instead.
+                if self.descriptor.interface.iterableInterface.isAsyncIterable():
+                    protoGetter = "GetRealmObjectPrototype"
+                else:
+                    protoGetter = "GetRealmIteratorPrototype"
             else:
                 protoGetter = "GetRealmObjectPrototype"
             getPrototypeProto = "prototype_proto.set(%s(*cx))" % protoGetter
@@ -3100,16 +3109,17 @@ assert!((*cache)[PrototypeList::Constructor::%(id)s as usize].is_null());
         aliasedMembers = [m for m in self.descriptor.interface.members if m.isMethod() and m.aliases]
         if aliasedMembers:
             def defineAlias(alias):
-                if alias == "@@iterator":
-                    symbolJSID = "RUST_SYMBOL_TO_JSID(GetWellKnownSymbol(*cx, SymbolCode::iterator), \
-                                  iteratorId.handle_mut())"
+                if alias == "@@iterator" or alias == "@@asyncIterator":
+                    symbolJSID = "RUST_SYMBOL_TO_JSID(GetWellKnownSymbol(*cx, SymbolCode::%s), \
+                                  iteratorId.handle_mut())" % alias[2:]
                     getSymbolJSID = CGGeneric(fill("rooted!(in(*cx) let mut iteratorId: jsid);\n${symbolJSID};\n",
                                                    symbolJSID=symbolJSID))
                     defineFn = "JS_DefinePropertyById2"
                     prop = "iteratorId.handle()"
                     enumFlags = "0"  # Not enumerable, per spec.
                 elif alias.startswith("@@"):
-                    raise TypeError("Can't handle any well-known Symbol other than @@iterator")
+                    raise TypeError("Can't handle any well-known Symbol other than @@iterator "
+                                    "and @@asyncIterator")
                 else:
                     getSymbolJSID = None
                     defineFn = "JS_DefineProperty"
@@ -3150,9 +3160,9 @@ assert!((*cache)[PrototypeList::Constructor::%(id)s as usize].is_null());
             ] + [defineAliasesFor(m) for m in sorted(aliasedMembers)])
             code.append(defineAliases)
 
-        constructors = self.descriptor.interface.namedConstructors
+        constructors = self.descriptor.interface.legacyFactoryFunctions
         if constructors:
-            decl = "let named_constructors: [(ConstructorClassHook, &'static [u8], u32); %d]" % len(constructors)
+            decl = "let legacy_factory_functions: [(ConstructorClassHook, &'static [u8], u32); %d]" % len(constructors)
             specs = []
             for constructor in constructors:
                 hook = CONSTRUCT_HOOK_NAME + "_" + constructor.identifier.name
@@ -3161,7 +3171,8 @@ assert!((*cache)[PrototypeList::Constructor::%(id)s as usize].is_null());
                 specs.append(CGGeneric("(%s as ConstructorClassHook, %s, %d)" % (hook, name, length)))
             values = CGIndenter(CGList(specs, "\n"), 4)
             code.append(CGWrapper(values, pre="%s = [\n" % decl, post="\n];"))
-            code.append(CGGeneric("create_named_constructors(cx, global, &named_constructors, prototype.handle());"))
+            code.append(CGGeneric("create_legacy_factory_functions(cx, global, &legacy_factory_functions, "
+                                  "prototype.handle());"))
 
         if self.descriptor.hasUnforgeableMembers:
             # We want to use the same JSClass and prototype as the object we'll
@@ -3496,14 +3507,23 @@ class CGPerSignatureCall(CGThing):
         if self.isFallible():
             errorResult = " false"
 
-        if idlNode.isMethod() and idlNode.isMaplikeOrSetlikeOrIterableMethod():
+        if idlNode.isAttr() and idlNode.isMaplikeOrSetlikeAttr():
+            cgThings.append(CGMaplikeOrSetlikeMethodGenerator(descriptor,
+                                                              idlNode.maplikeOrSetlike,
+                                                              idlNode.identifier.name,
+                                                              self.getArguments()))
+        elif idlNode.isMethod() and idlNode.isMaplikeOrSetlikeOrIterableMethod():
             if idlNode.maplikeOrSetlikeOrIterable.isMaplike() or \
                idlNode.maplikeOrSetlikeOrIterable.isSetlike():
-                raise TypeError('Maplike/Setlike methods are not supported yet')
+                cgThings.append(CGMaplikeOrSetlikeMethodGenerator(descriptor,
+                                                                  idlNode.maplikeOrSetlikeOrIterable,
+                                                                  idlNode.identifier.name,
+                                                                  self.getArguments()))
             else:
                 cgThings.append(CGIterableMethodGenerator(descriptor,
                                                           idlNode.maplikeOrSetlikeOrIterable,
-                                                          idlNode.identifier.name))
+                                                          idlNode.identifier.name,
+                                                          self.getArguments()))
         else:
             hasCEReactions = idlNode.getExtendedAttribute("CEReactions")
             cgThings.append(CGCallGenerator(
@@ -5800,7 +5820,7 @@ class CGInterfaceTrait(CGThing):
                                                      inRealm=name in descriptor.inRealmMethods)
                         rettype = return_type(descriptor, rettype, infallible)
                         yield name + ('_' * idx), arguments, rettype
-                elif m.isAttr() and not m.isStatic():
+                elif m.isAttr() and not m.isStatic() and not m.isMaplikeOrSetlikeAttr():
                     name = CGSpecializedGetter.makeNativeName(descriptor, m)
                     infallible = 'infallible' in descriptor.getExtendedAttributes(m, getter=True)
                     yield (name,
@@ -6052,7 +6072,7 @@ def generate_imports(config, cgthings, descriptors, callbacks=None, dictionaries
         'crate::dom::bindings::interface::create_global_object',
         'crate::dom::bindings::interface::create_callback_interface_object',
         'crate::dom::bindings::interface::create_interface_prototype_object',
-        'crate::dom::bindings::interface::create_named_constructors',
+        'crate::dom::bindings::interface::create_legacy_factory_functions',
         'crate::dom::bindings::interface::create_noncallback_interface_object',
         'crate::dom::bindings::interface::define_guarded_constants',
         'crate::dom::bindings::interface::define_guarded_methods',
@@ -6063,6 +6083,8 @@ def generate_imports(config, cgthings, descriptors, callbacks=None, dictionaries
         'crate::dom::bindings::htmlconstructor::push_new_element_queue',
         'crate::dom::bindings::iterable::Iterable',
         'crate::dom::bindings::iterable::IteratorType',
+        'crate::dom::bindings::like::Maplike',
+        'crate::dom::bindings::like::Setlike',
         'crate::dom::bindings::namespace::NamespaceObjectClass',
         'crate::dom::bindings::namespace::create_namespace_object',
         'crate::dom::bindings::reflector::MutDomObject',
@@ -6327,7 +6349,7 @@ class CGDescriptor(CGThing):
             if descriptor.interface.hasInterfaceObject():
                 if descriptor.interface.ctor():
                     cgThings.append(CGClassConstructHook(descriptor))
-                for ctor in descriptor.interface.namedConstructors:
+                for ctor in descriptor.interface.legacyFactoryFunctions:
                     cgThings.append(CGClassConstructHook(descriptor, ctor))
                 if not descriptor.interface.isCallback():
                     cgThings.append(CGInterfaceObjectJSClass(descriptor))
@@ -7403,7 +7425,7 @@ class CGIterableMethodGenerator(CGGeneric):
     CGMethodCall/CGPerSignatureCall. Functionality is filled in here instead of
     using CGCallGenerator.
     """
-    def __init__(self, descriptor, iterable, methodName):
+    def __init__(self, descriptor, iterable, methodName, arguments):
         if methodName == "forEach":
             CGGeneric.__init__(self, fill(
                 """
@@ -7443,6 +7465,28 @@ class CGIterableMethodGenerator(CGGeneric):
                 """,
                 ifaceName=descriptor.interface.identifier.name))
             return
+        if iterable.isAsyncIterable():
+            # The arguments of the declaration are passed on to the
+            # asynchronous iterator initialization steps as a tuple.
+            args = ["arg%d" % i for i in range(len(arguments))]
+            CGGeneric.__init__(self, fill(
+                """
+                let result = match ${iterClass}::new(&*this,
+                                                     IteratorType::${itrMethod},
+                                                     (${args}),
+                                                     super::${ifaceName}AsyncIteratorBinding::Wrap) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        throw_dom_exception(cx, &this.global(), e);
+                        return false;
+                    },
+                };
+                """,
+                iterClass=iteratorNativeType(descriptor, True),
+                ifaceName=descriptor.interface.identifier.name,
+                itrMethod=methodName.title(),
+                args=", ".join(args) + ("," if len(args) == 1 else "")))
+            return
         CGGeneric.__init__(self, fill(
             """
             let result = ${iterClass}::new(&*this,
@@ -7454,6 +7498,54 @@ class CGIterableMethodGenerator(CGGeneric):
             itrMethod=methodName.title()))
 
 
+class CGMaplikeOrSetlikeMethodGenerator(CGGeneric):
+    """
+    Creates methods and the size getter for maplike/setlike interfaces, by
+    calling into the Maplike or Setlike trait implemented by the interface.
+    Unwrapping/wrapping will be taken care of by the usual method generation
+    machinery in CGMethodCall/CGPerSignatureCall.
+    """
+    def __init__(self, descriptor, likeable, methodName, arguments):
+        if methodName in ["entries", "keys", "values", "forEach"]:
+            # Both maplike and setlike interfaces are iterated over through
+            # their implementation of the Iterable trait.
+            iterable = CGIterableMethodGenerator(descriptor, likeable, methodName, arguments)
+            CGGeneric.__init__(self, iterable.define())
+            return
+        trait = "Maplike" if likeable.isMaplike() else "Setlike"
+        args = "".join(", arg%d" % i for i in range(len(arguments)))
+        if methodName == "get":
+            code = fill(
+                """
+                rooted!(in(*cx) let mut result = UndefinedValue());
+                if let Some(value) = ${trait}::get(this${args}) {
+                    value.to_jsval(*cx, result.handle_mut());
+                }
+                """,
+                trait=trait,
+                args=args)
+        elif methodName in ["add", "set"]:
+            # add() and set() return the maplike/setlike object itself.
+            code = fill(
+                """
+                ${trait}::${method}(this${args});
+                let result = DomRoot::from_ref(this);
+                """,
+                trait=trait,
+                method=methodName,
+                args=args)
+        else:
+            assert methodName in ["size", "has", "delete", "clear"]
+            code = fill(
+                """
+                let result = ${trait}::${method}(this${args});
+                """,
+                trait=trait,
+                method=methodName,
+                args=args)
+        CGGeneric.__init__(self, code)
+
+
 def camel_to_upper_snake(s):
     return "_".join(m.group(0).upper() for m in re.finditer("[A-Z][a-z]*", s))
 
@@ -7518,7 +7610,7 @@ class GlobalGenRoots():
             pairs.append((d.name, binding, binding))
             for alias in d.interface.legacyWindowAliases:
                 pairs.append((alias, binding, binding))
-            for ctor in d.interface.namedConstructors:
+            for ctor in d.interface.legacyFactoryFunctions:
                 pairs.append((ctor.identifier.name, binding, binding))
         pairs.sort(key=operator.itemgetter(0))
         mappings = [
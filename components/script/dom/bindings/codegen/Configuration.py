@@ -182,7 +182,8 @@ class Descriptor(DescriptorProvider):
         nativeTypeDefault = ifaceName
 
         # For generated iterator interfaces for other iterable interfaces, we
-        # just use IterableIterator as the native type, templated on the
+        # just use IterableIterator (or AsyncIterableIterator for async
+        # iterables) as the native type, templated on the
         # nativeType of the iterable interface. That way we can have a
         # templated implementation for all the duplicated iterator
         # functionality.
@@ -213,7 +214,7 @@ class Descriptor(DescriptorProvider):
             self.argumentType = "&%s" % typeName
             self.nativeType = "*const %s" % typeName
             if self.interface.isIteratorInterface():
-                pathDefault = 'crate::dom::bindings::iterable::IterableIterator'
+                pathDefault = 'crate::dom::bindings::iterable::' + iteratorNativeType(itrDesc, True)
             else:
                 pathDefault = 'crate::dom::types::%s' % MakeNativeName(typeName)
 
@@ -439,7 +440,7 @@ def getTypesFromDescriptor(descriptor):
     members = [m for m in descriptor.interface.members]
     if descriptor.interface.ctor():
         members.append(descriptor.interface.ctor())
-    members.extend(descriptor.interface.namedConstructors)
+    members.extend(descriptor.interface.legacyFactoryFunctions)
     signatures = [s for m in members if m.isMethod() for s in m.signatures()]
     types = []
     for s in signatures:
@@ -484,7 +485,11 @@ def getUnwrappedType(type):
 
 
 def iteratorNativeType(descriptor, infer=False):
-    assert descriptor.interface.isIterable()
     iterableDecl = descriptor.interface.maplikeOrSetlikeOrIterable
-    assert iterableDecl.isPairIterator()
-    return "IterableIterator%s" % ("" if infer else '<%s>' % descriptor.interface.identifier.name)
+    assert (iterableDecl.isMaplike() or iterableDecl.isSetlike() or
+            iterableDecl.isAsyncIterable() or iterableDecl.isPairIterator())
+    if iterableDecl.isAsyncIterable():
+        iterClass = "AsyncIterableIterator"
+    else:
+        iterClass = "IterableIterator"
+    return "%s%s" % (iterClass, "" if infer else '<%s>' % descriptor.interface.identifier.name)
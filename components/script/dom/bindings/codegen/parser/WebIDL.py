@@ -6,6 +6,7 @@
 
 from __future__ import print_function
 from ply import lex, yacc
+import copy
 import re
 import os
 import traceback
@@ -301,9 +302,9 @@ class IDLScope(IDLObject):
                    originalObject.location, newObject.location), [])
 
         # We do the merging of overloads here as opposed to in IDLInterface
-        # because we need to merge overloads of NamedConstructors and we need to
+        # because we need to merge overloads of LegacyFactoryFunctions and we need to
         # detect conflicts in those across interfaces. See also the comment in
-        # IDLInterface.addExtendedAttributes for "NamedConstructor".
+        # IDLInterface.addExtendedAttributes for "LegacyFactoryFunction".
         if (isinstance(originalObject, IDLMethod) and
             isinstance(newObject, IDLMethod)):
             return originalObject.addOverload(newObject)
@@ -457,7 +458,10 @@ class IDLExposureMixins():
         assert scope.parentScope is None
         self._globalScope = scope
 
-        # Verify that our [Exposed] value, if any, makes sense.
+        # [Exposed=*] exposes us in every global, otherwise verify that our
+        # [Exposed] value, if any, makes sense.
+        if "*" in self._exposureGlobalNames:
+            self._exposureGlobalNames = set(scope.globalNames)
         for globalName in self._exposureGlobalNames:
             if globalName not in scope.globalNames:
                 raise WebIDLError("Unknown [Exposed] value %s" % globalName,
@@ -607,7 +611,7 @@ class IDLPartialInterfaceOrNamespace(IDLObject):
         for attr in attrs:
             identifier = attr.identifier()
 
-            if identifier == "NamedConstructor":
+            if identifier == "LegacyFactoryFunction":
                 self.propagatedExtendedAttrs.append(attr)
             elif identifier == "SecureContext":
                 self._haveSecureContextExtendedAttribute = True
@@ -881,10 +885,10 @@ class IDLInterfaceOrNamespace(IDLInterfaceOrInterfaceMixinOrNamespace):
         self.parent = None
         self._callback = False
         self.maplikeOrSetlikeOrIterable = None
-        # namedConstructors needs deterministic ordering because bindings code
-        # outputs the constructs in the order that namedConstructors enumerates
+        # legacyFactoryFunctions needs deterministic ordering because bindings code
+        # outputs the constructs in the order that legacyFactoryFunctions enumerates
         # them.
-        self.namedConstructors = list()
+        self.legacyFactoryFunctions = list()
         self.legacyWindowAliases = []
         self.includedMixins = set()
         # self.interfacesBasedOnSelf is the set of interfaces that inherit from
@@ -925,6 +929,10 @@ class IDLInterfaceOrNamespace(IDLInterfaceOrInterfaceMixinOrNamespace):
         return (self.maplikeOrSetlikeOrIterable and
                 self.maplikeOrSetlikeOrIterable.isIterable())
 
+    def isAsyncIterable(self):
+        return (self.maplikeOrSetlikeOrIterable and
+                self.maplikeOrSetlikeOrIterable.isAsyncIterable())
+
     def isIteratorInterface(self):
         return self.iterableInterface is not None
 
@@ -1089,10 +1097,10 @@ class IDLInterfaceOrNamespace(IDLInterfaceOrInterfaceMixinOrNamespace):
             # it doesn't get in the way later.
             self.members.remove(ctor)
 
-        for ctor in self.namedConstructors:
+        for ctor in self.legacyFactoryFunctions:
             if self.globalNames:
                 raise WebIDLError(
-                    "Can't have both a named constructor and [Global]",
+                    "Can't have both a legacy factory function and [Global]",
                     [self.location, ctor.location])
             assert len(ctor._exposureGlobalNames) == 0
             ctor._exposureGlobalNames.update(self._exposureGlobalNames)
@@ -1348,8 +1356,8 @@ class IDLInterfaceOrNamespace(IDLInterfaceOrInterfaceMixinOrNamespace):
         ctor = self.ctor()
         if ctor is not None:
             ctor.validate()
-        for namedCtor in self.namedConstructors:
-            namedCtor.validate()
+        for factoryFunction in self.legacyFactoryFunctions:
+            factoryFunction.validate()
 
         indexedGetter = None
         hasLengthAttribute = False
@@ -1645,9 +1653,9 @@ class IDLInterface(IDLInterfaceOrNamespace):
                                       [attr.location])
 
                 self._noInterfaceObject = True
-            elif identifier == "NamedConstructor":
+            elif identifier == "LegacyFactoryFunction":
                 if not attr.hasValue():
-                    raise WebIDLError("NamedConstructor must either take an identifier or take a named argument list",
+                    raise WebIDLError("LegacyFactoryFunction must either take an identifier or take a named argument list",
                                       [attr.location])
 
 
@@ -1663,26 +1671,26 @@ class IDLInterface(IDLInterfaceOrNamespace):
                 method.addExtendedAttributes(
                     [IDLExtendedAttribute(self.location, ("Throws",))])
 
-                # We need to detect conflicts for NamedConstructors across
+                # We need to detect conflicts for LegacyFactoryFunctions across
                 # interfaces. We first call resolve on the parentScope,
-                # which will merge all NamedConstructors with the same
+                # which will merge all LegacyFactoryFunctions with the same
                 # identifier accross interfaces as overloads.
                 method.resolve(self.parentScope)
 
                 # Then we look up the identifier on the parentScope. If the
                 # result is the same as the method we're adding then it
                 # hasn't been added as an overload and it's the first time
-                # we've encountered a NamedConstructor with that identifier.
+                # we've encountered a LegacyFactoryFunction with that identifier.
                 # If the result is not the same as the method we're adding
                 # then it has been added as an overload and we need to check
                 # whether the result is actually one of our existing
-                # NamedConstructors.
+                # LegacyFactoryFunctions.
                 newMethod = self.parentScope.lookupIdentifier(method.identifier)
                 if newMethod == method:
-                    self.namedConstructors.append(method)
-                elif newMethod not in self.namedConstructors:
-                    raise WebIDLError("NamedConstructor conflicts with a "
-                                      "NamedConstructor of a different interface",
+                    self.legacyFactoryFunctions.append(method)
+                elif newMethod not in self.legacyFactoryFunctions:
+                    raise WebIDLError("LegacyFactoryFunction conflicts with a "
+                                      "LegacyFactoryFunction of a different interface",
                                       [method.location, newMethod.location])
             elif (identifier == "ExceptionClass"):
                 if not attr.noArguments():
@@ -2174,9 +2182,6 @@ class IDLType(IDLObject):
     def isRecord(self):
         return False
 
-    def isReadableStream(self):
-        return False
-
     def isArrayBuffer(self):
         return False
 
@@ -2205,8 +2210,7 @@ class IDLType(IDLObject):
     def isSpiderMonkeyInterface(self):
         """ Returns a boolean indicating whether this type is an 'interface'
             type that is implemented in SpiderMonkey. """
-        return self.isInterface() and (self.isBufferSource() or
-                                       self.isReadableStream())
+        return self.isInterface() and self.isBufferSource()
 
     def isDictionary(self):
         return False
@@ -2417,9 +2421,6 @@ class IDLNullableType(IDLParametrizedType):
     def isRecord(self):
         return self.inner.isRecord()
 
-    def isReadableStream(self):
-        return self.inner.isReadableStream()
-
     def isArrayBuffer(self):
         return self.inner.isArrayBuffer()
 
@@ -2839,9 +2840,6 @@ class IDLTypedefType(IDLType):
     def isRecord(self):
         return self.inner.isRecord()
 
-    def isReadableStream(self):
-        return self.inner.isReadableStream()
-
     def isDictionary(self):
         return self.inner.isDictionary()
 
@@ -3179,7 +3177,6 @@ class IDLBuiltinType(IDLType):
         'Uint32Array',
         'Float32Array',
         'Float64Array',
-        'ReadableStream',
         )
 
     TagLookup = {
@@ -3215,7 +3212,6 @@ class IDLBuiltinType(IDLType):
         Types.Uint32Array: IDLType.Tags.interface,
         Types.Float32Array: IDLType.Tags.interface,
         Types.Float64Array: IDLType.Tags.interface,
-        Types.ReadableStream: IDLType.Tags.interface,
     }
 
     PrettyNames = {
@@ -3251,7 +3247,6 @@ class IDLBuiltinType(IDLType):
         Types.Uint32Array: "Uint32Array",
         Types.Float32Array: "Float32Array",
         Types.Float64Array: "Float64Array",
-        Types.ReadableStream: "ReadableStream",
     }
 
     def __init__(self, location, name, type, clamp=False, enforceRange=False, treatNullAsEmpty=False,
@@ -3376,17 +3371,13 @@ class IDLBuiltinType(IDLType):
         return (self._typeTag >= IDLBuiltinType.Types.Int8Array and
                 self._typeTag <= IDLBuiltinType.Types.Float64Array)
 
-    def isReadableStream(self):
-        return self._typeTag == IDLBuiltinType.Types.ReadableStream
-
     def isInterface(self):
         # TypedArray things are interface types per the TypedArray spec,
         # but we handle them as builtins because SpiderMonkey implements
         # all of it internally.
         return (self.isArrayBuffer() or
                 self.isArrayBufferView() or
-                self.isTypedArray() or
-                self.isReadableStream())
+                self.isTypedArray())
 
     def isNonCallbackInterface(self):
         # All the interfaces we can be are non-callback
@@ -3450,7 +3441,6 @@ class IDLBuiltinType(IDLType):
                  # ArrayBuffer is distinguishable from everything
                  # that's not an ArrayBuffer or a callback interface
                  (self.isArrayBuffer() and not other.isArrayBuffer()) or
-                 (self.isReadableStream() and not other.isReadableStream()) or
                  # ArrayBufferView is distinguishable from everything
                  # that's not an ArrayBufferView or typed array.
                  (self.isArrayBufferView() and not other.isArrayBufferView() and
@@ -3609,9 +3599,6 @@ BuiltinTypes = {
     IDLBuiltinType.Types.Float64Array:
         IDLBuiltinType(BuiltinLocation("<builtin type>"), "Float64Array",
                        IDLBuiltinType.Types.Float64Array),
-    IDLBuiltinType.Types.ReadableStream:
-        IDLBuiltinType(BuiltinLocation("<builtin type>"), "ReadableStream",
-                       IDLBuiltinType.Types.ReadableStream),
 }
 
 
@@ -3864,7 +3851,8 @@ class IDLInterfaceMember(IDLObjectWithIdentifier, IDLExposureMixins):
         'Attr',
         'Method',
         'MaplikeOrSetlike',
-        'Iterable'
+        'Iterable',
+        'AsyncIterable'
     )
 
     Special = enum(
@@ -3895,7 +3883,8 @@ class IDLInterfaceMember(IDLObjectWithIdentifier, IDLExposureMixins):
 
     def isMaplikeOrSetlikeOrIterable(self):
         return (self.tag == IDLInterfaceMember.Tags.MaplikeOrSetlike or
-                self.tag == IDLInterfaceMember.Tags.Iterable)
+                self.tag == IDLInterfaceMember.Tags.Iterable or
+                self.tag == IDLInterfaceMember.Tags.AsyncIterable)
 
     def isMaplikeOrSetlike(self):
         return self.tag == IDLInterfaceMember.Tags.MaplikeOrSetlike
@@ -3977,7 +3966,7 @@ class IDLMaplikeOrSetlikeOrIterableBase(IDLInterfaceMember):
             assert isinstance(keyType, IDLType)
         else:
             assert valueType is not None
-        assert ifaceType in ['maplike', 'setlike', 'iterable']
+        assert ifaceType in ['maplike', 'setlike', 'iterable', 'asynciterable']
         if valueType is not None:
             assert isinstance(valueType, IDLType)
         self.keyType = keyType
@@ -3995,6 +3984,9 @@ class IDLMaplikeOrSetlikeOrIterableBase(IDLInterfaceMember):
     def isIterable(self):
         return self.maplikeOrSetlikeOrIterableType == "iterable"
 
+    def isAsyncIterable(self):
+        return self.maplikeOrSetlikeOrIterableType == "asynciterable"
+
     def hasKeyType(self):
         return self.keyType is not None
 
@@ -4086,11 +4078,15 @@ class IDLMaplikeOrSetlikeOrIterableBase(IDLInterfaceMember):
             method.addExtendedAttributes(
                 [IDLExtendedAttribute(self.location, ("NewObject",))])
         if isIteratorAlias:
+            if not self.isAsyncIterable():
+                alias = "@@iterator"
+            else:
+                alias = "@@asyncIterator"
             method.addExtendedAttributes(
-                [IDLExtendedAttribute(self.location, ("Alias", "@@iterator"))])
+                [IDLExtendedAttribute(self.location, ("Alias", alias))])
         # Methods generated for iterables should be enumerable, but the ones for
         # maplike/setlike should not be.
-        if not self.isIterable():
+        if not self.isIterable() and not self.isAsyncIterable():
             method.addExtendedAttributes(
                 [IDLExtendedAttribute(self.location, ("NonEnumerable",))])
         members.append(method)
@@ -4189,6 +4185,60 @@ class IDLIterable(IDLMaplikeOrSetlikeOrIterableBase):
     def isPairIterator(self):
         return self.hasKeyType()
 
+# AsyncIterable adds ES2018 async iterator style functions and traits
+# (entries/keys/values/@@asyncIterator) to an interface.
+class IDLAsyncIterable(IDLMaplikeOrSetlikeOrIterableBase):
+
+    def __init__(self, location, identifier, keyType, valueType=None, argList=[], scope=None):
+        for arg in argList:
+            if not arg.optional:
+                raise WebIDLError("The arguments of the asynchronously iterable declaration on "
+                                  "%s must all be optional arguments." % identifier,
+                                  [arg.location])
+
+        IDLMaplikeOrSetlikeOrIterableBase.__init__(self, location, identifier,
+                                                   "asynciterable", keyType, valueType,
+                                                   IDLInterfaceMember.Tags.AsyncIterable)
+        self.iteratorType = None
+        self.argList = argList
+
+    def __str__(self):
+        return "declared async iterable with key '%s' and value '%s'" % (self.keyType, self.valueType)
+
+    def expand(self, members, isJSImplemented):
+        """
+        In order to take advantage of all of the method machinery in Codegen,
+        we generate our functions as if they were part of the interface
+        specification during parsing.
+        """
+        # object values()
+        self.addMethod("values", members, False, self.iteratorType,
+                       self.argList, affectsNothing=True, newObject=True,
+                       isIteratorAlias=(not self.isPairIterator()))
+
+        # We only need to add entries/keys here if we're a pair iterator.
+        if not self.isPairIterator():
+            return
+
+        # Methods can't share their IDLArguments, so we need to make copies here.
+        def copyArgList(argList):
+            return map(copy.copy, argList)
+
+        # object entries()
+        self.addMethod("entries", members, False, self.iteratorType,
+                       copyArgList(self.argList), affectsNothing=True,
+                       newObject=True, isIteratorAlias=True)
+        # object keys()
+        self.addMethod("keys", members, False, self.iteratorType,
+                       copyArgList(self.argList), affectsNothing=True,
+                       newObject=True)
+
+    def isValueIterator(self):
+        return not self.isPairIterator()
+
+    def isPairIterator(self):
+        return self.hasKeyType()
+
 # MaplikeOrSetlike adds ES6 map-or-set-like traits to an interface.
 class IDLMaplikeOrSetlike(IDLMaplikeOrSetlikeOrIterableBase):
 
@@ -4198,6 +4248,7 @@ class IDLMaplikeOrSetlike(IDLMaplikeOrSetlikeOrIterableBase):
                                                    keyType, valueType, IDLInterfaceMember.Tags.MaplikeOrSetlike)
         self.readonly = readonly
         self.slotIndices = None
+        self.iteratorType = None
 
         # When generating JSAPI access code, we need to know the backing object
         # type prefix to create the correct function. Generate here for reuse.
@@ -4229,14 +4280,16 @@ class IDLMaplikeOrSetlike(IDLMaplikeOrSetlikeOrIterableBase):
         self.disallowedMemberNames.append("size")
 
         # object entries()
-        self.addMethod("entries", members, False, BuiltinTypes[IDLBuiltinType.Types.object],
-                       affectsNothing=True, isIteratorAlias=self.isMaplike())
+        self.addMethod("entries", members, False, self.iteratorType,
+                       affectsNothing=True, newObject=True,
+                       isIteratorAlias=self.isMaplike())
         # object keys()
-        self.addMethod("keys", members, False, BuiltinTypes[IDLBuiltinType.Types.object],
-                       affectsNothing=True)
+        self.addMethod("keys", members, False, self.iteratorType,
+                       affectsNothing=True, newObject=True)
         # object values()
-        self.addMethod("values", members, False, BuiltinTypes[IDLBuiltinType.Types.object],
-                       affectsNothing=True, isIteratorAlias=self.isSetlike())
+        self.addMethod("values", members, False, self.iteratorType,
+                       affectsNothing=True, newObject=True,
+                       isIteratorAlias=self.isSetlike())
 
         # void forEach(callback(valueType, keyType), thisVal)
         self.addMethod("forEach", members, False, BuiltinTypes[IDLBuiltinType.Types.void],
@@ -5601,7 +5654,7 @@ class IDLConstructor(IDLMethod):
             if not attr.noArguments():
                 raise WebIDLError("[HTMLConstructor] must take no arguments",
                                   [attr.location])
-            # We shouldn't end up here for named constructors.
+            # We shouldn't end up here for legacy factory functions.
             assert(self.identifier.name == "constructor")
 
             if any(len(sig[1]) != 0 for sig in self.signatures()):
@@ -5826,6 +5879,7 @@ class Tokenizer(object):
         "?": "QUESTIONMARK",
         ",": "COMMA",
         "=": "EQUALS",
+        "*": "ASTERISK",
         "<": "LT",
         ">": "GT",
         "ArrayBuffer": "ARRAYBUFFER",
@@ -5834,7 +5888,6 @@ class Tokenizer(object):
         "setlike": "SETLIKE",
         "iterable": "ITERABLE",
         "namespace": "NAMESPACE",
-        "ReadableStream": "READABLESTREAM",
         "constructor": "CONSTRUCTOR",
         "symbol": "SYMBOL",
         "async": "ASYNC",
@@ -6546,6 +6599,7 @@ class Parser(Tokenizer):
                                                              | Maplike
                                                              | Setlike
                                                              | Iterable
+                                                             | AsyncIterable
                                                              | Operation
         """
         p[0] = p[1]
@@ -6567,6 +6621,36 @@ class Parser(Tokenizer):
 
         p[0] = IDLIterable(location, identifier, keyType, valueType, self.globalScope())
 
+    def p_AsyncIterable(self, p):
+        """
+            AsyncIterable : ASYNC ITERABLE LT TypeWithExtendedAttributes GT SEMICOLON
+                          | ASYNC ITERABLE LT TypeWithExtendedAttributes COMMA TypeWithExtendedAttributes GT SEMICOLON
+                          | ASYNC ITERABLE LT TypeWithExtendedAttributes GT LPAREN ArgumentList RPAREN SEMICOLON
+                          | ASYNC ITERABLE LT TypeWithExtendedAttributes COMMA TypeWithExtendedAttributes GT LPAREN ArgumentList RPAREN SEMICOLON
+        """
+        location = self.getLocation(p, 2)
+        identifier = IDLUnresolvedIdentifier(location, "__iterable",
+                                             allowDoubleUnderscore=True)
+        if len(p) == 12:
+            keyType = p[4]
+            valueType = p[6]
+            argList = p[9]
+        elif len(p) == 10:
+            keyType = None
+            valueType = p[4]
+            argList = p[7]
+        elif len(p) == 9:
+            keyType = p[4]
+            valueType = p[6]
+            argList = []
+        else:
+            keyType = None
+            valueType = p[4]
+            argList = []
+
+        p[0] = IDLAsyncIterable(location, identifier, keyType, valueType, argList,
+                                self.globalScope())
+
     def p_Setlike(self, p):
         """
             Setlike : ReadOnly SETLIKE LT TypeWithExtendedAttributes GT SEMICOLON
@@ -7021,6 +7105,7 @@ class Parser(Tokenizer):
                               | ExtendedAttributeIdent
                               | ExtendedAttributeNamedArgList
                               | ExtendedAttributeIdentList
+                              | ExtendedAttributeWildcard
         """
         p[0] = IDLExtendedAttribute(self.getLocation(p, 1), p[1])
 
@@ -7056,6 +7141,7 @@ class Parser(Tokenizer):
                   | SEMICOLON
                   | LT
                   | EQUALS
+                  | ASTERISK
                   | GT
                   | QUESTIONMARK
                   | DOMSTRING
@@ -7169,15 +7255,12 @@ class Parser(Tokenizer):
         """
             DistinguishableType : PrimitiveType Null
                                 | ARRAYBUFFER Null
-                                | READABLESTREAM Null
                                 | OBJECT Null
         """
         if p[1] == "object":
             type = BuiltinTypes[IDLBuiltinType.Types.object]
         elif p[1] == "ArrayBuffer":
             type = BuiltinTypes[IDLBuiltinType.Types.ArrayBuffer]
-        elif p[1] == "ReadableStream":
-            type = BuiltinTypes[IDLBuiltinType.Types.ReadableStream]
         else:
             type = BuiltinTypes[p[1]]
 
@@ -7452,6 +7535,12 @@ class Parser(Tokenizer):
         """
         p[0] = (p[1], p[3])
 
+    def p_ExtendedAttributeWildcard(self, p):
+        """
+            ExtendedAttributeWildcard : IDENTIFIER EQUALS ASTERISK
+        """
+        p[0] = (p[1], p[3])
+
     def p_ExtendedAttributeNamedArgList(self, p):
         """
             ExtendedAttributeNamedArgList : IDENTIFIER EQUALS IDENTIFIER LPAREN ArgumentList RPAREN
@@ -7578,20 +7667,28 @@ class Parser(Tokenizer):
             # means we have to loop through the members to see if we have an
             # iterable member.
             for m in iface.members:
-                if isinstance(m, IDLIterable):
+                if isinstance(m, (IDLIterable, IDLAsyncIterable, IDLMaplikeOrSetlike)):
                     iterable = m
                     break
-            if iterable and iterable.isPairIterator():
+            if iterable and (iterable.isMaplike() or iterable.isSetlike() or
+                             iterable.isAsyncIterable() or iterable.isPairIterator()):
                 def simpleExtendedAttr(str):
                     return IDLExtendedAttribute(iface.location, (str, ))
+                if iterable.isAsyncIterable():
+                    nextReturnType = IDLPromiseType(iterable.location,
+                                                    BuiltinTypes[IDLBuiltinType.Types.any])
+                    itr_suffix = "AsyncIterator"
+                else:
+                    nextReturnType = BuiltinTypes[IDLBuiltinType.Types.object]
+                    itr_suffix = "Iterator"
                 nextMethod = IDLMethod(
                     iface.location,
                     IDLUnresolvedIdentifier(iface.location, "next"),
-                    BuiltinTypes[IDLBuiltinType.Types.object], [])
+                    nextReturnType, [])
                 nextMethod.addExtendedAttributes([simpleExtendedAttr("Throws")])
                 itr_ident = IDLUnresolvedIdentifier(iface.location,
-                                                    iface.identifier.name + "Iterator")
-                toStringTag = iface.identifier.name + " Iterator"
+                                                    iface.identifier.name + itr_suffix)
+                toStringTag = iface.identifier.name + " " + itr_suffix
                 itr_iface = IDLInterface(iface.location, self.globalScope(),
                                          itr_ident, None, [nextMethod],
                                          isKnownNonPartial=True,
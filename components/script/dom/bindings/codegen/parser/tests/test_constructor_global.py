@@ -20,9 +20,9 @@ def WebIDLTest(parser, harness):
     threw = False
     try:
         parser.parse("""
-            [Global, Exposed=TestNamedConstructorGlobal,
-             NamedConstructor=FooBar]
-            interface TestNamedConstructorGlobal {
+            [Global, Exposed=TestLegacyFactoryFunctionGlobal,
+             LegacyFactoryFunction=FooBar]
+            interface TestLegacyFactoryFunctionGlobal {
             };
         """)
         results = parser.finish()
@@ -35,9 +35,9 @@ def WebIDLTest(parser, harness):
     threw = False
     try:
         parser.parse("""
-            [NamedConstructor=FooBar, Global,
-             Exposed=TestNamedConstructorGlobal]
-            interface TestNamedConstructorGlobal {
+            [LegacyFactoryFunction=FooBar, Global,
+             Exposed=TestLegacyFactoryFunctionGlobal]
+            interface TestLegacyFactoryFunctionGlobal {
             };
         """)
         results = parser.finish()
@@ -17,8 +17,8 @@ def WebIDLTest(parser, harness):
     parser = parser.reset()
 
     parser.parse("""
-        [NoInterfaceObject, NamedConstructor=FooBar]
-        interface TestNamedConstructorNoInterfaceObject {
+        [NoInterfaceObject, LegacyFactoryFunction=FooBar]
+        interface TestLegacyFactoryFunctionNoInterfaceObject {
         };
     """)
 
@@ -236,3 +236,33 @@ def WebIDLTest(parser, harness):
                "otherMethod should have the right exposure global names")
 
 
+
+    parser = parser.reset()
+    parser.parse("""
+      [Global, Exposed=Foo] interface Foo {};
+      [Global=(Bar, Bar1, Bar2), Exposed=Bar] interface Bar {};
+      [Global=(Baz, Baz2), Exposed=Baz] interface Baz {};
+
+      [Exposed=*]
+      interface Iface4 {
+        void method5();
+      };
+    """)
+    results = parser.finish()
+
+    harness.check(len(results), 4, "Should know about four things");
+    iface = results[3]
+    harness.ok(isinstance(iface, WebIDL.IDLInterface),
+               "Should have an interface here");
+    members = iface.members
+    harness.check(len(members), 1, "Should have one member")
+
+    harness.ok(members[0].exposureSet == set(["Foo", "Bar", "Baz"]),
+               "method5 should have the right exposure set")
+    harness.ok(members[0]._exposureGlobalNames == set(["Foo", "Bar", "Bar1", "Bar2", "Baz", "Baz2"]),
+               "method5 should have the right exposure global names")
+
+    harness.ok(iface.exposureSet == set(["Foo", "Bar", "Baz"]),
+               "Iface4 should have the right exposure set")
+    harness.ok(iface._exposureGlobalNames == set(["Foo", "Bar", "Bar1", "Bar2", "Baz", "Baz2"]),
+               "Iface4 should have the right exposure global names")
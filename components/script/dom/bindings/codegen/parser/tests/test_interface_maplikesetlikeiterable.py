@@ -78,6 +78,11 @@ def WebIDLTest(parser, harness):
     # __iterable to it for the iterable<> case.
     iterableMembers.append(("__iterable", WebIDL.IDLIterable))
 
+    asyncIterableMembers = [("__iterable", WebIDL.IDLAsyncIterable),
+                            ("values", WebIDL.IDLMethod)]
+    pairAsyncIterableMembers = ([(x, WebIDL.IDLMethod) for x in ["entries", "keys"]] +
+                                asyncIterableMembers)
+
     valueIterableMembers = [("__iterable", WebIDL.IDLIterable)]
     valueIterableMembers.append(("__indexedgetter", WebIDL.IDLMethod))
     valueIterableMembers.append(("length", WebIDL.IDLAttribute))
@@ -143,6 +148,49 @@ def WebIDLTest(parser, harness):
                # numProductions == 3 because of the generated iterator iface,
                numProductions=3)
 
+    shouldPass("Async iterable (key only)",
+               """
+               interface Foo1 {
+               async iterable<long>;
+               attribute long unrelatedAttribute;
+               long unrelatedMethod();
+               };
+               """, asyncIterableMembers + unrelatedMembers,
+               # numProductions == 2 because of the generated iterator iface,
+               numProductions=2)
+
+    shouldPass("Async iterable (key and value)",
+               """
+               interface Foo1 {
+               async iterable<long, long>;
+               attribute long unrelatedAttribute;
+               long unrelatedMethod();
+               };
+               """, pairAsyncIterableMembers + unrelatedMembers,
+               # numProductions == 2 because of the generated iterator iface,
+               numProductions=2)
+
+    results = shouldPass("Async iterable with optional arguments",
+                         """
+                         interface Foo1 {
+                         async iterable<long>(optional long start = 0);
+                         };
+                         """, asyncIterableMembers, numProductions=2)
+    values = [m for m in results[0].members if m.identifier.name == "values"][0]
+    harness.check(len(values.signatures()[0][1]), 1,
+                  "values() of an async iterable should take the declared arguments")
+    harness.check(results[1].identifier.name, "Foo1AsyncIterator",
+                  "Async iterable should generate an async iterator interface")
+    harness.ok(results[1].members[0].signatures()[0][0].isPromise(),
+               "next() of an async iterator should return a promise")
+
+    shouldFail("Async iterable with non-optional arguments",
+               """
+               interface Foo1 {
+               async iterable<long>(long start);
+               };
+               """)
+
     shouldPass("Maplike (readwrite)",
                """
                interface Foo1 {
@@ -150,7 +198,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, mapRWMembers + unrelatedMembers)
+               """, mapRWMembers + unrelatedMembers, numProductions=2)
 
     shouldPass("Maplike (readwrite) inheriting from parent",
                """
@@ -161,7 +209,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, mapRWMembers, numProductions=2)
+               """, mapRWMembers, numProductions=3)
 
     shouldPass("Maplike (readwrite)",
                """
@@ -170,7 +218,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, mapRWMembers + unrelatedMembers)
+               """, mapRWMembers + unrelatedMembers, numProductions=2)
 
     shouldPass("Maplike (readwrite) inheriting from parent",
                """
@@ -181,7 +229,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, mapRWMembers, numProductions=2)
+               """, mapRWMembers, numProductions=3)
 
     shouldPass("Maplike (readonly)",
                """
@@ -190,7 +238,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, mapROMembers + unrelatedMembers)
+               """, mapROMembers + unrelatedMembers, numProductions=2)
 
     shouldPass("Maplike (readonly) inheriting from parent",
                """
@@ -201,7 +249,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, mapROMembers, numProductions=2)
+               """, mapROMembers, numProductions=3)
 
     shouldPass("Setlike (readwrite)",
                """
@@ -210,7 +258,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, setRWMembers + unrelatedMembers)
+               """, setRWMembers + unrelatedMembers, numProductions=2)
 
     shouldPass("Setlike (readwrite) inheriting from parent",
                """
@@ -221,7 +269,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, setRWMembers, numProductions=2)
+               """, setRWMembers, numProductions=3)
 
     shouldPass("Setlike (readonly)",
                """
@@ -230,7 +278,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, setROMembers + unrelatedMembers)
+               """, setROMembers + unrelatedMembers, numProductions=2)
 
     shouldPass("Setlike (readonly) inheriting from parent",
                """
@@ -241,7 +289,7 @@ def WebIDLTest(parser, harness):
                attribute long unrelatedAttribute;
                long unrelatedMethod();
                };
-               """, setROMembers, numProductions=2)
+               """, setROMembers, numProductions=3)
 
     shouldPass("Inheritance of maplike/setlike",
                """
@@ -250,7 +298,7 @@ def WebIDLTest(parser, harness):
                };
                interface Foo2 : Foo1 {
                };
-               """, mapRWMembers, numProductions=2)
+               """, mapRWMembers, numProductions=3)
 
     shouldPass("JS Implemented maplike interface",
                """
@@ -259,7 +307,7 @@ def WebIDLTest(parser, harness):
                constructor();
                setlike<long>;
                };
-               """, setRWChromeMembers)
+               """, setRWChromeMembers, numProductions=2)
 
     shouldPass("JS Implemented maplike interface",
                """
@@ -268,7 +316,7 @@ def WebIDLTest(parser, harness):
                constructor();
                maplike<long, long>;
                };
-               """, mapRWChromeMembers)
+               """, mapRWChromeMembers, numProductions=2)
 
     #
     # Multiple maplike/setlike tests
@@ -360,7 +408,9 @@ def WebIDLTest(parser, harness):
                        [Throws]
                        void %s(long test1, double test2, double test3);
                        };
-                       """ % (likeMember, conflictName), expectedMembers)
+                       """ % (likeMember, conflictName), expectedMembers,
+                       # numProductions == 2 because of the generated iterator iface,
+                       numProductions=2)
         else:
             shouldFail("Conflicting method: %s and %s" % (likeMember, conflictName),
                        """
@@ -428,7 +478,7 @@ def WebIDLTest(parser, harness):
                interface Foo2 : Foo1 {
                void entries();
                };
-               """, mapRWMembers, numProductions=2)
+               """, mapRWMembers, numProductions=3)
 
     shouldPass("Inheritance of multi-level maplike/setlike with child member collision",
                """
@@ -440,7 +490,7 @@ def WebIDLTest(parser, harness):
                interface Foo3 : Foo2 {
                void entries();
                };
-               """, mapRWMembers, numProductions=3)
+               """, mapRWMembers, numProductions=4)
 
     shouldFail("Maplike interface with mixin member collision",
                """
@@ -464,7 +514,7 @@ def WebIDLTest(parser, harness):
                interface Foo3 : Foo1 {
                };
                Foo3 includes Foo2;
-               """, mapRWMembers, numProductions=4)
+               """, mapRWMembers, numProductions=5)
 
     shouldFail("Inheritance of name collision with child maplike/setlike",
                """
@@ -496,7 +546,7 @@ def WebIDLTest(parser, harness):
                interface Foo2 : Foo1 {
                attribute double size;
                };
-               """, mapRWMembers, numProductions=2)
+               """, mapRWMembers, numProductions=3)
 
     shouldPass("Inheritance of multi-level attribute collision with parent maplike/setlike",
                """
@@ -508,7 +558,7 @@ def WebIDLTest(parser, harness):
                interface Foo3 : Foo2 {
                attribute double size;
                };
-               """, mapRWMembers, numProductions=3)
+               """, mapRWMembers, numProductions=4)
 
     shouldFail("Inheritance of attribute collision with child maplike/setlike",
                """
@@ -560,7 +610,7 @@ def WebIDLTest(parser, harness):
                interface Foo2 : Foo1 {
                void clear();
                };
-               """, mapRWMembers, numProductions=2)
+               """, mapRWMembers, numProductions=3)
 
     shouldFail("Inheritance of unforgeable attribute collision with child maplike/setlike",
                """
@@ -592,7 +642,7 @@ def WebIDLTest(parser, harness):
                readonly setlike<long>;
                readonly attribute boolean clear;
                };
-               """, setROMembers + [("clear", WebIDL.IDLAttribute)])
+               """, setROMembers + [("clear", WebIDL.IDLAttribute)], numProductions=2)
 
     shouldPass("JS Implemented read-only interface with readonly allowable overrides",
                """
@@ -602,7 +652,7 @@ def WebIDLTest(parser, harness):
                readonly setlike<long>;
                readonly attribute boolean clear;
                };
-               """, setROChromeMembers + [("clear", WebIDL.IDLAttribute)])
+               """, setROChromeMembers + [("clear", WebIDL.IDLAttribute)], numProductions=2)
 
     shouldFail("JS Implemented read-write interface with non-readwrite allowable overrides",
                """
@@ -622,7 +672,7 @@ def WebIDLTest(parser, harness):
                    long set(long a, long b, double c, double d);
                    long delete(long a, long b, double c, double d);
                    };
-                   """, mapRWMembers)
+                   """, mapRWMembers, numProductions=2)
 
     for m in r[0].members:
         if m.identifier.name in ["clear", "set", "delete"]:
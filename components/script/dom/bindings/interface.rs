@@ -32,6 +32,7 @@ use js::rust::wrappers::{JS_DefineProperty3, JS_DefineProperty4, JS_DefineProper
 use js::rust::wrappers::{JS_LinkConstructorAndPrototype, JS_NewObjectWithUniqueType};
 use js::rust::{define_methods, define_properties, get_object_class};
 use js::rust::{HandleObject, HandleValue, MutableHandleObject, RealmOptions};
+use servo_config::pref;
 use std::convert::TryFrom;
 use std::ptr;
 
@@ -138,6 +139,7 @@ pub unsafe fn create_global_object(
     let mut options = RealmOptions::default();
     options.creationOptions_.traceGlobal_ = Some(trace);
     options.creationOptions_.sharedMemoryAndAtomics_ = true;
+    options.creationOptions_.weakRefs_ = pref!(js.weakrefs.enabled);
 
     rval.set(JS_NewGlobalObject(
         *cx,
@@ -266,16 +268,16 @@ pub fn create_noncallback_interface_object(
     }
 }
 
-/// Create and define the named constructors of a non-callback interface.
-pub fn create_named_constructors(
+/// Create and define the legacy factory functions of a non-callback interface.
+pub fn create_legacy_factory_functions(
     cx: SafeJSContext,
     global: HandleObject,
-    named_constructors: &[(ConstructorClassHook, &[u8], u32)],
+    legacy_factory_functions: &[(ConstructorClassHook, &[u8], u32)],
     interface_prototype_object: HandleObject,
 ) {
     rooted!(in(*cx) let mut constructor = ptr::null_mut::<JSObject>());
 
-    for &(native, name, arity) in named_constructors {
+    for &(native, name, arity) in legacy_factory_functions {
         assert_eq!(*name.last().unwrap(), b'\0');
 
         unsafe {
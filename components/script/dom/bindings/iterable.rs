@@ -4,8 +4,10 @@
 
 #![allow(unsafe_code)]
 
-//! Implementation of `iterable<...>` and `iterable<..., ...>` WebIDL declarations.
+//! Implementation of `iterable<...>`, `iterable<..., ...>`, `async iterable<...>`
+//! and `async iterable<..., ...>` WebIDL declarations.
 
+use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::IterableIteratorBinding::IterableKeyAndValueResult;
 use crate::dom::bindings::codegen::Bindings::IterableIteratorBinding::IterableKeyOrValueResult;
 use crate::dom::bindings::error::Fallible;
@@ -13,18 +15,23 @@ use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::trace::{JSTraceable, RootedTraceableBox};
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::realms::enter_realm;
 use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
 use js::conversions::ToJSValConvertible;
-use js::jsapi::{Heap, JSObject};
-use js::jsval::UndefinedValue;
+use js::jsapi::{Heap, JSContext as RawJSContext, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
 use js::rust::{HandleValue, MutableHandleObject};
+use malloc_size_of::MallocSizeOf;
 use std::cell::Cell;
 use std::ptr;
 use std::ptr::NonNull;
+use std::rc::Rc;
 
 /// The values that an iterator will iterate over.
-#[derive(JSTraceable, MallocSizeOf)]
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
 pub enum IteratorType {
     /// The keys of the iterable object.
     Keys,
@@ -119,6 +126,222 @@ impl<T: DomObject + JSTraceable + Iterable> IterableIterator<T> {
     }
 }
 
+/// A DOM object that can be iterated over asynchronously, using `for await`.
+pub trait AsyncIterable: DomObject + JSTraceable + MallocSizeOf + Sized {
+    /// The arguments of the `async iterable` declaration, as a tuple.
+    type Arguments;
+    /// The state kept by each iterator over the object.
+    type State: JSTraceable;
+    /// Run the asynchronous iterator initialization steps, returning the
+    /// state of a new iterator.
+    /// <https://heycam.github.io/webidl/#dfn-asynchronous-iterator-initialization-steps>
+    fn init_async_iterator(&self, args: Self::Arguments) -> Fallible<Self::State>;
+    /// Get the next iteration result of the iterator.
+    /// <https://heycam.github.io/webidl/#dfn-get-the-next-iteration-result>
+    ///
+    /// The returned promise is resolved with the next value for value
+    /// iterators, with `AsyncIterableIterator::resolve_pair` for pair
+    /// iterators, or with `AsyncIterableIterator::end_iteration` once there
+    /// are no values left.
+    fn get_next_iteration_result(&self, iterator: &AsyncIterableIterator<Self>) -> Rc<Promise>;
+}
+
+/// An asynchronous iterator over the values of a given DOM interface.
+#[dom_struct]
+pub struct AsyncIterableIterator<T: AsyncIterable> {
+    reflector: Reflector,
+    iterable: Dom<T>,
+    type_: IteratorType,
+    #[ignore_malloc_size_of = "defined by the async iterable interface"]
+    state: T::State,
+    #[ignore_malloc_size_of = "Rc"]
+    ongoing_promise: DomRefCell<Option<Rc<Promise>>>,
+    is_finished: Cell<bool>,
+    end_of_iteration: Cell<bool>,
+}
+
+impl<T: AsyncIterable> AsyncIterableIterator<T> {
+    /// Create a new asynchronous iterator instance for the provided async
+    /// iterable DOM interface.
+    pub fn new(
+        iterable: &T,
+        type_: IteratorType,
+        args: T::Arguments,
+        wrap: unsafe fn(JSContext, &GlobalScope, Box<AsyncIterableIterator<T>>) -> DomRoot<Self>,
+    ) -> Fallible<DomRoot<Self>> {
+        let state = iterable.init_async_iterator(args)?;
+        let iterator = Box::new(AsyncIterableIterator {
+            reflector: Reflector::new(),
+            iterable: Dom::from_ref(iterable),
+            type_: type_,
+            state: state,
+            ongoing_promise: DomRefCell::new(None),
+            is_finished: Cell::new(false),
+            end_of_iteration: Cell::new(false),
+        });
+        Ok(reflect_dom_object(iterator, &*iterable.global(), wrap))
+    }
+
+    /// The state of this iterator, as returned by `init_async_iterator`.
+    pub fn state(&self) -> &T::State {
+        &self.state
+    }
+
+    /// Resolve a promise returned by `get_next_iteration_result` to signal
+    /// that there are no values left.
+    pub fn end_iteration(&self, promise: &Promise) {
+        self.end_of_iteration.set(true);
+        promise.resolve_native(&());
+    }
+
+    /// Resolve a promise returned by `get_next_iteration_result` with the next
+    /// key and value of a pair iterator.
+    pub fn resolve_pair(
+        &self,
+        cx: JSContext,
+        promise: &Promise,
+        key: HandleValue,
+        value: HandleValue,
+    ) {
+        let _ac = enter_realm(self);
+        match self.type_ {
+            IteratorType::Keys => promise.resolve(cx, key),
+            IteratorType::Values => promise.resolve(cx, value),
+            IteratorType::Entries => {
+                let pair: Vec<_> = vec![key, value]
+                    .into_iter()
+                    .map(|handle| RootedTraceableBox::from_box(Heap::boxed(handle.get())))
+                    .collect();
+                promise.resolve_native(&pair);
+            },
+        }
+    }
+
+    /// Return a promise for the next value of the async iterable object.
+    /// <https://heycam.github.io/webidl/#es-asynchronous-iterator-prototype-object>
+    #[allow(non_snake_case, unrooted_must_root)]
+    pub fn Next(&self) -> Fallible<Rc<Promise>> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let ongoing_promise = self.ongoing_promise.borrow_mut().replace(promise.clone());
+        match ongoing_promise {
+            // Wait for the previous call to settle, so that values are
+            // produced in order even if next() is called repeatedly.
+            Some(ongoing_promise) => {
+                let handler = || -> Box<dyn Callback> {
+                    Box::new(NextSteps {
+                        iterator: Dom::from_ref(self),
+                        promise: promise.clone(),
+                    })
+                };
+                let handler = PromiseNativeHandler::new(&global, Some(handler()), Some(handler()));
+                ongoing_promise.append_native_handler(&handler);
+            },
+            None => self.next_steps(&promise),
+        }
+        Ok(promise)
+    }
+
+    #[allow(unrooted_must_root)]
+    fn next_steps(&self, promise: &Rc<Promise>) {
+        if self.is_finished.get() {
+            let cx = self.global().get_cx();
+            self.resolve_iterator_result(cx, promise, true, HandleValue::undefined());
+            return;
+        }
+        let next_promise = self.iterable.get_next_iteration_result(self);
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(NextIterationResult {
+                iterator: Dom::from_ref(self),
+                promise: promise.clone(),
+                fulfilled,
+            })
+        };
+        let handler =
+            PromiseNativeHandler::new(&self.global(), Some(handler(true)), Some(handler(false)));
+        next_promise.append_native_handler(&handler);
+    }
+
+    fn next_iteration_fulfilled(&self, cx: JSContext, promise: &Rc<Promise>, next: HandleValue) {
+        self.clear_ongoing_promise(promise);
+        if self.end_of_iteration.replace(false) {
+            self.is_finished.set(true);
+            self.resolve_iterator_result(cx, promise, true, HandleValue::undefined());
+        } else {
+            self.resolve_iterator_result(cx, promise, false, next);
+        }
+    }
+
+    fn next_iteration_rejected(&self, cx: JSContext, promise: &Rc<Promise>, reason: HandleValue) {
+        self.clear_ongoing_promise(promise);
+        self.is_finished.set(true);
+        promise.reject(cx, reason);
+    }
+
+    fn clear_ongoing_promise(&self, promise: &Rc<Promise>) {
+        // Later calls to next() may already have replaced the ongoing promise.
+        let mut ongoing_promise = self.ongoing_promise.borrow_mut();
+        if ongoing_promise
+            .as_ref()
+            .map_or(false, |ongoing| Rc::ptr_eq(ongoing, promise))
+        {
+            *ongoing_promise = None;
+        }
+    }
+
+    fn resolve_iterator_result(
+        &self,
+        cx: JSContext,
+        promise: &Promise,
+        done: bool,
+        value: HandleValue,
+    ) {
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut rval = ptr::null_mut::<JSObject>());
+        let _ = dict_return(cx, rval.handle_mut(), done, value);
+        rooted!(in(*cx) let result: JSVal = ObjectValue(rval.get()));
+        promise.resolve(cx, result.handle());
+    }
+}
+
+/// Runs the next steps of an asynchronous iterator once its previous
+/// `next()` call settled.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct NextSteps<T: AsyncIterable> {
+    iterator: Dom<AsyncIterableIterator<T>>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+}
+
+impl<T: AsyncIterable> Callback for NextSteps<T> {
+    fn callback(&self, _cx: *mut RawJSContext, _v: HandleValue) {
+        self.iterator.next_steps(&self.promise);
+    }
+}
+
+/// Turns the next iteration result of an async iterable object into the
+/// result of the `next()` call that requested it.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct NextIterationResult<T: AsyncIterable> {
+    iterator: Dom<AsyncIterableIterator<T>>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl<T: AsyncIterable> Callback for NextIterationResult<T> {
+    fn callback(&self, cx: *mut RawJSContext, v: HandleValue) {
+        let cx = unsafe { JSContext::from_ptr(cx) };
+        if self.fulfilled {
+            self.iterator.next_iteration_fulfilled(cx, &self.promise, v);
+        } else {
+            self.iterator.next_iteration_rejected(cx, &self.promise, v);
+        }
+    }
+}
+
 fn dict_return(
     cx: JSContext,
     mut result: MutableHandleObject,
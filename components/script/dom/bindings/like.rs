@@ -0,0 +1,121 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Implementation of `setlike<...>` and `maplike<..., ...>` WebIDL declarations.
+
+use crate::dom::bindings::iterable::Iterable;
+use js::conversions::ToJSValConvertible;
+
+/// A DOM object with a `setlike<...>` declaration.
+///
+/// The generated bindings call these methods for the `size` attribute and the
+/// `has`, `add`, `delete` and `clear` operations; iteration goes through the
+/// entries in the order returned by `get_index`. The `setlike!` macro
+/// implements it for entries stored in an `IndexSet`.
+pub trait Setlike {
+    /// The type of the entries of the set.
+    type Key: ToJSValConvertible + Clone;
+
+    /// Return the entry at the provided index, in insertion order.
+    fn get_index(&self, index: u32) -> Option<Self::Key>;
+    /// Return the number of entries in the set.
+    fn size(&self) -> u32;
+    /// Add an entry to the set.
+    fn add(&self, key: Self::Key);
+    /// Return whether the set contains the entry.
+    fn has(&self, key: Self::Key) -> bool;
+    /// Remove all entries from the set.
+    fn clear(&self);
+    /// Remove an entry from the set, returning whether it was present.
+    fn delete(&self, key: Self::Key) -> bool;
+}
+
+/// A DOM object with a `maplike<..., ...>` declaration.
+///
+/// The generated bindings call these methods for the `size` attribute and the
+/// `get`, `has`, `set`, `delete` and `clear` operations; iteration goes through
+/// the entries in the order returned by `get_index`. The `maplike!` macro
+/// implements it for entries stored in an `IndexMap`.
+pub trait Maplike {
+    /// The type of the keys of the map.
+    type Key: ToJSValConvertible;
+    /// The type of the values of the map.
+    type Value: ToJSValConvertible;
+
+    /// Return the entry at the provided index, in insertion order.
+    fn get_index(&self, index: u32) -> Option<(Self::Key, Self::Value)>;
+    /// Return the value associated with the key, if any.
+    fn get(&self, key: Self::Key) -> Option<Self::Value>;
+    /// Return the number of entries in the map.
+    fn size(&self) -> u32;
+    /// Associate the value with the key, replacing any previous value.
+    fn set(&self, key: Self::Key, value: Self::Value);
+    /// Return whether the map contains the key.
+    fn has(&self, key: Self::Key) -> bool;
+    /// Remove all entries from the map.
+    fn clear(&self);
+    /// Remove the entry for the key, returning whether it was present.
+    fn delete(&self, key: Self::Key) -> bool;
+}
+
+// An interface can only have a single iterable declaration, so setlike
+// interfaces are iterated over as maps whose keys are their own values,
+// and maplike interfaces are iterated over through their entries.
+impl<T: Setlike> Maplike for T {
+    type Key = <T as Setlike>::Key;
+    type Value = <T as Setlike>::Key;
+
+    fn get_index(&self, index: u32) -> Option<(Self::Key, Self::Value)> {
+        Setlike::get_index(self, index).map(|key| (key.clone(), key))
+    }
+
+    fn get(&self, key: Self::Key) -> Option<Self::Value> {
+        if Setlike::has(self, key.clone()) {
+            Some(key)
+        } else {
+            None
+        }
+    }
+
+    fn size(&self) -> u32 {
+        Setlike::size(self)
+    }
+
+    fn set(&self, key: Self::Key, _value: Self::Value) {
+        Setlike::add(self, key)
+    }
+
+    fn has(&self, key: Self::Key) -> bool {
+        Setlike::has(self, key)
+    }
+
+    fn clear(&self) {
+        Setlike::clear(self)
+    }
+
+    fn delete(&self, key: Self::Key) -> bool {
+        Setlike::delete(self, key)
+    }
+}
+
+impl<T: Maplike> Iterable for T {
+    type Key = <T as Maplike>::Key;
+    type Value = <T as Maplike>::Value;
+
+    fn get_iterable_length(&self) -> u32 {
+        Maplike::size(self)
+    }
+
+    fn get_value_at_index(&self, index: u32) -> Self::Value {
+        Maplike::get_index(self, index)
+            .expect("iterated past the end of a maplike")
+            .1
+    }
+
+    fn get_key_at_index(&self, index: u32) -> Self::Key {
+        Maplike::get_index(self, index)
+            .expect("iterated past the end of a maplike")
+            .0
+    }
+}
@@ -4,10 +4,11 @@
 
 //! Machinery to initialise namespace objects.
 
+use crate::dom::bindings::constant::ConstantSpec;
 use crate::dom::bindings::guard::Guard;
 use crate::dom::bindings::interface::{create_object, define_on_global_object};
 use crate::script_runtime::JSContext;
-use js::jsapi::{JSClass, JSFunctionSpec};
+use js::jsapi::{JSClass, JSFunctionSpec, JSPropertySpec};
 use js::rust::{HandleObject, MutableHandleObject};
 use std::ptr;
 
@@ -38,9 +39,13 @@ pub fn create_namespace_object(
     proto: HandleObject,
     class: &'static NamespaceObjectClass,
     methods: &[Guard<&'static [JSFunctionSpec]>],
+    properties: &[Guard<&'static [JSPropertySpec]>],
+    constants: &[Guard<&[ConstantSpec]>],
     name: &[u8],
     rval: MutableHandleObject,
 ) {
-    create_object(cx, global, proto, &class.0, methods, &[], &[], rval);
+    create_object(
+        cx, global, proto, &class.0, methods, properties, constants, rval,
+    );
     define_on_global_object(cx, global, name, rval.handle());
 }
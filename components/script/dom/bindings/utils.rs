@@ -389,7 +389,7 @@ pub unsafe extern "C" fn enumerate_global(
     true
 }
 
-/// Resolve a lazy global property, for interface objects and named constructors.
+/// Resolve a lazy global property, for interface objects and legacy factory functions.
 pub unsafe extern "C" fn resolve_global(
     cx: *mut JSContext,
     obj: RawHandleObject,
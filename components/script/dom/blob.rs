@@ -12,8 +12,9 @@ use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::serializable::{Serializable, StorageKey};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::bindings::structuredclone::StructuredDataHolder;
-use crate::dom::globalscope::GlobalScope;
+use crate::dom::globalscope::{BlobContents, GlobalScope};
 use crate::dom::promise::Promise;
+use crate::dom::readablestream::ReadableStream;
 use crate::realms::{AlreadyInRealm, InRealm};
 use dom_struct::dom_struct;
 use encoding_rs::UTF_8;
@@ -22,6 +23,7 @@ use net_traits::filemanager_thread::RelativePos;
 use script_traits::serializable::BlobImpl;
 use std::collections::HashMap;
 use std::num::NonZeroU32;
+use std::ops::Range;
 use std::rc::Rc;
 use uuid::Uuid;
 
@@ -81,6 +83,18 @@ impl Blob {
         self.global().get_blob_bytes(&self.blob_id)
     }
 
+    /// Get the contents of the blob, which are streamed from disk
+    /// for an uncached File-backed blob
+    pub fn get_contents(&self) -> Result<BlobContents, ()> {
+        self.global().get_blob_contents(&self.blob_id)
+    }
+
+    /// Get the contents of a range of the bytes of the blob, of which only the range is
+    /// read from disk for a File-backed blob
+    pub fn get_range_contents(&self, range: Range<usize>) -> Result<BlobContents, ()> {
+        self.global().get_blob_range_contents(&self.blob_id, range)
+    }
+
     /// Get a copy of the type_string
     pub fn type_string(&self) -> String {
         self.global().get_blob_type_string(&self.blob_id)
@@ -229,6 +243,12 @@ impl BlobMethods for Blob {
         Blob::new(&*self.global(), blob_impl)
     }
 
+    // https://w3c.github.io/FileAPI/#stream-method-algo
+    fn Stream(&self) -> DomRoot<ReadableStream> {
+        // The contents are read a chunk at a time, as the stream is pulled.
+        ReadableStream::new_from_blob(self)
+    }
+
     // https://w3c.github.io/FileAPI/#text-method-algo
     fn Text(&self) -> Rc<Promise> {
         let global = self.global();
@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ByteLengthQueuingStrategyBinding::{
+    self, ByteLengthQueuingStrategyMethods,
+};
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategyInit;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::error::throw_type_error;
+use js::jsapi::{CallArgs, Heap, JSContext, JSNative, JSObject};
+use js::jsapi::{JS_GetFunctionObject, JS_GetProperty, JS_NewFunction};
+use js::jsval::JSVal;
+use js::rust::HandleValue;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct ByteLengthQueuingStrategy {
+    reflector_: Reflector,
+    high_water_mark: f64,
+    #[ignore_malloc_size_of = "mozjs"]
+    size: Heap<*mut JSObject>,
+}
+
+impl ByteLengthQueuingStrategy {
+    fn new_inherited(high_water_mark: f64) -> ByteLengthQueuingStrategy {
+        ByteLengthQueuingStrategy {
+            reflector_: Reflector::new(),
+            high_water_mark,
+            size: Heap::default(),
+        }
+    }
+
+    fn new(global: &GlobalScope, high_water_mark: f64) -> DomRoot<ByteLengthQueuingStrategy> {
+        reflect_dom_object(
+            Box::new(ByteLengthQueuingStrategy::new_inherited(high_water_mark)),
+            global,
+            ByteLengthQueuingStrategyBinding::Wrap,
+        )
+    }
+
+    // https://streams.spec.whatwg.org/#blqs-constructor
+    pub fn Constructor(
+        global: &GlobalScope,
+        init: &QueuingStrategyInit,
+    ) -> Fallible<DomRoot<ByteLengthQueuingStrategy>> {
+        Ok(ByteLengthQueuingStrategy::new(global, init.highWaterMark))
+    }
+}
+
+impl ByteLengthQueuingStrategyMethods for ByteLengthQueuingStrategy {
+    // https://streams.spec.whatwg.org/#blqs-high-water-mark
+    fn HighWaterMark(&self) -> f64 {
+        self.high_water_mark
+    }
+
+    // https://streams.spec.whatwg.org/#blqs-size
+    fn Size(&self) -> Rc<Function> {
+        size_function(self.global().get_cx(), &self.size, Some(byte_length_size))
+    }
+}
+
+/// The size method of a queuing strategy, which is created the first time it is got.
+#[allow(unsafe_code)]
+pub fn size_function(
+    cx: SafeJSContext,
+    cache: &Heap<*mut JSObject>,
+    native: JSNative,
+) -> Rc<Function> {
+    unsafe {
+        if cache.get().is_null() {
+            let function = JS_NewFunction(*cx, native, 1, 0, b"size\0".as_ptr() as *const _);
+            assert!(!function.is_null());
+            cache.set(JS_GetFunctionObject(function));
+        }
+        Function::new(cx, cache.get())
+    }
+}
+
+/// The size of a chunk, which is its length in bytes.
+#[allow(unsafe_code)]
+unsafe extern "C" fn byte_length_size(cx: *mut JSContext, argc: u32, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let chunk = HandleValue::from_raw(args.get(0));
+    if !chunk.is_object() {
+        throw_type_error(cx, "The chunk does not have a length in bytes");
+        return false;
+    }
+    rooted!(in(cx) let chunk = chunk.to_object());
+    JS_GetProperty(
+        cx,
+        chunk.handle().into(),
+        b"byteLength\0".as_ptr() as *const _,
+        args.rval(),
+    )
+}
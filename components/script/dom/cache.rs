@@ -0,0 +1,549 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::body::{consume_body, BodyOperations, BodyType};
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::CacheBinding::{
+    self, CacheMethods, CacheQueryOptions,
+};
+use crate::dom::bindings::codegen::Bindings::RequestBinding::{RequestInfo, RequestInit};
+use crate::dom::bindings::codegen::Bindings::ResponseBinding::{
+    ResponseMethods, ResponseType as DOMResponseType,
+};
+use crate::dom::bindings::conversions::root_from_handlevalue;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::{PendingPromise, Promise};
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::request::Request;
+use crate::dom::response::Response;
+use crate::fetch;
+use crate::realms::{AlreadyInRealm, InRealm};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::task_source::TaskSourceName;
+use dom_struct::dom_struct;
+use ipc_channel::ipc::{self, IpcSender};
+use js::jsapi::JSContext;
+use js::rust::HandleValue;
+use js::typedarray::ArrayBuffer;
+use net_traits::storage_thread::CacheQueryOptions as NetTraitsCacheQueryOptions;
+use net_traits::storage_thread::StorageThreadMsg;
+use net_traits::storage_thread::{CacheId, CacheOperation, CacheOperationError};
+use net_traits::storage_thread::{CacheQueryTarget, CacheRequest, CacheResponse};
+use net_traits::IpcSend;
+use serde::{Deserialize, Serialize};
+use servo_url::ImmutableOrigin;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// <https://w3c.github.io/ServiceWorker/#cache-interface>
+#[dom_struct]
+pub struct Cache {
+    reflector_: Reflector,
+    /// The cache of the origin which this object refers to, even after it is deleted.
+    id: CacheId,
+}
+
+impl Cache {
+    fn new_inherited(id: CacheId) -> Cache {
+        Cache {
+            reflector_: Reflector::new(),
+            id,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, id: CacheId) -> DomRoot<Cache> {
+        reflect_dom_object(
+            Box::new(Cache::new_inherited(id)),
+            global,
+            CacheBinding::Wrap,
+        )
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#cache-matchall>
+    fn query(
+        &self,
+        request: Option<RequestInfo>,
+        options: &CacheQueryOptions,
+        steps: impl FnOnce(Rc<Promise>, Vec<(CacheRequest, CacheResponse)>) + Send + 'static,
+    ) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let origin = match cache_storage_origin(&global) {
+            Ok(origin) => origin,
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        // Steps 2-3.
+        let request = match request {
+            Some(request) => match query_request(&global, request, options) {
+                Ok(Some(request)) => Some(request),
+                Ok(None) => {
+                    steps(promise.clone(), vec![]);
+                    return promise;
+                },
+                Err(error) => {
+                    promise.reject_error(error);
+                    return promise;
+                },
+            },
+            None => None,
+        };
+        let target = CacheQueryTarget::Cache(self.id);
+        let options = net_traits_query_options(options);
+        request_from_cache_storage(
+            &promise,
+            |sender| StorageThreadMsg::QueryCache(sender, origin, target, request, options),
+            steps,
+        );
+        promise
+    }
+
+    /// Fetch requests and put their responses in the cache, once all of them are received.
+    /// <https://w3c.github.io/ServiceWorker/#cache-addAll>
+    fn add_all(&self, requests: Vec<RequestInfo>) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        if let Err(error) = cache_storage_origin(&global) {
+            promise.reject_error(error);
+            return promise;
+        }
+
+        // Steps 1-3.
+        let mut request_objects = vec![];
+        for request in requests {
+            match request_from_info(&global, request).and_then(|request| {
+                check_put_request(&request)?;
+                Ok(request)
+            }) {
+                Ok(request) => request_objects.push(request),
+                Err(error) => {
+                    promise.reject_error(error);
+                    return promise;
+                },
+            }
+        }
+
+        // Steps 4-7. The responses are checked and read as they are fetched, and stored
+        // once all of them are, unless one of them fails.
+        let batch = PutBatch::new(
+            &promise,
+            self.id,
+            request_objects
+                .iter()
+                .map(|request| request.cache_request())
+                .collect(),
+        );
+        let in_realm_proof = AlreadyInRealm::assert(&global);
+        for (index, request) in request_objects.into_iter().enumerate() {
+            let fetched = fetch::Fetch(
+                &global,
+                RequestInfo::Request(request),
+                RequestInit::empty(),
+                InRealm::Already(&in_realm_proof),
+            );
+            let handler = PromiseNativeHandler::new(
+                &global,
+                Some(Box::new(FetchedHandler {
+                    batch: batch.clone(),
+                    index,
+                })),
+                Some(Box::new(FailedHandler {
+                    batch: batch.clone(),
+                })),
+            );
+            fetched.append_native_handler(&handler);
+        }
+        if batch.requests.is_empty() {
+            batch.store();
+        }
+        promise
+    }
+}
+
+impl CacheMethods for Cache {
+    // https://w3c.github.io/ServiceWorker/#cache-match
+    fn Match(&self, request: RequestInfo, options: &CacheQueryOptions) -> Rc<Promise> {
+        self.query(Some(request), options, |promise, items| {
+            let global = promise.global();
+            match items.into_iter().next() {
+                Some((_, response)) => {
+                    promise.resolve_native(&Response::from_cache_response(&global, response))
+                },
+                None => promise.resolve_native(&()),
+            }
+        })
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-matchall
+    fn MatchAll(&self, request: Option<RequestInfo>, options: &CacheQueryOptions) -> Rc<Promise> {
+        self.query(request, options, |promise, items| {
+            let global = promise.global();
+            let responses: Vec<DomRoot<Response>> = items
+                .into_iter()
+                .map(|(_, response)| Response::from_cache_response(&global, response))
+                .collect();
+            promise.resolve_native(&responses);
+        })
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-add
+    fn Add(&self, request: RequestInfo) -> Rc<Promise> {
+        self.add_all(vec![request])
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-addAll
+    fn AddAll(&self, requests: Vec<RequestInfo>) -> Rc<Promise> {
+        self.add_all(requests)
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-put
+    fn Put(&self, request: RequestInfo, response: &Response) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let checked = cache_storage_origin(&global)
+            .and_then(|_| request_from_info(&global, request))
+            .and_then(|request| {
+                // Steps 1-4.
+                check_put_request(&request)?;
+                // Steps 5-7.
+                check_put_response(response)?;
+                // Step 8.
+                if response.get_body_used() || response.is_locked() {
+                    return Err(Error::Type(
+                        "The response's body is disturbed or locked".to_owned(),
+                    ));
+                }
+                Ok(request.cache_request())
+            });
+        match checked {
+            Ok(request) => PutBatch::new(&promise, self.id, vec![request]).read(0, response),
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-delete
+    fn Delete(&self, request: RequestInfo, options: &CacheQueryOptions) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let origin = match cache_storage_origin(&global) {
+            Ok(origin) => origin,
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        // Steps 1-2.
+        let request = match query_request(&global, request, options) {
+            Ok(Some(request)) => request,
+            Ok(None) => {
+                promise.resolve_native(&false);
+                return promise;
+            },
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        // Steps 3-6.
+        let operations = vec![CacheOperation::Delete(
+            request,
+            net_traits_query_options(options),
+        )];
+        let id = self.id;
+        request_from_cache_storage(
+            &promise,
+            |sender| StorageThreadMsg::BatchCacheOperations(sender, origin, id, operations),
+            |promise, result: Result<bool, CacheOperationError>| match result {
+                Ok(removed) => promise.resolve_native(&removed),
+                Err(CacheOperationError::InvalidState) => promise.reject_error(Error::InvalidState),
+                Err(CacheOperationError::QuotaExceeded) => {
+                    promise.reject_error(Error::QuotaExceeded)
+                },
+            },
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-keys
+    fn Keys(&self, request: Option<RequestInfo>, options: &CacheQueryOptions) -> Rc<Promise> {
+        self.query(request, options, |promise, items| {
+            let global = promise.global();
+            let requests: Vec<DomRoot<Request>> = items
+                .into_iter()
+                .map(|(request, _)| Request::from_cache_request(&global, request))
+                .collect();
+            promise.resolve_native(&requests);
+        })
+    }
+}
+
+/// The origin whose caches a global uses, which has none if it is opaque.
+pub fn cache_storage_origin(global: &GlobalScope) -> Fallible<ImmutableOrigin> {
+    let origin = global.origin();
+    if !origin.is_tuple() {
+        return Err(Error::Security);
+    }
+    Ok(origin.immutable().clone())
+}
+
+/// Send a message to the storage of the caches, and run `steps` with a promise and the
+/// reply in a task of the DOM manipulation task source.
+pub fn request_from_cache_storage<T, F>(
+    promise: &Rc<Promise>,
+    message: impl FnOnce(IpcSender<T>) -> StorageThreadMsg,
+    steps: F,
+) where
+    T: for<'de> Deserialize<'de> + Serialize,
+    F: FnOnce(Rc<Promise>, T) + Send + 'static,
+{
+    let global = promise.global();
+    let (sender, receiver) = ipc::channel().unwrap();
+    PendingPromise::new(
+        promise,
+        global.dom_manipulation_task_source(),
+        global.task_canceller(TaskSourceName::DOMManipulation),
+    )
+    .settle_with_message(receiver.to_opaque(), steps);
+    let _ = global.resource_threads().send(message(sender));
+}
+
+pub fn net_traits_query_options(options: &CacheQueryOptions) -> NetTraitsCacheQueryOptions {
+    NetTraitsCacheQueryOptions {
+        ignore_search: options.ignoreSearch,
+        ignore_method: options.ignoreMethod,
+        ignore_vary: options.ignoreVary,
+    }
+}
+
+/// The request of a `RequestInfo`, which is constructed if it is a URL.
+fn request_from_info(global: &GlobalScope, request: RequestInfo) -> Fallible<DomRoot<Request>> {
+    match request {
+        RequestInfo::Request(request) => Ok(request),
+        url => Request::Constructor(global, url, RequestInit::empty()),
+    }
+}
+
+/// The request which a query looks for, which is none if it cannot match any item since
+/// its method is not `GET`.
+/// <https://w3c.github.io/ServiceWorker/#cache-matchall>
+pub fn query_request(
+    global: &GlobalScope,
+    request: RequestInfo,
+    options: &CacheQueryOptions,
+) -> Fallible<Option<CacheRequest>> {
+    let request = request_from_info(global, request)?.cache_request();
+    if request.method != "GET" && !options.ignoreMethod {
+        return Ok(None);
+    }
+    Ok(Some(request))
+}
+
+/// Check that a request can be stored in a cache.
+/// <https://w3c.github.io/ServiceWorker/#cache-put>
+fn check_put_request(request: &Request) -> Fallible<()> {
+    let request = request.cache_request();
+    if request.method != "GET" {
+        return Err(Error::Type("Only GET requests can be cached".to_owned()));
+    }
+    match request.url.scheme() {
+        "http" | "https" => Ok(()),
+        _ => Err(Error::Type(
+            "Only HTTP(S) requests can be cached".to_owned(),
+        )),
+    }
+}
+
+/// Check that a response can be stored in a cache.
+/// <https://w3c.github.io/ServiceWorker/#cache-put>
+fn check_put_response(response: &Response) -> Fallible<()> {
+    if response.Status() == 206 {
+        return Err(Error::Type("Partial responses cannot be cached".to_owned()));
+    }
+    let headers = response.Headers().get_headers_list();
+    let varies_on_anything = headers.get_all("vary").iter().any(|value| {
+        value.to_str().map_or(false, |value| {
+            value.split(',').any(|name| name.trim() == "*")
+        })
+    });
+    if varies_on_anything {
+        return Err(Error::Type(
+            "Responses which vary on * cannot be cached".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// The items of a batch of `put` operations, which are stored in a cache once the bodies
+/// of all of their responses are read, unless one of them cannot be.
+#[derive(JSTraceable, MallocSizeOf)]
+struct PutBatch {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    cache: CacheId,
+    requests: Vec<CacheRequest>,
+    responses: DomRefCell<Vec<Option<CacheResponse>>>,
+    failed: Cell<bool>,
+}
+
+impl PutBatch {
+    fn new(promise: &Rc<Promise>, cache: CacheId, requests: Vec<CacheRequest>) -> Rc<PutBatch> {
+        Rc::new(PutBatch {
+            promise: promise.clone(),
+            cache,
+            responses: DomRefCell::new(vec![None; requests.len()]),
+            requests,
+            failed: Cell::new(false),
+        })
+    }
+
+    /// Read the body of the response of the request at an index.
+    fn read(self: &Rc<Self>, index: usize, response: &Response) {
+        if response.has_null_body() {
+            return self.set_response(index, response.cache_response(vec![]));
+        }
+        let stored = response.cache_response(vec![]);
+        let body = consume_body(response, BodyType::ArrayBuffer);
+        let handler = PromiseNativeHandler::new(
+            &response.global(),
+            Some(Box::new(BodyHandler {
+                batch: self.clone(),
+                index,
+                response: DomRefCell::new(Some(stored)),
+            })),
+            Some(Box::new(FailedHandler {
+                batch: self.clone(),
+            })),
+        );
+        body.append_native_handler(&handler);
+    }
+
+    fn set_response(&self, index: usize, response: CacheResponse) {
+        if self.failed.get() {
+            return;
+        }
+        self.responses.borrow_mut()[index] = Some(response);
+        if self.responses.borrow().iter().all(Option::is_some) {
+            self.store();
+        }
+    }
+
+    fn fail(&self, error: Error) {
+        if !self.failed.replace(true) {
+            self.promise.reject_error(error);
+        }
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#batch-cache-operations-algorithm>
+    fn store(&self) {
+        let global = self.promise.global();
+        let origin = match cache_storage_origin(&global) {
+            Ok(origin) => origin,
+            Err(error) => return self.fail(error),
+        };
+        let operations = self
+            .requests
+            .iter()
+            .cloned()
+            .zip(self.responses.borrow_mut().drain(..).flatten())
+            .map(|(request, response)| CacheOperation::Put(request, response))
+            .collect();
+        let cache = self.cache;
+        request_from_cache_storage(
+            &self.promise,
+            |sender| StorageThreadMsg::BatchCacheOperations(sender, origin, cache, operations),
+            |promise, result: Result<bool, CacheOperationError>| match result {
+                Ok(_) => promise.resolve_native(&()),
+                Err(CacheOperationError::InvalidState) => promise.reject_error(Error::InvalidState),
+                Err(CacheOperationError::QuotaExceeded) => {
+                    promise.reject_error(Error::QuotaExceeded)
+                },
+            },
+        );
+    }
+}
+
+/// Check a fetched response before reading its body.
+/// <https://w3c.github.io/ServiceWorker/#cache-addAll>
+#[derive(JSTraceable, MallocSizeOf)]
+struct FetchedHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    batch: Rc<PutBatch>,
+    index: usize,
+}
+
+impl Callback for FetchedHandler {
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let response = match root_from_handlevalue::<Response>(v, cx) {
+            Ok(response) => response,
+            Err(()) => return self.batch.fail(Error::Type("Not a response".to_owned())),
+        };
+        // Step 5.4.2.
+        if response.Type() == DOMResponseType::Error || !response.Ok() {
+            return self
+                .batch
+                .fail(Error::Type("The response is not successful".to_owned()));
+        }
+        if let Err(error) = check_put_response(&response) {
+            return self.batch.fail(error);
+        }
+        self.batch.read(self.index, &response);
+    }
+}
+
+/// Add the body of a response to a batch once it is read.
+#[derive(JSTraceable, MallocSizeOf)]
+struct BodyHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    batch: Rc<PutBatch>,
+    index: usize,
+    response: DomRefCell<Option<CacheResponse>>,
+}
+
+impl Callback for BodyHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, _cx: *mut JSContext, v: HandleValue) {
+        let mut response = match self.response.borrow_mut().take() {
+            Some(response) => response,
+            None => return,
+        };
+        if !v.is_object() {
+            return self
+                .batch
+                .fail(Error::Type("The body could not be read".to_owned()));
+        }
+        match unsafe { ArrayBuffer::from(v.to_object()) } {
+            Ok(body) => response.body = unsafe { body.as_slice().to_vec() },
+            Err(()) => {
+                return self
+                    .batch
+                    .fail(Error::Type("The body could not be read".to_owned()))
+            },
+        }
+        self.batch.set_response(self.index, response);
+    }
+}
+
+/// Reject the promise of a batch with the reason why a fetch or a read failed.
+#[derive(JSTraceable, MallocSizeOf)]
+struct FailedHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    batch: Rc<PutBatch>,
+}
+
+impl Callback for FailedHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        if !self.batch.failed.replace(true) {
+            self.batch
+                .promise
+                .reject(unsafe { SafeJSContext::from_ptr(cx) }, v);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CacheStorageBinding::{
+    self, CacheStorageMethods, MultiCacheQueryOptions,
+};
+use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInfo;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::cache::{cache_storage_origin, net_traits_query_options, query_request};
+use crate::dom::cache::{request_from_cache_storage, Cache};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::response::Response;
+use dom_struct::dom_struct;
+use net_traits::storage_thread::{CacheId, CacheQueryTarget, StorageThreadMsg};
+use servo_url::ImmutableOrigin;
+use std::rc::Rc;
+
+/// <https://w3c.github.io/ServiceWorker/#cachestorage-interface>
+#[dom_struct]
+pub struct CacheStorage {
+    reflector_: Reflector,
+}
+
+impl CacheStorage {
+    fn new_inherited() -> CacheStorage {
+        CacheStorage {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<CacheStorage> {
+        reflect_dom_object(
+            Box::new(CacheStorage::new_inherited()),
+            global,
+            CacheStorageBinding::Wrap,
+        )
+    }
+
+    /// A promise, and the origin of the caches if they can be used, otherwise the promise
+    /// is rejected.
+    fn promise_and_origin(&self) -> (Rc<Promise>, Option<ImmutableOrigin>) {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let origin = match cache_storage_origin(&global) {
+            Ok(origin) => Some(origin),
+            Err(error) => {
+                promise.reject_error(error);
+                None
+            },
+        };
+        (promise, origin)
+    }
+}
+
+impl CacheStorageMethods for CacheStorage {
+    // https://w3c.github.io/ServiceWorker/#cache-storage-match
+    fn Match(&self, request: RequestInfo, options: &MultiCacheQueryOptions) -> Rc<Promise> {
+        let (promise, origin) = self.promise_and_origin();
+        let origin = match origin {
+            Some(origin) => origin,
+            None => return promise,
+        };
+        let request = match query_request(&self.global(), request, &options.parent) {
+            Ok(Some(request)) => request,
+            Ok(None) => {
+                promise.resolve_native(&());
+                return promise;
+            },
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        let target = match options.cacheName {
+            Some(ref name) => CacheQueryTarget::Named(name.to_string()),
+            None => CacheQueryTarget::All,
+        };
+        let query_options = net_traits_query_options(&options.parent);
+        request_from_cache_storage(
+            &promise,
+            |sender| {
+                StorageThreadMsg::QueryCache(sender, origin, target, Some(request), query_options)
+            },
+            |promise, items| {
+                let global = promise.global();
+                match items.into_iter().next() {
+                    Some((_, response)) => {
+                        promise.resolve_native(&Response::from_cache_response(&global, response))
+                    },
+                    None => promise.resolve_native(&()),
+                }
+            },
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-has
+    fn Has(&self, name: DOMString) -> Rc<Promise> {
+        let (promise, origin) = self.promise_and_origin();
+        if let Some(origin) = origin {
+            request_from_cache_storage(
+                &promise,
+                |sender| StorageThreadMsg::HasCache(sender, origin, name.into()),
+                |promise, has: bool| promise.resolve_native(&has),
+            );
+        }
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-open
+    fn Open(&self, name: DOMString) -> Rc<Promise> {
+        let (promise, origin) = self.promise_and_origin();
+        if let Some(origin) = origin {
+            request_from_cache_storage(
+                &promise,
+                |sender| StorageThreadMsg::OpenCache(sender, origin, name.into()),
+                |promise, id: CacheId| promise.resolve_native(&Cache::new(&promise.global(), id)),
+            );
+        }
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-delete
+    fn Delete(&self, name: DOMString) -> Rc<Promise> {
+        let (promise, origin) = self.promise_and_origin();
+        if let Some(origin) = origin {
+            request_from_cache_storage(
+                &promise,
+                |sender| StorageThreadMsg::DeleteCache(sender, origin, name.into()),
+                |promise, deleted: bool| promise.resolve_native(&deleted),
+            );
+        }
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#cache-storage-keys
+    fn Keys(&self) -> Rc<Promise> {
+        let (promise, origin) = self.promise_and_origin();
+        if let Some(origin) = origin {
+            request_from_cache_storage(
+                &promise,
+                |sender| StorageThreadMsg::CacheNames(sender, origin),
+                |promise, names: Vec<String>| {
+                    let names: Vec<DOMString> = names.into_iter().map(DOMString::from).collect();
+                    promise.resolve_native(&names)
+                },
+            );
+        }
+        promise
+    }
+}
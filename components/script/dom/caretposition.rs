@@ -0,0 +1,62 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CaretPositionBinding::{self, CaretPositionMethods};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::domrect::DOMRect;
+use crate::dom::node::Node;
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+
+#[dom_struct]
+pub struct CaretPosition {
+    reflector_: Reflector,
+    offset_node: Dom<Node>,
+    offset: u32,
+}
+
+impl CaretPosition {
+    fn new_inherited(offset_node: &Node, offset: u32) -> CaretPosition {
+        CaretPosition {
+            reflector_: Reflector::new(),
+            offset_node: Dom::from_ref(offset_node),
+            offset: offset,
+        }
+    }
+
+    pub fn new(window: &Window, offset_node: &Node, offset: u32) -> DomRoot<CaretPosition> {
+        reflect_dom_object(
+            Box::new(CaretPosition::new_inherited(offset_node, offset)),
+            window,
+            CaretPositionBinding::Wrap,
+        )
+    }
+}
+
+impl CaretPositionMethods for CaretPosition {
+    // https://drafts.csswg.org/cssom-view/#dom-caretposition-offsetnode
+    fn OffsetNode(&self) -> DomRoot<Node> {
+        DomRoot::from_ref(&*self.offset_node)
+    }
+
+    // https://drafts.csswg.org/cssom-view/#dom-caretposition-offset
+    fn Offset(&self) -> u32 {
+        self.offset
+    }
+
+    // https://drafts.csswg.org/cssom-view/#dom-caretposition-getclientrect
+    fn GetClientRect(&self) -> Option<DomRoot<DOMRect>> {
+        // Layout cannot locate a character of a text yet, so the caret is a collapsed
+        // rectangle at the start of the box of its node rather than at its offset.
+        let rect = self.offset_node.bounding_content_box()?;
+        Some(DOMRect::new(
+            &self.global(),
+            rect.origin.x.to_f64_px(),
+            rect.origin.y.to_f64_px(),
+            0.,
+            rect.size.height.to_f64_px(),
+        ))
+    }
+}
@@ -4,12 +4,24 @@
 
 use crate::dom::bindings::codegen::Bindings::ClientBinding::FrameType;
 use crate::dom::bindings::codegen::Bindings::ClientBinding::{ClientMethods, Wrap};
-use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::codegen::Bindings::MessagePortBinding::PostMessageOptions;
+use crate::dom::bindings::error::ErrorResult;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::str::{DOMString, USVString};
+use crate::dom::bindings::structuredclone;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::globalscope::GlobalScope;
 use crate::dom::serviceworker::ServiceWorker;
+use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
 use crate::dom::window::Window;
+use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSObject};
+use js::rust::{CustomAutoRooter, CustomAutoRooterGuard, HandleValue};
+use msg::constellation_msg::PipelineId;
+use script_traits::{ClientFrameType, ClientInfo, DOMMessage, ScriptMsg, ServiceWorkerMsg};
 use servo_url::ServoUrl;
 use std::default::Default;
 use uuid::Uuid;
@@ -22,27 +34,60 @@ pub struct Client {
     frame_type: FrameType,
     #[ignore_malloc_size_of = "Defined in uuid"]
     id: Uuid,
+    /// The pipeline of the document of the client, which the messages of the service
+    /// workers are sent to.
+    pipeline_id: PipelineId,
 }
 
 impl Client {
-    fn new_inherited(url: ServoUrl) -> Client {
+    fn new_inherited(
+        url: ServoUrl,
+        frame_type: FrameType,
+        id: Uuid,
+        pipeline_id: PipelineId,
+    ) -> Client {
         Client {
             reflector_: Reflector::new(),
             active_worker: Default::default(),
             url: url,
-            frame_type: FrameType::None,
-            id: Uuid::new_v4(),
+            frame_type: frame_type,
+            id: id,
+            pipeline_id: pipeline_id,
         }
     }
 
     pub fn new(window: &Window) -> DomRoot<Client> {
         reflect_dom_object(
-            Box::new(Client::new_inherited(window.get_url())),
+            Box::new(Client::new_inherited(
+                window.get_url(),
+                FrameType::None,
+                Uuid::new_v4(),
+                window.pipeline_id(),
+            )),
             window,
             Wrap,
         )
     }
 
+    /// A client found by the constellation, exposed to a service worker.
+    pub fn from_info(global: &GlobalScope, info: &ClientInfo) -> DomRoot<Client> {
+        let frame_type = match info.frame_type {
+            ClientFrameType::Auxiliary => FrameType::Auxiliary,
+            ClientFrameType::TopLevel => FrameType::Top_level,
+            ClientFrameType::Nested => FrameType::Nested,
+        };
+        reflect_dom_object(
+            Box::new(Client::new_inherited(
+                info.url.clone(),
+                frame_type,
+                info.id,
+                info.pipeline_id,
+            )),
+            global,
+            Wrap,
+        )
+    }
+
     pub fn creation_url(&self) -> ServoUrl {
         self.url.clone()
     }
@@ -53,6 +98,41 @@ impl Client {
 
     pub fn set_controller(&self, worker: &ServiceWorker) {
         self.active_worker.set(Some(worker));
+        // The constellation keeps the controllers of the documents, which the service
+        // workers match their clients with.
+        if let Some(window) = self.global().downcast::<Window>() {
+            window.send_to_constellation(ScriptMsg::SetServiceWorkerController(
+                worker.get_scope_url(),
+            ));
+        }
+    }
+
+    /// <https://w3c.github.io/ServiceWorker/#client-postmessage>
+    fn post_message_impl(
+        &self,
+        cx: JSContext,
+        message: HandleValue,
+        transfer: CustomAutoRooterGuard<Vec<*mut JSObject>>,
+    ) -> ErrorResult {
+        // Step 6
+        let data = structuredclone::write(cx, message, Some(transfer))?;
+        let global = self.global();
+        let scope = match global.downcast::<ServiceWorkerGlobalScope>() {
+            Some(scope) => scope,
+            None => return Ok(()),
+        };
+        // Step 7
+        let incumbent = GlobalScope::incumbent().expect("no incumbent global?");
+        let message = DOMMessage {
+            origin: incumbent.origin().immutable().clone(),
+            data,
+        };
+        scope.send_to_manager(ServiceWorkerMsg::PostMessageToClient(
+            self.pipeline_id,
+            scope.scope_url().clone(),
+            message,
+        ));
+        Ok(())
     }
 }
 
@@ -69,7 +149,34 @@ impl ClientMethods for Client {
 
     // https://w3c.github.io/ServiceWorker/#client-id
     fn Id(&self) -> DOMString {
-        let uid_str = format!("{}", self.id);
-        DOMString::from_string(uid_str)
+        DOMString::from_string(self.id.to_string())
+    }
+
+    // https://w3c.github.io/ServiceWorker/#dom-client-postmessage
+    fn PostMessage(
+        &self,
+        cx: JSContext,
+        message: HandleValue,
+        transfer: CustomAutoRooterGuard<Vec<*mut JSObject>>,
+    ) -> ErrorResult {
+        self.post_message_impl(cx, message, transfer)
+    }
+
+    // https://w3c.github.io/ServiceWorker/#dom-client-postmessage-message-options
+    fn PostMessage_(
+        &self,
+        cx: JSContext,
+        message: HandleValue,
+        options: RootedTraceableBox<PostMessageOptions>,
+    ) -> ErrorResult {
+        let mut rooted = CustomAutoRooter::new(
+            options
+                .transfer
+                .iter()
+                .map(|js: &RootedTraceableBox<Heap<*mut JSObject>>| js.get())
+                .collect(),
+        );
+        let guard = CustomAutoRooterGuard::new(*cx, &mut rooted);
+        self.post_message_impl(cx, message, guard)
     }
 }
@@ -0,0 +1,160 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ClientsBinding::{
+    self, ClientQueryOptions, ClientType, ClientsMethods,
+};
+use crate::dom::bindings::error::Error;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::{DOMString, USVString};
+use crate::dom::client::Client;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::{PendingPromise, Promise};
+use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
+use crate::task_source::TaskSourceName;
+use dom_struct::dom_struct;
+use ipc_channel::ipc::{self, IpcSender};
+use script_traits::{ClientInfo, ServiceWorkerMsg};
+use serde::{Deserialize, Serialize};
+use servo_url::ServoUrl;
+use std::rc::Rc;
+
+/// <https://w3c.github.io/ServiceWorker/#clients-interface>
+#[dom_struct]
+pub struct Clients {
+    reflector_: Reflector,
+}
+
+impl Clients {
+    fn new_inherited() -> Clients {
+        Clients {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<Clients> {
+        reflect_dom_object(
+            Box::new(Clients::new_inherited()),
+            global,
+            ClientsBinding::Wrap,
+        )
+    }
+
+    /// Send a message about the clients of the service worker to the service worker
+    /// manager, and settle the promise by running `steps` with its answer.
+    fn request_from_manager<T, F>(
+        &self,
+        promise: &Rc<Promise>,
+        message: impl FnOnce(&ServoUrl, IpcSender<T>) -> ServiceWorkerMsg,
+        steps: F,
+    ) where
+        T: for<'de> Deserialize<'de> + Serialize,
+        F: FnOnce(Rc<Promise>, T) + Send + 'static,
+    {
+        let global = self.global();
+        let scope = match global.downcast::<ServiceWorkerGlobalScope>() {
+            Some(scope) => scope,
+            None => return promise.reject_error(Error::InvalidState),
+        };
+        let (sender, receiver) = ipc::channel().unwrap();
+        PendingPromise::new(
+            promise,
+            global.dom_manipulation_task_source(),
+            global.task_canceller(TaskSourceName::DOMManipulation),
+        )
+        .settle_with_message(receiver.to_opaque(), steps);
+        scope.send_to_manager(message(scope.scope_url(), sender));
+    }
+}
+
+impl ClientsMethods for Clients {
+    // https://w3c.github.io/ServiceWorker/#clients-get
+    fn Get(&self, id: DOMString) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        // The clients of the service worker are the documents of its origin, whether it
+        // controls them or not.
+        self.request_from_manager(
+            &promise,
+            |scope_url, sender| ServiceWorkerMsg::MatchClients(scope_url.clone(), true, sender),
+            move |promise, clients: Vec<ClientInfo>| {
+                let client = clients.iter().find(|client| client.id.to_string() == *id);
+                match client {
+                    Some(client) => {
+                        promise.resolve_native(&Client::from_info(&promise.global(), client))
+                    },
+                    None => promise.resolve_native(&()),
+                }
+            },
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#clients-matchall
+    fn MatchAll(&self, options: &ClientQueryOptions) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        // Only documents can be clients of service workers in Servo.
+        match options.type_ {
+            ClientType::Worker | ClientType::Sharedworker => {
+                promise.resolve_native(&Vec::<DomRoot<Client>>::new());
+                return promise;
+            },
+            ClientType::Window | ClientType::All => {},
+        }
+        let include_uncontrolled = options.includeUncontrolled;
+        self.request_from_manager(
+            &promise,
+            |scope_url, sender| {
+                ServiceWorkerMsg::MatchClients(scope_url.clone(), include_uncontrolled, sender)
+            },
+            |promise, clients: Vec<ClientInfo>| {
+                let global = promise.global();
+                let clients: Vec<DomRoot<Client>> = clients
+                    .iter()
+                    .map(|client| Client::from_info(&global, client))
+                    .collect();
+                promise.resolve_native(&clients);
+            },
+        );
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#clients-openwindow
+    fn OpenWindow(&self, url: USVString) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        // Step 1-2
+        let url = match global.api_base_url().join(&url.0) {
+            Ok(url) => url,
+            Err(_) => {
+                promise.reject_error(Error::Type("Invalid URL".to_owned()));
+                return promise;
+            },
+        };
+        // Step 3
+        if url.as_str() == "about:blank" {
+            promise.reject_error(Error::Type("Cannot open about:blank".to_owned()));
+            return promise;
+        }
+        // Step 4
+        // A service worker only has transient activation while it handles a
+        // notificationclick event, which Servo doesn't fire.
+        promise.reject_error(Error::InvalidAccess);
+        promise
+    }
+
+    // https://w3c.github.io/ServiceWorker/#clients-claim
+    fn Claim(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        // TODO Step 1: Reject if the service worker isn't the active worker of its
+        // registration.
+        self.request_from_manager(
+            &promise,
+            |scope_url, sender| ServiceWorkerMsg::ClaimClients(scope_url.clone(), sender),
+            |promise, (): ()| promise.resolve_native(&()),
+        );
+        promise
+    }
+}
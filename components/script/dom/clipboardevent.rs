@@ -0,0 +1,128 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ClipboardEventBinding;
+use crate::dom::bindings::codegen::Bindings::ClipboardEventBinding::ClipboardEventMethods;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::datatransfer::DataTransfer;
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
+use crate::dom::window::Window;
+use crate::textinput::CMD_OR_CONTROL;
+use dom_struct::dom_struct;
+use keyboard_types::{Key, KeyState, Modifiers, ShortcutMatcher};
+use servo_atoms::Atom;
+
+/// The clipboard actions, each of which fires the clipboard event of the same name.
+/// <https://w3c.github.io/clipboard-apis/#clipboard-actions>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipboardAction {
+    Copy,
+    Cut,
+    Paste,
+}
+
+impl ClipboardAction {
+    /// The action triggered by a keyboard shortcut of the platform, if any.
+    pub fn from_shortcut(key: &Key, modifiers: Modifiers) -> Option<ClipboardAction> {
+        ShortcutMatcher::new(KeyState::Down, key.clone(), modifiers)
+            .shortcut(CMD_OR_CONTROL, 'C', || Some(ClipboardAction::Copy))
+            .shortcut(CMD_OR_CONTROL, 'X', || Some(ClipboardAction::Cut))
+            .shortcut(CMD_OR_CONTROL, 'V', || Some(ClipboardAction::Paste))
+            .otherwise(|| None)
+            .unwrap()
+    }
+
+    /// The action of an `execCommand` command, if any. Commands are matched ASCII
+    /// case-insensitively.
+    pub fn from_command(command: &str) -> Option<ClipboardAction> {
+        match &*command.to_ascii_lowercase() {
+            "copy" => Some(ClipboardAction::Copy),
+            "cut" => Some(ClipboardAction::Cut),
+            "paste" => Some(ClipboardAction::Paste),
+            _ => None,
+        }
+    }
+
+    pub fn event_type(&self) -> Atom {
+        match *self {
+            ClipboardAction::Copy => atom!("copy"),
+            ClipboardAction::Cut => atom!("cut"),
+            ClipboardAction::Paste => atom!("paste"),
+        }
+    }
+
+    pub fn from_event_type(type_: &Atom) -> Option<ClipboardAction> {
+        match *type_ {
+            atom!("copy") => Some(ClipboardAction::Copy),
+            atom!("cut") => Some(ClipboardAction::Cut),
+            atom!("paste") => Some(ClipboardAction::Paste),
+            _ => None,
+        }
+    }
+}
+
+#[dom_struct]
+pub struct ClipboardEvent {
+    event: Event,
+    clipboard_data: MutNullableDom<DataTransfer>,
+}
+
+impl ClipboardEvent {
+    fn new_inherited() -> ClipboardEvent {
+        ClipboardEvent {
+            event: Event::new_inherited(),
+            clipboard_data: Default::default(),
+        }
+    }
+
+    pub fn new(
+        window: &Window,
+        type_: Atom,
+        bubbles: EventBubbles,
+        cancelable: EventCancelable,
+        clipboard_data: Option<&DataTransfer>,
+    ) -> DomRoot<ClipboardEvent> {
+        let ev = reflect_dom_object(
+            Box::new(ClipboardEvent::new_inherited()),
+            window,
+            ClipboardEventBinding::Wrap,
+        );
+        ev.upcast::<Event>()
+            .init_event(type_, bool::from(bubbles), bool::from(cancelable));
+        ev.clipboard_data.set(clipboard_data);
+        ev
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        type_: DOMString,
+        init: &ClipboardEventBinding::ClipboardEventInit,
+    ) -> Fallible<DomRoot<ClipboardEvent>> {
+        Ok(ClipboardEvent::new(
+            window,
+            Atom::from(type_),
+            EventBubbles::from(init.parent.bubbles),
+            EventCancelable::from(init.parent.cancelable),
+            init.clipboardData.as_deref(),
+        ))
+    }
+}
+
+impl ClipboardEventMethods for ClipboardEvent {
+    // https://w3c.github.io/clipboard-apis/#dom-clipboardevent-clipboarddata
+    fn GetClipboardData(&self) -> Option<DomRoot<DataTransfer>> {
+        self.clipboard_data.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-event-istrusted
+    fn IsTrusted(&self) -> bool {
+        self.event.IsTrusted()
+    }
+}
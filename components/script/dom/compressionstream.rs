@@ -0,0 +1,64 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CompressionStreamBinding::{
+    self, CompressionFormat, CompressionStreamMethods,
+};
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::streams::compression::CodecAlgorithms;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::transformstreamdefaultcontroller::Transformer;
+use crate::dom::writablestream::WritableStream;
+use dom_struct::dom_struct;
+
+#[dom_struct]
+pub struct CompressionStream {
+    reflector_: Reflector,
+    transform: Dom<TransformStream>,
+}
+
+impl CompressionStream {
+    fn new_inherited(transform: &TransformStream) -> CompressionStream {
+        CompressionStream {
+            reflector_: Reflector::new(),
+            transform: Dom::from_ref(transform),
+        }
+    }
+
+    fn new(global: &GlobalScope, transform: &TransformStream) -> DomRoot<CompressionStream> {
+        reflect_dom_object(
+            Box::new(CompressionStream::new_inherited(transform)),
+            global,
+            CompressionStreamBinding::Wrap,
+        )
+    }
+
+    // https://wicg.github.io/compression/#dom-compressionstream-compressionstream
+    pub fn Constructor(
+        global: &GlobalScope,
+        format: CompressionFormat,
+    ) -> Fallible<DomRoot<CompressionStream>> {
+        let transformer =
+            Transformer::with_algorithms(Box::new(CodecAlgorithms::compression(format)));
+        let transform =
+            TransformStream::new_with_transformer(global, transformer, 1., None, 0., None)?;
+        Ok(CompressionStream::new(global, &transform))
+    }
+}
+
+impl CompressionStreamMethods for CompressionStream {
+    // https://streams.spec.whatwg.org/#dom-generictransformstream-readable
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.transform.readable()
+    }
+
+    // https://streams.spec.whatwg.org/#dom-generictransformstream-writable
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.transform.writable()
+    }
+}
@@ -2,20 +2,34 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::conversions::{jsid_to_string, jsstring_to_str};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
-use devtools_traits::{ConsoleMessage, LogLevel, ScriptToDevtoolsControlMsg};
+use crate::script_runtime::JSContext;
+use devtools_traits::{ConsoleArgument, ConsoleMessage, LogLevel, ObjectPreview};
+use devtools_traits::{ScriptToDevtoolsControlMsg, StackFrame};
+use js::jsapi::{JS_ClearPendingException, JS_IsExceptionPending, PropertyDescriptor};
+use js::jsapi::{JS_ValueToSource, StackFormat, JSITER_OWNONLY, JSPROP_GETTER, JSPROP_SETTER};
+use js::jsval::ObjectValue;
+use js::rust::jsapi_wrapped::JS_GetArrayLength;
+use js::rust::wrappers::{GetPropertyKeys, JS_GetOwnPropertyDescriptor};
+use js::rust::wrappers::{JS_GetOwnPropertyDescriptorById, JS_IsArrayObject};
+use js::rust::{get_object_class, Handle, HandleObject, HandleValue, IdVector};
+use js::JSCLASS_IS_PROXY;
+use std::ffi::{CStr, CString};
 use std::io;
 
+/// The number of elements or properties shown in the preview of an object.
+const PREVIEW_LENGTH: usize = 10;
+
 // https://developer.mozilla.org/en-US/docs/Web/API/Console
 pub struct Console(());
 
 impl Console {
-    fn send_to_devtools(global: &GlobalScope, level: LogLevel, message: DOMString) {
+    fn send_to_devtools(global: &GlobalScope, console_message: ConsoleMessage) {
         if let Some(chan) = global.devtools_chan() {
-            let console_message = prepare_message(level, message);
             let worker_id = global
                 .downcast::<WorkerGlobalScope>()
                 .map(|worker| worker.get_worker_id());
@@ -27,6 +41,15 @@ impl Console {
             chan.send(devtools_message).unwrap();
         }
     }
+
+    /// Log a warning from the browser itself, without formatting it.
+    pub fn internal_warn(global: &GlobalScope, message: DOMString) {
+        console_message(
+            global,
+            LogLevel::Warn,
+            vec![ConsoleArgument::String(message.into())],
+        );
+    }
 }
 
 // In order to avoid interleaving the stdout output of the Console API methods
@@ -43,99 +66,726 @@ where
     f()
 }
 
-#[allow(non_snake_case)]
-impl Console {
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/log
-    pub fn Log(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Log, message);
+/// Print the given arguments, indented by the current groups, and send them to the
+/// devtools along with the location of the call.
+fn console_message(global: &GlobalScope, level: LogLevel, arguments: Vec<ConsoleArgument>) {
+    let text = stringify_arguments(&arguments);
+    console_message_with_text(global, level, arguments, text)
+}
+
+fn console_message_with_text(
+    global: &GlobalScope,
+    level: LogLevel,
+    arguments: Vec<ConsoleArgument>,
+    text: String,
+) {
+    let stack = stack_frames(global.get_cx());
+    let (filename, line_number, column_number) = match stack.first() {
+        Some(frame) => (
+            frame.filename.clone(),
+            frame.lineNumber as usize,
+            frame.columnNumber as usize,
+        ),
+        None => (String::new(), 0, 0),
+    };
+    let stacktrace = match level {
+        LogLevel::Error | LogLevel::Trace => Some(stack),
+        _ => None,
+    };
+
+    with_stderr_lock(move || {
+        let indent = "  ".repeat(global.console_group_depth());
+        for line in text.lines() {
+            println!("{}{}", indent, line);
+        }
+        if let LogLevel::Trace = level {
+            for frame in stacktrace.iter().flatten() {
+                println!(
+                    "{}  {}@{}:{}:{}",
+                    indent,
+                    frame.functionName,
+                    frame.filename,
+                    frame.lineNumber,
+                    frame.columnNumber
+                );
             }
-        })
+        }
+        let console_message = ConsoleMessage {
+            message: text,
+            logLevel: level,
+            filename: filename,
+            lineNumber: line_number,
+            columnNumber: column_number,
+            arguments: arguments,
+            stacktrace: stacktrace,
+        };
+        Console::send_to_devtools(global, console_message);
+    })
+}
+
+/// Log the given values, after substituting the format specifiers of the first one.
+fn console_messages(
+    cx: JSContext,
+    global: &GlobalScope,
+    level: LogLevel,
+    messages: Vec<HandleValue>,
+) {
+    let arguments = messages
+        .into_iter()
+        .map(|message| preview_value(cx, message, 1))
+        .collect();
+    console_message(global, level, apply_format_specifiers(arguments));
+}
+
+/// The frames of the current JavaScript stack, from the innermost one.
+#[allow(unsafe_code)]
+pub fn stack_frames(cx: JSContext) -> Vec<StackFrame> {
+    unsafe {
+        capture_stack!(in(*cx) let stack);
+        stack
+            .and_then(|stack| stack.as_string(None, StackFormat::SpiderMonkey))
+            .map_or(vec![], |stack| {
+                stack.lines().filter_map(parse_stack_frame).collect()
+            })
     }
+}
+
+/// Parse a frame formatted as `functionName@filename:lineNumber:columnNumber`.
+fn parse_stack_frame(frame: &str) -> Option<StackFrame> {
+    let at = frame.find('@')?;
+    let mut location = frame[at + 1..].rsplitn(3, ':');
+    let column_number = location.next()?.parse().ok()?;
+    let line_number = location.next()?.parse().ok()?;
+    Some(StackFrame {
+        filename: location.next()?.to_owned(),
+        functionName: frame[..at].to_owned(),
+        lineNumber: line_number,
+        columnNumber: column_number,
+    })
+}
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console
-    pub fn Debug(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Debug, message);
+/// Preview the given value, and the values of its properties up to the given depth.
+#[allow(unsafe_code)]
+fn preview_value(cx: JSContext, value: HandleValue, depth: u32) -> ConsoleArgument {
+    unsafe {
+        if value.is_undefined() {
+            ConsoleArgument::Undefined
+        } else if value.is_null() {
+            ConsoleArgument::Null
+        } else if value.is_boolean() {
+            ConsoleArgument::Boolean(value.to_boolean())
+        } else if value.is_number() {
+            ConsoleArgument::Number(value.to_number())
+        } else if value.is_string() {
+            ConsoleArgument::String(jsstring_to_str(*cx, value.to_string()).into())
+        } else if value.is_object() {
+            rooted!(in(*cx) let object = value.to_object());
+            ConsoleArgument::Object(preview_object(cx, object.handle(), depth))
+        } else {
+            // Symbols and big integers.
+            let source = JS_ValueToSource(*cx, value.into());
+            if source.is_null() {
+                JS_ClearPendingException(*cx);
+                return ConsoleArgument::Undefined;
             }
-        })
+            ConsoleArgument::String(jsstring_to_str(*cx, source).into())
+        }
     }
+}
+
+/// Preview the given object, and the values of its properties up to the given depth.
+///
+/// Previewing must not run any script, so only the data properties of the object
+/// are read, and proxies are not looked into.
+#[allow(unsafe_code)]
+fn preview_object(cx: JSContext, object: HandleObject, depth: u32) -> ObjectPreview {
+    unsafe {
+        let class = get_object_class(object.get());
+        let is_proxy = (*class).flags & JSCLASS_IS_PROXY != 0;
+        let class = if is_proxy {
+            "Proxy".to_owned()
+        } else {
+            CStr::from_ptr((*class).name).to_string_lossy().into_owned()
+        };
+        let mut preview = ObjectPreview {
+            class: class,
+            name: None,
+            length: None,
+            items: None,
+            ownProperties: vec![],
+            ownPropertiesLength: 0,
+        };
+        if is_proxy {
+            return preview;
+        }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/info
-    pub fn Info(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Info, message);
+        if preview.class == "Function" {
+            let name = match own_property(cx, object, "name", 0) {
+                Some(ConsoleArgument::String(name)) => name,
+                _ => String::new(),
+            };
+            preview.name = Some(name);
+            clear_pending_exception(cx);
+            return preview;
+        }
+
+        rooted!(in(*cx) let value = ObjectValue(object.get()));
+        let mut is_array = false;
+        if JS_IsArrayObject(*cx, value.handle(), &mut is_array) && is_array {
+            let mut length = 0;
+            if JS_GetArrayLength(*cx, object, &mut length) {
+                preview.length = Some(length);
+                if depth > 0 {
+                    let items = (0..length.min(PREVIEW_LENGTH as u32))
+                        .map(|index| {
+                            own_property(cx, object, &index.to_string(), depth - 1)
+                                .unwrap_or(ConsoleArgument::Undefined)
+                        })
+                        .collect();
+                    preview.items = Some(items);
+                }
             }
-        })
-    }
+            clear_pending_exception(cx);
+            return preview;
+        }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/warn
-    pub fn Warn(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Warn, message);
+        let mut ids = IdVector::new(*cx);
+        if !GetPropertyKeys(*cx, object, JSITER_OWNONLY, ids.handle_mut()) {
+            clear_pending_exception(cx);
+            return preview;
+        }
+        preview.ownPropertiesLength = ids.len() as u32;
+        if depth == 0 {
+            return preview;
+        }
+        for id in ids.iter().take(PREVIEW_LENGTH) {
+            rooted!(in(*cx) let id = *id);
+            let name = match jsid_to_string(*cx, id.handle()) {
+                Some(name) => name,
+                None => continue,
+            };
+            rooted!(in(*cx) let mut desc = PropertyDescriptor::default());
+            if !JS_GetOwnPropertyDescriptorById(*cx, object, id.handle(), desc.handle_mut()) {
+                break;
             }
-        })
+            if let Some(property) = preview_property(cx, desc.handle(), depth - 1) {
+                preview.ownProperties.push((name.into(), property));
+            }
+        }
+        clear_pending_exception(cx);
+        preview
     }
+}
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/error
-    pub fn Error(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Error, message);
-            }
-        })
+/// Preview the own property of the given object with the given name, if it has one.
+#[allow(unsafe_code)]
+fn own_property(
+    cx: JSContext,
+    object: HandleObject,
+    name: &str,
+    depth: u32,
+) -> Option<ConsoleArgument> {
+    let name = CString::new(name).ok()?;
+    unsafe {
+        rooted!(in(*cx) let mut desc = PropertyDescriptor::default());
+        if !JS_GetOwnPropertyDescriptor(*cx, object, name.as_ptr(), desc.handle_mut()) {
+            return None;
+        }
+        preview_property(cx, desc.handle(), depth)
     }
+}
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/assert
-    pub fn Assert(global: &GlobalScope, condition: bool, message: Option<DOMString>) {
-        with_stderr_lock(move || {
-            if !condition {
-                let message = message.unwrap_or_else(|| DOMString::from("no message"));
-                println!("Assertion failed: {}", message);
-                Self::send_to_devtools(global, LogLevel::Error, message);
-            }
-        })
+/// Preview the value of a property, without calling its getter if it is an accessor.
+fn preview_property(
+    cx: JSContext,
+    desc: Handle<PropertyDescriptor>,
+    depth: u32,
+) -> Option<ConsoleArgument> {
+    if desc.obj.is_null() {
+        return None;
     }
+    if desc.attrs & (JSPROP_GETTER | JSPROP_SETTER) as u32 != 0 {
+        return Some(ConsoleArgument::String("<getter>".to_owned()));
+    }
+    rooted!(in(*cx) let value = desc.value);
+    Some(preview_value(cx, value.handle(), depth))
+}
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/time
-    pub fn Time(global: &GlobalScope, label: DOMString) {
-        with_stderr_lock(move || {
-            if let Ok(()) = global.time(label.clone()) {
-                let message = DOMString::from(format!("{}: timer started", label));
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Log, message);
-            }
-        })
+#[allow(unsafe_code)]
+fn clear_pending_exception(cx: JSContext) {
+    unsafe {
+        if JS_IsExceptionPending(*cx) {
+            JS_ClearPendingException(*cx);
+        }
     }
+}
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/timeEnd
-    pub fn TimeEnd(global: &GlobalScope, label: DOMString) {
-        with_stderr_lock(move || {
-            if let Ok(delta) = global.time_end(&label) {
-                let message = DOMString::from(format!("{}: {}ms", label, delta));
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Log, message);
+/// Substitute the format specifiers of the first argument, if it is a string, with
+/// the next ones.
+///
+/// https://console.spec.whatwg.org/#formatter
+fn apply_format_specifiers(arguments: Vec<ConsoleArgument>) -> Vec<ConsoleArgument> {
+    let format = match arguments.first() {
+        Some(ConsoleArgument::String(format)) if format.contains('%') => format.clone(),
+        _ => return arguments,
+    };
+    let mut remaining = arguments.into_iter().skip(1);
+    let mut formatted = vec![];
+    let mut current = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            current.push(c);
+            continue;
+        }
+        let specifier = match chars.peek() {
+            Some(&specifier) if "sdifoOc%".contains(specifier) => specifier,
+            _ => {
+                current.push('%');
+                continue;
+            },
+        };
+        chars.next();
+        if specifier == '%' {
+            current.push('%');
+            continue;
+        }
+        let argument = match remaining.next() {
+            Some(argument) => argument,
+            None => {
+                current.push('%');
+                current.push(specifier);
+                continue;
+            },
+        };
+        match specifier {
+            's' => current.push_str(&stringify(&argument, false)),
+            'd' | 'i' => current.push_str(&to_integer(&argument)),
+            'f' => current.push_str(&to_float(&argument)),
+            'o' | 'O' => {
+                // Objects are kept as arguments of their own, to keep their preview.
+                if !current.is_empty() {
+                    formatted.push(ConsoleArgument::String(current.split_off(0)));
+                }
+                formatted.push(argument);
+            },
+            // Styles aren't supported.
+            _ => {},
+        }
+    }
+    if !current.is_empty() || formatted.is_empty() {
+        formatted.push(ConsoleArgument::String(current));
+    }
+    formatted.extend(remaining);
+    formatted
+}
+
+fn to_integer(argument: &ConsoleArgument) -> String {
+    match *argument {
+        ConsoleArgument::Number(n) if n.is_finite() => format_number(n.trunc()),
+        ConsoleArgument::String(ref s) => {
+            let s = s.trim_start();
+            let sign = if s.starts_with('-') || s.starts_with('+') {
+                1
+            } else {
+                0
             };
-        })
+            let digits = s[sign..].chars().take_while(|c| c.is_ascii_digit()).count();
+            s[..sign + digits]
+                .parse::<f64>()
+                .map(format_number)
+                .unwrap_or("NaN".to_owned())
+        },
+        _ => "NaN".to_owned(),
     }
 }
 
-fn prepare_message(log_level: LogLevel, message: DOMString) -> ConsoleMessage {
-    // TODO: Sending fake values for filename, lineNumber and columnNumber in LogMessage; adjust later
-    ConsoleMessage {
-        message: String::from(message),
-        logLevel: log_level,
-        filename: "test".to_owned(),
-        lineNumber: 1,
-        columnNumber: 1,
+fn to_float(argument: &ConsoleArgument) -> String {
+    match *argument {
+        ConsoleArgument::Number(n) => format_number(n),
+        ConsoleArgument::String(ref s) => {
+            let s = s.trim_start();
+            // The longest prefix that is a number.
+            (1..=s.len())
+                .rev()
+                .filter(|&end| s.is_char_boundary(end))
+                .filter_map(|end| s[..end].parse::<f64>().ok())
+                .next()
+                .map(format_number)
+                .unwrap_or("NaN".to_owned())
+        },
+        _ => "NaN".to_owned(),
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if n.is_infinite() {
+        if n < 0. { "-Infinity" } else { "Infinity" }.to_owned()
+    } else {
+        n.to_string()
+    }
+}
+
+/// The text of the given arguments, for the standard output.
+fn stringify_arguments(arguments: &[ConsoleArgument]) -> String {
+    arguments
+        .iter()
+        .map(|argument| stringify(argument, false))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The text of an argument, or of a value in the preview of an object when nested.
+fn stringify(argument: &ConsoleArgument, nested: bool) -> String {
+    match *argument {
+        ConsoleArgument::Undefined => "undefined".to_owned(),
+        ConsoleArgument::Null => "null".to_owned(),
+        ConsoleArgument::Boolean(b) => b.to_string(),
+        ConsoleArgument::Number(n) => format_number(n),
+        ConsoleArgument::String(ref s) if nested => format!("{:?}", s),
+        ConsoleArgument::String(ref s) => s.clone(),
+        ConsoleArgument::Object(ref preview) => stringify_object(preview),
+    }
+}
+
+fn stringify_object(preview: &ObjectPreview) -> String {
+    if let Some(ref name) = preview.name {
+        return format!("function {}()", name);
+    }
+    let ellipsis = |shown: usize, total: usize| if shown < total { ", …" } else { "" };
+    if let Some(length) = preview.length {
+        return match preview.items {
+            Some(ref items) => format!(
+                "[{}{}]",
+                items
+                    .iter()
+                    .map(|item| stringify(item, true))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                ellipsis(items.len(), length as usize),
+            ),
+            None => format!("Array({})", length),
+        };
+    }
+    if preview.ownProperties.is_empty() {
+        return if preview.class == "Object" && preview.ownPropertiesLength == 0 {
+            "{}".to_owned()
+        } else {
+            preview.class.clone()
+        };
+    }
+    let properties = preview
+        .ownProperties
+        .iter()
+        .map(|&(ref name, ref value)| format!("{}: {}", name, stringify(value, true)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let properties = format!(
+        "{{ {}{} }}",
+        properties,
+        ellipsis(
+            preview.ownProperties.len(),
+            preview.ownPropertiesLength as usize
+        ),
+    );
+    if preview.class == "Object" {
+        properties
+    } else {
+        format!("{} {}", preview.class, properties)
+    }
+}
+
+/// The rows of the given object, as their index and their value.
+fn table_rows(data: &ObjectPreview) -> Vec<(String, &ConsoleArgument)> {
+    match data.items {
+        Some(ref items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (index.to_string(), item))
+            .collect(),
+        None => data
+            .ownProperties
+            .iter()
+            .map(|&(ref name, ref value)| (name.clone(), value))
+            .collect(),
+    }
+}
+
+/// The text of a table for the standard output, with a column for the given
+/// properties of the rows, or for all of them.
+///
+/// https://console.spec.whatwg.org/#table
+fn stringify_table(data: &ObjectPreview, properties: &Option<Vec<DOMString>>) -> String {
+    let rows = table_rows(data);
+    let mut columns: Vec<String> = match *properties {
+        Some(ref properties) => properties.iter().map(|p| p.to_string()).collect(),
+        None => vec![],
+    };
+    let mut has_values = false;
+    for &(_, value) in &rows {
+        match *value {
+            ConsoleArgument::Object(ref row) if row.name.is_none() => {
+                if properties.is_none() {
+                    for (name, _) in table_rows(row) {
+                        if !columns.contains(&name) {
+                            columns.push(name);
+                        }
+                    }
+                }
+            },
+            _ => has_values = true,
+        }
+    }
+
+    let mut header = vec!["(index)".to_owned()];
+    header.extend(columns.iter().cloned());
+    if has_values {
+        header.push("Values".to_owned());
+    }
+    let mut lines = vec![header];
+    for (index, value) in rows {
+        let mut line = vec![index];
+        let cells = match *value {
+            ConsoleArgument::Object(ref row) if row.name.is_none() => table_rows(row),
+            _ => vec![],
+        };
+        for column in &columns {
+            line.push(
+                cells
+                    .iter()
+                    .find(|&&(ref name, _)| name == column)
+                    .map_or(String::new(), |&(_, cell)| stringify(cell, true)),
+            );
+        }
+        if has_values {
+            line.push(match *value {
+                ConsoleArgument::Object(ref row) if row.name.is_none() => String::new(),
+                ref value => stringify(value, true),
+            });
+        }
+        lines.push(line);
+    }
+
+    let widths: Vec<usize> = (0..lines[0].len())
+        .map(|column| {
+            lines
+                .iter()
+                .map(|line| line[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join(" | ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[allow(non_snake_case)]
+impl Console {
+    // https://console.spec.whatwg.org/#log
+    pub fn Log(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        console_messages(cx, global, LogLevel::Log, messages)
+    }
+
+    // https://console.spec.whatwg.org/#debug
+    pub fn Debug(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        console_messages(cx, global, LogLevel::Debug, messages)
+    }
+
+    // https://console.spec.whatwg.org/#info
+    pub fn Info(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        console_messages(cx, global, LogLevel::Info, messages)
+    }
+
+    // https://console.spec.whatwg.org/#warn
+    pub fn Warn(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        console_messages(cx, global, LogLevel::Warn, messages)
+    }
+
+    // https://console.spec.whatwg.org/#error
+    pub fn Error(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        console_messages(cx, global, LogLevel::Error, messages)
+    }
+
+    // https://console.spec.whatwg.org/#assert
+    pub fn Assert(
+        cx: JSContext,
+        global: &GlobalScope,
+        condition: bool,
+        messages: Vec<HandleValue>,
+    ) {
+        if condition {
+            return;
+        }
+        let mut arguments: Vec<ConsoleArgument> = messages
+            .into_iter()
+            .map(|message| preview_value(cx, message, 1))
+            .collect();
+        match arguments.first_mut() {
+            Some(&mut ConsoleArgument::String(ref mut message)) => {
+                *message = format!("Assertion failed: {}", message);
+            },
+            _ => arguments.insert(0, ConsoleArgument::String("Assertion failed".to_owned())),
+        }
+        console_message(global, LogLevel::Error, apply_format_specifiers(arguments));
+    }
+
+    // https://console.spec.whatwg.org/#clear
+    pub fn Clear(global: &GlobalScope) {
+        console_message_with_text(global, LogLevel::Clear, vec![], String::new());
+    }
+
+    // https://console.spec.whatwg.org/#table
+    pub fn Table(
+        cx: JSContext,
+        global: &GlobalScope,
+        tabular_data: HandleValue,
+        properties: Option<Vec<DOMString>>,
+    ) {
+        let data = match preview_value(cx, tabular_data, 2) {
+            ConsoleArgument::Object(ref data) if data.name.is_none() => data.clone(),
+            data => return console_message(global, LogLevel::Log, vec![data]),
+        };
+        let text = stringify_table(&data, &properties);
+        let mut arguments = vec![ConsoleArgument::Object(data)];
+        if let Some(properties) = properties {
+            let columns: Vec<ConsoleArgument> = properties
+                .into_iter()
+                .map(|property| ConsoleArgument::String(property.into()))
+                .collect();
+            arguments.push(ConsoleArgument::Object(ObjectPreview {
+                class: "Array".to_owned(),
+                name: None,
+                length: Some(columns.len() as u32),
+                items: Some(columns),
+                ownProperties: vec![],
+                ownPropertiesLength: 0,
+            }));
+        }
+        console_message_with_text(global, LogLevel::Table, arguments, text);
+    }
+
+    // https://console.spec.whatwg.org/#trace
+    pub fn Trace(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        let mut arguments: Vec<ConsoleArgument> = messages
+            .into_iter()
+            .map(|message| preview_value(cx, message, 1))
+            .collect();
+        if arguments.is_empty() {
+            arguments.push(ConsoleArgument::String("console.trace()".to_owned()));
+        }
+        console_message(global, LogLevel::Trace, apply_format_specifiers(arguments));
+    }
+
+    // https://console.spec.whatwg.org/#dir
+    pub fn Dir(cx: JSContext, global: &GlobalScope, item: HandleValue) {
+        let argument = preview_value(cx, item, 1);
+        console_message(global, LogLevel::Dir, vec![argument]);
+    }
+
+    // https://console.spec.whatwg.org/#dirxml
+    pub fn Dirxml(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        console_messages(cx, global, LogLevel::Log, messages)
+    }
+
+    // https://console.spec.whatwg.org/#count
+    pub fn Count(global: &GlobalScope, label: DOMString) {
+        let count = global.increment_console_count(&label);
+        let message = format!("{}: {}", label, count);
+        console_message(
+            global,
+            LogLevel::Info,
+            vec![ConsoleArgument::String(message)],
+        );
+    }
+
+    // https://console.spec.whatwg.org/#countreset
+    pub fn CountReset(global: &GlobalScope, label: DOMString) {
+        if global.reset_console_count(&label).is_err() {
+            let message = format!("Counter “{}” doesn’t exist.", label);
+            Self::internal_warn(global, DOMString::from(message));
+        }
+    }
+
+    // https://console.spec.whatwg.org/#group
+    pub fn Group(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        Self::group(cx, global, LogLevel::Group, messages)
+    }
+
+    // https://console.spec.whatwg.org/#groupcollapsed
+    pub fn GroupCollapsed(cx: JSContext, global: &GlobalScope, messages: Vec<HandleValue>) {
+        Self::group(cx, global, LogLevel::GroupCollapsed, messages)
+    }
+
+    fn group(cx: JSContext, global: &GlobalScope, level: LogLevel, messages: Vec<HandleValue>) {
+        let arguments: Vec<ConsoleArgument> = messages
+            .into_iter()
+            .map(|message| preview_value(cx, message, 1))
+            .collect();
+        let arguments = if arguments.is_empty() {
+            vec![ConsoleArgument::String("<no group label>".to_owned())]
+        } else {
+            apply_format_specifiers(arguments)
+        };
+        let label = stringify_arguments(&arguments);
+        console_message(global, level, arguments);
+        global.push_console_group(DOMString::from(label));
+    }
+
+    // https://console.spec.whatwg.org/#groupend
+    pub fn GroupEnd(global: &GlobalScope) {
+        global.pop_console_group();
+        console_message_with_text(global, LogLevel::GroupEnd, vec![], String::new());
+    }
+
+    // https://console.spec.whatwg.org/#time
+    pub fn Time(global: &GlobalScope, label: DOMString) {
+        let message = match global.time(label.clone()) {
+            Ok(()) => return,
+            Err(()) => format!("Timer “{}” already exists.", label),
+        };
+        Self::internal_warn(global, DOMString::from(message));
+    }
+
+    // https://console.spec.whatwg.org/#timelog
+    pub fn TimeLog(cx: JSContext, global: &GlobalScope, label: DOMString, data: Vec<HandleValue>) {
+        match global.time_log(&label) {
+            Ok(delta) => {
+                let mut arguments =
+                    vec![ConsoleArgument::String(format!("{}: {}ms", label, delta))];
+                arguments.extend(data.into_iter().map(|value| preview_value(cx, value, 1)));
+                console_message(global, LogLevel::Log, arguments);
+            },
+            Err(()) => {
+                let message = format!("Timer “{}” doesn’t exist.", label);
+                Self::internal_warn(global, DOMString::from(message));
+            },
+        }
+    }
+
+    // https://console.spec.whatwg.org/#timeend
+    pub fn TimeEnd(global: &GlobalScope, label: DOMString) {
+        let message = match global.time_end(&label) {
+            Ok(delta) => format!("{}: {}ms - timer ended", label, delta),
+            Err(()) => {
+                let message = format!("Timer “{}” doesn’t exist.", label);
+                return Self::internal_warn(global, DOMString::from(message));
+            },
+        };
+        console_message(
+            global,
+            LogLevel::Log,
+            vec![ConsoleArgument::String(message)],
+        );
     }
 }
@@ -0,0 +1,108 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CookieChangeEventBinding::{
+    self, CookieChangeEventMethods,
+};
+use crate::dom::bindings::codegen::Bindings::CookieStoreBinding::CookieListItem;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventBinding::EventMethods;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::event::Event;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::window::Window;
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::Heap;
+use js::jsval::{JSVal, UndefinedValue};
+use servo_atoms::Atom;
+
+#[dom_struct]
+pub struct CookieChangeEvent {
+    event: Event,
+    #[ignore_malloc_size_of = "mozjs"]
+    changed: Heap<JSVal>,
+    #[ignore_malloc_size_of = "mozjs"]
+    deleted: Heap<JSVal>,
+}
+
+impl CookieChangeEvent {
+    #[allow(unrooted_must_root)]
+    fn new_inherited() -> CookieChangeEvent {
+        CookieChangeEvent {
+            event: Event::new_inherited(),
+            changed: Heap::default(),
+            deleted: Heap::default(),
+        }
+    }
+
+    #[allow(unsafe_code)]
+    pub fn new(
+        global: &GlobalScope,
+        type_: Atom,
+        bubbles: bool,
+        cancelable: bool,
+        changed: &[CookieListItem],
+        deleted: &[CookieListItem],
+    ) -> DomRoot<CookieChangeEvent> {
+        let changeevent = reflect_dom_object(
+            Box::new(CookieChangeEvent::new_inherited()),
+            global,
+            CookieChangeEventBinding::Wrap,
+        );
+        {
+            let event = changeevent.upcast::<Event>();
+            event.init_event(type_, bubbles, cancelable);
+        }
+        let _ac = enter_realm(&*global);
+        let cx = global.get_cx();
+        unsafe {
+            rooted!(in(*cx) let mut changed_val = UndefinedValue());
+            changed.to_jsval(*cx, changed_val.handle_mut());
+            changeevent.changed.set(changed_val.get());
+            rooted!(in(*cx) let mut deleted_val = UndefinedValue());
+            deleted.to_jsval(*cx, deleted_val.handle_mut());
+            changeevent.deleted.set(deleted_val.get());
+        }
+
+        changeevent
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        type_: DOMString,
+        init: &CookieChangeEventBinding::CookieChangeEventInit,
+    ) -> DomRoot<CookieChangeEvent> {
+        CookieChangeEvent::new(
+            &window.global(),
+            Atom::from(type_),
+            init.parent.bubbles,
+            init.parent.cancelable,
+            init.changed.as_ref().map_or(&[], |changed| &changed[..]),
+            init.deleted.as_ref().map_or(&[], |deleted| &deleted[..]),
+        )
+    }
+}
+
+impl CookieChangeEventMethods for CookieChangeEvent {
+    // https://wicg.github.io/cookie-store/#dom-cookiechangeevent-changed
+    fn Changed(&self, _cx: JSContext) -> JSVal {
+        self.changed.get()
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiechangeevent-deleted
+    fn Deleted(&self, _cx: JSContext) -> JSVal {
+        self.deleted.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-event-istrusted
+    fn IsTrusted(&self) -> bool {
+        self.event.IsTrusted()
+    }
+}
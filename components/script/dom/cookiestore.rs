@@ -0,0 +1,423 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::CookieStoreBinding::{
+    self, CookieInit, CookieListItem, CookieSameSite, CookieStoreDeleteOptions,
+    CookieStoreGetOptions, CookieStoreMethods,
+};
+use crate::dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::USVString;
+use crate::dom::cookiechangeevent::CookieChangeEvent;
+use crate::dom::event::Event;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::promise::{PendingPromise, Promise};
+use crate::dom::window::Window;
+use crate::task_source::{TaskSource, TaskSourceName};
+use cookie::{Cookie, SameSite};
+use dom_struct::dom_struct;
+use hyper_serde::Serde;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use net_traits::CookieSource::NonHTTP;
+use net_traits::CoreResourceMsg::{GetCookiesDataForUrl, ObserveCookiesForUrl, SetCookieForUrl};
+use net_traits::IpcSend;
+use servo_url::ServoUrl;
+use std::mem;
+use std::rc::Rc;
+use time::{self, Timespec, Tm};
+
+/// The prefix of the names of the cookies which can only be set for the host of a document,
+/// on every path.
+const HOST_PREFIX: &str = "__Host-";
+
+#[dom_struct]
+pub struct CookieStore {
+    eventtarget: EventTarget,
+    /// The cookies of the document when they were last read, which `change` events are
+    /// relative to.
+    #[ignore_malloc_size_of = "Defined in cookie"]
+    cookies: DomRefCell<Option<Vec<Cookie<'static>>>>,
+}
+
+impl CookieStore {
+    fn new_inherited() -> CookieStore {
+        CookieStore {
+            eventtarget: EventTarget::new_inherited(),
+            cookies: DomRefCell::new(None),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<CookieStore> {
+        let store = reflect_dom_object(
+            Box::new(CookieStore::new_inherited()),
+            window,
+            CookieStoreBinding::Wrap,
+        );
+        store.observe_changes();
+        store
+    }
+
+    /// Read the cookies of the document, and be notified of their next change.
+    fn observe_changes(&self) {
+        let global = self.global();
+        let url = global.get_url();
+        let task_source = global.networking_task_source();
+        let canceller = global.task_canceller(TaskSourceName::Networking);
+        let this = Trusted::new(self);
+
+        // The notification of the next change is only sent once, and the cookies are
+        // observed again when it is received.
+        let (observed_sender, _) = ipc::channel().unwrap();
+        let (observer, invalidation) = ipc::channel::<()>().unwrap();
+        let store = this.clone();
+        let changes_task_source = task_source.clone();
+        let changes_canceller = global.task_canceller(TaskSourceName::Networking);
+        ROUTER.add_route(
+            invalidation.to_opaque(),
+            Box::new(move |_| {
+                let store = store.clone();
+                let _ = changes_task_source.queue_with_canceller(
+                    task!(observe_cookie_changes: move || {
+                        store.root().observe_changes();
+                    }),
+                    &changes_canceller,
+                );
+            }),
+        );
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let cookies: Vec<Serde<Cookie<'static>>> = match message.to() {
+                    Ok(cookies) => cookies,
+                    Err(_) => return,
+                };
+                let store = this.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(cookies_changed: move || {
+                        let cookies = cookies.into_iter().map(|cookie| cookie.into_inner());
+                        store.root().cookies_changed(cookies.collect());
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+
+        let resource_threads = global.resource_threads();
+        let _ = resource_threads.send(ObserveCookiesForUrl(url.clone(), observed_sender, observer));
+        let _ = resource_threads.send(GetCookiesDataForUrl(url, sender, NonHTTP));
+    }
+
+    /// <https://wicg.github.io/cookie-store/#process-changes>
+    fn cookies_changed(&self, cookies: Vec<Cookie<'static>>) {
+        let previous = mem::replace(&mut *self.cookies.borrow_mut(), Some(cookies.clone()));
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return,
+        };
+
+        let changed: Vec<CookieListItem> = cookies
+            .iter()
+            .filter(|cookie| !previous.contains(cookie))
+            .map(cookie_list_item)
+            .collect();
+        let deleted: Vec<CookieListItem> = previous
+            .iter()
+            .filter(|old| !cookies.iter().any(|cookie| is_same_cookie(cookie, old)))
+            .map(cookie_list_item)
+            .collect();
+        if changed.is_empty() && deleted.is_empty() {
+            return;
+        }
+
+        let event = CookieChangeEvent::new(
+            &self.global(),
+            atom!("change"),
+            false,
+            false,
+            &changed,
+            &deleted,
+        );
+        event.upcast::<Event>().fire(self.upcast());
+    }
+
+    /// The URL of the document, whose cookies are those of the store.
+    fn url(&self) -> Fallible<ServoUrl> {
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return Err(Error::Security);
+        }
+        Ok(global.get_url())
+    }
+
+    /// <https://wicg.github.io/cookie-store/#query-cookies>
+    fn query_cookies(
+        &self,
+        name: Option<USVString>,
+        url: Option<&USVString>,
+        first_only: bool,
+    ) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let document_url = match self.url() {
+            Ok(url) => url,
+            Err(error) => {
+                promise.reject_error(error);
+                return promise;
+            },
+        };
+        let url = match url {
+            Some(url) => match global.api_base_url().join(&url.0) {
+                Ok(mut url) => {
+                    // Only the cookies of the document itself can be read.
+                    let mut document_url = document_url.clone();
+                    url.set_fragment(None);
+                    document_url.set_fragment(None);
+                    if url != document_url {
+                        promise.reject_error(Error::Type("Invalid URL".to_owned()));
+                        return promise;
+                    }
+                    url
+                },
+                Err(_) => {
+                    promise.reject_error(Error::Type("Invalid URL".to_owned()));
+                    return promise;
+                },
+            },
+            None => document_url,
+        };
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        PendingPromise::new(
+            &promise,
+            global.networking_task_source(),
+            global.task_canceller(TaskSourceName::Networking),
+        )
+        .settle_with_message(
+            receiver.to_opaque(),
+            move |promise, cookies: Vec<Serde<Cookie<'static>>>| {
+                let cookies: Vec<CookieListItem> = cookies
+                    .iter()
+                    .filter(|cookie| name.as_ref().map_or(true, |name| cookie.name() == name.0))
+                    .map(|cookie| cookie_list_item(cookie))
+                    .collect();
+                if first_only {
+                    promise.resolve_native(&cookies.into_iter().next());
+                } else {
+                    promise.resolve_native(&cookies);
+                }
+            },
+        );
+        let _ = global
+            .resource_threads()
+            .send(GetCookiesDataForUrl(url, sender, NonHTTP));
+        promise
+    }
+
+    /// <https://wicg.github.io/cookie-store/#set-a-cookie>
+    fn set_cookie(
+        &self,
+        name: &str,
+        value: &str,
+        expires: Option<Tm>,
+        domain: Option<&str>,
+        path: &str,
+        same_site: CookieSameSite,
+    ) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        match self.url().and_then(|url| {
+            let cookie = create_cookie(&url, name, value, expires, domain, path, same_site)?;
+            Ok((url, cookie))
+        }) {
+            Ok((url, cookie)) => {
+                let _ =
+                    global
+                        .resource_threads()
+                        .send(SetCookieForUrl(url, Serde(cookie), NonHTTP));
+                promise.resolve_native(&());
+            },
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    /// <https://wicg.github.io/cookie-store/#delete-a-cookie>
+    fn delete_cookie(&self, name: &str, domain: Option<&str>, path: &str) -> Rc<Promise> {
+        // A cookie is deleted by replacing it with one which has already expired.
+        let expired = time::at_utc(Timespec::new(0, 0));
+        self.set_cookie(
+            name,
+            "",
+            Some(expired),
+            domain,
+            path,
+            CookieSameSite::Strict,
+        )
+    }
+}
+
+impl CookieStoreMethods for CookieStore {
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-get
+    fn Get(&self, name: USVString) -> Rc<Promise> {
+        self.query_cookies(Some(name), None, true)
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-get-options
+    fn Get_(&self, options: &CookieStoreGetOptions) -> Rc<Promise> {
+        self.query_cookies(options.name.clone(), options.url.as_ref(), true)
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-getall
+    fn GetAll(&self, name: USVString) -> Rc<Promise> {
+        self.query_cookies(Some(name), None, false)
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-getall-options
+    fn GetAll_(&self, options: &CookieStoreGetOptions) -> Rc<Promise> {
+        self.query_cookies(options.name.clone(), options.url.as_ref(), false)
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-set
+    fn Set(&self, name: USVString, value: USVString) -> Rc<Promise> {
+        self.set_cookie(&name.0, &value.0, None, None, "/", CookieSameSite::Strict)
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-set-options
+    fn Set_(&self, options: &CookieInit) -> Rc<Promise> {
+        self.set_cookie(
+            &options.name.0,
+            &options.value.0,
+            options.expires.map(time_from_timestamp),
+            options.domain.as_ref().map(|domain| &*domain.0),
+            &options.path.0,
+            options.sameSite,
+        )
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-delete
+    fn Delete(&self, name: USVString) -> Rc<Promise> {
+        self.delete_cookie(&name.0, None, "/")
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-delete-options
+    fn Delete_(&self, options: &CookieStoreDeleteOptions) -> Rc<Promise> {
+        self.delete_cookie(
+            &options.name.0,
+            options.domain.as_ref().map(|domain| &*domain.0),
+            &options.path.0,
+        )
+    }
+
+    // https://wicg.github.io/cookie-store/#dom-cookiestore-onchange
+    event_handler!(change, GetOnchange, SetOnchange);
+}
+
+/// <https://wicg.github.io/cookie-store/#set-a-cookie>
+fn create_cookie(
+    url: &ServoUrl,
+    name: &str,
+    value: &str,
+    expires: Option<Tm>,
+    domain: Option<&str>,
+    path: &str,
+    same_site: CookieSameSite,
+) -> Fallible<Cookie<'static>> {
+    // Step 1.
+    let is_invalid = |string: &str| {
+        string
+            .chars()
+            .any(|c| c == ';' || (c.is_control() && c != '\t'))
+    };
+    if is_invalid(name) || is_invalid(value) || name.contains('=') {
+        return Err(Error::Type("Invalid cookie name or value".to_owned()));
+    }
+    if name.is_empty() && (value.is_empty() || value.contains('=')) {
+        return Err(Error::Type("Invalid cookie name or value".to_owned()));
+    }
+
+    // Step 3.
+    let host = url.host_str().unwrap_or("").to_owned();
+    if let Some(domain) = domain {
+        if domain.starts_with('.') {
+            return Err(Error::Type("Invalid cookie domain".to_owned()));
+        }
+        if host != domain && !host.ends_with(&format!(".{}", domain)) {
+            return Err(Error::Type("Invalid cookie domain".to_owned()));
+        }
+    }
+
+    // Step 5.
+    if !path.starts_with('/') {
+        return Err(Error::Type("Invalid cookie path".to_owned()));
+    }
+    let mut path = path.to_owned();
+    if !path.ends_with('/') {
+        path.push('/');
+    }
+
+    // Step 6.
+    if name.starts_with(HOST_PREFIX) && (domain.is_some() || path != "/") {
+        return Err(Error::Type("Invalid __Host- cookie".to_owned()));
+    }
+
+    let mut cookie = Cookie::build(name.to_owned(), value.to_owned())
+        .path(path)
+        .secure(true)
+        .same_site(match same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        })
+        .finish();
+    if let Some(domain) = domain {
+        cookie.set_domain(domain.to_owned());
+    }
+    if let Some(expires) = expires {
+        cookie.set_expires(expires);
+    }
+    Ok(cookie)
+}
+
+/// <https://wicg.github.io/cookie-store/#create-a-cookielistitem>
+fn cookie_list_item(cookie: &Cookie) -> CookieListItem {
+    CookieListItem {
+        name: Some(USVString(cookie.name().to_owned())),
+        value: Some(USVString(cookie.value().to_owned())),
+        domain: Some(cookie.domain().map(|domain| USVString(domain.to_owned()))),
+        path: Some(USVString(cookie.path().unwrap_or("/").to_owned())),
+        expires: Some(cookie.expires().map(timestamp_from_time)),
+        secure: Some(cookie.secure().unwrap_or(false)),
+        sameSite: Some(match cookie.same_site() {
+            Some(SameSite::Strict) => CookieSameSite::Strict,
+            Some(SameSite::Lax) => CookieSameSite::Lax,
+            _ => CookieSameSite::None,
+        }),
+    }
+}
+
+/// Whether two cookies would replace each other when stored.
+fn is_same_cookie(a: &Cookie, b: &Cookie) -> bool {
+    a.name() == b.name() && a.domain() == b.domain() && a.path() == b.path()
+}
+
+fn time_from_timestamp(timestamp: u64) -> Tm {
+    let timestamp = timestamp as i64;
+    time::at_utc(Timespec::new(
+        timestamp / 1000,
+        (timestamp % 1000) as i32 * 1_000_000,
+    ))
+}
+
+fn timestamp_from_time(time: Tm) -> u64 {
+    let time = time.to_timespec();
+    (time.sec * 1000 + i64::from(time.nsec / 1_000_000)).max(0) as u64
+}
@@ -0,0 +1,72 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CountQueuingStrategyBinding::{
+    self, CountQueuingStrategyMethods,
+};
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategyInit;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bytelengthqueuingstrategy::size_function;
+use crate::dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+use js::jsapi::{CallArgs, Heap, JSContext, JSObject};
+use js::jsval::{Int32Value, JSVal};
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct CountQueuingStrategy {
+    reflector_: Reflector,
+    high_water_mark: f64,
+    #[ignore_malloc_size_of = "mozjs"]
+    size: Heap<*mut JSObject>,
+}
+
+impl CountQueuingStrategy {
+    fn new_inherited(high_water_mark: f64) -> CountQueuingStrategy {
+        CountQueuingStrategy {
+            reflector_: Reflector::new(),
+            high_water_mark,
+            size: Heap::default(),
+        }
+    }
+
+    fn new(global: &GlobalScope, high_water_mark: f64) -> DomRoot<CountQueuingStrategy> {
+        reflect_dom_object(
+            Box::new(CountQueuingStrategy::new_inherited(high_water_mark)),
+            global,
+            CountQueuingStrategyBinding::Wrap,
+        )
+    }
+
+    // https://streams.spec.whatwg.org/#cqs-constructor
+    pub fn Constructor(
+        global: &GlobalScope,
+        init: &QueuingStrategyInit,
+    ) -> Fallible<DomRoot<CountQueuingStrategy>> {
+        Ok(CountQueuingStrategy::new(global, init.highWaterMark))
+    }
+}
+
+impl CountQueuingStrategyMethods for CountQueuingStrategy {
+    // https://streams.spec.whatwg.org/#cqs-high-water-mark
+    fn HighWaterMark(&self) -> f64 {
+        self.high_water_mark
+    }
+
+    // https://streams.spec.whatwg.org/#cqs-size
+    fn Size(&self) -> Rc<Function> {
+        size_function(self.global().get_cx(), &self.size, Some(count_size))
+    }
+}
+
+/// The size of a chunk, which is 1 for every chunk.
+#[allow(unsafe_code)]
+unsafe extern "C" fn count_size(_cx: *mut JSContext, argc: u32, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    *args.rval() = Int32Value(1);
+    true
+}
@@ -4,23 +4,18 @@
 
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
 use crate::dom::bindings::error::Fallible;
-use crate::dom::bindings::reflector::Reflector;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::window::Window;
 use crate::dom::worklet::Worklet;
 use cssparser::{serialize_identifier, Parser, ParserInput};
-use dom_struct::dom_struct;
 use style::context::QuirksMode;
 use style::parser::ParserContext;
 use style::stylesheets::supports_rule::{parse_condition_or_declaration, Declaration};
 use style::stylesheets::CssRuleType;
 use style_traits::ParsingMode;
 
-#[dom_struct]
-pub struct CSS {
-    reflector_: Reflector,
-}
+pub struct CSS(());
 
 #[allow(non_snake_case)]
 impl CSS {
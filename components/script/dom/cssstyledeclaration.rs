@@ -41,6 +41,8 @@ pub struct CSSStyleDeclaration {
 #[derive(JSTraceable, MallocSizeOf)]
 #[unrooted_must_root_lint::must_root]
 pub enum CSSStyleOwner {
+    /// The owner of the empty declarations of computed styles that do not exist.
+    Null(Dom<Window>),
     Element(Dom<Element>),
     CSSRule(
         Dom<CSSRule>,
@@ -60,6 +62,9 @@ impl CSSStyleOwner {
         // This is somewhat complex but the complexity is encapsulated.
         let mut changed = true;
         match *self {
+            CSSStyleOwner::Null(..) => {
+                panic!("mutate_associated_block called on CSSStyleDeclaration with a Null owner")
+            },
             CSSStyleOwner::Element(ref el) => {
                 let document = document_from_node(&**el);
                 let shared_lock = document.style_shared_lock();
@@ -128,6 +133,7 @@ impl CSSStyleOwner {
         F: FnOnce(&PropertyDeclarationBlock) -> R,
     {
         match *self {
+            CSSStyleOwner::Null(..) => f(&PropertyDeclarationBlock::new()),
             CSSStyleOwner::Element(ref el) => match *el.style_attribute().borrow() {
                 Some(ref pdb) => {
                     let document = document_from_node(&**el);
@@ -148,6 +154,7 @@ impl CSSStyleOwner {
 
     fn window(&self) -> DomRoot<Window> {
         match *self {
+            CSSStyleOwner::Null(ref window) => DomRoot::from_ref(&**window),
             CSSStyleOwner::Element(ref el) => window_from_node(&**el),
             CSSStyleOwner::CSSRule(ref rule, _) => DomRoot::from_ref(rule.global().as_window()),
         }
@@ -155,6 +162,7 @@ impl CSSStyleOwner {
 
     fn base_url(&self) -> ServoUrl {
         match *self {
+            CSSStyleOwner::Null(ref window) => window.Document().base_url(),
             CSSStyleOwner::Element(ref el) => window_from_node(&**el).Document().base_url(),
             CSSStyleOwner::CSSRule(ref rule, _) => (*rule
                 .parent_stylesheet()
@@ -239,6 +247,7 @@ impl CSSStyleDeclaration {
 
     fn get_computed_style(&self, property: PropertyId) -> DOMString {
         match self.owner {
+            CSSStyleOwner::Null(..) => DOMString::new(),
             CSSStyleOwner::CSSRule(..) => {
                 panic!("get_computed_style called on CSSStyleDeclaration with a CSSRule owner")
             },
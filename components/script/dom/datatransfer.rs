@@ -0,0 +1,140 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::DataTransferBinding::{self, DataTransferMethods};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::utils::to_frozen_array;
+use crate::dom::window::Window;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::JSVal;
+use std::cell::Cell;
+
+/// <https://html.spec.whatwg.org/multipage/#drag-data-store-mode>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum DataStoreMode {
+    ReadWrite,
+    ReadOnly,
+    Protected,
+}
+
+#[dom_struct]
+pub struct DataTransfer {
+    reflector_: Reflector,
+    /// The string items of the drag data store, as pairs of format and data, in the
+    /// order they were added.
+    items: DomRefCell<Vec<(DOMString, DOMString)>>,
+    mode: Cell<DataStoreMode>,
+}
+
+impl DataTransfer {
+    fn new_inherited(mode: DataStoreMode) -> DataTransfer {
+        DataTransfer {
+            reflector_: Reflector::new(),
+            items: DomRefCell::new(vec![]),
+            mode: Cell::new(mode),
+        }
+    }
+
+    pub fn new(window: &Window, mode: DataStoreMode) -> DomRoot<DataTransfer> {
+        reflect_dom_object(
+            Box::new(DataTransfer::new_inherited(mode)),
+            window,
+            DataTransferBinding::Wrap,
+        )
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-datatransfer
+    #[allow(non_snake_case)]
+    pub fn Constructor(window: &Window) -> DomRoot<DataTransfer> {
+        DataTransfer::new(window, DataStoreMode::ReadWrite)
+    }
+
+    pub fn set_mode(&self, mode: DataStoreMode) {
+        self.mode.set(mode);
+    }
+
+    /// Add an item to the store regardless of its mode, such as the contents of the
+    /// clipboard before a paste event is fired.
+    pub fn add_item(&self, format: &str, data: DOMString) {
+        let format = normalize_format(format);
+        let mut items = self.items.borrow_mut();
+        items.retain(|&(ref item_format, _)| *item_format != format);
+        items.push((format, data));
+    }
+
+    /// The data of the given format, regardless of the mode of the store.
+    pub fn data(&self, format: &str) -> Option<DOMString> {
+        let format = normalize_format(format);
+        self.items
+            .borrow()
+            .iter()
+            .find(|&&(ref item_format, _)| *item_format == format)
+            .map(|&(_, ref data)| data.clone())
+    }
+}
+
+/// Steps 2-4 of <https://html.spec.whatwg.org/multipage/#dom-datatransfer-getdata>.
+fn normalize_format(format: &str) -> DOMString {
+    let format = format.to_ascii_lowercase();
+    match &*format {
+        "text" => DOMString::from("text/plain"),
+        "url" => DOMString::from("text/uri-list"),
+        _ => DOMString::from(format),
+    }
+}
+
+impl DataTransferMethods for DataTransfer {
+    // https://html.spec.whatwg.org/multipage/#dom-datatransfer-types
+    fn Types(&self, cx: JSContext) -> JSVal {
+        let types: Vec<DOMString> = self
+            .items
+            .borrow()
+            .iter()
+            .map(|&(ref format, _)| format.clone())
+            .collect();
+        to_frozen_array(types.as_slice(), cx)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-datatransfer-getdata
+    fn GetData(&self, format: DOMString) -> DOMString {
+        // Step 2.
+        if self.mode.get() == DataStoreMode::Protected {
+            return DOMString::new();
+        }
+        // Steps 3-9.
+        self.data(&format).unwrap_or_default()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-datatransfer-setdata
+    fn SetData(&self, format: DOMString, data: DOMString) {
+        // Step 2.
+        if self.mode.get() != DataStoreMode::ReadWrite {
+            return;
+        }
+        // Steps 3-5.
+        self.add_item(&format, data);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-datatransfer-cleardata
+    fn ClearData(&self, format: Option<DOMString>) {
+        // Step 2.
+        if self.mode.get() != DataStoreMode::ReadWrite {
+            return;
+        }
+        let mut items = self.items.borrow_mut();
+        match format {
+            // Step 3.
+            None => items.clear(),
+            // Steps 4-5.
+            Some(format) => {
+                let format = normalize_format(&format);
+                items.retain(|&(ref item_format, _)| *item_format != format);
+            },
+        }
+    }
+}
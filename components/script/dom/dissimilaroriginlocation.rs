@@ -11,7 +11,11 @@ use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::bindings::str::USVString;
 use crate::dom::dissimilaroriginwindow::DissimilarOriginWindow;
+use crate::dom::globalscope::GlobalScope;
 use dom_struct::dom_struct;
+use net_traits::request::Referrer;
+use script_traits::{HistoryEntryReplacement, LoadData, LoadOrigin, ScriptMsg};
+use servo_url::ServoUrl;
 
 /// Represents a dissimilar-origin `Location` that exists in another script thread.
 ///
@@ -45,6 +49,25 @@ impl DissimilarOriginLocation {
             DissimilarOriginLocationBinding::Wrap,
         )
     }
+
+    /// <https://html.spec.whatwg.org/multipage/#location-object-navigate>
+    ///
+    /// The document of the location is in another script thread, so the navigation
+    /// goes through the constellation.
+    fn navigate(&self, url: ServoUrl, replacement_flag: HistoryEntryReplacement) {
+        let entry = GlobalScope::entry();
+        let load_data = LoadData::new(
+            LoadOrigin::Script(entry.origin().immutable().clone()),
+            url,
+            Some(entry.pipeline_id()),
+            Some(Referrer::ReferrerUrl(entry.get_url())),
+            None,
+        );
+        let browsing_context_id = self.window.window_proxy().browsing_context_id();
+        let msg =
+            ScriptMsg::NavigateBrowsingContext(browsing_context_id, load_data, replacement_flag);
+        let _ = entry.script_to_constellation_chan().send(msg);
+    }
 }
 
 impl DissimilarOriginLocationMethods for DissimilarOriginLocation {
@@ -54,21 +77,28 @@ impl DissimilarOriginLocationMethods for DissimilarOriginLocation {
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-location-href
-    fn SetHref(&self, _: USVString) -> ErrorResult {
-        // TODO: setting href on a cross-origin window should succeed?
-        Err(Error::Security)
+    fn SetHref(&self, value: USVString) -> ErrorResult {
+        let url = match GlobalScope::entry().api_base_url().join(&value.0) {
+            Ok(url) => url,
+            Err(e) => return Err(Error::Type(format!("Couldn't parse URL: {}", e))),
+        };
+        self.navigate(url, HistoryEntryReplacement::Disabled);
+        Ok(())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-location-assign
     fn Assign(&self, _: USVString) -> Fallible<()> {
-        // TODO: setting href on a cross-origin window should succeed?
         Err(Error::Security)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-location-replace
-    fn Replace(&self, _: USVString) -> Fallible<()> {
-        // TODO: replacing href on a cross-origin window should succeed?
-        Err(Error::Security)
+    fn Replace(&self, url: USVString) -> Fallible<()> {
+        let url = match GlobalScope::entry().api_base_url().join(&url.0) {
+            Ok(url) => url,
+            Err(_) => return Err(Error::Syntax),
+        };
+        self.navigate(url, HistoryEntryReplacement::Enabled);
+        Ok(())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-location-reload
@@ -6,6 +6,7 @@ use crate::dom::bindings::codegen::Bindings::DissimilarOriginWindowBinding;
 use crate::dom::bindings::codegen::Bindings::DissimilarOriginWindowBinding::DissimilarOriginWindowMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowPostMessageOptions;
 use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::USVString;
 use crate::dom::bindings::structuredclone;
@@ -62,6 +63,7 @@ impl DissimilarOriginWindow {
                 global_to_clone_from.microtask_queue().clone(),
                 global_to_clone_from.is_headless(),
                 global_to_clone_from.get_user_agent(),
+                global_to_clone_from.is_private(),
             ),
             window_proxy: Dom::from_ref(window_proxy),
             location: Default::default(),
@@ -122,13 +124,28 @@ impl DissimilarOriginWindowMethods for DissimilarOriginWindow {
 
     // https://html.spec.whatwg.org/multipage/#dom-window-close
     fn Close(&self) {
-        // TODO: Implement x-origin close
+        // Step 2.
+        if self.window_proxy.is_browsing_context_discarded() || self.window_proxy.is_closing() {
+            return;
+        }
+        // Step 3. The browsing contexts of another origin are only script-closable
+        // when they were opened by script.
+        if !self.window_proxy.is_auxiliary() {
+            return;
+        }
+        // Step 3.1.
+        self.window_proxy.close();
+        // Step 3.2, the browsing context is closed by the constellation.
+        let msg = ScriptMsg::CloseAuxiliaryBrowsingContext(self.window_proxy.browsing_context_id());
+        let _ = self
+            .upcast::<GlobalScope>()
+            .script_to_constellation_chan()
+            .send(msg);
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-window-closed
     fn Closed(&self) -> bool {
-        // TODO: Implement x-origin close
-        false
+        self.window_proxy.is_browsing_context_discarded() || self.window_proxy.is_closing()
     }
 
     /// https://html.spec.whatwg.org/multipage/#dom-window-postmessage
@@ -175,12 +192,22 @@ impl DissimilarOriginWindowMethods for DissimilarOriginWindow {
 
     // https://html.spec.whatwg.org/multipage/#dom-window-blur
     fn Blur(&self) {
-        // TODO: Implement x-origin blur
+        // User agents are encouraged to ignore calls to this method.
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-focus
     fn Focus(&self) {
-        // TODO: Implement x-origin focus
+        // Step 2.
+        if self.window_proxy.is_browsing_context_discarded() {
+            return;
+        }
+        // Step 3, the focusing steps run in the constellation, which focuses the
+        // iframes the browsing context is nested in.
+        let msg = ScriptMsg::FocusBrowsingContext(self.window_proxy.browsing_context_id());
+        let _ = self
+            .upcast::<GlobalScope>()
+            .script_to_constellation_chan()
+            .send(msg);
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-location
@@ -2,6 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::clipboard_provider::ClipboardProvider;
+use crate::devtools;
 use crate::document_loader::{DocumentLoader, LoadType};
 use crate::dom::attr::Attr;
 use crate::dom::beforeunloadevent::BeforeUnloadEvent;
@@ -14,16 +16,21 @@ use crate::dom::bindings::codegen::Bindings::DocumentBinding::{
 };
 use crate::dom::bindings::codegen::Bindings::EventBinding::EventBinding::EventMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLIFrameElementBinding::HTMLIFrameElementBinding::HTMLIFrameElementMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLImageElementBinding::HTMLImageElementMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLInputElementBinding::HTMLInputElementMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLTextAreaElementBinding::HTMLTextAreaElementMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use crate::dom::bindings::codegen::Bindings::NodeFilterBinding::NodeFilter;
 use crate::dom::bindings::codegen::Bindings::PerformanceBinding::PerformanceMethods;
+use crate::dom::bindings::codegen::Bindings::SelectionBinding::SelectionMethods;
 use crate::dom::bindings::codegen::Bindings::ShadowRootBinding::ShadowRootMethods;
 use crate::dom::bindings::codegen::Bindings::TouchBinding::TouchMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::{
     FrameRequestCallback, ScrollBehavior, WindowMethods,
 };
+use crate::dom::bindings::codegen::Bindings::XPathNSResolverBinding::XPathNSResolver;
 use crate::dom::bindings::codegen::UnionTypes::{NodeOrString, StringOrElementCreationOptions};
-use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::error::{Error, ErrorInfo, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::{Castable, ElementTypeId, HTMLElementTypeId, NodeTypeId};
 use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
@@ -34,12 +41,15 @@ use crate::dom::bindings::xmlname::XMLName::InvalidXMLName;
 use crate::dom::bindings::xmlname::{
     namespace_from_domstring, validate_and_extract, xml_name_type,
 };
+use crate::dom::caretposition::CaretPosition;
 use crate::dom::cdatasection::CDATASection;
+use crate::dom::clipboardevent::{ClipboardAction, ClipboardEvent};
 use crate::dom::comment::Comment;
 use crate::dom::compositionevent::CompositionEvent;
 use crate::dom::cssstylesheet::CSSStyleSheet;
 use crate::dom::customelementregistry::CustomElementDefinition;
 use crate::dom::customevent::CustomEvent;
+use crate::dom::datatransfer::{DataStoreMode, DataTransfer};
 use crate::dom::documentfragment::DocumentFragment;
 use crate::dom::documentorshadowroot::{DocumentOrShadowRoot, StyleSheetInDocument};
 use crate::dom::documenttype::DocumentType;
@@ -65,8 +75,11 @@ use crate::dom::htmlheadelement::HTMLHeadElement;
 use crate::dom::htmlhtmlelement::HTMLHtmlElement;
 use crate::dom::htmliframeelement::HTMLIFrameElement;
 use crate::dom::htmlimageelement::HTMLImageElement;
+use crate::dom::htmlinputelement::{HTMLInputElement, InputType};
 use crate::dom::htmlscriptelement::{HTMLScriptElement, ScriptResult};
+use crate::dom::htmltextareaelement::HTMLTextAreaElement;
 use crate::dom::htmltitleelement::HTMLTitleElement;
+use crate::dom::htmlvideoelement::HTMLVideoElement;
 use crate::dom::keyboardevent::KeyboardEvent;
 use crate::dom::location::Location;
 use crate::dom::messageevent::MessageEvent;
@@ -79,6 +92,7 @@ use crate::dom::pagetransitionevent::PageTransitionEvent;
 use crate::dom::processinginstruction::ProcessingInstruction;
 use crate::dom::promise::Promise;
 use crate::dom::range::Range;
+use crate::dom::resizeobserver::ResizeObserver;
 use crate::dom::selection::Selection;
 use crate::dom::servoparser::ServoParser;
 use crate::dom::shadowroot::ShadowRoot;
@@ -95,11 +109,15 @@ use crate::dom::webglrenderingcontext::WebGLRenderingContext;
 use crate::dom::wheelevent::WheelEvent;
 use crate::dom::window::{ReflowReason, Window};
 use crate::dom::windowproxy::WindowProxy;
+use crate::dom::xpathevaluator;
+use crate::dom::xpathexpression::XPathExpression;
+use crate::dom::xpathresult::XPathResult;
 use crate::fetch::FetchCanceller;
 use crate::realms::{AlreadyInRealm, InRealm};
 use crate::script_runtime::JSContext;
 use crate::script_runtime::{CommonScriptMsg, ScriptThreadEventCategory};
 use crate::script_thread::{MainThreadScriptMsg, ScriptThread};
+use crate::security_manager::ReportingEndpoints;
 use crate::stylesheet_set::StylesheetSetRef;
 use crate::task::TaskBox;
 use crate::task_source::{TaskSource, TaskSourceName};
@@ -109,13 +127,16 @@ use content_security_policy::{self as csp, CspList};
 use cookie::Cookie;
 use devtools_traits::ScriptToDevtoolsControlMsg;
 use dom_struct::dom_struct;
-use embedder_traits::EmbedderMsg;
+use embedder_traits::{ContextMenuInfo, EmbedderMsg, FormControlState, PromptRequest};
+use embedder_traits::{PromptResponse, SavedDocumentState};
 use encoding_rs::{Encoding, UTF_8};
-use euclid::default::Point2D;
+use euclid::default::{Point2D, Rect, Size2D};
 use html5ever::{LocalName, Namespace, QualName};
 use hyper_serde::Serde;
-use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender, TryRecvError};
+use ipc_channel::router::ROUTER;
 use js::jsapi::{JSObject, JSRuntime};
+use js::rust::HandleValue;
 use keyboard_types::{Code, Key, KeyState};
 use metrics::{
     InteractiveFlag, InteractiveMetrics, InteractiveWindow, ProfilerMetadataFactory,
@@ -123,12 +144,12 @@ use metrics::{
 };
 use mime::{self, Mime};
 use msg::constellation_msg::BrowsingContextId;
-use net_traits::pub_domains::is_pub_domain;
-use net_traits::request::RequestBuilder;
+use net_traits::pub_domains::{is_pub_domain, is_same_site};
+use net_traits::request::{Initiator, RequestBuilder};
 use net_traits::response::HttpsState;
 use net_traits::CookieSource::NonHTTP;
-use net_traits::CoreResourceMsg::{GetCookiesForUrl, SetCookiesForUrl};
-use net_traits::{FetchResponseMsg, IpcSend, ReferrerPolicy};
+use net_traits::CoreResourceMsg::{ObserveCookiesForUrl, SetCookiesForUrl};
+use net_traits::{CoreResourceMsg, FetchChannels, FetchResponseMsg, IpcSend, ReferrerPolicy};
 use num_traits::ToPrimitive;
 use percent_encoding::percent_decode;
 use profile_traits::ipc as profile_ipc;
@@ -153,9 +174,12 @@ use std::default::Default;
 use std::mem;
 use std::ptr::NonNull;
 use std::rc::Rc;
-use std::time::{Duration, Instant};
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc as StdArc;
+use std::time::{Duration, Instant, SystemTime};
 use style::attr::AttrValue;
 use style::context::QuirksMode;
+use style::element_state::ElementState;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::media_queries::{Device, MediaType};
 use style::selector_parser::{RestyleDamage, Snapshot};
@@ -165,6 +189,7 @@ use style::stylesheet_set::DocumentStylesheetSet;
 use style::stylesheets::{Origin, OriginSet, Stylesheet};
 use url::Host;
 use uuid::Uuid;
+use webrender_api::units::DeviceIntPoint;
 
 /// The number of times we are allowed to see spurious `requestAnimationFrame()` calls before
 /// falling back to fake ones.
@@ -175,6 +200,11 @@ const SPURIOUS_ANIMATION_FRAME_THRESHOLD: u8 = 5;
 /// The amount of time between fake `requestAnimationFrame()`s.
 const FAKE_REQUEST_ANIMATION_FRAME_DELAY: u64 = 16;
 
+/// The size of the bodies of the keepalive requests which a document can have in flight.
+///
+/// <https://fetch.spec.whatwg.org/#http-network-or-cache-fetch>
+const KEEPALIVE_BODIES_QUOTA: usize = 64 * 1024;
+
 pub enum TouchEventResult {
     Processed(bool),
     Forwarded,
@@ -294,6 +324,8 @@ pub struct Document {
     /// Tracking this is not necessary for correctness. Instead, it is an optimization to avoid
     /// sending needless `ChangeRunningAnimationsState` messages to the compositor.
     running_animation_callbacks: Cell<bool>,
+    /// <https://drafts.csswg.org/resize-observer/#dom-document-resizeobservers-slot>
+    resize_observers: DomRefCell<Vec<Dom<ResizeObserver>>>,
     /// Tracks all outstanding loads related to this document.
     loader: DomRefCell<DocumentLoader>,
     /// The current active HTML parser, to allow resuming after interruptions.
@@ -355,6 +387,8 @@ pub struct Document {
     dom_count: Cell<u32>,
     /// Entry node for fullscreen.
     fullscreen_element: MutNullableDom<Element>,
+    /// <https://w3c.github.io/picture-in-picture/#dom-documentorshadowroot-pictureinpictureelement>
+    picture_in_picture_element: MutNullableDom<Element>,
     /// Map from ID to set of form control elements that have that ID as
     /// their 'form' content attribute. Used to reset form controls
     /// whenever any element with the same ID as the form attribute
@@ -371,6 +405,17 @@ pub struct Document {
     page_showing: Cell<bool>,
     /// Whether the document is salvageable.
     salvageable: Cell<bool>,
+    /// <https://wicg.github.io/page-lifecycle/#frozenness>
+    frozen: Cell<bool>,
+    /// <https://wicg.github.io/page-lifecycle/#dom-document-wasdiscarded>
+    was_discarded: Cell<bool>,
+    /// <https://html.spec.whatwg.org/multipage/#sandboxed-modals-flag>
+    sandboxed_modals: Cell<bool>,
+    /// Whether an element of this document has a `dir` attribute or is a `bdi` element,
+    /// without which all of them are left-to-right.
+    has_directionality_overrides: Cell<bool>,
+    /// Whether the states with which elements match `:dir()` must be updated.
+    directionality_dirty: Cell<bool>,
     /// Whether the document was aborted with an active parser
     active_parser_was_aborted: Cell<bool>,
     /// Whether the unload event has already been fired.
@@ -401,8 +446,24 @@ pub struct Document {
     /// https://html.spec.whatwg.org/multipage/#concept-document-csp-list
     #[ignore_malloc_size_of = "Defined in rust-content-security-policy"]
     csp_list: DomRefCell<Option<CspList>>,
+    /// The endpoint groups of the `Report-To` header of the document, for the reports of
+    /// the violations of its policies.
+    reporting_endpoints: DomRefCell<ReportingEndpoints>,
     /// https://w3c.github.io/slection-api/#dfn-selection
     selection: MutNullableDom<Selection>,
+    /// The state to restore once the document has loaded, when it is part of a restored session.
+    #[ignore_malloc_size_of = "Defined in embedder_traits"]
+    state_to_restore: DomRefCell<Option<SavedDocumentState>>,
+    /// The cookies of the document, which are read from the resource threads again once
+    /// they change.
+    #[ignore_malloc_size_of = "Defined in ipc-channel"]
+    cookie_cache: DomRefCell<Option<CookieCache>>,
+    /// The URLs which were prefetched, so that each of them is only fetched once.
+    prefetched_urls: DomRefCell<HashSet<ServoUrl>>,
+    /// The size of the bodies of the keepalive requests in flight, which are counted
+    /// down by the router thread once they are done.
+    #[ignore_malloc_size_of = "Arc"]
+    inflight_keepalive_bytes: StdArc<AtomicUsize>,
 }
 
 #[derive(JSTraceable, MallocSizeOf)]
@@ -501,6 +562,26 @@ impl Document {
         self.https_state.set(https_state);
     }
 
+    /// Whether the document is a secure context: it was delivered securely, as the
+    /// responses of https and of the secure schemes of the embedder are, or its URL is
+    /// potentially trustworthy. The ancestors of the document are not checked.
+    /// <https://w3c.github.io/webappsec-secure-contexts/#is-origin-trustworthy>
+    pub fn is_secure_context(&self) -> bool {
+        if let HttpsState::Modern = self.https_state.get() {
+            return true;
+        }
+        let url = self.url();
+        if url.is_secure_scheme() || url.scheme() == "file" {
+            return true;
+        }
+        match url.host() {
+            Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+            Some(Host::Ipv4(address)) => address.is_loopback(),
+            Some(Host::Ipv6(address)) => address.is_loopback(),
+            None => false,
+        }
+    }
+
     pub fn is_fully_active(&self) -> bool {
         self.activity.get() == DocumentActivity::FullyActive
     }
@@ -524,6 +605,10 @@ impl Document {
             ClientContextId::build(pipeline_id.namespace_id.0, pipeline_id.index.0.get());
 
         if activity != DocumentActivity::FullyActive {
+            // The documents which are kept in the session history are frozen.
+            if self.salvageable.get() {
+                self.freeze();
+            }
             self.window().suspend();
             media.suspend(&client_context_id);
             return;
@@ -535,6 +620,7 @@ impl Document {
             .reflow(ReflowGoal::Full, ReflowReason::CachedPageNeededReflow);
         self.window().resume();
         media.resume(&client_context_id);
+        self.resume();
 
         if self.ready_state.get() != DocumentReadyState::Complete {
             return;
@@ -576,10 +662,162 @@ impl Document {
             .unwrap();
     }
 
+    /// Freeze or resume a fully active document, such as the ones of a browser which is
+    /// left in the background by the embedder.
+    /// <https://wicg.github.io/page-lifecycle/#change-frozenness-of-a-top-level-document>
+    pub fn set_frozen(&self, frozen: bool) {
+        // The other documents are frozen already, until they are fully active again.
+        if !self.is_fully_active() || frozen == self.frozen.get() {
+            return;
+        }
+        let media = ServoMedia::get().unwrap();
+        let pipeline_id = self.window().pipeline_id();
+        let client_context_id =
+            ClientContextId::build(pipeline_id.namespace_id.0, pipeline_id.index.0.get());
+        let global = self.window.upcast::<GlobalScope>();
+        if frozen {
+            self.freeze();
+            global.suspend();
+            media.suspend(&client_context_id);
+        } else {
+            global.resume();
+            media.resume(&client_context_id);
+            self.resume();
+        }
+    }
+
+    /// <https://wicg.github.io/page-lifecycle/#freeze-steps>
+    fn freeze(&self) {
+        if self.frozen.get() {
+            return;
+        }
+        self.fire_lifecycle_event(atom!("freeze"));
+        self.frozen.set(true);
+    }
+
+    /// <https://wicg.github.io/page-lifecycle/#resume-steps>
+    fn resume(&self) {
+        if !self.frozen.get() {
+            return;
+        }
+        self.frozen.set(false);
+        self.fire_lifecycle_event(atom!("resume"));
+    }
+
+    fn fire_lifecycle_event(&self, name: Atom) {
+        let event = Event::new(
+            &self.window.upcast(),
+            name,
+            EventBubbles::DoesNotBubble,
+            EventCancelable::NotCancelable,
+        );
+        event.fire(self.upcast());
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.get()
+    }
+
+    /// Record that the previous document of the browsing context was discarded by the
+    /// embedder, to save resources, rather than navigated away from.
+    pub fn set_was_discarded(&self, was_discarded: bool) {
+        self.was_discarded.set(was_discarded);
+    }
+
+    /// Set the sandboxed modals flag, for a document in an iframe sandboxed without
+    /// `allow-modals`.
+    pub fn set_sandboxed_modals(&self, sandboxed_modals: bool) {
+        self.sandboxed_modals.set(sandboxed_modals);
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#sandboxed-modals-flag>
+    pub fn is_sandboxed_from_modals(&self) -> bool {
+        self.sandboxed_modals.get()
+    }
+
+    /// Note that an element has a `dir` attribute or is a `bdi` element, so that the
+    /// directionality of the elements must be computed.
+    pub fn add_directionality_override(&self) {
+        self.has_directionality_overrides.set(true);
+        self.directionality_dirty.set(true);
+    }
+
+    /// Note that the directionality of some elements may have changed, such as when nodes
+    /// are inserted or text is changed.
+    pub fn directionality_may_have_changed(&self) {
+        if self.has_directionality_overrides.get() {
+            self.directionality_dirty.set(true);
+        }
+    }
+
+    /// Update the states with which elements match `:dir()`, before they are matched
+    /// against selectors.
+    /// <https://html.spec.whatwg.org/multipage/#selector-ltr>
+    pub fn flush_directionality(&self) {
+        if !self.directionality_dirty.replace(false) {
+            return;
+        }
+        let root = match self.GetDocumentElement() {
+            Some(root) => root,
+            None => return,
+        };
+        let mut stack = vec![(root, false)];
+        while let Some((element, parent_is_rtl)) = stack.pop() {
+            let is_rtl = element
+                .downcast::<HTMLElement>()
+                .and_then(|element| element.directionality())
+                .map_or(parent_is_rtl, |directionality| directionality == "rtl");
+            element.set_state(ElementState::IN_RTL_STATE, is_rtl);
+            element.set_state(ElementState::IN_LTR_STATE, !is_rtl);
+            let shadow_root = element.shadow_root();
+            let children = element.upcast::<Node>().children().chain(
+                shadow_root
+                    .iter()
+                    .flat_map(|root| root.upcast::<Node>().children()),
+            );
+            stack.extend(
+                children
+                    .filter_map(DomRoot::downcast::<Element>)
+                    .map(|child| (child, is_rtl)),
+            );
+        }
+    }
+
     pub fn origin(&self) -> &MutableOrigin {
         &self.origin
     }
 
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-05#section-5.2.1>
+    pub fn site_for_cookies(&self) -> ImmutableOrigin {
+        let browsing_context = match self.browsing_context() {
+            Some(browsing_context) => browsing_context,
+            None => return self.origin.immutable().clone(),
+        };
+
+        // Step 1-2. The documents of other event loops, which are only shared within a site,
+        // are cross-site.
+        let top_origin = match browsing_context.top().document() {
+            Some(top_document) => top_document.origin().immutable().clone(),
+            None => return ImmutableOrigin::new_opaque(),
+        };
+
+        // Step 3-4.
+        if !is_same_site(self.origin.immutable(), &top_origin) {
+            return ImmutableOrigin::new_opaque();
+        }
+        let mut ancestor = browsing_context.parent();
+        while let Some(context) = ancestor {
+            match context.document() {
+                Some(document) if is_same_site(document.origin().immutable(), &top_origin) => {},
+                _ => return ImmutableOrigin::new_opaque(),
+            }
+            ancestor = context.parent();
+        }
+
+        // Step 5.
+        top_origin
+    }
+
     // https://dom.spec.whatwg.org/#concept-document-url
     pub fn url(&self) -> ServoUrl {
         self.url.borrow().clone()
@@ -840,7 +1078,6 @@ impl Document {
         if let Some((x, y)) = point {
             // Step 3
             let global_scope = self.window.upcast::<GlobalScope>();
-            self.window.update_viewport_for_scroll(x, y);
             self.window.perform_a_scroll(
                 x,
                 y,
@@ -881,7 +1118,11 @@ impl Document {
 
     /// Return whether scripting is enabled or not
     pub fn is_scripting_enabled(&self) -> bool {
-        self.scripting_enabled
+        self.scripting_enabled &&
+            self.window
+                .pref("dom.scripting.enabled")
+                .as_bool()
+                .unwrap_or(true)
     }
 
     /// Return the element that currently has focus.
@@ -973,7 +1214,7 @@ impl Document {
     pub fn handle_mouse_event(
         &self,
         js_runtime: *mut JSRuntime,
-        _button: MouseButton,
+        button: MouseButton,
         client_point: Point2D<f32>,
         mouse_event_type: MouseEventType,
         node_address: Option<UntrustedNodeAddress>,
@@ -1000,6 +1241,20 @@ impl Document {
 
         let node = el.upcast::<Node>();
         debug!("{} on {:?}", mouse_event_type_string, node.debug_str());
+
+        // While the devtools node picker is active, clicks pick elements instead of
+        // reaching the page.
+        if self.window.devtools_node_picking() {
+            if let MouseEventType::Click = mouse_event_type {
+                devtools::handle_picker_click(&self.window, &el);
+            }
+            return;
+        }
+
+        // https://html.spec.whatwg.org/multipage/#activation-triggering-input-event
+        if let MouseEventType::MouseDown = mouse_event_type {
+            self.window.notify_user_activation();
+        }
         // Prevent click event if form control element is disabled.
         if let MouseEventType::Click = mouse_event_type {
             if el.click_event_filter_by_disabled_state() {
@@ -1060,6 +1315,10 @@ impl Document {
 
                 let target = node.upcast();
                 event.fire(target);
+
+                if let MouseButton::Right = button {
+                    self.maybe_show_context_menu(client_point, &el, pressed_mouse_buttons);
+                }
             },
         }
 
@@ -1072,6 +1331,164 @@ impl Document {
             .reflow(ReflowGoal::Full, ReflowReason::MouseEvent);
     }
 
+    /// Fire a `contextmenu` event at `target` and, unless content cancels it,
+    /// ask the embedder to show a context menu describing what was hit.
+    ///
+    /// <https://w3c.github.io/uievents/#event-type-contextmenu>
+    fn maybe_show_context_menu(
+        &self,
+        client_point: Point2D<f32>,
+        target: &Element,
+        pressed_mouse_buttons: u16,
+    ) {
+        let client_x = client_point.x as i32;
+        let client_y = client_point.y as i32;
+        let event = MouseEvent::new(
+            &self.window,
+            DOMString::from("contextmenu"),
+            EventBubbles::Bubbles,
+            EventCancelable::Cancelable,
+            Some(&self.window),
+            0i32,
+            client_x,
+            client_y,
+            client_x,
+            client_y,
+            false,
+            false,
+            false,
+            false,
+            2i16,
+            pressed_mouse_buttons,
+            None,
+            None,
+        );
+        let event = event.upcast::<Event>();
+        event.set_trusted(true);
+        if let EventStatus::Canceled = event.fire(target.upcast()) {
+            return;
+        }
+
+        // The links of an image map are the areas of the image, which have no box of
+        // their own to be hit.
+        let image = target.downcast::<HTMLImageElement>();
+        let area = image.and_then(|image| image.area_at(&client_point));
+        let link_url = match area {
+            Some(area) => self.resolve_url_attribute(area.upcast(), &local_name!("href")),
+            None => target
+                .upcast::<Node>()
+                .inclusive_ancestors(ShadowIncluding::No)
+                .filter_map(DomRoot::downcast::<HTMLAnchorElement>)
+                .next()
+                .and_then(|anchor| {
+                    self.resolve_url_attribute(anchor.upcast(), &local_name!("href"))
+                }),
+        };
+        let image_url = image
+            .map(|image| image.CurrentSrc())
+            .and_then(|src| ServoUrl::parse(&src).ok());
+        let selected_text = self
+            .GetSelection()
+            .map(|selection| String::from(selection.Stringifier()))
+            .filter(|text| !text.is_empty());
+
+        let point = (client_point * self.window.device_pixel_ratio().get()).to_i32();
+        let info = ContextMenuInfo {
+            point: DeviceIntPoint::new(point.x, point.y),
+            page_url: self.url(),
+            link_url,
+            image_url,
+            selected_text,
+            is_editable: target.read_write_state(),
+        };
+        self.send_to_embedder(EmbedderMsg::ShowContextMenu(info));
+    }
+
+    /// The form controls whose state is saved along with the session, in tree order.
+    fn session_form_controls(&self) -> impl Iterator<Item = DomRoot<Node>> {
+        self.upcast::<Node>()
+            .traverse_preorder(ShadowIncluding::No)
+            .filter(|node| match node.downcast::<HTMLInputElement>() {
+                Some(input) => match input.input_type() {
+                    InputType::File | InputType::Password | InputType::Hidden => false,
+                    _ => true,
+                },
+                None => node.is::<HTMLTextAreaElement>(),
+            })
+    }
+
+    /// Collect the scroll offset and the form control values of this document,
+    /// so that they can be restored when the session is restored.
+    pub fn saved_state(&self) -> SavedDocumentState {
+        let form_controls = self
+            .session_form_controls()
+            .map(|node| match node.downcast::<HTMLInputElement>() {
+                Some(input) => match input.input_type() {
+                    InputType::Checkbox | InputType::Radio => {
+                        FormControlState::Checked(input.Checked())
+                    },
+                    _ => FormControlState::Value(input.Value().into()),
+                },
+                None => FormControlState::Value(
+                    node.downcast::<HTMLTextAreaElement>()
+                        .unwrap()
+                        .Value()
+                        .into(),
+                ),
+            })
+            .collect();
+        SavedDocumentState {
+            scroll_x: self.window.ScrollX() as f64,
+            scroll_y: self.window.ScrollY() as f64,
+            form_controls,
+        }
+    }
+
+    /// Set the state that will be restored once this document has loaded.
+    pub fn set_state_to_restore(&self, state: SavedDocumentState) {
+        *self.state_to_restore.borrow_mut() = Some(state);
+    }
+
+    /// Restore the state set with `set_state_to_restore`, if any. Form controls
+    /// that do not match the saved state anymore are left untouched.
+    /// Returns whether some state was restored.
+    fn restore_saved_state(&self) -> bool {
+        let state = match self.state_to_restore.borrow_mut().take() {
+            Some(state) => state,
+            None => return false,
+        };
+        for (node, control_state) in self
+            .session_form_controls()
+            .zip(state.form_controls.into_iter())
+        {
+            match (node.downcast::<HTMLInputElement>(), control_state) {
+                (Some(input), FormControlState::Checked(checked)) => match input.input_type() {
+                    InputType::Checkbox | InputType::Radio => input.SetChecked(checked),
+                    _ => {},
+                },
+                (Some(input), FormControlState::Value(value)) => {
+                    let _ = input.SetValue(DOMString::from(value));
+                },
+                (None, FormControlState::Value(value)) => {
+                    if let Some(textarea) = node.downcast::<HTMLTextAreaElement>() {
+                        textarea.SetValue(DOMString::from(value));
+                    }
+                },
+                (None, FormControlState::Checked(_)) => {},
+            }
+        }
+        self.window
+            .scroll(state.scroll_x, state.scroll_y, ScrollBehavior::Auto);
+        true
+    }
+
+    /// Resolve the value of a URL-valued attribute of `element` against the document URL.
+    fn resolve_url_attribute(&self, element: &Element, name: &LocalName) -> Option<ServoUrl> {
+        element
+            .get_attribute(&ns!(), name)
+            .and_then(|attr| self.url().join(&attr.value()).ok())
+    }
+
     fn maybe_fire_dblclick(
         &self,
         click_pos: Point2D<f32>,
@@ -1199,6 +1616,14 @@ impl Document {
             None => return,
         };
 
+        if self.window.devtools_node_picking() {
+            if maybe_new_target != prev_mouse_over_target.get() {
+                prev_mouse_over_target.set(maybe_new_target.as_deref());
+                devtools::handle_picker_hover(&self.window, new_target);
+            }
+            return;
+        }
+
         self.fire_mouse_event(
             client_point,
             new_target.upcast(),
@@ -1325,6 +1750,48 @@ impl Document {
         event.fire(target);
     }
 
+    /// Find the block containing the node that was double tapped, and ask the compositor
+    /// to zoom on it.
+    #[allow(unsafe_code)]
+    pub fn handle_double_tap_event(
+        &self,
+        js_runtime: *mut JSRuntime,
+        point: Point2D<f32>,
+        node_address: Option<UntrustedNodeAddress>,
+    ) {
+        let node = match node_address {
+            Some(address) => unsafe { node::from_untrusted_node_address(js_runtime, address) },
+            None => return,
+        };
+        let block = node
+            .inclusive_ancestors(ShadowIncluding::No)
+            .filter_map(DomRoot::downcast::<Element>)
+            .find(|element| {
+                element.style().map_or(false, |style| {
+                    let display = style.get_box().clone_display();
+                    !display.is_none() && !display.is_inline_flow()
+                })
+            });
+        let block = match block {
+            Some(block) => block,
+            None => return,
+        };
+
+        let rect = block.upcast::<Node>().bounding_content_box_or_zero();
+        let rect = Rect::new(
+            Point2D::new(rect.origin.x.to_f32_px(), rect.origin.y.to_f32_px()),
+            Size2D::new(rect.size.width.to_f32_px(), rect.size.height.to_f32_px()),
+        );
+        let point = Point2D::new(
+            point.x + self.window.PageXOffset() as f32,
+            point.y + self.window.PageYOffset() as f32,
+        );
+        self.window.send_to_constellation(ScriptMsg::ZoomToRect(
+            euclid::Rect::from_untyped(&rect),
+            euclid::Point2D::from_untyped(point),
+        ));
+    }
+
     #[allow(unsafe_code)]
     pub fn handle_touch_event(
         &self,
@@ -1425,6 +1892,12 @@ impl Document {
             false,
         );
         let event = event.upcast::<Event>();
+
+        // https://html.spec.whatwg.org/multipage/#activation-triggering-input-event
+        if let TouchEventType::Up = event_type {
+            window.notify_user_activation();
+        }
+
         let result = event.fire(&target);
 
         window.reflow(ReflowGoal::Full, ReflowReason::MouseEvent);
@@ -1463,9 +1936,25 @@ impl Document {
             keyboard_event.key.legacy_keycode(),
         );
         let event = keyevent.upcast::<Event>();
+
+        // https://html.spec.whatwg.org/multipage/#activation-triggering-input-event
+        if keyboard_event.state == KeyState::Down && keyboard_event.key != Key::Escape {
+            self.window.notify_user_activation();
+        }
+
         event.fire(target);
         let mut cancel_state = event.get_cancel_state();
 
+        // The clipboard shortcuts are not handled by the text controls, but fire
+        // clipboard events whose default actions edit them.
+        if keyboard_event.state == KeyState::Down && cancel_state == EventDefault::Allowed {
+            let action =
+                ClipboardAction::from_shortcut(&keyboard_event.key, keyboard_event.modifiers);
+            if let Some(action) = action {
+                self.perform_clipboard_action(action);
+            }
+        }
+
         // https://w3c.github.io/uievents/#keys-cancelable-keys
         if keyboard_event.state == KeyState::Down &&
             is_character_value_key(&(keyboard_event.key)) &&
@@ -1516,6 +2005,68 @@ impl Document {
         self.window.reflow(ReflowGoal::Full, ReflowReason::KeyEvent);
     }
 
+    /// Fire the clipboard event of a clipboard action, then copy the data set by the
+    /// page if the event was canceled, or perform the action on the selection of the
+    /// document otherwise. The text controls perform the actions on their own
+    /// selection when they are the target of the event.
+    ///
+    /// <https://w3c.github.io/clipboard-apis/#clipboard-actions>
+    pub fn perform_clipboard_action(&self, action: ClipboardAction) {
+        let target = match self.get_focused_element() {
+            Some(focused) => focused,
+            None => match self.GetBody().or_else(|| self.GetDocumentElement()) {
+                Some(element) => DomRoot::upcast(element),
+                None => return,
+            },
+        };
+
+        // https://w3c.github.io/clipboard-apis/#fire-a-clipboard-event
+        let mut chan = self
+            .window
+            .upcast::<GlobalScope>()
+            .script_to_constellation_chan()
+            .clone();
+        let mode = match action {
+            ClipboardAction::Paste => DataStoreMode::ReadOnly,
+            ClipboardAction::Copy | ClipboardAction::Cut => DataStoreMode::ReadWrite,
+        };
+        let clipboard_data = DataTransfer::new(&self.window, mode);
+        if action == ClipboardAction::Paste {
+            clipboard_data.add_item("text/plain", DOMString::from(chan.clipboard_contents()));
+        }
+        let event = ClipboardEvent::new(
+            &self.window,
+            action.event_type(),
+            EventBubbles::Bubbles,
+            EventCancelable::Cancelable,
+            Some(&clipboard_data),
+        );
+        let event = event.upcast::<Event>();
+        event.set_trusted(true);
+        event.fire(target.upcast());
+        // Scripts which kept the data can no longer read or change it.
+        clipboard_data.set_mode(DataStoreMode::Protected);
+
+        match (action, event.get_cancel_state()) {
+            (ClipboardAction::Copy, EventDefault::Prevented) |
+            (ClipboardAction::Cut, EventDefault::Prevented) => {
+                if let Some(text) = clipboard_data.data("text/plain") {
+                    chan.set_clipboard_contents(text.into());
+                }
+            },
+            // Only the selection of a text control can be cut or pasted into.
+            (ClipboardAction::Copy, EventDefault::Allowed) => {
+                let text = self
+                    .GetSelection()
+                    .map_or_else(DOMString::new, |selection| selection.Stringifier());
+                if !text.is_empty() {
+                    chan.set_clipboard_contents(text.into());
+                }
+            },
+            _ => {},
+        }
+    }
+
     pub fn dispatch_composition_event(
         &self,
         composition_event: ::keyboard_types::CompositionEvent,
@@ -1627,6 +2178,10 @@ impl Document {
         }
     }
 
+    pub fn has_animation_frame_callbacks(&self) -> bool {
+        !self.animation_frame_list.borrow().is_empty()
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#dom-window-requestanimationframe>
     pub fn request_animation_frame(&self, callback: AnimationFrameCallback) -> u32 {
         let ident = self.animation_frame_ident.get() + 1;
@@ -1670,6 +2225,77 @@ impl Document {
         }
     }
 
+    pub fn add_resize_observer(&self, observer: &ResizeObserver) {
+        self.resize_observers
+            .borrow_mut()
+            .push(Dom::from_ref(observer));
+    }
+
+    fn gather_active_resize_observations_at_depth(&self, depth: usize) -> bool {
+        let observers: Vec<DomRoot<ResizeObserver>> = self
+            .resize_observers
+            .borrow()
+            .iter()
+            .map(|observer| DomRoot::from_ref(&**observer))
+            .collect();
+        let mut has_active_observations = false;
+        for observer in observers {
+            observer.gather_active_observations_at_depth(depth);
+            has_active_observations |= observer.has_active_observations();
+        }
+        has_active_observations
+    }
+
+    /// <https://drafts.csswg.org/resize-observer/#html-event-loop>
+    pub fn update_resize_observations(&self) {
+        if self.resize_observers.borrow().is_empty() {
+            return;
+        }
+        let window = self.window();
+
+        // Step 1.
+        let mut depth = 0;
+        let mut has_active_observations = self.gather_active_resize_observations_at_depth(depth);
+
+        // Step 2.
+        while has_active_observations {
+            // Step 2.1.
+            let observers: Vec<DomRoot<ResizeObserver>> = self
+                .resize_observers
+                .borrow()
+                .iter()
+                .map(|observer| DomRoot::from_ref(&**observer))
+                .collect();
+            depth = observers
+                .iter()
+                .filter_map(|observer| observer.broadcast_active_observations(&window))
+                .min()
+                .unwrap_or(usize::max_value());
+            // Step 2.2 (the layout is recomputed by the box size queries).
+            has_active_observations = self.gather_active_resize_observations_at_depth(depth);
+        }
+
+        // Step 3.
+        let has_skipped_observations = self
+            .resize_observers
+            .borrow()
+            .iter()
+            .any(|observer| observer.has_skipped_observations());
+        if has_skipped_observations {
+            // https://drafts.csswg.org/resize-observer/#deliver-resize-loop-error-notification
+            let error_info = ErrorInfo {
+                message: "ResizeObserver loop completed with undelivered notifications."
+                    .to_owned(),
+                filename: String::new(),
+                lineno: 0,
+                column: 0,
+            };
+            window
+                .upcast::<GlobalScope>()
+                .report_an_error(error_info, HandleValue::null());
+        }
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#run-the-animation-frame-callbacks>
     pub fn run_the_animation_frame_callbacks(&self) {
         rooted_vec!(let mut animation_frame_list);
@@ -1750,14 +2376,97 @@ impl Document {
     pub fn fetch_async(
         &self,
         load: LoadType,
-        mut request: RequestBuilder,
+        request: RequestBuilder,
         fetch_target: IpcSender<FetchResponseMsg>,
     ) {
-        request.csp_list = self.get_csp_list().map(|x| x.clone());
+        let request = self.prepare_request(request);
         let mut loader = self.loader.borrow_mut();
         loader.fetch_async(load, request, fetch_target);
     }
 
+    /// Fetch a resource for the document without delaying its load event.
+    pub fn fetch_background(
+        &self,
+        request: RequestBuilder,
+        fetch_target: IpcSender<FetchResponseMsg>,
+    ) {
+        let request = self.prepare_request(request);
+        let mut loader = self.loader.borrow_mut();
+        loader.fetch_async_background(request, fetch_target);
+    }
+
+    /// Fetch a resource into the HTTP cache, with the lowest priority, for a navigation or
+    /// a load which may follow.
+    /// <https://html.spec.whatwg.org/multipage/#link-type-prefetch>
+    pub fn prefetch(&self, request: RequestBuilder) {
+        if !self
+            .prefetched_urls
+            .borrow_mut()
+            .insert(request.url.clone())
+        {
+            return;
+        }
+        debug!("Prefetch {}", request.url);
+        let request = self.prepare_request(request.initiator(Initiator::Prefetch));
+        let _ = self
+            .window
+            .upcast::<GlobalScope>()
+            .resource_threads()
+            .send(CoreResourceMsg::Fetch(request, FetchChannels::Prefetch));
+    }
+
+    /// Fetch a request which outlives the document, unless its body doesn't fit in the
+    /// quota of the keepalive requests in flight, in which case it returns false. The
+    /// messages of the fetch are given to `listener`, which has to cope with the document
+    /// being gone.
+    /// <https://fetch.spec.whatwg.org/#http-network-or-cache-fetch> Step 5.8
+    pub fn fetch_keepalive<F>(&self, request: RequestBuilder, mut listener: F) -> bool
+    where
+        F: FnMut(FetchResponseMsg) + Send + 'static,
+    {
+        let length = request.body.as_ref().map_or(0, |body| body.len());
+        let inflight_bytes = self.inflight_keepalive_bytes.clone();
+        let previous = inflight_bytes.fetch_add(length, atomic::Ordering::SeqCst);
+        if previous + length > KEEPALIVE_BODIES_QUOTA {
+            inflight_bytes.fetch_sub(length, atomic::Ordering::SeqCst);
+            return false;
+        }
+
+        // The fetch is neither cancelled with the other loads of the document, nor does
+        // it go through its event loop, which may be gone by the time it is done.
+        let (action_sender, action_receiver) = ipc::channel().unwrap();
+        ROUTER.add_route(
+            action_receiver.to_opaque(),
+            Box::new(move |message| {
+                let message = match message.to() {
+                    Ok(message) => message,
+                    Err(_) => return,
+                };
+                if let FetchResponseMsg::ProcessResponseEOF(_) = message {
+                    inflight_bytes.fetch_sub(length, atomic::Ordering::SeqCst);
+                }
+                listener(message);
+            }),
+        );
+        let request = self.prepare_request(request.keep_alive(true));
+        let _ =
+            self.window
+                .upcast::<GlobalScope>()
+                .resource_threads()
+                .send(CoreResourceMsg::Fetch(
+                    request,
+                    FetchChannels::ResponseMsg(action_sender, None),
+                ));
+        true
+    }
+
+    fn prepare_request(&self, mut request: RequestBuilder) -> RequestBuilder {
+        request.csp_list = self.get_csp_list().map(|x| x.clone());
+        request.viewport = Some(self.window.window_size().client_viewport());
+        request.site_for_cookies = Some(self.site_for_cookies());
+        request
+    }
+
     // https://html.spec.whatwg.org/multipage/#the-end
     // https://html.spec.whatwg.org/multipage/#delay-the-load-event
     pub fn finish_load(&self, load: LoadType) {
@@ -1819,6 +2528,13 @@ impl Document {
 
     // https://html.spec.whatwg.org/multipage/#prompt-to-unload-a-document
     pub fn prompt_to_unload(&self, recursive_flag: bool) -> bool {
+        self.prompt_to_unload_with_state(recursive_flag, &Cell::new(false))
+    }
+
+    /// Prompt to unload this document and, unless `recursive_flag` is set, its descendants,
+    /// in tree order. `prompted` records whether the user was already asked, since they
+    /// are asked at most once for a whole tree of documents.
+    fn prompt_to_unload_with_state(&self, recursive_flag: bool, prompted: &Cell<bool>) -> bool {
         // TODO: Step 1, increase the event loop's termination nesting level by 1.
         // Step 2
         self.incr_ignore_opens_during_unload_counter();
@@ -1840,25 +2556,29 @@ impl Document {
             self.salvageable.set(false);
         }
         let mut can_unload = true;
-        // TODO: Step 8, also check sandboxing modals flag.
+        // Step 8
         let default_prevented = event.DefaultPrevented();
         let return_value_not_empty = !event
             .downcast::<BeforeUnloadEvent>()
             .unwrap()
             .ReturnValue()
             .is_empty();
-        if default_prevented || return_value_not_empty {
-            let (chan, port) = ipc::channel().expect("Failed to create IPC channel!");
-            let msg = EmbedderMsg::AllowUnload(chan);
-            self.send_to_embedder(msg);
-            can_unload = port.recv().unwrap();
+        // The documents without sticky activation can't ask for a confirmation, so that
+        // they can't trap the user.
+        if (default_prevented || return_value_not_empty) &&
+            !prompted.get() &&
+            !self.is_sandboxed_from_modals() &&
+            self.window.has_sticky_activation()
+        {
+            prompted.set(true);
+            // A suppressed confirmation lets the document unload.
+            let response = self.window.show_dialog(PromptRequest::BeforeUnload);
+            can_unload = response.map_or(true, |response| response == PromptResponse::Accept);
         }
         // Step 9
-        if !recursive_flag {
-            for iframe in self.iter_iframes() {
-                // TODO: handle the case of cross origin iframes.
-                let document = document_from_node(&*iframe);
-                can_unload = document.prompt_to_unload(true);
+        if !recursive_flag && can_unload {
+            for document in self.child_documents() {
+                can_unload = document.prompt_to_unload_with_state(false, prompted);
                 if !document.salvageable() {
                     self.salvageable.set(false);
                 }
@@ -1874,6 +2594,17 @@ impl Document {
 
     // https://html.spec.whatwg.org/multipage/#unload-a-document
     pub fn unload(&self, recursive_flag: bool) {
+        // The descendants are unloaded first, in tree order.
+        // https://html.spec.whatwg.org/multipage/#unload-a-document-and-its-descendants
+        if !recursive_flag {
+            for document in self.child_documents() {
+                document.unload(false);
+                if !document.salvageable() {
+                    self.salvageable.set(false);
+                }
+            }
+        }
+
         // TODO: Step 1, increase the event loop's termination nesting level by 1.
         // Step 2
         self.incr_ignore_opens_during_unload_counter();
@@ -1912,18 +2643,6 @@ impl Document {
         }
         // TODO: Step 8, decrease the event loop's termination nesting level by 1.
 
-        // Step 13
-        if !recursive_flag {
-            for iframe in self.iter_iframes() {
-                // TODO: handle the case of cross origin iframes.
-                let document = document_from_node(&*iframe);
-                document.unload(true);
-                if !document.salvageable() {
-                    self.salvageable.set(false);
-                }
-            }
-        }
-
         let global_scope = self.window.upcast::<GlobalScope>();
         // Step 10, 14
         // https://html.spec.whatwg.org/multipage/#unloading-document-cleanup-steps
@@ -2008,6 +2727,10 @@ impl Document {
 
                     window.reflow(ReflowGoal::Full, ReflowReason::DocumentLoaded);
 
+                    if document.restore_saved_state() {
+                        return;
+                    }
+
                     if let Some(fragment) = document.url().fragment() {
                         document.check_and_scroll_fragment(fragment);
                     }
@@ -2317,6 +3040,15 @@ impl Document {
             .filter_map(DomRoot::downcast::<HTMLIFrameElement>)
     }
 
+    /// The documents of the iframes of this document, in tree order.
+    // TODO: handle the documents of the iframes which run in another event loop.
+    fn child_documents(&self) -> Vec<DomRoot<Document>> {
+        self.iter_iframes()
+            .filter_map(|iframe| iframe.pipeline_id())
+            .filter_map(ScriptThread::find_document)
+            .collect()
+    }
+
     /// Find an iframe element in the document.
     pub fn find_iframe(
         &self,
@@ -2869,6 +3601,7 @@ impl Document {
             animation_frame_ident: Cell::new(0),
             animation_frame_list: DomRefCell::new(vec![]),
             running_animation_callbacks: Cell::new(false),
+            resize_observers: DomRefCell::new(vec![]),
             loader: DomRefCell::new(doc_loader),
             current_parser: Default::default(),
             reflow_timeout: Cell::new(None),
@@ -2898,6 +3631,7 @@ impl Document {
             spurious_animation_frames: Cell::new(0),
             dom_count: Cell::new(1),
             fullscreen_element: MutNullableDom::new(None),
+            picture_in_picture_element: MutNullableDom::new(None),
             form_id_listener_map: Default::default(),
             interactive_time: DomRefCell::new(interactive_time),
             tti_window: DomRefCell::new(InteractiveWindow::new()),
@@ -2905,6 +3639,11 @@ impl Document {
             throw_on_dynamic_markup_insertion_counter: Cell::new(0),
             page_showing: Cell::new(false),
             salvageable: Cell::new(true),
+            frozen: Cell::new(false),
+            was_discarded: Cell::new(false),
+            sandboxed_modals: Cell::new(false),
+            has_directionality_overrides: Cell::new(false),
+            directionality_dirty: Cell::new(false),
             active_parser_was_aborted: Cell::new(false),
             fired_unload: Cell::new(false),
             responsive_images: Default::default(),
@@ -2917,11 +3656,21 @@ impl Document {
             media_controls: DomRefCell::new(HashMap::new()),
             dirty_webgl_contexts: DomRefCell::new(HashMap::new()),
             csp_list: DomRefCell::new(None),
+            reporting_endpoints: DomRefCell::new(ReportingEndpoints::new()),
             selection: MutNullableDom::new(None),
+            state_to_restore: DomRefCell::new(None),
+            cookie_cache: DomRefCell::new(None),
+            prefetched_urls: DomRefCell::new(HashSet::new()),
+            inflight_keepalive_bytes: StdArc::new(AtomicUsize::new(0)),
         }
     }
 
     pub fn set_csp_list(&self, csp_list: Option<CspList>) {
+        // Layout fetches the web fonts of the document.
+        self.window
+            .layout_chan()
+            .send(Msg::SetCspList(csp_list.clone()))
+            .unwrap();
         *self.csp_list.borrow_mut() = csp_list;
     }
 
@@ -2929,6 +3678,14 @@ impl Document {
         ref_filter_map(self.csp_list.borrow(), Option::as_ref)
     }
 
+    pub fn set_reporting_endpoints(&self, endpoints: ReportingEndpoints) {
+        *self.reporting_endpoints.borrow_mut() = endpoints;
+    }
+
+    pub fn reporting_endpoints(&self) -> ReportingEndpoints {
+        self.reporting_endpoints.borrow().clone()
+    }
+
     /// https://www.w3.org/TR/CSP/#should-block-inline
     pub fn should_elements_inline_type_behavior_be_blocked(
         &self,
@@ -2941,13 +3698,72 @@ impl Document {
                 .get_attribute(&ns!(), &local_name!("nonce"))
                 .map(|attr| Cow::Owned(attr.value().to_string())),
         };
-        // TODO: Instead of ignoring violations, report them.
-        self.get_csp_list()
-            .map(|c| {
-                c.should_elements_inline_type_behavior_be_blocked(&element, type_, source)
-                    .0
-            })
-            .unwrap_or(csp::CheckResult::Allowed)
+        let (result, violations) = match self.get_csp_list() {
+            Some(csp_list) => {
+                csp_list.should_elements_inline_type_behavior_be_blocked(&element, type_, source)
+            },
+            None => return csp::CheckResult::Allowed,
+        };
+        self.global().report_csp_violations(violations, Some(el));
+        result
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-navigation-request>, for the
+    /// navigations of the nested browsing context of a frame of the document.
+    ///
+    /// The policies have no request destination for frames, so the effective source
+    /// list of `frame-src` is matched against the URL as the one of an image load.
+    pub fn should_frame_navigation_be_blocked(&self, frame: &Element, url: &ServoUrl) -> bool {
+        let csp_list = match self.get_csp_list() {
+            Some(csp_list) => csp_list.clone(),
+            None => return false,
+        };
+        let request = csp::Request {
+            url: url.clone().into_url(),
+            origin: self.origin().immutable().clone().into_url_origin(),
+            redirect_count: 0,
+            destination: csp::Destination::Image,
+            initiator: csp::Initiator::None,
+            nonce: String::new(),
+            integrity_metadata: String::new(),
+            parser_metadata: csp::ParserMetadata::None,
+        };
+        let mut blocked = false;
+        let mut violations = vec![];
+        for policy in csp_list.0 {
+            let directive = ["frame-src", "child-src", "default-src"]
+                .iter()
+                .find_map(|name| policy.directive_set.iter().find(|d| d.name == *name));
+            let value = match directive {
+                Some(directive) => directive.value.clone(),
+                None => continue,
+            };
+            let image_policy = csp::Policy {
+                directive_set: vec![csp::Directive {
+                    name: "img-src".to_owned(),
+                    value: value.clone(),
+                }],
+                disposition: policy.disposition.clone(),
+                source: policy.source.clone(),
+            };
+            let (result, image_violations) =
+                CspList(vec![image_policy]).should_request_be_blocked(&request);
+            blocked |= result == csp::CheckResult::Blocked;
+            violations.extend(
+                image_violations
+                    .into_iter()
+                    .map(|violation| csp::Violation {
+                        resource: violation.resource,
+                        directive: csp::Directive {
+                            name: "frame-src".to_owned(),
+                            value: value.clone(),
+                        },
+                        policy: policy.clone(),
+                    }),
+            );
+        }
+        self.global().report_csp_violations(violations, Some(frame));
+        blocked
     }
 
     /// Prevent any JS or layout from running until the corresponding call to
@@ -3328,6 +4144,16 @@ impl Document {
         if !pending.fullscreen_element_ready_check() {
             error = true;
         }
+        // this algorithm is triggered by user activation
+        if pref!(dom.user_activation.required) && !self.window.has_transient_activation() {
+            error = true;
+        }
+
+        // https://fullscreen.spec.whatwg.org/#dom-element-requestfullscreen
+        // consumes the user activation when the request is allowed.
+        if !error {
+            self.window.consume_user_activation();
+        }
 
         if pref!(dom.fullscreen.test) {
             // For reftests we just take over the current window,
@@ -3412,6 +4238,99 @@ impl Document {
         self.fullscreen_element.set(element);
     }
 
+    /// Make `element` the fullscreen element of this document, and the containers
+    /// of this document the fullscreen elements of its ancestor documents, so that
+    /// the element fills the window. Returns the elements that became fullscreen.
+    /// <https://fullscreen.spec.whatwg.org/#fullscreen-an-element>
+    pub fn fullscreen_element_and_containers(&self, element: &Element) -> Vec<DomRoot<Element>> {
+        let mut fullscreened = vec![];
+        let mut document = DomRoot::from_ref(self);
+        let mut element = DomRoot::from_ref(element);
+        loop {
+            if document.fullscreen_element.get().as_deref() != Some(&*element) {
+                if let Some(previous) = document.fullscreen_element.get() {
+                    previous.set_fullscreen_state(false);
+                }
+                element.set_fullscreen_state(true);
+                document.set_fullscreen_element(Some(&element));
+                fullscreened.push(element.clone());
+            }
+
+            let container = match document.window().window_proxy().frame_element() {
+                Some(container) => DomRoot::from_ref(container),
+                None => break,
+            };
+            document = document_from_node(&*container);
+            element = container;
+        }
+        fullscreened
+    }
+
+    /// Unfullscreen the fullscreen elements of this document, of the documents
+    /// nested in it, and of its ancestor documents. Returns the elements that
+    /// stopped being fullscreen.
+    /// <https://fullscreen.spec.whatwg.org/#fully-exit-fullscreen>
+    pub fn fully_exit_fullscreen(&self) -> Vec<DomRoot<Element>> {
+        let mut unfullscreened = vec![];
+
+        // Nested documents first, their fullscreen element is in our fullscreen iframe.
+        let mut document = DomRoot::from_ref(self);
+        while let Some(nested) = document
+            .fullscreen_element
+            .get()
+            .and_then(DomRoot::downcast::<HTMLIFrameElement>)
+            .and_then(|iframe| iframe.GetContentDocument())
+        {
+            document = nested;
+        }
+
+        loop {
+            if let Some(element) = document.fullscreen_element.get() {
+                element.set_fullscreen_state(false);
+                document.set_fullscreen_element(None);
+                unfullscreened.push(element);
+            }
+
+            let container = match document.window().window_proxy().frame_element() {
+                Some(container) => DomRoot::from_ref(container),
+                None => break,
+            };
+            document = document_from_node(&*container);
+            if document.fullscreen_element.get().as_deref() != Some(&*container) {
+                break;
+            }
+        }
+        unfullscreened
+    }
+
+    pub fn set_picture_in_picture_element(&self, element: Option<&Element>) {
+        self.picture_in_picture_element.set(element);
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-document-exitpictureinpicture
+    pub fn exit_picture_in_picture(&self) -> Rc<Promise> {
+        let global = self.global();
+        let in_realm_proof = AlreadyInRealm::assert(&global);
+        let promise = Promise::new_in_current_realm(&global, InRealm::Already(&in_realm_proof));
+
+        // Step 1.
+        let video = match self
+            .picture_in_picture_element
+            .get()
+            .and_then(DomRoot::downcast::<HTMLVideoElement>)
+        {
+            Some(video) => video,
+            None => {
+                promise.reject_error(Error::InvalidState);
+                return promise;
+            },
+        };
+
+        // Step 2.
+        video.leave_picture_in_picture(Some(promise.clone()));
+        promise
+    }
+
     pub fn get_allow_fullscreen(&self) -> bool {
         // https://html.spec.whatwg.org/multipage/#allowed-to-use
         match self.browsing_context() {
@@ -3423,10 +4342,15 @@ impl Document {
                 if window.is_top_level() {
                     true
                 } else {
-                    // Step 3
-                    window.GetFrameElement().map_or(false, |el| {
-                        el.has_attribute(&local_name!("allowfullscreen"))
-                    })
+                    // Step 3. The container, and the documents it is nested in,
+                    // all need to allow fullscreen.
+                    window
+                        .window_proxy()
+                        .frame_element()
+                        .map_or(false, |container| {
+                            container.has_attribute(&local_name!("allowfullscreen")) &&
+                                document_from_node(container).get_allow_fullscreen()
+                        })
                 }
             },
         }
@@ -3672,6 +4596,38 @@ impl DocumentMethods for Document {
         false
     }
 
+    // https://w3c.github.io/editing/docs/execCommand/#execcommand()
+    fn ExecCommand(&self, command_id: DOMString, _show_ui: bool, _value: DOMString) -> bool {
+        // Steps 1-2. Only the clipboard commands are supported.
+        if !self.QueryCommandEnabled(command_id.clone()) {
+            return false;
+        }
+        let action = ClipboardAction::from_command(&command_id).unwrap();
+        // Writing to the clipboard on behalf of the user consumes their activation, as
+        // for the other APIs gated on it.
+        self.window.consume_user_activation();
+        // Steps 3-5.
+        self.perform_clipboard_action(action);
+        true
+    }
+
+    // https://w3c.github.io/editing/docs/execCommand/#querycommandenabled()
+    fn QueryCommandEnabled(&self, command_id: DOMString) -> bool {
+        // https://w3c.github.io/clipboard-apis/#integration-with-rich-text-editing-apis
+        // Scripts can copy and cut on behalf of the user, but never read the clipboard.
+        match ClipboardAction::from_command(&command_id) {
+            Some(ClipboardAction::Copy) | Some(ClipboardAction::Cut) => {
+                !pref!(dom.user_activation.required) || self.window.has_transient_activation()
+            },
+            Some(ClipboardAction::Paste) | None => false,
+        }
+    }
+
+    // https://w3c.github.io/editing/docs/execCommand/#querycommandsupported()
+    fn QueryCommandSupported(&self, command_id: DOMString) -> bool {
+        ClipboardAction::from_command(&command_id).is_some()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-document-domain
     fn Domain(&self) -> DOMString {
         // Step 1.
@@ -4432,14 +5388,34 @@ impl DocumentMethods for Document {
         }
 
         let url = self.url();
+        if let Some(ref cache) = *self.cookie_cache.borrow() {
+            if cache.is_valid_for(&url) {
+                return Ok(cache
+                    .cookies
+                    .clone()
+                    .map_or(DOMString::new(), DOMString::from));
+            }
+        }
+
         let (tx, rx) = profile_ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
+        let (observer, invalidation) = ipc::channel().unwrap();
         let _ = self
             .window
             .upcast::<GlobalScope>()
             .resource_threads()
-            .send(GetCookiesForUrl(url, tx, NonHTTP));
-        let cookies = rx.recv().unwrap();
-        Ok(cookies.map_or(DOMString::new(), DOMString::from))
+            .send(ObserveCookiesForUrl(url.clone(), tx, observer));
+        let observed = rx.recv().unwrap();
+        let cookies = observed
+            .cookies
+            .clone()
+            .map_or(DOMString::new(), DOMString::from);
+        *self.cookie_cache.borrow_mut() = Some(CookieCache {
+            url,
+            cookies: observed.cookies,
+            expiry_time: observed.expiry_time,
+            invalidation,
+        });
+        Ok(cookies)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-document-cookie
@@ -4463,6 +5439,8 @@ impl DocumentMethods for Document {
             .upcast::<GlobalScope>()
             .resource_threads()
             .send(SetCookiesForUrl(self.url(), cookies, NonHTTP));
+        // The cookies are read again after the resource threads have stored the new one.
+        *self.cookie_cache.borrow_mut() = None;
         Ok(())
     }
 
@@ -4570,6 +5548,7 @@ impl DocumentMethods for Document {
         self.document_or_shadow_root.element_from_point(
             x,
             y,
+            self.upcast(),
             self.GetDocumentElement(),
             self.has_browsing_context,
         )
@@ -4580,11 +5559,26 @@ impl DocumentMethods for Document {
         self.document_or_shadow_root.elements_from_point(
             x,
             y,
+            self.upcast(),
             self.GetDocumentElement(),
             self.has_browsing_context,
         )
     }
 
+    // https://drafts.csswg.org/cssom-view/#dom-document-caretpositionfrompoint
+    fn CaretPositionFromPoint(
+        &self,
+        x: Finite<f64>,
+        y: Finite<f64>,
+    ) -> Option<DomRoot<CaretPosition>> {
+        self.document_or_shadow_root.caret_position_from_point(
+            x,
+            y,
+            self.upcast(),
+            self.has_browsing_context,
+        )
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-document-open
     fn Open(
         &self,
@@ -4828,6 +5822,21 @@ impl DocumentMethods for Document {
         self.exit_fullscreen()
     }
 
+    // https://w3c.github.io/picture-in-picture/#dom-document-pictureinpictureenabled
+    fn PictureInPictureEnabled(&self) -> bool {
+        pref!(dom.picture_in_picture.enabled)
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-document-exitpictureinpicture
+    fn ExitPictureInPicture(&self) -> Rc<Promise> {
+        self.exit_picture_in_picture()
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-documentorshadowroot-pictureinpictureelement
+    fn GetPictureInPictureElement(&self) -> Option<DomRoot<Element>> {
+        self.picture_in_picture_element.get()
+    }
+
     // check-tidy: no specs after this line
     // Servo only API to get an instance of the controls of a specific
     // media element matching the given id.
@@ -4846,6 +5855,50 @@ impl DocumentMethods for Document {
             None
         }
     }
+
+    // https://wicg.github.io/page-lifecycle/#dom-document-onfreeze
+    event_handler!(freeze, GetOnfreeze, SetOnfreeze);
+
+    // https://wicg.github.io/page-lifecycle/#dom-document-onresume
+    event_handler!(resume, GetOnresume, SetOnresume);
+
+    // https://wicg.github.io/page-lifecycle/#dom-document-wasdiscarded
+    fn WasDiscarded(&self) -> bool {
+        self.was_discarded.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-createexpression
+    fn CreateExpression(
+        &self,
+        expression: DOMString,
+        resolver: Option<Rc<XPathNSResolver>>,
+    ) -> Fallible<DomRoot<XPathExpression>> {
+        xpathevaluator::create_expression(&self.window, expression, resolver)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-creatensresolver
+    fn CreateNSResolver(&self, node_resolver: &Node) -> DomRoot<Node> {
+        DomRoot::from_ref(node_resolver)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-evaluate
+    fn Evaluate(
+        &self,
+        expression: DOMString,
+        context_node: &Node,
+        resolver: Option<Rc<XPathNSResolver>>,
+        type_: u16,
+        result: Option<&XPathResult>,
+    ) -> Fallible<DomRoot<XPathResult>> {
+        xpathevaluator::evaluate(
+            &self.window,
+            expression,
+            context_node,
+            resolver,
+            type_,
+            result,
+        )
+    }
 }
 
 fn update_with_current_time_ms(marker: &Cell<u64>) {
@@ -4872,6 +5925,36 @@ pub fn determine_policy_for_token(token: &str) -> Option<ReferrerPolicy> {
     }
 }
 
+/// The cookies which `document.cookie` returns for a URL, which the document reads again
+/// once the resource threads notify it that the cookies of its site changed, or once the
+/// first of them expires, rather than for each access.
+struct CookieCache {
+    url: ServoUrl,
+    cookies: Option<String>,
+    expiry_time: Option<SystemTime>,
+    invalidation: IpcReceiver<()>,
+}
+
+unsafe_no_jsmanaged_fields!(CookieCache);
+
+impl CookieCache {
+    fn is_valid_for(&self, url: &ServoUrl) -> bool {
+        if self.url != *url {
+            return false;
+        }
+        if self
+            .expiry_time
+            .map_or(false, |expiry_time| expiry_time <= SystemTime::now())
+        {
+            return false;
+        }
+        match self.invalidation.try_recv() {
+            Err(TryRecvError::Empty) => true,
+            _ => false,
+        }
+    }
+}
+
 /// Specifies the type of focus event that is sent to a pipeline
 #[derive(Clone, Copy, PartialEq)]
 pub enum FocusType {
@@ -4,13 +4,17 @@
 
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeBinding::NodeMethods;
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::caretposition::CaretPosition;
+use crate::dom::characterdata::CharacterData;
 use crate::dom::element::Element;
 use crate::dom::htmlelement::HTMLElement;
 use crate::dom::htmlmetaelement::HTMLMetaElement;
 use crate::dom::node::{self, Node, VecPreOrderInsertionHelper};
+use crate::dom::text::Text;
 use crate::dom::window::Window;
 use crate::stylesheet_set::StylesheetSetRef;
 use euclid::default::Point2D;
@@ -104,18 +108,15 @@ impl DocumentOrShadowRoot {
         self.window.layout().nodes_from_point_response()
     }
 
+    /// Returns the node hit at a point of the viewport, or None if the point is
+    /// outside of the viewport.
     #[allow(unsafe_code)]
-    // https://drafts.csswg.org/cssom-view/#dom-document-elementfrompoint
-    pub fn element_from_point(
+    fn node_from_point(
         &self,
-        x: Finite<f64>,
-        y: Finite<f64>,
-        document_element: Option<DomRoot<Element>>,
+        x: f32,
+        y: f32,
         has_browsing_context: bool,
-    ) -> Option<DomRoot<Element>> {
-        let x = *x as f32;
-        let y = *y as f32;
-        let point = &Point2D::new(x, y);
+    ) -> Option<Option<DomRoot<Node>>> {
         let viewport = self.window.window_size().initial_viewport;
 
         if !has_browsing_context {
@@ -126,30 +127,94 @@ impl DocumentOrShadowRoot {
             return None;
         }
 
-        match self
-            .nodes_from_point(point, NodesFromPointQueryType::Topmost)
-            .first()
-        {
-            Some(address) => {
-                let js_runtime = unsafe { JS_GetRuntime(*self.window.get_cx()) };
-                let node = unsafe { node::from_untrusted_node_address(js_runtime, *address) };
-                let parent_node = node.GetParentNode().unwrap();
-                let element_ref = node
-                    .downcast::<Element>()
-                    .unwrap_or_else(|| parent_node.downcast::<Element>().unwrap());
-
-                Some(DomRoot::from_ref(element_ref))
-            },
+        let js_runtime = unsafe { JS_GetRuntime(*self.window.get_cx()) };
+        Some(
+            self.nodes_from_point(&Point2D::new(x, y), NodesFromPointQueryType::Topmost)
+                .first()
+                .map(|address| unsafe { node::from_untrusted_node_address(js_runtime, *address) }),
+        )
+    }
+
+    /// Returns the element a hit node belongs to, retargeted against the root of this
+    /// document or shadow root, so that nodes of closed shadow trees are not exposed.
+    fn hit_element(node: &Node, root: &Node) -> Option<DomRoot<Element>> {
+        let element = match node.downcast::<Element>() {
+            Some(element) => DomRoot::from_ref(element),
+            None => DomRoot::downcast::<Element>(node.GetParentNode()?)?,
+        };
+        DomRoot::downcast::<Element>(element.upcast::<Node>().retarget(root))
+    }
+
+    // https://drafts.csswg.org/cssom-view/#dom-document-elementfrompoint
+    pub fn element_from_point(
+        &self,
+        x: Finite<f64>,
+        y: Finite<f64>,
+        root: &Node,
+        document_element: Option<DomRoot<Element>>,
+        has_browsing_context: bool,
+    ) -> Option<DomRoot<Element>> {
+        match self.node_from_point(*x as f32, *y as f32, has_browsing_context)? {
+            Some(node) => DocumentOrShadowRoot::hit_element(&node, root),
             None => document_element,
         }
     }
 
+    // https://drafts.csswg.org/cssom-view/#dom-document-caretpositionfrompoint
+    pub fn caret_position_from_point(
+        &self,
+        x: Finite<f64>,
+        y: Finite<f64>,
+        root: &Node,
+        has_browsing_context: bool,
+    ) -> Option<DomRoot<CaretPosition>> {
+        let x = *x as f32;
+        let y = *y as f32;
+        let node = self.node_from_point(x, y, has_browsing_context)??;
+
+        let (offset_node, offset) = match node.downcast::<Text>() {
+            Some(text) => {
+                // The text index is computed from the inline fragments of the text,
+                // which are positioned relative to the initial containing block.
+                let point = Point2D::new(
+                    x + self.window.ScrollX() as f32,
+                    y + self.window.ScrollY() as f32,
+                );
+                let index = self.window.caret_position_query(&node, point);
+                // Layout counts characters, while offsets are in UTF-16 code units.
+                let data = text.upcast::<CharacterData>().data();
+                let offset = index.map_or(0, |index| {
+                    data.chars().take(index).map(char::len_utf16).sum::<usize>()
+                });
+                (node.clone(), offset as u32)
+            },
+            None => (
+                DomRoot::upcast(DocumentOrShadowRoot::hit_element(&node, root)?),
+                0,
+            ),
+        };
+
+        // The caret must not point into a shadow tree that is hidden from the root, in
+        // which case it is moved in front of the host of that tree.
+        let retargeted = offset_node.retarget(root);
+        if retargeted != offset_node {
+            let parent = retargeted.GetParentNode()?;
+            return Some(CaretPosition::new(
+                &self.window,
+                &parent,
+                retargeted.index(),
+            ));
+        }
+        Some(CaretPosition::new(&self.window, &offset_node, offset))
+    }
+
     #[allow(unsafe_code)]
     // https://drafts.csswg.org/cssom-view/#dom-document-elementsfrompoint
     pub fn elements_from_point(
         &self,
         x: Finite<f64>,
         y: Finite<f64>,
+        root: &Node,
         document_element: Option<DomRoot<Element>>,
         has_browsing_context: bool,
     ) -> Vec<DomRoot<Element>> {
@@ -171,19 +236,24 @@ impl DocumentOrShadowRoot {
 
         // Step 1 and Step 3
         let nodes = self.nodes_from_point(point, NodesFromPointQueryType::All);
-        let mut elements: Vec<DomRoot<Element>> = nodes
-            .iter()
-            .flat_map(|&untrusted_node_address| {
-                let node = unsafe {
-                    node::from_untrusted_node_address(js_runtime, untrusted_node_address)
-                };
-                DomRoot::downcast::<Element>(node)
-            })
-            .collect();
+        let mut elements: Vec<DomRoot<Element>> = vec![];
+        for &untrusted_node_address in &nodes {
+            let node =
+                unsafe { node::from_untrusted_node_address(js_runtime, untrusted_node_address) };
+            // Text is hit through its parent element, and all the nodes of a closed shadow
+            // tree are hit through its host.
+            let element = match DocumentOrShadowRoot::hit_element(&node, root) {
+                Some(element) => element,
+                None => continue,
+            };
+            if !elements.contains(&element) {
+                elements.push(element);
+            }
+        }
 
         // Step 4
         if let Some(root_element) = document_element {
-            if elements.last() != Some(&root_element) {
+            if !elements.contains(&root_element) {
                 elements.push(root_element);
             }
         }
@@ -7,19 +7,25 @@ use crate::dom::bindings::codegen::Bindings::DOMParserBinding;
 use crate::dom::bindings::codegen::Bindings::DOMParserBinding::DOMParserMethods;
 use crate::dom::bindings::codegen::Bindings::DOMParserBinding::SupportedType::Application_xhtml_xml;
 use crate::dom::bindings::codegen::Bindings::DOMParserBinding::SupportedType::Application_xml;
+use crate::dom::bindings::codegen::Bindings::DOMParserBinding::SupportedType::Image_svg_xml;
 use crate::dom::bindings::codegen::Bindings::DOMParserBinding::SupportedType::Text_html;
 use crate::dom::bindings::codegen::Bindings::DOMParserBinding::SupportedType::Text_xml;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentReadyState;
+use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::document::DocumentSource;
 use crate::dom::document::{Document, HasBrowsingContext, IsHTMLDocument};
+use crate::dom::element::{CustomElementCreationMode, Element, ElementCreator};
+use crate::dom::node::Node;
 use crate::dom::servoparser::ServoParser;
 use crate::dom::window::Window;
 use dom_struct::dom_struct;
+use html5ever::{LocalName, Namespace, QualName};
 use script_traits::DocumentActivity;
 
 #[dom_struct]
@@ -85,7 +91,7 @@ impl DOMParserMethods for DOMParser {
                 document.set_ready_state(DocumentReadyState::Complete);
                 Ok(document)
             },
-            Text_xml | Application_xml | Application_xhtml_xml => {
+            Text_xml | Application_xml | Application_xhtml_xml | Image_svg_xml => {
                 let document = Document::new(
                     &self.window,
                     HasBrowsingContext::No,
@@ -101,10 +107,36 @@ impl DOMParserMethods for DOMParser {
                     None,
                     Default::default(),
                 );
-                ServoParser::parse_xml_document(&document, s, url);
+                if let Err(error) = ServoParser::parse_complete_xml_document(&document, s, url) {
+                    replace_with_parser_error(&document, error);
+                }
                 document.set_ready_state(DocumentReadyState::Complete);
                 Ok(document)
             },
         }
     }
 }
+
+/// Replace the contents of a document that is not well-formed with a `parsererror`
+/// element describing the error, as browsers do.
+fn replace_with_parser_error(document: &Document, error: String) {
+    let name = QualName::new(
+        None,
+        Namespace::from("http://www.mozilla.org/newlayout/xml/parsererror.xml"),
+        LocalName::from("parsererror"),
+    );
+    let element = Element::create(
+        name,
+        None,
+        document,
+        ElementCreator::ScriptCreated,
+        CustomElementCreationMode::Synchronous,
+    );
+    element
+        .upcast::<Node>()
+        .SetTextContent(Some(DOMString::from(format!(
+            "XML Parsing Error: {}",
+            error
+        ))));
+    Node::replace_all(Some(element.upcast()), document.upcast());
+}
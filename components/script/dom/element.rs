@@ -10,6 +10,7 @@ use crate::dom::bindings::cell::{ref_filter_map, DomRefCell, Ref, RefMut};
 use crate::dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::ElementBinding;
+use crate::dom::bindings::codegen::Bindings::ElementBinding::CheckVisibilityOptions;
 use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use crate::dom::bindings::codegen::Bindings::HTMLTemplateElementBinding::HTMLTemplateElementMethods;
@@ -81,6 +82,7 @@ use crate::dom::text::Text;
 use crate::dom::validation::Validatable;
 use crate::dom::virtualmethods::{vtable_for, VirtualMethods};
 use crate::dom::window::ReflowReason;
+use crate::dom::xmlserializer;
 use crate::script_thread::ScriptThread;
 use crate::stylesheet_loader::StylesheetOwner;
 use crate::task::TaskOnce;
@@ -112,6 +114,8 @@ use std::rc::Rc;
 use std::str::FromStr;
 use style::applicable_declarations::ApplicableDeclarationBlock;
 use style::attr::{AttrValue, LengthOrPercentageOrAuto};
+use style::computed_values::direction::T as Direction;
+use style::computed_values::visibility::T as Visibility;
 use style::context::QuirksMode;
 use style::dom_apis;
 use style::element_state::ElementState;
@@ -132,11 +136,6 @@ use style::thread_state;
 use style::values::generics::NonNegative;
 use style::values::{computed, specified, CSSFloat};
 use style::CaseSensitivityExt;
-use xml5ever::serialize as xmlSerialize;
-use xml5ever::serialize::SerializeOpts as XmlSerializeOpts;
-use xml5ever::serialize::TraversalScope as XmlTraversalScope;
-use xml5ever::serialize::TraversalScope::ChildrenOnly as XmlChildrenOnly;
-use xml5ever::serialize::TraversalScope::IncludeNode as XmlIncludeNode;
 
 // TODO: Update focus state when the top-level browsing context gains or loses system focus,
 // and when the element enters or leaves a browsing context container.
@@ -277,7 +276,8 @@ impl Element {
             style_attribute: DomRefCell::new(None),
             attr_list: Default::default(),
             class_list: Default::default(),
-            state: Cell::new(state),
+            // The directionality of the elements is left-to-right until it is computed.
+            state: Cell::new(state | ElementState::IN_LTR_STATE),
             selector_flags: Cell::new(ElementSelectorFlags::empty()),
             rare_data: Default::default(),
         }
@@ -421,6 +421,25 @@ impl Element {
         self.ScrollHeight() > self.ClientHeight() || self.ScrollWidth() > self.ClientWidth()
     }
 
+    /// Scroll the scrolling box of this element to a position clamped to its scrollable
+    /// overflow area. Scroll positions are fractional, and the horizontal ones are negative
+    /// when the box is right-to-left since its scroll origin is then on the right.
+    /// <https://drafts.csswg.org/cssom-view/#scroll-an-element>
+    fn scroll_scrolling_box(&self, x: f64, y: f64, behavior: ScrollBehavior) {
+        let max_x = (self.ScrollWidth() - self.ClientWidth()).max(0) as f64;
+        let max_y = (self.ScrollHeight() - self.ClientHeight()).max(0) as f64;
+        let is_rtl = self.style().map_or(false, |style| {
+            style.get_inherited_box().clone_direction() == Direction::Rtl
+        });
+        let x = if is_rtl {
+            x.min(0.0).max(-max_x)
+        } else {
+            x.min(max_x).max(0.0)
+        };
+        let y = y.min(max_y).max(0.0);
+        window_from_node(self).scroll_node(self.upcast(), x, y, behavior);
+    }
+
     // TODO: Once #19183 is closed (overflow-x/y types moved out of mako), then we could implement
     //       a more generic `fn has_some_overflow(&self, overflow: Overflow)` rather than have
     //       these two `has_any_{visible,hidden}_overflow` methods which are very structurally
@@ -446,7 +465,7 @@ impl Element {
         })
     }
 
-    fn shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
+    pub fn shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
         self.rare_data()
             .as_ref()?
             .shadow_root
@@ -1246,22 +1265,6 @@ impl Element {
         }
     }
 
-    #[allow(non_snake_case)]
-    pub fn xmlSerialize(&self, traversal_scope: XmlTraversalScope) -> Fallible<DOMString> {
-        let mut writer = vec![];
-        match xmlSerialize::serialize(
-            &mut writer,
-            &self.upcast::<Node>(),
-            XmlSerializeOpts {
-                traversal_scope: traversal_scope,
-                ..Default::default()
-            },
-        ) {
-            Ok(()) => Ok(DOMString::from(String::from_utf8(writer).unwrap())),
-            Err(_) => panic!("Cannot serialize element"),
-        }
-    }
-
     pub fn root_element(&self) -> DomRoot<Element> {
         if self.node.is_in_doc() {
             self.upcast::<Node>()
@@ -1799,7 +1802,7 @@ impl Element {
         }
 
         // Step 11
-        win.scroll_node(node, x, y, behavior);
+        self.scroll_scrolling_box(x, y, behavior);
     }
 
     // https://w3c.github.io/DOM-Parsing/#parsing
@@ -2278,7 +2281,7 @@ impl ElementMethods for Element {
 
         // Step 9
         let point = node.scroll_offset();
-        return point.y.abs() as f64;
+        return point.y as f64;
     }
 
     // https://drafts.csswg.org/cssom-view/#dom-element-scrolltop
@@ -2328,7 +2331,7 @@ impl ElementMethods for Element {
         }
 
         // Step 11
-        win.scroll_node(node, self.ScrollLeft(), y, behavior);
+        self.scroll_scrolling_box(self.ScrollLeft(), y, behavior);
     }
 
     // https://drafts.csswg.org/cssom-view/#dom-element-scrolltop
@@ -2374,7 +2377,7 @@ impl ElementMethods for Element {
 
         // Step 9
         let point = node.scroll_offset();
-        return point.x.abs() as f64;
+        return point.x as f64;
     }
 
     // https://drafts.csswg.org/cssom-view/#dom-element-scrollleft
@@ -2425,7 +2428,7 @@ impl ElementMethods for Element {
         }
 
         // Step 11
-        win.scroll_node(node, x, self.ScrollTop(), behavior);
+        self.scroll_scrolling_box(x, self.ScrollTop(), behavior);
     }
 
     // https://drafts.csswg.org/cssom-view/#dom-element-scrollwidth
@@ -2458,6 +2461,48 @@ impl ElementMethods for Element {
         self.upcast::<Node>().client_rect().size.height
     }
 
+    // https://drafts.csswg.org/cssom-view/#dom-element-checkvisibility
+    fn CheckVisibility(&self, options: &CheckVisibilityOptions) -> bool {
+        // Step 1.
+        let style = match self.style() {
+            Some(style) => style,
+            None => return false,
+        };
+        let display = style.get_box().clone_display();
+        if display.is_none() || display.is_contents() {
+            return false;
+        }
+
+        // Steps 2 and 5. There is no `content-visibility` property, so no element skips
+        // its contents.
+
+        // Step 3.
+        if options.checkOpacity || options.opacityProperty {
+            let is_transparent = self
+                .upcast::<Node>()
+                .inclusive_ancestors(ShadowIncluding::Yes)
+                .filter_map(DomRoot::downcast::<Element>)
+                .any(|element| {
+                    element
+                        .style()
+                        .map_or(false, |style| style.get_effects().opacity == 0.0)
+                });
+            if is_transparent {
+                return false;
+            }
+        }
+
+        // Step 4.
+        if options.checkVisibilityCSS || options.visibilityProperty {
+            if style.get_inherited_box().clone_visibility() != Visibility::Visible {
+                return false;
+            }
+        }
+
+        // Step 6.
+        true
+    }
+
     /// <https://w3c.github.io/DOM-Parsing/#widl-Element-innerHTML>
     fn GetInnerHTML(&self) -> Fallible<DOMString> {
         let qname = QualName::new(
@@ -2468,7 +2513,7 @@ impl ElementMethods for Element {
         if document_from_node(self).is_html_document() {
             return self.serialize(ChildrenOnly(Some(qname)));
         } else {
-            return self.xmlSerialize(XmlChildrenOnly(Some(qname)));
+            return xmlserializer::serialize_children(self, true);
         }
     }
 
@@ -2492,7 +2537,7 @@ impl ElementMethods for Element {
         if document_from_node(self).is_html_document() {
             return self.serialize(IncludeNode);
         } else {
-            return self.xmlSerialize(XmlIncludeNode);
+            return xmlserializer::serialize(self.upcast(), true);
         }
     }
 
@@ -2623,7 +2668,9 @@ impl ElementMethods for Element {
             Ok(selectors) => selectors,
         };
 
-        let quirks_mode = document_from_node(self).quirks_mode();
+        let document = document_from_node(self);
+        document.flush_directionality();
+        let quirks_mode = document.quirks_mode();
         let element = DomRoot::from_ref(self);
 
         Ok(dom_apis::element_matches(&element, &selectors, quirks_mode))
@@ -2641,7 +2688,9 @@ impl ElementMethods for Element {
             Ok(selectors) => selectors,
         };
 
-        let quirks_mode = document_from_node(self).quirks_mode();
+        let document = document_from_node(self);
+        document.flush_directionality();
+        let quirks_mode = document.quirks_mode();
         Ok(dom_apis::element_closest(
             DomRoot::from_ref(self),
             &selectors,
@@ -2938,6 +2987,11 @@ impl VirtualMethods for Element {
             }
         }
 
+        // The bdi elements isolate their directionality from the one of their parent.
+        if self.namespace == ns!(html) && self.local_name == local_name!("bdi") {
+            doc.add_directionality_override();
+        }
+
         // This is used for layout optimization.
         doc.increment_dom_count();
     }
@@ -3144,6 +3198,9 @@ impl<'a> SelectorsElement for DomRoot<Element> {
 
             NonTSPseudoClass::ReadOnly => !Element::state(self).contains(pseudo_class.state_flag()),
 
+            // The state of the unknown directions is empty, so they never match.
+            NonTSPseudoClass::Dir(_) => Element::state(self).intersects(pseudo_class.state_flag()),
+
             NonTSPseudoClass::Active |
             NonTSPseudoClass::Focus |
             NonTSPseudoClass::Fullscreen |
@@ -3602,16 +3659,19 @@ impl TaskOnce for ElementPerformFullscreenEnter {
 
         // TODO Step 7.2-4
         // Step 7.5
-        element.set_fullscreen_state(true);
-        document.set_fullscreen_element(Some(&element));
-        document
-            .window()
-            .reflow(ReflowGoal::Full, ReflowReason::ElementStateChanged);
+        let fullscreened = document.fullscreen_element_and_containers(&element);
+        for element in &fullscreened {
+            document_from_node(&**element)
+                .window()
+                .reflow(ReflowGoal::Full, ReflowReason::ElementStateChanged);
+        }
 
         // Step 7.6
-        document
-            .upcast::<EventTarget>()
-            .fire_event(atom!("fullscreenchange"));
+        for element in &fullscreened {
+            element
+                .upcast::<EventTarget>()
+                .fire_bubbling_event(atom!("fullscreenchange"));
+        }
 
         // Step 7.7
         promise.resolve_native(&());
@@ -3642,18 +3702,25 @@ impl TaskOnce for ElementPerformFullscreenExit {
         let document = document_from_node(&*element);
         // TODO Step 9.1-5
         // Step 9.6
-        element.set_fullscreen_state(false);
-
-        document
-            .window()
-            .reflow(ReflowGoal::Full, ReflowReason::ElementStateChanged);
-
-        document.set_fullscreen_element(None);
+        let unfullscreened = document.fully_exit_fullscreen();
+        for element in &unfullscreened {
+            document_from_node(&**element)
+                .window()
+                .reflow(ReflowGoal::Full, ReflowReason::ElementStateChanged);
+        }
 
         // Step 9.8
-        document
-            .upcast::<EventTarget>()
-            .fire_event(atom!("fullscreenchange"));
+        for element in &unfullscreened {
+            if element.upcast::<Node>().is_connected() {
+                element
+                    .upcast::<EventTarget>()
+                    .fire_bubbling_event(atom!("fullscreenchange"));
+            } else {
+                document_from_node(&**element)
+                    .upcast::<EventTarget>()
+                    .fire_event(atom!("fullscreenchange"));
+            }
+        }
 
         // Step 9.10
         self.promise.root().resolve_native(&());
@@ -22,6 +22,7 @@ use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingLi
 use crate::realms::enter_realm;
 use crate::task_source::{TaskSource, TaskSourceName};
 use crate::timers::OneshotTimerCallback;
+use content_security_policy as csp;
 use dom_struct::dom_struct;
 use euclid::Length;
 use headers::ContentType;
@@ -414,6 +415,11 @@ impl FetchResponseListener for EventSourceContext {
         self.reestablish_the_connection();
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
@@ -535,7 +541,8 @@ impl EventSource {
             Some(true),
         )
         .origin(global.origin().immutable().clone())
-        .pipeline_id(Some(global.pipeline_id()));
+        .pipeline_id(Some(global.pipeline_id()))
+        .csp_list(global.get_csp_list());
 
         // Step 10
         // TODO(eijebong): Replace once typed headers allow it
@@ -12,18 +12,15 @@ use crate::dom::bindings::codegen::Bindings::XRInputSourceBinding::{
 use crate::dom::bindings::codegen::Bindings::XRSessionBinding::XRVisibilityState;
 use crate::dom::bindings::codegen::Bindings::XRViewBinding::XREye;
 use crate::dom::bindings::error::{Error, Fallible};
-use crate::dom::bindings::refcounted::TrustedPromise;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::fakexrinputcontroller::FakeXRInputController;
 use crate::dom::globalscope::GlobalScope;
-use crate::dom::promise::Promise;
-use crate::task_source::TaskSource;
+use crate::dom::promise::{PendingPromise, Promise};
 use dom_struct::dom_struct;
 use euclid::{Point2D, Rect, Size2D};
 use euclid::{RigidTransform3D, Rotation3D, Transform3D, Vector3D};
 use ipc_channel::ipc::IpcSender;
-use ipc_channel::router::ROUTER;
 use profile_traits::ipc;
 use std::cell::Cell;
 use std::rc::Rc;
@@ -235,21 +232,15 @@ impl FakeXRDeviceMethods for FakeXRDevice {
     fn Disconnect(&self) -> Rc<Promise> {
         let global = self.global();
         let p = Promise::new(&global);
-        let mut trusted = Some(TrustedPromise::new(p.clone()));
         let (task_source, canceller) = global
             .as_window()
             .task_manager()
             .dom_manipulation_task_source_with_canceller();
         let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
-        ROUTER.add_route(
-            receiver.to_opaque(),
-            Box::new(move |_| {
-                let trusted = trusted
-                    .take()
-                    .expect("disconnect callback called multiple times");
-                let _ = task_source.queue_with_canceller(trusted.resolve_task(()), &canceller);
-            }),
-        );
+        PendingPromise::new(&p, task_source, canceller)
+            .settle_with_message(receiver.to_opaque(), |promise, ()| {
+                promise.resolve_native(&())
+            });
         self.disconnect(sender);
         p
     }
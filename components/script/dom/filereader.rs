@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::background_jobs::{queue_background_job, BackgroundJob};
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::BlobBinding::BlobMethods;
 use crate::dom::bindings::codegen::Bindings::FileReaderBinding::{
@@ -19,23 +20,30 @@ use crate::dom::blob::Blob;
 use crate::dom::domexception::{DOMErrorName, DOMException};
 use crate::dom::event::{Event, EventBubbles, EventCancelable};
 use crate::dom::eventtarget::EventTarget;
-use crate::dom::globalscope::GlobalScope;
+use crate::dom::globalscope::{BlobContents, GlobalScope};
 use crate::dom::progressevent::ProgressEvent;
+use crate::dom::textdecodercommon::encoding_from_label;
 use crate::realms::enter_realm;
 use crate::script_runtime::JSContext;
-use crate::task_source::file_reading::FileReadingTask;
+use crate::task::TaskCanceller;
+use crate::task_source::file_reading::{FileReadingTask, FileReadingTaskSource};
 use crate::task_source::{TaskSource, TaskSourceName};
 use base64;
 use dom_struct::dom_struct;
-use encoding_rs::{Encoding, UTF_8};
+use encoding_rs::UTF_8;
 use js::jsapi::Heap;
 use js::jsapi::JSObject;
 use js::jsval::{self, JSVal};
+use js::rust::MutableHandleObject;
 use js::typedarray::{ArrayBuffer, CreateWith};
 use mime::{self, Mime};
+use net_traits::filemanager_thread::ReadFileProgress;
 use servo_atoms::Atom;
 use std::cell::Cell;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
 pub enum FileReaderFunction {
@@ -90,8 +98,9 @@ impl FileReaderSharedFunctionality {
     pub fn dataurl_format(blob_contents: &[u8], blob_type: String) -> DOMString {
         let base64 = base64::encode(&blob_contents);
 
+        // Blobs of an unspecified type are packaged as arbitrary binary data.
         let dataurl = if blob_type.is_empty() {
-            format!("data:base64,{}", base64)
+            format!("data:application/octet-stream;base64,{}", base64)
         } else {
             format!("data:{};base64,{}", blob_type, base64)
         };
@@ -108,8 +117,7 @@ impl FileReaderSharedFunctionality {
         // Steps 1 & 2 & 3
         let mut encoding = blob_label
             .as_ref()
-            .map(|string| string.as_bytes())
-            .and_then(Encoding::for_label);
+            .and_then(|label| encoding_from_label(label));
 
         // Step 4 & 5
         encoding = encoding.or_else(|| {
@@ -117,7 +125,7 @@ impl FileReaderSharedFunctionality {
             resultmime.and_then(|mime| {
                 mime.params()
                     .find(|(ref k, _)| &mime::CHARSET == k)
-                    .and_then(|(_, ref v)| Encoding::for_label(v.as_ref().as_bytes()))
+                    .and_then(|(_, ref v)| encoding_from_label(v.as_ref()))
             })
         });
 
@@ -129,6 +137,24 @@ impl FileReaderSharedFunctionality {
         let (output, _, _) = enc.decode(convert);
         DOMString::from(output)
     }
+
+    // https://w3c.github.io/FileAPI/#readAsBinaryString
+    pub fn binary_string(blob_contents: &[u8]) -> DOMString {
+        // Every byte is a code unit of the result.
+        DOMString::from(
+            blob_contents
+                .iter()
+                .map(|&byte| byte as char)
+                .collect::<String>(),
+        )
+    }
+
+    #[allow(unsafe_code)]
+    pub fn array_buffer(cx: JSContext, blob_contents: &[u8], rval: MutableHandleObject) {
+        unsafe {
+            assert!(ArrayBuffer::create(*cx, CreateWith::Slice(blob_contents), rval).is_ok());
+        }
+    }
 }
 
 #[dom_struct]
@@ -138,6 +164,9 @@ pub struct FileReader {
     error: MutNullableDom<DOMException>,
     result: DomRefCell<Option<FileReaderResult>>,
     generation_id: Cell<GenerationId>,
+    /// The token cancelling the read in progress, shared with the thread performing it.
+    #[ignore_malloc_size_of = "Arc"]
+    read_cancellation: DomRefCell<Option<Arc<AtomicBool>>>,
 }
 
 impl FileReader {
@@ -148,6 +177,7 @@ impl FileReader {
             error: MutNullableDom::new(None),
             result: DomRefCell::new(None),
             generation_id: Cell::new(GenerationId(0)),
+            read_cancellation: DomRefCell::new(None),
         }
     }
 
@@ -198,7 +228,12 @@ impl FileReader {
     }
 
     // https://w3c.github.io/FileAPI/#dfn-readAsText
-    pub fn process_read_data(filereader: TrustedFileReader, gen_id: GenerationId) {
+    pub fn process_read_data(
+        filereader: TrustedFileReader,
+        gen_id: GenerationId,
+        loaded: u64,
+        total: u64,
+    ) {
         let fr = filereader.root();
 
         macro_rules! return_on_abort(
@@ -209,8 +244,8 @@ impl FileReader {
             );
         );
         return_on_abort!();
-        //FIXME Step 7 send current progress
-        fr.dispatch_progress_event(atom!("progress"), 0, None);
+        // Step 7
+        fr.dispatch_progress_event(atom!("progress"), loaded, Some(total));
     }
 
     // https://w3c.github.io/FileAPI/#dfn-readAsText
@@ -247,6 +282,7 @@ impl FileReader {
         );
 
         return_on_abort!();
+        let total = blob_contents.len() as u64;
         // Step 8.1
         fr.change_ready_state(FileReaderReadyState::Done);
         // Step 8.2
@@ -270,11 +306,11 @@ impl FileReader {
         };
 
         // Step 8.3
-        fr.dispatch_progress_event(atom!("load"), 0, None);
+        fr.dispatch_progress_event(atom!("load"), total, Some(total));
         return_on_abort!();
         // Step 8.4
         if fr.ready_state.get() != FileReaderReadyState::Loading {
-            fr.dispatch_progress_event(atom!("loadend"), 0, None);
+            fr.dispatch_progress_event(atom!("loadend"), total, Some(total));
         }
         return_on_abort!();
     }
@@ -313,10 +349,7 @@ impl FileReader {
     ) {
         unsafe {
             rooted!(in(*cx) let mut array_buffer = ptr::null_mut::<JSObject>());
-            assert!(
-                ArrayBuffer::create(*cx, CreateWith::Slice(bytes), array_buffer.handle_mut())
-                    .is_ok()
-            );
+            FileReaderSharedFunctionality::array_buffer(cx, bytes, array_buffer.handle_mut());
 
             *result.borrow_mut() = Some(FileReaderResult::ArrayBuffer(Heap::default()));
 
@@ -422,6 +455,9 @@ impl FileReader {
     fn terminate_ongoing_reading(&self) {
         let GenerationId(prev_id) = self.generation_id.get();
         self.generation_id.set(GenerationId(prev_id + 1));
+        if let Some(cancellation) = self.read_cancellation.borrow_mut().take() {
+            cancellation.store(true, Ordering::Relaxed);
+        }
     }
 
     /// <https://w3c.github.io/FileAPI/#readOperation>
@@ -446,36 +482,57 @@ impl FileReader {
 
         let load_data = ReadMetaData::new(String::from(type_), label.map(String::from), function);
 
-        let GenerationId(prev_id) = self.generation_id.get();
-        self.generation_id.set(GenerationId(prev_id + 1));
+        self.terminate_ongoing_reading();
         let gen_id = self.generation_id.get();
-
-        // Step 10, in parallel, wait on stream promises to resolve and queue tasks.
+        let cancellation = Arc::new(AtomicBool::new(false));
+        *self.read_cancellation.borrow_mut() = Some(cancellation.clone());
 
         // TODO: follow the spec which requires implementing blob `get_stream`,
         // see https://github.com/servo/servo/issues/25209
 
-        // Currently bytes are first read "sync", and then the appropriate tasks are queued.
-
-        // Read the blob bytes "sync".
-        let blob_contents = blob.get_bytes().unwrap_or_else(|_| vec![]);
+        // The contents of File-backed blobs are streamed from disk in parallel,
+        // those of other blobs are read "sync", and then consumed in parallel.
+        let total = blob.Size();
+        let blob_contents = blob
+            .get_contents()
+            .unwrap_or_else(|_| BlobContents::Bytes(vec![]));
 
         let filereader = Trusted::new(self);
         let global = self.global();
         let canceller = global.task_canceller(TaskSourceName::FileReading);
         let task_source = global.file_reading_task_source();
 
-        // Queue tasks as appropriate.
+        // Fire loadstart before any of the chunks is read.
         let task = FileReadingTask::ProcessRead(filereader.clone(), gen_id);
         task_source.queue_with_canceller(task, &canceller).unwrap();
 
-        if !blob_contents.is_empty() {
-            let task = FileReadingTask::ProcessReadData(filereader.clone(), gen_id);
-            task_source.queue_with_canceller(task, &canceller).unwrap();
-        }
-
-        let task = FileReadingTask::ProcessReadEOF(filereader, gen_id, load_data, blob_contents);
-        task_source.queue_with_canceller(task, &canceller).unwrap();
+        // Run the error steps if the read fails unexpectedly.
+        let failed_filereader = filereader.clone();
+        let failed_task_source = task_source.clone();
+        let failed_canceller = global.task_canceller(TaskSourceName::FileReading);
+        queue_background_job(BackgroundJob {
+            // Step 10, in parallel, read chunks and queue tasks.
+            run: Box::new(move || {
+                perform_annotated_read_operation(
+                    gen_id,
+                    load_data,
+                    blob_contents,
+                    total,
+                    filereader,
+                    task_source,
+                    canceller,
+                    cancellation,
+                )
+            }),
+            failed: Box::new(move || {
+                let task = FileReadingTask::ProcessReadError(
+                    failed_filereader,
+                    gen_id,
+                    DOMErrorName::NotReadableError,
+                );
+                let _ = failed_task_source.queue_with_canceller(task, &failed_canceller);
+            }),
+        });
 
         Ok(())
     }
@@ -484,3 +541,90 @@ impl FileReader {
         self.ready_state.set(state);
     }
 }
+
+/// The size of the chunks in which the contents of a blob are read.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The minimum interval between two progress events of a read.
+/// <https://w3c.github.io/FileAPI/#ref-for-dfn-progress-event>
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(50);
+
+// https://w3c.github.io/FileAPI/#readOperation step 10
+fn perform_annotated_read_operation(
+    gen_id: GenerationId,
+    data: ReadMetaData,
+    blob_contents: BlobContents,
+    total: u64,
+    filereader: TrustedFileReader,
+    task_source: FileReadingTaskSource,
+    canceller: TaskCanceller,
+    cancellation: Arc<AtomicBool>,
+) {
+    // The read may have been aborted while it waited for a reading thread.
+    if cancellation.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut loaded = 0;
+    let mut last_progress_event: Option<Instant> = None;
+    let mut chunk_read = |chunk_len: usize| {
+        loaded += chunk_len as u64;
+
+        // Step 10.5.3, fire a progress event if roughly 50ms have passed since the last one.
+        let progress_event_due =
+            last_progress_event.map_or(true, |last| last.elapsed() >= PROGRESS_EVENT_INTERVAL);
+        if progress_event_due {
+            last_progress_event = Some(Instant::now());
+            let task = FileReadingTask::ProcessReadData(filereader.clone(), gen_id, loaded, total);
+            let _ = task_source.queue_with_canceller(task, &canceller);
+        }
+    };
+
+    // Stop reading as soon as the read is aborted, rather than queuing tasks that
+    // the file reader would ignore.
+    let bytes = match blob_contents {
+        BlobContents::Bytes(bytes) => {
+            for chunk in bytes.chunks(READ_CHUNK_SIZE) {
+                if cancellation.load(Ordering::Relaxed) {
+                    return;
+                }
+                chunk_read(chunk.len());
+            }
+            bytes
+        },
+        BlobContents::File(receiver) => {
+            // Dropping the receiver of an aborted read stops the reading of the file.
+            let mut bytes = Vec::with_capacity(total as usize);
+            loop {
+                if cancellation.load(Ordering::Relaxed) {
+                    return;
+                }
+                let mut chunk = match receiver.recv() {
+                    Ok(Ok(ReadFileProgress::Meta(blob_buf))) => blob_buf.bytes,
+                    Ok(Ok(ReadFileProgress::Partial(chunk))) => chunk,
+                    Ok(Ok(ReadFileProgress::EOF)) => break,
+                    Ok(Err(_)) | Err(_) => {
+                        // Run the error steps, the read failed.
+                        let task = FileReadingTask::ProcessReadError(
+                            filereader.clone(),
+                            gen_id,
+                            DOMErrorName::NotReadableError,
+                        );
+                        let _ = task_source.queue_with_canceller(task, &canceller);
+                        return;
+                    },
+                };
+                chunk_read(chunk.len());
+                bytes.append(&mut chunk);
+            }
+            bytes
+        },
+    };
+
+    // Step 10.6
+    if cancellation.load(Ordering::Relaxed) {
+        return;
+    }
+    let task = FileReadingTask::ProcessReadEOF(filereader, gen_id, data, bytes);
+    let _ = task_source.queue_with_canceller(task, &canceller);
+}
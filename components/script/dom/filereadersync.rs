@@ -16,7 +16,6 @@ use crate::dom::globalscope::GlobalScope;
 use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
 use js::jsapi::JSObject;
-use js::typedarray::{ArrayBuffer, CreateWith};
 use std::ptr;
 use std::ptr::NonNull;
 
@@ -57,7 +56,7 @@ impl FileReaderSyncMethods for FileReaderSync {
         let blob_contents = FileReaderSync::get_blob_bytes(blob)?;
 
         // step 2
-        Ok(DOMString::from(String::from_utf8_lossy(&blob_contents)))
+        Ok(FileReaderSharedFunctionality::binary_string(&blob_contents))
     }
 
     // https://w3c.github.io/FileAPI/#readAsTextSync
@@ -94,16 +93,9 @@ impl FileReaderSyncMethods for FileReaderSync {
         let blob_contents = FileReaderSync::get_blob_bytes(blob)?;
 
         // step 2
-        unsafe {
-            rooted!(in(*cx) let mut array_buffer = ptr::null_mut::<JSObject>());
-            assert!(ArrayBuffer::create(
-                *cx,
-                CreateWith::Slice(&blob_contents),
-                array_buffer.handle_mut()
-            )
-            .is_ok());
-
-            Ok(NonNull::new_unchecked(array_buffer.get()))
-        }
+        rooted!(in(*cx) let mut array_buffer = ptr::null_mut::<JSObject>());
+        FileReaderSharedFunctionality::array_buffer(cx, &blob_contents, array_buffer.handle_mut());
+
+        unsafe { Ok(NonNull::new_unchecked(array_buffer.get())) }
     }
 }
@@ -2,8 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::callback::ExceptionHandling;
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::BroadcastChannelBinding::BroadcastChannelMethods;
+use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::EventSourceBinding::EventSourceBinding::EventSourceMethods;
 use crate::dom::bindings::codegen::Bindings::PermissionStatusBinding::PermissionState;
 use crate::dom::bindings::codegen::Bindings::VoidFunctionBinding::VoidFunction;
@@ -12,7 +14,7 @@ use crate::dom::bindings::codegen::Bindings::WorkerGlobalScopeBinding::WorkerGlo
 use crate::dom::bindings::conversions::{root_from_object, root_from_object_static};
 use crate::dom::bindings::error::{report_pending_exception, Error, ErrorInfo};
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::settings_stack::{entry_global, incumbent_global, AutoEntryScript};
@@ -22,8 +24,10 @@ use crate::dom::bindings::utils::to_frozen_array;
 use crate::dom::bindings::weakref::{DOMTracker, WeakRef};
 use crate::dom::blob::Blob;
 use crate::dom::broadcastchannel::BroadcastChannel;
+use crate::dom::cachestorage::CacheStorage;
 use crate::dom::crypto::Crypto;
 use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
+use crate::dom::element::Element;
 use crate::dom::errorevent::ErrorEvent;
 use crate::dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
 use crate::dom::eventsource::EventSource;
@@ -33,10 +37,11 @@ use crate::dom::htmlscriptelement::ScriptId;
 use crate::dom::identityhub::Identities;
 use crate::dom::messageevent::MessageEvent;
 use crate::dom::messageport::MessagePort;
+use crate::dom::node::Node;
 use crate::dom::paintworkletglobalscope::PaintWorkletGlobalScope;
 use crate::dom::performance::Performance;
 use crate::dom::performanceobserver::VALID_ENTRY_TYPES;
-use crate::dom::promise::Promise;
+use crate::dom::promise::{PendingPromise, Promise};
 use crate::dom::window::Window;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::dom::workletglobalscope::WorkletGlobalScope;
@@ -45,9 +50,11 @@ use crate::realms::{enter_realm, InRealm};
 use crate::script_module::ModuleTree;
 use crate::script_runtime::{CommonScriptMsg, JSContext as SafeJSContext, ScriptChan, ScriptPort};
 use crate::script_thread::{MainThreadScriptChan, ScriptThread};
+use crate::security_manager::{CSPReportLimiter, CSPViolationReporter, ReportingEndpoints};
 use crate::task::TaskCanceller;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
+use crate::task_source::javascript_engine::JavaScriptEngineTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::performance_timeline::PerformanceTimelineTaskSource;
 use crate::task_source::port_message::PortMessageQueue;
@@ -58,10 +65,10 @@ use crate::task_source::TaskSource;
 use crate::task_source::TaskSourceName;
 use crate::timers::{IsInterval, OneshotTimerCallback, OneshotTimerHandle};
 use crate::timers::{OneshotTimers, TimerCallback};
-use content_security_policy::CspList;
+use content_security_policy::{CspList, PolicyDisposition, Violation};
 use devtools_traits::{PageError, ScriptToDevtoolsControlMsg};
 use dom_struct::dom_struct;
-use embedder_traits::EmbedderMsg;
+use embedder_traits::{EmbedderMsg, PromptRequest, PromptResponse};
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
 use js::glue::{IsWrapper, UnwrapObjectDynamic};
@@ -91,6 +98,7 @@ use script_traits::{
     ScriptToConstellationChan, TimerEvent,
 };
 use script_traits::{TimerEventId, TimerSchedulerMsg, TimerSource};
+use servo_config::user_agent::user_agent_for_host;
 use servo_url::{MutableOrigin, ServoUrl};
 use std::borrow::Cow;
 use std::cell::{Cell, RefCell, RefMut};
@@ -99,10 +107,11 @@ use std::collections::{HashMap, VecDeque};
 use std::ffi::CString;
 use std::marker::PhantomData;
 use std::mem;
-use std::ops::Index;
+use std::ops::{Index, Range};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use time::{get_time, Timespec};
 use uuid::Uuid;
 
@@ -119,6 +128,7 @@ impl Drop for AutoCloseWorker {
 pub struct GlobalScope {
     eventtarget: EventTarget,
     crypto: MutNullableDom<Crypto>,
+    caches: MutNullableDom<CacheStorage>,
 
     /// The message-port router id for this global, if it is managing ports.
     message_port_state: DomRefCell<MessagePortState>,
@@ -139,6 +149,12 @@ pub struct GlobalScope {
     /// Timers used by the Console API.
     console_timers: DomRefCell<HashMap<DOMString, u64>>,
 
+    /// Counters used by the Console API.
+    console_counters: DomRefCell<HashMap<DOMString, usize>>,
+
+    /// The labels of the groups opened by the Console API.
+    console_groups: DomRefCell<Vec<DOMString>>,
+
     /// module map is used when importing JavaScript modules
     /// https://html.spec.whatwg.org/multipage/#concept-settings-object-module-map
     #[ignore_malloc_size_of = "mozjs"]
@@ -221,18 +237,31 @@ pub struct GlobalScope {
     #[ignore_malloc_size_of = "mozjs"]
     consumed_rejections: DomRefCell<Vec<Box<Heap<*mut JSObject>>>>,
 
+    /// The cleanup jobs of finalization registries enqueued by SpiderMonkey, which
+    /// are run together by a single task on the JavaScript engine task source.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#hostenqueuefinalizationregistrycleanupjob>
+    #[ignore_malloc_size_of = "Rc<T> is hard"]
+    finalization_registry_cleanup_jobs: DomRefCell<Vec<Rc<VoidFunction>>>,
+
     /// True if headless mode.
     is_headless: bool,
 
     /// An optional string allowing the user agent to be set for testing.
     user_agent: Cow<'static, str>,
 
+    /// True if the global belongs to a private browsing session.
+    is_private: bool,
+
     #[ignore_malloc_size_of = "defined in wgpu"]
     gpu_id_hub: RefCell<Identities>,
 
     // https://w3c.github.io/performance-timeline/#supportedentrytypes-attribute
     #[ignore_malloc_size_of = "mozjs"]
     frozen_supported_performance_entry_types: DomRefCell<Option<Heap<JSVal>>>,
+
+    /// The limiter of the violation reports which this global sends.
+    csp_report_limiter: DomRefCell<CSPReportLimiter>,
 }
 
 /// A wrapper for glue-code between the ipc router and the event-loop.
@@ -262,15 +291,17 @@ struct FileListener {
     /// - Some(Empty) => Some(Receiving) => None
     /// - Some(Empty) => None
     state: Option<FileListenerState>,
-    task_source: FileReadingTaskSource,
-    task_canceller: TaskCanceller,
 }
 
 struct FileListenerCallback(Box<dyn Fn(Rc<Promise>, Result<Vec<u8>, Error>) + Send>);
 
 enum FileListenerState {
-    Empty(FileListenerCallback, TrustedPromise),
-    Receiving(Vec<u8>, FileListenerCallback, TrustedPromise),
+    Empty(FileListenerCallback, PendingPromise<FileReadingTaskSource>),
+    Receiving(
+        Vec<u8>,
+        FileListenerCallback,
+        PendingPromise<FileReadingTaskSource>,
+    ),
 }
 
 #[derive(JSTraceable, MallocSizeOf)]
@@ -294,6 +325,24 @@ pub struct BlobInfo {
     has_url: bool,
 }
 
+/// The contents of a blob, as returned by `GlobalScope::get_blob_contents`.
+pub enum BlobContents {
+    /// The bytes of a memory-backed blob, or of a file-backed one whose data is cached.
+    Bytes(Vec<u8>),
+    /// The chunks of a file, or of a slice of it, as the file manager reads them from disk.
+    File(profile_ipc::IpcReceiver<FileManagerResult<ReadFileProgress>>),
+}
+
+impl BlobContents {
+    /// Get the bytes of the contents, waiting for the file manager to read them from disk.
+    pub fn into_bytes(self) -> Result<Vec<u8>, ()> {
+        match self {
+            BlobContents::Bytes(bytes) => Ok(bytes),
+            BlobContents::File(receiver) => GlobalScope::read_msg(receiver),
+        }
+    }
+}
+
 /// State representing whether this global is currently managing blobs.
 #[derive(JSTraceable, MallocSizeOf)]
 pub enum BlobState {
@@ -504,32 +553,23 @@ impl FileListener {
                 ),
             },
             Ok(ReadFileProgress::EOF) => match self.state.take() {
-                Some(FileListenerState::Receiving(bytes, callback, trusted_promise)) => {
-                    let _ = self.task_source.queue_with_canceller(
-                        task!(resolve_promise: move || {
-                            let promise = trusted_promise.root();
-                            let _ac = enter_realm(&*promise.global());
-                            callback.0(promise, Ok(bytes));
-                        }),
-                        &self.task_canceller,
-                    );
+                Some(FileListenerState::Receiving(bytes, callback, promise)) => {
+                    promise.settle_with(move |promise| {
+                        let _ac = enter_realm(&*promise.global());
+                        callback.0(promise, Ok(bytes));
+                    });
                 },
                 _ => {
                     panic!("Unexpected FileListenerState when receiving ReadFileProgress::EOF msg.")
                 },
             },
             Err(_) => match self.state.take() {
-                Some(FileListenerState::Receiving(_, callback, trusted_promise)) |
-                Some(FileListenerState::Empty(callback, trusted_promise)) => {
-                    let bytes = Err(Error::Network);
-                    let _ = self.task_source.queue_with_canceller(
-                        task!(reject_promise: move || {
-                            let promise = trusted_promise.root();
-                            let _ac = enter_realm(&*promise.global());
-                            callback.0(promise, bytes);
-                        }),
-                        &self.task_canceller,
-                    );
+                Some(FileListenerState::Receiving(_, callback, promise)) |
+                Some(FileListenerState::Empty(callback, promise)) => {
+                    promise.settle_with(move |promise| {
+                        let _ac = enter_realm(&*promise.global());
+                        callback.0(promise, Err(Error::Network));
+                    });
                 },
                 _ => panic!("Unexpected FileListenerState when receiving Err msg."),
             },
@@ -550,6 +590,7 @@ impl GlobalScope {
         microtask_queue: Rc<MicrotaskQueue>,
         is_headless: bool,
         user_agent: Cow<'static, str>,
+        is_private: bool,
     ) -> Self {
         Self {
             message_port_state: DomRefCell::new(MessagePortState::UnManaged),
@@ -557,9 +598,12 @@ impl GlobalScope {
             blob_state: DomRefCell::new(BlobState::UnManaged),
             eventtarget: EventTarget::new_inherited(),
             crypto: Default::default(),
+            caches: Default::default(),
             pipeline_id,
             devtools_wants_updates: Default::default(),
             console_timers: DomRefCell::new(Default::default()),
+            console_counters: DomRefCell::new(Default::default()),
+            console_groups: DomRefCell::new(Default::default()),
             module_map: DomRefCell::new(Default::default()),
             inline_module_map: DomRefCell::new(Default::default()),
             devtools_chan,
@@ -578,10 +622,13 @@ impl GlobalScope {
             event_source_tracker: DOMTracker::new(),
             uncaught_rejections: Default::default(),
             consumed_rejections: Default::default(),
+            finalization_registry_cleanup_jobs: Default::default(),
             is_headless,
             user_agent,
+            is_private,
             gpu_id_hub: RefCell::new(Identities::new()),
             frozen_supported_performance_entry_types: DomRefCell::new(Default::default()),
+            csp_report_limiter: DomRefCell::new(CSPReportLimiter::new()),
         }
     }
 
@@ -1374,30 +1421,104 @@ impl GlobalScope {
 
     /// Get a slice to the inner data of a Blob,
     /// In the case of a File-backed blob, this might incur synchronous read and caching.
+    /// Only the slice is read from an uncached file backing a sliced blob.
     pub fn get_blob_bytes(&self, blob_id: &BlobId) -> Result<Vec<u8>, ()> {
-        let parent = {
+        match self.get_blob_parent(blob_id) {
+            Some((parent_id, rel_pos)) => match self.get_uncached_file_id(&parent_id) {
+                Some(file_id) => self.read_file(file_id, rel_pos),
+                None => self.get_blob_bytes_non_sliced(&parent_id).map(|v| {
+                    let range = rel_pos.to_abs_range(v.len());
+                    v.index(range).to_vec()
+                }),
+            },
+            None => self.get_blob_bytes_non_sliced(blob_id),
+        }
+    }
+
+    /// Get the contents of a Blob, without reading the file backing a File-backed blob,
+    /// or a slice of one, when its data is not cached: its chunks are then received
+    /// as they are read from disk.
+    pub fn get_blob_contents(&self, blob_id: &BlobId) -> Result<BlobContents, ()> {
+        let (non_sliced_id, rel_pos) = self
+            .get_blob_parent(blob_id)
+            .unwrap_or_else(|| (blob_id.clone(), RelativePos::full_range()));
+        match self.get_uncached_file_id(&non_sliced_id) {
+            Some(file_id) => Ok(BlobContents::File(self.send_msg(file_id, rel_pos))),
+            None => self.get_blob_bytes(blob_id).map(BlobContents::Bytes),
+        }
+    }
+
+    /// Get the contents of a range of the bytes of a Blob, such as the next chunk of its
+    /// stream: only that range is copied from memory, or read from the backing file.
+    pub fn get_blob_range_contents(
+        &self,
+        blob_id: &BlobId,
+        range: Range<usize>,
+    ) -> Result<BlobContents, ()> {
+        let (non_sliced_id, offset) = match self.get_blob_parent(blob_id) {
+            Some((parent_id, rel_pos)) => {
+                let parent_size = self.get_blob_size(&parent_id) as usize;
+                let offset = rel_pos.to_abs_range(parent_size).start;
+                (parent_id, offset)
+            },
+            None => (blob_id.clone(), 0),
+        };
+        let range = (offset + range.start)..(offset + range.end);
+        let file_id = {
             let blob_state = self.blob_state.borrow();
             if let BlobState::Managed(blobs_map) = &*blob_state {
                 let blob_info = blobs_map
-                    .get(blob_id)
-                    .expect("get_blob_bytes for an unknown blob.");
+                    .get(&non_sliced_id)
+                    .expect("get_blob_range_contents for an unknown blob.");
                 match blob_info.blob_impl.blob_data() {
-                    BlobData::Sliced(ref parent, ref rel_pos) => {
-                        Some((parent.clone(), rel_pos.clone()))
+                    BlobData::File(ref f) => f.get_id(),
+                    BlobData::Memory(ref bytes) => {
+                        return bytes
+                            .get(range)
+                            .map(|bytes| BlobContents::Bytes(bytes.to_vec()))
+                            .ok_or(());
                     },
-                    _ => None,
+                    BlobData::Sliced(_, _) => panic!("This blob doesn't have a parent."),
                 }
             } else {
-                panic!("get_blob_bytes called on a global not managing any blobs.");
+                panic!("get_blob_range_contents called on a global not managing any blobs.");
             }
         };
+        let rel_pos = RelativePos::from_opts(Some(range.start as i64), Some(range.end as i64));
+        Ok(BlobContents::File(self.send_msg(file_id, rel_pos)))
+    }
 
-        match parent {
-            Some((parent_id, rel_pos)) => self.get_blob_bytes_non_sliced(&parent_id).map(|v| {
-                let range = rel_pos.to_abs_range(v.len());
-                v.index(range).to_vec()
-            }),
-            None => self.get_blob_bytes_non_sliced(blob_id),
+    /// Get the parent of a sliced blob, and the position of the slice in it.
+    fn get_blob_parent(&self, blob_id: &BlobId) -> Option<(BlobId, RelativePos)> {
+        let blob_state = self.blob_state.borrow();
+        if let BlobState::Managed(blobs_map) = &*blob_state {
+            let blob_info = blobs_map
+                .get(blob_id)
+                .expect("get_blob_parent for an unknown blob.");
+            match blob_info.blob_impl.blob_data() {
+                BlobData::Sliced(ref parent, ref rel_pos) => {
+                    Some((parent.clone(), rel_pos.clone()))
+                },
+                _ => None,
+            }
+        } else {
+            panic!("get_blob_parent called on a global not managing any blobs.");
+        }
+    }
+
+    /// Get the id of the file backing a non-sliced File-backed blob, if its data is not cached.
+    fn get_uncached_file_id(&self, blob_id: &BlobId) -> Option<Uuid> {
+        let blob_state = self.blob_state.borrow();
+        if let BlobState::Managed(blobs_map) = &*blob_state {
+            let blob_info = blobs_map
+                .get(blob_id)
+                .expect("get_uncached_file_id for an unknown blob.");
+            match blob_info.blob_impl.blob_data() {
+                BlobData::File(ref f) if f.get_cache().is_none() => Some(f.get_id()),
+                _ => None,
+            }
+        } else {
+            panic!("get_uncached_file_id called on a global not managing any blobs.");
         }
     }
 
@@ -1413,7 +1534,7 @@ impl GlobalScope {
                     let (buffer, is_new_buffer) = match f.get_cache() {
                         Some(bytes) => (bytes, false),
                         None => {
-                            let bytes = self.read_file(f.get_id())?;
+                            let bytes = self.read_file(f.get_id(), RelativePos::full_range())?;
                             (bytes, true)
                         },
                     };
@@ -1632,8 +1753,8 @@ impl GlobalScope {
         let _ = resource_threads.send(CoreResourceMsg::ToFileManager(msg));
     }
 
-    fn read_file(&self, id: Uuid) -> Result<Vec<u8>, ()> {
-        let recv = self.send_msg(id);
+    fn read_file(&self, id: Uuid, rel_pos: RelativePos) -> Result<Vec<u8>, ()> {
+        let recv = self.send_msg(id, rel_pos);
         GlobalScope::read_msg(recv)
     }
 
@@ -1643,19 +1764,19 @@ impl GlobalScope {
         promise: Rc<Promise>,
         callback: Box<dyn Fn(Rc<Promise>, Result<Vec<u8>, Error>) + Send>,
     ) {
-        let recv = self.send_msg(id);
+        let recv = self.send_msg(id, RelativePos::full_range());
 
-        let trusted_promise = TrustedPromise::new(promise);
-        let task_canceller = self.task_canceller(TaskSourceName::FileReading);
-        let task_source = self.file_reading_task_source();
+        let promise = PendingPromise::new(
+            &promise,
+            self.file_reading_task_source(),
+            self.task_canceller(TaskSourceName::FileReading),
+        );
 
         let mut file_listener = FileListener {
             state: Some(FileListenerState::Empty(
                 FileListenerCallback(callback),
-                trusted_promise,
+                promise,
             )),
-            task_source,
-            task_canceller,
         };
 
         ROUTER.add_route(
@@ -1669,11 +1790,15 @@ impl GlobalScope {
         );
     }
 
-    fn send_msg(&self, id: Uuid) -> profile_ipc::IpcReceiver<FileManagerResult<ReadFileProgress>> {
+    fn send_msg(
+        &self,
+        id: Uuid,
+        rel_pos: RelativePos,
+    ) -> profile_ipc::IpcReceiver<FileManagerResult<ReadFileProgress>> {
         let resource_threads = self.resource_threads();
         let (chan, recv) = profile_ipc::channel(self.time_profiler_chan().clone()).unwrap();
         let origin = get_blob_origin(&self.get_url());
-        let msg = FileManagerThreadMsg::ReadFile(chan, id, origin);
+        let msg = FileManagerThreadMsg::ReadFile(chan, id, rel_pos, origin);
         let _ = resource_threads.send(CoreResourceMsg::ToFileManager(msg));
         recv
     }
@@ -1809,6 +1934,36 @@ impl GlobalScope {
         &self.consumed_rejections
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#hostenqueuefinalizationregistrycleanupjob>
+    pub fn enqueue_finalization_registry_cleanup_job(&self, job: Rc<VoidFunction>) {
+        let mut jobs = self.finalization_registry_cleanup_jobs.borrow_mut();
+        jobs.push(job);
+        if jobs.len() > 1 {
+            // A task to run the cleanup jobs is already queued.
+            return;
+        }
+        let this = Trusted::new(self);
+        let _ = self.javascript_engine_task_source().queue(
+            task!(cleanup_finalization_registries: move || {
+                this.root().cleanup_finalization_registries();
+            }),
+            self,
+        );
+    }
+
+    /// <https://tc39.es/ecma262/#sec-cleanup-finalization-registry>
+    fn cleanup_finalization_registries(&self) {
+        let jobs = mem::replace(
+            &mut *self.finalization_registry_cleanup_jobs.borrow_mut(),
+            vec![],
+        );
+        for job in jobs {
+            // The call setup enters the realm of the registry, and checks
+            // whether script can run in it.
+            let _ = job.Call_(self, ExceptionHandling::Report);
+        }
+    }
+
     pub fn set_module_map(&self, url: ServoUrl, module: ModuleTree) {
         self.module_map.borrow_mut().insert(url, Rc::new(module));
     }
@@ -1836,6 +1991,10 @@ impl GlobalScope {
         self.crypto.or_init(|| Crypto::new(self))
     }
 
+    pub fn caches(&self) -> DomRoot<CacheStorage> {
+        self.caches.or_init(|| CacheStorage::new(self))
+    }
+
     pub fn live_devtools_updates(&self) -> bool {
         self.devtools_wants_updates.get()
     }
@@ -1858,6 +2017,14 @@ impl GlobalScope {
         }
     }
 
+    pub fn time_log(&self, label: &str) -> Result<u64, ()> {
+        self.console_timers
+            .borrow()
+            .get(label)
+            .ok_or(())
+            .map(|start| timestamp_in_ms(get_time()) - start)
+    }
+
     pub fn time_end(&self, label: &str) -> Result<u64, ()> {
         self.console_timers
             .borrow_mut()
@@ -1866,6 +2033,38 @@ impl GlobalScope {
             .map(|start| timestamp_in_ms(get_time()) - start)
     }
 
+    /// Increment the counter of the Console API with the given label, returning its
+    /// new value.
+    pub fn increment_console_count(&self, label: &DOMString) -> usize {
+        let mut counters = self.console_counters.borrow_mut();
+        let count = counters.entry(label.clone()).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    pub fn reset_console_count(&self, label: &str) -> Result<(), ()> {
+        match self.console_counters.borrow_mut().get_mut(label) {
+            Some(count) => {
+                *count = 0;
+                Ok(())
+            },
+            None => Err(()),
+        }
+    }
+
+    pub fn push_console_group(&self, label: DOMString) {
+        self.console_groups.borrow_mut().push(label);
+    }
+
+    pub fn pop_console_group(&self) {
+        self.console_groups.borrow_mut().pop();
+    }
+
+    /// The number of groups opened by the Console API, which indent the messages.
+    pub fn console_group_depth(&self) -> usize {
+        self.console_groups.borrow().len()
+    }
+
     /// Get an `&IpcSender<ScriptToDevtoolsControlMsg>` to send messages
     /// to the devtools thread when available.
     pub fn devtools_chan(&self) -> Option<&IpcSender<ScriptToDevtoolsControlMsg>> {
@@ -1918,6 +2117,21 @@ impl GlobalScope {
         self.script_to_constellation_chan().send(msg).unwrap();
     }
 
+    /// Ask the user a question, blocking until it is answered. The prompt is only shown
+    /// once the prompts queued before it for the browser have been answered. The event
+    /// loop of a window is paused meanwhile, rather than blocked.
+    pub fn prompt(&self, request: PromptRequest) -> PromptResponse {
+        if self.is::<Window>() {
+            let (sender, receiver) = ipc::channel().unwrap();
+            self.send_to_constellation(ScriptMsg::Prompt(request, sender));
+            let receiver = ROUTER.route_ipc_receiver_to_new_crossbeam_receiver(receiver);
+            return ScriptThread::pause_for_prompt(receiver);
+        }
+        let (sender, receiver) = profile_ipc::channel(self.time_profiler_chan().clone()).unwrap();
+        self.send_to_constellation(ScriptMsg::Prompt(request, sender));
+        receiver.recv().unwrap_or(PromptResponse::Dismiss)
+    }
+
     pub fn scheduler_chan(&self) -> &IpcSender<TimerSchedulerMsg> {
         &self.scheduler_chan
     }
@@ -2112,6 +2326,18 @@ impl GlobalScope {
         unreachable!();
     }
 
+    /// `TaskSource` to send messages to the JavaScript engine task source of
+    /// this global scope.
+    pub fn javascript_engine_task_source(&self) -> JavaScriptEngineTaskSource {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.task_manager().javascript_engine_task_source();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.javascript_engine_task_source();
+        }
+        unreachable!();
+    }
+
     /// `TaskSource` to send messages to the websocket task source of
     /// this global scope.
     pub fn websocket_task_source(&self) -> WebsocketTaskSource {
@@ -2252,6 +2478,10 @@ impl GlobalScope {
         self.timers.speed_up();
     }
 
+    pub fn time_until_next_timer(&self) -> Option<Duration> {
+        self.timers.time_until_next_timer()
+    }
+
     fn timer_source(&self) -> TimerSource {
         if self.is::<Window>() {
             return TimerSource::FromWindow(self.pipeline_id());
@@ -2279,6 +2509,11 @@ impl GlobalScope {
 
     /// Perform a microtask checkpoint.
     pub fn perform_a_microtask_checkpoint(&self) {
+        // The windows share the microtask queue of their script thread, whose
+        // microtasks must run in their own globals.
+        if self.is::<Window>() {
+            return ScriptThread::perform_a_microtask_checkpoint();
+        }
         self.microtask_queue.checkpoint(
             self.get_cx(),
             |_| Some(DomRoot::from_ref(self)),
@@ -2437,6 +2672,17 @@ impl GlobalScope {
         self.user_agent.clone()
     }
 
+    /// The user agent string which is exposed to script, which embedders may override for
+    /// the host of the origin of the global.
+    pub fn user_agent_for_origin(&self) -> Cow<str> {
+        let host = self.origin().host().map(|host| host.to_string());
+        user_agent_for_host(host.as_deref(), &self.user_agent)
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.is_private
+    }
+
     /// https://www.w3.org/TR/CSP/#get-csp-of-object
     pub fn get_csp_list(&self) -> Option<CspList> {
         if let Some(window) = self.downcast::<Window>() {
@@ -2446,6 +2692,77 @@ impl GlobalScope {
         None
     }
 
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    ///
+    /// The element which caused the violations, if any, is the target of their events
+    /// while it is connected.
+    pub fn report_csp_violations(&self, violations: Vec<Violation>, element: Option<&Element>) {
+        if violations.is_empty() {
+            return;
+        }
+        // Step 2.
+        let (target, referrer, endpoints) = if let Some(window) = self.downcast::<Window>() {
+            let document = window.Document();
+            let target = match element {
+                Some(element) if element.upcast::<Node>().is_connected() => {
+                    DomRoot::from_ref(element.upcast::<EventTarget>())
+                },
+                _ => DomRoot::from_ref(document.upcast::<EventTarget>()),
+            };
+            (
+                target,
+                String::from(document.Referrer()),
+                document.reporting_endpoints(),
+            )
+        } else {
+            (
+                DomRoot::from_ref(self.upcast::<EventTarget>()),
+                String::new(),
+                ReportingEndpoints::new(),
+            )
+        };
+        // Step 3.
+        for violation in violations {
+            let task =
+                CSPViolationReporter::new(self, &target, &violation, referrer.clone(), &endpoints);
+            let _ = self.dom_manipulation_task_source().queue(task, self);
+        }
+    }
+
+    pub fn csp_report_limiter(&self) -> RefMut<CSPReportLimiter> {
+        self.csp_report_limiter.borrow_mut()
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-wasm-bytes>
+    pub fn can_compile_wasm_bytes(&self) -> bool {
+        let csp_list = match self.get_csp_list() {
+            Some(csp_list) => csp_list,
+            None => return true,
+        };
+        csp_list.0.iter().all(|policy| {
+            if let PolicyDisposition::Report = policy.disposition {
+                return true;
+            }
+            // The source list of `script-src`, or else of `default-src`.
+            let directive = policy
+                .directive_set
+                .iter()
+                .find(|directive| directive.name == "script-src")
+                .or_else(|| {
+                    policy
+                        .directive_set
+                        .iter()
+                        .find(|directive| directive.name == "default-src")
+                });
+            directive.map_or(true, |directive| {
+                directive.value.iter().any(|source| {
+                    source.eq_ignore_ascii_case("'unsafe-eval'") ||
+                        source.eq_ignore_ascii_case("'wasm-unsafe-eval'")
+                })
+            })
+        })
+    }
+
     pub fn wgpu_id_hub(&self) -> RefMut<Identities> {
         self.gpu_id_hub.borrow_mut()
     }
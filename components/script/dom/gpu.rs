@@ -5,18 +5,17 @@
 use crate::dom::bindings::codegen::Bindings::GPUBinding::GPURequestAdapterOptions;
 use crate::dom::bindings::codegen::Bindings::GPUBinding::{self, GPUMethods, GPUPowerPreference};
 use crate::dom::bindings::error::Error;
-use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::gpuadapter::GPUAdapter;
-use crate::dom::promise::Promise;
+use crate::dom::promise::{PendingPromise, Promise};
 use crate::realms::InRealm;
-use crate::task_source::{TaskSource, TaskSourceName};
+use crate::task_source::TaskSourceName;
 use dom_struct::dom_struct;
 use ipc_channel::ipc::{self, IpcSender};
-use ipc_channel::router::ROUTER;
 use js::jsapi::Heap;
 use script_traits::ScriptMsg;
 use std::rc::Rc;
@@ -44,25 +43,6 @@ pub trait AsyncWGPUListener {
     fn handle_response(&self, response: WebGPUResponse, promise: &Rc<Promise>);
 }
 
-struct WGPUResponse<T: AsyncWGPUListener + DomObject> {
-    trusted: TrustedPromise,
-    receiver: Trusted<T>,
-}
-
-impl<T: AsyncWGPUListener + DomObject> WGPUResponse<T> {
-    #[allow(unrooted_must_root)]
-    fn response(self, response: WebGPUResponseResult) {
-        let promise = self.trusted.root();
-        match response {
-            Ok(response) => self.receiver.root().handle_response(response, &promise),
-            Err(error) => promise.reject_error(Error::Type(format!(
-                "Received error from WebGPU thread: {}",
-                error
-            ))),
-        }
-    }
-}
-
 pub fn response_async<T: AsyncWGPUListener + DomObject + 'static>(
     promise: &Rc<Promise>,
     receiver: &T,
@@ -72,32 +52,16 @@ pub fn response_async<T: AsyncWGPUListener + DomObject + 'static>(
     let canceller = receiver
         .global()
         .task_canceller(TaskSourceName::DOMManipulation);
-    let mut trusted = Some(TrustedPromise::new(promise.clone()));
     let trusted_receiver = Trusted::new(receiver);
-    ROUTER.add_route(
+    PendingPromise::new(promise, task_source, canceller).settle_with_message(
         action_receiver.to_opaque(),
-        Box::new(move |message| {
-            let trusted = if let Some(trusted) = trusted.take() {
-                trusted
-            } else {
-                error!("WebGPU callback called twice!");
-                return;
-            };
-
-            let context = WGPUResponse {
-                trusted,
-                receiver: trusted_receiver.clone(),
-            };
-            let result = task_source.queue_with_canceller(
-                task!(process_webgpu_task: move|| {
-                    context.response(message.to().unwrap());
-                }),
-                &canceller,
-            );
-            if let Err(err) = result {
-                error!("Failed to queue GPU listener-task: {:?}", err);
-            }
-        }),
+        move |promise, response: WebGPUResponseResult| match response {
+            Ok(response) => trusted_receiver.root().handle_response(response, &promise),
+            Err(error) => promise.reject_error(Error::Type(format!(
+                "Received error from WebGPU thread: {}",
+                error
+            ))),
+        },
     );
     action_sender
 }
@@ -15,6 +15,7 @@ use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::inheritance::{ElementTypeId, HTMLElementTypeId, NodeTypeId};
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::characterdata::CharacterData;
 use crate::dom::cssstyledeclaration::{CSSModificationAccess, CSSStyleDeclaration, CSSStyleOwner};
 use crate::dom::document::{Document, FocusType};
 use crate::dom::documentfragment::DocumentFragment;
@@ -40,6 +41,7 @@ use std::default::Default;
 use std::rc::Rc;
 use style::attr::AttrValue;
 use style::element_state::*;
+use unicode_bidi::{bidi_class, BidiClass};
 
 #[dom_struct]
 pub struct HTMLElement {
@@ -778,7 +780,8 @@ impl HTMLElement {
     // returns Some if can infer direction by itself or from child nodes
     // returns None if requires to go up to parent
     pub fn directionality(&self) -> Option<String> {
-        let element_direction: &str = &self.Dir();
+        let element_direction = self.Dir().to_ascii_lowercase();
+        let element_direction: &str = &element_direction;
 
         if element_direction == "ltr" {
             return Some("ltr".to_owned());
@@ -807,14 +810,60 @@ impl HTMLElement {
             }
         }
 
-        // TODO(NeverHappened): Implement condition
-        // If the element's dir attribute is in the auto state OR
-        // If the element is a bdi element and the dir attribute is not in a defined state
-        // (i.e. it is not present or has an invalid value)
-        // Requires bdi element implementation (https://html.spec.whatwg.org/multipage/#the-bdi-element)
+        // A bdi element whose dir attribute is missing or invalid is in the auto state.
+        let is_bdi = self.upcast::<Element>().local_name() == &local_name!("bdi");
+        if element_direction == "auto" || is_bdi {
+            if !self.is::<HTMLInputElement>() {
+                return HTMLElement::text_directionality(self.upcast());
+            }
+        }
 
         None
     }
+
+    /// The directionality of the first strong character of the text of the descendants of
+    /// the element, skipping the descendants whose directionality doesn't depend on it, or
+    /// `None` if there is no such character.
+    /// <https://html.spec.whatwg.org/multipage/#auto-directionality>
+    fn text_directionality(node: &Node) -> Option<String> {
+        for child in node.children() {
+            if let Some(element) = child.downcast::<Element>() {
+                let skipped = match *element.local_name() {
+                    local_name!("bdi") |
+                    local_name!("script") |
+                    local_name!("style") |
+                    local_name!("textarea") => element.is::<HTMLElement>(),
+                    _ => false,
+                };
+                let has_dir = element.is::<HTMLElement>() &&
+                    match &*element
+                        .get_string_attribute(&local_name!("dir"))
+                        .to_ascii_lowercase()
+                    {
+                        "ltr" | "rtl" | "auto" => true,
+                        _ => false,
+                    };
+                if skipped || has_dir {
+                    continue;
+                }
+                if let Some(directionality) = HTMLElement::text_directionality(&child) {
+                    return Some(directionality);
+                }
+                continue;
+            }
+            if let Some(text) = child.downcast::<Text>() {
+                let data = text.upcast::<CharacterData>().data();
+                for c in data.chars() {
+                    match bidi_class(c) {
+                        BidiClass::L => return Some("ltr".to_owned()),
+                        BidiClass::R | BidiClass::AL => return Some("rtl".to_owned()),
+                        _ => {},
+                    }
+                }
+            }
+        }
+        None
+    }
 }
 
 impl VirtualMethods for HTMLElement {
@@ -836,6 +885,12 @@ impl VirtualMethods for HTMLElement {
                     DOMString::from(&**attr.value()),
                 );
             },
+            (&local_name!("dir"), AttributeMutation::Set(_)) => {
+                document_from_node(self).add_directionality_override();
+            },
+            (&local_name!("dir"), AttributeMutation::Removed) => {
+                document_from_node(self).directionality_may_have_changed();
+            },
             _ => {},
         }
     }
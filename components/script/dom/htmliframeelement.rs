@@ -54,6 +54,7 @@ bitflags! {
         const ALLOW_SCRIPTS = 0x08;
         const ALLOW_POINTER_LOCK = 0x10;
         const ALLOW_POPUPS = 0x20;
+        const ALLOW_MODALS = 0x40;
     }
 }
 
@@ -129,6 +130,20 @@ impl HTMLIFrameElement {
 
         let document = document_from_node(self);
 
+        // The sandboxed modals flag of the parent document is inherited, and the
+        // `sandbox` attribute sets it unless it has the `allow-modals` keyword.
+        load_data.sandboxed_modals = document.is_sandboxed_from_modals() ||
+            self.sandbox_allowance.get().map_or(false, |allowance| {
+                !allowance.contains(SandboxAllowance::ALLOW_MODALS)
+            });
+        // The navigation flags of the parent document are inherited by the constellation,
+        // which checks them.
+        load_data.sandboxed_navigation = self.is_sandboxed();
+        load_data.sandboxed_top_level_navigation =
+            self.sandbox_allowance.get().map_or(false, |allowance| {
+                !allowance.contains(SandboxAllowance::ALLOW_TOP_NAVIGATION)
+            });
+
         {
             let mut load_blocker = self.load_blocker.borrow_mut();
             // Any oustanding load is finished from the point of view of the blocked
@@ -136,6 +151,13 @@ impl HTMLIFrameElement {
             LoadBlocker::terminate(&mut load_blocker);
         }
 
+        if !matches!(load_data.url.scheme(), "about" | "javascript") &&
+            document.should_frame_navigation_be_blocked(self.upcast(), &load_data.url)
+        {
+            // The frame shows an empty document instead.
+            load_data.url = ServoUrl::parse("about:blank").unwrap();
+        }
+
         if load_data.url.scheme() == "javascript" {
             let window_proxy = self.GetContentWindow();
             if let Some(window_proxy) = window_proxy {
@@ -620,6 +642,7 @@ impl VirtualMethods for HTMLIFrameElement {
                             modes |= match &*token.to_ascii_lowercase() {
                                 "allow-same-origin" => SandboxAllowance::ALLOW_SAME_ORIGIN,
                                 "allow-forms" => SandboxAllowance::ALLOW_FORMS,
+                                "allow-modals" => SandboxAllowance::ALLOW_MODALS,
                                 "allow-pointer-lock" => SandboxAllowance::ALLOW_POINTER_LOCK,
                                 "allow-popups" => SandboxAllowance::ALLOW_POPUPS,
                                 "allow-scripts" => SandboxAllowance::ALLOW_SCRIPTS,
@@ -746,6 +769,12 @@ impl VirtualMethods for HTMLIFrameElement {
             }
         }
 
+        // The window proxy of a document of another origin, which runs in another
+        // script thread, is discarded here too.
+        if let Some(window_proxy) = ScriptThread::find_window_proxy(browsing_context_id) {
+            window_proxy.discard_browsing_context();
+        }
+
         // Resetting the pipeline_id to None is required here so that
         // if this iframe is subsequently re-added to the document
         // the load doesn't think that it's a navigation, but instead
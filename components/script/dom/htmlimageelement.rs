@@ -50,6 +50,7 @@ use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingLi
 use crate::script_thread::ScriptThread;
 use crate::task_source::TaskSource;
 use app_units::{Au, AU_PER_PX};
+use content_security_policy as csp;
 use cssparser::{Parser, ParserInput};
 use dom_struct::dom_struct;
 use euclid::Point2D;
@@ -58,7 +59,7 @@ use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use mime::{self, Mime};
 use msg::constellation_msg::PipelineId;
-use net_traits::image::base::{Image, ImageMetadata};
+use net_traits::image::base::{is_supported_image_mime_type, Image, ImageMetadata};
 use net_traits::image_cache::UsePlaceholder;
 use net_traits::image_cache::{CanRequestImages, CorsStatus, ImageCache, ImageOrMetadataAvailable};
 use net_traits::image_cache::{ImageResponder, ImageResponse, ImageState, PendingImageId};
@@ -76,17 +77,16 @@ use std::default::Default;
 use std::i32;
 use std::mem;
 use std::sync::{Arc, Mutex};
-use style::attr::{
-    parse_double, parse_length, parse_unsigned_integer, AttrValue, LengthOrPercentageOrAuto,
-};
+use style::attr::{parse_length, parse_unsigned_integer, AttrValue, LengthOrPercentageOrAuto};
 use style::context::QuirksMode;
 use style::media_queries::MediaList;
 use style::parser::ParserContext;
-use style::str::is_ascii_digit;
+use style::str::{is_ascii_digit, HTML_SPACE_CHARACTERS};
 use style::stylesheets::{CssRuleType, Origin};
 use style::values::specified::length::{Length, NoCalcLength};
 use style::values::specified::{source_size_list::SourceSizeList, AbsoluteLength};
 use style_traits::ParsingMode;
+use webrender_api::ImageKey;
 
 enum ParseState {
     InDescriptor,
@@ -253,6 +253,11 @@ impl FetchResponseListener for ImageContext {
             .notify_pending_response(self.id, FetchResponseMsg::ProcessResponseEOF(response));
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
@@ -395,9 +400,7 @@ impl HTMLImageElement {
 
         // This is a background load because the load blocker already fulfills the
         // purpose of delaying the document's load event.
-        document
-            .loader_mut()
-            .fetch_async_background(request, action_sender);
+        document.fetch_background(request, action_sender);
     }
 
     // Steps common to when an image has been loaded.
@@ -407,9 +410,12 @@ impl HTMLImageElement {
             width: image.width,
         });
         self.current_request.borrow_mut().final_url = Some(url);
-        self.current_request.borrow_mut().image = Some(image);
+        self.current_request.borrow_mut().image = Some(image.clone());
         self.current_request.borrow_mut().state = State::CompletelyAvailable;
         LoadBlocker::terminate(&mut self.current_request.borrow_mut().blocker);
+        if image.animation.is_some() {
+            window_from_node(self).start_image_animation(self, &image);
+        }
         // Mark the node dirty
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
@@ -447,6 +453,19 @@ impl HTMLImageElement {
                 self.pending_request.borrow_mut().state = State::PartiallyAvailable;
                 (false, false)
             },
+            // Paint the rows of the image received so far, only invalidating the element.
+            (ImageResponse::PartiallyLoaded(image, url), ImageRequestPhase::Current) => {
+                let mut current_request = self.current_request.borrow_mut();
+                current_request.final_url = Some(url);
+                current_request.image = Some(image);
+                current_request.state = State::PartiallyAvailable;
+                self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+                (false, false)
+            },
+            (ImageResponse::PartiallyLoaded(_, _), ImageRequestPhase::Pending) => {
+                self.pending_request.borrow_mut().state = State::PartiallyAvailable;
+                (false, false)
+            },
             (ImageResponse::None, ImageRequestPhase::Current) => {
                 self.abort_request(State::Broken, ImageRequestPhase::Current);
                 (false, true)
@@ -497,6 +516,8 @@ impl HTMLImageElement {
             ImageResponse::MetadataLoaded(meta) => {
                 self.pending_request.borrow_mut().metadata = Some(meta);
             },
+            // The current request stays displayed until the pending one completes.
+            ImageResponse::PartiallyLoaded(_, _) => {},
             ImageResponse::None => {
                 self.abort_request(State::Unavailable, ImageRequestPhase::Pending);
             },
@@ -631,14 +652,17 @@ impl HTMLImageElement {
 
             // Step 4.8
             if let Some(x) = element.get_attribute(&ns!(), &local_name!("type")) {
-                // TODO Handle unsupported mime type
-                let mime = x.value().parse::<Mime>();
-                match mime {
-                    Ok(m) => match m.type_() {
-                        mime::IMAGE => (),
-                        _ => continue,
-                    },
-                    _ => continue,
+                // The parameters of the type are ignored, and an empty type is
+                // treated as if the attribute was absent.
+                let essence = x
+                    .value()
+                    .split(';')
+                    .next()
+                    .unwrap_or("")
+                    .trim_matches(HTML_SPACE_CHARACTERS)
+                    .to_owned();
+                if !essence.is_empty() && !is_supported_image_mime_type(&essence) {
+                    continue;
                 }
             }
 
@@ -1317,6 +1341,41 @@ impl HTMLImageElement {
         useMapElements.map(|mapElem| mapElem.get_area_elements())
     }
 
+    /// The area of the image map of the element at `point`, in client coordinates.
+    pub fn area_at(&self, point: &Point2D<f32>) -> Option<DomRoot<HTMLAreaElement>> {
+        let elements = self.areas()?;
+        let bcr = self.upcast::<Element>().GetBoundingClientRect();
+        let bcr_p = Point2D::new(bcr.X() as f32, bcr.Y() as f32);
+
+        // Walk HTMLAreaElements
+        for element in elements {
+            let shp = match element.get_shape_from_coords() {
+                Some(shape) => shape.absolute_coords(bcr_p),
+                // Areas with malformed coordinates don't hide the areas after them.
+                None => continue,
+            };
+            if shp.hit_test(point) {
+                return Some(element);
+            }
+        }
+        None
+    }
+
+    /// Display a copy of the current image which has a WebRender image of its own, so
+    /// that the frames the element shows can be changed.
+    pub fn set_animated_image(&self, image: Arc<Image>) {
+        self.current_request.borrow_mut().image = Some(image);
+    }
+
+    /// The WebRender image of the image the element currently displays.
+    pub fn current_image_key(&self) -> Option<ImageKey> {
+        self.current_request
+            .borrow()
+            .image
+            .as_ref()
+            .and_then(|image| image.id)
+    }
+
     pub fn same_origin(&self, origin: &MutableOrigin) -> bool {
         if let Some(ref image) = self.current_request.borrow().image {
             return image.cors_status == CorsStatus::Safe;
@@ -1662,12 +1721,6 @@ impl VirtualMethods for HTMLImageElement {
             return;
         }
 
-        let area_elements = self.areas();
-        let elements = match area_elements {
-            Some(x) => x,
-            None => return,
-        };
-
         // Fetch click coordinates
         let mouse_event = match event.downcast::<MouseEvent>() {
             Some(x) => x,
@@ -1678,20 +1731,8 @@ impl VirtualMethods for HTMLImageElement {
             mouse_event.ClientX().to_f32().unwrap(),
             mouse_event.ClientY().to_f32().unwrap(),
         );
-        let bcr = self.upcast::<Element>().GetBoundingClientRect();
-        let bcr_p = Point2D::new(bcr.X() as f32, bcr.Y() as f32);
-
-        // Walk HTMLAreaElements
-        for element in elements {
-            let shape = element.get_shape_from_coords();
-            let shp = match shape {
-                Some(x) => x.absolute_coords(bcr_p),
-                None => return,
-            };
-            if shp.hit_test(&point) {
-                element.activation_behavior(event, self.upcast());
-                return;
-            }
+        if let Some(element) = self.area_at(&point) {
+            element.activation_behavior(event, self.upcast());
         }
     }
 
@@ -1899,42 +1940,38 @@ pub fn parse_a_srcset_attribute(input: &str) -> Vec<ImageSource> {
         let mut density: Option<f64> = None;
         let mut future_compat_h: Option<u32> = None;
         for descriptor in descriptors {
-            let (digits, remaining) =
-                collect_sequence_characters(&descriptor, |c| is_ascii_digit(c) || *c == '.');
-            let valid_non_negative_integer = parse_unsigned_integer(digits.chars());
-            let has_w = remaining == "w";
-            let valid_floating_point = parse_double(digits);
-            let has_x = remaining == "x";
-            let has_h = remaining == "h";
-            if valid_non_negative_integer.is_ok() && has_w {
-                let result = valid_non_negative_integer;
-                error = result.is_err();
-                if width.is_some() || density.is_some() {
-                    error = true;
-                }
-                if let Ok(w) = result {
-                    width = Some(w);
-                }
-            } else if valid_floating_point.is_ok() && has_x {
-                let result = valid_floating_point;
-                error = result.is_err();
-                if width.is_some() || density.is_some() || future_compat_h.is_some() {
-                    error = true;
-                }
-                if let Ok(x) = result {
-                    density = Some(x);
-                }
-            } else if valid_non_negative_integer.is_ok() && has_h {
-                let result = valid_non_negative_integer;
-                error = result.is_err();
-                if density.is_some() || future_compat_h.is_some() {
-                    error = true;
-                }
-                if let Ok(h) = result {
-                    future_compat_h = Some(h);
+            // The last character of a descriptor is its kind, preceded by its value.
+            let (value, kind) = match descriptor.char_indices().last() {
+                Some((index, kind)) => (&descriptor[..index], kind),
+                None => continue,
+            };
+            let valid_non_negative_integer = || {
+                if value.is_empty() || !value.chars().all(|c| is_ascii_digit(&c)) {
+                    return Err(());
                 }
-            } else {
-                error = true;
+                parse_unsigned_integer(value.chars())
+            };
+            match kind {
+                'w' if width.is_none() && density.is_none() => match valid_non_negative_integer() {
+                    Ok(w) if w > 0 => width = Some(w),
+                    _ => error = true,
+                },
+                'x' if width.is_none() && density.is_none() && future_compat_h.is_none() => {
+                    let value = DOMString::from(value);
+                    match value.parse_floating_point_number() {
+                        Ok(x) if x >= 0. && value.is_valid_floating_point_number_string() => {
+                            density = Some(x)
+                        },
+                        _ => error = true,
+                    }
+                },
+                'h' if density.is_none() && future_compat_h.is_none() => {
+                    match valid_non_negative_integer() {
+                        Ok(h) if h > 0 => future_compat_h = Some(h),
+                        _ => error = true,
+                    }
+                },
+                _ => error = true,
             }
         }
         if future_compat_h.is_some() && width.is_none() {
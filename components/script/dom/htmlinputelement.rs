@@ -17,6 +17,7 @@ use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{DomRoot, LayoutDom, MutNullableDom};
 use crate::dom::bindings::str::{DOMString, USVString};
+use crate::dom::clipboardevent::{ClipboardAction, ClipboardEvent};
 use crate::dom::compositionevent::CompositionEvent;
 use crate::dom::document::Document;
 use crate::dom::element::{
@@ -68,6 +69,7 @@ use profile_traits::ipc;
 use script_layout_interface::rpc::TextIndexResponse;
 use script_traits::ScriptToConstellationChan;
 use servo_atoms::Atom;
+use servo_config::locale;
 use std::borrow::ToOwned;
 use std::cell::Cell;
 use std::ops::Range;
@@ -77,10 +79,52 @@ use style::element_state::ElementState;
 use style::str::{split_commas, str_join};
 use unicode_bidi::{bidi_class, BidiClass};
 
-const DEFAULT_SUBMIT_VALUE: &'static str = "Submit";
-const DEFAULT_RESET_VALUE: &'static str = "Reset";
 const PASSWORD_REPLACEMENT_CHAR: char = '●';
 
+/// The primary language subtag of the preferred language of the user, such as `fr`.
+fn user_language() -> String {
+    let language = locale::language();
+    let primary = language.split('-').next().unwrap_or("");
+    primary.to_ascii_lowercase()
+}
+
+/// The labels of the submit and reset buttons without a value, in the preferred
+/// language of the user.
+fn default_button_labels() -> (&'static str, &'static str) {
+    match &*user_language() {
+        "de" => ("Senden", "Zurücksetzen"),
+        "es" => ("Enviar", "Restablecer"),
+        "fr" => ("Envoyer", "Réinitialiser"),
+        "it" => ("Invia", "Reimposta"),
+        "ja" => ("送信", "リセット"),
+        "pt" => ("Enviar", "Redefinir"),
+        "zh" => ("提交", "重置"),
+        _ => ("Submit", "Reset"),
+    }
+}
+
+/// A valid date string, such as `2020-01-31`, in the order and with the separators which
+/// are customary in the preferred language of the user.
+fn localize_date_string(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    let language = locale::language().to_ascii_lowercase();
+    let date = match &*user_language() {
+        "en" if language == "en" || language.starts_with("en-us") => {
+            format!("{}/{}/{}", month, day, year)
+        },
+        "ja" | "zh" => format!("{}/{}/{}", year, month, day),
+        "hu" | "ko" => format!("{}. {}. {}.", year, month, day),
+        "lt" | "sv" => value.to_owned(),
+        "cs" | "da" | "de" | "fi" | "nb" | "pl" | "ru" | "tr" | "uk" => {
+            format!("{}.{}.{}", day, month, year)
+        },
+        "nl" => format!("{}-{}-{}", day, month, year),
+        _ => format!("{}/{}/{}", day, month, year),
+    };
+    Some(date)
+}
+
 #[derive(Clone, Copy, JSTraceable, PartialEq)]
 #[allow(dead_code)]
 #[derive(MallocSizeOf)]
@@ -744,8 +788,8 @@ impl LayoutHTMLInputElementHelpers for LayoutDom<HTMLInputElement> {
             InputType::Checkbox | InputType::Radio => String::new(),
             InputType::File | InputType::Image => String::new(),
             InputType::Button => get_raw_attr_value(self, ""),
-            InputType::Submit => get_raw_attr_value(self, DEFAULT_SUBMIT_VALUE),
-            InputType::Reset => get_raw_attr_value(self, DEFAULT_RESET_VALUE),
+            InputType::Submit => get_raw_attr_value(self, default_button_labels().0),
+            InputType::Reset => get_raw_attr_value(self, default_button_labels().1),
             InputType::Password => {
                 let text = get_raw_textinput_value(self);
                 if !text.is_empty() {
@@ -754,6 +798,14 @@ impl LayoutHTMLInputElementHelpers for LayoutDom<HTMLInputElement> {
                     String::from((*self.unsafe_get()).placeholder.borrow_for_layout().clone())
                 }
             },
+            // The date is only shown as it is edited while the input is focused.
+            InputType::Date
+                if !(*self.unsafe_get()).upcast::<Element>().focus_state() &&
+                    !get_raw_textinput_value(self).is_empty() =>
+            {
+                let text = get_raw_textinput_value(self);
+                localize_date_string(&text).unwrap_or_else(|| String::from(text))
+            },
             _ => {
                 let text = get_raw_textinput_value(self);
                 if !text.is_empty() {
@@ -2270,6 +2322,20 @@ impl VirtualMethods for HTMLInputElement {
                     Nothing => (),
                 }
             }
+        } else if ClipboardAction::from_event_type(&event.type_()).is_some() &&
+            !event.DefaultPrevented() &&
+            self.input_type().is_textual_or_password()
+        {
+            if let Some(clipboard_event) = event.downcast::<ClipboardEvent>() {
+                let copyable = self.input_type() != InputType::Password;
+                if self.selection().handle_clipboard_event(
+                    clipboard_event,
+                    copyable,
+                    self.is_mutable(),
+                ) {
+                    self.update_placeholder_shown_state();
+                }
+            }
         } else if event.type_() == atom!("keypress") &&
             !event.DefaultPrevented() &&
             self.input_type().is_textual_or_password()
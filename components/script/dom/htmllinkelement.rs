@@ -8,6 +8,8 @@ use crate::dom::bindings::codegen::Bindings::DOMTokenListBinding::DOMTokenListBi
 use crate::dom::bindings::codegen::Bindings::HTMLLinkElementBinding;
 use crate::dom::bindings::codegen::Bindings::HTMLLinkElementBinding::HTMLLinkElementMethods;
 use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::cssstylesheet::CSSStyleSheet;
@@ -17,23 +19,38 @@ use crate::dom::element::{
     cors_setting_for_element, reflect_cross_origin_attribute, set_cross_origin_attribute,
 };
 use crate::dom::element::{AttributeMutation, Element, ElementCreator};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlelement::HTMLElement;
+use crate::dom::htmlscriptelement::SCRIPT_JS_MIMES;
 use crate::dom::node::{
     document_from_node, stylesheets_owner_from_node, window_from_node, BindContext, Node,
     UnbindContext,
 };
+use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::stylesheet::StyleSheet as DOMStyleSheet;
 use crate::dom::virtualmethods::VirtualMethods;
+use crate::fetch::create_a_potential_cors_request;
+use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
 use crate::stylesheet_loader::{StylesheetContextSource, StylesheetLoader, StylesheetOwner};
+use content_security_policy as csp;
 use cssparser::{Parser as CssParser, ParserInput};
 use dom_struct::dom_struct;
 use embedder_traits::EmbedderMsg;
 use html5ever::{LocalName, Prefix};
-use net_traits::ReferrerPolicy;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use mime::{self, Mime};
+use msg::constellation_msg::PipelineId;
+use net_traits::request::{CorsSettings, Destination, Initiator, Referrer, RequestBuilder};
+use net_traits::{CoreResourceMsg, FetchMetadata, FetchResponseListener, FilteredMetadata};
+use net_traits::{IpcSend, NetworkError, ReferrerPolicy, ResourceFetchTiming, ResourceTimingType};
 use servo_arc::Arc;
+use servo_url::{ImmutableOrigin, ServoUrl};
 use std::borrow::ToOwned;
 use std::cell::Cell;
 use std::default::Default;
+use std::sync::Mutex;
 use style::attr::AttrValue;
 use style::media_queries::MediaList;
 use style::parser::ParserContext as CssParserContext;
@@ -180,6 +197,95 @@ fn is_favicon(value: &Option<String>) -> bool {
     }
 }
 
+/// <https://html.spec.whatwg.org/multipage/#link-type-prefetch>
+fn is_prefetch(value: &Option<String>) -> bool {
+    match *value {
+        Some(ref value) => value
+            .split(HTML_SPACE_CHARACTERS)
+            .any(|s| s.eq_ignore_ascii_case("prefetch")),
+        None => false,
+    }
+}
+
+/// Whether the value of a `rel` attribute contains a link type, such as `preconnect`,
+/// `dns-prefetch` or `preload`.
+/// <https://html.spec.whatwg.org/multipage/#linkTypes>
+fn has_link_type(value: &Option<String>, link_type: &str) -> bool {
+    match *value {
+        Some(ref value) => value
+            .split(HTML_SPACE_CHARACTERS)
+            .any(|s| s.eq_ignore_ascii_case(link_type)),
+        None => false,
+    }
+}
+
+/// The destination of a preload, if the value of its `as` attribute is a
+/// [potential destination](https://fetch.spec.whatwg.org/#concept-potential-destination).
+/// <https://fetch.spec.whatwg.org/#concept-potential-destination-translate>
+pub(crate) fn translate_preload_destination(value: &str) -> Option<Destination> {
+    match &*value.to_ascii_lowercase() {
+        "fetch" => Some(Destination::None),
+        "audio" => Some(Destination::Audio),
+        "document" => Some(Destination::Document),
+        "embed" => Some(Destination::Embed),
+        "font" => Some(Destination::Font),
+        "image" => Some(Destination::Image),
+        "manifest" => Some(Destination::Manifest),
+        "object" => Some(Destination::Object),
+        "report" => Some(Destination::Report),
+        "script" => Some(Destination::Script),
+        "sharedworker" => Some(Destination::SharedWorker),
+        "style" => Some(Destination::Style),
+        "track" => Some(Destination::Track),
+        "video" => Some(Destination::Video),
+        "worker" => Some(Destination::Worker),
+        "xslt" => Some(Destination::Xslt),
+        _ => None,
+    }
+}
+
+/// Whether the `type` attribute of a preload names a type of resource which can be used
+/// for its destination, since the resources of the other types are never requested.
+fn preload_type_is_supported(destination: Destination, type_: &str) -> bool {
+    let mime = match type_.parse::<Mime>() {
+        Ok(mime) => mime,
+        Err(_) => return false,
+    };
+    let essence = format!("{}/{}", mime.type_(), mime.subtype()).to_ascii_lowercase();
+    match destination {
+        Destination::Script => SCRIPT_JS_MIMES.contains(&&*essence),
+        Destination::Style => essence == "text/css",
+        Destination::Image => mime.type_() == mime::IMAGE,
+        Destination::Font => mime.type_() == mime::FONT || essence.starts_with("application/font"),
+        Destination::Track => essence == "text/vtt",
+        Destination::Audio | Destination::Video => {
+            mime.type_() == mime::AUDIO || mime.type_() == mime::VIDEO
+        },
+        _ => true,
+    }
+}
+
+/// The request of a preload, which the request of the resource it preloads is handed the
+/// response of. <https://html.spec.whatwg.org/multipage/#create-a-link-request>
+pub(crate) fn preload_fetch_request(
+    url: ServoUrl,
+    destination: Destination,
+    cors_setting: Option<CorsSettings>,
+    origin: ImmutableOrigin,
+    pipeline_id: PipelineId,
+    referrer: Referrer,
+    referrer_policy: Option<ReferrerPolicy>,
+    integrity_metadata: String,
+) -> RequestBuilder {
+    create_a_potential_cors_request(url, destination, cors_setting, None)
+        .origin(origin)
+        .pipeline_id(Some(pipeline_id))
+        .referrer(Some(referrer))
+        .referrer_policy(referrer_policy)
+        .integrity_metadata(integrity_metadata)
+        .initiator(Initiator::Preload)
+}
+
 impl VirtualMethods for HTMLLinkElement {
     fn super_type(&self) -> Option<&dyn VirtualMethods> {
         Some(self.upcast::<HTMLElement>() as &dyn VirtualMethods)
@@ -199,6 +305,17 @@ impl VirtualMethods for HTMLLinkElement {
                 } else if is_favicon(&rel) {
                     let sizes = get_attr(self.upcast(), &local_name!("sizes"));
                     self.handle_favicon_url(rel.as_ref().unwrap(), &attr.value(), &sizes);
+                } else if is_prefetch(&rel) {
+                    self.handle_prefetch_url(&attr.value());
+                } else if has_link_type(&rel, "preload") {
+                    self.handle_preload_url(&attr.value());
+                } else {
+                    self.handle_connection_hints(&rel, &attr.value());
+                }
+            },
+            name if *name == LocalName::from("as") && has_link_type(&rel, "preload") => {
+                if let Some(ref href) = get_attr(self.upcast(), &local_name!("href")) {
+                    self.handle_preload_url(href);
                 }
             },
             &local_name!("sizes") => {
@@ -245,6 +362,13 @@ impl VirtualMethods for HTMLLinkElement {
                 Some(ref href) if is_favicon(&rel) => {
                     self.handle_favicon_url(rel.as_ref().unwrap(), href, &sizes);
                 },
+                Some(ref href) if is_prefetch(&rel) => {
+                    self.handle_prefetch_url(href);
+                },
+                Some(ref href) if has_link_type(&rel, "preload") => {
+                    self.handle_preload_url(href);
+                },
+                Some(ref href) => self.handle_connection_hints(&rel, href),
                 _ => {},
             }
         }
@@ -346,6 +470,132 @@ impl HTMLLinkElement {
             Err(e) => debug!("Parsing url {} failed: {}", href, e),
         }
     }
+
+    /// <https://html.spec.whatwg.org/multipage/#link-type-prefetch>
+    fn handle_prefetch_url(&self, href: &str) {
+        let document = document_from_node(self);
+        if document.browsing_context().is_none() || href.is_empty() {
+            return;
+        }
+        let url = match document.base_url().join(href) {
+            Ok(url) => url,
+            Err(e) => {
+                debug!("Parsing url {} failed: {}", href, e);
+                return;
+            },
+        };
+        let cors_setting = cors_setting_for_element(self.upcast());
+        let request = create_a_potential_cors_request(url, Destination::None, cors_setting, None)
+            .origin(document.origin().immutable().clone())
+            .pipeline_id(Some(document.window().pipeline_id()))
+            .referrer(Some(Referrer::ReferrerUrl(document.url())))
+            .referrer_policy(self.referrer_policy().or(document.get_referrer_policy()));
+        document.prefetch(request);
+    }
+
+    /// Resolve the host of the link, and open a connection to its origin for the
+    /// requests which may follow.
+    /// <https://html.spec.whatwg.org/multipage/#link-type-dns-prefetch>
+    /// <https://html.spec.whatwg.org/multipage/#link-type-preconnect>
+    fn handle_connection_hints(&self, rel: &Option<String>, href: &str) {
+        let preconnect = has_link_type(rel, "preconnect");
+        if !preconnect && !has_link_type(rel, "dns-prefetch") {
+            return;
+        }
+        let document = document_from_node(self);
+        if document.browsing_context().is_none() || href.is_empty() {
+            return;
+        }
+        let url = match document.base_url().join(href) {
+            Ok(url) => url,
+            Err(e) => {
+                debug!("Parsing url {} failed: {}", href, e);
+                return;
+            },
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return;
+        }
+        let msg = if preconnect {
+            CoreResourceMsg::Preconnect(url)
+        } else {
+            match url.host_str() {
+                Some(host) => CoreResourceMsg::PrefetchDns(host.to_owned()),
+                None => return,
+            }
+        };
+        let _ = document
+            .window()
+            .upcast::<GlobalScope>()
+            .resource_threads()
+            .send(msg);
+    }
+
+    /// Fetch the resource of the link, whose response is kept for the request of the
+    /// resource it is fetched for.
+    /// <https://html.spec.whatwg.org/multipage/#link-type-preload>
+    fn handle_preload_url(&self, href: &str) {
+        let document = document_from_node(self);
+        if document.browsing_context().is_none() || href.is_empty() {
+            return;
+        }
+        let element = self.upcast::<Element>();
+        let destination = match get_attr(element, &LocalName::from("as")) {
+            Some(ref value) => translate_preload_destination(value),
+            None => None,
+        };
+        let destination = match destination {
+            Some(destination) => destination,
+            None => return,
+        };
+        if let Some(ref type_) = get_attr(element, &local_name!("type")) {
+            if !type_.is_empty() && !preload_type_is_supported(destination, type_) {
+                return;
+            }
+        }
+        let url = match document.base_url().join(href) {
+            Ok(url) => url,
+            Err(e) => {
+                debug!("Parsing url {} failed: {}", href, e);
+                return;
+            },
+        };
+        let integrity_metadata = get_attr(element, &local_name!("integrity")).unwrap_or_default();
+        let request = preload_fetch_request(
+            url.clone(),
+            destination,
+            cors_setting_for_element(element),
+            document.origin().immutable().clone(),
+            document.window().pipeline_id(),
+            Referrer::ReferrerUrl(document.url()),
+            self.referrer_policy().or(document.get_referrer_policy()),
+            integrity_metadata,
+        );
+
+        let context = ::std::sync::Arc::new(Mutex::new(PreloadContext {
+            link: Trusted::new(self),
+            status: Ok(()),
+            url,
+            resource_timing: ResourceFetchTiming::new(ResourceTimingType::Resource),
+        }));
+        let (action_sender, action_receiver) = ipc::channel().unwrap();
+        let (task_source, canceller) = document
+            .window()
+            .task_manager()
+            .networking_task_source_with_canceller();
+        let listener = NetworkListener {
+            context,
+            task_source,
+            canceller: Some(canceller),
+        };
+        ROUTER.add_route(
+            action_receiver.to_opaque(),
+            Box::new(move |message| {
+                listener.notify_fetch(message.to().unwrap());
+            }),
+        );
+        document.fetch_background(request, action_sender);
+    }
 }
 
 impl StylesheetOwner for HTMLLinkElement {
@@ -452,6 +702,23 @@ impl HTMLLinkElementMethods for HTMLLinkElement {
     // https://html.spec.whatwg.org/multipage/#dom-link-target
     make_setter!(SetTarget, "target");
 
+    // https://html.spec.whatwg.org/multipage/#dom-link-as
+    fn As(&self) -> DOMString {
+        let value = self
+            .upcast::<Element>()
+            .get_string_attribute(&LocalName::from("as"));
+        match translate_preload_destination(&value) {
+            Some(_) => DOMString::from(value.to_ascii_lowercase()),
+            None => DOMString::new(),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-link-as
+    fn SetAs(&self, value: DOMString) {
+        self.upcast::<Element>()
+            .set_string_attribute(&LocalName::from("as"), value);
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-link-crossorigin
     fn GetCrossOrigin(&self) -> Option<DOMString> {
         reflect_cross_origin_attribute(self.upcast::<Element>())
@@ -467,3 +734,87 @@ impl HTMLLinkElementMethods for HTMLLinkElement {
         self.get_cssom_stylesheet().map(DomRoot::upcast)
     }
 }
+
+/// The fetch of a preload, which fires the load or error event at its link element.
+struct PreloadContext {
+    /// The link element that initiated the request.
+    link: Trusted<HTMLLinkElement>,
+    /// Indicates whether the request failed, and why.
+    status: Result<(), NetworkError>,
+    /// The initial URL requested.
+    url: ServoUrl,
+    /// Timing object for this resource.
+    resource_timing: ResourceFetchTiming,
+}
+
+impl FetchResponseListener for PreloadContext {
+    fn process_request_body(&mut self) {}
+
+    fn process_request_eof(&mut self) {}
+
+    fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>) {
+        self.status = metadata.and_then(|metadata| {
+            let status = match metadata {
+                FetchMetadata::Unfiltered(m) => m.status,
+                FetchMetadata::Filtered {
+                    filtered: FilteredMetadata::Basic(m),
+                    ..
+                } |
+                FetchMetadata::Filtered {
+                    filtered: FilteredMetadata::Cors(m),
+                    ..
+                } => m.status,
+                // The status of an opaque response isn't exposed.
+                FetchMetadata::Filtered { .. } => return Ok(()),
+            };
+            match status {
+                Some((code, _)) if (200..300).contains(&code) => Ok(()),
+                _ => Err(NetworkError::Internal("HTTP error".to_owned())),
+            }
+        });
+    }
+
+    fn process_response_chunk(&mut self, _chunk: Vec<u8>) {}
+
+    /// <https://html.spec.whatwg.org/multipage/#link-type-preload>
+    fn process_response_eof(&mut self, response: Result<ResourceFetchTiming, NetworkError>) {
+        let link = self.link.root();
+        let event = match response.and(self.status.clone()) {
+            Ok(_) => atom!("load"),
+            Err(_) => atom!("error"),
+        };
+        link.upcast::<EventTarget>().fire_event(event);
+    }
+
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
+    fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
+        &mut self.resource_timing
+    }
+
+    fn resource_timing(&self) -> &ResourceFetchTiming {
+        &self.resource_timing
+    }
+
+    fn submit_resource_timing(&mut self) {
+        network_listener::submit_timing(self)
+    }
+}
+
+impl ResourceTimingListener for PreloadContext {
+    fn resource_timing_information(&self) -> (InitiatorType, ServoUrl) {
+        (
+            InitiatorType::LocalName("link".to_owned()),
+            self.url.clone(),
+        )
+    }
+
+    fn resource_timing_global(&self) -> DomRoot<GlobalScope> {
+        self.link.root().global()
+    }
+}
+
+impl PreInvoke for PreloadContext {}
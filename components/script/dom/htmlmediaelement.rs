@@ -16,7 +16,10 @@ use crate::dom::bindings::codegen::Bindings::MediaErrorBinding::MediaErrorConsta
 use crate::dom::bindings::codegen::Bindings::MediaErrorBinding::MediaErrorMethods;
 use crate::dom::bindings::codegen::Bindings::NavigatorBinding::NavigatorBinding::NavigatorMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeBinding::NodeMethods;
-use crate::dom::bindings::codegen::Bindings::TextTrackBinding::{TextTrackKind, TextTrackMode};
+use crate::dom::bindings::codegen::Bindings::TextTrackBinding::{
+    TextTrackKind, TextTrackMethods, TextTrackMode,
+};
+use crate::dom::bindings::codegen::Bindings::VTTCueBinding::VTTCueMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::InheritTypes::{ElementTypeId, HTMLElementTypeId};
 use crate::dom::bindings::codegen::InheritTypes::{HTMLMediaElementTypeId, NodeTypeId};
@@ -26,7 +29,7 @@ use crate::dom::bindings::codegen::UnionTypes::{
 use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::num::Finite;
-use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{Dom, DomRoot, LayoutDom, MutNullableDom};
 use crate::dom::bindings::str::{DOMString, USVString};
@@ -35,7 +38,7 @@ use crate::dom::document::Document;
 use crate::dom::element::{
     cors_setting_for_element, reflect_cross_origin_attribute, set_cross_origin_attribute,
 };
-use crate::dom::element::{AttributeMutation, Element, ElementCreator};
+use crate::dom::element::{AttributeMutation, CustomElementCreationMode, Element, ElementCreator};
 use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
@@ -43,7 +46,9 @@ use crate::dom::htmlelement::HTMLElement;
 use crate::dom::htmlscriptelement::HTMLScriptElement;
 use crate::dom::htmlsourceelement::HTMLSourceElement;
 use crate::dom::htmlstyleelement::HTMLStyleElement;
+use crate::dom::htmltrackelement::HTMLTrackElement;
 use crate::dom::htmlvideoelement::HTMLVideoElement;
+use crate::dom::mediadevices::get_media_devices;
 use crate::dom::mediaerror::MediaError;
 use crate::dom::mediafragmentparser::MediaFragmentParser;
 use crate::dom::mediastream::MediaStream;
@@ -59,18 +64,22 @@ use crate::dom::url::URL;
 use crate::dom::videotrack::VideoTrack;
 use crate::dom::videotracklist::VideoTrackList;
 use crate::dom::virtualmethods::VirtualMethods;
+use crate::dom::vttcue::VTTCue;
 use crate::fetch::{create_a_potential_cors_request, FetchCanceller};
 use crate::microtask::{Microtask, MicrotaskRunnable};
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
 use crate::realms::InRealm;
 use crate::script_thread::ScriptThread;
 use crate::task_source::TaskSource;
+use content_security_policy as csp;
 use dom_struct::dom_struct;
 use embedder_traits::resources::{self, Resource as EmbedderResource};
-use embedder_traits::{MediaPositionState, MediaSessionEvent, MediaSessionPlaybackState};
+use embedder_traits::{
+    EmbedderMsg, MediaDeviceKind, MediaPositionState, MediaSessionEvent, MediaSessionPlaybackState,
+};
 use euclid::default::Size2D;
 use headers::{ContentLength, ContentRange, HeaderMapExt};
-use html5ever::{LocalName, Prefix};
+use html5ever::{LocalName, Prefix, QualName};
 use http::header::{self, HeaderMap, HeaderValue};
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
@@ -81,7 +90,7 @@ use net_traits::request::{Destination, Referrer};
 use net_traits::{CoreResourceMsg, FetchChannels, FetchMetadata, FetchResponseListener, Metadata};
 use net_traits::{NetworkError, ResourceFetchTiming, ResourceTimingType};
 use script_layout_interface::HTMLMediaData;
-use script_traits::WebrenderIpcSender;
+use script_traits::{ScriptMsg, ScriptToConstellationChan, WebrenderIpcSender};
 use servo_config::pref;
 use servo_media::player::audio::AudioRenderer;
 use servo_media::player::video::{VideoFrame, VideoFrameRenderer};
@@ -95,6 +104,7 @@ use std::mem;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use time::{self, Duration, Timespec};
+use webrender_api::units::DeviceIntSize;
 use webrender_api::{ExternalImageData, ExternalImageId, ExternalImageType, TextureTarget};
 use webrender_api::{ImageData, ImageDescriptor, ImageDescriptorFlags, ImageFormat};
 use webrender_api::{ImageKey, Transaction};
@@ -154,6 +164,9 @@ pub struct MediaFrameRenderer {
     old_frame: Option<ImageKey>,
     very_old_frame: Option<ImageKey>,
     current_frame_holder: Option<FrameHolder>,
+    /// Where to announce the current frame while the video is in picture-in-picture,
+    /// along with the last frame that was announced.
+    picture_in_picture: Option<(ScriptToConstellationChan, Option<(ImageKey, i32, i32)>)>,
 }
 
 impl MediaFrameRenderer {
@@ -165,12 +178,33 @@ impl MediaFrameRenderer {
             old_frame: None,
             very_old_frame: None,
             current_frame_holder: None,
+            picture_in_picture: None,
         }
     }
 
     fn render_poster_frame(&mut self, image: Arc<Image>) {
         if let Some(image_id) = image.id {
             self.current_frame = Some((image_id, image.width as i32, image.height as i32));
+            self.update_picture_in_picture();
+        }
+    }
+
+    /// Tell the embedder about the image to present in the picture-in-picture
+    /// surface, whenever it changed since it was last told.
+    fn update_picture_in_picture(&mut self) {
+        let current_frame = self.current_frame;
+        let (chan, presented_frame) = match self.picture_in_picture {
+            Some((ref chan, ref mut presented_frame)) => (chan, presented_frame),
+            None => return,
+        };
+        if current_frame == *presented_frame {
+            return;
+        }
+        *presented_frame = current_frame;
+        if let Some((image_key, width, height)) = current_frame {
+            let msg =
+                EmbedderMsg::EnterPictureInPicture(image_key, DeviceIntSize::new(width, height));
+            let _ = chan.send(ScriptMsg::ForwardToEmbedder(msg));
         }
     }
 }
@@ -269,6 +303,7 @@ impl VideoFrameRenderer for MediaFrameRenderer {
             },
         }
         self.api.update_resources(txn.resource_updates);
+        self.update_picture_in_picture();
     }
 }
 
@@ -361,6 +396,8 @@ pub struct HTMLMediaElement {
     video_tracks_list: MutNullableDom<VideoTrackList>,
     /// https://html.spec.whatwg.org/multipage/#dom-media-texttracks
     text_tracks_list: MutNullableDom<TextTrackList>,
+    /// The container of the rendered cues of the showing text tracks.
+    text_track_display: MutNullableDom<Element>,
     /// Time of last timeupdate notification.
     #[ignore_malloc_size_of = "Defined in time"]
     next_timeupdate_event: Cell<Timespec>,
@@ -435,6 +472,7 @@ impl HTMLMediaElement {
             audio_tracks_list: Default::default(),
             video_tracks_list: Default::default(),
             text_tracks_list: Default::default(),
+            text_track_display: Default::default(),
             next_timeupdate_event: Cell::new(time::get_time() + Duration::milliseconds(250)),
             current_fetch_context: DomRefCell::new(None),
             id: Cell::new(0),
@@ -484,6 +522,11 @@ impl HTMLMediaElement {
 
     /// https://html.spec.whatwg.org/multipage/#time-marches-on
     fn time_marches_on(&self) {
+        // Step 1-5.
+        if self.update_text_track_cues() {
+            self.update_text_track_display();
+        }
+
         // Step 6.
         if time::get_time() > self.next_timeupdate_event.get() {
             let window = window_from_node(self);
@@ -496,6 +539,198 @@ impl HTMLMediaElement {
         }
     }
 
+    /// Update the active cues of the text tracks of this media element and queue
+    /// the events for the cues that became active or inactive. Returns whether
+    /// any active cue changed.
+    /// <https://html.spec.whatwg.org/multipage/#time-marches-on>
+    fn update_text_track_cues(&self) -> bool {
+        let text_tracks = match self.text_tracks_list.get() {
+            Some(text_tracks) => text_tracks,
+            None => return false,
+        };
+        let current_time = self.playback_position.get();
+        let window = window_from_node(self);
+        let mut changed = false;
+        let mut pause = false;
+        for index in 0..text_tracks.len() {
+            let track = text_tracks.item(index).unwrap();
+            let (entered, exited) = track.update_active_cues(current_time);
+            if entered.is_empty() && exited.is_empty() {
+                continue;
+            }
+            changed = true;
+
+            // Step 10.
+            pause |= exited.iter().any(|cue| cue.pause_on_exit());
+
+            // Step 12-14.
+            let track = Trusted::new(&*track);
+            let entered: Vec<_> = entered.iter().map(|cue| Trusted::new(&**cue)).collect();
+            let exited: Vec<_> = exited.iter().map(|cue| Trusted::new(&**cue)).collect();
+            let _ = window.task_manager().media_element_task_source().queue(
+                task!(fire_cue_events: move || {
+                    for cue in exited {
+                        cue.root().upcast::<EventTarget>().fire_event(atom!("exit"));
+                    }
+                    for cue in entered {
+                        cue.root().upcast::<EventTarget>().fire_event(atom!("enter"));
+                    }
+                    track.root().upcast::<EventTarget>().fire_event(atom!("cuechange"));
+                }),
+                window.upcast(),
+            );
+        }
+
+        if pause && !self.Paused() {
+            self.internal_pause_steps();
+        }
+        changed
+    }
+
+    /// Called when the cues, the mode or the list of the text tracks of this
+    /// media element changed.
+    pub fn text_tracks_changed(&self) {
+        for track_element in self
+            .upcast::<Node>()
+            .children()
+            .filter_map(DomRoot::downcast::<HTMLTrackElement>)
+        {
+            track_element.text_track_mode_changed();
+        }
+        self.update_text_track_cues();
+        self.update_text_track_display();
+    }
+
+    /// Render the active cues of the showing subtitles and captions over the video.
+    /// <https://html.spec.whatwg.org/multipage/#rules-for-updating-the-text-track-rendering>
+    fn update_text_track_display(&self) {
+        if !self.is::<HTMLVideoElement>() {
+            return;
+        }
+
+        let mut cues = vec![];
+        if let Some(text_tracks) = self.text_tracks_list.get() {
+            for index in 0..text_tracks.len() {
+                let track = text_tracks.item(index).unwrap();
+                if track.Mode() != TextTrackMode::Showing {
+                    continue;
+                }
+                match track.Kind() {
+                    TextTrackKind::Subtitles | TextTrackKind::Captions => {},
+                    _ => continue,
+                }
+                cues.extend(
+                    track
+                        .active_cues()
+                        .into_iter()
+                        .filter_map(DomRoot::downcast::<VTTCue>),
+                );
+            }
+        }
+
+        let container = match self.text_track_display.get() {
+            Some(container) => container,
+            None if cues.is_empty() => return,
+            None => match self.create_text_track_display() {
+                Some(container) => container,
+                None => return,
+            },
+        };
+
+        let document = document_from_node(self);
+        let container = container.upcast::<Node>();
+        container.SetTextContent(None);
+        // The cues whose line is automatic are stacked at the bottom of the video, above
+        // the controls.
+        let stack = Element::create(
+            QualName::new(None, ns!(html), local_name!("div")),
+            None,
+            &document,
+            ElementCreator::ScriptCreated,
+            CustomElementCreationMode::Synchronous,
+        );
+        stack.set_string_attribute(&local_name!("class"), DOMString::from("cue-stack"));
+        if let Err(e) = container.AppendChild(stack.upcast()) {
+            warn!("Could not render text track cues {:?}", e);
+            return;
+        }
+        for cue in cues {
+            let (style, stacked) = cue.rendering_style();
+            let parent = if stacked {
+                stack.upcast::<Node>()
+            } else {
+                container
+            };
+            let cue_box = Element::create(
+                QualName::new(None, ns!(html), local_name!("div")),
+                None,
+                &document,
+                ElementCreator::ScriptCreated,
+                CustomElementCreationMode::Synchronous,
+            );
+            cue_box.set_string_attribute(&local_name!("class"), DOMString::from("cue"));
+            cue_box.set_string_attribute(&local_name!("style"), style);
+            let cue_text = Element::create(
+                QualName::new(None, ns!(html), local_name!("span")),
+                None,
+                &document,
+                ElementCreator::ScriptCreated,
+                CustomElementCreationMode::Synchronous,
+            );
+            let result = cue_text
+                .upcast::<Node>()
+                .AppendChild(cue.GetCueAsHTML().upcast())
+                .and_then(|_| cue_box.upcast::<Node>().AppendChild(cue_text.upcast()))
+                .and_then(|_| parent.AppendChild(cue_box.upcast()));
+            if let Err(e) = result {
+                warn!("Could not render text track cue {:?}", e);
+            }
+        }
+        self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+    }
+
+    /// Create the container of the rendered cues in the user agent shadow tree,
+    /// which is shared with the media controls.
+    fn create_text_track_display(&self) -> Option<DomRoot<Element>> {
+        let element = self.upcast::<Element>();
+        let shadow_root = match element.shadow_root() {
+            Some(shadow_root) => shadow_root,
+            None => element.attach_shadow(IsUserAgentWidget::Yes).ok()?,
+        };
+        let document = document_from_node(self);
+
+        let media_controls_style = resources::read_string(EmbedderResource::MediaControlsCSS);
+        let style = HTMLStyleElement::new(
+            local_name!("style"),
+            None,
+            &document,
+            ElementCreator::ScriptCreated,
+        );
+        style
+            .upcast::<Node>()
+            .SetTextContent(Some(DOMString::from(media_controls_style)));
+
+        let container = Element::create(
+            QualName::new(None, ns!(html), local_name!("div")),
+            None,
+            &document,
+            ElementCreator::ScriptCreated,
+            CustomElementCreationMode::Synchronous,
+        );
+        container.set_string_attribute(&local_name!("class"), DOMString::from("cues"));
+
+        let shadow_root = shadow_root.upcast::<Node>();
+        if let Err(e) = shadow_root
+            .AppendChild(style.upcast())
+            .and_then(|_| shadow_root.AppendChild(container.upcast()))
+        {
+            warn!("Could not render text track cues {:?}", e);
+            return None;
+        }
+        self.text_track_display.set(Some(&container));
+        Some(container)
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#internal-pause-steps>
     fn internal_pause_steps(&self) {
         // Step 1.
@@ -548,7 +783,14 @@ impl HTMLMediaElement {
     }
     // https://html.spec.whatwg.org/multipage/#allowed-to-play
     fn is_allowed_to_play(&self) -> bool {
-        true
+        // The autoplay policy of the embedder applies until the user interacts
+        // with the page.
+        let allowed_by_policy = if self.muted.get() || self.volume.get() == 0.0 {
+            pref!(media.autoplay.muted.enabled)
+        } else {
+            pref!(media.autoplay.unmuted.enabled)
+        };
+        allowed_by_policy || window_from_node(self).has_sticky_activation()
     }
 
     // https://html.spec.whatwg.org/multipage/#notify-about-playing
@@ -647,7 +889,11 @@ impl HTMLMediaElement {
             // FIXME(nox): I have no idea what this TODO is about.
 
             // FIXME(nox): Review this block.
-            if self.autoplaying.get() && self.Paused() && self.Autoplay() {
+            if self.autoplaying.get() &&
+                self.Paused() &&
+                self.Autoplay() &&
+                self.is_allowed_to_play()
+            {
                 // Step 1
                 self.paused.set(false);
                 // Step 2
@@ -846,7 +1092,8 @@ impl HTMLMediaElement {
             .origin(document.origin().immutable().clone())
             .pipeline_id(Some(self.global().pipeline_id()))
             .referrer(Some(Referrer::ReferrerUrl(document.url())))
-            .referrer_policy(document.get_referrer_policy());
+            .referrer_policy(document.get_referrer_policy())
+            .csp_list(document.get_csp_list().map(|csp_list| csp_list.clone()));
 
         let mut current_fetch_context = self.current_fetch_context.borrow_mut();
         if let Some(ref mut current_fetch_context) = *current_fetch_context {
@@ -1847,12 +2094,18 @@ impl HTMLMediaElement {
 
     fn render_controls(&self) {
         let element = self.htmlelement.upcast::<Element>();
-        if self.ready_state.get() < ReadyState::HaveMetadata || element.is_shadow_host() {
+        if self.ready_state.get() < ReadyState::HaveMetadata ||
+            self.media_controls_id.borrow().is_some()
+        {
             // Bail out if we have no metadata yet or
             // if we are already showing the controls.
             return;
         }
-        let shadow_root = element.attach_shadow(IsUserAgentWidget::Yes).unwrap();
+        // The shadow root may already host the text track cues.
+        let shadow_root = match element.shadow_root() {
+            Some(shadow_root) => shadow_root,
+            None => element.attach_shadow(IsUserAgentWidget::Yes).unwrap(),
+        };
         let document = document_from_node(self);
         let script = HTMLScriptElement::new(
             local_name!("script"),
@@ -1904,6 +2157,24 @@ impl HTMLMediaElement {
     fn remove_controls(&self) {
         if let Some(id) = self.media_controls_id.borrow_mut().take() {
             document_from_node(self).unregister_media_controls(&id);
+            // Unregistering the controls detached the shadow root, so render
+            // the text track cues again if needed.
+            self.text_track_display.set(None);
+            self.update_text_track_display();
+        }
+    }
+
+    /// Start or stop routing the frames of this media element to the
+    /// picture-in-picture surface of the embedder.
+    pub fn set_picture_in_picture(&self, enabled: bool) {
+        let mut video_renderer = self.video_renderer.lock().unwrap();
+        if enabled {
+            let chan = self.global().script_to_constellation_chan().clone();
+            video_renderer.picture_in_picture = Some((chan, None));
+            video_renderer.update_picture_in_picture();
+        } else if video_renderer.picture_in_picture.take().is_some() {
+            self.global()
+                .send_to_embedder(EmbedderMsg::LeavePictureInPicture);
         }
     }
 
@@ -2108,7 +2379,10 @@ impl HTMLMediaElementMethods for HTMLMediaElement {
     fn Play(&self, comp: InRealm) -> Rc<Promise> {
         let promise = Promise::new_in_current_realm(&self.global(), comp);
         // Step 1.
-        // FIXME(nox): Reject promise if not allowed to play.
+        if !self.is_allowed_to_play() {
+            promise.reject_error(Error::NotAllowed);
+            return promise;
+        }
 
         // Step 2.
         if self
@@ -2343,7 +2617,7 @@ impl HTMLMediaElementMethods for HTMLMediaElement {
     fn TextTracks(&self) -> DomRoot<TextTrackList> {
         let window = window_from_node(self);
         self.text_tracks_list
-            .or_init(|| TextTrackList::new(&window, &[]))
+            .or_init(|| TextTrackList::new(&window, &[], Some(self)))
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-media-addtexttrack
@@ -2399,6 +2673,42 @@ impl HTMLMediaElementMethods for HTMLMediaElement {
 
         Ok(())
     }
+
+    // https://w3c.github.io/mediacapture-output/#dom-htmlmediaelement-sinkid
+    fn SinkId(&self) -> DOMString {
+        // The audio is always played on the default output device.
+        DOMString::new()
+    }
+
+    // https://w3c.github.io/mediacapture-output/#dom-htmlmediaelement-setsinkid
+    fn SetSinkId(&self, sink_id: DOMString, comp: InRealm) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+
+        // Step 6.
+        if *sink_id == *self.SinkId() {
+            promise.resolve_native(&());
+            return promise;
+        }
+
+        // Step 7.
+        let trusted_promise = TrustedPromise::new(promise.clone());
+        get_media_devices(&self.global(), move |devices| {
+            let promise = trusted_promise.root();
+
+            // Step 7.1.
+            let is_known_device = devices.iter().any(|device| {
+                device.kind == MediaDeviceKind::AudioOutput && device.device_id == *sink_id
+            });
+            if !is_known_device {
+                return promise.reject_error(Error::NotFound);
+            }
+
+            // Step 7.2. The players of servo-media cannot play their audio on another
+            // device than the default one.
+            promise.reject_error(Error::NotSupported);
+        });
+        promise
+    }
 }
 
 impl VirtualMethods for HTMLMediaElement {
@@ -2808,6 +3118,11 @@ impl FetchResponseListener for HTMLMediaElementFetchListener {
         }
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
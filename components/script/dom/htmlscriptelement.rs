@@ -30,6 +30,7 @@ use crate::fetch::create_a_potential_cors_request;
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
 use crate::script_module::fetch_inline_module_script;
 use crate::script_module::{fetch_external_module_script, ModuleOwner};
+use crate::speculation_rules::prefetch_speculation_rules;
 use content_security_policy as csp;
 use dom_struct::dom_struct;
 use encoding_rs::Encoding;
@@ -283,6 +284,11 @@ impl FetchResponseListener for ClassicContext {
         document.finish_load(LoadType::Script(self.url.clone()));
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
@@ -423,6 +429,9 @@ impl HTMLScriptElement {
         let script_type = if let Some(ty) = self.get_script_type() {
             ty
         } else {
+            if self.is_speculation_rules() {
+                self.prepare_speculation_rules(was_parser_inserted, &text);
+            }
             // Step 7.
             return;
         };
@@ -887,6 +896,47 @@ impl HTMLScriptElement {
         );
     }
 
+    /// <https://wicg.github.io/nav-speculation/speculation-rules.html#script-element>
+    fn is_speculation_rules(&self) -> bool {
+        self.upcast::<Element>()
+            .get_attribute(&ns!(), &local_name!("type"))
+            .map_or(false, |ty| {
+                ty.value()
+                    .trim_matches(HTML_SPACE_CHARACTERS)
+                    .eq_ignore_ascii_case("speculationrules")
+            })
+    }
+
+    /// The steps of <https://html.spec.whatwg.org/multipage/#prepare-a-script> for the
+    /// speculation rules, which are only inline.
+    /// <https://wicg.github.io/nav-speculation/speculation-rules.html#script-element>
+    fn prepare_speculation_rules(&self, was_parser_inserted: bool, text: &DOMString) {
+        self.already_started.set(true);
+
+        let doc = document_from_node(self);
+        if was_parser_inserted && &*self.parser_document != &*doc {
+            return;
+        }
+        if !doc.is_scripting_enabled() {
+            return;
+        }
+
+        let element = self.upcast::<Element>();
+        if element.has_attribute(&local_name!("src")) {
+            return self.queue_error_event();
+        }
+        if doc.should_elements_inline_type_behavior_be_blocked(
+            &element,
+            csp::InlineCheckType::Script,
+            text,
+        ) == csp::CheckResult::Blocked
+        {
+            return;
+        }
+
+        prefetch_speculation_rules(&doc, text);
+    }
+
     // https://html.spec.whatwg.org/multipage/#prepare-a-script Step 7.
     pub fn get_script_type(&self) -> Option<ScriptType> {
         let element = self.upcast::<Element>();
@@ -13,6 +13,7 @@ use crate::dom::bindings::error::ErrorResult;
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::root::{DomRoot, LayoutDom, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::clipboardevent::{ClipboardAction, ClipboardEvent};
 use crate::dom::compositionevent::CompositionEvent;
 use crate::dom::document::Document;
 use crate::dom::element::RawLayoutElementHelpers;
@@ -582,6 +583,18 @@ impl VirtualMethods for HTMLTextAreaElement {
                     KeyReaction::Nothing => (),
                 }
             }
+        } else if ClipboardAction::from_event_type(&event.type_()).is_some() &&
+            !event.DefaultPrevented()
+        {
+            if let Some(clipboard_event) = event.downcast::<ClipboardEvent>() {
+                let mutable = self.upcast::<Element>().read_write_state();
+                if self
+                    .selection()
+                    .handle_clipboard_event(clipboard_event, true, mutable)
+                {
+                    self.update_placeholder_shown_state();
+                }
+            }
         } else if event.type_() == atom!("keypress") && !event.DefaultPrevented() {
             if event.IsTrusted() {
                 let window = window_from_node(self);
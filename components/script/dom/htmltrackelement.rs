@@ -2,23 +2,50 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::document_loader::LoadType;
+use crate::dom::attr::Attr;
+use crate::dom::bindings::codegen::Bindings::HTMLMediaElementBinding::HTMLMediaElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLTrackElementBinding::{
     self, HTMLTrackElementConstants, HTMLTrackElementMethods,
 };
+use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeBinding::NodeMethods;
+use crate::dom::bindings::codegen::Bindings::TextTrackBinding::{
+    TextTrackKind, TextTrackMethods, TextTrackMode,
+};
 use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::document::Document;
-use crate::dom::element::Element;
+use crate::dom::element::{cors_setting_for_element, AttributeMutation, Element};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlelement::HTMLElement;
-use crate::dom::node::Node;
+use crate::dom::htmlmediaelement::HTMLMediaElement;
+use crate::dom::node::{document_from_node, window_from_node, BindContext, Node, UnbindContext};
+use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::texttrack::TextTrack;
+use crate::dom::texttrackcue::TextTrackCue;
+use crate::dom::virtualmethods::VirtualMethods;
+use crate::dom::vttcue::VTTCue;
+use crate::dom::webvttparser;
+use crate::fetch::create_a_potential_cors_request;
+use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
+use content_security_policy as csp;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use net_traits::request::{Destination, Referrer};
+use net_traits::{FetchMetadata, FetchResponseListener, NetworkError};
+use net_traits::{ResourceFetchTiming, ResourceTimingType};
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
 #[repr(u16)]
-#[allow(unused)]
 pub enum ReadyState {
     None = HTMLTrackElementConstants::NONE,
     Loading = HTMLTrackElementConstants::LOADING,
@@ -29,8 +56,11 @@ pub enum ReadyState {
 #[dom_struct]
 pub struct HTMLTrackElement {
     htmlelement: HTMLElement,
-    ready_state: ReadyState,
+    ready_state: Cell<ReadyState>,
     track: Dom<TextTrack>,
+    /// Incremented every time a new load of the track is started, so that the
+    /// responses of stale loads can be ignored.
+    generation_id: Cell<u32>,
 }
 
 impl HTMLTrackElement {
@@ -42,8 +72,9 @@ impl HTMLTrackElement {
     ) -> HTMLTrackElement {
         HTMLTrackElement {
             htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
-            ready_state: ReadyState::None,
+            ready_state: Cell::new(ReadyState::None),
             track: Dom::from_ref(&track),
+            generation_id: Cell::new(0),
         }
     }
 
@@ -69,6 +100,194 @@ impl HTMLTrackElement {
             HTMLTrackElementBinding::Wrap,
         )
     }
+
+    fn media_element(&self) -> Option<DomRoot<HTMLMediaElement>> {
+        self.upcast::<Node>()
+            .GetParentNode()
+            .and_then(DomRoot::downcast::<HTMLMediaElement>)
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#text-track-kind>
+    fn track_kind(&self) -> TextTrackKind {
+        match &*self.Kind() {
+            "subtitles" => TextTrackKind::Subtitles,
+            "captions" => TextTrackKind::Captions,
+            "descriptions" => TextTrackKind::Descriptions,
+            "chapters" => TextTrackKind::Chapters,
+            _ => TextTrackKind::Metadata,
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#start-the-track-processing-model>
+    fn start_track_processing(&self, media_element: &HTMLMediaElement) {
+        let text_tracks = media_element.TextTracks();
+        if text_tracks.find(&self.track).is_some() {
+            return;
+        }
+        text_tracks.add(&self.track);
+
+        // https://html.spec.whatwg.org/multipage/#honor-user-preferences-for-automatic-text-track-selection
+        if self.Default() {
+            let mode = match self.track.Kind() {
+                TextTrackKind::Metadata => TextTrackMode::Hidden,
+                _ => TextTrackMode::Showing,
+            };
+            self.track.SetMode(mode);
+        }
+
+        self.text_track_mode_changed();
+    }
+
+    /// Start fetching the track the first time that its mode isn't "disabled".
+    /// <https://html.spec.whatwg.org/multipage/#sourcing-out-of-band-text-tracks>
+    pub fn text_track_mode_changed(&self) {
+        if self.ready_state.get() == ReadyState::None &&
+            self.track.Mode() != TextTrackMode::Disabled
+        {
+            self.load();
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#sourcing-out-of-band-text-tracks>
+    fn load(&self) {
+        let media_element = match self.media_element() {
+            Some(media_element) => media_element,
+            None => return,
+        };
+        self.generation_id.set(self.generation_id.get() + 1);
+
+        // Disabled tracks aren't fetched until they are enabled.
+        if self.track.Mode() == TextTrackMode::Disabled {
+            self.ready_state.set(ReadyState::None);
+            return;
+        }
+
+        // Step 10.
+        let element = self.upcast::<Element>();
+        let document = document_from_node(self);
+        let url = match element.get_attribute(&ns!(), &local_name!("src")) {
+            Some(src) => document.base_url().join(&src.value()).ok(),
+            None => None,
+        };
+        let url = match url {
+            Some(url) => url,
+            None => {
+                self.ready_state.set(ReadyState::Error);
+                let window = window_from_node(self);
+                window
+                    .task_manager()
+                    .dom_manipulation_task_source()
+                    .queue_simple_event(self.upcast(), atom!("error"), &window);
+                return;
+            },
+        };
+
+        // Step 12.
+        self.ready_state.set(ReadyState::Loading);
+        let cors_setting = cors_setting_for_element(media_element.upcast());
+        let request =
+            create_a_potential_cors_request(url.clone(), Destination::Track, cors_setting, None)
+                .origin(document.origin().immutable().clone())
+                .pipeline_id(Some(self.global().pipeline_id()))
+                .referrer(Some(Referrer::ReferrerUrl(document.url())))
+                .referrer_policy(document.get_referrer_policy());
+
+        let context = Arc::new(Mutex::new(TrackContext {
+            elem: Trusted::new(self),
+            generation_id: self.generation_id.get(),
+            data: vec![],
+            status: Ok(()),
+            url: url,
+            resource_timing: ResourceFetchTiming::new(ResourceTimingType::Resource),
+        }));
+
+        let (action_sender, action_receiver) = ipc::channel().unwrap();
+        let (task_source, canceller) = document
+            .window()
+            .task_manager()
+            .networking_task_source_with_canceller();
+        let listener = NetworkListener {
+            context,
+            task_source,
+            canceller: Some(canceller),
+        };
+
+        ROUTER.add_route(
+            action_receiver.to_opaque(),
+            Box::new(move |message| {
+                listener.notify_fetch(message.to().unwrap());
+            }),
+        );
+        document.fetch_async(LoadType::Media, request, action_sender);
+    }
+
+    /// Replace the cues of the track with the cues of the fetched WebVTT file.
+    fn process_track_data(&self, data: Result<Vec<u8>, NetworkError>) {
+        let cues = data.ok().and_then(|data| {
+            let data = String::from_utf8_lossy(&data);
+            webvttparser::parse(&data).ok()
+        });
+        let cues = match cues {
+            Some(cues) => cues,
+            None => {
+                self.ready_state.set(ReadyState::Error);
+                self.upcast::<EventTarget>().fire_event(atom!("error"));
+                return;
+            },
+        };
+
+        let cue_list = self.track.get_cues();
+        while let Some(cue) = cue_list.item(0) {
+            let _ = self.track.RemoveCue(&cue);
+        }
+        let global = self.global();
+        for cue in cues {
+            let cue = VTTCue::new_from_webvtt_cue(&global, cue);
+            let _ = self.track.AddCue(cue.upcast::<TextTrackCue>());
+        }
+
+        self.ready_state.set(ReadyState::Loaded);
+        self.upcast::<EventTarget>().fire_event(atom!("load"));
+    }
+}
+
+impl VirtualMethods for HTMLTrackElement {
+    fn super_type(&self) -> Option<&dyn VirtualMethods> {
+        Some(self.upcast::<HTMLElement>() as &dyn VirtualMethods)
+    }
+
+    fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
+        self.super_type().unwrap().attribute_mutated(attr, mutation);
+        match attr.local_name() {
+            &local_name!("kind") => self.track.set_kind(self.track_kind()),
+            &local_name!("label") => self.track.set_label(self.Label()),
+            &local_name!("srclang") => self.track.set_language(self.Srclang()),
+            &local_name!("src") => self.load(),
+            _ => {},
+        }
+    }
+
+    fn bind_to_tree(&self, context: &BindContext) {
+        self.super_type().unwrap().bind_to_tree(context);
+        if let Some(media_element) = self.media_element() {
+            self.start_track_processing(&media_element);
+        }
+    }
+
+    fn unbind_from_tree(&self, context: &UnbindContext) {
+        self.super_type().unwrap().unbind_from_tree(context);
+        if let Some(media_element) = context.parent.downcast::<HTMLMediaElement>() {
+            let text_tracks = media_element.TextTracks();
+            if let Some(index) = text_tracks.find(&self.track) {
+                text_tracks.remove(index);
+                self.generation_id.set(self.generation_id.get() + 1);
+                if self.ready_state.get() == ReadyState::Loading {
+                    self.ready_state.set(ReadyState::None);
+                }
+                media_element.text_tracks_changed();
+            }
+        }
+    }
 }
 
 impl HTMLTrackElementMethods for HTMLTrackElement {
@@ -126,7 +345,7 @@ impl HTMLTrackElementMethods for HTMLTrackElement {
 
     // https://html.spec.whatwg.org/multipage/#dom-track-readystate
     fn ReadyState(&self) -> u16 {
-        self.ready_state as u16
+        self.ready_state.get() as u16
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-track-track
@@ -134,3 +353,102 @@ impl HTMLTrackElementMethods for HTMLTrackElement {
         DomRoot::from_ref(&*self.track)
     }
 }
+
+/// The context required for asynchronously loading the WebVTT file of a track element.
+struct TrackContext {
+    /// The element that initiated the request.
+    elem: Trusted<HTMLTrackElement>,
+    /// The generation of the load of the element this request belongs to.
+    generation_id: u32,
+    /// The response body received to date.
+    data: Vec<u8>,
+    /// Indicates whether the request failed, and why.
+    status: Result<(), NetworkError>,
+    /// The initial URL requested.
+    url: ServoUrl,
+    /// Timing object for this resource.
+    resource_timing: ResourceFetchTiming,
+}
+
+impl FetchResponseListener for TrackContext {
+    fn process_request_body(&mut self) {}
+
+    fn process_request_eof(&mut self) {}
+
+    fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>) {
+        let metadata = metadata.ok().map(|meta| match meta {
+            FetchMetadata::Unfiltered(m) => m,
+            FetchMetadata::Filtered { unsafe_, .. } => unsafe_,
+        });
+        let status_code = metadata
+            .as_ref()
+            .and_then(|m| m.status.as_ref().map(|&(code, _)| code))
+            .unwrap_or(0);
+        self.status = match status_code {
+            0 => Err(NetworkError::Internal(
+                "No http status code received".to_owned(),
+            )),
+            200..=299 => Ok(()),
+            _ => Err(NetworkError::Internal(format!(
+                "HTTP error code {}",
+                status_code
+            ))),
+        };
+    }
+
+    fn process_response_chunk(&mut self, mut chunk: Vec<u8>) {
+        if self.status.is_ok() {
+            self.data.append(&mut chunk);
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#sourcing-out-of-band-text-tracks>
+    /// step 12
+    fn process_response_eof(&mut self, response: Result<ResourceFetchTiming, NetworkError>) {
+        let elem = self.elem.root();
+        document_from_node(&*elem).finish_load(LoadType::Media);
+        if elem.generation_id.get() != self.generation_id {
+            return;
+        }
+        let data = response
+            .and(self.status.clone())
+            .map(|_| std::mem::replace(&mut self.data, vec![]));
+        elem.process_track_data(data);
+    }
+
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
+    fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
+        &mut self.resource_timing
+    }
+
+    fn resource_timing(&self) -> &ResourceFetchTiming {
+        &self.resource_timing
+    }
+
+    fn submit_resource_timing(&mut self) {
+        network_listener::submit_timing(self)
+    }
+}
+
+impl ResourceTimingListener for TrackContext {
+    fn resource_timing_information(&self) -> (InitiatorType, ServoUrl) {
+        let initiator_type = InitiatorType::LocalName(
+            self.elem
+                .root()
+                .upcast::<Element>()
+                .local_name()
+                .to_string(),
+        );
+        (initiator_type, self.url.clone())
+    }
+
+    fn resource_timing_global(&self) -> DomRoot<GlobalScope> {
+        self.elem.root().global()
+    }
+}
+
+impl PreInvoke for TrackContext {}
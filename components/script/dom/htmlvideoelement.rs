@@ -5,23 +5,34 @@
 use crate::document_loader::{LoadBlocker, LoadType};
 use crate::dom::attr::Attr;
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLMediaElementBinding::HTMLMediaElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLVideoElementBinding;
 use crate::dom::bindings::codegen::Bindings::HTMLVideoElementBinding::HTMLVideoElementMethods;
+use crate::dom::bindings::codegen::Bindings::VideoTrackListBinding::VideoTrackListMethods;
+use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
 use crate::dom::bindings::reflector::DomObject;
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::document::Document;
 use crate::dom::element::{AttributeMutation, Element};
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlmediaelement::{HTMLMediaElement, ReadyState};
-use crate::dom::node::{document_from_node, window_from_node, Node};
+use crate::dom::node::{document_from_node, window_from_node, Node, UnbindContext};
 use crate::dom::performanceresourcetiming::InitiatorType;
+use crate::dom::pictureinpictureevent::PictureInPictureEvent;
+use crate::dom::pictureinpicturewindow::PictureInPictureWindow;
+use crate::dom::promise::Promise;
 use crate::dom::virtualmethods::VirtualMethods;
 use crate::fetch::FetchCanceller;
 use crate::image_listener::{add_cache_listener_for_element, ImageCacheListener};
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
+use crate::realms::InRealm;
+use crate::task_source::TaskSource;
+use content_security_policy as csp;
 use dom_struct::dom_struct;
 use euclid::default::Size2D;
 use html5ever::{LocalName, Prefix};
@@ -35,9 +46,12 @@ use net_traits::{
     CoreResourceMsg, FetchChannels, FetchMetadata, FetchResponseListener, FetchResponseMsg,
 };
 use net_traits::{NetworkError, ResourceFetchTiming, ResourceTimingType};
+use servo_atoms::Atom;
+use servo_config::pref;
 use servo_media::player::video::VideoFrame;
 use servo_url::ServoUrl;
 use std::cell::Cell;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 const DEFAULT_WIDTH: u32 = 300;
@@ -60,6 +74,8 @@ pub struct HTMLVideoElement {
     /// A copy of the last frame
     #[ignore_malloc_size_of = "VideoFrame"]
     last_frame: DomRefCell<Option<VideoFrame>>,
+    /// The window this video is presented in while it is in picture-in-picture.
+    picture_in_picture_window: MutNullableDom<PictureInPictureWindow>,
 }
 
 impl HTMLVideoElement {
@@ -76,6 +92,7 @@ impl HTMLVideoElement {
             poster_frame_canceller: DomRefCell::new(Default::default()),
             load_blocker: Default::default(),
             last_frame: Default::default(),
+            picture_in_picture_window: Default::default(),
         }
     }
 
@@ -196,7 +213,8 @@ impl HTMLVideoElement {
             .credentials_mode(CredentialsMode::Include)
             .use_url_credentials(true)
             .origin(document.origin().immutable().clone())
-            .pipeline_id(Some(document.global().pipeline_id()));
+            .pipeline_id(Some(document.global().pipeline_id()))
+            .csp_list(document.get_csp_list().map(|csp_list| csp_list.clone()));
 
         // Step 5.
         // This delay must be independent from the ones created by HTMLMediaElement during
@@ -240,6 +258,138 @@ impl HTMLVideoElement {
             ))
             .unwrap();
     }
+
+    /// <https://w3c.github.io/picture-in-picture/#request-pip>
+    fn request_picture_in_picture(&self) -> Fallible<DomRoot<PictureInPictureWindow>> {
+        let document = document_from_node(self);
+        let window = window_from_node(self);
+
+        // Step 1.
+        if !document.PictureInPictureEnabled() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 2.
+        if self.DisablePictureInPicture() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        if self.htmlmediaelement.get_ready_state() == ReadyState::HaveNothing {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 4.
+        if self.htmlmediaelement.VideoTracks().Length() == 0 {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 5.
+        let current = document.GetPictureInPictureElement();
+        if current.is_none() &&
+            pref!(dom.user_activation.required) &&
+            !window.has_transient_activation()
+        {
+            return Err(Error::NotAllowed);
+        }
+
+        // Step 6.
+        if current.as_deref() == Some(self.upcast::<Element>()) {
+            if let Some(picture_in_picture_window) = self.picture_in_picture_window.get() {
+                return Ok(picture_in_picture_window);
+            }
+        }
+
+        // Step 7. Only one video can be in picture-in-picture at a time.
+        if let Some(current) = current.and_then(DomRoot::downcast::<HTMLVideoElement>) {
+            current.leave_picture_in_picture(None);
+        }
+
+        // Step 8-10.
+        let picture_in_picture_window = PictureInPictureWindow::new(
+            &window,
+            self.video_width.get() as i32,
+            self.video_height.get() as i32,
+        );
+        self.picture_in_picture_window
+            .set(Some(&picture_in_picture_window));
+        document.set_picture_in_picture_element(Some(self.upcast()));
+        self.htmlmediaelement.set_picture_in_picture(true);
+
+        // Step 11.
+        let this = Trusted::new(self);
+        let _ = window.task_manager().media_element_task_source().queue(
+            task!(fire_enterpictureinpicture: move || {
+                let this = this.root();
+                if let Some(picture_in_picture_window) = this.picture_in_picture_window.get() {
+                    this.fire_picture_in_picture_event(
+                        atom!("enterpictureinpicture"),
+                        &picture_in_picture_window,
+                    );
+                }
+            }),
+            window.upcast(),
+        );
+
+        Ok(picture_in_picture_window)
+    }
+
+    /// <https://w3c.github.io/picture-in-picture/#exit-pip>
+    pub fn leave_picture_in_picture(&self, promise: Option<Rc<Promise>>) {
+        let window = window_from_node(self);
+
+        // Step 1-2.
+        self.htmlmediaelement.set_picture_in_picture(false);
+        let picture_in_picture_window = match self.picture_in_picture_window.take() {
+            Some(picture_in_picture_window) => picture_in_picture_window,
+            None => {
+                // The element left picture-in-picture in the meantime.
+                if let Some(promise) = promise {
+                    promise.reject_error(Error::InvalidState);
+                }
+                return;
+            },
+        };
+        picture_in_picture_window.close();
+
+        // Step 3.
+        let document = document_from_node(self);
+        if document.GetPictureInPictureElement().as_deref() == Some(self.upcast::<Element>()) {
+            document.set_picture_in_picture_element(None);
+        }
+
+        // Step 4.
+        let this = Trusted::new(self);
+        let picture_in_picture_window = Trusted::new(&*picture_in_picture_window);
+        let promise = promise.map(TrustedPromise::new);
+        let _ = window.task_manager().media_element_task_source().queue(
+            task!(fire_leavepictureinpicture: move || {
+                this.root().fire_picture_in_picture_event(
+                    atom!("leavepictureinpicture"),
+                    &picture_in_picture_window.root(),
+                );
+                if let Some(promise) = promise {
+                    promise.root().resolve_native(&());
+                }
+            }),
+            window.upcast(),
+        );
+    }
+
+    fn fire_picture_in_picture_event(
+        &self,
+        name: Atom,
+        picture_in_picture_window: &PictureInPictureWindow,
+    ) {
+        let event = PictureInPictureEvent::new(
+            &self.global(),
+            name,
+            EventBubbles::Bubbles,
+            EventCancelable::NotCancelable,
+            picture_in_picture_window,
+        );
+        event.upcast::<Event>().fire(self.upcast());
+    }
 }
 
 impl HTMLVideoElementMethods for HTMLVideoElement {
@@ -268,6 +418,42 @@ impl HTMLVideoElementMethods for HTMLVideoElement {
     // For testing purposes only. This is not an event from
     // https://html.spec.whatwg.org/multipage/#dom-video-poster
     event_handler!(postershown, GetOnpostershown, SetOnpostershown);
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-requestpictureinpicture
+    fn RequestPictureInPicture(&self, comp: InRealm) -> Rc<Promise> {
+        let promise = Promise::new_in_current_realm(&self.global(), comp);
+        match self.request_picture_in_picture() {
+            Ok(picture_in_picture_window) => promise.resolve_native(&picture_in_picture_window),
+            Err(error) => promise.reject_error(error),
+        }
+        promise
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-onenterpictureinpicture
+    event_handler!(
+        enterpictureinpicture,
+        GetOnenterpictureinpicture,
+        SetOnenterpictureinpicture
+    );
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-onleavepictureinpicture
+    event_handler!(
+        leavepictureinpicture,
+        GetOnleavepictureinpicture,
+        SetOnleavepictureinpicture
+    );
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-disablepictureinpicture
+    fn DisablePictureInPicture(&self) -> bool {
+        self.upcast::<Element>()
+            .has_attribute(&LocalName::from("disablepictureinpicture"))
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-htmlvideoelement-disablepictureinpicture
+    fn SetDisablePictureInPicture(&self, value: bool) {
+        self.upcast::<Element>()
+            .set_bool_attribute(&LocalName::from("disablepictureinpicture"), value)
+    }
 }
 
 impl VirtualMethods for HTMLVideoElement {
@@ -278,13 +464,28 @@ impl VirtualMethods for HTMLVideoElement {
     fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
         self.super_type().unwrap().attribute_mutated(attr, mutation);
 
-        if let Some(new_value) = mutation.new_value(attr) {
-            match attr.local_name() {
-                &local_name!("poster") => {
+        match attr.local_name() {
+            &local_name!("poster") => {
+                if let Some(new_value) = mutation.new_value(attr) {
                     self.fetch_poster_frame(&new_value);
-                },
-                _ => (),
-            };
+                }
+            },
+            // https://w3c.github.io/picture-in-picture/#disable-pip
+            name if &**name == "disablepictureinpicture" => {
+                if let AttributeMutation::Set(None) = mutation {
+                    self.leave_picture_in_picture(None);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn unbind_from_tree(&self, context: &UnbindContext) {
+        self.super_type().unwrap().unbind_from_tree(context);
+
+        // https://w3c.github.io/picture-in-picture/#remove-video
+        if context.tree_connected {
+            self.leave_picture_in_picture(None);
         }
     }
 }
@@ -358,6 +559,11 @@ impl FetchResponseListener for PosterFrameFetchContext {
             .notify_pending_response(self.id, FetchResponseMsg::ProcessResponseEOF(response));
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
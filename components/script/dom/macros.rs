@@ -485,6 +485,11 @@ macro_rules! global_event_handlers(
         event_handler!(progress, GetOnprogress, SetOnprogress);
         event_handler!(ratechange, GetOnratechange, SetOnratechange);
         event_handler!(reset, GetOnreset, SetOnreset);
+        event_handler!(
+            securitypolicyviolation,
+            GetOnsecuritypolicyviolation,
+            SetOnsecuritypolicyviolation
+        );
         event_handler!(seeked, GetOnseeked, SetOnseeked);
         event_handler!(seeking, GetOnseeking, SetOnseeking);
         event_handler!(select, GetOnselect, SetOnselect);
@@ -690,3 +695,100 @@ macro_rules! optional_root_object_to_js_or_null {
         rval.get()
     }};
 }
+
+/// Implement the methods of `Setlike` for a DOM object storing its entries in
+/// a `DomRefCell<IndexSet<Self::Key>>` field.
+///
+/// ```ignore
+/// impl Setlike for TestBindingSetlike {
+///     type Key = DOMString;
+///     setlike!(self, internal);
+/// }
+/// ```
+#[macro_export]
+macro_rules! setlike {
+    ( $self:ident, $field:ident ) => {
+        fn get_index(&$self, index: u32) -> Option<Self::Key> {
+            $self.$field.borrow().get_index(index as usize).cloned()
+        }
+
+        fn size(&$self) -> u32 {
+            $self.$field.borrow().len() as u32
+        }
+
+        fn add(&$self, key: Self::Key) {
+            $self.$field.borrow_mut().insert(key);
+        }
+
+        fn has(&$self, key: Self::Key) -> bool {
+            $self.$field.borrow().contains(&key)
+        }
+
+        fn clear(&$self) {
+            $self.$field.borrow_mut().clear()
+        }
+
+        fn delete(&$self, key: Self::Key) -> bool {
+            // Removing an entry must not change the order of the other ones.
+            let mut set = $self.$field.borrow_mut();
+            if !set.contains(&key) {
+                return false;
+            }
+            set.retain(|entry| *entry != key);
+            true
+        }
+    };
+}
+
+/// Implement the methods of `Maplike` for a DOM object storing its entries in
+/// a `DomRefCell<IndexMap<Self::Key, Self::Value>>` field.
+///
+/// ```ignore
+/// impl Maplike for TestBindingMaplike {
+///     type Key = DOMString;
+///     type Value = i32;
+///     maplike!(self, internal);
+/// }
+/// ```
+#[macro_export]
+macro_rules! maplike {
+    ( $self:ident, $field:ident ) => {
+        fn get_index(&$self, index: u32) -> Option<(Self::Key, Self::Value)> {
+            $self
+                .$field
+                .borrow()
+                .get_index(index as usize)
+                .map(|(key, value)| (key.clone(), value.clone()))
+        }
+
+        fn get(&$self, key: Self::Key) -> Option<Self::Value> {
+            $self.$field.borrow().get(&key).cloned()
+        }
+
+        fn size(&$self) -> u32 {
+            $self.$field.borrow().len() as u32
+        }
+
+        fn set(&$self, key: Self::Key, value: Self::Value) {
+            $self.$field.borrow_mut().insert(key, value);
+        }
+
+        fn has(&$self, key: Self::Key) -> bool {
+            $self.$field.borrow().contains_key(&key)
+        }
+
+        fn clear(&$self) {
+            $self.$field.borrow_mut().clear()
+        }
+
+        fn delete(&$self, key: Self::Key) -> bool {
+            // Removing an entry must not change the order of the other ones.
+            let mut map = $self.$field.borrow_mut();
+            if !map.contains_key(&key) {
+                return false;
+            }
+            map.retain(|entry, _| *entry != key);
+            true
+        }
+    };
+}
@@ -0,0 +1,68 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::MediaDeviceInfoBinding::{
+    self, MediaDeviceInfoMethods, MediaDeviceKind,
+};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+use embedder_traits::{self, MediaDeviceInfo as DeviceInfo};
+
+#[dom_struct]
+pub struct MediaDeviceInfo {
+    reflector: Reflector,
+    device_id: DOMString,
+    kind: MediaDeviceKind,
+    label: DOMString,
+    group_id: DOMString,
+}
+
+impl MediaDeviceInfo {
+    pub fn new_inherited(device: DeviceInfo) -> MediaDeviceInfo {
+        MediaDeviceInfo {
+            reflector: Reflector::new(),
+            device_id: DOMString::from(device.device_id),
+            kind: match device.kind {
+                embedder_traits::MediaDeviceKind::AudioInput => MediaDeviceKind::Audioinput,
+                embedder_traits::MediaDeviceKind::AudioOutput => MediaDeviceKind::Audiooutput,
+                embedder_traits::MediaDeviceKind::VideoInput => MediaDeviceKind::Videoinput,
+            },
+            label: DOMString::from(device.label),
+            group_id: DOMString::from(device.group_id),
+        }
+    }
+
+    pub fn new(global: &GlobalScope, device: DeviceInfo) -> DomRoot<MediaDeviceInfo> {
+        reflect_dom_object(
+            Box::new(MediaDeviceInfo::new_inherited(device)),
+            global,
+            MediaDeviceInfoBinding::Wrap,
+        )
+    }
+}
+
+impl MediaDeviceInfoMethods for MediaDeviceInfo {
+    // https://w3c.github.io/mediacapture-main/#dom-mediadeviceinfo-deviceid
+    fn DeviceId(&self) -> DOMString {
+        self.device_id.clone()
+    }
+
+    // https://w3c.github.io/mediacapture-main/#dom-mediadeviceinfo-kind
+    fn Kind(&self) -> MediaDeviceKind {
+        self.kind
+    }
+
+    // https://w3c.github.io/mediacapture-main/#dom-mediadeviceinfo-label
+    fn Label(&self) -> DOMString {
+        self.label.clone()
+    }
+
+    // https://w3c.github.io/mediacapture-main/#dom-mediadeviceinfo-groupid
+    fn GroupId(&self) -> DOMString {
+        self.group_id.clone()
+    }
+}
@@ -7,16 +7,23 @@ use crate::dom::bindings::codegen::Bindings::MediaDevicesBinding::{self, MediaDe
 use crate::dom::bindings::codegen::UnionTypes::BooleanOrMediaTrackConstraints;
 use crate::dom::bindings::codegen::UnionTypes::ClampedUnsignedLongOrConstrainULongRange as ConstrainULong;
 use crate::dom::bindings::codegen::UnionTypes::DoubleOrConstrainDoubleRange as ConstrainDouble;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::TrustedPromise;
 use crate::dom::bindings::reflector::reflect_dom_object;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::mediadeviceinfo::MediaDeviceInfo;
 use crate::dom::mediastream::MediaStream;
 use crate::dom::mediastreamtrack::MediaStreamTrack;
 use crate::dom::promise::Promise;
 use crate::realms::InRealm;
+use crate::task_source::{TaskSource, TaskSourceName};
 use dom_struct::dom_struct;
+use embedder_traits::{EmbedderMsg, MediaDeviceInfo as DeviceInfo, MediaDeviceKind};
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
 use servo_media::streams::capture::{Constrain, ConstrainRange, MediaTrackConstraintSet};
 use servo_media::streams::MediaStreamType;
 use servo_media::ServoMedia;
@@ -41,6 +48,48 @@ impl MediaDevices {
             MediaDevicesBinding::Wrap,
         )
     }
+
+    /// Called when the media devices known to the embedder changed.
+    /// <https://w3c.github.io/mediacapture-main/#event-mediadevices-devicechange>
+    pub fn devices_changed(&self) {
+        let window = self.global().as_window();
+        window
+            .task_manager()
+            .dom_manipulation_task_source()
+            .queue_simple_event(self.upcast(), atom!("devicechange"), &window);
+    }
+}
+
+/// Ask the embedder for the media devices that can be exposed to content, and
+/// run `callback` with them in a task of the DOM manipulation task source.
+pub fn get_media_devices<F>(global: &GlobalScope, callback: F)
+where
+    F: FnOnce(Vec<DeviceInfo>) + Send + 'static,
+{
+    let (sender, receiver) = ipc::channel().unwrap();
+    let task_source = global.dom_manipulation_task_source();
+    let canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+    let mut callback = Some(callback);
+    ROUTER.add_route(
+        receiver.to_opaque(),
+        Box::new(move |message| {
+            let devices: Vec<DeviceInfo> = message.to().unwrap();
+            let callback = match callback.take() {
+                Some(callback) => callback,
+                None => return,
+            };
+            let result = task_source.queue_with_canceller(
+                task!(media_devices: move || {
+                    callback(devices);
+                }),
+                &canceller,
+            );
+            if let Err(err) = result {
+                warn!("Failed to queue media devices task: {:?}", err);
+            }
+        }),
+    );
+    global.send_to_embedder(EmbedderMsg::GetMediaDevices(sender));
 }
 
 impl MediaDevicesMethods for MediaDevices {
@@ -66,6 +115,32 @@ impl MediaDevicesMethods for MediaDevices {
         p.resolve_native(&stream);
         p
     }
+
+    // https://w3c.github.io/mediacapture-main/#dom-mediadevices-enumeratedevices
+    fn EnumerateDevices(&self, comp: InRealm) -> Rc<Promise> {
+        let p = Promise::new_in_current_realm(&self.global(), comp);
+        let trusted_promise = TrustedPromise::new(p.clone());
+        get_media_devices(&self.global(), move |devices| {
+            let promise = trusted_promise.root();
+            let global = promise.global();
+            let devices: Vec<DomRoot<MediaDeviceInfo>> = devices
+                .into_iter()
+                .map(|mut device| {
+                    // Capture devices are only identified by their kind, as there is
+                    // no way for content to be granted the permission to use them yet.
+                    if device.kind != MediaDeviceKind::AudioOutput {
+                        device.label.clear();
+                    }
+                    MediaDeviceInfo::new(&global, device)
+                })
+                .collect();
+            promise.resolve_native(&devices);
+        });
+        p
+    }
+
+    // https://w3c.github.io/mediacapture-main/#dom-mediadevices-ondevicechange
+    event_handler!(devicechange, GetOndevicechange, SetOndevicechange);
 }
 
 fn convert_constraints(js: &BooleanOrMediaTrackConstraints) -> Option<MediaTrackConstraintSet> {
@@ -42,11 +42,14 @@ impl MediaQueryList {
     }
 
     pub fn new(document: &Document, media_query_list: MediaList) -> DomRoot<MediaQueryList> {
-        reflect_dom_object(
+        let mql = reflect_dom_object(
             Box::new(MediaQueryList::new_inherited(document, media_query_list)),
             document.window(),
             MediaQueryListBinding::Wrap,
-        )
+        );
+        // Changes are reported relative to the state of the list when it was created.
+        mql.last_match_state.set(Some(mql.evaluate()));
+        mql
     }
 }
 
@@ -82,10 +85,7 @@ impl MediaQueryListMethods for MediaQueryList {
 
     // https://drafts.csswg.org/cssom-view/#dom-mediaquerylist-matches
     fn Matches(&self) -> bool {
-        match self.last_match_state.get() {
-            None => self.evaluate(),
-            Some(state) => state,
-        }
+        self.evaluate()
     }
 
     // https://drafts.csswg.org/cssom-view/#dom-mediaquerylist-addlistener
@@ -240,19 +240,29 @@ pub mod bluetoothremotegattserver;
 pub mod bluetoothremotegattservice;
 pub mod bluetoothuuid;
 pub mod broadcastchannel;
+pub mod bytelengthqueuingstrategy;
+pub mod cache;
+pub mod cachestorage;
 pub mod canvasgradient;
 pub mod canvaspattern;
 pub mod canvasrenderingcontext2d;
+pub mod caretposition;
 pub mod cdatasection;
 pub mod channelmergernode;
 pub mod channelsplitternode;
 pub mod characterdata;
 pub mod client;
+pub mod clients;
+pub mod clipboardevent;
 pub mod closeevent;
 pub mod comment;
 pub mod compositionevent;
+pub mod compressionstream;
 pub mod console;
 pub mod constantsourcenode;
+pub mod cookiechangeevent;
+pub mod cookiestore;
+pub mod countqueuingstrategy;
 mod create;
 pub mod crypto;
 pub mod css;
@@ -274,6 +284,8 @@ pub mod csssupportsrule;
 pub mod cssviewportrule;
 pub mod customelementregistry;
 pub mod customevent;
+pub mod datatransfer;
+pub mod decompressionstream;
 pub mod dedicatedworkerglobalscope;
 pub mod dissimilaroriginlocation;
 pub mod dissimilaroriginwindow;
@@ -412,6 +424,7 @@ pub mod imagedata;
 pub mod inputevent;
 pub mod keyboardevent;
 pub mod location;
+pub mod mediadeviceinfo;
 pub mod mediadevices;
 pub mod mediaelementaudiosourcenode;
 pub mod mediaerror;
@@ -460,6 +473,8 @@ pub mod performancepainttiming;
 pub mod performanceresourcetiming;
 pub mod permissions;
 pub mod permissionstatus;
+pub mod pictureinpictureevent;
+pub mod pictureinpicturewindow;
 pub mod plugin;
 pub mod pluginarray;
 pub mod popstateevent;
@@ -471,7 +486,16 @@ pub mod promiserejectionevent;
 pub mod radionodelist;
 pub mod range;
 pub mod raredata;
+pub mod readablebytestreamcontroller;
+pub mod readablestream;
+pub mod readablestreambyobreader;
+pub mod readablestreambyobrequest;
+pub mod readablestreamdefaultcontroller;
+pub mod readablestreamdefaultreader;
 pub mod request;
+pub mod resizeobserver;
+pub mod resizeobserverentry;
+pub mod resizeobserversize;
 pub mod response;
 pub mod rtcicecandidate;
 pub mod rtcpeerconnection;
@@ -479,6 +503,7 @@ pub mod rtcpeerconnectioniceevent;
 pub mod rtcsessiondescription;
 pub mod rtctrackevent;
 pub mod screen;
+pub mod securitypolicyviolationevent;
 pub mod selection;
 pub mod serviceworker;
 pub mod serviceworkercontainer;
@@ -489,6 +514,7 @@ pub mod shadowroot;
 pub mod stereopannernode;
 pub mod storage;
 pub mod storageevent;
+pub mod streams;
 pub mod stylepropertymapreadonly;
 pub mod stylesheet;
 pub mod stylesheetlist;
@@ -498,15 +524,20 @@ pub mod svggraphicselement;
 pub mod svgsvgelement;
 pub mod testbinding;
 pub mod testbindingiterable;
+pub mod testbindingmaplike;
 pub mod testbindingpairiterable;
 pub mod testbindingproxy;
+pub mod testbindingsetlike;
 pub mod testrunner;
 pub mod testworklet;
 pub mod testworkletglobalscope;
 pub mod text;
 pub mod textcontrol;
 pub mod textdecoder;
+pub mod textdecodercommon;
+pub mod textdecoderstream;
 pub mod textencoder;
+pub mod textencoderstream;
 pub mod textmetrics;
 pub mod texttrack;
 pub mod texttrackcue;
@@ -517,12 +548,15 @@ pub mod touch;
 pub mod touchevent;
 pub mod touchlist;
 pub mod trackevent;
+pub mod transformstream;
+pub mod transformstreamdefaultcontroller;
 pub mod transitionevent;
 pub mod treewalker;
 pub mod uievent;
 pub mod url;
 pub mod urlhelper;
 pub mod urlsearchparams;
+pub mod useractivation;
 pub mod userscripts;
 pub mod validation;
 pub mod validitystate;
@@ -531,6 +565,7 @@ pub mod vertexarrayobject;
 pub mod videotrack;
 pub mod videotracklist;
 pub mod virtualmethods;
+pub mod visualviewport;
 pub mod vrdisplay;
 pub mod vrdisplaycapabilities;
 pub mod vrdisplayevent;
@@ -564,6 +599,8 @@ pub mod webgluniformlocation;
 pub mod webglvertexarrayobject;
 pub mod webglvertexarrayobjectoes;
 pub mod websocket;
+pub mod websocketstream;
+pub mod webvttparser;
 pub mod wheelevent;
 pub mod window;
 pub mod windowproxy;
@@ -573,11 +610,19 @@ pub mod workerlocation;
 pub mod workernavigator;
 pub mod worklet;
 pub mod workletglobalscope;
+pub mod writablestream;
+pub mod writablestreamdefaultcontroller;
+pub mod writablestreamdefaultwriter;
 pub mod xmldocument;
 pub mod xmlhttprequest;
 pub mod xmlhttprequesteventtarget;
 pub mod xmlhttprequestupload;
 pub mod xmlserializer;
+pub mod xpatheval;
+pub mod xpathevaluator;
+pub mod xpathexpression;
+pub mod xpathparser;
+pub mod xpathresult;
 pub mod xrframe;
 pub mod xrinputsource;
 pub mod xrinputsourcearray;
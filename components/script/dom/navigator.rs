@@ -2,12 +2,16 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::NavigatorBinding;
 use crate::dom::bindings::codegen::Bindings::NavigatorBinding::NavigatorMethods;
-use crate::dom::bindings::error::Error;
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::codegen::Bindings::XMLHttpRequestBinding::BodyInit;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::{DomRoot, MutNullableDom};
-use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::bluetooth::Bluetooth;
 use crate::dom::gamepadlist::GamepadList;
 use crate::dom::gpu::GPU;
@@ -19,12 +23,49 @@ use crate::dom::permissions::Permissions;
 use crate::dom::pluginarray::PluginArray;
 use crate::dom::promise::Promise;
 use crate::dom::serviceworkercontainer::ServiceWorkerContainer;
+use crate::dom::useractivation::UserActivation;
 use crate::dom::window::Window;
+use crate::dom::xmlhttprequest::Extractable;
 use crate::dom::xrsystem::XRSystem;
 use crate::realms::InRealm;
+use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
+use http::header::{self, HeaderMap, HeaderValue};
+use hyper::Method;
+use js::jsapi::Heap;
+use js::jsval::JSVal;
+use net_traits::request::{is_cors_safelisted_request_header, CredentialsMode, Referrer};
+use net_traits::request::{RequestBuilder, RequestMode};
+use script_traits::ScriptMsg;
+use servo_url::ServoUrl;
 use std::rc::Rc;
 
+/// The schemes which pages can register handlers for, besides those starting with `web+`.
+///
+/// <https://html.spec.whatwg.org/multipage/#safelisted-scheme>
+const SAFELISTED_SCHEMES: &[&str] = &[
+    "bitcoin",
+    "geo",
+    "im",
+    "irc",
+    "ircs",
+    "magnet",
+    "mailto",
+    "mms",
+    "news",
+    "nntp",
+    "openpgp4fpr",
+    "sip",
+    "sms",
+    "smsto",
+    "ssh",
+    "tel",
+    "urn",
+    "webcal",
+    "wtai",
+    "xmpp",
+];
+
 #[dom_struct]
 pub struct Navigator {
     reflector_: Reflector,
@@ -38,6 +79,10 @@ pub struct Navigator {
     permissions: MutNullableDom<Permissions>,
     mediasession: MutNullableDom<MediaSession>,
     gpu: MutNullableDom<GPU>,
+    user_activation: MutNullableDom<UserActivation>,
+    languages: DomRefCell<Vec<String>>,
+    #[ignore_malloc_size_of = "mozjs"]
+    languages_array: Heap<JSVal>,
 }
 
 impl Navigator {
@@ -54,6 +99,9 @@ impl Navigator {
             permissions: Default::default(),
             mediasession: Default::default(),
             gpu: Default::default(),
+            user_activation: Default::default(),
+            languages: Default::default(),
+            languages_array: Heap::default(),
         }
     }
 
@@ -64,6 +112,54 @@ impl Navigator {
             NavigatorBinding::Wrap,
         )
     }
+
+    /// The media devices of this navigator, if content ever accessed them.
+    pub fn media_devices(&self) -> Option<DomRoot<MediaDevices>> {
+        self.mediadevices.get()
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#normalize-protocol-handler-parameters>
+    fn normalize_protocol_handler_parameters(
+        &self,
+        scheme: DOMString,
+        url: USVString,
+    ) -> Fallible<(String, ServoUrl)> {
+        // The members of NavigatorContentUtils are [SecureContext], which the bindings
+        // don't support.
+        let global = self.global();
+        if !global.as_window().Document().is_secure_context() {
+            return Err(Error::Security);
+        }
+
+        // Step 1.
+        let scheme = scheme.to_ascii_lowercase();
+
+        // Step 2.
+        let is_web_scheme = scheme.starts_with("web+") &&
+            scheme.len() > 4 &&
+            scheme[4..].bytes().all(|byte| byte.is_ascii_lowercase());
+        if !is_web_scheme && !SAFELISTED_SCHEMES.contains(&&*scheme) {
+            return Err(Error::Security);
+        }
+
+        // Step 3.
+        if !url.0.contains("%s") {
+            return Err(Error::Syntax);
+        }
+
+        // Step 4.
+        let url = match global.as_window().Document().base_url().join(&url.0) {
+            Ok(url) => url,
+            Err(_) => return Err(Error::Syntax),
+        };
+
+        // Step 5.
+        if !matches!(url.scheme(), "http" | "https") || !url.origin().same_origin(global.origin()) {
+            return Err(Error::Security);
+        }
+
+        Ok((scheme, url))
+    }
 }
 
 impl NavigatorMethods for Navigator {
@@ -109,7 +205,7 @@ impl NavigatorMethods for Navigator {
 
     // https://html.spec.whatwg.org/multipage/#dom-navigator-useragent
     fn UserAgent(&self) -> DOMString {
-        navigatorinfo::UserAgent(self.global().get_user_agent())
+        navigatorinfo::UserAgent(&self.global().user_agent_for_origin())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-navigator-appversion
@@ -127,6 +223,21 @@ impl NavigatorMethods for Navigator {
         navigatorinfo::Language()
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-navigator-languages
+    fn Languages(&self, cx: JSContext) -> JSVal {
+        navigatorinfo::Languages(cx, &self.languages, &self.languages_array)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-navigator-hardwareconcurrency
+    fn HardwareConcurrency(&self) -> u64 {
+        navigatorinfo::HardwareConcurrency()
+    }
+
+    // https://www.w3.org/TR/device-memory/#dom-navigator-devicememory
+    fn DeviceMemory(&self) -> Finite<f64> {
+        navigatorinfo::DeviceMemory()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-navigator-plugins
     fn Plugins(&self) -> DomRoot<PluginArray> {
         self.plugins.or_init(|| PluginArray::new(&self.global()))
@@ -171,6 +282,12 @@ impl NavigatorMethods for Navigator {
             .or_init(|| Permissions::new(&self.global()))
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-navigator-useractivation
+    fn UserActivation(&self) -> DomRoot<UserActivation> {
+        self.user_activation
+            .or_init(|| UserActivation::new(self.global().as_window()))
+    }
+
     // https://w3c.github.io/webvr/spec/1.1/#navigator-getvrdisplays-attribute
     fn GetVRDisplays(&self, comp: InRealm) -> Rc<Promise> {
         let promise = Promise::new_in_current_realm(&self.global(), comp);
@@ -210,8 +327,81 @@ impl NavigatorMethods for Navigator {
         })
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-navigator-registerprotocolhandler
+    fn RegisterProtocolHandler(
+        &self,
+        scheme: DOMString,
+        url: USVString,
+        _title: DOMString,
+    ) -> ErrorResult {
+        let (scheme, url) = self.normalize_protocol_handler_parameters(scheme, url)?;
+        let msg = ScriptMsg::RegisterProtocolHandler(scheme, url);
+        let _ = self.global().script_to_constellation_chan().send(msg);
+        Ok(())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-navigator-unregisterprotocolhandler
+    fn UnregisterProtocolHandler(&self, scheme: DOMString, url: USVString) -> ErrorResult {
+        let (scheme, url) = self.normalize_protocol_handler_parameters(scheme, url)?;
+        let msg = ScriptMsg::UnregisterProtocolHandler(scheme, url);
+        let _ = self.global().script_to_constellation_chan().send(msg);
+        Ok(())
+    }
+
     // https://gpuweb.github.io/gpuweb/#dom-navigator-gpu
     fn Gpu(&self) -> DomRoot<GPU> {
         self.gpu.or_init(|| GPU::new(&self.global()))
     }
+
+    // https://w3c.github.io/beacon/#sec-sendBeacon-method
+    fn SendBeacon(&self, url: USVString, data: Option<BodyInit>) -> Fallible<bool> {
+        let global = self.global();
+        let document = global.as_window().Document();
+
+        // Step 1-2.
+        let url = match document.base_url().join(&url.0) {
+            Ok(url) => url,
+            Err(_) => return Err(Error::Type("The beacon URL is invalid.".to_owned())),
+        };
+
+        // Step 3.
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::Type(
+                "Beacons are only sent to HTTP(S) URLs.".to_owned(),
+            ));
+        }
+
+        // Step 4-5.
+        let mut headers = HeaderMap::new();
+        let mut mode = RequestMode::NoCors;
+        let mut body = None;
+        if let Some(data) = data {
+            let (bytes, content_type) = data.extract();
+            if let Some(content_type) = content_type {
+                if !is_cors_safelisted_request_header(&"content-type", &content_type.as_bytes()) {
+                    mode = RequestMode::CorsMode;
+                }
+                if let Ok(value) = HeaderValue::from_str(&content_type) {
+                    headers.insert(header::CONTENT_TYPE, value);
+                }
+            }
+            body = Some(bytes);
+        }
+
+        // Step 6.
+        let request = RequestBuilder::new(url)
+            .method(Method::POST)
+            .headers(headers)
+            .unsafe_request(true)
+            .body(body)
+            .mode(mode)
+            .credentials_mode(CredentialsMode::Include)
+            .origin(document.origin().immutable().clone())
+            .pipeline_id(Some(global.pipeline_id()))
+            .referrer(Some(Referrer::ReferrerUrl(document.url())))
+            .referrer_policy(document.get_referrer_policy());
+
+        // Step 7. The quota of step 4 is checked as the request is fetched.
+        Ok(document.fetch_keepalive(request, |_| {}))
+    }
 }
@@ -2,8 +2,15 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::str::DOMString;
-use std::borrow::Cow;
+use crate::dom::bindings::utils::to_frozen_array;
+use crate::script_runtime::JSContext;
+use js::jsapi::Heap;
+use js::jsval::JSVal;
+use servo_config::locale;
+use servo_config::pref;
 
 #[allow(non_snake_case)]
 pub fn Product() -> DOMString {
@@ -65,8 +72,8 @@ pub fn Platform() -> DOMString {
 }
 
 #[allow(non_snake_case)]
-pub fn UserAgent(user_agent: Cow<'static, str>) -> DOMString {
-    DOMString::from(&*user_agent)
+pub fn UserAgent(user_agent: &str) -> DOMString {
+    DOMString::from(user_agent)
 }
 
 #[allow(non_snake_case)]
@@ -76,5 +83,63 @@ pub fn AppVersion() -> DOMString {
 
 #[allow(non_snake_case)]
 pub fn Language() -> DOMString {
-    DOMString::from("en-US")
+    DOMString::from(locale::language())
+}
+
+/// The frozen array of the languages of the user, which is created again only once they
+/// have changed, so that `navigator.languages` is the same object until then.
+/// <https://html.spec.whatwg.org/multipage/#dom-navigator-languages>
+#[allow(non_snake_case)]
+pub fn Languages(cx: JSContext, languages: &DomRefCell<Vec<String>>, array: &Heap<JSVal>) -> JSVal {
+    let current = locale::languages();
+    if array.get().is_undefined() || *languages.borrow() != current {
+        array.set(to_frozen_array(current.as_slice(), cx));
+        *languages.borrow_mut() = current;
+    }
+    array.get()
+}
+
+#[allow(non_snake_case)]
+pub fn HardwareConcurrency() -> u64 {
+    let concurrency = num_cpus::get() as u64;
+    match pref!(dom.navigator.hardware_concurrency.max) {
+        max if max > 0 => concurrency.min(max as u64),
+        _ => concurrency,
+    }
+}
+
+/// The approximate memory of the device in GiB, rounded to the nearest power of two and
+/// clamped to the bounds of the `dom.navigator.device_memory` preferences, so that it
+/// does not tell devices apart.
+/// <https://www.w3.org/TR/device-memory/#computing-device-memory-value>
+#[allow(non_snake_case)]
+pub fn DeviceMemory() -> Finite<f64> {
+    let min = pref!(dom.navigator.device_memory.min);
+    let max = pref!(dom.navigator.device_memory.max);
+    let memory = match physical_memory() {
+        Some(bytes) => (bytes / (1u64 << 30) as f64).log2().round().exp2(),
+        None => max,
+    };
+    Finite::wrap(memory.min(max).max(min))
+}
+
+/// The physical memory of the device in bytes.
+#[cfg(unix)]
+#[allow(unsafe_code)]
+fn physical_memory() -> Option<f64> {
+    let (pages, page_size) = unsafe {
+        (
+            libc::sysconf(libc::_SC_PHYS_PAGES),
+            libc::sysconf(libc::_SC_PAGESIZE),
+        )
+    };
+    if pages <= 0 || page_size <= 0 {
+        return None;
+    }
+    Some(pages as f64 * page_size as f64)
+}
+
+#[cfg(not(unix))]
+fn physical_memory() -> Option<f64> {
+    None
 }
@@ -694,6 +694,7 @@ impl Node {
         if !self.is_connected() {
             return;
         }
+        self.owner_doc().directionality_may_have_changed();
 
         match self.type_id() {
             NodeTypeId::CharacterData(CharacterDataTypeId::Text(TextTypeId::Text)) => {
@@ -956,6 +957,7 @@ impl Node {
             Err(_) => Err(Error::Syntax),
             // Step 3.
             Ok(selectors) => {
+                self.owner_doc().flush_directionality();
                 // FIXME(bholley): Consider an nth-index cache here.
                 let mut ctx = MatchingContext::new(
                     MatchingMode::Normal,
@@ -982,6 +984,7 @@ impl Node {
             Err(_) => Err(Error::Syntax),
             // Step 3.
             Ok(selectors) => {
+                self.owner_doc().flush_directionality();
                 let mut descendants = self.traverse_preorder(ShadowIncluding::No);
                 // Skip the root of the tree.
                 assert!(&*descendants.next().unwrap() == self);
@@ -19,8 +19,7 @@ use crate::dom::promise::Promise;
 use crate::realms::{AlreadyInRealm, InRealm};
 use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
-use embedder_traits::{self, EmbedderMsg, PermissionPrompt, PermissionRequest};
-use ipc_channel::ipc;
+use embedder_traits::{self, PermissionPrompt, PromptRequest, PromptResponse};
 use js::conversions::ConversionResult;
 use js::jsapi::JSObject;
 use js::jsval::{ObjectValue, UndefinedValue};
@@ -356,19 +355,9 @@ fn allowed_in_nonsecure_contexts(permission_name: &PermissionName) -> bool {
 }
 
 fn prompt_user_from_embedder(prompt: PermissionPrompt, gs: &GlobalScope) -> PermissionState {
-    let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
-    gs.send_to_embedder(EmbedderMsg::PromptPermission(prompt, sender));
-
-    match receiver.recv() {
-        Ok(PermissionRequest::Granted) => PermissionState::Granted,
-        Ok(PermissionRequest::Denied) => PermissionState::Denied,
-        Err(e) => {
-            warn!(
-                "Failed to receive permission state from embedder ({:?}).",
-                e
-            );
-            PermissionState::Denied
-        },
+    match gs.prompt(PromptRequest::Permission(prompt)) {
+        PromptResponse::Accept => PermissionState::Granted,
+        _ => PermissionState::Denied,
     }
 }
 
@@ -0,0 +1,59 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::PictureInPictureWindowBinding::{
+    self, PictureInPictureWindowMethods,
+};
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+use std::cell::Cell;
+
+#[dom_struct]
+pub struct PictureInPictureWindow {
+    eventtarget: EventTarget,
+    width: Cell<i32>,
+    height: Cell<i32>,
+}
+
+impl PictureInPictureWindow {
+    fn new_inherited(width: i32, height: i32) -> PictureInPictureWindow {
+        PictureInPictureWindow {
+            eventtarget: EventTarget::new_inherited(),
+            width: Cell::new(width),
+            height: Cell::new(height),
+        }
+    }
+
+    pub fn new(window: &Window, width: i32, height: i32) -> DomRoot<PictureInPictureWindow> {
+        reflect_dom_object(
+            Box::new(PictureInPictureWindow::new_inherited(width, height)),
+            window,
+            PictureInPictureWindowBinding::Wrap,
+        )
+    }
+
+    /// <https://w3c.github.io/picture-in-picture/#close-window-algorithm>
+    pub fn close(&self) {
+        self.width.set(0);
+        self.height.set(0);
+    }
+}
+
+impl PictureInPictureWindowMethods for PictureInPictureWindow {
+    // https://w3c.github.io/picture-in-picture/#dom-pictureinpicturewindow-width
+    fn Width(&self) -> i32 {
+        self.width.get()
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-pictureinpicturewindow-height
+    fn Height(&self) -> i32 {
+        self.height.get()
+    }
+
+    // https://w3c.github.io/picture-in-picture/#dom-pictureinpicturewindow-onresize
+    event_handler!(resize, GetOnresize, SetOnresize);
+}
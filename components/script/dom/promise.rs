@@ -13,13 +13,18 @@
 
 use crate::dom::bindings::conversions::root_from_object;
 use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::refcounted::TrustedPromise;
 use crate::dom::bindings::reflector::{DomObject, MutDomObject, Reflector};
 use crate::dom::bindings::utils::AsCCharPtrPtr;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::promisenativehandler::PromiseNativeHandler;
 use crate::realms::{enter_realm, InRealm};
 use crate::script_runtime::JSContext as SafeJSContext;
+use crate::task::TaskCanceller;
+use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
+use ipc_channel::ipc::OpaqueIpcReceiver;
+use ipc_channel::router::ROUTER;
 use js::conversions::ToJSValConvertible;
 use js::jsapi::{AddRawValueRoot, CallArgs, GetFunctionNativeReserved};
 use js::jsapi::{Heap, JS_ClearPendingException};
@@ -33,6 +38,7 @@ use js::rust::wrappers::{
 use js::rust::wrappers::{GetPromiseState, IsPromiseObject};
 use js::rust::wrappers::{NewPromiseObject, RejectPromise, ResolvePromise};
 use js::rust::{HandleObject, HandleValue, MutableHandleObject, Runtime};
+use serde::{Deserialize, Serialize};
 use std::ptr;
 use std::rc::Rc;
 
@@ -258,6 +264,104 @@ impl Promise {
     }
 }
 
+/// A promise which is settled once an operation running on another thread or in another
+/// process completes, such as a file read, a fetch or a request to a device.
+///
+/// The promise is settled in a task of the task source of the operation, which is cancelled
+/// along with the other tasks of that task source in the global of the promise, for example
+/// when its document is unloaded, so the operation doesn't need to keep track of it.
+pub struct PendingPromise<S: TaskSource> {
+    promise: TrustedPromise,
+    task_source: S,
+    canceller: TaskCanceller,
+}
+
+impl<S: TaskSource> PendingPromise<S> {
+    pub fn new(promise: &Rc<Promise>, task_source: S, canceller: TaskCanceller) -> Self {
+        PendingPromise {
+            promise: TrustedPromise::new(promise.clone()),
+            task_source,
+            canceller,
+        }
+    }
+
+    /// Queue a task which runs `steps` with the promise, for operations whose result needs
+    /// to be converted to DOM objects before settling it.
+    pub fn settle_with<F>(self, steps: F)
+    where
+        F: FnOnce(Rc<Promise>) + Send + 'static,
+    {
+        let promise = self.promise;
+        let result = self.task_source.queue_with_canceller(
+            task!(settle_pending_promise: move || {
+                steps(promise.root());
+            }),
+            &self.canceller,
+        );
+        if result.is_err() {
+            warn!("Failed to queue a task to settle a promise");
+        }
+    }
+
+    /// Queue a task which resolves the promise with a value.
+    pub fn resolve<T>(self, value: T)
+    where
+        T: ToJSValConvertible + Send + 'static,
+    {
+        self.settle_with(move |promise| promise.resolve_native(&value));
+    }
+
+    /// Queue a task which rejects the promise with an error.
+    pub fn reject(self, error: Error) {
+        self.settle_with(move |promise| promise.reject_error(error));
+    }
+
+    /// Queue a task which resolves or rejects the promise with the result of the operation.
+    pub fn settle<T>(self, result: Fallible<T>)
+    where
+        T: ToJSValConvertible + Send + 'static,
+    {
+        match result {
+            Ok(value) => self.resolve(value),
+            Err(error) => self.reject(error),
+        }
+    }
+}
+
+impl<S: TaskSource + Send + 'static> PendingPromise<S> {
+    /// Settle the promise with the first message sent to the receiver of an IPC channel,
+    /// by running `steps` with the promise and the message in a task.
+    pub fn settle_with_message<T, F>(self, receiver: OpaqueIpcReceiver, steps: F)
+    where
+        T: for<'de> Deserialize<'de> + Serialize,
+        F: FnOnce(Rc<Promise>, T) + Send + 'static,
+    {
+        let mut pending = Some((self, steps));
+        ROUTER.add_route(
+            receiver,
+            Box::new(move |message| {
+                let (pending, steps) = match pending.take() {
+                    Some(pending) => pending,
+                    None => {
+                        warn!("A promise was settled more than once");
+                        return;
+                    },
+                };
+                match message.to::<T>() {
+                    Ok(message) => pending.settle_with(move |promise| steps(promise, message)),
+                    Err(error) => {
+                        warn!(
+                            "Could not deserialize the message to settle a promise: {}",
+                            error
+                        );
+                        pending.reject(Error::Operation);
+                    },
+                }
+            }),
+        );
+    }
+}
+
 #[allow(unsafe_code)]
 unsafe extern "C" fn do_nothing_promise_executor(
     _cx: *mut JSContext,
@@ -0,0 +1,825 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableByteStreamControllerBinding::{
+    self, ReadableByteStreamControllerMethods,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::{resolve_read_result, SourceMethod, UnderlyingSource};
+use crate::dom::readablestream::{ReadableStream, StreamState};
+use crate::dom::readablestreambyobrequest::ReadableStreamBYOBRequest;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::JS_NewUint8ClampedArrayWithBuffer;
+use js::jsapi::{Heap, JSObject, Type};
+use js::jsapi::{JS_GetArrayBufferViewBuffer, JS_GetArrayBufferViewByteOffset, JS_NewDataView};
+use js::jsapi::{JS_NewFloat32ArrayWithBuffer, JS_NewFloat64ArrayWithBuffer};
+use js::jsapi::{JS_NewInt16ArrayWithBuffer, JS_NewInt32ArrayWithBuffer};
+use js::jsapi::{JS_NewInt8ArrayWithBuffer, JS_NewUint16ArrayWithBuffer};
+use js::jsapi::{JS_NewUint32ArrayWithBuffer, JS_NewUint8ArrayWithBuffer};
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::wrappers::DetachArrayBuffer;
+use js::rust::{CustomAutoRooterGuard, HandleObject, HandleValue, MutableHandleObject};
+use js::typedarray::{ArrayBuffer, ArrayBufferView, CreateWith, Uint8Array};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ptr;
+use std::rc::Rc;
+
+unsafe_no_jsmanaged_fields!(Type);
+
+/// A pending read into a view, which is a read into a buffer of the size that the
+/// underlying source automatically allocates for reads of default readers.
+/// <https://streams.spec.whatwg.org/#pull-into-descriptor>
+#[derive(JSTraceable, MallocSizeOf)]
+struct PullIntoDescriptor {
+    #[ignore_malloc_size_of = "mozjs"]
+    buffer: Box<Heap<*mut JSObject>>,
+    byte_offset: usize,
+    byte_length: usize,
+    bytes_filled: usize,
+    element_size: usize,
+    #[ignore_malloc_size_of = "mozjs"]
+    view_type: Type,
+    for_default_reader: bool,
+}
+
+#[dom_struct]
+pub struct ReadableByteStreamController {
+    reflector_: Reflector,
+    stream: Dom<ReadableStream>,
+    source: UnderlyingSource,
+    /// The chunks that were enqueued and not yet read.
+    queue: DomRefCell<VecDeque<Vec<u8>>>,
+    queue_total_size: Cell<usize>,
+    high_water_mark: f64,
+    pending_pull_intos: DomRefCell<VecDeque<PullIntoDescriptor>>,
+    byob_request: MutNullableDom<ReadableStreamBYOBRequest>,
+    auto_allocate_chunk_size: Option<u64>,
+    started: Cell<bool>,
+    close_requested: Cell<bool>,
+    pulling: Cell<bool>,
+    pull_again: Cell<bool>,
+}
+
+impl ReadableByteStreamController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        stream: &ReadableStream,
+        source: UnderlyingSource,
+        high_water_mark: f64,
+        auto_allocate_chunk_size: Option<u64>,
+    ) -> ReadableByteStreamController {
+        ReadableByteStreamController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            source,
+            queue: DomRefCell::new(VecDeque::new()),
+            queue_total_size: Cell::new(0),
+            high_water_mark,
+            pending_pull_intos: DomRefCell::new(VecDeque::new()),
+            byob_request: Default::default(),
+            auto_allocate_chunk_size,
+            started: Cell::new(false),
+            close_requested: Cell::new(false),
+            pulling: Cell::new(false),
+            pull_again: Cell::new(false),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+        source: UnderlyingSource,
+        high_water_mark: f64,
+        auto_allocate_chunk_size: Option<u64>,
+    ) -> DomRoot<ReadableByteStreamController> {
+        reflect_dom_object(
+            Box::new(ReadableByteStreamController::new_inherited(
+                stream,
+                source,
+                high_water_mark,
+                auto_allocate_chunk_size,
+            )),
+            global,
+            ReadableByteStreamControllerBinding::Wrap,
+        )
+    }
+
+    /// Invoke the start method of the underlying source.
+    /// <https://streams.spec.whatwg.org/#set-up-readable-byte-stream-controller>
+    pub fn start(&self) -> ErrorResult {
+        let promise = self.source.start(self)?;
+        self.stream
+            .react_to_source_promise(&promise, SourceMethod::Start);
+        Ok(())
+    }
+
+    pub fn start_completed(&self) {
+        self.started.set(true);
+        self.call_pull_if_needed();
+    }
+
+    pub fn pull_completed(&self) {
+        self.pulling.set(false);
+        if self.pull_again.get() {
+            self.pull_again.set(false);
+            self.call_pull_if_needed();
+        }
+    }
+
+    pub fn can_close_or_enqueue(&self) -> bool {
+        !self.close_requested.get() && self.stream.state() == StreamState::Readable
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-get-desired-size>
+    fn desired_size(&self) -> Option<f64> {
+        match self.stream.state() {
+            StreamState::Errored => None,
+            StreamState::Closed => Some(0.),
+            StreamState::Readable => {
+                Some(self.high_water_mark - self.queue_total_size.get() as f64)
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-should-call-pull>
+    fn should_call_pull(&self) -> bool {
+        if !self.can_close_or_enqueue() || !self.started.get() {
+            return false;
+        }
+        if self.stream.is_locked() && self.stream.num_read_requests() > 0 {
+            return true;
+        }
+        self.desired_size().unwrap_or(0.) > 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-call-pull-if-needed>
+    fn call_pull_if_needed(&self) {
+        if !self.should_call_pull() {
+            return;
+        }
+        if self.pulling.get() {
+            self.pull_again.set(true);
+            return;
+        }
+        self.pulling.set(true);
+        let promise = self.source.pull(self);
+        self.stream
+            .react_to_source_promise(&promise, SourceMethod::Pull);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-close>
+    pub fn close(&self) -> ErrorResult {
+        if self.queue_total_size.get() > 0 {
+            self.close_requested.set(true);
+            return Ok(());
+        }
+        let partially_filled = self
+            .pending_pull_intos
+            .borrow()
+            .front()
+            .map_or(false, |descriptor| descriptor.bytes_filled > 0);
+        if partially_filled {
+            let error = Error::Type(
+                "The stream was closed in the middle of an element of a view".to_owned(),
+            );
+            self.error_with(error.clone());
+            return Err(error);
+        }
+        self.stream.close();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-enqueue>
+    pub fn enqueue(&self, bytes: Vec<u8>) {
+        if !self.pending_pull_intos.borrow().is_empty() {
+            self.invalidate_byob_request();
+        }
+        if self.stream.has_default_reader() && self.stream.num_read_requests() > 0 {
+            // A buffer that was allocated for the read is not needed anymore.
+            let allocated = self
+                .pending_pull_intos
+                .borrow()
+                .front()
+                .map_or(false, |descriptor| descriptor.for_default_reader);
+            if allocated {
+                self.pending_pull_intos.borrow_mut().pop_front();
+            }
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let mut array = ptr::null_mut::<JSObject>());
+            if create_uint8_array(cx, &bytes, array.handle_mut()).is_ok() {
+                rooted!(in(*cx) let chunk = ObjectValue(array.get()));
+                self.stream.fulfill_read_request(chunk.handle(), false);
+            }
+        } else {
+            self.enqueue_chunk_to_queue(bytes);
+            if self.stream.has_byob_reader() {
+                self.process_pull_into_descriptors_using_queue();
+            }
+        }
+        self.call_pull_if_needed();
+    }
+
+    fn enqueue_chunk_to_queue(&self, bytes: Vec<u8>) {
+        self.queue_total_size
+            .set(self.queue_total_size.get() + bytes.len());
+        self.queue.borrow_mut().push_back(bytes);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-error>
+    pub fn error(&self, error: HandleValue) {
+        if self.stream.state() != StreamState::Readable {
+            return;
+        }
+        self.clear_pending_pull_intos();
+        self.queue.borrow_mut().clear();
+        self.queue_total_size.set(0);
+        self.stream.error(error);
+    }
+
+    #[allow(unsafe_code)]
+    fn error_with(&self, error: Error) {
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut value = UndefinedValue());
+        unsafe { error.to_jsval(*cx, &self.global(), value.handle_mut()) };
+        self.error(value.handle());
+    }
+
+    fn clear_pending_pull_intos(&self) {
+        self.invalidate_byob_request();
+        self.pending_pull_intos.borrow_mut().clear();
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-invalidate-byob-request>
+    fn invalidate_byob_request(&self) {
+        if let Some(request) = self.byob_request.take() {
+            request.invalidate();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-handle-queue-drain>
+    fn handle_queue_drain(&self) {
+        if self.queue_total_size.get() == 0 && self.close_requested.get() {
+            self.stream.close();
+        } else {
+            self.call_pull_if_needed();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-private-pull>
+    pub fn pull_steps(&self, promise: &Rc<Promise>) {
+        let chunk = self.queue.borrow_mut().pop_front();
+        if let Some(chunk) = chunk {
+            self.queue_total_size
+                .set(self.queue_total_size.get() - chunk.len());
+            self.handle_queue_drain();
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let mut array = ptr::null_mut::<JSObject>());
+            match create_uint8_array(cx, &chunk, array.handle_mut()) {
+                Ok(()) => {
+                    rooted!(in(*cx) let chunk = ObjectValue(array.get()));
+                    resolve_read_result(promise, chunk.handle(), false);
+                },
+                Err(error) => promise.reject_error(error),
+            }
+            return;
+        }
+        if let Some(size) = self.auto_allocate_chunk_size {
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let mut buffer = ptr::null_mut::<JSObject>());
+            if let Err(error) = create_array_buffer(cx, size as usize, buffer.handle_mut()) {
+                promise.reject_error(error);
+                return;
+            }
+            self.pending_pull_intos
+                .borrow_mut()
+                .push_back(PullIntoDescriptor {
+                    buffer: Heap::boxed(buffer.get()),
+                    byte_offset: 0,
+                    byte_length: size as usize,
+                    bytes_filled: 0,
+                    element_size: 1,
+                    view_type: Type::Uint8,
+                    for_default_reader: true,
+                });
+        }
+        self.stream.add_read_request(promise);
+        self.call_pull_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#rbs-controller-private-cancel>
+    pub fn cancel_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        self.clear_pending_pull_intos();
+        self.queue.borrow_mut().clear();
+        self.queue_total_size.set(0);
+        self.source.cancel(&self.global(), reason)
+    }
+
+    /// Read into a view, whose buffer is transferred to the view of the result.
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-pull-into>
+    #[allow(unsafe_code)]
+    pub fn pull_into(&self, view: &ArrayBufferView, promise: &Rc<Promise>) {
+        let cx = self.global().get_cx();
+        let view_type = view.get_array_type();
+        let element_size = match element_size(view_type) {
+            Some(element_size) => element_size,
+            None => {
+                promise.reject_error(Error::Type(
+                    "The type of the view is not supported".to_owned(),
+                ));
+                return;
+            },
+        };
+        let byte_length = unsafe { view.as_slice().len() };
+        rooted!(in(*cx) let view = *view.underlying_object());
+        let byte_offset = unsafe { JS_GetArrayBufferViewByteOffset(view.get()) } as usize;
+        rooted!(in(*cx) let mut buffer = ptr::null_mut::<JSObject>());
+        if let Err(error) = view_buffer(cx, view.handle(), buffer.handle_mut()) {
+            promise.reject_error(error);
+            return;
+        }
+        rooted!(in(*cx) let mut transferred = ptr::null_mut::<JSObject>());
+        if let Err(error) = transfer_array_buffer(cx, buffer.handle(), transferred.handle_mut()) {
+            promise.reject_error(error);
+            return;
+        }
+        let descriptor = PullIntoDescriptor {
+            buffer: Heap::boxed(transferred.get()),
+            byte_offset,
+            byte_length,
+            bytes_filled: 0,
+            element_size,
+            view_type,
+            for_default_reader: false,
+        };
+
+        if !self.pending_pull_intos.borrow().is_empty() {
+            self.pending_pull_intos.borrow_mut().push_back(descriptor);
+            self.stream.add_read_request(promise);
+            return;
+        }
+        if self.stream.state() == StreamState::Closed {
+            // The read is done, with an empty view of the transferred buffer.
+            match descriptor.create_view(cx, 0) {
+                Ok(view) => {
+                    rooted!(in(*cx) let view = ObjectValue(view));
+                    resolve_read_result(promise, view.handle(), true);
+                },
+                Err(error) => promise.reject_error(error),
+            }
+            return;
+        }
+        if self.queue_total_size.get() > 0 {
+            let mut descriptor = descriptor;
+            if self.fill_pull_into_descriptor_from_queue(&mut descriptor) {
+                self.handle_queue_drain();
+                match descriptor.filled_view(cx) {
+                    Ok(view) => {
+                        rooted!(in(*cx) let view = ObjectValue(view));
+                        resolve_read_result(promise, view.handle(), false);
+                    },
+                    Err(error) => promise.reject_error(error),
+                }
+                return;
+            }
+            if self.close_requested.get() {
+                let error = Error::Type(
+                    "The stream was closed in the middle of an element of a view".to_owned(),
+                );
+                self.error_with(error.clone());
+                promise.reject_error(error);
+                return;
+            }
+            self.pending_pull_intos.borrow_mut().push_back(descriptor);
+        } else {
+            self.pending_pull_intos.borrow_mut().push_back(descriptor);
+        }
+        self.stream.add_read_request(promise);
+        self.call_pull_if_needed();
+    }
+
+    /// Copy the queued bytes into the buffer of a pending read, returning whether it
+    /// was filled with at least one element.
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-fill-pull-into-descriptor-from-queue>
+    #[allow(unsafe_code)]
+    fn fill_pull_into_descriptor_from_queue(&self, descriptor: &mut PullIntoDescriptor) -> bool {
+        let element_size = descriptor.element_size;
+        let current_aligned_bytes =
+            descriptor.bytes_filled - descriptor.bytes_filled % element_size;
+        let max_bytes_to_copy = self
+            .queue_total_size
+            .get()
+            .min(descriptor.byte_length - descriptor.bytes_filled);
+        let max_bytes_filled = descriptor.bytes_filled + max_bytes_to_copy;
+        let max_aligned_bytes = max_bytes_filled - max_bytes_filled % element_size;
+        let (mut total_bytes_to_copy_remaining, ready) =
+            if max_aligned_bytes > current_aligned_bytes {
+                (max_aligned_bytes - descriptor.bytes_filled, true)
+            } else {
+                (max_bytes_to_copy, false)
+            };
+
+        let cx = self.global().get_cx();
+        typedarray!(in(*cx) let buffer: ArrayBuffer = descriptor.buffer.get());
+        let mut buffer = match buffer {
+            Ok(buffer) => buffer,
+            Err(()) => return false,
+        };
+        let destination = unsafe { buffer.as_mut_slice() };
+        let mut queue = self.queue.borrow_mut();
+        while total_bytes_to_copy_remaining > 0 {
+            let bytes_to_copy = {
+                let head = queue.front_mut().expect("The queue has enough bytes");
+                let bytes_to_copy = total_bytes_to_copy_remaining.min(head.len());
+                let start = descriptor.byte_offset + descriptor.bytes_filled;
+                destination[start..start + bytes_to_copy].copy_from_slice(&head[..bytes_to_copy]);
+                head.drain(..bytes_to_copy);
+                bytes_to_copy
+            };
+            if queue.front().map_or(false, |head| head.is_empty()) {
+                queue.pop_front();
+            }
+            self.queue_total_size
+                .set(self.queue_total_size.get() - bytes_to_copy);
+            self.invalidate_byob_request();
+            descriptor.bytes_filled += bytes_to_copy;
+            total_bytes_to_copy_remaining -= bytes_to_copy;
+        }
+        ready
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-process-pull-into-descriptors-using-queue>
+    fn process_pull_into_descriptors_using_queue(&self) {
+        while self.queue_total_size.get() > 0 {
+            let descriptor = self.pending_pull_intos.borrow_mut().pop_front();
+            let mut descriptor = match descriptor {
+                Some(descriptor) => descriptor,
+                None => return,
+            };
+            if self.fill_pull_into_descriptor_from_queue(&mut descriptor) {
+                self.commit_pull_into_descriptor(&descriptor);
+            } else {
+                self.pending_pull_intos.borrow_mut().push_front(descriptor);
+            }
+        }
+    }
+
+    /// Resolve the oldest read with the view of the bytes that filled its buffer.
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-commit-pull-into-descriptor>
+    fn commit_pull_into_descriptor(&self, descriptor: &PullIntoDescriptor) {
+        let cx = self.global().get_cx();
+        let done = self.stream.state() == StreamState::Closed;
+        match descriptor.filled_view(cx) {
+            Ok(view) => {
+                rooted!(in(*cx) let view = ObjectValue(view));
+                self.stream.fulfill_read_request(view.handle(), done);
+            },
+            Err(error) => self.error_with(error),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond>
+    pub fn respond(&self, bytes_written: usize) -> ErrorResult {
+        match self.stream.state() {
+            StreamState::Closed if bytes_written != 0 => {
+                return Err(Error::Type(
+                    "bytesWritten must be 0 once the stream is closed".to_owned(),
+                ));
+            },
+            StreamState::Readable => {
+                if bytes_written == 0 {
+                    return Err(Error::Type("bytesWritten must not be 0".to_owned()));
+                }
+                let pending_pull_intos = self.pending_pull_intos.borrow();
+                let descriptor = pending_pull_intos
+                    .front()
+                    .expect("A request is responded to for a pending read");
+                if descriptor.bytes_filled + bytes_written > descriptor.byte_length {
+                    return Err(Error::Range(
+                        "bytesWritten is greater than the length of the view".to_owned(),
+                    ));
+                }
+            },
+            _ => {},
+        }
+        self.respond_internal(bytes_written)
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond-with-new-view>
+    #[allow(unsafe_code)]
+    pub fn respond_with_new_view(&self, view: &ArrayBufferView) -> ErrorResult {
+        let cx = self.global().get_cx();
+        let byte_length = unsafe { view.as_slice().len() };
+        rooted!(in(*cx) let view = *view.underlying_object());
+        let byte_offset = unsafe { JS_GetArrayBufferViewByteOffset(view.get()) } as usize;
+        rooted!(in(*cx) let mut buffer = ptr::null_mut::<JSObject>());
+        view_buffer(cx, view.handle(), buffer.handle_mut())?;
+
+        match self.stream.state() {
+            StreamState::Closed if byte_length != 0 => {
+                return Err(Error::Type(
+                    "The view must be empty once the stream is closed".to_owned(),
+                ));
+            },
+            StreamState::Readable if byte_length == 0 => {
+                return Err(Error::Type("The view must not be empty".to_owned()));
+            },
+            _ => {},
+        }
+        {
+            let pending_pull_intos = self.pending_pull_intos.borrow();
+            let descriptor = pending_pull_intos
+                .front()
+                .expect("A request is responded to for a pending read");
+            if descriptor.byte_offset + descriptor.bytes_filled != byte_offset {
+                return Err(Error::Range(
+                    "The view does not start at the position of the request".to_owned(),
+                ));
+            }
+            if array_buffer_length(cx, descriptor.buffer.get()) !=
+                array_buffer_length(cx, buffer.get())
+            {
+                return Err(Error::Range(
+                    "The buffer of the view does not have the length of the request".to_owned(),
+                ));
+            }
+            if descriptor.bytes_filled + byte_length > descriptor.byte_length {
+                return Err(Error::Range(
+                    "The view is longer than the request".to_owned(),
+                ));
+            }
+            descriptor.buffer.set(buffer.get());
+        }
+        self.respond_internal(byte_length)
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond-internal>
+    fn respond_internal(&self, bytes_written: usize) -> ErrorResult {
+        self.invalidate_byob_request();
+        // The buffer that the underlying source wrote into is transferred, so that it
+        // cannot write into the view of the result.
+        let cx = self.global().get_cx();
+        {
+            let pending_pull_intos = self.pending_pull_intos.borrow();
+            let descriptor = pending_pull_intos
+                .front()
+                .expect("A request is responded to for a pending read");
+            rooted!(in(*cx) let buffer = descriptor.buffer.get());
+            rooted!(in(*cx) let mut transferred = ptr::null_mut::<JSObject>());
+            transfer_array_buffer(cx, buffer.handle(), transferred.handle_mut())?;
+            descriptor.buffer.set(transferred.get());
+        }
+        if self.stream.state() == StreamState::Closed {
+            self.respond_in_closed_state();
+        } else {
+            self.respond_in_readable_state(bytes_written);
+        }
+        self.call_pull_if_needed();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond-in-closed-state>
+    fn respond_in_closed_state(&self) {
+        if !self.stream.has_byob_reader() {
+            return;
+        }
+        while self.stream.num_read_requests() > 0 {
+            let descriptor = self.pending_pull_intos.borrow_mut().pop_front();
+            match descriptor {
+                Some(descriptor) => self.commit_pull_into_descriptor(&descriptor),
+                None => return,
+            }
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-respond-in-readable-state>
+    #[allow(unsafe_code)]
+    fn respond_in_readable_state(&self, bytes_written: usize) {
+        let mut descriptor = {
+            let mut pending_pull_intos = self.pending_pull_intos.borrow_mut();
+            let descriptor = pending_pull_intos
+                .front_mut()
+                .expect("A request is responded to for a pending read");
+            descriptor.bytes_filled += bytes_written;
+            if descriptor.bytes_filled < descriptor.element_size {
+                return;
+            }
+            pending_pull_intos.pop_front().unwrap()
+        };
+        // The bytes of an incomplete element are enqueued for the next read.
+        let remainder_size = descriptor.bytes_filled % descriptor.element_size;
+        if remainder_size > 0 {
+            let end = descriptor.byte_offset + descriptor.bytes_filled;
+            let cx = self.global().get_cx();
+            typedarray!(in(*cx) let buffer: ArrayBuffer = descriptor.buffer.get());
+            if let Ok(buffer) = buffer {
+                let remainder = unsafe { buffer.as_slice()[end - remainder_size..end].to_vec() };
+                self.enqueue_chunk_to_queue(remainder);
+            }
+        }
+        descriptor.bytes_filled -= remainder_size;
+        self.commit_pull_into_descriptor(&descriptor);
+        self.process_pull_into_descriptors_using_queue();
+    }
+}
+
+impl ReadableByteStreamControllerMethods for ReadableByteStreamController {
+    // https://streams.spec.whatwg.org/#rbs-controller-byob-request
+    fn GetByobRequest(&self) -> Fallible<Option<DomRoot<ReadableStreamBYOBRequest>>> {
+        if self.byob_request.get().is_none() {
+            let pending_pull_intos = self.pending_pull_intos.borrow();
+            if let Some(descriptor) = pending_pull_intos.front() {
+                let cx = self.global().get_cx();
+                rooted!(in(*cx) let buffer = descriptor.buffer.get());
+                rooted!(in(*cx) let view = create_view(
+                    cx,
+                    Type::Uint8,
+                    buffer.handle(),
+                    descriptor.byte_offset + descriptor.bytes_filled,
+                    descriptor.byte_length - descriptor.bytes_filled,
+                )?);
+                let request = ReadableStreamBYOBRequest::new(&self.global(), self, view.handle());
+                self.byob_request.set(Some(&request));
+            }
+        }
+        Ok(self.byob_request.get())
+    }
+
+    // https://streams.spec.whatwg.org/#rbs-controller-desired-size
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.desired_size()
+    }
+
+    // https://streams.spec.whatwg.org/#rbs-controller-close
+    fn Close(&self) -> ErrorResult {
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream cannot be closed".to_owned()));
+        }
+        self.close()
+    }
+
+    // https://streams.spec.whatwg.org/#rbs-controller-enqueue
+    #[allow(unsafe_code)]
+    fn Enqueue(&self, chunk: CustomAutoRooterGuard<ArrayBufferView>) -> ErrorResult {
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream cannot be enqueued to".to_owned()));
+        }
+        let bytes = unsafe { chunk.as_slice().to_vec() };
+        if bytes.is_empty() {
+            return Err(Error::Type("The chunk must not be empty".to_owned()));
+        }
+        // The buffer of the chunk is transferred to the stream.
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let view = *chunk.underlying_object());
+        rooted!(in(*cx) let mut buffer = ptr::null_mut::<JSObject>());
+        view_buffer(cx, view.handle(), buffer.handle_mut())?;
+        if unsafe { !DetachArrayBuffer(*cx, buffer.handle()) } {
+            return Err(Error::JSFailed);
+        }
+        self.enqueue(bytes);
+        Ok(())
+    }
+
+    // https://streams.spec.whatwg.org/#rbs-controller-error
+    fn Error(&self, _cx: JSContext, e: HandleValue) {
+        self.error(e);
+    }
+}
+
+impl PullIntoDescriptor {
+    /// Create a view of the given number of bytes of the buffer, with the type of the
+    /// view that is read into.
+    fn create_view(&self, cx: JSContext, byte_length: usize) -> Fallible<*mut JSObject> {
+        rooted!(in(*cx) let buffer = self.buffer.get());
+        create_view(
+            cx,
+            self.view_type,
+            buffer.handle(),
+            self.byte_offset,
+            byte_length,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-byte-stream-controller-convert-pull-into-descriptor>
+    fn filled_view(&self, cx: JSContext) -> Fallible<*mut JSObject> {
+        self.create_view(cx, self.bytes_filled)
+    }
+}
+
+/// The size of the elements of a type of view, if it is supported.
+fn element_size(view_type: Type) -> Option<usize> {
+    match view_type {
+        Type::Int8 | Type::Uint8 | Type::Uint8Clamped => Some(1),
+        Type::Int16 | Type::Uint16 => Some(2),
+        Type::Int32 | Type::Uint32 | Type::Float32 => Some(4),
+        Type::Float64 => Some(8),
+        // The type of data views.
+        Type::MaxTypedArrayViewType => Some(1),
+        _ => None,
+    }
+}
+
+/// Create a view of a buffer, whose type is a data view if it is not a typed array.
+#[allow(unsafe_code)]
+fn create_view(
+    cx: JSContext,
+    view_type: Type,
+    buffer: HandleObject,
+    byte_offset: usize,
+    byte_length: usize,
+) -> Fallible<*mut JSObject> {
+    let offset = byte_offset as _;
+    let length = (byte_length / element_size(view_type).unwrap_or(1)) as _;
+    let view = unsafe {
+        match view_type {
+            Type::Int8 => JS_NewInt8ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            Type::Uint8 => JS_NewUint8ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            Type::Uint8Clamped => {
+                JS_NewUint8ClampedArrayWithBuffer(*cx, buffer.into(), offset, length)
+            },
+            Type::Int16 => JS_NewInt16ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            Type::Uint16 => JS_NewUint16ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            Type::Int32 => JS_NewInt32ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            Type::Uint32 => JS_NewUint32ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            Type::Float32 => JS_NewFloat32ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            Type::Float64 => JS_NewFloat64ArrayWithBuffer(*cx, buffer.into(), offset, length),
+            _ => JS_NewDataView(*cx, buffer.into(), offset, length),
+        }
+    };
+    if view.is_null() {
+        return Err(Error::JSFailed);
+    }
+    Ok(view)
+}
+
+#[allow(unsafe_code)]
+fn create_uint8_array(cx: JSContext, bytes: &[u8], result: MutableHandleObject) -> ErrorResult {
+    unsafe { Uint8Array::create(*cx, CreateWith::Slice(bytes), result) }
+        .map_err(|_| Error::JSFailed)
+}
+
+#[allow(unsafe_code)]
+fn create_array_buffer(cx: JSContext, length: usize, result: MutableHandleObject) -> ErrorResult {
+    unsafe { ArrayBuffer::create(*cx, CreateWith::Length(length as u32), result) }
+        .map_err(|_| Error::JSFailed)
+}
+
+/// The buffer of a view, which cannot be shared with other threads.
+#[allow(unsafe_code)]
+fn view_buffer(cx: JSContext, view: HandleObject, mut result: MutableHandleObject) -> ErrorResult {
+    let mut is_shared = false;
+    let buffer = unsafe { JS_GetArrayBufferViewBuffer(*cx, view.into(), &mut is_shared) };
+    if buffer.is_null() {
+        return Err(Error::JSFailed);
+    }
+    if is_shared {
+        return Err(Error::Type("The buffer of the view is shared".to_owned()));
+    }
+    result.set(buffer);
+    Ok(())
+}
+
+/// The length of an array buffer, which is 0 once it is detached.
+#[allow(unsafe_code)]
+fn array_buffer_length(cx: JSContext, buffer: *mut JSObject) -> usize {
+    typedarray!(in(*cx) let buffer: ArrayBuffer = buffer);
+    buffer.map_or(0, |buffer| unsafe { buffer.as_slice().len() })
+}
+
+/// Move the contents of an array buffer to a new one, detaching it.
+/// <https://streams.spec.whatwg.org/#transfer-array-buffer>
+///
+/// FIXME: The contents are copied, since the APIs which steal them are not exposed.
+#[allow(unsafe_code)]
+fn transfer_array_buffer(
+    cx: JSContext,
+    buffer: HandleObject,
+    result: MutableHandleObject,
+) -> ErrorResult {
+    let contents = {
+        typedarray!(in(*cx) let contents: ArrayBuffer = buffer.get());
+        match contents {
+            Ok(contents) => unsafe { contents.as_slice().to_vec() },
+            Err(()) => return Err(Error::Type("The buffer is not an ArrayBuffer".to_owned())),
+        }
+    };
+    if contents.is_empty() {
+        return Err(Error::Type("The buffer is detached".to_owned()));
+    }
+    unsafe {
+        ArrayBuffer::create(*cx, CreateWith::Slice(&contents), result)
+            .map_err(|_| Error::JSFailed)?;
+        if !DetachArrayBuffer(*cx, buffer) {
+            return Err(Error::JSFailed);
+        }
+    }
+    Ok(())
+}
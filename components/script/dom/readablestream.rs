@@ -0,0 +1,845 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::BlobBinding::BlobMethods;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamBinding::{
+    self, ReadableStreamGetReaderOptions, ReadableStreamIteratorOptions, ReadableStreamMethods,
+    ReadableStreamReaderMode, ReadableWritablePair, StreamPipeOptions,
+};
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultReaderBinding::ReadableStreamReadResult;
+use crate::dom::bindings::codegen::UnionTypes::ReadableStreamDefaultReaderOrReadableStreamBYOBReader as ReadableStreamReader;
+use crate::dom::bindings::conversions::{ConversionBehavior, StringificationBehavior};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::iterable::{AsyncIterable, AsyncIterableIterator};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::blob::Blob;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablebytestreamcontroller::ReadableByteStreamController;
+use crate::dom::readablestreambyobreader::ReadableStreamBYOBReader;
+use crate::dom::readablestreamdefaultcontroller::ReadableStreamDefaultController;
+use crate::dom::readablestreamdefaultreader::{GenericReader, ReadableStreamDefaultReader};
+use crate::dom::streams::pipe::pipe_to;
+use crate::dom::streams::{convert, get_member, get_method, high_water_mark_from_value};
+use crate::dom::streams::{mark_promise_as_handled, promise_call, settle_promise_with};
+use crate::dom::streams::{size_function_from_value, start_call};
+use crate::dom::transformstream::TransformStream;
+use crate::dom::websocketstream::WebSocketStream;
+use crate::dom::writablestream::WritableStream;
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleObject, HandleValue};
+use js::typedarray::ArrayBufferView;
+use std::cell::Cell;
+use std::cmp;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#readablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum StreamState {
+    Readable,
+    Closed,
+    Errored,
+}
+
+#[dom_struct]
+pub struct ReadableStream {
+    reflector_: Reflector,
+    state: Cell<StreamState>,
+    /// Whether the stream was read from or canceled.
+    disturbed: Cell<bool>,
+    #[ignore_malloc_size_of = "mozjs"]
+    stored_error: Heap<JSVal>,
+    /// The controller of the stream, which is a byte stream controller if the stream was
+    /// constructed with a "bytes" underlying source.
+    default_controller: MutNullableDom<ReadableStreamDefaultController>,
+    byte_controller: MutNullableDom<ReadableByteStreamController>,
+    /// The reader that the stream is locked to, if any.
+    default_reader: MutNullableDom<ReadableStreamDefaultReader>,
+    byob_reader: MutNullableDom<ReadableStreamBYOBReader>,
+}
+
+impl ReadableStream {
+    fn new_inherited() -> ReadableStream {
+        ReadableStream {
+            reflector_: Reflector::new(),
+            state: Cell::new(StreamState::Readable),
+            disturbed: Cell::new(false),
+            stored_error: Heap::default(),
+            default_controller: Default::default(),
+            byte_controller: Default::default(),
+            default_reader: Default::default(),
+            byob_reader: Default::default(),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStream> {
+        reflect_dom_object(
+            Box::new(ReadableStream::new_inherited()),
+            global,
+            ReadableStreamBinding::Wrap,
+        )
+    }
+
+    /// Create a byte stream whose chunks are enqueued by Servo, such as the contents of
+    /// a blob or the body of a response.
+    #[allow(unrooted_must_root)]
+    pub fn new_byte_stream(global: &GlobalScope) -> DomRoot<ReadableStream> {
+        let stream = ReadableStream::new(global);
+        let controller =
+            ReadableByteStreamController::new(global, &stream, UnderlyingSource::none(), 0., None);
+        stream.byte_controller.set(Some(&controller));
+        controller
+            .start()
+            .expect("Starting a stream without an underlying source cannot fail");
+        stream
+    }
+
+    /// Create a byte stream which reads the next chunk of a blob each time it is pulled.
+    #[allow(unrooted_must_root)]
+    pub fn new_from_blob(blob: &Blob) -> DomRoot<ReadableStream> {
+        let contents = ByteStreamContents::Blob(Dom::from_ref(blob));
+        ReadableStream::new_pulled_byte_stream(&blob.global(), contents)
+    }
+
+    /// Create a byte stream which enqueues the next chunk of `bytes` each time it is
+    /// pulled, rather than all of them at once.
+    #[allow(unrooted_must_root)]
+    pub fn new_from_bytes(global: &GlobalScope, bytes: Vec<u8>) -> DomRoot<ReadableStream> {
+        ReadableStream::new_pulled_byte_stream(global, ByteStreamContents::Bytes(bytes))
+    }
+
+    #[allow(unrooted_must_root)]
+    fn new_pulled_byte_stream(
+        global: &GlobalScope,
+        contents: ByteStreamContents,
+    ) -> DomRoot<ReadableStream> {
+        let stream = ReadableStream::new(global);
+        let source = UnderlyingSource::for_byte_stream_contents(&stream, contents);
+        let controller = ReadableByteStreamController::new(global, &stream, source, 0., None);
+        stream.byte_controller.set(Some(&controller));
+        controller
+            .start()
+            .expect("Starting a stream without a start method cannot fail");
+        stream
+    }
+
+    /// Create a stream with a default controller.
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-controller>
+    #[allow(unrooted_must_root)]
+    pub fn new_with_source(
+        global: &GlobalScope,
+        source: UnderlyingSource,
+        high_water_mark: f64,
+        size: Option<Rc<Function>>,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        let stream = ReadableStream::new(global);
+        let controller =
+            ReadableStreamDefaultController::new(global, &stream, source, high_water_mark, size);
+        stream.default_controller.set(Some(&controller));
+        controller.start()?;
+        Ok(stream)
+    }
+
+    // https://streams.spec.whatwg.org/#rs-constructor
+    #[allow(unrooted_must_root)]
+    pub fn Constructor(
+        cx: SafeJSContext,
+        global: &GlobalScope,
+        underlying_source: Option<*mut JSObject>,
+        strategy: Option<*mut JSObject>,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        rooted!(in(*cx) let source_object = underlying_source.unwrap_or(ptr::null_mut()));
+        rooted!(in(*cx) let strategy = strategy.unwrap_or(ptr::null_mut()));
+
+        rooted!(in(*cx) let mut size = UndefinedValue());
+        get_member(cx, strategy.handle(), b"size\0", size.handle_mut())?;
+        rooted!(in(*cx) let mut high_water_mark = UndefinedValue());
+        get_member(
+            cx,
+            strategy.handle(),
+            b"highWaterMark\0",
+            high_water_mark.handle_mut(),
+        )?;
+        rooted!(in(*cx) let mut type_ = UndefinedValue());
+        get_member(cx, source_object.handle(), b"type\0", type_.handle_mut())?;
+
+        if type_.is_undefined() {
+            let size = size_function_from_value(cx, size.handle())?;
+            let high_water_mark = high_water_mark_from_value(cx, high_water_mark.handle(), 1.)?;
+            let source = UnderlyingSource::new(cx, source_object.handle())?;
+            return ReadableStream::new_with_source(global, source, high_water_mark, size);
+        }
+
+        let type_: DOMString = convert(cx, type_.handle(), StringificationBehavior::Default)?;
+        if &*type_ != "bytes" {
+            return Err(Error::Range(format!("{} is not a type of stream", type_)));
+        }
+        if !size.is_undefined() {
+            return Err(Error::Range(
+                "The strategy of a byte stream cannot have a size".to_owned(),
+            ));
+        }
+        let high_water_mark = high_water_mark_from_value(cx, high_water_mark.handle(), 0.)?;
+        let source = UnderlyingSource::new(cx, source_object.handle())?;
+        let stream = ReadableStream::new(global);
+        rooted!(in(*cx) let mut auto_allocate_chunk_size = UndefinedValue());
+        get_member(
+            cx,
+            source_object.handle(),
+            b"autoAllocateChunkSize\0",
+            auto_allocate_chunk_size.handle_mut(),
+        )?;
+        let auto_allocate_chunk_size = if auto_allocate_chunk_size.is_undefined() {
+            None
+        } else {
+            match convert(
+                cx,
+                auto_allocate_chunk_size.handle(),
+                ConversionBehavior::EnforceRange,
+            )? {
+                0 => {
+                    return Err(Error::Type(
+                        "autoAllocateChunkSize must be greater than 0".to_owned(),
+                    ))
+                },
+                size => Some(size),
+            }
+        };
+        let controller = ReadableByteStreamController::new(
+            global,
+            &stream,
+            source,
+            high_water_mark,
+            auto_allocate_chunk_size,
+        );
+        stream.byte_controller.set(Some(&controller));
+        controller.start()?;
+        Ok(stream)
+    }
+
+    pub fn state(&self) -> StreamState {
+        self.state.get()
+    }
+
+    pub fn stored_error(&self) -> HandleValue {
+        self.stored_error.handle()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.default_reader.get().is_some() || self.byob_reader.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-disturbed>
+    pub fn is_disturbed(&self) -> bool {
+        self.disturbed.get()
+    }
+
+    pub fn set_disturbed(&self) {
+        self.disturbed.set(true);
+    }
+
+    pub fn default_controller(&self) -> Option<DomRoot<ReadableStreamDefaultController>> {
+        self.default_controller.get()
+    }
+
+    pub fn byte_controller(&self) -> Option<DomRoot<ReadableByteStreamController>> {
+        self.byte_controller.get()
+    }
+
+    pub fn has_default_reader(&self) -> bool {
+        self.default_reader.get().is_some()
+    }
+
+    pub fn has_byob_reader(&self) -> bool {
+        self.byob_reader.get().is_some()
+    }
+
+    pub fn set_default_reader(&self, reader: Option<&ReadableStreamDefaultReader>) {
+        self.default_reader.set(reader);
+    }
+
+    pub fn set_byob_reader(&self, reader: Option<&ReadableStreamBYOBReader>) {
+        self.byob_reader.set(reader);
+    }
+
+    /// Run a closure with the state of the reader that the stream is locked to.
+    fn with_reader<R>(&self, f: impl FnOnce(&GenericReader) -> R) -> Option<R> {
+        if let Some(reader) = self.default_reader.get() {
+            return Some(f(reader.generic()));
+        }
+        self.byob_reader.get().map(|reader| f(reader.generic()))
+    }
+
+    /// Lock the stream to a new default reader, as the body of a response is when it is
+    /// consumed.
+    /// <https://streams.spec.whatwg.org/#acquire-readable-stream-reader>
+    pub fn acquire_default_reader(&self) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        ReadableStreamDefaultReader::acquire(&self.global(), self)
+    }
+
+    /// Enqueue a chunk of bytes that Servo read to a stream created with
+    /// `new_byte_stream`.
+    pub fn enqueue_native(&self, bytes: Vec<u8>) {
+        if bytes.is_empty() {
+            return;
+        }
+        let _ac = enter_realm(self);
+        let controller = self
+            .byte_controller
+            .get()
+            .expect("Servo only enqueues chunks to byte streams");
+        if controller.can_close_or_enqueue() {
+            controller.enqueue(bytes);
+        }
+    }
+
+    /// Close a stream created with `new_byte_stream` once Servo read all its bytes.
+    pub fn close_native(&self) {
+        let _ac = enter_realm(self);
+        let controller = self
+            .byte_controller
+            .get()
+            .expect("Servo only closes byte streams");
+        if controller.can_close_or_enqueue() {
+            let _ = controller.close();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-add-read-request>
+    pub fn add_read_request(&self, promise: &Rc<Promise>) {
+        self.with_reader(|reader| reader.add_read_request(promise));
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-get-num-read-requests>
+    pub fn num_read_requests(&self) -> usize {
+        self.with_reader(|reader| reader.num_read_requests())
+            .unwrap_or(0)
+    }
+
+    /// Resolve the oldest read request of the reader with a chunk.
+    /// <https://streams.spec.whatwg.org/#readable-stream-fulfill-read-request>
+    pub fn fulfill_read_request(&self, chunk: HandleValue, done: bool) {
+        if let Some(promise) = self
+            .with_reader(|reader| reader.take_read_request())
+            .flatten()
+        {
+            resolve_read_result(&promise, chunk, done);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-read>
+    pub fn read(&self, promise: &Rc<Promise>) {
+        self.disturbed.set(true);
+        match self.state.get() {
+            StreamState::Closed => {
+                let cx = self.global().get_cx();
+                rooted!(in(*cx) let undefined = UndefinedValue());
+                resolve_read_result(promise, undefined.handle(), true);
+            },
+            StreamState::Errored => {
+                promise.reject(self.global().get_cx(), self.stored_error.handle());
+            },
+            StreamState::Readable => {
+                if let Some(controller) = self.default_controller.get() {
+                    controller.pull_steps(promise);
+                } else if let Some(controller) = self.byte_controller.get() {
+                    controller.pull_steps(promise);
+                }
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-byob-reader-read>
+    pub fn read_into(&self, view: &ArrayBufferView, promise: &Rc<Promise>) {
+        self.disturbed.set(true);
+        if self.state.get() == StreamState::Errored {
+            promise.reject(self.global().get_cx(), self.stored_error.handle());
+            return;
+        }
+        self.byte_controller
+            .get()
+            .expect("BYOB readers are only acquired for byte streams")
+            .pull_into(view, promise);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-cancel>
+    pub fn cancel(&self, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        self.disturbed.set(true);
+        match self.state.get() {
+            StreamState::Closed => {
+                promise.resolve_native(&());
+                return promise;
+            },
+            StreamState::Errored => {
+                promise.reject(global.get_cx(), self.stored_error.handle());
+                return promise;
+            },
+            StreamState::Readable => {},
+        }
+        self.close();
+        // The views of the pending reads of a BYOB reader are returned empty.
+        if let Some(reader) = self.byob_reader.get() {
+            reader.generic().close_read_requests();
+        }
+        let source_cancel_promise = match self.default_controller.get() {
+            Some(controller) => controller.cancel_steps(reason),
+            None => self
+                .byte_controller
+                .get()
+                .expect("A stream has a controller")
+                .cancel_steps(reason),
+        };
+        settle_promise_with(&promise, &source_cancel_promise);
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-close>
+    pub fn close(&self) {
+        assert_eq!(self.state.get(), StreamState::Readable);
+        self.state.set(StreamState::Closed);
+        if let Some(reader) = self.default_reader.get() {
+            reader.generic().close_read_requests();
+        }
+        self.with_reader(|reader| reader.resolve_closed());
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-error>
+    pub fn error(&self, error: HandleValue) {
+        assert_eq!(self.state.get(), StreamState::Readable);
+        self.state.set(StreamState::Errored);
+        self.stored_error.set(error.get());
+        self.with_reader(|reader| reader.error(error));
+    }
+
+    /// React to the promise of the start or the pull method of the underlying source of
+    /// the stream.
+    #[allow(unrooted_must_root)]
+    pub fn react_to_source_promise(&self, promise: &Promise, method: SourceMethod) {
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(SourcePromiseHandler {
+                stream: Dom::from_ref(self),
+                method,
+                fulfilled,
+            })
+        };
+        let handler =
+            PromiseNativeHandler::new(&self.global(), Some(handler(true)), Some(handler(false)));
+        promise.append_native_handler(&handler);
+    }
+}
+
+impl ReadableStreamMethods for ReadableStream {
+    // https://streams.spec.whatwg.org/#rs-locked
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    // https://streams.spec.whatwg.org/#rs-cancel
+    fn Cancel(&self, _cx: SafeJSContext, reason: HandleValue) -> Rc<Promise> {
+        if self.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked to a reader".to_owned()));
+            return promise;
+        }
+        self.cancel(reason)
+    }
+
+    // https://streams.spec.whatwg.org/#rs-get-reader
+    fn GetReader(
+        &self,
+        options: &ReadableStreamGetReaderOptions,
+    ) -> Fallible<ReadableStreamReader> {
+        let global = self.global();
+        match options.mode {
+            None => ReadableStreamDefaultReader::acquire(&global, self)
+                .map(ReadableStreamReader::ReadableStreamDefaultReader),
+            Some(ReadableStreamReaderMode::Byob) => {
+                ReadableStreamBYOBReader::acquire(&global, self)
+                    .map(ReadableStreamReader::ReadableStreamBYOBReader)
+            },
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#rs-pipe-through
+    fn PipeThrough(
+        &self,
+        transform: &ReadableWritablePair,
+        options: RootedTraceableBox<StreamPipeOptions>,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        check_pipe_options(&options)?;
+        if self.is_locked() {
+            return Err(Error::Type("The stream is locked to a reader".to_owned()));
+        }
+        if transform.writable.is_locked() {
+            return Err(Error::Type(
+                "The writable stream is locked to a writer".to_owned(),
+            ));
+        }
+        let promise = pipe_to(self, &transform.writable, &options);
+        mark_promise_as_handled(&promise);
+        Ok(DomRoot::from_ref(&*transform.readable))
+    }
+
+    // https://streams.spec.whatwg.org/#rs-pipe-to
+    fn PipeTo(
+        &self,
+        destination: &WritableStream,
+        options: RootedTraceableBox<StreamPipeOptions>,
+    ) -> Rc<Promise> {
+        if let Err(error) = check_pipe_options(&options) {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(error);
+            return promise;
+        }
+        if self.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked to a reader".to_owned()));
+            return promise;
+        }
+        if destination.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type(
+                "The writable stream is locked to a writer".to_owned(),
+            ));
+            return promise;
+        }
+        pipe_to(self, destination, &options)
+    }
+}
+
+/// Piping can't be aborted with a signal, since AbortSignal is not implemented, so passing
+/// one is an error instead of a pipe which can't be stopped.
+fn check_pipe_options(options: &StreamPipeOptions) -> Fallible<()> {
+    if !options.signal.handle().is_undefined() {
+        return Err(Error::Type(
+            "Aborting a pipe with a signal is not supported".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// The state of an async iterator over the chunks of a stream.
+/// <https://streams.spec.whatwg.org/#rs-asynciterator>
+#[derive(JSTraceable)]
+#[unrooted_must_root_lint::must_root]
+pub struct ReadableStreamIteratorState {
+    reader: Dom<ReadableStreamDefaultReader>,
+}
+
+impl AsyncIterable for ReadableStream {
+    // preventCancel only affects the return() method of the iterators, which the
+    // bindings do not generate.
+    type Arguments = (ReadableStreamIteratorOptions,);
+    type State = ReadableStreamIteratorState;
+
+    // https://streams.spec.whatwg.org/#ref-for-asynchronous-iterator-initialization-steps
+    #[allow(unrooted_must_root)]
+    fn init_async_iterator(
+        &self,
+        _args: (ReadableStreamIteratorOptions,),
+    ) -> Fallible<ReadableStreamIteratorState> {
+        let reader = self.acquire_default_reader()?;
+        Ok(ReadableStreamIteratorState {
+            reader: Dom::from_ref(&*reader),
+        })
+    }
+
+    // https://streams.spec.whatwg.org/#ref-for-dfn-get-the-next-iteration-result
+    #[allow(unrooted_must_root)]
+    fn get_next_iteration_result(&self, iterator: &AsyncIterableIterator<Self>) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        let stream = iterator
+            .state()
+            .reader
+            .generic()
+            .stream()
+            .expect("The reader is only released once the iterator is finished");
+        let read_promise = Promise::new(&global);
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(IteratorReadHandler {
+                iterator: Dom::from_ref(iterator),
+                promise: promise.clone(),
+                fulfilled,
+            })
+        };
+        let handler = PromiseNativeHandler::new(&global, Some(handler(true)), Some(handler(false)));
+        read_promise.append_native_handler(&handler);
+        stream.read(&read_promise);
+        promise
+    }
+}
+
+/// Turns the result of a read from the reader of an async iterator into its next
+/// iteration result, releasing the reader once the stream is closed or errored.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct IteratorReadHandler {
+    iterator: Dom<AsyncIterableIterator<ReadableStream>>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for IteratorReadHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        let reader = self.iterator.state().reader.generic();
+        if !self.fulfilled {
+            reader.release();
+            self.promise.reject(cx, v);
+            return;
+        }
+        rooted!(in(*cx) let result = v.to_object());
+        rooted!(in(*cx) let mut done = UndefinedValue());
+        rooted!(in(*cx) let mut value = UndefinedValue());
+        let _ = get_member(cx, result.handle(), b"done\0", done.handle_mut());
+        let _ = get_member(cx, result.handle(), b"value\0", value.handle_mut());
+        if done.get().is_boolean() && done.get().to_boolean() {
+            reader.release();
+            self.iterator.end_iteration(&self.promise);
+        } else {
+            self.promise.resolve(cx, value.handle());
+        }
+    }
+}
+
+/// The methods of an underlying source whose promises the controller of a stream reacts to.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+pub enum SourceMethod {
+    Start,
+    Pull,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct SourcePromiseHandler {
+    stream: Dom<ReadableStream>,
+    method: SourceMethod,
+    fulfilled: bool,
+}
+
+impl Callback for SourcePromiseHandler {
+    fn callback(&self, _cx: *mut JSContext, v: HandleValue) {
+        if let Some(controller) = self.stream.default_controller.get() {
+            match (self.fulfilled, self.method) {
+                (false, _) => controller.error(v),
+                (true, SourceMethod::Start) => controller.start_completed(),
+                (true, SourceMethod::Pull) => controller.pull_completed(),
+            }
+        } else if let Some(controller) = self.stream.byte_controller.get() {
+            match (self.fulfilled, self.method) {
+                (false, _) => controller.error(v),
+                (true, SourceMethod::Start) => controller.start_completed(),
+                (true, SourceMethod::Pull) => controller.pull_completed(),
+            }
+        }
+    }
+}
+
+/// The object with which script constructs a stream, whose methods are invoked with it as
+/// `this`.
+/// <https://streams.spec.whatwg.org/#underlying-source-api>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct UnderlyingSource {
+    #[ignore_malloc_size_of = "mozjs"]
+    object: Box<Heap<*mut JSObject>>,
+    #[ignore_malloc_size_of = "Rc"]
+    start: Option<Rc<Function>>,
+    #[ignore_malloc_size_of = "Rc"]
+    pull: Option<Rc<Function>>,
+    #[ignore_malloc_size_of = "Rc"]
+    cancel: Option<Rc<Function>>,
+    /// The transform stream whose readable side the stream is, which is pulled and
+    /// canceled instead of the methods of a source.
+    transform_stream: Option<Dom<TransformStream>>,
+    /// The WebSocket whose messages are read from the stream.
+    web_socket_stream: Option<Dom<WebSocketStream>>,
+    /// The contents that Servo reads a chunk of each time the stream is pulled.
+    native_source: Option<NativeByteSource>,
+}
+
+impl UnderlyingSource {
+    #[allow(unrooted_must_root)]
+    fn new(cx: SafeJSContext, object: HandleObject) -> Fallible<UnderlyingSource> {
+        Ok(UnderlyingSource {
+            pull: get_method(cx, object, b"pull\0")?,
+            cancel: get_method(cx, object, b"cancel\0")?,
+            start: get_method(cx, object, b"start\0")?,
+            object: Heap::boxed(object.get()),
+            transform_stream: None,
+            web_socket_stream: None,
+            native_source: None,
+        })
+    }
+
+    /// The source of the streams whose chunks are enqueued by Servo.
+    #[allow(unrooted_must_root)]
+    fn none() -> UnderlyingSource {
+        UnderlyingSource {
+            object: Heap::boxed(ptr::null_mut()),
+            start: None,
+            pull: None,
+            cancel: None,
+            transform_stream: None,
+            web_socket_stream: None,
+            native_source: None,
+        }
+    }
+
+    /// The source of the readable side of a transform stream.
+    #[allow(unrooted_must_root)]
+    pub fn for_transform_stream(stream: &TransformStream) -> UnderlyingSource {
+        UnderlyingSource {
+            transform_stream: Some(Dom::from_ref(stream)),
+            ..UnderlyingSource::none()
+        }
+    }
+
+    /// The source of the readable stream of a `WebSocketStream`.
+    #[allow(unrooted_must_root)]
+    pub fn for_web_socket_stream(stream: &WebSocketStream) -> UnderlyingSource {
+        UnderlyingSource {
+            web_socket_stream: Some(Dom::from_ref(stream)),
+            ..UnderlyingSource::none()
+        }
+    }
+
+    /// The source of a byte stream whose chunks Servo reads from `contents` on demand.
+    #[allow(unrooted_must_root)]
+    fn for_byte_stream_contents(
+        stream: &ReadableStream,
+        contents: ByteStreamContents,
+    ) -> UnderlyingSource {
+        UnderlyingSource {
+            native_source: Some(NativeByteSource {
+                stream: Dom::from_ref(stream),
+                contents,
+                offset: Cell::new(0),
+            }),
+            ..UnderlyingSource::none()
+        }
+    }
+
+    /// Invoke the start method of the source, returning a promise resolved with its
+    /// result, or the exception that it throws.
+    pub fn start(&self, controller: &impl DomObject) -> Fallible<Rc<Promise>> {
+        if let Some(ref stream) = self.transform_stream {
+            return Ok(stream.start_promise());
+        }
+        start_call(&controller.global(), &self.object, &self.start, controller)
+    }
+
+    /// Invoke the pull method of the source.
+    pub fn pull(&self, controller: &impl DomObject) -> Rc<Promise> {
+        if let Some(ref stream) = self.transform_stream {
+            return stream.source_pull();
+        }
+        if let Some(ref stream) = self.web_socket_stream {
+            return stream.source_pull();
+        }
+        if let Some(ref source) = self.native_source {
+            return source.pull();
+        }
+        let global = controller.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let controller = ObjectValue(controller.reflector().get_jsobject().get()));
+        promise_call(&global, &self.object, &self.pull, &[controller.get()])
+    }
+
+    /// Invoke the cancel method of the source.
+    pub fn cancel(&self, global: &GlobalScope, reason: HandleValue) -> Rc<Promise> {
+        if let Some(ref stream) = self.transform_stream {
+            return stream.source_cancel(reason);
+        }
+        if let Some(ref stream) = self.web_socket_stream {
+            return stream.source_cancel();
+        }
+        promise_call(global, &self.object, &self.cancel, &[reason.get()])
+    }
+}
+
+/// The size of the chunks that are read from the contents of a byte stream created by
+/// Servo, such as the stream of a blob.
+const NATIVE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The contents of a byte stream that Servo reads chunks of as the stream is pulled.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+enum ByteStreamContents {
+    /// A blob, of which only the chunk is read from the file backing a File-backed blob.
+    Blob(Dom<Blob>),
+    /// Bytes that were already received, such as the body of a response.
+    Bytes(Vec<u8>),
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct NativeByteSource {
+    stream: Dom<ReadableStream>,
+    contents: ByteStreamContents,
+    /// The offset of the next chunk to read.
+    offset: Cell<usize>,
+}
+
+impl NativeByteSource {
+    /// Enqueue the next chunk of the contents, or close the stream once all of them were
+    /// read. The pull is rejected if the chunk cannot be read.
+    fn pull(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.stream.global());
+        match self.read_next_chunk() {
+            Ok(Some(chunk)) => self.stream.enqueue_native(chunk),
+            Ok(None) => self.stream.close_native(),
+            Err(()) => {
+                promise.reject_error(Error::NotReadable);
+                return promise;
+            },
+        }
+        promise.resolve_native(&());
+        promise
+    }
+
+    fn read_next_chunk(&self) -> Result<Option<Vec<u8>>, ()> {
+        let size = match self.contents {
+            ByteStreamContents::Blob(ref blob) => blob.Size() as usize,
+            ByteStreamContents::Bytes(ref bytes) => bytes.len(),
+        };
+        let start = self.offset.get();
+        if start >= size {
+            return Ok(None);
+        }
+        let end = cmp::min(start + NATIVE_CHUNK_SIZE, size);
+        let chunk = match self.contents {
+            ByteStreamContents::Blob(ref blob) => {
+                blob.get_range_contents(start..end)?.into_bytes()?
+            },
+            ByteStreamContents::Bytes(ref bytes) => bytes[start..end].to_vec(),
+        };
+        self.offset.set(end);
+        Ok(Some(chunk))
+    }
+}
+
+/// Resolve the promise of a read with a result.
+pub fn resolve_read_result(promise: &Promise, value: HandleValue, done: bool) {
+    let mut result = ReadableStreamReadResult::empty();
+    result.value.set(value.get());
+    result.done = done;
+    promise.resolve_native(&*result);
+}
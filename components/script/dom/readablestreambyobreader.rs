@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ReadableStreamBYOBReaderBinding::{
+    self, ReadableStreamBYOBReaderMethods,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::readablestreamdefaultreader::GenericReader;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::rust::{CustomAutoRooterGuard, HandleValue};
+use js::typedarray::ArrayBufferView;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct ReadableStreamBYOBReader {
+    reflector_: Reflector,
+    reader: GenericReader,
+}
+
+impl ReadableStreamBYOBReader {
+    fn new_inherited(global: &GlobalScope) -> ReadableStreamBYOBReader {
+        ReadableStreamBYOBReader {
+            reflector_: Reflector::new(),
+            reader: GenericReader::new(global),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStreamBYOBReader> {
+        reflect_dom_object(
+            Box::new(ReadableStreamBYOBReader::new_inherited(global)),
+            global,
+            ReadableStreamBYOBReaderBinding::Wrap,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-byob-reader>
+    pub fn acquire(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamBYOBReader>> {
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked to a reader".to_owned()));
+        }
+        if stream.byte_controller().is_none() {
+            return Err(Error::Type(
+                "Only byte streams can be read with a BYOB reader".to_owned(),
+            ));
+        }
+        let reader = ReadableStreamBYOBReader::new(global);
+        reader.reader.initialize(stream);
+        stream.set_byob_reader(Some(&reader));
+        Ok(reader)
+    }
+
+    // https://streams.spec.whatwg.org/#byob-reader-constructor
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamBYOBReader>> {
+        ReadableStreamBYOBReader::acquire(global, stream)
+    }
+
+    pub fn generic(&self) -> &GenericReader {
+        &self.reader
+    }
+}
+
+impl ReadableStreamBYOBReaderMethods for ReadableStreamBYOBReader {
+    // https://streams.spec.whatwg.org/#byob-reader-closed
+    fn Closed(&self) -> Rc<Promise> {
+        self.reader.closed()
+    }
+
+    // https://streams.spec.whatwg.org/#byob-reader-cancel
+    fn Cancel(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        self.reader.cancel(&self.global(), reason)
+    }
+
+    // https://streams.spec.whatwg.org/#byob-reader-read
+    #[allow(unsafe_code)]
+    fn Read(&self, view: CustomAutoRooterGuard<ArrayBufferView>) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        if unsafe { view.as_slice().is_empty() } {
+            promise.reject_error(Error::Type("The view must not be empty".to_owned()));
+            return promise;
+        }
+        match self.reader.stream() {
+            Some(stream) => stream.read_into(&view, &promise),
+            None => promise.reject_error(Error::Type("The reader is released".to_owned())),
+        }
+        promise
+    }
+
+    // https://streams.spec.whatwg.org/#byob-reader-release-lock
+    fn ReleaseLock(&self) -> ErrorResult {
+        self.reader.release_lock()
+    }
+}
@@ -0,0 +1,280 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultControllerBinding::{
+    self, ReadableStreamDefaultControllerMethods,
+};
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::{resolve_read_result, SourceMethod, UnderlyingSource};
+use crate::dom::readablestream::{ReadableStream, StreamState};
+use crate::dom::streams::{chunk_size, error_to_jsval};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::Heap;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::wrappers::JS_GetPendingException;
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// A chunk of the queue of a controller, with the size that the strategy of the stream
+/// computed for it.
+#[derive(JSTraceable, MallocSizeOf)]
+struct QueuedChunk {
+    #[ignore_malloc_size_of = "mozjs"]
+    value: Box<Heap<JSVal>>,
+    size: f64,
+}
+
+#[dom_struct]
+pub struct ReadableStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<ReadableStream>,
+    source: UnderlyingSource,
+    queue: DomRefCell<VecDeque<QueuedChunk>>,
+    queue_total_size: Cell<f64>,
+    high_water_mark: f64,
+    /// The size method of the queuing strategy, without which every chunk has a size of 1.
+    #[ignore_malloc_size_of = "Rc"]
+    size: Option<Rc<Function>>,
+    started: Cell<bool>,
+    close_requested: Cell<bool>,
+    pulling: Cell<bool>,
+    pull_again: Cell<bool>,
+}
+
+impl ReadableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        stream: &ReadableStream,
+        source: UnderlyingSource,
+        high_water_mark: f64,
+        size: Option<Rc<Function>>,
+    ) -> ReadableStreamDefaultController {
+        ReadableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            source,
+            queue: DomRefCell::new(VecDeque::new()),
+            queue_total_size: Cell::new(0.),
+            high_water_mark,
+            size,
+            started: Cell::new(false),
+            close_requested: Cell::new(false),
+            pulling: Cell::new(false),
+            pull_again: Cell::new(false),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+        source: UnderlyingSource,
+        high_water_mark: f64,
+        size: Option<Rc<Function>>,
+    ) -> DomRoot<ReadableStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(ReadableStreamDefaultController::new_inherited(
+                stream,
+                source,
+                high_water_mark,
+                size,
+            )),
+            global,
+            ReadableStreamDefaultControllerBinding::Wrap,
+        )
+    }
+
+    /// Invoke the start method of the underlying source.
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-controller>
+    pub fn start(&self) -> ErrorResult {
+        let promise = self.source.start(self)?;
+        self.stream
+            .react_to_source_promise(&promise, SourceMethod::Start);
+        Ok(())
+    }
+
+    pub fn start_completed(&self) {
+        self.started.set(true);
+        self.call_pull_if_needed();
+    }
+
+    pub fn pull_completed(&self) {
+        self.pulling.set(false);
+        if self.pull_again.get() {
+            self.pull_again.set(false);
+            self.call_pull_if_needed();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-can-close-or-enqueue>
+    pub fn can_close_or_enqueue(&self) -> bool {
+        !self.close_requested.get() && self.stream.state() == StreamState::Readable
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-get-desired-size>
+    pub fn desired_size(&self) -> Option<f64> {
+        match self.stream.state() {
+            StreamState::Errored => None,
+            StreamState::Closed => Some(0.),
+            StreamState::Readable => Some(self.high_water_mark - self.queue_total_size.get()),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-should-call-pull>
+    fn should_call_pull(&self) -> bool {
+        if !self.can_close_or_enqueue() || !self.started.get() {
+            return false;
+        }
+        if self.stream.is_locked() && self.stream.num_read_requests() > 0 {
+            return true;
+        }
+        self.desired_size().unwrap_or(0.) > 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-has-backpressure>
+    pub fn has_backpressure(&self) -> bool {
+        !self.should_call_pull()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-call-pull-if-needed>
+    fn call_pull_if_needed(&self) {
+        if !self.should_call_pull() {
+            return;
+        }
+        if self.pulling.get() {
+            self.pull_again.set(true);
+            return;
+        }
+        self.pulling.set(true);
+        let promise = self.source.pull(self);
+        self.stream
+            .react_to_source_promise(&promise, SourceMethod::Pull);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-close>
+    pub fn close(&self) {
+        self.close_requested.set(true);
+        if self.queue.borrow().is_empty() {
+            self.stream.close();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    pub fn enqueue(&self, chunk: HandleValue) -> ErrorResult {
+        if self.stream.is_locked() && self.stream.num_read_requests() > 0 {
+            self.stream.fulfill_read_request(chunk, false);
+        } else {
+            let cx = self.global().get_cx();
+            let size = match chunk_size(cx, &self.size, chunk) {
+                Ok(size) => size,
+                Err(()) => {
+                    // The exception of the size method errors the stream and is rethrown.
+                    rooted!(in(*cx) let mut error = UndefinedValue());
+                    unsafe { JS_GetPendingException(*cx, error.handle_mut()) };
+                    self.error(error.handle());
+                    return Err(Error::JSFailed);
+                },
+            };
+            if !size.is_finite() || size < 0. {
+                let error =
+                    Error::Range("The size of a chunk must be a non-negative number".to_owned());
+                rooted!(in(*cx) let mut value = UndefinedValue());
+                error_to_jsval(&self.global(), error.clone(), value.handle_mut());
+                self.error(value.handle());
+                return Err(error);
+            }
+            self.queue.borrow_mut().push_back(QueuedChunk {
+                value: Heap::boxed(chunk.get()),
+                size,
+            });
+            self.queue_total_size
+                .set(self.queue_total_size.get() + size);
+        }
+        self.call_pull_if_needed();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-error>
+    pub fn error(&self, error: HandleValue) {
+        if self.stream.state() != StreamState::Readable {
+            return;
+        }
+        self.reset_queue();
+        self.stream.error(error);
+    }
+
+    fn reset_queue(&self) {
+        self.queue.borrow_mut().clear();
+        self.queue_total_size.set(0.);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-pull>
+    pub fn pull_steps(&self, promise: &Rc<Promise>) {
+        let chunk = self.queue.borrow_mut().pop_front();
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => {
+                self.stream.add_read_request(promise);
+                self.call_pull_if_needed();
+                return;
+            },
+        };
+        // Rounding errors could make the total size negative once the queue is empty.
+        self.queue_total_size
+            .set((self.queue_total_size.get() - chunk.size).max(0.));
+        if self.close_requested.get() && self.queue.borrow().is_empty() {
+            self.stream.close();
+        } else {
+            self.call_pull_if_needed();
+        }
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let value = chunk.value.get());
+        resolve_read_result(promise, value.handle(), false);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-cancel>
+    pub fn cancel_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        self.reset_queue();
+        self.source.cancel(&self.global(), reason)
+    }
+}
+
+impl ReadableStreamDefaultControllerMethods for ReadableStreamDefaultController {
+    // https://streams.spec.whatwg.org/#rs-default-controller-desired-size
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.desired_size()
+    }
+
+    // https://streams.spec.whatwg.org/#rs-default-controller-close
+    fn Close(&self) -> ErrorResult {
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream cannot be closed".to_owned()));
+        }
+        self.close();
+        Ok(())
+    }
+
+    // https://streams.spec.whatwg.org/#rs-default-controller-enqueue
+    fn Enqueue(&self, _cx: JSContext, chunk: HandleValue) -> ErrorResult {
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream cannot be enqueued to".to_owned()));
+        }
+        self.enqueue(chunk)
+    }
+
+    // https://streams.spec.whatwg.org/#rs-default-controller-error
+    fn Error(&self, _cx: JSContext, e: HandleValue) {
+        self.error(e);
+    }
+}
@@ -0,0 +1,228 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultReaderBinding::{
+    self, ReadableStreamDefaultReaderMethods,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::{resolve_read_result, ReadableStream, StreamState};
+use crate::dom::streams::mark_promise_as_handled;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// The state that default and BYOB readers share.
+/// <https://streams.spec.whatwg.org/#generic-reader-mixin>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct GenericReader {
+    /// The stream that the reader is locked to, until its lock is released.
+    stream: MutNullableDom<ReadableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    /// The promises of the pending reads, which are read requests for default readers
+    /// and read-into requests for BYOB readers.
+    #[ignore_malloc_size_of = "Rc"]
+    read_requests: DomRefCell<VecDeque<Rc<Promise>>>,
+}
+
+impl GenericReader {
+    #[allow(unrooted_must_root)]
+    pub fn new(global: &GlobalScope) -> GenericReader {
+        GenericReader {
+            stream: Default::default(),
+            closed_promise: DomRefCell::new(Promise::new(global)),
+            read_requests: DomRefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-reader-generic-initialize>
+    pub fn initialize(&self, stream: &ReadableStream) {
+        self.stream.set(Some(stream));
+        let closed_promise = self.closed_promise.borrow();
+        match stream.state() {
+            StreamState::Readable => {},
+            StreamState::Closed => closed_promise.resolve_native(&()),
+            StreamState::Errored => {
+                closed_promise.reject(stream.global().get_cx(), stream.stored_error());
+                mark_promise_as_handled(&closed_promise);
+            },
+        }
+    }
+
+    pub fn stream(&self) -> Option<DomRoot<ReadableStream>> {
+        self.stream.get()
+    }
+
+    pub fn closed(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-reader-generic-cancel>
+    pub fn cancel(&self, global: &GlobalScope, reason: HandleValue) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(stream) => stream.cancel(reason),
+            None => {
+                let promise = Promise::new(global);
+                promise.reject_error(Error::Type("The reader is released".to_owned()));
+                promise
+            },
+        }
+    }
+
+    /// The steps of the releaseLock method of both readers.
+    /// <https://streams.spec.whatwg.org/#default-reader-release-lock>
+    pub fn release_lock(&self) -> ErrorResult {
+        if self.stream.get().is_none() {
+            return Ok(());
+        }
+        if !self.read_requests.borrow().is_empty() {
+            return Err(Error::Type("The reader has pending reads".to_owned()));
+        }
+        self.release();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-reader-generic-release>
+    pub fn release(&self) {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return,
+        };
+        let error = Error::Type("The reader was released".to_owned());
+        if stream.state() == StreamState::Readable {
+            self.closed_promise.borrow().reject_error(error);
+        } else {
+            let promise = Promise::new(&stream.global());
+            promise.reject_error(error);
+            *self.closed_promise.borrow_mut() = promise;
+        }
+        mark_promise_as_handled(&self.closed_promise.borrow());
+        stream.set_default_reader(None);
+        stream.set_byob_reader(None);
+        self.stream.set(None);
+    }
+
+    pub fn add_read_request(&self, promise: &Rc<Promise>) {
+        self.read_requests.borrow_mut().push_back(promise.clone());
+    }
+
+    pub fn num_read_requests(&self) -> usize {
+        self.read_requests.borrow().len()
+    }
+
+    pub fn take_read_request(&self) -> Option<Rc<Promise>> {
+        self.read_requests.borrow_mut().pop_front()
+    }
+
+    /// Resolve the pending reads with results which are done, once the stream is closed.
+    pub fn close_read_requests(&self) {
+        let requests: Vec<_> = self.read_requests.borrow_mut().drain(..).collect();
+        for promise in requests {
+            let cx = promise.global().get_cx();
+            rooted!(in(*cx) let undefined = UndefinedValue());
+            resolve_read_result(&promise, undefined.handle(), true);
+        }
+    }
+
+    pub fn resolve_closed(&self) {
+        self.closed_promise.borrow().resolve_native(&());
+    }
+
+    /// Reject the pending reads and the closed promise once the stream errors.
+    pub fn error(&self, error: HandleValue) {
+        let closed_promise = self.closed_promise.borrow();
+        let cx = closed_promise.global().get_cx();
+        let requests: Vec<_> = self.read_requests.borrow_mut().drain(..).collect();
+        for promise in requests {
+            promise.reject(cx, error);
+        }
+        closed_promise.reject(cx, error);
+        mark_promise_as_handled(&closed_promise);
+    }
+}
+
+#[dom_struct]
+pub struct ReadableStreamDefaultReader {
+    reflector_: Reflector,
+    reader: GenericReader,
+}
+
+impl ReadableStreamDefaultReader {
+    fn new_inherited(global: &GlobalScope) -> ReadableStreamDefaultReader {
+        ReadableStreamDefaultReader {
+            reflector_: Reflector::new(),
+            reader: GenericReader::new(global),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStreamDefaultReader> {
+        reflect_dom_object(
+            Box::new(ReadableStreamDefaultReader::new_inherited(global)),
+            global,
+            ReadableStreamDefaultReaderBinding::Wrap,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-reader>
+    pub fn acquire(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked to a reader".to_owned()));
+        }
+        let reader = ReadableStreamDefaultReader::new(global);
+        reader.reader.initialize(stream);
+        stream.set_default_reader(Some(&reader));
+        Ok(reader)
+    }
+
+    // https://streams.spec.whatwg.org/#default-reader-constructor
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        ReadableStreamDefaultReader::acquire(global, stream)
+    }
+
+    pub fn generic(&self) -> &GenericReader {
+        &self.reader
+    }
+}
+
+impl ReadableStreamDefaultReaderMethods for ReadableStreamDefaultReader {
+    // https://streams.spec.whatwg.org/#default-reader-closed
+    fn Closed(&self) -> Rc<Promise> {
+        self.reader.closed()
+    }
+
+    // https://streams.spec.whatwg.org/#default-reader-cancel
+    fn Cancel(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        self.reader.cancel(&self.global(), reason)
+    }
+
+    // https://streams.spec.whatwg.org/#default-reader-read
+    fn Read(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        match self.reader.stream() {
+            Some(stream) => stream.read(&promise),
+            None => promise.reject_error(Error::Type("The reader is released".to_owned())),
+        }
+        promise
+    }
+
+    // https://streams.spec.whatwg.org/#default-reader-release-lock
+    fn ReleaseLock(&self) -> ErrorResult {
+        self.reader.release_lock()
+    }
+}
@@ -15,6 +15,7 @@ use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInit;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestMethods;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestMode;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestRedirect;
+use crate::dom::bindings::codegen::UnionTypes::ReadableStreamOrBodyInit;
 use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::{DomRoot, MutNullableDom};
@@ -23,6 +24,7 @@ use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::{Guard, Headers};
 use crate::dom::promise::Promise;
+use crate::dom::readablestream::ReadableStream;
 use crate::dom::xmlhttprequest::Extractable;
 use dom_struct::dom_struct;
 use http::header::{HeaderName, HeaderValue};
@@ -36,6 +38,7 @@ use net_traits::request::Referrer as NetTraitsRequestReferrer;
 use net_traits::request::Request as NetTraitsRequest;
 use net_traits::request::RequestMode as NetTraitsRequestMode;
 use net_traits::request::{Origin, Window};
+use net_traits::storage_thread::CacheRequest;
 use net_traits::ReferrerPolicy as MsgReferrerPolicy;
 use servo_url::ServoUrl;
 use std::cell::Cell;
@@ -51,6 +54,9 @@ pub struct Request {
     mime_type: DomRefCell<Vec<u8>>,
     #[ignore_malloc_size_of = "Rc"]
     body_promise: DomRefCell<Option<(Rc<Promise>, BodyType)>>,
+    /// The stream which the body is read from when it is transmitted, in place of the
+    /// body of the request.
+    body_stream: MutNullableDom<ReadableStream>,
 }
 
 impl Request {
@@ -62,6 +68,7 @@ impl Request {
             headers: Default::default(),
             mime_type: DomRefCell::new("".to_string().into_bytes()),
             body_promise: DomRefCell::new(None),
+            body_stream: Default::default(),
         }
     }
 
@@ -164,6 +171,7 @@ impl Request {
         request.cache_mode = temporary_request.cache_mode;
         request.redirect_mode = temporary_request.redirect_mode;
         request.integrity_metadata = temporary_request.integrity_metadata;
+        request.keep_alive = temporary_request.keep_alive;
 
         // Step 14
         if init.body.is_some() ||
@@ -282,7 +290,10 @@ impl Request {
             request.integrity_metadata = integrity;
         }
 
-        // Step 26 TODO: "If init["keepalive"] exists..."
+        // Step 26
+        if let Some(keepalive) = init.keepalive {
+            request.keep_alive = keepalive;
+        }
 
         // Step 27.1
         if let Some(init_method) = init.method.as_ref() {
@@ -384,16 +395,20 @@ impl Request {
         r.request.borrow_mut().headers = r.Headers().get_headers_list();
 
         // Step 33
-        let mut input_body = if let RequestInfo::Request(ref input_request) = input {
-            let input_request_request = input_request.request.borrow();
-            input_request_request.body.clone()
-        } else {
-            None
-        };
+        let (mut input_body, mut input_body_stream) =
+            if let RequestInfo::Request(ref input_request) = input {
+                let input_request_request = input_request.request.borrow();
+                (
+                    input_request_request.body.clone(),
+                    input_request.body_stream.get(),
+                )
+            } else {
+                (None, None)
+            };
 
         // Step 34
         if let Some(init_body_option) = init.body.as_ref() {
-            if init_body_option.is_some() || input_body.is_some() {
+            if init_body_option.is_some() || input_body.is_some() || input_body_stream.is_some() {
                 let req = r.request.borrow();
                 let req_method = &req.method;
                 match *req_method {
@@ -414,12 +429,35 @@ impl Request {
 
         // Step 35-36
         if let Some(Some(ref init_body)) = init.body {
-            // Step 36.2 TODO "If init["keepalive"] exists and is true..."
+            // Step 36.2, the body of a keepalive request is extracted with the keepalive
+            // flag set, which is checked below.
 
             // Step 36.3
-            let extracted_body_tmp = init_body.extract();
-            input_body = Some(extracted_body_tmp.0);
-            let content_type = extracted_body_tmp.1;
+            let content_type = match extract_bytes(init_body) {
+                Some((bytes, content_type)) => {
+                    input_body = Some(bytes);
+                    input_body_stream = None;
+                    content_type
+                },
+                None => {
+                    if let ReadableStreamOrBodyInit::ReadableStream(ref stream) = *init_body {
+                        // https://fetch.spec.whatwg.org/#concept-bodyinit-extract Step 4.
+                        if r.request.borrow().keep_alive {
+                            return Err(Error::Type(
+                                "A keepalive request can't have a stream body".to_string(),
+                            ));
+                        }
+                        if stream.is_locked() || stream.is_disturbed() {
+                            return Err(Error::Type(
+                                "The body stream is locked or disturbed".to_string(),
+                            ));
+                        }
+                        input_body_stream = Some(DomRoot::from_ref(&**stream));
+                    }
+                    input_body = None;
+                    None
+                },
+            };
 
             // Step 36.4
             if let Some(contents) = content_type {
@@ -448,17 +486,34 @@ impl Request {
             }
         }
 
-        // Step 37 "TODO if body is non-null and body's source is null..."
-        // This looks like where we need to set the use-preflight flag
-        // if the request has a body and nothing else has set the flag.
+        // Step 37
+        if input_body_stream.is_some() {
+            // Step 37.1
+            if let Some(Some(_)) = init.body {
+                if init.duplex.is_none() {
+                    return Err(Error::Type(
+                        "The duplex member must be \"half\" for a stream body".to_string(),
+                    ));
+                }
+            }
+            let mut request = r.request.borrow_mut();
+            // Step 37.2
+            if request.mode != NetTraitsRequestMode::SameOrigin &&
+                request.mode != NetTraitsRequestMode::CorsMode
+            {
+                return Err(Error::Type(
+                    "A stream body requires the same-origin or cors mode".to_string(),
+                ));
+            }
+            // Step 37.3
+            request.use_cors_preflight = true;
+        }
 
         // Step 38 is done earlier
 
-        // Step 39
-        // TODO: `ReadableStream` object is not implemented in Servo yet.
-
         // Step 40
         r.request.borrow_mut().body = input_body;
+        r.body_stream.set(input_body_stream.as_deref());
 
         // Step 41
         let extracted_mime_type = r.Headers().extract_mime_type();
@@ -470,9 +525,7 @@ impl Request {
 
     // https://fetch.spec.whatwg.org/#concept-body-locked
     fn locked(&self) -> bool {
-        // TODO: ReadableStream is unimplemented. Just return false
-        // for now.
-        false
+        request_is_locked(self)
     }
 }
 
@@ -508,6 +561,33 @@ impl Request {
     pub fn get_request(&self) -> NetTraitsRequest {
         self.request.borrow().clone()
     }
+
+    /// The stream of the body, if it is a stream rather than bytes.
+    pub fn body_stream(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body_stream.get()
+    }
+
+    /// The fields of the request which the Cache API stores and compares.
+    pub fn cache_request(&self) -> CacheRequest {
+        let request = self.request.borrow();
+        CacheRequest {
+            url: request.url(),
+            method: request.method.as_str().to_owned(),
+            headers: self.Headers().get_headers_list(),
+        }
+    }
+
+    /// Create a request from one stored by the Cache API, whose headers cannot be changed.
+    /// <https://w3c.github.io/ServiceWorker/#cache-keys>
+    pub fn from_cache_request(global: &GlobalScope, cached: CacheRequest) -> DomRoot<Request> {
+        let r = Request::new(global, cached.url);
+        r.request.borrow_mut().method =
+            HttpMethod::from_bytes(cached.method.as_bytes()).unwrap_or(HttpMethod::GET);
+        r.request.borrow_mut().headers = cached.headers.clone();
+        r.Headers().set_headers(cached.headers);
+        r.Headers().set_guard(Guard::Immutable);
+        r
+    }
 }
 
 fn net_request_from_global(global: &GlobalScope, url: ServoUrl) -> NetTraitsRequest {
@@ -558,14 +638,35 @@ fn includes_credentials(input: &ServoUrl) -> bool {
 
 // TODO: `Readable Stream` object is not implemented in Servo yet.
 // https://fetch.spec.whatwg.org/#concept-body-disturbed
-fn request_is_disturbed(_input: &Request) -> bool {
-    false
+fn request_is_disturbed(input: &Request) -> bool {
+    input
+        .body_stream
+        .get()
+        .map_or(false, |stream| stream.is_disturbed())
 }
 
-// TODO: `Readable Stream` object is not implemented in Servo yet.
 // https://fetch.spec.whatwg.org/#concept-body-locked
-fn request_is_locked(_input: &Request) -> bool {
-    false
+fn request_is_locked(input: &Request) -> bool {
+    input
+        .body_stream
+        .get()
+        .map_or(false, |stream| stream.is_locked())
+}
+
+/// The bytes and the content type of a body which is not a stream.
+/// <https://fetch.spec.whatwg.org/#concept-bodyinit-extract>
+fn extract_bytes(body: &ReadableStreamOrBodyInit) -> Option<(Vec<u8>, Option<DOMString>)> {
+    match *body {
+        ReadableStreamOrBodyInit::ReadableStream(_) => None,
+        ReadableStreamOrBodyInit::String(ref s) => Some(s.extract()),
+        ReadableStreamOrBodyInit::URLSearchParams(ref usp) => Some(usp.extract()),
+        ReadableStreamOrBodyInit::Blob(ref b) => Some(b.extract()),
+        ReadableStreamOrBodyInit::FormData(ref formdata) => Some(formdata.extract()),
+        ReadableStreamOrBodyInit::ArrayBuffer(ref typedarray) => Some((typedarray.to_vec(), None)),
+        ReadableStreamOrBodyInit::ArrayBufferView(ref typedarray) => {
+            Some((typedarray.to_vec(), None))
+        },
+    }
 }
 
 impl RequestMethods for Request {
@@ -642,9 +743,14 @@ impl RequestMethods for Request {
         DOMString::from_string(r.integrity_metadata.clone())
     }
 
+    // https://fetch.spec.whatwg.org/#dom-request-keepalive
+    fn Keepalive(&self) -> bool {
+        self.request.borrow().keep_alive
+    }
+
     // https://fetch.spec.whatwg.org/#dom-body-bodyused
     fn BodyUsed(&self) -> bool {
-        self.body_used.get()
+        self.body_used.get() || request_is_disturbed(self)
     }
 
     // https://fetch.spec.whatwg.org/#dom-request-clone
@@ -657,6 +763,13 @@ impl RequestMethods for Request {
             return Err(Error::Type("Request is disturbed".to_string()));
         }
 
+        // TODO: tee the stream of the body.
+        if self.body_stream.get().is_some() {
+            return Err(Error::Type(
+                "Requests with a stream body cannot be cloned".to_string(),
+            ));
+        }
+
         // Step 2
         Request::clone_from(self)
     }
@@ -0,0 +1,267 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ResizeObserverBinding::{
+    ResizeObserverBoxOptions, ResizeObserverCallback, ResizeObserverMethods, ResizeObserverOptions,
+    Wrap,
+};
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::domrectreadonly::DOMRectReadOnly;
+use crate::dom::element::Element;
+use crate::dom::node::{window_from_node, Node, ShadowIncluding};
+use crate::dom::resizeobserverentry::ResizeObserverEntry;
+use crate::dom::resizeobserversize::{BoxSize, ResizeObserverSize};
+use crate::dom::window::Window;
+use app_units::Au;
+use dom_struct::dom_struct;
+use euclid::default::Size2D;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// The box of an element which a `ResizeObserver` watches.
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum ObservedBox {
+    BorderBox,
+    ContentBox,
+    DevicePixelContentBox,
+}
+
+impl From<ResizeObserverBoxOptions> for ObservedBox {
+    fn from(options: ResizeObserverBoxOptions) -> ObservedBox {
+        match options {
+            ResizeObserverBoxOptions::Border_box => ObservedBox::BorderBox,
+            ResizeObserverBoxOptions::Content_box => ObservedBox::ContentBox,
+            ResizeObserverBoxOptions::Device_pixel_content_box => {
+                ObservedBox::DevicePixelContentBox
+            },
+        }
+    }
+}
+
+/// The sizes of the boxes of an element, as the spec's "calculate box size" returns them.
+struct CalculatedBoxSizes {
+    content_rect: (f64, f64, f64, f64),
+    border_box: BoxSize,
+    content_box: BoxSize,
+    device_pixel_content_box: BoxSize,
+}
+
+impl CalculatedBoxSizes {
+    fn size_of(&self, observed_box: ObservedBox) -> BoxSize {
+        match observed_box {
+            ObservedBox::BorderBox => self.border_box,
+            ObservedBox::ContentBox => self.content_box,
+            ObservedBox::DevicePixelContentBox => self.device_pixel_content_box,
+        }
+    }
+}
+
+// FIXME: the inline and block sizes assume a horizontal writing mode.
+fn box_size(size: Size2D<Au>) -> BoxSize {
+    BoxSize {
+        inline_size: size.width.to_f64_px(),
+        block_size: size.height.to_f64_px(),
+    }
+}
+
+/// <https://drafts.csswg.org/resize-observer/#calculate-box-size>
+fn calculate_box_sizes(target: &Element) -> CalculatedBoxSizes {
+    let window = window_from_node(target);
+    let box_sizes = match window.box_sizes_query(target.upcast::<Node>()) {
+        Some(box_sizes) => box_sizes,
+        // Elements which generate no box have a size of zero.
+        None => {
+            return CalculatedBoxSizes {
+                content_rect: (0., 0., 0., 0.),
+                border_box: BoxSize::zero(),
+                content_box: BoxSize::zero(),
+                device_pixel_content_box: BoxSize::zero(),
+            };
+        },
+    };
+    let content_box = box_size(box_sizes.content_box.size);
+    let device_pixel_ratio = window.device_pixel_ratio().get() as f64;
+    CalculatedBoxSizes {
+        content_rect: (
+            box_sizes.content_box.origin.x.to_f64_px(),
+            box_sizes.content_box.origin.y.to_f64_px(),
+            content_box.inline_size,
+            content_box.block_size,
+        ),
+        border_box: box_size(box_sizes.border_box),
+        content_box,
+        device_pixel_content_box: BoxSize {
+            inline_size: (content_box.inline_size * device_pixel_ratio).round(),
+            block_size: (content_box.block_size * device_pixel_ratio).round(),
+        },
+    }
+}
+
+/// <https://drafts.csswg.org/resize-observer/#resize-observation-interface>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct ResizeObservation {
+    target: Dom<Element>,
+    observed_box: ObservedBox,
+    last_reported_size: Cell<BoxSize>,
+}
+
+impl ResizeObservation {
+    /// <https://drafts.csswg.org/resize-observer/#dom-resizeobservation-isactive>
+    fn is_active(&self) -> bool {
+        let current_size = calculate_box_sizes(&self.target).size_of(self.observed_box);
+        current_size != self.last_reported_size.get()
+    }
+}
+
+/// <https://drafts.csswg.org/resize-observer/#calculate-depth-for-node>
+fn depth_of(target: &Element) -> usize {
+    target
+        .upcast::<Node>()
+        .inclusive_ancestors(ShadowIncluding::Yes)
+        .count()
+}
+
+#[dom_struct]
+pub struct ResizeObserver {
+    reflector_: Reflector,
+    #[ignore_malloc_size_of = "can't measure Rc values"]
+    callback: Rc<ResizeObserverCallback>,
+    observation_targets: DomRefCell<Vec<ResizeObservation>>,
+    /// The indices of the observations to broadcast.
+    active_targets: DomRefCell<Vec<usize>>,
+    /// Whether an observation was skipped because it was not deeper than the broadcast depth.
+    has_skipped_targets: Cell<bool>,
+}
+
+impl ResizeObserver {
+    fn new_inherited(callback: Rc<ResizeObserverCallback>) -> ResizeObserver {
+        ResizeObserver {
+            reflector_: Reflector::new(),
+            callback,
+            observation_targets: DomRefCell::new(vec![]),
+            active_targets: DomRefCell::new(vec![]),
+            has_skipped_targets: Cell::new(false),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        window: &Window,
+        callback: Rc<ResizeObserverCallback>,
+    ) -> Fallible<DomRoot<ResizeObserver>> {
+        let observer = reflect_dom_object(
+            Box::new(ResizeObserver::new_inherited(callback)),
+            window,
+            Wrap,
+        );
+        window.Document().add_resize_observer(&observer);
+        Ok(observer)
+    }
+
+    /// <https://drafts.csswg.org/resize-observer/#gather-active-observations-h>
+    pub fn gather_active_observations_at_depth(&self, depth: usize) {
+        let mut active_targets = self.active_targets.borrow_mut();
+        active_targets.clear();
+        self.has_skipped_targets.set(false);
+        for (index, observation) in self.observation_targets.borrow().iter().enumerate() {
+            if !observation.is_active() {
+                continue;
+            }
+            if depth_of(&observation.target) > depth {
+                active_targets.push(index);
+            } else {
+                self.has_skipped_targets.set(true);
+            }
+        }
+    }
+
+    pub fn has_active_observations(&self) -> bool {
+        !self.active_targets.borrow().is_empty()
+    }
+
+    pub fn has_skipped_observations(&self) -> bool {
+        self.has_skipped_targets.get()
+    }
+
+    /// Delivers the entries of the active observations to the callback, and returns the depth
+    /// of the shallowest target which was broadcast.
+    /// <https://drafts.csswg.org/resize-observer/#broadcast-active-observations>
+    pub fn broadcast_active_observations(&self, window: &Window) -> Option<usize> {
+        let active_targets: Vec<usize> = self.active_targets.borrow_mut().drain(..).collect();
+        if active_targets.is_empty() {
+            return None;
+        }
+        let mut shallowest_target_depth = usize::max_value();
+        let mut entries = vec![];
+        for index in active_targets {
+            let target = DomRoot::from_ref(&*self.observation_targets.borrow()[index].target);
+            let sizes = calculate_box_sizes(&target);
+            let (x, y, width, height) = sizes.content_rect;
+            let content_rect = DOMRectReadOnly::new(window.upcast(), x, y, width, height);
+            let border_box_size = ResizeObserverSize::new(window, sizes.border_box);
+            let content_box_size = ResizeObserverSize::new(window, sizes.content_box);
+            let device_pixel_content_box_size =
+                ResizeObserverSize::new(window, sizes.device_pixel_content_box);
+            entries.push(ResizeObserverEntry::new(
+                window,
+                &target,
+                &content_rect,
+                &[border_box_size],
+                &[content_box_size],
+                &[device_pixel_content_box_size],
+            ));
+            let observation = &self.observation_targets.borrow()[index];
+            observation
+                .last_reported_size
+                .set(sizes.size_of(observation.observed_box));
+            shallowest_target_depth = shallowest_target_depth.min(depth_of(&target));
+        }
+        let _ = self
+            .callback
+            .Call_(self, entries, self, ExceptionHandling::Report);
+        Some(shallowest_target_depth)
+    }
+
+    fn remove_observation(&self, target: &Element) {
+        self.active_targets.borrow_mut().clear();
+        self.observation_targets
+            .borrow_mut()
+            .retain(|observation| &*observation.target != target);
+    }
+}
+
+impl ResizeObserverMethods for ResizeObserver {
+    // https://drafts.csswg.org/resize-observer/#dom-resizeobserver-observe
+    fn Observe(&self, target: &Element, options: &ResizeObserverOptions) {
+        // Step 1-2.
+        self.remove_observation(target);
+        // Step 3-4.
+        self.observation_targets
+            .borrow_mut()
+            .push(ResizeObservation {
+                target: Dom::from_ref(target),
+                observed_box: options.box_.into(),
+                last_reported_size: Cell::new(BoxSize::zero()),
+            });
+    }
+
+    // https://drafts.csswg.org/resize-observer/#dom-resizeobserver-unobserve
+    fn Unobserve(&self, target: &Element) {
+        self.remove_observation(target);
+    }
+
+    // https://drafts.csswg.org/resize-observer/#dom-resizeobserver-disconnect
+    fn Disconnect(&self) {
+        self.observation_targets.borrow_mut().clear();
+        self.active_targets.borrow_mut().clear();
+        self.has_skipped_targets.set(false);
+    }
+}
@@ -0,0 +1,63 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ResizeObserverSizeBinding::{
+    ResizeObserverSizeMethods, Wrap,
+};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+
+/// The size of a box, in the writing mode of its element.
+/// <https://drafts.csswg.org/resize-observer/#resizeobserversize>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub struct BoxSize {
+    pub inline_size: f64,
+    pub block_size: f64,
+}
+
+impl BoxSize {
+    pub fn zero() -> BoxSize {
+        BoxSize {
+            inline_size: 0.,
+            block_size: 0.,
+        }
+    }
+}
+
+#[dom_struct]
+pub struct ResizeObserverSize {
+    reflector_: Reflector,
+    size: BoxSize,
+}
+
+impl ResizeObserverSize {
+    fn new_inherited(size: BoxSize) -> ResizeObserverSize {
+        ResizeObserverSize {
+            reflector_: Reflector::new(),
+            size,
+        }
+    }
+
+    pub fn new(window: &Window, size: BoxSize) -> DomRoot<ResizeObserverSize> {
+        reflect_dom_object(
+            Box::new(ResizeObserverSize::new_inherited(size)),
+            window,
+            Wrap,
+        )
+    }
+}
+
+impl ResizeObserverSizeMethods for ResizeObserverSize {
+    // https://drafts.csswg.org/resize-observer/#dom-resizeobserversize-inlinesize
+    fn InlineSize(&self) -> f64 {
+        self.size.inline_size
+    }
+
+    // https://drafts.csswg.org/resize-observer/#dom-resizeobserversize-blocksize
+    fn BlockSize(&self) -> f64 {
+        self.size.block_size
+    }
+}
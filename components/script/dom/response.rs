@@ -18,6 +18,7 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::{is_obs_text, is_vchar};
 use crate::dom::headers::{Guard, Headers};
 use crate::dom::promise::Promise;
+use crate::dom::readablestream::ReadableStream;
 use crate::dom::xmlhttprequest::Extractable;
 use crate::script_runtime::StreamConsumer;
 use dom_struct::dom_struct;
@@ -25,6 +26,9 @@ use http::header::HeaderMap as HyperHeaders;
 use hyper::StatusCode;
 use hyper_serde::Serde;
 use net_traits::response::ResponseBody as NetTraitsResponseBody;
+use net_traits::response::ResponseType as NetTraitsResponseType;
+use net_traits::storage_thread::CacheResponse;
+use net_traits::NetworkError;
 use servo_url::ServoUrl;
 use std::cell::Cell;
 use std::mem;
@@ -47,6 +51,8 @@ pub struct Response {
     url_list: DomRefCell<Vec<ServoUrl>>,
     // For now use the existing NetTraitsResponseBody enum
     body: DomRefCell<NetTraitsResponseBody>,
+    /// The stream of the body, which is created the first time it is got.
+    body_stream: MutNullableDom<ReadableStream>,
     #[ignore_malloc_size_of = "Rc"]
     body_promise: DomRefCell<Option<(Rc<Promise>, BodyType)>>,
     #[ignore_malloc_size_of = "StreamConsumer"]
@@ -67,6 +73,7 @@ impl Response {
             url: DomRefCell::new(None),
             url_list: DomRefCell::new(vec![]),
             body: DomRefCell::new(NetTraitsResponseBody::Empty),
+            body_stream: Default::default(),
             body_promise: DomRefCell::new(None),
             stream_consumer: DomRefCell::new(None),
         }
@@ -215,9 +222,9 @@ impl Response {
 
     // https://fetch.spec.whatwg.org/#concept-body-locked
     fn locked(&self) -> bool {
-        // TODO: ReadableStream is unimplemented. Just return false
-        // for now.
-        false
+        self.body_stream
+            .get()
+            .map_or(false, |stream| stream.is_locked())
     }
 }
 
@@ -230,6 +237,10 @@ impl BodyOperations for Response {
         assert!(self.body_promise.borrow().is_none());
         self.body_used.set(true);
         *self.body_promise.borrow_mut() = Some((p.clone(), body_type));
+        // The body is read by the promise rather than by the stream, which is locked.
+        if let Some(stream) = self.body_stream.get() {
+            let _ = stream.acquire_default_reader();
+        }
     }
 
     fn is_locked(&self) -> bool {
@@ -331,7 +342,7 @@ impl ResponseMethods for Response {
     // https://fetch.spec.whatwg.org/#dom-response-clone
     fn Clone(&self) -> Fallible<DomRoot<Response>> {
         // Step 1
-        if self.is_locked() || self.body_used.get() {
+        if self.is_locked() || self.BodyUsed() {
             return Err(Error::Type("cannot clone a disturbed response".to_string()));
         }
 
@@ -364,7 +375,30 @@ impl ResponseMethods for Response {
 
     // https://fetch.spec.whatwg.org/#dom-body-bodyused
     fn BodyUsed(&self) -> bool {
-        self.body_used.get()
+        self.body_used.get() ||
+            self.body_stream
+                .get()
+                .map_or(false, |stream| stream.is_disturbed())
+    }
+
+    // https://fetch.spec.whatwg.org/#dom-body-body
+    fn GetBody(&self) -> Option<DomRoot<ReadableStream>> {
+        if let Some(stream) = self.body_stream.get() {
+            return Some(stream);
+        }
+        let stream = match *self.body.borrow() {
+            NetTraitsResponseBody::Empty => return None,
+            NetTraitsResponseBody::Receiving(ref bytes) => {
+                let stream = ReadableStream::new_byte_stream(&self.global());
+                stream.enqueue_native(bytes.clone());
+                stream
+            },
+            NetTraitsResponseBody::Done(ref bytes) => {
+                ReadableStream::new_from_bytes(&self.global(), bytes.clone())
+            },
+        };
+        self.body_stream.set(Some(&stream));
+        Some(stream)
     }
 
     // https://fetch.spec.whatwg.org/#dom-body-text
@@ -398,6 +432,62 @@ fn serialize_without_fragment(url: &ServoUrl) -> &str {
 }
 
 impl Response {
+    /// Whether the body is null, rather than empty.
+    pub fn has_null_body(&self) -> bool {
+        *self.body.borrow() == NetTraitsResponseBody::Empty
+    }
+
+    /// The fields of the response which the Cache API stores, with its body once it is
+    /// read in full.
+    pub fn cache_response(&self, body: Vec<u8>) -> CacheResponse {
+        let mut url_list = self.url_list.borrow().clone();
+        if url_list.is_empty() {
+            url_list.extend(self.url.borrow().clone());
+        }
+        let response_type = match *self.response_type.borrow() {
+            DOMResponseType::Basic => NetTraitsResponseType::Basic,
+            DOMResponseType::Cors => NetTraitsResponseType::Cors,
+            DOMResponseType::Default => NetTraitsResponseType::Default,
+            DOMResponseType::Error => NetTraitsResponseType::Error(NetworkError::Internal(
+                "Cached network error".to_owned(),
+            )),
+            DOMResponseType::Opaque => NetTraitsResponseType::Opaque,
+            DOMResponseType::Opaqueredirect => NetTraitsResponseType::OpaqueRedirect,
+        };
+        CacheResponse {
+            response_type,
+            url_list,
+            status: self.raw_status.borrow().clone(),
+            headers: self.Headers().get_headers_list(),
+            body,
+        }
+    }
+
+    /// Create a response from one stored by the Cache API, whose headers cannot be changed.
+    /// <https://w3c.github.io/ServiceWorker/#cache-match>
+    pub fn from_cache_response(global: &GlobalScope, cached: CacheResponse) -> DomRoot<Response> {
+        let r = Response::new(global);
+        *r.response_type.borrow_mut() = match cached.response_type {
+            NetTraitsResponseType::Basic => DOMResponseType::Basic,
+            NetTraitsResponseType::Cors => DOMResponseType::Cors,
+            NetTraitsResponseType::Default => DOMResponseType::Default,
+            NetTraitsResponseType::Error(_) => DOMResponseType::Error,
+            NetTraitsResponseType::Opaque => DOMResponseType::Opaque,
+            NetTraitsResponseType::OpaqueRedirect => DOMResponseType::Opaqueredirect,
+        };
+        r.set_headers(Some(Serde(cached.headers)));
+        r.Headers().set_guard(Guard::Immutable);
+        *r.status.borrow_mut() = cached
+            .status
+            .as_ref()
+            .and_then(|&(code, _)| StatusCode::from_u16(code).ok());
+        r.set_raw_status(cached.status);
+        *r.url.borrow_mut() = cached.url_list.last().cloned();
+        *r.url_list.borrow_mut() = cached.url_list;
+        *r.body.borrow_mut() = NetTraitsResponseBody::Done(cached.body);
+        r
+    }
+
     pub fn set_type(&self, new_response_type: DOMResponseType) {
         *self.response_type.borrow_mut() = new_response_type;
         self.set_response_members_by_type(new_response_type);
@@ -446,19 +536,76 @@ impl Response {
         }
     }
 
-    pub fn set_stream_consumer(&self, sc: Option<StreamConsumer>) {
-        *self.stream_consumer.borrow_mut() = sc;
+    /// Consume the body with the compiler of a WebAssembly module, which receives the bytes
+    /// that were already received, and then those which are streamed.
+    /// <https://webassembly.github.io/spec/web-api/#compile-a-potential-webassembly-response>
+    pub fn set_stream_consumer(&self, stream_consumer: StreamConsumer) {
+        // Step 2.6.3. The body is read by the consumer rather than by the stream, which is
+        // locked.
+        self.body_used.set(true);
+        if let Some(stream) = self.body_stream.get() {
+            let _ = stream.acquire_default_reader();
+        }
+
+        match *self.body.borrow() {
+            // A null body is compiled as an empty module.
+            NetTraitsResponseBody::Empty => {
+                stream_consumer.stream_end();
+                return;
+            },
+            NetTraitsResponseBody::Receiving(ref bytes) => {
+                stream_consumer.consume_chunk(bytes);
+            },
+            NetTraitsResponseBody::Done(ref bytes) => {
+                stream_consumer.consume_chunk(bytes);
+                stream_consumer.stream_end();
+                return;
+            },
+        }
+        *self.stream_consumer.borrow_mut() = Some(stream_consumer);
+    }
+
+    /// Abort the compilation of the WebAssembly module which consumes the body, whose fetch
+    /// failed.
+    pub fn abort_stream_consumer(&self) {
+        if let Some(stream_consumer) = self.stream_consumer.borrow_mut().take() {
+            stream_consumer.stream_error(0);
+        }
     }
 
-    pub fn stream_chunk(&self, stream: &[u8]) {
+    /// Start receiving the body of a fetched response whose body is not filtered out.
+    pub fn start_receiving_body(&self) {
+        let mut body = self.body.borrow_mut();
+        if *body == NetTraitsResponseBody::Empty {
+            *body = NetTraitsResponseBody::Receiving(vec![]);
+        }
+    }
+
+    pub fn stream_chunk(&self, chunk: Vec<u8>) {
         if let Some(stream_consumer) = self.stream_consumer.borrow_mut().as_ref() {
-            stream_consumer.consume_chunk(stream);
+            stream_consumer.consume_chunk(&chunk);
+        }
+        match *self.body.borrow_mut() {
+            NetTraitsResponseBody::Receiving(ref mut body) => body.extend_from_slice(&chunk),
+            _ => return,
+        }
+        if let Some(stream) = self.body_stream.get() {
+            stream.enqueue_native(chunk);
         }
     }
 
     #[allow(unrooted_must_root)]
-    pub fn finish(&self, body: Vec<u8>) {
-        *self.body.borrow_mut() = NetTraitsResponseBody::Done(body);
+    pub fn finish(&self) {
+        let body = mem::replace(&mut *self.body.borrow_mut(), NetTraitsResponseBody::Empty);
+        *self.body.borrow_mut() = match body {
+            NetTraitsResponseBody::Empty => NetTraitsResponseBody::Done(vec![]),
+            NetTraitsResponseBody::Receiving(bytes) | NetTraitsResponseBody::Done(bytes) => {
+                NetTraitsResponseBody::Done(bytes)
+            },
+        };
+        if let Some(stream) = self.body_stream.get() {
+            stream.close_native();
+        }
         if let Some((p, body_type)) = self.body_promise.borrow_mut().take() {
             consume_body_with_promise(self, body_type, &p);
         }
@@ -81,6 +81,10 @@ impl ServiceWorker {
         ServoUrl::parse(&self.script_url.borrow().clone()).unwrap()
     }
 
+    pub fn get_scope_url(&self) -> ServoUrl {
+        self.scope_url.clone()
+    }
+
     /// https://w3c.github.io/ServiceWorker/#service-worker-postmessage
     fn post_message_impl(
         &self,
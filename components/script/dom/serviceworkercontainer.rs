@@ -6,19 +6,26 @@ use crate::dom::bindings::codegen::Bindings::ServiceWorkerContainerBinding::Regi
 use crate::dom::bindings::codegen::Bindings::ServiceWorkerContainerBinding::{
     ServiceWorkerContainerMethods, Wrap,
 };
+use crate::dom::bindings::codegen::UnionTypes::WindowProxyOrMessagePortOrServiceWorker;
 use crate::dom::bindings::error::Error;
+use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
-use crate::dom::bindings::str::USVString;
+use crate::dom::bindings::str::{DOMString, USVString};
+use crate::dom::bindings::structuredclone;
 use crate::dom::client::Client;
+use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::messageevent::MessageEvent;
 use crate::dom::promise::Promise;
 use crate::dom::serviceworker::ServiceWorker;
-use crate::realms::InRealm;
+use crate::realms::{enter_realm, InRealm};
 use crate::script_thread::ScriptThread;
 use crate::serviceworkerjob::{Job, JobType};
 use dom_struct::dom_struct;
+use js::jsval::UndefinedValue;
+use script_traits::DOMMessage;
 use std::default::Default;
 use std::rc::Rc;
 
@@ -44,6 +51,46 @@ impl ServiceWorkerContainer {
         let container = ServiceWorkerContainer::new_inherited(&*client);
         reflect_dom_object(Box::new(container), global, Wrap)
     }
+
+    /// Make a service worker which claimed the client its controller.
+    /// <https://w3c.github.io/ServiceWorker/#clients-claim>
+    pub fn claim(&self, worker: &ServiceWorker) {
+        if self.client.get_controller() == Some(DomRoot::from_ref(worker)) {
+            return;
+        }
+        self.client.set_controller(worker);
+        // https://w3c.github.io/ServiceWorker/#notify-controller-change-algorithm
+        self.upcast::<EventTarget>()
+            .fire_event(atom!("controllerchange"));
+    }
+
+    /// Dispatch a message posted to the client by a service worker.
+    /// <https://w3c.github.io/ServiceWorker/#client-postmessage>
+    pub fn dispatch_message(&self, source: &ServiceWorker, message: DOMMessage) {
+        let global = self.global();
+        let target = self.upcast::<EventTarget>();
+        let _ac = enter_realm(&*global);
+        rooted!(in(*global.get_cx()) let mut message_clone = UndefinedValue());
+        let DOMMessage { origin, data } = message;
+        let ports = match structuredclone::read(&global, data, message_clone.handle_mut()) {
+            Ok(ports) => ports,
+            Err(_) => return MessageEvent::dispatch_error(target, &global),
+        };
+        let source =
+            WindowProxyOrMessagePortOrServiceWorker::ServiceWorker(DomRoot::from_ref(source));
+        let event = MessageEvent::new(
+            &global,
+            atom!("message"),
+            false,
+            false,
+            message_clone.handle(),
+            DOMString::from(origin.ascii_serialization()),
+            Some(&source),
+            DOMString::new(),
+            ports,
+        );
+        event.upcast::<Event>().fire(target);
+    }
 }
 
 impl ServiceWorkerContainerMethods for ServiceWorkerContainer {
@@ -63,6 +110,12 @@ impl ServiceWorkerContainerMethods for ServiceWorkerContainer {
     ) -> Rc<Promise> {
         // A: Step 1
         let promise = Promise::new_in_current_realm(&*self.global(), comp);
+        // The service workers outlive the session, so they aren't available in private
+        // browsing, where nothing is kept once the browser is closed.
+        if self.global().is_private() {
+            promise.reject_error(Error::Security);
+            return promise;
+        }
         let USVString(ref script_url) = script_url;
         let api_base_url = self.global().api_base_url();
         // A: Step 3-5
@@ -135,4 +188,17 @@ impl ServiceWorkerContainerMethods for ServiceWorkerContainer {
         ScriptThread::schedule_job(job);
         promise
     }
+
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkercontainer-oncontrollerchange
+    event_handler!(
+        controllerchange,
+        GetOncontrollerchange,
+        SetOncontrollerchange
+    );
+
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkercontainer-onmessage
+    event_handler!(message, GetOnmessage, SetOnmessage);
+
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkercontainer-onmessageerror
+    event_handler!(messageerror, GetOnmessageerror, SetOnmessageerror);
 }
@@ -9,9 +9,10 @@ use crate::dom::bindings::codegen::Bindings::ServiceWorkerGlobalScopeBinding;
 use crate::dom::bindings::codegen::Bindings::ServiceWorkerGlobalScopeBinding::ServiceWorkerGlobalScopeMethods;
 use crate::dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::root::{DomRoot, RootCollection, ThreadLocalStackRoots};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom, RootCollection, ThreadLocalStackRoots};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::bindings::structuredclone;
+use crate::dom::clients::Clients;
 use crate::dom::dedicatedworkerglobalscope::AutoWorkerReset;
 use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
@@ -162,6 +163,8 @@ pub struct ServiceWorkerGlobalScope {
     swmanager_sender: IpcSender<ServiceWorkerMsg>,
 
     scope_url: ServoUrl,
+
+    clients: MutNullableDom<Clients>,
 }
 
 impl WorkerEventLoopMethods for ServiceWorkerGlobalScope {
@@ -216,6 +219,7 @@ impl ServiceWorkerGlobalScope {
             time_out_port,
             swmanager_sender: swmanager_sender,
             scope_url: scope_url,
+            clients: Default::default(),
         }
     }
 
@@ -432,6 +436,21 @@ impl ServiceWorkerGlobalScope {
         }
     }
 
+    pub fn scope_url(&self) -> &ServoUrl {
+        &self.scope_url
+    }
+
+    /// Send a message to the service worker manager, which forwards the ones about the
+    /// clients of the service worker to the constellation.
+    pub fn send_to_manager(&self, msg: ServiceWorkerMsg) {
+        if let Err(e) = self.swmanager_sender.send(msg) {
+            warn!(
+                "Failed to send a message to the service worker manager ({:?}).",
+                e
+            );
+        }
+    }
+
     pub fn script_chan(&self) -> Box<dyn ScriptChan + Send> {
         Box::new(ServiceWorkerChan {
             sender: self.own_sender.clone(),
@@ -458,6 +477,11 @@ unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
 }
 
 impl ServiceWorkerGlobalScopeMethods for ServiceWorkerGlobalScope {
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-clients
+    fn Clients(&self) -> DomRoot<Clients> {
+        self.clients.or_init(|| Clients::new(self.upcast()))
+    }
+
     // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-onmessage
     event_handler!(message, GetOnmessage, SetOnmessage);
 
@@ -49,6 +49,7 @@ impl Tokenizer {
             current_line: 1,
             script: Default::default(),
             parsing_algorithm: parsing_algorithm,
+            first_parse_error: None,
         };
 
         let options = TreeBuilderOpts {
@@ -36,6 +36,7 @@ use crate::dom::text::Text;
 use crate::dom::virtualmethods::vtable_for;
 use crate::network_listener::PreInvoke;
 use crate::script_thread::ScriptThread;
+use crate::security_manager::parse_report_to_header;
 use content_security_policy::{self as csp, CspList};
 use dom_struct::dom_struct;
 use embedder_traits::resources::{self, Resource};
@@ -256,6 +257,33 @@ impl ServoParser {
         parser.parse_string_chunk(String::from(input));
     }
 
+    /// Parse an XML document whose whole source is `input`, returning the first
+    /// error that makes it not well-formed, if any.
+    pub fn parse_complete_xml_document(
+        document: &Document,
+        input: DOMString,
+        url: ServoUrl,
+    ) -> Result<(), String> {
+        let parser = ServoParser::new(
+            document,
+            Tokenizer::Xml(self::xml::Tokenizer::new(document, url)),
+            LastChunkState::Received,
+            ParserKind::Normal,
+        );
+        parser.parse_string_chunk(String::from(input));
+        let error = match *parser.tokenizer.borrow() {
+            Tokenizer::Xml(ref tokenizer) => tokenizer.first_parse_error().map(str::to_owned),
+            _ => unreachable!(),
+        };
+        match error {
+            Some(error) => Err(error),
+            None if document.GetDocumentElement().is_none() => {
+                Err("No root element found".to_owned())
+            },
+            None => Ok(()),
+        }
+    }
+
     pub fn script_nesting_level(&self) -> usize {
         self.script_nesting_level.get()
     }
@@ -314,15 +342,21 @@ impl ServoParser {
     pub fn write(&self, text: Vec<DOMString>) {
         assert!(self.can_write());
 
+        let text: Vec<StrTendril> = text
+            .into_iter()
+            .map(|chunk| String::from(chunk).into())
+            .collect();
+
+        let markup: String = text.iter().map(|chunk| &**chunk).collect();
+
         if self.document.has_pending_parsing_blocking_script() {
             // There is already a pending parsing blocking script so the
             // parser is suspended, we just append everything to the
             // script input and abort these steps.
             for chunk in text {
-                self.script_input
-                    .borrow_mut()
-                    .push_back(String::from(chunk).into());
+                self.script_input.borrow_mut().push_back(chunk);
             }
+            self.prefetch_written(&markup, 0);
             return;
         }
 
@@ -333,7 +367,7 @@ impl ServoParser {
 
         let mut input = BufferQueue::new();
         for chunk in text {
-            input.push_back(String::from(chunk).into());
+            input.push_back(chunk);
         }
 
         self.tokenize(|tokenizer| tokenizer.feed(&mut input));
@@ -342,13 +376,74 @@ impl ServoParser {
             // Parser got suspended, insert remaining input at end of
             // script input, following anything written by scripts executed
             // reentrantly during this call.
+            let mut unparsed = 0;
             while let Some(chunk) = input.pop_front() {
+                unparsed += chunk.len();
                 self.script_input.borrow_mut().push_back(chunk);
             }
+            self.prefetch_written(&markup, markup.len() - unparsed);
             return;
         }
 
         assert!(input.is_empty());
+
+        self.prefetch_written(&markup, markup.len());
+    }
+
+    /// Prefetch the resources in markup written by a script, of which the first
+    /// `parsed` bytes were already parsed.
+    ///
+    /// The prefetch tokenizer scanned the input following the insertion point as
+    /// if nothing was inserted before it, which stays true unless the markup
+    /// changes how that input is tokenized, for instance by opening a comment.
+    /// In that case, the input which is left to parse is scanned again.
+    fn prefetch_written(&self, markup: &str, parsed: usize) {
+        if self.document.browsing_context().is_none() || !markup.contains('<') {
+            return;
+        }
+        if self
+            .prefetch_tokenizer
+            .borrow_mut()
+            .scan_inserted(markup, parsed)
+        {
+            return;
+        }
+        let consumed = if parsed == markup.len() { markup } else { "" };
+        self.restart_prefetching(consumed);
+    }
+
+    /// Throw away the state of the prefetch tokenizer, and scan the input which
+    /// is left to parse again, from the insertion point.
+    ///
+    /// `consumed` is the markup which was written at the insertion point and
+    /// already parsed. It is skipped first, so that the prefetch tokenizer is
+    /// in the same state as the main one.
+    fn restart_prefetching(&self, consumed: &str) {
+        let mut tokenizer = self.prefetch_tokenizer.borrow_mut().restart();
+        let mut consumed_input = BufferQueue::new();
+        consumed_input.push_back(consumed.into());
+        tokenizer.skip(&mut consumed_input);
+
+        // The remaining input is the script input followed by the network
+        // input, which we copy without consuming it.
+        let mut prefetch_input = BufferQueue::new();
+        for input in &[&self.script_input, &self.network_input] {
+            let mut input = input.borrow_mut();
+            let mut remaining = BufferQueue::new();
+            while let Some(chunk) = input.pop_front() {
+                prefetch_input.push_back(chunk.clone());
+                remaining.push_back(chunk);
+            }
+            *input = remaining;
+        }
+
+        // The main parser is running a script, which is what blocks it, so
+        // it's worth prefetching right away.
+        tokenizer.start_prefetching();
+        tokenizer.feed(&mut prefetch_input);
+
+        *self.prefetch_tokenizer.borrow_mut() = tokenizer;
+        *self.prefetch_input.borrow_mut() = prefetch_input;
     }
 
     // Steps 4-6 of https://html.spec.whatwg.org/multipage/#dom-document-close
@@ -759,25 +854,32 @@ impl FetchResponseListener for ParserContext {
         // TODO: Implement step 1 (local scheme special case)
         let csp_list = metadata.as_ref().and_then(|m| {
             let h = m.headers.as_ref()?;
-            let mut csp = h.get_all("content-security-policy").iter();
-            // This silently ignores the CSP if it contains invalid Unicode.
-            // We should probably report an error somewhere.
-            let c = csp.next().and_then(|c| c.to_str().ok())?;
-            let mut csp_list = CspList::parse(
-                c,
-                csp::PolicySource::Header,
-                csp::PolicyDisposition::Enforce,
-            );
-            for c in csp {
+            let csp = h
+                .get_all("content-security-policy")
+                .iter()
+                .map(|c| (c, csp::PolicyDisposition::Enforce));
+            let csp_report_only = h
+                .get_all("content-security-policy-report-only")
+                .iter()
+                .map(|c| (c, csp::PolicyDisposition::Report));
+            let mut csp_list: Option<CspList> = None;
+            for (c, disposition) in csp.chain(csp_report_only) {
+                // This silently ignores the CSP if it contains invalid Unicode.
+                // We should probably report an error somewhere.
                 let c = c.to_str().ok()?;
-                csp_list.append(CspList::parse(
-                    c,
-                    csp::PolicySource::Header,
-                    csp::PolicyDisposition::Enforce,
-                ));
+                let policies = CspList::parse(c, csp::PolicySource::Header, disposition);
+                match csp_list {
+                    Some(ref mut csp_list) => csp_list.append(policies),
+                    None => csp_list = Some(policies),
+                }
             }
-            Some(csp_list)
+            csp_list
         });
+        let reporting_endpoints = metadata
+            .as_ref()
+            .and_then(|m| m.headers.as_ref())
+            .map(|h| parse_report_to_header(h))
+            .unwrap_or_default();
 
         let parser = match ScriptThread::page_headers_available(&self.id, metadata) {
             Some(parser) => parser,
@@ -788,6 +890,7 @@ impl FetchResponseListener for ParserContext {
         }
 
         parser.document.set_csp_list(csp_list);
+        parser.document.set_reporting_endpoints(reporting_endpoints);
 
         self.parser = Some(Trusted::new(&*parser));
 
@@ -912,6 +1015,10 @@ impl FetchResponseListener for ParserContext {
         }
     }
 
+    fn process_csp_violations(&mut self, _violations: Vec<csp::Violation>) {
+        // Navigation requests are not checked against the policies of a document.
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
@@ -996,6 +1103,8 @@ pub struct Sink {
     current_line: u64,
     script: MutNullableDom<HTMLScriptElement>,
     parsing_algorithm: ParsingAlgorithm,
+    /// The message of the first parse error, which makes XML documents not well-formed.
+    first_parse_error: Option<String>,
 }
 
 impl Sink {
@@ -1127,6 +1236,9 @@ impl TreeSink for Sink {
 
     fn parse_error(&mut self, msg: Cow<'static, str>) {
         debug!("Parse error: {}", msg);
+        if self.first_parse_error.is_none() {
+            self.first_parse_error = Some(msg.into_owned());
+        }
     }
 
     fn set_quirks_mode(&mut self, mode: QuirksMode) {
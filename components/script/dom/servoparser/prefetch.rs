@@ -6,6 +6,7 @@ use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::trace::JSTraceable;
 use crate::dom::document::{determine_policy_for_token, Document};
 use crate::dom::htmlimageelement::{image_fetch_request, FromPictureOrSrcSet};
+use crate::dom::htmllinkelement::{preload_fetch_request, translate_preload_destination};
 use crate::dom::htmlscriptelement::script_fetch_request;
 use crate::stylesheet_loader::stylesheet_fetch_request;
 use html5ever::buffer_queue::BufferQueue;
@@ -22,7 +23,9 @@ use html5ever::LocalName;
 use js::jsapi::JSTracer;
 use msg::constellation_msg::PipelineId;
 use net_traits::request::CorsSettings;
+use net_traits::request::Initiator;
 use net_traits::request::Referrer;
+use net_traits::request::RequestBuilder;
 use net_traits::CoreResourceMsg;
 use net_traits::FetchChannels;
 use net_traits::IpcSend;
@@ -30,6 +33,8 @@ use net_traits::ReferrerPolicy;
 use net_traits::ResourceThreads;
 use servo_url::ImmutableOrigin;
 use servo_url::ServoUrl;
+use std::collections::HashSet;
+use std::mem;
 
 #[derive(JSTraceable, MallocSizeOf)]
 #[unrooted_must_root_lint::must_root]
@@ -50,7 +55,9 @@ impl Tokenizer {
         let sink = PrefetchSink {
             origin: document.origin().immutable().clone(),
             pipeline_id: document.global().pipeline_id(),
-            base_url: None,
+            // If the document already has a base URL, it wins over any `base` element found
+            // in the input.
+            base_url: document.base_element().map(|base| base.frozen_base_url()),
             document_url: document.url(),
             referrer: Referrer::ReferrerUrl(document.url()),
             referrer_policy: document.get_referrer_policy(),
@@ -59,15 +66,73 @@ impl Tokenizer {
             // true after the first script tag, since that is what will
             // block the main parser.
             prefetching: false,
+            prefetched: HashSet::new(),
+            probed: false,
         };
         let options = Default::default();
         let inner = HtmlTokenizer::new(sink, options);
         Tokenizer { inner }
     }
 
+    /// A tokenizer in the data state, which is the state of the main tokenizer at the
+    /// insertion point of `document.write`, right after the end tag of the script
+    /// which is running. The resources this tokenizer already requested are not
+    /// requested again.
+    pub fn restart(&mut self) -> Tokenizer {
+        let sink = PrefetchSink {
+            origin: self.inner.sink.origin.clone(),
+            pipeline_id: self.inner.sink.pipeline_id,
+            document_url: self.inner.sink.document_url.clone(),
+            base_url: self.inner.sink.base_url.clone(),
+            referrer: self.inner.sink.referrer.clone(),
+            referrer_policy: self.inner.sink.referrer_policy,
+            resource_threads: self.inner.sink.resource_threads.clone(),
+            prefetching: false,
+            prefetched: mem::replace(&mut self.inner.sink.prefetched, HashSet::new()),
+            probed: false,
+        };
+        let inner = HtmlTokenizer::new(sink, Default::default());
+        Tokenizer { inner }
+    }
+
+    /// Scan markup inserted by `document.write`, of which the first `parsed` bytes were
+    /// already parsed by the main parser, and prefetch the resources in the rest.
+    ///
+    /// Returns whether the tokenizer is back to the data state at the end of the markup,
+    /// in which case the input following the markup is tokenized as if it wasn't
+    /// inserted, and this tokenizer's scan of it is still valid.
+    pub fn scan_inserted(&mut self, markup: &str, parsed: usize) -> bool {
+        let mut scanner = self.restart();
+        let (parsed, unparsed) = markup.split_at(parsed);
+        let mut input = BufferQueue::new();
+        input.push_back(parsed.into());
+        scanner.skip(&mut input);
+        input.push_back(unparsed.into());
+        scanner.start_prefetching();
+        scanner.feed(&mut input);
+        // The probe is only emitted as a start tag from the data state.
+        input.push_back(format!("<{}>", PROBE).into());
+        scanner.feed(&mut input);
+        self.inner.sink.prefetched = scanner.inner.sink.prefetched;
+        scanner.inner.sink.probed
+    }
+
     pub fn feed(&mut self, input: &mut BufferQueue) {
         while let TokenizerResult::Script(PrefetchHandle) = self.inner.feed(input) {}
     }
+
+    /// Tokenize input which the main parser has already parsed, without prefetching the
+    /// resources found in it, so that this tokenizer ends up in the same state.
+    pub fn skip(&mut self, input: &mut BufferQueue) {
+        self.inner.sink.prefetching = false;
+        while let TokenizerResult::Script(PrefetchHandle) = self.inner.feed(input) {
+            self.inner.sink.prefetching = false;
+        }
+    }
+
+    pub fn start_prefetching(&mut self) {
+        self.inner.sink.prefetching = true;
+    }
 }
 
 #[derive(JSTraceable)]
@@ -80,8 +145,16 @@ struct PrefetchSink {
     referrer_policy: Option<ReferrerPolicy>,
     resource_threads: ResourceThreads,
     prefetching: bool,
+    /// The URLs which were already requested, so that scanning input again doesn't
+    /// issue duplicate fetches.
+    prefetched: HashSet<ServoUrl>,
+    /// Whether the start tag of `PROBE` was emitted.
+    probed: bool,
 }
 
+/// The name of a tag fed to a tokenizer to find whether it is in the data state.
+const PROBE: &'static str = "servo-prefetch-probe";
+
 /// The prefetch tokenizer produces trivial results
 struct PrefetchHandle;
 
@@ -113,10 +186,9 @@ impl TokenSink for PrefetchSink {
                         self.referrer.clone(),
                         self.referrer_policy,
                         integrity_metadata,
-                    );
-                    let _ = self
-                        .resource_threads
-                        .send(CoreResourceMsg::Fetch(request, FetchChannels::Prefetch));
+                    )
+                    .initiator(Initiator::SpeculativeParse);
+                    self.prefetch(request);
                 }
                 TokenSinkResult::RawData(RawKind::ScriptData)
             },
@@ -130,10 +202,9 @@ impl TokenSink for PrefetchSink {
                         self.get_cors_settings(tag, local_name!("crossorigin")),
                         self.get_referrer_policy(tag, local_name!("referrerpolicy")),
                         FromPictureOrSrcSet::No,
-                    );
-                    let _ = self
-                        .resource_threads
-                        .send(CoreResourceMsg::Fetch(request, FetchChannels::Prefetch));
+                    )
+                    .initiator(Initiator::SpeculativeParse);
+                    self.prefetch(request);
                 }
                 TokenSinkResult::Continue
             },
@@ -158,10 +229,33 @@ impl TokenSink for PrefetchSink {
                                 self.referrer.clone(),
                                 referrer_policy,
                                 integrity_metadata,
+                            )
+                            .initiator(Initiator::SpeculativeParse);
+                            self.prefetch(request);
+                        }
+                    } else if rel.value.eq_ignore_ascii_case("preload") {
+                        let destination = self
+                            .get_attr(tag, LocalName::from("as"))
+                            .and_then(|attr| translate_preload_destination(&attr.value));
+                        if let (Some(destination), Some(url)) =
+                            (destination, self.get_url(tag, local_name!("href")))
+                        {
+                            debug!("Preload {} {}", tag.name, url);
+                            let integrity_metadata = self
+                                .get_attr(tag, local_name!("integrity"))
+                                .map(|attr| String::from(&attr.value))
+                                .unwrap_or_default();
+                            let request = preload_fetch_request(
+                                url,
+                                destination,
+                                self.get_cors_settings(tag, local_name!("crossorigin")),
+                                self.origin.clone(),
+                                self.pipeline_id,
+                                self.referrer.clone(),
+                                self.get_referrer_policy(tag, local_name!("referrerpolicy")),
+                                integrity_metadata,
                             );
-                            let _ = self
-                                .resource_threads
-                                .send(CoreResourceMsg::Fetch(request, FetchChannels::Prefetch));
+                            self.prefetch(request);
                         }
                     }
                 }
@@ -170,6 +264,22 @@ impl TokenSink for PrefetchSink {
             (TagKind::StartTag, &local_name!("script")) => {
                 TokenSinkResult::RawData(RawKind::ScriptData)
             },
+            // The tokenizer switches to the same states as the main one does for the
+            // elements whose contents are text, so that no resource is found in them.
+            // https://html.spec.whatwg.org/multipage/#parsing-html-fragments
+            (TagKind::StartTag, &local_name!("title")) |
+            (TagKind::StartTag, &local_name!("textarea")) => {
+                TokenSinkResult::RawData(RawKind::Rcdata)
+            },
+            (TagKind::StartTag, &local_name!("style")) |
+            (TagKind::StartTag, &local_name!("xmp")) |
+            (TagKind::StartTag, &local_name!("iframe")) |
+            (TagKind::StartTag, &local_name!("noembed")) |
+            (TagKind::StartTag, &local_name!("noframes")) |
+            (TagKind::StartTag, &local_name!("noscript")) => {
+                TokenSinkResult::RawData(RawKind::Rawtext)
+            },
+            (TagKind::StartTag, &local_name!("plaintext")) => TokenSinkResult::Plaintext,
             (TagKind::EndTag, &local_name!("script")) => {
                 // After the first script tag, the main parser is blocked, so it's worth prefetching.
                 self.prefetching = true;
@@ -184,13 +294,26 @@ impl TokenSink for PrefetchSink {
                 }
                 TokenSinkResult::Continue
             },
+            (TagKind::StartTag, name) if &**name == PROBE => {
+                self.probed = true;
+                TokenSinkResult::Continue
+            },
             _ => TokenSinkResult::Continue,
         }
     }
 }
 
 impl PrefetchSink {
-    fn get_attr<'a>(&'a self, tag: &'a Tag, name: LocalName) -> Option<&'a Attribute> {
+    fn prefetch(&mut self, request: RequestBuilder) {
+        if !self.prefetched.insert(request.url.clone()) {
+            return;
+        }
+        let _ = self
+            .resource_threads
+            .send(CoreResourceMsg::Fetch(request, FetchChannels::Prefetch));
+    }
+
+    fn get_attr<'a>(&self, tag: &'a Tag, name: LocalName) -> Option<&'a Attribute> {
         tag.attrs.iter().find(|attr| attr.name.local == name)
     }
 
@@ -31,6 +31,7 @@ impl Tokenizer {
             current_line: 1,
             script: Default::default(),
             parsing_algorithm: ParsingAlgorithm::Normal,
+            first_parse_error: None,
         };
 
         let tb = XmlTreeBuilder::new(sink, Default::default());
@@ -54,6 +55,10 @@ impl Tokenizer {
     pub fn url(&self) -> &ServoUrl {
         &self.inner.sink.sink.base_url
     }
+
+    pub fn first_parse_error(&self) -> Option<&str> {
+        self.inner.sink.sink.first_parse_error.as_deref()
+    }
 }
 
 #[allow(unsafe_code)]
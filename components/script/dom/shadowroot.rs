@@ -9,6 +9,7 @@ use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::reflector::reflect_dom_object;
 use crate::dom::bindings::root::{Dom, DomRoot, LayoutDom, MutNullableDom};
+use crate::dom::caretposition::CaretPosition;
 use crate::dom::cssstylesheet::CSSStyleSheet;
 use crate::dom::document::Document;
 use crate::dom::documentfragment::DocumentFragment;
@@ -178,43 +179,42 @@ impl ShadowRootMethods for ShadowRoot {
 
     // https://drafts.csswg.org/cssom-view/#dom-document-elementfrompoint
     fn ElementFromPoint(&self, x: Finite<f64>, y: Finite<f64>) -> Option<DomRoot<Element>> {
-        // Return the result of running the retargeting algorithm with context object
-        // and the original result as input.
-        match self.document_or_shadow_root.element_from_point(
+        // Return the result of running the retargeting algorithm with the original
+        // result and context object as input.
+        self.document_or_shadow_root.element_from_point(
             x,
             y,
+            self.upcast(),
             None,
             self.document.has_browsing_context(),
-        ) {
-            Some(e) => {
-                let retargeted_node = self.upcast::<Node>().retarget(e.upcast::<Node>());
-                retargeted_node
-                    .downcast::<Element>()
-                    .map(|n| DomRoot::from_ref(n))
-            },
-            None => None,
-        }
+        )
     }
 
     // https://drafts.csswg.org/cssom-view/#dom-document-elementsfrompoint
     fn ElementsFromPoint(&self, x: Finite<f64>, y: Finite<f64>) -> Vec<DomRoot<Element>> {
-        // Return the result of running the retargeting algorithm with context object
-        // and the original result as input
-        let mut elements = Vec::new();
-        for e in self
-            .document_or_shadow_root
-            .elements_from_point(x, y, None, self.document.has_browsing_context())
-            .iter()
-        {
-            let retargeted_node = self.upcast::<Node>().retarget(e.upcast::<Node>());
-            if let Some(element) = retargeted_node
-                .downcast::<Element>()
-                .map(|n| DomRoot::from_ref(n))
-            {
-                elements.push(element);
-            }
-        }
-        elements
+        // Return the result of running the retargeting algorithm with the original
+        // result and context object as input.
+        self.document_or_shadow_root.elements_from_point(
+            x,
+            y,
+            self.upcast(),
+            None,
+            self.document.has_browsing_context(),
+        )
+    }
+
+    // https://drafts.csswg.org/cssom-view/#dom-document-caretpositionfrompoint
+    fn CaretPositionFromPoint(
+        &self,
+        x: Finite<f64>,
+        y: Finite<f64>,
+    ) -> Option<DomRoot<CaretPosition>> {
+        self.document_or_shadow_root.caret_position_from_point(
+            x,
+            y,
+            self.upcast(),
+            self.document.has_browsing_context(),
+        )
     }
 
     /// https://dom.spec.whatwg.org/#dom-shadowroot-mode
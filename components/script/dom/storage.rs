@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::{DomRefCell, RefMut};
 use crate::dom::bindings::codegen::Bindings::StorageBinding;
 use crate::dom::bindings::codegen::Bindings::StorageBinding::StorageMethods;
 use crate::dom::bindings::error::{Error, ErrorResult};
@@ -16,29 +17,108 @@ use crate::dom::window::Window;
 use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
 use ipc_channel::ipc::IpcSender;
-use net_traits::storage_thread::{StorageThreadMsg, StorageType};
+use msg::constellation_msg::TopLevelBrowsingContextId;
+use net_traits::storage_thread::QUOTA_SIZE_LIMIT;
+use net_traits::storage_thread::{StorageMutation, StorageThreadMsg, StorageType};
 use net_traits::IpcSend;
 use profile_traits::ipc;
 use script_traits::ScriptMsg;
 use servo_url::ServoUrl;
+use std::collections::BTreeMap;
+use std::mem;
 
 #[dom_struct]
 pub struct Storage {
     reflector_: Reflector,
     storage_type: StorageType,
+    /// The top-level browsing context whose session storage is used, the same for every
+    /// document it navigates to.
+    top_level_browsing_context_id: TopLevelBrowsingContextId,
+    /// The key/value pairs of the storage area, which are read from the storage thread on
+    /// first access, and then kept up to date with the changes of this document and with
+    /// those of the others, which it is notified of with their storage events.
+    snapshot: DomRefCell<Option<StorageSnapshot>>,
+    /// The changes which were made since the storage thread was last sent them, which it is
+    /// sent once the task which made them is over.
+    pending_changes: DomRefCell<Vec<StorageChange>>,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+struct StorageSnapshot {
+    items: BTreeMap<String, String>,
+    /// The number of bytes of the keys and values.
+    size: usize,
+    /// The number of bytes which the other storage area of the origin held when the
+    /// snapshot was last read or sent changes, which counts in the quota of this one.
+    other_size: usize,
+}
+
+impl StorageSnapshot {
+    fn new(items: BTreeMap<String, String>, other_size: usize) -> StorageSnapshot {
+        let size = items.iter().map(|(key, value)| item_size(key, value)).sum();
+        StorageSnapshot {
+            items,
+            size,
+            other_size,
+        }
+    }
+
+    fn set_item(&mut self, key: String, value: String) -> Option<String> {
+        self.size += item_size(&key, &value);
+        let old_value = self.items.insert(key.clone(), value);
+        if let Some(ref old_value) = old_value {
+            self.size -= item_size(&key, old_value);
+        }
+        old_value
+    }
+
+    fn remove_item(&mut self, key: &str) -> Option<String> {
+        let old_value = self.items.remove(key);
+        if let Some(ref old_value) = old_value {
+            self.size -= item_size(key, old_value);
+        }
+        old_value
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.size = 0;
+    }
+}
+
+fn item_size(key: &str, value: &str) -> usize {
+    key.as_bytes().len() + value.as_bytes().len()
+}
+
+/// A change to the storage area, which is both sent to the storage thread and broadcast to
+/// the other documents with a storage event.
+#[derive(JSTraceable, MallocSizeOf)]
+struct StorageChange {
+    key: Option<String>,
+    old_value: Option<String>,
+    new_value: Option<String>,
 }
 
 impl Storage {
-    fn new_inherited(storage_type: StorageType) -> Storage {
+    fn new_inherited(
+        storage_type: StorageType,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> Storage {
         Storage {
             reflector_: Reflector::new(),
             storage_type: storage_type,
+            top_level_browsing_context_id,
+            snapshot: Default::default(),
+            pending_changes: Default::default(),
         }
     }
 
     pub fn new(global: &Window, storage_type: StorageType) -> DomRoot<Storage> {
         reflect_dom_object(
-            Box::new(Storage::new_inherited(storage_type)),
+            Box::new(Storage::new_inherited(
+                storage_type,
+                global.window_proxy().top_level_browsing_context_id(),
+            )),
             global,
             StorageBinding::Wrap,
         )
@@ -51,117 +131,210 @@ impl Storage {
     fn get_storage_thread(&self) -> IpcSender<StorageThreadMsg> {
         self.global().resource_threads().sender()
     }
+
+    /// The snapshot of the storage area, which is read from the storage thread if this is
+    /// the first access to it.
+    fn snapshot(&self) -> RefMut<StorageSnapshot> {
+        let mut snapshot = self.snapshot.borrow_mut();
+        if snapshot.is_none() {
+            let (sender, receiver) =
+                ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
+            self.get_storage_thread()
+                .send(StorageThreadMsg::Snapshot(
+                    sender,
+                    self.top_level_browsing_context_id,
+                    self.get_url(),
+                    self.storage_type,
+                ))
+                .unwrap();
+            let (items, other_size) = receiver.recv().unwrap();
+            *snapshot = Some(StorageSnapshot::new(items, other_size));
+        }
+        RefMut::map(snapshot, |snapshot| snapshot.as_mut().unwrap())
+    }
+
+    /// Queue a change to be sent to the storage thread and broadcast once the current task
+    /// is over, together with the other changes of the task.
+    fn queue_change(
+        &self,
+        key: Option<String>,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        let first_change = {
+            let mut pending_changes = self.pending_changes.borrow_mut();
+            pending_changes.push(StorageChange {
+                key,
+                old_value,
+                new_value,
+            });
+            pending_changes.len() == 1
+        };
+        if !first_change {
+            return;
+        }
+        let global = self.global();
+        let this = Trusted::new(self);
+        let result = global
+            .as_window()
+            .task_manager()
+            .dom_manipulation_task_source()
+            .queue(
+                task!(flush_storage_changes: move || {
+                    this.root().flush_changes();
+                }),
+                global.upcast(),
+            );
+        // The task can't be queued once the document is being unloaded, so the change is
+        // sent right away instead of being lost.
+        if result.is_err() {
+            self.flush_changes();
+        }
+    }
+
+    /// Send the pending changes to the storage thread, coalescing those which override the
+    /// previous ones, and broadcast them to the other documents.
+    ///
+    /// The storage thread skips the items which would exceed the quota that the storage area
+    /// shares with the other one of the origin, in which case the snapshot is rolled back to
+    /// the values it kept, and the changes to their keys aren't broadcast.
+    pub fn flush_changes(&self) {
+        let changes = mem::replace(&mut *self.pending_changes.borrow_mut(), vec![]);
+        if changes.is_empty() {
+            return;
+        }
+        let mut mutations: Vec<StorageMutation> = vec![];
+        for change in &changes {
+            let mutation = match (&change.key, &change.new_value) {
+                (None, _) => {
+                    mutations.clear();
+                    StorageMutation::Clear
+                },
+                (Some(key), new_value) => {
+                    mutations.retain(|mutation| mutation.key() != Some(key.as_str()));
+                    match new_value {
+                        Some(value) => StorageMutation::SetItem(key.clone(), value.clone()),
+                        None => StorageMutation::RemoveItem(key.clone()),
+                    }
+                },
+            };
+            mutations.push(mutation);
+        }
+        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
+        let msg = StorageThreadMsg::Mutate(
+            sender,
+            self.top_level_browsing_context_id,
+            self.get_url(),
+            self.storage_type,
+            mutations,
+        );
+        self.get_storage_thread().send(msg).unwrap();
+        let (rejected, other_size) = receiver.recv().unwrap();
+        if let Some(ref mut snapshot) = *self.snapshot.borrow_mut() {
+            snapshot.other_size = other_size;
+            for (key, value) in &rejected {
+                match value {
+                    Some(value) => {
+                        snapshot.set_item(key.clone(), value.clone());
+                    },
+                    None => {
+                        snapshot.remove_item(key);
+                    },
+                }
+            }
+        }
+        for change in changes {
+            let is_rejected = change.key.as_ref().map_or(false, |key| {
+                rejected
+                    .iter()
+                    .any(|&(ref rejected_key, _)| rejected_key == key)
+            });
+            if is_rejected {
+                continue;
+            }
+            self.broadcast_change_notification(change.key, change.old_value, change.new_value);
+        }
+    }
 }
 
 impl StorageMethods for Storage {
     // https://html.spec.whatwg.org/multipage/#dom-storage-length
     fn Length(&self) -> u32 {
-        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
-
-        self.get_storage_thread()
-            .send(StorageThreadMsg::Length(
-                sender,
-                self.get_url(),
-                self.storage_type,
-            ))
-            .unwrap();
-        receiver.recv().unwrap() as u32
+        self.snapshot().items.len() as u32
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-storage-key
     fn Key(&self, index: u32) -> Option<DOMString> {
-        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
-
-        self.get_storage_thread()
-            .send(StorageThreadMsg::Key(
-                sender,
-                self.get_url(),
-                self.storage_type,
-                index,
-            ))
-            .unwrap();
-        receiver.recv().unwrap().map(DOMString::from)
+        self.snapshot()
+            .items
+            .keys()
+            .nth(index as usize)
+            .cloned()
+            .map(DOMString::from)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-storage-getitem
     fn GetItem(&self, name: DOMString) -> Option<DOMString> {
-        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
-        let name = String::from(name);
-
-        let msg = StorageThreadMsg::GetItem(sender, self.get_url(), self.storage_type, name);
-        self.get_storage_thread().send(msg).unwrap();
-        receiver.recv().unwrap().map(DOMString::from)
+        self.snapshot()
+            .items
+            .get(&*name)
+            .cloned()
+            .map(DOMString::from)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-storage-setitem
     fn SetItem(&self, name: DOMString, value: DOMString) -> ErrorResult {
-        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
         let name = String::from(name);
         let value = String::from(value);
 
-        let msg = StorageThreadMsg::SetItem(
-            sender,
-            self.get_url(),
-            self.storage_type,
-            name.clone(),
-            value.clone(),
-        );
-        self.get_storage_thread().send(msg).unwrap();
-        match receiver.recv().unwrap() {
-            Err(_) => Err(Error::QuotaExceeded),
-            Ok((changed, old_value)) => {
-                if changed {
-                    self.broadcast_change_notification(Some(name), old_value, Some(value));
-                }
-                Ok(())
-            },
-        }
+        let old_value = {
+            let mut snapshot = self.snapshot();
+            let old_size = snapshot
+                .items
+                .get(&name)
+                .map_or(0, |old_value| item_size(&name, old_value));
+            let new_size = snapshot.size - old_size + item_size(&name, &value);
+            if new_size + snapshot.other_size > QUOTA_SIZE_LIMIT {
+                return Err(Error::QuotaExceeded);
+            }
+            if snapshot.items.get(&name) == Some(&value) {
+                return Ok(());
+            }
+            snapshot.set_item(name.clone(), value.clone())
+        };
+        self.queue_change(Some(name), old_value, Some(value));
+        Ok(())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-storage-removeitem
     fn RemoveItem(&self, name: DOMString) {
-        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
         let name = String::from(name);
 
-        let msg =
-            StorageThreadMsg::RemoveItem(sender, self.get_url(), self.storage_type, name.clone());
-        self.get_storage_thread().send(msg).unwrap();
-        if let Some(old_value) = receiver.recv().unwrap() {
-            self.broadcast_change_notification(Some(name), Some(old_value), None);
+        let old_value = self.snapshot().remove_item(&name);
+        if let Some(old_value) = old_value {
+            self.queue_change(Some(name), Some(old_value), None);
         }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-storage-clear
     fn Clear(&self) {
-        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
-
-        self.get_storage_thread()
-            .send(StorageThreadMsg::Clear(
-                sender,
-                self.get_url(),
-                self.storage_type,
-            ))
-            .unwrap();
-        if receiver.recv().unwrap() {
-            self.broadcast_change_notification(None, None, None);
+        let cleared = {
+            let mut snapshot = self.snapshot();
+            let cleared = !snapshot.items.is_empty();
+            snapshot.clear();
+            cleared
+        };
+        if cleared {
+            self.queue_change(None, None, None);
         }
     }
 
     // https://html.spec.whatwg.org/multipage/#the-storage-interface:supported-property-names
     fn SupportedPropertyNames(&self) -> Vec<DOMString> {
-        let (sender, receiver) = ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
-
-        self.get_storage_thread()
-            .send(StorageThreadMsg::Keys(
-                sender,
-                self.get_url(),
-                self.storage_type,
-            ))
-            .unwrap();
-        receiver
-            .recv()
-            .unwrap()
-            .into_iter()
+        self.snapshot()
+            .items
+            .keys()
+            .cloned()
             .map(DOMString::from)
             .collect()
     }
@@ -205,6 +378,20 @@ impl Storage {
         old_value: Option<String>,
         new_value: Option<String>,
     ) {
+        // The snapshot is updated before the event is fired, so that the scripts which run
+        // in between read the new value too.
+        if let Some(ref mut snapshot) = *self.snapshot.borrow_mut() {
+            match (&key, &new_value) {
+                (None, _) => snapshot.clear(),
+                (Some(key), Some(value)) => {
+                    snapshot.set_item(key.clone(), value.clone());
+                },
+                (Some(key), None) => {
+                    snapshot.remove_item(key);
+                },
+            }
+        }
+
         let global = self.global();
         let this = Trusted::new(self);
         global
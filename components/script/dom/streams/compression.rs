@@ -0,0 +1,290 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The transform algorithms of compression and decompression streams, which compress or
+//! decompress the chunks on background threads and enqueue the output from tasks of the
+//! DOM manipulation task source, so that large chunks do not block the event loop.
+//! <https://wicg.github.io/compression/>
+
+use crate::background_jobs::{queue_background_job, BackgroundJob};
+use crate::dom::bindings::codegen::Bindings::CompressionStreamBinding::CompressionFormat;
+use crate::dom::bindings::error::Error;
+use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::promise::Promise;
+use crate::dom::streams::{buffer_source_bytes, settle_promise_with_result};
+use crate::dom::transformstreamdefaultcontroller::{
+    TransformAlgorithms, TransformStreamDefaultController,
+};
+use crate::realms::enter_realm;
+use crate::task_source::{TaskSource, TaskSourceName};
+use flate2::write::{DeflateEncoder, GzDecoder, GzEncoder, ZlibEncoder};
+use flate2::{Compression, Decompress, FlushDecompress, Status};
+use js::rust::HandleValue;
+use std::io::Write;
+use std::mem;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// The size by which the output buffer of a decompression grows.
+const OUTPUT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The state of a compression or a decompression.
+enum Codec {
+    Deflate(ZlibEncoder<Vec<u8>>),
+    DeflateRaw(DeflateEncoder<Vec<u8>>),
+    Gzip(GzEncoder<Vec<u8>>),
+    /// The decompression of the deflate and deflate-raw formats, which tracks the end of
+    /// the compressed data to reject the data after it and the truncated inputs.
+    Inflate {
+        decompress: Decompress,
+        ended: bool,
+    },
+    /// The decompression of the gzip format, which checks the trailer of the compressed
+    /// data once it is finished.
+    Gunzip(GzDecoder<Vec<u8>>),
+}
+
+unsafe_no_jsmanaged_fields!(Mutex<Codec>);
+
+impl Codec {
+    fn compression(format: CompressionFormat) -> Codec {
+        match format {
+            CompressionFormat::Deflate => {
+                Codec::Deflate(ZlibEncoder::new(vec![], Compression::default()))
+            },
+            CompressionFormat::Deflate_raw => {
+                Codec::DeflateRaw(DeflateEncoder::new(vec![], Compression::default()))
+            },
+            CompressionFormat::Gzip => Codec::Gzip(GzEncoder::new(vec![], Compression::default())),
+        }
+    }
+
+    fn decompression(format: CompressionFormat) -> Codec {
+        match format {
+            CompressionFormat::Deflate => Codec::Inflate {
+                decompress: Decompress::new(true),
+                ended: false,
+            },
+            CompressionFormat::Deflate_raw => Codec::Inflate {
+                decompress: Decompress::new(false),
+                ended: false,
+            },
+            CompressionFormat::Gzip => Codec::Gunzip(GzDecoder::new(vec![])),
+        }
+    }
+
+    /// Compress or decompress a chunk, returning the output which is available so far.
+    fn write(&mut self, chunk: &[u8]) -> Result<Vec<u8>, ()> {
+        match *self {
+            Codec::Deflate(ref mut encoder) => write_all(encoder, chunk),
+            Codec::DeflateRaw(ref mut encoder) => write_all(encoder, chunk),
+            Codec::Gzip(ref mut encoder) => write_all(encoder, chunk),
+            Codec::Gunzip(ref mut decoder) => write_all(decoder, chunk),
+            Codec::Inflate {
+                ref mut decompress,
+                ref mut ended,
+            } => inflate(decompress, ended, chunk),
+        }
+    }
+
+    /// Finish the compression or the decompression, returning the rest of the output, or
+    /// an error if the compressed data was truncated.
+    fn finish(&mut self) -> Result<Vec<u8>, ()> {
+        match *self {
+            Codec::Deflate(ref mut encoder) => {
+                encoder.try_finish().map_err(|_| ())?;
+                Ok(mem::replace(encoder.get_mut(), vec![]))
+            },
+            Codec::DeflateRaw(ref mut encoder) => {
+                encoder.try_finish().map_err(|_| ())?;
+                Ok(mem::replace(encoder.get_mut(), vec![]))
+            },
+            Codec::Gzip(ref mut encoder) => {
+                encoder.try_finish().map_err(|_| ())?;
+                Ok(mem::replace(encoder.get_mut(), vec![]))
+            },
+            Codec::Gunzip(ref mut decoder) => {
+                decoder.try_finish().map_err(|_| ())?;
+                Ok(mem::replace(decoder.get_mut(), vec![]))
+            },
+            Codec::Inflate { ended: true, .. } => Ok(vec![]),
+            Codec::Inflate { ended: false, .. } => Err(()),
+        }
+    }
+}
+
+/// The output buffers of the writers of flate2.
+trait OutputBuffer: Write {
+    fn output(&mut self) -> &mut Vec<u8>;
+}
+
+impl OutputBuffer for ZlibEncoder<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+}
+
+impl OutputBuffer for DeflateEncoder<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+}
+
+impl OutputBuffer for GzEncoder<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+}
+
+impl OutputBuffer for GzDecoder<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+}
+
+fn write_all(writer: &mut impl OutputBuffer, chunk: &[u8]) -> Result<Vec<u8>, ()> {
+    // The gzip decoder fails to write the data after the end of the compressed data.
+    writer.write_all(chunk).map_err(|_| ())?;
+    Ok(mem::replace(writer.output(), vec![]))
+}
+
+fn inflate(decompress: &mut Decompress, ended: &mut bool, mut input: &[u8]) -> Result<Vec<u8>, ()> {
+    let mut output = Vec::with_capacity(input.len().min(OUTPUT_CHUNK_SIZE));
+    while !*ended {
+        if output.len() == output.capacity() {
+            output.reserve(OUTPUT_CHUNK_SIZE);
+        }
+        let (total_in, total_out) = (decompress.total_in(), decompress.total_out());
+        let status = decompress
+            .decompress_vec(input, &mut output, FlushDecompress::None)
+            .map_err(|_| ())?;
+        input = &input[(decompress.total_in() - total_in) as usize..];
+        if status == Status::StreamEnd {
+            *ended = true;
+        }
+        let progressed = decompress.total_in() != total_in || decompress.total_out() != total_out;
+        // The output may be pending as long as the output buffer is full.
+        if !progressed || (input.is_empty() && output.len() < output.capacity()) {
+            break;
+        }
+    }
+    if !input.is_empty() {
+        return Err(());
+    }
+    Ok(output)
+}
+
+/// The transform algorithms of a compression or a decompression stream, whose chunks are
+/// processed in order, since a chunk is only transformed once the previous one was.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct CodecAlgorithms {
+    #[ignore_malloc_size_of = "Defined in flate2"]
+    codec: Arc<Mutex<Codec>>,
+    decompression: bool,
+}
+
+impl CodecAlgorithms {
+    pub fn compression(format: CompressionFormat) -> CodecAlgorithms {
+        CodecAlgorithms {
+            codec: Arc::new(Mutex::new(Codec::compression(format))),
+            decompression: false,
+        }
+    }
+
+    pub fn decompression(format: CompressionFormat) -> CodecAlgorithms {
+        CodecAlgorithms {
+            codec: Arc::new(Mutex::new(Codec::decompression(format))),
+            decompression: true,
+        }
+    }
+
+    /// Compress or decompress a chunk, or finish the stream without one, on a background
+    /// thread, and enqueue the output to the controller from a task.
+    fn process(
+        &self,
+        controller: &TransformStreamDefaultController,
+        chunk: Option<Vec<u8>>,
+    ) -> Rc<Promise> {
+        let global = controller.global();
+        let promise = Promise::new(&global);
+        let codec = self.codec.clone();
+        let error = if self.decompression {
+            "The compressed data is corrupted or truncated"
+        } else {
+            "The data could not be compressed"
+        };
+        let controller = Trusted::new(controller);
+        let trusted_promise = TrustedPromise::new(promise.clone());
+        let task_source = global.dom_manipulation_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+        // Reject the promise if the codec panics, in which case its state is lost and the
+        // next chunks fail as well.
+        let failed_promise = TrustedPromise::new(promise.clone());
+        let failed_task_source = task_source.clone();
+        let failed_canceller = global.task_canceller(TaskSourceName::DOMManipulation);
+        let run = Box::new(move || {
+            let output = match codec.lock() {
+                Ok(mut codec) => match chunk {
+                    Some(chunk) => codec.write(&chunk),
+                    None => codec.finish(),
+                },
+                Err(_) => Err(()),
+            };
+            let _ = task_source.queue_with_canceller(
+                task!(enqueue_codec_output: move || {
+                    let controller = controller.root();
+                    let promise = trusted_promise.root();
+                    let output = match output {
+                        Ok(output) => output,
+                        Err(()) => return promise.reject_error(Error::Type(error.to_owned())),
+                    };
+                    if output.is_empty() {
+                        return promise.resolve_native(&());
+                    }
+                    let _ac = enter_realm(&*controller);
+                    settle_promise_with_result(&promise, controller.enqueue_bytes(&output));
+                }),
+                &canceller,
+            );
+        });
+        let failed = Box::new(move || {
+            let _ = failed_task_source.queue_with_canceller(
+                task!(reject_codec_output: move || {
+                    failed_promise.root().reject_error(Error::Type(error.to_owned()));
+                }),
+                &failed_canceller,
+            );
+        });
+        queue_background_job(BackgroundJob { run, failed });
+        promise
+    }
+}
+
+impl TransformAlgorithms for CodecAlgorithms {
+    // https://wicg.github.io/compression/#compress-and-enqueue-a-chunk
+    // https://wicg.github.io/compression/#decompress-and-enqueue-a-chunk
+    fn transform(
+        &self,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Rc<Promise> {
+        match buffer_source_bytes(controller.global().get_cx(), chunk) {
+            Some(chunk) => self.process(controller, Some(chunk)),
+            None => {
+                let promise = Promise::new(&controller.global());
+                promise.reject_error(Error::Type(
+                    "The chunk must be an ArrayBuffer or an ArrayBufferView".to_owned(),
+                ));
+                promise
+            },
+        }
+    }
+
+    // https://wicg.github.io/compression/#compress-flush-and-enqueue
+    // https://wicg.github.io/compression/#decompress-flush-and-enqueue
+    fn flush(&self, controller: &TransformStreamDefaultController) -> Rc<Promise> {
+        self.process(controller, None)
+    }
+}
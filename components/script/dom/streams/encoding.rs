@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The transform algorithms of text encoder and decoder streams, which encode and decode
+//! the chunks as they are written, keeping the code units or the bytes which a character
+//! is split across until the next chunk.
+//! <https://encoding.spec.whatwg.org/#interface-textencoderstream>
+//! <https://encoding.spec.whatwg.org/#interface-textdecoderstream>
+
+use crate::dom::bindings::conversions::ToJSValConvertible;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::promise::Promise;
+use crate::dom::streams::{buffer_source_bytes, settle_promise_with_result};
+use crate::dom::textdecodercommon::TextDecoderCommon;
+use crate::dom::transformstreamdefaultcontroller::{
+    TransformAlgorithms, TransformStreamDefaultController,
+};
+use crate::script_runtime::JSContext;
+use js::jsapi::JS_StringHasLatin1Chars;
+use js::jsapi::{JS_GetLatin1StringCharsAndLength, JS_GetTwoByteStringCharsAndLength};
+use js::jsval::UndefinedValue;
+use js::rust::{HandleValue, ToString};
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+use std::slice;
+
+/// The UTF-8 encoding of U+FFFD REPLACEMENT CHARACTER.
+const REPLACEMENT_CHARACTER: [u8; 3] = [0xEF, 0xBF, 0xBD];
+
+/// A promise settled with the result of a transform algorithm.
+fn promise_with_result(
+    controller: &TransformStreamDefaultController,
+    result: ErrorResult,
+) -> Rc<Promise> {
+    let promise = Promise::new(&controller.global());
+    settle_promise_with_result(&promise, result);
+    promise
+}
+
+/// The UTF-16 code units of a chunk converted to a string, which may have unpaired
+/// surrogates.
+#[allow(unsafe_code)]
+fn code_units(cx: JSContext, chunk: HandleValue) -> Fallible<Vec<u16>> {
+    unsafe {
+        rooted!(in(*cx) let string = ToString(*cx, chunk));
+        if string.get().is_null() {
+            return Err(Error::JSFailed);
+        }
+        let mut length = 0;
+        if JS_StringHasLatin1Chars(string.get()) {
+            let chars =
+                JS_GetLatin1StringCharsAndLength(*cx, ptr::null(), string.get(), &mut length);
+            assert!(!chars.is_null());
+            let chars = slice::from_raw_parts(chars, length as usize);
+            Ok(chars.iter().map(|&c| c as u16).collect())
+        } else {
+            let chars =
+                JS_GetTwoByteStringCharsAndLength(*cx, ptr::null(), string.get(), &mut length);
+            assert!(!chars.is_null());
+            Ok(slice::from_raw_parts(chars, length as usize).to_vec())
+        }
+    }
+}
+
+fn is_high_surrogate(unit: u16) -> bool {
+    (0xD800..0xDC00).contains(&unit)
+}
+
+/// The transform algorithms of a `TextEncoderStream`.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct TextEncoderAlgorithms {
+    /// The high surrogate which the previous chunk ended with, which is paired with the
+    /// first code unit of the next chunk if it is a low surrogate.
+    pending_high_surrogate: Cell<Option<u16>>,
+}
+
+impl TextEncoderAlgorithms {
+    pub fn new() -> TextEncoderAlgorithms {
+        TextEncoderAlgorithms {
+            pending_high_surrogate: Cell::new(None),
+        }
+    }
+
+    /// Encode the code units of a chunk to UTF-8, replacing the unpaired surrogates.
+    /// <https://encoding.spec.whatwg.org/#encode-and-enqueue-a-chunk>
+    fn encode(&self, mut units: Vec<u16>) -> Vec<u8> {
+        if let Some(high_surrogate) = self.pending_high_surrogate.take() {
+            units.insert(0, high_surrogate);
+        }
+        if units.last().map_or(false, |&unit| is_high_surrogate(unit)) {
+            self.pending_high_surrogate.set(units.pop());
+        }
+        String::from_utf16_lossy(&units).into_bytes()
+    }
+}
+
+impl TransformAlgorithms for TextEncoderAlgorithms {
+    // https://encoding.spec.whatwg.org/#encode-and-enqueue-a-chunk
+    fn transform(
+        &self,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Rc<Promise> {
+        let result = code_units(controller.global().get_cx(), chunk).and_then(|units| {
+            let output = self.encode(units);
+            if output.is_empty() {
+                return Ok(());
+            }
+            controller.enqueue_bytes(&output)
+        });
+        promise_with_result(controller, result)
+    }
+
+    // https://encoding.spec.whatwg.org/#encode-and-flush
+    fn flush(&self, controller: &TransformStreamDefaultController) -> Rc<Promise> {
+        let result = match self.pending_high_surrogate.take() {
+            Some(_) => controller.enqueue_bytes(&REPLACEMENT_CHARACTER),
+            None => Ok(()),
+        };
+        promise_with_result(controller, result)
+    }
+}
+
+/// The transform algorithms of a `TextDecoderStream`, which shares its decoder with them.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct TextDecoderAlgorithms {
+    #[ignore_malloc_size_of = "Rc"]
+    decoder: Rc<TextDecoderCommon>,
+}
+
+impl TextDecoderAlgorithms {
+    pub fn new(decoder: Rc<TextDecoderCommon>) -> TextDecoderAlgorithms {
+        TextDecoderAlgorithms { decoder }
+    }
+
+    /// Decode the bytes, enqueuing the output if there is some.
+    #[allow(unsafe_code)]
+    fn decode_and_enqueue(
+        &self,
+        controller: &TransformStreamDefaultController,
+        bytes: &[u8],
+        last: bool,
+    ) -> ErrorResult {
+        let output = self.decoder.decode(bytes, last)?;
+        if output.is_empty() {
+            return Ok(());
+        }
+        let cx = controller.global().get_cx();
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        unsafe { output.to_jsval(*cx, chunk.handle_mut()) };
+        controller.enqueue(chunk.handle())
+    }
+}
+
+impl TransformAlgorithms for TextDecoderAlgorithms {
+    // https://encoding.spec.whatwg.org/#decode-and-enqueue-a-chunk
+    fn transform(
+        &self,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Rc<Promise> {
+        let result = match buffer_source_bytes(controller.global().get_cx(), chunk) {
+            Some(bytes) => self.decode_and_enqueue(controller, &bytes, false),
+            None => Err(Error::Type(
+                "The chunk must be an ArrayBuffer or an ArrayBufferView".to_owned(),
+            )),
+        };
+        promise_with_result(controller, result)
+    }
+
+    // https://encoding.spec.whatwg.org/#flush-and-enqueue
+    fn flush(&self, controller: &TransformStreamDefaultController) -> Rc<Promise> {
+        let result = self.decode_and_enqueue(controller, &[], true);
+        promise_with_result(controller, result)
+    }
+}
@@ -0,0 +1,302 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The abstract operations that readable, writable and transform streams share.
+//! <https://streams.spec.whatwg.org/#misc-abstract-ops>
+
+use crate::dom::bindings::callback::CallbackContainer;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::conversions::{ConversionResult, FromJSValConvertible};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::script_runtime::JSContext as SafeJSContext;
+use js::jsapi::JS_ClearPendingException;
+use js::jsapi::{HandleValueArray, Heap, IsCallable, JSContext, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::wrappers::{Call, JS_GetPendingException, JS_GetProperty};
+use js::rust::{HandleObject, HandleValue, MutableHandleValue};
+use js::typedarray::{ArrayBuffer, ArrayBufferView};
+use std::rc::Rc;
+
+pub mod compression;
+pub mod encoding;
+pub mod pipe;
+pub mod request_body;
+
+/// Resolve a promise with undefined once another one is fulfilled, or reject it with the
+/// same reason.
+#[derive(JSTraceable, MallocSizeOf)]
+struct SettlePromiseHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for SettlePromiseHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        if self.fulfilled {
+            self.promise.resolve_native(&());
+        } else {
+            self.promise
+                .reject(unsafe { SafeJSContext::from_ptr(cx) }, v);
+        }
+    }
+}
+
+/// Settle a promise once another one is settled, with undefined if it is fulfilled.
+pub fn settle_promise_with(promise: &Rc<Promise>, other: &Promise) {
+    let handler = |fulfilled| -> Box<dyn Callback> {
+        Box::new(SettlePromiseHandler {
+            promise: promise.clone(),
+            fulfilled,
+        })
+    };
+    let handler =
+        PromiseNativeHandler::new(&promise.global(), Some(handler(true)), Some(handler(false)));
+    other.append_native_handler(&handler);
+}
+
+/// Prevent the rejection of a promise from being reported as unhandled.
+pub fn mark_promise_as_handled(promise: &Promise) {
+    let handler = PromiseNativeHandler::new(&promise.global(), None, None);
+    promise.append_native_handler(&handler);
+}
+
+/// Resolve a promise with undefined if an operation succeeded, or reject it with the error
+/// of the operation, which is the pending exception if it threw one.
+pub fn settle_promise_with_result(promise: &Promise, result: ErrorResult) {
+    match result {
+        Ok(()) => promise.resolve_native(&()),
+        Err(Error::JSFailed) => {
+            let cx = promise.global().get_cx();
+            rooted!(in(*cx) let mut error = UndefinedValue());
+            take_pending_exception(cx, error.handle_mut());
+            promise.reject(cx, error.handle());
+        },
+        Err(error) => promise.reject_error(error),
+    }
+}
+
+/// Move the pending exception of a context to a value.
+#[allow(unsafe_code)]
+pub fn take_pending_exception(cx: SafeJSContext, rval: MutableHandleValue) {
+    unsafe {
+        JS_GetPendingException(*cx, rval);
+        JS_ClearPendingException(*cx);
+    }
+}
+
+/// Get a member of the underlying source, sink or transformer or of the queuing strategy
+/// of a stream, which are undefined if the object was omitted.
+#[allow(unsafe_code)]
+pub fn get_member(
+    cx: SafeJSContext,
+    object: HandleObject,
+    name: &[u8],
+    rval: MutableHandleValue,
+) -> ErrorResult {
+    if object.is_null() {
+        return Ok(());
+    }
+    if unsafe { !JS_GetProperty(*cx, object, name.as_ptr() as *const _, rval) } {
+        return Err(Error::JSFailed);
+    }
+    Ok(())
+}
+
+#[allow(unsafe_code)]
+pub fn get_method(
+    cx: SafeJSContext,
+    object: HandleObject,
+    name: &[u8],
+) -> Fallible<Option<Rc<Function>>> {
+    rooted!(in(*cx) let mut method = UndefinedValue());
+    get_member(cx, object, name, method.handle_mut())?;
+    if method.is_undefined() {
+        return Ok(None);
+    }
+    if !method.is_object() || unsafe { !IsCallable(method.to_object()) } {
+        return Err(Error::Type(format!(
+            "{} is not callable",
+            String::from_utf8_lossy(&name[..name.len() - 1])
+        )));
+    }
+    Ok(Some(unsafe { Function::new(cx, method.to_object()) }))
+}
+
+/// Invoke a method of an underlying source, sink or transformer with it as `this`.
+#[allow(unsafe_code)]
+pub fn invoke(
+    cx: SafeJSContext,
+    object: &Heap<*mut JSObject>,
+    method: &Function,
+    arguments: &[JSVal],
+    rval: MutableHandleValue,
+) -> bool {
+    rooted!(in(*cx) let this = ObjectValue(object.get()));
+    rooted!(in(*cx) let method = ObjectValue(method.callback()));
+    unsafe {
+        Call(
+            *cx,
+            this.handle(),
+            method.handle(),
+            &HandleValueArray::from_rooted_slice(arguments),
+            rval,
+        )
+    }
+}
+
+/// Invoke a method of an underlying source, sink or transformer, returning a promise
+/// resolved with its result, or rejected with the exception that it throws. The promise
+/// is resolved with undefined if the method is missing.
+/// <https://streams.spec.whatwg.org/#promise-call>
+pub fn promise_call(
+    global: &GlobalScope,
+    object: &Heap<*mut JSObject>,
+    method: &Option<Rc<Function>>,
+    arguments: &[JSVal],
+) -> Rc<Promise> {
+    let cx = global.get_cx();
+    let promise = Promise::new(global);
+    rooted!(in(*cx) let mut result = UndefinedValue());
+    if let Some(ref method) = *method {
+        if !invoke(cx, object, method, arguments, result.handle_mut()) {
+            take_pending_exception(cx, result.handle_mut());
+            promise.reject(cx, result.handle());
+            return promise;
+        }
+    }
+    promise.resolve(cx, result.handle());
+    promise
+}
+
+/// Invoke the start method of an underlying source, sink or transformer, returning a
+/// promise resolved with its result, or the exception that it throws.
+pub fn start_call(
+    global: &GlobalScope,
+    object: &Heap<*mut JSObject>,
+    method: &Option<Rc<Function>>,
+    controller: &impl DomObject,
+) -> Fallible<Rc<Promise>> {
+    let cx = global.get_cx();
+    rooted!(in(*cx) let controller = ObjectValue(controller.reflector().get_jsobject().get()));
+    rooted!(in(*cx) let mut result = UndefinedValue());
+    if let Some(ref method) = *method {
+        if !invoke(cx, object, method, &[controller.get()], result.handle_mut()) {
+            return Err(Error::JSFailed);
+        }
+    }
+    let promise = Promise::new(global);
+    promise.resolve(cx, result.handle());
+    Ok(promise)
+}
+
+#[allow(unsafe_code)]
+pub fn convert<T: FromJSValConvertible>(
+    cx: SafeJSContext,
+    value: HandleValue,
+    option: T::Config,
+) -> Fallible<T> {
+    match unsafe { T::from_jsval(*cx, value, option) } {
+        Ok(ConversionResult::Success(value)) => Ok(value),
+        Ok(ConversionResult::Failure(error)) => Err(Error::Type(error.into_owned())),
+        Err(()) => Err(Error::JSFailed),
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#validate-and-normalize-high-water-mark>
+pub fn high_water_mark_from_value(
+    cx: SafeJSContext,
+    value: HandleValue,
+    default: f64,
+) -> Fallible<f64> {
+    if value.is_undefined() {
+        return Ok(default);
+    }
+    let high_water_mark: f64 = convert(cx, value, ())?;
+    if high_water_mark.is_nan() || high_water_mark < 0. {
+        return Err(Error::Range(
+            "The high water mark must be a non-negative number".to_owned(),
+        ));
+    }
+    Ok(high_water_mark)
+}
+
+/// The size method of a queuing strategy, which is missing if the strategy does not have
+/// one.
+/// <https://streams.spec.whatwg.org/#make-size-algorithm-from-size-function>
+#[allow(unsafe_code)]
+pub fn size_function_from_value(
+    cx: SafeJSContext,
+    value: HandleValue,
+) -> Fallible<Option<Rc<Function>>> {
+    if value.is_undefined() {
+        return Ok(None);
+    }
+    if !value.is_object() || unsafe { !IsCallable(value.to_object()) } {
+        return Err(Error::Type(
+            "The size of the strategy is not callable".to_owned(),
+        ));
+    }
+    Ok(Some(unsafe { Function::new(cx, value.to_object()) }))
+}
+
+/// Compute the size of a chunk with the size method of a queuing strategy, which leaves
+/// its exception pending if it throws. Chunks have a size of 1 without a size method.
+#[allow(unsafe_code)]
+pub fn chunk_size(
+    cx: SafeJSContext,
+    size: &Option<Rc<Function>>,
+    chunk: HandleValue,
+) -> Result<f64, ()> {
+    let size = match *size {
+        Some(ref size) => size,
+        None => return Ok(1.),
+    };
+    rooted!(in(*cx) let this = UndefinedValue());
+    rooted!(in(*cx) let size = ObjectValue(size.callback()));
+    rooted!(in(*cx) let mut result = UndefinedValue());
+    let arguments = [chunk.get()];
+    unsafe {
+        if !Call(
+            *cx,
+            this.handle(),
+            size.handle(),
+            &HandleValueArray::from_rooted_slice(&arguments),
+            result.handle_mut(),
+        ) {
+            return Err(());
+        }
+        match f64::from_jsval(*cx, result.handle(), ())? {
+            ConversionResult::Success(size) => Ok(size),
+            ConversionResult::Failure(_) => unreachable!("Every value converts to a number"),
+        }
+    }
+}
+
+/// Convert an error to the value that a stream is errored with.
+#[allow(unsafe_code)]
+pub fn error_to_jsval(global: &GlobalScope, error: Error, rval: MutableHandleValue) {
+    unsafe { error.to_jsval(*global.get_cx(), global, rval) };
+}
+
+/// Copy the bytes of a chunk which is a buffer source.
+#[allow(unsafe_code)]
+pub fn buffer_source_bytes(cx: SafeJSContext, chunk: HandleValue) -> Option<Vec<u8>> {
+    if !chunk.is_object() {
+        return None;
+    }
+    rooted!(in(*cx) let object = chunk.to_object());
+    if let Ok(view) = unsafe { ArrayBufferView::from(object.get()) } {
+        return Some(unsafe { view.as_slice().to_vec() });
+    }
+    if let Ok(buffer) = unsafe { ArrayBuffer::from(object.get()) } {
+        return Some(unsafe { buffer.as_slice().to_vec() });
+    }
+    None
+}
@@ -0,0 +1,339 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Piping a readable stream to a writable stream, which reads a chunk whenever the
+//! writable stream does not apply backpressure, and propagates the closing and the errors
+//! of each stream to the other.
+//! <https://streams.spec.whatwg.org/#readable-stream-pipe-to>
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamBinding::StreamPipeOptions;
+use crate::dom::bindings::error::Error;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::bindings::root::Dom;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::{ReadableStream, StreamState};
+use crate::dom::readablestreamdefaultreader::ReadableStreamDefaultReader;
+use crate::dom::streams::{convert, error_to_jsval, get_member, mark_promise_as_handled};
+use crate::dom::writablestream::{WritableStream, WritableStreamState};
+use crate::dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use crate::script_runtime::JSContext as SafeJSContext;
+use js::jsapi::{Heap, JSContext};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Pipe a stream to another, returning a promise which is settled once piping stops.
+/// Neither stream may be locked.
+#[allow(unrooted_must_root)]
+pub fn pipe_to(
+    source: &ReadableStream,
+    dest: &WritableStream,
+    options: &StreamPipeOptions,
+) -> Rc<Promise> {
+    let global = source.global();
+    let reader = ReadableStreamDefaultReader::acquire(&global, source)
+        .expect("The source of a pipe is not locked");
+    let writer = WritableStreamDefaultWriter::acquire(&global, dest)
+        .expect("The destination of a pipe is not locked");
+    source.set_disturbed();
+    let pipe = Rc::new(Pipe {
+        source: Dom::from_ref(source),
+        dest: Dom::from_ref(dest),
+        reader: Dom::from_ref(&*reader),
+        writer: Dom::from_ref(&*writer),
+        prevent_close: options.preventClose,
+        prevent_abort: options.preventAbort,
+        prevent_cancel: options.preventCancel,
+        shutting_down: Cell::new(false),
+        action: Cell::new(None),
+        error: Heap::default(),
+        has_error: Cell::new(false),
+        pending_read: Default::default(),
+        pending_write: Default::default(),
+        promise: Promise::new(&global),
+    });
+    pipe.react(&reader.generic().closed(), PipeStep::SourceClosed);
+    pipe.react(&writer.closed(), PipeStep::DestClosed);
+    if !pipe.check_states() {
+        pipe.wait_for_ready();
+    }
+    pipe.promise.clone()
+}
+
+/// The action that is performed once piping shuts down, before the locks of the streams
+/// are released.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum ShutdownAction {
+    AbortDest,
+    CancelSource,
+    CloseDest,
+}
+
+/// The promises that a pipe reacts to.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum PipeStep {
+    /// The ready promise of the writer, after which a chunk is read.
+    Ready,
+    /// The promise of a read, whose chunk is written.
+    Read,
+    SourceClosed,
+    DestClosed,
+    /// The promise of the pending read, whose chunk is written before the shutdown
+    /// action.
+    LastRead,
+    /// The promise of the last write, which is settled before the shutdown action.
+    LastWrite,
+    Action,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct Pipe {
+    source: Dom<ReadableStream>,
+    dest: Dom<WritableStream>,
+    reader: Dom<ReadableStreamDefaultReader>,
+    writer: Dom<WritableStreamDefaultWriter>,
+    prevent_close: bool,
+    prevent_abort: bool,
+    prevent_cancel: bool,
+    shutting_down: Cell<bool>,
+    action: Cell<Option<ShutdownAction>>,
+    /// The error that the promise of the pipe is rejected with, if any.
+    #[ignore_malloc_size_of = "mozjs"]
+    error: Heap<JSVal>,
+    has_error: Cell<bool>,
+    /// The promise of the read whose chunk was not written yet, if any.
+    #[ignore_malloc_size_of = "Rc"]
+    pending_read: DomRefCell<Option<Rc<Promise>>>,
+    /// The promise of the last write, if any.
+    #[ignore_malloc_size_of = "Rc"]
+    pending_write: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+}
+
+impl Pipe {
+    #[allow(unrooted_must_root)]
+    fn react(self: &Rc<Self>, promise: &Promise, step: PipeStep) {
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(PipeHandler {
+                pipe: self.clone(),
+                step,
+                fulfilled,
+            })
+        };
+        let handler = PromiseNativeHandler::new(
+            &self.source.global(),
+            Some(handler(true)),
+            Some(handler(false)),
+        );
+        promise.append_native_handler(&handler);
+    }
+
+    /// Shut down if either stream is errored, closed or closing, in the order in which
+    /// the specification checks these conditions. Returns whether piping shut down.
+    fn check_states(self: &Rc<Self>) -> bool {
+        if self.source.state() == StreamState::Errored {
+            self.source_errored(self.source.stored_error());
+        } else if self.dest.state() == WritableStreamState::Errored {
+            self.dest_errored(self.dest.stored_error());
+        } else if self.source.state() == StreamState::Closed {
+            self.source_closed();
+        } else if self.dest.close_queued_or_in_flight() ||
+            self.dest.state() == WritableStreamState::Closed
+        {
+            self.dest_closing();
+        }
+        self.shutting_down.get()
+    }
+
+    fn source_errored(self: &Rc<Self>, error: HandleValue) {
+        if self.prevent_abort {
+            self.shutdown(None, Some(error));
+        } else {
+            self.shutdown(Some(ShutdownAction::AbortDest), Some(error));
+        }
+    }
+
+    fn dest_errored(self: &Rc<Self>, error: HandleValue) {
+        if self.prevent_cancel {
+            self.shutdown(None, Some(error));
+        } else {
+            self.shutdown(Some(ShutdownAction::CancelSource), Some(error));
+        }
+    }
+
+    fn source_closed(self: &Rc<Self>) {
+        if self.prevent_close {
+            self.shutdown(None, None);
+        } else {
+            self.shutdown(Some(ShutdownAction::CloseDest), None);
+        }
+    }
+
+    fn dest_closing(self: &Rc<Self>) {
+        let global = self.source.global();
+        rooted!(in(*global.get_cx()) let mut error = UndefinedValue());
+        error_to_jsval(
+            &global,
+            Error::Type("The destination of the pipe is closed".to_owned()),
+            error.handle_mut(),
+        );
+        if self.prevent_cancel {
+            self.shutdown(None, Some(error.handle()));
+        } else {
+            self.shutdown(Some(ShutdownAction::CancelSource), Some(error.handle()));
+        }
+    }
+
+    /// Read a chunk once the destination does not apply backpressure.
+    fn wait_for_ready(self: &Rc<Self>) {
+        self.react(&self.writer.ready(), PipeStep::Ready);
+    }
+
+    fn read(self: &Rc<Self>) {
+        let promise = Promise::new(&self.source.global());
+        *self.pending_read.borrow_mut() = Some(promise.clone());
+        self.source.read(&promise);
+        self.react(&promise, PipeStep::Read);
+    }
+
+    /// Write the chunk of a read result, returning whether the result had one.
+    fn write(&self, cx: SafeJSContext, result: HandleValue) -> bool {
+        if !result.is_object() {
+            return false;
+        }
+        rooted!(in(*cx) let result_object = result.to_object());
+        rooted!(in(*cx) let mut done = UndefinedValue());
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        let done = get_member(cx, result_object.handle(), b"done\0", done.handle_mut())
+            .and_then(|()| convert::<bool>(cx, done.handle(), ()));
+        if done.unwrap_or(true) ||
+            get_member(cx, result_object.handle(), b"value\0", chunk.handle_mut()).is_err()
+        {
+            return false;
+        }
+        let promise = self.writer.write(chunk.handle());
+        mark_promise_as_handled(&promise);
+        *self.pending_write.borrow_mut() = Some(promise);
+        true
+    }
+
+    fn dest_is_writable(&self) -> bool {
+        self.dest.state() == WritableStreamState::Writable && !self.dest.close_queued_or_in_flight()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown-with-action>
+    fn shutdown(self: &Rc<Self>, action: Option<ShutdownAction>, error: Option<HandleValue>) {
+        if self.shutting_down.get() {
+            return;
+        }
+        self.shutting_down.set(true);
+        self.action.set(action);
+        if let Some(error) = error {
+            self.error.set(error.get());
+            self.has_error.set(true);
+        }
+        // Every chunk that was read is written before the action is performed.
+        if self.dest_is_writable() {
+            let pending_read = self.pending_read.borrow_mut().take();
+            if let Some(pending_read) = pending_read {
+                return self.react(&pending_read, PipeStep::LastRead);
+            }
+        }
+        self.wait_for_last_write();
+    }
+
+    fn wait_for_last_write(self: &Rc<Self>) {
+        if self.dest_is_writable() {
+            let pending_write = self.pending_write.borrow_mut().take();
+            if let Some(pending_write) = pending_write {
+                return self.react(&pending_write, PipeStep::LastWrite);
+            }
+        }
+        self.perform_action();
+    }
+
+    fn perform_action(self: &Rc<Self>) {
+        let promise = match self.action.get() {
+            None => return self.finalize(),
+            Some(ShutdownAction::AbortDest) => self.dest.abort(self.error.handle()),
+            Some(ShutdownAction::CancelSource) => self.source.cancel(self.error.handle()),
+            Some(ShutdownAction::CloseDest) => self.writer.close_with_error_propagation(),
+        };
+        self.react(&promise, PipeStep::Action);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-finalize>
+    fn finalize(&self) {
+        self.writer.release();
+        self.reader.generic().release();
+        if self.has_error.get() {
+            self.promise
+                .reject(self.source.global().get_cx(), self.error.handle());
+        } else {
+            self.promise.resolve_native(&());
+        }
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct PipeHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    pipe: Rc<Pipe>,
+    step: PipeStep,
+    fulfilled: bool,
+}
+
+impl Callback for PipeHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        let pipe = &self.pipe;
+        match (self.step, self.fulfilled) {
+            (PipeStep::SourceClosed, true) => pipe.source_closed(),
+            (PipeStep::SourceClosed, false) => pipe.source_errored(v),
+            (PipeStep::DestClosed, true) => pipe.dest_closing(),
+            (PipeStep::DestClosed, false) => pipe.dest_errored(v),
+            (PipeStep::LastRead, fulfilled) => {
+                if fulfilled && pipe.dest_is_writable() {
+                    pipe.write(cx, v);
+                }
+                pipe.wait_for_last_write();
+            },
+            (PipeStep::LastWrite, _) => pipe.perform_action(),
+            (PipeStep::Action, true) => pipe.finalize(),
+            (PipeStep::Action, false) => {
+                // The error of the action replaces the original one.
+                pipe.error.set(v.get());
+                pipe.has_error.set(true);
+                pipe.finalize();
+            },
+            // The errors of reads and writes shut down piping through the closed
+            // promises of the reader and the writer.
+            (PipeStep::Ready, false) | (PipeStep::Read, false) => {
+                pipe.pending_read.borrow_mut().take();
+            },
+            (PipeStep::Ready, true) => {
+                if !pipe.shutting_down.get() && !pipe.check_states() {
+                    pipe.read();
+                }
+            },
+            // Once piping shuts down, the chunk of the pending read is written by the
+            // `LastRead` step instead.
+            (PipeStep::Read, true) => {
+                if !pipe.shutting_down.get() {
+                    pipe.pending_read.borrow_mut().take();
+                    if pipe.write(cx, v) {
+                        pipe.wait_for_ready();
+                    }
+                }
+            },
+        }
+    }
+}
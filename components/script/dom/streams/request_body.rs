@@ -0,0 +1,143 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Transmitting a readable stream as the body of a request, whose chunks are read one at
+//! a time as the network requests them, that is once it sent the previous one.
+//! <https://fetch.spec.whatwg.org/#concept-request-transmit-body>
+
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::streams::{convert, get_member, mark_promise_as_handled};
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::task_source::{TaskSource, TaskSourceName};
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
+use js::jsapi::JSContext;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use js::typedarray::Uint8Array;
+use net_traits::request::{BodyChunkRequest, BodyChunkResponse};
+
+/// Lock a stream to transmit it as the body of a request, returning the channel which the
+/// network requests its chunks with.
+pub fn transmit_body(stream: &ReadableStream) -> Fallible<IpcSender<BodyChunkRequest>> {
+    stream.acquire_default_reader()?;
+    let global = stream.global();
+    let task_source = global.networking_task_source();
+    let canceller = global.task_canceller(TaskSourceName::Networking);
+    let (sender, receiver) = ipc::channel().unwrap();
+    let stream = Trusted::new(stream);
+    let mut chunk_sender: Option<IpcSender<BodyChunkResponse>> = None;
+    ROUTER.add_route(
+        receiver.to_opaque(),
+        Box::new(move |message| {
+            let stream = stream.clone();
+            match message.to().unwrap() {
+                BodyChunkRequest::Connect(sender) => chunk_sender = Some(sender),
+                BodyChunkRequest::Chunk => {
+                    let sender = match chunk_sender {
+                        Some(ref sender) => sender.clone(),
+                        None => {
+                            warn!("A body chunk was requested before connecting");
+                            return;
+                        },
+                    };
+                    let _ = task_source.queue_with_canceller(
+                        task!(read_body_chunk: move || {
+                            read_chunk(&stream.root(), sender);
+                        }),
+                        &canceller,
+                    );
+                },
+                BodyChunkRequest::Done => {
+                    let _ = task_source.queue_with_canceller(
+                        task!(cancel_body_stream: move || {
+                            cancel(&stream.root());
+                        }),
+                        &canceller,
+                    );
+                },
+            }
+        }),
+    );
+    Ok(sender)
+}
+
+/// Read a chunk of the stream, sending it to the network once it is read.
+fn read_chunk(stream: &ReadableStream, sender: IpcSender<BodyChunkResponse>) {
+    let global = stream.global();
+    let _ac = enter_realm(&*global);
+    let promise = Promise::new(&global);
+    stream.read(&promise);
+    let handler = |fulfilled| -> Box<dyn Callback> {
+        Box::new(BodyChunkHandler {
+            sender: sender.clone(),
+            fulfilled,
+        })
+    };
+    let handler = PromiseNativeHandler::new(&global, Some(handler(true)), Some(handler(false)));
+    promise.append_native_handler(&handler);
+}
+
+/// Cancel the rest of the stream once the network does not need it anymore, which does
+/// nothing if it was read to its end.
+fn cancel(stream: &ReadableStream) {
+    let global = stream.global();
+    let _ac = enter_realm(&*global);
+    rooted!(in(*global.get_cx()) let reason = UndefinedValue());
+    mark_promise_as_handled(&stream.cancel(reason.handle()));
+}
+
+/// The chunk of a read result, which must be a Uint8Array.
+#[allow(unsafe_code)]
+fn read_result_chunk(cx: SafeJSContext, result: HandleValue) -> BodyChunkResponse {
+    if !result.is_object() {
+        return BodyChunkResponse::Error;
+    }
+    rooted!(in(*cx) let result_object = result.to_object());
+    rooted!(in(*cx) let mut done = UndefinedValue());
+    rooted!(in(*cx) let mut chunk = UndefinedValue());
+    let done = get_member(cx, result_object.handle(), b"done\0", done.handle_mut())
+        .and_then(|()| convert::<bool>(cx, done.handle(), ()));
+    match done {
+        Ok(true) => return BodyChunkResponse::Done,
+        Ok(false) => {},
+        Err(_) => return BodyChunkResponse::Error,
+    }
+    if get_member(cx, result_object.handle(), b"value\0", chunk.handle_mut()).is_err() ||
+        !chunk.is_object()
+    {
+        return BodyChunkResponse::Error;
+    }
+    rooted!(in(*cx) let chunk_object = chunk.to_object());
+    match unsafe { Uint8Array::from(chunk_object.get()) } {
+        Ok(array) => BodyChunkResponse::Chunk(unsafe { array.as_slice().to_vec() }),
+        Err(()) => BodyChunkResponse::Error,
+    }
+}
+
+/// Send the chunk of a read to the network, or the error which aborts the request.
+#[derive(JSTraceable, MallocSizeOf)]
+struct BodyChunkHandler {
+    #[ignore_malloc_size_of = "Channels are hard"]
+    sender: IpcSender<BodyChunkResponse>,
+    fulfilled: bool,
+}
+
+impl Callback for BodyChunkHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let response = if self.fulfilled {
+            read_result_chunk(unsafe { SafeJSContext::from_ptr(cx) }, v)
+        } else {
+            BodyChunkResponse::Error
+        };
+        let _ = self.sender.send(response);
+    }
+}
@@ -50,6 +50,7 @@ use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
 use crate::dom::url::URL;
 use crate::realms::{AlreadyInRealm, InRealm};
 use crate::script_runtime::JSContext as SafeJSContext;
+use crate::script_thread::ScriptThread;
 use crate::timers::OneshotTimerCallback;
 use dom_struct::dom_struct;
 use js::jsapi::{Heap, JSContext, JSObject};
@@ -59,7 +60,7 @@ use js::rust::CustomAutoRooterGuard;
 use js::rust::{HandleObject, HandleValue};
 use js::typedarray;
 use script_traits::serializable::BlobImpl;
-use script_traits::MsDuration;
+use script_traits::{EmbedderStylesheets, MsDuration};
 use servo_config::prefs;
 use std::borrow::ToOwned;
 use std::ptr;
@@ -1052,6 +1053,28 @@ impl TestBindingMethods for TestBinding {
         }
     }
 
+    fn SetEmbedderStylesheets(&self, user_agent: Vec<DOMString>, user: Vec<DOMString>) {
+        let global = self.global();
+        let window = global.as_window();
+        let url = window.get_url();
+        let with_url = |stylesheets: Vec<DOMString>| {
+            stylesheets
+                .into_iter()
+                .map(|source| (String::from(source), url.clone()))
+                .collect()
+        };
+        let stylesheets = EmbedderStylesheets {
+            user_agent: with_url(user_agent),
+            user: with_url(user),
+        };
+        if let Some(window_proxy) = window.undiscarded_window_proxy() {
+            ScriptThread::set_embedder_stylesheets(
+                window_proxy.top_level_browsing_context_id(),
+                stylesheets,
+            );
+        }
+    }
+
     fn AdvanceClock(&self, ms: i32, tick: bool) {
         self.global().as_window().advance_animation_clock(ms, tick);
     }
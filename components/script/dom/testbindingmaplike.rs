@@ -0,0 +1,48 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// check-tidy: no specs after this line
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::TestBindingMaplikeBinding;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::like::Maplike;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+use indexmap::IndexMap;
+
+#[dom_struct]
+pub struct TestBindingMaplike {
+    reflector: Reflector,
+    #[ignore_malloc_size_of = "defined in indexmap"]
+    internal: DomRefCell<IndexMap<DOMString, i32>>,
+}
+
+impl TestBindingMaplike {
+    fn new(global: &GlobalScope) -> DomRoot<TestBindingMaplike> {
+        reflect_dom_object(
+            Box::new(TestBindingMaplike {
+                reflector: Reflector::new(),
+                internal: DomRefCell::new(IndexMap::new()),
+            }),
+            global,
+            TestBindingMaplikeBinding::Wrap,
+        )
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(global: &GlobalScope) -> Fallible<DomRoot<TestBindingMaplike>> {
+        Ok(TestBindingMaplike::new(global))
+    }
+}
+
+impl Maplike for TestBindingMaplike {
+    type Key = DOMString;
+    type Value = i32;
+
+    maplike!(self, internal);
+}
@@ -0,0 +1,47 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// check-tidy: no specs after this line
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::TestBindingSetlikeBinding;
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::like::Setlike;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+use indexmap::IndexSet;
+
+#[dom_struct]
+pub struct TestBindingSetlike {
+    reflector: Reflector,
+    #[ignore_malloc_size_of = "defined in indexmap"]
+    internal: DomRefCell<IndexSet<DOMString>>,
+}
+
+impl TestBindingSetlike {
+    fn new(global: &GlobalScope) -> DomRoot<TestBindingSetlike> {
+        reflect_dom_object(
+            Box::new(TestBindingSetlike {
+                reflector: Reflector::new(),
+                internal: DomRefCell::new(IndexSet::new()),
+            }),
+            global,
+            TestBindingSetlikeBinding::Wrap,
+        )
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(global: &GlobalScope) -> Fallible<DomRoot<TestBindingSetlike>> {
+        Ok(TestBindingSetlike::new(global))
+    }
+}
+
+impl Setlike for TestBindingSetlike {
+    type Key = DOMString;
+
+    setlike!(self, internal);
+}
@@ -7,15 +7,20 @@
 //!
 //! https://html.spec.whatwg.org/multipage/#textFieldSelection
 
+use crate::clipboard_provider::ClipboardProvider;
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ClipboardEventBinding::ClipboardEventMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLFormElementBinding::SelectionMode;
 use crate::dom::bindings::conversions::DerivedFrom;
 use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::str::DOMString;
-use crate::dom::event::{EventBubbles, EventCancelable};
+use crate::dom::clipboardevent::{ClipboardAction, ClipboardEvent};
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
 use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
 use crate::dom::node::{window_from_node, Node, NodeDamage};
-use crate::textinput::{SelectionDirection, SelectionState, TextInput, UTF8Bytes};
+use crate::textinput::{Direction, SelectionDirection, SelectionState, TextInput, UTF8Bytes};
 use script_traits::ScriptToConstellationChan;
 
 pub trait TextControlElement: DerivedFrom<EventTarget> + DerivedFrom<Node> {
@@ -262,6 +267,78 @@ impl<'a, E: TextControlElement> TextControlSelection<'a, E> {
         Ok(())
     }
 
+    /// The default action of a clipboard event targeted at the text control: copy or
+    /// cut its selected text, or paste the text of the clipboard at its edit point.
+    /// The text of password controls can't be copied, and the text of controls which
+    /// are not mutable can't be changed. Returns whether the value changed.
+    ///
+    /// <https://w3c.github.io/clipboard-apis/#clipboard-actions>
+    pub fn handle_clipboard_event(
+        &self,
+        event: &ClipboardEvent,
+        copyable: bool,
+        mutable: bool,
+    ) -> bool {
+        let action = match ClipboardAction::from_event_type(&event.upcast::<Event>().type_()) {
+            Some(action) => action,
+            None => return false,
+        };
+        event.upcast::<Event>().mark_as_handled();
+
+        let window = window_from_node(self.element);
+        let mut textinput = self.textinput.borrow_mut();
+        let changed = match action {
+            ClipboardAction::Copy | ClipboardAction::Cut => {
+                let text = match textinput.get_selection_text() {
+                    Some(ref text) if copyable && !text.is_empty() => text.clone(),
+                    _ => return false,
+                };
+                window
+                    .upcast::<GlobalScope>()
+                    .script_to_constellation_chan()
+                    .clone()
+                    .set_clipboard_contents(text);
+                if action == ClipboardAction::Cut && mutable {
+                    textinput.delete_char(Direction::Backward);
+                    true
+                } else {
+                    false
+                }
+            },
+            ClipboardAction::Paste => {
+                let text = event
+                    .GetClipboardData()
+                    .and_then(|clipboard_data| clipboard_data.data("text/plain"));
+                match text {
+                    Some(text) if mutable => {
+                        textinput.insert_string(text);
+                        true
+                    },
+                    _ => false,
+                }
+            },
+        };
+        drop(textinput);
+
+        if changed {
+            self.element.set_dirty_value_flag(true);
+            window
+                .task_manager()
+                .user_interaction_task_source()
+                .queue_event(
+                    &self.element.upcast::<EventTarget>(),
+                    atom!("input"),
+                    EventBubbles::Bubbles,
+                    EventCancelable::NotCancelable,
+                    &window,
+                );
+            self.element
+                .upcast::<Node>()
+                .dirty(NodeDamage::OtherNodeDamage);
+        }
+        changed
+    }
+
     fn start(&self) -> u32 {
         let UTF8Bytes(offset) = self.textinput.borrow().selection_start_offset();
         offset as u32
@@ -7,99 +7,65 @@ use crate::dom::bindings::codegen::Bindings::TextDecoderBinding::{
     TextDecodeOptions, TextDecoderMethods,
 };
 use crate::dom::bindings::codegen::UnionTypes::ArrayBufferViewOrArrayBuffer;
-use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::error::Fallible;
 use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::textdecodercommon::TextDecoderCommon;
 use dom_struct::dom_struct;
-use encoding_rs::{Decoder, DecoderResult, Encoding};
-use std::borrow::ToOwned;
-use std::cell::{Cell, RefCell};
+use std::cell::Cell;
 
 #[dom_struct]
-#[allow(non_snake_case)]
 pub struct TextDecoder {
     reflector_: Reflector,
-    encoding: &'static Encoding,
-    fatal: bool,
-    ignoreBOM: bool,
-    #[ignore_malloc_size_of = "defined in encoding_rs"]
-    decoder: RefCell<Decoder>,
-    in_stream: RefCell<Vec<u8>>,
+    decoder: TextDecoderCommon,
     do_not_flush: Cell<bool>,
 }
 
-#[allow(non_snake_case)]
 impl TextDecoder {
-    fn new_inherited(encoding: &'static Encoding, fatal: bool, ignoreBOM: bool) -> TextDecoder {
+    fn new_inherited(decoder: TextDecoderCommon) -> TextDecoder {
         TextDecoder {
             reflector_: Reflector::new(),
-            encoding: encoding,
-            fatal: fatal,
-            ignoreBOM: ignoreBOM,
-            decoder: RefCell::new(if ignoreBOM {
-                encoding.new_decoder()
-            } else {
-                encoding.new_decoder_without_bom_handling()
-            }),
-            in_stream: RefCell::new(Vec::new()),
+            decoder,
             do_not_flush: Cell::new(false),
         }
     }
 
-    fn make_range_error() -> Fallible<DomRoot<TextDecoder>> {
-        Err(Error::Range(
-            "The given encoding is not supported.".to_owned(),
-        ))
-    }
-
-    pub fn new(
-        global: &GlobalScope,
-        encoding: &'static Encoding,
-        fatal: bool,
-        ignoreBOM: bool,
-    ) -> DomRoot<TextDecoder> {
+    fn new(global: &GlobalScope, decoder: TextDecoderCommon) -> DomRoot<TextDecoder> {
         reflect_dom_object(
-            Box::new(TextDecoder::new_inherited(encoding, fatal, ignoreBOM)),
+            Box::new(TextDecoder::new_inherited(decoder)),
             global,
             TextDecoderBinding::Wrap,
         )
     }
 
     /// <https://encoding.spec.whatwg.org/#dom-textdecoder>
+    #[allow(non_snake_case)]
     pub fn Constructor(
         global: &GlobalScope,
         label: DOMString,
         options: &TextDecoderBinding::TextDecoderOptions,
     ) -> Fallible<DomRoot<TextDecoder>> {
-        let encoding = match Encoding::for_label_no_replacement(label.as_bytes()) {
-            None => return TextDecoder::make_range_error(),
-            Some(enc) => enc,
-        };
-        Ok(TextDecoder::new(
-            global,
-            encoding,
-            options.fatal,
-            options.ignoreBOM,
-        ))
+        let decoder = TextDecoderCommon::from_label(&label, options.fatal, options.ignoreBOM)?;
+        Ok(TextDecoder::new(global, decoder))
     }
 }
 
 impl TextDecoderMethods for TextDecoder {
     // https://encoding.spec.whatwg.org/#dom-textdecoder-encoding
     fn Encoding(&self) -> DOMString {
-        DOMString::from(self.encoding.name().to_ascii_lowercase())
+        DOMString::from(self.decoder.encoding().name().to_ascii_lowercase())
     }
 
     // https://encoding.spec.whatwg.org/#dom-textdecoder-fatal
     fn Fatal(&self) -> bool {
-        self.fatal
+        self.decoder.fatal()
     }
 
     // https://encoding.spec.whatwg.org/#dom-textdecoder-ignorebom
     fn IgnoreBOM(&self) -> bool {
-        self.ignoreBOM
+        self.decoder.ignore_bom()
     }
 
     // https://encoding.spec.whatwg.org/#dom-textdecoder-decode
@@ -110,62 +76,20 @@ impl TextDecoderMethods for TextDecoder {
     ) -> Fallible<USVString> {
         // Step 1.
         if !self.do_not_flush.get() {
-            if self.ignoreBOM {
-                self.decoder
-                    .replace(self.encoding.new_decoder_without_bom_handling());
-            } else {
-                self.decoder.replace(self.encoding.new_decoder());
-            }
-            self.in_stream.replace(Vec::new());
+            self.decoder.reset();
         }
 
         // Step 2.
         self.do_not_flush.set(options.stream);
 
         // Step 3.
-        match input {
-            Some(ArrayBufferViewOrArrayBuffer::ArrayBufferView(ref a)) => {
-                self.in_stream.borrow_mut().extend_from_slice(&a.to_vec());
-            },
-            Some(ArrayBufferViewOrArrayBuffer::ArrayBuffer(ref a)) => {
-                self.in_stream.borrow_mut().extend_from_slice(&a.to_vec());
-            },
-            None => {},
+        let input = match input {
+            Some(ArrayBufferViewOrArrayBuffer::ArrayBufferView(ref a)) => a.to_vec(),
+            Some(ArrayBufferViewOrArrayBuffer::ArrayBuffer(ref a)) => a.to_vec(),
+            None => vec![],
         };
 
-        let mut decoder = self.decoder.borrow_mut();
-        let (remaining, s) = {
-            let mut in_stream = self.in_stream.borrow_mut();
-
-            let (remaining, s) = if self.fatal {
-                // Step 4.
-                let mut out_stream = String::with_capacity(
-                    decoder
-                        .max_utf8_buffer_length_without_replacement(in_stream.len())
-                        .unwrap(),
-                );
-                // Step 5: Implemented by encoding_rs::Decoder.
-                match decoder.decode_to_string_without_replacement(
-                    &in_stream,
-                    &mut out_stream,
-                    !options.stream,
-                ) {
-                    (DecoderResult::InputEmpty, read) => (in_stream.split_off(read), out_stream),
-                    // Step 5.3.3.
-                    _ => return Err(Error::Type("Decoding failed".to_owned())),
-                }
-            } else {
-                // Step 4.
-                let mut out_stream =
-                    String::with_capacity(decoder.max_utf8_buffer_length(in_stream.len()).unwrap());
-                // Step 5: Implemented by encoding_rs::Decoder.
-                let (_result, read, _replaced) =
-                    decoder.decode_to_string(&in_stream, &mut out_stream, !options.stream);
-                (in_stream.split_off(read), out_stream)
-            };
-            (remaining, s)
-        };
-        self.in_stream.replace(remaining);
-        Ok(USVString(s))
+        // Steps 4-5: Implemented by encoding_rs::Decoder.
+        self.decoder.decode(&input, !options.stream).map(USVString)
     }
 }
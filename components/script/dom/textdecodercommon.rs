@@ -0,0 +1,115 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::str::DOMString;
+use encoding_rs::{Decoder, DecoderResult, Encoding, REPLACEMENT};
+use std::cell::RefCell;
+
+/// The encoding of a label, which ignores its leading and trailing whitespace and its case.
+/// <https://encoding.spec.whatwg.org/#concept-encoding-get>
+pub fn encoding_from_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.as_bytes())
+}
+
+/// The state of the incremental decoding of `TextDecoder` and `TextDecoderStream`.
+/// <https://encoding.spec.whatwg.org/#textdecodercommon>
+#[derive(JSTraceable, MallocSizeOf)]
+#[allow(non_snake_case)]
+pub struct TextDecoderCommon {
+    encoding: &'static Encoding,
+    fatal: bool,
+    ignoreBOM: bool,
+    #[ignore_malloc_size_of = "defined in encoding_rs"]
+    decoder: RefCell<Decoder>,
+    io_queue: RefCell<Vec<u8>>,
+}
+
+#[allow(non_snake_case)]
+impl TextDecoderCommon {
+    fn new(encoding: &'static Encoding, fatal: bool, ignoreBOM: bool) -> TextDecoderCommon {
+        TextDecoderCommon {
+            encoding,
+            fatal,
+            ignoreBOM,
+            decoder: RefCell::new(TextDecoderCommon::new_decoder(encoding, ignoreBOM)),
+            io_queue: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The decoder of the encoding of a label, which is an error for the labels of no
+    /// encoding and for those of the replacement encoding.
+    /// <https://encoding.spec.whatwg.org/#dom-textdecoder>
+    pub fn from_label(
+        label: &DOMString,
+        fatal: bool,
+        ignoreBOM: bool,
+    ) -> Fallible<TextDecoderCommon> {
+        match encoding_from_label(label) {
+            Some(encoding) if encoding != REPLACEMENT => {
+                Ok(TextDecoderCommon::new(encoding, fatal, ignoreBOM))
+            },
+            _ => Err(Error::Range(
+                "The given encoding is not supported.".to_owned(),
+            )),
+        }
+    }
+
+    fn new_decoder(encoding: &'static Encoding, ignoreBOM: bool) -> Decoder {
+        if ignoreBOM {
+            encoding.new_decoder_without_bom_handling()
+        } else {
+            encoding.new_decoder()
+        }
+    }
+
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    pub fn fatal(&self) -> bool {
+        self.fatal
+    }
+
+    pub fn ignore_bom(&self) -> bool {
+        self.ignoreBOM
+    }
+
+    /// Start decoding a new stream, forgetting the state of the previous one.
+    pub fn reset(&self) {
+        self.decoder.replace(TextDecoderCommon::new_decoder(
+            self.encoding,
+            self.ignoreBOM,
+        ));
+        self.io_queue.replace(Vec::new());
+    }
+
+    /// Decode the input following the input decoded so far, flushing the decoder if it is
+    /// the end of the stream.
+    /// <https://encoding.spec.whatwg.org/#concept-td-serialize>
+    pub fn decode(&self, input: &[u8], last: bool) -> Fallible<String> {
+        let mut decoder = self.decoder.borrow_mut();
+        let mut io_queue = self.io_queue.borrow_mut();
+        io_queue.extend_from_slice(input);
+
+        let (read, output) = if self.fatal {
+            let mut output = String::with_capacity(
+                decoder
+                    .max_utf8_buffer_length_without_replacement(io_queue.len())
+                    .unwrap(),
+            );
+            match decoder.decode_to_string_without_replacement(&io_queue, &mut output, last) {
+                (DecoderResult::InputEmpty, read) => (read, output),
+                _ => return Err(Error::Type("Decoding failed".to_owned())),
+            }
+        } else {
+            let mut output =
+                String::with_capacity(decoder.max_utf8_buffer_length(io_queue.len()).unwrap());
+            let (_result, read, _replaced) = decoder.decode_to_string(&io_queue, &mut output, last);
+            (read, output)
+        };
+        io_queue.drain(..read);
+        Ok(output)
+    }
+}
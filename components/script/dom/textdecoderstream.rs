@@ -0,0 +1,100 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::TextDecoderBinding::TextDecoderOptions;
+use crate::dom::bindings::codegen::Bindings::TextDecoderStreamBinding::{
+    self, TextDecoderStreamMethods,
+};
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::streams::encoding::TextDecoderAlgorithms;
+use crate::dom::textdecodercommon::TextDecoderCommon;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::transformstreamdefaultcontroller::Transformer;
+use crate::dom::writablestream::WritableStream;
+use dom_struct::dom_struct;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct TextDecoderStream {
+    reflector_: Reflector,
+    #[ignore_malloc_size_of = "Rc"]
+    decoder: Rc<TextDecoderCommon>,
+    transform: Dom<TransformStream>,
+}
+
+impl TextDecoderStream {
+    fn new_inherited(
+        decoder: Rc<TextDecoderCommon>,
+        transform: &TransformStream,
+    ) -> TextDecoderStream {
+        TextDecoderStream {
+            reflector_: Reflector::new(),
+            decoder,
+            transform: Dom::from_ref(transform),
+        }
+    }
+
+    fn new(
+        global: &GlobalScope,
+        decoder: Rc<TextDecoderCommon>,
+        transform: &TransformStream,
+    ) -> DomRoot<TextDecoderStream> {
+        reflect_dom_object(
+            Box::new(TextDecoderStream::new_inherited(decoder, transform)),
+            global,
+            TextDecoderStreamBinding::Wrap,
+        )
+    }
+
+    // https://encoding.spec.whatwg.org/#dom-textdecoderstream
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        label: DOMString,
+        options: &TextDecoderOptions,
+    ) -> Fallible<DomRoot<TextDecoderStream>> {
+        let decoder = Rc::new(TextDecoderCommon::from_label(
+            &label,
+            options.fatal,
+            options.ignoreBOM,
+        )?);
+        let algorithms = TextDecoderAlgorithms::new(decoder.clone());
+        let transformer = Transformer::with_algorithms(Box::new(algorithms));
+        let transform =
+            TransformStream::new_with_transformer(global, transformer, 1., None, 0., None)?;
+        Ok(TextDecoderStream::new(global, decoder, &transform))
+    }
+}
+
+impl TextDecoderStreamMethods for TextDecoderStream {
+    // https://encoding.spec.whatwg.org/#dom-textdecoder-encoding
+    fn Encoding(&self) -> DOMString {
+        DOMString::from(self.decoder.encoding().name().to_ascii_lowercase())
+    }
+
+    // https://encoding.spec.whatwg.org/#dom-textdecoder-fatal
+    fn Fatal(&self) -> bool {
+        self.decoder.fatal()
+    }
+
+    // https://encoding.spec.whatwg.org/#dom-textdecoder-ignorebom
+    fn IgnoreBOM(&self) -> bool {
+        self.decoder.ignore_bom()
+    }
+
+    // https://streams.spec.whatwg.org/#dom-generictransformstream-readable
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.transform.readable()
+    }
+
+    // https://streams.spec.whatwg.org/#dom-generictransformstream-writable
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.transform.writable()
+    }
+}
@@ -0,0 +1,67 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::TextEncoderStreamBinding::{
+    self, TextEncoderStreamMethods,
+};
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::streams::encoding::TextEncoderAlgorithms;
+use crate::dom::transformstream::TransformStream;
+use crate::dom::transformstreamdefaultcontroller::Transformer;
+use crate::dom::writablestream::WritableStream;
+use dom_struct::dom_struct;
+
+#[dom_struct]
+pub struct TextEncoderStream {
+    reflector_: Reflector,
+    transform: Dom<TransformStream>,
+}
+
+impl TextEncoderStream {
+    fn new_inherited(transform: &TransformStream) -> TextEncoderStream {
+        TextEncoderStream {
+            reflector_: Reflector::new(),
+            transform: Dom::from_ref(transform),
+        }
+    }
+
+    fn new(global: &GlobalScope, transform: &TransformStream) -> DomRoot<TextEncoderStream> {
+        reflect_dom_object(
+            Box::new(TextEncoderStream::new_inherited(transform)),
+            global,
+            TextEncoderStreamBinding::Wrap,
+        )
+    }
+
+    // https://encoding.spec.whatwg.org/#dom-textencoderstream
+    #[allow(non_snake_case)]
+    pub fn Constructor(global: &GlobalScope) -> Fallible<DomRoot<TextEncoderStream>> {
+        let transformer = Transformer::with_algorithms(Box::new(TextEncoderAlgorithms::new()));
+        let transform =
+            TransformStream::new_with_transformer(global, transformer, 1., None, 0., None)?;
+        Ok(TextEncoderStream::new(global, &transform))
+    }
+}
+
+impl TextEncoderStreamMethods for TextEncoderStream {
+    // https://encoding.spec.whatwg.org/#dom-textencoder-encoding
+    fn Encoding(&self) -> DOMString {
+        DOMString::from("utf-8")
+    }
+
+    // https://streams.spec.whatwg.org/#dom-generictransformstream-readable
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.transform.readable()
+    }
+
+    // https://streams.spec.whatwg.org/#dom-generictransformstream-writable
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.transform.writable()
+    }
+}
@@ -6,6 +6,7 @@ use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::TextTrackBinding::{
     self, TextTrackKind, TextTrackMethods, TextTrackMode,
 };
+use crate::dom::bindings::codegen::Bindings::TextTrackCueListBinding::TextTrackCueListMethods;
 use crate::dom::bindings::error::{Error, ErrorResult};
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
@@ -21,12 +22,14 @@ use std::cell::Cell;
 #[dom_struct]
 pub struct TextTrack {
     eventtarget: EventTarget,
-    kind: TextTrackKind,
-    label: String,
-    language: String,
+    kind: Cell<TextTrackKind>,
+    label: DomRefCell<String>,
+    language: DomRefCell<String>,
     id: String,
     mode: Cell<TextTrackMode>,
     cue_list: MutNullableDom<TextTrackCueList>,
+    /// <https://html.spec.whatwg.org/multipage/#text-track-cue-active-flag>
+    active_cues: DomRefCell<Vec<Dom<TextTrackCue>>>,
     track_list: DomRefCell<Option<Dom<TextTrackList>>>,
 }
 
@@ -41,12 +44,13 @@ impl TextTrack {
     ) -> TextTrack {
         TextTrack {
             eventtarget: EventTarget::new_inherited(),
-            kind: kind,
-            label: label.into(),
-            language: language.into(),
+            kind: Cell::new(kind),
+            label: DomRefCell::new(label.into()),
+            language: DomRefCell::new(language.into()),
             id: id.into(),
             mode: Cell::new(mode),
             cue_list: Default::default(),
+            active_cues: Default::default(),
             track_list: DomRefCell::new(track_list.map(|t| Dom::from_ref(t))),
         }
     }
@@ -85,22 +89,89 @@ impl TextTrack {
     pub fn remove_track_list(&self) {
         *self.track_list.borrow_mut() = None;
     }
+
+    pub fn set_kind(&self, kind: TextTrackKind) {
+        self.kind.set(kind);
+    }
+
+    pub fn set_label(&self, label: DOMString) {
+        *self.label.borrow_mut() = label.into();
+    }
+
+    pub fn set_language(&self, language: DOMString) {
+        *self.language.borrow_mut() = language.into();
+    }
+
+    pub fn active_cues(&self) -> Vec<DomRoot<TextTrackCue>> {
+        self.active_cues
+            .borrow()
+            .iter()
+            .map(|cue| DomRoot::from_ref(&**cue))
+            .collect()
+    }
+
+    /// Update the cues that are active at `current_time`, and return the cues that
+    /// became active and the cues that stopped being active, in that order.
+    /// <https://html.spec.whatwg.org/multipage/#time-marches-on>
+    pub fn update_active_cues(
+        &self,
+        current_time: f64,
+    ) -> (Vec<DomRoot<TextTrackCue>>, Vec<DomRoot<TextTrackCue>>) {
+        let mut active_cues = vec![];
+        if self.mode.get() != TextTrackMode::Disabled {
+            let cues = self.get_cues();
+            for index in 0..cues.Length() {
+                let cue = cues.item(index as usize).unwrap();
+                if cue.start_time() <= current_time && current_time < cue.end_time() {
+                    active_cues.push(cue);
+                }
+            }
+        }
+
+        let previous = self.active_cues();
+        let entered = active_cues
+            .iter()
+            .filter(|cue| !previous.contains(*cue))
+            .cloned()
+            .collect();
+        let exited = previous
+            .into_iter()
+            .filter(|cue| !active_cues.contains(cue))
+            .collect();
+        *self.active_cues.borrow_mut() = active_cues
+            .iter()
+            .map(|cue| Dom::from_ref(&**cue))
+            .collect();
+        (entered, exited)
+    }
+
+    /// Let the media element of this track know that the cues it displays may have changed.
+    fn cues_changed(&self) {
+        let media_element = self
+            .track_list
+            .borrow()
+            .as_ref()
+            .and_then(|track_list| track_list.media_element());
+        if let Some(media_element) = media_element {
+            media_element.text_tracks_changed();
+        }
+    }
 }
 
 impl TextTrackMethods for TextTrack {
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-kind
     fn Kind(&self) -> TextTrackKind {
-        self.kind
+        self.kind.get()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-label
     fn Label(&self) -> DOMString {
-        DOMString::from(self.label.clone())
+        DOMString::from(self.label.borrow().clone())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-language
     fn Language(&self) -> DOMString {
-        DOMString::from(self.language.clone())
+        DOMString::from(self.language.borrow().clone())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-id
@@ -115,7 +186,11 @@ impl TextTrackMethods for TextTrack {
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-mode
     fn SetMode(&self, value: TextTrackMode) {
-        self.mode.set(value)
+        if self.mode.get() == value {
+            return;
+        }
+        self.mode.set(value);
+        self.cues_changed();
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-cues
@@ -128,9 +203,18 @@ impl TextTrackMethods for TextTrack {
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-activecues
     fn GetActiveCues(&self) -> Option<DomRoot<TextTrackCueList>> {
-        // XXX implement active cues logic
-        //      https://github.com/servo/servo/issues/22314
-        Some(TextTrackCueList::new(&self.global().as_window(), &[]))
+        match self.Mode() {
+            TextTrackMode::Disabled => None,
+            _ => {
+                let active_cues = self.active_cues();
+                let active_cues: Vec<&TextTrackCue> =
+                    active_cues.iter().map(|cue| &**cue).collect();
+                Some(TextTrackCueList::new(
+                    &self.global().as_window(),
+                    &active_cues,
+                ))
+            },
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-texttrack-addcue
@@ -147,6 +231,8 @@ impl TextTrackMethods for TextTrack {
         }
         // Step 4
         self.get_cues().add(cue);
+        cue.set_track(Some(self));
+        self.cues_changed();
         Ok(())
     }
 
@@ -160,6 +246,11 @@ impl TextTrackMethods for TextTrack {
         }?;
         // Step 2
         cues.remove(index);
+        cue.set_track(None);
+        self.active_cues
+            .borrow_mut()
+            .retain(|active_cue| &**active_cue != cue);
+        self.cues_changed();
         Ok(())
     }
 
@@ -12,6 +12,7 @@ use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
+use crate::dom::htmlmediaelement::HTMLMediaElement;
 use crate::dom::texttrack::TextTrack;
 use crate::dom::trackevent::TrackEvent;
 use crate::dom::window::Window;
@@ -22,24 +23,43 @@ use dom_struct::dom_struct;
 pub struct TextTrackList {
     eventtarget: EventTarget,
     dom_tracks: DomRefCell<Vec<Dom<TextTrack>>>,
+    media_element: Option<Dom<HTMLMediaElement>>,
 }
 
 impl TextTrackList {
-    pub fn new_inherited(tracks: &[&TextTrack]) -> TextTrackList {
+    pub fn new_inherited(
+        tracks: &[&TextTrack],
+        media_element: Option<&HTMLMediaElement>,
+    ) -> TextTrackList {
         TextTrackList {
             eventtarget: EventTarget::new_inherited(),
             dom_tracks: DomRefCell::new(tracks.iter().map(|g| Dom::from_ref(&**g)).collect()),
+            media_element: media_element.map(|m| Dom::from_ref(m)),
         }
     }
 
-    pub fn new(window: &Window, tracks: &[&TextTrack]) -> DomRoot<TextTrackList> {
+    pub fn new(
+        window: &Window,
+        tracks: &[&TextTrack],
+        media_element: Option<&HTMLMediaElement>,
+    ) -> DomRoot<TextTrackList> {
         reflect_dom_object(
-            Box::new(TextTrackList::new_inherited(tracks)),
+            Box::new(TextTrackList::new_inherited(tracks, media_element)),
             window,
             TextTrackListBinding::Wrap,
         )
     }
 
+    pub fn media_element(&self) -> Option<DomRoot<HTMLMediaElement>> {
+        self.media_element
+            .as_ref()
+            .map(|media_element| DomRoot::from_ref(&**media_element))
+    }
+
+    pub fn len(&self) -> usize {
+        self.dom_tracks.borrow().len()
+    }
+
     pub fn item(&self, idx: usize) -> Option<DomRoot<TextTrack>> {
         self.dom_tracks
             .borrow()
@@ -98,9 +118,6 @@ impl TextTrackList {
         }
     }
 
-    // FIXME(#22314, dlrobertson) allow TextTracks to be
-    // removed from the TextTrackList.
-    #[allow(dead_code)]
     pub fn remove(&self, idx: usize) {
         if let Some(track) = self.dom_tracks.borrow().get(idx) {
             track.remove_track_list();
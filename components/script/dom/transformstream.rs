@@ -0,0 +1,355 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::TransformStreamBinding::{
+    self, TransformStreamMethods,
+};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::{ReadableStream, StreamState, UnderlyingSource};
+use crate::dom::streams::{get_member, high_water_mark_from_value};
+use crate::dom::streams::{settle_promise_with, size_function_from_value};
+use crate::dom::transformstreamdefaultcontroller::{TransformStreamDefaultController, Transformer};
+use crate::dom::writablestream::{UnderlyingSink, WritableStream, WritableStreamState};
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::{HandleObject, HandleValue};
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct TransformStream {
+    reflector_: Reflector,
+    readable: MutNullableDom<ReadableStream>,
+    writable: MutNullableDom<WritableStream>,
+    controller: MutNullableDom<TransformStreamDefaultController>,
+    /// Whether the readable side is not pulled, which makes writes wait until it is.
+    backpressure: Cell<bool>,
+    /// The promise which is resolved when the backpressure changes.
+    #[ignore_malloc_size_of = "Rc"]
+    backpressure_change_promise: DomRefCell<Option<Rc<Promise>>>,
+    /// The promise of the start method of the transformer, which starts both sides.
+    #[ignore_malloc_size_of = "Rc"]
+    start_promise: Rc<Promise>,
+}
+
+impl TransformStream {
+    fn new_inherited(global: &GlobalScope) -> TransformStream {
+        TransformStream {
+            reflector_: Reflector::new(),
+            readable: Default::default(),
+            writable: Default::default(),
+            controller: Default::default(),
+            backpressure: Cell::new(false),
+            backpressure_change_promise: DomRefCell::new(None),
+            start_promise: Promise::new(global),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<TransformStream> {
+        reflect_dom_object(
+            Box::new(TransformStream::new_inherited(global)),
+            global,
+            TransformStreamBinding::Wrap,
+        )
+    }
+
+    // https://streams.spec.whatwg.org/#ts-constructor
+    #[allow(unrooted_must_root)]
+    pub fn Constructor(
+        cx: SafeJSContext,
+        global: &GlobalScope,
+        transformer: Option<*mut JSObject>,
+        writable_strategy: Option<*mut JSObject>,
+        readable_strategy: Option<*mut JSObject>,
+    ) -> Fallible<DomRoot<TransformStream>> {
+        rooted!(in(*cx) let transformer_object = transformer.unwrap_or(ptr::null_mut()));
+        rooted!(in(*cx) let writable_strategy = writable_strategy.unwrap_or(ptr::null_mut()));
+        rooted!(in(*cx) let readable_strategy = readable_strategy.unwrap_or(ptr::null_mut()));
+
+        for name in &[&b"readableType\0"[..], &b"writableType\0"[..]] {
+            rooted!(in(*cx) let mut type_ = UndefinedValue());
+            get_member(cx, transformer_object.handle(), name, type_.handle_mut())?;
+            if !type_.is_undefined() {
+                return Err(Error::Range(
+                    "The sides of transform streams do not have a type".to_owned(),
+                ));
+            }
+        }
+
+        let (writable_high_water_mark, writable_size) =
+            strategy_members(cx, writable_strategy.handle(), 1.)?;
+        let (readable_high_water_mark, readable_size) =
+            strategy_members(cx, readable_strategy.handle(), 0.)?;
+        let transformer = Transformer::new(cx, transformer_object.handle())?;
+        TransformStream::new_with_transformer(
+            global,
+            transformer,
+            writable_high_water_mark,
+            writable_size,
+            readable_high_water_mark,
+            readable_size,
+        )
+    }
+
+    /// Create a transform stream whose sides have the given queuing strategies.
+    /// <https://streams.spec.whatwg.org/#initialize-transform-stream>
+    #[allow(unrooted_must_root)]
+    pub fn new_with_transformer(
+        global: &GlobalScope,
+        transformer: Transformer,
+        writable_high_water_mark: f64,
+        writable_size: Option<Rc<Function>>,
+        readable_high_water_mark: f64,
+        readable_size: Option<Rc<Function>>,
+    ) -> Fallible<DomRoot<TransformStream>> {
+        let stream = TransformStream::new(global);
+        let writable = WritableStream::new_with_sink(
+            global,
+            UnderlyingSink::for_transform_stream(&stream),
+            writable_high_water_mark,
+            writable_size,
+        )?;
+        stream.writable.set(Some(&writable));
+        let readable = ReadableStream::new_with_source(
+            global,
+            UnderlyingSource::for_transform_stream(&stream),
+            readable_high_water_mark,
+            readable_size,
+        )?;
+        stream.readable.set(Some(&readable));
+        stream.set_backpressure(true);
+
+        let controller = TransformStreamDefaultController::new(global, &stream, transformer);
+        stream.controller.set(Some(&controller));
+        let start_result = controller.start()?;
+        settle_promise_with(&stream.start_promise, &start_result);
+        Ok(stream)
+    }
+
+    pub fn readable(&self) -> DomRoot<ReadableStream> {
+        self.readable
+            .get()
+            .expect("A transform stream has a readable side once it is constructed")
+    }
+
+    pub fn writable(&self) -> DomRoot<WritableStream> {
+        self.writable
+            .get()
+            .expect("A transform stream has a writable side once it is constructed")
+    }
+
+    fn controller(&self) -> DomRoot<TransformStreamDefaultController> {
+        self.controller
+            .get()
+            .expect("A transform stream has a controller once it is constructed")
+    }
+
+    pub fn start_promise(&self) -> Rc<Promise> {
+        self.start_promise.clone()
+    }
+
+    pub fn backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error>
+    pub fn error(&self, error: HandleValue) {
+        if let Some(controller) = self.readable().default_controller() {
+            controller.error(error);
+        }
+        self.error_writable_and_unblock_write(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error-writable-and-unblock-write>
+    pub fn error_writable_and_unblock_write(&self, error: HandleValue) {
+        self.writable().controller().error_if_needed(error);
+        if self.backpressure.get() {
+            self.set_backpressure(false);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-set-backpressure>
+    pub fn set_backpressure(&self, backpressure: bool) {
+        let promise = Promise::new(&self.global());
+        if let Some(promise) = self
+            .backpressure_change_promise
+            .borrow_mut()
+            .replace(promise)
+        {
+            promise.resolve_native(&());
+        }
+        self.backpressure.set(backpressure);
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-write-algorithm>
+    #[allow(unrooted_must_root)]
+    pub fn sink_write(&self, chunk: HandleValue) -> Rc<Promise> {
+        let controller = self.controller();
+        if !self.backpressure.get() {
+            return controller.perform_transform(chunk);
+        }
+        let promise = Promise::new(&self.global());
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(BackpressureChangeHandler {
+                stream: Dom::from_ref(self),
+                chunk: Heap::boxed(chunk.get()),
+                promise: promise.clone(),
+            })),
+            None,
+        );
+        self.backpressure_change_promise
+            .borrow()
+            .as_ref()
+            .expect("A transform stream has a backpressure change promise")
+            .append_native_handler(&handler);
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-abort-algorithm>
+    pub fn sink_abort(&self, reason: HandleValue) -> Rc<Promise> {
+        self.error(reason);
+        let promise = Promise::new(&self.global());
+        promise.resolve_native(&());
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-close-algorithm>
+    #[allow(unrooted_must_root)]
+    pub fn sink_close(&self) -> Rc<Promise> {
+        let flush_promise = self.controller().flush();
+        let promise = Promise::new(&self.global());
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(FlushHandler {
+                stream: Dom::from_ref(self),
+                promise: promise.clone(),
+                fulfilled,
+            })
+        };
+        let handler =
+            PromiseNativeHandler::new(&self.global(), Some(handler(true)), Some(handler(false)));
+        flush_promise.append_native_handler(&handler);
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-source-pull>
+    pub fn source_pull(&self) -> Rc<Promise> {
+        self.set_backpressure(false);
+        self.backpressure_change_promise
+            .borrow()
+            .clone()
+            .expect("A transform stream has a backpressure change promise")
+    }
+
+    /// Error the writable side once the readable side is canceled.
+    pub fn source_cancel(&self, reason: HandleValue) -> Rc<Promise> {
+        self.error_writable_and_unblock_write(reason);
+        let promise = Promise::new(&self.global());
+        promise.resolve_native(&());
+        promise
+    }
+}
+
+impl TransformStreamMethods for TransformStream {
+    // https://streams.spec.whatwg.org/#ts-readable
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.readable()
+    }
+
+    // https://streams.spec.whatwg.org/#ts-writable
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.writable()
+    }
+}
+
+/// Transform a chunk written to the stream once the readable side is pulled.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct BackpressureChangeHandler {
+    stream: Dom<TransformStream>,
+    #[ignore_malloc_size_of = "mozjs"]
+    chunk: Box<Heap<JSVal>>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+}
+
+impl Callback for BackpressureChangeHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, _v: HandleValue) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        let writable = self.stream.writable();
+        if writable.state() == WritableStreamState::Erroring {
+            self.promise.reject(cx, writable.stored_error());
+            return;
+        }
+        rooted!(in(*cx) let chunk = self.chunk.get());
+        let transform_promise = self.stream.controller().perform_transform(chunk.handle());
+        settle_promise_with(&self.promise, &transform_promise);
+    }
+}
+
+/// Close the readable side once the flush method of the transformer completes.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct FlushHandler {
+    stream: Dom<TransformStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for FlushHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        let readable = self.stream.readable();
+        if !self.fulfilled {
+            self.stream.error(v);
+            self.promise.reject(cx, readable.stored_error());
+            return;
+        }
+        if readable.state() == StreamState::Errored {
+            self.promise.reject(cx, readable.stored_error());
+            return;
+        }
+        if let Some(controller) = readable.default_controller() {
+            if controller.can_close_or_enqueue() {
+                controller.close();
+            }
+        }
+        self.promise.resolve_native(&());
+    }
+}
+
+/// The high water mark and the size method of a queuing strategy.
+/// <https://streams.spec.whatwg.org/#extract-high-water-mark>
+fn strategy_members(
+    cx: SafeJSContext,
+    strategy: HandleObject,
+    default_high_water_mark: f64,
+) -> Fallible<(f64, Option<Rc<Function>>)> {
+    rooted!(in(*cx) let mut size = UndefinedValue());
+    get_member(cx, strategy, b"size\0", size.handle_mut())?;
+    rooted!(in(*cx) let mut high_water_mark = UndefinedValue());
+    get_member(
+        cx,
+        strategy,
+        b"highWaterMark\0",
+        high_water_mark.handle_mut(),
+    )?;
+    let size = size_function_from_value(cx, size.handle())?;
+    let high_water_mark =
+        high_water_mark_from_value(cx, high_water_mark.handle(), default_high_water_mark)?;
+    Ok((high_water_mark, size))
+}
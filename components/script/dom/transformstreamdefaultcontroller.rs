@@ -0,0 +1,285 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::TransformStreamDefaultControllerBinding::{
+    self, TransformStreamDefaultControllerMethods,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::trace::JSTraceable;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestreamdefaultcontroller::ReadableStreamDefaultController;
+use crate::dom::streams::{error_to_jsval, get_method, promise_call};
+use crate::dom::streams::{settle_promise_with_result, start_call};
+use crate::dom::transformstream::TransformStream;
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{ExceptionStackBehavior, Heap, JSContext, JSObject, JS_ClearPendingException};
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::wrappers::JS_SetPendingException;
+use js::rust::{HandleObject, HandleValue};
+use js::typedarray::{CreateWith, Uint8Array};
+use malloc_size_of::MallocSizeOf;
+use std::ptr;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct TransformStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<TransformStream>,
+    transformer: Transformer,
+}
+
+impl TransformStreamDefaultController {
+    fn new_inherited(
+        stream: &TransformStream,
+        transformer: Transformer,
+    ) -> TransformStreamDefaultController {
+        TransformStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            transformer,
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        stream: &TransformStream,
+        transformer: Transformer,
+    ) -> DomRoot<TransformStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(TransformStreamDefaultController::new_inherited(
+                stream,
+                transformer,
+            )),
+            global,
+            TransformStreamDefaultControllerBinding::Wrap,
+        )
+    }
+
+    /// Invoke the start method of the transformer, returning a promise resolved with its
+    /// result, or the exception that it throws.
+    pub fn start(&self) -> Fallible<Rc<Promise>> {
+        start_call(
+            &self.global(),
+            &self.transformer.object,
+            &self.transformer.start,
+            self,
+        )
+    }
+
+    fn readable_controller(&self) -> DomRoot<ReadableStreamDefaultController> {
+        self.stream
+            .readable()
+            .default_controller()
+            .expect("The readable side of a transform stream has a default controller")
+    }
+
+    /// Enqueue a chunk to the readable side, leaving the exception pending if that fails.
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-enqueue>
+    #[allow(unsafe_code)]
+    pub fn enqueue(&self, chunk: HandleValue) -> ErrorResult {
+        let controller = self.readable_controller();
+        if !controller.can_close_or_enqueue() {
+            return Err(Error::Type(
+                "The readable side cannot be enqueued to".to_owned(),
+            ));
+        }
+        if controller.enqueue(chunk).is_err() {
+            // Enqueueing errored the readable side, whose error is rethrown.
+            let readable = self.stream.readable();
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let error = readable.stored_error().get());
+            self.stream.error_writable_and_unblock_write(error.handle());
+            unsafe {
+                JS_ClearPendingException(*cx);
+                JS_SetPendingException(*cx, error.handle(), ExceptionStackBehavior::Capture);
+            }
+            return Err(Error::JSFailed);
+        }
+        let backpressure = controller.has_backpressure();
+        if backpressure != self.stream.backpressure() {
+            assert!(backpressure);
+            self.stream.set_backpressure(true);
+        }
+        Ok(())
+    }
+
+    /// Enqueue a Uint8Array of bytes which the algorithms of a transform stream of the user
+    /// agent output, leaving the exception pending if that fails.
+    #[allow(unsafe_code)]
+    pub fn enqueue_bytes(&self, bytes: &[u8]) -> ErrorResult {
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut array = ptr::null_mut::<JSObject>());
+        unsafe { Uint8Array::create(*cx, CreateWith::Slice(bytes), array.handle_mut()) }
+            .map_err(|()| Error::JSFailed)?;
+        rooted!(in(*cx) let chunk = ObjectValue(array.get()));
+        self.enqueue(chunk.handle())
+    }
+
+    /// Invoke the transform method of the transformer, or enqueue the chunk as it is
+    /// without one, erroring the stream if that fails.
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-perform-transform>
+    #[allow(unrooted_must_root)]
+    pub fn perform_transform(&self, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        let cx = global.get_cx();
+        let transform_promise = match (&self.transformer.algorithms, &self.transformer.transform) {
+            (Some(algorithms), _) => algorithms.transform(self, chunk),
+            (None, Some(_)) => {
+                rooted!(in(*cx) let controller = ObjectValue(self.reflector().get_jsobject().get()));
+                promise_call(
+                    &global,
+                    &self.transformer.object,
+                    &self.transformer.transform,
+                    &[chunk.get(), controller.get()],
+                )
+            },
+            (None, None) => {
+                let promise = Promise::new(&global);
+                settle_promise_with_result(&promise, self.enqueue(chunk));
+                promise
+            },
+        };
+        let promise = Promise::new(&global);
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(TransformHandler {
+                stream: Dom::from_ref(&*self.stream),
+                promise: promise.clone(),
+                fulfilled,
+            })
+        };
+        let handler = PromiseNativeHandler::new(&global, Some(handler(true)), Some(handler(false)));
+        transform_promise.append_native_handler(&handler);
+        promise
+    }
+
+    /// Invoke the flush method of the transformer.
+    pub fn flush(&self) -> Rc<Promise> {
+        if let Some(ref algorithms) = self.transformer.algorithms {
+            return algorithms.flush(self);
+        }
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let controller = ObjectValue(self.reflector().get_jsobject().get()));
+        promise_call(
+            &global,
+            &self.transformer.object,
+            &self.transformer.flush,
+            &[controller.get()],
+        )
+    }
+}
+
+impl TransformStreamDefaultControllerMethods for TransformStreamDefaultController {
+    // https://streams.spec.whatwg.org/#ts-default-controller-desired-size
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.readable_controller().desired_size()
+    }
+
+    // https://streams.spec.whatwg.org/#ts-default-controller-enqueue
+    fn Enqueue(&self, _cx: SafeJSContext, chunk: HandleValue) -> ErrorResult {
+        self.enqueue(chunk)
+    }
+
+    // https://streams.spec.whatwg.org/#ts-default-controller-error
+    fn Error(&self, _cx: SafeJSContext, reason: HandleValue) {
+        self.stream.error(reason);
+    }
+
+    // https://streams.spec.whatwg.org/#ts-default-controller-terminate
+    fn Terminate(&self) {
+        let controller = self.readable_controller();
+        if controller.can_close_or_enqueue() {
+            controller.close();
+        }
+        let global = self.global();
+        rooted!(in(*global.get_cx()) let mut error = UndefinedValue());
+        error_to_jsval(
+            &global,
+            Error::Type("The transform stream was terminated".to_owned()),
+            error.handle_mut(),
+        );
+        self.stream.error_writable_and_unblock_write(error.handle());
+    }
+}
+
+/// Error the stream if the transformation of a chunk fails.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct TransformHandler {
+    stream: Dom<TransformStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for TransformHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        if self.fulfilled {
+            self.promise.resolve_native(&());
+        } else {
+            self.stream.error(v);
+            self.promise
+                .reject(unsafe { SafeJSContext::from_ptr(cx) }, v);
+        }
+    }
+}
+
+/// The algorithms of the transform streams which the user agent creates, which are run
+/// in place of the methods of a transformer.
+pub trait TransformAlgorithms: JSTraceable + MallocSizeOf {
+    /// Transform a chunk written to the stream, enqueuing the output to the controller.
+    fn transform(
+        &self,
+        controller: &TransformStreamDefaultController,
+        chunk: HandleValue,
+    ) -> Rc<Promise>;
+
+    /// Enqueue the remaining output to the controller once the writable side is closed.
+    fn flush(&self, controller: &TransformStreamDefaultController) -> Rc<Promise>;
+}
+
+/// The object with which script constructs a transform stream, whose methods are invoked
+/// with it as `this`, or the algorithms of a transform stream of the user agent.
+/// <https://streams.spec.whatwg.org/#transformer-api>
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct Transformer {
+    #[ignore_malloc_size_of = "mozjs"]
+    object: Box<Heap<*mut JSObject>>,
+    #[ignore_malloc_size_of = "Rc"]
+    start: Option<Rc<Function>>,
+    #[ignore_malloc_size_of = "Rc"]
+    transform: Option<Rc<Function>>,
+    #[ignore_malloc_size_of = "Rc"]
+    flush: Option<Rc<Function>>,
+    algorithms: Option<Box<dyn TransformAlgorithms>>,
+}
+
+impl Transformer {
+    pub fn new(cx: SafeJSContext, object: HandleObject) -> Fallible<Transformer> {
+        Ok(Transformer {
+            flush: get_method(cx, object, b"flush\0")?,
+            start: get_method(cx, object, b"start\0")?,
+            transform: get_method(cx, object, b"transform\0")?,
+            object: Heap::boxed(object.get()),
+            algorithms: None,
+        })
+    }
+
+    pub fn with_algorithms(algorithms: Box<dyn TransformAlgorithms>) -> Transformer {
+        Transformer {
+            object: Heap::boxed(ptr::null_mut()),
+            start: None,
+            transform: None,
+            flush: None,
+            algorithms: Some(algorithms),
+        }
+    }
+}
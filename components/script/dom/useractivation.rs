@@ -0,0 +1,44 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::UserActivationBinding::{self, UserActivationMethods};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+
+#[dom_struct]
+pub struct UserActivation {
+    reflector_: Reflector,
+    window: Dom<Window>,
+}
+
+impl UserActivation {
+    fn new_inherited(window: &Window) -> UserActivation {
+        UserActivation {
+            reflector_: Reflector::new(),
+            window: Dom::from_ref(window),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<UserActivation> {
+        reflect_dom_object(
+            Box::new(UserActivation::new_inherited(window)),
+            window,
+            UserActivationBinding::Wrap,
+        )
+    }
+}
+
+impl UserActivationMethods for UserActivation {
+    // https://html.spec.whatwg.org/multipage/#dom-useractivation-hasbeenactive
+    fn HasBeenActive(&self) -> bool {
+        self.window.has_sticky_activation()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-useractivation-isactive
+    fn IsActive(&self) -> bool {
+        self.window.has_transient_activation()
+    }
+}
@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::VisualViewportBinding::{self, VisualViewportMethods};
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::window::Window;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use servo_atoms::Atom;
+
+#[dom_struct]
+pub struct VisualViewport {
+    eventtarget: EventTarget,
+    window: Dom<Window>,
+}
+
+impl VisualViewport {
+    fn new_inherited(window: &Window) -> VisualViewport {
+        VisualViewport {
+            eventtarget: EventTarget::new_inherited(),
+            window: Dom::from_ref(window),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<VisualViewport> {
+        reflect_dom_object(
+            Box::new(VisualViewport::new_inherited(window)),
+            window,
+            VisualViewportBinding::Wrap,
+        )
+    }
+
+    /// Queue a `resize` or `scroll` event at the visual viewport.
+    pub fn queue_event(&self, name: Atom) {
+        let window = &*self.window;
+        window
+            .task_manager()
+            .dom_manipulation_task_source()
+            .queue_simple_event(self.upcast(), name, window);
+    }
+
+    fn is_fully_active(&self) -> bool {
+        self.window.Document().is_fully_active()
+    }
+}
+
+impl VisualViewportMethods for VisualViewport {
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-offsetleft
+    fn OffsetLeft(&self) -> Finite<f64> {
+        if !self.is_fully_active() {
+            return Finite::wrap(0.0);
+        }
+        Finite::wrap(self.window.visual_viewport_offset().x as f64)
+    }
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-offsettop
+    fn OffsetTop(&self) -> Finite<f64> {
+        if !self.is_fully_active() {
+            return Finite::wrap(0.0);
+        }
+        Finite::wrap(self.window.visual_viewport_offset().y as f64)
+    }
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-pageleft
+    fn PageLeft(&self) -> Finite<f64> {
+        if !self.is_fully_active() {
+            return Finite::wrap(0.0);
+        }
+        // The root scroll offset is that of the visual viewport, which is already the
+        // offset of the layout viewport plus offsetLeft.
+        Finite::wrap(self.window.ScrollX() as f64)
+    }
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-pagetop
+    fn PageTop(&self) -> Finite<f64> {
+        if !self.is_fully_active() {
+            return Finite::wrap(0.0);
+        }
+        Finite::wrap(self.window.ScrollY() as f64)
+    }
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-width
+    fn Width(&self) -> Finite<f64> {
+        if !self.is_fully_active() {
+            return Finite::wrap(0.0);
+        }
+        Finite::wrap(self.window.InnerWidth() as f64 / *self.Scale())
+    }
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-height
+    fn Height(&self) -> Finite<f64> {
+        if !self.is_fully_active() {
+            return Finite::wrap(0.0);
+        }
+        Finite::wrap(self.window.InnerHeight() as f64 / *self.Scale())
+    }
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-scale
+    fn Scale(&self) -> Finite<f64> {
+        if !self.is_fully_active() {
+            return Finite::wrap(0.0);
+        }
+        Finite::wrap(self.window.visual_viewport_scale())
+    }
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-onresize
+    event_handler!(resize, GetOnresize, SetOnresize);
+
+    // https://wicg.github.io/visual-viewport/#dom-visualviewport-onscroll
+    event_handler!(scroll, GetOnscroll, SetOnscroll);
+}
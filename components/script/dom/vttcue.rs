@@ -3,21 +3,34 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use crate::dom::bindings::codegen::Bindings::TextTrackCueBinding::TextTrackCueMethods;
 use crate::dom::bindings::codegen::Bindings::VTTCueBinding::{
     self, AlignSetting, AutoKeyword, DirectionSetting, LineAlignSetting, PositionAlignSetting,
     VTTCueMethods,
 };
 use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::document::Document;
 use crate::dom::documentfragment::DocumentFragment;
+use crate::dom::element::{CustomElementCreationMode, Element, ElementCreator};
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::node::Node;
+use crate::dom::processinginstruction::ProcessingInstruction;
+use crate::dom::text::Text;
 use crate::dom::texttrackcue::TextTrackCue;
 use crate::dom::vttregion::VTTRegion;
+use crate::dom::webvttparser::{
+    self, CueElementKind, CueLine, CueNode, LineAlignment, PositionAlignment, TextAlignment,
+    WebVttCue, WritingDirection,
+};
 use crate::dom::window::Window;
 use dom_struct::dom_struct;
+use html5ever::QualName;
 use std::cell::Cell;
 
 #[dom_struct]
@@ -70,6 +83,167 @@ impl VTTCue {
         )
     }
 
+    /// Create a cue from the result of parsing a WebVTT file.
+    pub fn new_from_webvtt_cue(global: &GlobalScope, cue: WebVttCue) -> DomRoot<Self> {
+        let vtt_cue = VTTCue::new(global, cue.start_time, cue.end_time, cue.text.into());
+        vtt_cue
+            .upcast::<TextTrackCue>()
+            .SetId(DOMString::from(cue.id));
+
+        let settings = cue.settings;
+        vtt_cue.vertical.set(match settings.direction {
+            WritingDirection::Horizontal => DirectionSetting::_empty,
+            WritingDirection::VerticalGrowingLeft => DirectionSetting::Rl,
+            WritingDirection::VerticalGrowingRight => DirectionSetting::Lr,
+        });
+        let (line, snap_to_lines) = match settings.line {
+            CueLine::Auto => (LineAndPositionSetting::Auto, true),
+            CueLine::Number(line) => (LineAndPositionSetting::Double(line), true),
+            CueLine::Percentage(line) => (LineAndPositionSetting::Double(line), false),
+        };
+        *vtt_cue.line.borrow_mut() = line;
+        vtt_cue.snap_to_lines.set(snap_to_lines);
+        vtt_cue.line_align.set(match settings.line_alignment {
+            LineAlignment::Start => LineAlignSetting::Start,
+            LineAlignment::Center => LineAlignSetting::Center,
+            LineAlignment::End => LineAlignSetting::End,
+        });
+        *vtt_cue.position.borrow_mut() = match settings.position {
+            Some(position) => LineAndPositionSetting::Double(position),
+            None => LineAndPositionSetting::Auto,
+        };
+        vtt_cue
+            .position_align
+            .set(match settings.position_alignment {
+                PositionAlignment::LineLeft => PositionAlignSetting::Line_left,
+                PositionAlignment::Center => PositionAlignSetting::Center,
+                PositionAlignment::LineRight => PositionAlignSetting::Line_right,
+                PositionAlignment::Auto => PositionAlignSetting::Auto,
+            });
+        vtt_cue.size.set(settings.size);
+        vtt_cue.align.set(match settings.text_alignment {
+            TextAlignment::Start => AlignSetting::Start,
+            TextAlignment::Center => AlignSetting::Center,
+            TextAlignment::End => AlignSetting::End,
+            TextAlignment::Left => AlignSetting::Left,
+            TextAlignment::Right => AlignSetting::Right,
+        });
+        vtt_cue
+    }
+
+    /// The inline style of the box of the cue when it is rendered over a video, and whether
+    /// the box is stacked at the bottom of the video with the other cues whose line is
+    /// automatic, rather than positioned on its own line. Only left-to-right text is laid
+    /// out, and boxes which are positioned on the same line may overlap.
+    /// <https://w3c.github.io/webvtt/#apply-webvtt-cue-settings>
+    pub fn rendering_style(&self) -> (DOMString, bool) {
+        // Steps 2-5, the computed position of the cue, and the size of its box.
+        let align = self.align.get();
+        let position = match *self.position.borrow() {
+            LineAndPositionSetting::Double(position) => position,
+            LineAndPositionSetting::Auto => match align {
+                AlignSetting::Left | AlignSetting::Start => 0.,
+                AlignSetting::Right | AlignSetting::End => 100.,
+                AlignSetting::Center => 50.,
+            },
+        };
+        let position_align = match self.position_align.get() {
+            PositionAlignSetting::Auto => match align {
+                AlignSetting::Left | AlignSetting::Start => PositionAlignSetting::Line_left,
+                AlignSetting::Right | AlignSetting::End => PositionAlignSetting::Line_right,
+                AlignSetting::Center => PositionAlignSetting::Center,
+            },
+            position_align => position_align,
+        };
+        let maximum_size = match position_align {
+            PositionAlignSetting::Line_left => 100. - position,
+            PositionAlignSetting::Line_right => position,
+            _ => position.min(100. - position) * 2.,
+        };
+        let size = self.size.get().min(maximum_size);
+
+        // Step 7, the offset of the box along its line.
+        let offset = match position_align {
+            PositionAlignSetting::Line_left => position,
+            PositionAlignSetting::Line_right => position - size,
+            _ => position - size / 2.,
+        };
+        let text_align = match align {
+            AlignSetting::Start => "start",
+            AlignSetting::Center => "center",
+            AlignSetting::End => "end",
+            AlignSetting::Left => "left",
+            AlignSetting::Right => "right",
+        };
+
+        let line = match *self.line.borrow() {
+            LineAndPositionSetting::Double(line) => Some(line),
+            LineAndPositionSetting::Auto => None,
+        };
+        let vertical = match self.vertical.get() {
+            DirectionSetting::_empty => None,
+            DirectionSetting::Rl => Some("rl"),
+            DirectionSetting::Lr => Some("lr"),
+        };
+        let translation = match self.line_align.get() {
+            LineAlignSetting::Start => 0,
+            LineAlignSetting::Center => -50,
+            LineAlignSetting::End => -100,
+        };
+
+        // Steps 8-10, the position of the line of the box, which is a number of lines
+        // from the edge of the video if the snap-to-lines flag is set, and a percentage
+        // of the video otherwise.
+        let style = match (vertical, line) {
+            (None, None) => {
+                return (
+                    DOMString::from(format!(
+                        "margin-left: {}%; width: {}%; text-align: {};",
+                        offset, size, text_align
+                    )),
+                    true,
+                );
+            },
+            (None, Some(line)) => {
+                let line = if !self.snap_to_lines.get() {
+                    format!("top: {}%; transform: translateY({}%);", line, translation)
+                } else if line >= 0. {
+                    format!("top: {}px;", line * CUE_LINE_HEIGHT)
+                } else {
+                    format!("bottom: {}px;", (-line - 1.) * CUE_LINE_HEIGHT)
+                };
+                format!(
+                    "position: absolute; left: {}%; width: {}%; text-align: {}; {}",
+                    offset, size, text_align, line
+                )
+            },
+            (Some(direction), line) => {
+                // The lines of a vertical cue are counted from the edge that the text
+                // grows away from.
+                let (start, end) = if direction == "rl" {
+                    ("right", "left")
+                } else {
+                    ("left", "right")
+                };
+                let line = match line {
+                    Some(line) if !self.snap_to_lines.get() => {
+                        format!("left: {}%; transform: translateX({}%);", line, translation)
+                    },
+                    Some(line) if line < 0. => {
+                        format!("{}: {}px;", end, (-line - 1.) * CUE_LINE_HEIGHT)
+                    },
+                    line => format!("{}: {}px;", start, line.unwrap_or(0.) * CUE_LINE_HEIGHT),
+                };
+                format!(
+                    "position: absolute; writing-mode: vertical-{}; top: {}%; height: {}%; \
+                     text-align: {}; {}",
+                    direction, offset, size, text_align, line
+                )
+            },
+        };
+        (DOMString::from(style), false)
+    }
+
     #[allow(non_snake_case)]
     pub fn Constructor(
         window: &Window,
@@ -199,10 +373,94 @@ impl VTTCueMethods for VTTCue {
 
     // https://w3c.github.io/webvtt/#dom-vttcue-getcueashtml
     fn GetCueAsHTML(&self) -> DomRoot<DocumentFragment> {
-        todo!()
+        let document = self.global().as_window().Document();
+        let fragment = DocumentFragment::new(&document);
+        let nodes = webvttparser::parse_cue_text(&self.text.borrow());
+        append_cue_nodes(fragment.upcast(), &nodes, &document);
+        fragment
     }
 }
 
+/// <https://w3c.github.io/webvtt/#webvtt-cue-text-dom-construction-rules>
+fn append_cue_nodes(parent: &Node, nodes: &[CueNode], document: &Document) {
+    for cue_node in nodes {
+        let node = match *cue_node {
+            CueNode::Text(ref text) => {
+                DomRoot::upcast::<Node>(Text::new(DOMString::from(text.clone()), document))
+            },
+            CueNode::Timestamp(timestamp) => DomRoot::upcast::<Node>(ProcessingInstruction::new(
+                DOMString::from("timestamp"),
+                DOMString::from(serialize_timestamp(timestamp)),
+                document,
+            )),
+            CueNode::Element {
+                kind,
+                ref classes,
+                ref annotation,
+                ref children,
+            } => {
+                let local_name = match kind {
+                    CueElementKind::Class | CueElementKind::Voice | CueElementKind::Language => {
+                        local_name!("span")
+                    },
+                    CueElementKind::Italic => local_name!("i"),
+                    CueElementKind::Bold => local_name!("b"),
+                    CueElementKind::Underline => local_name!("u"),
+                    CueElementKind::Ruby => local_name!("ruby"),
+                    CueElementKind::RubyText => local_name!("rt"),
+                };
+                let element = Element::create(
+                    QualName::new(None, ns!(html), local_name),
+                    None,
+                    document,
+                    ElementCreator::ScriptCreated,
+                    CustomElementCreationMode::Synchronous,
+                );
+                if !classes.is_empty() {
+                    element.set_string_attribute(
+                        &local_name!("class"),
+                        DOMString::from(classes.join(" ")),
+                    );
+                }
+                if let Some(ref annotation) = *annotation {
+                    match kind {
+                        CueElementKind::Voice => element.set_string_attribute(
+                            &local_name!("title"),
+                            DOMString::from(annotation.clone()),
+                        ),
+                        CueElementKind::Language => element.set_string_attribute(
+                            &local_name!("lang"),
+                            DOMString::from(annotation.clone()),
+                        ),
+                        _ => {},
+                    }
+                }
+                append_cue_nodes(element.upcast(), children, document);
+                DomRoot::upcast::<Node>(element)
+            },
+        };
+        if let Err(e) = parent.AppendChild(&node) {
+            warn!("Could not append cue node {:?}", e);
+        }
+    }
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-timestamp>
+fn serialize_timestamp(timestamp: f64) -> String {
+    let milliseconds = (timestamp * 1000.).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        milliseconds / 3_600_000,
+        milliseconds / 60_000 % 60,
+        milliseconds / 1000 % 60,
+        milliseconds % 1000
+    )
+}
+
+/// The height in pixels of the lines of the rendered cues, which is set by the
+/// `.cue` rule of the media controls style sheet.
+const CUE_LINE_HEIGHT: f64 = 24.;
+
 #[derive(Clone, JSTraceable, MallocSizeOf)]
 enum LineAndPositionSetting {
     Double(f64),
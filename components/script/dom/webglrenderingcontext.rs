@@ -631,7 +631,8 @@ impl WebGLRenderingContext {
                         ImageResponse::Loaded(img, _) => img,
                         ImageResponse::PlaceholderLoaded(_, _) |
                         ImageResponse::None |
-                        ImageResponse::MetadataLoaded(_) => return Ok(None),
+                        ImageResponse::MetadataLoaded(_) |
+                        ImageResponse::PartiallyLoaded(_, _) => return Ok(None),
                     };
 
                 let size = Size2D::new(img.width, img.height);
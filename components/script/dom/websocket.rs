@@ -54,7 +54,7 @@ enum WebSocketRequestState {
 // Close codes defined in https://tools.ietf.org/html/rfc6455#section-7.4.1
 // Names are from https://github.com/mozilla/gecko-dev/blob/master/netwerk/protocol/websocket/nsIWebSocketChannel.idl
 #[allow(dead_code)]
-mod close_code {
+pub mod close_code {
     pub const NORMAL: u16 = 1000;
     pub const GOING_AWAY: u16 = 1001;
     pub const PROTOCOL_ERROR: u16 = 1002;
@@ -103,6 +103,98 @@ pub fn fail_the_websocket_connection(
         .unwrap();
 }
 
+/// Parse the URL of a WebSocket, and check that the subprotocols are distinct tokens.
+/// Steps 1-4 and 6 of <https://html.spec.whatwg.org/multipage/#dom-websocket>.
+pub fn parse_websocket_url(url: &str, protocols: &[String]) -> Fallible<ServoUrl> {
+    // Steps 1-2.
+    let url_record = ServoUrl::parse(url).or(Err(Error::Syntax))?;
+
+    // Step 3.
+    match url_record.scheme() {
+        "ws" | "wss" => {},
+        _ => return Err(Error::Syntax),
+    }
+
+    // Step 4.
+    if url_record.fragment().is_some() {
+        return Err(Error::Syntax);
+    }
+
+    // Step 6.
+    for (i, protocol) in protocols.iter().enumerate() {
+        // https://tools.ietf.org/html/rfc6455#section-4.1
+        // Handshake requirements, step 10
+
+        if protocols[i + 1..]
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(protocol))
+        {
+            return Err(Error::Syntax);
+        }
+
+        // https://tools.ietf.org/html/rfc6455#section-4.1
+        if !is_token(protocol.as_bytes()) {
+            return Err(Error::Syntax);
+        }
+    }
+
+    Ok(url_record)
+}
+
+/// Ask the resource thread to open a WebSocket connection, returning the channels with
+/// which the connection is sent the actions of the DOM, and the DOM is sent its events.
+/// <https://fetch.spec.whatwg.org/#concept-websocket-establish>
+pub fn establish_a_websocket_connection(
+    global: &GlobalScope,
+    url: ServoUrl,
+    protocols: Vec<String>,
+) -> (
+    IpcSender<WebSocketDomAction>,
+    ProfiledIpc::IpcReceiver<WebSocketNetworkEvent>,
+) {
+    // Create the interface for communication with the resource thread
+    let (dom_action_sender, resource_action_receiver): (
+        IpcSender<WebSocketDomAction>,
+        IpcReceiver<WebSocketDomAction>,
+    ) = ipc::channel().unwrap();
+    let (resource_event_sender, dom_event_receiver): (
+        IpcSender<WebSocketNetworkEvent>,
+        ProfiledIpc::IpcReceiver<WebSocketNetworkEvent>,
+    ) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+
+    let request = RequestBuilder::new(url)
+        .origin(global.origin().immutable().clone())
+        .mode(RequestMode::WebSocket { protocols })
+        .referrer(Some(Referrer::NoReferrer));
+
+    let channels = FetchChannels::WebSocket {
+        event_sender: resource_event_sender,
+        action_receiver: resource_action_receiver,
+    };
+    let _ = global
+        .core_resource_thread()
+        .send(CoreResourceMsg::Fetch(request, channels));
+
+    (dom_action_sender, dom_event_receiver)
+}
+
+/// Check the code and the reason with which script closes a WebSocket.
+pub fn check_close_code_and_reason(code: Option<u16>, reason: Option<&str>) -> ErrorResult {
+    if let Some(code) = code {
+        //Fail if the supplied code isn't normal and isn't reserved for libraries, frameworks, and applications
+        if code != close_code::NORMAL && (code < 3000 || code > 4999) {
+            return Err(Error::InvalidAccess);
+        }
+    }
+    if let Some(reason) = reason {
+        if reason.as_bytes().len() > 123 {
+            //reason cannot be larger than 123 bytes
+            return Err(Error::Syntax);
+        }
+    }
+    Ok(())
+}
+
 #[dom_struct]
 pub struct WebSocket {
     eventtarget: EventTarget,
@@ -149,20 +241,6 @@ impl WebSocket {
         url: DOMString,
         protocols: Option<StringOrStringSequence>,
     ) -> Fallible<DomRoot<WebSocket>> {
-        // Steps 1-2.
-        let url_record = ServoUrl::parse(&url).or(Err(Error::Syntax))?;
-
-        // Step 3.
-        match url_record.scheme() {
-            "ws" | "wss" => {},
-            _ => return Err(Error::Syntax),
-        }
-
-        // Step 4.
-        if url_record.fragment().is_some() {
-            return Err(Error::Syntax);
-        }
-
         // Step 5.
         let protocols = protocols.map_or(vec![], |p| match p {
             StringOrStringSequence::String(string) => vec![string.into()],
@@ -171,51 +249,16 @@ impl WebSocket {
             },
         });
 
-        // Step 6.
-        for (i, protocol) in protocols.iter().enumerate() {
-            // https://tools.ietf.org/html/rfc6455#section-4.1
-            // Handshake requirements, step 10
+        // Steps 1-4, 6.
+        let url_record = parse_websocket_url(&url, &protocols)?;
 
-            if protocols[i + 1..]
-                .iter()
-                .any(|p| p.eq_ignore_ascii_case(protocol))
-            {
-                return Err(Error::Syntax);
-            }
-
-            // https://tools.ietf.org/html/rfc6455#section-4.1
-            if !is_token(protocol.as_bytes()) {
-                return Err(Error::Syntax);
-            }
-        }
+        // Step 8.
+        let (dom_action_sender, dom_event_receiver) =
+            establish_a_websocket_connection(global, url_record.clone(), protocols);
 
-        // Create the interface for communication with the resource thread
-        let (dom_action_sender, resource_action_receiver): (
-            IpcSender<WebSocketDomAction>,
-            IpcReceiver<WebSocketDomAction>,
-        ) = ipc::channel().unwrap();
-        let (resource_event_sender, dom_event_receiver): (
-            IpcSender<WebSocketNetworkEvent>,
-            ProfiledIpc::IpcReceiver<WebSocketNetworkEvent>,
-        ) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
-
-        let ws = WebSocket::new(global, url_record.clone(), dom_action_sender);
+        let ws = WebSocket::new(global, url_record, dom_action_sender);
         let address = Trusted::new(&*ws);
 
-        // Step 8.
-        let request = RequestBuilder::new(url_record)
-            .origin(global.origin().immutable().clone())
-            .mode(RequestMode::WebSocket { protocols })
-            .referrer(Some(Referrer::NoReferrer));
-
-        let channels = FetchChannels::WebSocket {
-            event_sender: resource_event_sender,
-            action_receiver: resource_action_receiver,
-        };
-        let _ = global
-            .core_resource_thread()
-            .send(CoreResourceMsg::Fetch(request, channels));
-
         let task_source = global.websocket_task_source();
         let canceller = global.task_canceller(WebsocketTaskSource::NAME);
         ROUTER.add_route(
@@ -411,18 +454,7 @@ impl WebSocketMethods for WebSocket {
 
     // https://html.spec.whatwg.org/multipage/#dom-websocket-close
     fn Close(&self, code: Option<u16>, reason: Option<USVString>) -> ErrorResult {
-        if let Some(code) = code {
-            //Fail if the supplied code isn't normal and isn't reserved for libraries, frameworks, and applications
-            if code != close_code::NORMAL && (code < 3000 || code > 4999) {
-                return Err(Error::InvalidAccess);
-            }
-        }
-        if let Some(ref reason) = reason {
-            if reason.0.as_bytes().len() > 123 {
-                //reason cannot be larger than 123 bytes
-                return Err(Error::Syntax);
-            }
-        }
+        check_close_code_and_reason(code, reason.as_ref().map(|reason| &*reason.0))?;
 
         match self.ready_state.get() {
             WebSocketRequestState::Closing | WebSocketRequestState::Closed => {}, //Do nothing
@@ -0,0 +1,439 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A WebSocket whose messages are read from a readable stream and written to a writable
+//! stream, rather than dispatched as events and sent without regard to `bufferedAmount`.
+//! The readable stream is only enqueued a message once it is pulled, and the writes of the
+//! writable stream are only completed once their message was handed to the connection,
+//! so that the queues of both streams exert backpressure.
+//! <https://github.com/ricea/websocketstream-explainer>
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::WebSocketStreamBinding::{
+    self, WebSocketCloseInfo, WebSocketConnection, WebSocketStreamMethods, WebSocketStreamOptions,
+};
+use crate::dom::bindings::conversions::ToJSValConvertible;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::str::{DOMString, USVString};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::{PendingPromise, Promise};
+use crate::dom::readablestream::{ReadableStream, UnderlyingSource};
+use crate::dom::streams::{buffer_source_bytes, convert, error_to_jsval, mark_promise_as_handled};
+use crate::dom::websocket::{check_close_code_and_reason, close_code};
+use crate::dom::websocket::{establish_a_websocket_connection, parse_websocket_url};
+use crate::dom::writablestream::{UnderlyingSink, WritableStream};
+use crate::realms::enter_realm;
+use crate::task_source::websocket::WebsocketTaskSource;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use ipc_channel::ipc::IpcSender;
+use ipc_channel::router::ROUTER;
+use js::jsapi::JSObject;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use js::typedarray::{ArrayBuffer, CreateWith};
+use net_traits::{MessageData, WebSocketDomAction, WebSocketNetworkEvent};
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ptr;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+enum WebSocketStreamState {
+    Connecting,
+    Open,
+    Closing,
+    Closed,
+}
+
+#[dom_struct]
+pub struct WebSocketStream {
+    reflector_: Reflector,
+    url: ServoUrl,
+    state: Cell<WebSocketStreamState>,
+    #[ignore_malloc_size_of = "Defined in std"]
+    sender: IpcSender<WebSocketDomAction>,
+    /// Resolved with the streams of the connection once it is established.
+    #[ignore_malloc_size_of = "Rc"]
+    connection: Rc<Promise>,
+    /// Resolved with the code and the reason of the closing handshake once the connection
+    /// is closed, or rejected if it fails.
+    #[ignore_malloc_size_of = "Rc"]
+    closed: Rc<Promise>,
+    readable: MutNullableDom<ReadableStream>,
+    writable: MutNullableDom<WritableStream>,
+    /// The messages received that the readable stream was not pulled for yet.
+    #[ignore_malloc_size_of = "Defined in net_traits"]
+    pending_messages: DomRefCell<VecDeque<MessageData>>,
+    /// The promise of the pull of the readable stream, if it waits for the next message.
+    #[ignore_malloc_size_of = "Rc"]
+    pull_promise: DomRefCell<Option<Rc<Promise>>>,
+    /// The promise of the closing of the writable stream, which waits for the closing
+    /// handshake.
+    #[ignore_malloc_size_of = "Rc"]
+    close_promise: DomRefCell<Option<Rc<Promise>>>,
+}
+
+impl WebSocketStream {
+    fn new_inherited(
+        global: &GlobalScope,
+        url: ServoUrl,
+        sender: IpcSender<WebSocketDomAction>,
+    ) -> WebSocketStream {
+        WebSocketStream {
+            reflector_: Reflector::new(),
+            url,
+            state: Cell::new(WebSocketStreamState::Connecting),
+            sender,
+            connection: Promise::new(global),
+            closed: Promise::new(global),
+            readable: Default::default(),
+            writable: Default::default(),
+            pending_messages: DomRefCell::new(VecDeque::new()),
+            pull_promise: DomRefCell::new(None),
+            close_promise: DomRefCell::new(None),
+        }
+    }
+
+    fn new(
+        global: &GlobalScope,
+        url: ServoUrl,
+        sender: IpcSender<WebSocketDomAction>,
+    ) -> DomRoot<WebSocketStream> {
+        reflect_dom_object(
+            Box::new(WebSocketStream::new_inherited(global, url, sender)),
+            global,
+            WebSocketStreamBinding::Wrap,
+        )
+    }
+
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        url: USVString,
+        options: &WebSocketStreamOptions,
+    ) -> Fallible<DomRoot<WebSocketStream>> {
+        let protocols: Vec<String> = options
+            .protocols
+            .iter()
+            .flatten()
+            .map(|protocol| protocol.0.clone())
+            .collect();
+        let url = parse_websocket_url(&url.0, &protocols)?;
+        let (sender, receiver) = establish_a_websocket_connection(global, url.clone(), protocols);
+
+        let stream = WebSocketStream::new(global, url, sender);
+        mark_promise_as_handled(&stream.connection);
+        mark_promise_as_handled(&stream.closed);
+
+        let address = Trusted::new(&*stream);
+        let task_source = global.websocket_task_source();
+        let canceller = global.task_canceller(WebsocketTaskSource::NAME);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let event: WebSocketNetworkEvent = message.to().unwrap();
+                let stream = address.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(websocket_stream_event: move || {
+                        stream.root().handle_network_event(event);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+
+        Ok(stream)
+    }
+
+    fn handle_network_event(&self, event: WebSocketNetworkEvent) {
+        match event {
+            WebSocketNetworkEvent::ConnectionEstablished { protocol_in_use } => {
+                self.connection_established(protocol_in_use.unwrap_or_default())
+            },
+            WebSocketNetworkEvent::MessageReceived(message) => self.message_received(message),
+            WebSocketNetworkEvent::Fail => self.connection_closed(None),
+            WebSocketNetworkEvent::Close(code, reason) => {
+                self.connection_closed(Some((code.unwrap_or(close_code::NO_STATUS), reason)))
+            },
+        }
+    }
+
+    /// Create the streams of the connection once it is established.
+    #[allow(unrooted_must_root)]
+    fn connection_established(&self, protocol: String) {
+        if self.state.get() != WebSocketStreamState::Connecting {
+            let _ = self.sender.send(WebSocketDomAction::Close(None, None));
+            return;
+        }
+        self.state.set(WebSocketStreamState::Open);
+
+        let global = self.global();
+        let readable = ReadableStream::new_with_source(
+            &global,
+            UnderlyingSource::for_web_socket_stream(self),
+            1.,
+            None,
+        )
+        .expect("Starting the readable stream of a WebSocket cannot fail");
+        let writable = WritableStream::new_with_sink(
+            &global,
+            UnderlyingSink::for_web_socket_stream(self),
+            1.,
+            None,
+        )
+        .expect("Starting the writable stream of a WebSocket cannot fail");
+        self.readable.set(Some(&readable));
+        self.writable.set(Some(&writable));
+
+        let connection = WebSocketConnection {
+            readable: Some(readable),
+            writable: Some(writable),
+            extensions: Some(DOMString::new()),
+            protocol: Some(DOMString::from(protocol)),
+        };
+        self.connection.resolve_native(&connection);
+    }
+
+    fn message_received(&self, message: MessageData) {
+        if self.state.get() != WebSocketStreamState::Open {
+            return;
+        }
+        let pull_promise = self.pull_promise.borrow_mut().take();
+        match pull_promise {
+            Some(promise) => {
+                self.enqueue(message);
+                promise.resolve_native(&());
+            },
+            None => self.pending_messages.borrow_mut().push_back(message),
+        }
+    }
+
+    /// Enqueue a message in the readable stream, as a string or an `ArrayBuffer`.
+    #[allow(unsafe_code)]
+    fn enqueue(&self, message: MessageData) {
+        let controller = match self.readable.get().and_then(|r| r.default_controller()) {
+            Some(controller) => controller,
+            None => return,
+        };
+        let cx = self.global().get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        match message {
+            MessageData::Text(text) => unsafe { text.to_jsval(*cx, chunk.handle_mut()) },
+            MessageData::Binary(data) => unsafe {
+                rooted!(in(*cx) let mut array_buffer = ptr::null_mut::<JSObject>());
+                assert!(ArrayBuffer::create(
+                    *cx,
+                    CreateWith::Slice(&data),
+                    array_buffer.handle_mut()
+                )
+                .is_ok());
+                (*array_buffer).to_jsval(*cx, chunk.handle_mut());
+            },
+        }
+        let _ = controller.enqueue(chunk.handle());
+    }
+
+    /// Settle the promises and the streams once the connection is closed, cleanly with the
+    /// code and the reason of the closing handshake, or not.
+    /// <https://html.spec.whatwg.org/multipage/#closeWebSocket>
+    fn connection_closed(&self, close_info: Option<(u16, String)>) {
+        let previous_state = self.state.replace(WebSocketStreamState::Closed);
+        if previous_state == WebSocketStreamState::Closed {
+            return;
+        }
+
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut error = UndefinedValue());
+        error_to_jsval(&global, Error::Network, error.handle_mut());
+
+        if previous_state == WebSocketStreamState::Connecting {
+            self.connection.reject_error(Error::Network);
+        }
+
+        // The messages received before the closing handshake can still be read.
+        let pending_messages: Vec<_> = self.pending_messages.borrow_mut().drain(..).collect();
+        for message in pending_messages {
+            self.enqueue(message);
+        }
+        if let Some(promise) = self.pull_promise.borrow_mut().take() {
+            promise.resolve_native(&());
+        }
+        if let Some(controller) = self.readable.get().and_then(|r| r.default_controller()) {
+            if controller.can_close_or_enqueue() {
+                match close_info {
+                    Some(_) => controller.close(),
+                    None => controller.error(error.handle()),
+                }
+            }
+        }
+
+        // The writable stream can no longer be written to, unless it is being closed, in
+        // which case its closing completes.
+        let close_promise = self.close_promise.borrow_mut().take();
+        if let Some(writable) = self.writable.get() {
+            if close_promise.is_none() {
+                writable.controller().error_if_needed(error.handle());
+            }
+        }
+
+        match close_info {
+            Some((code, reason)) => {
+                if let Some(promise) = close_promise {
+                    promise.resolve_native(&());
+                }
+                let close_info = WebSocketCloseInfo {
+                    closeCode: Some(code),
+                    reason: USVString(reason),
+                };
+                self.closed.resolve_native(&close_info);
+            },
+            None => {
+                if let Some(promise) = close_promise {
+                    promise.reject(cx, error.handle());
+                }
+                self.closed.reject(cx, error.handle());
+            },
+        }
+    }
+
+    /// Start the closing handshake, or fail the connection if it is not established yet.
+    fn start_closing(&self, code: Option<u16>, reason: Option<String>) {
+        match self.state.get() {
+            WebSocketStreamState::Connecting => {
+                self.state.set(WebSocketStreamState::Closing);
+                let stream = Trusted::new(self);
+                let _ = self.global().websocket_task_source().queue_with_canceller(
+                    task!(fail_websocket_stream: move || {
+                        stream.root().connection_closed(None);
+                    }),
+                    &self.global().task_canceller(WebsocketTaskSource::NAME),
+                );
+            },
+            WebSocketStreamState::Open => {
+                self.state.set(WebSocketStreamState::Closing);
+                let _ = self.sender.send(WebSocketDomAction::Close(code, reason));
+            },
+            WebSocketStreamState::Closing | WebSocketStreamState::Closed => {},
+        }
+    }
+
+    /// Enqueue the next message received, or wait for it.
+    pub fn source_pull(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        let message = self.pending_messages.borrow_mut().pop_front();
+        match message {
+            Some(message) => {
+                self.enqueue(message);
+                promise.resolve_native(&());
+            },
+            None if self.state.get() == WebSocketStreamState::Open => {
+                *self.pull_promise.borrow_mut() = Some(promise.clone());
+            },
+            None => promise.resolve_native(&()),
+        }
+        promise
+    }
+
+    /// Close the connection once the readable stream is canceled.
+    pub fn source_cancel(&self) -> Rc<Promise> {
+        self.start_closing(None, None);
+        let promise = Promise::new(&self.global());
+        promise.resolve_native(&());
+        promise
+    }
+
+    /// Send a chunk written to the writable stream as a text message if it is a string, or
+    /// as a binary message if it is a buffer source. The write completes once the message
+    /// was handed to the connection.
+    pub fn sink_write(&self, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new(&global);
+        if self.state.get() != WebSocketStreamState::Open {
+            promise.reject_error(Error::InvalidState);
+            return promise;
+        }
+        let message = if chunk.is_string() {
+            match convert::<USVString>(global.get_cx(), chunk, ()) {
+                Ok(text) => MessageData::Text(text.0),
+                Err(error) => {
+                    promise.reject_error(error);
+                    return promise;
+                },
+            }
+        } else {
+            match buffer_source_bytes(global.get_cx(), chunk) {
+                Some(bytes) => MessageData::Binary(bytes),
+                None => {
+                    promise.reject_error(Error::Type(
+                        "The chunk is neither a string nor a buffer source".to_owned(),
+                    ));
+                    return promise;
+                },
+            }
+        };
+        let _ = self.sender.send(WebSocketDomAction::SendMessage(message));
+        PendingPromise::new(
+            &promise,
+            global.websocket_task_source(),
+            global.task_canceller(WebsocketTaskSource::NAME),
+        )
+        .resolve(());
+        promise
+    }
+
+    /// Start the closing handshake once the writable stream is closed, whose closing
+    /// completes with the handshake.
+    pub fn sink_close(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        if self.state.get() == WebSocketStreamState::Closed {
+            promise.resolve_native(&());
+            return promise;
+        }
+        *self.close_promise.borrow_mut() = Some(promise.clone());
+        self.start_closing(None, None);
+        promise
+    }
+
+    /// Close the connection once the writable stream is aborted.
+    pub fn sink_abort(&self) -> Rc<Promise> {
+        self.start_closing(None, None);
+        let promise = Promise::new(&self.global());
+        promise.resolve_native(&());
+        promise
+    }
+}
+
+impl WebSocketStreamMethods for WebSocketStream {
+    fn Url(&self) -> USVString {
+        USVString(self.url.as_str().to_owned())
+    }
+
+    fn Connection(&self) -> Rc<Promise> {
+        self.connection.clone()
+    }
+
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed.clone()
+    }
+
+    fn Close(&self, close_info: &WebSocketCloseInfo) -> ErrorResult {
+        let reason = &close_info.reason.0;
+        check_close_code_and_reason(close_info.closeCode, Some(reason))?;
+        // A closing handshake with a reason must have a code.
+        let (code, reason) = match (close_info.closeCode, reason.is_empty()) {
+            (code, true) => (code, None),
+            (code, false) => (code.or(Some(close_code::NORMAL)), Some(reason.clone())),
+        };
+        self.start_closing(code, reason);
+        Ok(())
+    }
+}
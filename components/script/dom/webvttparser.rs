@@ -0,0 +1,478 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A parser for WebVTT files and cue text.
+//! <https://w3c.github.io/webvtt/#parsing>
+
+use std::mem;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WritingDirection {
+    Horizontal,
+    VerticalGrowingLeft,
+    VerticalGrowingRight,
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-line>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CueLine {
+    Auto,
+    /// A line number, with the snap-to-lines flag set.
+    Number(f64),
+    /// A percentage of the video, with the snap-to-lines flag unset.
+    Percentage(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineAlignment {
+    Start,
+    Center,
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionAlignment {
+    LineLeft,
+    Center,
+    LineRight,
+    Auto,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlignment {
+    Start,
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-cue-settings-list>
+#[derive(Clone, Debug, PartialEq)]
+pub struct CueSettings {
+    pub direction: WritingDirection,
+    pub line: CueLine,
+    pub line_alignment: LineAlignment,
+    /// `None` means that the position is automatic.
+    pub position: Option<f64>,
+    pub position_alignment: PositionAlignment,
+    pub size: f64,
+    pub text_alignment: TextAlignment,
+    pub region: Option<String>,
+}
+
+impl Default for CueSettings {
+    fn default() -> CueSettings {
+        CueSettings {
+            direction: WritingDirection::Horizontal,
+            line: CueLine::Auto,
+            line_alignment: LineAlignment::Start,
+            position: None,
+            position_alignment: PositionAlignment::Auto,
+            size: 100.,
+            text_alignment: TextAlignment::Center,
+            region: None,
+        }
+    }
+}
+
+/// A cue of a WebVTT file, with its text still unparsed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebVttCue {
+    pub id: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub settings: CueSettings,
+    pub text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CueElementKind {
+    Class,
+    Italic,
+    Bold,
+    Underline,
+    Ruby,
+    RubyText,
+    Voice,
+    Language,
+}
+
+impl CueElementKind {
+    fn from_tag_name(name: &str) -> Option<CueElementKind> {
+        match name {
+            "c" => Some(CueElementKind::Class),
+            "i" => Some(CueElementKind::Italic),
+            "b" => Some(CueElementKind::Bold),
+            "u" => Some(CueElementKind::Underline),
+            "ruby" => Some(CueElementKind::Ruby),
+            "rt" => Some(CueElementKind::RubyText),
+            "v" => Some(CueElementKind::Voice),
+            "lang" => Some(CueElementKind::Language),
+            _ => None,
+        }
+    }
+}
+
+/// A node of the tree built from cue text.
+/// <https://w3c.github.io/webvtt/#webvtt-node-object>
+#[derive(Clone, Debug, PartialEq)]
+pub enum CueNode {
+    Text(String),
+    Timestamp(f64),
+    Element {
+        kind: CueElementKind,
+        classes: Vec<String>,
+        /// The voice name of a `v` element, or the language of a `lang` element.
+        annotation: Option<String>,
+        children: Vec<CueNode>,
+    },
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-parser-algorithm>
+///
+/// Returns an error if the input is not a WebVTT file.
+pub fn parse(input: &str) -> Result<Vec<WebVttCue>, ()> {
+    // Step 1-3. Preprocess the input stream.
+    let input = input
+        .replace('\0', "\u{FFFD}")
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+    let input = input.trim_start_matches('\u{FEFF}');
+    let mut lines = input.split('\n').peekable();
+
+    // Step 4-9. The signature.
+    let signature = lines.next().ok_or(())?;
+    if !signature.starts_with("WEBVTT") {
+        return Err(());
+    }
+    match signature[6..].chars().next() {
+        None | Some(' ') | Some('\t') => {},
+        Some(_) => return Err(()),
+    }
+
+    // Step 10-11. Skip the rest of the header.
+    while let Some(line) = lines.next() {
+        if line.is_empty() {
+            break;
+        }
+    }
+
+    // Step 12-16. Collect the blocks.
+    let mut cues = vec![];
+    loop {
+        while lines.peek().map_or(false, |line| line.is_empty()) {
+            lines.next();
+        }
+        let mut block = vec![];
+        while let Some(line) = lines.peek() {
+            if line.is_empty() {
+                break;
+            }
+            // A line containing "-->" after the first two lines of a block
+            // starts a new block.
+            if block.len() > 1 && line.contains("-->") {
+                break;
+            }
+            block.push(lines.next().unwrap());
+        }
+        if block.is_empty() {
+            break;
+        }
+        if let Some(cue) = parse_cue_block(&block) {
+            cues.push(cue);
+        }
+    }
+    Ok(cues)
+}
+
+/// <https://w3c.github.io/webvtt/#collect-a-webvtt-block>
+fn parse_cue_block(block: &[&str]) -> Option<WebVttCue> {
+    let (id, timings, text) = if block[0].contains("-->") {
+        ("", block[0], &block[1..])
+    } else if block.len() > 1 && block[1].contains("-->") {
+        (block[0], block[1], &block[2..])
+    } else {
+        // A comment, a region or a style block.
+        return None;
+    };
+    let (start_time, end_time, settings) = parse_timings_and_settings(timings)?;
+    Some(WebVttCue {
+        id: id.to_owned(),
+        start_time,
+        end_time,
+        settings,
+        text: text.join("\n"),
+    })
+}
+
+/// <https://w3c.github.io/webvtt/#collect-webvtt-cue-timings-and-settings>
+fn parse_timings_and_settings(line: &str) -> Option<(f64, f64, CueSettings)> {
+    let mut parts = line.splitn(2, "-->");
+    let start_time = parse_timestamp(parts.next()?.trim_matches(is_whitespace))?;
+    let remainder = parts.next()?.trim_start_matches(is_whitespace);
+    let end = remainder
+        .find(is_whitespace)
+        .unwrap_or_else(|| remainder.len());
+    let end_time = parse_timestamp(&remainder[..end])?;
+    let settings = parse_settings(&remainder[end..]);
+    Some((start_time, end_time, settings))
+}
+
+/// <https://w3c.github.io/webvtt/#collect-a-webvtt-timestamp>
+pub fn parse_timestamp(input: &str) -> Option<f64> {
+    let components: Vec<&str> = input.split(':').collect();
+    let (hours, minutes, seconds) = match components[..] {
+        [minutes, seconds] => ("0", minutes, seconds),
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        _ => return None,
+    };
+    let mut seconds = seconds.splitn(2, '.');
+    let (seconds, fraction) = (seconds.next()?, seconds.next()?);
+
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(hours) ||
+        minutes.len() != 2 ||
+        !all_digits(minutes) ||
+        seconds.len() != 2 ||
+        !all_digits(seconds) ||
+        fraction.len() != 3 ||
+        !all_digits(fraction)
+    {
+        return None;
+    }
+
+    let hours: f64 = hours.parse().ok()?;
+    let minutes: f64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    let fraction: f64 = fraction.parse().ok()?;
+    if minutes > 59. || seconds > 59. {
+        return None;
+    }
+    Some(hours * 3600. + minutes * 60. + seconds + fraction / 1000.)
+}
+
+/// <https://w3c.github.io/webvtt/#parse-the-webvtt-cue-settings>
+fn parse_settings(input: &str) -> CueSettings {
+    let mut settings = CueSettings::default();
+    for setting in input.split(is_whitespace).filter(|s| !s.is_empty()) {
+        let mut parts = setting.splitn(2, ':');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if !name.is_empty() && !value.is_empty() => (name, value),
+            _ => continue,
+        };
+        match name {
+            "region" => settings.region = Some(value.to_owned()),
+            "vertical" => match value {
+                "rl" => settings.direction = WritingDirection::VerticalGrowingLeft,
+                "lr" => settings.direction = WritingDirection::VerticalGrowingRight,
+                _ => {},
+            },
+            "line" => {
+                let mut parts = value.splitn(2, ',');
+                let line = parts.next().unwrap();
+                let alignment = match parts.next() {
+                    None => Some(LineAlignment::Start),
+                    Some("start") => Some(LineAlignment::Start),
+                    Some("center") => Some(LineAlignment::Center),
+                    Some("end") => Some(LineAlignment::End),
+                    Some(_) => None,
+                };
+                let line = if line.ends_with('%') {
+                    parse_percentage(line).map(CueLine::Percentage)
+                } else {
+                    parse_line_number(line).map(CueLine::Number)
+                };
+                if let (Some(line), Some(alignment)) = (line, alignment) {
+                    settings.line = line;
+                    settings.line_alignment = alignment;
+                }
+            },
+            "position" => {
+                let mut parts = value.splitn(2, ',');
+                let position = parse_percentage(parts.next().unwrap());
+                let alignment = match parts.next() {
+                    None => Some(PositionAlignment::Auto),
+                    Some("line-left") => Some(PositionAlignment::LineLeft),
+                    Some("center") => Some(PositionAlignment::Center),
+                    Some("line-right") => Some(PositionAlignment::LineRight),
+                    Some(_) => None,
+                };
+                if let (Some(position), Some(alignment)) = (position, alignment) {
+                    settings.position = Some(position);
+                    settings.position_alignment = alignment;
+                }
+            },
+            "size" => {
+                if let Some(size) = parse_percentage(value) {
+                    settings.size = size;
+                }
+            },
+            "align" => match value {
+                "start" => settings.text_alignment = TextAlignment::Start,
+                "center" => settings.text_alignment = TextAlignment::Center,
+                "end" => settings.text_alignment = TextAlignment::End,
+                "left" => settings.text_alignment = TextAlignment::Left,
+                "right" => settings.text_alignment = TextAlignment::Right,
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+    settings
+}
+
+/// <https://w3c.github.io/webvtt/#webvtt-percentage>
+fn parse_percentage(input: &str) -> Option<f64> {
+    if !input.ends_with('%') {
+        return None;
+    }
+    let number = &input[..input.len() - 1];
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return None;
+    }
+    let value: f64 = number.parse().ok()?;
+    if value < 0. || value > 100. {
+        return None;
+    }
+    Some(value)
+}
+
+fn parse_line_number(input: &str) -> Option<f64> {
+    let digits = input.trim_start_matches('-');
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return None;
+    }
+    input.parse().ok()
+}
+
+fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n' || c == '\x0C'
+}
+
+/// <https://w3c.github.io/webvtt/#cue-text-parsing-rules>
+pub fn parse_cue_text(input: &str) -> Vec<CueNode> {
+    // The stack of open elements. The root is the bottom entry, without a kind.
+    let mut stack: Vec<(
+        Option<CueElementKind>,
+        Vec<String>,
+        Option<String>,
+        Vec<CueNode>,
+    )> = vec![(None, vec![], None, vec![])];
+    let mut text = String::new();
+    let mut rest = input;
+
+    fn close(
+        stack: &mut Vec<(
+            Option<CueElementKind>,
+            Vec<String>,
+            Option<String>,
+            Vec<CueNode>,
+        )>,
+    ) {
+        let (kind, classes, annotation, children) = stack.pop().unwrap();
+        stack.last_mut().unwrap().3.push(CueNode::Element {
+            kind: kind.unwrap(),
+            classes,
+            annotation,
+            children,
+        });
+    }
+
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = rest.find('>').unwrap_or_else(|| rest.len());
+            let tag = &rest[1..end];
+            rest = &rest[(end + 1).min(rest.len())..];
+
+            if !text.is_empty() {
+                let text = mem::replace(&mut text, String::new());
+                stack.last_mut().unwrap().3.push(CueNode::Text(text));
+            }
+
+            if tag.starts_with('/') {
+                let name = &tag[1..];
+                // An end tag closes the matching open element, if it is the current
+                // one, or the parent of the current `rt` element for `</ruby>`.
+                let kind = match CueElementKind::from_tag_name(name.trim()) {
+                    Some(kind) => kind,
+                    None => continue,
+                };
+                let current = stack.last().unwrap().0;
+                if current == Some(kind) {
+                    close(&mut stack);
+                } else if kind == CueElementKind::Ruby && current == Some(CueElementKind::RubyText)
+                {
+                    close(&mut stack);
+                    close(&mut stack);
+                }
+                continue;
+            }
+
+            if tag.starts_with(|c: char| c.is_ascii_digit()) {
+                if let Some(time) = parse_timestamp(tag) {
+                    stack.last_mut().unwrap().3.push(CueNode::Timestamp(time));
+                }
+                continue;
+            }
+
+            let mut parts = tag.splitn(2, is_whitespace);
+            let mut name_and_classes = parts.next().unwrap().split('.');
+            let annotation = parts
+                .next()
+                .map(|annotation| annotation.trim_matches(is_whitespace))
+                .filter(|annotation| !annotation.is_empty())
+                .map(|annotation| annotation.to_owned());
+            let kind = match CueElementKind::from_tag_name(name_and_classes.next().unwrap()) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            // `rt` elements are only allowed in `ruby` elements.
+            if kind == CueElementKind::RubyText &&
+                stack.last().unwrap().0 != Some(CueElementKind::Ruby)
+            {
+                continue;
+            }
+            let classes = name_and_classes
+                .filter(|class| !class.is_empty())
+                .map(|class| class.to_owned())
+                .collect();
+            let annotation = match kind {
+                CueElementKind::Voice | CueElementKind::Language => annotation,
+                _ => None,
+            };
+            stack.push((Some(kind), classes, annotation, vec![]));
+        } else if rest.starts_with('&') {
+            let (replacement, length) = [
+                ("&amp;", "&"),
+                ("&lt;", "<"),
+                ("&gt;", ">"),
+                ("&lrm;", "\u{200E}"),
+                ("&rlm;", "\u{200F}"),
+                ("&nbsp;", "\u{A0}"),
+            ]
+            .iter()
+            .find(|(reference, _)| rest.starts_with(reference))
+            .map_or(("&", 1), |(reference, replacement)| {
+                (*replacement, reference.len())
+            });
+            text.push_str(replacement);
+            rest = &rest[length..];
+        } else {
+            let end = rest
+                .find(|c: char| c == '<' || c == '&')
+                .unwrap_or_else(|| rest.len());
+            text.push_str(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+
+    if !text.is_empty() {
+        stack.last_mut().unwrap().3.push(CueNode::Text(text));
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    stack.pop().unwrap().3
+}
@@ -27,6 +27,9 @@ use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::bindings::utils::{GlobalStaticData, WindowProxyHandler};
 use crate::dom::bindings::weakref::DOMTracker;
 use crate::dom::bluetooth::BluetoothExtraPermissionData;
+use crate::dom::cachestorage::CacheStorage;
+use crate::dom::console::Console;
+use crate::dom::cookiestore::CookieStore;
 use crate::dom::crypto::Crypto;
 use crate::dom::cssstyledeclaration::{CSSModificationAccess, CSSStyleDeclaration, CSSStyleOwner};
 use crate::dom::customelementregistry::CustomElementRegistry;
@@ -37,6 +40,7 @@ use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::hashchangeevent::HashChangeEvent;
 use crate::dom::history::History;
+use crate::dom::htmlimageelement::HTMLImageElement;
 use crate::dom::location::Location;
 use crate::dom::mediaquerylist::{MediaQueryList, MediaQueryListMatchState};
 use crate::dom::mediaquerylistevent::MediaQueryListEvent;
@@ -49,6 +53,7 @@ use crate::dom::screen::Screen;
 use crate::dom::selection::Selection;
 use crate::dom::storage::Storage;
 use crate::dom::testrunner::TestRunner;
+use crate::dom::visualviewport::VisualViewport;
 use crate::dom::webglrenderingcontext::WebGLCommandSender;
 use crate::dom::windowproxy::WindowProxy;
 use crate::dom::worklet::Worklet;
@@ -64,7 +69,7 @@ use crate::script_thread::{ImageCacheMsg, MainThreadScriptChan, MainThreadScript
 use crate::script_thread::{ScriptThread, SendableMainThreadScriptChan};
 use crate::task_manager::TaskManager;
 use crate::task_source::{TaskSource, TaskSourceName};
-use crate::timers::{IsInterval, TimerCallback};
+use crate::timers::{IsInterval, OneshotTimerCallback, TimerCallback};
 use crate::webdriver_handlers::jsval_to_webdriver;
 use app_units::Au;
 use base64;
@@ -74,8 +79,8 @@ use crossbeam_channel::{unbounded, Sender, TryRecvError};
 use cssparser::{Parser, ParserInput, SourceLocation};
 use devtools_traits::{ScriptToDevtoolsControlMsg, TimelineMarker, TimelineMarkerType};
 use dom_struct::dom_struct;
-use embedder_traits::{EmbedderMsg, EventLoopWaker, PromptDefinition, PromptOrigin, PromptResult};
-use euclid::default::{Point2D as UntypedPoint2D, Rect as UntypedRect};
+use embedder_traits::{EmbedderMsg, EventLoopWaker, PromptRequest, PromptResponse};
+use euclid::default::{Point2D as UntypedPoint2D, Rect as UntypedRect, Size2D as UntypedSize2D};
 use euclid::{Point2D, Rect, Scale, Size2D, Vector2D};
 use ipc_channel::ipc::{channel, IpcSender};
 use ipc_channel::router::ROUTER;
@@ -89,16 +94,18 @@ use js::jsval::{JSVal, NullValue};
 use js::rust::wrappers::JS_DefineProperty;
 use js::rust::{CustomAutoRooter, CustomAutoRooterGuard, HandleValue};
 use media::WindowGLContext;
-use msg::constellation_msg::{BrowsingContextId, PipelineId};
+use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use net_traits::image::base::{Image, ImageAnimation};
 use net_traits::image_cache::{ImageCache, ImageResponder, ImageResponse};
 use net_traits::image_cache::{PendingImageId, PendingImageResponse};
-use net_traits::storage_thread::StorageType;
-use net_traits::ResourceThreads;
+use net_traits::storage_thread::{StorageThreadMsg, StorageType};
+use net_traits::{IpcSend, ResourceThreads};
 use num_traits::ToPrimitive;
 use profile_traits::ipc as ProfiledIpc;
 use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use profile_traits::time::{ProfilerChan as TimeProfilerChan, ProfilerMsg};
 use script_layout_interface::message::{Msg, QueryMsg, Reflow, ReflowGoal, ScriptReflow};
+use script_layout_interface::rpc::{BoxSizes, BoxSizesResponse};
 use script_layout_interface::rpc::{ContentBoxResponse, ContentBoxesResponse, LayoutRPC};
 use script_layout_interface::rpc::{
     NodeScrollIdResponse, ResolvedStyleResponse, TextIndexResponse,
@@ -107,10 +114,13 @@ use script_layout_interface::{PendingImageState, TrustedNodeAddress};
 use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSResult};
 use script_traits::{ConstellationControlMsg, DocumentState, HistoryEntryReplacement, LoadData};
 use script_traits::{
-    ScriptMsg, ScriptToConstellationChan, ScrollState, StructuredSerializedData, TimerEventId,
+    MsDuration, ScriptMsg, ScriptToConstellationChan, ScrollState, StructuredSerializedData,
+    TimerEventId,
 };
 use script_traits::{TimerSchedulerMsg, WebrenderIpcSender, WindowSizeData, WindowSizeType};
 use selectors::attr::CaseSensitivity;
+use servo_config::pref;
+use servo_config::prefs::PrefValue;
 use servo_geometry::{f32_rect_to_au_rect, MaxRect};
 use servo_url::{Host, ImmutableOrigin, MutableOrigin, ServoUrl};
 use std::borrow::Cow;
@@ -137,9 +147,28 @@ use style::stylesheets::CssRuleType;
 use style_traits::{CSSPixel, DevicePixel, ParsingMode};
 use url::Position;
 use webrender_api::units::{DeviceIntPoint, DeviceIntSize, LayoutPixel};
-use webrender_api::{DocumentId, ExternalScrollId};
+use webrender_api::{DirtyRect, DocumentId, ExternalScrollId, ImageData, ImageDescriptor};
+use webrender_api::{ImageDescriptorFlags, ImageFormat, ImageKey, Transaction};
 use webvr_traits::WebVRMsg;
 
+/// The duration of smooth scrolls, in milliseconds.
+const SMOOTH_SCROLL_DURATION: u64 = 250;
+
+/// The delay between the steps of smooth scrolls, in milliseconds.
+const SMOOTH_SCROLL_STEP_DELAY: u64 = 16;
+
+/// The delay below which frames of animated images are shown for
+/// `DEFAULT_IMAGE_FRAME_DELAY` instead, as other browsers do, in milliseconds.
+const MINIMUM_IMAGE_FRAME_DELAY: u64 = 10;
+
+/// The delay of the frames of animated images that are too short, in milliseconds.
+const DEFAULT_IMAGE_FRAME_DELAY: u64 = 100;
+
+/// The current time in milliseconds, as used for activation timestamps.
+fn activation_time_now() -> f64 {
+    time::precise_time_ns() as f64 / 1_000_000.
+}
+
 /// Current state of the window object
 #[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
 enum WindowState {
@@ -170,6 +199,9 @@ pub enum ReflowReason {
     IFrameLoadEvent,
     MissingExplicitReflow,
     ElementStateChanged,
+    MemoryPressure,
+    EmbedderStylesheetsChanged,
+    AutoResizeEnabled,
 }
 
 #[dom_struct]
@@ -179,6 +211,7 @@ pub struct Window {
     script_chan: MainThreadScriptChan,
     task_manager: TaskManager,
     navigator: MutNullableDom<Navigator>,
+    cookie_store: MutNullableDom<CookieStore>,
     #[ignore_malloc_size_of = "Arc"]
     image_cache: Arc<dyn ImageCache>,
     #[ignore_malloc_size_of = "channels are hard"]
@@ -192,6 +225,17 @@ pub struct Window {
     navigation_start: Cell<u64>,
     navigation_start_precise: Cell<u64>,
     screen: MutNullableDom<Screen>,
+    visual_viewport: MutNullableDom<VisualViewport>,
+    /// The scale of the visual viewport, set by the compositor after a pinch zoom.
+    visual_viewport_scale: Cell<f64>,
+    /// The offset of the visual viewport from the layout viewport, inside which it moves
+    /// when the page is pinch zoomed, set by the compositor.
+    visual_viewport_offset: Cell<Vector2D<f32, CSSPixel>>,
+    /// <https://html.spec.whatwg.org/multipage/#last-activation-timestamp>
+    last_activation_timestamp: Cell<f64>,
+    /// The number of dialogs shown since the user last interacted with the window, to
+    /// stop pages from showing dialogs endlessly.
+    dialogs_without_activation: Cell<u32>,
     session_storage: MutNullableDom<Storage>,
     local_storage: MutNullableDom<Storage>,
     status: DomRefCell<DOMString>,
@@ -201,6 +245,11 @@ pub struct Window {
     devtools_markers: DomRefCell<HashSet<TimelineMarkerType>>,
     #[ignore_malloc_size_of = "channels are hard"]
     devtools_marker_sender: DomRefCell<Option<IpcSender<Option<TimelineMarker>>>>,
+    /// Whether the devtools inspector wants to be notified of DOM mutations.
+    devtools_wants_dom_mutations: Cell<bool>,
+    /// Whether the devtools node picker is active, in which case mouse events
+    /// pick nodes instead of reaching the page.
+    devtools_node_picking: Cell<bool>,
 
     /// Pending resize event, if any.
     resize_event: Cell<Option<(WindowSizeData, WindowSizeType)>>,
@@ -258,9 +307,30 @@ pub struct Window {
     /// A list of scroll offsets for each scrollable element.
     scroll_offsets: DomRefCell<HashMap<OpaqueNode, Vector2D<f32, LayoutPixel>>>,
 
+    /// The smooth scrolls in progress, and whether their next step is scheduled.
+    smooth_scrolls: DomRefCell<Vec<SmoothScroll>>,
+    smooth_scroll_step_scheduled: Cell<bool>,
+
+    /// The animated images displayed by image elements, and whether the next change of
+    /// the frame of one of them is scheduled.
+    animated_images: DomRefCell<Vec<AnimatedImage>>,
+    image_animation_step_scheduled: Cell<bool>,
+
     /// All the MediaQueryLists we need to update
     media_query_lists: DOMTracker<MediaQueryList>,
 
+    /// Whether the environment changed since media queries were last evaluated, so that
+    /// they are evaluated once when the event loop next updates the rendering.
+    media_queries_dirty: Cell<bool>,
+
+    /// Whether the browser of this top-level window resizes to fit its content, and is
+    /// told about the size of the content after the reflows which change it.
+    auto_resize: Cell<bool>,
+
+    /// The size of the content last reported to the embedder, when the browser of this
+    /// window resizes to fit its content.
+    reported_content_size: Cell<Option<DeviceIntSize>>,
+
     test_runner: MutNullableDom<TestRunner>,
 
     /// A handle for communicating messages to the WebGL thread, if available.
@@ -420,6 +490,16 @@ impl Window {
         self.window_proxy.get().unwrap()
     }
 
+    /// The value of a preference for this window, which the embedder may override for
+    /// its top-level browsing context.
+    pub fn pref(&self, key: &str) -> PrefValue {
+        let top_level_browsing_context_id = self
+            .window_proxy
+            .get()
+            .map(|window_proxy| window_proxy.top_level_browsing_context_id());
+        ScriptThread::pref(top_level_browsing_context_id, key)
+    }
+
     /// Returns the window proxy if it has not been discarded.
     /// <https://html.spec.whatwg.org/multipage/#a-browsing-context-is-discarded>
     pub fn undiscarded_window_proxy(&self) -> Option<DomRoot<WindowProxy>> {
@@ -488,7 +568,7 @@ impl Window {
             node.dirty(NodeDamage::OtherNodeDamage);
         }
         match response.response {
-            ImageResponse::MetadataLoaded(_) => {},
+            ImageResponse::MetadataLoaded(_) | ImageResponse::PartiallyLoaded(_, _) => {},
             ImageResponse::Loaded(_, _) |
             ImageResponse::PlaceholderLoaded(_, _) |
             ImageResponse::None => {
@@ -619,32 +699,36 @@ impl WindowMethods for Window {
             stdout.flush().unwrap();
             stderr.flush().unwrap();
         }
-        let (sender, receiver) =
-            ProfiledIpc::channel(self.global().time_profiler_chan().clone()).unwrap();
-        let prompt = PromptDefinition::Alert(s.to_string(), sender);
-        let msg = EmbedderMsg::Prompt(prompt, PromptOrigin::Untrusted);
-        self.send_to_embedder(msg);
-        receiver.recv().unwrap();
+        // Step 1.
+        if self.cannot_show_simple_dialogs() {
+            return;
+        }
+        // Steps 2-7.
+        self.show_dialog(PromptRequest::Alert(s.to_string()));
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-confirm
     fn Confirm(&self, s: DOMString) -> bool {
-        let (sender, receiver) =
-            ProfiledIpc::channel(self.global().time_profiler_chan().clone()).unwrap();
-        let prompt = PromptDefinition::OkCancel(s.to_string(), sender);
-        let msg = EmbedderMsg::Prompt(prompt, PromptOrigin::Untrusted);
-        self.send_to_embedder(msg);
-        receiver.recv().unwrap() == PromptResult::Primary
+        // Step 1.
+        if self.cannot_show_simple_dialogs() {
+            return false;
+        }
+        // Steps 2-8.
+        self.show_dialog(PromptRequest::Confirm(s.to_string())) == Some(PromptResponse::Accept)
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-prompt
     fn Prompt(&self, message: DOMString, default: DOMString) -> Option<DOMString> {
-        let (sender, receiver) =
-            ProfiledIpc::channel(self.global().time_profiler_chan().clone()).unwrap();
-        let prompt = PromptDefinition::Input(message.to_string(), default.to_string(), sender);
-        let msg = EmbedderMsg::Prompt(prompt, PromptOrigin::Untrusted);
-        self.send_to_embedder(msg);
-        receiver.recv().unwrap().map(|s| s.into())
+        // Step 1.
+        if self.cannot_show_simple_dialogs() {
+            return None;
+        }
+        // Steps 2-10.
+        let request = PromptRequest::Input(message.to_string(), default.to_string());
+        match self.show_dialog(request) {
+            Some(PromptResponse::Text(text)) => Some(text.into()),
+            _ => None,
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-window-stop
@@ -805,6 +889,11 @@ impl WindowMethods for Window {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/ServiceWorker/#global-caches
+    fn Caches(&self) -> DomRoot<CacheStorage> {
+        self.upcast::<GlobalScope>().caches()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-frameelement
     fn GetFrameElement(&self) -> Option<DomRoot<Element>> {
         // Steps 1-3.
@@ -834,6 +923,11 @@ impl WindowMethods for Window {
         self.navigator.or_init(|| Navigator::new(self))
     }
 
+    // https://wicg.github.io/cookie-store/#dom-window-cookiestore
+    fn CookieStore(&self) -> DomRoot<CookieStore> {
+        self.cookie_store.or_init(|| CookieStore::new(self))
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowtimers-settimeout
     fn SetTimeout(
         &self,
@@ -954,6 +1048,14 @@ impl WindowMethods for Window {
         self.screen.or_init(|| Screen::new(self))
     }
 
+    // https://wicg.github.io/visual-viewport/#dom-window-visualviewport
+    fn GetVisualViewport(&self) -> Option<DomRoot<VisualViewport>> {
+        if !self.Document().is_fully_active() {
+            return None;
+        }
+        Some(self.visual_viewport.or_init(|| VisualViewport::new(self)))
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowbase64-btoa
     fn Btoa(&self, btoa: DOMString) -> Fallible<DOMString> {
         base64_btoa(btoa)
@@ -1077,11 +1179,22 @@ impl WindowMethods for Window {
             s.make_ascii_lowercase();
             s
         }) {
-            Some(ref pseudo) if pseudo == ":before" || pseudo == "::before" => {
-                Some(PseudoElement::Before)
-            },
-            Some(ref pseudo) if pseudo == ":after" || pseudo == "::after" => {
-                Some(PseudoElement::After)
+            Some(ref pseudo) if pseudo.starts_with(':') => match &**pseudo {
+                ":before" | "::before" => Some(PseudoElement::Before),
+                ":after" | "::after" => Some(PseudoElement::After),
+                ":first-letter" | "::first-letter" => Some(PseudoElement::FirstLetter),
+                ":first-line" | "::first-line" => Some(PseudoElement::FirstLine),
+                "::marker" => Some(PseudoElement::Marker),
+                "::placeholder" => Some(PseudoElement::Placeholder),
+                // The declarations of pseudo-elements that are not supported are empty.
+                _ => {
+                    return CSSStyleDeclaration::new(
+                        self,
+                        CSSStyleOwner::Null(Dom::from_ref(self)),
+                        None,
+                        CSSModificationAccess::Readonly,
+                    );
+                },
             },
             _ => None,
         };
@@ -1391,7 +1504,33 @@ impl Window {
         cancelled.store(true, Ordering::Relaxed);
     }
 
+    /// Copy the session storage area of the origin of this document, with the changes made
+    /// so far in this task, to a top-level browsing context which it opened.
+    /// <https://html.spec.whatwg.org/multipage/#copy-the-session-storage-bottle>
+    pub fn copy_session_storage(&self, target: TopLevelBrowsingContextId) {
+        if let Some(storage) = self.session_storage.get() {
+            storage.flush_changes();
+        }
+        let msg = StorageThreadMsg::CloneSessionStorage(
+            self.window_proxy().top_level_browsing_context_id(),
+            target,
+            self.get_url(),
+        );
+        if let Err(e) = self.upcast::<GlobalScope>().resource_threads().send(msg) {
+            warn!("Failed to copy the session storage ({:?}).", e);
+        }
+    }
+
     pub fn clear_js_runtime(&self) {
+        // The tasks which would send the last changes to the storage areas are cancelled, so
+        // they are sent now.
+        if let Some(storage) = self.local_storage.get() {
+            storage.flush_changes();
+        }
+        if let Some(storage) = self.session_storage.get() {
+            storage.flush_changes();
+        }
+
         // We tear down the active document, which causes all the attached
         // nodes to dispose of their layout data. This messages the layout
         // thread, informing it that it can safely free the memory.
@@ -1485,7 +1624,6 @@ impl Window {
         let global_scope = self.upcast::<GlobalScope>();
         let x = x.to_f32().unwrap_or(0.0f32);
         let y = y.to_f32().unwrap_or(0.0f32);
-        self.update_viewport_for_scroll(x, y);
         self.perform_a_scroll(
             x,
             y,
@@ -1495,36 +1633,374 @@ impl Window {
         );
     }
 
+    /// Scroll the scroll frame with the given id, which is the viewport or belongs to
+    /// the given element.
     /// <https://drafts.csswg.org/cssom-view/#perform-a-scroll>
     pub fn perform_a_scroll(
         &self,
         x: f32,
         y: f32,
         scroll_id: ExternalScrollId,
-        _behavior: ScrollBehavior,
-        _element: Option<&Element>,
+        behavior: ScrollBehavior,
+        element: Option<&Element>,
+    ) {
+        let node = if scroll_id == self.upcast::<GlobalScope>().pipeline_id().root_scroll_id() {
+            None
+        } else {
+            element.map(|element| element.upcast::<Node>().to_opaque())
+        };
+        let position = Vector2D::new(x, y);
+
+        // Step 1.
+        self.smooth_scrolls
+            .borrow_mut()
+            .retain(|scroll| scroll.scroll_id != scroll_id);
+
+        // Step 2. There is no `scroll-behavior` property, so only scrolls asking for
+        // a smooth behavior are smooth.
+        if behavior == ScrollBehavior::Smooth {
+            // WebRender cannot animate scrolls, so script moves the scroll frame a
+            // step at a time instead.
+            let scroll = SmoothScroll {
+                scroll_id,
+                node,
+                from: self.scroll_position(node),
+                to: position,
+                start: time::precise_time_ns(),
+            };
+            self.smooth_scrolls.borrow_mut().push(scroll);
+            self.schedule_smooth_scroll_step();
+            return;
+        }
+
+        // Step 3.
+        self.set_scroll_position(scroll_id, node, position);
+    }
+
+    /// The scroll position of the scroll frame of a node, or of the viewport.
+    fn scroll_position(&self, node: Option<OpaqueNode>) -> Vector2D<f32, LayoutPixel> {
+        match node {
+            Some(node) => self.scroll_offset_query_for(node),
+            None => {
+                let origin = self.current_viewport.get().origin;
+                Vector2D::new(origin.x.to_f32_px(), origin.y.to_f32_px())
+            },
+        }
+    }
+
+    fn set_scroll_position(
+        &self,
+        scroll_id: ExternalScrollId,
+        node: Option<OpaqueNode>,
+        position: Vector2D<f32, LayoutPixel>,
     ) {
-        // TODO Step 1
-        // TODO(mrobinson, #18709): Add smooth scrolling support to WebRender so that we can
-        // properly process ScrollBehavior here.
+        // The scroll positions are immediately updated since later calls to scrollTop
+        // and others may access them before WebRender has a chance to report them.
+        match node {
+            Some(node) => {
+                self.scroll_offsets.borrow_mut().insert(node, position);
+            },
+            None => self.update_viewport_for_scroll(position.x, position.y),
+        }
         self.layout_chan
             .send(Msg::UpdateScrollStateFromScript(ScrollState {
                 scroll_id,
-                scroll_offset: Vector2D::new(-x, -y),
+                scroll_offset: -position,
             }))
             .unwrap();
     }
 
+    fn schedule_smooth_scroll_step(&self) {
+        if self.smooth_scroll_step_scheduled.get() {
+            return;
+        }
+        self.smooth_scroll_step_scheduled.set(true);
+        let callback = SmoothScrollCallback {
+            window: Trusted::new(self),
+        };
+        self.upcast::<GlobalScope>().schedule_callback(
+            OneshotTimerCallback::SmoothScroll(callback),
+            MsDuration::new(SMOOTH_SCROLL_STEP_DELAY),
+        );
+    }
+
+    /// Move the scroll frames being smoothly scrolled to their positions at this time.
+    fn step_smooth_scrolls(&self) {
+        self.smooth_scroll_step_scheduled.set(false);
+        let now = time::precise_time_ns();
+        let scrolls = mem::replace(&mut *self.smooth_scrolls.borrow_mut(), vec![]);
+        for scroll in scrolls {
+            let elapsed = now.saturating_sub(scroll.start) / 1_000_000;
+            let progress = (elapsed as f32 / SMOOTH_SCROLL_DURATION as f32).min(1.0);
+            // Ease out, so that the scroll slows down as it reaches its destination.
+            let eased_progress = 1.0 - (1.0 - progress).powi(3);
+            let position = scroll.from.lerp(scroll.to, eased_progress);
+            self.set_scroll_position(scroll.scroll_id, scroll.node, position);
+            if progress < 1.0 {
+                self.smooth_scrolls.borrow_mut().push(scroll);
+            }
+        }
+        if !self.smooth_scrolls.borrow().is_empty() {
+            self.schedule_smooth_scroll_step();
+        }
+    }
+
+    /// Animate an image displayed by an image element, until the element displays
+    /// another image or is removed from the document. The element is given its own
+    /// WebRender image, since the image of the cache is shared by all the elements and
+    /// documents which display it.
+    pub fn start_image_animation(&self, element: &HTMLImageElement, image: &Arc<Image>) {
+        let animation = match image.animation.as_ref() {
+            Some(animation) => animation,
+            None => return,
+        };
+        let key = self.webrender_api_sender.generate_image_key();
+        let mut txn = Transaction::new();
+        let (descriptor, data) = image_frame_data(image, animation.frame_bytes(0));
+        txn.add_image(key, descriptor, data, None);
+        self.webrender_api_sender
+            .update_resources(txn.resource_updates);
+        element.set_animated_image(Arc::new(Image {
+            width: image.width,
+            height: image.height,
+            format: image.format,
+            bytes: image.bytes.clone(),
+            id: Some(key),
+            cors_status: image.cors_status,
+            animation: None,
+            orientation: image.orientation,
+        }));
+
+        self.animated_images.borrow_mut().push(AnimatedImage {
+            image: image.clone(),
+            element: Dom::from_ref(element),
+            key,
+            current_frame: 0,
+            frame_start: time::precise_time_ns(),
+        });
+        self.schedule_image_animation_step(image_frame_delay(animation, 0));
+    }
+
+    fn schedule_image_animation_step(&self, delay: u64) {
+        if self.image_animation_step_scheduled.get() {
+            return;
+        }
+        self.image_animation_step_scheduled.set(true);
+        let callback = ImageAnimationCallback {
+            window: Trusted::new(self),
+        };
+        self.upcast::<GlobalScope>().schedule_callback(
+            OneshotTimerCallback::ImageAnimation(callback),
+            MsDuration::new(delay),
+        );
+    }
+
+    /// Show the frames that animated images should display at this time, by updating
+    /// the pixels of the WebRender images of their elements with frames decoded beforehand.
+    fn step_image_animations(&self) {
+        self.image_animation_step_scheduled.set(false);
+        let now = time::precise_time_ns();
+        let mut next_step = None;
+        let mut txn = Transaction::new();
+        let mut animated_images = self.animated_images.borrow_mut();
+        animated_images.retain(|animated_image| {
+            let element = &animated_image.element;
+            let displayed = element.upcast::<Node>().is_connected() &&
+                element.current_image_key() == Some(animated_image.key);
+            if !displayed {
+                txn.delete_image(animated_image.key);
+            }
+            displayed
+        });
+        for animated_image in animated_images.iter_mut() {
+            let image = &animated_image.image;
+            let animation = image.animation.as_ref().unwrap();
+
+            // Skip the whole loops of the animation that were missed, and then frames.
+            let loop_duration: u64 = (0..animation.frames.len())
+                .map(|index| image_frame_delay(animation, index) * 1_000_000)
+                .sum();
+            let elapsed = now - animated_image.frame_start;
+            if elapsed >= loop_duration {
+                animated_image.frame_start += elapsed / loop_duration * loop_duration;
+            }
+            let mut frame = animated_image.current_frame;
+            let mut frame_end =
+                animated_image.frame_start + image_frame_delay(animation, frame) * 1_000_000;
+            while frame_end <= now {
+                animated_image.frame_start = frame_end;
+                frame = (frame + 1) % animation.frames.len();
+                frame_end += image_frame_delay(animation, frame) * 1_000_000;
+            }
+            let delay = (frame_end - now) / 1_000_000;
+            next_step = Some(next_step.map_or(delay, |next_step: u64| next_step.min(delay)));
+
+            if frame == animated_image.current_frame {
+                continue;
+            }
+            animated_image.current_frame = frame;
+            let (descriptor, data) = image_frame_data(image, animation.frame_bytes(frame));
+            txn.update_image(animated_image.key, descriptor, data, &DirtyRect::All);
+            animated_image
+                .element
+                .upcast::<Node>()
+                .dirty(NodeDamage::OtherNodeDamage);
+        }
+        drop(animated_images);
+
+        if !txn.resource_updates.is_empty() {
+            self.webrender_api_sender
+                .update_resources(txn.resource_updates);
+        }
+        if let Some(delay) = next_step {
+            self.schedule_image_animation_step(delay);
+        }
+    }
+
     pub fn update_viewport_for_scroll(&self, x: f32, y: f32) {
-        let size = self.current_viewport.get().size;
-        let new_viewport = Rect::new(Point2D::new(Au::from_f32_px(x), Au::from_f32_px(y)), size);
-        self.current_viewport.set(new_viewport)
+        let old_viewport = self.current_viewport.get();
+        let new_viewport = Rect::new(
+            Point2D::new(Au::from_f32_px(x), Au::from_f32_px(y)),
+            old_viewport.size,
+        );
+        self.current_viewport.set(new_viewport);
+        if new_viewport.origin != old_viewport.origin {
+            if let Some(visual_viewport) = self.visual_viewport.get() {
+                visual_viewport.queue_event(atom!("scroll"));
+            }
+        }
+    }
+
+    pub fn visual_viewport_scale(&self) -> f64 {
+        self.visual_viewport_scale.get()
+    }
+
+    pub fn visual_viewport_offset(&self) -> Vector2D<f32, CSSPixel> {
+        self.visual_viewport_offset.get()
+    }
+
+    pub fn set_visual_viewport(&self, scale: f64, offset: Vector2D<f32, CSSPixel>) {
+        if self.visual_viewport_offset.get() != offset {
+            self.visual_viewport_offset.set(offset);
+            if let Some(visual_viewport) = self.visual_viewport.get() {
+                visual_viewport.queue_event(atom!("scroll"));
+            }
+        }
+        if self.visual_viewport_scale.get() != scale {
+            self.visual_viewport_scale.set(scale);
+            self.visual_viewport_resized();
+        }
+    }
+
+    /// Let the visual viewport know that its size or scale changed.
+    pub fn visual_viewport_resized(&self) {
+        if let Some(visual_viewport) = self.visual_viewport.get() {
+            visual_viewport.queue_event(atom!("resize"));
+        }
     }
 
     pub fn device_pixel_ratio(&self) -> Scale<f32, CSSPixel, DevicePixel> {
         self.window_size.get().device_pixel_ratio
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#sticky-activation>
+    pub fn has_sticky_activation(&self) -> bool {
+        self.last_activation_timestamp.get() != f64::INFINITY
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#transient-activation>
+    pub fn has_transient_activation(&self) -> bool {
+        let timestamp = self.last_activation_timestamp.get();
+        let duration = pref!(dom.user_activation.transient_timeout_ms) as f64;
+        timestamp.is_finite() && activation_time_now() - timestamp < duration
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#activation-notification>
+    pub fn notify_user_activation(&self) {
+        // Step 1-2.
+        self.set_user_activation();
+
+        // Step 3. Ancestors might be in other script threads, so the
+        // constellation takes care of them.
+        if !self.is_top_level() {
+            self.send_to_constellation(ScriptMsg::NotifyUserActivation);
+        }
+
+        // Step 4.
+        self.set_user_activation_for_descendants(self.origin());
+    }
+
+    /// Step 4 of <https://html.spec.whatwg.org/multipage/#activation-notification>.
+    /// Same origin descendants always live in this script thread.
+    fn set_user_activation_for_descendants(&self, origin: &MutableOrigin) {
+        for iframe in self.Document().iter_iframes() {
+            let document = match iframe.pipeline_id().and_then(ScriptThread::find_document) {
+                Some(document) => document,
+                None => continue,
+            };
+            if document.origin().same_origin(origin) {
+                let window = document.window();
+                window.set_user_activation();
+                window.set_user_activation_for_descendants(origin);
+            }
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#cannot-show-simple-dialogs>
+    fn cannot_show_simple_dialogs(&self) -> bool {
+        let document = self.Document();
+        // Step 1
+        if document.is_sandboxed_from_modals() {
+            return true;
+        }
+        // Step 2. The termination nesting level is approximated by the unload
+        // counter of the document, which is raised while it is being unloaded.
+        document.is_prompting_or_unloading()
+    }
+
+    /// Show a dialog, pausing the event loop until the user answers it. Once the window
+    /// has shown `dom.dialogs.max_without_activation` dialogs since the user last
+    /// interacted with it, the next ones are suppressed and `None` is returned.
+    pub fn show_dialog(&self, request: PromptRequest) -> Option<PromptResponse> {
+        let max = pref!(dom.dialogs.max_without_activation);
+        let shown = self.dialogs_without_activation.get();
+        if max >= 0 && i64::from(shown) >= max {
+            if i64::from(shown) == max {
+                Console::internal_warn(
+                    self.upcast::<GlobalScope>(),
+                    DOMString::from(
+                        "Suppressing the dialogs of this page until it is interacted with.",
+                    ),
+                );
+                self.dialogs_without_activation.set(shown + 1);
+            }
+            return None;
+        }
+        self.dialogs_without_activation.set(shown + 1);
+        Some(self.upcast::<GlobalScope>().prompt(request))
+    }
+
+    /// Set the last activation timestamp, on behalf of an activated descendant.
+    pub fn set_user_activation(&self) {
+        self.last_activation_timestamp.set(activation_time_now());
+        self.dialogs_without_activation.set(0);
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#consume-user-activation>
+    pub fn consume_user_activation(&self) {
+        self.clear_user_activation();
+        // The other windows of the top-level browsing context are consumed
+        // by the constellation.
+        self.send_to_constellation(ScriptMsg::ConsumeUserActivation);
+    }
+
+    /// Step 5 of <https://html.spec.whatwg.org/multipage/#consume-user-activation>
+    pub fn clear_user_activation(&self) {
+        if self.last_activation_timestamp.get() != f64::INFINITY {
+            self.last_activation_timestamp.set(f64::NEG_INFINITY);
+        }
+    }
+
     fn client_window(&self) -> (Size2D<u32, CSSPixel>, Point2D<i32, CSSPixel>) {
         let timer_profile_chan = self.global().time_profiler_chan().clone();
         let (send, recv) =
@@ -1602,6 +2078,7 @@ impl Window {
 
         let document = self.Document();
 
+        document.flush_directionality();
         let stylesheets_changed = document.flush_stylesheets_for_reflow();
 
         // If this reflow is for display, ensure webgl canvases are composited with
@@ -1692,6 +2169,10 @@ impl Window {
             ScriptThread::note_newly_transitioning_nodes(complete.newly_transitioning_nodes);
         }
 
+        if let Some(content_size) = complete.content_size {
+            self.report_content_size(content_size);
+        }
+
         true
     }
 
@@ -1796,6 +2277,14 @@ impl Window {
         rects
     }
 
+    pub fn box_sizes_query(&self, node: &Node) -> Option<BoxSizes> {
+        if !self.layout_reflow(QueryMsg::BoxSizesQuery(node.to_opaque())) {
+            return None;
+        }
+        let BoxSizesResponse(box_sizes) = self.layout_rpc.box_sizes();
+        box_sizes
+    }
+
     pub fn client_rect_query(&self, node: &Node) -> UntypedRect<i32> {
         if !self.layout_reflow(QueryMsg::ClientRectQuery(node.to_opaque())) {
             return Rect::zero();
@@ -1811,7 +2300,11 @@ impl Window {
     }
 
     pub fn scroll_offset_query(&self, node: &Node) -> Vector2D<f32, LayoutPixel> {
-        if let Some(scroll_offset) = self.scroll_offsets.borrow().get(&node.to_opaque()) {
+        self.scroll_offset_query_for(node.to_opaque())
+    }
+
+    fn scroll_offset_query_for(&self, node: OpaqueNode) -> Vector2D<f32, LayoutPixel> {
+        if let Some(scroll_offset) = self.scroll_offsets.borrow().get(&node) {
             return *scroll_offset;
         }
         Vector2D::new(0.0, 0.0)
@@ -1823,13 +2316,6 @@ impl Window {
             return;
         }
 
-        // The scroll offsets are immediatly updated since later calls
-        // to topScroll and others may access the properties before
-        // webrender has a chance to update the offsets.
-        self.scroll_offsets
-            .borrow_mut()
-            .insert(node.to_opaque(), Vector2D::new(x_ as f32, y_ as f32));
-
         let NodeScrollIdResponse(scroll_id) = self.layout_rpc.node_scroll_id();
 
         // Step 12
@@ -1838,7 +2324,7 @@ impl Window {
             y_.to_f32().unwrap_or(0.0f32),
             scroll_id,
             behavior,
-            None,
+            node.downcast::<Element>(),
         );
     }
 
@@ -1901,6 +2387,15 @@ impl Window {
         self.layout_rpc.text_index()
     }
 
+    /// The index of the character of a text node that is nearest to a point relative to
+    /// the initial containing block.
+    pub fn caret_position_query(&self, node: &Node, point: UntypedPoint2D<f32>) -> Option<usize> {
+        if !self.layout_reflow(QueryMsg::CaretPositionQuery(node.to_opaque(), point)) {
+            return None;
+        }
+        self.layout_rpc.text_index().0
+    }
+
     #[allow(unsafe_code)]
     pub fn init_window_proxy(&self, window_proxy: &WindowProxy) {
         assert!(self.window_proxy.get().is_none());
@@ -2147,6 +2642,22 @@ impl Window {
         }
     }
 
+    pub fn devtools_wants_dom_mutations(&self) -> bool {
+        self.devtools_wants_dom_mutations.get()
+    }
+
+    pub fn set_devtools_wants_dom_mutations(&self, value: bool) {
+        self.devtools_wants_dom_mutations.set(value);
+    }
+
+    pub fn devtools_node_picking(&self) -> bool {
+        self.devtools_node_picking.get()
+    }
+
+    pub fn set_devtools_node_picking(&self, value: bool) {
+        self.devtools_node_picking.set(value);
+    }
+
     pub fn set_webdriver_script_chan(&self, chan: Option<IpcSender<WebDriverJSResult>>) {
         *self.webdriver_script_chan.borrow_mut() = chan;
     }
@@ -2160,9 +2671,58 @@ impl Window {
         self.parent_info.is_none()
     }
 
+    /// Note that the viewport, the device pixel ratio or the user preferences changed,
+    /// which may change the result of media queries.
+    pub fn media_queries_need_evaluation(&self) {
+        self.media_queries_dirty.set(true);
+    }
+
+    /// Evaluate media query lists and report changes, if the environment changed since
+    /// they were last evaluated.
+    pub fn maybe_evaluate_media_queries_and_report_changes(&self) {
+        if self.media_queries_dirty.get() {
+            self.evaluate_media_queries_and_report_changes();
+        }
+    }
+
+    /// Start or stop reporting the size of the content of the document to the embedder,
+    /// for browsers which resize to fit their content. The size is reported right away,
+    /// then after the reflows which change it.
+    pub fn set_auto_resize(&self, auto_resize: bool) {
+        self.auto_resize.set(auto_resize);
+        self.reported_content_size.set(None);
+        // A window whose document is not loaded yet reports its size on its first reflow.
+        let is_fully_active = self
+            .document
+            .get()
+            .map_or(false, |document| document.is_fully_active());
+        if auto_resize && is_fully_active {
+            self.force_reflow(ReflowGoal::Full, ReflowReason::AutoResizeEnabled);
+        }
+    }
+
+    /// Report the content size computed by a reflow to the embedder if it changed since it
+    /// was last reported.
+    fn report_content_size(&self, content_size: UntypedSize2D<Au>) {
+        if !self.auto_resize.get() {
+            return;
+        }
+        let size = Size2D::<f32, CSSPixel>::new(
+            content_size.width.to_f32_px(),
+            content_size.height.to_f32_px(),
+        );
+        let size = (size * self.device_pixel_ratio()).round().to_i32();
+        if self.reported_content_size.get() == Some(size) {
+            return;
+        }
+        self.reported_content_size.set(Some(size));
+        self.send_to_embedder(EmbedderMsg::ContentSizeChanged(size));
+    }
+
     /// Evaluate media query lists and report changes
     /// <https://drafts.csswg.org/cssom-view/#evaluate-media-queries-and-report-changes>
     pub fn evaluate_media_queries_and_report_changes(&self) {
+        self.media_queries_dirty.set(false);
         rooted_vec!(let mut mql_list);
         self.media_query_lists.for_each(|mql| {
             if let MediaQueryListMatchState::Changed(_) = mql.evaluate_changes() {
@@ -2263,6 +2823,7 @@ impl Window {
         is_headless: bool,
         replace_surrogates: bool,
         user_agent: Cow<'static, str>,
+        is_private: bool,
         player_context: WindowGLContext,
         event_loop_waker: Option<Box<dyn EventLoopWaker>>,
     ) -> DomRoot<Self> {
@@ -2288,12 +2849,14 @@ impl Window {
                 microtask_queue,
                 is_headless,
                 user_agent,
+                is_private,
             ),
             script_chan,
             task_manager,
             image_cache_chan,
             image_cache,
             navigator: Default::default(),
+            cookie_store: Default::default(),
             location: Default::default(),
             history: Default::default(),
             custom_element_registry: Default::default(),
@@ -2303,6 +2866,11 @@ impl Window {
             navigation_start: Cell::new(navigation_start),
             navigation_start_precise: Cell::new(navigation_start_precise),
             screen: Default::default(),
+            visual_viewport: Default::default(),
+            visual_viewport_scale: Cell::new(1.0),
+            visual_viewport_offset: Cell::new(Vector2D::zero()),
+            last_activation_timestamp: Cell::new(f64::INFINITY),
+            dialogs_without_activation: Cell::new(0),
             session_storage: Default::default(),
             local_storage: Default::default(),
             status: DomRefCell::new(DOMString::new()),
@@ -2322,10 +2890,19 @@ impl Window {
             current_state: Cell::new(WindowState::Alive),
             devtools_marker_sender: Default::default(),
             devtools_markers: Default::default(),
+            devtools_wants_dom_mutations: Default::default(),
+            devtools_node_picking: Default::default(),
             webdriver_script_chan: Default::default(),
             error_reporter,
             scroll_offsets: Default::default(),
+            smooth_scrolls: Default::default(),
+            smooth_scroll_step_scheduled: Cell::new(false),
+            animated_images: Default::default(),
+            image_animation_step_scheduled: Cell::new(false),
             media_query_lists: DOMTracker::new(),
+            media_queries_dirty: Cell::new(false),
+            auto_resize: Cell::new(false),
+            reported_content_size: Cell::new(None),
             test_runner: Default::default(),
             webgl_chan,
             webvr_chan,
@@ -2389,6 +2966,7 @@ fn debug_reflow_events(id: PipelineId, reflow_goal: &ReflowGoal, reason: &Reflow
         ReflowGoal::LayoutQuery(ref query_msg, _) => match query_msg {
             &QueryMsg::ContentBoxQuery(_n) => "\tContentBoxQuery",
             &QueryMsg::ContentBoxesQuery(_n) => "\tContentBoxesQuery",
+            &QueryMsg::BoxSizesQuery(_n) => "\tBoxSizesQuery",
             &QueryMsg::NodesFromPointQuery(..) => "\tNodesFromPointQuery",
             &QueryMsg::ClientRectQuery(_n) => "\tClientRectQuery",
             &QueryMsg::NodeScrollGeometryQuery(_n) => "\tNodeScrollGeometryQuery",
@@ -2397,6 +2975,7 @@ fn debug_reflow_events(id: PipelineId, reflow_goal: &ReflowGoal, reason: &Reflow
             &QueryMsg::OffsetParentQuery(_n) => "\tOffsetParentQuery",
             &QueryMsg::StyleQuery(_n) => "\tStyleQuery",
             &QueryMsg::TextIndexQuery(..) => "\tTextIndexQuery",
+            &QueryMsg::CaretPositionQuery(..) => "\tCaretPositionQuery",
             &QueryMsg::ElementInnerTextQuery(_) => "\tElementInnerTextQuery",
             &QueryMsg::InnerWindowDimensionsQuery(_) => "\tInnerWindowDimensionsQuery",
         },
@@ -2423,6 +3002,9 @@ fn debug_reflow_events(id: PipelineId, reflow_goal: &ReflowGoal, reason: &Reflow
         ReflowReason::IFrameLoadEvent => "\tIFrameLoadEvent",
         ReflowReason::MissingExplicitReflow => "\tMissingExplicitReflow",
         ReflowReason::ElementStateChanged => "\tElementStateChanged",
+        ReflowReason::MemoryPressure => "\tMemoryPressure",
+        ReflowReason::EmbedderStylesheetsChanged => "\tEmbedderStylesheetsChanged",
+        ReflowReason::AutoResizeEnabled => "\tAutoResizeEnabled",
     });
 
     println!("{}", debug_msg);
@@ -2490,6 +3072,86 @@ impl Window {
     }
 }
 
+/// A smooth scroll of a scroll frame, from the position it had when the scroll started.
+#[derive(JSTraceable, MallocSizeOf)]
+struct SmoothScroll {
+    scroll_id: ExternalScrollId,
+    /// The node whose scroll frame is scrolled, or none for the viewport.
+    node: Option<OpaqueNode>,
+    from: Vector2D<f32, LayoutPixel>,
+    to: Vector2D<f32, LayoutPixel>,
+    /// The time at which the scroll started, in nanoseconds.
+    start: u64,
+}
+
+/// A callback stepping the smooth scrolls of a window.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct SmoothScrollCallback {
+    #[ignore_malloc_size_of = "non-owning"]
+    window: Trusted<Window>,
+}
+
+impl SmoothScrollCallback {
+    pub fn invoke(self) {
+        self.window.root().step_smooth_scrolls();
+    }
+}
+
+/// An image displayed by an image element of a window, whose frames are animated.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct AnimatedImage {
+    #[ignore_malloc_size_of = "Arc"]
+    image: Arc<Image>,
+    element: Dom<HTMLImageElement>,
+    /// The WebRender image of the element, whose pixels are the current frame.
+    #[ignore_malloc_size_of = "Defined in webrender_api"]
+    key: ImageKey,
+    current_frame: usize,
+    /// The time at which the current frame started to be displayed, in nanoseconds.
+    frame_start: u64,
+}
+
+/// The WebRender description and pixels of a frame of an animated image.
+fn image_frame_data(image: &Image, frame_bytes: &[u8]) -> (ImageDescriptor, ImageData) {
+    let mut bytes = frame_bytes.to_vec();
+    let mut flags = ImageDescriptorFlags::ALLOW_MIPMAPS;
+    flags.set(
+        ImageDescriptorFlags::IS_OPAQUE,
+        pixels::rgba8_premultiply_inplace(&mut bytes),
+    );
+    let descriptor = ImageDescriptor::new(
+        image.width as i32,
+        image.height as i32,
+        ImageFormat::BGRA8,
+        flags,
+    );
+    (descriptor, ImageData::new(bytes))
+}
+
+/// How long a frame of an animated image is displayed, in milliseconds.
+fn image_frame_delay(animation: &ImageAnimation, index: usize) -> u64 {
+    let delay = animation.frames[index].delay.as_millis() as u64;
+    if delay <= MINIMUM_IMAGE_FRAME_DELAY {
+        DEFAULT_IMAGE_FRAME_DELAY
+    } else {
+        delay
+    }
+}
+
+/// A callback changing the frames of the animated images of a window.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct ImageAnimationCallback {
+    #[ignore_malloc_size_of = "non-owning"]
+    window: Trusted<Window>,
+}
+
+impl ImageAnimationCallback {
+    pub fn invoke(self) {
+        self.window.root().step_image_animations();
+    }
+}
+
 #[derive(Clone, MallocSizeOf)]
 pub struct CSSErrorReporter {
     pub pipelineid: PipelineId,
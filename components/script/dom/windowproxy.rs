@@ -52,6 +52,7 @@ use script_traits::{
     AuxiliaryBrowsingContextLoadInfo, HistoryEntryReplacement, LoadData, LoadOrigin,
 };
 use script_traits::{NewLayoutInfo, ScriptMsg};
+use servo_config::pref;
 use servo_url::ServoUrl;
 use std::cell::Cell;
 use std::ptr;
@@ -270,6 +271,10 @@ impl WindowProxy {
             .and_then(|id| ScriptThread::find_document(id))
             .and_then(|doc| Some(DomRoot::from_ref(doc.window())))
             .unwrap();
+        // Popups need a transient activation, which opening one consumes.
+        if pref!(dom.user_activation.required) && !window.has_transient_activation() {
+            return None;
+        }
         let msg = EmbedderMsg::AllowOpeningBrowser(chan);
         window.send_to_embedder(msg);
         if port.recv().unwrap() {
@@ -315,11 +320,15 @@ impl WindowProxy {
             ScriptThread::process_attach_layout(new_layout_info, document.origin().clone());
             let msg = EmbedderMsg::BrowserCreated(new_top_level_browsing_context_id);
             window.send_to_embedder(msg);
-            // TODO: if noopener is false, copy the sessionStorage storage area of the creator origin.
-            // See step 14 of https://html.spec.whatwg.org/multipage/#creating-a-new-browsing-context
+            // Step 14 of https://html.spec.whatwg.org/multipage/#creating-a-new-browsing-context
+            if !noopener {
+                window.copy_session_storage(new_top_level_browsing_context_id);
+            }
             let auxiliary =
                 ScriptThread::find_document(new_pipeline_id).and_then(|doc| doc.browsing_context());
             if let Some(proxy) = auxiliary {
+                // Only a popup that was actually opened consumes the activation.
+                window.consume_user_activation();
                 if name.to_lowercase() != "_blank" {
                     proxy.set_name(name);
                 }
@@ -108,6 +108,7 @@ impl Worker {
             let page_info = DevtoolsPageInfo {
                 title: title,
                 url: worker_url.clone(),
+                is_private: global.is_private(),
             };
             let _ = chan.send(ScriptToDevtoolsControlMsg::NewGlobal(
                 (pipeline_id, Some(worker_id)),
@@ -15,6 +15,7 @@ use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::settings_stack::AutoEntryScript;
 use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::cachestorage::CacheStorage;
 use crate::dom::crypto::Crypto;
 use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use crate::dom::globalscope::GlobalScope;
@@ -31,6 +32,7 @@ use crate::script_runtime::{get_reports, CommonScriptMsg, Runtime, ScriptChan, S
 use crate::task::TaskCanceller;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
+use crate::task_source::javascript_engine::JavaScriptEngineTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::performance_timeline::PerformanceTimelineTaskSource;
 use crate::task_source::port_message::PortMessageQueue;
@@ -76,6 +78,7 @@ pub fn prepare_workerscope_init(
         origin: global.origin().immutable().clone(),
         is_headless: global.is_headless(),
         user_agent: global.get_user_agent(),
+        is_private: global.is_private(),
     };
 
     init
@@ -137,6 +140,7 @@ impl WorkerGlobalScope {
                 runtime.microtask_queue.clone(),
                 init.is_headless,
                 init.user_agent,
+                init.is_private,
             ),
             worker_id: init.worker_id,
             worker_name,
@@ -280,6 +284,11 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/ServiceWorker/#global-caches
+    fn Caches(&self) -> DomRoot<CacheStorage> {
+        self.upcast::<GlobalScope>().caches()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowbase64-btoa
     fn Btoa(&self, btoa: DOMString) -> Fallible<DOMString> {
         base64_btoa(btoa)
@@ -430,6 +439,10 @@ impl WorkerGlobalScope {
         NetworkingTaskSource(self.script_chan(), self.pipeline_id())
     }
 
+    pub fn javascript_engine_task_source(&self) -> JavaScriptEngineTaskSource {
+        JavaScriptEngineTaskSource(self.script_chan(), self.pipeline_id())
+    }
+
     pub fn performance_timeline_task_source(&self) -> PerformanceTimelineTaskSource {
         PerformanceTimelineTaskSource(self.script_chan(), self.pipeline_id())
     }
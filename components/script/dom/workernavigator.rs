@@ -2,8 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::WorkerNavigatorBinding;
 use crate::dom::bindings::codegen::Bindings::WorkerNavigatorBinding::WorkerNavigatorMethods;
+use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
@@ -11,7 +13,10 @@ use crate::dom::gpu::GPU;
 use crate::dom::navigatorinfo;
 use crate::dom::permissions::Permissions;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
+use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
+use js::jsapi::Heap;
+use js::jsval::JSVal;
 
 // https://html.spec.whatwg.org/multipage/#workernavigator
 #[dom_struct]
@@ -19,6 +24,9 @@ pub struct WorkerNavigator {
     reflector_: Reflector,
     permissions: MutNullableDom<Permissions>,
     gpu: MutNullableDom<GPU>,
+    languages: DomRefCell<Vec<String>>,
+    #[ignore_malloc_size_of = "mozjs"]
+    languages_array: Heap<JSVal>,
 }
 
 impl WorkerNavigator {
@@ -27,6 +35,8 @@ impl WorkerNavigator {
             reflector_: Reflector::new(),
             permissions: Default::default(),
             gpu: Default::default(),
+            languages: Default::default(),
+            languages_array: Heap::default(),
         }
     }
 
@@ -82,7 +92,7 @@ impl WorkerNavigatorMethods for WorkerNavigator {
 
     // https://html.spec.whatwg.org/multipage/#dom-navigator-useragent
     fn UserAgent(&self) -> DOMString {
-        navigatorinfo::UserAgent(self.global().get_user_agent())
+        navigatorinfo::UserAgent(&self.global().user_agent_for_origin())
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-navigator-appversion
@@ -95,6 +105,21 @@ impl WorkerNavigatorMethods for WorkerNavigator {
         navigatorinfo::Language()
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-navigator-languages
+    fn Languages(&self, cx: JSContext) -> JSVal {
+        navigatorinfo::Languages(cx, &self.languages, &self.languages_array)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-navigator-hardwareconcurrency
+    fn HardwareConcurrency(&self) -> u64 {
+        navigatorinfo::HardwareConcurrency()
+    }
+
+    // https://www.w3.org/TR/device-memory/#dom-navigator-devicememory
+    fn DeviceMemory(&self) -> Finite<f64> {
+        navigatorinfo::DeviceMemory()
+    }
+
     // https://w3c.github.io/permissions/#navigator-and-workernavigator-extension
     fn Permissions(&self) -> DomRoot<Permissions> {
         self.permissions
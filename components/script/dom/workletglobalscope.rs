@@ -71,6 +71,7 @@ impl WorkletGlobalScope {
                 Default::default(),
                 init.is_headless,
                 init.user_agent.clone(),
+                init.is_private,
             ),
             base_url,
             to_script_thread_sender: init.to_script_thread_sender.clone(),
@@ -156,6 +157,8 @@ pub struct WorkletGlobalScopeInit {
     pub is_headless: bool,
     /// An optional string allowing the user agent to be set for testing
     pub user_agent: Cow<'static, str>,
+    /// True if the worklets belong to a private browsing session
+    pub is_private: bool,
 }
 
 /// <https://drafts.css-houdini.org/worklets/#worklet-global-scope-type>
@@ -0,0 +1,575 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::WritableStreamBinding::{self, WritableStreamMethods};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::streams::{get_member, get_method, high_water_mark_from_value};
+use crate::dom::streams::{promise_call, size_function_from_value, start_call};
+use crate::dom::transformstream::TransformStream;
+use crate::dom::websocketstream::WebSocketStream;
+use crate::dom::writablestreamdefaultcontroller::WritableStreamDefaultController;
+use crate::dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleObject, HandleValue};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#writablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum WritableStreamState {
+    Writable,
+    Closed,
+    Erroring,
+    Errored,
+}
+
+/// <https://streams.spec.whatwg.org/#pending-abort-request>
+#[derive(JSTraceable, MallocSizeOf)]
+struct PendingAbortRequest {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    #[ignore_malloc_size_of = "mozjs"]
+    reason: Box<Heap<JSVal>>,
+    was_already_erroring: bool,
+}
+
+#[dom_struct]
+pub struct WritableStream {
+    reflector_: Reflector,
+    state: Cell<WritableStreamState>,
+    #[ignore_malloc_size_of = "mozjs"]
+    stored_error: Heap<JSVal>,
+    controller: MutNullableDom<WritableStreamDefaultController>,
+    /// The writer that the stream is locked to, if any.
+    writer: MutNullableDom<WritableStreamDefaultWriter>,
+    /// The promises of the writes that the sink was not asked to perform yet.
+    #[ignore_malloc_size_of = "Rc"]
+    write_requests: DomRefCell<VecDeque<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    in_flight_write_request: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    close_request: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    in_flight_close_request: DomRefCell<Option<Rc<Promise>>>,
+    pending_abort_request: DomRefCell<Option<PendingAbortRequest>>,
+    backpressure: Cell<bool>,
+}
+
+impl WritableStream {
+    fn new_inherited() -> WritableStream {
+        WritableStream {
+            reflector_: Reflector::new(),
+            state: Cell::new(WritableStreamState::Writable),
+            stored_error: Heap::default(),
+            controller: Default::default(),
+            writer: Default::default(),
+            write_requests: DomRefCell::new(VecDeque::new()),
+            in_flight_write_request: DomRefCell::new(None),
+            close_request: DomRefCell::new(None),
+            in_flight_close_request: DomRefCell::new(None),
+            pending_abort_request: DomRefCell::new(None),
+            backpressure: Cell::new(false),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<WritableStream> {
+        reflect_dom_object(
+            Box::new(WritableStream::new_inherited()),
+            global,
+            WritableStreamBinding::Wrap,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-controller>
+    #[allow(unrooted_must_root)]
+    pub fn new_with_sink(
+        global: &GlobalScope,
+        sink: UnderlyingSink,
+        high_water_mark: f64,
+        size: Option<Rc<Function>>,
+    ) -> Fallible<DomRoot<WritableStream>> {
+        let stream = WritableStream::new(global);
+        let controller =
+            WritableStreamDefaultController::new(global, &stream, sink, high_water_mark, size);
+        stream.controller.set(Some(&controller));
+        controller.start()?;
+        Ok(stream)
+    }
+
+    // https://streams.spec.whatwg.org/#ws-constructor
+    #[allow(unrooted_must_root)]
+    pub fn Constructor(
+        cx: SafeJSContext,
+        global: &GlobalScope,
+        underlying_sink: Option<*mut JSObject>,
+        strategy: Option<*mut JSObject>,
+    ) -> Fallible<DomRoot<WritableStream>> {
+        rooted!(in(*cx) let sink_object = underlying_sink.unwrap_or(ptr::null_mut()));
+        rooted!(in(*cx) let strategy = strategy.unwrap_or(ptr::null_mut()));
+
+        rooted!(in(*cx) let mut size = UndefinedValue());
+        get_member(cx, strategy.handle(), b"size\0", size.handle_mut())?;
+        rooted!(in(*cx) let mut high_water_mark = UndefinedValue());
+        get_member(
+            cx,
+            strategy.handle(),
+            b"highWaterMark\0",
+            high_water_mark.handle_mut(),
+        )?;
+        rooted!(in(*cx) let mut type_ = UndefinedValue());
+        get_member(cx, sink_object.handle(), b"type\0", type_.handle_mut())?;
+        if !type_.is_undefined() {
+            return Err(Error::Range(
+                "Writable streams do not have a type".to_owned(),
+            ));
+        }
+
+        let size = size_function_from_value(cx, size.handle())?;
+        let high_water_mark = high_water_mark_from_value(cx, high_water_mark.handle(), 1.)?;
+        let sink = UnderlyingSink::new(cx, sink_object.handle())?;
+        WritableStream::new_with_sink(global, sink, high_water_mark, size)
+    }
+
+    pub fn state(&self) -> WritableStreamState {
+        self.state.get()
+    }
+
+    pub fn stored_error(&self) -> HandleValue {
+        self.stored_error.handle()
+    }
+
+    pub fn controller(&self) -> DomRoot<WritableStreamDefaultController> {
+        self.controller
+            .get()
+            .expect("A stream has a controller once it is constructed")
+    }
+
+    pub fn writer(&self) -> Option<DomRoot<WritableStreamDefaultWriter>> {
+        self.writer.get()
+    }
+
+    pub fn set_writer(&self, writer: Option<&WritableStreamDefaultWriter>) {
+        self.writer.set(writer);
+    }
+
+    pub fn backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-writable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.writer.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close-queued-or-in-flight>
+    pub fn close_queued_or_in_flight(&self) -> bool {
+        self.close_request.borrow().is_some() || self.in_flight_close_request.borrow().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-has-operation-marked-in-flight>
+    fn has_operation_marked_in_flight(&self) -> bool {
+        self.in_flight_write_request.borrow().is_some() ||
+            self.in_flight_close_request.borrow().is_some()
+    }
+
+    pub fn has_in_flight_write_request(&self) -> bool {
+        self.in_flight_write_request.borrow().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-abort>
+    pub fn abort(&self, reason: HandleValue) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        match self.state.get() {
+            WritableStreamState::Closed | WritableStreamState::Errored => {
+                promise.resolve_native(&());
+                return promise;
+            },
+            WritableStreamState::Writable | WritableStreamState::Erroring => {},
+        }
+        if let Some(ref request) = *self.pending_abort_request.borrow() {
+            return request.promise.clone();
+        }
+        let was_already_erroring = self.state.get() == WritableStreamState::Erroring;
+        *self.pending_abort_request.borrow_mut() = Some(PendingAbortRequest {
+            promise: promise.clone(),
+            reason: Heap::boxed(if was_already_erroring {
+                UndefinedValue()
+            } else {
+                reason.get()
+            }),
+            was_already_erroring,
+        });
+        if !was_already_erroring {
+            self.start_erroring(reason);
+        }
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close>
+    pub fn close(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        match self.state.get() {
+            WritableStreamState::Closed | WritableStreamState::Errored => {
+                promise.reject_error(Error::Type("The stream is closed or errored".to_owned()));
+                return promise;
+            },
+            WritableStreamState::Writable | WritableStreamState::Erroring => {},
+        }
+        assert!(!self.close_queued_or_in_flight());
+        *self.close_request.borrow_mut() = Some(promise.clone());
+        if let Some(writer) = self.writer.get() {
+            if self.backpressure.get() && self.state.get() == WritableStreamState::Writable {
+                writer.resolve_ready();
+            }
+        }
+        self.controller().close();
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-add-write-request>
+    pub fn add_write_request(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        self.write_requests.borrow_mut().push_back(promise.clone());
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-deal-with-rejection>
+    pub fn deal_with_rejection(&self, error: HandleValue) {
+        if self.state.get() == WritableStreamState::Writable {
+            self.start_erroring(error);
+            return;
+        }
+        self.finish_erroring();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-start-erroring>
+    pub fn start_erroring(&self, reason: HandleValue) {
+        assert_eq!(self.state.get(), WritableStreamState::Writable);
+        self.state.set(WritableStreamState::Erroring);
+        self.stored_error.set(reason.get());
+        if let Some(writer) = self.writer.get() {
+            writer.ensure_ready_promise_rejected(reason);
+        }
+        if !self.has_operation_marked_in_flight() && self.controller().started() {
+            self.finish_erroring();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-erroring>
+    #[allow(unrooted_must_root)]
+    pub fn finish_erroring(&self) {
+        assert_eq!(self.state.get(), WritableStreamState::Erroring);
+        assert!(!self.has_operation_marked_in_flight());
+        self.state.set(WritableStreamState::Errored);
+        let controller = self.controller();
+        controller.error_steps();
+        let cx = self.global().get_cx();
+        let requests: Vec<_> = self.write_requests.borrow_mut().drain(..).collect();
+        for promise in requests {
+            promise.reject(cx, self.stored_error.handle());
+        }
+        let request = match self.pending_abort_request.borrow_mut().take() {
+            Some(request) => request,
+            None => return self.reject_close_and_closed_promise_if_needed(),
+        };
+        if request.was_already_erroring {
+            request.promise.reject(cx, self.stored_error.handle());
+            return self.reject_close_and_closed_promise_if_needed();
+        }
+        rooted!(in(*cx) let reason = request.reason.get());
+        let abort_promise = controller.abort_steps(reason.handle());
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(AbortRequestHandler {
+                stream: Dom::from_ref(self),
+                promise: request.promise.clone(),
+                fulfilled,
+            })
+        };
+        let handler =
+            PromiseNativeHandler::new(&self.global(), Some(handler(true)), Some(handler(false)));
+        abort_promise.append_native_handler(&handler);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write>
+    pub fn finish_in_flight_write(&self) {
+        let promise = self
+            .in_flight_write_request
+            .borrow_mut()
+            .take()
+            .expect("A write is in flight");
+        promise.resolve_native(&());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write-with-error>
+    pub fn finish_in_flight_write_with_error(&self, error: HandleValue) {
+        let promise = self
+            .in_flight_write_request
+            .borrow_mut()
+            .take()
+            .expect("A write is in flight");
+        promise.reject(self.global().get_cx(), error);
+        self.deal_with_rejection(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close>
+    pub fn finish_in_flight_close(&self) {
+        let promise = self
+            .in_flight_close_request
+            .borrow_mut()
+            .take()
+            .expect("A close is in flight");
+        promise.resolve_native(&());
+        if self.state.get() == WritableStreamState::Erroring {
+            self.stored_error.set(UndefinedValue());
+            if let Some(request) = self.pending_abort_request.borrow_mut().take() {
+                request.promise.resolve_native(&());
+            }
+        }
+        self.state.set(WritableStreamState::Closed);
+        if let Some(writer) = self.writer.get() {
+            writer.resolve_closed();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close-with-error>
+    pub fn finish_in_flight_close_with_error(&self, error: HandleValue) {
+        let promise = self
+            .in_flight_close_request
+            .borrow_mut()
+            .take()
+            .expect("A close is in flight");
+        let cx = self.global().get_cx();
+        promise.reject(cx, error);
+        if let Some(request) = self.pending_abort_request.borrow_mut().take() {
+            request.promise.reject(cx, error);
+        }
+        self.deal_with_rejection(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-close-request-in-flight>
+    pub fn mark_close_request_in_flight(&self) {
+        let request = self.close_request.borrow_mut().take();
+        *self.in_flight_close_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-first-write-request-in-flight>
+    pub fn mark_first_write_request_in_flight(&self) {
+        let request = self.write_requests.borrow_mut().pop_front();
+        *self.in_flight_write_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-reject-close-and-closed-promise-if-needed>
+    fn reject_close_and_closed_promise_if_needed(&self) {
+        assert_eq!(self.state.get(), WritableStreamState::Errored);
+        if let Some(promise) = self.close_request.borrow_mut().take() {
+            promise.reject(self.global().get_cx(), self.stored_error.handle());
+        }
+        if let Some(writer) = self.writer.get() {
+            writer.ensure_closed_promise_rejected(self.stored_error.handle());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-update-backpressure>
+    pub fn update_backpressure(&self, backpressure: bool) {
+        if let Some(writer) = self.writer.get() {
+            if backpressure != self.backpressure.get() {
+                if backpressure {
+                    writer.reset_ready_promise();
+                } else {
+                    writer.resolve_ready();
+                }
+            }
+        }
+        self.backpressure.set(backpressure);
+    }
+}
+
+impl WritableStreamMethods for WritableStream {
+    // https://streams.spec.whatwg.org/#ws-locked
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    // https://streams.spec.whatwg.org/#ws-abort
+    fn Abort(&self, _cx: SafeJSContext, reason: HandleValue) -> Rc<Promise> {
+        if self.is_locked() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type("The stream is locked to a writer".to_owned()));
+            return promise;
+        }
+        self.abort(reason)
+    }
+
+    // https://streams.spec.whatwg.org/#ws-close
+    fn Close(&self) -> Rc<Promise> {
+        if self.is_locked() || self.close_queued_or_in_flight() {
+            let promise = Promise::new(&self.global());
+            promise.reject_error(Error::Type(
+                "The stream is locked to a writer or closing".to_owned(),
+            ));
+            return promise;
+        }
+        self.close()
+    }
+
+    // https://streams.spec.whatwg.org/#ws-get-writer
+    fn GetWriter(&self) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        WritableStreamDefaultWriter::acquire(&self.global(), self)
+    }
+}
+
+/// Settle the promise of an abort request once the sink is aborted.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct AbortRequestHandler {
+    stream: Dom<WritableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for AbortRequestHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        if self.fulfilled {
+            self.promise.resolve_native(&());
+        } else {
+            self.promise
+                .reject(unsafe { SafeJSContext::from_ptr(cx) }, v);
+        }
+        self.stream.reject_close_and_closed_promise_if_needed();
+    }
+}
+
+/// The object with which script constructs a stream, whose methods are invoked with it as
+/// `this`.
+/// <https://streams.spec.whatwg.org/#underlying-sink-api>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct UnderlyingSink {
+    #[ignore_malloc_size_of = "mozjs"]
+    object: Box<Heap<*mut JSObject>>,
+    #[ignore_malloc_size_of = "Rc"]
+    start: Option<Rc<Function>>,
+    #[ignore_malloc_size_of = "Rc"]
+    write: Option<Rc<Function>>,
+    #[ignore_malloc_size_of = "Rc"]
+    close: Option<Rc<Function>>,
+    #[ignore_malloc_size_of = "Rc"]
+    abort: Option<Rc<Function>>,
+    /// The transform stream whose writable side the stream is, which transforms the
+    /// chunks written to it instead of the methods of a sink.
+    transform_stream: Option<Dom<TransformStream>>,
+    /// The WebSocket whose messages are written to the stream.
+    web_socket_stream: Option<Dom<WebSocketStream>>,
+}
+
+impl UnderlyingSink {
+    #[allow(unrooted_must_root)]
+    fn new(cx: SafeJSContext, object: HandleObject) -> Fallible<UnderlyingSink> {
+        Ok(UnderlyingSink {
+            abort: get_method(cx, object, b"abort\0")?,
+            close: get_method(cx, object, b"close\0")?,
+            start: get_method(cx, object, b"start\0")?,
+            write: get_method(cx, object, b"write\0")?,
+            object: Heap::boxed(object.get()),
+            transform_stream: None,
+            web_socket_stream: None,
+        })
+    }
+
+    /// The sink of the streams whose chunks are written by Servo.
+    #[allow(unrooted_must_root)]
+    fn none() -> UnderlyingSink {
+        UnderlyingSink {
+            object: Heap::boxed(ptr::null_mut()),
+            start: None,
+            write: None,
+            close: None,
+            abort: None,
+            transform_stream: None,
+            web_socket_stream: None,
+        }
+    }
+
+    /// The sink of the writable side of a transform stream.
+    #[allow(unrooted_must_root)]
+    pub fn for_transform_stream(stream: &TransformStream) -> UnderlyingSink {
+        UnderlyingSink {
+            transform_stream: Some(Dom::from_ref(stream)),
+            ..UnderlyingSink::none()
+        }
+    }
+
+    /// The sink of the writable stream of a `WebSocketStream`.
+    #[allow(unrooted_must_root)]
+    pub fn for_web_socket_stream(stream: &WebSocketStream) -> UnderlyingSink {
+        UnderlyingSink {
+            web_socket_stream: Some(Dom::from_ref(stream)),
+            ..UnderlyingSink::none()
+        }
+    }
+
+    /// Invoke the start method of the sink, returning a promise resolved with its result,
+    /// or the exception that it throws.
+    pub fn start(&self, controller: &impl DomObject) -> Fallible<Rc<Promise>> {
+        if let Some(ref stream) = self.transform_stream {
+            return Ok(stream.start_promise());
+        }
+        start_call(&controller.global(), &self.object, &self.start, controller)
+    }
+
+    /// Invoke the write method of the sink.
+    pub fn write(&self, controller: &impl DomObject, chunk: HandleValue) -> Rc<Promise> {
+        if let Some(ref stream) = self.transform_stream {
+            return stream.sink_write(chunk);
+        }
+        if let Some(ref stream) = self.web_socket_stream {
+            return stream.sink_write(chunk);
+        }
+        let global = controller.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let controller = ObjectValue(controller.reflector().get_jsobject().get()));
+        promise_call(
+            &global,
+            &self.object,
+            &self.write,
+            &[chunk.get(), controller.get()],
+        )
+    }
+
+    /// Invoke the close method of the sink.
+    pub fn close(&self, global: &GlobalScope) -> Rc<Promise> {
+        if let Some(ref stream) = self.transform_stream {
+            return stream.sink_close();
+        }
+        if let Some(ref stream) = self.web_socket_stream {
+            return stream.sink_close();
+        }
+        promise_call(global, &self.object, &self.close, &[])
+    }
+
+    /// Invoke the abort method of the sink.
+    pub fn abort(&self, global: &GlobalScope, reason: HandleValue) -> Rc<Promise> {
+        if let Some(ref stream) = self.transform_stream {
+            return stream.sink_abort(reason);
+        }
+        if let Some(ref stream) = self.web_socket_stream {
+            return stream.sink_abort();
+        }
+        promise_call(global, &self.object, &self.abort, &[reason.get()])
+    }
+}
@@ -0,0 +1,304 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::WritableStreamDefaultControllerBinding::{
+    self, WritableStreamDefaultControllerMethods,
+};
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::streams::{chunk_size, error_to_jsval, take_pending_exception};
+use crate::dom::writablestream::{UnderlyingSink, WritableStream, WritableStreamState};
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext};
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// A chunk of the queue of a controller, with the size that the strategy of the stream
+/// computed for it.
+#[derive(JSTraceable, MallocSizeOf)]
+struct QueuedChunk {
+    #[ignore_malloc_size_of = "mozjs"]
+    value: Box<Heap<JSVal>>,
+    size: f64,
+}
+
+#[dom_struct]
+pub struct WritableStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<WritableStream>,
+    sink: UnderlyingSink,
+    queue: DomRefCell<VecDeque<QueuedChunk>>,
+    /// Whether the stream was closed, which closes the sink once the chunks of the queue
+    /// are written. This stands for the close sentinel at the end of the queue.
+    close_queued: Cell<bool>,
+    queue_total_size: Cell<f64>,
+    high_water_mark: f64,
+    /// The size method of the queuing strategy, without which every chunk has a size of 1.
+    #[ignore_malloc_size_of = "Rc"]
+    size: Option<Rc<Function>>,
+    started: Cell<bool>,
+}
+
+impl WritableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        stream: &WritableStream,
+        sink: UnderlyingSink,
+        high_water_mark: f64,
+        size: Option<Rc<Function>>,
+    ) -> WritableStreamDefaultController {
+        WritableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            sink,
+            queue: DomRefCell::new(VecDeque::new()),
+            close_queued: Cell::new(false),
+            queue_total_size: Cell::new(0.),
+            high_water_mark,
+            size,
+            started: Cell::new(false),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        stream: &WritableStream,
+        sink: UnderlyingSink,
+        high_water_mark: f64,
+        size: Option<Rc<Function>>,
+    ) -> DomRoot<WritableStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(WritableStreamDefaultController::new_inherited(
+                stream,
+                sink,
+                high_water_mark,
+                size,
+            )),
+            global,
+            WritableStreamDefaultControllerBinding::Wrap,
+        )
+    }
+
+    /// Invoke the start method of the underlying sink.
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-controller>
+    pub fn start(&self) -> ErrorResult {
+        self.stream.update_backpressure(self.backpressure());
+        let promise = self.sink.start(self)?;
+        self.react_to_sink_promise(&promise, SinkMethod::Start);
+        Ok(())
+    }
+
+    pub fn started(&self) -> bool {
+        self.started.get()
+    }
+
+    #[allow(unrooted_must_root)]
+    fn react_to_sink_promise(&self, promise: &Promise, method: SinkMethod) {
+        let handler = |fulfilled| -> Box<dyn Callback> {
+            Box::new(SinkPromiseHandler {
+                controller: Dom::from_ref(self),
+                method,
+                fulfilled,
+            })
+        };
+        let handler =
+            PromiseNativeHandler::new(&self.global(), Some(handler(true)), Some(handler(false)));
+        promise.append_native_handler(&handler);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-close>
+    pub fn close(&self) {
+        self.close_queued.set(true);
+        self.advance_queue_if_needed();
+    }
+
+    /// Compute the size of a chunk, erroring the stream if the size method of the
+    /// strategy throws.
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-chunk-size>
+    pub fn chunk_size(&self, chunk: HandleValue) -> f64 {
+        let cx = self.global().get_cx();
+        match chunk_size(cx, &self.size, chunk) {
+            Ok(size) => size,
+            Err(()) => {
+                rooted!(in(*cx) let mut error = UndefinedValue());
+                take_pending_exception(cx, error.handle_mut());
+                self.error_if_needed(error.handle());
+                1.
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-desired-size>
+    pub fn desired_size(&self) -> f64 {
+        self.high_water_mark - self.queue_total_size.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-backpressure>
+    fn backpressure(&self) -> bool {
+        self.desired_size() <= 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-write>
+    pub fn write(&self, chunk: HandleValue, size: f64) {
+        if !size.is_finite() || size < 0. {
+            let global = self.global();
+            rooted!(in(*global.get_cx()) let mut error = UndefinedValue());
+            error_to_jsval(
+                &global,
+                Error::Range("The size of a chunk must be a non-negative number".to_owned()),
+                error.handle_mut(),
+            );
+            self.error_if_needed(error.handle());
+            return;
+        }
+        self.queue.borrow_mut().push_back(QueuedChunk {
+            value: Heap::boxed(chunk.get()),
+            size,
+        });
+        self.queue_total_size
+            .set(self.queue_total_size.get() + size);
+        self.update_backpressure_if_writable();
+        self.advance_queue_if_needed();
+    }
+
+    fn update_backpressure_if_writable(&self) {
+        if !self.stream.close_queued_or_in_flight() &&
+            self.stream.state() == WritableStreamState::Writable
+        {
+            self.stream.update_backpressure(self.backpressure());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-advance-queue-if-needed>
+    fn advance_queue_if_needed(&self) {
+        if !self.started.get() || self.stream.has_in_flight_write_request() {
+            return;
+        }
+        match self.stream.state() {
+            WritableStreamState::Erroring => return self.stream.finish_erroring(),
+            WritableStreamState::Writable => {},
+            state => unreachable!("The queue of a {:?} stream is not advanced", state),
+        }
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        match self.queue.borrow().front() {
+            Some(front) => chunk.set(front.value.get()),
+            None if self.close_queued.get() => {},
+            None => return,
+        }
+        if self.queue.borrow().is_empty() {
+            self.process_close();
+        } else {
+            self.process_write(chunk.handle());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error-if-needed>
+    pub fn error_if_needed(&self, error: HandleValue) {
+        if self.stream.state() == WritableStreamState::Writable {
+            self.error(error);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-close>
+    fn process_close(&self) {
+        self.stream.mark_close_request_in_flight();
+        self.close_queued.set(false);
+        let promise = self.sink.close(&self.global());
+        self.react_to_sink_promise(&promise, SinkMethod::Close);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-write>
+    fn process_write(&self, chunk: HandleValue) {
+        self.stream.mark_first_write_request_in_flight();
+        let promise = self.sink.write(self, chunk);
+        self.react_to_sink_promise(&promise, SinkMethod::Write);
+    }
+
+    fn write_completed(&self) {
+        self.stream.finish_in_flight_write();
+        if let Some(chunk) = self.queue.borrow_mut().pop_front() {
+            // Rounding errors could make the total size negative once the queue is empty.
+            self.queue_total_size
+                .set((self.queue_total_size.get() - chunk.size).max(0.));
+        }
+        self.update_backpressure_if_writable();
+        self.advance_queue_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error>
+    fn error(&self, error: HandleValue) {
+        self.stream.start_erroring(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-abort>
+    pub fn abort_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        self.sink.abort(&self.global(), reason)
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-error>
+    pub fn error_steps(&self) {
+        self.queue.borrow_mut().clear();
+        self.close_queued.set(false);
+        self.queue_total_size.set(0.);
+    }
+}
+
+impl WritableStreamDefaultControllerMethods for WritableStreamDefaultController {
+    // https://streams.spec.whatwg.org/#ws-default-controller-error
+    fn Error(&self, _cx: SafeJSContext, e: HandleValue) {
+        if self.stream.state() != WritableStreamState::Writable {
+            return;
+        }
+        self.error(e);
+    }
+}
+
+/// The methods of an underlying sink whose promises the controller of a stream reacts to.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum SinkMethod {
+    Start,
+    Write,
+    Close,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct SinkPromiseHandler {
+    controller: Dom<WritableStreamDefaultController>,
+    method: SinkMethod,
+    fulfilled: bool,
+}
+
+impl Callback for SinkPromiseHandler {
+    fn callback(&self, _cx: *mut JSContext, v: HandleValue) {
+        let controller = &self.controller;
+        let stream = &controller.stream;
+        match (self.method, self.fulfilled) {
+            (SinkMethod::Start, fulfilled) => {
+                controller.started.set(true);
+                if fulfilled {
+                    controller.advance_queue_if_needed();
+                } else {
+                    stream.deal_with_rejection(v);
+                }
+            },
+            (SinkMethod::Write, true) => controller.write_completed(),
+            (SinkMethod::Write, false) => stream.finish_in_flight_write_with_error(v),
+            (SinkMethod::Close, true) => stream.finish_in_flight_close(),
+            (SinkMethod::Close, false) => stream.finish_in_flight_close_with_error(v),
+        }
+    }
+}
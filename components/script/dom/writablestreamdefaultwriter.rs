@@ -0,0 +1,271 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::WritableStreamDefaultWriterBinding::{
+    self, WritableStreamDefaultWriterMethods,
+};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::streams::{error_to_jsval, mark_promise_as_handled};
+use crate::dom::writablestream::{WritableStream, WritableStreamState};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct WritableStreamDefaultWriter {
+    reflector_: Reflector,
+    /// The stream that the writer is locked to, until its lock is released.
+    stream: MutNullableDom<WritableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    /// The promise which is pending while the stream applies backpressure.
+    #[ignore_malloc_size_of = "Rc"]
+    ready_promise: DomRefCell<Rc<Promise>>,
+}
+
+impl WritableStreamDefaultWriter {
+    fn new_inherited(global: &GlobalScope) -> WritableStreamDefaultWriter {
+        WritableStreamDefaultWriter {
+            reflector_: Reflector::new(),
+            stream: Default::default(),
+            closed_promise: DomRefCell::new(Promise::new(global)),
+            ready_promise: DomRefCell::new(Promise::new(global)),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<WritableStreamDefaultWriter> {
+        reflect_dom_object(
+            Box::new(WritableStreamDefaultWriter::new_inherited(global)),
+            global,
+            WritableStreamDefaultWriterBinding::Wrap,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-writer>
+    pub fn acquire(
+        global: &GlobalScope,
+        stream: &WritableStream,
+    ) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked to a writer".to_owned()));
+        }
+        let writer = WritableStreamDefaultWriter::new(global);
+        writer.stream.set(Some(stream));
+        stream.set_writer(Some(&writer));
+        let cx = global.get_cx();
+        match stream.state() {
+            WritableStreamState::Writable => {
+                if stream.close_queued_or_in_flight() || !stream.backpressure() {
+                    writer.resolve_ready();
+                }
+            },
+            WritableStreamState::Erroring => {
+                let ready_promise = writer.ready_promise.borrow();
+                ready_promise.reject(cx, stream.stored_error());
+                mark_promise_as_handled(&ready_promise);
+            },
+            WritableStreamState::Closed => {
+                writer.resolve_ready();
+                writer.resolve_closed();
+            },
+            WritableStreamState::Errored => {
+                writer.ensure_ready_promise_rejected(stream.stored_error());
+                writer.ensure_closed_promise_rejected(stream.stored_error());
+            },
+        }
+        Ok(writer)
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-constructor
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &WritableStream,
+    ) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        WritableStreamDefaultWriter::acquire(global, stream)
+    }
+
+    pub fn ready(&self) -> Rc<Promise> {
+        self.ready_promise.borrow().clone()
+    }
+
+    pub fn closed(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn resolve_ready(&self) {
+        self.ready_promise.borrow().resolve_native(&());
+    }
+
+    /// Replace the ready promise with a pending one once the stream applies backpressure.
+    pub fn reset_ready_promise(&self) {
+        *self.ready_promise.borrow_mut() = Promise::new(&self.global());
+    }
+
+    pub fn resolve_closed(&self) {
+        self.closed_promise.borrow().resolve_native(&());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-ready-promise-rejected>
+    pub fn ensure_ready_promise_rejected(&self, error: HandleValue) {
+        self.ensure_promise_rejected(&self.ready_promise, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-closed-promise-rejected>
+    pub fn ensure_closed_promise_rejected(&self, error: HandleValue) {
+        self.ensure_promise_rejected(&self.closed_promise, error);
+    }
+
+    fn ensure_promise_rejected(&self, promise: &DomRefCell<Rc<Promise>>, error: HandleValue) {
+        // `is_fulfilled` is true for settled promises, which are replaced by rejected ones.
+        if promise.borrow().is_fulfilled() {
+            *promise.borrow_mut() = Promise::new(&self.global());
+        }
+        let promise = promise.borrow();
+        promise.reject(self.global().get_cx(), error);
+        mark_promise_as_handled(&promise);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-get-desired-size>
+    fn desired_size(&self, stream: &WritableStream) -> Option<f64> {
+        match stream.state() {
+            WritableStreamState::Errored | WritableStreamState::Erroring => None,
+            WritableStreamState::Closed => Some(0.),
+            WritableStreamState::Writable => Some(stream.controller().desired_size()),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-release>
+    pub fn release(&self) {
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return,
+        };
+        let global = self.global();
+        rooted!(in(*global.get_cx()) let mut error = UndefinedValue());
+        error_to_jsval(
+            &global,
+            Error::Type("The writer was released".to_owned()),
+            error.handle_mut(),
+        );
+        self.ensure_ready_promise_rejected(error.handle());
+        self.ensure_closed_promise_rejected(error.handle());
+        stream.set_writer(None);
+        self.stream.set(None);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-write>
+    pub fn write(&self, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return rejected_promise(&global, "The writer is released"),
+        };
+        let controller = stream.controller();
+        let chunk_size = controller.chunk_size(chunk);
+        // The size method of the strategy could have released the writer.
+        if stream.writer().map_or(true, |writer| &*writer != self) {
+            return rejected_promise(&global, "The writer is released");
+        }
+        let state = stream.state();
+        if state == WritableStreamState::Errored {
+            let promise = Promise::new(&global);
+            promise.reject(global.get_cx(), stream.stored_error());
+            return promise;
+        }
+        if stream.close_queued_or_in_flight() || state == WritableStreamState::Closed {
+            return rejected_promise(&global, "The stream is closing or closed");
+        }
+        if state == WritableStreamState::Erroring {
+            let promise = Promise::new(&global);
+            promise.reject(global.get_cx(), stream.stored_error());
+            return promise;
+        }
+        let promise = stream.add_write_request();
+        controller.write(chunk, chunk_size);
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-close-with-error-propagation>
+    pub fn close_with_error_propagation(&self) -> Rc<Promise> {
+        let global = self.global();
+        let stream = match self.stream.get() {
+            Some(stream) => stream,
+            None => return rejected_promise(&global, "The writer is released"),
+        };
+        let state = stream.state();
+        if stream.close_queued_or_in_flight() || state == WritableStreamState::Closed {
+            let promise = Promise::new(&global);
+            promise.resolve_native(&());
+            return promise;
+        }
+        if state == WritableStreamState::Errored {
+            let promise = Promise::new(&global);
+            promise.reject(global.get_cx(), stream.stored_error());
+            return promise;
+        }
+        stream.close()
+    }
+}
+
+impl WritableStreamDefaultWriterMethods for WritableStreamDefaultWriter {
+    // https://streams.spec.whatwg.org/#default-writer-closed
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed()
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-desired-size
+    fn GetDesiredSize(&self) -> Fallible<Option<f64>> {
+        match self.stream.get() {
+            Some(stream) => Ok(self.desired_size(&stream)),
+            None => Err(Error::Type("The writer is released".to_owned())),
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-ready
+    fn Ready(&self) -> Rc<Promise> {
+        self.ready()
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-abort
+    fn Abort(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(stream) => stream.abort(reason),
+            None => rejected_promise(&self.global(), "The writer is released"),
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-close
+    fn Close(&self) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(ref stream) if stream.close_queued_or_in_flight() => {
+                rejected_promise(&self.global(), "The stream is closing")
+            },
+            Some(stream) => stream.close(),
+            None => rejected_promise(&self.global(), "The writer is released"),
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-release-lock
+    fn ReleaseLock(&self) {
+        self.release();
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-write
+    fn Write(&self, _cx: JSContext, chunk: HandleValue) -> Rc<Promise> {
+        self.write(chunk)
+    }
+}
+
+fn rejected_promise(global: &GlobalScope, message: &str) -> Rc<Promise> {
+    let promise = Promise::new(global);
+    promise.reject_error(Error::Type(message.to_owned()));
+    promise
+}
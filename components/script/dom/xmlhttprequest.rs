@@ -10,7 +10,7 @@ use crate::dom::bindings::codegen::Bindings::XMLHttpRequestBinding;
 use crate::dom::bindings::codegen::Bindings::XMLHttpRequestBinding::BodyInit;
 use crate::dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestMethods;
 use crate::dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestResponseType;
-use crate::dom::bindings::codegen::UnionTypes::DocumentOrBodyInit;
+use crate::dom::bindings::codegen::UnionTypes::DocumentOrReadableStreamOrBodyInit;
 use crate::dom::bindings::conversions::ToJSValConvertible;
 use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::Castable;
@@ -31,6 +31,7 @@ use crate::dom::node::Node;
 use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::progressevent::ProgressEvent;
 use crate::dom::servoparser::ServoParser;
+use crate::dom::streams::request_body::transmit_body;
 use crate::dom::urlsearchparams::URLSearchParams;
 use crate::dom::window::Window;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
@@ -42,6 +43,7 @@ use crate::script_runtime::JSContext;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::TaskSourceName;
 use crate::timers::{OneshotTimerCallback, OneshotTimerHandle};
+use content_security_policy as csp;
 use dom_struct::dom_struct;
 use encoding_rs::{Encoding, UTF_8};
 use euclid::Length;
@@ -272,6 +274,11 @@ impl XMLHttpRequest {
                 *self.sync_status.borrow_mut() = Some(rv);
             }
 
+            fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+                let global = &self.resource_timing_global();
+                global.report_csp_violations(violations, None);
+            }
+
             fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
                 &mut self.resource_timing
             }
@@ -555,7 +562,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
     }
 
     // https://xhr.spec.whatwg.org/#the-send()-method
-    fn Send(&self, data: Option<DocumentOrBodyInit>) -> ErrorResult {
+    fn Send(&self, data: Option<DocumentOrReadableStreamOrBodyInit>) -> ErrorResult {
         // Step 1, 2
         if self.ready_state.get() != XMLHttpRequestState::Opened || self.send_flag.get() {
             return Err(Error::InvalidState);
@@ -568,7 +575,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         };
         // Step 4 (first half)
         let extracted_or_serialized = match data {
-            Some(DocumentOrBodyInit::Document(ref doc)) => {
+            Some(DocumentOrReadableStreamOrBodyInit::Document(ref doc)) => {
                 let data = Vec::from(serialize_document(&doc)?.as_ref());
                 let content_type = if doc.is_html_document() {
                     "text/html;charset=UTF-8"
@@ -577,17 +584,42 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
                 };
                 Some((data, Some(DOMString::from(content_type))))
             },
-            Some(DocumentOrBodyInit::Blob(ref b)) => Some(b.extract()),
-            Some(DocumentOrBodyInit::FormData(ref formdata)) => Some(formdata.extract()),
-            Some(DocumentOrBodyInit::String(ref str)) => Some(str.extract()),
-            Some(DocumentOrBodyInit::URLSearchParams(ref urlsp)) => Some(urlsp.extract()),
-            Some(DocumentOrBodyInit::ArrayBuffer(ref typedarray)) => {
+            Some(DocumentOrReadableStreamOrBodyInit::Blob(ref b)) => Some(b.extract()),
+            Some(DocumentOrReadableStreamOrBodyInit::FormData(ref formdata)) => {
+                Some(formdata.extract())
+            },
+            Some(DocumentOrReadableStreamOrBodyInit::String(ref str)) => Some(str.extract()),
+            Some(DocumentOrReadableStreamOrBodyInit::URLSearchParams(ref urlsp)) => {
+                Some(urlsp.extract())
+            },
+            Some(DocumentOrReadableStreamOrBodyInit::ArrayBuffer(ref typedarray)) => {
                 Some((typedarray.to_vec(), None))
             },
-            Some(DocumentOrBodyInit::ArrayBufferView(ref typedarray)) => {
+            Some(DocumentOrReadableStreamOrBodyInit::ArrayBufferView(ref typedarray)) => {
                 Some((typedarray.to_vec(), None))
             },
-            None => None,
+            Some(DocumentOrReadableStreamOrBodyInit::ReadableStream(_)) | None => None,
+        };
+
+        // https://fetch.spec.whatwg.org/#concept-bodyinit-extract Step 4, the chunks of a
+        // stream are read as the network requests them.
+        let body_stream = match data {
+            Some(DocumentOrReadableStreamOrBodyInit::ReadableStream(ref stream)) => {
+                if stream.is_locked() || stream.is_disturbed() {
+                    return Err(Error::Type(
+                        "The body stream is locked or disturbed".to_string(),
+                    ));
+                }
+                // The stream could not be read while the script thread waits for the
+                // response.
+                if self.sync.get() {
+                    return Err(Error::Type(
+                        "A synchronous request can't have a stream body".to_string(),
+                    ));
+                }
+                Some(transmit_body(stream)?)
+            },
+            _ => None,
         };
 
         self.request_body_len
@@ -599,7 +631,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         self.upload_complete.set(false);
         // Step 7
         self.upload_complete.set(match extracted_or_serialized {
-            None => true,
+            None => body_stream.is_none(),
             Some(ref e) if e.0.is_empty() => true,
             _ => false,
         });
@@ -660,13 +692,21 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
                     .map(|referrer_url| Referrer::ReferrerUrl(referrer_url)),
             )
             .referrer_policy(self.referrer_policy.clone())
-            .pipeline_id(Some(self.global().pipeline_id()));
+            .pipeline_id(Some(self.global().pipeline_id()))
+            .csp_list(self.global().get_csp_list())
+            .site_for_cookies(
+                self.global()
+                    .downcast::<Window>()
+                    .map(|window| window.Document().site_for_cookies()),
+            );
+        request.body_stream = body_stream;
 
         // step 4 (second half)
         match extracted_or_serialized {
             Some((_, ref content_type)) => {
                 let encoding = match data {
-                    Some(DocumentOrBodyInit::String(_)) | Some(DocumentOrBodyInit::Document(_)) =>
+                    Some(DocumentOrReadableStreamOrBodyInit::String(_)) |
+                    Some(DocumentOrReadableStreamOrBodyInit::Document(_)) =>
                     // XHR spec differs from http, and says UTF-8 should be in capitals,
                     // instead of "utf-8", which is what Hyper defaults to. So not
                     // using content types provided by Hyper.
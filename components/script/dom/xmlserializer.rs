@@ -2,16 +2,28 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLTemplateElementBinding::HTMLTemplateElementMethods;
 use crate::dom::bindings::codegen::Bindings::XMLSerializerBinding;
 use crate::dom::bindings::codegen::Bindings::XMLSerializerBinding::XMLSerializerMethods;
 use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::inheritance::{CharacterDataTypeId, NodeTypeId, TextTypeId};
 use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::characterdata::CharacterData;
+use crate::dom::document::Document;
+use crate::dom::documenttype::DocumentType;
+use crate::dom::element::Element;
+use crate::dom::htmltemplateelement::HTMLTemplateElement;
 use crate::dom::node::Node;
+use crate::dom::processinginstruction::ProcessingInstruction;
 use crate::dom::window::Window;
+use crate::dom::xpathparser::{is_name_char, is_name_start_char};
 use dom_struct::dom_struct;
-use xml5ever::serialize::{serialize, SerializeOpts, TraversalScope};
+use html5ever::Namespace;
+use std::collections::{HashMap, HashSet};
 
 #[dom_struct]
 pub struct XMLSerializer {
@@ -44,18 +56,477 @@ impl XMLSerializer {
 impl XMLSerializerMethods for XMLSerializer {
     // https://w3c.github.io/DOM-Parsing/#the-xmlserializer-interface
     fn SerializeToString(&self, root: &Node) -> Fallible<DOMString> {
-        let mut writer = vec![];
-        match serialize(
-            &mut writer,
-            &root,
-            SerializeOpts {
-                traversal_scope: TraversalScope::IncludeNode,
+        serialize(root, false)
+    }
+}
+
+/// Produce the XML serialization of a node.
+/// <https://w3c.github.io/DOM-Parsing/#dfn-xml-serialization>
+pub fn serialize(node: &Node, require_well_formed: bool) -> Fallible<DOMString> {
+    let mut serializer = Serializer::new(require_well_formed);
+    serializer.serialize_node(node, &ns!(), &NamespacePrefixMap::new())?;
+    Ok(DOMString::from(serializer.markup))
+}
+
+/// Produce the XML serialization of the children of an element, as done by the
+/// `innerHTML` getter of elements of XML documents.
+pub fn serialize_children(element: &Element, require_well_formed: bool) -> Fallible<DOMString> {
+    let mut serializer = Serializer::new(require_well_formed);
+    let mut map = NamespacePrefixMap::new();
+    map.record_namespace_information(element, &mut HashMap::new());
+    serializer.serialize_children(element.upcast(), element.namespace(), &map)?;
+    Ok(DOMString::from(serializer.markup))
+}
+
+/// A map from namespaces to the prefixes defined for them, in the order they were
+/// defined. The null namespace is the empty namespace.
+/// <https://w3c.github.io/DOM-Parsing/#dfn-namespace-prefix-map>
+#[derive(Clone)]
+struct NamespacePrefixMap(HashMap<Namespace, Vec<String>>);
+
+impl NamespacePrefixMap {
+    fn new() -> NamespacePrefixMap {
+        let mut map = NamespacePrefixMap(HashMap::new());
+        map.add("xml", ns!(xml));
+        map
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#dfn-retrieving-a-preferred-prefix-string>
+    fn preferred_prefix(&self, preferred: Option<&str>, namespace: &Namespace) -> Option<String> {
+        let candidates = self.0.get(namespace)?;
+        match preferred {
+            Some(preferred) if candidates.iter().any(|prefix| prefix == preferred) => {
+                Some(preferred.to_owned())
+            },
+            _ => candidates.last().cloned(),
+        }
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#dfn-found>
+    fn contains(&self, prefix: &str, namespace: &Namespace) -> bool {
+        self.0
+            .get(namespace)
+            .map_or(false, |candidates| candidates.iter().any(|p| p == prefix))
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#dfn-add>
+    fn add(&mut self, prefix: &str, namespace: Namespace) {
+        self.0
+            .entry(namespace)
+            .or_insert_with(Vec::new)
+            .push(prefix.to_owned());
+    }
+
+    /// Add the prefixes declared by the attributes of an element, returning the value
+    /// of its default namespace declaration, if any.
+    /// <https://w3c.github.io/DOM-Parsing/#recording-the-namespace-information>
+    fn record_namespace_information(
+        &mut self,
+        element: &Element,
+        local_prefixes: &mut HashMap<String, Namespace>,
+    ) -> Option<String> {
+        // Step 1.
+        let mut default_namespace = None;
+        // Step 2.
+        for attr in element.attrs().iter() {
+            if *attr.namespace() != ns!(xmlns) {
+                continue;
+            }
+            // Step 2.3.1.
+            if attr.prefix().is_none() {
+                default_namespace = Some(String::from(&**attr.value()));
+                continue;
+            }
+            // Steps 2.3.2.1-2.3.2.4.
+            let prefix = &**attr.local_name();
+            let namespace = Namespace::from(&**attr.value());
+            if namespace == ns!(xml) {
+                continue;
+            }
+            // Step 2.3.2.5.
+            if self.contains(prefix, &namespace) {
+                continue;
+            }
+            // Steps 2.3.2.6-2.3.2.7.
+            self.add(prefix, namespace.clone());
+            local_prefixes.insert(prefix.to_owned(), namespace);
+        }
+        // Step 3.
+        default_namespace
+    }
+}
+
+struct Serializer {
+    markup: String,
+    prefix_index: u32,
+    require_well_formed: bool,
+}
+
+impl Serializer {
+    fn new(require_well_formed: bool) -> Serializer {
+        Serializer {
+            markup: String::new(),
+            prefix_index: 1,
+            require_well_formed: require_well_formed,
+        }
+    }
+
+    /// Fail if the well-formed flag is set and a constraint is violated.
+    fn check(&self, well_formed: bool) -> Fallible<()> {
+        if self.require_well_formed && !well_formed {
+            return Err(Error::InvalidState);
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#dfn-xml-serialization-algorithm>
+    fn serialize_node(
+        &mut self,
+        node: &Node,
+        namespace: &Namespace,
+        map: &NamespacePrefixMap,
+    ) -> Fallible<()> {
+        match node.type_id() {
+            NodeTypeId::Element(_) => {
+                self.serialize_element(node.downcast::<Element>().unwrap(), namespace, map)
+            },
+            NodeTypeId::Document(_) => {
+                let document = node.downcast::<Document>().unwrap();
+                self.check(document.GetDocumentElement().is_some())?;
+                self.serialize_children(node, namespace, map)
+            },
+            NodeTypeId::DocumentFragment(_) => self.serialize_children(node, namespace, map),
+            NodeTypeId::DocumentType => {
+                self.serialize_doctype(node.downcast::<DocumentType>().unwrap())
+            },
+            NodeTypeId::CharacterData(CharacterDataTypeId::Text(TextTypeId::CDATASection)) => {
+                let data = node.downcast::<CharacterData>().unwrap().data();
+                self.check(!data.contains("]]>"))?;
+                self.markup.push_str("<![CDATA[");
+                self.markup.push_str(&data);
+                self.markup.push_str("]]>");
+                Ok(())
             },
-        ) {
-            Ok(_) => Ok(DOMString::from(String::from_utf8(writer).unwrap())),
-            Err(_) => Err(Error::Type(String::from(
-                "root must be a Node or an Attr object",
-            ))),
+            NodeTypeId::CharacterData(CharacterDataTypeId::Text(_)) => {
+                let data = node.downcast::<CharacterData>().unwrap().data();
+                self.check(data.chars().all(is_char))?;
+                escape(&data, false, &mut self.markup);
+                Ok(())
+            },
+            NodeTypeId::CharacterData(CharacterDataTypeId::Comment) => {
+                let data = node.downcast::<CharacterData>().unwrap().data();
+                self.check(
+                    data.chars().all(is_char) && !data.contains("--") && !data.ends_with('-'),
+                )?;
+                self.markup.push_str("<!--");
+                self.markup.push_str(&data);
+                self.markup.push_str("-->");
+                Ok(())
+            },
+            NodeTypeId::CharacterData(CharacterDataTypeId::ProcessingInstruction) => {
+                let target = node.downcast::<ProcessingInstruction>().unwrap().target();
+                let data = node.downcast::<CharacterData>().unwrap().data();
+                self.check(
+                    !target.contains(':') &&
+                        !target.eq_ignore_ascii_case("xml") &&
+                        data.chars().all(is_char) &&
+                        !data.contains("?>"),
+                )?;
+                self.markup.push_str("<?");
+                self.markup.push_str(target);
+                self.markup.push(' ');
+                self.markup.push_str(&data);
+                self.markup.push_str("?>");
+                Ok(())
+            },
+            NodeTypeId::Attr => Ok(()),
+        }
+    }
+
+    fn serialize_children(
+        &mut self,
+        node: &Node,
+        namespace: &Namespace,
+        map: &NamespacePrefixMap,
+    ) -> Fallible<()> {
+        let node = match node.downcast::<HTMLTemplateElement>() {
+            Some(template) => DomRoot::upcast(template.Content()),
+            None => DomRoot::from_ref(node),
+        };
+        for child in node.children() {
+            self.serialize_node(&child, namespace, map)?;
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#xml-serializing-a-documenttype-node>
+    fn serialize_doctype(&mut self, doctype: &DocumentType) -> Fallible<()> {
+        let public_id = doctype.public_id();
+        let system_id = doctype.system_id();
+        // Steps 1-2.
+        self.check(public_id.chars().all(is_pubid_char))?;
+        self.check(
+            system_id.chars().all(is_char) &&
+                !(system_id.contains('"') && system_id.contains('\'')),
+        )?;
+        // Steps 3-10.
+        self.markup.push_str("<!DOCTYPE ");
+        self.markup.push_str(doctype.name());
+        if !public_id.is_empty() {
+            self.markup.push_str(" PUBLIC \"");
+            self.markup.push_str(public_id);
+            self.markup.push('"');
+        }
+        if !system_id.is_empty() {
+            if public_id.is_empty() {
+                self.markup.push_str(" SYSTEM");
+            }
+            self.markup.push_str(" \"");
+            self.markup.push_str(system_id);
+            self.markup.push('"');
+        }
+        self.markup.push('>');
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#xml-serializing-an-element-node>
+    fn serialize_element(
+        &mut self,
+        element: &Element,
+        namespace: &Namespace,
+        map: &NamespacePrefixMap,
+    ) -> Fallible<()> {
+        let local_name = &**element.local_name();
+        // Step 1.
+        self.check(is_ncname(local_name))?;
+        // Steps 2-5.
+        self.markup.push('<');
+        let qualified_name;
+        let mut ignore_namespace_definition_attribute = false;
+        // Steps 6-8.
+        let mut map = map.clone();
+        let mut local_prefixes = HashMap::new();
+        let local_default_namespace =
+            map.record_namespace_information(element, &mut local_prefixes);
+        // Steps 9-10.
+        let mut inherited_namespace = namespace.clone();
+        let element_namespace = element.namespace();
+        if inherited_namespace == *element_namespace {
+            // Step 11.
+            if local_default_namespace.is_some() {
+                ignore_namespace_definition_attribute = true;
+            }
+            qualified_name = if *element_namespace == ns!(xml) {
+                format!("xml:{}", local_name)
+            } else {
+                local_name.to_owned()
+            };
+            self.markup.push_str(&qualified_name);
+        } else {
+            // Step 12.
+            let mut prefix = element.prefix().as_ref().map(|prefix| prefix.to_string());
+            let mut candidate_prefix = map.preferred_prefix(prefix.as_deref(), element_namespace);
+            if prefix.as_deref() == Some("xmlns") {
+                self.check(false)?;
+                candidate_prefix = prefix.clone();
+            }
+            if let Some(candidate_prefix) = candidate_prefix {
+                // Step 12.4.
+                qualified_name = format!("{}:{}", candidate_prefix, local_name);
+                if let Some(ref local_default_namespace) = local_default_namespace {
+                    if Namespace::from(&**local_default_namespace) != ns!(xml) {
+                        inherited_namespace = Namespace::from(&**local_default_namespace);
+                    }
+                }
+                self.markup.push_str(&qualified_name);
+            } else if let Some(mut prefix) = prefix.take() {
+                // Step 12.5.
+                if local_prefixes.contains_key(&prefix) {
+                    prefix = self.generate_prefix(&mut map, element_namespace);
+                } else {
+                    map.add(&prefix, element_namespace.clone());
+                }
+                qualified_name = format!("{}:{}", prefix, local_name);
+                self.markup.push_str(&qualified_name);
+                self.markup.push_str(" xmlns:");
+                self.markup.push_str(&prefix);
+                self.markup.push_str("=\"");
+                self.serialize_attribute_value(element_namespace)?;
+                self.markup.push('"');
+                if let Some(ref local_default_namespace) = local_default_namespace {
+                    inherited_namespace = Namespace::from(&**local_default_namespace);
+                }
+            } else if local_default_namespace.map_or(true, |local_default_namespace| {
+                Namespace::from(local_default_namespace) != *element_namespace
+            }) {
+                // Step 12.6.
+                ignore_namespace_definition_attribute = true;
+                qualified_name = local_name.to_owned();
+                inherited_namespace = element_namespace.clone();
+                self.markup.push_str(&qualified_name);
+                self.markup.push_str(" xmlns=\"");
+                self.serialize_attribute_value(element_namespace)?;
+                self.markup.push('"');
+            } else {
+                // Step 12.7.
+                qualified_name = local_name.to_owned();
+                inherited_namespace = element_namespace.clone();
+                self.markup.push_str(&qualified_name);
+            }
+        }
+        // Step 13.
+        self.serialize_attributes(
+            element,
+            &mut map,
+            &local_prefixes,
+            ignore_namespace_definition_attribute,
+        )?;
+        // Steps 14-17.
+        let node = element.upcast::<Node>();
+        if node.children_count() == 0 {
+            if *element_namespace != ns!(html) {
+                self.markup.push_str("/>");
+                return Ok(());
+            }
+            if element.is_void() {
+                self.markup.push_str(" />");
+                return Ok(());
+            }
+        }
+        self.markup.push('>');
+        // Steps 18-19.
+        self.serialize_children(node, &inherited_namespace, &map)?;
+        // Step 20.
+        self.markup.push_str("</");
+        self.markup.push_str(&qualified_name);
+        self.markup.push('>');
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#dfn-xml-serialization-of-the-attributes>
+    fn serialize_attributes(
+        &mut self,
+        element: &Element,
+        map: &mut NamespacePrefixMap,
+        local_prefixes: &HashMap<String, Namespace>,
+        ignore_namespace_definition_attribute: bool,
+    ) -> Fallible<()> {
+        // Step 2.
+        let mut local_names = HashSet::new();
+        // Step 3.
+        for attr in element.attrs().iter() {
+            let attr_namespace = attr.namespace();
+            let local_name = &**attr.local_name();
+            let value = attr.value();
+            // Steps 3.1-3.2.
+            self.check(local_names.insert((attr_namespace.clone(), local_name.to_owned())))?;
+            // Steps 3.3-3.4.
+            let mut candidate_prefix = None;
+            // Step 3.5.
+            if *attr_namespace != ns!() {
+                let prefix = attr.prefix().map(|prefix| &**prefix);
+                candidate_prefix = map.preferred_prefix(prefix, attr_namespace);
+                if *attr_namespace == ns!(xmlns) {
+                    // Step 3.5.2.1.
+                    let redefines_local_prefix = prefix.is_some() &&
+                        local_prefixes
+                            .get(local_name)
+                            .map_or(true, |namespace| **namespace != **value) &&
+                        map.contains(local_name, &Namespace::from(&**value));
+                    if Namespace::from(&**value) == ns!(xml) ||
+                        (prefix.is_none() && ignore_namespace_definition_attribute) ||
+                        redefines_local_prefix
+                    {
+                        continue;
+                    }
+                    // Steps 3.5.2.2-3.5.2.3.
+                    self.check(Namespace::from(&**value) != ns!(xmlns))?;
+                    self.check(prefix.is_none() || !value.is_empty())?;
+                    // Step 3.5.2.4.
+                    if prefix == Some("xmlns") {
+                        candidate_prefix = Some("xmlns".to_owned());
+                    }
+                } else if candidate_prefix.is_none() {
+                    // Step 3.5.3.
+                    let prefix = self.generate_prefix(map, attr_namespace);
+                    self.markup.push_str(" xmlns:");
+                    self.markup.push_str(&prefix);
+                    self.markup.push_str("=\"");
+                    self.serialize_attribute_value(attr_namespace)?;
+                    self.markup.push('"');
+                    candidate_prefix = Some(prefix);
+                }
+            }
+            // Steps 3.6-3.7.
+            self.markup.push(' ');
+            if let Some(candidate_prefix) = candidate_prefix {
+                self.markup.push_str(&candidate_prefix);
+                self.markup.push(':');
+            }
+            // Step 3.8.
+            self.check(
+                is_ncname(local_name) && (local_name != "xmlns" || *attr_namespace != ns!()),
+            )?;
+            // Step 3.9.
+            self.markup.push_str(local_name);
+            self.markup.push_str("=\"");
+            self.serialize_attribute_value(&**value)?;
+            self.markup.push('"');
         }
+        Ok(())
     }
+
+    /// <https://w3c.github.io/DOM-Parsing/#dfn-generating-a-prefix>
+    fn generate_prefix(&mut self, map: &mut NamespacePrefixMap, namespace: &Namespace) -> String {
+        let prefix = format!("ns{}", self.prefix_index);
+        self.prefix_index += 1;
+        map.add(&prefix, namespace.clone());
+        prefix
+    }
+
+    /// <https://w3c.github.io/DOM-Parsing/#dfn-serializing-an-attribute-value>
+    fn serialize_attribute_value(&mut self, value: &str) -> Fallible<()> {
+        self.check(value.chars().all(is_char))?;
+        escape(value, true, &mut self.markup);
+        Ok(())
+    }
+}
+
+fn escape(text: &str, attr_mode: bool, markup: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => markup.push_str("&amp;"),
+            '<' => markup.push_str("&lt;"),
+            '>' => markup.push_str("&gt;"),
+            '"' if attr_mode => markup.push_str("&quot;"),
+            // Keep whitespace from being normalized when the value is parsed again.
+            '\t' if attr_mode => markup.push_str("&#x9;"),
+            '\n' if attr_mode => markup.push_str("&#xA;"),
+            '\r' if attr_mode => markup.push_str("&#xD;"),
+            c => markup.push(c),
+        }
+    }
+}
+
+/// <https://www.w3.org/TR/xml/#NT-Char>
+fn is_char(c: char) -> bool {
+    match c {
+        '\t' | '\n' | '\r' => true,
+        '\u{FFFE}' | '\u{FFFF}' => false,
+        c => c >= ' ',
+    }
+}
+
+/// <https://www.w3.org/TR/xml/#NT-PubidChar>
+fn is_pubid_char(c: char) -> bool {
+    match c {
+        ' ' | '\r' | '\n' | 'a'..='z' | 'A'..='Z' | '0'..='9' => true,
+        _ => "-'()+,./:=?;!*#@$_%".contains(c),
+    }
+}
+
+/// <https://www.w3.org/TR/xml-names/#NT-NCName>
+fn is_ncname(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().map_or(false, is_name_start_char) && chars.all(is_name_char)
 }
@@ -0,0 +1,740 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The evaluation of XPath 1.0 expressions over the DOM.
+//! <https://www.w3.org/TR/1999/REC-xpath-19991116/>
+//!
+//! Documents are mapped to the data model of XPath as browsers do: attributes are
+//! the `Attr` nodes of an element, except namespace declarations, document types are
+//! not part of the tree, and there are no namespace nodes.
+
+use crate::dom::attr::Attr;
+use crate::dom::bindings::codegen::Bindings::NodeBinding::{NodeConstants, NodeMethods};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::characterdata::CharacterData;
+use crate::dom::comment::Comment;
+use crate::dom::documenttype::DocumentType;
+use crate::dom::element::Element;
+use crate::dom::node::{Node, ShadowIncluding};
+use crate::dom::processinginstruction::ProcessingInstruction;
+use crate::dom::text::Text;
+use crate::dom::xpathparser::{is_xpath_whitespace, Axis, BinaryOperator, CoreFunction};
+use crate::dom::xpathparser::{Expr, NodeTest, PathStart, Step};
+use servo_atoms::Atom;
+use std::cmp::Ordering;
+
+/// The value of an expression.
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#section-Introduction>
+pub enum Value {
+    /// The nodes of a node-set, in document order.
+    NodeSet(Vec<DomRoot<Node>>),
+    Boolean(bool),
+    Number(f64),
+    String(String),
+}
+
+impl Value {
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#function-boolean>
+    pub fn boolean(&self) -> bool {
+        match *self {
+            Value::NodeSet(ref nodes) => !nodes.is_empty(),
+            Value::Boolean(boolean) => boolean,
+            Value::Number(number) => number != 0.0 && !number.is_nan(),
+            Value::String(ref string) => !string.is_empty(),
+        }
+    }
+
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#function-number>
+    pub fn number(&self) -> f64 {
+        match *self {
+            Value::Boolean(boolean) => {
+                if boolean {
+                    1.0
+                } else {
+                    0.0
+                }
+            },
+            Value::Number(number) => number,
+            _ => string_to_number(&self.string()),
+        }
+    }
+
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#function-string>
+    pub fn string(&self) -> String {
+        match *self {
+            Value::NodeSet(ref nodes) => nodes
+                .first()
+                .map_or(String::new(), |node| string_value(node)),
+            Value::Boolean(boolean) => boolean.to_string(),
+            Value::Number(number) => number_to_string(number),
+            Value::String(ref string) => string.clone(),
+        }
+    }
+
+    fn into_node_set(self) -> Fallible<Vec<DomRoot<Node>>> {
+        match self {
+            Value::NodeSet(nodes) => Ok(nodes),
+            _ => Err(Error::Type("The value is not a node-set.".to_owned())),
+        }
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#function-number>
+fn string_to_number(string: &str) -> f64 {
+    let string = string.trim_matches(is_xpath_whitespace);
+    let digits = if string.starts_with('-') {
+        &string[1..]
+    } else {
+        string
+    };
+    let mut parts = digits.splitn(2, '.');
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    let is_number = !(integer.is_empty() && fraction.is_empty()) &&
+        integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit());
+    if !is_number {
+        return ::std::f64::NAN;
+    }
+    string.parse().unwrap_or(::std::f64::NAN)
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#function-string>
+fn number_to_string(number: f64) -> String {
+    if number.is_nan() {
+        "NaN".to_owned()
+    } else if number == 0.0 {
+        "0".to_owned()
+    } else if number.is_infinite() {
+        if number > 0.0 {
+            "Infinity".to_owned()
+        } else {
+            "-Infinity".to_owned()
+        }
+    } else {
+        // Floats are formatted without an exponent, and integers without a decimal point.
+        number.to_string()
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#function-round>
+fn round(number: f64) -> f64 {
+    if number.is_nan() || number.is_infinite() {
+        number
+    } else if number >= -0.5 && number < 0.0 {
+        -0.0
+    } else {
+        (number + 0.5).floor()
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#dt-string-value>
+pub fn string_value(node: &Node) -> String {
+    if let Some(attr) = node.downcast::<Attr>() {
+        return String::from(&**attr.value());
+    }
+    if let Some(data) = node.downcast::<CharacterData>() {
+        return String::from(&**data.data());
+    }
+    String::from(node.descendant_text_content())
+}
+
+/// The element of an attribute, or the node itself.
+fn element_or_self(node: &Node) -> DomRoot<Node> {
+    match node.downcast::<Attr>().and_then(|attr| attr.owner()) {
+        Some(element) => DomRoot::upcast(element),
+        None => DomRoot::from_ref(node),
+    }
+}
+
+/// The root of the tree of a node, where the root of an attribute is that of its
+/// element.
+fn root_of(node: &Node) -> DomRoot<Node> {
+    element_or_self(node)
+        .inclusive_ancestors(ShadowIncluding::No)
+        .last()
+        .unwrap()
+}
+
+/// The parent of a node, where the parent of an attribute is its element.
+fn parent_of(node: &Node) -> Option<DomRoot<Node>> {
+    match node.downcast::<Attr>() {
+        Some(attr) => attr.owner().map(DomRoot::upcast),
+        None => node.GetParentNode(),
+    }
+}
+
+fn attributes_of(node: &Node) -> Vec<DomRoot<Node>> {
+    match node.downcast::<Element>() {
+        Some(element) => element
+            .attrs()
+            .iter()
+            .filter(|attr| *attr.namespace() != ns!(xmlns))
+            .map(|attr| DomRoot::from_ref(attr.upcast::<Node>()))
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// The descendants of a node, in document order.
+fn descendants_of(node: &Node) -> impl Iterator<Item = DomRoot<Node>> {
+    node.traverse_preorder(ShadowIncluding::No).skip(1)
+}
+
+/// The nodes of an axis from a node, in the order of the axis. Attributes have no
+/// children nor siblings.
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#axes>
+fn axis_nodes(axis: Axis, node: &Node) -> Vec<DomRoot<Node>> {
+    match axis {
+        Axis::Child => node.children().collect(),
+        Axis::Descendant => descendants_of(node).collect(),
+        Axis::DescendantOrSelf => node.traverse_preorder(ShadowIncluding::No).collect(),
+        Axis::Parent => parent_of(node).into_iter().collect(),
+        Axis::Ancestor => ancestors_of(node),
+        Axis::AncestorOrSelf => {
+            let mut nodes = vec![DomRoot::from_ref(node)];
+            nodes.extend(ancestors_of(node));
+            nodes
+        },
+        Axis::FollowingSibling => node.following_siblings().collect(),
+        Axis::PrecedingSibling => node.preceding_siblings().collect(),
+        Axis::Following => {
+            // The nodes following an attribute include the descendants of its element.
+            let start = element_or_self(node);
+            let mut nodes = vec![];
+            if node.is::<Attr>() {
+                nodes.extend(descendants_of(&start));
+            }
+            for ancestor in start.inclusive_ancestors(ShadowIncluding::No) {
+                for sibling in ancestor.following_siblings() {
+                    nodes.extend(sibling.traverse_preorder(ShadowIncluding::No));
+                }
+            }
+            nodes
+        },
+        Axis::Preceding => {
+            let start = element_or_self(node);
+            let mut nodes = vec![];
+            for ancestor in start.inclusive_ancestors(ShadowIncluding::No) {
+                for sibling in ancestor.preceding_siblings() {
+                    let subtree: Vec<_> = sibling.traverse_preorder(ShadowIncluding::No).collect();
+                    nodes.extend(subtree.into_iter().rev());
+                }
+            }
+            nodes
+        },
+        Axis::Attribute => attributes_of(node),
+        Axis::Namespace => vec![],
+        Axis::Self_ => vec![DomRoot::from_ref(node)],
+    }
+}
+
+fn ancestors_of(node: &Node) -> Vec<DomRoot<Node>> {
+    match parent_of(node) {
+        Some(parent) => parent.inclusive_ancestors(ShadowIncluding::No).collect(),
+        None => vec![],
+    }
+}
+
+/// Sort nodes in document order, and remove duplicates. The order of the nodes of
+/// different trees is implementation-specific, but consistent.
+fn sort_in_document_order(nodes: &mut Vec<DomRoot<Node>>) {
+    nodes.sort_by(|a, b| compare_in_document_order(a, b));
+    nodes.dedup();
+}
+
+fn compare_in_document_order(a: &Node, b: &Node) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    // The attributes of an element follow it, which `CompareDocumentPosition` gets
+    // backwards.
+    if parent_of(b).map_or(false, |parent| b.is::<Attr>() && *parent == *a) {
+        return Ordering::Less;
+    }
+    if parent_of(a).map_or(false, |parent| a.is::<Attr>() && *parent == *b) {
+        return Ordering::Greater;
+    }
+    if a.CompareDocumentPosition(b) & NodeConstants::DOCUMENT_POSITION_FOLLOWING != 0 {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+/// The context of the evaluation of an expression.
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#section-Introduction>
+struct Context<'a> {
+    node: DomRoot<Node>,
+    position: usize,
+    size: usize,
+    /// The namespaces of the prefixes of the expression.
+    namespaces: &'a [(String, String)],
+}
+
+impl<'a> Context<'a> {
+    fn with_node(&self, node: DomRoot<Node>, position: usize, size: usize) -> Context<'a> {
+        Context {
+            node,
+            position,
+            size,
+            namespaces: self.namespaces,
+        }
+    }
+}
+
+/// Evaluate an expression with the given context node. The namespaces of all the
+/// prefixes of the expression must be given.
+pub fn evaluate(expr: &Expr, node: &Node, namespaces: &[(String, String)]) -> Fallible<Value> {
+    let context = Context {
+        node: DomRoot::from_ref(node),
+        position: 1,
+        size: 1,
+        namespaces,
+    };
+    evaluate_expr(expr, &context)
+}
+
+fn evaluate_expr(expr: &Expr, context: &Context) -> Fallible<Value> {
+    match *expr {
+        Expr::Binary(ref left, operator, ref right) => {
+            evaluate_binary(left, operator, right, context)
+        },
+        Expr::Negate(ref expr) => Ok(Value::Number(-evaluate_expr(expr, context)?.number())),
+        Expr::Literal(ref literal) => Ok(Value::String(literal.clone())),
+        Expr::Number(number) => Ok(Value::Number(number)),
+        Expr::Function(function, ref args) => evaluate_function(function, args, context),
+        Expr::Filter(ref expr, ref predicates) => {
+            let nodes = evaluate_expr(expr, context)?.into_node_set()?;
+            Ok(Value::NodeSet(filter(nodes, predicates, context)?))
+        },
+        Expr::Path(ref start, ref steps) => {
+            let mut nodes = match *start {
+                PathStart::Root => vec![root_of(&context.node)],
+                PathStart::ContextNode => vec![context.node.clone()],
+                PathStart::Filter(ref expr) => evaluate_expr(expr, context)?.into_node_set()?,
+            };
+            for step in steps {
+                nodes = evaluate_step(step, nodes, context)?;
+            }
+            Ok(Value::NodeSet(nodes))
+        },
+    }
+}
+
+fn evaluate_binary(
+    left: &Expr,
+    operator: BinaryOperator,
+    right: &Expr,
+    context: &Context,
+) -> Fallible<Value> {
+    let left = evaluate_expr(left, context)?;
+    // The right operands of `and` and `or` are not evaluated if the left ones decide
+    // the result.
+    match operator {
+        BinaryOperator::Or if left.boolean() => return Ok(Value::Boolean(true)),
+        BinaryOperator::And if !left.boolean() => return Ok(Value::Boolean(false)),
+        _ => {},
+    }
+    let right = evaluate_expr(right, context)?;
+    Ok(match operator {
+        BinaryOperator::Or | BinaryOperator::And => Value::Boolean(right.boolean()),
+        BinaryOperator::Equal |
+        BinaryOperator::NotEqual |
+        BinaryOperator::Less |
+        BinaryOperator::LessOrEqual |
+        BinaryOperator::Greater |
+        BinaryOperator::GreaterOrEqual => Value::Boolean(compare(operator, &left, &right)),
+        BinaryOperator::Add => Value::Number(left.number() + right.number()),
+        BinaryOperator::Subtract => Value::Number(left.number() - right.number()),
+        BinaryOperator::Multiply => Value::Number(left.number() * right.number()),
+        BinaryOperator::Divide => Value::Number(left.number() / right.number()),
+        BinaryOperator::Modulo => Value::Number(left.number() % right.number()),
+        BinaryOperator::Union => {
+            let mut nodes = left.into_node_set()?;
+            nodes.extend(right.into_node_set()?);
+            sort_in_document_order(&mut nodes);
+            Value::NodeSet(nodes)
+        },
+    })
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#booleans>
+fn compare(operator: BinaryOperator, left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (&Value::NodeSet(ref left), &Value::NodeSet(ref right)) => {
+            let right: Vec<_> = right
+                .iter()
+                .map(|node| Value::String(string_value(node)))
+                .collect();
+            left.iter().any(|node| {
+                let left = Value::String(string_value(node));
+                right
+                    .iter()
+                    .any(|right| compare_values(operator, &left, right))
+            })
+        },
+        (&Value::NodeSet(_), &Value::Boolean(_)) | (&Value::Boolean(_), &Value::NodeSet(_)) => {
+            compare_values(
+                operator,
+                &Value::Boolean(left.boolean()),
+                &Value::Boolean(right.boolean()),
+            )
+        },
+        (&Value::NodeSet(ref left), right) => left
+            .iter()
+            .any(|node| compare_values(operator, &Value::String(string_value(node)), right)),
+        (left, &Value::NodeSet(ref right)) => right
+            .iter()
+            .any(|node| compare_values(operator, left, &Value::String(string_value(node)))),
+        (left, right) => compare_values(operator, left, right),
+    }
+}
+
+/// Compare two values which are not node-sets.
+fn compare_values(operator: BinaryOperator, left: &Value, right: &Value) -> bool {
+    match operator {
+        BinaryOperator::Equal | BinaryOperator::NotEqual => {
+            let equal = match (left, right) {
+                (&Value::Boolean(_), _) | (_, &Value::Boolean(_)) => {
+                    left.boolean() == right.boolean()
+                },
+                (&Value::Number(_), _) | (_, &Value::Number(_)) => left.number() == right.number(),
+                _ => left.string() == right.string(),
+            };
+            equal == (operator == BinaryOperator::Equal)
+        },
+        BinaryOperator::Less => left.number() < right.number(),
+        BinaryOperator::LessOrEqual => left.number() <= right.number(),
+        BinaryOperator::Greater => left.number() > right.number(),
+        BinaryOperator::GreaterOrEqual => left.number() >= right.number(),
+        _ => unreachable!(),
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#location-paths>
+fn evaluate_step(
+    step: &Step,
+    nodes: Vec<DomRoot<Node>>,
+    context: &Context,
+) -> Fallible<Vec<DomRoot<Node>>> {
+    let mut result = vec![];
+    let several = nodes.len() > 1;
+    for node in nodes {
+        let candidates: Vec<_> = axis_nodes(step.axis, &node)
+            .into_iter()
+            .filter(|candidate| matches_test(&step.test, step.axis, candidate, context))
+            .collect();
+        // Predicates are applied to the nodes in the order of the axis.
+        result.extend(filter(candidates, &step.predicates, context)?);
+    }
+    if several {
+        sort_in_document_order(&mut result);
+    } else if step.axis.is_reverse() {
+        result.reverse();
+    }
+    Ok(result)
+}
+
+/// Filter nodes by predicates, where the position of a node is its index in `nodes`.
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#predicates>
+fn filter(
+    mut nodes: Vec<DomRoot<Node>>,
+    predicates: &[Expr],
+    context: &Context,
+) -> Fallible<Vec<DomRoot<Node>>> {
+    for predicate in predicates {
+        let size = nodes.len();
+        let mut filtered = vec![];
+        for (index, node) in nodes.into_iter().enumerate() {
+            let position = index + 1;
+            let value = evaluate_expr(predicate, &context.with_node(node.clone(), position, size))?;
+            let matches = match value {
+                Value::Number(number) => number == position as f64,
+                value => value.boolean(),
+            };
+            if matches {
+                filtered.push(node);
+            }
+        }
+        nodes = filtered;
+    }
+    Ok(nodes)
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#node-tests>
+fn matches_test(test: &NodeTest, axis: Axis, node: &Node, context: &Context) -> bool {
+    match *test {
+        NodeTest::Name {
+            ref prefix,
+            ref local_name,
+        } => {
+            let namespace = match *prefix {
+                Some(ref prefix) => {
+                    match context.namespaces.iter().find(|&&(ref p, _)| p == prefix) {
+                        Some(&(_, ref namespace)) => Some(&**namespace),
+                        None => return false,
+                    }
+                },
+                None => None,
+            };
+            let local_name = local_name.as_ref().map(|name| &**name);
+            if axis == Axis::Attribute {
+                node.downcast::<Attr>()
+                    .map_or(false, |attr| matches_attr_name(attr, namespace, local_name))
+            } else {
+                node.downcast::<Element>().map_or(false, |element| {
+                    matches_element_name(element, namespace, local_name)
+                })
+            }
+        },
+        NodeTest::Node => !node.is::<DocumentType>(),
+        NodeTest::Text => node.is::<Text>(),
+        NodeTest::Comment => node.is::<Comment>(),
+        NodeTest::ProcessingInstruction(ref target) => node
+            .downcast::<ProcessingInstruction>()
+            .map_or(false, |pi| {
+                target
+                    .as_ref()
+                    .map_or(true, |target| **pi.target() == **target)
+            }),
+    }
+}
+
+/// Unprefixed names match HTML elements of HTML documents ASCII case-insensitively,
+/// and only those elements.
+/// <https://html.spec.whatwg.org/multipage/#interactions-with-xpath-and-xslt>
+fn matches_element_name(
+    element: &Element,
+    namespace: Option<&str>,
+    local_name: Option<&str>,
+) -> bool {
+    let is_html_document = element.upcast::<Node>().owner_doc().is_html_document();
+    match (namespace, local_name) {
+        (None, None) => true,
+        (Some(namespace), None) => **element.namespace() == *namespace,
+        (Some(namespace), Some(local_name)) => {
+            **element.namespace() == *namespace && **element.local_name() == *local_name
+        },
+        (None, Some(local_name)) if is_html_document => {
+            element.is_html_element() && **element.local_name() == *local_name.to_ascii_lowercase()
+        },
+        (None, Some(local_name)) => {
+            *element.namespace() == ns!() && **element.local_name() == *local_name
+        },
+    }
+}
+
+/// Unprefixed names match the attributes of HTML elements of HTML documents ASCII
+/// case-insensitively.
+fn matches_attr_name(attr: &Attr, namespace: Option<&str>, local_name: Option<&str>) -> bool {
+    match (namespace, local_name) {
+        (None, None) => true,
+        (Some(namespace), None) => **attr.namespace() == *namespace,
+        (Some(namespace), Some(local_name)) => {
+            **attr.namespace() == *namespace && **attr.local_name() == *local_name
+        },
+        (None, Some(local_name)) => {
+            let lowercase = attr
+                .owner()
+                .map_or(false, |element| element.html_element_in_html_document());
+            *attr.namespace() == ns!() &&
+                if lowercase {
+                    **attr.local_name() == *local_name.to_ascii_lowercase()
+                } else {
+                    **attr.local_name() == *local_name
+                }
+        },
+    }
+}
+
+/// The first node of a node-set argument, or the context node if there is none.
+fn node_argument(args: &[Expr], context: &Context) -> Fallible<Option<DomRoot<Node>>> {
+    match args.first() {
+        Some(arg) => Ok(evaluate_expr(arg, context)?
+            .into_node_set()?
+            .into_iter()
+            .next()),
+        None => Ok(Some(context.node.clone())),
+    }
+}
+
+/// The string value of an argument, or of the context node if there is none.
+fn string_argument(args: &[Expr], context: &Context) -> Fallible<String> {
+    match args.first() {
+        Some(arg) => Ok(evaluate_expr(arg, context)?.string()),
+        None => Ok(string_value(&context.node)),
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#corelib>
+fn evaluate_function(function: CoreFunction, args: &[Expr], context: &Context) -> Fallible<Value> {
+    let arg = |index: usize| evaluate_expr(&args[index], context);
+    Ok(match function {
+        CoreFunction::Last => Value::Number(context.size as f64),
+        CoreFunction::Position => Value::Number(context.position as f64),
+        CoreFunction::Count => Value::Number(arg(0)?.into_node_set()?.len() as f64),
+        CoreFunction::Id => {
+            let ids: Vec<String> = match arg(0)? {
+                Value::NodeSet(nodes) => nodes.iter().map(|node| string_value(node)).collect(),
+                value => vec![value.string()],
+            };
+            let root = root_of(&context.node);
+            let mut nodes = vec![];
+            for id in ids.iter().flat_map(|ids| ids.split(is_xpath_whitespace)) {
+                if id.is_empty() {
+                    continue;
+                }
+                let id = Atom::from(id);
+                let element = root
+                    .traverse_preorder(ShadowIncluding::No)
+                    .filter_map(DomRoot::downcast::<Element>)
+                    .find(|element| element.get_id().as_ref() == Some(&id));
+                nodes.extend(element.map(DomRoot::upcast));
+            }
+            sort_in_document_order(&mut nodes);
+            Value::NodeSet(nodes)
+        },
+        CoreFunction::LocalName | CoreFunction::NamespaceUri | CoreFunction::Name => {
+            let node = match node_argument(args, context)? {
+                Some(node) => node,
+                None => return Ok(Value::String(String::new())),
+            };
+            let name = if let Some(element) = node.downcast::<Element>() {
+                match function {
+                    CoreFunction::LocalName => String::from(&**element.local_name()),
+                    CoreFunction::NamespaceUri => String::from(&**element.namespace()),
+                    _ => match *element.prefix() {
+                        Some(ref prefix) => format!("{}:{}", &**prefix, &**element.local_name()),
+                        None => String::from(&**element.local_name()),
+                    },
+                }
+            } else if let Some(attr) = node.downcast::<Attr>() {
+                match function {
+                    CoreFunction::LocalName => String::from(&**attr.local_name()),
+                    CoreFunction::NamespaceUri => String::from(&**attr.namespace()),
+                    _ => String::from(&**attr.name()),
+                }
+            } else if let Some(pi) = node.downcast::<ProcessingInstruction>() {
+                match function {
+                    CoreFunction::NamespaceUri => String::new(),
+                    _ => String::from(pi.target().clone()),
+                }
+            } else {
+                String::new()
+            };
+            Value::String(name)
+        },
+        CoreFunction::String => Value::String(string_argument(args, context)?),
+        CoreFunction::Concat => {
+            let mut string = String::new();
+            for arg in args {
+                string.push_str(&evaluate_expr(arg, context)?.string());
+            }
+            Value::String(string)
+        },
+        CoreFunction::StartsWith => {
+            Value::Boolean(arg(0)?.string().starts_with(&*arg(1)?.string()))
+        },
+        CoreFunction::Contains => Value::Boolean(arg(0)?.string().contains(&*arg(1)?.string())),
+        CoreFunction::SubstringBefore => {
+            let string = arg(0)?.string();
+            let pattern = arg(1)?.string();
+            Value::String(
+                string
+                    .find(&*pattern)
+                    .map_or(String::new(), |index| string[..index].to_owned()),
+            )
+        },
+        CoreFunction::SubstringAfter => {
+            let string = arg(0)?.string();
+            let pattern = arg(1)?.string();
+            Value::String(string.find(&*pattern).map_or(String::new(), |index| {
+                string[index + pattern.len()..].to_owned()
+            }))
+        },
+        CoreFunction::Substring => {
+            let string = arg(0)?.string();
+            let start = round(arg(1)?.number());
+            let end = match args.get(2) {
+                Some(length) => start + round(evaluate_expr(length, context)?.number()),
+                None => ::std::f64::INFINITY,
+            };
+            // Comparisons with NaN are false, so no characters are included then.
+            Value::String(
+                string
+                    .chars()
+                    .enumerate()
+                    .filter(|&(index, _)| {
+                        let position = (index + 1) as f64;
+                        position >= start && position < end
+                    })
+                    .map(|(_, c)| c)
+                    .collect(),
+            )
+        },
+        CoreFunction::StringLength => {
+            Value::Number(string_argument(args, context)?.chars().count() as f64)
+        },
+        CoreFunction::NormalizeSpace => {
+            let string = string_argument(args, context)?;
+            let words: Vec<_> = string
+                .split(is_xpath_whitespace)
+                .filter(|word| !word.is_empty())
+                .collect();
+            Value::String(words.join(" "))
+        },
+        CoreFunction::Translate => {
+            let string = arg(0)?.string();
+            let from: Vec<char> = arg(1)?.string().chars().collect();
+            let to: Vec<char> = arg(2)?.string().chars().collect();
+            // Only the first occurrence of a character in `from` is used, and characters
+            // without a counterpart in `to` are removed.
+            Value::String(
+                string
+                    .chars()
+                    .filter_map(|c| match from.iter().position(|&from| from == c) {
+                        Some(index) => to.get(index).cloned(),
+                        None => Some(c),
+                    })
+                    .collect(),
+            )
+        },
+        CoreFunction::Boolean => Value::Boolean(arg(0)?.boolean()),
+        CoreFunction::Not => Value::Boolean(!arg(0)?.boolean()),
+        CoreFunction::True => Value::Boolean(true),
+        CoreFunction::False => Value::Boolean(false),
+        CoreFunction::Lang => {
+            let lang = arg(0)?.string().to_lowercase();
+            // Languages are compared caseless, which is approximated by lowercasing.
+            let value = element_or_self(&context.node)
+                .inclusive_ancestors(ShadowIncluding::No)
+                .filter_map(DomRoot::downcast::<Element>)
+                .filter_map(|element| element.get_attribute(&ns!(xml), &local_name!("lang")))
+                .next()
+                .map(|attr| String::from(&**attr.value()).to_lowercase());
+            Value::Boolean(value.map_or(false, |value| {
+                value == lang || (value.starts_with(&*lang) && value[lang.len()..].starts_with('-'))
+            }))
+        },
+        CoreFunction::Number => Value::Number(match args.first() {
+            Some(arg) => evaluate_expr(arg, context)?.number(),
+            None => string_to_number(&string_value(&context.node)),
+        }),
+        CoreFunction::Sum => Value::Number(
+            arg(0)?
+                .into_node_set()?
+                .iter()
+                .map(|node| string_to_number(&string_value(node)))
+                .sum(),
+        ),
+        CoreFunction::Floor => Value::Number(arg(0)?.number().floor()),
+        CoreFunction::Ceiling => Value::Number(arg(0)?.number().ceil()),
+        CoreFunction::Round => Value::Number(round(arg(0)?.number())),
+    })
+}
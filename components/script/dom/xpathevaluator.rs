@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::codegen::Bindings::XPathEvaluatorBinding::{self, XPathEvaluatorMethods};
+use crate::dom::bindings::codegen::Bindings::XPathExpressionBinding::XPathExpressionMethods;
+use crate::dom::bindings::codegen::Bindings::XPathNSResolverBinding::XPathNSResolver;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::node::Node;
+use crate::dom::window::Window;
+use crate::dom::xpathexpression::XPathExpression;
+use crate::dom::xpathparser;
+use crate::dom::xpathresult::XPathResult;
+use dom_struct::dom_struct;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct XPathEvaluator {
+    reflector_: Reflector,
+}
+
+impl XPathEvaluator {
+    fn new_inherited() -> XPathEvaluator {
+        XPathEvaluator {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<XPathEvaluator> {
+        reflect_dom_object(
+            Box::new(XPathEvaluator::new_inherited()),
+            window,
+            XPathEvaluatorBinding::Wrap,
+        )
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathevaluator-xpathevaluator
+    #[allow(non_snake_case)]
+    pub fn Constructor(window: &Window) -> DomRoot<XPathEvaluator> {
+        XPathEvaluator::new(window)
+    }
+}
+
+/// Parse an expression, and resolve the namespaces of its prefixes with the resolver.
+/// <https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-createexpression>
+pub fn create_expression(
+    window: &Window,
+    expression: DOMString,
+    resolver: Option<Rc<XPathNSResolver>>,
+) -> Fallible<DomRoot<XPathExpression>> {
+    let expr = xpathparser::parse(&expression).map_err(|()| Error::Syntax)?;
+    let mut namespaces = vec![];
+    for prefix in expr.prefixes() {
+        let namespace = match resolver {
+            Some(ref resolver) => resolver.LookupNamespaceURI__(
+                Some(DOMString::from(&*prefix)),
+                ExceptionHandling::Rethrow,
+            )?,
+            None => None,
+        };
+        match namespace {
+            Some(ref namespace) if !namespace.is_empty() => {
+                namespaces.push((prefix, String::from(namespace.clone())))
+            },
+            _ => return Err(Error::Namespace),
+        }
+    }
+    Ok(XPathExpression::new(window, expr, namespaces))
+}
+
+/// <https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-evaluate>
+pub fn evaluate(
+    window: &Window,
+    expression: DOMString,
+    context_node: &Node,
+    resolver: Option<Rc<XPathNSResolver>>,
+    type_: u16,
+    result: Option<&XPathResult>,
+) -> Fallible<DomRoot<XPathResult>> {
+    create_expression(window, expression, resolver)?.Evaluate(context_node, type_, result)
+}
+
+impl XPathEvaluatorMethods for XPathEvaluator {
+    // https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-createexpression
+    fn CreateExpression(
+        &self,
+        expression: DOMString,
+        resolver: Option<Rc<XPathNSResolver>>,
+    ) -> Fallible<DomRoot<XPathExpression>> {
+        create_expression(self.global().as_window(), expression, resolver)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-creatensresolver
+    fn CreateNSResolver(&self, node_resolver: &Node) -> DomRoot<Node> {
+        DomRoot::from_ref(node_resolver)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathevaluatorbase-evaluate
+    fn Evaluate(
+        &self,
+        expression: DOMString,
+        context_node: &Node,
+        resolver: Option<Rc<XPathNSResolver>>,
+        type_: u16,
+        result: Option<&XPathResult>,
+    ) -> Fallible<DomRoot<XPathResult>> {
+        evaluate(
+            self.global().as_window(),
+            expression,
+            context_node,
+            resolver,
+            type_,
+            result,
+        )
+    }
+}
@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::XPathExpressionBinding::{
+    self, XPathExpressionMethods,
+};
+use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::node::Node;
+use crate::dom::window::Window;
+use crate::dom::xpatheval;
+use crate::dom::xpathparser::Expr;
+use crate::dom::xpathresult::XPathResult;
+use dom_struct::dom_struct;
+
+#[dom_struct]
+pub struct XPathExpression {
+    reflector_: Reflector,
+    expr: Expr,
+    /// The namespaces of the prefixes of the expression, as given by the resolver
+    /// when the expression was created.
+    namespaces: Vec<(String, String)>,
+}
+
+impl XPathExpression {
+    fn new_inherited(expr: Expr, namespaces: Vec<(String, String)>) -> XPathExpression {
+        XPathExpression {
+            reflector_: Reflector::new(),
+            expr,
+            namespaces,
+        }
+    }
+
+    pub fn new(
+        window: &Window,
+        expr: Expr,
+        namespaces: Vec<(String, String)>,
+    ) -> DomRoot<XPathExpression> {
+        reflect_dom_object(
+            Box::new(XPathExpression::new_inherited(expr, namespaces)),
+            window,
+            XPathExpressionBinding::Wrap,
+        )
+    }
+}
+
+impl XPathExpressionMethods for XPathExpression {
+    // https://dom.spec.whatwg.org/#dom-xpathexpression-evaluate
+    fn Evaluate(
+        &self,
+        context_node: &Node,
+        type_: u16,
+        result: Option<&XPathResult>,
+    ) -> Fallible<DomRoot<XPathResult>> {
+        let value = xpatheval::evaluate(&self.expr, context_node, &self.namespaces)?;
+        // The given result is reused, as browsers do.
+        let result = match result {
+            Some(result) => DomRoot::from_ref(result),
+            None => XPathResult::new(self.global().as_window()),
+        };
+        result.set_value(type_, value, context_node)?;
+        Ok(result)
+    }
+}
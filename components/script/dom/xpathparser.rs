@@ -0,0 +1,857 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A parser for XPath 1.0 expressions.
+//! <https://www.w3.org/TR/1999/REC-xpath-19991116/>
+
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Union,
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#axes>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum Axis {
+    Ancestor,
+    AncestorOrSelf,
+    Attribute,
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Following,
+    FollowingSibling,
+    Namespace,
+    Parent,
+    Preceding,
+    PrecedingSibling,
+    Self_,
+}
+
+impl Axis {
+    fn from_name(name: &str) -> Option<Axis> {
+        Some(match name {
+            "ancestor" => Axis::Ancestor,
+            "ancestor-or-self" => Axis::AncestorOrSelf,
+            "attribute" => Axis::Attribute,
+            "child" => Axis::Child,
+            "descendant" => Axis::Descendant,
+            "descendant-or-self" => Axis::DescendantOrSelf,
+            "following" => Axis::Following,
+            "following-sibling" => Axis::FollowingSibling,
+            "namespace" => Axis::Namespace,
+            "parent" => Axis::Parent,
+            "preceding" => Axis::Preceding,
+            "preceding-sibling" => Axis::PrecedingSibling,
+            "self" => Axis::Self_,
+            _ => return None,
+        })
+    }
+
+    /// Whether the nodes of the axis are in reverse document order.
+    pub fn is_reverse(&self) -> bool {
+        match *self {
+            Axis::Ancestor | Axis::AncestorOrSelf | Axis::Preceding | Axis::PrecedingSibling => {
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#node-tests>
+#[derive(Clone, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum NodeTest {
+    /// A name test, where a missing local name stands for `*`.
+    Name {
+        prefix: Option<String>,
+        local_name: Option<String>,
+    },
+    Node,
+    Text,
+    Comment,
+    ProcessingInstruction(Option<String>),
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#location-paths>
+#[derive(Clone, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub struct Step {
+    pub axis: Axis,
+    pub test: NodeTest,
+    pub predicates: Vec<Expr>,
+}
+
+/// The nodes the steps of a path start from.
+#[derive(Clone, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum PathStart {
+    /// The root of the tree of the context node, for absolute location paths.
+    Root,
+    /// The context node, for relative location paths.
+    ContextNode,
+    /// The nodes of a filter expression.
+    Filter(Box<Expr>),
+}
+
+/// The functions of the core function library.
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#corelib>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum CoreFunction {
+    Last,
+    Position,
+    Count,
+    Id,
+    LocalName,
+    NamespaceUri,
+    Name,
+    String,
+    Concat,
+    StartsWith,
+    Contains,
+    SubstringBefore,
+    SubstringAfter,
+    Substring,
+    StringLength,
+    NormalizeSpace,
+    Translate,
+    Boolean,
+    Not,
+    True,
+    False,
+    Lang,
+    Number,
+    Sum,
+    Floor,
+    Ceiling,
+    Round,
+}
+
+impl CoreFunction {
+    fn from_name(name: &str) -> Option<CoreFunction> {
+        Some(match name {
+            "last" => CoreFunction::Last,
+            "position" => CoreFunction::Position,
+            "count" => CoreFunction::Count,
+            "id" => CoreFunction::Id,
+            "local-name" => CoreFunction::LocalName,
+            "namespace-uri" => CoreFunction::NamespaceUri,
+            "name" => CoreFunction::Name,
+            "string" => CoreFunction::String,
+            "concat" => CoreFunction::Concat,
+            "starts-with" => CoreFunction::StartsWith,
+            "contains" => CoreFunction::Contains,
+            "substring-before" => CoreFunction::SubstringBefore,
+            "substring-after" => CoreFunction::SubstringAfter,
+            "substring" => CoreFunction::Substring,
+            "string-length" => CoreFunction::StringLength,
+            "normalize-space" => CoreFunction::NormalizeSpace,
+            "translate" => CoreFunction::Translate,
+            "boolean" => CoreFunction::Boolean,
+            "not" => CoreFunction::Not,
+            "true" => CoreFunction::True,
+            "false" => CoreFunction::False,
+            "lang" => CoreFunction::Lang,
+            "number" => CoreFunction::Number,
+            "sum" => CoreFunction::Sum,
+            "floor" => CoreFunction::Floor,
+            "ceiling" => CoreFunction::Ceiling,
+            "round" => CoreFunction::Round,
+            _ => return None,
+        })
+    }
+
+    /// The minimum and maximum numbers of arguments of the function.
+    fn arity(&self) -> (usize, Option<usize>) {
+        match *self {
+            CoreFunction::Last |
+            CoreFunction::Position |
+            CoreFunction::True |
+            CoreFunction::False => (0, Some(0)),
+            CoreFunction::LocalName |
+            CoreFunction::NamespaceUri |
+            CoreFunction::Name |
+            CoreFunction::String |
+            CoreFunction::StringLength |
+            CoreFunction::NormalizeSpace |
+            CoreFunction::Number => (0, Some(1)),
+            CoreFunction::Count |
+            CoreFunction::Id |
+            CoreFunction::Boolean |
+            CoreFunction::Not |
+            CoreFunction::Lang |
+            CoreFunction::Sum |
+            CoreFunction::Floor |
+            CoreFunction::Ceiling |
+            CoreFunction::Round => (1, Some(1)),
+            CoreFunction::StartsWith |
+            CoreFunction::Contains |
+            CoreFunction::SubstringBefore |
+            CoreFunction::SubstringAfter => (2, Some(2)),
+            CoreFunction::Substring => (2, Some(3)),
+            CoreFunction::Translate => (3, Some(3)),
+            CoreFunction::Concat => (2, None),
+        }
+    }
+}
+
+#[derive(Clone, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum Expr {
+    Binary(Box<Expr>, BinaryOperator, Box<Expr>),
+    Negate(Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Function(CoreFunction, Vec<Expr>),
+    /// A primary expression filtered by predicates.
+    Filter(Box<Expr>, Vec<Expr>),
+    Path(PathStart, Vec<Step>),
+}
+
+impl Expr {
+    /// The namespace prefixes used by the name tests of the expression, which must be
+    /// resolved before it is evaluated.
+    pub fn prefixes(&self) -> Vec<String> {
+        let mut prefixes = vec![];
+        self.collect_prefixes(&mut prefixes);
+        prefixes
+    }
+
+    fn collect_prefixes(&self, prefixes: &mut Vec<String>) {
+        match *self {
+            Expr::Binary(ref left, _, ref right) => {
+                left.collect_prefixes(prefixes);
+                right.collect_prefixes(prefixes);
+            },
+            Expr::Negate(ref expr) => expr.collect_prefixes(prefixes),
+            Expr::Literal(_) | Expr::Number(_) => {},
+            Expr::Function(_, ref args) => {
+                for arg in args {
+                    arg.collect_prefixes(prefixes);
+                }
+            },
+            Expr::Filter(ref expr, ref predicates) => {
+                expr.collect_prefixes(prefixes);
+                for predicate in predicates {
+                    predicate.collect_prefixes(prefixes);
+                }
+            },
+            Expr::Path(ref start, ref steps) => {
+                if let PathStart::Filter(ref expr) = *start {
+                    expr.collect_prefixes(prefixes);
+                }
+                for step in steps {
+                    if let NodeTest::Name {
+                        prefix: Some(ref prefix),
+                        ..
+                    } = step.test
+                    {
+                        if !prefixes.contains(prefix) {
+                            prefixes.push(prefix.clone());
+                        }
+                    }
+                    for predicate in &step.predicates {
+                        predicate.collect_prefixes(prefixes);
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#exprlex>
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Dot,
+    DoubleDot,
+    At,
+    Comma,
+    DoubleColon,
+    Slash,
+    DoubleSlash,
+    Operator(BinaryOperator),
+    Minus,
+    Literal(String),
+    Number(f64),
+    /// A name test, where a missing local name stands for `*`.
+    NameTest(Option<String>, Option<String>),
+    NodeType(String),
+    FunctionName(String),
+    AxisName(Axis),
+    VariableReference(String),
+}
+
+impl Token {
+    /// Whether a `*` or a name following the token is an operator.
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#exprlex>
+    fn precedes_operator(&self) -> bool {
+        match *self {
+            Token::At |
+            Token::DoubleColon |
+            Token::LeftParen |
+            Token::LeftBracket |
+            Token::Comma |
+            Token::Slash |
+            Token::DoubleSlash |
+            Token::Operator(_) |
+            Token::Minus => false,
+            _ => true,
+        }
+    }
+}
+
+/// <https://www.w3.org/TR/1999/REC-xpath-19991116/#NT-ExprWhitespace>
+pub fn is_xpath_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\r' || c == '\n'
+}
+
+/// <https://www.w3.org/TR/xml/#NT-NameStartChar>, without the colon.
+pub fn is_name_start_char(c: char) -> bool {
+    match c {
+        'A'..='Z' |
+        '_' |
+        'a'..='z' |
+        '\u{C0}'..='\u{D6}' |
+        '\u{D8}'..='\u{F6}' |
+        '\u{F8}'..='\u{2FF}' |
+        '\u{370}'..='\u{37D}' |
+        '\u{37F}'..='\u{1FFF}' |
+        '\u{200C}'..='\u{200D}' |
+        '\u{2070}'..='\u{218F}' |
+        '\u{2C00}'..='\u{2FEF}' |
+        '\u{3001}'..='\u{D7FF}' |
+        '\u{F900}'..='\u{FDCF}' |
+        '\u{FDF0}'..='\u{FFFD}' |
+        '\u{10000}'..='\u{EFFFF}' => true,
+        _ => false,
+    }
+}
+
+/// <https://www.w3.org/TR/xml/#NT-NameChar>, without the colon.
+pub fn is_name_char(c: char) -> bool {
+    match c {
+        '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}' => true,
+        _ => is_name_start_char(c),
+    }
+}
+
+struct Tokenizer<'a> {
+    input: &'a str,
+    position: usize,
+    tokens: Vec<Token>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start_matches(is_xpath_whitespace).len();
+    }
+
+    /// The next character which is not whitespace, and the one after it.
+    fn lookahead(&self) -> (Option<char>, Option<char>) {
+        let mut chars = self.rest().trim_start_matches(is_xpath_whitespace).chars();
+        (chars.next(), chars.next())
+    }
+
+    fn consume_name(&mut self) -> Option<String> {
+        if !self.peek().map_or(false, is_name_start_char) {
+            return None;
+        }
+        let rest = self.rest();
+        let length = rest.len() - rest.trim_start_matches(is_name_char).len();
+        self.position += length;
+        Some(rest[..length].to_owned())
+    }
+
+    fn consume_digits(&mut self) -> &'a str {
+        let rest = self.rest();
+        let length = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        self.position += length;
+        &rest[..length]
+    }
+
+    fn push(&mut self, token: Token, length: usize) {
+        self.position += length;
+        self.tokens.push(token);
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, ()> {
+        loop {
+            self.skip_whitespace();
+            let mut chars = self.rest().chars();
+            let c = match chars.next() {
+                Some(c) => c,
+                None => return Ok(self.tokens),
+            };
+            let next = chars.next();
+            let operator_expected = self
+                .tokens
+                .last()
+                .map_or(false, |token| token.precedes_operator());
+            match c {
+                '(' => self.push(Token::LeftParen, 1),
+                ')' => self.push(Token::RightParen, 1),
+                '[' => self.push(Token::LeftBracket, 1),
+                ']' => self.push(Token::RightBracket, 1),
+                '@' => self.push(Token::At, 1),
+                ',' => self.push(Token::Comma, 1),
+                '|' => self.push(Token::Operator(BinaryOperator::Union), 1),
+                '+' => self.push(Token::Operator(BinaryOperator::Add), 1),
+                '-' => self.push(Token::Minus, 1),
+                '=' => self.push(Token::Operator(BinaryOperator::Equal), 1),
+                '!' if next == Some('=') => self.push(Token::Operator(BinaryOperator::NotEqual), 2),
+                '<' if next == Some('=') => {
+                    self.push(Token::Operator(BinaryOperator::LessOrEqual), 2)
+                },
+                '<' => self.push(Token::Operator(BinaryOperator::Less), 1),
+                '>' if next == Some('=') => {
+                    self.push(Token::Operator(BinaryOperator::GreaterOrEqual), 2)
+                },
+                '>' => self.push(Token::Operator(BinaryOperator::Greater), 1),
+                ':' if next == Some(':') => self.push(Token::DoubleColon, 2),
+                '/' if next == Some('/') => self.push(Token::DoubleSlash, 2),
+                '/' => self.push(Token::Slash, 1),
+                '.' if next == Some('.') => self.push(Token::DoubleDot, 2),
+                '.' if next.map_or(false, |c| c.is_ascii_digit()) => self.tokenize_number(),
+                '.' => self.push(Token::Dot, 1),
+                '0'..='9' => self.tokenize_number(),
+                '"' | '\'' => {
+                    let rest = &self.rest()[1..];
+                    let end = rest.find(c).ok_or(())?;
+                    let literal = rest[..end].to_owned();
+                    self.push(Token::Literal(literal), end + 2);
+                },
+                '$' => {
+                    self.position += 1;
+                    let name = self.consume_qualified_name()?;
+                    self.tokens.push(Token::VariableReference(name));
+                },
+                '*' if operator_expected => self.push(Token::Operator(BinaryOperator::Multiply), 1),
+                '*' => self.push(Token::NameTest(None, None), 1),
+                _ if operator_expected => {
+                    let operator = match self.consume_name().as_ref().map(|name| &**name) {
+                        Some("and") => BinaryOperator::And,
+                        Some("or") => BinaryOperator::Or,
+                        Some("mod") => BinaryOperator::Modulo,
+                        Some("div") => BinaryOperator::Divide,
+                        _ => return Err(()),
+                    };
+                    self.tokens.push(Token::Operator(operator));
+                },
+                _ => self.tokenize_name()?,
+            }
+        }
+    }
+
+    fn tokenize_number(&mut self) {
+        let start = self.position;
+        self.consume_digits();
+        if self.peek() == Some('.') {
+            self.position += 1;
+            self.consume_digits();
+        }
+        let number = self.input[start..self.position]
+            .parse()
+            .unwrap_or(::std::f64::NAN);
+        self.tokens.push(Token::Number(number));
+    }
+
+    /// A QName, for variable references.
+    fn consume_qualified_name(&mut self) -> Result<String, ()> {
+        let mut name = self.consume_name().ok_or(())?;
+        let rest = self.rest();
+        if rest.starts_with(':') && !rest.starts_with("::") {
+            self.position += 1;
+            name.push(':');
+            name.push_str(&self.consume_name().ok_or(())?);
+        }
+        Ok(name)
+    }
+
+    /// A name test, a node type, a function name or an axis name.
+    fn tokenize_name(&mut self) -> Result<(), ()> {
+        let name = self.consume_name().ok_or(())?;
+        let rest = self.rest();
+        if rest.starts_with(':') && !rest.starts_with("::") {
+            self.position += 1;
+            if self.peek() == Some('*') {
+                self.push(Token::NameTest(Some(name), None), 1);
+                return Ok(());
+            }
+            let local_name = self.consume_name().ok_or(())?;
+            let token = if self.lookahead().0 == Some('(') {
+                Token::FunctionName(format!("{}:{}", name, local_name))
+            } else {
+                Token::NameTest(Some(name), Some(local_name))
+            };
+            self.tokens.push(token);
+            return Ok(());
+        }
+        let token = match self.lookahead() {
+            (Some('('), _) => match &*name {
+                "comment" | "text" | "processing-instruction" | "node" => Token::NodeType(name),
+                _ => Token::FunctionName(name),
+            },
+            (Some(':'), Some(':')) => Token::AxisName(Axis::from_name(&name).ok_or(())?),
+            _ => Token::NameTest(None, Some(name)),
+        };
+        self.tokens.push(token);
+        Ok(())
+    }
+}
+
+/// The maximum nesting depth of the expressions, beyond which an expression is a syntax
+/// error rather than a stack overflow when it is parsed or evaluated.
+const MAX_NESTING_DEPTH: usize = 512;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// The nesting depth of the expression being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), ()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Enter a nested expression, failing if it is nested too deeply.
+    fn enter(&mut self) -> Result<(), ()> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Parse a left-associative sequence of binary operations whose operators are
+    /// in `operators`, with operands parsed by `operand`. Each operation nests the
+    /// previous ones, so it counts towards the nesting depth.
+    fn parse_binary(
+        &mut self,
+        operators: &[BinaryOperator],
+        operand: fn(&mut Parser) -> Result<Expr, ()>,
+    ) -> Result<Expr, ()> {
+        let depth = self.depth;
+        let mut expr = operand(self)?;
+        loop {
+            let operator = match self.peek() {
+                Some(&Token::Operator(operator)) if operators.contains(&operator) => operator,
+                Some(&Token::Minus) if operators.contains(&BinaryOperator::Subtract) => {
+                    BinaryOperator::Subtract
+                },
+                _ => break,
+            };
+            self.position += 1;
+            self.enter()?;
+            let right = operand(self)?;
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#NT-Expr>
+    fn parse_expr(&mut self) -> Result<Expr, ()> {
+        self.enter()?;
+        let expr = self.parse_binary(&[BinaryOperator::Or], Parser::parse_and_expr)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn parse_and_expr(&mut self) -> Result<Expr, ()> {
+        self.parse_binary(&[BinaryOperator::And], Parser::parse_equality_expr)
+    }
+
+    fn parse_equality_expr(&mut self) -> Result<Expr, ()> {
+        self.parse_binary(
+            &[BinaryOperator::Equal, BinaryOperator::NotEqual],
+            Parser::parse_relational_expr,
+        )
+    }
+
+    fn parse_relational_expr(&mut self) -> Result<Expr, ()> {
+        self.parse_binary(
+            &[
+                BinaryOperator::Less,
+                BinaryOperator::LessOrEqual,
+                BinaryOperator::Greater,
+                BinaryOperator::GreaterOrEqual,
+            ],
+            Parser::parse_additive_expr,
+        )
+    }
+
+    fn parse_additive_expr(&mut self) -> Result<Expr, ()> {
+        self.parse_binary(
+            &[BinaryOperator::Add, BinaryOperator::Subtract],
+            Parser::parse_multiplicative_expr,
+        )
+    }
+
+    fn parse_multiplicative_expr(&mut self) -> Result<Expr, ()> {
+        self.parse_binary(
+            &[
+                BinaryOperator::Multiply,
+                BinaryOperator::Divide,
+                BinaryOperator::Modulo,
+            ],
+            Parser::parse_unary_expr,
+        )
+    }
+
+    fn parse_unary_expr(&mut self) -> Result<Expr, ()> {
+        if self.eat(&Token::Minus) {
+            self.enter()?;
+            let expr = self.parse_unary_expr()?;
+            self.depth -= 1;
+            return Ok(Expr::Negate(Box::new(expr)));
+        }
+        self.parse_binary(&[BinaryOperator::Union], Parser::parse_path_expr)
+    }
+
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#NT-PathExpr>
+    fn parse_path_expr(&mut self) -> Result<Expr, ()> {
+        match self.peek() {
+            Some(&Token::Slash) => {
+                self.position += 1;
+                let steps = if self.at_step() {
+                    self.parse_relative_location_path()?
+                } else {
+                    vec![]
+                };
+                Ok(Expr::Path(PathStart::Root, steps))
+            },
+            Some(&Token::DoubleSlash) => {
+                self.position += 1;
+                let mut steps = vec![descendant_or_self_step()];
+                steps.extend(self.parse_relative_location_path()?);
+                Ok(Expr::Path(PathStart::Root, steps))
+            },
+            _ if self.at_step() => Ok(Expr::Path(
+                PathStart::ContextNode,
+                self.parse_relative_location_path()?,
+            )),
+            _ => {
+                let primary = self.parse_primary_expr()?;
+                let mut predicates = vec![];
+                while self.peek() == Some(&Token::LeftBracket) {
+                    predicates.push(self.parse_predicate()?);
+                }
+                let filter = if predicates.is_empty() {
+                    primary
+                } else {
+                    Expr::Filter(Box::new(primary), predicates)
+                };
+                let mut steps = vec![];
+                match self.peek() {
+                    Some(&Token::Slash) => self.position += 1,
+                    Some(&Token::DoubleSlash) => {
+                        self.position += 1;
+                        steps.push(descendant_or_self_step());
+                    },
+                    _ => return Ok(filter),
+                }
+                steps.extend(self.parse_relative_location_path()?);
+                Ok(Expr::Path(PathStart::Filter(Box::new(filter)), steps))
+            },
+        }
+    }
+
+    fn at_step(&self) -> bool {
+        match self.peek() {
+            Some(&Token::Dot) |
+            Some(&Token::DoubleDot) |
+            Some(&Token::At) |
+            Some(&Token::AxisName(_)) |
+            Some(&Token::NameTest(..)) |
+            Some(&Token::NodeType(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#NT-RelativeLocationPath>
+    fn parse_relative_location_path(&mut self) -> Result<Vec<Step>, ()> {
+        let mut steps = vec![self.parse_step()?];
+        loop {
+            match self.peek() {
+                Some(&Token::Slash) => self.position += 1,
+                Some(&Token::DoubleSlash) => {
+                    self.position += 1;
+                    steps.push(descendant_or_self_step());
+                },
+                _ => return Ok(steps),
+            }
+            steps.push(self.parse_step()?);
+        }
+    }
+
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#NT-Step>
+    fn parse_step(&mut self) -> Result<Step, ()> {
+        let axis = match self.next() {
+            Some(Token::Dot) => return Ok(node_step(Axis::Self_)),
+            Some(Token::DoubleDot) => return Ok(node_step(Axis::Parent)),
+            Some(Token::At) => Axis::Attribute,
+            Some(Token::AxisName(axis)) => {
+                self.expect(&Token::DoubleColon)?;
+                axis
+            },
+            _ => {
+                self.position -= 1;
+                Axis::Child
+            },
+        };
+        let test = match self.next() {
+            Some(Token::NameTest(prefix, local_name)) => NodeTest::Name { prefix, local_name },
+            Some(Token::NodeType(node_type)) => {
+                self.expect(&Token::LeftParen)?;
+                let test = match &*node_type {
+                    "comment" => NodeTest::Comment,
+                    "text" => NodeTest::Text,
+                    "node" => NodeTest::Node,
+                    _ => match self.peek() {
+                        Some(&Token::Literal(ref target)) => {
+                            let target = target.clone();
+                            self.position += 1;
+                            NodeTest::ProcessingInstruction(Some(target))
+                        },
+                        _ => NodeTest::ProcessingInstruction(None),
+                    },
+                };
+                self.expect(&Token::RightParen)?;
+                test
+            },
+            _ => return Err(()),
+        };
+        let mut predicates = vec![];
+        while self.peek() == Some(&Token::LeftBracket) {
+            predicates.push(self.parse_predicate()?);
+        }
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+        })
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr, ()> {
+        self.expect(&Token::LeftBracket)?;
+        let expr = self.parse_expr()?;
+        self.expect(&Token::RightBracket)?;
+        Ok(expr)
+    }
+
+    /// <https://www.w3.org/TR/1999/REC-xpath-19991116/#NT-PrimaryExpr>
+    fn parse_primary_expr(&mut self) -> Result<Expr, ()> {
+        match self.next() {
+            Some(Token::LeftParen) => {
+                let expr = self.parse_expr()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expr)
+            },
+            Some(Token::Literal(literal)) => Ok(Expr::Literal(literal)),
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::FunctionName(name)) => {
+                let function = CoreFunction::from_name(&name).ok_or(())?;
+                self.expect(&Token::LeftParen)?;
+                let mut args = vec![];
+                if !self.eat(&Token::RightParen) {
+                    loop {
+                        args.push(self.parse_expr()?);
+                        if self.eat(&Token::RightParen) {
+                            break;
+                        }
+                        self.expect(&Token::Comma)?;
+                    }
+                }
+                let (min, max) = function.arity();
+                if args.len() < min || max.map_or(false, |max| args.len() > max) {
+                    return Err(());
+                }
+                Ok(Expr::Function(function, args))
+            },
+            // There are no variable bindings in the DOM.
+            _ => Err(()),
+        }
+    }
+}
+
+fn node_step(axis: Axis) -> Step {
+    Step {
+        axis,
+        test: NodeTest::Node,
+        predicates: vec![],
+    }
+}
+
+/// The step `//` abbreviates.
+fn descendant_or_self_step() -> Step {
+    node_step(Axis::DescendantOrSelf)
+}
+
+/// Parse an XPath expression. Functions which are not in the core library, variable
+/// references, calls with the wrong number of arguments and expressions nested more
+/// than `MAX_NESTING_DEPTH` deep are errors.
+pub fn parse(input: &str) -> Result<Expr, ()> {
+    let tokenizer = Tokenizer {
+        input,
+        position: 0,
+        tokens: vec![],
+    };
+    let mut parser = Parser {
+        tokens: tokenizer.tokenize()?,
+        position: 0,
+        depth: 0,
+    };
+    let expr = parser.parse_expr()?;
+    if parser.position != parser.tokens.len() {
+        return Err(());
+    }
+    Ok(expr)
+}
@@ -0,0 +1,224 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::XPathResultBinding::XPathResultConstants;
+use crate::dom::bindings::codegen::Bindings::XPathResultBinding::{self, XPathResultMethods};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::node::{Node, ShadowIncluding};
+use crate::dom::window::Window;
+use crate::dom::xpatheval::Value;
+use dom_struct::dom_struct;
+use std::cell::Cell;
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+enum ResultValue {
+    Number(f64),
+    String(DOMString),
+    Boolean(bool),
+    /// The nodes of the result, in document order.
+    Nodes(Vec<Dom<Node>>),
+}
+
+#[dom_struct]
+pub struct XPathResult {
+    reflector_: Reflector,
+    result_type: Cell<u16>,
+    value: DomRefCell<ResultValue>,
+    /// The index of the next node of an iterator.
+    next_index: Cell<usize>,
+    /// The root of the tree of the context node, and its version when the result was
+    /// computed, which invalidate iterators when the tree is mutated.
+    root: MutNullableDom<Node>,
+    root_version: Cell<u64>,
+}
+
+impl XPathResult {
+    fn new_inherited() -> XPathResult {
+        XPathResult {
+            reflector_: Reflector::new(),
+            result_type: Cell::new(XPathResultConstants::ANY_TYPE),
+            value: DomRefCell::new(ResultValue::Nodes(vec![])),
+            next_index: Cell::new(0),
+            root: Default::default(),
+            root_version: Cell::new(0),
+        }
+    }
+
+    pub fn new(window: &Window) -> DomRoot<XPathResult> {
+        reflect_dom_object(
+            Box::new(XPathResult::new_inherited()),
+            window,
+            XPathResultBinding::Wrap,
+        )
+    }
+
+    /// Set the result to the value of an expression evaluated with the given context
+    /// node, converted to the given type.
+    pub fn set_value(&self, type_: u16, value: Value, context_node: &Node) -> Fallible<()> {
+        if type_ > XPathResultConstants::FIRST_ORDERED_NODE_TYPE {
+            return Err(Error::NotSupported);
+        }
+        let type_ = match (type_, &value) {
+            (XPathResultConstants::ANY_TYPE, &Value::NodeSet(_)) => {
+                XPathResultConstants::UNORDERED_NODE_ITERATOR_TYPE
+            },
+            (XPathResultConstants::ANY_TYPE, &Value::Boolean(_)) => {
+                XPathResultConstants::BOOLEAN_TYPE
+            },
+            (XPathResultConstants::ANY_TYPE, &Value::Number(_)) => {
+                XPathResultConstants::NUMBER_TYPE
+            },
+            (XPathResultConstants::ANY_TYPE, &Value::String(_)) => {
+                XPathResultConstants::STRING_TYPE
+            },
+            (XPathResultConstants::NUMBER_TYPE, _) |
+            (XPathResultConstants::STRING_TYPE, _) |
+            (XPathResultConstants::BOOLEAN_TYPE, _) |
+            (_, &Value::NodeSet(_)) => type_,
+            _ => {
+                return Err(Error::Type(
+                    "The result cannot be converted to the requested type.".to_owned(),
+                ));
+            },
+        };
+        *self.value.borrow_mut() = match type_ {
+            XPathResultConstants::NUMBER_TYPE => ResultValue::Number(value.number()),
+            XPathResultConstants::STRING_TYPE => {
+                ResultValue::String(DOMString::from(value.string()))
+            },
+            XPathResultConstants::BOOLEAN_TYPE => ResultValue::Boolean(value.boolean()),
+            _ => match value {
+                Value::NodeSet(nodes) => {
+                    ResultValue::Nodes(nodes.iter().map(|node| Dom::from_ref(&**node)).collect())
+                },
+                _ => unreachable!(),
+            },
+        };
+        let root = context_node
+            .inclusive_ancestors(ShadowIncluding::No)
+            .last()
+            .unwrap();
+        self.root_version.set(root.inclusive_descendants_version());
+        self.root.set(Some(&root));
+        self.result_type.set(type_);
+        self.next_index.set(0);
+        Ok(())
+    }
+
+    fn is_iterator(&self) -> bool {
+        match self.result_type.get() {
+            XPathResultConstants::UNORDERED_NODE_ITERATOR_TYPE |
+            XPathResultConstants::ORDERED_NODE_ITERATOR_TYPE => true,
+            _ => false,
+        }
+    }
+
+    fn is_snapshot(&self) -> bool {
+        match self.result_type.get() {
+            XPathResultConstants::UNORDERED_NODE_SNAPSHOT_TYPE |
+            XPathResultConstants::ORDERED_NODE_SNAPSHOT_TYPE => true,
+            _ => false,
+        }
+    }
+
+    fn node(&self, index: usize) -> Option<DomRoot<Node>> {
+        match *self.value.borrow() {
+            ResultValue::Nodes(ref nodes) => {
+                nodes.get(index).map(|node| DomRoot::from_ref(&**node))
+            },
+            _ => None,
+        }
+    }
+
+    fn wrong_type() -> Error {
+        Error::Type("The result is not of this type.".to_owned())
+    }
+}
+
+impl XPathResultMethods for XPathResult {
+    // https://dom.spec.whatwg.org/#dom-xpathresult-resulttype
+    fn ResultType(&self) -> u16 {
+        self.result_type.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-numbervalue
+    fn GetNumberValue(&self) -> Fallible<f64> {
+        match *self.value.borrow() {
+            ResultValue::Number(number) => Ok(number),
+            _ => Err(XPathResult::wrong_type()),
+        }
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-stringvalue
+    fn GetStringValue(&self) -> Fallible<DOMString> {
+        match *self.value.borrow() {
+            ResultValue::String(ref string) => Ok(string.clone()),
+            _ => Err(XPathResult::wrong_type()),
+        }
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-booleanvalue
+    fn GetBooleanValue(&self) -> Fallible<bool> {
+        match *self.value.borrow() {
+            ResultValue::Boolean(boolean) => Ok(boolean),
+            _ => Err(XPathResult::wrong_type()),
+        }
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-singlenodevalue
+    fn GetSingleNodeValue(&self) -> Fallible<Option<DomRoot<Node>>> {
+        match self.result_type.get() {
+            XPathResultConstants::ANY_UNORDERED_NODE_TYPE |
+            XPathResultConstants::FIRST_ORDERED_NODE_TYPE => Ok(self.node(0)),
+            _ => Err(XPathResult::wrong_type()),
+        }
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-invaliditeratorstate
+    fn InvalidIteratorState(&self) -> bool {
+        self.is_iterator() &&
+            self.root.get().map_or(false, |root| {
+                root.inclusive_descendants_version() != self.root_version.get()
+            })
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-snapshotlength
+    fn GetSnapshotLength(&self) -> Fallible<u32> {
+        if !self.is_snapshot() {
+            return Err(XPathResult::wrong_type());
+        }
+        match *self.value.borrow() {
+            ResultValue::Nodes(ref nodes) => Ok(nodes.len() as u32),
+            _ => Ok(0),
+        }
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-iteratenext
+    fn IterateNext(&self) -> Fallible<Option<DomRoot<Node>>> {
+        if !self.is_iterator() {
+            return Err(XPathResult::wrong_type());
+        }
+        if self.InvalidIteratorState() {
+            return Err(Error::InvalidState);
+        }
+        let node = self.node(self.next_index.get());
+        if node.is_some() {
+            self.next_index.set(self.next_index.get() + 1);
+        }
+        Ok(node)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-xpathresult-snapshotitem
+    fn SnapshotItem(&self, index: u32) -> Fallible<Option<DomRoot<Node>>> {
+        if !self.is_snapshot() {
+            return Err(XPathResult::wrong_type());
+        }
+        Ok(self.node(index as usize))
+    }
+}
@@ -10,7 +10,7 @@ use crate::dom::bindings::codegen::Bindings::XRSystemBinding::{XRSessionMode, XR
 use crate::dom::bindings::conversions::{ConversionResult, FromJSValConvertible};
 use crate::dom::bindings::error::Error;
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::trace::RootedTraceableBox;
@@ -18,7 +18,7 @@ use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::gamepad::Gamepad;
 use crate::dom::gamepadevent::GamepadEventType;
-use crate::dom::promise::Promise;
+use crate::dom::promise::{PendingPromise, Promise};
 use crate::dom::vrdisplay::VRDisplay;
 use crate::dom::vrdisplayevent::VRDisplayEvent;
 use crate::dom::window::Window;
@@ -26,10 +26,8 @@ use crate::dom::xrsession::XRSession;
 use crate::dom::xrtest::XRTest;
 use crate::realms::InRealm;
 use crate::script_thread::ScriptThread;
-use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
 use ipc_channel::ipc::{self as ipc_crate, IpcReceiver, IpcSender};
-use ipc_channel::router::ROUTER;
 use msg::constellation_msg::PipelineId;
 use profile_traits::ipc;
 use std::cell::Cell;
@@ -130,37 +128,15 @@ impl XRSystemMethods for XRSystem {
     fn IsSessionSupported(&self, mode: XRSessionMode) -> Rc<Promise> {
         // XXXManishearth this should select an XR device first
         let promise = Promise::new(&self.global());
-        let mut trusted = Some(TrustedPromise::new(promise.clone()));
         let global = self.global();
         let window = global.as_window();
         let (task_source, canceller) = window
             .task_manager()
             .dom_manipulation_task_source_with_canceller();
         let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
-        ROUTER.add_route(
+        PendingPromise::new(&promise, task_source, canceller).settle_with_message(
             receiver.to_opaque(),
-            Box::new(move |message| {
-                // router doesn't know this is only called once
-                let trusted = if let Some(trusted) = trusted.take() {
-                    trusted
-                } else {
-                    error!("supportsSession callback called twice!");
-                    return;
-                };
-                let message: Result<(), webxr_api::Error> = if let Ok(message) = message.to() {
-                    message
-                } else {
-                    error!("supportsSession callback given incorrect payload");
-                    return;
-                };
-                if let Ok(()) = message {
-                    let _ =
-                        task_source.queue_with_canceller(trusted.resolve_task(true), &canceller);
-                } else {
-                    let _ =
-                        task_source.queue_with_canceller(trusted.resolve_task(false), &canceller);
-                };
-            }),
+            |promise, message: Result<(), XRError>| promise.resolve_native(&message.is_ok()),
         );
         window
             .webxr_registry()
@@ -239,34 +215,18 @@ impl XRSystemMethods for XRSystem {
             optional_features,
         };
 
-        let mut trusted = Some(TrustedPromise::new(promise.clone()));
         let this = Trusted::new(self);
         let (task_source, canceller) = window
             .task_manager()
             .dom_manipulation_task_source_with_canceller();
         let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
         let (frame_sender, frame_receiver) = ipc_crate::channel().unwrap();
-        let mut frame_receiver = Some(frame_receiver);
-        ROUTER.add_route(
+        PendingPromise::new(&promise, task_source, canceller).settle_with_message(
             receiver.to_opaque(),
-            Box::new(move |message| {
-                // router doesn't know this is only called once
-                let trusted = trusted.take().unwrap();
-                let this = this.clone();
-                let frame_receiver = frame_receiver.take().unwrap();
-                let message: Result<Session, webxr_api::Error> = if let Ok(message) = message.to() {
-                    message
-                } else {
-                    error!("requestSession callback given incorrect payload");
-                    return;
-                };
-                let _ = task_source.queue_with_canceller(
-                    task!(request_session: move || {
-                        this.root().session_obtained(message, trusted.root(), mode, frame_receiver);
-                    }),
-                    &canceller,
-                );
-            }),
+            move |promise, message: Result<Session, XRError>| {
+                this.root()
+                    .session_obtained(message, promise, mode, frame_receiver);
+            },
         );
         window
             .webxr_registry()
@@ -12,14 +12,13 @@ use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use crate::dom::bindings::codegen::Bindings::XRTestBinding::{
     self, FakeXRDeviceInit, XRTestMethods,
 };
-use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::fakexrdevice::{get_origin, get_views, FakeXRDevice};
 use crate::dom::globalscope::GlobalScope;
-use crate::dom::promise::Promise;
+use crate::dom::promise::{PendingPromise, Promise};
 use crate::script_thread::ScriptThread;
-use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
 use ipc_channel::ipc::IpcSender;
 use ipc_channel::router::ROUTER;
@@ -52,9 +51,8 @@ impl XRTest {
     fn device_obtained(
         &self,
         response: Result<IpcSender<MockDeviceMsg>, XRError>,
-        trusted: TrustedPromise,
+        promise: Rc<Promise>,
     ) {
-        let promise = trusted.root();
         if let Ok(sender) = response {
             let device = FakeXRDevice::new(&self.global(), sender);
             self.devices_connected
@@ -124,31 +122,15 @@ impl XRTestMethods for XRTest {
         let global = self.global();
         let window = global.as_window();
         let this = Trusted::new(self);
-        let mut trusted = Some(TrustedPromise::new(p.clone()));
 
         let (task_source, canceller) = window
             .task_manager()
             .dom_manipulation_task_source_with_canceller();
         let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
-        ROUTER.add_route(
-            receiver.to_opaque(),
-            Box::new(move |message| {
-                let trusted = trusted
-                    .take()
-                    .expect("SimulateDeviceConnection callback called twice");
-                let this = this.clone();
-                let message = message
-                    .to()
-                    .expect("SimulateDeviceConnection callback given incorrect payload");
-
-                let _ = task_source.queue_with_canceller(
-                    task!(request_session: move || {
-                        this.root().device_obtained(message, trusted);
-                    }),
-                    &canceller,
-                );
-            }),
-        );
+        PendingPromise::new(&p, task_source, canceller)
+            .settle_with_message(receiver.to_opaque(), move |promise, message| {
+                this.root().device_obtained(message, promise)
+            });
         window
             .webxr_registry()
             .simulate_device_connection(init, sender);
@@ -158,6 +140,7 @@ impl XRTestMethods for XRTest {
 
     /// https://github.com/immersive-web/webxr-test-api/blob/master/explainer.md
     fn SimulateUserActivation(&self, f: Rc<Function>) {
+        self.global().as_window().notify_user_activation();
         ScriptThread::set_user_interacting(true);
         let _ = f.Call__(vec![], ExceptionHandling::Rethrow);
         ScriptThread::set_user_interacting(false);
@@ -179,22 +162,21 @@ impl XRTestMethods for XRTest {
                 devices.iter().map(|x| DomRoot::from_ref(&**x)).collect();
             devices.clear();
 
-            let mut trusted = Some(TrustedPromise::new(p.clone()));
             let (task_source, canceller) = global
                 .as_window()
                 .task_manager()
                 .dom_manipulation_task_source_with_canceller();
+            let mut pending = Some(PendingPromise::new(&p, task_source, canceller));
 
             ROUTER.add_route(
                 receiver.to_opaque(),
                 Box::new(move |_| {
                     len -= 1;
                     if len == 0 {
-                        let trusted = trusted
+                        let pending = pending
                             .take()
                             .expect("DisconnectAllDevices disconnected more devices than expected");
-                        let _ =
-                            task_source.queue_with_canceller(trusted.resolve_task(()), &canceller);
+                        pending.resolve(());
                     }
                 }),
             );
@@ -6,24 +6,29 @@ use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInfo;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInit;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseBinding::ResponseMethods;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseType as DOMResponseType;
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::error::Error;
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::Guard;
 use crate::dom::performanceresourcetiming::InitiatorType;
-use crate::dom::promise::Promise;
+use crate::dom::promise::{PendingPromise, Promise};
 use crate::dom::request::Request;
 use crate::dom::response::Response;
 use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
+use crate::dom::streams::request_body::transmit_body;
+use crate::dom::window::Window;
 use crate::network_listener::{
     self, submit_timing_data, NetworkListener, PreInvoke, ResourceTimingListener,
 };
 use crate::realms::{enter_realm, InRealm};
+use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::TaskSourceName;
+use content_security_policy as csp;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use net_traits::request::{
@@ -36,14 +41,14 @@ use net_traits::{FetchChannels, FetchResponseListener, NetworkError};
 use net_traits::{FetchMetadata, FilteredMetadata, Metadata};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
 use servo_url::ServoUrl;
-use std::mem;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 struct FetchContext {
-    fetch_promise: Option<TrustedPromise>,
+    /// The promise returned by `fetch()`, which is settled once the response arrives, in a
+    /// task which is cancelled if the global is torn down in the meantime.
+    fetch_promise: Option<PendingPromise<NetworkingTaskSource>>,
     response_object: Trusted<Response>,
-    body: Vec<u8>,
     resource_timing: ResourceFetchTiming,
 }
 
@@ -106,6 +111,7 @@ fn request_init_from_request(request: NetTraitsRequest) -> RequestBuilder {
         headers: request.headers.clone(),
         unsafe_request: request.unsafe_request,
         body: request.body.clone(),
+        body_stream: None,
         service_workers_mode: ServiceWorkersMode::All,
         destination: request.destination,
         synchronous: request.synchronous,
@@ -128,6 +134,10 @@ fn request_init_from_request(request: NetTraitsRequest) -> RequestBuilder {
         parser_metadata: request.parser_metadata,
         initiator: request.initiator,
         csp_list: None,
+        viewport: None,
+        site_for_cookies: None,
+        top_level_navigation: false,
+        keep_alive: request.keep_alive,
     }
 }
 
@@ -146,17 +156,30 @@ pub fn Fetch(
     let response = Response::new(global);
 
     // Step 2
-    let request = match Request::Constructor(global, input, init) {
+    let (request, body_stream) = match Request::Constructor(global, input, init) {
         Err(e) => {
             promise.reject_error(e);
             return promise;
         },
-        Ok(r) => r.get_request(),
+        Ok(r) => (r.get_request(), r.body_stream()),
     };
     let timing_type = request.timing_type();
 
     let mut request_init = request_init_from_request(request);
+    if let Some(stream) = body_stream {
+        match transmit_body(&stream) {
+            Ok(sender) => request_init.body_stream = Some(sender),
+            Err(e) => {
+                promise.reject_error(e);
+                return promise;
+            },
+        }
+    }
     request_init.csp_list = global.get_csp_list().clone();
+    if let Some(window) = global.downcast::<Window>() {
+        request_init.viewport = Some(window.window_size().client_viewport());
+        request_init.site_for_cookies = Some(window.Document().site_for_cookies());
+    }
 
     // Step 3
     if global.downcast::<ServiceWorkerGlobalScope>().is_some() {
@@ -167,11 +190,13 @@ pub fn Fetch(
     response.Headers().set_guard(Guard::Immutable);
 
     // Step 5
-    let (action_sender, action_receiver) = ipc::channel().unwrap();
     let fetch_context = Arc::new(Mutex::new(FetchContext {
-        fetch_promise: Some(TrustedPromise::new(promise.clone())),
+        fetch_promise: Some(PendingPromise::new(
+            &promise,
+            global.networking_task_source(),
+            global.task_canceller(TaskSourceName::Networking),
+        )),
         response_object: Trusted::new(&*response),
-        body: vec![],
         resource_timing: ResourceFetchTiming::new(timing_type),
     }));
     let listener = NetworkListener {
@@ -180,6 +205,23 @@ pub fn Fetch(
         canceller: Some(global.task_canceller(TaskSourceName::Networking)),
     };
 
+    // A keepalive fetch goes on after its document is unloaded, and its response is
+    // dropped then, with the tasks of the document.
+    if request_init.keep_alive {
+        if let Some(window) = global.downcast::<Window>() {
+            let fetching = window
+                .Document()
+                .fetch_keepalive(request_init, move |message| listener.notify_fetch(message));
+            if !fetching {
+                promise.reject_error(Error::Type(
+                    "The bodies of the keepalive requests exceed their quota".to_string(),
+                ));
+            }
+            return promise;
+        }
+    }
+
+    let (action_sender, action_receiver) = ipc::channel().unwrap();
     ROUTER.add_route(
         action_receiver.to_opaque(),
         Box::new(move |message| {
@@ -207,21 +249,16 @@ impl FetchResponseListener for FetchContext {
         // TODO
     }
 
-    #[allow(unrooted_must_root)]
     fn process_response(&mut self, fetch_metadata: Result<FetchMetadata, NetworkError>) {
-        let promise = self
-            .fetch_promise
-            .take()
-            .expect("fetch promise is missing")
-            .root();
+        let promise = self.fetch_promise.take().expect("fetch promise is missing");
 
-        let _ac = enter_realm(&*promise);
+        let response = self.response_object.root();
+        let _ac = enter_realm(&*response);
         match fetch_metadata {
             // Step 4.1
             Err(_) => {
-                promise.reject_error(Error::Type("Network error occurred".to_string()));
-                self.fetch_promise = Some(TrustedPromise::new(promise));
                 self.response_object.root().set_type(DOMResponseType::Error);
+                promise.reject(Error::Type("Network error occurred".to_string()));
                 return;
             },
             // Step 4.2
@@ -254,24 +291,34 @@ impl FetchResponseListener for FetchContext {
                 },
             },
         }
+        // The bodies of opaque responses are filtered out, and the others are received.
+        self.response_object.root().start_receiving_body();
         // Step 4.3
-        promise.resolve_native(&self.response_object.root());
-        self.fetch_promise = Some(TrustedPromise::new(promise));
+        let trusted_response = self.response_object.clone();
+        promise.settle_with(move |promise| promise.resolve_native(&trusted_response.root()));
     }
 
-    fn process_response_chunk(&mut self, mut chunk: Vec<u8>) {
-        self.response_object.root().stream_chunk(chunk.as_slice());
-        self.body.append(&mut chunk);
+    fn process_response_chunk(&mut self, chunk: Vec<u8>) {
+        self.response_object.root().stream_chunk(chunk);
     }
 
-    fn process_response_eof(&mut self, _response: Result<ResourceFetchTiming, NetworkError>) {
+    fn process_response_eof(&mut self, response: Result<ResourceFetchTiming, NetworkError>) {
+        let failed = response.is_err();
         let response = self.response_object.root();
         let _ac = enter_realm(&*response);
-        response.finish(mem::replace(&mut self.body, vec![]));
+        if failed {
+            response.abort_stream_consumer();
+        }
+        response.finish();
         // TODO
         // ... trailerObject is not supported in Servo yet.
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
@@ -335,6 +382,9 @@ pub fn load_whole_resource(
                 })
             },
             FetchResponseMsg::ProcessResponseChunk(data) => buf.extend_from_slice(&data),
+            FetchResponseMsg::ProcessCspViolations(violations) => {
+                global.report_csp_violations(violations, None)
+            },
             FetchResponseMsg::ProcessResponseEOF(Ok(_)) => {
                 let metadata = metadata.unwrap();
                 if let Some(timing) = &metadata.timing {
@@ -15,6 +15,7 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::node::{document_from_node, Node};
 use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
+use content_security_policy as csp;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use net_traits::image_cache::{ImageCache, PendingImageId};
@@ -50,6 +51,11 @@ impl FetchResponseListener for LayoutImageContext {
             .notify_pending_response(self.id, FetchResponseMsg::ProcessResponseEOF(response));
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
@@ -116,7 +122,5 @@ pub fn fetch_image_for_layout(
         .pipeline_id(Some(document.global().pipeline_id()));
 
     // Layout image loads do not delay the document load event.
-    document
-        .loader_mut()
-        .fetch_async_background(request, action_sender);
+    document.fetch_background(request, action_sender);
 }
@@ -54,10 +54,14 @@ extern crate style;
 #[macro_use]
 mod task;
 #[warn(deprecated)]
+mod background_jobs;
+#[warn(deprecated)]
 mod body;
 #[warn(deprecated)]
 pub mod clipboard_provider;
 #[warn(deprecated)]
+mod debugger;
+#[warn(deprecated)]
 mod devtools;
 #[warn(deprecated)]
 pub mod document_loader;
@@ -91,10 +95,14 @@ pub mod script_runtime;
 #[allow(unsafe_code)]
 pub mod script_thread;
 #[warn(deprecated)]
+mod security_manager;
+#[warn(deprecated)]
 mod serviceworker_manager;
 #[warn(deprecated)]
 mod serviceworkerjob;
 #[warn(deprecated)]
+mod speculation_rules;
+#[warn(deprecated)]
 mod stylesheet_loader;
 #[warn(deprecated)]
 mod stylesheet_set;
@@ -2,10 +2,29 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-//! Routines for handling measuring the memory usage of arbitrary DOM nodes.
+//! Routines for handling measuring the memory usage of arbitrary DOM nodes, and for
+//! detecting the windows which leak after their pipeline exited.
 
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::conversions::{get_dom_class, private_from_object};
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::weakref::{WeakRef, WeakReferenceable};
+use crate::dom::document::Document;
+use crate::dom::window::Window;
+use crate::script_runtime::JSContext;
+use js::glue::CollectServoSizes;
+use js::jsapi::{GetNonCCWObjectGlobal, JSObject};
+use js::rust::get_object_class;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
+use profile_traits::mem::{Report, ReportKind};
+use servo_url::ServoUrl;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::CStr;
+use std::mem;
 use std::os::raw::c_void;
+use std::time::{Duration, Instant};
 
 /// Used by codegen to include the pointer to the `MallocSizeOf` implementation of each
 /// IDL interface. This way we don't have to find the most-derived interface of DOM
@@ -17,3 +36,142 @@ pub unsafe fn malloc_size_of_including_raw_self<T: MallocSizeOf>(
 ) -> usize {
     ops.malloc_size_of(obj) + (*(obj as *const T)).size_of(ops)
 }
+
+/// How long a window can stay alive after its pipeline exited before it's considered
+/// leaked, as a "ghost window".
+const GHOST_WINDOW_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Whether a window which is still alive is a ghost window, given when its pipeline
+/// exited.
+pub fn is_ghost_window(discarded_at: Instant, now: Instant) -> bool {
+    now.duration_since(discarded_at) >= GHOST_WINDOW_TIMEOUT
+}
+
+/// A window whose pipeline exited. Its document, which is alive exactly as long as the
+/// window since they refer to each other, is referred to weakly so as not to leak them.
+struct DiscardedWindow {
+    url: ServoUrl,
+    discarded_at: Instant,
+    document: WeakRef<Document>,
+    /// Whether the window was already reported as a ghost window in the logs.
+    reported: bool,
+}
+
+thread_local!(static DISCARDED_WINDOWS: RefCell<Vec<DiscardedWindow>> = RefCell::new(vec![]));
+
+/// The sizes of the DOM objects measured by `measure_dom_object`, by global object and
+/// by interface.
+type DomSizes = HashMap<*mut JSObject, BTreeMap<&'static str, usize>>;
+
+thread_local!(static DOM_SIZES: RefCell<DomSizes> = RefCell::new(HashMap::new()));
+
+/// Starts tracking a window whose pipeline exited, to detect whether it leaks.
+pub fn note_window_discarded(window: &Window) {
+    let document = window.Document();
+    let discarded = DiscardedWindow {
+        url: document.url(),
+        discarded_at: Instant::now(),
+        document: document.downgrade(),
+        reported: false,
+    };
+    DISCARDED_WINDOWS.with(|windows| windows.borrow_mut().push(discarded));
+}
+
+/// Measures a DOM object with the `MallocSizeOf` implementation of its most-derived
+/// interface, recording its size in `DOM_SIZES` under its global and the name of the
+/// interface.
+#[allow(unsafe_code)]
+unsafe extern "C" fn measure_dom_object(obj: *mut JSObject) -> usize {
+    let dom_class = match get_dom_class(obj) {
+        Ok(dom_class) => dom_class,
+        Err(()) => return 0,
+    };
+    let dom_object = private_from_object(obj) as *const c_void;
+    if dom_object.is_null() {
+        return 0;
+    }
+    let mut ops = MallocSizeOfOps::new(servo_allocator::usable_size, None, None);
+    let size = (dom_class.malloc_size_of)(&mut ops, dom_object);
+    if let Ok(name) = CStr::from_ptr((*get_object_class(obj)).name).to_str() {
+        let global = GetNonCCWObjectGlobal(obj);
+        DOM_SIZES.with(|sizes| {
+            *sizes
+                .borrow_mut()
+                .entry(global)
+                .or_insert_with(BTreeMap::new)
+                .entry(name)
+                .or_insert(0) += size;
+        });
+    }
+    size
+}
+
+/// The reports of the memory used by the DOM objects of a global, grouped by interface,
+/// under the given path.
+pub fn interface_reports(sizes: &BTreeMap<&'static str, usize>, path: &[String]) -> Vec<Report> {
+    sizes
+        .iter()
+        .map(|(name, size)| Report {
+            path: path.iter().cloned().chain(path!["dom", *name]).collect(),
+            kind: ReportKind::ExplicitJemallocHeapSize,
+            size: *size,
+        })
+        .collect()
+}
+
+fn global_object(window: &Window) -> *mut JSObject {
+    window.reflector().get_jsobject().get()
+}
+
+/// The reports of the memory used by the DOM objects of the given documents, and by the
+/// ghost windows of the thread: the windows which are still alive long after their
+/// pipeline exited, which are most likely leaked. Every DOM object of the runtime is
+/// measured, and attributed to the window of the realm it was created in.
+#[allow(unsafe_code)]
+pub fn dom_reports(cx: JSContext, documents: &[DomRoot<Document>]) -> Vec<Report> {
+    let measured = unsafe {
+        let mut stats = mem::zeroed();
+        CollectServoSizes(*cx, &mut stats, Some(measure_dom_object))
+    };
+    let mut sizes = DOM_SIZES.with(|sizes| mem::replace(&mut *sizes.borrow_mut(), HashMap::new()));
+    if !measured {
+        return vec![];
+    }
+
+    let mut reports = vec![];
+    for document in documents {
+        if let Some(sizes) = sizes.remove(&global_object(document.window())) {
+            let path = path![format!("url({})", document.url())];
+            reports.extend(interface_reports(&sizes, &path));
+        }
+    }
+
+    DISCARDED_WINDOWS.with(|windows| {
+        let mut windows = windows.borrow_mut();
+        // The windows which were collected since are forgotten.
+        windows.retain(|discarded| discarded.document.root().is_some());
+        let now = Instant::now();
+        for discarded in windows.iter_mut() {
+            if !is_ghost_window(discarded.discarded_at, now) {
+                continue;
+            }
+            if !discarded.reported {
+                warn!(
+                    "Window of {} is still alive after its pipeline exited.",
+                    discarded.url
+                );
+                discarded.reported = true;
+            }
+            let document = match discarded.document.root() {
+                Some(document) => document,
+                None => continue,
+            };
+            if let Some(sizes) = sizes.remove(&global_object(document.window())) {
+                let path = path!["ghost-windows", format!("window({})", discarded.url)];
+                reports.extend(interface_reports(&sizes, &path));
+            }
+        }
+    });
+
+    reports
+}
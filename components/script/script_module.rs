@@ -35,6 +35,7 @@ use crate::realms::{enter_realm, AlreadyInRealm, InRealm};
 use crate::script_runtime::JSContext as SafeJSContext;
 use crate::task::TaskBox;
 use crate::task_source::TaskSourceName;
+use content_security_policy as csp;
 use encoding_rs::UTF_8;
 use hyper_serde::Serde;
 use ipc_channel::ipc;
@@ -1039,6 +1040,11 @@ impl FetchResponseListener for ModuleContext {
         }
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<csp::Violation>) {
+        let global = &self.resource_timing_global();
+        global.report_csp_violations(violations, None);
+    }
+
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
         &mut self.resource_timing
     }
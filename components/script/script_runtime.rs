@@ -11,6 +11,7 @@ use crate::body::BodyOperations;
 use crate::dom::bindings::codegen::Bindings::PromiseBinding::PromiseJobCallback;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseBinding::ResponseMethods;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseType as DOMResponseType;
+use crate::dom::bindings::codegen::Bindings::VoidFunctionBinding::VoidFunction;
 use crate::dom::bindings::conversions::get_dom_class;
 use crate::dom::bindings::conversions::private_from_object;
 use crate::dom::bindings::conversions::root_from_handleobject;
@@ -37,7 +38,7 @@ use crate::task::TaskBox;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::{TaskSource, TaskSourceName};
 use js::glue::{CollectServoSizes, CreateJobQueue, DeleteJobQueue, DispatchableRun};
-use js::glue::{JobQueueTraps, RUST_js_GetErrorMessage, SetBuildId, StreamConsumerConsumeChunk};
+use js::glue::{JobQueueTraps, SetBuildId, StreamConsumerConsumeChunk};
 use js::glue::{
     StreamConsumerNoteResponseURLs, StreamConsumerStreamEnd, StreamConsumerStreamError,
 };
@@ -48,14 +49,18 @@ use js::jsapi::MimeType;
 use js::jsapi::StreamConsumer as JSStreamConsumer;
 use js::jsapi::{BuildIdCharVector, DisableIncrementalGC, GCDescription, GCProgress};
 use js::jsapi::{Dispatchable as JSRunnable, Dispatchable_MaybeShuttingDown};
-use js::jsapi::{HandleObject, Heap, JobQueue};
+use js::jsapi::{GCReason, IncrementalGCSlice, IsIncrementalGCInProgress, JS_GetGCParameter};
+use js::jsapi::{HandleObject, Heap, JS_RequestInterruptCallback, JobQueue};
 use js::jsapi::{JSContext as RawJSContext, JSTracer, SetDOMCallbacks, SetGCSliceCallback};
+use js::jsapi::{JSFunction, JS_GetFunctionObject, SetHostCleanupFinalizationRegistryCallback};
 use js::jsapi::{JSGCInvocationKind, JSGCStatus, JS_AddExtraGCRootsTracer, JS_SetGCCallback};
 use js::jsapi::{JSGCMode, JSGCParamKey, JS_SetGCParameter, JS_SetGlobalJitCompilerOption};
 use js::jsapi::{
     JSJitCompilerOption, JS_SetOffthreadIonCompilationEnabled, JS_SetParallelParsingEnabled,
 };
 use js::jsapi::{JSObject, PromiseRejectionHandlingState, SetPreserveWrapperCallback};
+use js::jsapi::{JSRuntime, JS_SetDefaultLocale};
+use js::jsapi::{NonIncrementalGC, PrepareForFullGC, StartIncrementalGC};
 use js::jsapi::{SetJobQueue, SetProcessBuildIdOp, SetPromiseRejectionTrackerCallback};
 use js::jsval::UndefinedValue;
 use js::panic::wrap_panic;
@@ -69,6 +74,7 @@ use js::rust::{JSEngine, JSEngineHandle};
 use malloc_size_of::MallocSizeOfOps;
 use msg::constellation_msg::PipelineId;
 use profile_traits::mem::{Report, ReportKind, ReportsChan};
+use servo_config::locale;
 use servo_config::opts;
 use servo_config::pref;
 use std::cell::Cell;
@@ -83,7 +89,7 @@ use std::ptr;
 use std::rc::Rc;
 use std::sync::Mutex;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use style::thread_state::{self, ThreadState};
 use time::{now, Tm};
 
@@ -221,6 +227,25 @@ unsafe extern "C" fn enqueue_promise_job(
     )
 }
 
+/// SM callback for finalization registries with cells to clean up. Queues the
+/// cleanup function on the JavaScript engine task source of the incumbent global.
+/// <https://html.spec.whatwg.org/multipage/#hostenqueuefinalizationregistrycleanupjob>
+#[allow(unsafe_code)]
+unsafe extern "C" fn enqueue_finalization_registry_cleanup_job(
+    do_cleanup: *mut JSFunction,
+    incumbent_global: *mut JSObject,
+    _data: *mut c_void,
+) {
+    wrap_panic(
+        AssertUnwindSafe(|| {
+            let global = GlobalScope::from_object(incumbent_global);
+            let job = VoidFunction::new(global.get_cx(), JS_GetFunctionObject(do_cleanup));
+            global.enqueue_finalization_registry_cleanup_job(job);
+        }),
+        (),
+    )
+}
+
 #[allow(unsafe_code, unrooted_must_root)]
 /// https://html.spec.whatwg.org/multipage/#the-hostpromiserejectiontracker-implementation
 unsafe extern "C" fn promise_rejection_tracker(
@@ -438,6 +463,16 @@ pub fn new_rt_and_cx(networking_task_source: Option<NetworkingTaskSource>) -> Ru
     unsafe { new_rt_and_cx_with_parent(None, networking_task_source) }
 }
 
+/// Make the preferred language of the user the default locale of the `Intl` objects and
+/// of the locale-sensitive methods, such as `Date.prototype.toLocaleString`.
+#[allow(unsafe_code)]
+pub unsafe fn set_default_locale(rt: *mut JSRuntime) {
+    let locale = CString::new(locale::language()).unwrap();
+    if !JS_SetDefaultLocale(rt, locale.as_ptr()) {
+        warn!("Failed to set the default locale.");
+    }
+}
+
 #[allow(unsafe_code)]
 unsafe fn new_rt_and_cx_with_parent(
     parent: Option<ParentRuntime>,
@@ -501,9 +536,16 @@ unsafe fn new_rt_and_cx_with_parent(
     );
     SetJobQueue(cx, job_queue);
     SetPromiseRejectionTrackerCallback(cx, Some(promise_rejection_tracker), ptr::null_mut());
+    SetHostCleanupFinalizationRegistryCallback(
+        cx,
+        Some(enqueue_finalization_registry_cleanup_job),
+        ptr::null_mut(),
+    );
 
     EnsureModuleHooksInitialized(runtime.rt());
 
+    set_default_locale(runtime.rt());
+
     set_gc_zeal_options(cx);
 
     // Enable or disable the JITs.
@@ -736,6 +778,158 @@ pub fn get_reports(cx: *mut RawJSContext, path_seg: String) -> Vec<Report> {
     reports
 }
 
+/// The longest idle period.
+/// <https://w3c.github.io/requestidlecallback/#start-an-idle-period-algorithm>
+const MAX_IDLE_PERIOD: Duration = Duration::from_millis(50);
+
+/// The time between two frames, when the next one is expected.
+const FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// The deadline of an idle period of an event loop which starts at a given time, given
+/// how long until its next timer is due and whether it has to render a frame.
+/// <https://w3c.github.io/requestidlecallback/#start-an-idle-period-algorithm>
+pub fn idle_period_deadline(
+    start: Instant,
+    time_until_next_timer: Option<Duration>,
+    rendering_pending: bool,
+) -> Instant {
+    let mut idle_period = MAX_IDLE_PERIOD;
+    if let Some(time_until_next_timer) = time_until_next_timer {
+        idle_period = idle_period.min(time_until_next_timer);
+    }
+    if rendering_pending {
+        idle_period = idle_period.min(FRAME_INTERVAL);
+    }
+    start + idle_period
+}
+
+/// The budget in milliseconds of a slice of garbage collection which has to end before a
+/// deadline, if there's time for one.
+pub fn idle_slice_budget(slice_ms: i64, time_remaining: Duration) -> Option<i64> {
+    let time_remaining = time_remaining.as_millis() as i64;
+    if time_remaining < 1 {
+        return None;
+    }
+    Some(slice_ms.max(1).min(time_remaining))
+}
+
+/// The garbage collections which a script thread schedules itself, on top of the ones
+/// which SpiderMonkey triggers when allocating: incremental ones, sliced over the idle
+/// periods of the event loop, and full ones when the system is under memory pressure.
+#[derive(Default, JSTraceable)]
+pub struct GcScheduler {
+    /// The size of the GC heap after the last collection which was observed.
+    heap_after_last_gc: Cell<u32>,
+    /// The size of the GC heap when the incremental collection started in an idle
+    /// period began, if one is in progress.
+    idle_gc_start_heap: Cell<Option<u32>>,
+    /// The bytes freed by the collections which ran in idle periods.
+    freed_by_idle_gcs: Cell<usize>,
+    /// The bytes freed by the collections which ran because of memory pressure.
+    freed_by_memory_pressure_gcs: Cell<usize>,
+}
+
+#[allow(unsafe_code)]
+impl GcScheduler {
+    /// Whether a slice of garbage collection should run in the current idle period,
+    /// either because an incremental collection is in progress or because the heap
+    /// grew above the idle threshold since the last collection.
+    pub fn is_idle_slice_due(&self, cx: JSContext) -> bool {
+        if !pref!(js.mem.gc.idle.enabled) {
+            return false;
+        }
+        if unsafe { IsIncrementalGCInProgress(*cx) } {
+            return true;
+        }
+        // The collections which SpiderMonkey triggered itself also lower the baseline.
+        let heap = unsafe { JS_GetGCParameter(*cx, JSGCParamKey::JSGC_BYTES) };
+        if heap < self.heap_after_last_gc.get() {
+            self.heap_after_last_gc.set(heap);
+        }
+        let threshold = pref!(js.mem.gc.idle.threshold_mb).max(0) as u32;
+        heap.saturating_sub(self.heap_after_last_gc.get()) >= threshold.saturating_mul(1 << 20)
+    }
+
+    /// Runs a slice of incremental garbage collection which ends before the deadline of
+    /// the idle period, starting a new collection if none is in progress. Returns whether
+    /// there was time for one.
+    pub fn run_idle_slice(&self, cx: JSContext, deadline: Instant) -> bool {
+        let time_remaining = deadline.saturating_duration_since(Instant::now());
+        let budget = match idle_slice_budget(pref!(js.mem.gc.idle.slice_ms), time_remaining) {
+            Some(budget) => budget,
+            None => return false,
+        };
+        unsafe {
+            if IsIncrementalGCInProgress(*cx) {
+                IncrementalGCSlice(*cx, GCReason::INTER_SLICE_GC, budget);
+            } else {
+                let heap = JS_GetGCParameter(*cx, JSGCParamKey::JSGC_BYTES);
+                self.idle_gc_start_heap.set(Some(heap));
+                PrepareForFullGC(*cx);
+                StartIncrementalGC(*cx, JSGCInvocationKind::GC_NORMAL, GCReason::API, budget);
+            }
+            if IsIncrementalGCInProgress(*cx) {
+                return true;
+            }
+        }
+        let heap = self.collection_finished(cx);
+        if let Some(start_heap) = self.idle_gc_start_heap.take() {
+            let freed = start_heap.saturating_sub(heap) as usize;
+            self.freed_by_idle_gcs
+                .set(self.freed_by_idle_gcs.get() + freed);
+        }
+        true
+    }
+
+    /// Runs a full, shrinking garbage collection, finishing any incremental one which
+    /// is in progress, and returns the number of bytes it freed.
+    pub fn collect_on_memory_pressure(&self, cx: JSContext) -> usize {
+        let heap_before = unsafe { JS_GetGCParameter(*cx, JSGCParamKey::JSGC_BYTES) };
+        unsafe {
+            PrepareForFullGC(*cx);
+            NonIncrementalGC(*cx, JSGCInvocationKind::GC_SHRINK, GCReason::MEM_PRESSURE);
+        }
+        self.idle_gc_start_heap.set(None);
+        let heap_after = self.collection_finished(cx);
+        let freed = heap_before.saturating_sub(heap_after) as usize;
+        self.freed_by_memory_pressure_gcs
+            .set(self.freed_by_memory_pressure_gcs.get() + freed);
+        freed
+    }
+
+    fn collection_finished(&self, cx: JSContext) -> u32 {
+        let heap = unsafe { JS_GetGCParameter(*cx, JSGCParamKey::JSGC_BYTES) };
+        self.heap_after_last_gc.set(heap);
+        heap
+    }
+
+    /// The measurements of the collections which were scheduled by the script thread,
+    /// for about:memory.
+    pub fn get_reports(&self, path_seg: String) -> Vec<Report> {
+        let mut reports = vec![];
+        let mut report = |mut path_suffix, size| {
+            let mut path = path![path_seg, "js-gc-scheduler"];
+            path.append(&mut path_suffix);
+            reports.push(Report {
+                path: path,
+                kind: ReportKind::NonExplicitSize,
+                size: size,
+            })
+        };
+
+        report(
+            path!["heap-after-last-gc"],
+            self.heap_after_last_gc.get() as usize,
+        );
+        report(path!["freed", "idle"], self.freed_by_idle_gcs.get());
+        report(
+            path!["freed", "memory-pressure"],
+            self.freed_by_memory_pressure_gcs.get(),
+        );
+        reports
+    }
+}
+
 thread_local!(static GC_CYCLE_START: Cell<Option<Tm>> = Cell::new(None));
 thread_local!(static GC_SLICE_START: Cell<Option<Tm>> = Cell::new(None));
 
@@ -835,6 +1029,27 @@ unsafe fn set_gc_zeal_options(cx: *mut RawJSContext) {
 #[cfg(not(feature = "debugmozjs"))]
 unsafe fn set_gc_zeal_options(_: *mut RawJSContext) {}
 
+/// A JS context, with which the script running in it can be interrupted from
+/// another thread.
+pub struct ContextForRequestInterrupt(*mut RawJSContext);
+
+#[allow(unsafe_code)]
+unsafe impl Send for ContextForRequestInterrupt {}
+
+#[allow(unsafe_code)]
+impl ContextForRequestInterrupt {
+    /// The context must outlive the value, and the threads it's sent to.
+    pub unsafe fn new(cx: *mut RawJSContext) -> ContextForRequestInterrupt {
+        ContextForRequestInterrupt(cx)
+    }
+
+    /// Calls the interrupt callback of the context, as soon as the script running in
+    /// it checks for interrupts.
+    pub fn request_interrupt(&self) {
+        unsafe { JS_RequestInterruptCallback(self.0) };
+    }
+}
+
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 pub struct JSContext(*mut RawJSContext);
@@ -973,7 +1188,19 @@ unsafe extern "C" fn consume_stream(
             );
             return false;
         }
-        unwrapped_source.set_stream_consumer(Some(StreamConsumer(_consumer)));
+        // https://w3c.github.io/webappsec-csp/#can-compile-wasm-bytes
+        if !global.can_compile_wasm_bytes() {
+            throw_dom_exception(
+                cx,
+                &global,
+                Error::Type(
+                    "WebAssembly compilation is blocked by the Content Security Policy".to_string(),
+                ),
+            );
+            return false;
+        }
+
+        unwrapped_source.set_stream_consumer(StreamConsumer(_consumer));
     } else {
         //Step 3 Upon rejection of source, return with reason.
         throw_dom_exception(
@@ -986,11 +1213,18 @@ unsafe extern "C" fn consume_stream(
     return true;
 }
 
+/// Rejects the compilation of a WebAssembly module whose response failed to be fetched, which
+/// is the only error that is reported to stream consumers.
 #[allow(unsafe_code)]
-unsafe extern "C" fn report_stream_error(_cx: *mut RawJSContext, error_code: usize) {
-    error!(
-        "Error initializing StreamConsumer: {:?}",
-        RUST_js_GetErrorMessage(ptr::null_mut(), error_code as u32)
+unsafe extern "C" fn report_stream_error(cx: *mut RawJSContext, error_code: usize) {
+    debug!("Error streaming a WebAssembly module: {}", error_code);
+    let cx = JSContext::from_ptr(cx);
+    let in_realm_proof = AlreadyInRealm::assert_for_cx(cx);
+    let global = GlobalScope::from_context(*cx, InRealm::Already(&in_realm_proof));
+    throw_dom_exception(
+        cx,
+        &global,
+        Error::Type("The response failed to be fetched".to_string()),
     );
 }
 
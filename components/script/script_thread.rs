@@ -17,6 +17,7 @@
 //! a page runs its course and the script thread returns to processing events in the main event
 //! loop.
 
+use crate::debugger::ScriptDebugger;
 use crate::devtools;
 use crate::document_loader::DocumentLoader;
 use crate::dom::bindings::cell::DomRefCell;
@@ -39,6 +40,7 @@ use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom, RootCollection};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::bindings::trace::JSTraceable;
 use crate::dom::bindings::utils::WRAP_CALLBACKS;
+use crate::dom::console::stack_frames;
 use crate::dom::customelementregistry::{
     CallbackReaction, CustomElementDefinition, CustomElementReactionStack,
 };
@@ -56,8 +58,8 @@ use crate::dom::node::{
 };
 use crate::dom::performanceentry::PerformanceEntry;
 use crate::dom::performancepainttiming::PerformancePaintTiming;
-use crate::dom::serviceworker::TrustedServiceWorkerAddress;
-use crate::dom::serviceworkerregistration::ServiceWorkerRegistration;
+use crate::dom::serviceworker::{ServiceWorker, TrustedServiceWorkerAddress};
+use crate::dom::serviceworkerregistration::{longest_prefix_match, ServiceWorkerRegistration};
 use crate::dom::servoparser::{ParserContext, ServoParser};
 use crate::dom::transitionevent::TransitionEvent;
 use crate::dom::uievent::UIEvent;
@@ -67,16 +69,20 @@ use crate::dom::worker::TrustedWorkerAddress;
 use crate::dom::worklet::WorkletThreadPool;
 use crate::dom::workletglobalscope::WorkletGlobalScopeInit;
 use crate::fetch::FetchCanceller;
+use crate::mem::{dom_reports, note_window_discarded};
 use crate::microtask::{Microtask, MicrotaskQueue};
 use crate::realms::enter_realm;
-use crate::script_runtime::{get_reports, new_rt_and_cx, JSContext, Runtime, ScriptPort};
-use crate::script_runtime::{CommonScriptMsg, ScriptChan, ScriptThreadEventCategory};
+use crate::script_runtime::ContextForRequestInterrupt;
+use crate::script_runtime::{get_reports, new_rt_and_cx, set_default_locale};
+use crate::script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort, ScriptThreadEventCategory};
+use crate::script_runtime::{GcScheduler, JSContext, Runtime};
 use crate::serviceworkerjob::{Job, JobQueue};
 use crate::task_manager::TaskManager;
 use crate::task_queue::{QueuedTask, QueuedTaskConversion, TaskQueue};
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::history_traversal::HistoryTraversalTaskSource;
+use crate::task_source::javascript_engine::JavaScriptEngineTaskSource;
 use crate::task_source::media_element::MediaElementTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::performance_timeline::PerformanceTimelineTaskSource;
@@ -92,24 +98,28 @@ use bluetooth_traits::BluetoothRequest;
 use canvas_traits::webgl::WebGLPipeline;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use devtools_traits::CSSError;
-use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo};
-use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
-use embedder_traits::{EmbedderMsg, EventLoopWaker};
+use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo, PauseInfo, ResumeLimit};
+use devtools_traits::{ScriptToDevtoolsControlMsg, SourceInfo, WorkerId};
+use embedder_traits::TaskReport;
+use embedder_traits::{EmbedderMsg, EventLoopWaker, MemoryPressureReport};
+use embedder_traits::{PromptResponse, SavedDocumentState};
 use euclid::default::{Point2D, Rect};
-use euclid::Vector2D;
+use euclid::{Size2D, Vector2D};
 use headers::ReferrerPolicy as ReferrerPolicyHeader;
 use headers::{HeaderMapExt, LastModified};
 use hyper_serde::Serde;
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
 use js::glue::GetWindowProxyClass;
-use js::jsapi::JS_SetWrapObjectCallbacks;
+use js::jsapi::JSContext as RawJSContext;
 use js::jsapi::{JSTracer, SetWindowProxyClass};
+use js::jsapi::{JS_AddInterruptCallback, JS_SetWrapObjectCallbacks};
 use js::jsval::UndefinedValue;
 use js::rust::ParentRuntime;
 use media::WindowGLContext;
 use metrics::{PaintTimeMetrics, MAX_TASK_NS};
 use mime::{self, Mime};
+use msg::constellation_msg::HangProfileSymbol;
 use msg::constellation_msg::{
     BackgroundHangMonitor, BackgroundHangMonitorRegister, ScriptHangAnnotation,
 };
@@ -119,6 +129,7 @@ use msg::constellation_msg::{PipelineNamespace, TopLevelBrowsingContextId};
 use net_traits::image_cache::{ImageCache, PendingImageResponse};
 use net_traits::request::{CredentialsMode, Destination, RedirectMode, RequestBuilder};
 use net_traits::storage_thread::StorageType;
+use net_traits::{CoreResourceMsg, IpcSend};
 use net_traits::{FetchMetadata, FetchResponseListener, FetchResponseMsg};
 use net_traits::{
     Metadata, NetworkError, ReferrerPolicy, ResourceFetchTiming, ResourceThreads,
@@ -130,11 +141,11 @@ use profile_traits::time::{self as profile_time, profile, ProfilerCategory};
 use script_layout_interface::message::{self, LayoutThreadInit, Msg, ReflowGoal};
 use script_traits::webdriver_msg::WebDriverScriptCommand;
 use script_traits::CompositorEvent::{
-    CompositionEvent, KeyboardEvent, MouseButtonEvent, MouseMoveEvent, ResizeEvent, TouchEvent,
-    WheelEvent,
+    CompositionEvent, DoubleTapEvent, KeyboardEvent, MouseButtonEvent, MouseMoveEvent, ResizeEvent,
+    TouchEvent, WheelEvent,
 };
 use script_traits::StructuredSerializedData;
-use script_traits::{CompositorEvent, ConstellationControlMsg};
+use script_traits::{CompositorEvent, ConstellationControlMsg, DOMMessage, EmbedderStylesheets};
 use script_traits::{
     DiscardBrowsingContext, DocumentActivity, EventResult, HistoryEntryReplacement,
 };
@@ -144,12 +155,14 @@ use script_traits::{Painter, ProgressiveWebMetricType, ScriptMsg, ScriptThreadFa
 use script_traits::{ScriptToConstellationChan, TimerSchedulerMsg};
 use script_traits::{TouchEventType, TouchId, UntrustedNodeAddress, WheelDelta};
 use script_traits::{UpdatePipelineIdReason, WebrenderIpcSender, WindowSizeData, WindowSizeType};
+use servo_arc::Arc as ServoArc;
 use servo_atoms::Atom;
+use servo_config::prefs::{self, PrefValue};
 use servo_url::{ImmutableOrigin, MutableOrigin, ServoUrl};
 use std::borrow::Cow;
 use std::cell::Cell;
 use std::cell::RefCell;
-use std::collections::{hash_map, HashMap, HashSet};
+use std::collections::{hash_map, HashMap, HashSet, VecDeque};
 use std::default::Default;
 use std::ops::Deref;
 use std::option::Option;
@@ -159,9 +172,14 @@ use std::result::Result;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
+use style::context::QuirksMode;
 use style::dom::OpaqueNode;
+use style::global_style_data::GLOBAL_STYLE_DATA;
+use style::media_queries::MediaList;
+use style::stylesheets::{DocumentStyleSheet, Origin, Stylesheet};
 use style::thread_state::{self, ThreadState};
+use style_traits::CSSPixel;
 use time::{at_utc, get_time, precise_time_ns, Timespec};
 use url::Position;
 use webrender_api::units::LayoutPixel;
@@ -181,6 +199,31 @@ pub unsafe fn trace_thread(tr: *mut JSTracer) {
     });
 }
 
+/// Sends the stack of the running script to the hang monitor, which requested it
+/// because the script thread hangs.
+#[allow(unsafe_code)]
+unsafe extern "C" fn interrupt_callback(cx: *mut RawJSContext) -> bool {
+    SCRIPT_THREAD_ROOT.with(|root| {
+        let script_thread = match root.get() {
+            Some(script_thread) => &*script_thread,
+            None => return,
+        };
+        if let Some(ref bhm) = script_thread.background_hang_monitor {
+            let stack = stack_frames(JSContext::from_ptr(cx))
+                .into_iter()
+                .map(|frame| HangProfileSymbol {
+                    name: Some(frame.functionName),
+                    filename: Some(frame.filename),
+                    lineno: Some(frame.lineNumber),
+                })
+                .collect();
+            bhm.notify_js_stack(stack);
+        }
+    });
+    // The script goes on.
+    true
+}
+
 /// A document load that is in the process of fetching the requested resource. Contains
 /// data that will need to be present when the document and frame tree entry are created,
 /// but is only easily available at initiation of the load and on a push basis (so some
@@ -217,6 +260,12 @@ struct InProgressLoad {
     canceller: FetchCanceller,
     /// Flag for sharing with the layout thread that is not yet created.
     layout_is_busy: Arc<AtomicBool>,
+    /// The state to restore in the document once loaded, if any.
+    document_state: Option<SavedDocumentState>,
+    /// Whether the previous document of the browsing context was discarded by the embedder.
+    was_discarded: bool,
+    /// Whether the document is sandboxed from showing modal dialogs.
+    sandboxed_modals: bool,
 }
 
 impl InProgressLoad {
@@ -254,6 +303,9 @@ impl InProgressLoad {
             navigation_start_precise: navigation_start_precise,
             canceller: Default::default(),
             layout_is_busy: layout_is_busy,
+            document_state: None,
+            was_discarded: false,
+            sandboxed_modals: false,
         }
     }
 }
@@ -512,6 +564,7 @@ type IncompleteParserContexts = Vec<(PipelineId, ParserContext)>;
 unsafe_no_jsmanaged_fields!(RefCell<IncompleteParserContexts>);
 
 unsafe_no_jsmanaged_fields!(TaskQueue<MainThreadScriptMsg>);
+unsafe_no_jsmanaged_fields!(RefCell<VecDeque<MixedMessage>>);
 
 unsafe_no_jsmanaged_fields!(dyn BackgroundHangMonitorRegister);
 unsafe_no_jsmanaged_fields!(dyn BackgroundHangMonitor);
@@ -538,11 +591,17 @@ pub struct ScriptThread {
     /// A handle to the resource thread. This is an `Arc` to avoid running out of file descriptors if
     /// there are many iframes.
     resource_threads: ResourceThreads,
+    /// Whether the resource threads are those of a private browsing session, whose
+    /// state is only kept in memory.
+    is_private: bool,
     /// A handle to the bluetooth thread.
     bluetooth_thread: IpcSender<BluetoothRequest>,
 
     /// A queue of tasks to be executed in this script-thread.
     task_queue: TaskQueue<MainThreadScriptMsg>,
+    /// The events received while the event loop was paused for a prompt, which are
+    /// handled before any new event once it resumes.
+    paused_events: RefCell<VecDeque<MixedMessage>>,
 
     /// A handle to register associated layout threads for hang-monitoring.
     background_hang_monitor_register: Option<Box<dyn BackgroundHangMonitorRegister>>,
@@ -565,6 +624,8 @@ pub struct ScriptThread {
 
     file_reading_task_sender: Box<dyn ScriptChan>,
 
+    javascript_engine_task_sender: Box<dyn ScriptChan>,
+
     performance_timeline_task_sender: Box<dyn ScriptChan>,
 
     port_message_sender: Box<dyn ScriptChan>,
@@ -607,6 +668,9 @@ pub struct ScriptThread {
     /// The JavaScript runtime.
     js_runtime: Rc<Runtime>,
 
+    /// The garbage collections scheduled in the idle periods and under memory pressure.
+    gc_scheduler: GcScheduler,
+
     /// The topmost element over the mouse.
     topmost_mouse_over_target: MutNullableDom<Element>,
 
@@ -696,6 +760,21 @@ pub struct ScriptThread {
 
     /// Code is running as a consequence of a user interaction
     is_user_interacting: Cell<bool>,
+
+    /// The debugger of the scripts of this thread, created when a devtools client
+    /// first attaches to it.
+    debugger: DomRefCell<Option<Rc<ScriptDebugger>>>,
+
+    /// The preferences overridden by the embedder for a top-level browsing context.
+    pref_overrides: DomRefCell<HashMap<TopLevelBrowsingContextId, HashMap<String, PrefValue>>>,
+
+    /// The user agent and user stylesheets supplied by the embedder for a top-level
+    /// browsing context.
+    embedder_stylesheets: DomRefCell<HashMap<TopLevelBrowsingContextId, Vec<DocumentStyleSheet>>>,
+
+    /// The top-level browsing contexts which report the size of their content to the
+    /// embedder when a reflow changes it.
+    auto_resize_browsers: DomRefCell<HashSet<TopLevelBrowsingContextId>>,
 }
 
 /// In the event of thread panic, all data on the stack runs its destructor. However, there
@@ -935,6 +1014,42 @@ impl ScriptThread {
         });
     }
 
+    /// The value of a preference in the given top-level browsing context, which may be
+    /// overridden by the embedder.
+    pub fn pref(
+        top_level_browsing_context_id: Option<TopLevelBrowsingContextId>,
+        key: &str,
+    ) -> PrefValue {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = match root.get() {
+                Some(script_thread) => unsafe { &*script_thread },
+                None => return prefs::pref_map().get(key),
+            };
+            top_level_browsing_context_id
+                .and_then(|id| {
+                    script_thread
+                        .pref_overrides
+                        .borrow()
+                        .get(&id)
+                        .and_then(|overrides| overrides.get(key).cloned())
+                })
+                .unwrap_or_else(|| prefs::pref_map().get(key))
+        })
+    }
+
+    /// Replace the stylesheets supplied by the embedder for a browser, for the tests.
+    pub fn set_embedder_stylesheets(
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        stylesheets: EmbedderStylesheets,
+    ) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            if let Some(script_thread) = root.get() {
+                let script_thread = unsafe { &*script_thread };
+                script_thread.handle_set_stylesheets(top_level_browsing_context_id, stylesheets);
+            }
+        })
+    }
+
     // https://html.spec.whatwg.org/multipage/#await-a-stable-state
     pub fn await_stable_state(task: Microtask) {
         SCRIPT_THREAD_ROOT.with(|root| {
@@ -1080,7 +1195,8 @@ impl ScriptThread {
                     .borrow()
                     .iter()
                     .filter_map(|(id, document)| {
-                        if document.is_fully_active() {
+                        // The tasks of the frozen documents wait until they are resumed.
+                        if document.is_fully_active() && !document.is_frozen() {
                             Some(id.clone())
                         } else {
                             None
@@ -1139,6 +1255,7 @@ impl ScriptThread {
                         image_cache: script_thread.image_cache.clone(),
                         is_headless: script_thread.headless,
                         user_agent: script_thread.user_agent.clone(),
+                        is_private: script_thread.is_private,
                     };
                     Rc::new(WorkletThreadPool::spawn(init))
                 })
@@ -1246,6 +1363,34 @@ impl ScriptThread {
         })
     }
 
+    /// Tell the devtools server that the debugger found a new source.
+    pub fn debugger_new_source(pipeline_id: PipelineId, source: SourceInfo) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            if let Some(ref chan) = script_thread.devtools_chan {
+                let msg = ScriptToDevtoolsControlMsg::NewSource(pipeline_id, source);
+                let _ = chan.send(msg);
+            }
+        })
+    }
+
+    /// Block the execution of the scripts of this thread while the debugger is
+    /// paused, returning how far it should step once the devtools client resumes it.
+    pub fn debugger_paused(pipeline_id: PipelineId, pause: PauseInfo) -> Option<ResumeLimit> {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            script_thread.handle_debugger_pause(pipeline_id, pause)
+        })
+    }
+
+    /// Pause the event loop of this thread until the given prompt is answered.
+    pub fn pause_for_prompt(receiver: Receiver<PromptResponse>) -> PromptResponse {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            script_thread.handle_prompt_pause(receiver)
+        })
+    }
+
     /// Creates a new script thread.
     pub fn new(
         state: InitialScriptState,
@@ -1291,6 +1436,13 @@ impl ScriptThread {
             )
         });
 
+        // The hang monitor interrupts the scripts which hang, to capture their stack.
+        if let Some(ref bhm) = background_hang_monitor {
+            let context = unsafe { ContextForRequestInterrupt::new(cx) };
+            bhm.set_js_stack_requester(Box::new(move || context.request_interrupt()));
+            unsafe { JS_AddInterruptCallback(cx, Some(interrupt_callback)) };
+        }
+
         // Ask the router to proxy IPC messages from the control port to us.
         let control_port = ROUTER.route_ipc_receiver_to_new_crossbeam_receiver(state.control_port);
 
@@ -1307,9 +1459,11 @@ impl ScriptThread {
             image_cache_port: image_cache_port,
 
             resource_threads: state.resource_threads,
+            is_private: state.is_private,
             bluetooth_thread: state.bluetooth_thread,
 
             task_queue,
+            paused_events: Default::default(),
 
             background_hang_monitor_register: state.background_hang_monitor_register,
             background_hang_monitor,
@@ -1321,6 +1475,7 @@ impl ScriptThread {
             networking_task_sender: boxed_script_sender.clone(),
             port_message_sender: boxed_script_sender.clone(),
             file_reading_task_sender: boxed_script_sender.clone(),
+            javascript_engine_task_sender: boxed_script_sender.clone(),
             performance_timeline_task_sender: boxed_script_sender.clone(),
             timer_task_sender: boxed_script_sender.clone(),
             remote_event_task_sender: boxed_script_sender.clone(),
@@ -1340,6 +1495,7 @@ impl ScriptThread {
             microtask_queue: runtime.microtask_queue.clone(),
 
             js_runtime: Rc::new(runtime),
+            gc_scheduler: Default::default(),
             topmost_mouse_over_target: MutNullableDom::new(Default::default()),
             closed_pipelines: DomRefCell::new(HashSet::new()),
 
@@ -1384,9 +1540,17 @@ impl ScriptThread {
 
             node_ids: Default::default(),
             is_user_interacting: Cell::new(false),
+            debugger: Default::default(),
+            pref_overrides: Default::default(),
+            embedder_stylesheets: Default::default(),
+            auto_resize_browsers: Default::default(),
         }
     }
 
+    fn debugger(&self) -> Option<Rc<ScriptDebugger>> {
+        self.debugger.borrow().clone()
+    }
+
     #[allow(unsafe_code)]
     pub fn get_cx(&self) -> JSContext {
         unsafe { JSContext::from_ptr(self.js_runtime.cx()) }
@@ -1403,6 +1567,34 @@ impl ScriptThread {
         debug!("Stopped script thread.");
     }
 
+    /// Whether the event loop has no event to process.
+    fn is_idle(&self) -> bool {
+        self.paused_events.borrow().is_empty() &&
+            self.task_queue.queue_length() == 0 &&
+            self.control_port.is_empty() &&
+            self.devtools_port.is_empty() &&
+            self.image_cache_port.is_empty()
+    }
+
+    /// The deadline of an idle period of the event loop starting now, before the next
+    /// timer of its documents is due and the next frame is rendered.
+    fn idle_period_deadline(&self) -> Instant {
+        let documents = self.documents.borrow();
+        let time_until_next_timer = documents
+            .iter()
+            .filter_map(|(_, document)| {
+                document
+                    .window()
+                    .upcast::<GlobalScope>()
+                    .time_until_next_timer()
+            })
+            .min();
+        let rendering_pending = documents
+            .iter()
+            .any(|(_, document)| document.has_animation_frame_callbacks());
+        idle_period_deadline(Instant::now(), time_until_next_timer, rendering_pending)
+    }
+
     /// Handle incoming control messages.
     fn handle_msgs(&self) -> bool {
         use self::MixedMessage::FromScript;
@@ -1426,6 +1618,17 @@ impl ScriptThread {
         // Store new resizes, and gather all other events.
         let mut sequential = vec![];
 
+        // Use the idle period, if any, to collect the garbage, one slice at a time
+        // so that the events which arrive in the meantime aren't delayed for long.
+        if self.is_idle() && self.gc_scheduler.is_idle_slice_due(self.get_cx()) {
+            let deadline = self.idle_period_deadline();
+            while self.is_idle() && self.gc_scheduler.is_idle_slice_due(self.get_cx()) {
+                if !self.gc_scheduler.run_idle_slice(self.get_cx(), deadline) {
+                    break;
+                }
+            }
+        }
+
         // Notify the background-hang-monitor we are waiting for an event.
         self.background_hang_monitor
             .as_ref()
@@ -1433,19 +1636,24 @@ impl ScriptThread {
 
         // Receive at least one message so we don't spinloop.
         debug!("Waiting for event.");
-        let mut event = select! {
-            recv(self.task_queue.select()) -> msg => {
-                self.task_queue.take_tasks(msg.unwrap());
-                let event = self
-                    .task_queue
-                    .recv()
-                    .expect("Spurious wake-up of the event-loop, task-queue has no tasks available");
-                FromScript(event)
-            },
-            recv(self.control_port) -> msg => FromConstellation(msg.unwrap()),
-            recv(self.devtools_chan.as_ref().map(|_| &self.devtools_port).unwrap_or(&crossbeam_channel::never())) -> msg
-                => FromDevtools(msg.unwrap()),
-            recv(self.image_cache_port) -> msg => FromImageCache(msg.unwrap()),
+        let paused_event = self.paused_events.borrow_mut().pop_front();
+        let mut event = if let Some(event) = paused_event {
+            event
+        } else {
+            select! {
+                recv(self.task_queue.select()) -> msg => {
+                    self.task_queue.take_tasks(msg.unwrap());
+                    let event = self
+                        .task_queue
+                        .recv()
+                        .expect("Spurious wake-up of the event-loop, task-queue has no tasks available");
+                    FromScript(event)
+                },
+                recv(self.control_port) -> msg => FromConstellation(msg.unwrap()),
+                recv(self.devtools_chan.as_ref().map(|_| &self.devtools_port).unwrap_or(&crossbeam_channel::never())) -> msg
+                    => FromDevtools(msg.unwrap()),
+                recv(self.image_cache_port) -> msg => FromImageCache(msg.unwrap()),
+            }
         };
         debug!("Got event.");
 
@@ -1538,6 +1746,11 @@ impl ScriptThread {
             // If any of our input sources has an event pending, we'll perform another iteration
             // and check for more resize events. If there are no events pending, we'll move
             // on and execute the sequential non-resize events we've seen.
+            let paused_event = self.paused_events.borrow_mut().pop_front();
+            if let Some(paused_event) = paused_event {
+                event = paused_event;
+                continue;
+            }
             match self.control_port.try_recv() {
                 Err(_) => match self.task_queue.try_recv() {
                     Err(_) => match self.devtools_port.try_recv() {
@@ -1577,7 +1790,7 @@ impl ScriptThread {
             });
 
             // https://html.spec.whatwg.org/multipage/#event-loop-processing-model step 6
-            self.perform_a_microtask_checkpoint();
+            ScriptThread::perform_a_microtask_checkpoint();
 
             if let Some(retval) = result {
                 return retval;
@@ -1610,6 +1823,9 @@ impl ScriptThread {
                 .upcast::<GlobalScope>()
                 .perform_a_dom_garbage_collection_checkpoint();
 
+            // https://html.spec.whatwg.org/multipage/#event-loop-processing-model step 7.7
+            window.maybe_evaluate_media_queries_and_report_changes();
+
             let pending_reflows = window.get_pending_reflow_count();
             if pending_reflows > 0 {
                 window.reflow(ReflowGoal::Full, ReflowReason::ImageLoaded);
@@ -1620,6 +1836,9 @@ impl ScriptThread {
                 // minimize unnecessary work.
                 window.reflow(ReflowGoal::Full, ReflowReason::MissingExplicitReflow);
             }
+
+            // https://drafts.csswg.org/resize-observer/#html-event-loop
+            document.update_resize_observations();
         }
 
         true
@@ -1705,6 +1924,7 @@ impl ScriptThread {
                     SetScrollState(id, ..) => Some(id),
                     GetTitle(id) => Some(id),
                     SetDocumentActivity(id, ..) => Some(id),
+                    SetDocumentFrozen(id, ..) => Some(id),
                     ChangeFrameVisibilityStatus(id, ..) => Some(id),
                     NotifyVisibilityChange(id, ..) => Some(id),
                     NavigateIframe(id, ..) => Some(id),
@@ -1730,6 +1950,20 @@ impl ScriptThread {
                     PaintMetric(..) => None,
                     ExitFullScreen(id, ..) => Some(id),
                     MediaSessionAction(..) => None,
+                    GetSavedDocumentState(id, ..) => Some(id),
+                    GetDocumentScrollSize(id, ..) => Some(id),
+                    SetVisualViewport(id, ..) => Some(id),
+                    NotifyUserActivation(id) => Some(id),
+                    ConsumeUserActivation(id) => Some(id),
+                    ExitPictureInPicture(id) => Some(id),
+                    MediaDevicesChanged(id) => Some(id),
+                    MemoryPressure(..) => None,
+                    SetPreference(..) => None,
+                    SetPreferenceOverrides(..) => None,
+                    SetStylesheets(..) => None,
+                    SetAutoResize(..) => None,
+                    ClaimByServiceWorker(id, ..) => Some(id),
+                    PostMessageFromServiceWorker(id, ..) => Some(id),
                 }
             },
             MixedMessage::FromDevtools(_) => None,
@@ -1821,9 +2055,38 @@ impl ScriptThread {
             }
             doc.record_tti_if_necessary();
         }
+        if let Some(pipeline_id) = pipeline_id {
+            self.report_long_task(pipeline_id, category, end - start);
+        }
         value
     }
 
+    /// Report a task which ran for longer than the `dom.instrumentation.long_task_threshold`
+    /// pref to the embedder, through the constellation.
+    fn report_long_task(
+        &self,
+        pipeline_id: PipelineId,
+        category: ScriptThreadEventCategory,
+        duration_ns: u64,
+    ) {
+        if !pref!(dom.instrumentation.enabled) {
+            return;
+        }
+        let threshold_ns = pref!(dom.instrumentation.long_task_threshold).max(0) as u64 * 1_000_000;
+        if duration_ns < threshold_ns {
+            return;
+        }
+        let report = TaskReport {
+            pipeline_id,
+            category: format!("{:?}", category),
+            duration: Duration::from_nanos(duration_ns),
+            queue_length: self.task_queue.queue_length(),
+        };
+        let _ = self
+            .script_sender
+            .send((pipeline_id, ScriptMsg::ReportLongTask(report)));
+    }
+
     fn handle_msg_from_constellation(&self, msg: ConstellationControlMsg) {
         match msg {
             ConstellationControlMsg::StopDelayingLoadEventsMode(pipeline_id) => {
@@ -1852,6 +2115,9 @@ impl ScriptThread {
                 load_data,
                 replace,
             ),
+            ConstellationControlMsg::Navigate(pipeline_id, load_data, replace) => {
+                self.handle_navigate(pipeline_id, load_data, replace)
+            },
             ConstellationControlMsg::UnloadDocument(pipeline_id) => {
                 self.handle_unload_document(pipeline_id)
             },
@@ -1865,6 +2131,12 @@ impl ScriptThread {
             ConstellationControlMsg::SetDocumentActivity(pipeline_id, activity) => {
                 self.handle_set_document_activity_msg(pipeline_id, activity)
             },
+            ConstellationControlMsg::SetDocumentFrozen(pipeline_id, frozen) => {
+                let document = self.documents.borrow().find_document(pipeline_id);
+                if let Some(document) = document {
+                    document.set_frozen(frozen);
+                }
+            },
             ConstellationControlMsg::ChangeFrameVisibilityStatus(pipeline_id, visible) => {
                 self.handle_visibility_change_msg(pipeline_id, visible)
             },
@@ -1955,6 +2227,75 @@ impl ScriptThread {
             ConstellationControlMsg::MediaSessionAction(pipeline_id, action) => {
                 self.handle_media_session_action(pipeline_id, action)
             },
+            ConstellationControlMsg::GetSavedDocumentState(pipeline_id, sender) => {
+                self.handle_get_saved_document_state(pipeline_id, sender)
+            },
+            ConstellationControlMsg::MemoryPressure(sender) => self.handle_memory_pressure(sender),
+            ConstellationControlMsg::SetPreference(key, value) => {
+                if let Err(e) = prefs::pref_map().set(&key, value) {
+                    warn!("Failed to set preference {} ({:?}).", key, e);
+                }
+                // User preference media features and the rules enabled by preferences
+                // may have changed.
+                for (_, document) in self.documents.borrow().iter() {
+                    let window = document.window();
+                    window.media_queries_need_evaluation();
+                    if let Err(e) = window.layout_chan().send(Msg::PreferencesChanged) {
+                        warn!("Failed to send preference change to layout ({:?}).", e);
+                    }
+                    document.invalidate_stylesheets();
+                }
+                if key == "intl.accept_languages" {
+                    self.handle_languages_change();
+                }
+            },
+            ConstellationControlMsg::SetPreferenceOverrides(
+                top_level_browsing_context_id,
+                overrides,
+            ) => {
+                let mut pref_overrides = self.pref_overrides.borrow_mut();
+                if overrides.is_empty() {
+                    pref_overrides.remove(&top_level_browsing_context_id);
+                } else {
+                    pref_overrides.insert(top_level_browsing_context_id, overrides);
+                }
+            },
+            ConstellationControlMsg::SetStylesheets(top_level_browsing_context_id, stylesheets) => {
+                self.handle_set_stylesheets(top_level_browsing_context_id, stylesheets)
+            },
+            ConstellationControlMsg::SetAutoResize(top_level_browsing_context_id, enabled) => {
+                self.handle_set_auto_resize(top_level_browsing_context_id, enabled)
+            },
+            ConstellationControlMsg::ClaimByServiceWorker(pipeline_id, scope_url) => {
+                self.handle_claim_by_serviceworker(pipeline_id, scope_url)
+            },
+            ConstellationControlMsg::PostMessageFromServiceWorker(pipeline_id, scope_url, msg) => {
+                self.handle_post_message_from_serviceworker(pipeline_id, scope_url, msg)
+            },
+            ConstellationControlMsg::GetDocumentScrollSize(pipeline_id, sender) => {
+                self.handle_get_document_scroll_size(pipeline_id, sender)
+            },
+            ConstellationControlMsg::SetVisualViewport(pipeline_id, scale, offset) => {
+                if let Some(window) = self.documents.borrow().find_window(pipeline_id) {
+                    window.set_visual_viewport(scale as f64, offset);
+                }
+            },
+            ConstellationControlMsg::NotifyUserActivation(pipeline_id) => {
+                if let Some(window) = self.documents.borrow().find_window(pipeline_id) {
+                    window.set_user_activation();
+                }
+            },
+            ConstellationControlMsg::ConsumeUserActivation(pipeline_id) => {
+                if let Some(window) = self.documents.borrow().find_window(pipeline_id) {
+                    window.clear_user_activation();
+                }
+            },
+            ConstellationControlMsg::ExitPictureInPicture(pipeline_id) => {
+                self.handle_exit_picture_in_picture(pipeline_id)
+            },
+            ConstellationControlMsg::MediaDevicesChanged(pipeline_id) => {
+                self.handle_media_devices_changed(pipeline_id)
+            },
             msg @ ConstellationControlMsg::AttachLayout(..) |
             msg @ ConstellationControlMsg::Viewport(..) |
             msg @ ConstellationControlMsg::SetScrollState(..) |
@@ -1968,7 +2309,12 @@ impl ScriptThread {
 
     fn handle_msg_from_script(&self, msg: MainThreadScriptMsg) {
         match msg {
-            MainThreadScriptMsg::Common(CommonScriptMsg::Task(_, task, _, _)) => task.run_box(),
+            MainThreadScriptMsg::Common(CommonScriptMsg::Task(_, task, _, task_source)) => {
+                if let Some(ref bhm) = self.background_hang_monitor {
+                    bhm.notify_task(task_source.as_str(), task.name());
+                }
+                task.run_box()
+            },
             MainThreadScriptMsg::Common(CommonScriptMsg::CollectReports(chan)) => {
                 self.collect_reports(chan)
             },
@@ -2011,6 +2357,24 @@ impl ScriptThread {
             DevtoolScriptControlMsg::ModifyAttribute(id, node_id, modifications) => {
                 devtools::handle_modify_attribute(&*documents, id, node_id, modifications)
             },
+            DevtoolScriptControlMsg::GetComputedStyle(id, node_id, reply) => {
+                devtools::handle_get_computed_style(&*documents, id, node_id, reply)
+            },
+            DevtoolScriptControlMsg::GetAppliedStyles(id, node_id, reply) => {
+                devtools::handle_get_applied_styles(&*documents, id, node_id, reply)
+            },
+            DevtoolScriptControlMsg::ModifyInlineStyle(id, node_id, modifications) => {
+                devtools::handle_modify_inline_style(&*documents, id, node_id, modifications)
+            },
+            DevtoolScriptControlMsg::WantsDomMutations(id, to_send) => {
+                devtools::handle_wants_dom_mutations(&*documents, id, to_send)
+            },
+            DevtoolScriptControlMsg::HighlightNode(id, node_id) => {
+                devtools::handle_highlight_node(&*documents, id, node_id)
+            },
+            DevtoolScriptControlMsg::SetNodePicking(id, picking) => {
+                devtools::handle_set_node_picking(&*documents, id, picking)
+            },
             DevtoolScriptControlMsg::WantsLiveNotifications(id, to_send) => match documents
                 .find_window(id)
             {
@@ -2027,6 +2391,147 @@ impl ScriptThread {
                 devtools::handle_request_animation_frame(&*documents, id, name)
             },
             DevtoolScriptControlMsg::Reload(id) => devtools::handle_reload(&*documents, id),
+            DevtoolScriptControlMsg::AttachDebugger(id, reply) => {
+                let window = match documents.find_window(id) {
+                    Some(window) => window,
+                    None => return warn!("Message sent to closed pipeline {}.", id),
+                };
+                let debugger = self
+                    .debugger
+                    .borrow_mut()
+                    .get_or_insert_with(|| ScriptDebugger::new(self.get_cx()))
+                    .clone();
+                let _ = reply.send(debugger.add_debuggee(window.upcast(), id));
+            },
+            DevtoolScriptControlMsg::DetachDebugger(id) => {
+                let window = match documents.find_window(id) {
+                    Some(window) => window,
+                    None => return warn!("Message sent to closed pipeline {}.", id),
+                };
+                if let Some(debugger) = self.debugger() {
+                    debugger.remove_debuggee(window.upcast());
+                }
+            },
+            DevtoolScriptControlMsg::GetSourceText(_, source_id, reply) => {
+                let text = self
+                    .debugger()
+                    .and_then(|debugger| debugger.source_text(self.get_cx(), source_id));
+                let _ = reply.send(text);
+            },
+            DevtoolScriptControlMsg::GetBreakpointPositions(_, source_id, reply) => {
+                let positions = self
+                    .debugger()
+                    .map(|debugger| debugger.breakpoint_positions(self.get_cx(), source_id));
+                let _ = reply.send(positions.unwrap_or(vec![]));
+            },
+            DevtoolScriptControlMsg::SetBreakpoint(_, location) => {
+                if let Some(debugger) = self.debugger() {
+                    debugger.set_breakpoint(self.get_cx(), location);
+                }
+            },
+            DevtoolScriptControlMsg::RemoveBreakpoint(_, location) => {
+                if let Some(debugger) = self.debugger() {
+                    debugger.remove_breakpoint(self.get_cx(), location);
+                }
+            },
+            DevtoolScriptControlMsg::Interrupt(_) => {
+                if let Some(debugger) = self.debugger() {
+                    debugger.interrupt(self.get_cx());
+                }
+            },
+            // Only meaningful while the debugger is paused, see `handle_debugger_pause`.
+            DevtoolScriptControlMsg::Resume(..) => {},
+            DevtoolScriptControlMsg::GetFrames(_, reply) => {
+                let frames = self
+                    .debugger()
+                    .map(|debugger| debugger.frames(self.get_cx()));
+                let _ = reply.send(frames.unwrap_or(vec![]));
+            },
+            DevtoolScriptControlMsg::GetEnvironment(_, frame_id, reply) => {
+                let scopes = self
+                    .debugger()
+                    .map(|debugger| debugger.environment(self.get_cx(), frame_id));
+                let _ = reply.send(scopes.unwrap_or(vec![]));
+            },
+            DevtoolScriptControlMsg::StartProfiler(id, rate, max_duration) => {
+                match documents.find_window(id) {
+                    Some(window) => window.send_to_constellation(ScriptMsg::EnableProfiler(
+                        Duration::from_millis(rate),
+                        Duration::from_millis(max_duration),
+                    )),
+                    None => return warn!("Message sent to closed pipeline {}.", id),
+                }
+            },
+            DevtoolScriptControlMsg::StopProfiler(id, reply) => match documents.find_window(id) {
+                Some(window) => {
+                    window.send_to_constellation(ScriptMsg::DisableProfiler(Some(reply)))
+                },
+                None => return warn!("Message sent to closed pipeline {}.", id),
+            },
+        }
+    }
+
+    /// Handle the devtools messages until the paused debugger is resumed, leaving the
+    /// other events of the thread queued.
+    fn handle_debugger_pause(
+        &self,
+        pipeline_id: PipelineId,
+        pause: PauseInfo,
+    ) -> Option<ResumeLimit> {
+        let chan = self.devtools_chan.as_ref()?;
+        chan.send(ScriptToDevtoolsControlMsg::DebuggerPaused(
+            pipeline_id,
+            pause,
+        ))
+        .ok()?;
+
+        self.background_hang_monitor
+            .as_ref()
+            .map(|bhm| bhm.notify_wait());
+
+        loop {
+            match self.devtools_port.recv() {
+                Ok(DevtoolScriptControlMsg::Resume(_, limit)) => return limit,
+                Ok(msg @ DevtoolScriptControlMsg::DetachDebugger(..)) => {
+                    self.handle_msg_from_devtools(msg);
+                    return None;
+                },
+                Ok(msg) => self.handle_msg_from_devtools(msg),
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Wait for the answer to a prompt with the event loop paused: no task runs until it
+    /// is answered, and the other events of the thread are queued to be handled once it
+    /// resumes. Resizes and scrolls, which only update the state of the windows, are
+    /// still handled to keep the pages responsive.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#pause>
+    fn handle_prompt_pause(&self, receiver: Receiver<PromptResponse>) -> PromptResponse {
+        use self::MixedMessage::{FromConstellation, FromDevtools, FromImageCache};
+
+        self.background_hang_monitor
+            .as_ref()
+            .map(|bhm| bhm.notify_wait());
+
+        loop {
+            let event = select! {
+                recv(receiver) -> response => return response.unwrap_or(PromptResponse::Dismiss),
+                recv(self.control_port) -> msg => FromConstellation(msg.unwrap()),
+                recv(self.devtools_chan.as_ref().map(|_| &self.devtools_port).unwrap_or(&crossbeam_channel::never())) -> msg
+                    => FromDevtools(msg.unwrap()),
+                recv(self.image_cache_port) -> msg => FromImageCache(msg.unwrap()),
+            };
+            match event {
+                FromConstellation(ConstellationControlMsg::Resize(id, size, size_type)) => {
+                    self.handle_resize(id, size, size_type)
+                },
+                FromConstellation(ConstellationControlMsg::SetScrollState(id, scroll_state)) => {
+                    self.handle_set_scroll_state(id, &scroll_state)
+                },
+                event => self.paused_events.borrow_mut().push_back(event),
+            }
         }
     }
 
@@ -2087,6 +2592,14 @@ impl ScriptThread {
                     reply,
                 )
             },
+            WebDriverScriptCommand::FindElementXPath(selector, reply) => {
+                webdriver_handlers::handle_find_element_xpath(
+                    &*documents,
+                    pipeline_id,
+                    selector,
+                    reply,
+                )
+            },
             WebDriverScriptCommand::FindElementsCSS(selector, reply) => {
                 webdriver_handlers::handle_find_elements_css(
                     &*documents,
@@ -2112,6 +2625,14 @@ impl ScriptThread {
                     reply,
                 )
             },
+            WebDriverScriptCommand::FindElementsXPath(selector, reply) => {
+                webdriver_handlers::handle_find_elements_xpath(
+                    &*documents,
+                    pipeline_id,
+                    selector,
+                    reply,
+                )
+            },
             WebDriverScriptCommand::FindElementElementCSS(selector, element_id, reply) => {
                 webdriver_handlers::handle_find_element_element_css(
                     &*documents,
@@ -2143,6 +2664,15 @@ impl ScriptThread {
                     reply,
                 )
             },
+            WebDriverScriptCommand::FindElementElementXPath(selector, element_id, reply) => {
+                webdriver_handlers::handle_find_element_element_xpath(
+                    &*documents,
+                    pipeline_id,
+                    element_id,
+                    selector,
+                    reply,
+                )
+            },
             WebDriverScriptCommand::FindElementElementsCSS(selector, element_id, reply) => {
                 webdriver_handlers::handle_find_element_elements_css(
                     &*documents,
@@ -2174,6 +2704,15 @@ impl ScriptThread {
                     reply,
                 )
             },
+            WebDriverScriptCommand::FindElementElementsXPath(selector, element_id, reply) => {
+                webdriver_handlers::handle_find_element_elements_xpath(
+                    &*documents,
+                    pipeline_id,
+                    element_id,
+                    selector,
+                    reply,
+                )
+            },
             WebDriverScriptCommand::FocusElement(element_id, reply) => {
                 webdriver_handlers::handle_focus_element(
                     &*documents,
@@ -2182,6 +2721,14 @@ impl ScriptThread {
                     reply,
                 )
             },
+            WebDriverScriptCommand::ElementClear(element_id, reply) => {
+                webdriver_handlers::handle_element_clear(
+                    &*documents,
+                    pipeline_id,
+                    element_id,
+                    reply,
+                )
+            },
             WebDriverScriptCommand::ElementClick(element_id, reply) => {
                 webdriver_handlers::handle_element_click(
                     &*documents,
@@ -2290,12 +2837,37 @@ impl ScriptThread {
     fn handle_exit_fullscreen(&self, id: PipelineId) {
         let document = self.documents.borrow().find_document(id);
         if let Some(document) = document {
+            // The embedder leaving fullscreen is not an error when nothing is fullscreen.
+            if document.GetFullscreenElement().is_none() {
+                return;
+            }
             let _ac = enter_realm(&*document);
             document.exit_fullscreen();
             return;
         }
     }
 
+    fn handle_exit_picture_in_picture(&self, id: PipelineId) {
+        let document = match self.documents.borrow().find_document(id) {
+            Some(document) => document,
+            None => return,
+        };
+        if document.GetPictureInPictureElement().is_some() {
+            let _ac = enter_realm(&*document);
+            document.exit_picture_in_picture();
+        }
+    }
+
+    fn handle_media_devices_changed(&self, id: PipelineId) {
+        let window = match self.documents.borrow().find_window(id) {
+            Some(window) => window,
+            None => return,
+        };
+        if let Some(media_devices) = window.Navigator().media_devices() {
+            media_devices.devices_changed();
+        }
+    }
+
     fn handle_viewport(&self, id: PipelineId, rect: Rect<f32>) {
         let document = self.documents.borrow().find_document(id);
         if let Some(document) = document {
@@ -2396,7 +2968,7 @@ impl ScriptThread {
         };
 
         // Kick off the fetch for the new resource.
-        let new_load = InProgressLoad::new(
+        let mut new_load = InProgressLoad::new(
             new_pipeline_id,
             browsing_context_id,
             top_level_browsing_context_id,
@@ -2408,6 +2980,7 @@ impl ScriptThread {
             origin,
             layout_is_busy.clone(),
         );
+        new_load.sandboxed_modals = load_data.sandboxed_modals;
         if load_data.url.as_str() == "about:blank" {
             self.start_page_load_about_blank(new_load, load_data.js_eval_result);
         } else if load_data.url.as_str() == "about:srcdoc" {
@@ -2423,7 +2996,10 @@ impl ScriptThread {
         let path_seg = format!("url({})", urls);
 
         let mut reports = vec![];
-        reports.extend(get_reports(*self.get_cx(), path_seg));
+        reports.extend(get_reports(*self.get_cx(), path_seg.clone()));
+        reports.extend(self.gc_scheduler.get_reports(path_seg));
+        let documents: Vec<_> = documents.iter().map(|(_, document)| document).collect();
+        reports.extend(dom_reports(self.get_cx(), &documents));
         reports_chan.send(reports);
     }
 
@@ -2636,6 +3212,10 @@ impl ScriptThread {
         let window = self.documents.borrow().find_window(id)
             .expect("ScriptThread: received a resize msg for a pipeline not in this script thread. This is a bug.");
         window.set_window_size(new_size);
+        // Media queries, and the `srcset` candidates of images, have to be evaluated
+        // again once the document is fully active, since the viewport size or the
+        // device pixel ratio may have changed.
+        window.media_queries_need_evaluation();
     }
 
     /// We have received notification that the response associated with a load has completed.
@@ -2788,6 +3368,13 @@ impl ScriptThread {
         FileReadingTaskSource(self.file_reading_task_sender.clone(), pipeline_id)
     }
 
+    pub fn javascript_engine_task_source(
+        &self,
+        pipeline_id: PipelineId,
+    ) -> JavaScriptEngineTaskSource {
+        JavaScriptEngineTaskSource(self.javascript_engine_task_sender.clone(), pipeline_id)
+    }
+
     pub fn remote_event_task_source(&self, pipeline_id: PipelineId) -> RemoteEventTaskSource {
         RemoteEventTaskSource(self.remote_event_task_sender.clone(), pipeline_id)
     }
@@ -2875,6 +3462,7 @@ impl ScriptThread {
                 window.discard_browsing_context();
             }
             window.clear_js_runtime();
+            note_window_discarded(&window);
         }
 
         debug!("Exited pipeline {}.", id);
@@ -3011,6 +3599,88 @@ impl ScriptThread {
         storage.queue_storage_event(url, key, old_value, new_value);
     }
 
+    /// The window of a pipeline, and the service worker of a scope registered in this
+    /// script thread.
+    fn window_and_serviceworker(
+        &self,
+        pipeline_id: PipelineId,
+        scope_url: &ServoUrl,
+    ) -> Option<(DomRoot<Window>, DomRoot<ServiceWorker>)> {
+        let window = match self.documents.borrow().find_window(pipeline_id) {
+            Some(window) => window,
+            None => {
+                warn!(
+                    "Service worker message sent to closed pipeline {}.",
+                    pipeline_id
+                );
+                return None;
+            },
+        };
+        let worker = self
+            .handle_get_registration(scope_url)
+            .and_then(|registration| registration.get_newest_worker());
+        match worker {
+            Some(worker) => Some((window, worker)),
+            None => {
+                warn!(
+                    "No service worker for {} in pipeline {}.",
+                    scope_url, pipeline_id
+                );
+                None
+            },
+        }
+    }
+
+    /// Make the service worker of a scope the controller of a document if its url is in
+    /// the scope.
+    /// <https://w3c.github.io/ServiceWorker/#clients-claim>
+    fn handle_claim_by_serviceworker(&self, pipeline_id: PipelineId, scope_url: ServoUrl) {
+        let document = match self.documents.borrow().find_document(pipeline_id) {
+            Some(document) => document,
+            None => return warn!("Service worker claimed closed pipeline {}.", pipeline_id),
+        };
+        // Step 3.3-3.4
+        if !longest_prefix_match(&scope_url, &document.url()) {
+            return;
+        }
+        let (window, worker) = match self.window_and_serviceworker(pipeline_id, &scope_url) {
+            Some(window_and_worker) => window_and_worker,
+            None => return,
+        };
+        let container = Trusted::new(&*window.Navigator().ServiceWorker());
+        let worker = Trusted::new(&*worker);
+        // Step 3.5
+        let _ = window.task_manager().dom_manipulation_task_source().queue(
+            task!(claim_by_serviceworker: move || {
+                container.root().claim(&*worker.root());
+            }),
+            window.upcast(),
+        );
+    }
+
+    /// Dispatch a message from the service worker of a scope to a document.
+    /// <https://w3c.github.io/ServiceWorker/#client-postmessage>
+    fn handle_post_message_from_serviceworker(
+        &self,
+        pipeline_id: PipelineId,
+        scope_url: ServoUrl,
+        msg: DOMMessage,
+    ) {
+        let (window, worker) = match self.window_and_serviceworker(pipeline_id, &scope_url) {
+            Some(window_and_worker) => window_and_worker,
+            None => return,
+        };
+        let container = Trusted::new(&*window.Navigator().ServiceWorker());
+        let worker = Trusted::new(&*worker);
+        // TODO: Use the client message queue, which starts to be run with `startMessages()`.
+        let _ = window.task_manager().dom_manipulation_task_source().queue(
+            task!(post_message_from_serviceworker: move || {
+                container.root().dispatch_message(&*worker.root(), msg);
+            }),
+            window.upcast(),
+        );
+    }
+
     /// Notify the containing document of a child iframe that has completed loading.
     fn handle_iframe_load_event(
         &self,
@@ -3187,6 +3857,7 @@ impl ScriptThread {
             self.dom_manipulation_task_source(incomplete.pipeline_id),
             self.file_reading_task_source(incomplete.pipeline_id),
             self.history_traversal_task_source(incomplete.pipeline_id),
+            self.javascript_engine_task_source(incomplete.pipeline_id),
             self.media_element_task_source(incomplete.pipeline_id),
             self.networking_task_source(incomplete.pipeline_id),
             self.performance_timeline_task_source(incomplete.pipeline_id)
@@ -3233,6 +3904,7 @@ impl ScriptThread {
             self.headless,
             self.replace_surrogates,
             self.user_agent.clone(),
+            self.is_private,
             self.player_context.clone(),
             self.event_loop_waker.as_ref().map(|w| (*w).clone_box()),
         );
@@ -3252,7 +3924,34 @@ impl ScriptThread {
             // when this navigation algorithm later matures.
             window_proxy.stop_delaying_load_events_mode();
         }
+
+        // The stylesheets of the embedder reach layout before the first reflow.
+        if let Some(stylesheets) = self
+            .embedder_stylesheets
+            .borrow()
+            .get(&incomplete.top_level_browsing_context_id)
+        {
+            let msg = Msg::SetEmbedderStylesheets(stylesheets.clone());
+            if let Err(e) = window.layout_chan().send(msg) {
+                warn!("Failed to send stylesheets to layout ({:?}).", e);
+            }
+        }
+        if pref!(network.content_blocker.enabled) {
+            if let Some(stylesheet) = self.element_hiding_stylesheet(&final_url) {
+                let msg = Msg::SetElementHidingStylesheet(stylesheet);
+                if let Err(e) = window.layout_chan().send(msg) {
+                    warn!("Failed to send stylesheet to layout ({:?}).", e);
+                }
+            }
+        }
         window.init_window_proxy(&window_proxy);
+        if window_proxy.parent().is_none() &&
+            self.auto_resize_browsers
+                .borrow()
+                .contains(&incomplete.top_level_browsing_context_id)
+        {
+            window.set_auto_resize(true);
+        }
 
         let last_modified = metadata.headers.as_ref().and_then(|headers| {
             headers
@@ -3311,6 +4010,11 @@ impl ScriptThread {
             referrer_policy,
             incomplete.canceller,
         );
+        if let Some(state) = incomplete.document_state {
+            document.set_state_to_restore(state);
+        }
+        document.set_was_discarded(incomplete.was_discarded);
+        document.set_sandboxed_modals(incomplete.sandboxed_modals);
         document.set_ready_state(DocumentReadyState::Loading);
 
         self.documents
@@ -3380,6 +4084,7 @@ impl ScriptThread {
             let page_info = DevtoolsPageInfo {
                 title: String::from(title),
                 url: url,
+                is_private: self.is_private,
             };
             chan.send(ScriptToDevtoolsControlMsg::NewGlobal(
                 ids,
@@ -3478,15 +4183,11 @@ impl ScriptThread {
                         .filter_map(DomRoot::downcast::<HTMLAnchorElement>)
                         .next()
                     {
-                        let status = anchor
+                        let url = anchor
                             .upcast::<Element>()
                             .get_attribute(&ns!(), &local_name!("href"))
-                            .and_then(|href| {
-                                let value = href.value();
-                                let url = document.url();
-                                url.join(&value).map(|url| url.to_string()).ok()
-                            });
-                        let event = EmbedderMsg::Status(status);
+                            .and_then(|href| document.url().join(&href.value()).ok());
+                        let event = EmbedderMsg::HoveredLinkChanged(url);
                         window.send_to_embedder(event);
 
                         state_already_changed = true;
@@ -3502,7 +4203,7 @@ impl ScriptThread {
                             .filter_map(DomRoot::downcast::<HTMLAnchorElement>)
                             .next()
                         {
-                            let event = EmbedderMsg::Status(None);
+                            let event = EmbedderMsg::HoveredLinkChanged(None);
                             window.send_to_embedder(event);
                         }
                     }
@@ -3538,6 +4239,14 @@ impl ScriptThread {
                 self.handle_wheel_event(pipeline_id, delta, point, node_address);
             },
 
+            DoubleTapEvent(point, node_address) => {
+                let document = match { self.documents.borrow().find_document(pipeline_id) } {
+                    Some(document) => document,
+                    None => return warn!("Message sent to closed pipeline {}.", pipeline_id),
+                };
+                document.handle_double_tap_event(self.js_runtime.rt(), point, node_address);
+            },
+
             KeyboardEvent(key_event) => {
                 let document = match { self.documents.borrow().find_document(pipeline_id) } {
                     Some(document) => document,
@@ -3715,18 +4424,40 @@ impl ScriptThread {
                 0i32,
             );
             uievent.upcast::<Event>().fire(window.upcast());
+            window.visual_viewport_resized();
         }
 
         // https://html.spec.whatwg.org/multipage/#event-loop-processing-model
         // Step 7.7 - evaluate media queries and report changes
-        // Since we have resized, we need to re-evaluate MQLs
-        window.evaluate_media_queries_and_report_changes();
+        // Since we have resized, we need to re-evaluate MQLs once the pending events
+        // are processed.
+        window.media_queries_need_evaluation();
     }
 
     /// Instructs the constellation to fetch the document that will be loaded. Stores the InProgressLoad
     /// argument until a notification is received that the fetch is complete.
     fn pre_page_load(&self, mut incomplete: InProgressLoad, load_data: LoadData) {
         let id = incomplete.pipeline_id.clone();
+        incomplete.document_state = load_data.document_state;
+        incomplete.was_discarded = load_data.was_discarded;
+        incomplete.sandboxed_modals = load_data.sandboxed_modals;
+        // The site for cookies of a nested navigation is that of its parent document, which is
+        // cross-site when it is in another event loop, and that of a top-level navigation is
+        // the origin of the document which initiated it.
+        let site_for_cookies = match incomplete.parent_info {
+            Some(parent_id) => Some(
+                self.documents
+                    .borrow()
+                    .find_document(parent_id)
+                    .map_or_else(ImmutableOrigin::new_opaque, |parent| {
+                        parent.site_for_cookies()
+                    }),
+            ),
+            None => match load_data.load_origin {
+                LoadOrigin::Script(ref origin) => Some(origin.clone()),
+                LoadOrigin::Constellation | LoadOrigin::WebDriver => None,
+            },
+        };
         let req_init = RequestBuilder::new(load_data.url.clone())
             .method(load_data.method)
             .destination(Destination::Document)
@@ -3738,7 +4469,10 @@ impl ScriptThread {
             .headers(load_data.headers)
             .body(load_data.data)
             .redirect_mode(RedirectMode::Manual)
-            .origin(incomplete.origin.immutable().clone());
+            .origin(incomplete.origin.immutable().clone())
+            .viewport(Some(incomplete.window_size.client_viewport()))
+            .site_for_cookies(site_for_cookies)
+            .top_level_navigation(incomplete.parent_info.is_none());
 
         let context = ParserContext::new(id, load_data.url);
         self.incomplete_parser_contexts
@@ -3879,6 +4613,20 @@ impl ScriptThread {
         }
     }
 
+    /// Navigate the window of a pipeline on behalf of a document of another script
+    /// thread, which only scrolls the document if the url only differs by its fragment.
+    fn handle_navigate(
+        &self,
+        pipeline_id: PipelineId,
+        load_data: LoadData,
+        replace: HistoryEntryReplacement,
+    ) {
+        let window = self.documents.borrow().find_window(pipeline_id);
+        if let Some(window) = window {
+            window.load_url(replace, false, load_data);
+        }
+    }
+
     fn handle_reload(&self, pipeline_id: PipelineId) {
         let window = self.documents.borrow().find_window(pipeline_id);
         if let Some(window) = window {
@@ -3922,6 +4670,185 @@ impl ScriptThread {
         };
     }
 
+    fn handle_get_saved_document_state(
+        &self,
+        pipeline_id: PipelineId,
+        sender: IpcSender<Option<SavedDocumentState>>,
+    ) {
+        let state = self
+            .documents
+            .borrow()
+            .find_document(pipeline_id)
+            .map(|document| document.saved_state());
+        if let Err(e) = sender.send(state) {
+            warn!("Failed to send saved document state ({:?}).", e);
+        }
+    }
+
+    /// Parse the stylesheets supplied by the embedder for a browser, and restyle its
+    /// documents with them.
+    fn handle_set_stylesheets(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        stylesheets: EmbedderStylesheets,
+    ) {
+        let user_agent = stylesheets
+            .user_agent
+            .iter()
+            .map(|stylesheet| (stylesheet, Origin::UserAgent));
+        let user = stylesheets
+            .user
+            .iter()
+            .map(|stylesheet| (stylesheet, Origin::User));
+        let stylesheets: Vec<_> = user_agent
+            .chain(user)
+            .map(|(&(ref source, ref url), origin)| parse_stylesheet(source, url, origin))
+            .collect();
+
+        let documents: Vec<_> = self
+            .documents
+            .borrow()
+            .iter()
+            .map(|(_, document)| document)
+            .filter(|document| {
+                document
+                    .window()
+                    .undiscarded_window_proxy()
+                    .map_or(false, |window_proxy| {
+                        window_proxy.top_level_browsing_context_id() ==
+                            top_level_browsing_context_id
+                    })
+            })
+            .collect();
+        for document in documents {
+            let window = document.window();
+            let msg = Msg::SetEmbedderStylesheets(stylesheets.clone());
+            if let Err(e) = window.layout_chan().send(msg) {
+                warn!("Failed to send stylesheets to layout ({:?}).", e);
+            }
+            if document.is_fully_active() {
+                self.rebuild_and_force_reflow(&document, ReflowReason::EmbedderStylesheetsChanged);
+            }
+        }
+
+        let mut embedder_stylesheets = self.embedder_stylesheets.borrow_mut();
+        if stylesheets.is_empty() {
+            embedder_stylesheets.remove(&top_level_browsing_context_id);
+        } else {
+            embedder_stylesheets.insert(top_level_browsing_context_id, stylesheets);
+        }
+    }
+
+    fn handle_set_auto_resize(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        enabled: bool,
+    ) {
+        {
+            let mut auto_resize_browsers = self.auto_resize_browsers.borrow_mut();
+            if enabled {
+                auto_resize_browsers.insert(top_level_browsing_context_id);
+            } else {
+                auto_resize_browsers.remove(&top_level_browsing_context_id);
+            }
+        }
+        let windows: Vec<_> = self
+            .documents
+            .borrow()
+            .iter()
+            .map(|(_, document)| DomRoot::from_ref(document.window()))
+            .filter(|window| {
+                window.is_top_level() &&
+                    window
+                        .undiscarded_window_proxy()
+                        .map_or(false, |window_proxy| {
+                            window_proxy.top_level_browsing_context_id() ==
+                                top_level_browsing_context_id
+                        })
+            })
+            .collect();
+        for window in windows {
+            window.set_auto_resize(enabled);
+        }
+    }
+
+    /// Get the stylesheet which hides the elements matching the element hiding rules of
+    /// the content blocker, for the page at the given URL.
+    fn element_hiding_stylesheet(&self, url: &ServoUrl) -> Option<DocumentStyleSheet> {
+        let (sender, receiver) = ipc::channel().expect("Failed to create IPC channel!");
+        let msg = CoreResourceMsg::GetElementHidingStylesheet(url.clone(), sender);
+        if let Err(e) = self.resource_threads.send(msg) {
+            warn!("Failed to get the element hiding stylesheet ({:?}).", e);
+            return None;
+        }
+        let source = receiver.recv().ok()??;
+        Some(parse_stylesheet(&source, url, Origin::UserAgent))
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-navigator-languages>
+    #[allow(unsafe_code)]
+    fn handle_languages_change(&self) {
+        unsafe { set_default_locale(self.js_runtime.rt()) };
+        for (_, document) in self.documents.borrow().iter() {
+            let window = document.window();
+            window
+                .task_manager()
+                .dom_manipulation_task_source()
+                .queue_simple_event(window.upcast(), atom!("languagechange"), &window);
+        }
+    }
+
+    fn handle_memory_pressure(&self, sender: IpcSender<MemoryPressureReport>) {
+        // Collect the garbage first, so that the images of the collected elements
+        // can be evicted.
+        let js_heap = self.gc_scheduler.collect_on_memory_pressure(self.get_cx());
+
+        let images = self.image_cache.evict_unused_images();
+
+        // The images of the CSS properties aren't kept by the DOM, so the documents
+        // are laid out again to reload the ones which are still used.
+        if images > 0 {
+            let documents: Vec<_> = self
+                .documents
+                .borrow()
+                .iter()
+                .map(|(_, document)| document)
+                .filter(|document| document.is_fully_active())
+                .collect();
+            for document in documents {
+                self.rebuild_and_force_reflow(&document, ReflowReason::MemoryPressure);
+            }
+        }
+
+        let report = MemoryPressureReport {
+            js_heap: js_heap,
+            images: images,
+            documents: 0,
+        };
+        if let Err(e) = sender.send(report) {
+            warn!("Failed to send memory pressure report ({:?}).", e);
+        }
+    }
+
+    fn handle_get_document_scroll_size(
+        &self,
+        pipeline_id: PipelineId,
+        sender: IpcSender<Option<Size2D<f32, CSSPixel>>>,
+    ) {
+        let size = self
+            .documents
+            .borrow()
+            .find_document(pipeline_id)
+            .and_then(|document| {
+                let element = document.GetDocumentElement()?;
+                let area = document.window().scroll_area_query(element.upcast());
+                Some(Size2D::new(area.size.width as f32, area.size.height as f32))
+            });
+        if let Err(e) = sender.send(size) {
+            warn!("Failed to send document scroll size ({:?}).", e);
+        }
+    }
+
     pub fn enqueue_microtask(job: Microtask) {
         SCRIPT_THREAD_ROOT.with(|root| {
             let script_thread = unsafe { &*root.get().unwrap() };
@@ -3931,19 +4858,25 @@ impl ScriptThread {
         });
     }
 
-    fn perform_a_microtask_checkpoint(&self) {
-        let globals = self
-            .documents
-            .borrow()
-            .iter()
-            .map(|(_id, document)| document.global())
-            .collect();
+    /// Perform a microtask checkpoint of the script thread, which runs each microtask
+    /// in the global of its pipeline, and notifies the globals of all documents about
+    /// their rejected promises.
+    pub fn perform_a_microtask_checkpoint() {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            let globals = script_thread
+                .documents
+                .borrow()
+                .iter()
+                .map(|(_id, document)| document.global())
+                .collect();
 
-        self.microtask_queue.checkpoint(
-            self.get_cx(),
-            |id| self.documents.borrow().find_global(id),
-            globals,
-        )
+            script_thread.microtask_queue.checkpoint(
+                script_thread.get_cx(),
+                |id| script_thread.documents.borrow().find_global(id),
+                globals,
+            )
+        })
     }
 }
 
@@ -3955,6 +4888,22 @@ impl Drop for ScriptThread {
     }
 }
 
+/// Parse a stylesheet of the user agent or user origins, which isn't owned by a document.
+fn parse_stylesheet(source: &str, url: &ServoUrl, origin: Origin) -> DocumentStyleSheet {
+    let shared_lock = &GLOBAL_STYLE_DATA.shared_lock;
+    DocumentStyleSheet(ServoArc::new(Stylesheet::from_str(
+        source,
+        url.clone(),
+        origin,
+        ServoArc::new(shared_lock.wrap(MediaList::empty())),
+        shared_lock.clone(),
+        None,
+        None,
+        QuirksMode::NoQuirks,
+        0,
+    )))
+}
+
 fn dom_last_modified(tm: &SystemTime) -> String {
     let tm = tm.duration_since(SystemTime::UNIX_EPOCH).unwrap();
     let tm = Timespec::new(tm.as_secs() as i64, 0);
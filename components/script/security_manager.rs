@@ -0,0 +1,352 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Reporting of the violations of the Content Security Policies of a global, to script
+//! with `securitypolicyviolation` events and to servers with `report-uri` and
+//! `report-to`.
+
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventInit;
+use crate::dom::bindings::codegen::Bindings::SecurityPolicyViolationEventBinding::{
+    SecurityPolicyViolationEventDisposition, SecurityPolicyViolationEventInit,
+};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::bindings::str::{DOMString, USVString};
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::securitypolicyviolationevent::SecurityPolicyViolationEvent;
+use crate::task::TaskOnce;
+use content_security_policy as csp;
+use http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use http::Method;
+use msg::constellation_msg::PipelineId;
+use net_traits::request::{
+    CredentialsMode, Destination, RedirectMode, RequestBuilder, RequestMode,
+};
+use net_traits::{CoreResourceMsg, CoreResourceThread, FetchChannels};
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// How long the reports which a global sent are remembered.
+const REPORT_WINDOW: Duration = Duration::from_secs(60);
+
+/// The number of distinct reports which a global sends within `REPORT_WINDOW`.
+const MAX_REPORTS_PER_WINDOW: usize = 100;
+
+/// Limits the violation reports which a global sends to servers, so that a page which
+/// violates its policies repeatedly doesn't flood their endpoints: identical reports are
+/// sent once per window of time, and at most `MAX_REPORTS_PER_WINDOW` are sent in it.
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct CSPReportLimiter {
+    #[ignore_malloc_size_of = "Instant"]
+    window_start: Instant,
+    sent_reports: HashSet<Vec<u8>>,
+}
+
+impl CSPReportLimiter {
+    pub fn new() -> CSPReportLimiter {
+        CSPReportLimiter {
+            window_start: Instant::now(),
+            sent_reports: HashSet::new(),
+        }
+    }
+
+    /// Whether a report can be sent, which records it as sent if so.
+    fn allow(&mut self, report: &[u8]) -> bool {
+        let now = Instant::now();
+        if now.duration_since(self.window_start) >= REPORT_WINDOW {
+            self.window_start = now;
+            self.sent_reports.clear();
+        }
+        if self.sent_reports.len() >= MAX_REPORTS_PER_WINDOW || self.sent_reports.contains(report) {
+            return false;
+        }
+        self.sent_reports.insert(report.to_vec());
+        true
+    }
+}
+
+/// The endpoint groups of the `Report-To` header of a response, keyed by name.
+pub type ReportingEndpoints = HashMap<String, ServoUrl>;
+
+/// <https://w3c.github.io/reporting/#header>
+///
+/// The groups without a valid endpoint are ignored, as is the whole header when it is not
+/// a list of JSON objects.
+pub fn parse_report_to_header(headers: &HeaderMap) -> ReportingEndpoints {
+    let mut endpoints = ReportingEndpoints::new();
+    for value in headers.get_all("report-to") {
+        let value = match value.to_str() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let groups: Vec<serde_json::Value> = match serde_json::from_str(&format!("[{}]", value)) {
+            Ok(groups) => groups,
+            Err(_) => continue,
+        };
+        for group in groups {
+            let name = group
+                .get("group")
+                .and_then(|name| name.as_str())
+                .unwrap_or("default");
+            let url = group
+                .get("endpoints")
+                .and_then(|endpoints| endpoints.as_array())
+                .and_then(|endpoints| endpoints.first())
+                .and_then(|endpoint| endpoint.get("url"))
+                .and_then(|url| url.as_str())
+                .and_then(|url| ServoUrl::parse(url).ok());
+            if let Some(url) = url {
+                endpoints.entry(name.to_owned()).or_insert(url);
+            }
+        }
+    }
+    endpoints
+}
+
+/// <https://w3c.github.io/webappsec-csp/#strip-url-for-use-in-reports>
+fn strip_url_for_use_in_reports(url: &ServoUrl) -> String {
+    if !matches!(url.scheme(), "http" | "https") {
+        return url.scheme().to_owned();
+    }
+    let mut url = url.as_url().clone();
+    url.set_fragment(None);
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.into_string()
+}
+
+/// The serialization of a policy, as in the header which delivered it.
+fn serialize_policy(policy: &csp::Policy) -> String {
+    policy
+        .directive_set
+        .iter()
+        .map(|directive| {
+            let mut serialized = directive.name.clone();
+            for value in &directive.value {
+                serialized.push(' ');
+                serialized.push_str(value);
+            }
+            serialized
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// The values of a directive of a policy.
+fn directive_values<'a>(policy: &'a csp::Policy, name: &str) -> Option<&'a [String]> {
+    policy
+        .directive_set
+        .iter()
+        .find(|directive| directive.name == name)
+        .map(|directive| &directive.value[..])
+}
+
+/// The details of a violation, as exposed to script and sent in reports.
+#[derive(Clone, Debug)]
+struct ViolationDetails {
+    document_url: String,
+    referrer: String,
+    blocked_url: String,
+    effective_directive: String,
+    original_policy: String,
+    disposition: SecurityPolicyViolationEventDisposition,
+    status_code: u16,
+}
+
+impl ViolationDetails {
+    fn disposition(&self) -> &'static str {
+        match self.disposition {
+            SecurityPolicyViolationEventDisposition::Enforce => "enforce",
+            SecurityPolicyViolationEventDisposition::Report => "report",
+        }
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#deprecated-serialize-violation>
+    fn deprecated_serialization(&self) -> Vec<u8> {
+        serde_json::json!({
+            "csp-report": {
+                "document-uri": self.document_url,
+                "referrer": self.referrer,
+                "blocked-uri": self.blocked_url,
+                "effective-directive": self.effective_directive,
+                "violated-directive": self.effective_directive,
+                "original-policy": self.original_policy,
+                "disposition": self.disposition(),
+                "status-code": self.status_code,
+            }
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    /// <https://w3c.github.io/reporting/#serialize-reports>
+    fn report_serialization(&self, user_agent: &str) -> Vec<u8> {
+        serde_json::json!([{
+            "type": "csp-violation",
+            "age": 0,
+            "url": self.document_url,
+            "user_agent": user_agent,
+            "body": {
+                "documentURL": self.document_url,
+                "referrer": self.referrer,
+                "blockedURL": self.blocked_url,
+                "effectiveDirective": self.effective_directive,
+                "originalPolicy": self.original_policy,
+                "disposition": self.disposition(),
+                "statusCode": self.status_code,
+            }
+        }])
+        .to_string()
+        .into_bytes()
+    }
+}
+
+/// A task which reports a violation of the policies of a global.
+pub struct CSPViolationReporter {
+    target: Trusted<EventTarget>,
+    details: ViolationDetails,
+    report_uris: Vec<ServoUrl>,
+    report_to: Option<ServoUrl>,
+    user_agent: String,
+    origin: ImmutableOrigin,
+    pipeline_id: PipelineId,
+    core_resource_thread: CoreResourceThread,
+}
+
+impl CSPViolationReporter {
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    pub fn new(
+        global: &GlobalScope,
+        target: &EventTarget,
+        violation: &csp::Violation,
+        referrer: String,
+        endpoints: &ReportingEndpoints,
+    ) -> CSPViolationReporter {
+        let url = global.get_url();
+        let blocked_url = match &violation.resource {
+            csp::ViolationResource::Url(blocked_url) => {
+                strip_url_for_use_in_reports(&ServoUrl::from_url(blocked_url.clone()))
+            },
+            csp::ViolationResource::Inline { .. } => "inline".to_owned(),
+        };
+        let policy = &violation.policy;
+        let details = ViolationDetails {
+            document_url: strip_url_for_use_in_reports(&url),
+            referrer,
+            blocked_url,
+            effective_directive: violation.directive.name.clone(),
+            original_policy: serialize_policy(policy),
+            disposition: match policy.disposition {
+                csp::PolicyDisposition::Enforce => SecurityPolicyViolationEventDisposition::Enforce,
+                csp::PolicyDisposition::Report => SecurityPolicyViolationEventDisposition::Report,
+            },
+            status_code: 0,
+        };
+
+        // Step 3.3. `report-to` takes precedence over `report-uri`.
+        let report_to = directive_values(policy, "report-to")
+            .and_then(|groups| groups.first())
+            .and_then(|group| endpoints.get(group))
+            .cloned();
+        let report_uris = match (&report_to, directive_values(policy, "report-uri")) {
+            (None, Some(uris)) => uris.iter().filter_map(|uri| url.join(uri).ok()).collect(),
+            _ => vec![],
+        };
+
+        CSPViolationReporter {
+            target: Trusted::new(target),
+            details,
+            report_uris,
+            report_to,
+            user_agent: global.get_user_agent().into_owned(),
+            origin: global.origin().immutable().clone(),
+            pipeline_id: global.pipeline_id(),
+            core_resource_thread: global.core_resource_thread(),
+        }
+    }
+
+    fn event_init(&self) -> SecurityPolicyViolationEventInit {
+        let details = &self.details;
+        SecurityPolicyViolationEventInit {
+            parent: EventInit {
+                bubbles: true,
+                cancelable: false,
+            },
+            documentURI: USVString(details.document_url.clone()),
+            referrer: USVString(details.referrer.clone()),
+            blockedURI: USVString(details.blocked_url.clone()),
+            effectiveDirective: DOMString::from(details.effective_directive.clone()),
+            violatedDirective: DOMString::from(details.effective_directive.clone()),
+            originalPolicy: DOMString::from(details.original_policy.clone()),
+            sourceFile: USVString(String::new()),
+            sample: DOMString::new(),
+            disposition: details.disposition,
+            statusCode: details.status_code,
+            lineNumber: 0,
+            columnNumber: 0,
+        }
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>, steps 3.3 and 3.4.
+    fn send_report(&self, url: ServoUrl, content_type: &'static str, body: Vec<u8>) {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        let request = RequestBuilder::new(url)
+            .method(Method::POST)
+            .headers(headers)
+            .body(Some(body))
+            .destination(Destination::Report)
+            .mode(RequestMode::NoCors)
+            .credentials_mode(CredentialsMode::CredentialsSameOrigin)
+            .redirect_mode(RedirectMode::Error)
+            .origin(self.origin.clone())
+            .pipeline_id(Some(self.pipeline_id));
+        // Reports are sent in the background, and their responses are ignored.
+        let _ = self
+            .core_resource_thread
+            .send(CoreResourceMsg::Fetch(request, FetchChannels::Prefetch));
+    }
+}
+
+impl TaskOnce for CSPViolationReporter {
+    fn run_once(self) {
+        // Step 3.1.
+        let target = self.target.root();
+        let event = SecurityPolicyViolationEvent::new(
+            &target.global(),
+            atom!("securitypolicyviolation"),
+            EventBubbles::Bubble,
+            EventCancelable::NotCancelable,
+            &self.event_init(),
+        );
+        event.upcast::<Event>().fire(&target);
+
+        if self.report_uris.is_empty() && self.report_to.is_none() {
+            return;
+        }
+        let report = self.details.deprecated_serialization();
+        if !target.global().csp_report_limiter().allow(&report) {
+            debug!("Not sending a repeated or excess violation report.");
+            return;
+        }
+
+        // Step 3.3.
+        for url in &self.report_uris {
+            self.send_report(url.clone(), "application/csp-report", report.clone());
+        }
+
+        // Step 3.4.
+        if let Some(url) = &self.report_to {
+            self.send_report(
+                url.clone(),
+                "application/reports+json",
+                self.details.report_serialization(&self.user_agent),
+            );
+        }
+    }
+}
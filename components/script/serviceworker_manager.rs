@@ -15,7 +15,8 @@ use devtools_traits::{DevtoolsPageInfo, ScriptToDevtoolsControlMsg};
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
 use net_traits::{CoreResourceMsg, CustomResponseMediator};
-use script_traits::{DOMMessage, SWManagerMsg, SWManagerSenders, ScopeThings, ServiceWorkerMsg};
+use script_traits::{ClientInfo, DOMMessage, SWManagerMsg, SWManagerSenders};
+use script_traits::{ScopeThings, ServiceWorkerMsg};
 use servo_config::pref;
 use servo_url::ServoUrl;
 use std::collections::HashMap;
@@ -37,6 +38,8 @@ pub struct ServiceWorkerManager {
     own_port: Receiver<ServiceWorkerMsg>,
     // to receive resource messages
     resource_receiver: Receiver<CustomResponseMediator>,
+    // sender to send messages to the constellation, which knows the clients
+    constellation_sender: IpcSender<SWManagerMsg>,
 }
 
 impl ServiceWorkerManager {
@@ -44,6 +47,7 @@ impl ServiceWorkerManager {
         own_sender: IpcSender<ServiceWorkerMsg>,
         from_constellation_receiver: Receiver<ServiceWorkerMsg>,
         resource_port: Receiver<CustomResponseMediator>,
+        constellation_sender: IpcSender<SWManagerMsg>,
     ) -> ServiceWorkerManager {
         ServiceWorkerManager {
             registered_workers: HashMap::new(),
@@ -51,6 +55,7 @@ impl ServiceWorkerManager {
             own_sender: own_sender,
             own_port: from_constellation_receiver,
             resource_receiver: resource_port,
+            constellation_sender,
         }
     }
 
@@ -69,8 +74,13 @@ impl ServiceWorkerManager {
         thread::Builder::new()
             .name("ServiceWorkerManager".to_owned())
             .spawn(move || {
-                ServiceWorkerManager::new(own_sender, from_constellation, resource_port)
-                    .handle_message();
+                ServiceWorkerManager::new(
+                    own_sender,
+                    from_constellation,
+                    resource_port,
+                    sw_senders.swmanager_sender,
+                )
+                .handle_message();
             })
             .expect("Thread spawning failed");
     }
@@ -97,6 +107,7 @@ impl ServiceWorkerManager {
                 let page_info = DevtoolsPageInfo {
                     title: title,
                     url: scope_things.script_url.clone(),
+                    is_private: scope_things.init.is_private,
                 };
                 let _ = chan.send(ScriptToDevtoolsControlMsg::NewGlobal(
                     (scope_things.init.pipeline_id, Some(scope_things.worker_id)),
@@ -140,6 +151,35 @@ impl ServiceWorkerManager {
         ));
     }
 
+    /// Find the clients of the service worker of a scope, which are the ones it controls
+    /// unless `include_uncontrolled` is set.
+    /// <https://w3c.github.io/ServiceWorker/#clients-matchall>
+    fn match_clients(
+        &self,
+        scope_url: ServoUrl,
+        include_uncontrolled: bool,
+        sender: IpcSender<Vec<ClientInfo>>,
+    ) {
+        let (clients_sender, clients_receiver) = ipc::channel().unwrap();
+        let origin = scope_url.origin();
+        ROUTER.add_route(
+            clients_receiver.to_opaque(),
+            Box::new(move |message| {
+                let clients: Vec<ClientInfo> = message.to().unwrap_or_default();
+                let clients = clients
+                    .into_iter()
+                    .filter(|client| {
+                        include_uncontrolled || client.controller.as_ref() == Some(&scope_url)
+                    })
+                    .collect();
+                let _ = sender.send(clients);
+            }),
+        );
+        let _ = self
+            .constellation_sender
+            .send(SWManagerMsg::MatchClients(origin, clients_sender));
+    }
+
     fn handle_message_from_constellation(&mut self, msg: ServiceWorkerMsg) -> bool {
         match msg {
             ServiceWorkerMsg::RegisterServiceWorker(scope_things, scope) => {
@@ -170,6 +210,34 @@ impl ServiceWorkerManager {
                 }
                 true
             },
+            ServiceWorkerMsg::MatchClients(scope_url, include_uncontrolled, sender) => {
+                self.match_clients(scope_url, include_uncontrolled, sender);
+                true
+            },
+            ServiceWorkerMsg::ClaimClients(scope_url, sender) => {
+                if self.registered_workers.contains_key(&scope_url) {
+                    let _ = self
+                        .constellation_sender
+                        .send(SWManagerMsg::ClaimClients(scope_url, sender));
+                } else {
+                    warn!(
+                        "No service worker registered for {:?} to claim clients",
+                        scope_url
+                    );
+                    let _ = sender.send(());
+                }
+                true
+            },
+            ServiceWorkerMsg::PostMessageToClient(pipeline_id, scope_url, msg) => {
+                let _ = self
+                    .constellation_sender
+                    .send(SWManagerMsg::PostMessageToClient(
+                        pipeline_id,
+                        scope_url,
+                        msg,
+                    ));
+                true
+            },
             ServiceWorkerMsg::Exit => false,
         }
     }
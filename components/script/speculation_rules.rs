@@ -0,0 +1,75 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The speculation rules with which documents list the pages they are likely to navigate
+//! to, which are prefetched into the HTTP cache.
+//! <https://wicg.github.io/nav-speculation/speculation-rules.html>
+
+use crate::dom::document::Document;
+use net_traits::request::{CredentialsMode, Destination, Referrer, RequestBuilder, RequestMode};
+use servo_url::ServoUrl;
+
+/// <https://wicg.github.io/nav-speculation/speculation-rules.html#speculation-rule-set>
+#[derive(Deserialize)]
+struct SpeculationRuleSet {
+    #[serde(default)]
+    prefetch: Vec<SpeculationRule>,
+}
+
+/// <https://wicg.github.io/nav-speculation/speculation-rules.html#speculation-rule>
+#[derive(Deserialize)]
+struct SpeculationRule {
+    source: Option<String>,
+    #[serde(default)]
+    urls: Vec<String>,
+    #[serde(default)]
+    requires: Vec<String>,
+}
+
+/// The URLs to prefetch of a speculation rule set.
+/// <https://wicg.github.io/nav-speculation/speculation-rules.html#parse-speculation-rules>
+fn parse_speculation_rules(text: &str, base_url: &ServoUrl) -> Result<Vec<ServoUrl>, String> {
+    let rule_set: SpeculationRuleSet = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    Ok(rule_set
+        .prefetch
+        .into_iter()
+        // Only the lists of URLs are supported, and the rules with requirements are
+        // skipped, since none of them can be met.
+        .filter(|rule| {
+            rule.source.as_ref().map_or(true, |source| source == "list") && rule.requires.is_empty()
+        })
+        .flat_map(|rule| rule.urls)
+        .filter_map(|url| base_url.join(&url).ok())
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+        .collect())
+}
+
+/// Prefetch the documents listed by the rules of a `<script type=speculationrules>`.
+pub fn prefetch_speculation_rules(document: &Document, text: &str) {
+    let urls = match parse_speculation_rules(text, &document.base_url()) {
+        Ok(urls) => urls,
+        Err(e) => {
+            warn!("Ignoring invalid speculation rules ({}).", e);
+            return;
+        },
+    };
+    let origin = document.origin().immutable().clone();
+    for url in urls {
+        // The cross-origin documents would have to be fetched without credentials, and
+        // then kept apart from the other responses of the HTTP cache.
+        if url.origin() != origin {
+            debug!("Not prefetching the cross-origin document {}", url);
+            continue;
+        }
+        let request = RequestBuilder::new(url)
+            .destination(Destination::Document)
+            .mode(RequestMode::Navigate)
+            .credentials_mode(CredentialsMode::Include)
+            .origin(origin.clone())
+            .pipeline_id(Some(document.window().pipeline_id()))
+            .referrer(Some(Referrer::ReferrerUrl(document.url())))
+            .referrer_policy(document.get_referrer_policy());
+        document.prefetch(request);
+    }
+}
@@ -183,6 +183,18 @@ impl<T: QueuedTaskConversion> TaskQueue<T> {
         self.recv()
     }
 
+    /// The number of tasks which are waiting to run, including the throttled ones,
+    /// but not the ones of documents which aren't fully active.
+    pub fn queue_length(&self) -> usize {
+        let throttled: usize = self
+            .throttled
+            .borrow()
+            .values()
+            .map(|queue| queue.len())
+            .sum();
+        self.port.len() + self.msg_queue.borrow().len() + throttled
+    }
+
     /// Drain the queue for the current iteration of the event-loop.
     /// Holding-back throttles above a given high-water mark.
     pub fn take_tasks(&self, first_msg: T) {
@@ -40,10 +40,9 @@ impl TaskOnce for FileReadingTask {
     }
 }
 
-#[allow(dead_code)]
 pub enum FileReadingTask {
     ProcessRead(TrustedFileReader, GenerationId),
-    ProcessReadData(TrustedFileReader, GenerationId),
+    ProcessReadData(TrustedFileReader, GenerationId, u64, u64),
     ProcessReadError(TrustedFileReader, GenerationId, DOMErrorName),
     ProcessReadEOF(TrustedFileReader, GenerationId, ReadMetaData, Vec<u8>),
 }
@@ -54,7 +53,9 @@ impl FileReadingTask {
 
         match self {
             ProcessRead(reader, gen_id) => FileReader::process_read(reader, gen_id),
-            ProcessReadData(reader, gen_id) => FileReader::process_read_data(reader, gen_id),
+            ProcessReadData(reader, gen_id, loaded, total) => {
+                FileReader::process_read_data(reader, gen_id, loaded, total)
+            },
             ProcessReadError(reader, gen_id, error) => {
                 FileReader::process_read_error(reader, gen_id, error)
             },
@@ -5,6 +5,7 @@
 pub mod dom_manipulation;
 pub mod file_reading;
 pub mod history_traversal;
+pub mod javascript_engine;
 pub mod media_element;
 pub mod networking;
 pub mod performance_timeline;
@@ -28,6 +29,7 @@ pub enum TaskSourceName {
     DOMManipulation,
     FileReading,
     HistoryTraversal,
+    JavaScriptEngine,
     Networking,
     PerformanceTimeline,
     PortMessage,
@@ -42,6 +44,24 @@ impl TaskSourceName {
     pub fn all() -> Vec<TaskSourceName> {
         TaskSourceName::into_enum_iter().collect()
     }
+
+    /// The name of the task source, as shown in the hang reports.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TaskSourceName::DOMManipulation => "DOMManipulation",
+            TaskSourceName::FileReading => "FileReading",
+            TaskSourceName::HistoryTraversal => "HistoryTraversal",
+            TaskSourceName::JavaScriptEngine => "JavaScriptEngine",
+            TaskSourceName::Networking => "Networking",
+            TaskSourceName::PerformanceTimeline => "PerformanceTimeline",
+            TaskSourceName::PortMessage => "PortMessage",
+            TaskSourceName::UserInteraction => "UserInteraction",
+            TaskSourceName::RemoteEvent => "RemoteEvent",
+            TaskSourceName::MediaElement => "MediaElement",
+            TaskSourceName::Websocket => "Websocket",
+            TaskSourceName::Timer => "Timer",
+        }
+    }
 }
 
 pub trait TaskSource {
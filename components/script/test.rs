@@ -60,6 +60,14 @@ pub mod size_of {
     }
 }
 
+pub mod gc_scheduler {
+    pub use crate::script_runtime::{idle_period_deadline, idle_slice_budget};
+}
+
+pub mod mem {
+    pub use crate::mem::{interface_reports, is_ghost_window};
+}
+
 pub mod srcset {
     pub use crate::dom::htmlimageelement::{parse_a_srcset_attribute, Descriptor, ImageSource};
 }
@@ -67,3 +75,20 @@ pub mod srcset {
 pub mod timeranges {
     pub use crate::dom::timeranges::TimeRangesContainer;
 }
+
+pub mod timers {
+    pub use crate::timers::TimerThrottling;
+}
+
+pub mod webvtt {
+    pub use crate::dom::webvttparser::{
+        parse, parse_cue_text, CueElementKind, CueLine, CueNode, LineAlignment, PositionAlignment,
+        TextAlignment, WritingDirection,
+    };
+}
+
+pub mod xpath {
+    pub use crate::dom::xpathparser::{
+        parse, Axis, BinaryOperator, CoreFunction, Expr, NodeTest, PathStart, Step,
+    };
+}
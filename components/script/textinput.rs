@@ -6,6 +6,7 @@
 
 use crate::clipboard_provider::ClipboardProvider;
 use crate::dom::bindings::str::DOMString;
+use crate::dom::clipboardevent::ClipboardAction;
 use crate::dom::compositionevent::CompositionEvent;
 use crate::dom::keyboardevent::KeyboardEvent;
 use keyboard_types::{Key, KeyState, Modifiers, ShortcutMatcher};
@@ -825,6 +826,11 @@ impl<T: ClipboardProvider> TextInput<T> {
     pub fn handle_keydown(&mut self, event: &KeyboardEvent) -> KeyReaction {
         let key = event.key();
         let mods = event.modifiers();
+        // The document fires clipboard events for the clipboard shortcuts, whose
+        // default actions edit the text input.
+        if ClipboardAction::from_shortcut(&key, mods).is_some() {
+            return KeyReaction::Nothing;
+        }
         self.handle_keydown_aux(key, mods, cfg!(target_os = "macos"))
     }
 
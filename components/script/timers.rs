@@ -11,6 +11,7 @@ use crate::dom::document::FakeRequestAnimationFrameCallback;
 use crate::dom::eventsource::EventSourceTimeoutCallback;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::testbinding::TestBindingCallback;
+use crate::dom::window::{ImageAnimationCallback, SmoothScrollCallback};
 use crate::dom::xmlhttprequest::XHRTimeoutCallback;
 use euclid::Length;
 use ipc_channel::ipc::IpcSender;
@@ -26,6 +27,7 @@ use std::cmp::{self, Ord, Ordering};
 use std::collections::HashMap;
 use std::default::Default;
 use std::rc::Rc;
+use std::time::Duration;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, JSTraceable, MallocSizeOf, Ord, PartialEq, PartialOrd)]
 pub struct OneshotTimerHandle(i32);
@@ -56,6 +58,15 @@ pub struct OneshotTimers {
     ///  - a timer was added with an earlier callback time. In this case the
     ///    original timer is rescheduled when it is the next one to get called.
     expected_event_id: Cell<TimerEventId>,
+    /// When the associated document was hidden, if it is hidden. The timers of
+    /// the hidden documents are throttled to cut their CPU usage.
+    hidden_since: Cell<Option<MsDuration>>,
+    /// The time in ms that the timers of a document hidden for a long time can
+    /// still run for. It regenerates over time, and while it is negative the
+    /// timers are delayed.
+    budget: Cell<f64>,
+    /// When the budget was last regenerated.
+    budget_updated_at: Cell<MsDuration>,
 }
 
 #[derive(DenyPublicFields, JSTraceable, MallocSizeOf)]
@@ -76,6 +87,8 @@ pub enum OneshotTimerCallback {
     JsTimer(JsTimerTask),
     TestBindingCallback(TestBindingCallback),
     FakeRequestAnimationFrame(FakeRequestAnimationFrameCallback),
+    SmoothScroll(SmoothScrollCallback),
+    ImageAnimation(ImageAnimationCallback),
 }
 
 impl OneshotTimerCallback {
@@ -86,6 +99,8 @@ impl OneshotTimerCallback {
             OneshotTimerCallback::JsTimer(task) => task.invoke(this, js_timers),
             OneshotTimerCallback::TestBindingCallback(callback) => callback.invoke(),
             OneshotTimerCallback::FakeRequestAnimationFrame(callback) => callback.invoke(),
+            OneshotTimerCallback::SmoothScroll(callback) => callback.invoke(),
+            OneshotTimerCallback::ImageAnimation(callback) => callback.invoke(),
         }
     }
 }
@@ -123,6 +138,9 @@ impl OneshotTimers {
             suspended_since: Cell::new(None),
             suspension_offset: Cell::new(Length::new(0)),
             expected_event_id: Cell::new(TimerEventId(0)),
+            hidden_since: Cell::new(None),
+            budget: Cell::new(0.),
+            budget_updated_at: Cell::new(Length::new(0)),
         }
     }
 
@@ -202,23 +220,36 @@ impl OneshotTimers {
             return;
         }
 
-        // select timers to run to prevent firing timers
-        // that were installed during fire of another timer
-        let mut timers_to_run = Vec::new();
+        // The due timers of the document run in order, each as its own task, so that the
+        // microtasks queued by a timer run before the next one, and a timer canceled by an
+        // earlier one doesn't run. The timers installed during the fire of another timer
+        // wait for the next fire, even if they are already due.
+        let first_new_handle = self.next_timer_handle.get();
 
+        let start = time::precise_time_ns();
         loop {
-            let mut timers = self.timers.borrow_mut();
-
-            if timers.is_empty() || timers.last().unwrap().scheduled_for > base_time {
-                break;
-            }
+            let timer = {
+                let mut timers = self.timers.borrow_mut();
+                let index = timers
+                    .iter()
+                    .rposition(|timer| timer.handle < first_new_handle);
+                match index {
+                    Some(index) if timers[index].scheduled_for <= base_time => timers.remove(index),
+                    _ => break,
+                }
+            };
 
-            timers_to_run.push(timers.pop().unwrap());
+            timer.callback.invoke(global, &self.js_timers);
+            global.perform_a_microtask_checkpoint();
         }
 
-        for timer in timers_to_run {
-            let callback = timer.callback;
-            callback.invoke(global, &self.js_timers);
+        // The timers of the documents hidden for a long time spend their budget.
+        let now = precise_time_ms();
+        let throttling = TimerThrottling::from_prefs();
+        if self.is_budget_throttled(now, &throttling) {
+            self.regenerate_budget(now, &throttling);
+            let run_time = (time::precise_time_ns() - start) as f64 / 1_000_000.;
+            self.budget.set(self.budget.get() - run_time);
         }
 
         self.schedule_timer_call();
@@ -234,12 +265,57 @@ impl OneshotTimers {
     }
 
     pub fn slow_down(&self) {
-        let duration = pref!(js.timers.minimum_duration) as u64;
+        let duration = pref!(js.timers.minimum_duration).max(0) as u64;
         self.js_timers.set_min_duration(MsDuration::new(duration));
+
+        if self.hidden_since.get().is_none() {
+            let now = precise_time_ms();
+            self.hidden_since.set(Some(now));
+            self.budget
+                .set(TimerThrottling::from_prefs().budget_maximum);
+            self.budget_updated_at.set(now);
+            self.schedule_timer_call();
+        }
     }
 
     pub fn speed_up(&self) {
         self.js_timers.remove_min_duration();
+
+        if self.hidden_since.get().is_some() {
+            self.hidden_since.set(None);
+            self.schedule_timer_call();
+        }
+    }
+
+    /// Whether the document has been hidden long enough for its timers to be limited
+    /// by a budget.
+    fn is_budget_throttled(&self, now: MsDuration, throttling: &TimerThrottling) -> bool {
+        let delay = MsDuration::new(throttling.budget_delay);
+        match self.hidden_since.get() {
+            Some(hidden_since) => {
+                throttling.budget_regeneration_rate > 0. && now - hidden_since >= delay
+            },
+            None => false,
+        }
+    }
+
+    fn regenerate_budget(&self, now: MsDuration, throttling: &TimerThrottling) {
+        let elapsed = (now - self.budget_updated_at.get()).get() as f64;
+        self.budget
+            .set(throttling.regenerate_budget(self.budget.get(), elapsed));
+        self.budget_updated_at.set(now);
+    }
+
+    /// The delay of the next wake up of a hidden document.
+    fn throttle_delay(&self, now: MsDuration, delay: u64) -> u64 {
+        let throttling = TimerThrottling::from_prefs();
+        let budget = if self.is_budget_throttled(now, &throttling) {
+            self.regenerate_budget(now, &throttling);
+            Some(self.budget.get())
+        } else {
+            None
+        };
+        throttling.throttle_delay(now.get(), delay, budget)
     }
 
     pub fn suspend(&self) {
@@ -268,6 +344,19 @@ impl OneshotTimers {
         self.schedule_timer_call();
     }
 
+    /// How long until the next timer is due, if one is scheduled and the timers aren't
+    /// suspended.
+    pub fn time_until_next_timer(&self) -> Option<Duration> {
+        if self.suspended_since.get().is_some() {
+            return None;
+        }
+        let now = precise_time_ms();
+        self.timers
+            .borrow()
+            .last()
+            .map(|timer| Duration::from_millis(timer.scheduled_for.get().saturating_sub(now.get())))
+    }
+
     fn schedule_timer_call(&self) {
         if self.suspended_since.get().is_some() {
             // The timer will be scheduled when the pipeline is fully activated.
@@ -279,12 +368,12 @@ impl OneshotTimers {
         if let Some(timer) = timers.last() {
             let expected_event_id = self.invalidate_expected_event_id();
 
-            let delay = Length::new(
-                timer
-                    .scheduled_for
-                    .get()
-                    .saturating_sub(precise_time_ms().get()),
-            );
+            let now = precise_time_ms();
+            let mut delay = timer.scheduled_for.get().saturating_sub(now.get());
+            if self.hidden_since.get().is_some() {
+                delay = self.throttle_delay(now, delay);
+            }
+            let delay = Length::new(delay);
             let request = TimerEventRequest(
                 self.timer_event_chan
                     .borrow()
@@ -335,6 +424,72 @@ impl OneshotTimers {
     }
 }
 
+/// How the timers of the hidden documents are throttled, as the js.timers prefs say. The
+/// negative values of the prefs are treated as 0, which disables what they control.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimerThrottling {
+    /// The interval in ms which the wake ups of the hidden documents are aligned to.
+    pub alignment: u64,
+    /// How long in ms a document is hidden before its timers spend a budget.
+    pub budget_delay: u64,
+    /// The budget in ms which a hidden document starts with, and which it regenerates to.
+    pub budget_maximum: f64,
+    /// How many ms of budget regenerate every second.
+    pub budget_regeneration_rate: f64,
+}
+
+impl TimerThrottling {
+    pub fn new(
+        alignment: i64,
+        budget_delay: i64,
+        budget_maximum: i64,
+        budget_regeneration_rate: i64,
+    ) -> TimerThrottling {
+        TimerThrottling {
+            alignment: alignment.max(0) as u64,
+            budget_delay: budget_delay.max(0) as u64,
+            budget_maximum: budget_maximum.max(0) as f64,
+            budget_regeneration_rate: budget_regeneration_rate.max(0) as f64,
+        }
+    }
+
+    fn from_prefs() -> TimerThrottling {
+        TimerThrottling::new(
+            pref!(js.timers.alignment),
+            pref!(js.timers.budget.delay),
+            pref!(js.timers.budget.maximum),
+            pref!(js.timers.budget.regeneration_rate),
+        )
+    }
+
+    /// The budget regenerated for the given number of ms.
+    pub fn regenerate_budget(&self, budget: f64, elapsed: f64) -> f64 {
+        (budget + elapsed * self.budget_regeneration_rate / 1000.).min(self.budget_maximum)
+    }
+
+    /// The delay of a wake up of a hidden document, which is aligned with the wake ups of
+    /// the other hidden documents, so that their timers run together, and postponed until
+    /// the budget is regenerated if it has run out.
+    pub fn throttle_delay(&self, now: u64, delay: u64, budget: Option<f64>) -> u64 {
+        let mut delay = delay;
+        if let Some(budget) = budget {
+            if budget < 0. && self.budget_regeneration_rate > 0. {
+                let regeneration = -budget * 1000. / self.budget_regeneration_rate;
+                delay = cmp::max(delay, regeneration.ceil() as u64);
+            }
+        }
+        if self.alignment > 0 {
+            let wake_up = now.saturating_add(delay);
+            let aligned_wake_up = match wake_up % self.alignment {
+                0 => wake_up,
+                remainder => wake_up.saturating_add(self.alignment - remainder),
+            };
+            delay = aligned_wake_up - now;
+        }
+        delay
+    }
+}
+
 #[derive(Clone, Copy, Eq, Hash, JSTraceable, MallocSizeOf, Ord, PartialEq, PartialOrd)]
 pub struct JsTimerHandle(i32);
 
@@ -538,17 +693,19 @@ impl JsTimerTask {
             },
         };
 
-        // reset nesting level (see above)
-        timers.nesting_level.set(0);
-
         // step 4.3
         // Since we choose proactively prevent execution (see 4.1 above), we must only
         // reschedule repeating timers when they were not canceled as part of step 4.2.
-        if self.is_interval == IsInterval::Interval &&
-            timers.active_timers.borrow().contains_key(&self.handle)
-        {
+        // The repeating timers are rescheduled from within their task, so that they
+        // are clamped like nested timers.
+        let reschedule = self.is_interval == IsInterval::Interval &&
+            timers.active_timers.borrow().contains_key(&self.handle);
+        if reschedule {
             timers.initialize_and_schedule(&this.global(), self);
         }
+
+        // reset nesting level (see above)
+        timers.nesting_level.set(0);
     }
 
     // Returning Handles directly from Heap values is inherently unsafe, but here it's
@@ -6,13 +6,17 @@ use crate::dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyl
 use crate::dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use crate::dom::bindings::codegen::Bindings::FileListBinding::FileListMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLInputElementBinding::HTMLInputElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLOptionElementBinding::HTMLOptionElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLSelectElementBinding::HTMLSelectElementMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLTextAreaElementBinding::HTMLTextAreaElementMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::{GetRootNodeOptions, NodeMethods};
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::Bindings::XMLSerializerBinding::XMLSerializerMethods;
+use crate::dom::bindings::codegen::Bindings::XPathResultBinding::XPathResultConstants;
+use crate::dom::bindings::codegen::Bindings::XPathResultBinding::XPathResultMethods;
 use crate::dom::bindings::conversions::{
     get_property, get_property_jsval, is_array_like, root_from_object,
 };
@@ -33,6 +37,7 @@ use crate::dom::htmliframeelement::HTMLIFrameElement;
 use crate::dom::htmlinputelement::{HTMLInputElement, InputType};
 use crate::dom::htmloptionelement::HTMLOptionElement;
 use crate::dom::htmlselectelement::HTMLSelectElement;
+use crate::dom::htmltextareaelement::HTMLTextAreaElement;
 use crate::dom::node::{window_from_node, Node, ShadowIncluding};
 use crate::dom::nodelist::NodeList;
 use crate::dom::window::Window;
@@ -130,6 +135,34 @@ fn first_matching_link(
         .map(|nodes| matching_links(&nodes, link_text, partial).take(1).next())
 }
 
+/// <https://w3c.github.io/webdriver/#xpath>
+fn all_matching_xpath(root_node: &Node, selector: String) -> Result<Vec<String>, ErrorStatus> {
+    let result = root_node
+        .owner_doc()
+        .Evaluate(
+            DOMString::from(selector),
+            root_node,
+            None,
+            XPathResultConstants::ORDERED_NODE_SNAPSHOT_TYPE,
+            None,
+        )
+        .map_err(|_| ErrorStatus::InvalidSelector)?;
+    let length = result
+        .GetSnapshotLength()
+        .map_err(|_| ErrorStatus::UnknownError)?;
+    // Selecting nodes which are not elements is an error.
+    (0..length)
+        .map(|index| match result.SnapshotItem(index) {
+            Ok(Some(ref node)) if node.is::<Element>() => Ok(node.unique_id()),
+            _ => Err(ErrorStatus::InvalidSelector),
+        })
+        .collect()
+}
+
+fn first_matching_xpath(root_node: &Node, selector: String) -> Result<Option<String>, ErrorStatus> {
+    all_matching_xpath(root_node, selector).map(|ids| ids.into_iter().next())
+}
+
 #[allow(unsafe_code)]
 unsafe fn object_has_to_json_property(
     cx: *mut JSContext,
@@ -460,6 +493,22 @@ pub fn handle_find_element_link_text(
         .unwrap();
 }
 
+pub fn handle_find_element_xpath(
+    documents: &Documents,
+    pipeline: PipelineId,
+    selector: String,
+    reply: IpcSender<Result<Option<String>, ErrorStatus>>,
+) {
+    reply
+        .send(
+            documents
+                .find_document(pipeline)
+                .ok_or(ErrorStatus::UnknownError)
+                .and_then(|document| first_matching_xpath(document.upcast::<Node>(), selector)),
+        )
+        .unwrap();
+}
+
 pub fn handle_find_element_tag_name(
     documents: &Documents,
     pipeline: PipelineId,
@@ -527,6 +576,22 @@ pub fn handle_find_elements_link_text(
         .unwrap();
 }
 
+pub fn handle_find_elements_xpath(
+    documents: &Documents,
+    pipeline: PipelineId,
+    selector: String,
+    reply: IpcSender<Result<Vec<String>, ErrorStatus>>,
+) {
+    reply
+        .send(
+            documents
+                .find_document(pipeline)
+                .ok_or(ErrorStatus::UnknownError)
+                .and_then(|document| all_matching_xpath(document.upcast::<Node>(), selector)),
+        )
+        .unwrap();
+}
+
 pub fn handle_find_elements_tag_name(
     documents: &Documents,
     pipeline: PipelineId,
@@ -583,6 +648,21 @@ pub fn handle_find_element_element_link_text(
         .unwrap();
 }
 
+pub fn handle_find_element_element_xpath(
+    documents: &Documents,
+    pipeline: PipelineId,
+    element_id: String,
+    selector: String,
+    reply: IpcSender<Result<Option<String>, ErrorStatus>>,
+) {
+    reply
+        .send(
+            find_node_by_unique_id(documents, pipeline, element_id)
+                .and_then(|node| first_matching_xpath(&node, selector)),
+        )
+        .unwrap();
+}
+
 pub fn handle_find_element_element_tag_name(
     documents: &Documents,
     pipeline: PipelineId,
@@ -645,6 +725,21 @@ pub fn handle_find_element_elements_link_text(
         .unwrap();
 }
 
+pub fn handle_find_element_elements_xpath(
+    documents: &Documents,
+    pipeline: PipelineId,
+    element_id: String,
+    selector: String,
+    reply: IpcSender<Result<Vec<String>, ErrorStatus>>,
+) {
+    reply
+        .send(
+            find_node_by_unique_id(documents, pipeline, element_id)
+                .and_then(|node| all_matching_xpath(&node, selector)),
+        )
+        .unwrap();
+}
+
 pub fn handle_find_element_elements_tag_name(
     documents: &Documents,
     pipeline: PipelineId,
@@ -668,6 +763,7 @@ pub fn handle_find_element_elements_tag_name(
         .unwrap();
 }
 
+// https://w3c.github.io/webdriver/#element-send-keys
 pub fn handle_focus_element(
     documents: &Documents,
     pipeline: PipelineId,
@@ -677,19 +773,140 @@ pub fn handle_focus_element(
     reply
         .send(
             find_node_by_unique_id(documents, pipeline, element_id).and_then(|node| {
-                match node.downcast::<HTMLElement>() {
-                    Some(element) => {
-                        // Need a way to find if this actually succeeded
-                        element.Focus();
-                        Ok(())
-                    },
-                    None => Err(ErrorStatus::UnknownError),
+                // Step 7
+                if let Some(input_element) = node.downcast::<HTMLInputElement>() {
+                    if input_element.input_type() == InputType::File {
+                        return Err(ErrorStatus::UnsupportedOperation);
+                    }
+                }
+
+                let element = match node.downcast::<HTMLElement>() {
+                    Some(element) => element,
+                    None => return Err(ErrorStatus::UnknownError),
+                };
+                let was_focused = element.upcast::<Element>().focus_state();
+
+                // Step 8
+                element.Focus();
+
+                // Step 9
+                if !element.upcast::<Element>().focus_state() {
+                    return Err(ErrorStatus::ElementNotInteractable);
                 }
+
+                // Step 10
+                if !was_focused {
+                    if let Some(input_element) = node.downcast::<HTMLInputElement>() {
+                        let length = input_element.Value().encode_utf16().count() as u32;
+                        // Only the textual inputs have a caret.
+                        let _ = input_element.SetSelectionRange(length, length, None);
+                    } else if let Some(textarea_element) = node.downcast::<HTMLTextAreaElement>() {
+                        let length = textarea_element.TextLength();
+                        let _ = textarea_element.SetSelectionRange(length, length, None);
+                    }
+                }
+
+                Ok(())
             }),
         )
         .unwrap();
 }
 
+// https://w3c.github.io/webdriver/#element-clear
+pub fn handle_element_clear(
+    documents: &Documents,
+    pipeline: PipelineId,
+    element_id: String,
+    reply: IpcSender<Result<(), ErrorStatus>>,
+) {
+    reply
+        .send(
+            find_node_by_unique_id(documents, pipeline, element_id).and_then(|node| {
+                // Step 4
+                if let Some(input_element) = node.downcast::<HTMLInputElement>() {
+                    // https://w3c.github.io/webdriver/#dfn-mutable-form-control-element
+                    match input_element.input_type() {
+                        InputType::Text |
+                        InputType::Search |
+                        InputType::Url |
+                        InputType::Tel |
+                        InputType::Email |
+                        InputType::Password |
+                        InputType::Date |
+                        InputType::Month |
+                        InputType::Week |
+                        InputType::Time |
+                        InputType::DatetimeLocal |
+                        InputType::Number |
+                        InputType::Range |
+                        InputType::Color |
+                        InputType::File => {},
+                        _ => return Err(ErrorStatus::InvalidElementState),
+                    }
+                    if input_element.Disabled() || input_element.ReadOnly() {
+                        return Err(ErrorStatus::InvalidElementState);
+                    }
+
+                    // Step 7
+                    match input_element.input_type() {
+                        InputType::File => {
+                            if input_element
+                                .GetFiles()
+                                .map_or(false, |files| files.Length() > 0)
+                            {
+                                clear_resettable_element(input_element.upcast(), || {
+                                    let _ = input_element.SetValue(DOMString::new());
+                                });
+                            }
+                        },
+                        _ => {
+                            if !input_element.Value().is_empty() {
+                                clear_resettable_element(input_element.upcast(), || {
+                                    let _ = input_element.SetValue(DOMString::new());
+                                });
+                            }
+                        },
+                    }
+                    Ok(())
+                } else if let Some(textarea_element) = node.downcast::<HTMLTextAreaElement>() {
+                    // Step 4
+                    if textarea_element.Disabled() || textarea_element.ReadOnly() {
+                        return Err(ErrorStatus::InvalidElementState);
+                    }
+
+                    // Step 7
+                    if !textarea_element.Value().is_empty() {
+                        clear_resettable_element(textarea_element.upcast(), || {
+                            textarea_element.SetValue(DOMString::new())
+                        });
+                    }
+                    Ok(())
+                } else {
+                    // Content editable elements aren't supported.
+                    Err(ErrorStatus::InvalidElementState)
+                }
+            }),
+        )
+        .unwrap();
+}
+
+// https://w3c.github.io/webdriver/#dfn-clear-a-resettable-element
+fn clear_resettable_element<F: FnOnce()>(element: &HTMLElement, clear: F) {
+    // Step 2
+    element.Focus();
+
+    // Step 3
+    clear();
+
+    // Steps 4 - 5
+    let event_target = element.upcast::<EventTarget>();
+    event_target.fire_bubbling_event(atom!("input"));
+    event_target.fire_bubbling_event(atom!("change"));
+
+    // Step 6
+    element.Blur();
+}
+
 pub fn handle_get_active_element(
     documents: &Documents,
     pipeline: PipelineId,
@@ -5,8 +5,9 @@
 use crate::rpc::LayoutRPC;
 use crate::{OpaqueStyleAndLayoutData, PendingImage, TrustedNodeAddress};
 use app_units::Au;
+use content_security_policy::CspList;
 use crossbeam_channel::{Receiver, Sender};
-use euclid::default::{Point2D, Rect};
+use euclid::default::{Point2D, Rect, Size2D};
 use gfx_traits::Epoch;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use metrics::PaintTimeMetrics;
@@ -25,7 +26,7 @@ use style::context::QuirksMode;
 use style::dom::OpaqueNode;
 use style::properties::PropertyId;
 use style::selector_parser::PseudoElement;
-use style::stylesheets::Stylesheet;
+use style::stylesheets::{DocumentStyleSheet, Stylesheet};
 
 /// Asynchronous messages that script can send to layout.
 pub enum Msg {
@@ -40,6 +41,15 @@ pub enum Msg {
     /// Change the quirks mode.
     SetQuirksMode(QuirksMode),
 
+    /// Replaces the user agent and user stylesheets supplied by the embedder.
+    SetEmbedderStylesheets(Vec<DocumentStyleSheet>),
+
+    /// Sets the stylesheet which hides the elements matching the rules of the content blocker.
+    SetElementHidingStylesheet(DocumentStyleSheet),
+
+    /// The preferences of the process changed, and may affect the style.
+    PreferencesChanged,
+
     /// Requests a reflow.
     Reflow(ScriptReflow),
 
@@ -88,6 +98,9 @@ pub enum Msg {
     /// Set the final Url.
     SetFinalUrl(ServoUrl),
 
+    /// Set the Content Security Policies of the document, which apply to the web fonts it loads.
+    SetCspList(Option<CspList>),
+
     /// Tells layout about the new scrolling offsets of each scrollable stacking context.
     SetScrollStates(Vec<ScrollState>),
 
@@ -115,10 +128,16 @@ pub enum NodesFromPointQueryType {
 pub enum QueryMsg {
     ContentBoxQuery(OpaqueNode),
     ContentBoxesQuery(OpaqueNode),
+    /// The sizes of the content box and of the border box of a node, as observed by
+    /// resize observers.
+    BoxSizesQuery(OpaqueNode),
     ClientRectQuery(OpaqueNode),
     NodeScrollGeometryQuery(OpaqueNode),
     OffsetParentQuery(OpaqueNode),
     TextIndexQuery(OpaqueNode, Point2D<f32>),
+    /// The index of the character of a text node nearest to a point relative to the
+    /// initial containing block.
+    CaretPositionQuery(OpaqueNode, Point2D<f32>),
     NodesFromPointQuery(Point2D<f32>, NodesFromPointQueryType),
 
     // FIXME(nox): The following queries use the TrustedNodeAddress to
@@ -148,10 +167,12 @@ impl ReflowGoal {
             ReflowGoal::LayoutQuery(ref querymsg, _) => match *querymsg {
                 QueryMsg::NodesFromPointQuery(..) |
                 QueryMsg::TextIndexQuery(..) |
+                QueryMsg::CaretPositionQuery(..) |
                 QueryMsg::InnerWindowDimensionsQuery(_) |
                 QueryMsg::ElementInnerTextQuery(_) => true,
                 QueryMsg::ContentBoxQuery(_) |
                 QueryMsg::ContentBoxesQuery(_) |
+                QueryMsg::BoxSizesQuery(_) |
                 QueryMsg::ClientRectQuery(_) |
                 QueryMsg::NodeScrollGeometryQuery(_) |
                 QueryMsg::NodeScrollIdQuery(_) |
@@ -170,9 +191,11 @@ impl ReflowGoal {
             ReflowGoal::LayoutQuery(ref querymsg, _) => match *querymsg {
                 QueryMsg::NodesFromPointQuery(..) |
                 QueryMsg::TextIndexQuery(..) |
+                QueryMsg::CaretPositionQuery(..) |
                 QueryMsg::ElementInnerTextQuery(_) => true,
                 QueryMsg::ContentBoxQuery(_) |
                 QueryMsg::ContentBoxesQuery(_) |
+                QueryMsg::BoxSizesQuery(_) |
                 QueryMsg::ClientRectQuery(_) |
                 QueryMsg::NodeScrollGeometryQuery(_) |
                 QueryMsg::NodeScrollIdQuery(_) |
@@ -198,6 +221,9 @@ pub struct ReflowComplete {
     pub pending_images: Vec<PendingImage>,
     /// The list of nodes that initiated a CSS transition.
     pub newly_transitioning_nodes: Vec<UntrustedNodeAddress>,
+    /// The intrinsic size of the content of the document, which is its max-content width
+    /// and its height at that of the viewport, if it was laid out.
+    pub content_size: Option<Size2D<Au>>,
 }
 
 /// Information needed for a script-initiated reflow.
@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use app_units::Au;
-use euclid::default::Rect;
+use euclid::default::{Rect, Size2D as UntypedSize2D};
 use euclid::Size2D;
 use script_traits::UntrustedNodeAddress;
 use servo_arc::Arc;
@@ -24,6 +24,9 @@ pub trait LayoutRPC {
     fn content_box(&self) -> ContentBoxResponse;
     /// Requests the dimensions of all the content boxes, as in the `getClientRects()` call.
     fn content_boxes(&self) -> ContentBoxesResponse;
+    /// Requests the sizes of the content box and of the border box of a node, as observed
+    /// by resize observers.
+    fn box_sizes(&self) -> BoxSizesResponse;
     /// Requests the geometry of this node. Used by APIs such as `clientTop`.
     fn node_geometry(&self) -> NodeGeometryResponse;
     /// Requests the scroll geometry of this node. Used by APIs such as `scrollTop`.
@@ -49,6 +52,16 @@ pub struct ContentBoxResponse(pub Option<Rect<Au>>);
 
 pub struct ContentBoxesResponse(pub Vec<Rect<Au>>);
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoxSizes {
+    /// The content box, relative to the padding box.
+    pub content_box: Rect<Au>,
+    pub border_box: UntypedSize2D<Au>,
+}
+
+/// The sizes of the boxes of a node, or `None` if it has no box.
+pub struct BoxSizesResponse(pub Option<BoxSizes>);
+
 pub struct NodeGeometryResponse {
     pub client_rect: Rect<i32>,
 }
@@ -33,6 +33,7 @@ use webrender_api::ExternalScrollId;
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PseudoElementType {
     Normal,
+    Backdrop,
     Before,
     After,
     DetailsSummary,
@@ -43,6 +44,7 @@ impl PseudoElementType {
     pub fn fragment_type(&self) -> FragmentType {
         match *self {
             PseudoElementType::Normal => FragmentType::FragmentBody,
+            PseudoElementType::Backdrop => FragmentType::FragmentBody,
             PseudoElementType::Before => FragmentType::BeforePseudoContent,
             PseudoElementType::After => FragmentType::AfterPseudoContent,
             PseudoElementType::DetailsSummary => FragmentType::FragmentBody,
@@ -69,6 +71,7 @@ impl PseudoElementType {
             PseudoElementType::Normal => {
                 unreachable!("style_pseudo_element called with PseudoElementType::Normal")
             },
+            PseudoElementType::Backdrop => PseudoElement::Backdrop,
             PseudoElementType::Before => PseudoElement::Before,
             PseudoElementType::After => PseudoElement::After,
             PseudoElementType::DetailsSummary => PseudoElement::DetailsSummary,
@@ -191,6 +194,12 @@ pub trait ThreadSafeLayoutNode:
     /// the parent until all the children have been processed.
     fn parent_style(&self) -> Arc<ComputedValues>;
 
+    fn get_backdrop_pseudo(&self) -> Option<Self> {
+        self.as_element()
+            .and_then(|el| el.get_backdrop_pseudo())
+            .map(|el| el.as_node())
+    }
+
     fn get_before_pseudo(&self) -> Option<Self> {
         self.as_element()
             .and_then(|el| el.get_before_pseudo())
@@ -355,6 +364,17 @@ pub trait ThreadSafeLayoutElement:
 
     fn get_pseudo_element_type(&self) -> PseudoElementType;
 
+    /// Elements in the top layer, like the fullscreen element, have a backdrop.
+    /// <https://fullscreen.spec.whatwg.org/#::backdrop-pseudo-element>
+    #[inline]
+    fn get_backdrop_pseudo(&self) -> Option<Self> {
+        if self.style_data().styles.primary().in_top_layer() {
+            Some(self.with_pseudo(PseudoElementType::Backdrop))
+        } else {
+            None
+        }
+    }
+
     #[inline]
     fn get_before_pseudo(&self) -> Option<Self> {
         if self
@@ -466,6 +486,18 @@ pub trait ThreadSafeLayoutElement:
             .clone()
     }
 
+    /// Returns the style of the `::first-letter` or `::first-line` pseudo-element of this
+    /// element, if any rules apply to it. Unlike other pseudo-elements, these style parts of
+    /// the boxes of the element rather than boxes of their own.
+    #[inline]
+    fn part_pseudo_style(&self, pseudo: &PseudoElement) -> Option<Arc<ComputedValues>> {
+        debug_assert!(pseudo.is_first_letter() || pseudo.is_first_line());
+        if self.get_pseudo_element_type() != PseudoElementType::Normal {
+            return None;
+        }
+        self.style_data().styles.pseudos.get(pseudo).cloned()
+    }
+
     /// Returns the already resolved style of the node.
     ///
     /// This differs from `style(ctx)` in that if the pseudo-element has not yet
@@ -28,7 +28,8 @@ use bluetooth_traits::BluetoothRequest;
 use canvas_traits::webgl::WebGLPipeline;
 use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 use devtools_traits::{DevtoolScriptControlMsg, ScriptToDevtoolsControlMsg, WorkerId};
-use embedder_traits::EventLoopWaker;
+use embedder_traits::SavedDocumentState;
+use embedder_traits::{EventLoopWaker, MemoryPressureReport, PromptRequest, PromptResponse};
 use euclid::{default::Point2D, Length, Rect, Scale, Size2D, UnknownUnit, Vector2D};
 use gfx_traits::Epoch;
 use http::HeaderMap;
@@ -47,7 +48,7 @@ use msg::constellation_msg::{
 use msg::constellation_msg::{PipelineNamespaceId, TopLevelBrowsingContextId};
 use net_traits::image::base::Image;
 use net_traits::image_cache::ImageCache;
-use net_traits::request::Referrer;
+use net_traits::request::{ClientViewport, Referrer};
 use net_traits::storage_thread::StorageType;
 use net_traits::{FetchResponseMsg, ReferrerPolicy, ResourceThreads};
 use pixels::PixelFormat;
@@ -55,6 +56,7 @@ use profile_traits::mem;
 use profile_traits::time as profile_time;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use servo_atoms::Atom;
+use servo_config::prefs::PrefValue;
 use servo_url::ImmutableOrigin;
 use servo_url::ServoUrl;
 use std::borrow::Cow;
@@ -72,11 +74,11 @@ use webrender_api::{BuiltDisplayListDescriptor, HitTestFlags, HitTestResult, Res
 use webvr_traits::{WebVREvent, WebVRMsg};
 
 pub use crate::script_msg::{
-    DOMMessage, HistoryEntryReplacement, SWManagerMsg, SWManagerSenders, ScopeThings,
-    ServiceWorkerMsg,
+    BoxModelHighlight, EventResult, IFrameSize, IFrameSizeMsg, LayoutMsg, LogEntry, ScriptMsg,
 };
 pub use crate::script_msg::{
-    EventResult, IFrameSize, IFrameSizeMsg, LayoutMsg, LogEntry, ScriptMsg,
+    ClientFrameType, ClientInfo, DOMMessage, HistoryEntryReplacement, SWManagerMsg,
+    SWManagerSenders, ScopeThings, ServiceWorkerMsg,
 };
 
 /// The address of a node. Layout sends these back. They must be validated via
@@ -132,6 +134,8 @@ pub enum LayoutControlMsg {
     GetWebFontLoadState(IpcSender<bool>),
     /// Send the paint time for a specific epoch to the layout thread.
     PaintMetric(Epoch, u64),
+    /// The system is low on memory: the layout thread clears its font caches.
+    MemoryPressure,
 }
 
 /// The origin where a given load was initiated.
@@ -179,6 +183,25 @@ pub struct LoadData {
 
     /// The source to use instead of a network response for a srcdoc document.
     pub srcdoc: String,
+
+    /// The state to restore in the document once it has loaded, if this load
+    /// comes from a restored session.
+    pub document_state: Option<SavedDocumentState>,
+
+    /// Whether this load replaces a document which the embedder discarded.
+    pub was_discarded: bool,
+
+    /// Whether the loaded document can't show modal dialogs, because it is in an iframe
+    /// sandboxed without `allow-modals`.
+    pub sandboxed_modals: bool,
+
+    /// Whether the loaded document can only navigate itself, its descendants and its
+    /// top-level browsing context, because it is in a sandboxed iframe.
+    pub sandboxed_navigation: bool,
+
+    /// Whether the loaded document can't navigate its top-level browsing context,
+    /// because it is in an iframe sandboxed without `allow-top-navigation`.
+    pub sandboxed_top_level_navigation: bool,
 }
 
 /// The result of evaluating a javascript scheme url.
@@ -211,6 +234,11 @@ impl LoadData {
             referrer: referrer,
             referrer_policy: referrer_policy,
             srcdoc: "".to_string(),
+            document_state: None,
+            was_discarded: false,
+            sandboxed_modals: false,
+            sandboxed_navigation: false,
+            sandboxed_top_level_navigation: false,
         }
     }
 }
@@ -320,6 +348,8 @@ pub enum ConstellationControlMsg {
     GetTitle(PipelineId),
     /// Notifies script thread of a change to one of its document's activity
     SetDocumentActivity(PipelineId, DocumentActivity),
+    /// Notifies script thread that the embedder froze or resumed one of its documents.
+    SetDocumentFrozen(PipelineId, bool),
     /// Notifies script thread whether frame is visible
     ChangeFrameVisibilityStatus(PipelineId, bool),
     /// Notifies script thread that frame visibility change is complete
@@ -333,6 +363,9 @@ pub enum ConstellationControlMsg {
         LoadData,
         HistoryEntryReplacement,
     ),
+    /// Notifies script thread that a url should be loaded in the browsing context of
+    /// this pipeline, which a document of another script thread navigated.
+    Navigate(PipelineId, LoadData, HistoryEntryReplacement),
     /// Post a message to a given window.
     PostMessage {
         /// The target of the message.
@@ -403,6 +436,42 @@ pub enum ConstellationControlMsg {
     PaintMetric(PipelineId, ProgressiveWebMetricType, u64),
     /// Notifies the media session about a user requested media session action.
     MediaSessionAction(PipelineId, MediaSessionActionType),
+    /// Requests the scroll offsets and form control state of the document, to save
+    /// it as part of the session.
+    GetSavedDocumentState(PipelineId, IpcSender<Option<SavedDocumentState>>),
+    /// Requests the size of the scrolling area of the document, used to take screenshots
+    /// of the whole page.
+    GetDocumentScrollSize(PipelineId, IpcSender<Option<Size2D<f32, CSSPixel>>>),
+    /// Notifies the script thread that the scale of the visual viewport of a top-level
+    /// document, or its offset from the layout viewport, changed after a pinch zoom or
+    /// a scroll.
+    SetVisualViewport(PipelineId, f32, Vector2D<f32, CSSPixel>),
+    /// Notifies the script thread that a descendant of a document was activated by the user.
+    NotifyUserActivation(PipelineId),
+    /// Notifies the script thread that the user activation of a document was consumed.
+    ConsumeUserActivation(PipelineId),
+    /// The embedder closed the picture-in-picture surface.
+    ExitPictureInPicture(PipelineId),
+    /// The media input or output devices available to content changed.
+    MediaDevicesChanged(PipelineId),
+    /// The system is low on memory: the script thread garbage collects its JS heap and
+    /// evicts the unused images from its image cache, and reports the memory recovered.
+    MemoryPressure(IpcSender<MemoryPressureReport>),
+    /// A preference changed in the main process, and changes in the content processes.
+    SetPreference(String, PrefValue),
+    /// The embedder overrode preferences for the documents of a browser.
+    SetPreferenceOverrides(TopLevelBrowsingContextId, HashMap<String, PrefValue>),
+    /// The embedder replaced the stylesheets of the documents of a browser.
+    SetStylesheets(TopLevelBrowsingContextId, EmbedderStylesheets),
+    /// The embedder asked for the size of the content of a browser to be reported
+    /// whenever it changes, or stopped asking for it.
+    SetAutoResize(TopLevelBrowsingContextId, bool),
+    /// The service worker of the given scope claimed the clients of its origin, which
+    /// the document becomes controlled by if its url is in the scope.
+    ClaimByServiceWorker(PipelineId, ServoUrl),
+    /// Dispatch a message posted with `Client.postMessage` by the service worker of the
+    /// given scope to the document.
+    PostMessageFromServiceWorker(PipelineId, ServoUrl, DOMMessage),
 }
 
 impl fmt::Debug for ConstellationControlMsg {
@@ -422,9 +491,11 @@ impl fmt::Debug for ConstellationControlMsg {
             SetScrollState(..) => "SetScrollState",
             GetTitle(..) => "GetTitle",
             SetDocumentActivity(..) => "SetDocumentActivity",
+            SetDocumentFrozen(..) => "SetDocumentFrozen",
             ChangeFrameVisibilityStatus(..) => "ChangeFrameVisibilityStatus",
             NotifyVisibilityChange(..) => "NotifyVisibilityChange",
             NavigateIframe(..) => "NavigateIframe",
+            Navigate(..) => "Navigate",
             PostMessage { .. } => "PostMessage",
             UpdatePipelineId(..) => "UpdatePipelineId",
             UpdateHistoryState(..) => "UpdateHistoryState",
@@ -442,6 +513,20 @@ impl fmt::Debug for ConstellationControlMsg {
             PaintMetric(..) => "PaintMetric",
             ExitFullScreen(..) => "ExitFullScreen",
             MediaSessionAction(..) => "MediaSessionAction",
+            GetSavedDocumentState(..) => "GetSavedDocumentState",
+            GetDocumentScrollSize(..) => "GetDocumentScrollSize",
+            SetVisualViewport(..) => "SetVisualViewport",
+            NotifyUserActivation(..) => "NotifyUserActivation",
+            ConsumeUserActivation(..) => "ConsumeUserActivation",
+            ExitPictureInPicture(..) => "ExitPictureInPicture",
+            MediaDevicesChanged(..) => "MediaDevicesChanged",
+            MemoryPressure(..) => "MemoryPressure",
+            SetPreference(..) => "SetPreference",
+            SetPreferenceOverrides(..) => "SetPreferenceOverrides",
+            SetStylesheets(..) => "SetStylesheets",
+            SetAutoResize(..) => "SetAutoResize",
+            ClaimByServiceWorker(..) => "ClaimByServiceWorker",
+            PostMessageFromServiceWorker(..) => "PostMessageFromServiceWorker",
         };
         write!(formatter, "ConstellationControlMsg::{}", variant)
     }
@@ -566,6 +651,8 @@ pub enum CompositorEvent {
     ),
     /// A wheel event was generated with a delta in the X, Y, and/or Z directions
     WheelEvent(WheelDelta, Point2D<f32>, Option<UntrustedNodeAddress>),
+    /// A double tap, used to zoom on the tapped block.
+    DoubleTapEvent(Point2D<f32>, Option<UntrustedNodeAddress>),
     /// A key was pressed.
     KeyboardEvent(KeyboardEvent),
     /// An event from the IME is dispatched.
@@ -651,6 +738,8 @@ pub struct InitialScriptState {
     pub scheduler_chan: IpcSender<TimerSchedulerMsg>,
     /// A channel to the resource manager thread.
     pub resource_threads: ResourceThreads,
+    /// Whether the resource threads are those of a private browsing session.
+    pub is_private: bool,
     /// A channel to the bluetooth thread.
     pub bluetooth_thread: IpcSender<BluetoothRequest>,
     /// The image cache for this script thread.
@@ -792,6 +881,16 @@ pub struct WindowSizeData {
     pub device_pixel_ratio: Scale<f32, CSSPixel, DevicePixel>,
 }
 
+impl WindowSizeData {
+    /// The viewport which the client hints of the requests of the window describe.
+    pub fn client_viewport(&self) -> ClientViewport {
+        ClientViewport {
+            device_pixel_ratio: self.device_pixel_ratio.get(),
+            width: self.initial_viewport.width,
+        }
+    }
+}
+
 /// The type of window size change.
 #[derive(Clone, Copy, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
 pub enum WindowSizeType {
@@ -821,6 +920,9 @@ pub enum WebDriverCommandMsg {
     MouseButtonAction(MouseEventType, MouseButton, f32, f32),
     /// Act as if the mouse was moved in the browsing context with the given ID.
     MouseMoveAction(f32, f32),
+    /// Act as if the mouse wheel was scrolled by the given delta, in CSS pixels, at the
+    /// given point.
+    WheelScrollAction(f32, f32, f64, f64),
     /// Set the window size.
     SetWindowSize(
         TopLevelBrowsingContextId,
@@ -830,9 +932,43 @@ pub enum WebDriverCommandMsg {
     /// Take a screenshot of the window.
     TakeScreenshot(
         TopLevelBrowsingContextId,
-        Option<Rect<f32, CSSPixel>>,
+        ScreenshotArea,
         IpcSender<Option<Image>>,
     ),
+    /// Get the prompt currently shown for the top-level browsing context, if any.
+    GetPrompt(TopLevelBrowsingContextId, IpcSender<Option<PromptRequest>>),
+    /// Answer the prompt currently shown for the top-level browsing context.
+    RespondToPrompt(TopLevelBrowsingContextId, PromptResponse),
+}
+
+/// The part of a page captured by a screenshot.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScreenshotArea {
+    /// The part of the page currently visible in the viewport.
+    Viewport,
+    /// A rectangle of the viewport.
+    Rect(Rect<f32, CSSPixel>),
+    /// The whole page. The viewport is temporarily resized to the size of the
+    /// document while the screenshot is taken.
+    FullPage,
+}
+
+/// The stylesheets which the embedder supplies for the documents of a browser, as
+/// their source and URL.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct EmbedderStylesheets {
+    /// Stylesheets at the user agent origin, which replace the user agent stylesheet of the
+    /// engine in its place in the cascade.
+    pub user_agent: Vec<(String, ServoUrl)>,
+    /// Stylesheets at the user origin, whose `!important` declarations override those
+    /// of the documents.
+    pub user: Vec<(String, ServoUrl)>,
+}
+
+impl EmbedderStylesheets {
+    pub fn is_empty(&self) -> bool {
+        self.user_agent.is_empty() && self.user.is_empty()
+    }
 }
 
 /// Resources required by workerglobalscopes
@@ -862,6 +998,8 @@ pub struct WorkerGlobalScopeInit {
     pub is_headless: bool,
     /// An optional string allowing the user agnet to be set for testing.
     pub user_agent: Cow<'static, str>,
+    /// True if the worker belongs to a private browsing session.
+    pub is_private: bool,
 }
 
 /// Common entities representing a network load origin
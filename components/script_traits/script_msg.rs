@@ -17,9 +17,9 @@ use crate::WorkerGlobalScopeInit;
 use crate::WorkerScriptLoadOrigin;
 use canvas_traits::canvas::{CanvasId, CanvasMsg};
 use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
-use embedder_traits::{EmbedderMsg, MediaSessionEvent};
+use embedder_traits::{EmbedderMsg, MediaSessionEvent, PromptRequest, PromptResponse, TaskReport};
 use euclid::default::Size2D as UntypedSize2D;
-use euclid::Size2D;
+use euclid::{Point2D, Rect, Size2D};
 use gfx_traits::Epoch;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use msg::constellation_msg::{
@@ -35,8 +35,10 @@ use servo_url::ServoUrl;
 use smallvec::SmallVec;
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::time::Duration;
 use style_traits::viewport::ViewportConstraints;
 use style_traits::CSSPixel;
+use uuid::Uuid;
 use webgpu::{wgpu, WebGPUResponseResult};
 use webrender_api::units::{DeviceIntPoint, DeviceIntSize};
 
@@ -58,6 +60,16 @@ pub struct IFrameSizeMsg {
     pub type_: WindowSizeType,
 }
 
+/// The boxes of a node highlighted by the devtools inspector, in CSS pixels relative to
+/// the viewport.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct BoxModelHighlight {
+    pub margin: Rect<f32, CSSPixel>,
+    pub border: Rect<f32, CSSPixel>,
+    pub padding: Rect<f32, CSSPixel>,
+    pub content: Rect<f32, CSSPixel>,
+}
+
 /// Messages from the layout to the constellation.
 #[derive(Deserialize, Serialize)]
 pub enum LayoutMsg {
@@ -208,6 +220,12 @@ pub enum ScriptMsg {
     LoadUrl(LoadData, HistoryEntryReplacement),
     /// Abort loading after sending a LoadUrl message.
     AbortLoadUrl,
+    /// Navigate a browsing context of another origin, through its `Location` object.
+    NavigateBrowsingContext(BrowsingContextId, LoadData, HistoryEntryReplacement),
+    /// Close an auxiliary browsing context of another origin, with `window.close()`.
+    CloseAuxiliaryBrowsingContext(BrowsingContextId),
+    /// Focus a browsing context of another origin, with `window.focus()`.
+    FocusBrowsingContext(BrowsingContextId),
     /// Post a message to the currently active window of a given browsing context.
     PostMessage {
         /// The target of the posted message.
@@ -254,8 +272,26 @@ pub enum ScriptMsg {
     SetFinalUrl(ServoUrl),
     /// Script has handled a touch event, and either prevented or allowed default actions.
     TouchEventProcessed(EventResult),
+    /// Script has found the block that was double tapped. Zoom so that the given rect
+    /// fills the width of the viewport, keeping the tapped point in view. Both are in
+    /// page coordinates.
+    ZoomToRect(Rect<f32, CSSPixel>, Point2D<f32, CSSPixel>),
+    /// Draw the given box model highlight over the page, or remove the current one.
+    HighlightBoxModel(Option<BoxModelHighlight>),
+    /// Start the sampling profiler, with the given sampling rate and maximum duration.
+    EnableProfiler(Duration, Duration),
+    /// Stop the sampling profiler, sending the profile to the given channel instead
+    /// of the embedder.
+    DisableProfiler(Option<IpcSender<Vec<u8>>>),
+    /// The user activated this document. Ancestor documents need to be notified.
+    NotifyUserActivation,
+    /// An API consumed the user activation of this document. All the documents of
+    /// its top-level browsing context lose their activation.
+    ConsumeUserActivation,
     /// A log entry, with the top-level browsing context id and thread name
     LogEntry(Option<String>, LogEntry),
+    /// A task of this pipeline ran for long, which is reported to the embedder.
+    ReportLongTask(TaskReport),
     /// Discard the document.
     DiscardDocument,
     /// Discard the browsing context.
@@ -267,6 +303,15 @@ pub enum ScriptMsg {
     ForwardDOMMessage(DOMMessage, ServoUrl),
     /// Store the data required to activate a service worker for the given scope
     RegisterServiceWorker(ScopeThings, ServoUrl),
+    /// The document of this pipeline is now controlled by the service worker of a scope.
+    SetServiceWorkerController(ServoUrl),
+    /// Register the URL of the handler of a scheme, which contains `%s`.
+    RegisterProtocolHandler(String, ServoUrl),
+    /// Unregister the handler of a scheme, if it has the given URL.
+    UnregisterProtocolHandler(String, ServoUrl),
+    /// Queue a prompt for the browser of the pipeline. The answer is sent once the
+    /// prompts queued before it have been answered.
+    Prompt(PromptRequest, IpcSender<PromptResponse>),
     /// Get Window Informations size and position
     GetClientWindow(IpcSender<(DeviceIntSize, DeviceIntPoint)>),
     /// Get the screen size (pixel)
@@ -314,6 +359,9 @@ impl fmt::Debug for ScriptMsg {
             LoadComplete => "LoadComplete",
             LoadUrl(..) => "LoadUrl",
             AbortLoadUrl => "AbortLoadUrl",
+            NavigateBrowsingContext(..) => "NavigateBrowsingContext",
+            CloseAuxiliaryBrowsingContext(..) => "CloseAuxiliaryBrowsingContext",
+            FocusBrowsingContext(..) => "FocusBrowsingContext",
             PostMessage { .. } => "PostMessage",
             NavigatedToFragment(..) => "NavigatedToFragment",
             TraverseHistory(..) => "TraverseHistory",
@@ -329,12 +377,23 @@ impl fmt::Debug for ScriptMsg {
             SetDocumentState(..) => "SetDocumentState",
             SetFinalUrl(..) => "SetFinalUrl",
             TouchEventProcessed(..) => "TouchEventProcessed",
+            ZoomToRect(..) => "ZoomToRect",
+            HighlightBoxModel(..) => "HighlightBoxModel",
+            EnableProfiler(..) => "EnableProfiler",
+            DisableProfiler(..) => "DisableProfiler",
+            NotifyUserActivation => "NotifyUserActivation",
+            ConsumeUserActivation => "ConsumeUserActivation",
             LogEntry(..) => "LogEntry",
+            ReportLongTask(..) => "ReportLongTask",
             DiscardDocument => "DiscardDocument",
             DiscardTopLevelBrowsingContext => "DiscardTopLevelBrowsingContext",
             PipelineExited => "PipelineExited",
             ForwardDOMMessage(..) => "ForwardDOMMessage",
             RegisterServiceWorker(..) => "RegisterServiceWorker",
+            SetServiceWorkerController(..) => "SetServiceWorkerController",
+            RegisterProtocolHandler(..) => "RegisterProtocolHandler",
+            UnregisterProtocolHandler(..) => "UnregisterProtocolHandler",
+            Prompt(..) => "Prompt",
             GetClientWindow(..) => "GetClientWindow",
             GetScreenSize(..) => "GetScreenSize",
             GetScreenAvailSize(..) => "GetScreenAvailSize",
@@ -369,6 +428,34 @@ pub struct DOMMessage {
     pub data: StructuredSerializedData,
 }
 
+/// The kind of browsing context of a window client.
+/// <https://w3c.github.io/ServiceWorker/#dom-client-frametype>
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ClientFrameType {
+    /// A top-level browsing context opened by another one.
+    Auxiliary,
+    /// A top-level browsing context.
+    TopLevel,
+    /// A nested browsing context.
+    Nested,
+}
+
+/// A window client of a service worker, as found by the constellation.
+/// <https://w3c.github.io/ServiceWorker/#client-concept>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClientInfo {
+    /// The pipeline of the document of the client, which the messages to it are sent to.
+    pub pipeline_id: PipelineId,
+    /// The id of the client, which is exposed to service workers instead of the pipeline.
+    pub id: Uuid,
+    /// The url of the document of the client.
+    pub url: ServoUrl,
+    /// The kind of browsing context of the client.
+    pub frame_type: ClientFrameType,
+    /// The scope of the service worker controlling the client, if any.
+    pub controller: Option<ServoUrl>,
+}
+
 /// Channels to allow service worker manager to communicate with constellation and resource thread
 pub struct SWManagerSenders {
     /// sender for communicating with constellation
@@ -386,6 +473,13 @@ pub enum ServiceWorkerMsg {
     Timeout(ServoUrl),
     /// Message sent by constellation to forward to a running service worker
     ForwardDOMMessage(DOMMessage, ServoUrl),
+    /// Find the clients of the service worker of a scope, including the ones which it
+    /// doesn't control if the flag is set.
+    MatchClients(ServoUrl, bool, IpcSender<Vec<ClientInfo>>),
+    /// Make the service worker of a scope the controller of the clients in its scope.
+    ClaimClients(ServoUrl, IpcSender<()>),
+    /// Message sent by the service worker of a scope to one of its clients.
+    PostMessageToClient(PipelineId, ServoUrl, DOMMessage),
     /// Exit the service worker manager
     Exit,
 }
@@ -395,4 +489,10 @@ pub enum ServiceWorkerMsg {
 pub enum SWManagerMsg {
     /// Provide the constellation with a means of communicating with the Service Worker Manager
     OwnSender(IpcSender<ServiceWorkerMsg>),
+    /// Find the documents of an origin which are service worker clients
+    MatchClients(ImmutableOrigin, IpcSender<Vec<ClientInfo>>),
+    /// Let the clients of the origin of a scope be claimed by its service worker
+    ClaimClients(ServoUrl, IpcSender<()>),
+    /// Forward a message from the service worker of a scope to one of its clients
+    PostMessageToClient(PipelineId, ServoUrl, DOMMessage),
 }
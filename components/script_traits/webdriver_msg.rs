@@ -30,9 +30,11 @@ pub enum WebDriverScriptCommand {
     FindElementCSS(String, IpcSender<Result<Option<String>, ErrorStatus>>),
     FindElementLinkText(String, bool, IpcSender<Result<Option<String>, ErrorStatus>>),
     FindElementTagName(String, IpcSender<Result<Option<String>, ErrorStatus>>),
+    FindElementXPath(String, IpcSender<Result<Option<String>, ErrorStatus>>),
     FindElementsCSS(String, IpcSender<Result<Vec<String>, ErrorStatus>>),
     FindElementsLinkText(String, bool, IpcSender<Result<Vec<String>, ErrorStatus>>),
     FindElementsTagName(String, IpcSender<Result<Vec<String>, ErrorStatus>>),
+    FindElementsXPath(String, IpcSender<Result<Vec<String>, ErrorStatus>>),
     FindElementElementCSS(
         String,
         String,
@@ -49,6 +51,11 @@ pub enum WebDriverScriptCommand {
         String,
         IpcSender<Result<Option<String>, ErrorStatus>>,
     ),
+    FindElementElementXPath(
+        String,
+        String,
+        IpcSender<Result<Option<String>, ErrorStatus>>,
+    ),
     FindElementElementsCSS(String, String, IpcSender<Result<Vec<String>, ErrorStatus>>),
     FindElementElementsLinkText(
         String,
@@ -57,8 +64,10 @@ pub enum WebDriverScriptCommand {
         IpcSender<Result<Vec<String>, ErrorStatus>>,
     ),
     FindElementElementsTagName(String, String, IpcSender<Result<Vec<String>, ErrorStatus>>),
+    FindElementElementsXPath(String, String, IpcSender<Result<Vec<String>, ErrorStatus>>),
     FocusElement(String, IpcSender<Result<(), ErrorStatus>>),
     ElementClick(String, IpcSender<Result<Option<String>, ErrorStatus>>),
+    ElementClear(String, IpcSender<Result<(), ErrorStatus>>),
     GetActiveElement(IpcSender<Option<String>>),
     GetCookie(String, IpcSender<Vec<Serde<Cookie<'static>>>>),
     GetCookies(IpcSender<Vec<Serde<Cookie<'static>>>>),
@@ -70,7 +70,7 @@ use canvas_traits::webgl::WebGLThreads;
 use compositing::compositor_thread::{
     CompositorProxy, CompositorReceiver, InitialCompositorState, Msg,
 };
-use compositing::windowing::{EmbedderMethods, WindowEvent, WindowMethods};
+use compositing::windowing::{EmbedderMethods, EventLoopObserver, WindowEvent, WindowMethods};
 use compositing::{CompositingReason, ConstellationMsg, IOCompositor, ShutdownState};
 #[cfg(all(
     not(target_os = "windows"),
@@ -82,8 +82,9 @@ use compositing::{CompositingReason, ConstellationMsg, IOCompositor, ShutdownSta
 use constellation::content_process_sandbox_profile;
 use constellation::{Constellation, InitialConstellationState, UnprivilegedPipelineContent};
 use constellation::{FromCompositorLogger, FromScriptLogger};
-use crossbeam_channel::{unbounded, Sender};
-use embedder_traits::{EmbedderMsg, EmbedderProxy, EmbedderReceiver, EventLoopWaker};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg};
+use embedder_traits::{EmbedderMsg, EmbedderProxy, EmbedderReceiver, EventLoopWaker, PromptMsg};
 use env_logger::Builder as EnvLoggerBuilder;
 use euclid::{Scale, Size2D};
 #[cfg(all(
@@ -100,6 +101,7 @@ use log::{Log, Metadata, Record};
 use media::{GLPlayerThreads, WindowGLContext};
 use msg::constellation_msg::{PipelineNamespace, PipelineNamespaceId};
 use net::resource_thread::new_resource_threads;
+use net_traits::protocol_handler::ProtocolRegistry;
 use net_traits::IpcSend;
 use profile::mem as profile_mem;
 use profile::time as profile_time;
@@ -108,7 +110,7 @@ use profile_traits::time;
 use script::JSEngineSetup;
 use script_traits::{SWManagerSenders, ScriptToConstellationChan, WindowSizeData};
 use servo_config::opts;
-use servo_config::{pref, prefs};
+use servo_config::{observe_pref, pref, prefs, set_pref};
 use servo_media::player::context::GlContext;
 use servo_media::ServoMedia;
 use std::borrow::Cow;
@@ -358,10 +360,22 @@ where
         );
         let mem_profiler_chan = profile_mem::Profiler::create(opts.mem_profiler_period);
 
+        // The prompts of the network and devtools threads are queued by the constellation.
+        let (prompt_sender, prompt_receiver) = unbounded();
+
         let debugger_chan = opts.debugger_port.map(|port| debugger::start_server(port));
-        let devtools_chan = opts
-            .devtools_port
-            .map(|port| devtools::start_server(port, embedder_proxy.clone()));
+        let devtools_port = opts.devtools_port.or_else(|| {
+            if pref!(devtools.server.enabled) {
+                Some(pref!(devtools.server.port) as u16)
+            } else {
+                None
+            }
+        });
+        let devtools_chan = devtools_port.map(|port| {
+            devtools::start_server(port, embedder_proxy.clone(), prompt_sender.clone())
+        });
+        let observed_devtools_chan = devtools_chan.clone();
+        let devtools_prompt_sender = prompt_sender.clone();
 
         let coordinates = window.get_coordinates();
         let device_pixel_ratio = coordinates.hidpi_factor.get();
@@ -515,6 +529,14 @@ where
 
         let pending_wr_frame = Arc::new(AtomicBool::new(false));
 
+        let protocols = Arc::new(embedder.get_protocol_handlers());
+
+        // The script threads only report their long tasks when they are observed.
+        let event_loop_observer = embedder.create_event_loop_observer();
+        if event_loop_observer.is_some() {
+            set_pref!(dom.instrumentation.enabled, true);
+        }
+
         // Create the constellation, which maintains the engine
         // pipelines, including the script and layout threads, as well
         // as the navigation context.
@@ -523,6 +545,8 @@ where
             opts.config_dir.clone(),
             embedder_proxy.clone(),
             compositor_proxy.clone(),
+            prompt_sender,
+            prompt_receiver,
             time_profiler_chan.clone(),
             mem_profiler_chan.clone(),
             debugger_chan,
@@ -538,11 +562,20 @@ where
             event_loop_waker,
             window_size,
             pending_wr_frame.clone(),
+            protocols,
+            event_loop_observer.clone(),
         );
 
         // Send the constellation's swmanager sender to service worker manager thread
         script::init_service_workers(sw_senders);
 
+        observe_devtools_server(
+            observed_devtools_chan,
+            embedder_proxy.clone(),
+            devtools_prompt_sender,
+            constellation_chan.clone(),
+        );
+
         if cfg!(feature = "webdriver") {
             if let Some(port) = opts.webdriver_port {
                 webdriver(port, constellation_chan.clone());
@@ -565,6 +598,7 @@ where
                 webvr_heartbeats,
                 webxr_main_thread,
                 pending_wr_frame,
+                event_loop_observer,
             },
             opts.output_file.clone(),
             opts.is_running_problem_test,
@@ -671,6 +705,26 @@ where
                 }
             },
 
+            WindowEvent::ExitPictureInPicture(top_level_browsing_context_id) => {
+                let msg = ConstellationMsg::ExitPictureInPicture(top_level_browsing_context_id);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending exit picture-in-picture to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
+            WindowEvent::MediaDevicesChanged => {
+                let msg = ConstellationMsg::MediaDevicesChanged;
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending media devices change to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
             WindowEvent::Reload(top_level_browsing_context_id) => {
                 let msg = ConstellationMsg::Reload(top_level_browsing_context_id);
                 if let Err(e) = self.constellation_chan.send(msg) {
@@ -708,6 +762,16 @@ where
                 }
             },
 
+            WindowEvent::NewPrivateBrowser(url, browser_id) => {
+                let msg = ConstellationMsg::NewPrivateBrowser(url, browser_id);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending NewPrivateBrowser message to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
             WindowEvent::SelectBrowser(ctx) => {
                 let msg = ConstellationMsg::SelectBrowser(ctx);
                 if let Err(e) = self.constellation_chan.send(msg) {
@@ -760,6 +824,119 @@ where
                     );
                 }
             },
+
+            WindowEvent::SaveSession => {
+                let msg = ConstellationMsg::SaveSession;
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending SaveSession to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::RestoreSession(session) => {
+                let msg = ConstellationMsg::RestoreSession(session);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending RestoreSession to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::CaptureScreenshot(top_level_browsing_context_id, area, id) => {
+                let msg =
+                    ConstellationMsg::CaptureScreenshot(top_level_browsing_context_id, area, id);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending CaptureScreenshot to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
+            WindowEvent::OverrideDevicePixelRatio(top_level_browsing_context_id, ratio) => {
+                self.compositor
+                    .on_device_pixel_ratio_override(top_level_browsing_context_id, ratio);
+            },
+
+            WindowEvent::MemoryPressure => {
+                let msg = ConstellationMsg::MemoryPressure;
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending MemoryPressure to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::CollectMemoryReports => {
+                let msg = ConstellationMsg::CollectMemoryReports;
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending CollectMemoryReports to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
+            WindowEvent::SetPreferenceOverrides(top_level_browsing_context_id, overrides) => {
+                let msg = ConstellationMsg::SetPreferenceOverrides(
+                    top_level_browsing_context_id,
+                    overrides,
+                );
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending SetPreferenceOverrides to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
+            WindowEvent::SetStylesheets(top_level_browsing_context_id, stylesheets) => {
+                let msg =
+                    ConstellationMsg::SetStylesheets(top_level_browsing_context_id, stylesheets);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending SetStylesheets to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::SetAutoResize(top_level_browsing_context_id, enabled) => {
+                let msg = ConstellationMsg::SetAutoResize(top_level_browsing_context_id, enabled);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending SetAutoResize to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::FreezeBrowser(top_level_browsing_context_id, frozen) => {
+                let msg = ConstellationMsg::FreezeBrowser(top_level_browsing_context_id, frozen);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending FreezeBrowser to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::DiscardBrowser(top_level_browsing_context_id) => {
+                let msg = ConstellationMsg::DiscardBrowser(top_level_browsing_context_id);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending DiscardBrowser to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::SetProxy(url, bypass) => {
+                let msg = ConstellationMsg::SetProxy(url, bypass);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending SetProxy to constellation failed ({:?}).", e);
+                }
+            },
+
+            WindowEvent::ReportBlockedContent(top_level_browsing_context_id) => {
+                let msg = ConstellationMsg::ReportBlockedContent(top_level_browsing_context_id);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending ReportBlockedContent to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
+
+            WindowEvent::RespondToPrompt(prompt_id, response) => {
+                let msg = ConstellationMsg::RespondToPrompt(prompt_id, response);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!("Sending RespondToPrompt to constellation failed ({:?}).", e);
+                }
+            },
         }
     }
 
@@ -832,6 +1009,43 @@ where
     }
 }
 
+/// Start the devtools server when it is enabled at runtime, or make it stop listening when
+/// it is disabled.
+fn observe_devtools_server(
+    devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+    embedder_proxy: EmbedderProxy,
+    prompt_sender: Sender<PromptMsg>,
+    constellation_chan: Sender<ConstellationMsg>,
+) {
+    let devtools_chan = Mutex::new(devtools_chan);
+    let embedder_proxy = Mutex::new(embedder_proxy);
+    observe_pref!(devtools.server.enabled, move |enabled: bool| {
+        let port = pref!(devtools.server.port) as u16;
+        let mut devtools_chan = devtools_chan.lock().unwrap();
+        if let Some(ref devtools_chan) = *devtools_chan {
+            let msg = if enabled {
+                ChromeToDevtoolsControlMsg::StartListening(port)
+            } else {
+                ChromeToDevtoolsControlMsg::StopListening
+            };
+            if let Err(e) = devtools_chan.send(DevtoolsControlMsg::FromChrome(msg)) {
+                warn!("Sending to devtools failed ({:?}).", e);
+            }
+            return;
+        }
+        if !enabled {
+            return;
+        }
+        let embedder_proxy = embedder_proxy.lock().unwrap().clone();
+        let chan = devtools::start_server(port, embedder_proxy, prompt_sender.clone());
+        let msg = ConstellationMsg::SetDevtoolsChan(chan.clone());
+        if let Err(e) = constellation_chan.send(msg) {
+            warn!("Sending devtools chan to constellation failed ({:?}).", e);
+        }
+        *devtools_chan = Some(chan);
+    });
+}
+
 fn create_embedder_channel(
     event_loop_waker: Box<dyn EventLoopWaker>,
 ) -> (EmbedderProxy, EmbedderReceiver) {
@@ -863,6 +1077,8 @@ fn create_constellation(
     config_dir: Option<PathBuf>,
     embedder_proxy: EmbedderProxy,
     compositor_proxy: CompositorProxy,
+    prompt_sender: Sender<PromptMsg>,
+    prompt_receiver: Receiver<PromptMsg>,
     time_profiler_chan: time::ProfilerChan,
     mem_profiler_chan: mem::ProfilerChan,
     debugger_chan: Option<debugger::Sender>,
@@ -878,6 +1094,8 @@ fn create_constellation(
     event_loop_waker: Option<Box<dyn EventLoopWaker>>,
     initial_window_size: WindowSizeData,
     pending_wr_frame: Arc<AtomicBool>,
+    protocols: Arc<ProtocolRegistry>,
+    event_loop_observer: Option<Arc<dyn EventLoopObserver>>,
 ) -> (Sender<ConstellationMsg>, SWManagerSenders) {
     // Global configuration options, parsed from the command line.
     let opts = opts::get();
@@ -891,8 +1109,10 @@ fn create_constellation(
         time_profiler_chan.clone(),
         mem_profiler_chan.clone(),
         embedder_proxy.clone(),
+        prompt_sender,
         config_dir,
         opts.certificate_path.clone(),
+        protocols,
     );
     let font_cache_thread = FontCacheThread::new(
         public_resource_threads.sender(),
@@ -904,6 +1124,7 @@ fn create_constellation(
     let initial_state = InitialConstellationState {
         compositor_proxy,
         embedder_proxy,
+        prompt_receiver,
         debugger_chan,
         devtools_chan,
         bluetooth_thread,
@@ -921,6 +1142,7 @@ fn create_constellation(
         player_context,
         event_loop_waker,
         pending_wr_frame,
+        event_loop_observer,
     };
 
     let (canvas_chan, ipc_canvas_chan) = canvas::canvas_paint_thread::CanvasPaintThread::start();
@@ -653,9 +653,10 @@ ${helpers.predefined_type(
     "will-change",
     "WillChange",
     "computed::WillChange::auto()",
-    engines="gecko",
+    engines="gecko servo-2013",
     animation_value_type="none",
     spec="https://drafts.csswg.org/css-will-change/#will-change",
+    servo_restyle_damage="reflow_out_of_flow",
 )}
 
 // The spec issue for the parse_method: https://github.com/w3c/csswg-drafts/issues/4102.
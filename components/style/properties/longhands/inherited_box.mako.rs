@@ -85,9 +85,11 @@ ${helpers.single_keyword(
 
 ${helpers.single_keyword(
     "image-orientation",
-    "none from-image",
-    engines="gecko",
+    "from-image none",
+    engines="gecko servo-2013 servo-2020",
+    servo_2020_pref="layout.2020.unimplemented",
     gecko_enum_prefix="StyleImageOrientation",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-images/#propdef-image-orientation",
+    servo_restyle_damage="rebuild_and_reflow",
 )}
@@ -99,14 +99,12 @@ ${helpers.predefined_type(
     servo_restyle_damage="rebuild_and_reflow",
 )}
 
-// TODO(pcwalton): Support `text-justify: distribute`.
 <%helpers:single_keyword
     name="text-justify"
-    values="auto none inter-word"
+    values="auto none inter-word inter-character"
     engines="gecko servo-2013 servo-2020",
     servo_2020_pref="layout.2020.unimplemented",
-    extra_gecko_values="inter-character"
-    extra_specified="${'distribute' if engine == 'gecko' else ''}"
+    extra_specified="distribute"
     gecko_enum_prefix="StyleTextJustify"
     animation_value_type="discrete"
     gecko_pref="layout.css.text-justify.enabled"
@@ -114,7 +112,6 @@ ${helpers.predefined_type(
     spec="https://drafts.csswg.org/css-text/#propdef-text-justify"
     servo_restyle_damage="rebuild_and_reflow"
 >
-    % if engine == 'gecko':
     impl ToComputedValue for SpecifiedValue {
         type ComputedValue = computed_value::T;
 
@@ -138,16 +135,16 @@ ${helpers.predefined_type(
             }
         }
     }
-    % endif
 </%helpers:single_keyword>
 
 ${helpers.single_keyword(
     "text-align-last",
     "auto start end left right center justify",
-    engines="gecko",
+    engines="gecko servo-2013",
     gecko_constant_prefix="NS_STYLE_TEXT_ALIGN",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-text/#propdef-text-align-last",
+    servo_restyle_damage="reflow",
 )}
 
 // TODO make this a shorthand and implement text-align-last/text-align-all
@@ -183,10 +180,10 @@ ${helpers.predefined_type(
 
 <%helpers:single_keyword
     name="white-space"
-    values="normal pre nowrap pre-wrap pre-line"
+    values="normal pre nowrap pre-wrap pre-line break-spaces"
     engines="gecko servo-2013 servo-2020",
     servo_2020_pref="layout.2020.unimplemented",
-    extra_gecko_values="break-spaces -moz-pre-space"
+    extra_gecko_values="-moz-pre-space"
     gecko_enum_prefix="StyleWhiteSpace"
     needs_conversion="True"
     animation_value_type="discrete"
@@ -201,7 +198,8 @@ ${helpers.predefined_type(
                 SpecifiedValue::Pre => false,
                 SpecifiedValue::Normal |
                 SpecifiedValue::PreWrap |
-                SpecifiedValue::PreLine => true,
+                SpecifiedValue::PreLine |
+                SpecifiedValue::BreakSpaces => true,
             }
         }
 
@@ -211,7 +209,8 @@ ${helpers.predefined_type(
                 SpecifiedValue::Nowrap => false,
                 SpecifiedValue::Pre |
                 SpecifiedValue::PreWrap |
-                SpecifiedValue::PreLine => true,
+                SpecifiedValue::PreLine |
+                SpecifiedValue::BreakSpaces => true,
             }
         }
 
@@ -221,9 +220,16 @@ ${helpers.predefined_type(
                 SpecifiedValue::Nowrap |
                 SpecifiedValue::PreLine => false,
                 SpecifiedValue::Pre |
-                SpecifiedValue::PreWrap => true,
+                SpecifiedValue::PreWrap |
+                SpecifiedValue::BreakSpaces => true,
             }
         }
+
+        /// Whether there is a soft wrap opportunity after every preserved space, and
+        /// preserved spaces at the end of a line take up space instead of hanging.
+        pub fn breaks_spaces(&self) -> bool {
+            *self == SpecifiedValue::BreakSpaces
+        }
     }
     % endif
 </%helpers:single_keyword>
@@ -273,12 +279,14 @@ ${helpers.predefined_type(
 )}
 
 ${helpers.predefined_type(
-    "-moz-tab-size",
+    "tab-size",
     "NonNegativeLengthOrNumber",
     "generics::length::LengthOrNumber::Number(From::from(8.0))",
-    engines="gecko",
+    engines="gecko servo-2013",
+    alias="-moz-tab-size",
     animation_value_type="LengthOrNumber",
     spec="https://drafts.csswg.org/css-text-3/#tab-size-property",
+    servo_restyle_damage="rebuild_and_reflow",
 )}
 
 ${helpers.predefined_type(
@@ -62,7 +62,7 @@ ${helpers.predefined_type(
     "initial-letter",
     "InitialLetter",
     "computed::InitialLetter::normal()",
-    engines="gecko",
+    engines="gecko servo-2013",
     initial_specified_value="specified::InitialLetter::normal()",
     animation_value_type="discrete",
     gecko_pref="layout.css.initial-letter.enabled",
@@ -3245,11 +3245,17 @@ impl ComputedValuesInner {
 
     /// <https://drafts.csswg.org/css-transforms/#grouping-property-values>
     pub fn get_used_transform_style(&self) -> computed_values::transform_style::T {
+        use crate::computed_values::overflow_x::T as Overflow;
         use crate::computed_values::transform_style::T as TransformStyle;
 
         let box_ = self.get_box();
 
-        if self.overrides_transform_style() {
+        // Clipping the overflow flattens the contents into the plane of the element too, but
+        // unlike the effects in `overrides_transform_style`, it doesn't establish a stacking
+        // context.
+        if self.overrides_transform_style() ||
+           box_.overflow_x != Overflow::Visible ||
+           box_.overflow_y != Overflow::Visible {
             TransformStyle::Flat
         } else {
             // Return the computed value if not overridden by the above exceptions
@@ -10,13 +10,14 @@ use crate::media_queries::media_feature::{Evaluator, MediaFeatureDescription};
 use crate::media_queries::media_feature_expression::RangeOrOperator;
 use crate::media_queries::MediaType;
 use crate::properties::ComputedValues;
-use crate::values::computed::CSSPixelLength;
+use crate::values::computed::{CSSPixelLength, Resolution};
 use crate::values::specified::font::FONT_MEDIUM_PX;
 use crate::values::KeyframesName;
 use app_units::Au;
 use cssparser::RGBA;
 use euclid::default::Size2D as UntypedSize2D;
 use euclid::{Scale, SideOffsets2D, Size2D};
+use servo_config::pref;
 use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
 use style_traits::viewport::ViewportConstraints;
 use style_traits::{CSSPixel, DevicePixel};
@@ -189,6 +190,51 @@ fn eval_width(
     )
 }
 
+/// https://drafts.csswg.org/mediaqueries-4/#height
+fn eval_height(
+    device: &Device,
+    value: Option<CSSPixelLength>,
+    range_or_operator: Option<RangeOrOperator>,
+) -> bool {
+    RangeOrOperator::evaluate(
+        range_or_operator,
+        value.map(Au::from),
+        device.au_viewport_size().height,
+    )
+}
+
+#[derive(Clone, Copy, Debug, FromPrimitive, Parse, ToCss)]
+#[repr(u8)]
+enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+/// https://drafts.csswg.org/mediaqueries-4/#orientation
+fn eval_orientation(device: &Device, value: Option<Orientation>) -> bool {
+    let size = device.au_viewport_size();
+    // Per spec, square viewports should be 'portrait'
+    let is_landscape = size.width > size.height;
+    match value {
+        Some(Orientation::Landscape) => is_landscape,
+        Some(Orientation::Portrait) => !is_landscape,
+        None => true,
+    }
+}
+
+/// https://drafts.csswg.org/mediaqueries-4/#resolution
+fn eval_resolution(
+    device: &Device,
+    value: Option<Resolution>,
+    range_or_operator: Option<RangeOrOperator>,
+) -> bool {
+    RangeOrOperator::evaluate(
+        range_or_operator,
+        value.map(|r| r.dppx()),
+        device.device_pixel_ratio().get(),
+    )
+}
+
 #[derive(Clone, Copy, Debug, FromPrimitive, Parse, ToCss)]
 #[repr(u8)]
 enum Scan {
@@ -203,20 +249,116 @@ fn eval_scan(_: &Device, _: Option<Scan>) -> bool {
     false
 }
 
+#[derive(Clone, Copy, Debug, FromPrimitive, Parse, PartialEq, PartialOrd, ToCss)]
+#[repr(u8)]
+enum ColorGamut {
+    Srgb,
+    P3,
+    Rec2020,
+}
+
+/// https://drafts.csswg.org/mediaqueries-4/#color-gamut
+fn eval_color_gamut(_: &Device, query_value: Option<ColorGamut>) -> bool {
+    let display_color_gamut = match &*pref!(layout.display_color_gamut) {
+        "p3" => ColorGamut::P3,
+        "rec2020" => ColorGamut::Rec2020,
+        _ => ColorGamut::Srgb,
+    };
+    // A display whose gamut is wider also covers the smaller gamuts.
+    match query_value {
+        Some(v) => v <= display_color_gamut,
+        None => true,
+    }
+}
+
+#[derive(Clone, Copy, Debug, FromPrimitive, Parse, PartialEq, ToCss)]
+#[repr(u8)]
+enum PrefersColorScheme {
+    Light,
+    Dark,
+    NoPreference,
+}
+
+/// https://drafts.csswg.org/mediaqueries-5/#prefers-color-scheme
+fn eval_prefers_color_scheme(_: &Device, query_value: Option<PrefersColorScheme>) -> bool {
+    let prefers_color_scheme = match &*pref!(layout.prefers_color_scheme) {
+        "light" => PrefersColorScheme::Light,
+        "dark" => PrefersColorScheme::Dark,
+        _ => PrefersColorScheme::NoPreference,
+    };
+    match query_value {
+        Some(v) => prefers_color_scheme == v,
+        None => prefers_color_scheme != PrefersColorScheme::NoPreference,
+    }
+}
+
+#[derive(Clone, Copy, Debug, FromPrimitive, Parse, ToCss)]
+#[repr(u8)]
+enum PrefersReducedMotion {
+    NoPreference,
+    Reduce,
+}
+
+/// https://drafts.csswg.org/mediaqueries-5/#prefers-reduced-motion
+fn eval_prefers_reduced_motion(_: &Device, query_value: Option<PrefersReducedMotion>) -> bool {
+    let prefers_reduced = pref!(layout.prefers_reduced_motion);
+    match query_value {
+        Some(PrefersReducedMotion::NoPreference) => !prefers_reduced,
+        Some(PrefersReducedMotion::Reduce) => prefers_reduced,
+        None => prefers_reduced,
+    }
+}
+
 lazy_static! {
     /// A list with all the media features that Servo supports.
-    pub static ref MEDIA_FEATURES: [MediaFeatureDescription; 2] = [
+    pub static ref MEDIA_FEATURES: [MediaFeatureDescription; 8] = [
         feature!(
             atom!("width"),
             AllowsRanges::Yes,
             Evaluator::Length(eval_width),
             ParsingRequirements::empty(),
         ),
+        feature!(
+            atom!("height"),
+            AllowsRanges::Yes,
+            Evaluator::Length(eval_height),
+            ParsingRequirements::empty(),
+        ),
+        feature!(
+            atom!("orientation"),
+            AllowsRanges::No,
+            keyword_evaluator!(eval_orientation, Orientation),
+            ParsingRequirements::empty(),
+        ),
+        feature!(
+            atom!("resolution"),
+            AllowsRanges::Yes,
+            Evaluator::Resolution(eval_resolution),
+            ParsingRequirements::empty(),
+        ),
         feature!(
             atom!("scan"),
             AllowsRanges::No,
             keyword_evaluator!(eval_scan, Scan),
             ParsingRequirements::empty(),
         ),
+        feature!(
+            atom!("color-gamut"),
+            AllowsRanges::No,
+            keyword_evaluator!(eval_color_gamut, ColorGamut),
+            ParsingRequirements::empty(),
+        ),
+        feature!(
+            atom!("prefers-color-scheme"),
+            AllowsRanges::No,
+            keyword_evaluator!(eval_prefers_color_scheme, PrefersColorScheme),
+            ParsingRequirements::empty(),
+        ),
+        feature!(
+            atom!("prefers-reduced-motion"),
+            AllowsRanges::No,
+            keyword_evaluator!(eval_prefers_reduced_motion, PrefersReducedMotion),
+            ParsingRequirements::empty(),
+        ),
     ];
 }
@@ -839,11 +839,20 @@ impl MaybeNew for ViewportConstraints {
             },
         });
 
+        // DEVICE-ADAPT § 10: an 'auto' zoom fits the actual viewport width
+        // into the initial viewport, constrained to [min-zoom, max-zoom].
+        let initial_zoom = initial_zoom.unwrap_or_else(|| {
+            let fit_width = match width {
+                Au(0) => 1.,
+                width => initial_viewport.width.to_f32_px() / width.to_f32_px(),
+            };
+            max!(min_zoom, min!(max_zoom, Some(fit_width))).unwrap()
+        });
+
         Some(ViewportConstraints {
             size: Size2D::new(width.to_f32_px(), height.to_f32_px()),
 
-            // TODO: compute a zoom factor for 'auto' as suggested by DEVICE-ADAPT § 10.
-            initial_zoom: PinchZoomFactor::new(initial_zoom.unwrap_or(1.)),
+            initial_zoom: PinchZoomFactor::new(initial_zoom),
             min_zoom: min_zoom.map(PinchZoomFactor::new),
             max_zoom: max_zoom.map(PinchZoomFactor::new),
 
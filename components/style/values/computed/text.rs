@@ -6,7 +6,7 @@
 
 #[cfg(feature = "servo")]
 use crate::properties::StyleBuilder;
-use crate::values::computed::length::{Length, LengthPercentage};
+use crate::values::computed::length::LengthPercentage;
 use crate::values::computed::{Context, NonNegativeLength, NonNegativeNumber, ToComputedValue};
 use crate::values::generics::text::InitialLetter as GenericInitialLetter;
 use crate::values::generics::text::LineHeight as GenericLineHeight;
@@ -36,7 +36,6 @@ pub type TextDecorationLength = GenericTextDecorationLength<LengthPercentage>;
     Animate,
     Clone,
     ComputeSquaredDistance,
-    Copy,
     Debug,
     MallocSizeOf,
     PartialEq,
@@ -44,13 +43,13 @@ pub type TextDecorationLength = GenericTextDecorationLength<LengthPercentage>;
     ToAnimatedZero,
     ToResolvedValue,
 )]
-pub struct LetterSpacing(pub Length);
+pub struct LetterSpacing(pub LengthPercentage);
 
 impl LetterSpacing {
     /// Return the `normal` computed value, which is just zero.
     #[inline]
     pub fn normal() -> Self {
-        LetterSpacing(Length::zero())
+        LetterSpacing(LengthPercentage::zero())
     }
 }
 
@@ -74,7 +73,7 @@ impl ToComputedValue for specified::LetterSpacing {
     type ComputedValue = LetterSpacing;
     fn to_computed_value(&self, context: &Context) -> Self::ComputedValue {
         match *self {
-            Spacing::Normal => LetterSpacing(Length::zero()),
+            Spacing::Normal => LetterSpacing(LengthPercentage::zero()),
             Spacing::Value(ref v) => LetterSpacing(v.to_computed_value(context)),
         }
     }
@@ -1073,6 +1073,12 @@ impl WillChange {
     pub fn auto() -> Self {
         Self::default()
     }
+
+    #[inline]
+    /// Get the kinds of change that the value will create.
+    pub fn bits(&self) -> WillChangeBits {
+        self.bits
+    }
 }
 
 bitflags! {
@@ -14,7 +14,7 @@ use crate::values::generics::text::InitialLetter as GenericInitialLetter;
 use crate::values::generics::text::LineHeight as GenericLineHeight;
 use crate::values::generics::text::{GenericTextDecorationLength, Spacing};
 use crate::values::specified::length::NonNegativeLengthPercentage;
-use crate::values::specified::length::{FontRelativeLength, Length};
+use crate::values::specified::length::FontRelativeLength;
 use crate::values::specified::length::{LengthPercentage, NoCalcLength};
 use crate::values::specified::{AllowQuirks, Integer, NonNegativeNumber, Number};
 use cssparser::{Parser, Token};
@@ -28,7 +28,7 @@ use unicode_segmentation::UnicodeSegmentation;
 pub type InitialLetter = GenericInitialLetter<Number, Integer>;
 
 /// A specified value for the `letter-spacing` property.
-pub type LetterSpacing = Spacing<Length>;
+pub type LetterSpacing = Spacing<LengthPercentage>;
 
 /// A specified value for the `word-spacing` property.
 pub type WordSpacing = Spacing<LengthPercentage>;
@@ -56,7 +56,7 @@ impl Parse for LetterSpacing {
         input: &mut Parser<'i, 't>,
     ) -> Result<Self, ParseError<'i>> {
         Spacing::parse_with(context, input, |c, i| {
-            Length::parse_quirky(c, i, AllowQuirks::Yes)
+            LengthPercentage::parse_quirky(c, i, AllowQuirks::Yes)
         })
     }
 }
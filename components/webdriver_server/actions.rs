@@ -8,6 +8,7 @@ use ipc_channel::ipc;
 use keyboard_types::webdriver::KeyInputState;
 use script_traits::webdriver_msg::WebDriverScriptCommand;
 use script_traits::{MouseButton, MouseEventType, WebDriverCommandMsg};
+use serde_json::Value;
 use std::cmp;
 use std::collections::HashSet;
 use std::thread;
@@ -18,7 +19,7 @@ use webdriver::actions::{
     PointerAction, PointerActionItem, PointerActionParameters, PointerDownAction,
 };
 use webdriver::actions::{PointerMoveAction, PointerOrigin, PointerType, PointerUpAction};
-use webdriver::error::ErrorStatus;
+use webdriver::error::{ErrorStatus, WebDriverError, WebDriverResult};
 
 // Interval between pointerMove increments in ms, based on common vsync
 static POINTERMOVE_INTERVAL: u64 = 17;
@@ -28,6 +29,7 @@ pub(crate) enum InputSourceState {
     Null,
     Key(KeyInputState),
     Pointer(PointerInputState),
+    Wheel,
 }
 
 // https://w3c.github.io/webdriver/#dfn-pointer-input-source
@@ -53,30 +55,119 @@ impl PointerInputState {
     }
 }
 
-// https://w3c.github.io/webdriver/#dfn-computing-the-tick-duration
-fn compute_tick_duration(tick_actions: &ActionSequence) -> u64 {
-    let mut duration = 0;
-    match &tick_actions.actions {
-        ActionsType::Null { actions } => {
-            for action in actions.iter() {
-                let NullActionItem::General(GeneralAction::Pause(pause_action)) = action;
-                duration = cmp::max(duration, pause_action.duration.unwrap_or(0));
+/// The actions of a wheel input source, which the webdriver crate doesn't parse yet, so
+/// that they are only accepted by the `servo/actions` extension command.
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) struct WheelActionSequence {
+    id: String,
+    actions: Vec<WheelActionItem>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum WheelActionItem {
+    Pause { duration: Option<u64> },
+    Scroll(WheelScrollAction),
+}
+
+// https://w3c.github.io/webdriver/#dfn-process-a-wheel-action
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct WheelScrollAction {
+    #[serde(default = "viewport_origin")]
+    origin: PointerOrigin,
+    x: i64,
+    y: i64,
+    delta_x: i64,
+    delta_y: i64,
+    duration: Option<u64>,
+}
+
+fn viewport_origin() -> PointerOrigin {
+    PointerOrigin::Viewport
+}
+
+/// The action sequences of the `servo/actions` extension command, which are those of
+/// the Perform Actions command with the wheel input sources split out.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ServoActionsParameters {
+    pub actions: Vec<ActionSequence>,
+    pub wheel_actions: Vec<WheelActionSequence>,
+}
+
+impl ServoActionsParameters {
+    pub fn from_json(body: &Value) -> WebDriverResult<ServoActionsParameters> {
+        let sequences = body
+            .get("actions")
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                WebDriverError::new(ErrorStatus::InvalidArgument, "Missing 'actions' list")
+            })?;
+        let mut parameters = ServoActionsParameters {
+            actions: vec![],
+            wheel_actions: vec![],
+        };
+        for sequence in sequences {
+            if sequence.get("type").and_then(Value::as_str) == Some("wheel") {
+                let sequence = serde_json::from_value(sequence.clone())?;
+                parameters.wheel_actions.push(sequence);
+            } else {
+                parameters
+                    .actions
+                    .push(serde_json::from_value(sequence.clone())?);
             }
-        },
+        }
+        Ok(parameters)
+    }
+}
+
+/// The number of ticks of an action sequence, which is its number of actions.
+fn sequence_length(sequence: &ActionSequence) -> usize {
+    match &sequence.actions {
+        ActionsType::Null { actions } => actions.len(),
+        ActionsType::Key { actions } => actions.len(),
         ActionsType::Pointer {
             parameters: _,
             actions,
-        } => {
-            for action in actions.iter() {
-                let action_duration = match action {
-                    PointerActionItem::General(GeneralAction::Pause(action)) => action.duration,
-                    PointerActionItem::Pointer(PointerAction::Move(action)) => action.duration,
-                    _ => None,
-                };
-                duration = cmp::max(duration, action_duration.unwrap_or(0));
-            }
-        },
-        ActionsType::Key { actions: _ } => (),
+        } => actions.len(),
+    }
+}
+
+// https://w3c.github.io/webdriver/#dfn-computing-the-tick-duration
+fn compute_tick_duration(
+    actions_by_source: &[ActionSequence],
+    wheel_actions_by_source: &[WheelActionSequence],
+    tick: usize,
+) -> u64 {
+    let mut duration = 0;
+    for sequence in wheel_actions_by_source.iter() {
+        let action_duration = match sequence.actions.get(tick) {
+            Some(WheelActionItem::Pause { duration }) => *duration,
+            Some(WheelActionItem::Scroll(action)) => action.duration,
+            None => None,
+        };
+        duration = cmp::max(duration, action_duration.unwrap_or(0));
+    }
+    for sequence in actions_by_source.iter() {
+        let action_duration = match &sequence.actions {
+            ActionsType::Null { actions } => actions.get(tick).and_then(|action| {
+                let NullActionItem::General(GeneralAction::Pause(pause_action)) = action;
+                pause_action.duration
+            }),
+            ActionsType::Key { actions } => match actions.get(tick) {
+                Some(KeyActionItem::General(GeneralAction::Pause(action))) => action.duration,
+                _ => None,
+            },
+            ActionsType::Pointer {
+                parameters: _,
+                actions,
+            } => match actions.get(tick) {
+                Some(PointerActionItem::General(GeneralAction::Pause(action))) => action.duration,
+                Some(PointerActionItem::Pointer(PointerAction::Move(action))) => action.duration,
+                _ => None,
+            },
+        };
+        duration = cmp::max(duration, action_duration.unwrap_or(0));
     }
     duration
 }
@@ -97,11 +188,34 @@ impl Handler {
     // https://w3c.github.io/webdriver/#dfn-dispatch-actions
     pub(crate) fn dispatch_actions(
         &mut self,
-        actions_by_tick: &[ActionSequence],
+        actions_by_source: &[ActionSequence],
+        wheel_actions_by_source: &[WheelActionSequence],
     ) -> Result<(), ErrorStatus> {
-        for tick_actions in actions_by_tick.iter() {
-            let tick_duration = compute_tick_duration(&tick_actions);
-            self.dispatch_tick_actions(&tick_actions, tick_duration)?;
+        // The actions of the n-th tick are the n-th actions of every input source.
+        let ticks = actions_by_source
+            .iter()
+            .map(sequence_length)
+            .chain(
+                wheel_actions_by_source
+                    .iter()
+                    .map(|sequence| sequence.actions.len()),
+            )
+            .max()
+            .unwrap_or(0);
+        for tick in 0..ticks {
+            let tick_start = Instant::now();
+            let tick_duration =
+                compute_tick_duration(actions_by_source, wheel_actions_by_source, tick);
+            self.dispatch_tick_actions(actions_by_source, tick, tick_duration)?;
+            self.dispatch_tick_wheel_actions(wheel_actions_by_source, tick, tick_duration)?;
+
+            // Wait for the tick duration to have elapsed, which is how the pauses
+            // are performed.
+            let tick_duration = Duration::from_millis(tick_duration);
+            let elapsed = tick_start.elapsed();
+            if elapsed < tick_duration {
+                thread::sleep(tick_duration - elapsed);
+            }
         }
         Ok(())
     }
@@ -119,80 +233,108 @@ impl Handler {
     // https://w3c.github.io/webdriver/#dfn-dispatch-tick-actions
     fn dispatch_tick_actions(
         &mut self,
-        tick_actions: &ActionSequence,
+        actions_by_source: &[ActionSequence],
+        tick: usize,
         tick_duration: u64,
     ) -> Result<(), ErrorStatus> {
-        let source_id = &tick_actions.id;
-        match &tick_actions.actions {
-            ActionsType::Null { actions } => {
-                for _action in actions.iter() {
-                    self.dispatch_general_action(source_id);
-                }
-            },
-            ActionsType::Key { actions } => {
-                for action in actions.iter() {
-                    match action {
-                        KeyActionItem::General(_action) => {
-                            self.dispatch_general_action(source_id);
-                        },
-                        KeyActionItem::Key(action) => {
-                            self.session_mut()
-                                .unwrap()
-                                .input_state_table
-                                .entry(source_id.to_string())
-                                .or_insert(InputSourceState::Key(KeyInputState::new()));
-                            match action {
-                                KeyAction::Down(action) => {
-                                    self.dispatch_keydown_action(&source_id, &action)
-                                },
-                                KeyAction::Up(action) => {
-                                    self.dispatch_keyup_action(&source_id, &action)
-                                },
-                            };
-                        },
-                    }
-                }
-            },
-            ActionsType::Pointer {
-                parameters,
-                actions,
-            } => {
-                for action in actions.iter() {
-                    match action {
-                        PointerActionItem::General(_action) => {
-                            self.dispatch_general_action(source_id);
-                        },
-                        PointerActionItem::Pointer(action) => {
-                            self.session_mut()
-                                .unwrap()
-                                .input_state_table
-                                .entry(source_id.to_string())
-                                .or_insert(InputSourceState::Pointer(PointerInputState::new(
-                                    &parameters.pointer_type,
-                                )));
-                            match action {
-                                PointerAction::Cancel => (),
-                                PointerAction::Down(action) => {
-                                    self.dispatch_pointerdown_action(&source_id, &action)
-                                },
-                                PointerAction::Move(action) => self.dispatch_pointermove_action(
-                                    &source_id,
-                                    &action,
-                                    tick_duration,
-                                )?,
-                                PointerAction::Up(action) => {
-                                    self.dispatch_pointerup_action(&source_id, &action)
-                                },
-                            }
-                        },
+        for sequence in actions_by_source.iter() {
+            let source_id = &sequence.id;
+            match &sequence.actions {
+                ActionsType::Null { actions } => {
+                    if actions.get(tick).is_some() {
+                        self.dispatch_general_action(source_id);
                     }
-                }
-            },
+                },
+                ActionsType::Key { actions } => match actions.get(tick) {
+                    Some(KeyActionItem::General(_action)) => {
+                        self.dispatch_general_action(source_id);
+                    },
+                    Some(KeyActionItem::Key(action)) => {
+                        self.session_mut()
+                            .unwrap()
+                            .input_state_table
+                            .entry(source_id.to_string())
+                            .or_insert(InputSourceState::Key(KeyInputState::new()));
+                        match action {
+                            KeyAction::Down(action) => {
+                                self.dispatch_keydown_action(&source_id, &action)
+                            },
+                            KeyAction::Up(action) => {
+                                self.dispatch_keyup_action(&source_id, &action)
+                            },
+                        };
+                    },
+                    None => (),
+                },
+                ActionsType::Pointer {
+                    parameters,
+                    actions,
+                } => match actions.get(tick) {
+                    Some(PointerActionItem::General(_action)) => {
+                        self.dispatch_general_action(source_id);
+                    },
+                    Some(PointerActionItem::Pointer(action)) => {
+                        self.session_mut()
+                            .unwrap()
+                            .input_state_table
+                            .entry(source_id.to_string())
+                            .or_insert(InputSourceState::Pointer(PointerInputState::new(
+                                &parameters.pointer_type,
+                            )));
+                        match action {
+                            PointerAction::Cancel => (),
+                            PointerAction::Down(action) => {
+                                self.dispatch_pointerdown_action(&source_id, &action)
+                            },
+                            PointerAction::Move(action) => self.dispatch_pointermove_action(
+                                &source_id,
+                                &action,
+                                tick_duration,
+                            )?,
+                            PointerAction::Up(action) => {
+                                self.dispatch_pointerup_action(&source_id, &action)
+                            },
+                        }
+                    },
+                    None => (),
+                },
+            }
         }
 
         Ok(())
     }
 
+    /// Dispatch the actions of the wheel input sources for the given tick, after those
+    /// of the other input sources.
+    fn dispatch_tick_wheel_actions(
+        &mut self,
+        wheel_actions_by_source: &[WheelActionSequence],
+        tick: usize,
+        tick_duration: u64,
+    ) -> Result<(), ErrorStatus> {
+        for sequence in wheel_actions_by_source.iter() {
+            match sequence.actions.get(tick) {
+                Some(WheelActionItem::Pause { .. }) => {
+                    self.session_mut()
+                        .unwrap()
+                        .input_state_table
+                        .entry(sequence.id.clone())
+                        .or_insert(InputSourceState::Wheel);
+                },
+                Some(WheelActionItem::Scroll(action)) => {
+                    self.session_mut()
+                        .unwrap()
+                        .input_state_table
+                        .entry(sequence.id.clone())
+                        .or_insert(InputSourceState::Wheel);
+                    self.dispatch_scroll_action(action, tick_duration)?;
+                },
+                None => (),
+            }
+        }
+        Ok(())
+    }
+
     // https://w3c.github.io/webdriver/#dfn-dispatch-a-keydown-action
     fn dispatch_keydown_action(&mut self, source_id: &str, action: &KeyDownAction) {
         let session = self.session.as_mut().unwrap();
@@ -202,6 +344,7 @@ impl Handler {
             InputSourceState::Null => unreachable!(),
             InputSourceState::Key(key_input_state) => key_input_state,
             InputSourceState::Pointer(_) => unreachable!(),
+            InputSourceState::Wheel => unreachable!(),
         };
 
         session.input_cancel_list.push(ActionSequence {
@@ -230,6 +373,7 @@ impl Handler {
             InputSourceState::Null => unreachable!(),
             InputSourceState::Key(key_input_state) => key_input_state,
             InputSourceState::Pointer(_) => unreachable!(),
+            InputSourceState::Wheel => unreachable!(),
         };
 
         session.input_cancel_list.push(ActionSequence {
@@ -262,6 +406,7 @@ impl Handler {
             InputSourceState::Null => unreachable!(),
             InputSourceState::Key(_) => unreachable!(),
             InputSourceState::Pointer(pointer_input_state) => pointer_input_state,
+            InputSourceState::Wheel => unreachable!(),
         };
 
         if pointer_input_state.pressed.contains(&action.button) {
@@ -308,6 +453,7 @@ impl Handler {
             InputSourceState::Null => unreachable!(),
             InputSourceState::Key(_) => unreachable!(),
             InputSourceState::Pointer(pointer_input_state) => pointer_input_state,
+            InputSourceState::Wheel => unreachable!(),
         };
 
         if !pointer_input_state.pressed.contains(&action.button) {
@@ -373,6 +519,7 @@ impl Handler {
             InputSourceState::Pointer(pointer_input_state) => {
                 (pointer_input_state.x, pointer_input_state.y)
             },
+            InputSourceState::Wheel => unreachable!(),
         };
 
         // Step 5 - 6
@@ -452,6 +599,7 @@ impl Handler {
             InputSourceState::Null => unreachable!(),
             InputSourceState::Key(_) => unreachable!(),
             InputSourceState::Pointer(pointer_input_state) => pointer_input_state,
+            InputSourceState::Wheel => unreachable!(),
         };
 
         loop {
@@ -507,4 +655,112 @@ impl Handler {
             thread::sleep(Duration::from_millis(POINTERMOVE_INTERVAL));
         }
     }
+
+    // https://w3c.github.io/webdriver/#dfn-dispatch-a-scroll-action
+    fn dispatch_scroll_action(
+        &mut self,
+        action: &WheelScrollAction,
+        tick_duration: u64,
+    ) -> Result<(), ErrorStatus> {
+        let tick_start = Instant::now();
+
+        // Steps 1 - 6
+        let (x, y) = match action.origin {
+            PointerOrigin::Viewport => (action.x, action.y),
+            PointerOrigin::Pointer => return Err(ErrorStatus::InvalidArgument),
+            PointerOrigin::Element(ref element) => {
+                let (sender, receiver) = ipc::channel().unwrap();
+                self.top_level_script_command(WebDriverScriptCommand::GetElementInViewCenterPoint(
+                    element.to_string(),
+                    sender,
+                ))
+                .unwrap();
+
+                match receiver.recv().unwrap() {
+                    Ok(Some((center_x, center_y))) => (center_x + action.x, center_y + action.y),
+                    _ => return Err(ErrorStatus::UnknownError),
+                }
+            },
+        };
+
+        // Steps 7 - 8
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetWindowSize(
+            self.session.as_ref().unwrap().top_level_browsing_context_id,
+            sender,
+        );
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        let viewport = receiver.recv().unwrap().initial_viewport;
+        if x < 0 || x as f32 > viewport.width || y < 0 || y as f32 > viewport.height {
+            return Err(ErrorStatus::MoveTargetOutOfBounds);
+        }
+
+        // Step 9
+        let duration = action.duration.unwrap_or(tick_duration);
+
+        // Steps 10 - 11
+        self.perform_scroll(duration, x, y, action.delta_x, action.delta_y, tick_start);
+        Ok(())
+    }
+
+    // https://w3c.github.io/webdriver/#dfn-perform-a-scroll
+    fn perform_scroll(
+        &mut self,
+        duration: u64,
+        x: i64,
+        y: i64,
+        target_delta_x: i64,
+        target_delta_y: i64,
+        tick_start: Instant,
+    ) {
+        let (mut current_delta_x, mut current_delta_y) = (0, 0);
+        loop {
+            // Steps 1 - 3
+            let time_delta = tick_start.elapsed().as_millis();
+            let duration_ratio = if duration > 0 {
+                time_delta as f64 / duration as f64
+            } else {
+                1.0
+            };
+            let last = 1.0 - duration_ratio < 0.001;
+
+            // Step 4
+            let (delta_x, delta_y) = if last {
+                (
+                    target_delta_x - current_delta_x,
+                    target_delta_y - current_delta_y,
+                )
+            } else {
+                (
+                    (duration_ratio * target_delta_x as f64) as i64 - current_delta_x,
+                    (duration_ratio * target_delta_y as f64) as i64 - current_delta_y,
+                )
+            };
+
+            // Step 5
+            if delta_x != 0 || delta_y != 0 {
+                let cmd_msg = WebDriverCommandMsg::WheelScrollAction(
+                    x as f32,
+                    y as f32,
+                    delta_x as f64,
+                    delta_y as f64,
+                );
+                self.constellation_chan
+                    .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+                    .unwrap();
+                current_delta_x += delta_x;
+                current_delta_y += delta_y;
+            }
+
+            // Step 6
+            if last {
+                return;
+            }
+
+            // Step 7
+            thread::sleep(Duration::from_millis(POINTERMOVE_INTERVAL));
+        }
+    }
 }
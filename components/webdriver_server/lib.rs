@@ -18,11 +18,12 @@ extern crate serde_json;
 mod actions;
 mod capabilities;
 
-use crate::actions::{InputSourceState, PointerInputState};
+use crate::actions::{InputSourceState, PointerInputState, ServoActionsParameters};
 use base64;
 use capabilities::ServoCapabilities;
 use compositing::ConstellationMsg;
 use crossbeam_channel::{after, unbounded, Receiver, Sender};
+use embedder_traits::{PromptRequest, PromptResponse};
 use euclid::{Rect, Size2D};
 use hyper::Method;
 use image::{DynamicImage, ImageFormat, RgbImage};
@@ -35,7 +36,7 @@ use script_traits::webdriver_msg::{LoadStatus, WebDriverCookieError, WebDriverFr
 use script_traits::webdriver_msg::{
     WebDriverJSError, WebDriverJSResult, WebDriverJSValue, WebDriverScriptCommand,
 };
-use script_traits::{LoadData, LoadOrigin, WebDriverCommandMsg};
+use script_traits::{LoadData, LoadOrigin, ScreenshotArea, WebDriverCommandMsg};
 use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 use serde::ser::{Serialize, Serializer};
 use serde_json::{json, Value};
@@ -46,9 +47,9 @@ use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::mem;
 use std::net::{SocketAddr, SocketAddrV4};
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
-use style_traits::CSSPixel;
 use uuid::Uuid;
 use webdriver::actions::{
     ActionSequence, PointerDownAction, PointerMoveAction, PointerOrigin, PointerType,
@@ -90,6 +91,16 @@ fn extension_routes() -> Vec<(Method, &'static str, ServoExtensionRoute)> {
             "/session/{sessionId}/servo/prefs/reset",
             ServoExtensionRoute::ResetPrefs,
         ),
+        (
+            Method::GET,
+            "/session/{sessionId}/servo/screenshot/full",
+            ServoExtensionRoute::TakeFullPageScreenshot,
+        ),
+        (
+            Method::POST,
+            "/session/{sessionId}/servo/actions",
+            ServoExtensionRoute::PerformActions,
+        ),
     ];
 }
 
@@ -143,6 +154,9 @@ pub struct WebDriverSession {
     strict_file_interactability: bool,
     unhandled_prompt_behavior: String,
 
+    /// The text sent to the current prompt, which is its answer if it is accepted.
+    prompt_text: Option<String>,
+
     // https://w3c.github.io/webdriver/#dfn-active-input-sources
     active_input_sources: Vec<InputSourceState>,
     // https://w3c.github.io/webdriver/#dfn-input-state-table
@@ -169,6 +183,7 @@ impl WebDriverSession {
             secure_tls: true,
             strict_file_interactability: false,
             unhandled_prompt_behavior: "dismiss and notify".to_string(),
+            prompt_text: None,
 
             active_input_sources: Vec::new(),
             input_state_table: HashMap::new(),
@@ -196,6 +211,8 @@ enum ServoExtensionRoute {
     GetPrefs,
     SetPrefs,
     ResetPrefs,
+    TakeFullPageScreenshot,
+    PerformActions,
 }
 
 impl WebDriverExtensionRoute for ServoExtensionRoute {
@@ -219,6 +236,12 @@ impl WebDriverExtensionRoute for ServoExtensionRoute {
                 let parameters: GetPrefsParameters = serde_json::from_value(body_data.clone())?;
                 ServoExtensionCommand::ResetPrefs(parameters)
             },
+            ServoExtensionRoute::TakeFullPageScreenshot => {
+                ServoExtensionCommand::TakeFullPageScreenshot
+            },
+            ServoExtensionRoute::PerformActions => ServoExtensionCommand::PerformActions(Arc::new(
+                ServoActionsParameters::from_json(body_data)?,
+            )),
         };
         Ok(WebDriverCommand::Extension(command))
     }
@@ -229,6 +252,9 @@ enum ServoExtensionCommand {
     GetPrefs(GetPrefsParameters),
     SetPrefs(SetPrefsParameters),
     ResetPrefs(GetPrefsParameters),
+    TakeFullPageScreenshot,
+    /// Perform Actions, with wheel input sources.
+    PerformActions(Arc<ServoActionsParameters>),
 }
 
 impl WebDriverExtensionCommand for ServoExtensionCommand {
@@ -237,6 +263,8 @@ impl WebDriverExtensionCommand for ServoExtensionCommand {
             ServoExtensionCommand::GetPrefs(ref x) => serde_json::to_value(x).ok(),
             ServoExtensionCommand::SetPrefs(ref x) => serde_json::to_value(x).ok(),
             ServoExtensionCommand::ResetPrefs(ref x) => serde_json::to_value(x).ok(),
+            ServoExtensionCommand::TakeFullPageScreenshot => None,
+            ServoExtensionCommand::PerformActions(_) => None,
         }
     }
 }
@@ -547,7 +575,7 @@ impl Handler {
                     match processed.get("unhandledPromptBehavior") {
                         Some(unhandled_prompt_behavior) => {
                             session.unhandled_prompt_behavior =
-                                unhandled_prompt_behavior.to_string()
+                                unhandled_prompt_behavior.as_str().unwrap().to_owned()
                         },
                         None => {
                             processed.insert(
@@ -864,11 +892,10 @@ impl Handler {
                     WebDriverScriptCommand::FindElementTagName(parameters.value.clone(), sender);
                 self.browsing_context_script_command(cmd)?;
             },
-            _ => {
-                return Err(WebDriverError::new(
-                    ErrorStatus::UnsupportedOperation,
-                    "Unsupported locator strategy",
-                ));
+            LocatorStrategy::XPath => {
+                let cmd =
+                    WebDriverScriptCommand::FindElementXPath(parameters.value.clone(), sender);
+                self.browsing_context_script_command(cmd)?;
             },
         }
 
@@ -972,11 +999,10 @@ impl Handler {
                     WebDriverScriptCommand::FindElementsTagName(parameters.value.clone(), sender);
                 self.browsing_context_script_command(cmd)?;
             },
-            _ => {
-                return Err(WebDriverError::new(
-                    ErrorStatus::UnsupportedOperation,
-                    "Unsupported locator strategy",
-                ));
+            LocatorStrategy::XPath => {
+                let cmd =
+                    WebDriverScriptCommand::FindElementsXPath(parameters.value.clone(), sender);
+                self.browsing_context_script_command(cmd)?;
             },
         }
 
@@ -1028,11 +1054,13 @@ impl Handler {
                 );
                 self.browsing_context_script_command(cmd)?;
             },
-            _ => {
-                return Err(WebDriverError::new(
-                    ErrorStatus::UnsupportedOperation,
-                    "Unsupported locator strategy",
-                ));
+            LocatorStrategy::XPath => {
+                let cmd = WebDriverScriptCommand::FindElementElementXPath(
+                    parameters.value.clone(),
+                    element.to_string(),
+                    sender,
+                );
+                self.browsing_context_script_command(cmd)?;
             },
         }
 
@@ -1081,11 +1109,13 @@ impl Handler {
                 );
                 self.browsing_context_script_command(cmd)?;
             },
-            _ => {
-                return Err(WebDriverError::new(
-                    ErrorStatus::UnsupportedOperation,
-                    "Unsupported locator strategy",
-                ));
+            LocatorStrategy::XPath => {
+                let cmd = WebDriverScriptCommand::FindElementElementsXPath(
+                    parameters.value.clone(),
+                    element.to_string(),
+                    sender,
+                );
+                self.browsing_context_script_command(cmd)?;
             },
         }
 
@@ -1288,12 +1318,113 @@ impl Handler {
         }
     }
 
+    /// The prompt currently shown for the top-level browsing context of the session.
+    fn current_prompt(&self) -> WebDriverResult<PromptRequest> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg =
+            WebDriverCommandMsg::GetPrompt(self.session()?.top_level_browsing_context_id, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        receiver
+            .recv()
+            .unwrap()
+            .ok_or(WebDriverError::new(ErrorStatus::NoSuchAlert, ""))
+    }
+
+    fn respond_to_prompt(&self, response: PromptResponse) -> WebDriverResult<()> {
+        let cmd_msg = WebDriverCommandMsg::RespondToPrompt(
+            self.session()?.top_level_browsing_context_id,
+            response,
+        );
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        Ok(())
+    }
+
+    // https://w3c.github.io/webdriver/#dfn-handle-any-user-prompts
+    fn handle_any_user_prompts(&mut self) -> WebDriverResult<()> {
+        let prompt = match self.current_prompt() {
+            Ok(prompt) => prompt,
+            Err(_) => return Ok(()),
+        };
+        let behavior = self.session()?.unhandled_prompt_behavior.clone();
+        match &*behavior {
+            "accept" | "accept and notify" => {
+                self.handle_accept_alert()?;
+            },
+            "dismiss" | "dismiss and notify" => {
+                self.handle_dismiss_alert()?;
+            },
+            // The prompt is left open with "ignore".
+            _ => {},
+        }
+        match &*behavior {
+            "accept" | "dismiss" => Ok(()),
+            _ => {
+                let text = match prompt {
+                    PromptRequest::Alert(message) |
+                    PromptRequest::Confirm(message) |
+                    PromptRequest::Input(message, _) |
+                    PromptRequest::YesNo(message) => message,
+                    _ => String::new(),
+                };
+                Err(WebDriverError::new(ErrorStatus::UnexpectedAlertOpen, text))
+            },
+        }
+    }
+
     // https://w3c.github.io/webdriver/#dismiss-alert
     fn handle_dismiss_alert(&mut self) -> WebDriverResult<WebDriverResponse> {
-        // Since user prompts are not yet implement this will always succeed
+        self.current_prompt()?;
+        self.session_mut()?.prompt_text = None;
+        self.respond_to_prompt(PromptResponse::Dismiss)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/#accept-alert
+    fn handle_accept_alert(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let prompt = self.current_prompt()?;
+        let text = self.session_mut()?.prompt_text.take();
+        let response = match prompt {
+            PromptRequest::Input(_, default) => PromptResponse::Text(text.unwrap_or(default)),
+            _ => PromptResponse::Accept,
+        };
+        self.respond_to_prompt(response)?;
         Ok(WebDriverResponse::Void)
     }
 
+    // https://w3c.github.io/webdriver/#get-alert-text
+    fn handle_get_alert_text(&self) -> WebDriverResult<WebDriverResponse> {
+        let text = match self.current_prompt()? {
+            PromptRequest::Alert(message) |
+            PromptRequest::Confirm(message) |
+            PromptRequest::Input(message, _) |
+            PromptRequest::YesNo(message) => Value::String(message),
+            _ => Value::Null,
+        };
+        Ok(WebDriverResponse::Generic(ValueResponse(text)))
+    }
+
+    // https://w3c.github.io/webdriver/#send-alert-text
+    fn handle_send_alert_text(
+        &mut self,
+        parameters: &SendKeysParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        match self.current_prompt()? {
+            PromptRequest::Input(..) => {
+                self.session_mut()?.prompt_text = Some(parameters.text.clone());
+                Ok(WebDriverResponse::Void)
+            },
+            PromptRequest::Alert(..) | PromptRequest::Confirm(..) => Err(WebDriverError::new(
+                ErrorStatus::ElementNotInteractable,
+                "The prompt has no text field",
+            )),
+            _ => Err(WebDriverError::new(ErrorStatus::UnsupportedOperation, "")),
+        }
+    }
+
     fn handle_get_timeouts(&mut self) -> WebDriverResult<WebDriverResponse> {
         let session = self
             .session
@@ -1349,7 +1480,17 @@ impl Handler {
         &mut self,
         parameters: &ActionsParameters,
     ) -> WebDriverResult<WebDriverResponse> {
-        match self.dispatch_actions(&parameters.actions) {
+        match self.dispatch_actions(&parameters.actions, &[]) {
+            Ok(_) => Ok(WebDriverResponse::Void),
+            Err(error) => Err(WebDriverError::new(error, "")),
+        }
+    }
+
+    fn handle_perform_servo_actions(
+        &mut self,
+        parameters: &ServoActionsParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        match self.dispatch_actions(&parameters.actions, &parameters.wheel_actions) {
             Ok(_) => Ok(WebDriverResponse::Void),
             Err(error) => Err(WebDriverError::new(error, "")),
         }
@@ -1362,7 +1503,7 @@ impl Handler {
             mem::replace(&mut session.input_cancel_list, Vec::new())
         };
 
-        if let Err(error) = self.dispatch_actions(&input_cancel_list) {
+        if let Err(error) = self.dispatch_actions(&input_cancel_list, &[]) {
             return Err(WebDriverError::new(error, ""));
         }
 
@@ -1459,7 +1600,6 @@ impl Handler {
             .send(ConstellationMsg::WebDriverCommand(cmd_msg))
             .unwrap();
 
-        // TODO: distinguish the not found and not focusable cases
         receiver
             .recv()
             .unwrap()
@@ -1478,6 +1618,19 @@ impl Handler {
         Ok(WebDriverResponse::Void)
     }
 
+    // https://w3c.github.io/webdriver/#element-clear
+    fn handle_element_clear(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+
+        let command = WebDriverScriptCommand::ElementClear(element.to_string(), sender);
+        self.browsing_context_script_command(command)?;
+
+        match receiver.recv().unwrap() {
+            Ok(()) => Ok(WebDriverResponse::Void),
+            Err(error) => Err(WebDriverError::new(error, "")),
+        }
+    }
+
     // https://w3c.github.io/webdriver/#element-click
     fn handle_element_click(&mut self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
         let (sender, receiver) = ipc::channel().unwrap();
@@ -1537,7 +1690,7 @@ impl Handler {
         }
     }
 
-    fn take_screenshot(&self, rect: Option<Rect<f32, CSSPixel>>) -> WebDriverResult<String> {
+    fn take_screenshot(&self, area: ScreenshotArea) -> WebDriverResult<String> {
         let mut img = None;
 
         let interval = 1000;
@@ -1548,7 +1701,7 @@ impl Handler {
 
             let cmd_msg = WebDriverCommandMsg::TakeScreenshot(
                 self.session()?.top_level_browsing_context_id,
-                rect,
+                area,
                 sender,
             );
             self.constellation_chan
@@ -1590,7 +1743,15 @@ impl Handler {
     }
 
     fn handle_take_screenshot(&self) -> WebDriverResult<WebDriverResponse> {
-        let encoded = self.take_screenshot(None)?;
+        let encoded = self.take_screenshot(ScreenshotArea::Viewport)?;
+
+        Ok(WebDriverResponse::Generic(ValueResponse(
+            serde_json::to_value(encoded)?,
+        )))
+    }
+
+    fn handle_take_full_page_screenshot(&self) -> WebDriverResult<WebDriverResponse> {
+        let encoded = self.take_screenshot(ScreenshotArea::FullPage)?;
 
         Ok(WebDriverResponse::Generic(ValueResponse(
             serde_json::to_value(encoded)?,
@@ -1608,7 +1769,8 @@ impl Handler {
 
         match receiver.recv().unwrap() {
             Ok(rect) => {
-                let encoded = self.take_screenshot(Some(Rect::from_untyped(&rect)))?;
+                let encoded =
+                    self.take_screenshot(ScreenshotArea::Rect(Rect::from_untyped(&rect)))?;
 
                 Ok(WebDriverResponse::Generic(ValueResponse(
                     serde_json::to_value(encoded)?,
@@ -1700,6 +1862,23 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             },
         }
 
+        // The commands which don't interact with the document leave its prompt open.
+        match msg.command {
+            WebDriverCommand::NewSession(_) |
+            WebDriverCommand::DeleteSession |
+            WebDriverCommand::Status |
+            WebDriverCommand::GetTimeouts |
+            WebDriverCommand::SetTimeouts(_) |
+            WebDriverCommand::GetWindowHandle |
+            WebDriverCommand::GetWindowHandles |
+            WebDriverCommand::SwitchToWindow(_) |
+            WebDriverCommand::DismissAlert |
+            WebDriverCommand::AcceptAlert |
+            WebDriverCommand::GetAlertText |
+            WebDriverCommand::SendAlertText(_) => {},
+            _ => self.handle_any_user_prompts()?,
+        }
+
         match msg.command {
             WebDriverCommand::NewSession(ref parameters) => self.handle_new_session(parameters),
             WebDriverCommand::DeleteSession => self.handle_delete_session(),
@@ -1758,7 +1937,11 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
                 self.handle_element_send_keys(element, keys)
             },
             WebDriverCommand::ElementClick(ref element) => self.handle_element_click(element),
+            WebDriverCommand::ElementClear(ref element) => self.handle_element_clear(element),
             WebDriverCommand::DismissAlert => self.handle_dismiss_alert(),
+            WebDriverCommand::AcceptAlert => self.handle_accept_alert(),
+            WebDriverCommand::GetAlertText => self.handle_get_alert_text(),
+            WebDriverCommand::SendAlertText(ref x) => self.handle_send_alert_text(x),
             WebDriverCommand::DeleteCookies => self.handle_delete_cookies(),
             WebDriverCommand::GetTimeouts => self.handle_get_timeouts(),
             WebDriverCommand::SetTimeouts(ref x) => self.handle_set_timeouts(x),
@@ -1770,6 +1953,12 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
                 ServoExtensionCommand::GetPrefs(ref x) => self.handle_get_prefs(x),
                 ServoExtensionCommand::SetPrefs(ref x) => self.handle_set_prefs(x),
                 ServoExtensionCommand::ResetPrefs(ref x) => self.handle_reset_prefs(x),
+                ServoExtensionCommand::TakeFullPageScreenshot => {
+                    self.handle_take_full_page_screenshot()
+                },
+                ServoExtensionCommand::PerformActions(ref x) => {
+                    self.handle_perform_servo_actions(x)
+                },
             },
             _ => Err(WebDriverError::new(
                 ErrorStatus::UnsupportedOperation,
@@ -43,9 +43,16 @@ class StringTable:
 
 with open(sys.argv[1]) as f:
     profile = json.load(f)
-    rate = profile["rate"]
-    samples = profile["data"]
-    startTime = profile["start"]
+
+# The sampling profiler now outputs processed profiles itself, which are passed
+# through; only the raw samples of older builds are converted.
+if 'meta' in profile:
+    print(json.dumps(profile))
+    sys.exit(0)
+
+rate = profile["rate"]
+samples = profile["data"]
+startTime = profile["start"]
 
 frames = {}
 stacks = {}
@@ -8,8 +8,8 @@ use euclid::{Point2D, Vector2D};
 use keyboard_types::{Key, KeyboardEvent, Modifiers, ShortcutMatcher};
 use servo::compositing::windowing::{WebRenderDebugOption, WindowEvent};
 use servo::embedder_traits::{
-    EmbedderMsg, FilterPattern, PermissionRequest, PromptDefinition, PromptOrigin, PromptResult,
-    PermissionPrompt,
+    EmbedderMsg, FilterPattern, MediaDeviceInfo, MediaDeviceKind, PermissionPrompt, PromptOrigin,
+    PromptRequest, PromptResponse,
 };
 use servo::msg::constellation_msg::TopLevelBrowsingContextId as BrowserId;
 use servo::msg::constellation_msg::TraversalDirection;
@@ -278,8 +278,8 @@ where
     pub fn handle_servo_events(&mut self, events: Vec<(Option<BrowserId>, EmbedderMsg)>) {
         for (browser_id, msg) in events {
             match msg {
-                EmbedderMsg::Status(status) => {
-                    self.status = status;
+                EmbedderMsg::HoveredLinkChanged(url) => {
+                    self.status = url.map(|url| url.into_string());
                 },
                 EmbedderMsg::ChangePageTitle(title) => {
                     self.title = title;
@@ -302,89 +302,29 @@ where
                 EmbedderMsg::ResizeTo(size) => {
                     self.window.set_inner_size(size);
                 },
-                EmbedderMsg::Prompt(definition, origin) => {
-                    let res = if opts::get().headless {
-                        match definition {
-                            PromptDefinition::Alert(_message, sender) => {
-                                sender.send(())
-                            }
-                            PromptDefinition::YesNo(_message, sender) => {
-                                sender.send(PromptResult::Primary)
-                            }
-                            PromptDefinition::OkCancel(_message, sender) => {
-                                sender.send(PromptResult::Primary)
-                            }
-                            PromptDefinition::Input(_message, default, sender) => {
-                                sender.send(Some(default.to_owned()))
-                            }
+                EmbedderMsg::ShowPrompt(prompt_id, request) => {
+                    let response = if opts::get().headless {
+                        match request {
+                            PromptRequest::Input(_message, default) => PromptResponse::Text(default),
+                            PromptRequest::Authentication(..) | PromptRequest::Permission(..) => {
+                                PromptResponse::Dismiss
+                            },
+                            _ => PromptResponse::Accept,
                         }
                     } else {
                         thread::Builder::new()
                             .name("display alert dialog".to_owned())
-                            .spawn(move || {
-                                match definition {
-                                    PromptDefinition::Alert(mut message, sender) => {
-                                        if origin == PromptOrigin::Untrusted {
-                                            message = tiny_dialog_escape(&message);
-                                        }
-                                        tinyfiledialogs::message_box_ok(
-                                            "Alert!",
-                                            &message,
-                                            MessageBoxIcon::Warning,
-                                        );
-                                        sender.send(())
-                                    }
-                                    PromptDefinition::YesNo(mut message, sender) => {
-                                        if origin == PromptOrigin::Untrusted {
-                                            message = tiny_dialog_escape(&message);
-                                        }
-                                        let result = tinyfiledialogs::message_box_yes_no(
-                                            "", &message, MessageBoxIcon::Warning, YesNo::No,
-                                        );
-                                        sender.send(match result {
-                                            YesNo::Yes => PromptResult::Primary,
-                                            YesNo::No => PromptResult::Secondary,
-                                        })
-                                    }
-                                    PromptDefinition::OkCancel(mut message, sender) => {
-                                        if origin == PromptOrigin::Untrusted {
-                                            message = tiny_dialog_escape(&message);
-                                        }
-                                        let result = tinyfiledialogs::message_box_ok_cancel(
-                                            "", &message, MessageBoxIcon::Warning, OkCancel::Cancel,
-                                        );
-                                        sender.send(match result {
-                                            OkCancel::Ok => PromptResult::Primary,
-                                            OkCancel::Cancel => PromptResult::Secondary,
-                                        })
-                                    }
-                                    PromptDefinition::Input(mut message, mut default, sender) => {
-                                        if origin == PromptOrigin::Untrusted {
-                                            message = tiny_dialog_escape(&message);
-                                            default = tiny_dialog_escape(&default);
-                                        }
-                                        let result = tinyfiledialogs::input_box("", &message, &default);
-                                        sender.send(result)
-                                    }
-                                }
-                            })
+                            .spawn(move || prompt_user(request))
                             .unwrap()
                             .join()
                             .expect("Thread spawning failed")
                     };
-                    if let Err(e) = res {
-                        let reason = format!("Failed to send Prompt response: {}", e);
-                        self.event_queue
-                            .push(WindowEvent::SendError(browser_id, reason));
-                    }
+                    self.event_queue
+                        .push(WindowEvent::RespondToPrompt(prompt_id, response));
                 },
-                EmbedderMsg::AllowUnload(sender) => {
-                    // Always allow unload for now.
-                    if let Err(e) = sender.send(true) {
-                        let reason = format!("Failed to send AllowUnload response: {}", e);
-                        self.event_queue
-                            .push(WindowEvent::SendError(browser_id, reason));
-                    }
+                EmbedderMsg::HidePrompt(prompt_id) => {
+                    // The dialogs are modal, so the prompt was answered before it is hidden.
+                    debug!("HidePrompt received ({:?})", prompt_id);
                 },
                 EmbedderMsg::AllowNavigationRequest(pipeline_id, _url) => {
                     if let Some(_browser_id) = browser_id {
@@ -452,6 +392,27 @@ where
                 EmbedderMsg::SetFullscreenState(state) => {
                     self.window.set_fullscreen(state);
                 },
+                EmbedderMsg::EnterPictureInPicture(image_key, size) => {
+                    // TODO: present the image in a separate always-on-top window.
+                    debug!(
+                        "Picture-in-picture of {:?} ({:?}) is not supported",
+                        image_key, size
+                    );
+                },
+                EmbedderMsg::LeavePictureInPicture => {},
+                EmbedderMsg::GetMediaDevices(sender) => {
+                    // Audio is always played on the default output device.
+                    let devices = vec![MediaDeviceInfo {
+                        device_id: "default".to_owned(),
+                        kind: MediaDeviceKind::AudioOutput,
+                        label: "Default".to_owned(),
+                        group_id: "default".to_owned(),
+                    }];
+                    if let Err(e) = sender.send(devices) {
+                        let reason = format!("Failed to send GetMediaDevices response: {}", e);
+                        self.event_queue.push(WindowEvent::SendError(None, reason));
+                    };
+                },
                 EmbedderMsg::LoadStart => {
                     self.loading_state = Some(LoadingState::Connecting);
                 },
@@ -494,10 +455,6 @@ where
                         self.event_queue.push(WindowEvent::SendError(None, reason));
                     };
                 },
-                EmbedderMsg::PromptPermission(prompt, sender) => {
-                    let permission_state = prompt_user(prompt);
-                    let _ = sender.send(permission_state);
-                }
                 EmbedderMsg::ShowIME(_kind) => {
                     debug!("ShowIME received");
                 },
@@ -521,17 +478,110 @@ where
                         Err(()) => error!("Error running devtools server"),
                     }
                 },
+                EmbedderMsg::ShowContextMenu(info) => {
+                    debug!("ShowContextMenu received ({:?})", info);
+                    // TODO: Native context menus for Glutin based browsers.
+                },
+                EmbedderMsg::SessionSaved(_) => {
+                    debug!("SessionSaved received");
+                },
+                EmbedderMsg::ScreenshotCaptured(..) => {
+                    debug!("ScreenshotCaptured received");
+                },
+                EmbedderMsg::MemoryPressureHandled(report) => {
+                    info!("Memory pressure handled ({:?})", report);
+                },
+                EmbedderMsg::MemoryReports(reports) => {
+                    debug!("MemoryReports received ({} reports)", reports.len());
+                },
+                EmbedderMsg::HangReport(hang) => {
+                    debug!("HangReport received ({:?})", hang);
+                },
+                EmbedderMsg::BlockedContentReport(count) => {
+                    info!("{} subresources blocked", count);
+                },
+                EmbedderMsg::ContentSizeChanged(size) => {
+                    debug!("ContentSizeChanged received ({:?})", size);
+                },
             }
         }
     }
 }
 
-#[cfg(target_os = "linux")]
-fn prompt_user(prompt: PermissionPrompt) -> PermissionRequest {
-    if opts::get().headless {
-        return PermissionRequest::Denied;
+/// Show a prompt with a native dialog, and return the answer of the user.
+fn prompt_user(request: PromptRequest) -> PromptResponse {
+    let untrusted = request.origin() == PromptOrigin::Untrusted;
+    let escape = |text: String| {
+        if untrusted {
+            tiny_dialog_escape(&text)
+        } else {
+            text
+        }
+    };
+    match request {
+        PromptRequest::Alert(message) => {
+            tinyfiledialogs::message_box_ok("Alert!", &escape(message), MessageBoxIcon::Warning);
+            PromptResponse::Accept
+        },
+        PromptRequest::Confirm(message) => ok_cancel(&escape(message)),
+        PromptRequest::BeforeUnload => {
+            ok_cancel("This page is asking you to confirm that you want to leave.")
+        },
+        PromptRequest::YesNo(message) => {
+            match tinyfiledialogs::message_box_yes_no(
+                "",
+                &escape(message),
+                MessageBoxIcon::Warning,
+                YesNo::No,
+            ) {
+                YesNo::Yes => PromptResponse::Accept,
+                YesNo::No => PromptResponse::Dismiss,
+            }
+        },
+        PromptRequest::Input(message, default) => {
+            match tinyfiledialogs::input_box("", &escape(message), &escape(default)) {
+                Some(text) => PromptResponse::Text(text),
+                None => PromptResponse::Dismiss,
+            }
+        },
+        PromptRequest::Authentication(url, realm) => {
+            let origin = url.origin().ascii_serialization();
+            let message = match realm {
+                Some(realm) => format!(
+                    "{} requires a user name and password for \"{}\".",
+                    origin, realm
+                ),
+                None => format!("{} requires a user name and password.", origin),
+            };
+            let message = tiny_dialog_escape(&message);
+            let title = "Authentication required";
+            let user_name = match tinyfiledialogs::input_box(title, &message, "") {
+                Some(user_name) => user_name,
+                None => return PromptResponse::Dismiss,
+            };
+            match tinyfiledialogs::password_box(title, &message) {
+                Some(password) => PromptResponse::Credentials(user_name, password),
+                None => PromptResponse::Dismiss,
+            }
+        },
+        PromptRequest::Permission(prompt) => prompt_permission(prompt),
+    }
+}
+
+fn ok_cancel(message: &str) -> PromptResponse {
+    match tinyfiledialogs::message_box_ok_cancel(
+        "",
+        message,
+        MessageBoxIcon::Warning,
+        OkCancel::Cancel,
+    ) {
+        OkCancel::Ok => PromptResponse::Accept,
+        OkCancel::Cancel => PromptResponse::Dismiss,
     }
+}
 
+#[cfg(target_os = "linux")]
+fn prompt_permission(prompt: PermissionPrompt) -> PromptResponse {
     let message = match prompt {
         PermissionPrompt::Request(permission_name) => {
             format!("Do you want to grant permission for {:?}?", permission_name)
@@ -551,15 +601,15 @@ fn prompt_user(prompt: PermissionPrompt) -> PermissionRequest {
         MessageBoxIcon::Question,
         YesNo::No,
     ) {
-        YesNo::Yes => PermissionRequest::Granted,
-        YesNo::No => PermissionRequest::Denied,
+        YesNo::Yes => PromptResponse::Accept,
+        YesNo::No => PromptResponse::Dismiss,
     }
 }
 
 #[cfg(not(target_os = "linux"))]
-fn prompt_user(_prompt: PermissionPrompt) -> PermissionRequest {
+fn prompt_permission(_prompt: PermissionPrompt) -> PromptResponse {
     // TODO popup only supported on linux
-    PermissionRequest::Denied
+    PromptResponse::Dismiss
 }
 
 #[cfg(target_os = "linux")]
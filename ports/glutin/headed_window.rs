@@ -554,6 +554,15 @@ impl WindowPortsMethods for Window {
                     self.event_queue.borrow_mut().push(WindowEvent::Resize);
                 }
             },
+            glutin::WindowEvent::HiDpiFactorChanged(factor) => {
+                // The window moved to a monitor with a different scale factor.
+                let size = self.inner_size.get();
+                let logical_size = LogicalSize::new(size.width as f64, size.height as f64);
+                self.gl_context
+                    .borrow_mut()
+                    .resize(logical_size.to_physical(factor));
+                self.event_queue.borrow_mut().push(WindowEvent::Resize);
+            },
             _ => {},
         }
     }
@@ -7,9 +7,7 @@ extern crate log;
 
 pub mod gl_glue;
 
-pub use servo::embedder_traits::{
-    MediaSessionPlaybackState, PermissionPrompt, PermissionRequest, PromptResult,
-};
+pub use servo::embedder_traits::{MediaSessionPlaybackState, PermissionPrompt, PromptResult};
 pub use servo::script_traits::{MediaSessionActionType, MouseButton};
 
 use getopts::Options;
@@ -19,12 +17,13 @@ use servo::compositing::windowing::{
     WindowMethods,
 };
 use servo::embedder_traits::resources::{self, Resource, ResourceReaderMethods};
-use servo::embedder_traits::{EmbedderMsg, MediaSessionEvent, PromptDefinition, PromptOrigin};
+use servo::embedder_traits::{EmbedderMsg, MediaSessionEvent, PromptOrigin};
+use servo::embedder_traits::{PromptRequest, PromptResponse};
 use servo::euclid::{Point2D, Rect, Scale, Size2D, Vector2D};
 use servo::keyboard_types::{Key, KeyState, KeyboardEvent};
 use servo::msg::constellation_msg::TraversalDirection;
 use servo::script_traits::{TouchEventType, TouchId};
-use servo::servo_config::opts;
+use servo::servo_config::{self, opts};
 use servo::servo_config::{pref, set_pref};
 use servo::servo_url::ServoUrl;
 use servo::webrender_api::units::DevicePixel;
@@ -33,6 +32,7 @@ use servo::webvr::{VRExternalShmemPtr, VRMainThreadHeartbeat, VRService, VRServi
 use servo::{self, gl, BrowserId, Servo};
 use servo_media::player::context as MediaPlayerContext;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::os::raw::c_void;
 use std::path::PathBuf;
@@ -154,16 +154,45 @@ pub struct ServoGlue {
     servo: Servo<ServoWindowCallbacks>,
     batch_mode: bool,
     callbacks: Rc<ServoWindowCallbacks>,
-    /// id of the top level browsing context. It is unique as tabs
-    /// are not supported yet. None until created.
+    /// id of the selected top level browsing context, which is displayed
+    /// and receives the input events. None until created.
     browser_id: Option<BrowserId>,
-    // A rudimentary stack of "tabs".
+    // The "tabs", in creation order.
     // EmbedderMsg::BrowserCreated will push onto it.
-    // EmbedderMsg::CloseBrowser will pop from it,
+    // EmbedderMsg::CloseBrowser and close_browser will remove from it,
     // and exit if it is empty afterwards.
     browsers: Vec<BrowserId>,
+    /// The last known state of each browser, reported to the host when
+    /// the browser gets selected.
+    browser_states: HashMap<BrowserId, BrowserState>,
+    /// Browsers created with new_browser that must not be selected once created.
+    background_browsers: HashSet<BrowserId>,
     events: Vec<WindowEvent>,
-    current_url: Option<ServoUrl>,
+}
+
+/// The state of a browser, as last reported by Servo.
+#[derive(Default)]
+struct BrowserState {
+    title: Option<String>,
+    url: Option<ServoUrl>,
+    can_go_back: bool,
+    can_go_forward: bool,
+    loading: bool,
+}
+
+impl BrowserState {
+    fn display_title(&self) -> String {
+        let fallback_title: String = if let Some(ref current_url) = self.url {
+            current_url.to_string()
+        } else {
+            String::from("Untitled")
+        };
+        let title = match self.title {
+            Some(ref title) if title.len() > 0 => &**title,
+            _ => &fallback_title,
+        };
+        format!("{} - Servo", title)
+    }
 }
 
 pub fn servo_version() -> String {
@@ -238,8 +267,9 @@ pub fn init(
             callbacks: window_callbacks,
             browser_id: None,
             browsers: vec![],
+            browser_states: HashMap::new(),
+            background_browsers: HashSet::new(),
             events: vec![],
-            current_url: Some(url.clone()),
         };
         let browser_id = BrowserId::new();
         let _ = servo_glue.process_event(WindowEvent::NewBrowser(url, browser_id));
@@ -492,6 +522,98 @@ impl ServoGlue {
         self.process_event(WindowEvent::MediaSessionAction(action))
     }
 
+    /// Create a new browser ("tab") loading the given url. If `foreground` is
+    /// true, the new browser is selected once created. Otherwise it is created
+    /// hidden, and can be selected later with `select_browser`.
+    pub fn new_browser(&mut self, url: &str, foreground: bool) -> Result<BrowserId, &'static str> {
+        info!("new_browser: {}", url);
+        let url = ServoUrl::parse(url).map_err(|_| "Can't parse URL")?;
+        let browser_id = BrowserId::new();
+        if !foreground {
+            self.background_browsers.insert(browser_id);
+        }
+        self.process_event(WindowEvent::NewBrowser(url, browser_id))?;
+        Ok(browser_id)
+    }
+
+    /// Display the given browser, and send the input events to it.
+    /// The state of the browser (title, url, history…) is reported to the host.
+    pub fn select_browser(&mut self, browser_id: BrowserId) -> Result<(), &'static str> {
+        info!("select_browser: {}", browser_id);
+        if !self.browsers.contains(&browser_id) {
+            return Err("Unknown browser");
+        }
+        self.background_browsers.remove(&browser_id);
+        self.set_selected_browser(browser_id);
+        self.perform_updates_if_needed()
+    }
+
+    /// Close the given browser. If it was the selected browser, the most
+    /// recently created browser is selected instead.
+    pub fn close_browser(&mut self, browser_id: BrowserId) -> Result<(), &'static str> {
+        info!("close_browser: {}", browser_id);
+        if !self.browsers.contains(&browser_id) {
+            return Err("Unknown browser");
+        }
+        self.events.push(WindowEvent::CloseBrowser(browser_id));
+        self.remove_browser(browser_id);
+        self.perform_updates_if_needed()
+    }
+
+    /// Move a browser offscreen or back onscreen, without changing the selected browser.
+    /// Hidden browsers don't tick their animations.
+    pub fn set_browser_visibility(
+        &mut self,
+        browser_id: BrowserId,
+        visible: bool,
+    ) -> Result<(), &'static str> {
+        info!("set_browser_visibility: {} {}", browser_id, visible);
+        if !self.browsers.contains(&browser_id) {
+            return Err("Unknown browser");
+        }
+        self.process_event(WindowEvent::ChangeBrowserVisibility(browser_id, visible))
+    }
+
+    /// Freeze the documents of a browser, such as one left in the background, or resume them.
+    pub fn set_browser_frozen(
+        &mut self,
+        browser_id: BrowserId,
+        frozen: bool,
+    ) -> Result<(), &'static str> {
+        info!("set_browser_frozen: {} {}", browser_id, frozen);
+        if !self.browsers.contains(&browser_id) {
+            return Err("Unknown browser");
+        }
+        self.process_event(WindowEvent::FreezeBrowser(browser_id, frozen))
+    }
+
+    /// Discard the documents of a hidden browser to save memory. The current one is loaded
+    /// again once the browser is shown, reloaded or traversed.
+    pub fn discard_browser(&mut self, browser_id: BrowserId) -> Result<(), &'static str> {
+        info!("discard_browser: {}", browser_id);
+        if !self.browsers.contains(&browser_id) {
+            return Err("Unknown browser");
+        }
+        self.process_event(WindowEvent::DiscardBrowser(browser_id))
+    }
+
+    /// Set the languages of the user, in order of preference, such as the ones of the
+    /// system. The documents are sent a `languagechange` event.
+    pub fn set_languages(&mut self, languages: &[&str]) -> Result<(), &'static str> {
+        info!("set_languages: {:?}", languages);
+        servo_config::locale::set_languages(languages).map_err(|_| "Invalid languages")
+    }
+
+    /// All the browsers of this instance, in creation order.
+    pub fn browsers(&self) -> &[BrowserId] {
+        &self.browsers
+    }
+
+    /// The browser that is displayed and receives the input events, if any.
+    pub fn selected_browser(&self) -> Option<BrowserId> {
+        self.browser_id
+    }
+
     pub fn change_visibility(&mut self, visible: bool) -> Result<(), &'static str> {
         info!("change_visibility");
         if let Ok(id) = self.get_browser_id() {
@@ -503,8 +625,18 @@ impl ServoGlue {
         }
     }
 
+    /// Free as much memory as possible, when the system is low on memory.
+    pub fn memory_pressure(&mut self) -> Result<(), &'static str> {
+        info!("memory_pressure");
+        self.process_event(WindowEvent::MemoryPressure)
+    }
+
     fn process_event(&mut self, event: WindowEvent) -> Result<(), &'static str> {
         self.events.push(event);
+        self.perform_updates_if_needed()
+    }
+
+    fn perform_updates_if_needed(&mut self) -> Result<(), &'static str> {
         if !self.batch_mode {
             self.perform_updates()
         } else {
@@ -512,21 +644,63 @@ impl ServoGlue {
         }
     }
 
+    fn set_selected_browser(&mut self, browser_id: BrowserId) {
+        self.browser_id = Some(browser_id);
+        self.events.push(WindowEvent::SelectBrowser(browser_id));
+        self.report_browser_state();
+    }
+
+    fn remove_browser(&mut self, browser_id: BrowserId) {
+        self.browsers.retain(|id| *id != browser_id);
+        self.browser_states.remove(&browser_id);
+        self.background_browsers.remove(&browser_id);
+        if self.browser_id != Some(browser_id) {
+            return;
+        }
+        self.browser_id = None;
+        if let Some(prev_browser_id) = self.browsers.last().cloned() {
+            self.set_selected_browser(prev_browser_id);
+        } else {
+            self.events.push(WindowEvent::Quit);
+        }
+    }
+
+    /// Let the host know about the state of the newly selected browser.
+    fn report_browser_state(&self) {
+        let state = match self.browser_id.and_then(|id| self.browser_states.get(&id)) {
+            Some(state) => state,
+            None => return,
+        };
+        let callbacks = &self.callbacks.host_callbacks;
+        callbacks.on_title_changed(state.display_title());
+        if let Some(ref url) = state.url {
+            callbacks.on_url_changed(url.to_string());
+        }
+        callbacks.on_history_changed(state.can_go_back, state.can_go_forward);
+        if state.loading {
+            callbacks.on_load_started();
+        } else {
+            callbacks.on_load_ended();
+        }
+    }
+
+    fn is_selected(&self, browser_id: Option<BrowserId>) -> bool {
+        browser_id.is_some() && browser_id == self.browser_id
+    }
+
     fn handle_servo_events(&mut self) -> Result<(), &'static str> {
         for (browser_id, event) in self.servo.get_events() {
             match event {
                 EmbedderMsg::ChangePageTitle(title) => {
-                    let fallback_title: String = if let Some(ref current_url) = self.current_url {
-                        current_url.to_string()
-                    } else {
-                        String::from("Untitled")
-                    };
-                    let title = match title {
-                        Some(ref title) if title.len() > 0 => &**title,
-                        _ => &fallback_title,
-                    };
-                    let title = format!("{} - Servo", title);
-                    self.callbacks.host_callbacks.on_title_changed(title);
+                    let selected = self.is_selected(browser_id);
+                    if let Some(state) = browser_id.and_then(|id| self.browser_states.get_mut(&id))
+                    {
+                        state.title = title;
+                        if selected {
+                            let title = state.display_title();
+                            self.callbacks.host_callbacks.on_title_changed(title);
+                        }
+                    }
                 },
                 EmbedderMsg::AllowNavigationRequest(pipeline_id, url) => {
                     if let Some(_browser_id) = browser_id {
@@ -542,47 +716,90 @@ impl ServoGlue {
                 EmbedderMsg::HistoryChanged(entries, current) => {
                     let can_go_back = current > 0;
                     let can_go_forward = current < entries.len() - 1;
-                    self.callbacks
-                        .host_callbacks
-                        .on_history_changed(can_go_back, can_go_forward);
-                    self.callbacks
-                        .host_callbacks
-                        .on_url_changed(entries[current].clone().to_string());
-                    self.current_url = Some(entries[current].clone());
+                    if let Some(state) = browser_id.and_then(|id| self.browser_states.get_mut(&id))
+                    {
+                        state.can_go_back = can_go_back;
+                        state.can_go_forward = can_go_forward;
+                        state.url = Some(entries[current].clone());
+                    }
+                    if self.is_selected(browser_id) {
+                        self.callbacks
+                            .host_callbacks
+                            .on_history_changed(can_go_back, can_go_forward);
+                        self.callbacks
+                            .host_callbacks
+                            .on_url_changed(entries[current].clone().to_string());
+                    }
                 },
                 EmbedderMsg::LoadStart => {
-                    self.callbacks.host_callbacks.on_load_started();
+                    if let Some(state) = browser_id.and_then(|id| self.browser_states.get_mut(&id))
+                    {
+                        state.loading = true;
+                    }
+                    if self.is_selected(browser_id) {
+                        self.callbacks.host_callbacks.on_load_started();
+                    }
                 },
                 EmbedderMsg::LoadComplete => {
-                    self.callbacks.host_callbacks.on_load_ended();
+                    if let Some(state) = browser_id.and_then(|id| self.browser_states.get_mut(&id))
+                    {
+                        state.loading = false;
+                    }
+                    if self.is_selected(browser_id) {
+                        self.callbacks.host_callbacks.on_load_ended();
+                    }
                 },
                 EmbedderMsg::GetSelectedBluetoothDevice(_, sender) => {
                     let _ = sender.send(None);
                 },
-                EmbedderMsg::AllowUnload(sender) => {
-                    let _ = sender.send(true);
+                EmbedderMsg::GetMediaDevices(sender) => {
+                    let _ = sender.send(vec![]);
                 },
-                EmbedderMsg::Prompt(definition, origin) => {
+                EmbedderMsg::ShowPrompt(prompt_id, request) => {
                     let cb = &self.callbacks.host_callbacks;
-                    let trusted = origin == PromptOrigin::Trusted;
-                    let res = match definition {
-                        PromptDefinition::Alert(message, sender) => {
-                            sender.send(cb.prompt_alert(message, trusted))
+                    let trusted = request.origin() == PromptOrigin::Trusted;
+                    let response = match request {
+                        PromptRequest::Alert(message) => {
+                            cb.prompt_alert(message, trusted);
+                            PromptResponse::Accept
+                        },
+                        PromptRequest::Confirm(message) => {
+                            prompt_response(cb.prompt_ok_cancel(message, trusted))
                         },
-                        PromptDefinition::OkCancel(message, sender) => {
-                            sender.send(cb.prompt_ok_cancel(message, trusted))
+                        PromptRequest::YesNo(message) => {
+                            prompt_response(cb.prompt_yes_no(message, trusted))
                         },
-                        PromptDefinition::YesNo(message, sender) => {
-                            sender.send(cb.prompt_yes_no(message, trusted))
+                        PromptRequest::Input(message, default) => {
+                            match cb.prompt_input(message, default, trusted) {
+                                Some(text) => PromptResponse::Text(text),
+                                None => PromptResponse::Dismiss,
+                            }
                         },
-                        PromptDefinition::Input(message, default, sender) => {
-                            sender.send(cb.prompt_input(message, default, trusted))
+                        // Always allow unload for now.
+                        PromptRequest::BeforeUnload => PromptResponse::Accept,
+                        // The host has no way to ask for credentials.
+                        PromptRequest::Authentication(..) => PromptResponse::Dismiss,
+                        PromptRequest::Permission(prompt) => {
+                            let message = match prompt {
+                                PermissionPrompt::Request(permission_name) => {
+                                    format!(
+                                        "Do you want to grant permission for {:?}?",
+                                        permission_name
+                                    )
+                                },
+                                PermissionPrompt::Insecure(permission_name) => {
+                                    format!(
+                                        "The {:?} feature is only safe to use in secure context, but servo can't guarantee\n\
+                                        that the current context is secure. Do you want to proceed and grant permission?",
+                                        permission_name
+                                    )
+                                },
+                            };
+                            prompt_response(cb.prompt_yes_no(message, true))
                         },
                     };
-                    if let Err(e) = res {
-                        let reason = format!("Failed to send Prompt response: {}", e);
-                        self.events.push(WindowEvent::SendError(browser_id, reason));
-                    }
+                    self.events
+                        .push(WindowEvent::RespondToPrompt(prompt_id, response));
                 },
                 EmbedderMsg::AllowOpeningBrowser(response_chan) => {
                     // Note: would be a place to handle pop-ups config.
@@ -592,12 +809,15 @@ impl ServoGlue {
                     };
                 },
                 EmbedderMsg::BrowserCreated(new_browser_id) => {
-                    // TODO: properly handle a new "tab"
                     self.browsers.push(new_browser_id);
-                    if self.browser_id.is_none() {
-                        self.browser_id = Some(new_browser_id);
+                    self.browser_states
+                        .insert(new_browser_id, BrowserState::default());
+                    if self.background_browsers.remove(&new_browser_id) {
+                        self.events
+                            .push(WindowEvent::ChangeBrowserVisibility(new_browser_id, false));
+                    } else {
+                        self.set_selected_browser(new_browser_id);
                     }
-                    self.events.push(WindowEvent::SelectBrowser(new_browser_id));
                 },
                 EmbedderMsg::GetClipboardContents(sender) => {
                     let contents = self.callbacks.host_callbacks.get_clipboard_contents();
@@ -607,42 +827,13 @@ impl ServoGlue {
                     self.callbacks.host_callbacks.set_clipboard_contents(text);
                 },
                 EmbedderMsg::CloseBrowser => {
-                    // TODO: close the appropriate "tab".
-                    let _ = self.browsers.pop();
-                    if let Some(prev_browser_id) = self.browsers.last() {
-                        self.browser_id = Some(*prev_browser_id);
-                        self.events
-                            .push(WindowEvent::SelectBrowser(*prev_browser_id));
-                    } else {
-                        self.events.push(WindowEvent::Quit);
+                    if let Some(browser_id) = browser_id {
+                        self.remove_browser(browser_id);
                     }
                 },
                 EmbedderMsg::Shutdown => {
                     self.callbacks.host_callbacks.on_shutdown_complete();
                 },
-                EmbedderMsg::PromptPermission(prompt, sender) => {
-                    let message = match prompt {
-                        PermissionPrompt::Request(permission_name) => {
-                            format!("Do you want to grant permission for {:?}?", permission_name)
-                        },
-                        PermissionPrompt::Insecure(permission_name) => {
-                            format!(
-                                "The {:?} feature is only safe to use in secure context, but servo can't guarantee\n\
-                                that the current context is secure. Do you want to proceed and grant permission?",
-                                permission_name
-                            )
-                        },
-                    };
-
-                    let result = match self.callbacks.host_callbacks.prompt_yes_no(message, true) {
-                        PromptResult::Primary => PermissionRequest::Granted,
-                        PromptResult::Secondary | PromptResult::Dismissed => {
-                            PermissionRequest::Denied
-                        },
-                    };
-
-                    let _ = sender.send(result);
-                },
                 EmbedderMsg::ShowIME(..) => {
                     self.callbacks.host_callbacks.on_ime_state_changed(true);
                 },
@@ -675,7 +866,16 @@ impl ServoGlue {
                 EmbedderMsg::OnDevtoolsStarted(port) => {
                     self.callbacks.host_callbacks.on_devtools_started(port);
                 },
-                EmbedderMsg::Status(..) |
+                EmbedderMsg::HidePrompt(..) |
+                EmbedderMsg::HoveredLinkChanged(..) |
+                EmbedderMsg::ShowContextMenu(..) |
+                EmbedderMsg::SessionSaved(..) |
+                EmbedderMsg::ScreenshotCaptured(..) |
+                EmbedderMsg::MemoryPressureHandled(..) |
+                EmbedderMsg::MemoryReports(..) |
+                EmbedderMsg::HangReport(..) |
+                EmbedderMsg::BlockedContentReport(..) |
+                EmbedderMsg::ContentSizeChanged(..) |
                 EmbedderMsg::SelectFiles(..) |
                 EmbedderMsg::MoveTo(..) |
                 EmbedderMsg::ResizeTo(..) |
@@ -684,6 +884,8 @@ impl ServoGlue {
                 EmbedderMsg::NewFavicon(..) |
                 EmbedderMsg::HeadParsed |
                 EmbedderMsg::SetFullscreenState(..) |
+                EmbedderMsg::EnterPictureInPicture(..) |
+                EmbedderMsg::LeavePictureInPicture |
                 EmbedderMsg::Panic(..) |
                 EmbedderMsg::ReportProfile(..) => {},
             }
@@ -692,6 +894,14 @@ impl ServoGlue {
     }
 }
 
+/// The answer to a prompt, given the button clicked by the user.
+fn prompt_response(result: PromptResult) -> PromptResponse {
+    match result {
+        PromptResult::Primary => PromptResponse::Accept,
+        PromptResult::Secondary | PromptResult::Dismissed => PromptResponse::Dismiss,
+    }
+}
+
 struct ServoEmbedderCallbacks {
     waker: Box<dyn EventLoopWaker>,
     xr_discovery: Option<webxr::Discovery>,
@@ -875,6 +1085,7 @@ impl ResourceReaderMethods for ResourceReaderInstance {
             Resource::MediaControlsJS => {
                 &include_bytes!("../../../../resources/media-controls.js")[..]
             },
+            Resource::DebuggerJS => &include_bytes!("../../../../resources/debugger.js")[..],
         })
     }
 
@@ -0,0 +1,345 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// The debugger of a script thread, running in its own global. The script thread
+// provides two functions to this global:
+// - `reportSource(json)`, to tell the devtools server about a new source;
+// - `pauseDebuggee(json)`, which blocks until the devtools client resumes the
+//   execution, and returns the limit to step to as JSON.
+// The functions of this script that the script thread calls take and return
+// JSON strings, which are deserialized as the types of `devtools_traits`.
+
+(() => {
+  "use strict";
+
+  const dbg = new Debugger();
+
+  // The pipeline of each debuggee global, keyed by their `Debugger.Object`.
+  const pipelines = new Map();
+
+  // The sources seen so far, by id.
+  const sources = new Map();
+  const sourceIds = new Map();
+  let nextSourceId = 1;
+
+  // The handlers of the breakpoints, keyed by "sourceId:line:column".
+  const breakpoints = new Map();
+
+  // The frames of the current pause, from the youngest one.
+  let pausedFrames = [];
+
+  // The frames with stepping hooks, which are cleared when pausing again.
+  let steppingFrames = [];
+  let interrupted = false;
+
+  function sourceId(source) {
+    let id = sourceIds.get(source);
+    if (id === undefined) {
+      id = nextSourceId++;
+      sourceIds.set(source, id);
+      sources.set(id, source);
+    }
+    return id;
+  }
+
+  function sourceInfo(source) {
+    return {
+      id: sourceId(source),
+      url: source.url || "",
+      sourceMapURL: source.sourceMapURL || null,
+      introductionType: source.introductionType || null,
+    };
+  }
+
+  function scriptsOf(source) {
+    return dbg.findScripts().filter(script => script.source === source);
+  }
+
+  function frameLocation(frame) {
+    const script = frame.script;
+    const { lineNumber, columnNumber } = script.getOffsetLocation(frame.offset);
+    return {
+      sourceId: sourceId(script.source),
+      line: lineNumber,
+      column: columnNumber,
+    };
+  }
+
+  function value(v) {
+    if (typeof v === "object" && v !== null && !(v instanceof Debugger.Object)) {
+      // The value of a variable that was optimized out or isn't initialized.
+      return "Undefined";
+    }
+    if (v === undefined) {
+      return "Undefined";
+    }
+    if (v === null) {
+      return "Null";
+    }
+    switch (typeof v) {
+      case "boolean":
+        return { Boolean: v };
+      case "number":
+        return { Number: v };
+      case "string":
+        return { String: v };
+      case "object":
+        return {
+          Object: {
+            class: v.class,
+            name: v.callable ? v.displayName || v.name || null : null,
+          },
+        };
+      default:
+        // Symbols.
+        return { String: String(v) };
+    }
+  }
+
+  function frameInfo(frame, id) {
+    let thisValue;
+    try {
+      thisValue = value(frame.this);
+    } catch (e) {
+      thisValue = "Undefined";
+    }
+    const callee = frame.callee;
+    return {
+      id,
+      type_: frame.type,
+      displayName: callee ? callee.displayName || callee.name || null : null,
+      location: frameLocation(frame),
+      this: thisValue,
+      arguments: (frame.arguments || []).map(value),
+    };
+  }
+
+  function clearStepping() {
+    for (const frame of steppingFrames) {
+      if (frame.live) {
+        frame.onStep = undefined;
+        frame.onPop = undefined;
+      }
+    }
+    steppingFrames = [];
+    dbg.onEnterFrame = undefined;
+  }
+
+  function pause(frame, why) {
+    clearStepping();
+    interrupted = false;
+
+    pausedFrames = [];
+    for (let f = frame; f; f = f.older) {
+      pausedFrames.push(f);
+    }
+
+    const pipeline = pipelines.get(frame.script.global);
+    const limit = JSON.parse(
+      pauseDebuggee(JSON.stringify({
+        pipeline,
+        pause: { why, frame: frameInfo(frame, 0) },
+      }))
+    );
+    pausedFrames = [];
+
+    if (limit) {
+      step(frame, limit);
+    }
+    if (interrupted) {
+      dbg.onEnterFrame = f => pause(f, "interrupted");
+    }
+    return undefined;
+  }
+
+  function step(frame, limit) {
+    const startLine = frameLocation(frame).line;
+
+    // Pause at the first step of the given frame.
+    const pauseOnStep = f => {
+      f.onStep = function() {
+        return pause(this, "resumeLimit");
+      };
+      steppingFrames.push(f);
+    };
+
+    // Keep stepping in the caller once the frame returns.
+    const onPop = function() {
+      if (this.older) {
+        pauseOnStep(this.older);
+      }
+      return undefined;
+    };
+
+    if (limit !== "Finish") {
+      frame.onStep = function() {
+        if (frameLocation(this).line === startLine) {
+          return undefined;
+        }
+        return pause(this, "resumeLimit");
+      };
+    }
+    frame.onPop = onPop;
+    steppingFrames.push(frame);
+
+    if (limit === "Step") {
+      dbg.onEnterFrame = f => pause(f, "resumeLimit");
+    }
+  }
+
+  function setBreakpointIn(script, location, handler) {
+    for (const offset of script.getLineOffsets(location.line)) {
+      const { columnNumber } = script.getOffsetLocation(offset);
+      if (location.column === 0 || columnNumber === location.column) {
+        script.setBreakpoint(offset, handler);
+      }
+    }
+  }
+
+  dbg.onNewScript = script => {
+    const source = script.source;
+    const known = sourceIds.has(source);
+    const id = sourceId(source);
+    if (!known) {
+      reportSource(JSON.stringify({
+        pipeline: pipelines.get(script.global),
+        source: sourceInfo(source),
+      }));
+    }
+
+    // Apply the existing breakpoints to the scripts of the source that are created
+    // later, like the functions of an eval'd source.
+    for (const { location, handler } of breakpoints.values()) {
+      if (location.sourceId === id) {
+        setBreakpointIn(script, location, handler);
+        for (const child of script.getChildScripts()) {
+          setBreakpointIn(child, location, handler);
+        }
+      }
+    }
+  };
+
+  dbg.onDebuggerStatement = frame => pause(frame, "debuggerStatement");
+
+  globalThis.addDebuggee = (global, pipeline) => {
+    const debuggee = dbg.addDebuggee(global);
+    pipelines.set(debuggee, JSON.parse(pipeline));
+
+    const seen = new Set();
+    const infos = [];
+    for (const script of dbg.findScripts({ global: debuggee })) {
+      if (!seen.has(script.source)) {
+        seen.add(script.source);
+        infos.push(sourceInfo(script.source));
+      }
+    }
+    return JSON.stringify(infos);
+  };
+
+  globalThis.removeDebuggee = global => {
+    const debuggee = dbg.makeGlobalObjectReference(global);
+
+    // Clear the breakpoints of the scripts of the global, and forget its sources,
+    // so that a breakpoint doesn't outlive the document it was set in.
+    const removedSources = new Set();
+    for (const script of dbg.findScripts({ global: debuggee })) {
+      script.clearAllBreakpoints();
+      removedSources.add(sourceId(script.source));
+    }
+    for (const [key, { location }] of breakpoints) {
+      if (removedSources.has(location.sourceId)) {
+        breakpoints.delete(key);
+      }
+    }
+    for (const id of removedSources) {
+      sourceIds.delete(sources.get(id));
+      sources.delete(id);
+    }
+
+    pipelines.delete(debuggee);
+    dbg.removeDebuggee(global);
+    return "null";
+  };
+
+  globalThis.getSourceText = id => {
+    const source = sources.get(JSON.parse(id));
+    return JSON.stringify(source ? source.text : null);
+  };
+
+  globalThis.getBreakpointPositions = id => {
+    const source = sources.get(JSON.parse(id));
+    const positions = [];
+    if (source) {
+      for (const script of scriptsOf(source)) {
+        for (const { lineNumber, columnNumber } of script.getAllColumnOffsets()) {
+          positions.push([lineNumber, columnNumber]);
+        }
+      }
+    }
+    return JSON.stringify(positions);
+  };
+
+  globalThis.setBreakpoint = json => {
+    const location = JSON.parse(json);
+    const key = `${location.sourceId}:${location.line}:${location.column}`;
+    const source = sources.get(location.sourceId);
+    if (!source || breakpoints.has(key)) {
+      return "null";
+    }
+    const handler = {
+      hit: frame => pause(frame, "breakpoint"),
+    };
+    breakpoints.set(key, { location, handler });
+    for (const script of scriptsOf(source)) {
+      setBreakpointIn(script, location, handler);
+    }
+    return "null";
+  };
+
+  globalThis.removeBreakpoint = json => {
+    const location = JSON.parse(json);
+    const key = `${location.sourceId}:${location.line}:${location.column}`;
+    const breakpoint = breakpoints.get(key);
+    if (breakpoint) {
+      breakpoints.delete(key);
+      for (const script of scriptsOf(sources.get(location.sourceId))) {
+        script.clearBreakpoint(breakpoint.handler);
+      }
+    }
+    return "null";
+  };
+
+  globalThis.interrupt = () => {
+    interrupted = true;
+    dbg.onEnterFrame = f => pause(f, "interrupted");
+    return "null";
+  };
+
+  globalThis.getFrames = () => {
+    return JSON.stringify(pausedFrames.map(frameInfo));
+  };
+
+  globalThis.getEnvironment = id => {
+    const frame = pausedFrames[JSON.parse(id)];
+    const scopes = [];
+    for (let env = frame ? frame.environment : null; env; env = env.parent) {
+      if (env.type === "declarative") {
+        scopes.push({
+          type_: env.callee ? "function" : "declarative",
+          functionName: env.callee ? env.callee.displayName || env.callee.name || null : null,
+          variables: env.names().map(name => [name, value(env.getVariable(name))]),
+          object: null,
+        });
+      } else {
+        scopes.push({
+          type_: env.type,
+          functionName: null,
+          variables: [],
+          object: value(env.object),
+        });
+      }
+    }
+    return JSON.stringify(scopes);
+  };
+})();
@@ -3,10 +3,16 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use ipc_channel::ipc;
+use profile::chrome_trace::ChromeTrace;
 use profile::time;
+use profile_traits::gecko::GeckoProfile;
 use profile_traits::ipc as ProfiledIpc;
-use profile_traits::time::{ProfilerCategory, ProfilerData, ProfilerMsg};
+use profile_traits::time::TraceThread;
+use profile_traits::time::{self as time_traits, ProfilerCategory, ProfilerData, ProfilerMsg};
+use profile_traits::time::{TimerMetadata, TimerMetadataFrameType, TimerMetadataReflowType};
 use servo_config::opts::OutputOptions;
+use std::env;
+use std::fs;
 use std::thread;
 use std::time::Duration;
 
@@ -93,6 +99,113 @@ fn bytes_channel_profiler_test() {
     };
 }
 
+#[test]
+fn time_profiler_markers_test() {
+    let chan = time::Profiler::create(&None, None, false);
+    chan.send(ProfilerMsg::Time(
+        (ProfilerCategory::LayoutPerform, None),
+        (0, 1000000),
+        (0, 0),
+        None,
+    ));
+    chan.send(ProfilerMsg::StartRecordingMarkers);
+    time_traits::profile(ProfilerCategory::ScriptEvaluate, None, chan.clone(), || {
+        thread::sleep(Duration::from_millis(10));
+    });
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    chan.send(ProfilerMsg::StopRecordingMarkers(sender));
+    let markers = receiver.recv().unwrap();
+    assert_eq!(
+        markers.len(),
+        1,
+        "Only the times reported while recording are markers"
+    );
+    assert_eq!(markers[0].category, ProfilerCategory::ScriptEvaluate);
+    assert!(markers[0].end - markers[0].start >= 10.);
+
+    let start = markers[0].start - 5.;
+    let mut profile = GeckoProfile::new(start, 1.);
+    profile.add_markers(markers);
+    assert_eq!(profile.threads.len(), 1);
+    assert_eq!(profile.threads[0].name, "Script markers");
+    let (name, marker_time, ref data) = profile.threads[0].markers.data[0];
+    assert_eq!(profile.threads[0].string_table[name], "ScriptEvaluate");
+    assert!((marker_time - 5.).abs() < 1e-6);
+    assert_eq!(data.start_time, marker_time);
+}
+
+#[test]
+fn chrome_trace_test() {
+    let path = env::temp_dir().join(format!("servo-trace-{}.json", std::process::id()));
+    let mut trace = ChromeTrace::create(path.to_string_lossy().into_owned()).unwrap();
+    let thread = TraceThread {
+        process_id: 42,
+        thread_id: 7,
+        thread_name: Some("LayoutThread".to_owned()),
+    };
+    let metadata = TimerMetadata {
+        url: "https://example.com/".to_owned(),
+        iframe: TimerMetadataFrameType::RootWindow,
+        incremental: TimerMetadataReflowType::FirstReflow,
+    };
+    trace.write_one(
+        &(ProfilerCategory::LayoutStyleRecalc, Some(metadata)),
+        (1_000_000, 3_500_000),
+        &thread,
+    );
+    trace.write_one(
+        &(ProfilerCategory::LayoutMain, None),
+        (4_000_000, 5_000_000),
+        &thread,
+    );
+    drop(trace);
+
+    let events: Vec<serde_json::Value> =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let _ = fs::remove_file(&path);
+    assert_eq!(events.len(), 4, "The process and thread are named once");
+    assert_eq!(events[0]["name"], "process_name");
+    assert_eq!(events[0]["args"]["name"], "Content process");
+    assert_eq!(events[1]["name"], "thread_name");
+    assert_eq!(events[1]["tid"], 7);
+    assert_eq!(events[1]["args"]["name"], "LayoutThread");
+    assert_eq!(events[2]["name"], "LayoutStyleRecalc");
+    assert_eq!(events[2]["cat"], "layout");
+    assert_eq!(events[2]["ph"], "X");
+    assert_eq!(events[2]["pid"], 42);
+    assert_eq!(events[2]["ts"], 1000.);
+    assert_eq!(events[2]["dur"], 2500.);
+    assert_eq!(events[2]["args"]["url"], "https://example.com/");
+    assert_eq!(events[3]["name"], "LayoutMain");
+    assert!(events[3].get("args").is_none());
+}
+
+#[test]
+fn gecko_profile_samples_test() {
+    let mut profile = GeckoProfile::new(0., 1.);
+    profile
+        .thread("Script")
+        .add_sample(1., vec!["main", "run", "eval"]);
+    profile
+        .thread("Script")
+        .add_sample(2., vec!["main", "run", "layout"]);
+    profile.thread("Script").add_sample(3., vec![]);
+    assert_eq!(profile.threads.len(), 1);
+
+    let thread = &profile.threads[0];
+    assert_eq!(thread.string_table, vec!["main", "run", "eval", "layout"]);
+    assert_eq!(thread.frame_table.data, vec![(0,), (1,), (2,), (3,)]);
+    assert_eq!(
+        thread.stack_table.data,
+        vec![(0, None), (1, Some(0)), (2, Some(1)), (3, Some(1))]
+    );
+    assert_eq!(
+        thread.samples.data,
+        vec![(Some(2), 1.), (Some(3), 2.), (None, 3.)]
+    );
+}
+
 #[cfg(debug_assertions)]
 #[test]
 #[should_panic]
@@ -0,0 +1,53 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::gc_scheduler::{idle_period_deadline, idle_slice_budget};
+use std::time::{Duration, Instant};
+
+#[test]
+fn idle_period_is_at_most_50ms() {
+    let start = Instant::now();
+    assert_eq!(
+        idle_period_deadline(start, None, false),
+        start + Duration::from_millis(50)
+    );
+    assert_eq!(
+        idle_period_deadline(start, Some(Duration::from_secs(1)), false),
+        start + Duration::from_millis(50)
+    );
+}
+
+#[test]
+fn idle_period_ends_when_the_next_timer_is_due() {
+    let start = Instant::now();
+    assert_eq!(
+        idle_period_deadline(start, Some(Duration::from_millis(20)), false),
+        start + Duration::from_millis(20)
+    );
+    assert_eq!(
+        idle_period_deadline(start, Some(Duration::from_millis(0)), true),
+        start
+    );
+}
+
+#[test]
+fn idle_period_ends_before_the_next_frame() {
+    let start = Instant::now();
+    let deadline = idle_period_deadline(start, None, true);
+    assert!(deadline > start);
+    assert!(deadline <= start + Duration::from_millis(17));
+    assert_eq!(
+        idle_period_deadline(start, Some(Duration::from_millis(5)), true),
+        start + Duration::from_millis(5)
+    );
+}
+
+#[test]
+fn idle_slice_ends_before_the_deadline() {
+    assert_eq!(idle_slice_budget(5, Duration::from_millis(50)), Some(5));
+    assert_eq!(idle_slice_budget(5, Duration::from_millis(3)), Some(3));
+    assert_eq!(idle_slice_budget(0, Duration::from_millis(3)), Some(1));
+    assert_eq!(idle_slice_budget(5, Duration::from_micros(500)), None);
+    assert_eq!(idle_slice_budget(5, Duration::from_millis(0)), None);
+}
@@ -162,3 +162,27 @@ fn different_descriptor() {
         sources
     );
 }
+
+#[test]
+fn exponent_density_descriptor() {
+    let first_descriptor = Descriptor {
+        wid: None,
+        den: Some(1.0),
+    };
+    let first_imagesource = ImageSource {
+        url: "small-image.jpg".to_string(),
+        descriptor: first_descriptor,
+    };
+    let sources = &[first_imagesource];
+    assert_eq!(parse_a_srcset_attribute("small-image.jpg 1e0x"), sources);
+}
+
+//Does not parse an ImageSource when a descriptor is out of range or malformed
+#[test]
+fn invalid_descriptor() {
+    let empty_vec = Vec::new();
+    assert_eq!(parse_a_srcset_attribute("small-image.jpg 0w"), empty_vec);
+    assert_eq!(parse_a_srcset_attribute("small-image.jpg -1x"), empty_vec);
+    assert_eq!(parse_a_srcset_attribute("small-image.jpg 1.x"), empty_vec);
+    assert_eq!(parse_a_srcset_attribute("small-image.jpg +320w"), empty_vec);
+}
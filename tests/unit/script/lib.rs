@@ -2,6 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+#[cfg(test)]
+mod gc_scheduler;
 #[cfg(test)]
 mod headers;
 #[cfg(test)]
@@ -9,6 +11,8 @@ mod htmlareaelement;
 #[cfg(test)]
 mod htmlimageelement;
 #[cfg(test)]
+mod mem;
+#[cfg(test)]
 mod origin;
 #[cfg(all(test, target_pointer_width = "64"))]
 mod size_of;
@@ -16,6 +20,12 @@ mod size_of;
 mod textinput;
 #[cfg(test)]
 mod timeranges;
+#[cfg(test)]
+mod timers;
+#[cfg(test)]
+mod webvtt;
+#[cfg(test)]
+mod xpath;
 
 /**
 ```compile_fail,E0277
@@ -0,0 +1,53 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::mem::{interface_reports, is_ghost_window};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+#[test]
+fn interface_reports_are_grouped_under_the_path() {
+    let mut sizes = BTreeMap::new();
+    sizes.insert("HTMLDivElement", 640);
+    sizes.insert("Document", 2048);
+    sizes.insert("Text", 96);
+    let path = vec!["url(https://servo.org/)".to_owned()];
+
+    let reports = interface_reports(&sizes, &path);
+    let reports: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            assert_eq!(format!("{:?}", report.kind), "ExplicitJemallocHeapSize");
+            (report.path.join("/"), report.size)
+        })
+        .collect();
+    assert_eq!(
+        reports,
+        vec![
+            ("url(https://servo.org/)/dom/Document".to_owned(), 2048),
+            ("url(https://servo.org/)/dom/HTMLDivElement".to_owned(), 640),
+            ("url(https://servo.org/)/dom/Text".to_owned(), 96),
+        ]
+    );
+}
+
+#[test]
+fn no_interface_reports_without_dom_objects() {
+    let path = vec!["ghost-windows".to_owned()];
+    assert!(interface_reports(&BTreeMap::new(), &path).is_empty());
+}
+
+#[test]
+fn windows_become_ghosts_a_minute_after_being_discarded() {
+    let discarded_at = Instant::now();
+    assert!(!is_ghost_window(discarded_at, discarded_at));
+    assert!(!is_ghost_window(
+        discarded_at,
+        discarded_at + Duration::from_secs(59)
+    ));
+    assert!(is_ghost_window(
+        discarded_at,
+        discarded_at + Duration::from_secs(60)
+    ));
+}
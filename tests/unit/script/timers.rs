@@ -0,0 +1,32 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::timers::TimerThrottling;
+
+#[test]
+fn negative_prefs_disable_throttling() {
+    let throttling = TimerThrottling::new(-1000, -10000, -1000, -10);
+    assert_eq!(throttling, TimerThrottling::new(0, 0, 0, 0));
+    assert_eq!(throttling.throttle_delay(1234, 56, None), 56);
+    assert_eq!(throttling.throttle_delay(1234, 56, Some(-100.)), 56);
+    assert_eq!(throttling.regenerate_budget(-100., 1000.), -100.);
+}
+
+#[test]
+fn wake_ups_are_aligned() {
+    let throttling = TimerThrottling::new(1000, 10000, 1000, 10);
+    assert_eq!(throttling.throttle_delay(1234, 0, None), 766);
+    assert_eq!(throttling.throttle_delay(1234, 1766, None), 1766);
+    assert_eq!(throttling.throttle_delay(1234, 1767, None), 2766);
+    assert_eq!(throttling.throttle_delay(u64::max_value() - 1, 5, None), 1);
+}
+
+#[test]
+fn wake_ups_wait_for_the_budget_to_regenerate() {
+    let throttling = TimerThrottling::new(0, 10000, 1000, 10);
+    assert_eq!(throttling.throttle_delay(1234, 56, Some(500.)), 56);
+    assert_eq!(throttling.throttle_delay(1234, 56, Some(-5.)), 500);
+    assert_eq!(throttling.regenerate_budget(-5., 1000.), 5.);
+    assert_eq!(throttling.regenerate_budget(995., 1000.), 1000.);
+}
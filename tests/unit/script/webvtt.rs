@@ -0,0 +1,114 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::webvtt::{
+    parse, parse_cue_text, CueElementKind, CueLine, CueNode, LineAlignment, PositionAlignment,
+    TextAlignment, WritingDirection,
+};
+
+#[test]
+fn signature() {
+    assert!(parse("WEBVTT").unwrap().is_empty());
+    assert!(parse("\u{FEFF}WEBVTT - a title\n").unwrap().is_empty());
+    assert!(parse("WEBVTTX").is_err());
+    assert!(parse("webvtt").is_err());
+    assert!(parse("").is_err());
+}
+
+#[test]
+fn cues() {
+    let cues = parse(
+        "WEBVTT\r\nKind: captions\r\n\r\n\
+         NOTE a comment\r\n\r\n\
+         intro\r\n00:01.000 --> 00:04.500\r\nHello\r\nworld\r\n\r\n\
+         01:00:00.250 --> 01:00:01.000\r\nBye\r\n\r\n\
+         00:05.000 --> 00:0x.000\r\nInvalid\r\n",
+    )
+    .unwrap();
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].id, "intro");
+    assert_eq!(cues[0].start_time, 1.);
+    assert_eq!(cues[0].end_time, 4.5);
+    assert_eq!(cues[0].text, "Hello\nworld");
+    assert_eq!(cues[1].id, "");
+    assert_eq!(cues[1].start_time, 3600.25);
+    assert_eq!(cues[1].end_time, 3601.);
+    assert_eq!(cues[1].text, "Bye");
+}
+
+#[test]
+fn settings() {
+    let cues = parse(
+        "WEBVTT\n\n\
+         00:00.000 --> 00:01.000 vertical:rl line:10%,end position:20%,line-left size:50% align:left\n\
+         text\n\n\
+         00:00.000 --> 00:01.000 line:-2 size:150% align:middle\n\
+         text\n",
+    )
+    .unwrap();
+    let settings = &cues[0].settings;
+    assert_eq!(settings.direction, WritingDirection::VerticalGrowingLeft);
+    assert_eq!(settings.line, CueLine::Percentage(10.));
+    assert_eq!(settings.line_alignment, LineAlignment::End);
+    assert_eq!(settings.position, Some(20.));
+    assert_eq!(settings.position_alignment, PositionAlignment::LineLeft);
+    assert_eq!(settings.size, 50.);
+    assert_eq!(settings.text_alignment, TextAlignment::Left);
+
+    let settings = &cues[1].settings;
+    assert_eq!(settings.direction, WritingDirection::Horizontal);
+    assert_eq!(settings.line, CueLine::Number(-2.));
+    assert_eq!(settings.size, 100.);
+    assert_eq!(settings.text_alignment, TextAlignment::Center);
+}
+
+#[test]
+fn cue_text() {
+    assert_eq!(
+        parse_cue_text("<v.loud Bob>Hi &amp; <i>bye</i></v><00:00.500>&lt;3"),
+        vec![
+            CueNode::Element {
+                kind: CueElementKind::Voice,
+                classes: vec!["loud".to_owned()],
+                annotation: Some("Bob".to_owned()),
+                children: vec![
+                    CueNode::Text("Hi & ".to_owned()),
+                    CueNode::Element {
+                        kind: CueElementKind::Italic,
+                        classes: vec![],
+                        annotation: None,
+                        children: vec![CueNode::Text("bye".to_owned())],
+                    },
+                ],
+            },
+            CueNode::Timestamp(0.5),
+            CueNode::Text("<3".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn cue_text_ruby_and_unknown_tags() {
+    assert_eq!(
+        parse_cue_text("<ruby>a<rt>b</ruby><foo>c</foo><rt>d"),
+        vec![
+            CueNode::Element {
+                kind: CueElementKind::Ruby,
+                classes: vec![],
+                annotation: None,
+                children: vec![
+                    CueNode::Text("a".to_owned()),
+                    CueNode::Element {
+                        kind: CueElementKind::RubyText,
+                        classes: vec![],
+                        annotation: None,
+                        children: vec![CueNode::Text("b".to_owned())],
+                    },
+                ],
+            },
+            CueNode::Text("c".to_owned()),
+            CueNode::Text("d".to_owned()),
+        ]
+    );
+}
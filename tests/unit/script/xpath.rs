@@ -0,0 +1,194 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use script::test::xpath::{
+    parse, Axis, BinaryOperator, CoreFunction, Expr, NodeTest, PathStart, Step,
+};
+
+fn name(local_name: &str) -> NodeTest {
+    NodeTest::Name {
+        prefix: None,
+        local_name: Some(local_name.to_owned()),
+    }
+}
+
+fn step(axis: Axis, test: NodeTest) -> Step {
+    Step {
+        axis,
+        test,
+        predicates: vec![],
+    }
+}
+
+fn binary(left: Expr, operator: BinaryOperator, right: Expr) -> Expr {
+    Expr::Binary(Box::new(left), operator, Box::new(right))
+}
+
+#[test]
+fn location_paths() {
+    assert_eq!(
+        parse("//para[1]/..").unwrap(),
+        Expr::Path(
+            PathStart::Root,
+            vec![
+                step(Axis::DescendantOrSelf, NodeTest::Node),
+                Step {
+                    axis: Axis::Child,
+                    test: name("para"),
+                    predicates: vec![Expr::Number(1.)],
+                },
+                step(Axis::Parent, NodeTest::Node),
+            ]
+        )
+    );
+    assert_eq!(
+        parse("ancestor-or-self :: text() / @*").unwrap(),
+        Expr::Path(
+            PathStart::ContextNode,
+            vec![
+                step(Axis::AncestorOrSelf, NodeTest::Text),
+                step(
+                    Axis::Attribute,
+                    NodeTest::Name {
+                        prefix: None,
+                        local_name: None,
+                    }
+                ),
+            ]
+        )
+    );
+    assert_eq!(parse("/").unwrap(), Expr::Path(PathStart::Root, vec![]));
+    assert_eq!(
+        parse("(a)[2]/b").unwrap(),
+        Expr::Path(
+            PathStart::Filter(Box::new(Expr::Filter(
+                Box::new(Expr::Path(
+                    PathStart::ContextNode,
+                    vec![step(Axis::Child, name("a"))]
+                )),
+                vec![Expr::Number(2.)]
+            ))),
+            vec![step(Axis::Child, name("b"))]
+        )
+    );
+}
+
+#[test]
+fn operators() {
+    // A `*` or a name following an operand is an operator.
+    let child = |local_name| {
+        Expr::Path(
+            PathStart::ContextNode,
+            vec![step(Axis::Child, name(local_name))],
+        )
+    };
+    assert_eq!(
+        parse("div div div").unwrap(),
+        binary(child("div"), BinaryOperator::Divide, child("div"))
+    );
+    assert_eq!(
+        parse("* * *").unwrap(),
+        binary(
+            Expr::Path(
+                PathStart::ContextNode,
+                vec![step(
+                    Axis::Child,
+                    NodeTest::Name {
+                        prefix: None,
+                        local_name: None,
+                    }
+                )]
+            ),
+            BinaryOperator::Multiply,
+            Expr::Path(
+                PathStart::ContextNode,
+                vec![step(
+                    Axis::Child,
+                    NodeTest::Name {
+                        prefix: None,
+                        local_name: None,
+                    }
+                )]
+            )
+        )
+    );
+    assert_eq!(
+        parse("1 + 2 * 3 = 7 or -.5").unwrap(),
+        binary(
+            binary(
+                binary(
+                    Expr::Number(1.),
+                    BinaryOperator::Add,
+                    binary(Expr::Number(2.), BinaryOperator::Multiply, Expr::Number(3.))
+                ),
+                BinaryOperator::Equal,
+                Expr::Number(7.)
+            ),
+            BinaryOperator::Or,
+            Expr::Negate(Box::new(Expr::Number(0.5)))
+        )
+    );
+    assert_eq!(
+        parse("a-b").unwrap(),
+        Expr::Path(PathStart::ContextNode, vec![step(Axis::Child, name("a-b"))])
+    );
+    assert_eq!(
+        parse("3 - 1 - 1").unwrap(),
+        binary(
+            binary(Expr::Number(3.), BinaryOperator::Subtract, Expr::Number(1.)),
+            BinaryOperator::Subtract,
+            Expr::Number(1.)
+        )
+    );
+}
+
+#[test]
+fn functions() {
+    assert_eq!(
+        parse("concat('a', \"b\", 'c')").unwrap(),
+        Expr::Function(
+            CoreFunction::Concat,
+            vec![
+                Expr::Literal("a".to_owned()),
+                Expr::Literal("b".to_owned()),
+                Expr::Literal("c".to_owned()),
+            ]
+        )
+    );
+    assert!(parse("position()").is_ok());
+    assert!(parse("concat('a')").is_err());
+    assert!(parse("substring('a', 1, 2, 3)").is_err());
+    assert!(parse("unknown()").is_err());
+    assert!(parse("h:lower-case('A')").is_err());
+}
+
+#[test]
+fn prefixes() {
+    let expr = parse("svg:path[@xlink:href] | svg:*").unwrap();
+    assert_eq!(expr.prefixes(), vec!["svg".to_owned(), "xlink".to_owned()]);
+    assert!(parse("p").unwrap().prefixes().is_empty());
+}
+
+#[test]
+fn errors() {
+    assert!(parse("").is_err());
+    assert!(parse("p[").is_err());
+    assert!(parse("'unterminated").is_err());
+    assert!(parse("$variable").is_err());
+    assert!(parse("unknown-axis::p").is_err());
+    assert!(parse("p p").is_err());
+    assert!(parse("\u{2019}p\u{2019}").is_err());
+    assert!(parse("\u{3000}p").is_err());
+}
+
+#[test]
+fn nesting_depth() {
+    let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(parse(&nested(100)).is_ok());
+    assert!(parse(&nested(100_000)).is_err());
+    assert!(parse(&format!("{}1", "-".repeat(100_000))).is_err());
+    assert!(parse(&vec!["1"; 100].join(" + ")).is_ok());
+    assert!(parse(&vec!["1"; 100_000].join(" + ")).is_err());
+    assert!(parse(&format!("p{}", "[p".repeat(100_000))).is_err());
+}
@@ -603,7 +603,7 @@ fn constrain_viewport() {
         Some(ViewportConstraints {
             size: Size2D::new(320., 240.),
 
-            initial_zoom: PinchZoomFactor::new(1.),
+            initial_zoom: PinchZoomFactor::new(0.625),
             min_zoom: None,
             max_zoom: None,
 
@@ -611,4 +611,19 @@ fn constrain_viewport() {
             orientation: Orientation::Auto
         })
     );
+
+    let mut input = ParserInput::new("width: 320px auto; min-zoom: 0.8; user-zoom: fixed");
+    assert_eq!(
+        ViewportConstraints::maybe_new(&device, from_css!(input), QuirksMode::NoQuirks),
+        Some(ViewportConstraints {
+            size: Size2D::new(320., 240.),
+
+            initial_zoom: PinchZoomFactor::new(0.8),
+            min_zoom: Some(PinchZoomFactor::new(0.8)),
+            max_zoom: None,
+
+            user_zoom: UserZoom::Fixed,
+            orientation: Orientation::Auto
+        })
+    );
 }
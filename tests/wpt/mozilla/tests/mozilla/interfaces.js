@@ -16,6 +16,7 @@ function test_interfaces(interfaceNamesInGlobalScope) {
       "Date",
       "Error",
       "EvalError",
+      "FinalizationRegistry",
       "Float32Array",
       "Float64Array",
       "Function",
@@ -52,6 +53,7 @@ function test_interfaces(interfaceNamesInGlobalScope) {
       "Uint8Array",
       "Uint8ClampedArray",
       "WeakMap",
+      "WeakRef",
       "WeakSet",
       "WebAssembly",
     ];
@@ -9,9 +9,14 @@ importScripts("interfaces.js");
 test_interfaces([
   "Blob",
   "BroadcastChannel",
+  "ByteLengthQueuingStrategy",
+  "Cache",
+  "CacheStorage",
   "CanvasGradient",
   "CanvasPattern",
   "CloseEvent",
+  "CompressionStream",
+  "CountQueuingStrategy",
   "DOMMatrix",
   "DOMMatrixReadOnly",
   "DOMPoint",
@@ -21,6 +26,7 @@ test_interfaces([
   "DOMRectReadOnly",
   "DOMStringList",
   "CustomEvent",
+  "DecompressionStream",
   "DedicatedWorkerGlobalScope",
   "DOMException",
   "ErrorEvent",
@@ -48,17 +54,32 @@ test_interfaces([
   "PerformanceResourceTiming",
   "ProgressEvent",
   "PromiseRejectionEvent",
+  "ReadableByteStreamController",
+  "ReadableStream",
+  "ReadableStreamBYOBReader",
+  "ReadableStreamBYOBRequest",
+  "ReadableStreamDefaultController",
+  "ReadableStreamDefaultReader",
   "Request",
   "Response",
+  "SecurityPolicyViolationEvent",
   "TextDecoder",
+  "TextDecoderStream",
   "TextEncoder",
+  "TextEncoderStream",
+  "TransformStream",
+  "TransformStreamDefaultController",
   "URL",
   "URLSearchParams",
   "WebSocket",
+  "WebSocketStream",
   "Worker",
   "WorkerGlobalScope",
   "WorkerLocation",
   "WorkerNavigator",
+  "WritableStream",
+  "WritableStreamDefaultController",
+  "WritableStreamDefaultWriter",
   "XMLHttpRequest",
   "XMLHttpRequestEventTarget",
   "XMLHttpRequestUpload",
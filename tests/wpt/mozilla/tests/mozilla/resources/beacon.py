@@ -0,0 +1,17 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import json
+
+
+def main(request, response):
+    key = request.GET.first("key")
+    if request.method == "POST":
+        request.server.stash.put(key, {
+            "body": request.body,
+            "content_type": request.headers.get("Content-Type", ""),
+        })
+        return [("Content-Type", "text/plain")], ""
+    beacon = request.server.stash.take(key)
+    return [("Cache-Control", "no-store"), ("Content-Type", "application/json")], json.dumps(beacon)
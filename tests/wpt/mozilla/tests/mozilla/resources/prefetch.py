@@ -0,0 +1,16 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import json
+
+
+def main(request, response):
+    key = request.GET.first("key")
+    requests = request.server.stash.take(key) or []
+    if "querystate" in request.GET:
+        request.server.stash.put(key, requests)
+        return [("Cache-Control", "no-store"), ("Content-Type", "application/json")], json.dumps(requests)
+    requests.append(request.headers.get("Sec-Purpose", ""))
+    request.server.stash.put(key, requests)
+    return [("Cache-Control", "max-age=3600"), ("Content-Type", "text/plain")], "prefetched"
@@ -0,0 +1,26 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import time
+
+
+def main(request, response):
+    key = request.GET.first("key")
+    response.headers.set("Content-Type", "text/html")
+    response.headers.set("Cache-Control", "no-store")
+    response.write_status_headers()
+    # The prefetch tokenizer scans the rest of the document while the parser waits for the
+    # slow script.
+    response.writer.write_content(
+        '<!doctype html>'
+        '<script src="/common/slow.py"></script>'
+        '<script>document.write("<b>written</b>");</script>'
+        '<script src="preload.py?key=%s-script"></script>'
+        '<script>document.write("<textarea id=written>");</script>' % key)
+    response.writer.flush()
+    # The rest of the document is only scanned after the textarea was written.
+    time.sleep(3)
+    response.writer.write_content(
+        '<script src="preload.py?key=%s-textarea"></script>'
+        '</textarea>' % key)
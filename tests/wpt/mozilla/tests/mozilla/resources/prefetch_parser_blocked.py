@@ -0,0 +1,21 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    key = request.GET.first("key")
+    headers = [("Cache-Control", "no-store"), ("Content-Type", "text/html")]
+    # The prefetch tokenizer finds the image while the parser waits for the slow script,
+    # and the script following it counts the requests before the parser reaches the image.
+    return headers, (
+        '<!doctype html>'
+        '<script src="/common/slow.py"></script>'
+        '<script>'
+        'var xhr = new XMLHttpRequest();'
+        'xhr.open("GET", "preload.py?querystate&key=%s-image", false);'
+        'xhr.send();'
+        'window.requestsBeforeParsing = Number(xhr.responseText);'
+        '</script>'
+        '<!-- <img src="preload.py?key=%s-comment"> -->'
+        '<img src="preload.py?key=%s-image">' % (key, key, key))
@@ -0,0 +1,15 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    key = request.GET.first("key")
+    count = request.server.stash.take(key) or 0
+    if "querystate" in request.GET:
+        request.server.stash.put(key, count)
+        return [("Cache-Control", "no-store"), ("Content-Type", "text/plain")], str(count)
+    request.server.stash.put(key, count + 1)
+    status = int(request.GET.first("status", "200"))
+    headers = [("Cache-Control", "no-store"), ("Content-Type", "text/javascript")]
+    return status, headers, "window.preloadedScriptRuns = (window.preloadedScriptRuns || 0) + 1;"
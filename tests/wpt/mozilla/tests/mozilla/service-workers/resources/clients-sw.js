@@ -0,0 +1,41 @@
+// Answers the commands of the clients of the service worker, by posting the answer to
+// each of them.
+self.onmessage = async function(event) {
+  let answer = { command: event.data.command };
+  switch (event.data.command) {
+    case "matchAll": {
+      let clients = await self.clients.matchAll();
+      answer.clients = clients.map(c => ({ url: c.url, id: c.id, frameType: c.frameType }));
+      break;
+    }
+    case "matchAllUncontrolled": {
+      let clients = await self.clients.matchAll({ includeUncontrolled: true });
+      answer.clients = clients.map(c => ({ url: c.url, id: c.id, frameType: c.frameType }));
+      break;
+    }
+    case "get": {
+      let client = await self.clients.get(event.data.id);
+      answer.url = client ? client.url : null;
+      let missing = await self.clients.get("not-a-client-id");
+      answer.missing = missing === undefined;
+      break;
+    }
+    case "openWindow": {
+      try {
+        await self.clients.openWindow("../clients-frame.html");
+        answer.error = null;
+      } catch (e) {
+        answer.error = e.name;
+      }
+      break;
+    }
+    case "claim": {
+      await self.clients.claim();
+      break;
+    }
+  }
+  let clients = await self.clients.matchAll({ includeUncontrolled: true });
+  for (let client of clients) {
+    client.postMessage(answer);
+  }
+};